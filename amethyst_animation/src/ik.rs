@@ -0,0 +1,505 @@
+use amethyst_core::{
+    ecs::prelude::{
+        Component, DenseVecStorage, Entities, Entity, Join, ReadStorage, System, SystemData,
+        WriteStorage,
+    },
+    ecs::storage::GenericReadStorage,
+    math::{Point3, Unit, UnitQuaternion, Vector3},
+    Parent, Transform,
+};
+use amethyst_derive::SystemDesc;
+use amethyst_error::Error;
+use log::error;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Bones shorter than this are considered to have zero length.
+const MIN_BONE_LENGTH: f32 = 1e-5;
+
+/// Fraction of the chain length kept as slack when the target is out of reach, so the chain never
+/// becomes completely straight and the solver stays well defined.
+const REACH_SLACK: f32 = 1e-4;
+
+/// What an inverse kinematics constraint reaches for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IkTarget {
+    /// The world position of an entity with a `Transform`
+    Entity(Entity),
+    /// A fixed world position
+    Position(Vector3<f32>),
+}
+
+/// The joints affected by an inverse kinematics constraint.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IkChain {
+    /// Analytic two bone solver, e.g. for arms and legs.
+    TwoBone {
+        /// Upper joint of the chain, e.g. the hip
+        root: Entity,
+        /// Middle joint of the chain, e.g. the knee, must be a descendant of `root`
+        mid: Entity,
+        /// End effector, e.g. the ankle, must be a descendant of `mid`
+        end: Entity,
+        /// The middle joint bends towards this hint, if given
+        pole: Option<IkTarget>,
+    },
+    /// Rotates a single joint so that its `forward` axis points at the target, e.g. for heads and
+    /// eyes.
+    LookAt {
+        /// The aiming joint
+        joint: Entity,
+        /// The axis of the joint, in its local space, that should point at the target
+        forward: Vector3<f32>,
+    },
+}
+
+/// Inverse kinematics constraint, attach to any entity, for example the end effector.
+///
+/// Solved by the `IkSystem` after animations have been sampled, so the corrected rotations end up
+/// in the joint transforms used for skinning in the same frame. Scaled joints are assumed to be
+/// scaled uniformly.
+#[derive(Clone, Debug)]
+pub struct IkConstraint {
+    /// The joints to rotate
+    pub chain: IkChain,
+    /// Where the chain should reach
+    pub target: IkTarget,
+    /// How much of the correction is applied, between 0 (none) and 1 (fully solved)
+    pub weight: f32,
+    validated: bool,
+}
+
+impl Component for IkConstraint {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl IkConstraint {
+    /// Create a two bone constraint without pole hint, at full weight
+    pub fn two_bone(root: Entity, mid: Entity, end: Entity, target: IkTarget) -> Self {
+        IkConstraint {
+            chain: IkChain::TwoBone {
+                root,
+                mid,
+                end,
+                pole: None,
+            },
+            target,
+            weight: 1.,
+            validated: false,
+        }
+    }
+
+    /// Create a look at constraint, at full weight
+    pub fn look_at(joint: Entity, forward: Vector3<f32>, target: IkTarget) -> Self {
+        IkConstraint {
+            chain: IkChain::LookAt { joint, forward },
+            target,
+            weight: 1.,
+            validated: false,
+        }
+    }
+
+    /// Bend the middle joint of a two bone chain towards the given hint.
+    ///
+    /// Has no effect on look at constraints.
+    pub fn with_pole(mut self, hint: IkTarget) -> Self {
+        if let IkChain::TwoBone { ref mut pole, .. } = self.chain {
+            *pole = Some(hint);
+        }
+        self
+    }
+
+    /// Set the weight of the constraint
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Check that the chain can be solved in its current pose.
+    ///
+    /// Fails if a joint has no `Transform`, if a joint is used twice or if a bone has zero
+    /// length.
+    pub fn validate<T>(
+        &self,
+        transforms: &T,
+        parents: &ReadStorage<'_, Parent>,
+    ) -> Result<(), Error>
+    where
+        T: GenericReadStorage<Component = Transform>,
+    {
+        match self.chain {
+            IkChain::TwoBone { root, mid, end, .. } => {
+                if root == mid || mid == end || root == end {
+                    return Err(Error::from_string(
+                        "Two bone chain uses the same joint more than once",
+                    ));
+                }
+                let a = joint_position(root, transforms, parents)?;
+                let b = joint_position(mid, transforms, parents)?;
+                let c = joint_position(end, transforms, parents)?;
+                if (b - a).norm() < MIN_BONE_LENGTH || (c - b).norm() < MIN_BONE_LENGTH {
+                    return Err(Error::from_string("Two bone chain has a zero length bone"));
+                }
+                Ok(())
+            }
+            IkChain::LookAt { joint, forward } => {
+                if forward.norm() < MIN_BONE_LENGTH {
+                    return Err(Error::from_string("Look at constraint has no forward axis"));
+                }
+                joint_position(joint, transforms, parents).map(|_| ())
+            }
+        }
+    }
+}
+
+/// World rotations that bring the end of the chain `a -> b -> c` to `target`.
+///
+/// Returns the rotation to apply around `a`, which moves the whole chain, and the rotation to
+/// apply around `b` before that. Targets out of reach stretch the chain towards them.
+pub(crate) fn solve_two_bone(
+    a: Vector3<f32>,
+    b: Vector3<f32>,
+    c: Vector3<f32>,
+    target: Vector3<f32>,
+    pole: Option<Vector3<f32>>,
+) -> (UnitQuaternion<f32>, UnitQuaternion<f32>) {
+    let ab = b - a;
+    let bc = c - b;
+    let ac = c - a;
+    let lab = ab.norm();
+    let lcb = bc.norm();
+    if lab < MIN_BONE_LENGTH || lcb < MIN_BONE_LENGTH {
+        return (UnitQuaternion::identity(), UnitQuaternion::identity());
+    }
+    let slack = REACH_SLACK * (lab + lcb);
+    let lat = (target - a)
+        .norm()
+        .max((lab - lcb).abs() + slack)
+        .min(lab + lcb - slack);
+
+    // Bend the chain in its current plane, or towards the pole if it is straight
+    let axis = [
+        Some(ac.cross(&ab)),
+        pole.map(|p| ac.cross(&(p - a))),
+        Some(ac.cross(&Vector3::x())),
+        Some(ac.cross(&Vector3::y())),
+    ]
+    .iter()
+    .filter_map(|axis| axis.and_then(|axis| Unit::try_new(axis, MIN_BONE_LENGTH)))
+    .next()
+    .unwrap_or_else(Vector3::z_axis);
+
+    let ac_ab_0 = angle(&ac, &ab);
+    let ba_bc_0 = angle(&-ab, &bc);
+    let ac_ab_1 = clamped_acos((lcb * lcb - lab * lab - lat * lat) / (-2. * lab * lat));
+    let ba_bc_1 = clamped_acos((lat * lat - lab * lab - lcb * lcb) / (-2. * lab * lcb));
+    let bend_root = UnitQuaternion::from_axis_angle(&axis, ac_ab_1 - ac_ab_0);
+    let bend_mid = UnitQuaternion::from_axis_angle(&axis, ba_bc_1 - ba_bc_0);
+
+    // Aim the bent chain at the target
+    let bent = bend_root * (ab + bend_mid * bc);
+    let aim = rotation_between(&bent, &(target - a), &axis);
+    let mut root = aim * bend_root;
+
+    // Twist the chain around the aim axis, so the middle joint points towards the pole
+    if let (Some(pole), Some(aim_axis)) = (pole, Unit::try_new(target - a, MIN_BONE_LENGTH)) {
+        let project = |v: Vector3<f32>| v - aim_axis.into_inner() * v.dot(&aim_axis);
+        let current = project(root * ab);
+        let wanted = project(pole - a);
+        if current.norm() > MIN_BONE_LENGTH && wanted.norm() > MIN_BONE_LENGTH {
+            root = rotation_between(&current, &wanted, &aim_axis) * root;
+        }
+    }
+    (root, bend_mid)
+}
+
+/// World rotation that points the joint axis `forward`, given in world space, at `to`.
+pub(crate) fn solve_look_at(forward: Vector3<f32>, to: Vector3<f32>) -> UnitQuaternion<f32> {
+    let fallback = [Vector3::x(), Vector3::y()]
+        .iter()
+        .filter_map(|v| Unit::try_new(forward.cross(v), MIN_BONE_LENGTH))
+        .next()
+        .unwrap_or_else(Vector3::z_axis);
+    rotation_between(&forward, &to, &fallback)
+}
+
+/// Apply only `weight` of the given rotation.
+pub(crate) fn weighted(rotation: UnitQuaternion<f32>, weight: f32) -> UnitQuaternion<f32> {
+    if weight >= 1. {
+        return rotation;
+    }
+    match rotation.axis_angle() {
+        Some((axis, angle)) => UnitQuaternion::from_axis_angle(&axis, angle * weight.max(0.)),
+        None => UnitQuaternion::identity(),
+    }
+}
+
+fn angle(a: &Vector3<f32>, b: &Vector3<f32>) -> f32 {
+    let norm = a.norm() * b.norm();
+    if norm < MIN_BONE_LENGTH * MIN_BONE_LENGTH {
+        0.
+    } else {
+        clamped_acos(a.dot(b) / norm)
+    }
+}
+
+fn clamped_acos(cos: f32) -> f32 {
+    cos.max(-1.).min(1.).acos()
+}
+
+/// Like `UnitQuaternion::rotation_between`, but turns around `fallback` for opposite vectors.
+fn rotation_between(
+    from: &Vector3<f32>,
+    to: &Vector3<f32>,
+    fallback: &Unit<Vector3<f32>>,
+) -> UnitQuaternion<f32> {
+    UnitQuaternion::rotation_between(from, to)
+        .unwrap_or_else(|| UnitQuaternion::from_axis_angle(fallback, std::f32::consts::PI))
+}
+
+/// Global position and rotation of an entity, computed from the local transforms of its
+/// ancestors, so that it reflects the animations sampled this frame.
+fn global_pose<T>(
+    entity: Entity,
+    transforms: &T,
+    parents: &ReadStorage<'_, Parent>,
+) -> Result<(Vector3<f32>, UnitQuaternion<f32>), Error>
+where
+    T: GenericReadStorage<Component = Transform>,
+{
+    let mut position = Vector3::zeros();
+    let mut rotation = UnitQuaternion::identity();
+    let mut current = Some(entity);
+    while let Some(e) = current {
+        let transform = transforms.get(e).ok_or_else(|| {
+            Error::from_string(format!("Joint {:?} of IK constraint has no Transform", e))
+        })?;
+        position = transform
+            .matrix()
+            .transform_point(&Point3::from(position))
+            .coords;
+        rotation = transform.rotation() * rotation;
+        current = parents.get(e).map(|parent| parent.entity);
+    }
+    Ok((position, rotation))
+}
+
+fn joint_position<T>(
+    entity: Entity,
+    transforms: &T,
+    parents: &ReadStorage<'_, Parent>,
+) -> Result<Vector3<f32>, Error>
+where
+    T: GenericReadStorage<Component = Transform>,
+{
+    global_pose(entity, transforms, parents).map(|(position, _)| position)
+}
+
+fn target_position<T>(
+    target: IkTarget,
+    transforms: &T,
+    parents: &ReadStorage<'_, Parent>,
+) -> Result<Vector3<f32>, Error>
+where
+    T: GenericReadStorage<Component = Transform>,
+{
+    match target {
+        IkTarget::Entity(entity) => joint_position(entity, transforms, parents),
+        IkTarget::Position(position) => Ok(position),
+    }
+}
+
+/// Rotate the joint by the given world rotation, blended by weight.
+fn rotate_joint(
+    joint: Entity,
+    world: UnitQuaternion<f32>,
+    global: UnitQuaternion<f32>,
+    weight: f32,
+    transforms: &mut WriteStorage<'_, Transform>,
+) {
+    let local = global.inverse() * world * global;
+    if let Some(transform) = transforms.get_mut(joint) {
+        let rotation = *transform.rotation() * weighted(local, weight);
+        *transform.rotation_mut() = rotation;
+    }
+}
+
+fn solve(
+    constraint: &IkConstraint,
+    transforms: &mut WriteStorage<'_, Transform>,
+    parents: &ReadStorage<'_, Parent>,
+) -> Result<(), Error> {
+    let target = target_position(constraint.target, transforms, parents)?;
+    match constraint.chain {
+        IkChain::TwoBone {
+            root,
+            mid,
+            end,
+            pole,
+        } => {
+            let (a, root_global) = global_pose(root, transforms, parents)?;
+            let (b, mid_global) = global_pose(mid, transforms, parents)?;
+            let c = joint_position(end, transforms, parents)?;
+            let pole = match pole {
+                Some(pole) => Some(target_position(pole, transforms, parents)?),
+                None => None,
+            };
+            let (root_rotation, mid_rotation) = solve_two_bone(a, b, c, target, pole);
+            rotate_joint(
+                root,
+                root_rotation,
+                root_global,
+                constraint.weight,
+                transforms,
+            );
+            rotate_joint(mid, mid_rotation, mid_global, constraint.weight, transforms);
+        }
+        IkChain::LookAt { joint, forward } => {
+            let (position, global) = global_pose(joint, transforms, parents)?;
+            let rotation = solve_look_at(global * forward, target - position);
+            rotate_joint(joint, rotation, global, constraint.weight, transforms);
+        }
+    }
+    Ok(())
+}
+
+/// Solves all `IkConstraint`s by rotating the local transforms of their joints.
+///
+/// Needs to run after the `SamplerInterpolationSystem` and before the `TransformSystem`, so the
+/// corrections build on the sampled pose and are included in the global transforms used by the
+/// `VertexSkinningSystem`.
+///
+/// Constraints are validated the first time they are seen. Invalid constraints, for example with
+/// zero length bones, are logged as errors and removed.
+#[derive(Default, Debug, SystemDesc)]
+pub struct IkSystem;
+
+impl<'a> System<'a> for IkSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, IkConstraint>,
+        WriteStorage<'a, Transform>,
+        ReadStorage<'a, Parent>,
+    );
+
+    fn run(&mut self, (entities, mut constraints, mut transforms, parents): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("ik_system");
+
+        let mut rejected = Vec::new();
+        for (entity, constraint) in (&*entities, &mut constraints).join() {
+            if !constraint.validated {
+                if let Err(err) = constraint.validate(&transforms, &parents) {
+                    error!("Rejecting IK constraint on {:?}: {}", entity, err);
+                    rejected.push(entity);
+                    continue;
+                }
+                constraint.validated = true;
+            }
+            if constraint.weight <= 0. {
+                continue;
+            }
+            if let Err(err) = solve(constraint, &mut transforms, &parents) {
+                error!("Failed to solve IK constraint on {:?}: {}", entity, err);
+            }
+        }
+        for entity in rejected {
+            constraints.remove(entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::ecs::{Builder, World, WorldExt};
+    use approx::assert_relative_eq;
+
+    fn solved_end(
+        a: Vector3<f32>,
+        b: Vector3<f32>,
+        c: Vector3<f32>,
+        target: Vector3<f32>,
+        pole: Option<Vector3<f32>>,
+    ) -> (Vector3<f32>, Vector3<f32>) {
+        let (root, mid) = solve_two_bone(a, b, c, target, pole);
+        (a + root * (b - a), a + root * ((b - a) + mid * (c - b)))
+    }
+
+    #[test]
+    fn two_bone_reaches_target() {
+        let (_, end) = solved_end(
+            Vector3::zeros(),
+            Vector3::new(0., 1., 0.),
+            Vector3::new(0.5, 1.5, 0.),
+            Vector3::new(1., 1., 0.5),
+            None,
+        );
+        assert_relative_eq!(end, Vector3::new(1., 1., 0.5), epsilon = 1e-3);
+    }
+
+    #[test]
+    fn straight_chain_bends_towards_pole() {
+        let (mid, end) = solved_end(
+            Vector3::zeros(),
+            Vector3::new(0., 1., 0.),
+            Vector3::new(0., 2., 0.),
+            Vector3::new(0., 1., 0.),
+            Some(Vector3::new(0., 0.5, 1.)),
+        );
+        assert_relative_eq!(end, Vector3::new(0., 1., 0.), epsilon = 1e-3);
+        assert!(mid.z > 0.5);
+        assert!((mid.norm() - 1.).abs() < 1e-3);
+    }
+
+    #[test]
+    fn out_of_reach_stretches_without_nan() {
+        let target = Vector3::new(10., 0., 0.);
+        let (mid, end) = solved_end(
+            Vector3::zeros(),
+            Vector3::new(0., 1., 0.),
+            Vector3::new(0., 2., 0.),
+            target,
+            None,
+        );
+        assert!(mid.iter().chain(end.iter()).all(|v| v.is_finite()));
+        assert!(mid.normalize().dot(&Vector3::x()) > 0.99);
+        assert_relative_eq!(end, Vector3::new(2., 0., 0.), epsilon = 1e-3);
+    }
+
+    #[test]
+    fn zero_length_bone_is_rejected() {
+        let mut world = World::new();
+        world.register::<Transform>();
+        world.register::<Parent>();
+        let root = world.create_entity().with(Transform::default()).build();
+        let mid = world
+            .create_entity()
+            .with(Transform::default())
+            .with(Parent { entity: root })
+            .build();
+        let end = world
+            .create_entity()
+            .with(Transform::default())
+            .with(Parent { entity: mid })
+            .build();
+        let constraint = IkConstraint::two_bone(root, mid, end, IkTarget::Position(Vector3::x()));
+        assert!(constraint
+            .validate(&world.read_storage::<Transform>(), &world.read_storage())
+            .is_err());
+    }
+
+    #[test]
+    fn look_at_points_forward_axis() {
+        let rotation = solve_look_at(Vector3::z(), Vector3::new(0., 0., -3.));
+        assert_relative_eq!(rotation * Vector3::z(), -Vector3::z(), epsilon = 1e-3);
+    }
+
+    #[test]
+    fn weight_blends_rotation() {
+        let rotation = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), 1.);
+        let half = weighted(rotation, 0.5);
+        assert!((half.angle() - 0.5).abs() < 1e-5);
+    }
+}
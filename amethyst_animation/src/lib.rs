@@ -58,30 +58,45 @@ pub use minterpolate::{InterpolationFunction, InterpolationPrimitive};
 
 pub use self::{
     bundle::{AnimationBundle, SamplingBundle, VertexSkinningBundle},
+    ik::{IkChain, IkConstraint, IkSystem, IkTarget},
     material::{MaterialChannel, MaterialPrimitive},
+    morph::MorphChannel,
     prefab::{AnimatablePrefab, AnimationHierarchyPrefab, AnimationPrefab, AnimationSetPrefab},
     resources::{
-        Animation, AnimationCommand, AnimationControl, AnimationControlSet, AnimationHierarchy,
-        AnimationSampling, AnimationSet, ApplyData, BlendMethod, ControlState, DeferStartRelation,
-        EndControl, RestState, Sampler, SamplerControl, SamplerControlSet, StepDirection,
+        AdditiveReference, Animation, AnimationCommand, AnimationControl, AnimationControlSet,
+        AnimationEvent, AnimationEventKind, AnimationHierarchy, AnimationMarker, AnimationSampling,
+        AnimationSet, AnimationTimeScale, ApplyData, BlendMethod, BlendMode, ControlState,
+        DeferStartRelation, EndControl, RateRamp, RestState, Sampler, SamplerControl,
+        SamplerControlSet, StepDirection, WeightFade,
     },
+    root_motion::{RootMotion, RootMotionMode, RootMotionSettings, SamplerRootMotion},
     skinning::{Joint, JointPrefab, Skin, SkinPrefab, SkinnablePrefab, VertexSkinningSystem},
     sprite::{SpriteRenderChannel, SpriteRenderPrimitive},
+    state_machine::{
+        AnimationState, AnimationStateMachine, Condition, Parameter, Reenter, Transition,
+    },
     systems::{
-        AnimationControlSystem, AnimationProcessor, SamplerInterpolationSystem, SamplerProcessor,
+        AnimationControlSystem, AnimationProcessor, AnimationStateMachineSystem,
+        SamplerInterpolationSystem, SamplerProcessor,
     },
     transform::TransformChannel,
+    ui::{AnimatedUi, UiAnimationPrefab, UiImageChannel, UiTextChannel},
     ui_transform::UiTransformChannel,
     util::{get_animation_set, SamplerPrimitive},
 };
 
 mod bundle;
+mod ik;
 mod material;
+mod morph;
 mod prefab;
 mod resources;
+mod root_motion;
 mod skinning;
 mod sprite;
+mod state_machine;
 mod systems;
 mod transform;
+mod ui;
 mod ui_transform;
 mod util;
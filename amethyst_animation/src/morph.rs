@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+use amethyst_rendy::morph::MorphWeights;
+
+use crate::{
+    resources::{AnimationSampling, ApplyData, BlendMethod},
+    util::SamplerPrimitive,
+};
+
+/// Channels that are animatable on `MorphWeights`
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum MorphChannel {
+    /// The weight of the morph target with the given index
+    Weight(usize),
+}
+
+impl<'a> ApplyData<'a> for MorphWeights {
+    type ApplyData = ();
+}
+
+impl AnimationSampling for MorphWeights {
+    type Primitive = SamplerPrimitive<f32>;
+    type Channel = MorphChannel;
+
+    fn apply_sample(&mut self, channel: &Self::Channel, data: &SamplerPrimitive<f32>, _: &()) {
+        match (channel, *data) {
+            (&MorphChannel::Weight(index), SamplerPrimitive::Scalar(weight)) => {
+                if index >= self.weights.len() {
+                    self.weights.resize(index + 1, 0.);
+                }
+                self.weights[index] = weight;
+            }
+            _ => panic!("Attempt to apply invalid sample to MorphWeights"),
+        }
+    }
+
+    fn current_sample(&self, channel: &Self::Channel, _: &()) -> SamplerPrimitive<f32> {
+        match *channel {
+            MorphChannel::Weight(index) => {
+                SamplerPrimitive::Scalar(self.weights.get(index).cloned().unwrap_or(0.))
+            }
+        }
+    }
+
+    fn default_primitive(_: &Self::Channel) -> Self::Primitive {
+        SamplerPrimitive::Scalar(0.)
+    }
+
+    fn blend_method(&self, _: &Self::Channel) -> Option<BlendMethod> {
+        Some(BlendMethod::Linear)
+    }
+}
@@ -13,7 +13,10 @@ use amethyst_core::ecs::prelude::{Entity, Read, ReadExpect, WriteStorage};
 use amethyst_derive::PrefabData;
 use amethyst_error::Error;
 
-use crate::{Animation, AnimationHierarchy, AnimationSampling, AnimationSet, RestState, Sampler};
+use crate::{
+    Animation, AnimationHierarchy, AnimationMarker, AnimationSampling, AnimationSet, RestState,
+    Sampler,
+};
 
 /// `PrefabData` for loading a single `Animation`
 ///
@@ -34,6 +37,9 @@ where
 {
     /// All samplers in the `Animation`
     pub samplers: Vec<(usize, T::Channel, Sampler<T::Primitive>)>,
+    /// All markers in the `Animation`
+    #[serde(default)]
+    pub markers: Vec<AnimationMarker>,
     #[serde(skip, default = "default_handle")]
     handle: Option<Handle<Animation<T>>>,
 }
@@ -52,6 +58,7 @@ where
     fn default() -> Self {
         AnimationPrefab {
             samplers: Vec::default(),
+            markers: Vec::default(),
             handle: None,
         }
     }
@@ -113,6 +120,7 @@ where
                     )
                 })
                 .collect(),
+            markers: self.markers.clone(),
         };
         self.handle = Some(loader.load_from_data(animation, progress, animation_storage));
         Ok(true)
@@ -1,7 +1,7 @@
 use std::{cmp::Ordering, fmt::Debug, hash::Hash, marker, time::Duration};
 
 use derivative::Derivative;
-use fnv::FnvHashMap;
+use fnv::{FnvHashMap, FnvHashSet};
 use log::error;
 use minterpolate::{get_input_index, InterpolationFunction, InterpolationPrimitive};
 use serde::{Deserialize, Serialize};
@@ -10,16 +10,42 @@ use amethyst_assets::{Asset, AssetStorage, Handle, PrefabData};
 use amethyst_core::{
     ecs::prelude::{Component, DenseVecStorage, Entity, VecStorage, WriteStorage},
     shred::SystemData,
-    timing::{duration_to_secs, secs_to_duration},
+    timing::{duration_to_secs, secs_to_duration, Time},
 };
 use amethyst_derive::PrefabData;
 use amethyst_error::Error;
 
+use crate::root_motion::{RootMotion, RootMotionSettings, SamplerRootMotion};
+
 /// Blend method for sampler blending
 #[derive(Clone, Copy, Debug, PartialOrd, PartialEq, Eq, Hash)]
 pub enum BlendMethod {
     /// Simple linear blending
     Linear,
+    /// Normalized linear blending of unit quaternions (nlerp). Samples are flipped to the same
+    /// hemisphere before blending, so the shortest rotation is taken.
+    Spherical,
+}
+
+/// How the samples of an animation are combined with the samples of the other animations
+/// targeting the same channel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BlendMode {
+    /// The samples replace the rest state, blended with the samples of the other overriding
+    /// animations by their weights. Weights are normalized if they sum to more than 1, otherwise
+    /// the remaining weight is given to the rest state.
+    Override,
+    /// The samples are made relative to a reference pose, see `AdditiveReference`, and the
+    /// resulting offsets are added to the result of the overriding animations, scaled by their
+    /// weight, see `AnimationSampling::add_sample`. Additive animations are added in the order
+    /// of their layer.
+    Additive,
+}
+
+impl Default for BlendMode {
+    fn default() -> Self {
+        BlendMode::Override
+    }
 }
 
 /// Extra data to extract from `World`, for use when applying or fetching a sample
@@ -58,6 +84,57 @@ pub trait AnimationSampling: Send + Sync + 'static + for<'b> ApplyData<'b> {
 
     /// Get blend config
     fn blend_method(&self, channel: &Self::Channel) -> Option<BlendMethod>;
+
+    /// Get the offset of `sample` from `reference`, so that adding it to `reference` with
+    /// `add_sample` results in `sample`. Used for `BlendMode::Additive`.
+    ///
+    /// The default subtracts the values.
+    fn sample_delta(
+        _channel: &Self::Channel,
+        reference: &Self::Primitive,
+        sample: &Self::Primitive,
+    ) -> Self::Primitive {
+        sample.sub(reference)
+    }
+
+    /// Add the offset `delta`, scaled by `weight`, to `base`. Used for `BlendMode::Additive`.
+    ///
+    /// The default adds the scaled values, which suits translations, but not rotations, which
+    /// should be combined with a quaternion product instead.
+    fn add_sample(
+        _channel: &Self::Channel,
+        base: &Self::Primitive,
+        delta: &Self::Primitive,
+        weight: f32,
+    ) -> Self::Primitive {
+        base.add(&delta.mul(weight))
+    }
+
+    /// Split a sample of the root node of an animation into the pose the node keeps and the motion
+    /// extracted from it, for `RootMotionMode::Extract` and `RootMotionMode::InPlace`.
+    /// `reference` is the first frame of the sampler, `yaw` is set if the rotation around the up
+    /// axis should be extracted too.
+    ///
+    /// The motion of consecutive frames is turned into a per-frame offset with `sample_delta`.
+    /// The default extracts nothing.
+    fn strip_root_motion(
+        _channel: &Self::Channel,
+        _reference: &Self::Primitive,
+        _sample: &Self::Primitive,
+        _yaw: bool,
+    ) -> Option<(Self::Primitive, Self::Primitive)> {
+        None
+    }
+
+    /// Accumulate the per-frame offset of the motion extracted by `strip_root_motion`.
+    ///
+    /// The default ignores the offset.
+    fn add_root_motion(
+        _channel: &Self::Channel,
+        _delta: &Self::Primitive,
+        _motion: &mut RootMotion,
+    ) {
+    }
 }
 
 /// Sampler defines a single animation for a single channel on a single component
@@ -79,20 +156,87 @@ where
     ///
     /// * [Linear][lin]: `i` — `[pos_0, .., pos_n]`
     /// * [Spherical Linear][sph]: `i` — `[pos_0, .., pos_n]`
-    /// * [Step][step]: `i` — `[pos_0, .., pos_n]`
+    /// * Step: `i` — `[pos_0, .., pos_n]`
     /// * [Catmull Rom Spline][cm]: `i + 2` — `[in_tangent_0, pos_0, .., pos_n, out_tangent_n]`
-    /// * [Cubic Spline][cub]: `3 * i` — `[in_tangent_0, pos_0, out_tangent_0, ..]`
+    /// * Cubic Spline: `3 * i` — `[in_tangent_0, pos_0, out_tangent_0, ..]`
     ///
     /// [lin]: https://docs.rs/minterpolate/0.2.2/minterpolate/fn.linear_interpolate.html
     /// [sph]: https://docs.rs/minterpolate/0.2.2/minterpolate/fn.spherical_linear_interpolate.html
-    /// [step]: https://docs.rs/minterpolate/0.2.2/minterpolate/fn.step_interpolate.html
     /// [cm]: https://docs.rs/minterpolate/0.2.2/minterpolate/fn.catmull_rom_spline_interpolate.html
-    /// [cub]: https://docs.rs/minterpolate/0.2.2/minterpolate/fn.cubic_spline_interpolate.html
     pub output: Vec<T>,
     /// How interpolation should be done
     pub function: InterpolationFunction<T>,
 }
 
+impl<T> Sampler<T>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    /// Sample the output at `time` seconds.
+    ///
+    /// Step and cubic spline samplers are evaluated as specified by glTF: step samplers hold the
+    /// value of the previous key frame, cubic spline samplers use the Hermite form with the
+    /// tangents scaled by the time between key frames. Times outside of the key frames are clamped.
+    pub fn sample(&self, time: f32) -> T {
+        if self.input.is_empty() {
+            return self
+                .function
+                .interpolate(time, &self.input, &self.output, false);
+        }
+        match self.function {
+            InterpolationFunction::Step => {
+                let index = key_frame_index(&self.input, time);
+                self.output[index].clone()
+            }
+            InterpolationFunction::CubicSpline => {
+                cubic_spline_sample(&self.input, &self.output, time)
+            }
+            ref function => function.interpolate(time, &self.input, &self.output, false),
+        }
+    }
+}
+
+/// Index of the last key frame at or before `time`, 0 if `time` is before the first key frame.
+fn key_frame_index(input: &[f32], time: f32) -> usize {
+    match input.binary_search_by(|t| t.partial_cmp(&time).unwrap_or(Ordering::Less)) {
+        Ok(index) => index,
+        Err(0) => 0,
+        Err(index) => index - 1,
+    }
+}
+
+/// Evaluate a cubic spline, with `output` holding `[in_tangent, value, out_tangent]` for each key
+/// frame.
+fn cubic_spline_sample<T>(input: &[f32], output: &[T], time: f32) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let value = |index: usize| output[index * 3 + 1].clone();
+    let last = input.len() - 1;
+    if time <= input[0] {
+        return value(0);
+    }
+    if time >= input[last] {
+        return value(last);
+    }
+    let index = key_frame_index(input, time);
+    let delta = input[index + 1] - input[index];
+    // key frames sharing a timestamp, there is nothing to interpolate
+    if delta <= 0. {
+        return value(index);
+    }
+    let t = (time - input[index]) / delta;
+    let t2 = t * t;
+    let t3 = t2 * t;
+    let out_tangent = output[index * 3 + 2].mul(delta);
+    let in_tangent = output[(index + 1) * 3].mul(delta);
+    value(index)
+        .mul(2. * t3 - 3. * t2 + 1.)
+        .add(&out_tangent.mul(t3 - 2. * t2 + t))
+        .add(&value(index + 1).mul(-2. * t3 + 3. * t2))
+        .add(&in_tangent.mul(t3 - t2))
+}
+
 impl<T> Asset for Sampler<T>
 where
     T: InterpolationPrimitive + Send + Sync + 'static,
@@ -214,11 +358,16 @@ where
 /// Defines relationships between the node index in `AnimationHierarchy` and a `Sampler` handle.
 /// If the animation only targets a single node index, `AnimationHierarchy` is not required.
 ///
+/// An animation can also contain [`AnimationMarker`][marker]s, which will trigger an
+/// [`AnimationEvent`][event] whenever a running animation passes them.
+///
 /// ### Type parameters:
 ///
 /// - `T`: the component type that the animation should be applied to
 ///
 /// [sampler]: struct.Sampler.html
+/// [marker]: struct.AnimationMarker.html
+/// [event]: struct.AnimationEvent.html
 #[derive(Clone, Debug, Default)]
 pub struct Animation<T>
 where
@@ -226,6 +375,8 @@ where
 {
     /// node index -> sampler handle
     pub nodes: Vec<(usize, T::Channel, Handle<Sampler<T::Primitive>>)>,
+    /// Named markers in the animation timeline
+    pub markers: Vec<AnimationMarker>,
 }
 
 impl<T> Animation<T>
@@ -234,7 +385,10 @@ where
 {
     /// Create new empty animation
     pub fn new() -> Self {
-        Animation { nodes: vec![] }
+        Animation {
+            nodes: vec![],
+            markers: vec![],
+        }
     }
 
     /// Create an animation with a single sampler
@@ -245,6 +399,7 @@ where
     ) -> Self {
         Animation {
             nodes: vec![(index, channel, sampler)],
+            markers: vec![],
         }
     }
 
@@ -268,6 +423,17 @@ where
         self.nodes.push((node_index, channel, sampler));
         self
     }
+
+    /// Add a marker to the animation, `time` is normalized to the length of the animation
+    pub fn add_marker<N: Into<String>>(&mut self, name: N, time: f32) {
+        self.markers.push(AnimationMarker::new(name, time));
+    }
+
+    /// Add a marker to the animation, `time` is normalized to the length of the animation
+    pub fn with_marker<N: Into<String>>(mut self, name: N, time: f32) -> Self {
+        self.add_marker(name, time);
+        self
+    }
 }
 
 impl<T> Asset for Animation<T>
@@ -279,6 +445,58 @@ where
     type HandleStorage = VecStorage<Handle<Self>>;
 }
 
+/// A named point in time within an `Animation`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnimationMarker {
+    /// Name of the marker, reported in the `AnimationEvent`
+    pub name: String,
+    /// Normalized time of the marker, 0.0 is the start and 1.0 is the end of the animation
+    pub time: f32,
+}
+
+impl AnimationMarker {
+    /// Creates a new marker
+    pub fn new<N: Into<String>>(name: N, time: f32) -> Self {
+        AnimationMarker {
+            name: name.into(),
+            time,
+        }
+    }
+}
+
+/// Event written to `EventChannel<AnimationEvent<I>>` by the `AnimationControlSystem` when a
+/// running animation passes one of its `AnimationMarker`s, or completes.
+///
+/// Looping animations emit their markers on every loop, animations playing backwards emit
+/// them in reverse order, and all markers passed during a single frame are emitted once each,
+/// in the order they were passed.
+///
+/// ### Type parameters:
+///
+/// - `I`: identifier type of the animation in the `AnimationControlSet`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationEvent<I> {
+    /// The entity the `AnimationControlSet` is attached to
+    pub entity: Entity,
+    /// Id of the animation in the `AnimationControlSet`
+    pub animation_id: I,
+    /// What happened
+    pub kind: AnimationEventKind,
+}
+
+/// Kind of an `AnimationEvent`
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnimationEventKind {
+    /// The marker with the given name was passed
+    Marker(String),
+    /// The animation played to its end, not sent for aborted animations
+    Complete {
+        /// Number of passes through the animation that were played, each direction of a
+        /// ping-pong counts as one pass
+        loops: u32,
+    },
+}
+
 /// State of animation
 #[derive(Debug, Clone, PartialEq)]
 pub enum ControlState {
@@ -317,8 +535,14 @@ impl ControlState {
 /// Control handling of animation/sampler end
 #[derive(Debug, Clone)]
 pub enum EndControl {
-    /// Loop the requested number of iterations, None = loop infinitely
+    /// Loop the requested number of iterations, None = loop infinitely, then go back to rest state
     Loop(Option<u32>),
+    /// Loop the requested number of iterations, then stay at the last frame
+    LoopAndStay(u32),
+    /// Play forwards and backwards in turn, reversing direction at each end. Stops after the
+    /// requested number of passes, each direction counting as one, None = play infinitely. Stays
+    /// at the frame it stopped on.
+    PingPong(Option<u32>),
     /// When duration of sampler/animation is reached, go back to rest state
     Normal,
     /// When duration of sampler/animation is reached, do nothing: stay at the last sampled state
@@ -341,23 +565,66 @@ where
     pub channel: T::Channel,
     /// Blend weight
     pub blend_weight: f32,
+    /// Weight of the animation, multiplied with `blend_weight`
+    pub weight: f32,
+    /// How the samples are combined with those of other animations
+    pub blend_mode: BlendMode,
+    /// Layer of the animation, additive animations are added in the order of their layers
+    pub layer: u32,
+    /// Reference sample additive samples are made relative to, `None` if the samples are
+    /// offsets already
+    pub reference: Option<T::Primitive>,
+    /// Root motion extraction, `None` if the sampler isn't for the root node or the root node
+    /// moves as animated
+    pub root_motion: Option<SamplerRootMotion<T>>,
     /// Sampler
     pub sampler: Handle<Sampler<T::Primitive>>,
     /// State of sampling
     pub state: ControlState,
     /// What to do when sampler ends
     pub end: EndControl,
+    /// Number of passes through the sampler completed so far
+    pub loops: u32,
+    /// Whether the current pass of a `EndControl::PingPong` plays backwards
+    pub reverse: bool,
+    /// Stop at the end of the current loop, set by `AnimationCommand::FinishCurrentLoop`
+    pub finish: bool,
     /// What the transform should return to after end
     pub after: T::Primitive,
     /// Control the rate of animation, default is 1.0
     pub rate_multiplier: f32,
 }
 
+impl<T> SamplerControl<T>
+where
+    T: AnimationSampling,
+{
+    /// Number of passes through the sampler before it ends, `None` if it plays until aborted
+    pub fn pass_limit(&self) -> Option<u32> {
+        let limit = match self.end {
+            EndControl::Loop(limit) | EndControl::PingPong(limit) => limit,
+            EndControl::LoopAndStay(limit) => Some(limit),
+            EndControl::Normal | EndControl::Stay => Some(1),
+        };
+        let limit = if self.finish {
+            // a ping-pong finishes once it's back at the start
+            let current = match self.end {
+                EndControl::PingPong(_) => (self.loops / 2 + 1) * 2,
+                _ => self.loops + 1,
+            };
+            Some(limit.map_or(current, |limit| limit.min(current)))
+        } else {
+            limit
+        };
+        limit.map(|limit| limit.max(1))
+    }
+}
+
 /// Sampler control set, containing a set of sampler controllers for a single component.
 ///
-/// Have support for multiple samplers per channel, will do linear blending between all active
-/// samplers. The target component specifies if it can be blended, if it can't, the last added
-/// sampler wins.
+/// Have support for multiple samplers per channel, will blend all active samplers by their weights
+/// and `BlendMode`. The target component specifies if it can be blended, if it can't, the last
+/// added sampler wins.
 ///
 /// ### Type parameters:
 ///
@@ -459,6 +726,22 @@ where
             .for_each(|sampler| sampler.rate_multiplier = rate_multiplier);
     }
 
+    /// Update the weight of the animation
+    pub fn set_weight(&mut self, control_id: u64, weight: f32) {
+        self.samplers
+            .iter_mut()
+            .filter(|t| t.control_id == control_id)
+            .for_each(|sampler| sampler.weight = weight);
+    }
+
+    /// Update how the animation is blended with other animations
+    pub fn set_blend_mode(&mut self, control_id: u64, blend_mode: BlendMode) {
+        self.samplers
+            .iter_mut()
+            .filter(|t| t.control_id == control_id)
+            .for_each(|sampler| sampler.blend_mode = blend_mode);
+    }
+
     /// Forcibly set the input value (point of interpolation)
     pub fn set_input(&mut self, control_id: u64, input: f32)
     where
@@ -475,6 +758,24 @@ where
             });
     }
 
+    /// Stop the samplers at the end of their current loop
+    pub fn finish(&mut self, control_id: u64) {
+        self.samplers
+            .iter_mut()
+            .filter(|t| t.control_id == control_id)
+            .for_each(|sampler| sampler.finish = true);
+    }
+
+    /// Number of passes completed by the samplers of the given animation, `None` if it has no
+    /// samplers in the set
+    pub fn loops(&self, control_id: u64) -> Option<u32> {
+        self.samplers
+            .iter()
+            .filter(|t| t.control_id == control_id)
+            .map(|t| t.loops)
+            .max()
+    }
+
     /// Check if a control set can be terminated
     pub fn check_termination(&self, control_id: u64) -> bool {
         self.samplers
@@ -594,6 +895,11 @@ where
     Abort,
     /// Only initialise the animation without starting it
     Init,
+    /// Keep playing until the end of the current loop, then end as if the loop count was reached
+    FinishCurrentLoop,
+    /// Change the rate multiplier to the first value over the second value in seconds, 0 changes
+    /// it immediately
+    SetRate(f32, f32),
 }
 
 /// Controls the state of a single running animation on a specific component type
@@ -619,9 +925,100 @@ where
     pub command: AnimationCommand<T>,
     /// Control the rate of animation, default is 1.0
     pub rate_multiplier: f32,
+    /// Weight of the animation when blending with other animations, default is 1.0
+    pub weight: f32,
+    /// How the animation is blended with other animations, default is `BlendMode::Override`
+    pub blend_mode: BlendMode,
+    /// Reference pose of an additive animation, default is `AdditiveReference::FirstFrame`
+    pub reference: AdditiveReference<T>,
+    /// Layer of the animation, additive animations are added in the order of their layers,
+    /// default is 0
+    pub layer: u32,
+    /// Node indices the animation is restricted to, `None` for all nodes of the animation
+    pub mask: Option<FnvHashSet<usize>>,
+    /// Extraction of the motion of the root node, default is `RootMotionMode::Animated`
+    pub root_motion: RootMotionSettings,
+    /// Ongoing change of `weight`
+    pub fade: Option<WeightFade>,
+    /// Ongoing change of `rate_multiplier`
+    pub rate_ramp: Option<RateRamp>,
+    /// Number of passes through the animation completed so far
+    pub loops: u32,
     m: marker::PhantomData<T>,
 }
 
+/// Reference pose an additive animation is made relative to.
+///
+/// The offset of each sample from the reference is added to the blended pose of the overriding
+/// animations, so an animation turning the head to the side can be layered on top of any
+/// animation of the body.
+///
+/// ### Type parameters:
+///
+/// - `T`: the component type that the animation should be applied to
+#[derive(Clone, Debug)]
+pub enum AdditiveReference<T>
+where
+    T: AnimationSampling,
+{
+    /// The samples of the animation are offsets already
+    None,
+    /// The first frame of the animation
+    FirstFrame,
+    /// The first frame of another animation, e.g. an idle pose. Nodes and channels missing in the
+    /// reference animation use the first frame of the additive animation.
+    Animation(Handle<Animation<T>>),
+}
+
+/// Gradual change of the rate of an animation, e.g. to speed up a run cycle with the velocity of
+/// a character.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateRamp {
+    /// Rate multiplier at the end of the ramp
+    pub target: f32,
+    /// Change of the rate multiplier per second
+    pub speed: f32,
+}
+
+/// Gradual change of the weight of an animation, e.g. for cross-fading between animations.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeightFade {
+    /// Weight at the end of the fade
+    pub target: f32,
+    /// Change of the weight per second
+    pub speed: f32,
+    /// Abort the animation when the fade is done
+    pub abort: bool,
+}
+
+/// Multiplier of the time of all animations, applied on top of the rate of each animation.
+///
+/// Follows `Time::time_scale` by default, so animations slow down and pause with the game. A
+/// fixed scale keeps animations playing regardless, e.g. for the animations of a pause menu.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AnimationTimeScale {
+    /// Animations play with `Time::time_scale`, and stop while the time is paused
+    FollowTime,
+    /// Animations play with the given scale, ignoring `Time::time_scale` and pauses
+    Fixed(f32),
+}
+
+impl Default for AnimationTimeScale {
+    fn default() -> Self {
+        AnimationTimeScale::FollowTime
+    }
+}
+
+impl AnimationTimeScale {
+    /// Time elapsed for animations since the last frame, in seconds
+    pub fn delta_seconds(self, time: &Time) -> f32 {
+        match self {
+            AnimationTimeScale::FollowTime => time.delta_seconds(),
+            AnimationTimeScale::Fixed(scale) => time.delta_real_seconds() * scale,
+        }
+    }
+}
+
 impl<T> AnimationControl<T>
 where
     T: AnimationSampling,
@@ -641,9 +1038,79 @@ where
             state,
             command,
             rate_multiplier,
+            weight: 1.0,
+            blend_mode: BlendMode::Override,
+            reference: AdditiveReference::FirstFrame,
+            layer: 0,
+            mask: None,
+            root_motion: RootMotionSettings::default(),
+            fade: None,
+            rate_ramp: None,
+            loops: 0,
             m: marker::PhantomData,
         }
     }
+
+    /// Fade the weight to `target` over `duration` seconds, aborting the animation at the end if
+    /// `abort` is set.
+    pub fn fade_to(&mut self, target: f32, duration: f32, abort: bool) {
+        let speed = if duration > 0. {
+            (target - self.weight).abs() / duration
+        } else {
+            std::f32::INFINITY
+        };
+        self.fade = Some(WeightFade {
+            target,
+            speed,
+            abort,
+        });
+    }
+
+    /// Ramp the rate multiplier to `target` over `duration` seconds.
+    pub fn ramp_rate_to(&mut self, target: f32, duration: f32) {
+        if duration > 0. {
+            self.rate_ramp = Some(RateRamp {
+                target,
+                speed: (target - self.rate_multiplier).abs() / duration,
+            });
+        } else {
+            self.rate_multiplier = target;
+            self.rate_ramp = None;
+        }
+    }
+
+    /// Advance the ongoing rate ramp by `delta_seconds`.
+    pub(crate) fn update_rate_ramp(&mut self, delta_seconds: f32) {
+        if let Some(ramp) = self.rate_ramp.clone() {
+            let step = ramp.speed * delta_seconds;
+            if (ramp.target - self.rate_multiplier).abs() <= step {
+                self.rate_multiplier = ramp.target;
+                self.rate_ramp = None;
+            } else if ramp.target > self.rate_multiplier {
+                self.rate_multiplier += step;
+            } else {
+                self.rate_multiplier -= step;
+            }
+        }
+    }
+
+    /// Advance the ongoing fade by `delta_seconds`.
+    pub(crate) fn update_fade(&mut self, delta_seconds: f32) {
+        if let Some(fade) = self.fade.clone() {
+            let step = fade.speed * delta_seconds;
+            if fade.speed.is_infinite() || (fade.target - self.weight).abs() <= step {
+                self.weight = fade.target;
+                self.fade = None;
+                if fade.abort {
+                    self.command = AnimationCommand::Abort;
+                }
+            } else if fade.target > self.weight {
+                self.weight += step;
+            } else {
+                self.weight -= step;
+            }
+        }
+    }
 }
 
 impl<T> Component for AnimationControl<T>
@@ -674,9 +1141,18 @@ where
 
 /// Contains all currently running animations for an entity.
 ///
-/// Have support for running multiple animations, will do linear blending between all active
-/// animations. The target component specifies if it can be blended, if it can't, the last added
-/// animation wins.
+/// Have support for running multiple animations, will blend all active animations by their
+/// weights and `BlendMode`. The target component specifies if it can be blended, if it can't, the
+/// last added animation wins.
+///
+/// Weights can be changed gradually to cross-fade between animations:
+///
+/// ```rust,ignore
+/// control_set
+///     .add_animation(Run, &run, EndControl::Loop(None), 1.0, AnimationCommand::Start)
+///     .fade_in(Run, 0.3)
+///     .fade_out(Walk, 0.3);
+/// ```
 ///
 /// ### Type parameters:
 ///
@@ -727,14 +1203,21 @@ where
     }
 
     fn set_command(&mut self, id: I, command: AnimationCommand<T>) -> &mut Self {
+        self.update_control(id, |control| control.command = command)
+    }
+
+    fn update_control<F>(&mut self, id: I, f: F) -> &mut Self
+    where
+        F: FnOnce(&mut AnimationControl<T>),
+    {
         if let Some(&mut (_, ref mut control)) = self.animations.iter_mut().find(|a| a.0 == id) {
-            control.command = command;
+            f(control);
         } else if let Some(ref mut control) = self
             .deferred_animations
             .iter_mut()
             .find(|a| a.animation_id == id)
         {
-            control.control.command = command;
+            f(&mut control.control);
         }
 
         self
@@ -779,11 +1262,21 @@ where
         self
     }
 
+    /// Ramp the animation rate to `rate_multiplier` over `duration` seconds
+    pub fn ramp_rate(&mut self, id: I, rate_multiplier: f32, duration: f32) -> &mut Self {
+        self.set_command(id, AnimationCommand::SetRate(rate_multiplier, duration))
+    }
+
     /// Step animation
     pub fn step(&mut self, id: I, direction: StepDirection) -> &mut Self {
         self.set_command(id, AnimationCommand::Step(direction))
     }
 
+    /// Let the animation finish its current loop, then end
+    pub fn finish_current_loop(&mut self, id: I) -> &mut Self {
+        self.set_command(id, AnimationCommand::FinishCurrentLoop)
+    }
+
     /// Set animation input value (point of interpolation)
     pub fn set_input(&mut self, id: I, input: f32) -> &mut Self {
         self.set_command(id, AnimationCommand::SetInputValue(input))
@@ -794,6 +1287,73 @@ where
         self.set_command(id, AnimationCommand::SetBlendWeights(weights))
     }
 
+    /// Set the weight of the animation, stopping any ongoing fade
+    pub fn set_weight(&mut self, id: I, weight: f32) -> &mut Self {
+        self.update_control(id, |control| {
+            control.weight = weight;
+            control.fade = None;
+        })
+    }
+
+    /// Set how the animation is blended with other animations
+    pub fn set_blend_mode(&mut self, id: I, blend_mode: BlendMode) -> &mut Self {
+        self.update_control(id, |control| control.blend_mode = blend_mode)
+    }
+
+    /// Make the animation additive, relative to the given reference pose, on the given layer.
+    ///
+    /// The reference and layer take effect when the animation starts.
+    pub fn set_additive(
+        &mut self,
+        id: I,
+        reference: AdditiveReference<T>,
+        layer: u32,
+    ) -> &mut Self {
+        self.update_control(id, |control| {
+            control.blend_mode = BlendMode::Additive;
+            control.reference = reference;
+            control.layer = layer;
+        })
+    }
+
+    /// Restrict the animation to the nodes with the given indices, e.g. the joints of the upper
+    /// body.
+    ///
+    /// The mask takes effect when the animation starts.
+    pub fn set_mask<M>(&mut self, id: I, mask: M) -> &mut Self
+    where
+        M: IntoIterator<Item = usize>,
+    {
+        let mask = mask.into_iter().collect();
+        self.update_control(id, |control| control.mask = Some(mask))
+    }
+
+    /// Set how the motion of the root node is played back, e.g. extracting it into the
+    /// `RootMotion` component of the entity for a character controller to move the entity with.
+    ///
+    /// The settings take effect when the animation starts.
+    pub fn set_root_motion(&mut self, id: I, root_motion: RootMotionSettings) -> &mut Self {
+        self.update_control(id, |control| control.root_motion = root_motion)
+    }
+
+    /// Fade the weight of the animation to `weight` over `duration` seconds
+    pub fn fade_to(&mut self, id: I, weight: f32, duration: f32) -> &mut Self {
+        self.update_control(id, |control| control.fade_to(weight, duration, false))
+    }
+
+    /// Fade the animation in from a weight of 0 to 1 over `duration` seconds
+    pub fn fade_in(&mut self, id: I, duration: f32) -> &mut Self {
+        self.update_control(id, |control| {
+            control.weight = 0.;
+            control.fade_to(1., duration, false);
+        })
+    }
+
+    /// Fade the weight of the animation to 0 over `duration` seconds, then abort it
+    pub fn fade_out(&mut self, id: I, duration: f32) -> &mut Self {
+        self.update_control(id, |control| control.fade_to(0., duration, true))
+    }
+
     /// Abort animation
     pub fn abort(&mut self, id: I) -> &mut Self {
         self.set_command(id, AnimationCommand::Abort)
@@ -931,3 +1491,121 @@ where
 {
     type Storage = DenseVecStorage<Self>;
 }
+
+#[cfg(test)]
+mod tests {
+    use minterpolate::InterpolationFunction;
+
+    use amethyst_assets::AssetStorage;
+    use amethyst_core::{Time, Transform};
+
+    use super::{
+        Animation, AnimationCommand, AnimationControl, AnimationTimeScale, ControlState,
+        EndControl, Sampler,
+    };
+
+    fn sampler(
+        input: Vec<f32>,
+        output: Vec<f32>,
+        function: InterpolationFunction<f32>,
+    ) -> Sampler<f32> {
+        Sampler {
+            input,
+            output,
+            function,
+        }
+    }
+
+    fn hermite(p0: f32, m0: f32, p1: f32, m1: f32, t: f32) -> f32 {
+        (2. * t.powi(3) - 3. * t.powi(2) + 1.) * p0
+            + (t.powi(3) - 2. * t.powi(2) + t) * m0
+            + (-2. * t.powi(3) + 3. * t.powi(2)) * p1
+            + (t.powi(3) - t.powi(2)) * m1
+    }
+
+    #[test]
+    fn cubic_spline_follows_gltf_hermite_form() {
+        // [in_tangent, value, out_tangent] for key frames at 0 and 2
+        let sampler = sampler(
+            vec![0., 2.],
+            vec![0., 1., 0.5, -1., 3., 0.],
+            InterpolationFunction::CubicSpline,
+        );
+        // tangents are scaled by the time between the key frames
+        let reference = |t: f32| hermite(1., 2. * 0.5, 3., 2. * -1., t / 2.);
+        assert!((sampler.sample(1.) - 2.375).abs() < 1e-6);
+        for &time in &[0.5, 1., 1.5] {
+            assert!((sampler.sample(time) - reference(time)).abs() < 1e-6);
+        }
+        assert!((sampler.sample(0.) - 1.).abs() < 1e-6);
+        assert!((sampler.sample(3.) - 3.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn cubic_spline_uses_tangents_of_the_current_key_frames() {
+        let sampler = sampler(
+            vec![0., 1., 3.],
+            vec![0., 0., 1., 2., 1., -1., 0., 4., 0.],
+            InterpolationFunction::CubicSpline,
+        );
+        let reference = hermite(1., 2. * -1., 4., 0., 0.25);
+        assert!((sampler.sample(1.5) - reference).abs() < 1e-6);
+    }
+
+    #[test]
+    fn cubic_spline_handles_key_frames_at_the_same_time() {
+        let sampler = sampler(
+            vec![0., 1., 1., 2.],
+            vec![0., 1., 0., 0., 5., 0., 0., 7., 0., 0., 9., 0.],
+            InterpolationFunction::CubicSpline,
+        );
+        // either of the key frames at 1 is a valid left key frame
+        let value = sampler.sample(1.);
+        assert!(value == 5. || value == 7., "{}", value);
+        assert!(sampler.sample(0.5).is_finite());
+        assert!(sampler.sample(1.5).is_finite());
+    }
+
+    #[test]
+    fn step_holds_previous_key_frame() {
+        let sampler = sampler(
+            vec![0., 1., 2.],
+            vec![5., 7., 9.],
+            InterpolationFunction::Step,
+        );
+        assert_eq!(5., sampler.sample(0.));
+        assert_eq!(5., sampler.sample(0.99));
+        assert_eq!(7., sampler.sample(1.));
+        assert_eq!(7., sampler.sample(1.5));
+        assert_eq!(9., sampler.sample(2.5));
+    }
+
+    #[test]
+    fn rate_ramps_to_target() {
+        let animation = AssetStorage::new().insert(Animation::<Transform>::new());
+        let mut control = AnimationControl::new(
+            animation,
+            EndControl::Loop(None),
+            ControlState::Requested,
+            AnimationCommand::Start,
+            1.,
+        );
+        control.ramp_rate_to(0., 0.5);
+        control.update_rate_ramp(0.25);
+        assert_eq!(0.5, control.rate_multiplier);
+        control.update_rate_ramp(0.5);
+        assert_eq!(0., control.rate_multiplier);
+        assert!(control.rate_ramp.is_none());
+        control.ramp_rate_to(2., 0.);
+        assert_eq!(2., control.rate_multiplier);
+    }
+
+    #[test]
+    fn fixed_time_scale_ignores_time_scale() {
+        let mut time = Time::default();
+        time.set_time_scale(0.);
+        time.set_delta_seconds(0.5);
+        assert_eq!(0., AnimationTimeScale::FollowTime.delta_seconds(&time));
+        assert_eq!(1., AnimationTimeScale::Fixed(2.).delta_seconds(&time));
+    }
+}
@@ -0,0 +1,143 @@
+use amethyst_core::{
+    ecs::prelude::{Component, DenseVecStorage, Entity},
+    math::{UnitQuaternion, Vector3},
+    Transform,
+};
+
+use crate::resources::AnimationSampling;
+
+/// How the motion of the root node of an animation is played back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RootMotionMode {
+    /// The root node moves as animated
+    Animated,
+    /// The motion is removed from the root node and accumulated in the `RootMotion` component of
+    /// the entity the animation is played on
+    Extract,
+    /// The motion is removed from the root node and discarded, playing the animation in place
+    InPlace,
+}
+
+/// Root motion settings of an animation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RootMotionSettings {
+    /// How the motion of the root node is played back, default is `RootMotionMode::Animated`
+    pub mode: RootMotionMode,
+    /// Node index of the root node in the animation, default is 0
+    pub node: usize,
+    /// Also remove the rotation around the up axis from the root node, default is `false`
+    pub yaw: bool,
+}
+
+impl Default for RootMotionSettings {
+    fn default() -> Self {
+        RootMotionSettings {
+            mode: RootMotionMode::Animated,
+            node: 0,
+            yaw: false,
+        }
+    }
+}
+
+impl RootMotionSettings {
+    /// Extract the motion of the node with the given index into the `RootMotion` component
+    pub fn extract(node: usize) -> Self {
+        RootMotionSettings {
+            mode: RootMotionMode::Extract,
+            node,
+            yaw: false,
+        }
+    }
+
+    /// Discard the motion of the node with the given index
+    pub fn in_place(node: usize) -> Self {
+        RootMotionSettings {
+            mode: RootMotionMode::InPlace,
+            node,
+            yaw: false,
+        }
+    }
+
+    /// Also remove the rotation around the up axis from the root node
+    pub fn with_yaw(mut self) -> Self {
+        self.yaw = true;
+        self
+    }
+}
+
+/// Root motion state of a single sampler of the root node.
+///
+/// ### Type parameters:
+///
+/// - `T`: the component type that the sampling should be applied to
+#[derive(Clone, Debug)]
+pub struct SamplerRootMotion<T>
+where
+    T: AnimationSampling,
+{
+    /// How the motion is played back
+    pub mode: RootMotionMode,
+    /// Remove the rotation around the up axis too
+    pub yaw: bool,
+    /// Entity receiving the `RootMotion`
+    pub entity: Entity,
+    /// Motion sampled in the previous frame
+    pub previous: Option<T::Primitive>,
+}
+
+/// Motion extracted from the root node of animations played with `RootMotionMode::Extract`,
+/// accumulated since it was last taken.
+///
+/// Added by the `SamplerInterpolationSystem` to the entity with the `AnimationControlSet`. A
+/// character controller running after it moves the entity by the motion:
+///
+/// ```rust,ignore
+/// for (motion, transform) in (&mut root_motions, &mut transforms).join() {
+///     motion.apply(transform);
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct RootMotion {
+    /// Translation of the root node, in the space of the entity
+    pub translation: Vector3<f32>,
+    /// Rotation of the root node
+    pub rotation: UnitQuaternion<f32>,
+}
+
+impl Default for RootMotion {
+    fn default() -> Self {
+        RootMotion {
+            translation: Vector3::zeros(),
+            rotation: UnitQuaternion::identity(),
+        }
+    }
+}
+
+impl RootMotion {
+    /// Add a translation, relative to the accumulated rotation
+    pub fn translate(&mut self, translation: Vector3<f32>) {
+        self.translation += self.rotation * translation;
+    }
+
+    /// Add a rotation
+    pub fn rotate(&mut self, rotation: UnitQuaternion<f32>) {
+        self.rotation *= rotation;
+    }
+
+    /// Take the accumulated motion, leaving no motion behind
+    pub fn take(&mut self) -> RootMotion {
+        std::mem::replace(self, RootMotion::default())
+    }
+
+    /// Move `transform` by the accumulated motion, and take it
+    pub fn apply(&mut self, transform: &mut Transform) {
+        let motion = self.take();
+        let translation = transform.rotation() * motion.translation;
+        transform.prepend_translation(translation);
+        transform.append_rotation(motion.rotation);
+    }
+}
+
+impl Component for RootMotion {
+    type Storage = DenseVecStorage<Self>;
+}
@@ -18,6 +18,10 @@ use super::resources::*;
 /// System for performing vertex skinning.
 ///
 /// Needs to run after global transforms have been updated for the current frame.
+///
+/// The joint transforms include all animation layers, which are resolved by the
+/// `SamplerInterpolationSystem`: the overriding animations are blended by their weights first,
+/// then the additive animations are added in the order of their layers.
 #[derive(Debug, SystemDesc)]
 #[system_desc(name(VertexSkinningSystemDesc))]
 pub struct VertexSkinningSystem {
@@ -134,3 +138,136 @@ impl<'a> System<'a> for VertexSkinningSystem {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use amethyst_assets::AssetStorage;
+    use amethyst_core::{
+        ecs::{
+            hibitset::BitSet,
+            prelude::{Builder, DispatcherBuilder, RunNow, System, World, WorldExt},
+        },
+        math::{Matrix4, Vector3},
+        secs_to_duration, SystemBundle, SystemDesc, Transform, TransformBundle,
+    };
+    use amethyst_rendy::skinning::JointTransforms;
+    use minterpolate::InterpolationFunction;
+
+    use super::VertexSkinningSystemDesc;
+    use crate::{
+        resources::{
+            BlendMode, ControlState, EndControl, Sampler, SamplerControl, SamplerControlSet,
+        },
+        skinning::{Joint, Skin},
+        systems::SamplerInterpolationSystem,
+        transform::TransformChannel,
+        util::SamplerPrimitive,
+    };
+
+    fn control(
+        sampler: &Sampler<SamplerPrimitive<f32>>,
+        world: &mut World,
+        blend_mode: BlendMode,
+        reference: Option<SamplerPrimitive<f32>>,
+        time: f32,
+    ) -> SamplerControl<Transform> {
+        let sampler = world
+            .write_resource::<AssetStorage<Sampler<SamplerPrimitive<f32>>>>()
+            .insert(sampler.clone());
+        SamplerControl {
+            control_id: 1,
+            channel: TransformChannel::Translation,
+            blend_weight: 1.,
+            weight: 1.,
+            blend_mode,
+            layer: 0,
+            reference,
+            root_motion: None,
+            sampler,
+            state: ControlState::Running(secs_to_duration(time)),
+            end: EndControl::Loop(None),
+            loops: 0,
+            reverse: false,
+            finish: false,
+            after: [0., 0., 0.].into(),
+            rate_multiplier: 1.,
+        }
+    }
+
+    #[test]
+    fn additive_offset_reaches_joint_matrices() {
+        let mut world = World::new();
+        let mut transforms = DispatcherBuilder::new();
+        TransformBundle::new()
+            .build(&mut world, &mut transforms)
+            .unwrap();
+        let mut transforms = transforms.build();
+        transforms.setup(&mut world);
+        let mut sampling = SamplerInterpolationSystem::<Transform>::new();
+        System::setup(&mut sampling, &mut world);
+        let mut skinning = VertexSkinningSystemDesc::default().build(&mut world);
+        System::setup(&mut skinning, &mut world);
+
+        // base pose at (1, 0, 0), additive animation moving from its first frame at (0, 0, 0)
+        // to (0, 4, 0), sampled halfway
+        let base = Sampler {
+            input: vec![0., 1.],
+            output: vec![[1., 0., 0.].into(), [1., 0., 0.].into()],
+            function: InterpolationFunction::Linear,
+        };
+        let additive = Sampler {
+            input: vec![0., 1.],
+            output: vec![[0., 0., 0.].into(), [0., 4., 0.].into()],
+            function: InterpolationFunction::Linear,
+        };
+        let mut set = SamplerControlSet::<Transform>::default();
+        set.add_control(control(&base, &mut world, BlendMode::Override, None, 0.));
+        let mut additive = control(
+            &additive,
+            &mut world,
+            BlendMode::Additive,
+            Some([0., 0., 0.].into()),
+            0.5,
+        );
+        additive.control_id = 2;
+        set.add_control(additive);
+
+        let skin = world.create_entity().build();
+        let joint = world
+            .create_entity()
+            .with(Transform::default())
+            .with(Joint { skins: vec![skin] })
+            .with(set)
+            .build();
+        let mesh = world
+            .create_entity()
+            .with(Transform::default())
+            .with(JointTransforms {
+                skin,
+                matrices: Vec::new(),
+            })
+            .build();
+        let mut meshes = BitSet::new();
+        meshes.add(mesh.id());
+        let inverse_bind = Matrix4::new_translation(&Vector3::new(-1., 0., 0.));
+        world
+            .write_storage::<Skin>()
+            .insert(skin, Skin::new(vec![joint], meshes, vec![inverse_bind]))
+            .unwrap();
+
+        sampling.run_now(&world);
+        transforms.dispatch(&world);
+        skinning.run_now(&world);
+        world.maintain();
+
+        let expected = Matrix4::new_translation(&Vector3::new(1., 2., 0.)) * inverse_bind;
+        let matrices = world.read_storage::<JointTransforms>();
+        let actual = matrices.get(mesh).unwrap().matrices[0];
+        assert!(
+            (expected - actual).abs().max() < 1e-5,
+            "{} != {}",
+            expected,
+            actual
+        );
+    }
+}
@@ -0,0 +1,355 @@
+use fnv::FnvHashMap;
+
+use amethyst_core::ecs::prelude::{Component, DenseVecStorage};
+
+use crate::resources::EndControl;
+
+/// Value of a parameter of an `AnimationStateMachine`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Parameter {
+    /// A number, e.g. the speed of a character
+    Float(f32),
+    /// A flag, e.g. whether a character is on the ground
+    Bool(bool),
+}
+
+/// Condition of a `Transition`, comparing a parameter of the state machine with a value.
+///
+/// Parameters which aren't set are `0.0` and `false`, conditions comparing a parameter with a
+/// value of another type are never met.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// The float parameter is greater than the value
+    Greater(String, f32),
+    /// The float parameter is less than the value
+    Less(String, f32),
+    /// The bool parameter has the value
+    Is(String, bool),
+}
+
+impl Condition {
+    /// Condition met if the float parameter `name` is greater than `value`
+    pub fn greater<N: Into<String>>(name: N, value: f32) -> Self {
+        Condition::Greater(name.into(), value)
+    }
+
+    /// Condition met if the float parameter `name` is less than `value`
+    pub fn less<N: Into<String>>(name: N, value: f32) -> Self {
+        Condition::Less(name.into(), value)
+    }
+
+    /// Condition met if the bool parameter `name` is `true`
+    pub fn is_true<N: Into<String>>(name: N) -> Self {
+        Condition::Is(name.into(), true)
+    }
+
+    /// Condition met if the bool parameter `name` is `false`
+    pub fn is_false<N: Into<String>>(name: N) -> Self {
+        Condition::Is(name.into(), false)
+    }
+
+    fn is_met(&self, parameters: &FnvHashMap<String, Parameter>) -> bool {
+        let float = |name: &String| match parameters.get(name) {
+            Some(&Parameter::Float(v)) => Some(v),
+            Some(&Parameter::Bool(_)) => None,
+            None => Some(0.),
+        };
+        match self {
+            Condition::Greater(name, value) => float(name).map_or(false, |v| v > *value),
+            Condition::Less(name, value) => float(name).map_or(false, |v| v < *value),
+            Condition::Is(name, value) => match parameters.get(name) {
+                Some(&Parameter::Bool(v)) => v == *value,
+                Some(&Parameter::Float(_)) => false,
+                None => !*value,
+            },
+        }
+    }
+}
+
+/// What happens when a `Transition` leads to the current state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reenter {
+    /// Nothing happens, the animation keeps playing
+    Ignore,
+    /// The animation of the state restarts from the beginning
+    Restart,
+}
+
+/// Transition between the states of an `AnimationStateMachine`, taken when all of its conditions
+/// are met.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    /// State the transition starts from, `None` for transitions from any state
+    pub from: Option<String>,
+    /// State the transition leads to
+    pub to: String,
+    /// Conditions which must all be met to take the transition
+    pub conditions: Vec<Condition>,
+    /// Duration of the cross-fade between the animations of the states, in seconds
+    pub duration: f32,
+    /// What happens when the transition leads to the current state, default is `Reenter::Ignore`
+    pub reenter: Reenter,
+}
+
+impl Transition {
+    /// Create a transition from the state `from` to the state `to`, cross-fading for `duration`
+    /// seconds
+    pub fn new<F, N>(from: F, to: N, duration: f32) -> Self
+    where
+        F: Into<String>,
+        N: Into<String>,
+    {
+        Transition {
+            from: Some(from.into()),
+            to: to.into(),
+            conditions: Vec::new(),
+            duration,
+            reenter: Reenter::Ignore,
+        }
+    }
+
+    /// Create a transition from any state to the state `to`, cross-fading for `duration` seconds
+    pub fn any<N: Into<String>>(to: N, duration: f32) -> Self {
+        Transition {
+            from: None,
+            to: to.into(),
+            conditions: Vec::new(),
+            duration,
+            reenter: Reenter::Ignore,
+        }
+    }
+
+    /// Add a condition to the transition
+    pub fn when(mut self, condition: Condition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Set what happens when the transition leads to the current state
+    pub fn with_reenter(mut self, reenter: Reenter) -> Self {
+        self.reenter = reenter;
+        self
+    }
+}
+
+/// A state of an `AnimationStateMachine`, playing an animation of the `AnimationSet` of the
+/// entity.
+///
+/// ### Type parameters:
+///
+/// - `I`: identifier type of the animation in the `AnimationSet` and `AnimationControlSet`
+#[derive(Debug, Clone)]
+pub struct AnimationState<I> {
+    /// Id of the animation played in the state
+    pub animation: I,
+    /// What to do when the animation ends, default is looping forever
+    pub end: EndControl,
+    /// Rate of the animation, default is 1.0
+    pub rate_multiplier: f32,
+}
+
+impl<I> AnimationState<I> {
+    /// Create a state looping the animation with the given id
+    pub fn new(animation: I) -> Self {
+        AnimationState {
+            animation,
+            end: EndControl::Loop(None),
+            rate_multiplier: 1.0,
+        }
+    }
+
+    /// Set what to do when the animation ends
+    pub fn with_end(mut self, end: EndControl) -> Self {
+        self.end = end;
+        self
+    }
+
+    /// Set the rate of the animation
+    pub fn with_rate(mut self, rate_multiplier: f32) -> Self {
+        self.rate_multiplier = rate_multiplier;
+        self
+    }
+}
+
+/// Chooses the animations of an entity from named states and the transitions between them.
+///
+/// Gameplay systems set the parameters of the state machine, and the
+/// `AnimationStateMachineSystem` takes the first transition, in the order they were added, from
+/// the current state or any state whose conditions are met, cross-fading between the animations
+/// of the states in the `AnimationControlSet` of the entity.
+///
+/// ### Example:
+///
+/// ```rust,ignore
+/// let state_machine = AnimationStateMachine::new("idle")
+///     .with_state("idle", AnimationState::new(AnimationId::Idle))
+///     .with_state("walk", AnimationState::new(AnimationId::Walk))
+///     .with_state("jump", AnimationState::new(AnimationId::Jump).with_end(EndControl::Stay))
+///     .with_transition(Transition::new("idle", "walk", 0.2).when(Condition::greater("speed", 0.1)))
+///     .with_transition(Transition::new("walk", "idle", 0.2).when(Condition::less("speed", 0.1)))
+///     .with_transition(
+///         Transition::any("jump", 0.1)
+///             .when(Condition::is_true("jumping"))
+///             .with_reenter(Reenter::Restart),
+///     );
+///
+/// // In a gameplay system
+/// state_machine.set_float("speed", velocity.norm());
+/// ```
+///
+/// ### Type parameters:
+///
+/// - `I`: identifier type of the animations in the `AnimationSet` and `AnimationControlSet`
+#[derive(Debug, Clone)]
+pub struct AnimationStateMachine<I> {
+    states: FnvHashMap<String, AnimationState<I>>,
+    transitions: Vec<Transition>,
+    entry: String,
+    pub(crate) current: Option<String>,
+    parameters: FnvHashMap<String, Parameter>,
+}
+
+impl<I> AnimationStateMachine<I> {
+    /// Create a state machine starting in the state `entry`
+    pub fn new<N: Into<String>>(entry: N) -> Self {
+        AnimationStateMachine {
+            states: FnvHashMap::default(),
+            transitions: Vec::new(),
+            entry: entry.into(),
+            current: None,
+            parameters: FnvHashMap::default(),
+        }
+    }
+
+    /// Add a state
+    pub fn add_state<N: Into<String>>(&mut self, name: N, state: AnimationState<I>) {
+        self.states.insert(name.into(), state);
+    }
+
+    /// Add a state
+    pub fn with_state<N: Into<String>>(mut self, name: N, state: AnimationState<I>) -> Self {
+        self.add_state(name, state);
+        self
+    }
+
+    /// Add a transition
+    pub fn add_transition(&mut self, transition: Transition) {
+        self.transitions.push(transition);
+    }
+
+    /// Add a transition
+    pub fn with_transition(mut self, transition: Transition) -> Self {
+        self.add_transition(transition);
+        self
+    }
+
+    /// Set a float parameter
+    pub fn set_float<N: Into<String>>(&mut self, name: N, value: f32) {
+        self.parameters.insert(name.into(), Parameter::Float(value));
+    }
+
+    /// Set a bool parameter
+    pub fn set_bool<N: Into<String>>(&mut self, name: N, value: bool) {
+        self.parameters.insert(name.into(), Parameter::Bool(value));
+    }
+
+    /// Get a parameter
+    pub fn parameter(&self, name: &str) -> Option<Parameter> {
+        self.parameters.get(name).cloned()
+    }
+
+    /// Name of the current state, `None` until the state machine has been started by the
+    /// `AnimationStateMachineSystem`
+    pub fn current(&self) -> Option<&str> {
+        self.current.as_ref().map(String::as_str)
+    }
+
+    /// Get a state
+    pub fn state(&self, name: &str) -> Option<&AnimationState<I>> {
+        self.states.get(name)
+    }
+
+    /// Name of the entry state
+    pub fn entry(&self) -> &str {
+        &self.entry
+    }
+
+    /// The first transition to take from the current state, if any
+    pub fn next_transition(&self) -> Option<&Transition> {
+        let current = self.current.as_ref()?;
+        self.transitions.iter().find(|t| {
+            t.from.as_ref().map_or(true, |from| from == current)
+                && (t.to != *current || t.reenter == Reenter::Restart)
+                && t.conditions.iter().all(|c| c.is_met(&self.parameters))
+        })
+    }
+}
+
+impl<I> Component for AnimationStateMachine<I>
+where
+    I: Send + Sync + 'static,
+{
+    type Storage = DenseVecStorage<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnimationState, AnimationStateMachine, Condition, Reenter, Transition};
+
+    fn state_machine() -> AnimationStateMachine<u32> {
+        let mut state_machine = AnimationStateMachine::new("idle")
+            .with_state("idle", AnimationState::new(0))
+            .with_state("walk", AnimationState::new(1))
+            .with_state("jump", AnimationState::new(2))
+            .with_transition(Transition::any("jump", 0.1).when(Condition::is_true("jumping")))
+            .with_transition(
+                Transition::new("idle", "walk", 0.2).when(Condition::greater("speed", 0.1)),
+            )
+            .with_transition(
+                Transition::new("walk", "idle", 0.2).when(Condition::less("speed", 0.1)),
+            );
+        state_machine.current = Some("idle".into());
+        state_machine
+    }
+
+    fn next(state_machine: &AnimationStateMachine<u32>) -> Option<&str> {
+        state_machine.next_transition().map(|t| t.to.as_str())
+    }
+
+    #[test]
+    fn transitions_when_conditions_are_met() {
+        let mut state_machine = state_machine();
+        assert_eq!(None, next(&state_machine));
+        state_machine.set_float("speed", 1.);
+        assert_eq!(Some("walk"), next(&state_machine));
+        state_machine.current = Some("walk".into());
+        assert_eq!(None, next(&state_machine));
+        state_machine.set_float("speed", 0.);
+        assert_eq!(Some("idle"), next(&state_machine));
+    }
+
+    #[test]
+    fn any_state_transitions() {
+        let mut state_machine = state_machine();
+        state_machine.set_float("speed", 1.);
+        state_machine.set_bool("jumping", true);
+        assert_eq!(Some("jump"), next(&state_machine));
+        state_machine.current = Some("jump".into());
+        assert_eq!(None, next(&state_machine));
+    }
+
+    #[test]
+    fn reentering_can_restart() {
+        let mut state_machine = state_machine()
+            .with_transition(Transition::any("idle", 0.).with_reenter(Reenter::Restart));
+        state_machine.set_bool("jumping", false);
+        assert_eq!(Some("idle"), next(&state_machine));
+    }
+
+    #[test]
+    fn mismatched_parameter_types_are_not_met() {
+        let mut state_machine = state_machine();
+        state_machine.set_bool("speed", true);
+        assert_eq!(None, next(&state_machine));
+    }
+}
@@ -1,4 +1,5 @@
 use std::{
+    cmp::Ordering,
     hash::Hash,
     marker::{self, PhantomData},
     time::Duration,
@@ -12,17 +13,22 @@ use minterpolate::InterpolationPrimitive;
 use amethyst_assets::{AssetStorage, Handle};
 use amethyst_core::{
     ecs::prelude::{
-        Component, Entities, Entity, Join, Read, ReadStorage, System, SystemData, World,
+        Component, Entities, Entity, Join, Read, ReadStorage, System, SystemData, World, Write,
         WriteStorage,
     },
-    timing::secs_to_duration,
+    shrev::EventChannel,
+    timing::{duration_to_secs, secs_to_duration, Time},
     SystemDesc,
 };
 
-use crate::resources::{
-    Animation, AnimationCommand, AnimationControl, AnimationControlSet, AnimationHierarchy,
-    AnimationSampling, AnimationSet, ApplyData, ControlState, DeferStartRelation, RestState,
-    Sampler, SamplerControl, SamplerControlSet, StepDirection,
+use crate::{
+    resources::{
+        AdditiveReference, Animation, AnimationCommand, AnimationControl, AnimationControlSet,
+        AnimationEvent, AnimationEventKind, AnimationHierarchy, AnimationMarker, AnimationSampling,
+        AnimationSet, AnimationTimeScale, ApplyData, BlendMode, ControlState, DeferStartRelation,
+        EndControl, RestState, Sampler, SamplerControl, SamplerControlSet, StepDirection,
+    },
+    root_motion::{RootMotionMode, SamplerRootMotion},
 };
 
 #[cfg(feature = "profiler")]
@@ -55,6 +61,10 @@ where
 /// animations they describe. If an animation only targets a single node/entity, there is no need
 /// for `AnimationHierarchy`.
 ///
+/// Will also write an `AnimationEvent` to `EventChannel<AnimationEvent<I>>` for every
+/// `AnimationMarker` that a running animation passes during the sampling of the current frame,
+/// and for every animation that completes.
+///
 /// ### Type parameters:
 ///
 /// - `I`: identifier type for running animations, only one animation can be run at the same time
@@ -70,6 +80,7 @@ where
     remove_ids: Vec<I>,
     state_set: FnvHashMap<I, f32>,
     deferred_start: Vec<(I, f32)>,
+    passed_markers: Vec<String>,
 }
 
 impl<I, T> AnimationControlSystem<I, T>
@@ -85,6 +96,7 @@ where
             remove_ids: Vec::default(),
             state_set: FnvHashMap::default(),
             deferred_start: Vec::default(),
+            passed_markers: Vec::default(),
         }
     }
 }
@@ -97,6 +109,9 @@ where
     #[allow(clippy::type_complexity)]
     type SystemData = (
         Entities<'a>,
+        Read<'a, Time>,
+        Read<'a, AnimationTimeScale>,
+        Write<'a, EventChannel<AnimationEvent<I>>>,
         Read<'a, AssetStorage<Animation<T>>>,
         Read<'a, AssetStorage<Sampler<T::Primitive>>>,
         WriteStorage<'a, AnimationControlSet<I, T>>,
@@ -113,6 +128,9 @@ where
 
         let (
             entities,
+            time,
+            time_scale,
+            mut events,
             animation_storage,
             sampler_storage,
             mut controls,
@@ -122,6 +140,7 @@ where
             mut rest_states,
             apply_data,
         ) = data;
+        let delta_seconds = time_scale.delta_seconds(&time);
         let mut remove_sets = Vec::default();
         for (entity, control_set) in (&*entities, &mut controls).join() {
             self.remove_ids.clear();
@@ -129,6 +148,10 @@ where
             let hierarchy = hierarchies.get(entity);
             for &mut (ref id, ref mut control) in control_set.animations.iter_mut() {
                 let mut remove = false;
+                if control.state.is_running() {
+                    control.update_fade(delta_seconds);
+                    control.update_rate_ramp(delta_seconds);
+                }
                 if let Some(state) =
                     animation_storage
                         .get(&control.animation)
@@ -138,6 +161,7 @@ where
                                 animation,
                                 control,
                                 hierarchy,
+                                &*animation_storage,
                                 &*sampler_storage,
                                 &mut samplers,
                                 &mut rest_states,
@@ -150,9 +174,46 @@ where
                 {
                     control.state = state;
                 }
+                if let Some(animation) = animation_storage.get(&control.animation) {
+                    if !animation.markers.is_empty() {
+                        self.passed_markers.clear();
+                        find_passed_markers(
+                            animation,
+                            control.id,
+                            entity,
+                            hierarchy,
+                            &samplers,
+                            &*sampler_storage,
+                            delta_seconds,
+                            &mut self.passed_markers,
+                        );
+                        events.iter_write(self.passed_markers.drain(..).map(|marker_name| {
+                            AnimationEvent {
+                                entity,
+                                animation_id: *id,
+                                kind: AnimationEventKind::Marker(marker_name),
+                            }
+                        }));
+                    }
+                }
+                if remove && control.state == ControlState::Done {
+                    events.single_write(AnimationEvent {
+                        entity,
+                        animation_id: *id,
+                        kind: AnimationEventKind::Complete {
+                            loops: control.loops,
+                        },
+                    });
+                }
                 if let AnimationCommand::Step(_) = control.command {
                     control.command = AnimationCommand::Start;
                 }
+                if let AnimationCommand::FinishCurrentLoop = control.command {
+                    control.command = AnimationCommand::Start;
+                }
+                if let AnimationCommand::SetRate(..) = control.command {
+                    control.command = AnimationCommand::Start;
+                }
                 if let AnimationCommand::SetInputValue(_) = control.command {
                     control.command = AnimationCommand::Start;
                 }
@@ -213,6 +274,7 @@ where
                                 animation,
                                 &mut def.control,
                                 hierarchy,
+                                &*animation_storage,
                                 &*sampler_storage,
                                 &mut samplers,
                                 &mut rest_states,
@@ -242,6 +304,164 @@ where
     }
 }
 
+/// Find the markers of an animation that will be passed when its samplers are interpolated
+/// during the current frame, in the order they will be passed.
+///
+/// The longest sampler of the animation is used as the reference for the animation length and
+/// the current position.
+#[allow(clippy::too_many_arguments)]
+fn find_passed_markers<T>(
+    animation: &Animation<T>,
+    control_id: u64,
+    entity: Entity,
+    hierarchy: Option<&AnimationHierarchy<T>>,
+    samplers: &WriteStorage<'_, SamplerControlSet<T>>,
+    sampler_storage: &AssetStorage<Sampler<T::Primitive>>,
+    delta_seconds: f32,
+    passed: &mut Vec<String>,
+) where
+    T: AnimationSampling,
+{
+    if animation.nodes.is_empty() {
+        return;
+    }
+    let h_fallback;
+    let hierarchy = match hierarchy {
+        Some(h) => h,
+        None => {
+            h_fallback = AnimationHierarchy::new_single(animation.nodes[0].0, entity);
+            &h_fallback
+        }
+    };
+    let longest = hierarchy
+        .nodes
+        .values()
+        .filter_map(|node_entity| samplers.get(*node_entity))
+        .flat_map(|set| set.samplers.iter())
+        .filter(|c| c.control_id == control_id)
+        .filter_map(|c| {
+            sampler_storage
+                .get(&c.sampler)
+                .and_then(|s| s.input.last().cloned())
+                .map(|length| (c, length))
+        })
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+    let (control, length) = match longest {
+        Some(longest) => longest,
+        None => return,
+    };
+    match control.state {
+        // Sampling starts at the beginning of the animation this frame
+        ControlState::Requested => {
+            passed.extend(
+                sorted_markers(&animation.markers, length)
+                    .into_iter()
+                    .filter(|m| m.0 <= 0.)
+                    .map(|m| m.1.name.clone()),
+            );
+        }
+        ControlState::Running(dur) => {
+            let wraps = control
+                .pass_limit()
+                .map(|limit| limit.saturating_sub(control.loops + 1));
+            let ping_pong = match control.end {
+                EndControl::PingPong(_) => true,
+                _ => false,
+            };
+            // negative rates freeze the animation, same as in `SamplerInterpolationSystem`
+            let advance = (delta_seconds * control.rate_multiplier).max(0.);
+            markers_between(
+                &animation.markers,
+                length,
+                duration_to_secs(dur),
+                if control.reverse { -advance } else { advance },
+                wraps,
+                ping_pong,
+                passed,
+            );
+        }
+        _ => {}
+    }
+}
+
+fn sorted_markers(markers: &[AnimationMarker], length: f32) -> Vec<(f32, &AnimationMarker)> {
+    let mut sorted = markers
+        .iter()
+        .map(|m| (m.time * length, m))
+        .collect::<Vec<_>>();
+    sorted.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+    sorted
+}
+
+/// Collect the markers passed when moving `advance` seconds from `start` in an animation of the
+/// given length, wrapping around at most `wraps` times (`None` = no limit).
+///
+/// A negative `advance` moves backwards through the animation. With `ping_pong`, the direction
+/// reverses at each end instead of wrapping around.
+fn markers_between(
+    markers: &[AnimationMarker],
+    length: f32,
+    start: f32,
+    advance: f32,
+    mut wraps: Option<u32>,
+    ping_pong: bool,
+    passed: &mut Vec<String>,
+) {
+    if length <= 0. || advance == 0. || !advance.is_finite() {
+        return;
+    }
+    let sorted = sorted_markers(markers, length);
+    let mut pos = start;
+    let mut remaining = advance.abs();
+    let mut forward = advance > 0.;
+    let mut wrapped = false;
+    loop {
+        // After wrapping around, a marker on the wrap point is passed as well
+        let after_pos = |t: f32| match (forward, wrapped && !ping_pong) {
+            (true, false) => t > pos,
+            (true, true) => t >= pos,
+            (false, false) => t < pos,
+            (false, true) => t <= pos,
+        };
+        if forward {
+            let target = pos + remaining;
+            passed.extend(
+                sorted
+                    .iter()
+                    .filter(|m| after_pos(m.0) && m.0 <= target.min(length))
+                    .map(|m| m.1.name.clone()),
+            );
+            if target <= length {
+                break;
+            }
+            remaining = target - length;
+        } else {
+            let target = pos - remaining;
+            passed.extend(
+                sorted
+                    .iter()
+                    .rev()
+                    .filter(|m| after_pos(m.0) && m.0 >= target.max(0.))
+                    .map(|m| m.1.name.clone()),
+            );
+            if target >= 0. {
+                break;
+            }
+            remaining = -target;
+        }
+        match wraps {
+            Some(0) => break,
+            Some(ref mut n) => *n -= 1,
+            None => {}
+        }
+        pos = if forward == ping_pong { length } else { 0. };
+        if ping_pong {
+            forward = !forward;
+        }
+        wrapped = true;
+    }
+}
+
 fn get_running_duration<T>(
     entity: Entity,
     control: &AnimationControl<T>,
@@ -298,6 +518,7 @@ where
 ///                active for, if this is None the animation must be for a single node, which is the
 ///                local entity. If the animation contains more than a single node index, the
 ///                animation will be silently dropped.
+/// - `animation_storage`: `AssetStorage` for all `Animation`s
 /// - `sampler_storage`: `AssetStorage` for all `Sampler`s
 /// - `samplers`: the active sampler sets
 /// - `targets`: Target components, used to retrieve the rest pose before animation starts.
@@ -313,6 +534,7 @@ fn process_animation_control<T>(
     animation: &Animation<T>,
     control: &mut AnimationControl<T>,
     hierarchy: Option<&AnimationHierarchy<T>>,
+    animation_storage: &AssetStorage<Animation<T>>,
     sampler_storage: &AssetStorage<Sampler<T::Primitive>>,
     samplers: &mut WriteStorage<'_, SamplerControlSet<T>>,
     rest_states: &mut WriteStorage<'_, RestState<T>>,
@@ -358,7 +580,9 @@ where
             control.id = *next_id;
             *next_id += 1;
             if start_animation(
+                entity,
                 animation,
+                animation_storage,
                 sampler_storage,
                 control,
                 hierarchy,
@@ -377,7 +601,9 @@ where
             control.id = *next_id;
             *next_id += 1;
             if start_animation(
+                entity,
                 animation,
+                animation_storage,
                 sampler_storage,
                 control,
                 hierarchy,
@@ -414,6 +640,17 @@ where
             None
         }
 
+        (&ControlState::Running(..), &AnimationCommand::SetRate(rate_multiplier, duration)) => {
+            control.ramp_rate_to(rate_multiplier, duration);
+            update_animation_rate(control.id, hierarchy, samplers, control.rate_multiplier);
+            None
+        }
+
+        (&ControlState::Running(..), &AnimationCommand::FinishCurrentLoop) => {
+            finish_animation(control.id, hierarchy, samplers);
+            None
+        }
+
         (&ControlState::Running(..), &AnimationCommand::SetBlendWeights(ref weights)) => {
             set_blend_weights(control.id, hierarchy, samplers, weights);
             None
@@ -422,6 +659,15 @@ where
         // check for finished/aborted animations, wait for samplers to signal done,
         // then remove control objects
         (&ControlState::Running(..), _) => {
+            if let Some(loops) = hierarchy
+                .nodes
+                .values()
+                .filter_map(|node_entity| samplers.get(*node_entity))
+                .filter_map(|set| set.loops(control.id))
+                .max()
+            {
+                control.loops = loops;
+            }
             if check_termination(control.id, hierarchy, &samplers) {
                 // Do termination
                 for node_entity in hierarchy.nodes.values() {
@@ -437,10 +683,18 @@ where
                     }
                 }
                 *remove = true;
+                Some(ControlState::Done)
             } else {
                 update_animation_rate(control.id, hierarchy, samplers, control.rate_multiplier);
+                update_animation_weight(
+                    control.id,
+                    hierarchy,
+                    samplers,
+                    control.weight,
+                    control.blend_mode,
+                );
+                None
             }
-            None
         }
 
         _ => None,
@@ -453,7 +707,9 @@ where
 ///
 /// ## Parameters
 ///
+/// - `entity`: the entity the animation is played on, receiving its root motion
 /// - `animation`: the animation to start
+/// - `animation_storage`: all animations, for the reference pose of additive animations
 /// - `sampler_storage`: all samplers
 /// - `control`: the control object for the animation instance
 /// - `hierarchy`: the animation node hierarchy for the entity hierarchy the animation instance is active for
@@ -464,7 +720,9 @@ where
 ///
 /// True if the animation was started, false if it wasn't.
 fn start_animation<T>(
+    entity: Entity,
     animation: &Animation<T>,
+    animation_storage: &AssetStorage<Animation<T>>,
     sampler_storage: &AssetStorage<Sampler<T::Primitive>>,
     control: &AnimationControl<T>,
     hierarchy: &AnimationHierarchy<T>,
@@ -476,19 +734,43 @@ fn start_animation<T>(
 where
     T: AnimationSampling + Component + Clone,
 {
+    let in_mask = |node_index: &usize| {
+        control
+            .mask
+            .as_ref()
+            .map_or(true, |mask| mask.contains(node_index))
+    };
+
     // check that hierarchy is valid, and all samplers exist
-    let valid = animation
-        .nodes
-        .iter()
-        .all(|&(ref node_index, _, ref sampler_handle)| {
+    let valid = animation.nodes.iter().filter(|node| in_mask(&node.0)).all(
+        |&(ref node_index, _, ref sampler_handle)| {
             hierarchy.nodes.contains_key(node_index)
                 && sampler_storage.get(sampler_handle).is_some()
-        });
+        },
+    );
 
     if !valid {
         return false;
     }
 
+    // additive animations are made relative to their reference pose
+    let reference_animation = match (control.blend_mode, &control.reference) {
+        (BlendMode::Additive, AdditiveReference::Animation(handle)) => {
+            match animation_storage.get(handle) {
+                Some(reference)
+                    if reference
+                        .nodes
+                        .iter()
+                        .all(|node| sampler_storage.get(&node.2).is_some()) =>
+                {
+                    Some(reference)
+                }
+                _ => return false,
+            }
+        }
+        _ => None,
+    };
+
     hierarchy.rest_state(|entity| targets.get(entity).cloned(), rest_states);
 
     let start_state = if let ControlState::Deferred(dur) = control.state {
@@ -498,7 +780,9 @@ where
     };
 
     // setup sampler tree
-    for &(ref node_index, ref channel, ref sampler_handle) in &animation.nodes {
+    for &(ref node_index, ref channel, ref sampler_handle) in
+        animation.nodes.iter().filter(|node| in_mask(&node.0))
+    {
         let node_entity = hierarchy.nodes.get(node_index).expect(
             "Unreachable: Existence of all nodes are checked in validation of hierarchy above",
         );
@@ -507,15 +791,52 @@ where
             .map(RestState::state)
             .or_else(|| targets.get(*node_entity))
         {
+            let reference = match (control.blend_mode, &control.reference) {
+                (BlendMode::Additive, AdditiveReference::None) | (BlendMode::Override, _) => None,
+                (BlendMode::Additive, _) => {
+                    let sampler = reference_animation
+                        .and_then(|reference| {
+                            reference
+                                .nodes
+                                .iter()
+                                .find(|node| node.0 == *node_index && node.1 == *channel)
+                        })
+                        .and_then(|node| sampler_storage.get(&node.2))
+                        .or_else(|| sampler_storage.get(sampler_handle))
+                        .expect("Unreachable: Existence of all samplers is checked above");
+                    Some(sampler.sample(sampler.input.first().cloned().unwrap_or(0.)))
+                }
+            };
+            let root_motion = match control.root_motion.mode {
+                RootMotionMode::Extract | RootMotionMode::InPlace
+                    if *node_index == control.root_motion.node =>
+                {
+                    Some(SamplerRootMotion {
+                        mode: control.root_motion.mode,
+                        yaw: control.root_motion.yaw,
+                        entity,
+                        previous: None,
+                    })
+                }
+                _ => None,
+            };
             let sampler_control = SamplerControl::<T> {
                 control_id: control.id,
                 channel: channel.clone(),
                 state: start_state.clone(),
                 sampler: sampler_handle.clone(),
                 end: control.end.clone(),
+                loops: 0,
+                reverse: false,
+                finish: false,
                 after: component.current_sample(channel, apply_data),
                 rate_multiplier: control.rate_multiplier,
                 blend_weight: 1.0,
+                weight: control.weight,
+                blend_mode: control.blend_mode,
+                layer: control.layer,
+                reference,
+                root_motion,
             };
             if let Some(ref mut set) = samplers.get_mut(*node_entity) {
                 set.add_control(sampler_control);
@@ -596,6 +917,20 @@ fn set_animation_input<T>(
     }
 }
 
+fn finish_animation<T>(
+    control_id: u64,
+    hierarchy: &AnimationHierarchy<T>,
+    controls: &mut WriteStorage<'_, SamplerControlSet<T>>,
+) where
+    T: AnimationSampling,
+{
+    for node_entity in hierarchy.nodes.values() {
+        if let Some(ref mut s) = controls.get_mut(*node_entity) {
+            s.finish(control_id);
+        }
+    }
+}
+
 fn set_blend_weights<T>(
     control_id: u64,
     hierarchy: &AnimationHierarchy<T>,
@@ -628,6 +963,23 @@ fn update_animation_rate<T>(
     }
 }
 
+fn update_animation_weight<T>(
+    control_id: u64,
+    hierarchy: &AnimationHierarchy<T>,
+    samplers: &mut WriteStorage<'_, SamplerControlSet<T>>,
+    weight: f32,
+    blend_mode: BlendMode,
+) where
+    T: AnimationSampling,
+{
+    for node_entity in hierarchy.nodes.values() {
+        if let Some(ref mut s) = samplers.get_mut(*node_entity) {
+            s.set_weight(control_id, weight);
+            s.set_blend_mode(control_id, blend_mode);
+        }
+    }
+}
+
 /// Check if all nodes in an `AnimationHierarchy` are ready for termination, if so remove all
 /// `SamplerControlSet`s for the hierarchy, if not request termination on all sampler controls
 fn check_and_terminate_animation<T>(
@@ -680,3 +1032,176 @@ where
         .flat_map(|(_, node_entity)| samplers.get(*node_entity))
         .all(|s| s.check_termination(control_id))
 }
+
+#[cfg(test)]
+mod tests {
+    use amethyst_assets::AssetStorage;
+    use amethyst_core::{
+        ecs::prelude::{Builder, Entity, RunNow, System, World, WorldExt},
+        shrev::{EventChannel, ReaderId},
+        SystemDesc, Time, Transform,
+    };
+    use minterpolate::InterpolationFunction;
+
+    use super::{markers_between, AnimationControlSystemDesc};
+    use crate::{
+        resources::{
+            Animation, AnimationCommand, AnimationControlSet, AnimationEvent, AnimationEventKind,
+            AnimationMarker, EndControl, Sampler,
+        },
+        systems::SamplerInterpolationSystem,
+        transform::TransformChannel,
+        util::SamplerPrimitive,
+    };
+
+    fn markers() -> Vec<AnimationMarker> {
+        vec![
+            AnimationMarker::new("c", 0.75),
+            AnimationMarker::new("a", 0.25),
+            AnimationMarker::new("b", 0.5),
+        ]
+    }
+
+    fn passed(start: f32, advance: f32, wraps: Option<u32>) -> Vec<String> {
+        let mut passed = Vec::new();
+        markers_between(&markers(), 2.0, start, advance, wraps, false, &mut passed);
+        passed
+    }
+
+    fn passed_ping_pong(start: f32, advance: f32, wraps: Option<u32>) -> Vec<String> {
+        let mut passed = Vec::new();
+        markers_between(&markers(), 2.0, start, advance, wraps, true, &mut passed);
+        passed
+    }
+
+    #[test]
+    fn forward() {
+        assert_eq!(passed(0.0, 0.4, None), Vec::<String>::new());
+        assert_eq!(passed(0.4, 0.2, None), vec!["a"]);
+        assert_eq!(passed(0.5, 0.1, None), Vec::<String>::new());
+    }
+
+    #[test]
+    fn large_step_fires_each_marker_once() {
+        assert_eq!(passed(0.1, 1.8, Some(0)), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn looping_fires_every_loop() {
+        assert_eq!(passed(1.4, 1.2, None), vec!["c", "a"]);
+        assert_eq!(passed(1.0, 4.0, None), vec!["c", "a", "b", "c", "a", "b"]);
+    }
+
+    #[test]
+    fn stops_at_end_without_loop() {
+        assert_eq!(passed(1.4, 1.2, Some(0)), vec!["c"]);
+        assert_eq!(passed(1.0, 4.0, Some(1)), vec!["c", "a", "b", "c"]);
+    }
+
+    #[test]
+    fn reverse_fires_in_reverse_order() {
+        assert_eq!(passed(1.8, -1.7, Some(0)), vec!["c", "b", "a"]);
+        assert_eq!(passed(0.6, -1.2, None), vec!["a", "c"]);
+    }
+
+    #[test]
+    fn ping_pong_fires_on_the_way_back() {
+        assert_eq!(passed_ping_pong(1.4, 1.2, None), vec!["c", "c"]);
+        assert_eq!(
+            passed_ping_pong(0.6, 4.0, None),
+            vec!["b", "c", "c", "b", "a", "a"]
+        );
+        assert_eq!(passed_ping_pong(0.6, -1.2, Some(1)), vec!["a", "a"]);
+        assert_eq!(passed_ping_pong(1.4, 1.2, Some(0)), vec!["c"]);
+    }
+
+    fn marker_events(
+        world: &World,
+        reader: &mut ReaderId<AnimationEvent<usize>>,
+        entity: Entity,
+    ) -> Vec<String> {
+        world
+            .read_resource::<EventChannel<AnimationEvent<usize>>>()
+            .read(reader)
+            .filter(|e| e.entity == entity)
+            .filter_map(|e| match e.kind {
+                AnimationEventKind::Marker(ref name) => Some(name.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn markers_follow_sampling() {
+        let mut world = World::new();
+        let mut control =
+            AnimationControlSystemDesc::<usize, Transform>::default().build(&mut world);
+        let mut sampling = SamplerInterpolationSystem::<Transform>::new();
+        System::setup(&mut sampling, &mut world);
+        world.write_resource::<Time>().set_delta_seconds(0.6);
+        let mut reader = world
+            .write_resource::<EventChannel<AnimationEvent<usize>>>()
+            .register_reader();
+
+        // moves from (0, 0, 0) to (2, 0, 0) in 2 seconds, with markers at 0.5 and 1.5 seconds
+        let sampler = world
+            .write_resource::<AssetStorage<Sampler<SamplerPrimitive<f32>>>>()
+            .insert(Sampler {
+                input: vec![0., 2.],
+                output: vec![[0., 0., 0.].into(), [2., 0., 0.].into()],
+                function: InterpolationFunction::Linear,
+            });
+        let animation = world
+            .write_resource::<AssetStorage<Animation<Transform>>>()
+            .insert(
+                Animation::new_single(0, TransformChannel::Translation, sampler)
+                    .with_marker("a", 0.25)
+                    .with_marker("b", 0.75),
+            );
+        let mut play = |rate: f32| {
+            let mut set = AnimationControlSet::<usize, Transform>::default();
+            set.add_animation(
+                0,
+                &animation,
+                EndControl::Loop(None),
+                rate,
+                AnimationCommand::Start,
+            );
+            world
+                .create_entity()
+                .with(Transform::default())
+                .with(set)
+                .build()
+        };
+        let forward = play(1.);
+        let backward = play(-1.);
+
+        let mut frames = Vec::new();
+        for _ in 0..4 {
+            control.run_now(&world);
+            sampling.run_now(&world);
+            world.maintain();
+            let transforms = world.read_storage::<Transform>();
+            frames.push((
+                transforms.get(forward).unwrap().translation().x,
+                marker_events(&world, &mut reader, forward),
+                transforms.get(backward).unwrap().translation().x,
+                marker_events(&world, &mut reader, backward),
+            ));
+        }
+
+        let none = Vec::<String>::new;
+        let expected = vec![
+            (0.0, none(), 0.0, none()),
+            (0.6, vec!["a".to_string()], 0.0, none()),
+            (1.2, none(), 0.0, none()),
+            (1.8, vec!["b".to_string()], 0.0, none()),
+        ];
+        for (expected, actual) in expected.into_iter().zip(frames) {
+            assert!((expected.0 - actual.0).abs() < 1e-5, "{:?}", actual);
+            assert_eq!(expected.1, actual.1);
+            assert!((expected.2 - actual.2).abs() < 1e-5, "{:?}", actual);
+            assert_eq!(expected.3, actual.3);
+        }
+    }
+}
@@ -5,10 +5,12 @@ use crate::resources::{Animation, Sampler};
 pub use self::{
     control::{AnimationControlSystem, AnimationControlSystemDesc},
     sampling::SamplerInterpolationSystem,
+    state_machine::AnimationStateMachineSystem,
 };
 
 mod control;
 mod sampling;
+mod state_machine;
 
 /// Asset storage processor for `Sampler`
 pub type SamplerProcessor<S> = Processor<Sampler<S>>;
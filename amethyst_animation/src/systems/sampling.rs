@@ -5,14 +5,17 @@ use minterpolate::InterpolationPrimitive;
 
 use amethyst_assets::AssetStorage;
 use amethyst_core::{
-    duration_to_nanos, duration_to_secs,
-    ecs::prelude::{Component, Join, Read, System, WriteStorage},
-    nanos_to_duration, secs_to_duration, Time,
+    duration_to_secs,
+    ecs::prelude::{Component, Entity, Join, Read, System, WriteStorage},
+    secs_to_duration, Time,
 };
 
-use crate::resources::{
-    AnimationSampling, ApplyData, BlendMethod, ControlState, EndControl, Sampler, SamplerControl,
-    SamplerControlSet,
+use crate::{
+    resources::{
+        AnimationSampling, AnimationTimeScale, ApplyData, BlendMethod, BlendMode, ControlState,
+        EndControl, Sampler, SamplerControl, SamplerControlSet,
+    },
+    root_motion::{RootMotion, RootMotionMode},
 };
 
 #[cfg(feature = "profiler")]
@@ -24,7 +27,11 @@ use thread_profiler::profile_scope;
 /// on `AnimationControlSystem`.
 ///
 /// Will process all active `SamplerControlSet`, and update the target component for the entity they
-/// belong to.
+/// belong to. When several samplers target the same channel, their samples are blended by their
+/// weights and `BlendMode`, using the `BlendMethod` of the component.
+///
+/// The motion extracted from the root node of animations played with `RootMotionMode::Extract` is
+/// accumulated in the `RootMotion` component of the entity the animation is played on.
 ///
 /// ### Type parameters:
 ///
@@ -35,8 +42,25 @@ where
     T: AnimationSampling,
 {
     m: marker::PhantomData<T>,
-    inner: Vec<(f32, T::Channel, T::Primitive)>,
+    inner: Vec<ChannelSample<T>>,
     channels: Vec<T::Channel>,
+    motions: Vec<(Entity, T::Channel, T::Primitive)>,
+}
+
+/// A sample of a single sampler for the current frame.
+#[derive(Debug)]
+struct ChannelSample<T>
+where
+    T: AnimationSampling,
+{
+    /// Effective weight of the sample
+    weight: f32,
+    mode: BlendMode,
+    layer: u32,
+    channel: T::Channel,
+    value: T::Primitive,
+    /// The value of the channel in the rest state
+    rest: T::Primitive,
 }
 
 impl<T> SamplerInterpolationSystem<T>
@@ -49,6 +73,7 @@ where
             m: marker::PhantomData,
             inner: Vec::default(),
             channels: Vec::default(),
+            motions: Vec::default(),
         }
     }
 }
@@ -59,50 +84,66 @@ where
 {
     type SystemData = (
         Read<'a, Time>,
+        Read<'a, AnimationTimeScale>,
         Read<'a, AssetStorage<Sampler<T::Primitive>>>,
         WriteStorage<'a, SamplerControlSet<T>>,
         WriteStorage<'a, T>,
+        WriteStorage<'a, RootMotion>,
         <T as ApplyData<'a>>::ApplyData,
     );
 
-    fn run(&mut self, (time, samplers, mut control_sets, mut comps, apply_data): Self::SystemData) {
+    fn run(
+        &mut self,
+        (
+            time,
+            time_scale,
+            samplers,
+            mut control_sets,
+            mut comps,
+            mut root_motions,
+            apply_data,
+        ): Self::SystemData,
+    ) {
         #[cfg(feature = "profiler")]
         profile_scope!("sampler_interpolation_system");
 
+        let delta_seconds = time_scale.delta_seconds(&time);
+        self.motions.clear();
         for (control_set, comp) in (&mut control_sets, &mut comps).join() {
             self.inner.clear();
             for control in control_set.samplers.iter_mut() {
                 if let Some(ref sampler) = samplers.get(&control.sampler) {
-                    process_sampler(control, sampler, &time, &mut self.inner);
+                    process_sampler(
+                        control,
+                        sampler,
+                        delta_seconds,
+                        &mut self.inner,
+                        &mut self.motions,
+                    );
                 }
             }
             if !self.inner.is_empty() {
                 self.channels.clear();
                 self.channels
-                    .extend(self.inner.iter().map(|o| &o.1).unique().cloned());
+                    .extend(self.inner.iter().map(|o| &o.channel).unique().cloned());
                 for channel in &self.channels {
-                    match comp.blend_method(channel) {
-                        None => {
-                            if let Some(p) = self
-                                .inner
-                                .iter()
-                                .filter(|p| p.1 == *channel)
-                                .map(|p| p.2.clone())
-                                .last()
-                            {
-                                comp.apply_sample(channel, &p, &apply_data);
-                            }
-                        }
-
-                        Some(BlendMethod::Linear) => {
-                            if let Some(p) = linear_blend::<T>(channel, &self.inner) {
-                                comp.apply_sample(channel, &p, &apply_data);
-                            }
-                        }
+                    let samples = self.inner.iter().filter(|p| p.channel == *channel);
+                    let sample = match comp.blend_method(channel) {
+                        None => samples.map(|p| p.value.clone()).last(),
+                        Some(method) => blend(method, channel, samples),
+                    };
+                    if let Some(p) = sample {
+                        comp.apply_sample(channel, &p, &apply_data);
                     }
                 }
             }
         }
+
+        for (entity, channel, delta) in self.motions.drain(..) {
+            if let Ok(entry) = root_motions.entry(entity) {
+                T::add_root_motion(&channel, &delta, entry.or_insert_with(RootMotion::default));
+            }
+        }
     }
 }
 
@@ -113,85 +154,180 @@ where
 /// - `control`: sampler control object
 /// - `sampler`: the sampler reference from the control object
 /// - `component`: the component to update
-/// - `now`: synchronized `Instant` for the current frame
+/// - `delta_seconds`: time elapsed for animations since the last frame
+/// - `motions`: root motion extracted this frame, for `RootMotionMode::Extract`
 fn process_sampler<T>(
     control: &mut SamplerControl<T>,
     sampler: &Sampler<T::Primitive>,
-    time: &Time,
-    output: &mut Vec<(f32, T::Channel, T::Primitive)>,
+    delta_seconds: f32,
+    output: &mut Vec<ChannelSample<T>>,
+    motions: &mut Vec<(Entity, T::Channel, T::Primitive)>,
 ) where
     T: AnimationSampling,
 {
     use crate::resources::ControlState::*;
 
-    let (new_state, new_end) = update_duration_and_check(&control, sampler, time);
-
-    // If a new end condition has been computed, update in control state
-    if let Some(end) = new_end {
-        control.end = end;
-    }
+    let loops = control.loops;
+    let ping_pong = match control.end {
+        EndControl::PingPong(_) => true,
+        _ => false,
+    };
+    let new_state = update_duration_and_check(control, sampler, delta_seconds);
 
     // Do sampling
-    match new_state {
-        Running(duration) | Paused(duration) => {
-            output.push((
-                control.blend_weight,
-                control.channel.clone(),
-                sampler.function.interpolate(
-                    duration_to_secs(duration),
-                    &sampler.input,
-                    &sampler.output,
-                    false,
-                ),
-            ));
-        }
-        Done => {
-            if let EndControl::Normal = control.end {
-                output.push((
-                    control.blend_weight,
-                    control.channel.clone(),
-                    control.after.clone(),
-                ));
-            }
-            if let EndControl::Stay = control.end {
-                let last_frame = sampler.input.last().cloned().unwrap_or(0.);
-
-                output.push((
-                    control.blend_weight,
-                    control.channel.clone(),
-                    sampler.function.interpolate(
-                        last_frame,
-                        &sampler.input,
-                        &sampler.output,
-                        false,
-                    ),
-                ));
+    let value = match new_state {
+        Running(duration) | Paused(duration) => Some(sampler.sample(duration_to_secs(duration))),
+        Done => match control.end {
+            // additive animations return to their reference pose, which adds nothing
+            EndControl::Normal => match (control.blend_mode, &control.reference) {
+                (BlendMode::Additive, Some(reference)) => Some(reference.clone()),
+                (BlendMode::Additive, None) => Some(T::sample_delta(
+                    &control.channel,
+                    &control.after,
+                    &control.after,
+                )),
+                (BlendMode::Override, _) => Some(control.after.clone()),
+            },
+            // a ping-pong ending on a backwards pass stays at the first frame
+            EndControl::Stay => {
+                let frame = if control.reverse {
+                    sampler.input.first()
+                } else {
+                    sampler.input.last()
+                };
+                Some(sampler.sample(frame.cloned().unwrap_or(0.)))
             }
+            _ => None,
+        },
+        _ => None,
+    };
+    // Remove the motion from the root node, stays at the last frame when done
+    let value = match (value, &new_state) {
+        (Some(value), Running(_)) | (Some(value), Paused(_)) | (Some(value), Done)
+            if control.root_motion.is_some() && !is_rest(control, &new_state) =>
+        {
+            let looped = !ping_pong && new_state.is_running() && control.loops > loops;
+            Some(extract_root_motion(
+                control, sampler, value, looped, motions,
+            ))
         }
-        _ => {}
+        (value, _) => value,
+    };
+    if let Some(value) = value {
+        let value = match control.reference {
+            Some(ref reference) => T::sample_delta(&control.channel, reference, &value),
+            None => value,
+        };
+        output.push(ChannelSample {
+            weight: control.blend_weight * control.weight,
+            mode: control.blend_mode,
+            layer: control.layer,
+            channel: control.channel.clone(),
+            value,
+            rest: control.after.clone(),
+        });
     }
 
     // Update state for next iteration
     control.state = new_state;
 }
 
+/// Whether the sample of a `Done` sampler is the rest state rather than a frame of the sampler
+fn is_rest<T>(control: &SamplerControl<T>, state: &ControlState) -> bool
+where
+    T: AnimationSampling,
+{
+    match (state, &control.end) {
+        (ControlState::Done, EndControl::Normal) => true,
+        _ => false,
+    }
+}
+
+/// Split the root motion off a sample of the root node, returning the remaining pose.
+///
+/// The motion since the previous frame is pushed to `motions` for `RootMotionMode::Extract`,
+/// scaled by the weight of the sampler. When the sampler `looped` since the previous frame, the
+/// motion is the rest of the previous loop added to the start of the new one.
+fn extract_root_motion<T>(
+    control: &mut SamplerControl<T>,
+    sampler: &Sampler<T::Primitive>,
+    value: T::Primitive,
+    looped: bool,
+    motions: &mut Vec<(Entity, T::Channel, T::Primitive)>,
+) -> T::Primitive
+where
+    T: AnimationSampling,
+{
+    let channel = &control.channel;
+    let root_motion = control
+        .root_motion
+        .as_mut()
+        .expect("Unreachable: root motion is checked by the caller");
+    let yaw = root_motion.yaw;
+    let frame = |time: f32| sampler.sample(time);
+    let first_frame = frame(sampler.input.first().cloned().unwrap_or(0.));
+    let (pose, motion) = match T::strip_root_motion(channel, &first_frame, &value, yaw) {
+        Some(stripped) => stripped,
+        None => return value,
+    };
+
+    if let Some(previous) = root_motion.previous.take() {
+        let delta = if looped {
+            let stripped_frame = |time: f32| {
+                T::strip_root_motion(channel, &first_frame, &frame(time), yaw)
+                    .map(|(_, motion)| motion)
+                    .unwrap_or_else(|| frame(time))
+            };
+            let start = stripped_frame(sampler.input.first().cloned().unwrap_or(0.));
+            let end = stripped_frame(sampler.input.last().cloned().unwrap_or(0.));
+            let (from, to) = if control.rate_multiplier < 0. {
+                (start, end)
+            } else {
+                (end, start)
+            };
+            T::add_sample(
+                channel,
+                &T::sample_delta(channel, &previous, &from),
+                &T::sample_delta(channel, &to, &motion),
+                1.,
+            )
+        } else {
+            T::sample_delta(channel, &previous, &motion)
+        };
+        if root_motion.mode == RootMotionMode::Extract {
+            let weight = (control.blend_weight * control.weight).max(0.).min(1.);
+            let none = T::sample_delta(channel, &delta, &delta);
+            motions.push((
+                root_motion.entity,
+                channel.clone(),
+                T::add_sample(channel, &none, &delta, weight),
+            ));
+        }
+    }
+    root_motion.previous = Some(motion);
+    pose
+}
+
 /// Update durations, check if the sampler is finished, start new samplers, and check for aborted
 /// samplers.
 ///
+/// Steps crossing several ends of the sampler in a single frame count each of them as a loop, and
+/// land on the phase and direction the sampler would have reached with smaller steps.
+///
 /// ## Parameters
 ///
-/// - `control`: sampler control object
+/// - `control`: sampler control object, its loop count, direction and end control are updated
 /// - `sampler`: sampler reference from control
-/// - `now`: synchronized `Instant` for the current frame
+/// - `delta_seconds`: time elapsed for animations since the last frame
 ///
 /// ## Returns
 ///
-/// Will return the new state of the sampling, and optionally a new end control state (for looping)
+/// Will return the new state of the sampling
 fn update_duration_and_check<T>(
-    control: &SamplerControl<T>,
+    control: &mut SamplerControl<T>,
     sampler: &Sampler<T::Primitive>,
-    time: &Time,
-) -> (ControlState, Option<EndControl>)
+    delta_seconds: f32,
+) -> ControlState
 where
     T: AnimationSampling,
 {
@@ -200,94 +336,516 @@ where
     // Check duration for end of sampling
     match control.state {
         // requested sampling => start interpolating
-        Requested => (Running(Duration::from_secs(0)), None),
+        Requested => Running(Duration::from_secs(0)),
 
         // deferred start that should start now
-        Deferred(dur) => (Running(dur), None),
+        Deferred(dur) => Running(dur),
 
         // abort sampling => end interpolating
-        Abort => (Done, None),
+        Abort => Done,
 
         // sampling is running, update duration and check end condition
         Running(duration) => {
-            let current_dur =
-                duration + secs_to_duration(time.delta_seconds() * control.rate_multiplier);
-            let last_frame = sampler
-                .input
-                .last()
-                .cloned()
-                .map(secs_to_duration)
-                .unwrap_or(Duration::from_secs(0));
-            // duration is past last frame of sampling
-            if current_dur > last_frame {
-                // Check end conditions
-                match control.end {
-                    // Do loop control
-                    EndControl::Loop(Some(i)) if i <= 1 => (Done, Some(EndControl::Normal)),
-                    EndControl::Loop(None) => {
-                        (Running(next_duration(last_frame, current_dur).0), None)
-                    }
-                    EndControl::Loop(Some(i)) => {
-                        let (next_dur, loops_removed) = next_duration(last_frame, current_dur);
-                        let remaining_loops = i - loops_removed;
-                        if remaining_loops <= 1 {
-                            (Done, Some(EndControl::Normal))
-                        } else {
-                            (
-                                Running(next_dur),
-                                Some(EndControl::Loop(Some(remaining_loops))),
-                            )
-                        }
-                    }
-                    // All other end cases will be handled during sampling
-                    _ => (Done, None),
-                }
+            let advance = (delta_seconds * control.rate_multiplier).max(0.);
+            // frozen, e.g. by a rate of 0, stay exactly where we are
+            if advance == 0. {
+                return Running(duration);
+            }
+            let length = sampler.input.last().cloned().unwrap_or(0.);
+            let position = duration_to_secs(duration);
+            // distance covered in the direction of the current pass
+            let travelled = if control.reverse {
+                length - position
             } else {
-                // last frame not reached, keep sampling
-                (Running(current_dur), None)
+                position
+            } + advance;
+            // end of the current pass not reached, keep sampling
+            if travelled <= length {
+                let position = if control.reverse {
+                    length - travelled
+                } else {
+                    travelled
+                };
+                return Running(secs_to_duration(position));
+            }
+
+            // passes ended this frame, and how far into the pass after them
+            let (ends, remainder) = if length > 0. {
+                let ends = (travelled / length).ceil() - 1.;
+                (ends as u32, travelled - ends * length)
+            } else {
+                (1, 0.)
+            };
+            let ping_pong = match control.end {
+                EndControl::PingPong(_) => true,
+                _ => false,
+            };
+            let limit = control.pass_limit();
+            let loops = control.loops.saturating_add(ends);
+            if length <= 0. || limit.map_or(false, |limit| loops >= limit) {
+                control.loops = limit.map_or(loops, |limit| loops.min(limit));
+                control.reverse = ping_pong && control.loops % 2 == 0;
+                // All other end cases will be handled during sampling
+                control.end = match control.end {
+                    EndControl::Loop(_) => EndControl::Normal,
+                    EndControl::LoopAndStay(_) | EndControl::PingPong(_) => EndControl::Stay,
+                    ref end => end.clone(),
+                };
+                Done
+            } else {
+                control.loops = loops;
+                control.reverse = ping_pong && loops % 2 == 1;
+                let position = if control.reverse {
+                    length - remainder
+                } else {
+                    remainder
+                };
+                Running(secs_to_duration(position))
             }
         }
 
         // Done and paused will be handled during sampling
-        ref state => (state.clone(), None),
+        ref state => state.clone(),
     }
 }
 
-fn next_duration(last_frame: Duration, duration: Duration) -> (Duration, u32) {
-    let animation_duration = duration_to_nanos(last_frame);
-    let current_duration = duration_to_nanos(duration);
-    let remain_duration = current_duration % animation_duration;
-    let loops = current_duration / animation_duration;
-    (nanos_to_duration(remain_duration), loops as u32)
-}
-
-fn linear_blend<T>(
-    channel: &T::Channel,
-    output: &[(f32, T::Channel, T::Primitive)],
-) -> Option<T::Primitive>
+/// Blend the samples of a single channel.
+///
+/// The overriding samples are blended by their weights, which are normalized if they sum to more
+/// than 1, otherwise the rest state makes up the remaining weight. The additive samples are then
+/// added to the result, in the order of their layers.
+fn blend<'s, T, I>(method: BlendMethod, channel: &T::Channel, samples: I) -> Option<T::Primitive>
 where
     T: AnimationSampling,
+    I: Iterator<Item = &'s ChannelSample<T>> + Clone,
 {
-    let total_blend_weight = output.iter().filter(|o| o.1 == *channel).map(|o| o.0).sum();
-    if total_blend_weight == 0. {
-        None
+    let rest = samples.clone().next()?.rest.clone();
+    let overrides = samples.clone().filter(|s| s.mode == BlendMode::Override);
+    let total: f32 = overrides.clone().map(|s| s.weight.max(0.)).sum();
+    let mut result = if total > 0. {
+        let reference = overrides.clone().next()?.value.clone();
+        let align = |value: &T::Primitive| match method {
+            BlendMethod::Spherical if value.dot(&reference) < 0. => value.mul(-1.),
+            _ => value.clone(),
+        };
+        let blended = overrides
+            .map(|s| align(&s.value).mul(s.weight.max(0.) / total.max(1.)))
+            .fold(T::default_primitive(channel), |acc, p| acc.add(&p));
+        let blended = if total < 1. {
+            blended.add(&align(&rest).mul(1. - total))
+        } else {
+            blended
+        };
+        match method {
+            BlendMethod::Spherical => blended.normalize(),
+            BlendMethod::Linear => blended,
+        }
     } else {
-        Some(
-            output
-                .iter()
-                .filter(|o| o.1 == *channel)
-                .map(|o| single_blend::<T>(total_blend_weight, o))
-                .fold(T::default_primitive(channel), |acc, p| acc.add(&p)),
-        )
+        rest
+    };
+    let mut additive = samples
+        .filter(|s| s.mode == BlendMode::Additive)
+        .collect::<Vec<_>>();
+    additive.sort_by_key(|s| s.layer);
+    for sample in additive {
+        result = T::add_sample(channel, &result, &sample.value, sample.weight);
     }
+    Some(result)
 }
 
-fn single_blend<T>(
-    total: f32,
-    &(ref weight, _, ref primitive): &(f32, T::Channel, T::Primitive),
-) -> T::Primitive
-where
-    T: AnimationSampling,
-{
-    primitive.mul(*weight / total)
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use amethyst_assets::AssetStorage;
+    use amethyst_core::{
+        duration_to_secs,
+        ecs::prelude::{Builder, World, WorldExt},
+        math::{UnitQuaternion, Vector3},
+        secs_to_duration, Transform,
+    };
+
+    use super::{blend, extract_root_motion, update_duration_and_check, ChannelSample};
+    use crate::{
+        resources::{
+            AnimationSampling, BlendMode, ControlState, EndControl, Sampler, SamplerControl,
+        },
+        root_motion::{RootMotionMode, SamplerRootMotion},
+        transform::TransformChannel,
+        util::SamplerPrimitive,
+        InterpolationFunction,
+    };
+
+    fn rotation(angle: f32) -> SamplerPrimitive<f32> {
+        SamplerPrimitive::Vec4(
+            (*UnitQuaternion::from_axis_angle(&Vector3::z_axis(), angle).as_vector()).into(),
+        )
+    }
+
+    fn sample(
+        weight: f32,
+        mode: BlendMode,
+        channel: TransformChannel,
+        value: SamplerPrimitive<f32>,
+        rest: SamplerPrimitive<f32>,
+    ) -> ChannelSample<Transform> {
+        ChannelSample {
+            weight,
+            mode,
+            layer: 0,
+            channel,
+            value,
+            rest,
+        }
+    }
+
+    fn blended(
+        channel: TransformChannel,
+        samples: &[ChannelSample<Transform>],
+    ) -> SamplerPrimitive<f32> {
+        let transform = Transform::default();
+        let method = transform.blend_method(&channel).unwrap();
+        blend(method, &channel, samples.iter()).unwrap()
+    }
+
+    fn assert_close(expected: SamplerPrimitive<f32>, actual: SamplerPrimitive<f32>) {
+        match (expected, actual) {
+            (SamplerPrimitive::Vec3(e), SamplerPrimitive::Vec3(a)) => {
+                for i in 0..3 {
+                    assert!((e[i] - a[i]).abs() < 1e-5, "{:?} != {:?}", e, a);
+                }
+            }
+            (SamplerPrimitive::Vec4(e), SamplerPrimitive::Vec4(a)) => {
+                // q and -q are the same rotation
+                let sign = if e.iter().zip(&a).map(|(e, a)| e * a).sum::<f32>() < 0. {
+                    -1.
+                } else {
+                    1.
+                };
+                for i in 0..4 {
+                    assert!((e[i] - sign * a[i]).abs() < 1e-5, "{:?} != {:?}", e, a);
+                }
+            }
+            (e, a) => panic!("{:?} != {:?}", e, a),
+        }
+    }
+
+    #[test]
+    fn opposing_rotations_blend_to_midpoint() {
+        let samples = [
+            sample(
+                0.5,
+                BlendMode::Override,
+                TransformChannel::Rotation,
+                rotation(FRAC_PI_2),
+                rotation(0.),
+            ),
+            sample(
+                0.5,
+                BlendMode::Override,
+                TransformChannel::Rotation,
+                rotation(-FRAC_PI_2),
+                rotation(0.),
+            ),
+        ];
+        assert_close(rotation(0.), blended(TransformChannel::Rotation, &samples));
+    }
+
+    #[test]
+    fn rotations_blend_along_shortest_path() {
+        let flipped = match rotation(FRAC_PI_2) {
+            SamplerPrimitive::Vec4(q) => SamplerPrimitive::Vec4([-q[0], -q[1], -q[2], -q[3]]),
+            _ => unreachable!(),
+        };
+        let samples = [
+            sample(
+                0.5,
+                BlendMode::Override,
+                TransformChannel::Rotation,
+                rotation(0.),
+                rotation(0.),
+            ),
+            sample(
+                0.5,
+                BlendMode::Override,
+                TransformChannel::Rotation,
+                flipped,
+                rotation(0.),
+            ),
+        ];
+        assert_close(
+            rotation(FRAC_PI_2 / 2.),
+            blended(TransformChannel::Rotation, &samples),
+        );
+    }
+
+    #[test]
+    fn partial_weights_blend_with_rest_state() {
+        let samples = [sample(
+            0.25,
+            BlendMode::Override,
+            TransformChannel::Translation,
+            [4., 0., 0.].into(),
+            [0., 8., 0.].into(),
+        )];
+        assert_close(
+            [1., 6., 0.].into(),
+            blended(TransformChannel::Translation, &samples),
+        );
+    }
+
+    #[test]
+    fn weights_above_one_are_normalized() {
+        let samples = [
+            sample(
+                1.,
+                BlendMode::Override,
+                TransformChannel::Translation,
+                [4., 0., 0.].into(),
+                [0., 0., 0.].into(),
+            ),
+            sample(
+                3.,
+                BlendMode::Override,
+                TransformChannel::Translation,
+                [0., 4., 0.].into(),
+                [0., 0., 0.].into(),
+            ),
+        ];
+        assert_close(
+            [1., 3., 0.].into(),
+            blended(TransformChannel::Translation, &samples),
+        );
+    }
+
+    #[test]
+    fn additive_samples_are_added_to_overrides() {
+        let samples = [
+            sample(
+                1.,
+                BlendMode::Override,
+                TransformChannel::Translation,
+                [1., 0., 0.].into(),
+                [0., 0., 0.].into(),
+            ),
+            sample(
+                0.5,
+                BlendMode::Additive,
+                TransformChannel::Translation,
+                [0., 2., 0.].into(),
+                [0., 0., 0.].into(),
+            ),
+        ];
+        assert_close(
+            [1., 1., 0.].into(),
+            blended(TransformChannel::Translation, &samples),
+        );
+
+        let samples = [
+            sample(
+                1.,
+                BlendMode::Override,
+                TransformChannel::Rotation,
+                rotation(FRAC_PI_2),
+                rotation(0.),
+            ),
+            sample(
+                0.5,
+                BlendMode::Additive,
+                TransformChannel::Rotation,
+                rotation(FRAC_PI_2),
+                rotation(0.),
+            ),
+        ];
+        assert_close(
+            rotation(FRAC_PI_2 * 1.5),
+            blended(TransformChannel::Rotation, &samples),
+        );
+    }
+
+    #[test]
+    fn additive_layers_are_added_in_order() {
+        let x = UnitQuaternion::from_axis_angle(&Vector3::x_axis(), FRAC_PI_2);
+        let z = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), FRAC_PI_2);
+        let primitive = |q: UnitQuaternion<f32>| SamplerPrimitive::Vec4((*q.as_vector()).into());
+        let mut upper = sample(
+            1.,
+            BlendMode::Additive,
+            TransformChannel::Rotation,
+            primitive(x),
+            rotation(0.),
+        );
+        upper.layer = 1;
+        let lower = sample(
+            1.,
+            BlendMode::Additive,
+            TransformChannel::Rotation,
+            primitive(z),
+            rotation(0.),
+        );
+        assert_close(
+            primitive(z * x),
+            blended(TransformChannel::Rotation, &[upper, lower]),
+        );
+    }
+
+    #[test]
+    fn deltas_add_up_to_sample() {
+        let reference = rotation(0.3);
+        let value = rotation(1.2);
+        let delta = Transform::sample_delta(&TransformChannel::Rotation, &reference, &value);
+        assert_close(rotation(0.9), delta);
+        assert_close(
+            value,
+            Transform::add_sample(&TransformChannel::Rotation, &reference, &delta, 1.),
+        );
+    }
+
+    fn translation_sampler() -> Sampler<SamplerPrimitive<f32>> {
+        Sampler {
+            input: vec![0., 2.],
+            output: vec![[0., 0., 0.].into(), [2., 0., 0.].into()],
+            function: InterpolationFunction::Linear,
+        }
+    }
+
+    fn control(end: EndControl, position: f32) -> SamplerControl<Transform> {
+        SamplerControl {
+            control_id: 1,
+            channel: TransformChannel::Translation,
+            blend_weight: 1.,
+            weight: 1.,
+            blend_mode: BlendMode::Override,
+            layer: 0,
+            reference: None,
+            root_motion: None,
+            sampler: AssetStorage::new().insert(translation_sampler()),
+            state: ControlState::Running(secs_to_duration(position)),
+            end,
+            loops: 0,
+            reverse: false,
+            finish: false,
+            after: [0., 0., 0.].into(),
+            rate_multiplier: 1.,
+        }
+    }
+
+    fn advance(control: &mut SamplerControl<Transform>, seconds: f32) -> ControlState {
+        update_duration_and_check(control, &translation_sampler(), seconds)
+    }
+
+    fn assert_running_at(expected: f32, state: ControlState) {
+        match state {
+            ControlState::Running(dur) => {
+                let position = duration_to_secs(dur);
+                assert!(
+                    (expected - position).abs() < 1e-3,
+                    "{} != {}",
+                    expected,
+                    position
+                );
+            }
+            state => panic!("{:?} is not running", state),
+        }
+    }
+
+    #[test]
+    fn large_steps_cross_several_loops() {
+        let mut control = control(EndControl::Loop(None), 1.5);
+        assert_running_at(0.5, advance(&mut control, 5.));
+        assert_eq!(3, control.loops);
+
+        let mut control = self::control(EndControl::LoopAndStay(3), 1.);
+        assert_eq!(ControlState::Done, advance(&mut control, 10.));
+        assert_eq!(3, control.loops);
+        match control.end {
+            EndControl::Stay => {}
+            ref end => panic!("{:?} doesn't stay", end),
+        }
+    }
+
+    #[test]
+    fn ping_pong_reverses_at_each_end() {
+        let mut control = control(EndControl::PingPong(None), 1.5);
+        assert_running_at(1.5, advance(&mut control, 1.));
+        assert!(control.reverse);
+        assert_running_at(0.5, advance(&mut control, 1.));
+        assert_running_at(0.5, advance(&mut control, 5.));
+        assert_eq!(4, control.loops);
+        assert!(!control.reverse);
+
+        let mut control = self::control(EndControl::PingPong(Some(2)), 1.);
+        assert_eq!(ControlState::Done, advance(&mut control, 7.));
+        assert_eq!(2, control.loops);
+        assert!(control.reverse);
+    }
+
+    #[test]
+    fn zero_rate_freezes_in_place() {
+        let mut control = control(EndControl::PingPong(None), 1.3);
+        control.reverse = true;
+        control.rate_multiplier = 0.;
+        let state = control.state.clone();
+        assert_eq!(state, advance(&mut control, 0.5));
+        control.rate_multiplier = 1.;
+        assert_running_at(0.8, advance(&mut control, 0.5));
+    }
+
+    #[test]
+    fn finish_ends_after_current_loop() {
+        let mut control = control(EndControl::Loop(None), 1.);
+        control.loops = 4;
+        control.finish = true;
+        assert_running_at(1.5, advance(&mut control, 0.5));
+        assert_eq!(ControlState::Done, advance(&mut control, 1.));
+        assert_eq!(5, control.loops);
+    }
+
+    #[test]
+    fn root_motion_is_continuous_across_the_loop_seam() {
+        let mut world = World::new();
+        let entity = world.create_entity().build();
+        let mut control = control(EndControl::Loop(None), 1.8);
+        control.root_motion = Some(SamplerRootMotion {
+            mode: RootMotionMode::Extract,
+            yaw: false,
+            entity,
+            previous: Some([1.8, 0., 0.].into()),
+        });
+        let mut motions = Vec::new();
+        let pose = extract_root_motion(
+            &mut control,
+            &translation_sampler(),
+            [0.2, 0., 0.].into(),
+            true,
+            &mut motions,
+        );
+        assert_close([0., 0., 0.].into(), pose);
+        assert_eq!(1, motions.len());
+        assert_eq!(entity, motions[0].0);
+        assert_close([0.4, 0., 0.].into(), motions[0].2);
+    }
+
+    #[test]
+    fn yaw_is_stripped_from_root_rotation() {
+        let yaw = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), 0.7);
+        let tilt = UnitQuaternion::from_axis_angle(&Vector3::x_axis(), 0.3);
+        let primitive = |q: UnitQuaternion<f32>| SamplerPrimitive::Vec4((*q.as_vector()).into());
+        let (pose, motion) = Transform::strip_root_motion(
+            &TransformChannel::Rotation,
+            &primitive(UnitQuaternion::identity()),
+            &primitive(yaw * tilt),
+            true,
+        )
+        .unwrap();
+        assert_close(primitive(tilt), pose);
+        assert_close(primitive(yaw), motion);
+        assert!(Transform::strip_root_motion(
+            &TransformChannel::Rotation,
+            &primitive(UnitQuaternion::identity()),
+            &primitive(yaw * tilt),
+            false,
+        )
+        .is_none());
+    }
 }
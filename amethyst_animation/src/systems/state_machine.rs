@@ -0,0 +1,138 @@
+use std::{hash::Hash, marker::PhantomData};
+
+use derivative::Derivative;
+use log::error;
+
+use amethyst_core::ecs::prelude::{Entities, Join, ReadStorage, System, WriteStorage};
+
+use crate::{
+    resources::{
+        AnimationCommand, AnimationControlSet, AnimationSampling, AnimationSet, ControlState,
+    },
+    state_machine::AnimationStateMachine,
+    util::get_animation_set,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// System running the `AnimationStateMachine`s, should run before `AnimationControlSystem`.
+///
+/// Starts the animation of the entry state of new state machines, and takes the transitions
+/// whose conditions are met, fading out the animation of the current state and fading in the
+/// animation of the next state in the `AnimationControlSet` of the entity. The animations are
+/// taken from the `AnimationSet` of the entity.
+///
+/// ### Type parameters:
+///
+/// - `I`: identifier type of the animations in the `AnimationSet` and `AnimationControlSet`
+/// - `T`: the component type that the animations should be applied to
+#[derive(Derivative, Debug)]
+#[derivative(Default(bound = ""))]
+pub struct AnimationStateMachineSystem<I, T> {
+    m: PhantomData<(I, T)>,
+}
+
+impl<I, T> AnimationStateMachineSystem<I, T> {
+    /// Creates a new `AnimationStateMachineSystem`
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<'a, I, T> System<'a> for AnimationStateMachineSystem<I, T>
+where
+    I: PartialEq + Eq + Hash + Copy + Send + Sync + 'static,
+    T: AnimationSampling,
+{
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, AnimationStateMachine<I>>,
+        ReadStorage<'a, AnimationSet<I, T>>,
+        WriteStorage<'a, AnimationControlSet<I, T>>,
+    );
+
+    fn run(&mut self, (entities, mut state_machines, sets, mut controls): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("animation_state_machine_system");
+
+        for (entity, state_machine, set) in (&*entities, &mut state_machines, &sets).join() {
+            let (from, to, duration) = match state_machine.current {
+                None => (None, state_machine.entry().to_owned(), 0.),
+                Some(ref current) => match state_machine.next_transition() {
+                    Some(transition) => (
+                        Some(current.clone()),
+                        transition.to.clone(),
+                        transition.duration,
+                    ),
+                    None => continue,
+                },
+            };
+            let state = match state_machine.state(&to) {
+                Some(state) => state,
+                None => {
+                    error!("Transition to unknown animation state {:?}", to);
+                    continue;
+                }
+            };
+            let control_set = match get_animation_set(&mut controls, entity) {
+                Some(control_set) => control_set,
+                None => continue,
+            };
+
+            if from.as_ref() == Some(&to) {
+                control_set.set_input(state.animation, 0.);
+                continue;
+            }
+            // An aborting animation can't be resumed, wait until it is removed
+            if is_aborting(control_set, state.animation) {
+                continue;
+            }
+            if let Some(from_state) = from.and_then(|from| state_machine.state(&from)) {
+                if from_state.animation != state.animation {
+                    control_set.fade_out(from_state.animation, duration);
+                }
+            }
+            if control_set.has_animation(state.animation) {
+                control_set
+                    .fade_to(state.animation, 1., duration)
+                    .set_rate(state.animation, state.rate_multiplier);
+            } else if let Some(animation) = set.get(&state.animation) {
+                control_set.add_animation(
+                    state.animation,
+                    animation,
+                    state.end.clone(),
+                    state.rate_multiplier,
+                    AnimationCommand::Start,
+                );
+                if duration > 0. {
+                    control_set.fade_in(state.animation, duration);
+                }
+            } else {
+                error!(
+                    "Animation of state {:?} is missing in the `AnimationSet`",
+                    to
+                );
+                continue;
+            }
+            state_machine.current = Some(to);
+        }
+    }
+}
+
+fn is_aborting<I, T>(control_set: &AnimationControlSet<I, T>, id: I) -> bool
+where
+    I: PartialEq,
+    T: AnimationSampling,
+{
+    control_set
+        .animations
+        .iter()
+        .filter(|a| a.0 == id)
+        .any(|(_, control)| match (&control.state, &control.command) {
+            (ControlState::Abort, _) | (ControlState::Done, _) | (_, AnimationCommand::Abort) => {
+                true
+            }
+            _ => false,
+        })
+}
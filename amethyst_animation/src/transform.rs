@@ -1,5 +1,7 @@
+use minterpolate::InterpolationPrimitive;
+
 use amethyst_core::{
-    math::{zero, Quaternion, Unit, Vector3, Vector4},
+    math::{zero, Quaternion, Unit, UnitQuaternion, Vector3, Vector4},
     Transform,
 };
 
@@ -7,6 +9,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     resources::{AnimationSampling, ApplyData, BlendMethod},
+    root_motion::RootMotion,
     util::SamplerPrimitive,
 };
 
@@ -65,7 +68,106 @@ impl AnimationSampling for Transform {
         }
     }
 
-    fn blend_method(&self, _: &Self::Channel) -> Option<BlendMethod> {
-        Some(BlendMethod::Linear)
+    fn blend_method(&self, channel: &Self::Channel) -> Option<BlendMethod> {
+        match channel {
+            TransformChannel::Rotation => Some(BlendMethod::Spherical),
+            _ => Some(BlendMethod::Linear),
+        }
+    }
+
+    fn sample_delta(
+        channel: &Self::Channel,
+        reference: &SamplerPrimitive<f32>,
+        sample: &SamplerPrimitive<f32>,
+    ) -> SamplerPrimitive<f32> {
+        use crate::util::SamplerPrimitive::*;
+
+        match (channel, reference, sample) {
+            (&TransformChannel::Rotation, Vec4(ref r), Vec4(ref s)) => {
+                let delta = Quaternion::from(Vector4::from(*r)).conjugate()
+                    * Quaternion::from(Vector4::from(*s));
+                Vec4(delta.coords.into())
+            }
+            _ => sample.sub(reference),
+        }
+    }
+
+    fn add_sample(
+        channel: &Self::Channel,
+        base: &SamplerPrimitive<f32>,
+        delta: &SamplerPrimitive<f32>,
+        weight: f32,
+    ) -> SamplerPrimitive<f32> {
+        use crate::util::SamplerPrimitive::*;
+
+        match (channel, base, delta) {
+            (&TransformChannel::Rotation, Vec4(ref b), Vec4(ref d)) => {
+                // Take the shortest path from the identity to the offset rotation
+                let d = if d[3] < 0. {
+                    Vector4::from(*d) * -1.
+                } else {
+                    Vector4::from(*d)
+                };
+                let delta = UnitQuaternion::identity()
+                    .nlerp(&Unit::new_normalize(Quaternion::from(d)), weight);
+                let rotation = Quaternion::from(Vector4::from(*b)) * delta.into_inner();
+                Vec4(rotation.coords.into())
+            }
+            _ => base.add(&delta.mul(weight)),
+        }
+    }
+
+    fn strip_root_motion(
+        channel: &Self::Channel,
+        reference: &SamplerPrimitive<f32>,
+        sample: &SamplerPrimitive<f32>,
+        yaw: bool,
+    ) -> Option<(SamplerPrimitive<f32>, SamplerPrimitive<f32>)> {
+        use crate::util::SamplerPrimitive::*;
+
+        match (channel, reference, sample) {
+            (&TransformChannel::Translation, _, _) => Some((*reference, *sample)),
+            (&TransformChannel::Rotation, Vec4(ref r), Vec4(ref s)) if yaw => {
+                // Split off the rotation around the up axis, keeping the one of the first frame
+                let sample = Quaternion::from(Vector4::from(*s));
+                let twist = yaw_of(&sample);
+                let pose = yaw_of(&Quaternion::from(Vector4::from(*r)))
+                    * twist.inverse()
+                    * Unit::new_normalize(sample);
+                Some((
+                    Vec4((*pose.as_vector()).into()),
+                    Vec4((*twist.as_vector()).into()),
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    fn add_root_motion(
+        channel: &Self::Channel,
+        delta: &SamplerPrimitive<f32>,
+        motion: &mut RootMotion,
+    ) {
+        use crate::util::SamplerPrimitive::*;
+
+        match (channel, *delta) {
+            (&TransformChannel::Translation, Vec3(ref d)) => {
+                motion.translate(Vector3::from(*d));
+            }
+            (&TransformChannel::Rotation, Vec4(ref d)) => {
+                motion.rotate(Unit::new_normalize(Quaternion::from(Vector4::from(*d))));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The rotation of `rotation` around the up axis
+fn yaw_of(rotation: &Quaternion<f32>) -> UnitQuaternion<f32> {
+    let twist = Quaternion::new(rotation.w, 0., rotation.j, 0.);
+    if twist.norm_squared() > 1e-12 {
+        Unit::new_normalize(twist)
+    } else {
+        UnitQuaternion::identity()
     }
 }
@@ -0,0 +1,174 @@
+use std::hash::Hash;
+
+use derivative::Derivative;
+use serde::{Deserialize, Serialize};
+
+use amethyst_assets::PrefabData;
+use amethyst_core::ecs::prelude::Entity;
+use amethyst_derive::PrefabData;
+use amethyst_error::Error;
+use amethyst_ui::{ToNativeWidget, UiImage, UiText, UiTransform, UiWidget};
+
+use crate::{
+    prefab::AnimationSetPrefab,
+    resources::{AnimationSampling, ApplyData, BlendMethod},
+    util::SamplerPrimitive,
+};
+
+/// Channels that can be animated on `UiText`
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum UiTextChannel {
+    /// The color of the text, using a range of 0.0 to 1.0 per channel
+    Color,
+}
+
+impl<'a> ApplyData<'a> for UiText {
+    type ApplyData = ();
+}
+
+impl AnimationSampling for UiText {
+    type Primitive = SamplerPrimitive<f32>;
+    type Channel = UiTextChannel;
+
+    fn apply_sample(&mut self, channel: &Self::Channel, data: &SamplerPrimitive<f32>, _: &()) {
+        match (channel, *data) {
+            (UiTextChannel::Color, SamplerPrimitive::Vec4(color)) => self.color = color,
+            _ => panic!("Attempt to apply invalid sample to UiText"),
+        }
+    }
+
+    fn current_sample(&self, channel: &Self::Channel, _: &()) -> SamplerPrimitive<f32> {
+        match channel {
+            UiTextChannel::Color => SamplerPrimitive::Vec4(self.color),
+        }
+    }
+
+    fn default_primitive(_: &Self::Channel) -> Self::Primitive {
+        SamplerPrimitive::Vec4([0.; 4])
+    }
+
+    fn blend_method(&self, _: &Self::Channel) -> Option<BlendMethod> {
+        Some(BlendMethod::Linear)
+    }
+}
+
+/// Channels that can be animated on `UiImage`
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum UiImageChannel {
+    /// The color of a `UiImage::SolidColor`, using a range of 0.0 to 1.0 per channel. Other
+    /// images are left unchanged, and sample as white.
+    Color,
+}
+
+impl<'a> ApplyData<'a> for UiImage {
+    type ApplyData = ();
+}
+
+impl AnimationSampling for UiImage {
+    type Primitive = SamplerPrimitive<f32>;
+    type Channel = UiImageChannel;
+
+    fn apply_sample(&mut self, channel: &Self::Channel, data: &SamplerPrimitive<f32>, _: &()) {
+        match (channel, *data) {
+            (UiImageChannel::Color, SamplerPrimitive::Vec4(color)) => {
+                if let UiImage::SolidColor(ref mut c) = self {
+                    *c = color;
+                }
+            }
+            _ => panic!("Attempt to apply invalid sample to UiImage"),
+        }
+    }
+
+    fn current_sample(&self, channel: &Self::Channel, _: &()) -> SamplerPrimitive<f32> {
+        match (channel, self) {
+            (UiImageChannel::Color, UiImage::SolidColor(color)) => SamplerPrimitive::Vec4(*color),
+            (UiImageChannel::Color, _) => SamplerPrimitive::Vec4([1.; 4]),
+        }
+    }
+
+    fn default_primitive(_: &Self::Channel) -> Self::Primitive {
+        SamplerPrimitive::Vec4([0.; 4])
+    }
+
+    fn blend_method(&self, _: &Self::Channel) -> Option<BlendMethod> {
+        Some(BlendMethod::Linear)
+    }
+}
+
+/// `PrefabData` for the animations of an UI entity, placing an `AnimationSet` for each animated
+/// component on the `Entity`.
+///
+/// ### Type parameters
+///
+/// - `I`: Id type of `Animation`s in `AnimationSet`s
+#[derive(Derivative, Clone, Debug, Deserialize, Serialize, PrefabData)]
+#[serde(
+    default,
+    bound(serialize = "I: Serialize", deserialize = "I: Deserialize<'de>")
+)]
+#[derivative(Default(bound = ""))]
+pub struct UiAnimationPrefab<I>
+where
+    I: Clone + Hash + Eq + Send + Sync + 'static,
+{
+    /// Animations of the `UiTransform`
+    pub transform: Option<AnimationSetPrefab<I, UiTransform>>,
+    /// Animations of the `UiText`
+    pub text: Option<AnimationSetPrefab<I, UiText>>,
+    /// Animations of the `UiImage`
+    pub image: Option<AnimationSetPrefab<I, UiImage>>,
+}
+
+/// Custom UI widget adding animations to a widget of an UI prefab.
+///
+/// Load UI prefabs with animated widgets with `UiCreator<'_, AnimatedUi<I>>`, after adding the
+/// `UiBundle` with `AnimatedUi<I>` as the custom UI type. The animations are started by id through
+/// the `AnimationControlSet` of the animated component on the entity of the widget.
+///
+/// ```ron,ignore
+/// Custom((
+///     animations: (
+///         text: (
+///             animations: [
+///                 (FadeIn, (
+///                     samplers: [(0, Color, (
+///                         input: [0., 1.],
+///                         output: [Vec4((1., 1., 1., 0.)), Vec4((1., 1., 1., 1.))],
+///                         function: Linear,
+///                     ))],
+///                 )),
+///             ],
+///         ),
+///     ),
+///     widget: Label(
+///         // ...
+///     ),
+/// ))
+/// ```
+///
+/// ### Type parameters
+///
+/// - `I`: Id type of `Animation`s in `AnimationSet`s
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(bound(serialize = "I: Serialize", deserialize = "I: Deserialize<'de>"))]
+pub struct AnimatedUi<I>
+where
+    I: Clone + Hash + Eq + Send + Sync + 'static,
+{
+    /// Animations of the widget
+    #[serde(default)]
+    pub animations: UiAnimationPrefab<I>,
+    /// The animated widget
+    pub widget: UiWidget<AnimatedUi<I>>,
+}
+
+impl<I> ToNativeWidget for AnimatedUi<I>
+where
+    I: Clone + Hash + Eq + Send + Sync + 'static,
+{
+    type PrefabData = UiAnimationPrefab<I>;
+
+    fn to_native_widget(self, _: Self::PrefabData) -> (UiWidget<Self>, Self::PrefabData) {
+        (self.widget, self.animations)
+    }
+}
@@ -13,6 +13,10 @@ use crate::{
 pub enum UiTransformChannel {
     /// The 2 dimensional position for an UI entity
     Translation,
+    /// The width of an UI entity
+    Width,
+    /// The height of an UI entity
+    Height,
 }
 
 impl<'a> ApplyData<'a> for UiTransform {
@@ -33,6 +37,8 @@ impl AnimationSampling for UiTransform {
                 self.local_x = d[0];
                 self.local_y = d[1];
             }
+            (&Width, Scalar(d)) => self.width = d,
+            (&Height, Scalar(d)) => self.height = d,
             _ => panic!("Attempt to apply invalid sample to UiTransform"),
         }
     }
@@ -41,12 +47,15 @@ impl AnimationSampling for UiTransform {
         use self::UiTransformChannel::*;
         match channel {
             Translation => SamplerPrimitive::Vec2([self.local_x, self.local_y]),
+            Width => SamplerPrimitive::Scalar(self.width),
+            Height => SamplerPrimitive::Scalar(self.height),
         }
     }
     fn default_primitive(channel: &Self::Channel) -> Self::Primitive {
         use self::UiTransformChannel::*;
         match channel {
             Translation => SamplerPrimitive::Vec2([zero(); 2]),
+            Width | Height => SamplerPrimitive::Scalar(zero()),
         }
     }
 
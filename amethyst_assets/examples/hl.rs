@@ -153,10 +153,7 @@ impl State {
                     eprintln!("Asset loading failed!");
                     eprintln!("-- Errors --");
                     progress.errors().iter().enumerate().for_each(|(n, e)| {
-                        eprintln!("{}: error: {}", n, e.error);
-                        for cause in e.error.causes().skip(1) {
-                            eprintln!("{}: caused by: {}", n, cause);
-                        }
+                        eprintln!("{}: {}", n, e);
                     });
                     eprintln!("Quitting game..");
 
@@ -1,4 +1,7 @@
-use crate::{storage::ProcessingState, FormatRegisteredData, Handle, Reload, SingleFile, Source};
+use crate::{
+    storage::ProcessingState, Dependencies, FormatRegisteredData, Handle, Reload, SingleFile,
+    Source,
+};
 use amethyst_core::ecs::storage::UnprotectedStorage;
 use amethyst_error::{Error, ResultExt};
 use std::{fmt::Debug, ops::Deref, sync::Arc};
@@ -92,6 +95,7 @@ pub trait Format<D: 'static>: objekt::Clone + Debug + Send + Sync + 'static {
             Ok(FormatValue {
                 data: self.import_simple(b)?,
                 reload: Some(Box::new(SingleFile::new(boxed_format, m, name, source))),
+                dependencies: Dependencies::new(),
             })
         } else {
             let b = source
@@ -163,11 +167,23 @@ pub struct FormatValue<D> {
     pub data: D,
     /// An optional reload structure
     pub reload: Option<Box<dyn Reload<D>>>,
+    /// Sub assets which have to be loaded before the asset is complete.
+    pub dependencies: Dependencies,
 }
 
 impl<D> FormatValue<D> {
     /// Creates a `FormatValue` from only the data (setting `reload` to `None`).
     pub fn data(data: D) -> Self {
-        FormatValue { data, reload: None }
+        FormatValue {
+            data,
+            reload: None,
+            dependencies: Dependencies::new(),
+        }
+    }
+
+    /// Sets the sub assets which have to be loaded before the asset is complete.
+    pub fn with_dependencies(mut self, dependencies: Dependencies) -> Self {
+        self.dependencies = dependencies;
+        self
     }
 }
@@ -0,0 +1,542 @@
+//! Sub assets which are loaded together with the asset that requested them.
+
+use std::{
+    any::{Any, TypeId},
+    fmt::{Debug, Formatter, Result as FmtResult},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use derivative::Derivative;
+use fnv::FnvHashMap;
+use log::debug;
+use parking_lot::{Mutex, RwLock};
+use rayon::ThreadPool;
+
+use amethyst_error::Error;
+
+use crate::{
+    error,
+    loader::import_asset,
+    progress::{Completion, Progress, ProgressCounter, Tracker},
+    queue::{LoadPriority, LoadQueue},
+    reload::Reload,
+    storage::{AssetStorage, StorageEndpoint},
+    Asset, Format, FormatValue, Handle, Source,
+};
+
+/// Sub assets requested by a `Format` while importing an asset, e.g. the textures of a
+/// material.
+///
+/// Sub assets are loaded from the same source as the asset that requested them, and
+/// may request sub assets of their own. The requesting asset is only processed, and its
+/// `Tracker` is only notified, once all of its sub assets have been loaded. If any of them
+/// fails to load, the requesting asset fails as well. Requesting an asset that is already
+/// being loaded further up the chain of requests is reported as a circular dependency.
+///
+/// When a sub asset is hot-reloaded, the asset that requested it is reloaded too.
+///
+/// Sub assets can only be requested for asset types whose `AssetStorage` the `Loader`
+/// knows about, which is the case once it has a `Processor` or has been passed to the
+/// `Loader` before. Use `Loader::register_storage` for storages processed by other systems.
+///
+/// ## Examples
+///
+/// ```rust,ignore
+/// fn import(
+///     &self,
+///     name: String,
+///     source: Arc<dyn Source>,
+///     _create_reload: Option<Box<dyn Format<MaterialData>>>,
+/// ) -> Result<FormatValue<MaterialData>, Error> {
+///     let description = parse(&source.load(&name)?)?;
+///
+///     let mut dependencies = Dependencies::new();
+///     let albedo = dependencies.request::<Texture, _, _>(description.albedo, ImageFormat::default());
+///
+///     Ok(FormatValue::data(MaterialData { albedo }).with_dependencies(dependencies))
+/// }
+/// ```
+#[derive(Default)]
+pub struct Dependencies {
+    requests: Vec<Box<dyn Request>>,
+    progress: ProgressCounter,
+}
+
+impl Dependencies {
+    /// Creates an empty list of sub asset requests.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Requests the sub asset `name`, to be imported using `format`.
+    ///
+    /// The returned `Dependency` is meant to be stored in the asset data, so that the asset
+    /// can get the handle to its sub asset when it is processed.
+    pub fn request<A, F, N>(&mut self, name: N, format: F) -> Dependency<A>
+    where
+        A: Asset,
+        F: Format<A::Data>,
+        N: Into<String>,
+    {
+        let dependency = Dependency {
+            handle: Default::default(),
+        };
+        self.requests.push(Box::new(TypedRequest {
+            name: name.into(),
+            format,
+            dependency: dependency.clone(),
+        }));
+        dependency
+    }
+
+    /// Returns `true` if no sub assets have been requested.
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// Returns `Ok(true)` once all sub assets have been loaded, and the error of the first
+    /// sub asset that failed to load, if any.
+    pub(crate) fn check(&self) -> Result<bool, Error> {
+        match self.progress.complete() {
+            Completion::Complete => Ok(true),
+            Completion::Loading => Ok(false),
+            Completion::Failed => {
+                let error = self.progress.errors().into_iter().next().map_or_else(
+                    || Error::from_string("Failed to load dependency"),
+                    |meta| {
+                        Error::from(error::Error::Dependency(
+                            meta.asset_type_name,
+                            meta.asset_name,
+                        ))
+                        .with_source(meta.error)
+                    },
+                );
+                Err(error)
+            }
+        }
+    }
+}
+
+impl Debug for Dependencies {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Dependencies")
+            .field("requests", &self.requests.len())
+            .field("progress", &self.progress)
+            .finish()
+    }
+}
+
+/// A sub asset requested through `Dependencies::request`.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""), Debug(bound = ""))]
+pub struct Dependency<A> {
+    handle: Arc<Mutex<Option<Handle<A>>>>,
+}
+
+impl<A> Dependency<A> {
+    /// Returns the handle of the sub asset.
+    ///
+    /// This is always `Some` by the time the asset that requested it gets processed.
+    pub fn handle(&self) -> Option<Handle<A>> {
+        self.handle.lock().clone()
+    }
+}
+
+/// The storages the `Loader` can load sub assets into, by asset type.
+#[derive(Clone, Default)]
+pub(crate) struct StorageRegistry {
+    endpoints: Arc<RwLock<FnvHashMap<TypeId, Box<dyn Any + Send + Sync>>>>,
+}
+
+impl StorageRegistry {
+    pub(crate) fn register<A: Asset>(&self, storage: &AssetStorage<A>) {
+        let registered = self
+            .endpoints
+            .read()
+            .get(&TypeId::of::<A>())
+            .and_then(|endpoint| endpoint.downcast_ref::<StorageEndpoint<A>>())
+            .map_or(false, |endpoint| endpoint.same_storage(&storage.endpoint));
+        if !registered {
+            self.endpoints
+                .write()
+                .insert(TypeId::of::<A>(), Box::new(storage.endpoint.clone()));
+        }
+    }
+
+    fn endpoint<A: Asset>(&self) -> Option<StorageEndpoint<A>> {
+        self.endpoints
+            .read()
+            .get(&TypeId::of::<A>())
+            .and_then(|endpoint| endpoint.downcast_ref::<StorageEndpoint<A>>())
+            .cloned()
+    }
+}
+
+/// Everything needed to load sub assets from a worker thread.
+#[derive(Clone)]
+pub(crate) struct DependencyContext {
+    registry: StorageRegistry,
+    pool: Arc<ThreadPool>,
+    queue: Arc<LoadQueue>,
+    hot_reload: bool,
+    priority: LoadPriority,
+    /// The assets which requested the asset currently being imported, root first.
+    chain: Vec<(TypeId, &'static str, String)>,
+}
+
+impl DependencyContext {
+    pub(crate) fn new(
+        registry: StorageRegistry,
+        pool: Arc<ThreadPool>,
+        queue: Arc<LoadQueue>,
+        hot_reload: bool,
+        priority: LoadPriority,
+    ) -> Self {
+        DependencyContext {
+            registry,
+            pool,
+            queue,
+            hot_reload,
+            priority,
+            chain: Vec::new(),
+        }
+    }
+
+    pub(crate) fn hot_reload(&self) -> bool {
+        self.hot_reload
+    }
+
+    pub(crate) fn pool(&self) -> &Arc<ThreadPool> {
+        &self.pool
+    }
+
+    /// Starts loading the sub assets requested by the asset `name`.
+    ///
+    /// If the asset can be reloaded, its reload object is wrapped so that it also reloads
+    /// whenever one of the sub assets has been reloaded.
+    pub(crate) fn resolve<A: Asset>(
+        &self,
+        mut value: FormatValue<A::Data>,
+        name: &str,
+        source: &Arc<dyn Source>,
+    ) -> FormatValue<A::Data> {
+        if value.dependencies.is_empty() {
+            return value;
+        }
+
+        let mut child = self.clone();
+        child
+            .chain
+            .push((TypeId::of::<A>(), A::NAME, name.to_owned()));
+
+        let mut reloaded = Vec::new();
+        let requests = std::mem::replace(&mut value.dependencies.requests, Vec::new());
+        for request in requests {
+            request.load(
+                source,
+                &child,
+                &mut value.dependencies.progress,
+                &mut reloaded,
+            );
+        }
+
+        value.reload = value.reload.map(|inner| {
+            Box::new(DependentReload::<A> {
+                inner,
+                context: self.clone(),
+                source: source.clone(),
+                reloaded,
+            }) as Box<dyn Reload<A::Data>>
+        });
+        value
+    }
+
+    fn circular_dependency(&self, type_id: TypeId, name: &str) -> Option<String> {
+        let start = self
+            .chain
+            .iter()
+            .position(|&(id, _, ref n)| id == type_id && n == name)?;
+        let mut chain = self.chain[start..]
+            .iter()
+            .map(|&(_, asset_type, ref n)| format!("{} {:?}", asset_type, n))
+            .collect::<Vec<_>>();
+        chain.push(chain[0].clone());
+        Some(chain.join(" -> "))
+    }
+}
+
+trait Request: Send + Sync {
+    fn load(
+        self: Box<Self>,
+        source: &Arc<dyn Source>,
+        context: &DependencyContext,
+        progress: &mut ProgressCounter,
+        reloaded: &mut Vec<Arc<AtomicBool>>,
+    );
+}
+
+struct TypedRequest<A, F> {
+    name: String,
+    format: F,
+    dependency: Dependency<A>,
+}
+
+impl<A, F> Request for TypedRequest<A, F>
+where
+    A: Asset,
+    F: Format<A::Data>,
+{
+    fn load(
+        self: Box<Self>,
+        source: &Arc<dyn Source>,
+        context: &DependencyContext,
+        mut progress: &mut ProgressCounter,
+        reloaded: &mut Vec<Arc<AtomicBool>>,
+    ) {
+        let TypedRequest {
+            name,
+            format,
+            dependency,
+        } = *self;
+
+        progress.add_assets(1);
+        let tracker = Box::new(progress.create_tracker()) as Box<dyn Tracker>;
+
+        let endpoint = match context.registry.endpoint::<A>() {
+            Some(endpoint) => endpoint,
+            None => {
+                let e = Error::from(error::Error::UnknownStorage(A::NAME));
+                tracker.fail(0, A::NAME, name, e);
+                return;
+            }
+        };
+        let handle = endpoint.allocate();
+
+        if let Some(chain) = context.circular_dependency(TypeId::of::<A>(), &name) {
+            let e = Error::from(error::Error::CircularDependency(chain));
+            tracker.fail(handle.id(), A::NAME, name, e);
+            return;
+        }
+
+        debug!(
+            "{:?}: Loading dependency {:?} with format {:?} (handle id: {:?})",
+            A::NAME,
+            name,
+            format.name(),
+            handle,
+        );
+
+        let flag = Arc::new(AtomicBool::new(false));
+        endpoint.listen_for_reload(handle.id(), &flag);
+        reloaded.push(flag);
+        *dependency.handle.lock() = Some(handle.clone());
+
+        let source = source.clone();
+        let context = context.clone();
+        let (queue, pool, priority) = (
+            context.queue.clone(),
+            context.pool.clone(),
+            context.priority,
+        );
+        queue.spawn(&pool, priority, move || {
+            import_asset(
+                name,
+                format,
+                source,
+                handle,
+                tracker,
+                endpoint.processed,
+                context,
+            );
+        });
+    }
+}
+
+/// Reload object of an asset with sub assets, which also reloads the asset whenever one
+/// of its sub assets has been reloaded.
+struct DependentReload<A: Asset> {
+    inner: Box<dyn Reload<A::Data>>,
+    context: DependencyContext,
+    source: Arc<dyn Source>,
+    reloaded: Vec<Arc<AtomicBool>>,
+}
+
+impl<A: Asset> Clone for DependentReload<A> {
+    fn clone(&self) -> Self {
+        DependentReload {
+            inner: self.inner.clone(),
+            context: self.context.clone(),
+            source: self.source.clone(),
+            reloaded: self.reloaded.clone(),
+        }
+    }
+}
+
+impl<A: Asset> Reload<A::Data> for DependentReload<A> {
+    fn needs_reload(&self) -> bool {
+        self.inner.needs_reload()
+            || self
+                .reloaded
+                .iter()
+                .any(|flag| flag.load(Ordering::Relaxed))
+    }
+
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    fn format(&self) -> &'static str {
+        self.inner.format()
+    }
+
+    fn reload(self: Box<Self>) -> Result<FormatValue<A::Data>, Error> {
+        let name = self.inner.name();
+        let value = self.inner.reload()?;
+        Ok(self.context.resolve::<A>(value, &name, &self.source))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::from_utf8, sync::Arc, thread::sleep, time::Duration};
+
+    use rayon::ThreadPoolBuilder;
+
+    use amethyst_core::ecs::prelude::VecStorage;
+    use amethyst_error::Error;
+
+    use super::{Dependencies, Dependency};
+    use crate::{
+        Asset, AssetStorage, Completion, Format, FormatValue, Handle, Loader, ProcessingState,
+        ProgressCounter, Source,
+    };
+
+    /// Each asset is a list of the names of the assets it depends on.
+    struct Node(Vec<Handle<Node>>);
+
+    impl Asset for Node {
+        const NAME: &'static str = "Node";
+        type Data = Vec<Dependency<Node>>;
+        type HandleStorage = VecStorage<Handle<Node>>;
+    }
+
+    #[derive(Clone, Debug)]
+    struct NodeFormat;
+
+    impl Format<Vec<Dependency<Node>>> for NodeFormat {
+        fn name(&self) -> &'static str {
+            "NODE"
+        }
+
+        fn import(
+            &self,
+            name: String,
+            source: Arc<dyn Source>,
+            _create_reload: Option<Box<dyn Format<Vec<Dependency<Node>>>>>,
+        ) -> Result<FormatValue<Vec<Dependency<Node>>>, Error> {
+            let bytes = source.load(&name)?;
+            let mut dependencies = Dependencies::new();
+            let children = from_utf8(&bytes)?
+                .split_whitespace()
+                .map(|child| dependencies.request::<Node, _, _>(child, NodeFormat))
+                .collect();
+            Ok(FormatValue::data(children).with_dependencies(dependencies))
+        }
+    }
+
+    /// Source where the asset `"a"` depends on the assets `"b"` and `"c"`, and so on.
+    struct Graph(&'static [(&'static str, &'static str)]);
+
+    impl Source for Graph {
+        fn modified(&self, _path: &str) -> Result<u64, Error> {
+            Ok(0)
+        }
+
+        fn load(&self, path: &str) -> Result<Vec<u8>, Error> {
+            self.0
+                .iter()
+                .find(|&&(name, _)| name == path)
+                .map(|&(_, children)| children.as_bytes().to_vec())
+                .ok_or_else(|| Error::from_string(format!("No asset named {}", path)))
+        }
+    }
+
+    fn load(graph: &'static [(&'static str, &'static str)]) -> (ProgressCounter, Option<usize>) {
+        load_with(graph, |loader, progress, storage| {
+            loader.load("a", NodeFormat, progress, storage)
+        })
+    }
+
+    fn load_with<F>(
+        graph: &'static [(&'static str, &'static str)],
+        start: F,
+    ) -> (ProgressCounter, Option<usize>)
+    where
+        F: FnOnce(&Loader, &mut ProgressCounter, &AssetStorage<Node>) -> Handle<Node>,
+    {
+        let pool = Arc::new(ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+        let mut loader = Loader::with_default_source(Graph(graph), pool.clone());
+        loader.set_hot_reload(false);
+        let mut storage = AssetStorage::<Node>::new();
+        let mut progress = ProgressCounter::new();
+        let handle = start(&loader, &mut progress, &storage);
+
+        for frame in 0..1000 {
+            if progress.complete() != Completion::Loading {
+                break;
+            }
+            sleep(Duration::from_millis(1));
+            storage.process(
+                |children| {
+                    let handles = children.iter().map(|c| c.handle().unwrap()).collect();
+                    Ok(ProcessingState::Loaded(Node(handles)))
+                },
+                frame,
+                &pool,
+                None,
+            );
+        }
+
+        let children = storage.get(&handle).map(|node| node.0.len());
+        (progress, children)
+    }
+
+    #[test]
+    fn parent_completes_after_its_dependencies() {
+        let (progress, children) = load(&[("a", "b c"), ("b", "c"), ("c", "")]);
+        assert_eq!(Completion::Complete, progress.complete());
+        assert_eq!(Some(2), children);
+    }
+
+    #[test]
+    fn asset_from_bytes_loads_dependencies_from_default_source() {
+        let (progress, children) =
+            load_with(&[("b", "c"), ("c", "")], |loader, progress, storage| {
+                loader.load_from_bytes("embedded", b"b c".to_vec(), NodeFormat, progress, storage)
+            });
+        assert_eq!(Completion::Complete, progress.complete());
+        assert_eq!(Some(2), children);
+    }
+
+    #[test]
+    fn failed_dependency_fails_parent() {
+        let (progress, children) = load(&[("a", "b"), ("b", "missing")]);
+        assert_eq!(Completion::Failed, progress.complete());
+        assert_eq!(None, children);
+    }
+
+    #[test]
+    fn circular_dependency_is_reported() {
+        let (progress, children) = load(&[("a", "b"), ("b", "a")]);
+        assert_eq!(Completion::Failed, progress.complete());
+        assert_eq!(None, children);
+
+        let errors = progress.errors();
+        assert!(errors[0]
+            .error_chain()
+            .contains("Circular dependency: Node \"a\" -> Node \"b\" -> Node \"a\""));
+    }
+}
@@ -10,6 +10,21 @@ pub enum Error {
     Format(&'static str),
     #[error(display = "Asset was loaded but no handle to it was saved.")]
     UnusedHandle,
+    #[error(display = "Failed to load dependency {:?} of type {}", _1, _0)]
+    Dependency(&'static str, String),
+    #[error(display = "Circular dependency: {}", _0)]
+    CircularDependency(String),
+    #[error(
+        display = "No storage for {} assets has been registered with the loader",
+        _0
+    )]
+    UnknownStorage(&'static str),
+    #[error(
+        display = "Unrecognized file signature [{}], tried formats: {}",
+        _0,
+        _1
+    )]
+    UnknownSignature(String, String),
     #[error(display = "Some error has occurred")]
     #[doc(hidden)]
     __Nonexhaustive,
@@ -1,8 +1,10 @@
 use crate::Format;
+use amethyst_config::parse_ron;
 use amethyst_error::{format_err, Error, ResultExt};
 use serde::{Deserialize, Serialize};
 
 /// Format for loading from RON files. Mostly useful for prefabs.
+/// Errors report the line, column and field path they occurred at, see `amethyst_config::RonError`.
 /// This type cannot be used for tagged deserialization.
 /// It is meant to be used at top-level loading, manually specified to the loader.
 /// ```rust,ignore
@@ -20,15 +22,7 @@ where
     }
 
     fn import_simple(&self, bytes: Vec<u8>) -> Result<D, Error> {
-        use ron::de::Deserializer;
-        let mut d = Deserializer::from_bytes(&bytes)
-            .with_context(|_| format_err!("Failed deserializing Ron file"))?;
-        let val =
-            D::deserialize(&mut d).with_context(|_| format_err!("Failed parsing Ron file"))?;
-        d.end()
-            .with_context(|_| format_err!("Failed parsing Ron file"))?;
-
-        Ok(val)
+        parse_ron(&bytes).with_context(|_| format_err!("Failed parsing Ron file"))
     }
 }
 
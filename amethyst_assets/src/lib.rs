@@ -11,26 +11,38 @@
 
 #[cfg(feature = "json")]
 pub use crate::formats::JsonFormat;
+#[cfg(feature = "saveload")]
+pub use crate::snapshot::{
+    load_world, save_world, SaveBundle, SaveMarker, SaveMarkerAllocator, SaveRegistry, Saved,
+    UpgradeFn, SNAPSHOT_VERSION,
+};
+#[cfg(feature = "http")]
+pub use crate::source::HttpSource;
 pub use crate::{
     asset::{Asset, Format, FormatValue, ProcessableAsset, SerializableFormat},
     cache::Cache,
+    dependency::{Dependencies, Dependency},
     dyn_format::FormatRegisteredData,
     formats::RonFormat,
     helper::AssetLoaderSystemData,
     loader::Loader,
     prefab::{
         AssetPrefab, Prefab, PrefabData, PrefabLoader, PrefabLoaderSystem, PrefabLoaderSystemDesc,
+        PrefabOverrides, PrefabRef, PrefabSpawner,
     },
-    progress::{Completion, Progress, ProgressCounter, Tracker},
+    progress::{AssetErrorMeta, ByteProgress, Completion, Progress, ProgressCounter, Tracker},
+    queue::{LoadPriority, LoadQueueStats},
     reload::{HotReloadBundle, HotReloadStrategy, HotReloadSystem, Reload, SingleFile},
-    source::{Directory, Source},
-    storage::{AssetStorage, Handle, ProcessingState, Processor, WeakHandle},
+    signature::{AutoFormat, Signature, SignatureRegistry},
+    source::{build_pack, Directory, LoadFuture, Pack, Source},
+    storage::{AssetStorage, Handle, ProcessingState, Processor, UnloadPolicy, WeakHandle},
 };
 
 pub use rayon::ThreadPool;
 
 mod asset;
 mod cache;
+mod dependency;
 mod dyn_format;
 mod error;
 mod formats;
@@ -38,7 +50,11 @@ mod helper;
 mod loader;
 mod prefab;
 mod progress;
+mod queue;
 mod reload;
+mod signature;
+#[cfg(feature = "saveload")]
+mod snapshot;
 mod source;
 mod storage;
 
@@ -1,16 +1,30 @@
-use std::{borrow::Borrow, hash::Hash, path::PathBuf, sync::Arc};
+use std::{
+    borrow::Borrow,
+    hash::Hash,
+    path::PathBuf,
+    sync::Arc,
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+};
 
 use fnv::FnvHashMap;
 use log::debug;
+use parking_lot::Mutex;
 use rayon::ThreadPool;
 
-use amethyst_error::ResultExt;
+use amethyst_error::{keys, Error as AmethystError, ResultExt};
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
 
+use crossbeam_queue::SegQueue;
+
 use crate::{
+    dependency::{DependencyContext, StorageRegistry},
     error::Error,
-    storage::{AssetStorage, Handle, Processed},
+    progress::Tracker,
+    queue::{LoadPriority, LoadQueue, LoadQueueStats},
+    signature::SignatureRegistry,
+    source::{LoadFuture, Prefetched},
+    storage::{AssetStorage, Handle, Processed, StorageEndpoint},
     Asset, Directory, Format, FormatValue, Progress, Source,
 };
 
@@ -18,7 +32,9 @@ use crate::{
 pub struct Loader {
     hot_reload: bool,
     pool: Arc<ThreadPool>,
+    queue: Arc<LoadQueue>,
     sources: FnvHashMap<String, Arc<dyn Source>>,
+    storages: StorageRegistry,
 }
 
 impl Loader {
@@ -39,7 +55,9 @@ impl Loader {
         let mut loader = Loader {
             hot_reload: true,
             pool,
+            queue: Default::default(),
             sources: Default::default(),
+            storages: Default::default(),
         };
 
         loader.set_default_source(source);
@@ -73,6 +91,24 @@ impl Loader {
         self.hot_reload = value;
     }
 
+    /// Makes the `Loader` aware of the given storage, so that `Format`s can request sub assets
+    /// of type `A` through `Dependencies`.
+    ///
+    /// This happens automatically when a `Processor` for `A` is built, or when an asset is
+    /// loaded into the storage with this `Loader`.
+    pub fn register_storage<A>(&self, storage: &AssetStorage<A>)
+    where
+        A: Asset,
+    {
+        self.storages.register(storage);
+    }
+
+    /// Returns the number of load requests which are waiting for a worker thread,
+    /// by priority, e.g. to show how many critical assets remain on a loading screen.
+    pub fn queue_stats(&self) -> LoadQueueStats {
+        self.queue.stats()
+    }
+
     /// Loads an asset with a given format from the default (directory) source.
     /// If you want to load from a custom source instead, use `load_from`.
     ///
@@ -95,6 +131,46 @@ impl Loader {
         self.load_from::<A, F, _, _, _>(name, format, "", progress, storage)
     }
 
+    /// Loads an asset with a given format and priority from the default (directory) source.
+    ///
+    /// See `load_from_with_priority` for more information.
+    pub fn load_with_priority<A, F, N, P>(
+        &self,
+        name: N,
+        format: F,
+        priority: LoadPriority,
+        progress: P,
+        storage: &AssetStorage<A>,
+    ) -> Handle<A>
+    where
+        A: Asset,
+        F: Format<A::Data>,
+        N: Into<String>,
+        P: Progress,
+    {
+        self.load_from_with_priority::<A, F, _, _, _>(name, format, "", priority, progress, storage)
+    }
+
+    /// Loads an asset from the default (directory) source, importing it with the format
+    /// whose signature matches the leading bytes of the asset.
+    ///
+    /// If no registered signature matches, loading fails with an error listing the bytes
+    /// seen and the formats tried.
+    pub fn load_auto<A, N, P>(
+        &self,
+        name: N,
+        signatures: &SignatureRegistry<A::Data>,
+        progress: P,
+        storage: &AssetStorage<A>,
+    ) -> Handle<A>
+    where
+        A: Asset,
+        N: Into<String>,
+        P: Progress,
+    {
+        self.load(name, signatures.auto(), progress, storage)
+    }
+
     /// Loads an asset with a given id and format from a custom source.
     /// The actual work is done in a worker thread, thus this method immediately returns a handle.
     ///
@@ -113,7 +189,41 @@ impl Loader {
         name: N,
         format: F,
         source: &S,
-        mut progress: P,
+        progress: P,
+        storage: &AssetStorage<A>,
+    ) -> Handle<A>
+    where
+        A: Asset,
+        F: Format<A::Data>,
+        N: Into<String>,
+        P: Progress,
+        S: AsRef<str> + Eq + Hash + ?Sized,
+        String: Borrow<S>,
+    {
+        self.load_from_with_priority::<A, F, _, _, _>(
+            name,
+            format,
+            source,
+            LoadPriority::Normal,
+            progress,
+            storage,
+        )
+    }
+
+    /// Loads an asset with a given id, format and priority from a custom source.
+    ///
+    /// Requests with a higher `priority` are handed to the worker threads first. Requests
+    /// which have been waiting for a long time move up in priority, so they are not starved.
+    /// Sub assets requested by the asset are loaded with the same priority.
+    ///
+    /// See `load_from` for the other parameters.
+    pub fn load_from_with_priority<A, F, N, P, S>(
+        &self,
+        name: N,
+        format: F,
+        source: &S,
+        priority: LoadPriority,
+        progress: P,
         storage: &AssetStorage<A>,
     ) -> Handle<A>
     where
@@ -126,8 +236,30 @@ impl Loader {
     {
         #[cfg(feature = "profiler")]
         profile_scope!("load_asset_from");
-        use crate::progress::Tracker;
 
+        self.register_storage(storage);
+        self.load_into(name, format, source, priority, progress, &storage.endpoint)
+    }
+
+    /// Loads an asset into the storage behind `endpoint`, for loads started while the
+    /// storage itself is borrowed, e.g. from within `AssetStorage::process`.
+    pub(crate) fn load_into<A, F, N, P, S>(
+        &self,
+        name: N,
+        format: F,
+        source: &S,
+        priority: LoadPriority,
+        mut progress: P,
+        endpoint: &StorageEndpoint<A>,
+    ) -> Handle<A>
+    where
+        A: Asset,
+        F: Format<A::Data>,
+        N: Into<String>,
+        P: Progress,
+        S: AsRef<str> + Eq + Hash + ?Sized,
+        String: Borrow<S>,
+    {
         let name = name.into();
         let source = source.as_ref();
 
@@ -137,46 +269,97 @@ impl Loader {
             other => other,
         };
 
-        let handle = storage.allocate();
+        let handle = endpoint.allocate();
+        endpoint.set_name(handle.id(), name.clone());
 
         debug!(
-            "{:?}: Loading asset {:?} with format {:?} from source {:?} (handle id: {:?})",
+            "{:?}: Loading asset {:?} with format {:?} from source {:?} with priority {:?} (handle id: {:?})",
             A::NAME,
             name,
             format_name,
             source_name,
+            priority,
             handle,
         );
 
         progress.add_assets(1);
-        let tracker = progress.create_tracker();
+        let tracker = Box::new(progress.create_tracker()) as Box<dyn Tracker>;
 
         let source = self.source(source);
         let handle_clone = handle.clone();
-        let processed = storage.processed.clone();
-
-        let hot_reload = if self.hot_reload {
-            Some(objekt::clone_box(&format) as Box<dyn Format<A::Data>>)
-        } else {
-            None
-        };
+        let processed = endpoint.processed.clone();
+        let context = DependencyContext::new(
+            self.storages.clone(),
+            self.pool.clone(),
+            self.queue.clone(),
+            self.hot_reload,
+            priority,
+        );
 
         let cl = move || {
             #[cfg(feature = "profiler")]
             profile_scope!("load_asset_from_worker");
-            let data = format
-                .import(name.clone(), source, hot_reload)
-                .with_context(|_| Error::Format(format_name));
-            let tracker = Box::new(tracker) as Box<dyn Tracker>;
-
-            processed.push(Processed::NewAsset {
-                data,
-                handle,
-                name,
-                tracker,
-            });
+            import_asset(name, format, source, handle, tracker, processed, context);
         };
-        self.pool.spawn(cl);
+        self.queue.spawn(&self.pool, priority, cl);
+
+        handle_clone
+    }
+
+    /// Loads an asset with a given format from bytes which are already in memory, e.g.
+    /// embedded with `include_bytes!` or downloaded by the game itself.
+    /// The bytes are imported in a worker thread, thus this method immediately returns a handle.
+    ///
+    /// `name` only identifies the asset in logs and errors. Sub assets requested by the format
+    /// are loaded from the default source. Assets loaded from bytes are never hot-reloaded,
+    /// as there is no file to watch.
+    pub fn load_from_bytes<A, F, N, P>(
+        &self,
+        name: N,
+        bytes: Vec<u8>,
+        format: F,
+        mut progress: P,
+        storage: &AssetStorage<A>,
+    ) -> Handle<A>
+    where
+        A: Asset,
+        F: Format<A::Data>,
+        N: Into<String>,
+        P: Progress,
+    {
+        let name = name.into();
+        self.register_storage(storage);
+
+        let handle = storage.endpoint.allocate();
+        storage.endpoint.set_name(handle.id(), name.clone());
+
+        debug!(
+            "{:?}: Loading asset {:?} with format {:?} from {} bytes (handle id: {:?})",
+            A::NAME,
+            name,
+            format.name(),
+            bytes.len(),
+            handle,
+        );
+
+        progress.add_assets(1);
+        let tracker = Box::new(progress.create_tracker()) as Box<dyn Tracker>;
+
+        let source =
+            Arc::new(Prefetched::new(name.clone(), Ok(bytes), self.source(""))) as Arc<dyn Source>;
+        let handle_clone = handle.clone();
+        let processed = storage.endpoint.processed.clone();
+        let context = DependencyContext::new(
+            self.storages.clone(),
+            self.pool.clone(),
+            self.queue.clone(),
+            false,
+            LoadPriority::Normal,
+        );
+
+        self.queue.spawn(&self.pool, LoadPriority::Normal, move || {
+            import_asset(name, format, source, handle, tracker, processed, context);
+        });
 
         handle_clone
     }
@@ -196,7 +379,7 @@ impl Loader {
         let tracker = progress.create_tracker();
         let tracker = Box::new(tracker);
         let handle = storage.allocate();
-        storage.processed.push(Processed::NewAsset {
+        storage.endpoint.processed.push(Processed::NewAsset {
             data: Ok(FormatValue::data(data)),
             handle: handle.clone(),
             name: "<Data>".into(),
@@ -222,9 +405,9 @@ impl Loader {
         let tracker = progress.create_tracker();
         let tracker = Box::new(tracker);
         let handle = storage.allocate();
-        let processed = storage.processed.clone();
+        let processed = storage.endpoint.processed.clone();
 
-        self.pool.spawn({
+        self.queue.spawn(&self.pool, LoadPriority::Normal, {
             let handle = handle.clone();
             move || {
                 processed.push(Processed::NewAsset {
@@ -246,3 +429,122 @@ impl Loader {
             .clone()
     }
 }
+
+/// Fetches the bytes of an asset, imports it and queues the result for processing, after
+/// starting to load the sub assets it requested.
+pub(crate) fn import_asset<A, F>(
+    name: String,
+    format: F,
+    source: Arc<dyn Source>,
+    handle: Handle<A>,
+    tracker: Box<dyn Tracker>,
+    processed: Arc<SegQueue<Processed<A>>>,
+    context: DependencyContext,
+) where
+    A: Asset,
+    F: Format<A::Data>,
+{
+    let fetch = source.load_async(&name, tracker.bytes());
+    let pool = context.pool().clone();
+
+    poll_on_pool(fetch, pool, move |bytes| {
+        #[cfg(feature = "profiler")]
+        profile_scope!("import_asset_worker");
+
+        let source = Arc::new(Prefetched::new(name.clone(), bytes, source)) as Arc<dyn Source>;
+
+        let format_name = format.name();
+        let hot_reload = if context.hot_reload() {
+            Some(objekt::clone_box(&format) as Box<dyn Format<A::Data>>)
+        } else {
+            None
+        };
+
+        let data = format
+            .import(name.clone(), source.clone(), hot_reload)
+            .with_context(|_| Error::Format(format_name))
+            .with_meta(keys::FORMAT, || format_name)
+            .map(|value| context.resolve::<A>(value, &name, &source));
+
+        processed.push(Processed::NewAsset {
+            data,
+            handle,
+            name,
+            tracker,
+        });
+    });
+}
+
+type Continuation = Box<dyn FnOnce(Result<Vec<u8>, AmethystError>) + Send>;
+
+/// A future being polled by `poll_on_pool`.
+struct Fetch {
+    future: Mutex<Option<LoadFuture>>,
+    then: Mutex<Option<Continuation>>,
+    pool: Arc<ThreadPool>,
+}
+
+/// Polls `future` on the calling thread, and again on the thread pool whenever it is woken,
+/// so no thread is blocked while the future is pending. Once the future has completed, `then`
+/// is called with its output on the thread which polled it last.
+fn poll_on_pool<T>(future: LoadFuture, pool: Arc<ThreadPool>, then: T)
+where
+    T: FnOnce(Result<Vec<u8>, AmethystError>) + Send + 'static,
+{
+    poll_fetch(Arc::new(Fetch {
+        future: Mutex::new(Some(future)),
+        then: Mutex::new(Some(Box::new(then))),
+        pool,
+    }));
+}
+
+fn poll_fetch(fetch: Arc<Fetch>) {
+    let waker = fetch_waker(fetch.clone());
+    let mut cx = Context::from_waker(&waker);
+
+    let result = {
+        let mut future = fetch.future.lock();
+        match future.as_mut().map(|f| f.as_mut().poll(&mut cx)) {
+            Some(Poll::Ready(result)) => {
+                *future = None;
+                result
+            }
+            // Either still pending, or the future has completed on another thread already.
+            Some(Poll::Pending) | None => return,
+        }
+    };
+
+    if let Some(then) = fetch.then.lock().take() {
+        then(result);
+    }
+}
+
+fn fetch_waker(fetch: Arc<Fetch>) -> Waker {
+    unsafe fn clone(data: *const ()) -> RawWaker {
+        let fetch = Arc::from_raw(data as *const Fetch);
+        let cloned = fetch.clone();
+        std::mem::forget(fetch);
+        RawWaker::new(Arc::into_raw(cloned) as *const (), &VTABLE)
+    }
+
+    unsafe fn wake(data: *const ()) {
+        let fetch = Arc::from_raw(data as *const Fetch);
+        fetch.pool.clone().spawn(move || poll_fetch(fetch));
+    }
+
+    unsafe fn wake_by_ref(data: *const ()) {
+        let fetch = Arc::from_raw(data as *const Fetch);
+        let cloned = fetch.clone();
+        std::mem::forget(fetch);
+        cloned.pool.clone().spawn(move || poll_fetch(cloned));
+    }
+
+    unsafe fn drop(data: *const ()) {
+        std::mem::drop(Arc::from_raw(data as *const Fetch));
+    }
+
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake_by_ref, drop);
+
+    let raw = RawWaker::new(Arc::into_raw(fetch) as *const (), &VTABLE);
+    unsafe { Waker::from_raw(raw) }
+}
@@ -1,20 +1,29 @@
-use std::marker::PhantomData;
+use std::{marker::PhantomData, sync::Arc};
 
+use derivative::Derivative;
 use serde::{Deserialize, Serialize};
 
-use amethyst_core::ecs::prelude::{
-    Component, DenseVecStorage, Entity, FlaggedStorage, Read, ReadExpect, ResourceId, SystemData,
-    World, WriteStorage,
+use amethyst_core::{
+    ecs::prelude::{
+        Component, DenseVecStorage, Entity, FlaggedStorage, Read, ReadExpect, ResourceId,
+        SystemData, World, WriteStorage,
+    },
+    Transform,
 };
-use amethyst_error::Error;
+use amethyst_error::{format_err, Error};
 
 use crate::{
-    Asset, AssetStorage, Format, Handle, Loader, Progress, ProgressCounter, SerializableFormat,
+    error, storage::StorageEndpoint, Asset, AssetStorage, Format, FormatValue, Handle,
+    LoadPriority, Loader, Progress, ProgressCounter, SerializableFormat, Source,
 };
 
-pub use self::system::{PrefabLoaderSystem, PrefabLoaderSystemDesc};
+pub use self::{
+    overrides::{PrefabOverrides, PrefabSpawner},
+    system::{PrefabLoaderSystem, PrefabLoaderSystemDesc},
+};
 
 mod impls;
+mod overrides;
 mod system;
 
 /// Trait for loading a prefabs data for a single entity
@@ -82,6 +91,8 @@ pub trait PrefabData<'a> {
 ///
 /// The recommended way of loading resources is to place them on the main `Entity`.
 ///
+/// Entries can also place instances of other prefabs, see `PrefabRef`.
+///
 /// ### Example:
 ///
 /// If we want to give the existing Baker entity a Knife and a Plate with a
@@ -105,12 +116,21 @@ pub trait PrefabData<'a> {
 ///
 /// - `T`: `PrefabData`
 #[derive(Default, Deserialize, Serialize)]
-pub struct Prefab<T> {
+pub struct Prefab<T>
+where
+    T: 'static,
+{
     #[serde(skip)]
     tag: Option<u64>,
     entities: Vec<PrefabEntity<T>>,
     #[serde(skip)]
     counter: Option<ProgressCounter>,
+    /// Names of the prefabs referencing this one, ending with its own name.
+    #[serde(skip)]
+    chain: Vec<String>,
+    /// Format the prefab was loaded with, used for the prefabs it references.
+    #[serde(skip)]
+    format: Option<Box<dyn Format<Prefab<T>>>>,
 }
 
 /// Prefab data container for a single entity
@@ -120,21 +140,29 @@ pub struct Prefab<T> {
 /// - `T`: `PrefabData`
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(default)]
-pub struct PrefabEntity<T> {
+pub struct PrefabEntity<T>
+where
+    T: 'static,
+{
     parent: Option<usize>,
     data: Option<T>,
+    prefab: Option<PrefabRef<T>>,
 }
 
-impl<T> Default for PrefabEntity<T> {
+impl<T: 'static> Default for PrefabEntity<T> {
     fn default() -> Self {
         PrefabEntity::new(None, None)
     }
 }
 
-impl<T> PrefabEntity<T> {
+impl<T: 'static> PrefabEntity<T> {
     /// New prefab entity
     pub fn new(parent: Option<usize>, data: Option<T>) -> Self {
-        PrefabEntity { parent, data }
+        PrefabEntity {
+            parent,
+            data,
+            prefab: None,
+        }
     }
 
     /// Set parent index
@@ -147,6 +175,16 @@ impl<T> PrefabEntity<T> {
         self.data = Some(data);
     }
 
+    /// Set the prefab instantiated as a child of this entity
+    pub fn set_prefab(&mut self, prefab: PrefabRef<T>) {
+        self.prefab = Some(prefab);
+    }
+
+    /// Get the prefab instantiated as a child of this entity
+    pub fn prefab(&self) -> Option<&PrefabRef<T>> {
+        self.prefab.as_ref()
+    }
+
     /// Get immutable access to the data
     pub fn data(&self) -> Option<&T> {
         self.data.as_ref()
@@ -183,21 +221,28 @@ impl<T> PrefabEntity<T> {
     where
         T: PrefabData<'a>,
     {
+        let mut ret = false;
         if let Some(ref mut data) = self.data {
-            data.load_sub_assets(progress, system_data)
-        } else {
-            Ok(false)
+            ret |= data.load_sub_assets(progress, system_data)?;
+        }
+        if let Some(ref mut prefab) = self.prefab {
+            for data in prefab.overrides.data.values_mut() {
+                ret |= data.load_sub_assets(progress, system_data)?;
+            }
         }
+        Ok(ret)
     }
 }
 
-impl<T> Prefab<T> {
+impl<T: 'static> Prefab<T> {
     /// Create new empty prefab
     pub fn new() -> Self {
         Prefab {
             tag: None,
             entities: vec![PrefabEntity::default()],
             counter: None,
+            chain: Vec::new(),
+            format: None,
         }
     }
 
@@ -207,6 +252,8 @@ impl<T> Prefab<T> {
             tag: None,
             entities: vec![PrefabEntity::new(None, Some(data))],
             counter: None,
+            chain: Vec::new(),
+            format: None,
         }
     }
 
@@ -306,6 +353,203 @@ impl<T> Prefab<T> {
         self.counter = Some(progress);
         Ok(ret)
     }
+
+    /// Starts loading the prefabs referenced by the entities of this prefab.
+    ///
+    /// Must be called after `load_sub_assets`, the loads are tracked by its `ProgressCounter`.
+    pub(crate) fn load_references(
+        &mut self,
+        loader: &Loader,
+        endpoint: &StorageEndpoint<Prefab<T>>,
+    ) -> Result<bool, Error>
+    where
+        T: Send + Sync + 'static,
+    {
+        let mut ret = false;
+        let counter = self.counter.get_or_insert_with(ProgressCounter::default);
+        for reference in self.entities.iter_mut().filter_map(|e| e.prefab.as_mut()) {
+            if reference.handle.is_some() {
+                continue;
+            }
+
+            let mut chain = self.chain.clone();
+            let cycle = chain.contains(&reference.name);
+            chain.push(reference.name.clone());
+            if cycle {
+                let chain = chain
+                    .iter()
+                    .map(|name| format!("{:?}", name))
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                return Err(error::Error::CircularDependency(chain).into());
+            }
+
+            let format = reference
+                .format
+                .clone()
+                .or_else(|| self.format.clone())
+                .ok_or_else(|| {
+                    format_err!(
+                        "No format to load the referenced prefab {:?} with. Load the \
+                         referencing prefab with `PrefabLoader` or set the format of the reference",
+                        reference.name
+                    )
+                })?;
+            let handle = loader.load_into(
+                reference.name.clone(),
+                PrefabFormat { format, chain },
+                "",
+                LoadPriority::Normal,
+                &mut *counter,
+                endpoint,
+            );
+            reference.handle = Some(handle);
+            ret = true;
+        }
+        Ok(ret)
+    }
+}
+
+/// Reference to another prefab, placing an instance of it on a `PrefabEntity`.
+///
+/// When the referencing prefab is instantiated, the main entity of the referenced prefab is
+/// created as a child of the entity the reference is placed on, with the attachment transform
+/// if one is given. This way, a "house" prefab can place four instances of a "window" prefab:
+///
+/// ```ron
+/// #![enable(implicit_some)]
+/// Prefab (
+///     entities: [
+///         (data: ( /* the house */ )),
+///         (
+///             parent: 0,
+///             prefab: (
+///                 name: "prefab/window.ron",
+///                 transform: (translation: (1.0, 2.0, 0.0)),
+///             ),
+///         ),
+///         // ...
+///     ],
+/// )
+/// ```
+///
+/// The instance can be customized with `PrefabOverrides`.
+///
+/// Referenced prefabs are loaded along with the sub assets of the referencing prefab, with
+/// the format the referencing prefab was loaded with by `PrefabLoader`. Prefabs referencing
+/// themselves, directly or through other prefabs, fail to load.
+///
+/// Hot reloading a referenced prefab only affects instances created after the reload.
+#[derive(Derivative, Deserialize, Serialize)]
+#[derivative(Debug(bound = "T: std::fmt::Debug"))]
+pub struct PrefabRef<T>
+where
+    T: 'static,
+{
+    name: String,
+    #[serde(default)]
+    transform: Option<Transform>,
+    #[serde(default)]
+    overrides: PrefabOverrides<T>,
+    #[serde(skip)]
+    format: Option<Box<dyn Format<Prefab<T>>>>,
+    #[serde(skip)]
+    handle: Option<Handle<Prefab<T>>>,
+}
+
+impl<T: 'static> PrefabRef<T> {
+    /// Creates a reference to the prefab with the given asset name.
+    pub fn new<N>(name: N) -> Self
+    where
+        N: Into<String>,
+    {
+        PrefabRef {
+            name: name.into(),
+            transform: None,
+            overrides: PrefabOverrides::new(),
+            format: None,
+            handle: None,
+        }
+    }
+
+    /// Sets the transform of the main entity of the referenced prefab, relative to the
+    /// entity the reference is placed on.
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = Some(transform);
+        self
+    }
+
+    /// Sets the overrides applied to the instance of the referenced prefab.
+    ///
+    /// Only the data overrides are used, components set with
+    /// `PrefabOverrides::set_component` only apply to instances spawned with `PrefabSpawner`.
+    pub fn with_overrides(mut self, overrides: PrefabOverrides<T>) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Sets the format to load the referenced prefab with, instead of the format of the
+    /// referencing prefab.
+    pub fn with_format<F>(mut self, format: F) -> Self
+    where
+        F: Format<Prefab<T>>,
+    {
+        self.format = Some(Box::new(format));
+        self
+    }
+
+    /// Asset name of the referenced prefab
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Attachment transform of the referenced prefab
+    pub fn transform(&self) -> Option<&Transform> {
+        self.transform.as_ref()
+    }
+
+    /// Overrides applied to the instance of the referenced prefab
+    pub fn overrides(&self) -> &PrefabOverrides<T> {
+        &self.overrides
+    }
+
+    /// Handle of the referenced prefab, once loading has been triggered
+    pub fn handle(&self) -> Option<&Handle<Prefab<T>>> {
+        self.handle.as_ref()
+    }
+}
+
+/// Format wrapper recording how a prefab was loaded, so the prefabs it references can be
+/// loaded the same way.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""), Debug(bound = ""))]
+struct PrefabFormat<T>
+where
+    T: 'static,
+{
+    format: Box<dyn Format<Prefab<T>>>,
+    chain: Vec<String>,
+}
+
+impl<T> Format<Prefab<T>> for PrefabFormat<T>
+where
+    T: Send + Sync + 'static,
+{
+    fn name(&self) -> &'static str {
+        self.format.name()
+    }
+
+    fn import(
+        &self,
+        name: String,
+        source: Arc<dyn Source>,
+        create_reload: Option<Box<dyn Format<Prefab<T>>>>,
+    ) -> Result<FormatValue<Prefab<T>>, Error> {
+        let mut value = self.format.import(name, source, create_reload)?;
+        value.data.chain = self.chain.clone();
+        value.data.format = Some(self.format.clone());
+        Ok(value)
+    }
 }
 
 /// Tag placed on entities created by the prefab system.
@@ -451,6 +695,11 @@ where
         N: Into<String>,
         P: Progress,
     {
+        let name = name.into();
+        let format = PrefabFormat {
+            format: Box::new(format),
+            chain: vec![name.clone()],
+        };
         self.loader.load(name, format, progress, &self.storage)
     }
 
@@ -465,21 +714,69 @@ where
 
 #[cfg(test)]
 mod tests {
-    use std::sync::Arc;
+    use std::{sync::Arc, thread, time::Duration};
 
     use rayon::ThreadPoolBuilder;
 
     use amethyst_core::{
-        ecs::{Builder, RunNow, World, WorldExt},
-        SystemDesc, Time, Transform,
+        ecs::{Builder, Join, RunNow, World, WorldExt},
+        math::Vector3,
+        Parent, SystemDesc, Time, Transform,
     };
 
-    use crate::Loader;
+    use crate::{Completion, Loader, RonFormat};
 
     use super::*;
 
     type MyPrefab = Transform;
 
+    /// Source serving prefabs from memory.
+    struct Prefabs(Vec<(&'static str, &'static str)>);
+
+    impl Source for Prefabs {
+        fn modified(&self, _path: &str) -> Result<u64, Error> {
+            Ok(0)
+        }
+
+        fn load(&self, path: &str) -> Result<Vec<u8>, Error> {
+            self.0
+                .iter()
+                .find(|(name, _)| *name == path)
+                .map(|(_, ron)| ron.as_bytes().to_vec())
+                .ok_or_else(|| format_err!("No prefab {:?}", path))
+        }
+    }
+
+    fn setup(prefabs: Prefabs) -> (World, PrefabLoaderSystem<MyPrefab>) {
+        let mut world = World::new();
+        let pool = Arc::new(ThreadPoolBuilder::default().build().unwrap());
+        world.insert(pool.clone());
+        world.insert(Loader::with_default_source(prefabs, pool));
+        world.insert(Time::default());
+        let mut system = PrefabLoaderSystemDesc::<MyPrefab>::default().build(&mut world);
+        RunNow::setup(&mut system, &mut world);
+        (world, system)
+    }
+
+    fn run_until_loaded(
+        world: &mut World,
+        system: &mut PrefabLoaderSystem<MyPrefab>,
+        progress: &ProgressCounter,
+    ) {
+        for _ in 0..500 {
+            system.run_now(world);
+            world.maintain();
+            if progress.complete() != Completion::Loading {
+                // Instantiate the prefabs now that they are loaded.
+                system.run_now(world);
+                world.maintain();
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("Prefab did not finish loading");
+    }
+
     #[test]
     fn test_prefab_load() {
         let mut world = World::new();
@@ -505,4 +802,170 @@ mod tests {
         );
         assert!(world.read_storage::<Transform>().get(root_entity).is_some());
     }
+
+    #[test]
+    fn test_nested_prefab_instantiation() {
+        let (mut world, mut system) = setup(Prefabs(vec![
+            (
+                "house",
+                r#"(entities: [
+                    (),
+                    (parent: Some(0), prefab: Some((
+                        name: "window",
+                        transform: Some((translation: (1.0, 0.0, 0.0))),
+                    ))),
+                    (parent: Some(0), prefab: Some((name: "window"))),
+                ])"#,
+            ),
+            (
+                "window",
+                r#"(entities: [
+                    (data: Some((translation: (0.0, 5.0, 0.0)))),
+                    (parent: Some(0), data: Some((translation: (0.0, 0.0, 1.0)))),
+                ])"#,
+            ),
+        ]));
+
+        let mut progress = ProgressCounter::new();
+        let handle = world.exec(|loader: PrefabLoader<'_, MyPrefab>| {
+            loader.load("house", RonFormat, &mut progress)
+        });
+        world.create_entity().with(handle).build();
+        run_until_loaded(&mut world, &mut system, &progress);
+        assert!(progress.is_complete());
+
+        // Two house entries, each with a window consisting of two entities.
+        assert_eq!(6, world.read_storage::<Parent>().join().count());
+        let translations = world
+            .read_storage::<Transform>()
+            .join()
+            .map(|t| *t.translation())
+            .collect::<Vec<_>>();
+        let count = |v: Vector3<f32>| translations.iter().filter(|&&t| t == v).count();
+        assert_eq!(1, count(Vector3::new(1.0, 0.0, 0.0)));
+        assert_eq!(1, count(Vector3::new(0.0, 5.0, 0.0)));
+        assert_eq!(2, count(Vector3::new(0.0, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn test_circular_prefab_references() {
+        let (mut world, mut system) = setup(Prefabs(vec![
+            ("a", r#"(entities: [(prefab: Some((name: "b")))])"#),
+            ("b", r#"(entities: [(prefab: Some((name: "a")))])"#),
+        ]));
+
+        let mut progress = ProgressCounter::new();
+        let handle = world
+            .exec(|loader: PrefabLoader<'_, MyPrefab>| loader.load("a", RonFormat, &mut progress));
+        world.create_entity().with(handle).build();
+        run_until_loaded(&mut world, &mut system, &progress);
+
+        let errors = progress.errors();
+        assert_eq!(1, errors.len());
+        let message = errors[0]
+            .error
+            .causes()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(message.contains(r#""a" -> "b" -> "a""#), "{}", message);
+    }
+
+    fn translation_of(world: &World, entity: Entity) -> Option<Vector3<f32>> {
+        world
+            .read_storage::<Transform>()
+            .get(entity)
+            .map(|t| *t.translation())
+    }
+
+    #[test]
+    fn test_prefab_overrides() {
+        let (mut world, mut system) = setup(Prefabs(vec![
+            (
+                "level",
+                r#"(entities: [
+                    (prefab: Some((
+                        name: "enemy",
+                        overrides: {1: (translation: (0.0, 0.0, 3.0))},
+                    ))),
+                ])"#,
+            ),
+            (
+                "enemy",
+                r#"(entities: [
+                    (data: Some((translation: (0.0, 5.0, 0.0)))),
+                    (parent: Some(0), data: Some((translation: (0.0, 0.0, 1.0)))),
+                ])"#,
+            ),
+        ]));
+
+        let mut progress = ProgressCounter::new();
+        let (level, enemy) = world.exec(|loader: PrefabLoader<'_, MyPrefab>| {
+            (
+                loader.load("level", RonFormat, &mut progress),
+                loader.load("enemy", RonFormat, &mut progress),
+            )
+        });
+        let level = world.create_entity().with(level).build();
+        let moved = PrefabSpawner::new(enemy.clone())
+            .with_transform(Transform::from(Vector3::<f32>::new(1.0, 0.0, 0.0)))
+            .with_data(1, Transform::from(Vector3::<f32>::new(0.0, 2.0, 0.0)))
+            .spawn(&mut world);
+        let invalid = PrefabSpawner::new(enemy)
+            .with(2, Transform::default())
+            .spawn(&mut world);
+        run_until_loaded(&mut world, &mut system, &progress);
+        assert!(progress.is_complete());
+
+        let children_of = |world: &World, parent: Entity| {
+            (&world.entities(), &world.read_storage::<Parent>())
+                .join()
+                .filter(|(_, p)| p.entity == parent)
+                .map(|(e, _)| e)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            Some(Vector3::new(1.0, 0.0, 0.0)),
+            translation_of(&world, moved)
+        );
+        let moved_children = children_of(&world, moved);
+        assert_eq!(1, moved_children.len());
+        assert_eq!(
+            Some(Vector3::new(0.0, 2.0, 0.0)),
+            translation_of(&world, moved_children[0])
+        );
+
+        assert!(children_of(&world, invalid).is_empty());
+        assert_eq!(None, translation_of(&world, invalid));
+
+        let placed = children_of(&world, level);
+        assert_eq!(1, placed.len());
+        let placed_children = children_of(&world, placed[0]);
+        assert_eq!(1, placed_children.len());
+        assert_eq!(
+            Some(Vector3::new(0.0, 0.0, 3.0)),
+            translation_of(&world, placed_children[0])
+        );
+    }
+
+    #[test]
+    fn test_prefab_parse_errors_report_location() {
+        let ron = r#"(entities: [
+            (data: Some((translation: (0.0, 0.0, 0.0)))),
+            (parent: Some(0), data: Some((translation: (1.0, "2.0", 0.0)))),
+        ])"#;
+        let result: Result<Prefab<MyPrefab>, Error> =
+            RonFormat.import_simple(ron.as_bytes().to_vec());
+        let message = result
+            .err()
+            .unwrap()
+            .causes()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(message.contains("line 3"), "{}", message);
+        assert!(message.contains("entities[1]"), "{}", message);
+        assert!(message.contains("translation"), "{}", message);
+    }
 }
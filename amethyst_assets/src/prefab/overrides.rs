@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+
+use derivative::Derivative;
+use serde::{Deserialize, Serialize};
+
+use amethyst_core::{
+    ecs::prelude::{Builder, Component, DenseVecStorage, Entity, LazyUpdate, World, WorldExt},
+    Transform,
+};
+use amethyst_error::{format_err, Error};
+
+use crate::Handle;
+
+use super::Prefab;
+
+/// Deferred insertion of a component on an entity of a prefab instance.
+pub(crate) type ComponentOverride = Box<dyn FnOnce(Entity, &LazyUpdate) + Send + Sync>;
+
+/// Data overriding parts of a prefab for a single instance, e.g. the health and tint of one
+/// enemy.
+///
+/// Overrides are applied after the prefab data has been added to the entities of the
+/// instance, to the entity with the given index in the prefab. The override data is added
+/// like any other prefab data, so for prefab data made of optional components only the
+/// components given in the override replace the ones of the prefab. Overrides for indices
+/// the prefab does not have prevent the prefab from being instantiated.
+///
+/// The data overrides are serialized as a map from entity index to prefab data, so level
+/// files can place parametrized instances with `PrefabRef`:
+///
+/// ```ron
+/// #![enable(implicit_some)]
+/// (
+///     name: "prefab/enemy.ron",
+///     transform: (translation: (10.0, 0.0, 0.0)),
+///     overrides: {
+///         0: (health: (max: 50.0)),
+///     },
+/// )
+/// ```
+///
+/// Overrides with plain components, added with `with`, can't be serialized.
+///
+/// ### Type parameters:
+///
+/// - `T`: `PrefabData`
+#[derive(Derivative, Deserialize, Serialize)]
+#[derivative(Debug(bound = "T: std::fmt::Debug"), Default(bound = ""))]
+#[serde(transparent)]
+pub struct PrefabOverrides<T> {
+    pub(crate) data: BTreeMap<usize, T>,
+    #[derivative(Debug = "ignore")]
+    #[serde(skip)]
+    pub(crate) components: Vec<(usize, ComponentOverride)>,
+}
+
+impl<T> PrefabOverrides<T> {
+    /// Creates empty overrides.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Overrides the prefab data of the entity with the given index.
+    pub fn set_data(&mut self, index: usize, data: T) {
+        self.data.insert(index, data);
+    }
+
+    /// Inserts `component` on the entity with the given index, replacing the component added
+    /// by the prefab data.
+    pub fn set_component<C>(&mut self, index: usize, component: C)
+    where
+        C: Component + Send + Sync,
+    {
+        self.components.push((
+            index,
+            Box::new(move |entity, lazy: &LazyUpdate| lazy.insert(entity, component)),
+        ));
+    }
+
+    /// Returns `true` if nothing is overridden.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty() && self.components.is_empty()
+    }
+}
+
+impl<T> Component for PrefabOverrides<T>
+where
+    T: Send + Sync + 'static,
+{
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Checks that all overridden entity `indices` exist in a prefab with `len` entities.
+pub(crate) fn check_indices<'i>(
+    mut indices: impl Iterator<Item = &'i usize>,
+    len: usize,
+) -> Result<(), Error> {
+    match indices.find(|&&index| index >= len) {
+        Some(index) => Err(format_err!(
+            "Override for entity {} of a prefab with only {} entities",
+            index,
+            len
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Builder spawning a prefab instance with overrides.
+///
+/// ### Example:
+///
+/// ```rust,ignore
+/// let enemy = PrefabSpawner::new(enemy_prefab.clone())
+///     .with_transform(Transform::from(Vector3::new(10.0, 0.0, 0.0)))
+///     .with::<Tint>(1, Tint(Srgba::new(1.0, 0.0, 0.0, 1.0)))
+///     .spawn(world);
+/// ```
+///
+/// ### Type parameters:
+///
+/// - `T`: `PrefabData`
+#[derive(Derivative)]
+#[derivative(Debug(bound = "T: std::fmt::Debug"))]
+pub struct PrefabSpawner<T>
+where
+    T: 'static,
+{
+    handle: Handle<Prefab<T>>,
+    overrides: PrefabOverrides<T>,
+}
+
+impl<T> PrefabSpawner<T>
+where
+    T: Send + Sync + 'static,
+{
+    /// Creates a spawner for instances of the prefab with the given handle.
+    pub fn new(handle: Handle<Prefab<T>>) -> Self {
+        PrefabSpawner {
+            handle,
+            overrides: PrefabOverrides::new(),
+        }
+    }
+
+    /// Sets the transform of the main entity of the instance.
+    pub fn with_transform(self, transform: Transform) -> Self {
+        self.with(0, transform)
+    }
+
+    /// Inserts `component` on the entity with the given index.
+    pub fn with<C>(mut self, index: usize, component: C) -> Self
+    where
+        C: Component + Send + Sync,
+    {
+        self.overrides.set_component(index, component);
+        self
+    }
+
+    /// Overrides the prefab data of the entity with the given index.
+    pub fn with_data(mut self, index: usize, data: T) -> Self {
+        self.overrides.set_data(index, data);
+        self
+    }
+
+    /// Creates the main entity of the instance. The `PrefabLoaderSystem` instantiates the
+    /// prefab on it once the prefab is loaded.
+    pub fn spawn(self, world: &mut World) -> Entity {
+        world
+            .create_entity()
+            .with(self.handle)
+            .with(self.overrides)
+            .build()
+    }
+}
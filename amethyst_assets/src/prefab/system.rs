@@ -1,23 +1,31 @@
-use std::{collections::HashMap, marker::PhantomData};
+use std::{
+    collections::{BTreeMap, HashMap},
+    marker::PhantomData,
+};
 
 use derivative::Derivative;
 use log::error;
 
 use amethyst_core::{
     ecs::{
-        storage::ComponentEvent, BitSet, Entities, Entity, Join, Read, ReadExpect, ReadStorage,
-        ReaderId, System, SystemData, World, Write, WriteStorage,
+        storage::ComponentEvent, BitSet, Entities, Entity, Join, LazyUpdate, Read, ReadExpect,
+        ReadStorage, ReaderId, System, SystemData, World, Write, WriteStorage,
     },
     ArcThreadPool, Parent, SystemDesc, Time,
 };
-use amethyst_error::{format_err, Error, ResultExt};
+use amethyst_error::{format_err, keys, Error, ResultExt};
 
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
 
-use crate::{AssetStorage, Completion, Handle, HotReloadStrategy, ProcessingState};
+use crate::{
+    AssetStorage, Completion, Handle, HotReloadStrategy, Loader, ProcessingState, ProgressCounter,
+};
 
-use super::{Prefab, PrefabData, PrefabTag};
+use super::{
+    overrides::{check_indices, ComponentOverride},
+    Prefab, PrefabData, PrefabOverrides, PrefabTag,
+};
 
 /// Builds a `PrefabLoaderSystem`.
 #[derive(Derivative, Debug)]
@@ -46,7 +54,6 @@ where
 /// - `T`: `PrefabData`
 pub struct PrefabLoaderSystem<T> {
     _m: PhantomData<T>,
-    entities: Vec<Entity>,
     finished: Vec<Entity>,
     to_process: BitSet,
     insert_reader: ReaderId<ComponentEvent>,
@@ -61,7 +68,6 @@ where
     pub fn new(insert_reader: ReaderId<ComponentEvent>) -> Self {
         Self {
             _m: PhantomData,
-            entities: Vec::default(),
             finished: Vec::default(),
             to_process: BitSet::default(),
             insert_reader,
@@ -81,9 +87,12 @@ where
         ReadStorage<'a, Handle<Prefab<T>>>,
         Read<'a, Time>,
         ReadExpect<'a, ArcThreadPool>,
+        ReadExpect<'a, Loader>,
         Option<Read<'a, HotReloadStrategy>>,
+        Read<'a, LazyUpdate>,
         WriteStorage<'a, Parent>,
         WriteStorage<'a, PrefabTag<T>>,
+        WriteStorage<'a, PrefabOverrides<T>>,
         T::SystemData,
     );
 
@@ -97,28 +106,50 @@ where
             prefab_handles,
             time,
             pool,
+            loader,
             strategy,
+            lazy,
             mut parents,
             mut tags,
+            mut overrides,
             mut prefab_system_data,
         ) = data;
         let strategy = strategy.as_deref();
+        let endpoint = prefab_storage.endpoint.clone();
         prefab_storage.process(
             |mut d| {
                 d.tag = Some(self.next_tag);
                 self.next_tag += 1;
-                if !d.loading()
-                    && !d
+                if !d.loading() {
+                    let sub_assets = d
                         .load_sub_assets(&mut prefab_system_data)
-                        .with_context(|_| format_err!("Failed starting sub asset loading"))?
-                {
-                    return Ok(ProcessingState::Loaded(d));
+                        .with_context(|_| format_err!("Failed starting sub asset loading"))?;
+                    let references = d
+                        .load_references(&loader, &endpoint)
+                        .with_context(|_| format_err!("Failed loading referenced prefabs"))?;
+                    if !sub_assets && !references {
+                        return Ok(ProcessingState::Loaded(d));
+                    }
                 }
                 match d.progress().complete() {
                     Completion::Complete => Ok(ProcessingState::Loaded(d)),
                     Completion::Failed => {
-                        error!("Failed loading sub asset: {:?}", d.progress().errors());
-                        Err(Error::from_string("Failed loading sub asset"))
+                        let mut errors = d.progress().errors();
+                        let names = errors
+                            .iter()
+                            .map(|e| e.asset_name.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        let err = errors.iter().fold(
+                            Error::from_string(format!("Failed loading sub asset(s): {}", names)),
+                            |err, e| err.with_meta(keys::ASSET_NAME, e.asset_name.clone()),
+                        );
+                        // Keep the original error of the first failed sub asset as the cause.
+                        if errors.is_empty() {
+                            Err(err)
+                        } else {
+                            Err(err.with_source(errors.swap_remove(0).error))
+                        }
                     }
                     Completion::Loading => Ok(ProcessingState::Loading(d)),
                 }
@@ -136,57 +167,27 @@ where
                 }
             });
         self.finished.clear();
+        let mut spawner = Spawner {
+            entities: &entities,
+            storage: &prefab_storage,
+            lazy: &lazy,
+            parents: &mut parents,
+            tags: &mut tags,
+            system_data: &mut prefab_system_data,
+        };
         for (root_entity, handle, _) in (&*entities, &prefab_handles, &self.to_process).join() {
             if let Some(prefab) = prefab_storage.get(handle) {
                 self.finished.push(root_entity);
-                // create entities
-                self.entities.clear();
-                self.entities.push(root_entity);
-
-                let mut children = HashMap::new();
-                for entity_data in prefab.entities.iter().skip(1) {
-                    let new_entity = entities.create();
-                    self.entities.push(new_entity);
-                    if let Some(parent) = entity_data.parent {
-                        parents
-                            .insert(
-                                new_entity,
-                                Parent {
-                                    entity: self.entities[parent],
-                                },
-                            )
-                            .expect("Unable to insert `Parent` for prefab");
-
-                        children
-                            .entry(parent)
-                            .or_insert_with(Vec::new)
-                            .push(new_entity);
-                    }
-                    tags.insert(
-                        new_entity,
-                        PrefabTag::new(
-                            prefab.tag.expect(
-                                "Unreachable: Every loaded prefab should have a `PrefabTag`",
-                            ),
-                        ),
-                    )
-                    .expect("Unable to insert `PrefabTag` for prefab entity");
-                }
-                // create components
-                for (index, entity_data) in prefab.entities.iter().enumerate() {
-                    if let Some(ref prefab_data) = &entity_data.data {
-                        prefab_data
-                            .add_to_entity(
-                                self.entities[index],
-                                &mut prefab_system_data,
-                                &self.entities,
-                                children
-                                    .get(&index)
-                                    .map(|children| &children[..])
-                                    .unwrap_or(&[]),
-                            )
-                            .expect("Unable to add prefab system data to entity");
-                    }
+                let PrefabOverrides {
+                    mut data,
+                    components,
+                } = overrides.remove(root_entity).unwrap_or_default();
+                let spawned = spawner
+                    .load_sub_assets(&mut data)
+                    .and_then(|_| spawner.spawn(prefab, root_entity, &data, components));
+                if let Err(e) = spawned {
+                    let e = e.with_meta(keys::ENTITY, format!("{:?}", root_entity));
+                    error!("Failed to instantiate prefab: {:#}", e);
                 }
             }
         }
@@ -196,3 +197,140 @@ where
         }
     }
 }
+
+/// Creates the entities of prefab instances.
+struct Spawner<'s, 'a, T>
+where
+    T: PrefabData<'a> + Send + Sync + 'static,
+{
+    entities: &'s Entities<'a>,
+    storage: &'s AssetStorage<Prefab<T>>,
+    lazy: &'s LazyUpdate,
+    parents: &'s mut WriteStorage<'a, Parent>,
+    tags: &'s mut WriteStorage<'a, PrefabTag<T>>,
+    system_data: &'s mut T::SystemData,
+}
+
+impl<'s, 'a, T> Spawner<'s, 'a, T>
+where
+    T: PrefabData<'a> + Send + Sync + 'static,
+{
+    /// Instantiates `prefab` on `root_entity`, along with the prefabs it references, and
+    /// applies the overrides for the instance.
+    ///
+    /// Nothing is created if the overrides refer to entities the prefab does not have.
+    fn spawn(
+        &mut self,
+        prefab: &Prefab<T>,
+        root_entity: Entity,
+        data_overrides: &BTreeMap<usize, T>,
+        component_overrides: Vec<(usize, ComponentOverride)>,
+    ) -> Result<(), Error> {
+        let tag = prefab
+            .tag
+            .expect("Unreachable: Every loaded prefab should have a `PrefabTag`");
+        check_indices(
+            data_overrides
+                .keys()
+                .chain(component_overrides.iter().map(|(index, _)| index)),
+            prefab.entities.len(),
+        )?;
+
+        // create entities
+        let mut created = vec![root_entity];
+        let mut children = HashMap::new();
+        for entity_data in prefab.entities.iter().skip(1) {
+            let new_entity = self.entities.create();
+            created.push(new_entity);
+            if let Some(parent) = entity_data.parent {
+                self.parent(new_entity, created[parent], tag);
+                children
+                    .entry(parent)
+                    .or_insert_with(Vec::new)
+                    .push(new_entity);
+            } else {
+                self.tag(new_entity, tag);
+            }
+        }
+        // create the main entities of referenced prefabs
+        let mut references = Vec::new();
+        for (index, entity_data) in prefab.entities.iter().enumerate() {
+            let reference = match entity_data.prefab {
+                Some(ref reference) => reference,
+                None => continue,
+            };
+            let referenced = match reference.handle.as_ref().and_then(|h| self.storage.get(h)) {
+                Some(referenced) => referenced,
+                None => continue,
+            };
+            let new_entity = self.entities.create();
+            self.parent(new_entity, created[index], tag);
+            if let Some(ref transform) = reference.transform {
+                // Applied after the components of the referenced prefab.
+                self.lazy.insert(new_entity, transform.clone());
+            }
+            children
+                .entry(index)
+                .or_insert_with(Vec::new)
+                .push(new_entity);
+            references.push((referenced, &reference.overrides, new_entity));
+        }
+        // create components, then apply the overrides on top of them
+        let data = prefab
+            .entities
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entity_data)| entity_data.data.as_ref().map(|d| (index, d)))
+            .chain(data_overrides.iter().map(|(&index, d)| (index, d)));
+        for (index, prefab_data) in data {
+            prefab_data
+                .add_to_entity(
+                    created[index],
+                    self.system_data,
+                    &created,
+                    children
+                        .get(&index)
+                        .map(|children| &children[..])
+                        .unwrap_or(&[]),
+                )
+                .expect("Unable to add prefab system data to entity");
+        }
+        for (index, insert) in component_overrides {
+            insert(created[index], self.lazy);
+        }
+
+        for (referenced, overrides, entity) in references {
+            if let Err(e) = self.spawn(referenced, entity, &overrides.data, Vec::new()) {
+                error!(
+                    "Failed to instantiate referenced prefab on {:?}: {}",
+                    entity, e
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Triggers sub asset loading for override data, which is added to the entities without
+    /// waiting for the sub assets.
+    fn load_sub_assets(&mut self, data: &mut BTreeMap<usize, T>) -> Result<(), Error> {
+        let mut progress = ProgressCounter::new();
+        for data in data.values_mut() {
+            data.load_sub_assets(&mut progress, self.system_data)
+                .with_context(|_| format_err!("Failed starting sub asset loading"))?;
+        }
+        Ok(())
+    }
+
+    fn parent(&mut self, entity: Entity, parent: Entity, tag: u64) {
+        self.parents
+            .insert(entity, Parent { entity: parent })
+            .expect("Unable to insert `Parent` for prefab");
+        self.tag(entity, tag);
+    }
+
+    fn tag(&mut self, entity: Entity, tag: u64) {
+        self.tags
+            .insert(entity, PrefabTag::new(tag))
+            .expect("Unable to insert `PrefabTag` for prefab entity");
+    }
+}
@@ -1,6 +1,9 @@
-use std::sync::{
-    atomic::{AtomicUsize, Ordering},
-    Arc,
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
 };
 
 use amethyst_error::Error;
@@ -41,10 +44,43 @@ impl Progress for () {
     fn create_tracker(self) {}
 }
 
+/// Number of bytes of assets being fetched by a `Source`, e.g. downloaded over the network.
+///
+/// Sources which know the size of an asset ahead of time report it through `add_total`,
+/// and the bytes received so far through `add_loaded`.
+#[derive(Clone, Default, Debug)]
+pub struct ByteProgress {
+    total: Arc<AtomicUsize>,
+    loaded: Arc<AtomicUsize>,
+}
+
+impl ByteProgress {
+    /// Adds `num` bytes to the expected total.
+    pub fn add_total(&self, num: usize) {
+        self.total.fetch_add(num, Ordering::Relaxed);
+    }
+
+    /// Adds `num` bytes to the bytes received so far.
+    pub fn add_loaded(&self, num: usize) {
+        self.loaded.fetch_add(num, Ordering::Relaxed);
+    }
+
+    /// Returns the expected total number of bytes.
+    pub fn total(&self) -> usize {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of bytes received so far.
+    pub fn loaded(&self) -> usize {
+        self.loaded.load(Ordering::Relaxed)
+    }
+}
+
 /// A progress tracker which is passed to the `Loader`
 /// in order to check how many assets are loaded.
 #[derive(Default, Debug)]
 pub struct ProgressCounter {
+    bytes: ByteProgress,
     errors: Arc<Mutex<Vec<AssetErrorMeta>>>,
     num_assets: usize,
     num_failed: Arc<AtomicUsize>,
@@ -58,6 +94,8 @@ impl ProgressCounter {
     }
 
     /// Removes all errors and returns them.
+    ///
+    /// Each error records the asset that failed to load, and the error that caused it.
     pub fn errors(&self) -> Vec<AssetErrorMeta> {
         let mut lock = self.errors.lock();
         lock.drain(..).collect()
@@ -83,6 +121,12 @@ impl ProgressCounter {
         self.num_assets - self.num_loading() - self.num_failed()
     }
 
+    /// Returns the byte counts reported by sources which fetch the tracked assets
+    /// incrementally, like `HttpSource`.
+    pub fn bytes(&self) -> &ByteProgress {
+        &self.bytes
+    }
+
     /// Returns `Completion::Complete` if all tracked assets are finished.
     pub fn complete(&self) -> Completion {
         match (
@@ -115,6 +159,7 @@ impl<'a> Progress for &'a mut ProgressCounter {
         num_loading.fetch_add(1, Ordering::Relaxed);
 
         ProgressCounterTracker {
+            bytes: self.bytes.clone(),
             errors,
             num_failed,
             num_loading,
@@ -125,6 +170,7 @@ impl<'a> Progress for &'a mut ProgressCounter {
 /// Progress tracker for `ProgressCounter`.
 #[derive(Default, Debug)]
 pub struct ProgressCounterTracker {
+    bytes: ByteProgress,
     errors: Arc<Mutex<Vec<AssetErrorMeta>>>,
     num_failed: Arc<AtomicUsize>,
     num_loading: Arc<AtomicUsize>,
@@ -135,6 +181,10 @@ impl Tracker for ProgressCounterTracker {
         self.num_loading.fetch_sub(1, Ordering::Relaxed);
     }
 
+    fn bytes(&self) -> Option<ByteProgress> {
+        Some(self.bytes.clone())
+    }
+
     fn fail(
         self: Box<Self>,
         handle_id: u32,
@@ -142,13 +192,14 @@ impl Tracker for ProgressCounterTracker {
         asset_name: String,
         error: Error,
     ) {
-        show_error(handle_id, asset_type_name, &asset_name, &error);
-        self.errors.lock().push(AssetErrorMeta {
+        let meta = AssetErrorMeta {
             error,
             handle_id,
             asset_type_name,
             asset_name,
-        });
+        };
+        error!("{}", meta);
+        self.errors.lock().push(meta);
         self.num_failed.fetch_add(1, Ordering::Relaxed);
 
         // Failed assets are not requeued for loading, so we subtract it from the number that tracks
@@ -157,14 +208,40 @@ impl Tracker for ProgressCounterTracker {
     }
 }
 
+/// Describes an asset that failed to load, as returned by `ProgressCounter::errors`.
 #[derive(Debug)]
 pub struct AssetErrorMeta {
+    /// The error that caused the asset to fail loading.
     pub error: Error,
+    /// Id of the handle of the asset.
     pub handle_id: u32,
+    /// Type name of the asset, see `Asset::NAME`.
     pub asset_type_name: &'static str,
+    /// Name of the asset, usually the path it was loaded from.
     pub asset_name: String,
 }
 
+impl AssetErrorMeta {
+    /// Formats the error together with all of its causes and their metadata, each cause on an
+    /// indented line below the error it caused.
+    pub fn error_chain(&self) -> String {
+        format!("{:#}", self.error)
+    }
+}
+
+impl Display for AssetErrorMeta {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "Error loading handle {}, {}, with name {}: {}",
+            self.handle_id,
+            self.asset_type_name,
+            self.asset_name,
+            self.error_chain(),
+        )
+    }
+}
+
 /// The `Tracker` trait which will be used by the loader to report
 /// back to `Progress`.
 pub trait Tracker: Send + 'static {
@@ -179,6 +256,11 @@ pub trait Tracker: Send + 'static {
         asset_name: String,
         error: Error,
     );
+
+    /// Returns where sources should report the number of bytes fetched for the asset.
+    fn bytes(&self) -> Option<ByteProgress> {
+        None
+    }
 }
 
 impl Tracker for () {
@@ -190,22 +272,17 @@ impl Tracker for () {
         asset_name: String,
         error: Error,
     ) {
-        show_error(handle_id, asset_type_name, &asset_name, &error);
+        let meta = AssetErrorMeta {
+            error,
+            handle_id,
+            asset_type_name,
+            asset_name,
+        };
+        error!("{}", meta);
         error!("Note: to handle the error, use a `Progress` other than `()`");
     }
 }
 
-fn show_error(handle_id: u32, asset_type_name: &'static str, asset_name: &str, error: &Error) {
-    let mut err_out = format!(
-        "Error loading handle {}, {}, with name {}: {}",
-        handle_id, asset_type_name, asset_name, error,
-    );
-    error
-        .causes()
-        .for_each(|e| err_out.push_str(&format!("\ncaused by: {}\n{:?}", e, e)));
-    error!("{}", err_out);
-}
-
 #[cfg(test)]
 mod tests {
     use amethyst_error::Error;
@@ -288,4 +365,29 @@ mod tests {
         tracker_2.success();
         assert_eq!(2, progress.num_finished());
     }
+
+    #[test]
+    fn progress_counter_errors_record_asset_and_error_chain() {
+        let mut progress_counter = ProgressCounter::new();
+        let mut progress = &mut progress_counter;
+        progress.add_assets(1);
+        let tracker = Box::new(progress.create_tracker());
+
+        tracker.fail(
+            3,
+            "AssetType",
+            String::from("textures/missing.png"),
+            Error::from_string("Failed to load asset").with_source(Error::from_string("not found")),
+        );
+
+        let errors = progress_counter.errors();
+        assert_eq!(1, errors.len());
+        assert_eq!(3, errors[0].handle_id);
+        assert_eq!("AssetType", errors[0].asset_type_name);
+        assert_eq!("textures/missing.png", errors[0].asset_name);
+        assert_eq!(
+            "Failed to load asset\n  caused by: not found",
+            errors[0].error_chain()
+        );
+    }
 }
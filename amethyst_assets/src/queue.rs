@@ -0,0 +1,221 @@
+//! Priority queue for the load requests handled by the `Loader`'s worker threads.
+
+use std::{cmp::Ordering, collections::BinaryHeap, sync::Arc};
+
+use parking_lot::Mutex;
+use rayon::ThreadPool;
+
+/// Number of requests submitted after a request which it takes for the request to move up
+/// by one priority, so that low priority requests are not starved by a steady stream of
+/// more urgent ones.
+const AGING: i64 = 32;
+
+/// How urgently an asset is needed.
+///
+/// The `Loader` hands pending requests with higher priority to its worker threads first,
+/// e.g. to load the player model and UI before distant scenery.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LoadPriority {
+    /// Assets which are not needed right away.
+    Low,
+    /// The priority used by `Loader::load`.
+    Normal,
+    /// Assets which are needed as soon as possible.
+    High,
+}
+
+impl Default for LoadPriority {
+    fn default() -> Self {
+        LoadPriority::Normal
+    }
+}
+
+impl LoadPriority {
+    fn level(self) -> i64 {
+        match self {
+            LoadPriority::Low => 0,
+            LoadPriority::Normal => 1,
+            LoadPriority::High => 2,
+        }
+    }
+}
+
+/// Number of load requests which are waiting for a worker thread, by priority.
+///
+/// Returned by `Loader::queue_stats`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LoadQueueStats {
+    /// Pending requests with `LoadPriority::Low`.
+    pub low: usize,
+    /// Pending requests with `LoadPriority::Normal`.
+    pub normal: usize,
+    /// Pending requests with `LoadPriority::High`.
+    pub high: usize,
+}
+
+impl LoadQueueStats {
+    /// Returns the number of pending requests with the given priority.
+    pub fn pending(&self, priority: LoadPriority) -> usize {
+        match priority {
+            LoadPriority::Low => self.low,
+            LoadPriority::Normal => self.normal,
+            LoadPriority::High => self.high,
+        }
+    }
+
+    /// Returns the number of pending requests.
+    pub fn total(&self) -> usize {
+        self.low + self.normal + self.high
+    }
+
+    fn pending_mut(&mut self, priority: LoadPriority) -> &mut usize {
+        match priority {
+            LoadPriority::Low => &mut self.low,
+            LoadPriority::Normal => &mut self.normal,
+            LoadPriority::High => &mut self.high,
+        }
+    }
+}
+
+struct Job {
+    /// Requests with a lower rank are handled first.
+    rank: i64,
+    seq: u64,
+    priority: LoadPriority,
+    task: Box<dyn FnOnce() + Send>,
+}
+
+impl PartialEq for Job {
+    fn eq(&self, other: &Self) -> bool {
+        self.seq == other.seq
+    }
+}
+
+impl Eq for Job {}
+
+impl PartialOrd for Job {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Job {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // `BinaryHeap` is a max-heap, so the lowest rank has to compare greatest.
+        (other.rank, other.seq).cmp(&(self.rank, self.seq))
+    }
+}
+
+#[derive(Default)]
+struct QueueInner {
+    jobs: BinaryHeap<Job>,
+    next_seq: u64,
+    stats: LoadQueueStats,
+}
+
+/// Queue of load requests, ordered by priority and age.
+#[derive(Default)]
+pub(crate) struct LoadQueue {
+    inner: Mutex<QueueInner>,
+}
+
+impl LoadQueue {
+    /// Queues `task` and asks the pool to run the most urgent queued task.
+    pub(crate) fn spawn<T>(self: &Arc<Self>, pool: &ThreadPool, priority: LoadPriority, task: T)
+    where
+        T: FnOnce() + Send + 'static,
+    {
+        self.push(priority, task);
+        let queue = self.clone();
+        pool.spawn(move || {
+            if let Some(task) = queue.pop() {
+                task();
+            }
+        });
+    }
+
+    pub(crate) fn stats(&self) -> LoadQueueStats {
+        self.inner.lock().stats
+    }
+
+    fn push<T>(&self, priority: LoadPriority, task: T)
+    where
+        T: FnOnce() + Send + 'static,
+    {
+        let mut inner = self.inner.lock();
+        let seq = inner.next_seq;
+        inner.next_seq += 1;
+        *inner.stats.pending_mut(priority) += 1;
+        inner.jobs.push(Job {
+            rank: seq as i64 - AGING * priority.level(),
+            seq,
+            priority,
+            task: Box::new(task),
+        });
+    }
+
+    fn pop(&self) -> Option<Box<dyn FnOnce() + Send>> {
+        let mut inner = self.inner.lock();
+        let job = inner.jobs.pop()?;
+        *inner.stats.pending_mut(job.priority) -= 1;
+        Some(job.task)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::{LoadPriority, LoadQueue, AGING};
+
+    fn run_all(queue: &LoadQueue) {
+        while let Some(task) = queue.pop() {
+            task();
+        }
+    }
+
+    #[test]
+    fn higher_priorities_are_pulled_first() {
+        let queue = LoadQueue::default();
+        let order = Arc::new(Mutex::new(Vec::new()));
+        for &(name, priority) in &[
+            ("scenery", LoadPriority::Low),
+            ("props", LoadPriority::Normal),
+            ("player", LoadPriority::High),
+            ("ui", LoadPriority::High),
+        ] {
+            let order = order.clone();
+            queue.push(priority, move || order.lock().unwrap().push(name));
+        }
+
+        let stats = queue.stats();
+        assert_eq!(2, stats.pending(LoadPriority::High));
+        assert_eq!(4, stats.total());
+
+        run_all(&queue);
+        assert_eq!(
+            vec!["player", "ui", "props", "scenery"],
+            *order.lock().unwrap()
+        );
+        assert_eq!(0, queue.stats().total());
+    }
+
+    #[test]
+    fn low_priorities_are_not_starved() {
+        let queue = LoadQueue::default();
+        let order = Arc::new(Mutex::new(Vec::new()));
+        {
+            let order = order.clone();
+            queue.push(LoadPriority::Low, move || order.lock().unwrap().push(-1));
+        }
+        for i in 0..(3 * AGING) {
+            let order = order.clone();
+            queue.push(LoadPriority::High, move || order.lock().unwrap().push(i));
+        }
+
+        run_all(&queue);
+        let order = order.lock().unwrap();
+        let low = order.iter().position(|&i| i == -1).unwrap();
+        assert_eq!(2 * AGING as usize - 1, low);
+    }
+}
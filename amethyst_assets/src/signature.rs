@@ -0,0 +1,329 @@
+//! Detection of the format of a file by the bytes it starts with.
+
+use std::sync::Arc;
+
+use derivative::Derivative;
+use log::warn;
+
+use amethyst_error::{Error, ResultExt};
+
+use crate::{error, source::Prefetched, Format, FormatValue, Source};
+
+/// Number of leading bytes shown when no signature matches.
+const SHOWN_BYTES: usize = 16;
+
+/// The magic bytes identifying a file format, e.g. `\x89PNG` for PNG images.
+///
+/// A signature can consist of several parts at different offsets, which all have to match:
+///
+/// ```rust
+/// # use amethyst_assets::Signature;
+/// let wav = Signature::new(b"RIFF").and_at(8, b"WAVE");
+/// assert!(wav.matches(b"RIFF\x24\x08\x00\x00WAVEfmt "));
+/// assert!(!wav.matches(b"RIFF\x24\x08\x00\x00AVI LIST"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+    parts: Vec<(usize, &'static [u8])>,
+}
+
+impl Signature {
+    /// Creates a signature matching files starting with `magic`.
+    pub fn new(magic: &'static [u8]) -> Self {
+        Signature::at(0, magic)
+    }
+
+    /// Creates a signature matching files containing `magic` at `offset`.
+    pub fn at(offset: usize, magic: &'static [u8]) -> Self {
+        Signature {
+            parts: vec![(offset, magic)],
+        }
+    }
+
+    /// Additionally requires `magic` at `offset`.
+    pub fn and_at(mut self, offset: usize, magic: &'static [u8]) -> Self {
+        self.parts.push((offset, magic));
+        self
+    }
+
+    /// Returns whether `bytes` carry this signature.
+    pub fn matches(&self, bytes: &[u8]) -> bool {
+        self.parts.iter().all(|&(offset, magic)| {
+            bytes
+                .get(offset..offset + magic.len())
+                .map_or(false, |b| b == magic)
+        })
+    }
+}
+
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""), Debug(bound = ""))]
+struct Entry<D> {
+    label: &'static str,
+    signatures: Vec<Signature>,
+    format: Box<dyn Format<D>>,
+}
+
+/// Registry of formats for the asset data `D`, recognized by the signatures of their files.
+///
+/// The registry is used by `Loader::load_auto`, which picks the format of an asset by its
+/// leading bytes instead of its file extension. Crates providing formats offer registries
+/// with their formats already registered, which games can extend with their own:
+///
+/// ```rust,ignore
+/// let mut signatures = texture_signatures(ImageFormat::default());
+/// signatures.register("KTX", KtxFormat, vec![Signature::new(b"\xABKTX 11\xBB")]);
+///
+/// let texture = loader.load_auto("texture/logo", &signatures, (), &storage);
+/// ```
+///
+/// Formats are tried in the order of registration.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""), Debug(bound = ""), Default(bound = ""))]
+pub struct SignatureRegistry<D> {
+    entries: Vec<Entry<D>>,
+}
+
+impl<D: 'static> SignatureRegistry<D> {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Registers `format` for files carrying any of the given signatures. The `label` names
+    /// the kind of file, e.g. `"PNG"`, in error messages.
+    pub fn register<F>(
+        &mut self,
+        label: &'static str,
+        format: F,
+        signatures: Vec<Signature>,
+    ) -> &mut Self
+    where
+        F: Format<D>,
+    {
+        self.entries.push(Entry {
+            label,
+            signatures,
+            format: Box::new(format),
+        });
+        self
+    }
+
+    /// Builder method version of `register`.
+    pub fn with<F>(mut self, label: &'static str, format: F, signatures: Vec<Signature>) -> Self
+    where
+        F: Format<D>,
+    {
+        self.register(label, format, signatures);
+        self
+    }
+
+    /// Returns the format registered for the signature of `bytes`.
+    ///
+    /// Fails with an error listing the leading bytes and the kinds of files tried if no
+    /// registered signature matches.
+    pub fn detect(&self, bytes: &[u8]) -> Result<&dyn Format<D>, Error> {
+        self.detect_entry(bytes).map(|entry| &*entry.format)
+    }
+
+    /// Returns a format importing assets with the format detected from their bytes.
+    pub fn auto(&self) -> AutoFormat<D> {
+        AutoFormat {
+            registry: self.clone(),
+            primary: None,
+        }
+    }
+
+    /// Returns a format importing assets with `format`, falling back to the format detected
+    /// from their bytes if `format` fails to import them, e.g. because a PNG file was saved
+    /// with a `.jpg` extension.
+    pub fn with_fallback<F>(&self, format: F) -> AutoFormat<D>
+    where
+        F: Format<D>,
+    {
+        AutoFormat {
+            registry: self.clone(),
+            primary: Some(Box::new(format)),
+        }
+    }
+
+    fn detect_entry(&self, bytes: &[u8]) -> Result<&Entry<D>, Error> {
+        self.entries
+            .iter()
+            .find(|entry| entry.signatures.iter().any(|s| s.matches(bytes)))
+            .ok_or_else(|| {
+                let seen = bytes
+                    .iter()
+                    .take(SHOWN_BYTES)
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let tried = self
+                    .entries
+                    .iter()
+                    .map(|entry| entry.label)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                Error::from(error::Error::UnknownSignature(seen, tried))
+            })
+    }
+}
+
+/// Format picking the format to import an asset with from a `SignatureRegistry`.
+///
+/// Created with `SignatureRegistry::auto` and `SignatureRegistry::with_fallback`.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""), Debug(bound = ""))]
+pub struct AutoFormat<D> {
+    registry: SignatureRegistry<D>,
+    primary: Option<Box<dyn Format<D>>>,
+}
+
+impl<D: 'static> Format<D> for AutoFormat<D> {
+    fn name(&self) -> &'static str {
+        "AUTO"
+    }
+
+    fn import(
+        &self,
+        name: String,
+        source: Arc<dyn Source>,
+        create_reload: Option<Box<dyn Format<D>>>,
+    ) -> Result<FormatValue<D>, Error> {
+        let bytes = source.load(&name).with_context(|_| error::Error::Source)?;
+
+        let primary_error = match self.primary {
+            Some(ref primary) => {
+                let prefetched = Prefetched::new(name.clone(), Ok(bytes.clone()), source.clone());
+                match primary.import(name.clone(), Arc::new(prefetched), create_reload.clone()) {
+                    Ok(value) => return Ok(value),
+                    Err(e) => Some((primary.name(), e)),
+                }
+            }
+            None => None,
+        };
+
+        let entry = match (self.registry.detect_entry(&bytes), primary_error) {
+            (Ok(entry), None) => entry,
+            (Ok(entry), Some((primary, e))) => {
+                if entry.format.name() == primary {
+                    return Err(e);
+                }
+                warn!(
+                    "Format {:?} failed to import {:?}, which looks like a {} file: {}",
+                    primary, name, entry.label, e
+                );
+                entry
+            }
+            (Err(e), None) => {
+                return Err(e).with_context(|_| error::Error::Format(self.name()));
+            }
+            (Err(_), Some((_, e))) => return Err(e),
+        };
+
+        let prefetched = Prefetched::new(name.clone(), Ok(bytes), source);
+        entry
+            .format
+            .import(name, Arc::new(prefetched), create_reload)
+            .with_context(|_| error::Error::Format(entry.format.name()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use amethyst_error::Error;
+
+    use crate::{Format, Source};
+
+    use super::{Signature, SignatureRegistry};
+
+    #[derive(Clone, Debug)]
+    struct Tagged(&'static str, &'static [u8]);
+
+    impl Format<String> for Tagged {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn import_simple(&self, bytes: Vec<u8>) -> Result<String, Error> {
+            if bytes.starts_with(self.1) {
+                Ok(self.0.to_owned())
+            } else {
+                Err(amethyst_error::format_err!("not a {} file", self.0))
+            }
+        }
+    }
+
+    struct Bytes(&'static [u8]);
+
+    impl Source for Bytes {
+        fn modified(&self, _path: &str) -> Result<u64, Error> {
+            Ok(0)
+        }
+
+        fn load(&self, _path: &str) -> Result<Vec<u8>, Error> {
+            Ok(self.0.to_vec())
+        }
+    }
+
+    fn registry() -> SignatureRegistry<String> {
+        SignatureRegistry::new()
+            .with(
+                "PNG",
+                Tagged("PNG", b"\x89PNG"),
+                vec![Signature::new(b"\x89PNG")],
+            )
+            .with(
+                "WAV",
+                Tagged("WAV", b"RIFF"),
+                vec![Signature::new(b"RIFF").and_at(8, b"WAVE")],
+            )
+    }
+
+    fn import(format: &dyn Format<String>, bytes: &'static [u8]) -> Result<String, Error> {
+        format
+            .import("asset".into(), Arc::new(Bytes(bytes)), None)
+            .map(|value| value.data)
+    }
+
+    #[test]
+    fn detects_registered_signatures() {
+        let registry = registry();
+        assert_eq!("PNG", registry.detect(b"\x89PNG\r\n").unwrap().name());
+        assert_eq!(
+            "WAV",
+            import(&registry.auto(), b"RIFF\0\0\0\0WAVEfmt ").unwrap()
+        );
+    }
+
+    #[test]
+    fn unknown_signatures_list_bytes_and_formats() {
+        let registry = registry();
+        for bytes in &[&b""[..], b"RIFF", b"GIF89a"] {
+            assert!(registry.detect(bytes).is_err());
+        }
+
+        let message = registry
+            .auto()
+            .import("asset".into(), Arc::new(Bytes(b"GIF89a")), None)
+            .err()
+            .unwrap()
+            .causes()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(message.contains("47 49 46 38 39 61"), "{}", message);
+        assert!(message.contains("PNG, WAV"), "{}", message);
+    }
+
+    #[test]
+    fn falls_back_to_detected_format() {
+        let registry = registry();
+        let fallback = registry.with_fallback(Tagged("WAV", b"RIFF"));
+        assert_eq!("PNG", import(&fallback, b"\x89PNG\r\n").unwrap());
+        assert_eq!("WAV", import(&fallback, b"RIFF\0\0\0\0WAVE").unwrap());
+        assert!(import(&fallback, b"GIF89a").is_err());
+    }
+}
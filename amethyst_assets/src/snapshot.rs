@@ -0,0 +1,643 @@
+//! Saving the state of a `World` and restoring it, e.g. for save games.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    marker::PhantomData,
+};
+
+use derivative::Derivative;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use amethyst_config::parse_ron;
+use amethyst_core::{
+    ecs::{
+        prelude::{Component, DispatcherBuilder, Entity, Join, World, WorldExt},
+        saveload::{ConvertSaveload, Marker, MarkerAllocator, SimpleMarker, SimpleMarkerAllocator},
+    },
+    SystemBundle,
+};
+use amethyst_error::{format_err, Error, ResultExt};
+
+use crate::{Asset, AssetStorage, Format, Handle, Loader, ProgressCounter};
+
+/// Version of the snapshot format written by `save_world`.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Marker type of the entities `save_world` saves.
+#[derive(Debug)]
+pub struct Saved;
+
+/// Marker of entities which are saved by `save_world`. The markers stay the same across
+/// saves and loads, so entities keep their identity in save files.
+///
+/// Mark entities with `MarkedBuilder::marked::<SaveMarker>()` when creating them.
+pub type SaveMarker = SimpleMarker<Saved>;
+
+/// Allocator of `SaveMarker`s, added to the `World` by the `SaveBundle`.
+pub type SaveMarkerAllocator = SimpleMarkerAllocator<Saved>;
+
+/// Conversion of save data written by an older version of a component.
+///
+/// Receives the version the data was saved with and the data as RON text.
+pub type UpgradeFn<C> = fn(u32, &str) -> Result<<C as ConvertSaveload<SaveMarker>>::Data, Error>;
+
+/// Component data parsed from a save, which is inserted once the entities of the save exist.
+type ParsedComponent = Box<
+    dyn FnOnce(
+        &World,
+        Entity,
+        &mut dyn FnMut(SaveMarker) -> Option<Entity>,
+        &mut ProgressCounter,
+    ) -> Result<(), Error>,
+>;
+
+#[derive(Deserialize, Serialize)]
+struct Snapshot {
+    version: u32,
+    components: BTreeMap<String, u32>,
+    entities: Vec<SavedEntity>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct SavedEntity {
+    marker: SaveMarker,
+    components: BTreeMap<String, String>,
+}
+
+trait SaveEntry: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn version(&self) -> u32;
+
+    fn can_load(&self, version: u32) -> bool;
+
+    fn setup(&self, world: &mut World);
+
+    fn save(
+        &self,
+        world: &World,
+        entity: Entity,
+        ids: &mut dyn FnMut(Entity) -> Option<SaveMarker>,
+    ) -> Result<Option<String>, Error>;
+
+    fn parse(&self, data: &str, version: u32) -> Result<ParsedComponent, Error>;
+}
+
+struct ComponentEntry<C>
+where
+    C: ConvertSaveload<SaveMarker>,
+{
+    name: String,
+    version: u32,
+    upgrade: Option<UpgradeFn<C>>,
+    marker: PhantomData<C>,
+}
+
+impl<C> SaveEntry for ComponentEntry<C>
+where
+    C: Component + ConvertSaveload<SaveMarker> + Send + Sync,
+    C::Data: 'static,
+    C::Error: std::fmt::Display,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    fn can_load(&self, version: u32) -> bool {
+        version == self.version || self.upgrade.is_some()
+    }
+
+    fn setup(&self, world: &mut World) {
+        world.register::<C>();
+    }
+
+    fn save(
+        &self,
+        world: &World,
+        entity: Entity,
+        ids: &mut dyn FnMut(Entity) -> Option<SaveMarker>,
+    ) -> Result<Option<String>, Error> {
+        let storage = world.read_storage::<C>();
+        let component = match storage.get(entity) {
+            Some(component) => component,
+            None => return Ok(None),
+        };
+        let data = component
+            .convert_into(|entity| ids(entity))
+            .map_err(|e| format_err!("{}", e))?;
+        Ok(Some(ron::ser::to_string(&data)?))
+    }
+
+    fn parse(&self, data: &str, version: u32) -> Result<ParsedComponent, Error> {
+        let data = if version == self.version {
+            parse_ron(data.as_bytes())?
+        } else {
+            let upgrade = self.upgrade.expect("Loading data checked by `can_load`");
+            upgrade(version, data)?
+        };
+        Ok(Box::new(move |world, entity, ids, _progress| {
+            let component =
+                C::convert_from(data, |marker| ids(marker)).map_err(|e| format_err!("{}", e))?;
+            world.write_storage::<C>().insert(entity, component)?;
+            Ok(())
+        }))
+    }
+}
+
+struct AssetEntry<A>
+where
+    A: Asset,
+{
+    name: String,
+    format: Box<dyn Format<A::Data>>,
+    marker: PhantomData<A>,
+}
+
+impl<A> SaveEntry for AssetEntry<A>
+where
+    A: Asset,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn version(&self) -> u32 {
+        SNAPSHOT_VERSION
+    }
+
+    fn can_load(&self, version: u32) -> bool {
+        version == SNAPSHOT_VERSION
+    }
+
+    fn setup(&self, world: &mut World) {
+        world.register::<Handle<A>>();
+    }
+
+    fn save(
+        &self,
+        world: &World,
+        entity: Entity,
+        _ids: &mut dyn FnMut(Entity) -> Option<SaveMarker>,
+    ) -> Result<Option<String>, Error> {
+        let handles = world.read_storage::<Handle<A>>();
+        let handle = match handles.get(entity) {
+            Some(handle) => handle,
+            None => return Ok(None),
+        };
+        let name = world
+            .read_resource::<AssetStorage<A>>()
+            .asset_name(handle)
+            .ok_or_else(|| {
+                format_err!(
+                    "{} asset with id {} was not loaded by name and can't be saved",
+                    A::NAME,
+                    handle.id()
+                )
+            })?;
+        Ok(Some(ron::ser::to_string(&name)?))
+    }
+
+    fn parse(&self, data: &str, _version: u32) -> Result<ParsedComponent, Error> {
+        let name: String = parse_ron(data.as_bytes())?;
+        let format = self.format.clone();
+        Ok(Box::new(move |world, entity, _ids, progress| {
+            let handle = world.read_resource::<Loader>().load(
+                name,
+                format,
+                progress,
+                &world.read_resource::<AssetStorage<A>>(),
+            );
+            world.write_storage::<Handle<A>>().insert(entity, handle)?;
+            Ok(())
+        }))
+    }
+}
+
+/// The components saved by `save_world`, each under a name which must not change between
+/// versions of the game.
+///
+/// Created by the `SaveBundle`, which also adds it to the `World`.
+#[derive(Default)]
+pub struct SaveRegistry {
+    entries: Vec<Box<dyn SaveEntry>>,
+}
+
+impl SaveRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Saves components of type `C` under `name`.
+    ///
+    /// Entities referenced by the component, like the parent of `Parent`, are saved as their
+    /// markers, so references to entities which aren't saved fail the save.
+    pub fn register<C>(&mut self, name: &str)
+    where
+        C: Component + ConvertSaveload<SaveMarker> + Send + Sync,
+        C::Data: 'static,
+        C::Error: std::fmt::Display,
+    {
+        self.add(ComponentEntry::<C> {
+            name: name.to_owned(),
+            version: 0,
+            upgrade: None,
+            marker: PhantomData,
+        });
+    }
+
+    /// Saves components of type `C` under `name`, with the version of their save data.
+    ///
+    /// Data saved with another version is converted by `upgrade`. Without an upgrade,
+    /// loading data of another version fails.
+    pub fn register_versioned<C>(&mut self, name: &str, version: u32, upgrade: Option<UpgradeFn<C>>)
+    where
+        C: Component + ConvertSaveload<SaveMarker> + Send + Sync,
+        C::Data: 'static,
+        C::Error: std::fmt::Display,
+    {
+        self.add(ComponentEntry::<C> {
+            name: name.to_owned(),
+            version,
+            upgrade,
+            marker: PhantomData,
+        });
+    }
+
+    /// Saves `Handle<A>` components under `name` as the names of their assets, which are
+    /// loaded again with `format` when the save is loaded.
+    ///
+    /// Saving fails for handles of assets which weren't loaded by name, e.g. with
+    /// `Loader::load_from_data`.
+    pub fn register_asset<A, F>(&mut self, name: &str, format: F)
+    where
+        A: Asset,
+        F: Format<A::Data>,
+    {
+        self.add(AssetEntry::<A> {
+            name: name.to_owned(),
+            format: Box::new(format),
+            marker: PhantomData,
+        });
+    }
+
+    fn add<E: SaveEntry + 'static>(&mut self, entry: E) {
+        assert!(
+            self.entry(entry.name()).is_none(),
+            "Component name {:?} is registered twice",
+            entry.name()
+        );
+        self.entries.push(Box::new(entry));
+    }
+
+    fn entry(&self, name: &str) -> Option<&dyn SaveEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name() == name)
+            .map(|entry| &**entry)
+    }
+}
+
+/// Bundle registering the components saved by `save_world`.
+///
+/// Adds the `SaveRegistry` and the `SaveMarkerAllocator` to the `World`.
+///
+/// ### Example:
+///
+/// ```rust,ignore
+/// let bundle = SaveBundle::new()
+///     .with::<Transform>("transform")
+///     .with::<Parent>("parent")
+///     .with_versioned::<Health>("health", 2, Some(upgrade_health))
+///     .with_asset::<Mesh, _>("mesh", ObjFormat);
+/// ```
+#[derive(Derivative, Default)]
+#[derivative(Debug)]
+pub struct SaveBundle {
+    #[derivative(Debug = "ignore")]
+    registry: SaveRegistry,
+}
+
+impl SaveBundle {
+    /// Creates a bundle saving no components.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Saves components of type `C` under `name`, see `SaveRegistry::register`.
+    pub fn with<C>(mut self, name: &str) -> Self
+    where
+        C: Component + ConvertSaveload<SaveMarker> + Send + Sync,
+        C::Data: 'static,
+        C::Error: std::fmt::Display,
+    {
+        self.registry.register::<C>(name);
+        self
+    }
+
+    /// Saves versioned components of type `C` under `name`, see
+    /// `SaveRegistry::register_versioned`.
+    pub fn with_versioned<C>(
+        mut self,
+        name: &str,
+        version: u32,
+        upgrade: Option<UpgradeFn<C>>,
+    ) -> Self
+    where
+        C: Component + ConvertSaveload<SaveMarker> + Send + Sync,
+        C::Data: 'static,
+        C::Error: std::fmt::Display,
+    {
+        self.registry
+            .register_versioned::<C>(name, version, upgrade);
+        self
+    }
+
+    /// Saves handles to assets of type `A` under `name`, see `SaveRegistry::register_asset`.
+    pub fn with_asset<A, F>(mut self, name: &str, format: F) -> Self
+    where
+        A: Asset,
+        F: Format<A::Data>,
+    {
+        self.registry.register_asset::<A, F>(name, format);
+        self
+    }
+}
+
+impl<'a, 'b> SystemBundle<'a, 'b> for SaveBundle {
+    fn build(
+        self,
+        world: &mut World,
+        _dispatcher: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        for entry in &self.registry.entries {
+            entry.setup(world);
+        }
+        world.register::<SaveMarker>();
+        world.insert(SaveMarkerAllocator::default());
+        world.insert(self.registry);
+        Ok(())
+    }
+}
+
+/// Saves the registered components of the marked entities passing `filter`.
+///
+/// Entities are saved with their `SaveMarker`, so the save only contains entities marked
+/// with it. The returned bytes are a RON document, which can be restored with `load_world`.
+pub fn save_world<F>(world: &World, filter: F) -> Result<Vec<u8>, Error>
+where
+    F: Fn(Entity) -> bool,
+{
+    let registry = world
+        .try_fetch::<SaveRegistry>()
+        .ok_or_else(|| format_err!("No `SaveRegistry` in the world, add the `SaveBundle`"))?;
+    let entities = world.entities();
+    let markers = world.read_storage::<SaveMarker>();
+
+    let mut saved = (&entities, &markers)
+        .join()
+        .filter(|&(entity, _)| filter(entity))
+        .map(|(entity, marker)| (entity, marker.clone()))
+        .collect::<Vec<_>>();
+    saved.sort_by_key(|(_, marker)| marker.id());
+    let ids = saved.iter().cloned().collect::<HashMap<_, _>>();
+
+    let mut snapshot = Snapshot {
+        version: SNAPSHOT_VERSION,
+        components: registry
+            .entries
+            .iter()
+            .map(|entry| (entry.name().to_owned(), entry.version()))
+            .collect(),
+        entities: Vec::with_capacity(saved.len()),
+    };
+    for (entity, marker) in saved {
+        let mut components = BTreeMap::new();
+        for entry in &registry.entries {
+            let data = entry
+                .save(world, entity, &mut |entity| ids.get(&entity).cloned())
+                .with_context(|_| {
+                    format_err!(
+                        "Failed to save component {:?} of entity {}",
+                        entry.name(),
+                        marker.id()
+                    )
+                })?;
+            if let Some(data) = data {
+                components.insert(entry.name().to_owned(), data);
+            }
+        }
+        snapshot.entities.push(SavedEntity { marker, components });
+    }
+
+    Ok(ron::ser::to_string_pretty(&snapshot, PrettyConfig::default())?.into_bytes())
+}
+
+/// Restores the entities saved by `save_world`, returning them.
+///
+/// Entities with a marker already in the world are updated, others are created. References
+/// between the saved entities are restored, and assets are loaded by their names, which
+/// `progress` tracks.
+///
+/// Fails without changing the world if the save was written by a newer version of the
+/// format, or contains components which aren't registered, can't be upgraded or can't be
+/// parsed.
+pub fn load_world(
+    world: &World,
+    bytes: &[u8],
+    progress: &mut ProgressCounter,
+) -> Result<Vec<Entity>, Error> {
+    let snapshot: Snapshot = parse_ron(bytes).with_context(|_| format_err!("Invalid save"))?;
+    if snapshot.version != SNAPSHOT_VERSION {
+        return Err(format_err!(
+            "Unsupported save format version {}, expected {}",
+            snapshot.version,
+            SNAPSHOT_VERSION
+        ));
+    }
+
+    let registry = world
+        .try_fetch::<SaveRegistry>()
+        .ok_or_else(|| format_err!("No `SaveRegistry` in the world, add the `SaveBundle`"))?;
+    let mut entries = HashMap::new();
+    for name in snapshot
+        .entities
+        .iter()
+        .flat_map(|saved| saved.components.keys())
+    {
+        if entries.contains_key(name.as_str()) {
+            continue;
+        }
+        let entry = registry
+            .entry(name)
+            .ok_or_else(|| format_err!("Unknown component {:?} in save", name))?;
+        let version = snapshot.components.get(name).cloned().unwrap_or_default();
+        if !entry.can_load(version) {
+            return Err(format_err!(
+                "Component {:?} was saved with version {}, which can't be upgraded to version {}",
+                name,
+                version,
+                entry.version()
+            ));
+        }
+        entries.insert(name.as_str(), (entry, version));
+    }
+
+    // Everything is parsed before the first entity is created, so a malformed save leaves
+    // the world untouched.
+    let mut parsed = Vec::with_capacity(snapshot.entities.len());
+    for saved in &snapshot.entities {
+        let mut components = Vec::with_capacity(saved.components.len());
+        for (name, data) in &saved.components {
+            let (entry, version) = entries[name.as_str()];
+            let component = entry.parse(data, version).with_context(|_| {
+                format_err!(
+                    "Failed to parse component {:?} of entity {}",
+                    name,
+                    saved.marker.id()
+                )
+            })?;
+            components.push((name, component));
+        }
+        parsed.push(components);
+    }
+
+    let created = {
+        let mut allocator = world.write_resource::<SaveMarkerAllocator>();
+        let mut markers = world.write_storage::<SaveMarker>();
+        let entities = world.entities();
+        snapshot
+            .entities
+            .iter()
+            .map(|saved| allocator.retrieve_entity(saved.marker.clone(), &mut markers, &entities))
+            .collect::<Vec<_>>()
+    };
+    let ids = snapshot
+        .entities
+        .iter()
+        .map(|saved| saved.marker.clone())
+        .zip(created.iter().cloned())
+        .collect::<HashMap<_, _>>();
+
+    for ((saved, &entity), components) in snapshot.entities.iter().zip(&created).zip(parsed) {
+        for (name, component) in components {
+            component(
+                world,
+                entity,
+                &mut |marker| ids.get(&marker).cloned(),
+                progress,
+            )
+            .with_context(|_| {
+                format_err!(
+                    "Failed to load component {:?} of entity {}",
+                    name,
+                    saved.marker.id()
+                )
+            })?;
+        }
+    }
+
+    Ok(created)
+}
+
+#[cfg(test)]
+mod tests {
+    use amethyst_core::{
+        ecs::{
+            prelude::{Builder, DispatcherBuilder, Entity, Join, World, WorldExt},
+            saveload::MarkedBuilder,
+        },
+        math::Vector3,
+        Parent, SystemBundle, Transform,
+    };
+
+    use crate::ProgressCounter;
+
+    use super::{load_world, save_world, SaveBundle, SaveMarker};
+
+    fn setup() -> World {
+        let mut world = World::new();
+        SaveBundle::new()
+            .with::<Transform>("transform")
+            .with::<Parent>("parent")
+            .build(&mut world, &mut DispatcherBuilder::new())
+            .unwrap();
+        world
+    }
+
+    fn spawn(world: &mut World, x: f32, parent: Option<Entity>) -> Entity {
+        let mut builder = world
+            .create_entity()
+            .with(Transform::from(Vector3::new(x, 0.0, 0.0)));
+        if let Some(entity) = parent {
+            builder = builder.with(Parent { entity });
+        }
+        builder.marked::<SaveMarker>().build()
+    }
+
+    #[test]
+    fn restores_hierarchy() {
+        let mut world = setup();
+        let root = spawn(&mut world, 1.0, None);
+        spawn(&mut world, 2.0, Some(root));
+        world
+            .create_entity()
+            .with(Transform::from(Vector3::new(3.0, 0.0, 0.0)))
+            .build();
+        let bytes = save_world(&world, |_| true).unwrap();
+
+        let restored = setup();
+        let entities = load_world(&restored, &bytes, &mut ProgressCounter::new()).unwrap();
+        assert_eq!(2, entities.len());
+
+        let transforms = restored.read_storage::<Transform>();
+        let parents = restored.read_storage::<Parent>();
+        let (child, parent) = (&transforms, &parents).join().next().unwrap();
+        assert_eq!(2.0, child.translation().x);
+        assert_eq!(1.0, transforms.get(parent.entity).unwrap().translation().x);
+        assert_eq!(2, transforms.join().count());
+    }
+
+    #[test]
+    fn rejects_unsaved_parents() {
+        let mut world = setup();
+        let root = world.create_entity().build();
+        spawn(&mut world, 0.0, Some(root));
+        assert!(save_world(&world, |_| true).is_err());
+    }
+
+    #[test]
+    fn rejects_newer_versions_and_unknown_components() {
+        let mut world = setup();
+        spawn(&mut world, 1.0, None);
+        let save = String::from_utf8(save_world(&world, |_| true).unwrap()).unwrap();
+
+        let newer = save.replacen("version: 1", "version: 2", 1);
+        let unknown = save.replace("\"transform\"", "\"health\"");
+        for bytes in &[newer, unknown] {
+            let restored = setup();
+            assert!(load_world(&restored, bytes.as_bytes(), &mut ProgressCounter::new()).is_err());
+            assert_eq!(0, restored.read_storage::<SaveMarker>().join().count());
+        }
+    }
+
+    #[test]
+    fn malformed_saves_create_no_entities() {
+        let mut world = setup();
+        let root = spawn(&mut world, 1.0, None);
+        spawn(&mut world, 2.0, Some(root));
+        let save = String::from_utf8(save_world(&world, |_| true).unwrap()).unwrap();
+
+        // Only the data of the second entity is broken
+        let malformed = save.replacen("\"parent\": \"", "\"parent\": \"broken", 1);
+        assert_ne!(save, malformed);
+        let restored = setup();
+        assert!(load_world(&restored, malformed.as_bytes(), &mut ProgressCounter::new()).is_err());
+        assert_eq!(0, restored.entities().join().count());
+    }
+}
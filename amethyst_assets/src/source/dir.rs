@@ -7,7 +7,7 @@ use std::{
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
 
-use amethyst_error::{format_err, Error, ResultExt};
+use amethyst_error::{format_err, keys, Error, ResultExt};
 
 use crate::{error, source::Source};
 
@@ -48,7 +48,8 @@ impl Source for Directory {
         let path = self.path(path);
 
         metadata(&path)
-            .with_context(|_| format_err!("Failed to fetch metadata for {:?}", path))?
+            .with_context(|_| format_err!("Failed to fetch metadata for {:?}", path))
+            .with_meta(keys::PATH, || path.clone())?
             .modified()
             .with_context(|_| format_err!("Could not get modification time"))?
             .duration_since(UNIX_EPOCH)
@@ -68,9 +69,11 @@ impl Source for Directory {
         let mut v = Vec::new();
         let mut file = File::open(&path)
             .with_context(|_| format_err!("Failed to open file {:?}", path))
+            .with_meta(keys::PATH, || path.clone())
             .with_context(|_| error::Error::Source)?;
         file.read_to_end(&mut v)
             .with_context(|_| format_err!("Failed to read file {:?}", path))
+            .with_meta(keys::PATH, || path.clone())
             .with_context(|_| error::Error::Source)?;
 
         Ok(v)
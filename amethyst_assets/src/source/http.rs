@@ -0,0 +1,430 @@
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    future::Future,
+    io::Read,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::warn;
+use parking_lot::{Condvar, Mutex};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+use amethyst_error::{format_err, Error};
+
+use crate::{
+    error,
+    source::{LoadFuture, Source},
+    ByteProgress,
+};
+
+/// Source fetching assets over HTTP, relative to a base URL.
+///
+/// `load_async` queues the request for a small set of download threads shared by the clones
+/// of the source, so the `Loader` keeps its worker threads free while waiting for the server.
+/// At most `with_max_connections` requests run at once, and the threads exit once the queue is
+/// empty. Requests that time out or fail to connect are queued again to be retried a few times
+/// after a short delay; error responses such as `404 Not Found` are not retried. If the server
+/// sends a `Content-Length`, the download is reported to the `ByteProgress` of the
+/// `ProgressCounter` the asset is loaded with.
+///
+/// Assets fetched over HTTP are never hot-reloaded.
+///
+/// ```rust,no_run
+/// # use std::sync::Arc;
+/// # use amethyst_assets::{HttpSource, Loader};
+/// # let pool = Arc::new(rayon::ThreadPoolBuilder::new().build().unwrap());
+/// let mut loader = Loader::new("assets", pool);
+/// loader.add_source("cdn", HttpSource::new("https://example.com/assets"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct HttpSource {
+    base_url: String,
+    retries: u32,
+    timeout: Duration,
+    max_connections: usize,
+    queue: Arc<Queue>,
+}
+
+impl HttpSource {
+    /// Creates a new source fetching assets relative to `base_url`.
+    pub fn new<S>(base_url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        HttpSource {
+            base_url: base_url.into(),
+            retries: 2,
+            timeout: Duration::from_secs(30),
+            max_connections: 4,
+            queue: Arc::new(Queue::default()),
+        }
+    }
+
+    /// Sets how often a request which timed out or failed to connect is retried.
+    /// Defaults to 2.
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Sets the timeout of a single request. Defaults to 30 seconds.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets how many requests of `load_async` run at once, each on a download thread of its
+    /// own. Defaults to 4.
+    pub fn with_max_connections(mut self, max_connections: usize) -> Self {
+        self.max_connections = max_connections.max(1);
+        self
+    }
+
+    /// Returns the URL an asset is fetched from.
+    pub fn url(&self, path: &str) -> String {
+        format!(
+            "{}/{}",
+            self.base_url.trim_end_matches('/'),
+            path.trim_start_matches('/')
+        )
+    }
+
+    fn fetch(&self, path: &str, progress: Option<&ByteProgress>) -> Result<Vec<u8>, Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("http_fetch_asset");
+
+        let url = self.url(path);
+        let mut attempt = 0;
+        loop {
+            match self.fetch_once(&url, progress) {
+                Ok(bytes) => return Ok(bytes),
+                Err((e, true)) if attempt < self.retries => {
+                    attempt += 1;
+                    warn!("Retrying {} ({}/{}): {}", url, attempt, self.retries, e);
+                    thread::sleep(retry_delay(attempt));
+                }
+                Err((e, _)) => return Err(fetch_error(e, &url)),
+            }
+        }
+    }
+
+    /// Makes one attempt at the request of `job`, and queues it again if it may be retried.
+    fn run(&self, mut job: Job) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("http_fetch_asset");
+
+        let url = self.url(&job.path);
+        let result = match self.fetch_once(&url, job.progress.as_ref()) {
+            Ok(bytes) => Ok(bytes),
+            Err((e, true)) if job.attempt < self.retries => {
+                job.attempt += 1;
+                warn!("Retrying {} ({}/{}): {}", url, job.attempt, self.retries, e);
+                job.not_before = Instant::now() + retry_delay(job.attempt);
+                self.queue.jobs.lock().pending.push(job);
+                return;
+            }
+            Err((e, _)) => Err(fetch_error(e, &url)),
+        };
+        job.state.lock().complete(result);
+    }
+
+    /// Runs the queued requests until there are none left.
+    fn work(&self) {
+        let mut jobs = self.queue.jobs.lock();
+        loop {
+            let now = Instant::now();
+            if let Some(index) = jobs.pending.iter().position(|job| job.not_before <= now) {
+                let job = jobs.pending.remove(index);
+                drop(jobs);
+                self.run(job);
+                jobs = self.queue.jobs.lock();
+            } else if let Some(next) = jobs.pending.iter().map(|job| job.not_before).min() {
+                // Only retries are left, wait for the first one or for a new request.
+                jobs.idle += 1;
+                self.queue.ready.wait_for(&mut jobs, next - now);
+                jobs.idle -= 1;
+            } else {
+                jobs.workers -= 1;
+                return;
+            }
+        }
+    }
+
+    /// Fetches `url` once. On error, also returns whether the request may be retried.
+    fn fetch_once(
+        &self,
+        url: &str,
+        progress: Option<&ByteProgress>,
+    ) -> Result<Vec<u8>, (Error, bool)> {
+        let response = ureq::get(url).timeout(self.timeout).call();
+        if let Some(error) = response.synthetic_error() {
+            return Err((format_err!("{}", error), true));
+        }
+        if response.error() {
+            return Err((
+                format_err!("HTTP {} {}", response.status(), response.status_text()),
+                false,
+            ));
+        }
+
+        let len = response
+            .header("Content-Length")
+            .and_then(|len| len.parse::<usize>().ok());
+        if let (Some(progress), Some(len)) = (progress, len) {
+            progress.add_total(len);
+        }
+
+        let mut reader = response.into_reader();
+        let mut bytes = Vec::with_capacity(len.unwrap_or(0));
+        let mut buf = [0; 16 * 1024];
+        loop {
+            let read = reader.read(&mut buf).map_err(|e| (Error::from(e), true))?;
+            if read == 0 {
+                break;
+            }
+            bytes.extend_from_slice(&buf[..read]);
+            if let (Some(progress), Some(_)) = (progress, len) {
+                progress.add_loaded(read);
+            }
+        }
+
+        Ok(bytes)
+    }
+}
+
+impl Source for HttpSource {
+    fn modified(&self, _path: &str) -> Result<u64, Error> {
+        Ok(0)
+    }
+
+    fn load(&self, path: &str) -> Result<Vec<u8>, Error> {
+        self.fetch(path, None)
+    }
+
+    fn load_async(&self, path: &str, progress: Option<ByteProgress>) -> LoadFuture {
+        let state = Arc::new(Mutex::new(FetchState::default()));
+
+        let mut jobs = self.queue.jobs.lock();
+        jobs.pending.push(Job {
+            path: path.to_owned(),
+            progress,
+            attempt: 0,
+            not_before: Instant::now(),
+            state: state.clone(),
+        });
+        if jobs.idle > 0 || jobs.workers >= self.max_connections {
+            self.queue.ready.notify_one();
+        } else {
+            let source = self.clone();
+            let spawned = thread::Builder::new()
+                .name("amethyst-http-source".into())
+                .spawn(move || source.work());
+            match spawned {
+                Ok(_) => jobs.workers += 1,
+                Err(e) if jobs.workers == 0 => {
+                    jobs.pending.pop();
+                    state.lock().complete(Err(Error::from(e)));
+                }
+                // The running download threads pick the request up.
+                Err(_) => {}
+            }
+        }
+
+        Box::pin(Fetch { state })
+    }
+}
+
+/// Returns how long to wait before the given retry of a request.
+fn retry_delay(attempt: u32) -> Duration {
+    Duration::from_millis(250 * u64::from(attempt))
+}
+
+fn fetch_error(cause: Error, url: &str) -> Error {
+    Error::from(error::Error::Source)
+        .with_source(format_err!("Failed to fetch {}", url).with_source(cause))
+}
+
+/// Requests of `load_async` waiting for a download thread.
+#[derive(Default)]
+struct Queue {
+    jobs: Mutex<Jobs>,
+    /// Notified when a request is queued while download threads are waiting for retries.
+    ready: Condvar,
+}
+
+impl Debug for Queue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let jobs = self.jobs.lock();
+        f.debug_struct("Queue")
+            .field("pending", &jobs.pending.len())
+            .field("workers", &jobs.workers)
+            .finish()
+    }
+}
+
+#[derive(Default)]
+struct Jobs {
+    pending: Vec<Job>,
+    /// Number of running download threads.
+    workers: usize,
+    /// Number of download threads waiting for a retry to be due.
+    idle: usize,
+}
+
+struct Job {
+    path: String,
+    progress: Option<ByteProgress>,
+    /// Number of retries made so far.
+    attempt: u32,
+    not_before: Instant,
+    state: Arc<Mutex<FetchState>>,
+}
+
+#[derive(Default)]
+struct FetchState {
+    result: Option<Result<Vec<u8>, Error>>,
+    waker: Option<Waker>,
+}
+
+impl FetchState {
+    fn complete(&mut self, result: Result<Vec<u8>, Error>) {
+        self.result = Some(result);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Future completed by the download thread performing the request.
+struct Fetch {
+    state: Arc<Mutex<FetchState>>,
+}
+
+impl Future for Fetch {
+    type Output = Result<Vec<u8>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock();
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        ptr,
+        sync::Arc,
+        task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+        thread,
+        time::Duration,
+    };
+
+    use amethyst_error::Error;
+    use parking_lot::Mutex;
+
+    use super::HttpSource;
+    use crate::source::{LoadFuture, Source};
+
+    /// Serves `body` to every request, after dropping the first `drop_first` connections
+    /// without an answer. Returns the base URL and the highest number of requests served
+    /// at once.
+    fn serve(body: &'static str, drop_first: usize) -> (String, Arc<Mutex<Served>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let served = Arc::new(Mutex::new(Served::default()));
+        let counts = served.clone();
+        thread::spawn(move || {
+            for (index, stream) in listener.incoming().enumerate() {
+                let mut stream = stream.unwrap();
+                if index < drop_first {
+                    continue;
+                }
+                let counts = counts.clone();
+                thread::spawn(move || {
+                    {
+                        let mut counts = counts.lock();
+                        counts.active += 1;
+                        counts.max_active = counts.max_active.max(counts.active);
+                    }
+                    let mut request = [0; 1024];
+                    let _ = stream.read(&mut request);
+                    thread::sleep(Duration::from_millis(50));
+                    counts.lock().active -= 1;
+                    write!(
+                        stream,
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                    .unwrap();
+                });
+            }
+        });
+        (url, served)
+    }
+
+    #[derive(Default)]
+    struct Served {
+        active: usize,
+        max_active: usize,
+    }
+
+    fn wait(mut future: LoadFuture) -> Result<Vec<u8>, Error> {
+        fn clone(_: *const ()) -> RawWaker {
+            RawWaker::new(ptr::null(), &VTABLE)
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+        let waker = unsafe { Waker::from_raw(clone(ptr::null())) };
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(result) = future.as_mut().poll(&mut cx) {
+                return result;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn downloads_are_limited_to_max_connections() {
+        let (url, served) = serve("asset", 0);
+        let source = HttpSource::new(url).with_max_connections(2);
+
+        let fetches = (0..6)
+            .map(|i| source.load_async(&format!("asset_{}.ron", i), None))
+            .collect::<Vec<_>>();
+        for fetch in fetches {
+            assert_eq!(b"asset".to_vec(), wait(fetch).unwrap());
+        }
+        assert!(served.lock().max_active <= 2);
+    }
+
+    #[test]
+    fn dropped_connections_are_retried() {
+        let (url, _) = serve("asset", 1);
+        let source = HttpSource::new(url).with_retries(1);
+        assert_eq!(
+            b"asset".to_vec(),
+            wait(source.load_async("asset.ron", None)).unwrap()
+        );
+
+        let (url, _) = serve("asset", 2);
+        let source = HttpSource::new(url).with_retries(1);
+        assert!(wait(source.load_async("asset.ron", None)).is_err());
+    }
+}
@@ -1,11 +1,27 @@
+use std::{future::Future, pin::Pin, sync::Arc};
+
 use amethyst_error::Error;
+use parking_lot::Mutex;
+
+#[cfg(feature = "http")]
+pub use self::http::HttpSource;
+pub use self::{
+    dir::Directory,
+    pack::{build_pack, Pack},
+};
 
-pub use self::dir::Directory;
+use crate::ByteProgress;
 
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
 
 mod dir;
+#[cfg(feature = "http")]
+mod http;
+mod pack;
+
+/// The bytes of an asset which are being fetched by `Source::load_async`.
+pub type LoadFuture = Pin<Box<dyn Future<Output = Result<Vec<u8>, Error>> + Send>>;
 
 /// A trait for asset sources, which provides
 /// methods for loading bytes.
@@ -32,4 +48,63 @@ pub trait Source: Send + Sync + 'static {
 
         Ok((b, m))
     }
+
+    /// Starts loading the bytes given a path, for sources which have to wait for them,
+    /// e.g. on the network.
+    ///
+    /// The `Loader` polls the returned future without blocking one of its worker threads,
+    /// and only imports the asset once the bytes have arrived. Sources that know how large
+    /// the asset is can report the bytes fetched so far to `progress`.
+    ///
+    /// The default implementation calls `load` right away and returns its result.
+    fn load_async(&self, path: &str, _progress: Option<ByteProgress>) -> LoadFuture {
+        let result = self.load(path);
+        Box::pin(async move { result })
+    }
+}
+
+/// Source handing out bytes which have been fetched ahead of time, which
+/// defers to the source they were fetched from once they have been taken.
+pub(crate) struct Prefetched {
+    path: String,
+    bytes: Mutex<Option<Result<Vec<u8>, Error>>>,
+    source: Arc<dyn Source>,
+}
+
+impl Prefetched {
+    pub(crate) fn new(
+        path: String,
+        bytes: Result<Vec<u8>, Error>,
+        source: Arc<dyn Source>,
+    ) -> Self {
+        Prefetched {
+            path,
+            bytes: Mutex::new(Some(bytes)),
+            source,
+        }
+    }
+}
+
+impl Source for Prefetched {
+    fn modified(&self, path: &str) -> Result<u64, Error> {
+        self.source.modified(path)
+    }
+
+    fn load(&self, path: &str) -> Result<Vec<u8>, Error> {
+        if path == self.path {
+            if let Some(bytes) = self.bytes.lock().take() {
+                return bytes;
+            }
+        }
+        self.source.load(path)
+    }
+
+    fn load_async(&self, path: &str, progress: Option<ByteProgress>) -> LoadFuture {
+        if path == self.path {
+            if let Some(bytes) = self.bytes.lock().take() {
+                return Box::pin(async move { bytes });
+            }
+        }
+        self.source.load_async(path, progress)
+    }
 }
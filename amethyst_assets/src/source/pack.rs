@@ -0,0 +1,276 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use fnv::FnvHashMap;
+use parking_lot::Mutex;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+use amethyst_error::{format_err, Error, ResultExt};
+
+use crate::{error, source::Source};
+
+const MAGIC: &[u8; 4] = b"AMPK";
+const VERSION: u32 = 1;
+
+/// An entry of the pack index.
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+    offset: u64,
+    len: u64,
+    modified: u64,
+}
+
+/// Pack file source, reading assets from a single archive file.
+///
+/// Only the index of the archive is read when it is opened; the assets themselves are
+/// read on demand. Use `build_pack` to create a pack from a directory of assets, e.g. as
+/// part of a release build:
+///
+/// ```rust,no_run
+/// # use std::sync::Arc;
+/// # use amethyst_assets::{build_pack, Loader, Pack};
+/// # fn main() -> Result<(), amethyst_error::Error> {
+/// # let pool = Arc::new(rayon::ThreadPoolBuilder::new().build().unwrap());
+/// build_pack("assets", "assets.pack")?;
+///
+/// let mut loader = Loader::new("assets", pool);
+/// loader.add_source("pack", Pack::open("assets.pack")?);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// The modification times of the packed files are kept in the index, so hot reloading only
+/// happens when the pack has been rebuilt with newer files.
+#[derive(Debug)]
+pub struct Pack {
+    path: PathBuf,
+    file: Mutex<File>,
+    entries: FnvHashMap<String, Entry>,
+}
+
+impl Pack {
+    /// Opens the pack file at the given location and reads its index.
+    pub fn open<P>(path: P) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        let mut file = File::open(&path)
+            .with_context(|_| format_err!("Failed to open pack file {:?}", path))?;
+        let entries = read_index(&mut file)
+            .with_context(|_| format_err!("Failed to read index of pack file {:?}", path))?;
+
+        Ok(Pack {
+            path,
+            file: Mutex::new(file),
+            entries,
+        })
+    }
+
+    /// Returns the names of all assets in the pack.
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.entries.keys().map(String::as_str)
+    }
+
+    fn entry(&self, path: &str) -> Result<Entry, Error> {
+        self.entries
+            .get(path)
+            .cloned()
+            .ok_or_else(|| format_err!("No asset {:?} in pack file {:?}", path, self.path))
+    }
+}
+
+impl Source for Pack {
+    fn modified(&self, path: &str) -> Result<u64, Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("pack_modified_asset");
+
+        self.entry(path).map(|entry| entry.modified)
+    }
+
+    fn load(&self, path: &str) -> Result<Vec<u8>, Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("pack_load_asset");
+
+        let entry = self.entry(path).with_context(|_| error::Error::Source)?;
+
+        let mut v = vec![0; entry.len as usize];
+        {
+            let mut file = self.file.lock();
+            file.seek(SeekFrom::Start(entry.offset))
+                .and_then(|_| file.read_exact(&mut v))
+                .with_context(|_| format_err!("Failed to read {:?} from {:?}", path, self.path))
+                .with_context(|_| error::Error::Source)?;
+        }
+
+        Ok(v)
+    }
+}
+
+/// Packs all files below the directory `dir` into a pack file at `out`, which can then be
+/// read with `Pack`.
+///
+/// Assets are named by their path relative to `dir`, using `/` as separator.
+pub fn build_pack<D, O>(dir: D, out: O) -> Result<(), Error>
+where
+    D: AsRef<Path>,
+    O: AsRef<Path>,
+{
+    let dir = dir.as_ref();
+    let out = out.as_ref();
+
+    let mut files = Vec::new();
+    collect_files(dir, String::new(), &mut files)?;
+    files.sort();
+
+    let mut index = Vec::with_capacity(files.len());
+    for (name, path) in &files {
+        let metadata =
+            fs::metadata(path).with_context(|_| format_err!("Failed to read {:?}", path))?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs());
+        index.push((name, metadata.len(), modified));
+    }
+
+    let mut writer = BufWriter::new(
+        File::create(out).with_context(|_| format_err!("Failed to create {:?}", out))?,
+    );
+    let write_err = |_: &_| format_err!("Failed to write pack file {:?}", out);
+
+    let header_len = 12
+        + index
+            .iter()
+            .map(|(name, _, _)| 4 + name.len() as u64 + 24)
+            .sum::<u64>();
+    let mut header = Vec::with_capacity(header_len as usize);
+    header.extend_from_slice(MAGIC);
+    header.extend_from_slice(&VERSION.to_le_bytes());
+    header.extend_from_slice(&(index.len() as u32).to_le_bytes());
+    let mut offset_in_pack = header_len;
+    for (name, len, modified) in &index {
+        header.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        header.extend_from_slice(name.as_bytes());
+        header.extend_from_slice(&offset_in_pack.to_le_bytes());
+        header.extend_from_slice(&len.to_le_bytes());
+        header.extend_from_slice(&modified.to_le_bytes());
+        offset_in_pack += len;
+    }
+    writer.write_all(&header).with_context(write_err)?;
+
+    for (_, path) in &files {
+        let mut file =
+            File::open(path).with_context(|_| format_err!("Failed to open {:?}", path))?;
+        std::io::copy(&mut file, &mut writer).with_context(write_err)?;
+    }
+    writer.flush().with_context(write_err)?;
+
+    Ok(())
+}
+
+fn collect_files(
+    dir: &Path,
+    prefix: String,
+    files: &mut Vec<(String, PathBuf)>,
+) -> Result<(), Error> {
+    let entries =
+        fs::read_dir(dir).with_context(|_| format_err!("Failed to read directory {:?}", dir))?;
+    for entry in entries {
+        let entry = entry.with_context(|_| format_err!("Failed to read directory {:?}", dir))?;
+        let path = entry.path();
+        let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+        if path.is_dir() {
+            collect_files(&path, format!("{}/", name), files)?;
+        } else {
+            files.push((name, path));
+        }
+    }
+
+    Ok(())
+}
+
+fn read_index(file: &mut File) -> Result<FnvHashMap<String, Entry>, Error> {
+    let mut magic = [0; 4];
+    file.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(format_err!("Not a pack file"));
+    }
+    let version = read_u32(file)?;
+    if version != VERSION {
+        return Err(format_err!("Unsupported pack file version {}", version));
+    }
+
+    let count = read_u32(file)?;
+    let mut entries = FnvHashMap::default();
+    for _ in 0..count {
+        let mut name = vec![0; read_u32(file)? as usize];
+        file.read_exact(&mut name)?;
+        let name = String::from_utf8(name)?;
+        let entry = Entry {
+            offset: read_u64(file)?,
+            len: read_u64(file)?,
+            modified: read_u64(file)?,
+        };
+        entries.insert(name, entry);
+    }
+
+    Ok(entries)
+}
+
+fn read_u32(file: &mut File) -> Result<u32, Error> {
+    let mut bytes = [0; 4];
+    file.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(file: &mut File) -> Result<u64, Error> {
+    let mut bytes = [0; 8];
+    file.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::Path, sync::Arc, thread};
+
+    use crate::source::{Directory, Source};
+
+    use super::{build_pack, Pack};
+
+    #[test]
+    fn loads_assets_from_pack_built_from_directory() {
+        let test_assets_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets");
+        let pack_path =
+            std::env::temp_dir().join(format!("amethyst_assets_test_{}.pack", std::process::id()));
+        build_pack(&test_assets_dir, &pack_path).expect("Failed to build pack");
+
+        let pack = Arc::new(Pack::open(&pack_path).expect("Failed to open pack"));
+        let directory = Directory::new(test_assets_dir);
+
+        assert_eq!(
+            directory.modified("subdir/asset").unwrap(),
+            pack.modified("subdir/asset").unwrap()
+        );
+        assert!(pack.load("missing").is_err());
+
+        let threads = (0..4)
+            .map(|_| {
+                let pack = pack.clone();
+                thread::spawn(move || pack.load("subdir/asset").unwrap())
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            assert_eq!(b"data".to_vec(), thread.join().unwrap());
+        }
+
+        fs::remove_file(pack_path).ok();
+    }
+}
@@ -1,14 +1,16 @@
 use std::{
     marker::PhantomData,
     sync::{
-        atomic::{AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc, Weak,
     },
 };
 
 use crossbeam_queue::SegQueue;
 use derivative::Derivative;
+use fnv::FnvHashMap;
 use log::{debug, error, trace, warn};
+use parking_lot::Mutex;
 use rayon::ThreadPool;
 
 use amethyst_core::{
@@ -19,14 +21,16 @@ use amethyst_core::{
     },
     SystemDesc, Time,
 };
-use amethyst_error::{Error, ResultExt};
+use amethyst_error::{keys, Error, ResultExt};
 
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
 
 use crate::{
     asset::{Asset, FormatValue, ProcessableAsset},
+    dependency::Dependencies,
     error,
+    loader::Loader,
     progress::Tracker,
     reload::{HotReloadStrategy, Reload},
 };
@@ -50,10 +54,101 @@ pub struct AssetStorage<A: Asset> {
     assets: VecStorage<(A, u32)>,
     bitset: BitSet,
     handles: Vec<Handle<A>>,
-    handle_alloc: Allocator,
-    pub(crate) processed: Arc<SegQueue<Processed<A>>>,
+    pub(crate) endpoint: StorageEndpoint<A>,
     reloads: Vec<(WeakHandle<A>, Box<dyn Reload<A::Data>>)>,
-    unused_handles: SegQueue<Handle<A>>,
+    unload_policy: UnloadPolicy,
+    unload_requested: bool,
+    unused_since: FnvHashMap<u32, u64>,
+}
+
+/// Decides when assets which are no longer referenced by any `Handle` are removed
+/// from their `AssetStorage`.
+///
+/// Assets that are still unused when they are about to be unloaded can be brought back
+/// by upgrading a `WeakHandle` to them, which makes longer policies useful for caches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnloadPolicy {
+    /// Unload assets as soon as their last handle has been dropped.
+    Immediate,
+    /// Unload assets once they have been unused for the given number of frames.
+    AfterFrames(u64),
+    /// Only unload assets when `AssetStorage::unload_unused` is called.
+    Manual,
+}
+
+impl Default for UnloadPolicy {
+    fn default() -> Self {
+        UnloadPolicy::Immediate
+    }
+}
+
+/// The parts of an `AssetStorage` needed to queue assets for loading into it
+/// from worker threads.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""))]
+pub(crate) struct StorageEndpoint<A: Asset> {
+    handle_alloc: Arc<Allocator>,
+    unused_handles: Arc<SegQueue<Handle<A>>>,
+    pub(crate) processed: Arc<SegQueue<Processed<A>>>,
+    reload_listeners: Arc<Mutex<FnvHashMap<u32, Vec<Weak<AtomicBool>>>>>,
+    names: Arc<Mutex<FnvHashMap<u32, String>>>,
+}
+
+impl<A: Asset> StorageEndpoint<A> {
+    /// Allocate a new handle.
+    pub(crate) fn allocate(&self) -> Handle<A> {
+        self.unused_handles.pop().unwrap_or_else(|_| {
+            let id = self.handle_alloc.next_id() as u32;
+            Handle {
+                id: Arc::new(id),
+                marker: PhantomData,
+            }
+        })
+    }
+
+    /// Registers a flag which is set once the asset with the given handle id has been
+    /// hot-reloaded.
+    pub(crate) fn listen_for_reload(&self, id: u32, flag: &Arc<AtomicBool>) {
+        self.reload_listeners
+            .lock()
+            .entry(id)
+            .or_insert_with(Vec::new)
+            .push(Arc::downgrade(flag));
+    }
+
+    fn notify_reloaded(&self, id: u32) {
+        if let Some(listeners) = self.reload_listeners.lock().get_mut(&id) {
+            listeners.retain(|flag| match flag.upgrade() {
+                Some(flag) => {
+                    flag.store(true, Ordering::Relaxed);
+                    true
+                }
+                None => false,
+            });
+        }
+    }
+
+    /// Records the name the asset with the given handle id is loaded from.
+    pub(crate) fn set_name(&self, id: u32, name: String) {
+        self.names.lock().insert(id, name);
+    }
+
+    /// Returns `true` if both endpoints belong to the same storage.
+    pub(crate) fn same_storage(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.processed, &other.processed)
+    }
+}
+
+impl<A: Asset> Default for StorageEndpoint<A> {
+    fn default() -> Self {
+        StorageEndpoint {
+            handle_alloc: Default::default(),
+            unused_handles: Arc::new(SegQueue::new()),
+            processed: Arc::new(SegQueue::new()),
+            reload_listeners: Default::default(),
+            names: Default::default(),
+        }
+    }
 }
 
 /// Returned by processor systems, describes the loading state of the asset.
@@ -75,17 +170,7 @@ impl<A: Asset> AssetStorage<A> {
 
     /// Allocate a new handle.
     pub(crate) fn allocate(&self) -> Handle<A> {
-        self.unused_handles
-            .pop()
-            .unwrap_or_else(|_| self.allocate_new())
-    }
-
-    fn allocate_new(&self) -> Handle<A> {
-        let id = self.handle_alloc.next_id() as u32;
-        Handle {
-            id: Arc::new(id),
-            marker: PhantomData,
-        }
+        self.endpoint.allocate()
     }
 
     /// Remove all data from asset storages, invalidating all associated handles.
@@ -95,6 +180,26 @@ impl<A: Asset> AssetStorage<A> {
         self.bitset.clear();
     }
 
+    /// Sets when assets which are no longer referenced by any `Handle` get unloaded.
+    pub fn set_unload_policy(&mut self, policy: UnloadPolicy) {
+        self.unload_policy = policy;
+    }
+
+    /// Returns when assets which are no longer referenced by any `Handle` get unloaded.
+    pub fn unload_policy(&self) -> UnloadPolicy {
+        self.unload_policy
+    }
+
+    /// Unloads all assets which are no longer referenced by any `Handle`, regardless of the
+    /// `UnloadPolicy`, e.g. after switching levels.
+    ///
+    /// The assets are removed the next time the storage is processed, so that processors
+    /// which have to release resources at a particular point, like the GPU resources of
+    /// textures and meshes, get to do so.
+    pub fn unload_unused(&mut self) {
+        self.unload_requested = true;
+    }
+
     /// When cloning an asset handle, you'll get another handle,
     /// but pointing to the same asset. If you instead want to
     /// indeed create a new asset, you can use this method.
@@ -121,6 +226,13 @@ impl<A: Asset> AssetStorage<A> {
         }
     }
 
+    /// Returns the name the asset with the given handle was loaded from, e.g. its path.
+    ///
+    /// Returns `None` for assets created from data with `Loader::load_from_data`.
+    pub fn asset_name(&self, handle: &Handle<A>) -> Option<String> {
+        self.endpoint.names.lock().get(&handle.id()).cloned()
+    }
+
     /// Get an asset from a given asset handle.
     pub fn get(&self, handle: &Handle<A>) -> Option<&A> {
         if self.bitset.contains(handle.id()) {
@@ -130,6 +242,11 @@ impl<A: Asset> AssetStorage<A> {
         }
     }
 
+    /// Get an asset from a given weak handle, without keeping it alive.
+    pub fn get_weak(&self, handle: &WeakHandle<A>) -> Option<&A> {
+        handle.upgrade().and_then(|handle| self.get(&handle))
+    }
+
     /// Get an asset version from a given asset handle.
     pub fn get_version(&self, handle: &Handle<A>) -> Option<u32> {
         if self.bitset.contains(handle.id()) {
@@ -216,6 +333,74 @@ impl<A: Asset> AssetStorage<A> {
         }
     }
 
+    /// Validates finished asset data with `f` and then drops it instead of storing an asset.
+    ///
+    /// This is meant for environments which can parse assets but can not create them, like a
+    /// headless application without a GPU. Loads are still reported to their progress trackers,
+    /// but handles to discarded assets never resolve to an asset. Hot reloads are ignored.
+    pub fn discard_processed<F>(&mut self, mut f: F)
+    where
+        F: FnMut(A::Data) -> Result<(), Error>,
+    {
+        let mut requeue = Vec::new();
+        while let Ok(processed) = self.endpoint.processed.pop() {
+            let (data, handle, name, tracker) = match processed {
+                Processed::NewAsset {
+                    data,
+                    handle,
+                    name,
+                    tracker,
+                } => (data, handle, name, tracker),
+                Processed::HotReload { .. } => continue,
+            };
+            let data = match data.and_then(|value| {
+                let complete = value.dependencies.check()?;
+                Ok((value, complete))
+            }) {
+                Ok((value, false)) => {
+                    requeue.push(Processed::NewAsset {
+                        data: Ok(value),
+                        handle,
+                        name,
+                        tracker,
+                    });
+                    continue;
+                }
+                Ok((value, true)) => Ok(value),
+                Err(e) => Err(e),
+            };
+            match data
+                .and_then(|FormatValue { data, .. }| f(data))
+                .with_context(|_| error::Error::Asset(name.clone()))
+                .with_meta(keys::ASSET_NAME, || name.clone())
+            {
+                Ok(()) => {
+                    debug!(
+                        "{:?}: Asset {:?} (handle id: {:?}) has been processed and discarded",
+                        A::NAME,
+                        name,
+                        handle,
+                    );
+                    tracker.success();
+                }
+                Err(e) => {
+                    error!(
+                        "{:?}: Asset {:?} (handle id: {:?}) could not be loaded: {}",
+                        A::NAME,
+                        name,
+                        handle,
+                        e,
+                    );
+                    tracker.fail(handle.id(), A::NAME, name, e);
+                }
+            }
+        }
+
+        for p in requeue {
+            self.endpoint.processed.push(p);
+        }
+    }
+
     /// Process finished asset data and maintain the storage.
     pub fn process<F>(
         &mut self,
@@ -234,17 +419,33 @@ impl<A: Asset> AssetStorage<A> {
     pub fn process_custom_drop<F, D>(
         &mut self,
         mut f: F,
-        mut drop_fn: D,
+        drop_fn: D,
         frame_number: u64,
         pool: &ThreadPool,
         strategy: Option<&HotReloadStrategy>,
     ) where
         D: FnMut(A),
         F: FnMut(A::Data) -> Result<ProcessingState<A>, Error>,
+    {
+        self.process_custom_drop_named(|data, _, _| f(data), drop_fn, frame_number, pool, strategy);
+    }
+
+    /// Like `process_custom_drop`, but also passes the handle the data is processed for and
+    /// the name it was loaded from to `f`, e.g. to keep track of the resources created for it.
+    pub fn process_custom_drop_named<F, D>(
+        &mut self,
+        mut f: F,
+        mut drop_fn: D,
+        frame_number: u64,
+        pool: &ThreadPool,
+        strategy: Option<&HotReloadStrategy>,
+    ) where
+        D: FnMut(A),
+        F: FnMut(A::Data, &Handle<A>, &str) -> Result<ProcessingState<A>, Error>,
     {
         {
             let mut requeue = Vec::new();
-            while let Ok(processed) = self.processed.pop() {
+            while let Ok(processed) = self.endpoint.processed.pop() {
                 let assets = &mut self.assets;
                 let bitset = &mut self.bitset;
                 let handles = &mut self.handles;
@@ -258,10 +459,33 @@ impl<A: Asset> AssetStorage<A> {
                         name,
                         tracker,
                     } => {
+                        let data = match data.and_then(|value| {
+                            let complete = value.dependencies.check()?;
+                            Ok((value, complete))
+                        }) {
+                            Ok((value, false)) => {
+                                trace!(
+                                    "{:?}: Asset {:?} (handle id: {:?}) is waiting for its dependencies",
+                                    A::NAME,
+                                    name,
+                                    handle,
+                                );
+                                requeue.push(Processed::NewAsset {
+                                    data: Ok(value),
+                                    handle,
+                                    name,
+                                    tracker,
+                                });
+                                continue;
+                            }
+                            Ok((value, true)) => Ok(value),
+                            Err(e) => Err(e),
+                        };
                         let (asset, reload_obj) = match data
-                            .map(|FormatValue { data, reload }| (data, reload))
-                            .and_then(|(d, rel)| f(d).map(|a| (a, rel)))
+                            .map(|FormatValue { data, reload, .. }| (data, reload))
+                            .and_then(|(d, rel)| f(d, &handle, &name).map(|a| (a, rel)))
                             .with_context(|_| error::Error::Asset(name.clone()))
+                            .with_meta(keys::ASSET_NAME, || name.clone())
                         {
                             Ok((ProcessingState::Loaded(x), r)) => {
                                 debug!(
@@ -298,7 +522,11 @@ impl<A: Asset> AssetStorage<A> {
                                         handle,
                                     );
                                 requeue.push(Processed::NewAsset {
-                                    data: Ok(FormatValue { data: x, reload: r }),
+                                    data: Ok(FormatValue {
+                                        data: x,
+                                        reload: r,
+                                        dependencies: Dependencies::default(),
+                                    }),
                                     handle,
                                     name,
                                     tracker,
@@ -337,10 +565,27 @@ impl<A: Asset> AssetStorage<A> {
                         name,
                         old_reload,
                     } => {
+                        let data = match data.and_then(|value| {
+                            let complete = value.dependencies.check()?;
+                            Ok((value, complete))
+                        }) {
+                            Ok((value, false)) => {
+                                requeue.push(Processed::HotReload {
+                                    data: Ok(value),
+                                    handle,
+                                    name,
+                                    old_reload,
+                                });
+                                continue;
+                            }
+                            Ok((value, true)) => Ok(value),
+                            Err(e) => Err(e),
+                        };
                         let (asset, reload_obj) = match data
-                            .map(|FormatValue { data, reload }| (data, reload))
-                            .and_then(|(d, rel)| f(d).map(|a| (a, rel)))
+                            .map(|FormatValue { data, reload, .. }| (data, reload))
+                            .and_then(|(d, rel)| f(d, &handle, &name).map(|a| (a, rel)))
                             .with_context(|_| error::Error::Asset(name.clone()))
+                            .with_meta(keys::ASSET_NAME, || name.clone())
                         {
                             Ok((ProcessingState::Loaded(x), r)) => (x, r),
                             Ok((ProcessingState::Loading(x), r)) => {
@@ -351,7 +596,11 @@ impl<A: Asset> AssetStorage<A> {
                                     handle,
                                 );
                                 requeue.push(Processed::HotReload {
-                                    data: Ok(FormatValue { data: x, reload: r }),
+                                    data: Ok(FormatValue {
+                                        data: x,
+                                        reload: r,
+                                        dependencies: Dependencies::default(),
+                                    }),
                                     handle,
                                     name,
                                     old_reload,
@@ -383,6 +632,7 @@ impl<A: Asset> AssetStorage<A> {
                         let data = unsafe { self.assets.get_mut(id) };
                         data.1 += 1;
                         drop_fn(std::mem::replace(&mut data.0, asset));
+                        self.endpoint.notify_reloaded(id);
 
                         (reload_obj, handle)
                     }
@@ -395,28 +645,52 @@ impl<A: Asset> AssetStorage<A> {
             }
 
             for p in requeue.drain(..) {
-                self.processed.push(p);
+                self.endpoint.processed.push(p);
             }
         }
 
+        let unload_unused = std::mem::replace(&mut self.unload_requested, false);
         let mut count = 0;
-        let mut skip = 0;
-        while let Some(i) = self.handles.iter().skip(skip).position(Handle::is_unique) {
+        let mut i = 0;
+        while i < self.handles.len() {
+            let id = self.handles[i].id();
+            if !self.handles[i].is_unique() {
+                // The asset may have been brought back through a `WeakHandle`.
+                if !self.unused_since.is_empty() {
+                    self.unused_since.remove(&id);
+                }
+                i += 1;
+                continue;
+            }
+
+            let unload = unload_unused
+                || match self.unload_policy {
+                    UnloadPolicy::Immediate => true,
+                    UnloadPolicy::AfterFrames(frames) => {
+                        let since = *self.unused_since.entry(id).or_insert(frame_number);
+                        frame_number.saturating_sub(since) >= frames
+                    }
+                    UnloadPolicy::Manual => false,
+                };
+            if !unload {
+                i += 1;
+                continue;
+            }
+
             count += 1;
-            // Re-normalize index
-            let i = skip + i;
-            skip = i;
-            let handle = self.handles.swap_remove(i);
-            let id = handle.id();
+            self.handles.swap_remove(i);
+            self.unused_since.remove(&id);
             unsafe {
                 let (asset, _) = self.assets.remove(id);
                 drop_fn(asset);
             }
             self.bitset.remove(id);
+            self.endpoint.reload_listeners.lock().remove(&id);
+            self.endpoint.names.lock().remove(&id);
 
             // Can't reuse old handle here, because otherwise weak handles would still be valid.
             // TODO: maybe just store u32?
-            self.unused_handles.push(Handle {
+            self.endpoint.unused_handles.push(Handle {
                 id: Arc::new(id),
                 marker: PhantomData,
             });
@@ -456,7 +730,7 @@ impl<A: Asset> AssetStorage<A> {
             );
 
             if let Some(handle) = handle {
-                let processed = self.processed.clone();
+                let processed = self.endpoint.processed.clone();
                 pool.spawn(move || {
                     let old_reload = rel.clone();
                     let data = rel.reload().with_context(|_| error::Error::Format(format));
@@ -480,10 +754,11 @@ impl<A: Asset> Default for AssetStorage<A> {
             assets: Default::default(),
             bitset: Default::default(),
             handles: Default::default(),
-            handle_alloc: Default::default(),
-            processed: Arc::new(SegQueue::new()),
+            endpoint: Default::default(),
             reloads: Default::default(),
-            unused_handles: SegQueue::new(),
+            unload_policy: Default::default(),
+            unload_requested: false,
+            unused_since: Default::default(),
         }
     }
 }
@@ -522,6 +797,9 @@ where
 {
     fn build(self, world: &mut World) -> Processor<A> {
         <Processor<A> as System<'_>>::SystemData::setup(world);
+        if let Some(loader) = world.try_fetch::<Loader>() {
+            loader.register_storage(&world.fetch::<AssetStorage<A>>());
+        }
         self
     }
 }
@@ -583,6 +861,12 @@ impl<A> Handle<A> {
         }
     }
 
+    /// Returns the number of handles to the asset, including the one kept by the
+    /// `AssetStorage` while the asset is loaded.
+    pub fn strong_count(&self) -> usize {
+        Arc::strong_count(&self.id)
+    }
+
     /// Returns `true` if this is the only handle to the asset its pointing at.
     fn is_unique(&self) -> bool {
         Arc::strong_count(&self.id) == 1
@@ -613,6 +897,9 @@ pub(crate) enum Processed<A: Asset> {
 
 /// A weak handle, which is useful if you don't directly need the asset
 /// like in caches. This way, the asset can still get dropped (if you want that).
+///
+/// The `AssetStorage` keeps a handle to every asset it holds, so a weak handle stays
+/// alive exactly as long as the asset has not been unloaded.
 #[derive(Derivative)]
 #[derivative(Clone(bound = ""), Debug(bound = ""))]
 pub struct WeakHandle<A> {
@@ -636,4 +923,83 @@ impl<A> WeakHandle<A> {
     pub fn is_dead(&self) -> bool {
         self.id.upgrade().is_none()
     }
+
+    /// Returns `true` if the asset has not been unloaded yet.
+    #[inline]
+    pub fn is_alive(&self) -> bool {
+        !self.is_dead()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::ThreadPoolBuilder;
+
+    use amethyst_core::ecs::prelude::VecStorage;
+
+    use super::{AssetStorage, Handle, UnloadPolicy};
+    use crate::Asset;
+
+    struct Level(u32);
+
+    impl Asset for Level {
+        const NAME: &'static str = "Level";
+        type Data = Level;
+        type HandleStorage = VecStorage<Handle<Level>>;
+    }
+
+    fn process(storage: &mut AssetStorage<Level>, frame_number: u64) {
+        let pool = ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        storage.process(|_| unreachable!(), frame_number, &pool, None);
+    }
+
+    #[test]
+    fn unused_assets_are_unloaded_immediately_by_default() {
+        let mut storage = AssetStorage::new();
+        let handle = storage.insert(Level(1));
+        let weak = handle.downgrade();
+        assert_eq!(2, handle.strong_count());
+
+        drop(handle);
+        process(&mut storage, 0);
+        assert!(weak.is_dead());
+        assert!(storage.get_weak(&weak).is_none());
+    }
+
+    #[test]
+    fn unused_assets_are_unloaded_after_frames() {
+        let mut storage = AssetStorage::new();
+        storage.set_unload_policy(UnloadPolicy::AfterFrames(2));
+        let weak = storage.insert(Level(1)).downgrade();
+
+        process(&mut storage, 0);
+        process(&mut storage, 1);
+        assert_eq!(1, storage.get_weak(&weak).map_or(0, |level| level.0));
+
+        // Upgrading the weak handle keeps the asset alive.
+        let handle = weak.upgrade().unwrap();
+        process(&mut storage, 2);
+        assert!(storage.contains(&handle));
+
+        drop(handle);
+        process(&mut storage, 3);
+        process(&mut storage, 4);
+        assert!(weak.is_alive());
+        process(&mut storage, 5);
+        assert!(weak.is_dead());
+    }
+
+    #[test]
+    fn manual_policy_waits_for_unload_unused() {
+        let mut storage = AssetStorage::new();
+        storage.set_unload_policy(UnloadPolicy::Manual);
+        let weak = storage.insert(Level(1)).downgrade();
+
+        process(&mut storage, 0);
+        assert!(weak.is_alive());
+
+        storage.unload_unused();
+        process(&mut storage, 1);
+        assert!(weak.is_dead());
+    }
 }
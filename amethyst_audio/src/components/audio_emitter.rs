@@ -16,7 +16,7 @@ use crate::{source::Source, DecoderError};
 #[derive(Default)]
 pub struct AudioEmitter {
     pub(crate) sinks: SmallVec<[(SpatialSink, Arc<AtomicBool>); 4]>,
-    pub(crate) sound_queue: SmallVec<[Decoder<Cursor<Source>>; 4]>,
+    pub(crate) sound_queue: SmallVec<[(Decoder<Cursor<Source>>, f32, Option<String>); 4]>,
     pub(crate) picker: Option<Box<dyn FnMut(&mut AudioEmitter) -> bool + Send + Sync>>,
 }
 
@@ -30,8 +30,26 @@ impl AudioEmitter {
 
     /// Plays an audio source from this emitter.
     pub fn play(&mut self, source: &Source) -> Result<(), DecoderError> {
-        self.sound_queue
-            .push(Decoder::new(Cursor::new(source.clone())).map_err(|_| DecoderError)?);
+        self.play_in_group(source, 1.0, None)
+    }
+
+    /// Plays an audio source from this emitter, optionally as part of a named `SoundGroup`.
+    /// A volume of 1.0 is unchanged, while 0.0 is silent.
+    ///
+    /// The `AudioSystem` will not start the sound if the group is already playing its maximum
+    /// number of voices and its `StealPolicy` is `Reject`. Without a group the sound is always
+    /// played.
+    pub fn play_in_group(
+        &mut self,
+        source: &Source,
+        volume: f32,
+        group: Option<&str>,
+    ) -> Result<(), DecoderError> {
+        self.sound_queue.push((
+            Decoder::new(Cursor::new(source.clone())).map_err(|_| DecoderError)?,
+            volume,
+            group.map(String::from),
+        ));
         Ok(())
     }
 
@@ -118,6 +136,27 @@ mod tests {
         test_play("tests/sound_test.fake", false);
     }
 
+    // test_play_in_group tests that the volume and group are queued with the sound
+    #[test]
+    fn test_play_in_group() {
+        let app_root = application_root_dir().unwrap();
+        let mut f = File::open(app_root.join("tests/sound_test.wav")).unwrap();
+        let mut buffer = Vec::new();
+        f.read_to_end(&mut buffer).unwrap();
+        let src = Source { bytes: buffer };
+
+        let mut emitter = AudioEmitter::default();
+        emitter.play_in_group(&src, 0.25, Some("impacts")).unwrap();
+        emitter.play(&src).unwrap();
+
+        let queued = emitter
+            .sound_queue
+            .iter()
+            .map(|(_, volume, group)| (*volume, group.as_ref().map(String::as_str)))
+            .collect::<Vec<_>>();
+        assert_eq!(queued, vec![(0.25, Some("impacts")), (1.0, None)]);
+    }
+
     // test_picker tests the set and clear picker functions
     #[test]
     fn test_picker() {
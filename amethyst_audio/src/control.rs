@@ -0,0 +1,152 @@
+//! Global playback control.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use rodio::{Sample, Source};
+
+use crate::output::Output;
+
+/// Resource used to pause and resume every sound at once, e.g. while a pause menu is open.
+///
+/// Pausing applies to the sinks of all `AudioEmitter`s, to one-shot sounds played through the
+/// `Output` the control was created from, and to the music `AudioSink` unless music has been
+/// flagged to keep playing with `set_music_plays_while_paused`. Playback positions are
+/// preserved, and sounds started while paused begin in the paused state.
+///
+/// The `AudioSystem` inserts this resource for its `Output`. The natural place to use it is in
+/// the `on_pause` and `on_resume` callbacks of the gameplay state:
+///
+/// ```rust,ignore
+/// use amethyst::{audio::AudioControl, prelude::*};
+///
+/// struct Gameplay;
+///
+/// impl SimpleState for Gameplay {
+///     fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+///         // Keep the background music going while the pause menu is shown.
+///         data.world
+///             .write_resource::<AudioControl>()
+///             .set_music_plays_while_paused(true);
+///     }
+///
+///     fn on_pause(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+///         data.world.write_resource::<AudioControl>().pause_all();
+///     }
+///
+///     fn on_resume(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+///         data.world.write_resource::<AudioControl>().resume_all();
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct AudioControl {
+    paused: Arc<AtomicBool>,
+    music_plays_while_paused: bool,
+}
+
+impl AudioControl {
+    /// Creates a new `AudioControl` for sounds played through the given `Output`.
+    pub fn new(output: &Output) -> Self {
+        AudioControl::with_flag(output.paused.clone())
+    }
+
+    fn with_flag(paused: Arc<AtomicBool>) -> Self {
+        AudioControl {
+            paused,
+            music_plays_while_paused: false,
+        }
+    }
+
+    /// Pauses all sounds.
+    pub fn pause_all(&mut self) {
+        self.paused.store(true, Ordering::Relaxed);
+    }
+
+    /// Resumes all sounds paused with `pause_all`.
+    pub fn resume_all(&mut self) {
+        self.paused.store(false, Ordering::Relaxed);
+    }
+
+    /// Returns true if sounds are currently paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Sets whether the music `AudioSink` keeps playing while all other sounds are paused.
+    pub fn set_music_plays_while_paused(&mut self, plays: bool) {
+        self.music_plays_while_paused = plays;
+    }
+
+    /// Returns true if the music `AudioSink` keeps playing while all other sounds are paused.
+    pub fn music_plays_while_paused(&self) -> bool {
+        self.music_plays_while_paused
+    }
+
+    /// Returns true if the music `AudioSink` should currently be paused.
+    pub(crate) fn music_paused(&self) -> bool {
+        self.is_paused() && !self.music_plays_while_paused
+    }
+}
+
+/// Wraps a source so that it outputs silence, without advancing, while `paused` is set.
+pub(crate) fn pausable<S>(source: S, paused: Arc<AtomicBool>) -> impl Source<Item = S::Item>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    let initially_paused = paused.load(Ordering::Relaxed);
+    source
+        .pausable(initially_paused)
+        .periodic_access(Duration::from_millis(5), move |src| {
+            src.set_paused(paused.load(Ordering::Relaxed));
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{atomic::AtomicBool, Arc};
+
+    use rodio::buffer::SamplesBuffer;
+
+    use super::{pausable, AudioControl};
+
+    #[test]
+    fn music_follows_the_control_unless_flagged() {
+        let mut control = AudioControl::with_flag(Arc::new(AtomicBool::new(false)));
+        assert!(!control.is_paused());
+        assert!(!control.music_paused());
+
+        control.pause_all();
+        assert!(control.is_paused());
+        assert!(control.music_paused());
+
+        control.set_music_plays_while_paused(true);
+        assert!(control.is_paused());
+        assert!(!control.music_paused());
+
+        control.resume_all();
+        assert!(!control.is_paused());
+    }
+
+    #[test]
+    fn paused_sources_output_silence_without_advancing() {
+        let paused = Arc::new(AtomicBool::new(false));
+        let mut control = AudioControl::with_flag(paused.clone());
+        // One update every 5 samples at 1000 Hz.
+        let samples = (1..=20).map(|s| s as f32).collect::<Vec<_>>();
+        let mut source = pausable(SamplesBuffer::new(1, 1000, samples), paused);
+
+        let mut take = |n| source.by_ref().take(n).collect::<Vec<f32>>();
+        assert_eq!(vec![1.0, 2.0, 3.0, 4.0, 5.0], take(5));
+        control.pause_all();
+        assert_eq!(vec![0.0; 5], take(5));
+        control.resume_all();
+        assert_eq!(vec![6.0, 7.0, 8.0, 9.0, 10.0], take(5));
+    }
+}
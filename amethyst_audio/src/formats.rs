@@ -66,3 +66,26 @@ impl Format<AudioData> for Mp3Format {
         Ok(AudioData(bytes))
     }
 }
+
+/// Returns a `SignatureRegistry` recognizing wav, ogg, flac and mp3 files, to be used
+/// with `Loader::load_auto`.
+pub fn audio_signatures() -> SignatureRegistry<AudioData> {
+    SignatureRegistry::new()
+        .with(
+            "WAV",
+            WavFormat,
+            vec![Signature::new(b"RIFF").and_at(8, b"WAVE")],
+        )
+        .with("OGG", OggFormat, vec![Signature::new(b"OggS")])
+        .with("FLAC", FlacFormat, vec![Signature::new(b"fLaC")])
+        .with(
+            "MP3",
+            Mp3Format,
+            vec![
+                Signature::new(b"ID3"),
+                Signature::new(b"\xff\xfb"),
+                Signature::new(b"\xff\xf3"),
+                Signature::new(b"\xff\xf2"),
+            ],
+        )
+}
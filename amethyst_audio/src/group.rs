@@ -0,0 +1,285 @@
+//! Polyphony limits for groups of sounds.
+
+use std::{
+    cmp::Ordering as CmpOrdering,
+    collections::HashMap,
+    iter::Iterator,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use log::debug;
+use rodio::{Sample, Source};
+
+/// What to do when a sound is started in a group that is already playing its maximum
+/// number of voices.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StealPolicy {
+    /// Drop the new sound and leave the playing voices alone.
+    Reject,
+    /// Stop the voice that started playing first to make room for the new sound.
+    StealOldest,
+    /// Stop the voice that was started with the lowest volume to make room for the new sound.
+    /// Ties are broken by stopping the oldest of them.
+    StealQuietest,
+}
+
+impl Default for StealPolicy {
+    fn default() -> Self {
+        StealPolicy::StealOldest
+    }
+}
+
+/// Limits how many sounds belonging to a group may play at the same time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SoundGroup {
+    /// Maximum number of voices of this group that may be playing at once.
+    pub max_voices: usize,
+    /// How to make room once `max_voices` is reached.
+    pub policy: StealPolicy,
+}
+
+impl SoundGroup {
+    /// Creates a new `SoundGroup` playing at most `max_voices` sounds at once.
+    pub fn new(max_voices: usize, policy: StealPolicy) -> Self {
+        SoundGroup { max_voices, policy }
+    }
+}
+
+/// A single playing sound, as tracked by `SoundGroups`.
+#[derive(Debug)]
+struct Voice {
+    id: u64,
+    volume: f32,
+    ended: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Voice {
+    fn is_active(&self) -> bool {
+        !self.ended.load(Ordering::Relaxed) && !self.stop.load(Ordering::Relaxed)
+    }
+}
+
+/// Signals shared between `SoundGroups` and the source a voice is playing.
+#[derive(Clone, Debug)]
+pub(crate) struct VoiceSignals {
+    /// Set by the playing source once it has run out of samples.
+    pub(crate) ended: Arc<AtomicBool>,
+    /// Set by `SoundGroups` when the voice has been stolen.
+    pub(crate) stop: Arc<AtomicBool>,
+}
+
+/// Resource holding the named sound groups and the voices currently playing in them.
+///
+/// Sounds that are played without a group are never limited. Sounds played in a group that
+/// has not been registered with `insert` are not limited either, but are still counted.
+#[derive(Debug, Default)]
+pub struct SoundGroups {
+    groups: HashMap<String, SoundGroup>,
+    voices: HashMap<String, Vec<Voice>>,
+    next_id: u64,
+}
+
+impl SoundGroups {
+    /// Creates an empty set of sound groups.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Registers or replaces the group with the given name.
+    pub fn insert<N: Into<String>>(&mut self, name: N, group: SoundGroup) {
+        self.groups.insert(name.into(), group);
+    }
+
+    /// Removes the group with the given name. Voices that are already playing keep playing.
+    pub fn remove(&mut self, name: &str) -> Option<SoundGroup> {
+        self.groups.remove(name)
+    }
+
+    /// Returns the settings of the group with the given name.
+    pub fn get(&self, name: &str) -> Option<&SoundGroup> {
+        self.groups.get(name)
+    }
+
+    /// Returns the number of voices currently playing in the given group.
+    pub fn active_voices(&self, name: &str) -> usize {
+        self.voices
+            .get(name)
+            .map_or(0, |voices| voices.iter().filter(|v| v.is_active()).count())
+    }
+
+    /// Returns the number of voices currently playing in each group that has played a sound.
+    pub fn stats(&self) -> impl Iterator<Item = (&str, usize)> + '_ {
+        self.voices.iter().map(|(name, voices)| {
+            (
+                name.as_str(),
+                voices.iter().filter(|v| v.is_active()).count(),
+            )
+        })
+    }
+
+    /// Asks for a new voice in the given group, stealing a playing voice if the group is full
+    /// and its policy allows it.
+    ///
+    /// Returns `None` if the sound must not be played.
+    pub(crate) fn admit(&mut self, name: &str, volume: f32) -> Option<VoiceSignals> {
+        let voices = self.voices.entry(name.to_owned()).or_insert_with(Vec::new);
+        voices.retain(Voice::is_active);
+
+        if let Some(group) = self.groups.get(name) {
+            while voices.len() >= group.max_voices {
+                let victim = match group.policy {
+                    StealPolicy::Reject => None,
+                    // Voices are kept in the order they were started.
+                    StealPolicy::StealOldest => Some(0),
+                    StealPolicy::StealQuietest => voices
+                        .iter()
+                        .enumerate()
+                        .min_by(|(_, a), (_, b)| {
+                            a.volume
+                                .partial_cmp(&b.volume)
+                                .unwrap_or(CmpOrdering::Equal)
+                        })
+                        .map(|(i, _)| i),
+                };
+                match victim {
+                    Some(i) if i < voices.len() => {
+                        let stolen = voices.remove(i);
+                        debug!("Stealing voice {} from sound group `{}`", stolen.id, name);
+                        stolen.stop.store(true, Ordering::Relaxed);
+                    }
+                    _ => {
+                        debug!("Sound group `{}` is full, rejecting new sound", name);
+                        return None;
+                    }
+                }
+            }
+        }
+
+        let signals = VoiceSignals {
+            ended: Arc::new(AtomicBool::new(false)),
+            stop: Arc::new(AtomicBool::new(false)),
+        };
+        voices.push(Voice {
+            id: self.next_id,
+            volume,
+            ended: signals.ended.clone(),
+            stop: signals.stop.clone(),
+        });
+        self.next_id += 1;
+        Some(signals)
+    }
+}
+
+// Wraps a source and ends it early once the given flag has been set.
+pub(crate) struct StopSignalSource<I: Source>
+where
+    <I as Iterator>::Item: Sample,
+{
+    input: I,
+    stop: Arc<AtomicBool>,
+}
+
+impl<I: Source> StopSignalSource<I>
+where
+    <I as Iterator>::Item: Sample,
+{
+    pub(crate) fn new(input: I, stop: Arc<AtomicBool>) -> StopSignalSource<I> {
+        StopSignalSource { input, stop }
+    }
+}
+
+impl<I: Source> Iterator for StopSignalSource<I>
+where
+    <I as Iterator>::Item: Sample,
+{
+    type Item = <I as Iterator>::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.stop.load(Ordering::Relaxed) {
+            None
+        } else {
+            self.input.next()
+        }
+    }
+}
+
+impl<I: Source> Source for StopSignalSource<I>
+where
+    <I as Iterator>::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use super::{SoundGroup, SoundGroups, StealPolicy};
+
+    #[test]
+    fn ungrouped_names_are_unlimited() {
+        let mut groups = SoundGroups::new();
+        for _ in 0..32 {
+            assert!(groups.admit("impacts", 1.0).is_some());
+        }
+        assert_eq!(groups.active_voices("impacts"), 32);
+    }
+
+    #[test]
+    fn reject_keeps_playing_voices() {
+        let mut groups = SoundGroups::new();
+        groups.insert("impacts", SoundGroup::new(2, StealPolicy::Reject));
+        let first = groups.admit("impacts", 1.0).unwrap();
+        let _second = groups.admit("impacts", 1.0).unwrap();
+        assert!(groups.admit("impacts", 1.0).is_none());
+        assert!(!first.stop.load(Ordering::Relaxed));
+        assert_eq!(groups.active_voices("impacts"), 2);
+
+        // Once a voice has ended there is room again.
+        first.ended.store(true, Ordering::Relaxed);
+        assert!(groups.admit("impacts", 1.0).is_some());
+    }
+
+    #[test]
+    fn steal_oldest() {
+        let mut groups = SoundGroups::new();
+        groups.insert("impacts", SoundGroup::new(2, StealPolicy::StealOldest));
+        let first = groups.admit("impacts", 1.0).unwrap();
+        let second = groups.admit("impacts", 1.0).unwrap();
+        assert!(groups.admit("impacts", 1.0).is_some());
+        assert!(first.stop.load(Ordering::Relaxed));
+        assert!(!second.stop.load(Ordering::Relaxed));
+        assert_eq!(groups.active_voices("impacts"), 2);
+    }
+
+    #[test]
+    fn steal_quietest() {
+        let mut groups = SoundGroups::new();
+        groups.insert("impacts", SoundGroup::new(2, StealPolicy::StealQuietest));
+        let loud = groups.admit("impacts", 1.0).unwrap();
+        let quiet = groups.admit("impacts", 0.2).unwrap();
+        assert!(groups.admit("impacts", 0.5).is_some());
+        assert!(!loud.stop.load(Ordering::Relaxed));
+        assert!(quiet.stop.load(Ordering::Relaxed));
+        assert_eq!(groups.stats().collect::<Vec<_>>(), vec![("impacts", 2)]);
+    }
+}
@@ -11,7 +11,9 @@
 pub use self::{
     bundle::AudioBundle,
     components::*,
-    formats::{FlacFormat, Mp3Format, OggFormat, WavFormat},
+    control::AudioControl,
+    formats::{audio_signatures, FlacFormat, Mp3Format, OggFormat, WavFormat},
+    group::{SoundGroup, SoundGroups, StealPolicy},
     sink::AudioSink,
     source::{Source, SourceHandle},
     systems::*,
@@ -26,8 +28,10 @@ pub mod output;
 
 mod bundle;
 mod components;
+mod control;
 mod end_signal;
 mod formats;
+mod group;
 mod sink;
 mod source;
 mod systems;
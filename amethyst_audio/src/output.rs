@@ -4,7 +4,10 @@
 use std::{
     fmt::{Debug, Formatter, Result as FmtResult},
     io::Cursor,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
 };
 
 use cpal::traits::DeviceTrait;
@@ -16,7 +19,14 @@ use rodio::{
 
 use amethyst_core::ecs::World;
 
-use crate::{sink::AudioSink, source::Source, DecoderError};
+use crate::{
+    control::{pausable, AudioControl},
+    end_signal::EndSignalSource,
+    group::{SoundGroups, StopSignalSource},
+    sink::AudioSink,
+    source::Source,
+    DecoderError,
+};
 
 /// A speaker(s) through which audio can be played.
 ///
@@ -24,6 +34,8 @@ use crate::{sink::AudioSink, source::Source, DecoderError};
 #[derive(Clone)]
 pub struct Output {
     pub(crate) device: Arc<Device>,
+    /// Shared with the `AudioControl` created for this output.
+    pub(crate) paused: Arc<AtomicBool>,
 }
 
 /// Convenience method for opening the default output device.
@@ -34,14 +46,19 @@ pub struct Output {
 impl Default for Output {
     fn default() -> Self {
         default_output_device()
-            .map(|device| Output {
-                device: Arc::new(device),
-            })
+            .map(Output::from_device)
             .expect("No default output device")
     }
 }
 
 impl Output {
+    fn from_device(device: Device) -> Self {
+        Output {
+            device: Arc::new(device),
+            paused: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
     /// Gets the name of the output
     pub fn name(&self) -> String {
         self.device.name().unwrap_or_else(|e| {
@@ -84,11 +101,89 @@ impl Output {
     ) -> Result<(), DecoderError> {
         let sink = Sink::new(&self.device);
         for _ in 0..n {
-            sink.append(
+            sink.append(pausable(
                 Decoder::new(Cursor::new(source.clone()))
                     .map_err(|_| DecoderError)?
                     .amplify(volume),
-            );
+                self.paused.clone(),
+            ));
+        }
+        sink.detach();
+        Ok(())
+    }
+
+    /// Play a sound once as part of the named `SoundGroup`. A volume of 1.0 is unchanged, while
+    /// 0.0 is silent.
+    ///
+    /// The sound is dropped if the group is full and its `StealPolicy` is `Reject`.
+    /// This may silently fail, in order to get error information use `try_play_once_in_group`.
+    pub fn play_once_in_group(
+        &self,
+        source: &Source,
+        volume: f32,
+        groups: &mut SoundGroups,
+        group: &str,
+    ) {
+        if let Err(err) = self.try_play_n_times_in_group(source, volume, 1, groups, group) {
+            error!("An error occurred while trying to play a sound: {:?}", err);
+        }
+    }
+
+    /// Play a sound once as part of the named `SoundGroup`. A volume of 1.0 is unchanged, while
+    /// 0.0 is silent.
+    ///
+    /// The sound is dropped if the group is full and its `StealPolicy` is `Reject`.
+    /// This will return an Error if the loaded audio file in source could not be decoded.
+    pub fn try_play_once_in_group(
+        &self,
+        source: &Source,
+        volume: f32,
+        groups: &mut SoundGroups,
+        group: &str,
+    ) -> Result<(), DecoderError> {
+        self.try_play_n_times_in_group(source, volume, 1, groups, group)
+    }
+
+    /// Play a sound n times as part of the named `SoundGroup`. A volume of 1.0 is unchanged,
+    /// while 0.0 is silent. All repetitions together count as a single voice.
+    ///
+    /// The sound is dropped if the group is full and its `StealPolicy` is `Reject`.
+    /// This will return an Error if the loaded audio file in source could not be decoded.
+    pub fn try_play_n_times_in_group(
+        &self,
+        source: &Source,
+        volume: f32,
+        n: u16,
+        groups: &mut SoundGroups,
+        group: &str,
+    ) -> Result<(), DecoderError> {
+        if n == 0 {
+            return Ok(());
+        }
+        let mut decoders = Vec::with_capacity(n as usize);
+        for _ in 0..n {
+            decoders.push(Decoder::new(Cursor::new(source.clone())).map_err(|_| DecoderError)?);
+        }
+        let signals = match groups.admit(group, volume) {
+            Some(signals) => signals,
+            None => return Ok(()),
+        };
+        let sink = Sink::new(&self.device);
+        let remaining = Arc::new(AtomicUsize::new(decoders.len()));
+        for decoder in decoders {
+            let remaining = remaining.clone();
+            let ended = signals.ended.clone();
+            sink.append(EndSignalSource::new(
+                StopSignalSource::new(
+                    pausable(decoder.amplify(volume), self.paused.clone()),
+                    signals.stop.clone(),
+                ),
+                move || {
+                    if remaining.fetch_sub(1, Ordering::Relaxed) == 1 {
+                        ended.store(true, Ordering::Relaxed);
+                    }
+                },
+            ));
         }
         sink.detach();
         Ok(())
@@ -113,17 +208,13 @@ impl Iterator for OutputIterator {
     type Item = Output;
 
     fn next(&mut self) -> Option<Output> {
-        self.devices.next().map(|device| Output {
-            device: Arc::new(device),
-        })
+        self.devices.next().map(Output::from_device)
     }
 }
 
 /// Get the default output, returns none if no outputs are available.
 pub fn default_output() -> Option<Output> {
-    default_output_device().map(|device| Output {
-        device: Arc::new(device),
-    })
+    default_output_device().map(Output::from_device)
 }
 
 /// Get a list of outputs available to the system.
@@ -140,6 +231,10 @@ pub fn init_output(world: &mut World) {
             .entry::<AudioSink>()
             .or_insert_with(|| AudioSink::new(&o));
         world.entry::<Output>().or_insert_with(|| o);
+        if !world.has_value::<AudioControl>() {
+            let control = AudioControl::new(&world.fetch::<Output>());
+            world.insert(control);
+        }
     } else {
         error!("Failed finding a default audio output to hook AudioSink to, audio will not work!")
     }
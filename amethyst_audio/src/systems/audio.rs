@@ -8,14 +8,14 @@ use std::{
 };
 
 use derive_new::new;
-use rodio::SpatialSink;
+use rodio::{Source, SpatialSink};
 
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
 
 use amethyst_core::{
     ecs::prelude::{
-        Entities, Entity, Join, Read, ReadStorage, System, SystemData, World, WriteStorage,
+        Entities, Entity, Join, Read, ReadStorage, System, SystemData, World, Write, WriteStorage,
     },
     math::convert,
     transform::Transform,
@@ -24,8 +24,11 @@ use amethyst_core::{
 
 use crate::{
     components::{AudioEmitter, AudioListener},
+    control::AudioControl,
     end_signal::EndSignalSource,
+    group::{SoundGroups, StopSignalSource},
     output::Output,
+    sink::AudioSink,
 };
 
 /// Builds an `AudioSystem`.
@@ -40,14 +43,45 @@ impl<'a, 'b> SystemDesc<'a, 'b, AudioSystem> for AudioSystemDesc {
         <AudioSystem as System<'_>>::SystemData::setup(world);
 
         world.insert(self.output.clone());
+        if !world.has_value::<AudioControl>() {
+            world.insert(AudioControl::new(&self.output));
+        }
 
         AudioSystem::new(self.output)
     }
 }
 
 /// Syncs 3D transform data with the audio engine to provide 3D audio.
+///
+/// Also applies the `AudioControl` pause state to emitters and to the music `AudioSink`.
 #[derive(Debug, Default, new)]
-pub struct AudioSystem(Output);
+pub struct AudioSystem {
+    output: Output,
+    #[new(default)]
+    music: MusicPause,
+}
+
+/// Pause state last applied to the music `AudioSink` by the `AudioControl`.
+///
+/// The sink is only touched when the control changes its mind, so that music paused manually
+/// stays paused.
+#[derive(Debug, Default)]
+struct MusicPause {
+    paused: bool,
+}
+
+impl MusicPause {
+    /// Returns `Some(true)` if the music has to be paused to follow the control, `Some(false)`
+    /// if it has to be resumed, and `None` if it was already in that state.
+    fn update(&mut self, paused: bool) -> Option<bool> {
+        if paused == self.paused {
+            None
+        } else {
+            self.paused = paused;
+            Some(paused)
+        }
+    }
+}
 
 /// Add this structure to world as a resource with ID 0 to select an entity whose AudioListener
 /// component will be used.  If this resource isn't found then the system will arbitrarily select
@@ -59,6 +93,9 @@ impl<'a> System<'a> for AudioSystem {
     type SystemData = (
         Option<Read<'a, Output>>,
         Option<Read<'a, SelectedListener>>,
+        Option<Read<'a, AudioControl>>,
+        Option<Read<'a, AudioSink>>,
+        Write<'a, SoundGroups>,
         Entities<'a>,
         ReadStorage<'a, Transform>,
         ReadStorage<'a, AudioListener>,
@@ -67,10 +104,31 @@ impl<'a> System<'a> for AudioSystem {
 
     fn run(
         &mut self,
-        (output, select_listener, entities, transform, listener, mut audio_emitter): Self::SystemData,
+        (
+            output,
+            select_listener,
+            control,
+            music,
+            mut sound_groups,
+            entities,
+            transform,
+            listener,
+            mut audio_emitter,
+        ): Self::SystemData,
     ) {
         #[cfg(feature = "profiler")]
         profile_scope!("audio_system");
+        let paused = control.as_ref().map_or(false, |c| c.is_paused());
+        let music_paused = control.as_ref().map_or(false, |c| c.music_paused());
+        if let Some(pause) = self.music.update(music_paused) {
+            if let Some(music) = &music {
+                if pause {
+                    music.pause();
+                } else {
+                    music.play();
+                }
+            }
+        }
         // Process emitters and listener.
         if let Some((listener, entity)) = select_listener
             .as_ref()
@@ -107,6 +165,13 @@ impl<'a> System<'a> for AudioSystem {
                     // Remove all sinks whose sounds have ended.
                     audio_emitter.sinks.retain(|s| !s.1.load(Ordering::Relaxed));
                     for &mut (ref mut sink, _) in &mut audio_emitter.sinks {
+                        if sink.is_paused() != paused {
+                            if paused {
+                                sink.pause();
+                            } else {
+                                sink.play();
+                            }
+                        }
                         sink.set_emitter_position(emitter_position);
                         sink.set_left_ear_position(left_ear_position);
                         sink.set_right_ear_position(right_ear_position);
@@ -118,19 +183,34 @@ impl<'a> System<'a> for AudioSystem {
                             }
                         }
                     }
-                    while let Some(source) = audio_emitter.sound_queue.pop() {
+                    while let Some((source, volume, group)) = audio_emitter.sound_queue.pop() {
                         if let Some(output) = &output {
+                            let (atomic_bool, stop) = match group {
+                                Some(group) => match sound_groups.admit(&group, volume) {
+                                    Some(signals) => (signals.ended, signals.stop),
+                                    None => continue,
+                                },
+                                None => (
+                                    Arc::new(AtomicBool::new(false)),
+                                    Arc::new(AtomicBool::new(false)),
+                                ),
+                            };
                             let sink = SpatialSink::new(
                                 &output.device,
                                 emitter_position,
                                 left_ear_position,
                                 right_ear_position,
                             );
-                            let atomic_bool = Arc::new(AtomicBool::new(false));
+                            if paused {
+                                sink.pause();
+                            }
                             let clone = atomic_bool.clone();
-                            sink.append(EndSignalSource::new(source, move || {
-                                clone.store(true, Ordering::Relaxed);
-                            }));
+                            sink.append(EndSignalSource::new(
+                                StopSignalSource::new(source.amplify(volume), stop),
+                                move || {
+                                    clone.store(true, Ordering::Relaxed);
+                                },
+                            ));
                             audio_emitter.sinks.push((sink, atomic_bool));
                         }
                     }
@@ -139,3 +219,18 @@ impl<'a> System<'a> for AudioSystem {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::MusicPause;
+
+    #[test]
+    fn music_is_only_touched_when_the_control_changes() {
+        let mut music = MusicPause::default();
+        assert_eq!(None, music.update(false));
+        assert_eq!(Some(true), music.update(true));
+        assert_eq!(None, music.update(true));
+        assert_eq!(Some(false), music.update(false));
+        assert_eq!(None, music.update(false));
+    }
+}
@@ -0,0 +1,741 @@
+//! Merging of layered RON configuration files and environment variable overrides.
+
+use std::{ffi::OsStr, fs, io, path::Path};
+
+use log::{debug, info};
+use ron::de::Error as DeError;
+use serde::Deserialize;
+
+use crate::{parse_ron, ConfigError, RonError};
+
+/// A RON value, keeping enough of its syntax to write it back.
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    /// Numbers, strings, characters, booleans, unit variants and unit structs, as written.
+    Atom(String),
+    /// Structs and struct variants, e.g. `Window(title: "Game")` or `(title: "Game")`.
+    Struct {
+        name: Option<String>,
+        fields: Vec<(String, Node)>,
+    },
+    /// Tuples, tuple structs and tuple variants, e.g. `(800, 600)` or `Some(3)`.
+    Tuple {
+        name: Option<String>,
+        items: Vec<Node>,
+    },
+    /// Sequences, e.g. `[1, 2]`.
+    List(Vec<Node>),
+    /// Maps, e.g. `{"jump": Key(Space)}`.
+    Map(Vec<(Node, Node)>),
+}
+
+impl Node {
+    fn is_some(&self) -> bool {
+        matches!(
+            self,
+            Node::Tuple { name, items } if name.as_deref() == Some("Some") && items.len() == 1
+        )
+    }
+
+    fn is_mergeable(&self) -> bool {
+        matches!(self, Node::Struct { .. } | Node::Map(_))
+    }
+
+    fn into_some_content(self) -> Node {
+        match self {
+            Node::Tuple { name, mut items }
+                if name.as_deref() == Some("Some") && items.len() == 1 =>
+            {
+                items.remove(0)
+            }
+            other => other,
+        }
+    }
+
+    fn to_ron(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out);
+        out
+    }
+
+    fn write(&self, out: &mut String) {
+        fn write_all<T>(out: &mut String, items: &[T], mut write: impl FnMut(&mut String, &T)) {
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write(out, item);
+            }
+        }
+
+        match self {
+            Node::Atom(text) => out.push_str(text),
+            Node::Struct { name, fields } => {
+                out.push_str(name.as_deref().unwrap_or(""));
+                out.push('(');
+                write_all(out, fields, |out, (field, value)| {
+                    out.push_str(field);
+                    out.push_str(": ");
+                    value.write(out);
+                });
+                out.push(')');
+            }
+            Node::Tuple { name, items } => {
+                out.push_str(name.as_deref().unwrap_or(""));
+                out.push('(');
+                write_all(out, items, |out, item| item.write(out));
+                out.push(')');
+            }
+            Node::List(items) => {
+                out.push('[');
+                write_all(out, items, |out, item| item.write(out));
+                out.push(']');
+            }
+            Node::Map(entries) => {
+                out.push('{');
+                write_all(out, entries, |out, (key, value)| {
+                    key.write(out);
+                    out.push_str(": ");
+                    value.write(out);
+                });
+                out.push('}');
+            }
+        }
+    }
+}
+
+/// Merges `over` into `base`.
+///
+/// Structs are merged field by field, unless both are named and their names differ, as for
+/// different enum variants. Maps are merged key by key, and `Some` is merged with its content.
+/// Any other value of `base`, including sequences and tuples, is replaced as a whole.
+fn merge(base: &mut Node, over: Node) {
+    match (base, over) {
+        (
+            Node::Struct { name, fields },
+            Node::Struct {
+                name: over_name,
+                fields: over_fields,
+            },
+        ) if name.is_none() || over_name.is_none() || *name == over_name => {
+            if over_name.is_some() {
+                *name = over_name;
+            }
+            for (field, value) in over_fields {
+                match fields.iter_mut().find(|(f, _)| *f == field) {
+                    Some((_, base_value)) => merge(base_value, value),
+                    None => fields.push((field, value)),
+                }
+            }
+        }
+        (Node::Map(entries), Node::Map(over_entries)) => {
+            for (key, value) in over_entries {
+                let text = key.to_ron();
+                match entries.iter_mut().find(|(k, _)| k.to_ron() == text) {
+                    Some((_, base_value)) => merge(base_value, value),
+                    None => entries.push((key, value)),
+                }
+            }
+        }
+        // With `implicit_some`, either side may leave out `Some`.
+        (Node::Tuple { name, items }, over)
+            if name.as_deref() == Some("Some")
+                && items.len() == 1
+                && (over.is_some() || over.is_mergeable()) =>
+        {
+            merge(&mut items[0], over.into_some_content())
+        }
+        (base, over) if over.is_some() && base.is_mergeable() => {
+            merge(base, over.into_some_content())
+        }
+        (base, over) => *base = over,
+    }
+}
+
+/// Merges `value` into the field of `node` at `path`, creating the structs leading to it.
+fn set(node: &mut Node, path: &[String], value: Node) {
+    let (field, rest) = match path.split_first() {
+        Some(split) => split,
+        None => return merge(node, value),
+    };
+    match node {
+        Node::Struct { fields, .. } => match fields.iter_mut().find(|(f, _)| f == field) {
+            Some((_, child)) => set(child, rest, value),
+            None => fields.push((field.clone(), nested(rest, value))),
+        },
+        Node::Map(entries) => {
+            let key = format!("{:?}", field);
+            match entries.iter_mut().find(|(k, _)| k.to_ron() == key) {
+                Some((_, child)) => set(child, rest, value),
+                None => entries.push((Node::Atom(key), nested(rest, value))),
+            }
+        }
+        Node::Tuple { name, items } if name.as_deref() == Some("Some") && items.len() == 1 => {
+            set(&mut items[0], path, value)
+        }
+        _ => *node = nested(path, value),
+    }
+}
+
+fn nested(path: &[String], value: Node) -> Node {
+    path.iter().rev().fold(value, |value, field| Node::Struct {
+        name: None,
+        fields: vec![(field.clone(), value)],
+    })
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).cloned()
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn error(&self, message: &str) -> String {
+        let consumed = &self.text[..self.pos];
+        let line = 1 + consumed.matches('\n').count();
+        let column = consumed.len() - consumed.rfind('\n').map_or(0, |newline| newline + 1) + 1;
+        format!("line {}, column {}: {}", line, column, message)
+    }
+
+    fn skip_ws(&mut self) -> Result<(), String> {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if trimmed.starts_with("//") {
+                self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if trimmed.starts_with("/*") {
+                match trimmed.find("*/") {
+                    Some(end) => self.pos += end + 2,
+                    None => return Err(self.error("unterminated block comment")),
+                }
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn eat(&mut self, c: u8) -> Result<bool, String> {
+        self.skip_ws()?;
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), String> {
+        if self.eat(c)? {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", c as char)))
+        }
+    }
+
+    fn ident(&mut self) -> Option<&'a str> {
+        let rest = self.rest();
+        let bytes = rest.as_bytes();
+        let start = if rest.starts_with("r#") { 2 } else { 0 };
+        match bytes.get(start) {
+            Some(c) if c.is_ascii_alphabetic() || *c == b'_' => {}
+            _ => return None,
+        }
+        let len = bytes[start..]
+            .iter()
+            .position(|c| !(c.is_ascii_alphanumeric() || *c == b'_'))
+            .map_or(bytes.len(), |len| start + len);
+        self.pos += len;
+        Some(&rest[..len])
+    }
+
+    fn expect_ident(&mut self) -> Result<&'a str, String> {
+        self.skip_ws()?;
+        self.ident()
+            .ok_or_else(|| self.error("expected an identifier"))
+    }
+
+    /// Parses the document, returning the extensions it enables and its value.
+    fn document(&mut self) -> Result<(Vec<String>, Node), String> {
+        let mut extensions = Vec::new();
+        loop {
+            self.skip_ws()?;
+            if !self.rest().starts_with("#![") {
+                break;
+            }
+            self.pos += 3;
+            if self.expect_ident()? != "enable" {
+                return Err(self.error("expected `enable`"));
+            }
+            self.expect(b'(')?;
+            let names = self.list(b')', |p| p.expect_ident().map(str::to_owned))?;
+            self.expect(b']')?;
+            extensions.extend(names);
+        }
+        let value = self.value()?;
+        self.skip_ws()?;
+        if self.pos != self.text.len() {
+            return Err(self.error("expected the end of the document"));
+        }
+        Ok((extensions, value))
+    }
+
+    fn value(&mut self) -> Result<Node, String> {
+        self.skip_ws()?;
+        let start = self.pos;
+        match self.peek() {
+            Some(b'(') => self.parens(None),
+            Some(b'[') => {
+                self.pos += 1;
+                Ok(Node::List(self.list(b']', Self::value)?))
+            }
+            Some(b'{') => {
+                self.pos += 1;
+                let entries = self.list(b'}', |p| {
+                    let key = p.value()?;
+                    p.expect(b':')?;
+                    Ok((key, p.value()?))
+                })?;
+                Ok(Node::Map(entries))
+            }
+            Some(quote @ b'"') | Some(quote @ b'\'') => {
+                self.quoted(quote)?;
+                Ok(Node::Atom(self.text[start..self.pos].to_owned()))
+            }
+            Some(b'r') if self.raw_string()? => {
+                Ok(Node::Atom(self.text[start..self.pos].to_owned()))
+            }
+            Some(c) if c.is_ascii_alphabetic() || c == b'_' => {
+                let name = self.ident().unwrap_or_default().to_owned();
+                self.skip_ws()?;
+                if self.peek() == Some(b'(') {
+                    self.parens(Some(name))
+                } else {
+                    Ok(Node::Atom(name))
+                }
+            }
+            Some(c) if c.is_ascii_digit() || c == b'-' || c == b'+' || c == b'.' => {
+                let len = self
+                    .rest()
+                    .bytes()
+                    .position(|c| !(c.is_ascii_alphanumeric() || b"_.+-".contains(&c)))
+                    .unwrap_or_else(|| self.rest().len());
+                self.pos += len;
+                Ok(Node::Atom(self.text[start..self.pos].to_owned()))
+            }
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    /// Parses a struct or tuple, starting at its opening parenthesis.
+    fn parens(&mut self, name: Option<String>) -> Result<Node, String> {
+        self.pos += 1;
+        self.skip_ws()?;
+        let start = self.pos;
+        let is_struct = self.ident().is_some() && self.eat(b':')?;
+        self.pos = start;
+        if is_struct {
+            let fields = self.list(b')', |p| {
+                let field = p.expect_ident()?.to_owned();
+                p.expect(b':')?;
+                Ok((field, p.value()?))
+            })?;
+            Ok(Node::Struct { name, fields })
+        } else {
+            let items = self.list(b')', Self::value)?;
+            Ok(Node::Tuple { name, items })
+        }
+    }
+
+    /// Parses comma separated items up to `close`, allowing a trailing comma.
+    fn list<T>(
+        &mut self,
+        close: u8,
+        mut item: impl FnMut(&mut Self) -> Result<T, String>,
+    ) -> Result<Vec<T>, String> {
+        let mut items = Vec::new();
+        loop {
+            if self.eat(close)? {
+                return Ok(items);
+            }
+            items.push(item(self)?);
+            if !self.eat(b',')? {
+                self.expect(close)?;
+                return Ok(items);
+            }
+        }
+    }
+
+    /// Skips a string or character literal.
+    fn quoted(&mut self, quote: u8) -> Result<(), String> {
+        let bytes = self.text.as_bytes();
+        let mut pos = self.pos + 1;
+        while pos < bytes.len() {
+            match bytes[pos] {
+                b'\\' => pos += 2,
+                c if c == quote => {
+                    self.pos = pos + 1;
+                    return Ok(());
+                }
+                _ => pos += 1,
+            }
+        }
+        Err(self.error("unterminated literal"))
+    }
+
+    /// Skips a raw string literal like `r#"..."#`, returning `false` if there is none.
+    fn raw_string(&mut self) -> Result<bool, String> {
+        let rest = self.rest();
+        let hashes = rest[1..].bytes().take_while(|&c| c == b'#').count();
+        if rest.as_bytes().get(1 + hashes) != Some(&b'"') {
+            return Ok(false);
+        }
+        let end = format!("\"{}", "#".repeat(hashes));
+        match rest[2 + hashes..].find(&end) {
+            Some(len) => {
+                self.pos += 2 + hashes + len + end.len();
+                Ok(true)
+            }
+            None => Err(self.error("unterminated raw string")),
+        }
+    }
+}
+
+/// Parses the value of an environment variable as RON, or as a string if it is not valid RON.
+fn env_value(value: &str) -> Node {
+    let mut parser = Parser {
+        text: value,
+        pos: 0,
+    };
+    let node = parser.value().and_then(|node| {
+        parser.skip_ws()?;
+        if parser.pos == value.len() {
+            Ok(node)
+        } else {
+            Err(String::new())
+        }
+    });
+    node.unwrap_or_else(|_| Node::Atom(format!("{:?}", value)))
+}
+
+fn layer_error(name: &str, message: String) -> ConfigError {
+    ConfigError::Parser(RonError::from(DeError::Message(format!(
+        "{}: {}",
+        name, message
+    ))))
+}
+
+/// Configuration files merged over each other.
+#[derive(Debug, Default)]
+struct Layers {
+    extensions: Vec<String>,
+    root: Option<Node>,
+}
+
+impl Layers {
+    /// Merges the RON document `bytes` over the layers added before.
+    fn add(&mut self, name: &str, bytes: &[u8]) -> Result<(), ConfigError> {
+        let text = std::str::from_utf8(bytes).map_err(|e| layer_error(name, e.to_string()))?;
+        let (extensions, node) = Parser { text, pos: 0 }
+            .document()
+            .map_err(|e| layer_error(name, e))?;
+        for extension in extensions {
+            if !self.extensions.contains(&extension) {
+                self.extensions.push(extension);
+            }
+        }
+        match self.root {
+            Some(ref mut root) => merge(root, node),
+            None => self.root = Some(node),
+        }
+        Ok(())
+    }
+
+    /// Merges the values of the variables named `prefix` followed by `__` and the path of a
+    /// field, returning the number of fields overridden.
+    fn apply_env<I>(&mut self, prefix: &str, vars: I) -> usize
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let prefix = format!("{}__", prefix);
+        let mut vars = vars
+            .into_iter()
+            .filter(|(key, _)| key.starts_with(&prefix))
+            .collect::<Vec<_>>();
+        // Parents are overridden before their fields.
+        vars.sort();
+
+        let root = self.root.get_or_insert_with(|| Node::Struct {
+            name: None,
+            fields: Vec::new(),
+        });
+        let mut overridden = 0;
+        for (key, value) in vars {
+            let path = key[prefix.len()..]
+                .split("__")
+                .map(str::to_lowercase)
+                .collect::<Vec<_>>();
+            if path.iter().any(String::is_empty) {
+                continue;
+            }
+            debug!(
+                "Overriding configuration field `{}` with the environment variable {}",
+                path.join("."),
+                key
+            );
+            set(root, &path, env_value(&value));
+            overridden += 1;
+        }
+        overridden
+    }
+
+    fn to_ron(&self) -> String {
+        let mut out = String::new();
+        if !self.extensions.is_empty() {
+            out.push_str(&format!("#![enable({})]\n", self.extensions.join(", ")));
+        }
+        if let Some(ref root) = self.root {
+            root.write(&mut out);
+        }
+        out
+    }
+}
+
+/// Loads the files at `paths` merged over each other, with environment variable overrides if
+/// `env_prefix` is given.
+pub(crate) fn load_layers<T, P>(paths: &[P], env_prefix: Option<&str>) -> Result<T, ConfigError>
+where
+    T: for<'a> Deserialize<'a>,
+    P: AsRef<Path>,
+{
+    let mut layers = Layers::default();
+    let mut loaded = Vec::new();
+    for path in paths {
+        let path = path.as_ref();
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                info!("Skipping missing configuration file {}", path.display());
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if path.extension().and_then(OsStr::to_str) != Some("ron") {
+            return Err(ConfigError::Extension(path.to_path_buf()));
+        }
+        layers.add(&path.display().to_string(), &bytes)?;
+        loaded.push(bytes);
+    }
+
+    if loaded.is_empty() {
+        let paths = paths
+            .iter()
+            .map(|p| p.as_ref().display().to_string())
+            .collect::<Vec<_>>();
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "None of the configuration files exist: {}",
+                paths.join(", ")
+            ),
+        )
+        .into());
+    }
+
+    let overridden = match env_prefix {
+        Some(prefix) => layers.apply_env(
+            prefix,
+            std::env::vars_os().filter_map(|(key, value)| {
+                Some((key.into_string().ok()?, value.into_string().ok()?))
+            }),
+        ),
+        None => 0,
+    };
+
+    // A single file is parsed as is, so errors point to its lines.
+    if loaded.len() == 1 && overridden == 0 {
+        Ok(parse_ron(&loaded[0])?)
+    } else {
+        Ok(parse_ron(layers.to_ron().as_bytes())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::Deserialize;
+
+    use super::{load_layers, Layers};
+    use crate::parse_ron;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Display {
+        title: String,
+        dimensions: Option<(u32, u32)>,
+        fullscreen: bool,
+        icons: Vec<String>,
+        mode: Mode,
+        limits: Option<Limits>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Limits {
+        min: u32,
+        max: u32,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Mode {
+        Windowed,
+        Borderless { monitor: u32, vsync: bool },
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Button {
+        Key(String),
+        Mouse(u8),
+    }
+
+    const DEFAULTS: &str = r#"
+#![enable(implicit_some)]
+(
+    // Shipped with the game.
+    title: "Game",
+    dimensions: (800, 600),
+    fullscreen: false,
+    icons: ["small.png", "large.png"],
+    mode: Borderless(monitor: 0, vsync: true),
+    limits: (min: 1, max: 10),
+)"#;
+
+    fn merged<T: for<'a> Deserialize<'a>>(layers: &[&str], env: &[(&str, &str)]) -> T {
+        let mut merged = Layers::default();
+        for (i, layer) in layers.iter().enumerate() {
+            merged
+                .add(&format!("layer {}", i), layer.as_bytes())
+                .unwrap();
+        }
+        merged.apply_env(
+            "GAME_DISPLAY",
+            env.iter().map(|&(k, v)| (k.to_owned(), v.to_owned())),
+        );
+        parse_ron(merged.to_ron().as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn structs_merge_by_field_and_sequences_as_a_whole() {
+        let display: Display = merged(
+            &[
+                DEFAULTS,
+                "(icons: [\"user.png\"], mode: Borderless(vsync: false), limits: Some((max: 20)))",
+                "/* Command line */ (fullscreen: true)",
+            ],
+            &[],
+        );
+        assert_eq!(
+            Display {
+                title: "Game".to_owned(),
+                dimensions: Some((800, 600)),
+                fullscreen: true,
+                icons: vec!["user.png".to_owned()],
+                mode: Mode::Borderless {
+                    monitor: 0,
+                    vsync: false
+                },
+                limits: Some(Limits { min: 1, max: 20 }),
+            },
+            display
+        );
+    }
+
+    #[test]
+    fn other_variants_and_tuples_replace_the_value() {
+        let display: Display = merged(
+            &[DEFAULTS, "(dimensions: (1024, 768), mode: Windowed)"],
+            &[],
+        );
+        assert_eq!(Some((1024, 768)), display.dimensions);
+        assert_eq!(Mode::Windowed, display.mode);
+    }
+
+    #[test]
+    fn maps_merge_by_key() {
+        let bindings: BTreeMap<String, Vec<Button>> = merged(
+            &[
+                r#"{"jump": [Key("Space")], "fire": [Mouse(0)]}"#,
+                r#"{"jump": [Key("W"), Mouse(2)]}"#,
+            ],
+            &[("GAME_DISPLAY__FIRE", "[Key(\"F\")]")],
+        );
+        assert_eq!(
+            vec![Button::Key("W".to_owned()), Button::Mouse(2)],
+            bindings["jump"]
+        );
+        assert_eq!(vec![Button::Key("F".to_owned())], bindings["fire"]);
+    }
+
+    #[test]
+    fn environment_overrides_nested_fields() {
+        let display: Display = merged(
+            &[DEFAULTS],
+            &[
+                ("GAME_DISPLAY__FULLSCREEN", "true"),
+                ("GAME_DISPLAY__TITLE", "Game of the Year Edition"),
+                ("GAME_DISPLAY__MODE__MONITOR", "2"),
+                ("GAME_DISPLAY__LIMITS__MIN", "5"),
+                ("GAME_DISPLAY__DIMENSIONS", "None"),
+                ("GAME_AUDIO__VOLUME", "0.5"),
+            ],
+        );
+        assert!(display.fullscreen);
+        assert_eq!("Game of the Year Edition", display.title);
+        assert_eq!(
+            Mode::Borderless {
+                monitor: 2,
+                vsync: true
+            },
+            display.mode
+        );
+        assert_eq!(Some(Limits { min: 5, max: 10 }), display.limits);
+        assert_eq!(None, display.dimensions);
+    }
+
+    #[test]
+    fn skips_missing_files() {
+        let dir = std::env::temp_dir().join("amethyst_config_layers");
+        std::fs::create_dir_all(&dir).unwrap();
+        let defaults = dir.join("defaults.ron");
+        let user = dir.join("user.ron");
+        std::fs::write(&defaults, DEFAULTS).unwrap();
+        std::fs::write(&user, "(title: \"Custom\")").unwrap();
+
+        let display: Display =
+            load_layers(&[defaults, dir.join("missing.ron"), user], None).unwrap();
+        assert_eq!("Custom", display.title);
+        assert!(load_layers::<Display, _>(&[dir.join("missing.ron")], None).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reports_the_layer_with_a_syntax_error() {
+        let mut layers = Layers::default();
+        layers.add("defaults.ron", DEFAULTS.as_bytes()).unwrap();
+        let error = layers
+            .add(
+                "user.ron",
+                b"(\n    title: \"Game\",\n    size: (800, 600\n)",
+            )
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("user.ron: line 4"), "{}", error);
+    }
+}
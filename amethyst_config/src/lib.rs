@@ -19,6 +19,11 @@ use std::{
 use ron::{self, de::Error as DeError, ser::Error as SerError};
 use serde::{Deserialize, Serialize};
 
+pub use crate::parse::{parse_ron, RonError};
+
+mod layer;
+mod parse;
+
 /// Error related to anything that manages/creates configurations as well as
 /// "workspace"-related things.
 #[derive(Debug)]
@@ -26,7 +31,7 @@ pub enum ConfigError {
     /// Forward to the `std::io::Error` error.
     File(io::Error),
     /// Errors related to serde's parsing of configuration files.
-    Parser(DeError),
+    Parser(RonError),
     /// Occurs if a value is ill-formed during serialization (like a poisoned mutex).
     Serializer(SerError),
     /// Related to the path of the file.
@@ -64,6 +69,12 @@ impl From<io::Error> for ConfigError {
 
 impl From<DeError> for ConfigError {
     fn from(e: DeError) -> Self {
+        ConfigError::Parser(e.into())
+    }
+}
+
+impl From<RonError> for ConfigError {
+    fn from(e: RonError) -> Self {
         ConfigError::Parser(e)
     }
 }
@@ -87,6 +98,7 @@ impl Error for ConfigError {
     fn cause(&self) -> Option<&dyn Error> {
         match *self {
             ConfigError::File(ref err) => Some(err),
+            ConfigError::Parser(ref err) => Some(err),
             _ => None,
         }
     }
@@ -109,6 +121,35 @@ where
     /// Loads configuration structure from raw bytes.
     fn load_bytes(bytes: &[u8]) -> Result<Self, ConfigError>;
 
+    /// Loads a configuration structure from several files, each overriding the files before it,
+    /// e.g. the defaults shipped with the game followed by the settings of the player.
+    ///
+    /// Files which do not exist are skipped, but at least one of them has to exist. The files
+    /// are merged by the following rules:
+    ///
+    /// * Structs are merged field by field, so a file only needs the fields it changes. Struct
+    ///   variants are merged the same way if both files name the same variant.
+    /// * Maps are merged key by key.
+    /// * `Some` is merged with its content, which may also leave out `Some` if the
+    ///   `implicit_some` extension is enabled.
+    /// * Any other value, including sequences, tuples and other enum variants, replaces the
+    ///   value of the files before as a whole.
+    fn load_layered<P: AsRef<Path>>(paths: &[P]) -> Result<Self, ConfigError>;
+
+    /// Loads a configuration structure like `load_layered`, then overrides its fields with the
+    /// environment variables named `prefix`, followed by `__` and the path of the field with
+    /// its parts separated by `__`.
+    ///
+    /// For example with the prefix `AMETHYST_DISPLAY`, `AMETHYST_DISPLAY__RESIZABLE=false`
+    /// sets the field `resizable`, and `AMETHYST_DISPLAY__MIN_DIMENSIONS="Some((800, 600))"`
+    /// sets `min_dimensions`. Field names and map keys are matched in lower case. The values are
+    /// parsed as RON and merged like a file, so strings which are a valid RON identifier need
+    /// quotes. Values which are not valid RON are used as strings.
+    fn load_layered_with_env<P: AsRef<Path>>(
+        paths: &[P],
+        prefix: &str,
+    ) -> Result<Self, ConfigError>;
+
     /// Writes a configuration structure to a file.
     fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError>;
 }
@@ -138,11 +179,18 @@ where
     }
 
     fn load_bytes(bytes: &[u8]) -> Result<Self, ConfigError> {
-        let mut de = ron::de::Deserializer::from_bytes(bytes)?;
-        let val = T::deserialize(&mut de)?;
-        de.end()?;
+        Ok(parse_ron(bytes)?)
+    }
+
+    fn load_layered<P: AsRef<Path>>(paths: &[P]) -> Result<Self, ConfigError> {
+        layer::load_layers(paths, None)
+    }
 
-        Ok(val)
+    fn load_layered_with_env<P: AsRef<Path>>(
+        paths: &[P],
+        prefix: &str,
+    ) -> Result<Self, ConfigError> {
+        layer::load_layers(paths, Some(prefix))
     }
 
     fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError> {
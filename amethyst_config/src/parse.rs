@@ -0,0 +1,189 @@
+use std::{error::Error, fmt};
+
+use ron::de::{Deserializer, Error as DeError};
+use serde::Deserialize;
+
+/// Error from deserializing a RON document, with the location the error occurred at.
+///
+/// The location consists of the line and column in the document and the path of the
+/// field being deserialized, e.g. `entities[3].data.transform`, so the error can be
+/// found in large files:
+///
+/// ```text
+/// line 12, column 19, at `entities[3].data.transform`: unknown field `translaton`, expected one of `translation`, `rotation`, `scale`
+/// ```
+#[derive(Debug)]
+pub struct RonError {
+    error: DeError,
+    position: Option<(usize, usize)>,
+    path: String,
+}
+
+impl RonError {
+    /// Creates the error for `error`, which occurred with `remainder` of the document
+    /// `bytes` left to deserialize.
+    fn new(error: DeError, bytes: &[u8], remainder: &str, path: String) -> Self {
+        let position = match error {
+            DeError::Parser(_, ref position) => Some((position.line, position.col)),
+            _ => {
+                let consumed = &bytes[..bytes.len().saturating_sub(remainder.len())];
+                let line_start = consumed
+                    .iter()
+                    .rposition(|&b| b == b'\n')
+                    .map_or(0, |newline| newline + 1);
+                let line = 1 + consumed.iter().filter(|&&b| b == b'\n').count();
+                Some((line, consumed.len() - line_start + 1))
+            }
+        };
+
+        RonError {
+            error,
+            position,
+            path,
+        }
+    }
+
+    /// The error reported by the RON deserializer.
+    pub fn error(&self) -> &DeError {
+        &self.error
+    }
+
+    /// The line the error occurred at, starting at 1.
+    pub fn line(&self) -> Option<usize> {
+        self.position.map(|(line, _)| line)
+    }
+
+    /// The column the error occurred at, starting at 1.
+    pub fn column(&self) -> Option<usize> {
+        self.position.map(|(_, column)| column)
+    }
+
+    /// The path of the field being deserialized when the error occurred, e.g.
+    /// `entities[3].data.transform`. Empty if the error occurred outside of any field.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl From<DeError> for RonError {
+    fn from(error: DeError) -> Self {
+        let position = match error {
+            DeError::Parser(_, ref position) => Some((position.line, position.col)),
+            _ => None,
+        };
+
+        RonError {
+            error,
+            position,
+            path: String::new(),
+        }
+    }
+}
+
+impl fmt::Display for RonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.position, self.path.is_empty()) {
+            (Some((line, column)), true) => write!(f, "line {}, column {}: ", line, column)?,
+            (Some((line, column)), false) => {
+                write!(f, "line {}, column {}, at `{}`: ", line, column, self.path)?
+            }
+            (None, false) => write!(f, "at `{}`: ", self.path)?,
+            (None, true) => {}
+        }
+        match self.error {
+            // The position is already part of the message.
+            DeError::Parser(ref code, _) => write!(f, "{:?}", code),
+            ref error => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for RonError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Deserializes a `T` from the RON document `bytes`, reporting the location of errors.
+///
+/// Prefer this over `ron::de::from_bytes` for files written by hand, like prefabs and
+/// configuration files.
+pub fn parse_ron<T>(bytes: &[u8]) -> Result<T, RonError>
+where
+    T: for<'a> Deserialize<'a>,
+{
+    let mut de = Deserializer::from_bytes(bytes).map_err(RonError::from)?;
+    let value = serde_path_to_error::deserialize(&mut de).map_err(|e| {
+        let path = match e.path().to_string() {
+            ref root if root == "." => String::new(),
+            path => path,
+        };
+        RonError::new(e.into_inner(), bytes, &de.remainder(), path)
+    })?;
+    de.end()
+        .map_err(|e| RonError::new(e, bytes, &de.remainder(), String::new()))?;
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::parse_ron;
+
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Window {
+        title: String,
+        size: (u32, u32),
+        mode: Mode,
+    }
+
+    #[derive(Debug, Deserialize)]
+    enum Mode {
+        Windowed,
+        Fullscreen,
+    }
+
+    fn error(ron: &str) -> String {
+        parse_ron::<Window>(ron.as_bytes()).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn reports_line_and_field_of_type_errors() {
+        let message =
+            error("(\n    title: \"Game\",\n    size: (800, \"600\"),\n    mode: Windowed,\n)");
+        assert!(message.contains("line 3"), "{}", message);
+        assert!(message.contains("size"), "{}", message);
+    }
+
+    #[test]
+    fn reports_expected_variants() {
+        let message =
+            error("(\n    title: \"Game\",\n    size: (800, 600),\n    mode: Borderless,\n)");
+        assert!(message.contains("line 4"), "{}", message);
+        assert!(message.contains("mode"), "{}", message);
+        assert!(message.contains("Borderless"), "{}", message);
+        assert!(
+            message.contains("`Windowed` or `Fullscreen`"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn reports_unknown_fields() {
+        let message =
+            error("(\n    title: \"Game\",\n    sise: (800, 600),\n    mode: Windowed,\n)");
+        assert!(message.contains("line 3"), "{}", message);
+        assert!(message.contains("sise"), "{}", message);
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        let message =
+            error("(\n    title: \"Game\",\n    size: (800, 600)\n    mode: Windowed,\n)");
+        assert!(message.contains("line 4"), "{}", message);
+    }
+}
@@ -0,0 +1,162 @@
+use std::sync::Arc;
+
+use amethyst_core::{
+    ecs::prelude::{Builder, Entity, RunNow, World, WorldExt},
+    transform::{Parent, Transform, TransformSystem, TransformSystemDesc},
+    ArcThreadPool, HiddenPropagate, HideHierarchySystem, HideHierarchySystemDesc, SystemDesc,
+};
+use specs_hierarchy::HierarchySystem;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+const ROOTS: usize = 500;
+const CHILDREN: usize = 99;
+
+// 50k entities: `ROOTS` trees of one root with `CHILDREN` children each, every tenth tree hidden.
+fn hide_scene() -> (
+    World,
+    HierarchySystem<Parent>,
+    HideHierarchySystem,
+    Vec<Entity>,
+) {
+    let mut world = World::new();
+    let mut hierarchy = HierarchySystem::<Parent>::new(&mut world);
+    let mut hide = HideHierarchySystemDesc::default().build(&mut world);
+
+    let mut roots = Vec::with_capacity(ROOTS);
+    for i in 0..ROOTS {
+        let mut builder = world.create_entity();
+        if i % 10 == 0 {
+            builder = builder.with(HiddenPropagate::new());
+        }
+        let root = builder.build();
+        for _ in 0..CHILDREN {
+            world.create_entity().with(Parent { entity: root }).build();
+        }
+        roots.push(root);
+    }
+
+    hierarchy.run_now(&world);
+    hide.run_now(&world);
+    world.maintain();
+    (world, hierarchy, hide, roots)
+}
+
+pub fn hide_hierarchy_static_50k(b: &mut Criterion) {
+    let (world, mut hierarchy, mut hide, _) = hide_scene();
+
+    b.bench_function("hide_hierarchy_static_50k", move |b| {
+        b.iter(|| {
+            hierarchy.run_now(&world);
+            hide.run_now(&world);
+        });
+    });
+}
+
+pub fn hide_hierarchy_toggle_one_of_50k(b: &mut Criterion) {
+    let (mut world, mut hierarchy, mut hide, roots) = hide_scene();
+    let toggled = roots[1];
+
+    b.bench_function("hide_hierarchy_toggle_one_of_50k", move |b| {
+        b.iter(|| {
+            {
+                let mut hidden = world.write_storage::<HiddenPropagate>();
+                if hidden.remove(toggled).is_none() {
+                    hidden.insert(toggled, HiddenPropagate::new()).unwrap();
+                }
+            }
+            hierarchy.run_now(&world);
+            hide.run_now(&world);
+            world.maintain();
+        });
+    });
+}
+
+// 100k entities below `roots`, as `roots.len()` chains of `depth` entities each.
+fn transform_scene(
+    roots: usize,
+    depth: usize,
+    pool: Option<ArcThreadPool>,
+) -> (World, HierarchySystem<Parent>, TransformSystem, Vec<Entity>) {
+    let mut world = World::new();
+    if let Some(pool) = pool {
+        world.insert(pool);
+    }
+    let mut hierarchy = HierarchySystem::<Parent>::new(&mut world);
+    let mut transforms = TransformSystemDesc::default().build(&mut world);
+
+    let root_entities = (0..roots)
+        .map(|i| {
+            let mut local = Transform::default();
+            local.set_translation_xyz(i as f32, 0.0, 0.0);
+            let root = world.create_entity().with(local).build();
+            let mut parent = root;
+            for _ in 0..depth {
+                let mut local = Transform::default();
+                local.set_translation_xyz(0.0, 1.0, 0.0);
+                local.set_rotation_y_axis(0.1);
+                parent = world
+                    .create_entity()
+                    .with(local)
+                    .with(Parent { entity: parent })
+                    .build();
+            }
+            root
+        })
+        .collect::<Vec<_>>();
+
+    hierarchy.run_now(&world);
+    transforms.run_now(&world);
+    world.maintain();
+    (world, hierarchy, transforms, root_entities)
+}
+
+fn bench_transforms(b: &mut Criterion, name: &str, roots: usize, depth: usize, parallel: bool) {
+    let pool = if parallel {
+        Some(Arc::new(rayon::ThreadPoolBuilder::new().build().unwrap()))
+    } else {
+        None
+    };
+    let (world, mut hierarchy, mut transforms, roots) = transform_scene(roots, depth, pool);
+
+    b.bench_function(name, move |b| {
+        b.iter(|| {
+            {
+                // Flag every root as modified, so the whole hierarchy is propagated.
+                let mut locals = world.write_storage::<Transform>();
+                for root in &roots {
+                    locals.get_mut(*root).unwrap().move_up(0.0);
+                }
+            }
+            hierarchy.run_now(&world);
+            transforms.run_now(&world);
+        });
+    });
+}
+
+pub fn transform_deep_100k_serial(b: &mut Criterion) {
+    bench_transforms(b, "transform_deep_100k_serial", 1000, 100, false);
+}
+
+pub fn transform_deep_100k_parallel(b: &mut Criterion) {
+    bench_transforms(b, "transform_deep_100k_parallel", 1000, 100, true);
+}
+
+pub fn transform_wide_100k_serial(b: &mut Criterion) {
+    bench_transforms(b, "transform_wide_100k_serial", 100_000, 1, false);
+}
+
+pub fn transform_wide_100k_parallel(b: &mut Criterion) {
+    bench_transforms(b, "transform_wide_100k_parallel", 100_000, 1, true);
+}
+
+criterion_group!(
+    hierarchy,
+    hide_hierarchy_static_50k,
+    hide_hierarchy_toggle_one_of_50k,
+    transform_deep_100k_serial,
+    transform_deep_100k_parallel,
+    transform_wide_100k_serial,
+    transform_wide_100k_parallel,
+);
+criterion_main!(hierarchy);
@@ -39,7 +39,7 @@
 //!
 //! # Frame Rate Limiting Strategies
 //!
-//! The four possible strategies described by [`FrameRateLimitStrategy`] are as follows:
+//! The five possible strategies described by [`FrameRateLimitStrategy`] are as follows:
 //!
 //! * `Unlimited` will not try to limit the frame rate to the specified maximum. Amethyst
 //!   will call [`thread::yield_now`] once and then continue to the next frame.
@@ -53,6 +53,13 @@
 //!   and then will yield until the next frame starts. This approach attempts to get the
 //!   consistent frame timings of yielding, while reducing CPU usage compared to the yield-only
 //!   approach.
+//! * `SleepAndSpin` will sleep until there's only a small amount of time left in the frame, and
+//!   then will busy-wait until the next frame starts. Unlike yielding, spinning never hands the
+//!   thread to the scheduler, so a busy system can't delay the frame by a whole scheduling
+//!   quantum, at the cost of a fully utilized core during the grace period.
+//!
+//! The strategy and the maximum frame rate can be changed at runtime through the
+//! [`FrameLimiter`] resource, and take effect from the next frame on.
 //!
 //! By default amethyst will use the `Yield` strategy, which is fine for desktop and console
 //! games that aren't as affected by extra CPU usage. For mobile devices, the `Sleep` strategy
@@ -64,6 +71,7 @@
 //!
 //! [`Application`]: ../../amethyst/struct.Application.html
 //! [`FrameRateLimitStrategy`]: ./enum.FrameRateLimitStrategy.html
+//! [`FrameLimiter`]: ./struct.FrameLimiter.html
 //! [`thread::yield_now`]: https://doc.rust-lang.org/std/thread/fn.yield_now.html
 //! [`thread::sleep`]: https://doc.rust-lang.org/stable/std/thread/fn.sleep.html
 
@@ -83,7 +91,7 @@ const ZERO: Duration = Duration::from_millis(0);
 /// these different strategies should be used.
 ///
 /// [module documentation]: ./index.html#frame-rate-limiting-strategies
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub enum FrameRateLimitStrategy {
     /// No limit, will do a single yield and then continue with the next frame.
     Unlimited,
@@ -99,6 +107,12 @@ pub enum FrameRateLimitStrategy {
     /// Will sleep repeatedly until the given duration remains, and then will yield repeatedly
     /// for the remaining frame time.
     SleepAndYield(Duration),
+
+    /// Use sleep and busy-waiting combined.
+    ///
+    /// Will sleep repeatedly until the given duration remains, and then will spin for the
+    /// remaining frame time without yielding.
+    SleepAndSpin(Duration),
 }
 
 impl Default for FrameRateLimitStrategy {
@@ -145,8 +159,29 @@ impl Default for FrameRateLimitConfig {
 /// `FrameLimiter` is used internally by amethyst to limit the frame rate to the
 /// rate specified by the user. It is added as a resource to the world so that user code may
 /// change the frame rate limit at runtime if necessary.
+///
+/// # Examples
+///
+/// ```
+/// use amethyst::{
+///     core::frame_limiter::{FrameLimiter, FrameRateLimitStrategy},
+///     ecs::{System, Write},
+/// };
+///
+/// struct LowPowerSystem;
+///
+/// impl<'s> System<'s> for LowPowerSystem {
+///     type SystemData = Write<'s, FrameLimiter>;
+///
+///     fn run(&mut self, mut limiter: Self::SystemData) {
+///         limiter.set_strategy(FrameRateLimitStrategy::Sleep);
+///         limiter.set_fps(30);
+///     }
+/// }
+/// ```
 #[derive(Debug)]
 pub struct FrameLimiter {
+    fps: u32,
     frame_duration: Duration,
     strategy: FrameRateLimitStrategy,
     last_call: Instant,
@@ -162,6 +197,7 @@ impl FrameLimiter {
     /// Creates a new frame limiter.
     pub fn new(strategy: FrameRateLimitStrategy, fps: u32) -> Self {
         let mut s = Self {
+            fps: 0,
             frame_duration: Duration::from_secs(0),
             strategy: Default::default(),
             last_call: Instant::now(),
@@ -177,9 +213,33 @@ impl FrameLimiter {
             fps = 144;
         }
         self.strategy = strategy;
+        self.fps = fps;
         self.frame_duration = Duration::from_secs(1) / fps;
     }
 
+    /// Sets the frame rate limiting strategy, keeping the maximum fps.
+    pub fn set_strategy(&mut self, strategy: FrameRateLimitStrategy) {
+        self.strategy = strategy;
+    }
+
+    /// Sets the maximum fps, keeping the strategy.
+    ///
+    /// A maximum of 0 disables the limit, like `set_rate` does.
+    pub fn set_fps(&mut self, fps: u32) {
+        let strategy = self.strategy.clone();
+        self.set_rate(strategy, fps);
+    }
+
+    /// Returns the frame rate limiting strategy.
+    pub fn strategy(&self) -> &FrameRateLimitStrategy {
+        &self.strategy
+    }
+
+    /// Returns the maximum fps.
+    pub fn fps(&self) -> u32 {
+        self.fps
+    }
+
     /// Creates a new frame limiter with the given config.
     pub fn from_config(config: FrameRateLimitConfig) -> Self {
         Self::new(config.strategy, config.fps)
@@ -213,6 +273,11 @@ impl FrameLimiter {
                 self.do_sleep(dur);
                 self.do_yield();
             }
+
+            SleepAndSpin(dur) => {
+                self.do_sleep(dur);
+                self.do_spin();
+            }
         }
         self.last_call = Instant::now();
     }
@@ -223,8 +288,15 @@ impl FrameLimiter {
         }
     }
 
+    fn do_spin(&self) {
+        while Instant::now() - self.last_call < self.frame_duration {}
+    }
+
     fn do_sleep(&self, stop_on_remaining: Duration) {
-        let frame_duration = self.frame_duration - stop_on_remaining;
+        let frame_duration = self
+            .frame_duration
+            .checked_sub(stop_on_remaining)
+            .unwrap_or(ZERO);
         loop {
             let elapsed = Instant::now() - self.last_call;
             if elapsed >= frame_duration {
@@ -235,3 +307,35 @@ impl FrameLimiter {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_rate_at_runtime() {
+        let mut limiter = FrameLimiter::new(FrameRateLimitStrategy::Yield, 60);
+        limiter.set_fps(30);
+        assert_eq!(limiter.fps(), 30);
+        assert_eq!(limiter.strategy(), &FrameRateLimitStrategy::Yield);
+
+        limiter.set_strategy(FrameRateLimitStrategy::Sleep);
+        assert_eq!(limiter.strategy(), &FrameRateLimitStrategy::Sleep);
+        assert_eq!(limiter.frame_duration, Duration::from_secs(1) / 30);
+
+        limiter.set_fps(0);
+        assert_eq!(limiter.strategy(), &FrameRateLimitStrategy::Unlimited);
+    }
+
+    #[test]
+    fn spins_until_the_frame_ends() {
+        let mut limiter = FrameLimiter::new(
+            FrameRateLimitStrategy::SleepAndSpin(Duration::from_millis(2)),
+            100,
+        );
+        let start = Instant::now();
+        limiter.start();
+        limiter.wait();
+        assert!(Instant::now() - start >= Duration::from_millis(10));
+    }
+}
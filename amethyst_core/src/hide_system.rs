@@ -1,7 +1,7 @@
 use crate::{
     ecs::prelude::{
-        BitSet, ComponentEvent, ReadExpect, ReadStorage, ReaderId, System, SystemData, World,
-        WriteStorage,
+        BitSet, ComponentEvent, Entities, Entity, Join, ReadExpect, ReadStorage, ReaderId, System,
+        SystemData, World, WriteStorage,
     },
     transform::components::{HierarchyEvent, Parent, ParentHierarchy},
     SystemDesc,
@@ -38,11 +38,12 @@ impl<'a, 'b> SystemDesc<'a, 'b, HideHierarchySystem> for HideHierarchySystemDesc
 /// Depends on the resource "ParentHierarchy", which is set up by the
 /// [TransformBundle](struct.TransformBundle.html)
 ///
-/// Based on the [UiTransformSystem](struct.UiTransformSystem.html).
+/// Only the subtrees of entities whose `HiddenPropagate` or `Parent` changed are visited, so a
+/// frame without such changes costs next to nothing.
 #[derive(Debug)]
 pub struct HideHierarchySystem {
-    marked_as_modified: BitSet,
-    manually_hidden: BitSet,
+    dirty: BitSet,
+    stack: Vec<Entity>,
     hidden_events_id: ReaderId<ComponentEvent>,
     parent_events_id: ReaderId<HierarchyEvent>,
 }
@@ -54,8 +55,8 @@ impl HideHierarchySystem {
         parent_events_id: ReaderId<HierarchyEvent>,
     ) -> Self {
         Self {
-            marked_as_modified: BitSet::default(),
-            manually_hidden: BitSet::default(),
+            dirty: BitSet::default(),
+            stack: Vec::new(),
             hidden_events_id,
             parent_events_id,
         }
@@ -64,90 +65,211 @@ impl HideHierarchySystem {
 
 impl<'a> System<'a> for HideHierarchySystem {
     type SystemData = (
+        Entities<'a>,
         WriteStorage<'a, HiddenPropagate>,
         ReadStorage<'a, Parent>,
         ReadExpect<'a, ParentHierarchy>,
     );
-    fn run(&mut self, (mut hidden, parents, hierarchy): Self::SystemData) {
+    fn run(&mut self, (entities, mut hidden, parents, hierarchy): Self::SystemData) {
         #[cfg(feature = "profiler")]
         profile_scope!("hide_hierarchy_system");
 
-        self.marked_as_modified.clear();
-        self.manually_hidden.clear();
-
-        // Borrow multiple parts of self as mutable
-        let self_hidden_events_id = &mut self.hidden_events_id;
-        let self_marked_as_modified = &mut self.marked_as_modified;
-        let self_manually_hidden = &mut self.manually_hidden;
-
-        hidden
-            .channel()
-            .read(self_hidden_events_id)
-            .for_each(|event| match event {
-                ComponentEvent::Inserted(id) | ComponentEvent::Removed(id) => {
-                    self_marked_as_modified.add(*id);
+        self.dirty.clear();
+        for event in hidden.channel().read(&mut self.hidden_events_id) {
+            match event {
+                ComponentEvent::Inserted(id)
+                | ComponentEvent::Removed(id)
+                | ComponentEvent::Modified(id) => {
+                    self.dirty.add(*id);
                 }
-                ComponentEvent::Modified(_id) => {}
-            });
-
+            }
+        }
         for event in hierarchy.changed().read(&mut self.parent_events_id) {
             match *event {
-                HierarchyEvent::Removed(entity) => {
-                    self_marked_as_modified.add(entity.id());
-                }
-                HierarchyEvent::Modified(entity) => {
-                    // HierarchyEvent::Modified includes insertion of new components to the storage.
-                    self_marked_as_modified.add(entity.id());
+                // HierarchyEvent::Modified includes insertion of new components to the storage.
+                HierarchyEvent::Removed(entity) | HierarchyEvent::Modified(entity) => {
+                    self.dirty.add(entity.id());
                 }
             }
         }
+        if self.dirty.is_empty() {
+            return;
+        }
 
-        // Compute hide status with parents.
-        for entity in hierarchy.all() {
-            {
-                let self_hidden = hidden.get(*entity);
-                let self_is_manually_hidden =
-                    self_hidden.as_ref().map_or(false, |p| !p.is_propagated);
-                if self_is_manually_hidden {
-                    self_manually_hidden.add(entity.id());
-                }
-
-                if let Some(parent) = parents.get(*entity) {
-                    let parent_entity = parent.entity;
-                    let parent_dirty = self_marked_as_modified.contains(parent_entity.id());
-                    let parent_hidden = hidden.get(parent_entity);
-                    let parent_is_manually_hidden =
-                        parent_hidden.as_ref().map_or(false, |p| !p.is_propagated);
-                    if parent_is_manually_hidden {
-                        self_manually_hidden.add(parent_entity.id());
-                        self_manually_hidden.add(entity.id());
-                    }
+        self.stack.clear();
+        for id in (&self.dirty).join() {
+            let entity = entities.entity(id);
+            if entities.is_alive(entity) {
+                self.stack.push(entity);
+            }
+        }
 
-                    if parent_dirty && !self_is_manually_hidden {
-                        if parent_hidden.is_some() {
-                            if let Err(e) =
-                                hidden.insert(*entity, HiddenPropagate::new_propagated())
-                            {
-                                error!("Failed to automatically add `HiddenPropagate`: {:?}", e);
-                            }
-                        } else {
-                            hidden.remove(*entity);
+        // Parents are always updated before their children are pushed. When a dirty entity is
+        // also below another dirty entity, its subtree is simply visited twice.
+        while let Some(entity) = self.stack.pop() {
+            let manually_hidden = hidden.get(entity).map_or(false, |h| !h.is_propagated);
+            if !manually_hidden {
+                let parent_hidden = parents
+                    .get(entity)
+                    .map_or(false, |parent| hidden.contains(parent.entity));
+                match (parent_hidden, hidden.contains(entity)) {
+                    (true, false) => {
+                        if let Err(e) = hidden.insert(entity, HiddenPropagate::new_propagated()) {
+                            error!("Failed to automatically add `HiddenPropagate`: {:?}", e);
                         }
                     }
+                    (false, true) => {
+                        hidden.remove(entity);
+                    }
+                    _ => {}
                 }
             }
-            // Populate the modifications we just did.
-            // Happens inside the for-loop, so that the changes are picked up in the next iteration
-            // already, instead of on the next `system.run()`
-            hidden
-                .channel()
-                .read(self_hidden_events_id)
-                .for_each(|event| match event {
-                    ComponentEvent::Inserted(id) | ComponentEvent::Removed(id) => {
-                        self_marked_as_modified.add(*id);
-                    }
-                    ComponentEvent::Modified(_id) => {}
-                });
+            self.stack.extend_from_slice(hierarchy.children(entity));
         }
+
+        // Skip the events of the changes made above, they are already propagated.
+        hidden
+            .channel()
+            .read(&mut self.hidden_events_id)
+            .for_each(|_| {});
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs_hierarchy::HierarchySystem;
+
+    use super::*;
+    use crate::ecs::prelude::{Builder, RunNow, WorldExt};
+
+    struct Scene {
+        world: World,
+        hierarchy: HierarchySystem<Parent>,
+        hide: HideHierarchySystem,
+    }
+
+    impl Scene {
+        fn new() -> Self {
+            let mut world = World::new();
+            let hierarchy = HierarchySystem::<Parent>::new(&mut world);
+            let hide = HideHierarchySystemDesc::default().build(&mut world);
+            Scene {
+                world,
+                hierarchy,
+                hide,
+            }
+        }
+
+        fn entity(&mut self, parent: Option<Entity>) -> Entity {
+            let builder = self.world.create_entity();
+            match parent {
+                Some(entity) => builder.with(Parent { entity }).build(),
+                None => builder.build(),
+            }
+        }
+
+        fn run(&mut self) {
+            self.hierarchy.run_now(&self.world);
+            self.hide.run_now(&self.world);
+            self.world.maintain();
+        }
+
+        fn hide(&mut self, entity: Entity) {
+            self.world
+                .write_storage::<HiddenPropagate>()
+                .insert(entity, HiddenPropagate::new())
+                .unwrap();
+        }
+
+        fn is_hidden(&self, entity: Entity) -> bool {
+            self.world
+                .read_storage::<HiddenPropagate>()
+                .contains(entity)
+        }
+    }
+
+    #[test]
+    fn hidden_root_hides_descendants() {
+        let mut scene = Scene::new();
+        let root = scene.entity(None);
+        let middle = scene.entity(Some(root));
+        let leaf = scene.entity(Some(middle));
+        let other = scene.entity(None);
+        scene.run();
+
+        scene.hide(root);
+        scene.run();
+        assert!(scene.is_hidden(middle));
+        assert!(scene.is_hidden(leaf));
+        assert!(!scene.is_hidden(other));
+    }
+
+    #[test]
+    fn reparenting_under_hidden_ancestor() {
+        let mut scene = Scene::new();
+        let root = scene.entity(None);
+        let child = scene.entity(Some(root));
+        let moved = scene.entity(None);
+        let moved_child = scene.entity(Some(moved));
+        scene.hide(root);
+        scene.run();
+        assert!(!scene.is_hidden(moved_child));
+
+        scene
+            .world
+            .write_storage::<Parent>()
+            .insert(moved, Parent { entity: child })
+            .unwrap();
+        scene.run();
+        assert!(scene.is_hidden(moved));
+        assert!(scene.is_hidden(moved_child));
+
+        scene.world.write_storage::<Parent>().remove(moved);
+        scene.run();
+        assert!(!scene.is_hidden(moved));
+        assert!(!scene.is_hidden(moved_child));
+    }
+
+    #[test]
+    fn unhiding_a_middle_node() {
+        let mut scene = Scene::new();
+        let root = scene.entity(None);
+        let middle = scene.entity(Some(root));
+        let leaf = scene.entity(Some(middle));
+        scene.hide(middle);
+        scene.run();
+        assert!(!scene.is_hidden(root));
+        assert!(scene.is_hidden(leaf));
+
+        scene
+            .world
+            .write_storage::<HiddenPropagate>()
+            .remove(middle);
+        scene.run();
+        assert!(!scene.is_hidden(middle));
+        assert!(!scene.is_hidden(leaf));
+
+        // A manually hidden node stays hidden when its ancestor is unhidden.
+        scene.hide(root);
+        scene.hide(leaf);
+        scene.run();
+        scene.world.write_storage::<HiddenPropagate>().remove(root);
+        scene.run();
+        assert!(!scene.is_hidden(middle));
+        assert!(scene.is_hidden(leaf));
+    }
+
+    #[test]
+    fn deleting_the_hidden_root() {
+        let mut scene = Scene::new();
+        let root = scene.entity(None);
+        let child = scene.entity(Some(root));
+        scene.hide(root);
+        scene.run();
+
+        scene.world.delete_entity(root).unwrap();
+        scene.run();
+        scene.run();
+        assert!(!scene.world.is_alive(child) || !scene.is_hidden(child));
     }
 }
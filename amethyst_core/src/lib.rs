@@ -28,7 +28,10 @@ use std::sync::Arc;
 pub use crate::{
     bundle::SystemBundle,
     event::EventReader,
-    system_ext::{Pausable, SystemExt},
+    system_ext::{
+        GroupEnabled, Grouped, LazyFetch, Pausable, PausedBy, ResourceAbsent, ResourceCondition,
+        RunCondition, RunWhen, SystemExt, SystemGroups, ValueIs,
+    },
     timing::*,
     transform::*,
 };
@@ -37,6 +40,7 @@ pub use self::{
     axis::{Axis2, Axis3},
     hidden::{Hidden, HiddenPropagate},
     hide_system::{HideHierarchySystem, HideHierarchySystemDesc},
+    name_registry::{NameLookupError, NameRegistry, NameRegistrySystem, NameRegistrySystemDesc},
     named::{Named, WithNamed},
     system_desc::{RunNowDesc, SystemDesc},
 };
@@ -52,6 +56,7 @@ mod axis;
 mod event;
 mod hidden;
 mod hide_system;
+mod name_registry;
 mod named;
 mod system_desc;
 mod system_ext;
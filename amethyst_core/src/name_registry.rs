@@ -0,0 +1,268 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+
+use smallvec::SmallVec;
+
+use crate::{
+    ecs::{
+        prelude::{
+            ComponentEvent, Entities, Entity, ReadStorage, ReaderId, System, SystemData, World,
+            Write, WriteStorage,
+        },
+        world::{EntitiesRes, Index},
+    },
+    Named, SystemDesc,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// An error returned by `NameRegistry::get_single`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NameLookupError {
+    /// No living entity has the name.
+    NotFound(String),
+    /// More than one living entity has the name.
+    Duplicate(String, usize),
+}
+
+impl Display for NameLookupError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            NameLookupError::NotFound(name) => write!(f, "No entity is named `{}`", name),
+            NameLookupError::Duplicate(name, count) => {
+                write!(f, "{} entities are named `{}`", count, name)
+            }
+        }
+    }
+}
+
+impl Error for NameLookupError {}
+
+/// Resource finding entities by their `Named` component.
+///
+/// The registry is kept up to date by the `NameRegistrySystem`, which the `TransformBundle`
+/// registers, so names added by code, `WithNamed` or prefabs are found without extra work.
+///
+/// Components of deleted entities are only removed when the world is maintained at the end of
+/// the frame, so lookups take the `Entities` resource and skip the entities that are not alive
+/// anymore, until the system prunes them.
+///
+/// # Examples
+///
+/// ```
+/// use amethyst::core::NameRegistry;
+/// use amethyst::ecs::prelude::*;
+///
+/// pub struct FollowPlayerSystem;
+///
+/// impl<'s> System<'s> for FollowPlayerSystem {
+///     type SystemData = (Entities<'s>, Read<'s, NameRegistry>);
+///
+///     fn run(&mut self, (entities, names): Self::SystemData) {
+///         if let Ok(player) = names.get_single("player", &entities) {
+///             println!("The player is {:?}", player);
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct NameRegistry {
+    entities: HashMap<Cow<'static, str>, SmallVec<[Entity; 1]>>,
+    names: HashMap<Index, (Entity, Cow<'static, str>)>,
+}
+
+impl NameRegistry {
+    /// Returns the only living entity named `name`.
+    ///
+    /// Fails if no entity, or more than one, has the name.
+    pub fn get_single(
+        &self,
+        name: &str,
+        entities: &EntitiesRes,
+    ) -> Result<Entity, NameLookupError> {
+        let mut found = self.get_all(name, entities);
+        match (found.next(), found.count()) {
+            (Some(entity), 0) => Ok(entity),
+            (Some(_), others) => Err(NameLookupError::Duplicate(name.to_string(), others + 1)),
+            (None, _) => Err(NameLookupError::NotFound(name.to_string())),
+        }
+    }
+
+    /// Returns the living entities named `name`, in the order they were named.
+    pub fn get_all<'a>(
+        &'a self,
+        name: &str,
+        entities: &'a EntitiesRes,
+    ) -> impl Iterator<Item = Entity> + 'a {
+        self.entities
+            .get(name)
+            .into_iter()
+            .flatten()
+            .cloned()
+            .filter(move |entity| entities.is_alive(*entity))
+    }
+
+    /// Returns the name the registry knows `entity` by.
+    pub fn name(&self, entity: Entity) -> Option<&str> {
+        self.names
+            .get(&entity.id())
+            .filter(|(named, _)| *named == entity)
+            .map(|(_, name)| name.as_ref())
+    }
+
+    fn insert(&mut self, entity: Entity, name: Cow<'static, str>) {
+        self.remove(entity.id());
+        self.entities.entry(name.clone()).or_default().push(entity);
+        self.names.insert(entity.id(), (entity, name));
+    }
+
+    fn remove(&mut self, id: Index) {
+        if let Some((entity, name)) = self.names.remove(&id) {
+            if let Some(entities) = self.entities.get_mut(&name) {
+                entities.retain(|named| *named != entity);
+                if entities.is_empty() {
+                    self.entities.remove(&name);
+                }
+            }
+        }
+    }
+}
+
+/// Builds a `NameRegistrySystem`.
+#[derive(Default, Debug)]
+pub struct NameRegistrySystemDesc;
+
+impl<'a, 'b> SystemDesc<'a, 'b, NameRegistrySystem> for NameRegistrySystemDesc {
+    fn build(self, world: &mut World) -> NameRegistrySystem {
+        <NameRegistrySystem as System<'_>>::SystemData::setup(world);
+
+        let mut names = WriteStorage::<Named>::fetch(&world);
+        let reader_id = names.register_reader();
+
+        NameRegistrySystem::new(reader_id)
+    }
+}
+
+/// System keeping the `NameRegistry` in sync with the `Named` components.
+#[derive(Debug)]
+pub struct NameRegistrySystem {
+    reader_id: ReaderId<ComponentEvent>,
+}
+
+impl NameRegistrySystem {
+    /// Creates a new `NameRegistrySystem`.
+    pub fn new(reader_id: ReaderId<ComponentEvent>) -> Self {
+        Self { reader_id }
+    }
+}
+
+impl<'a> System<'a> for NameRegistrySystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Named>,
+        Write<'a, NameRegistry>,
+    );
+
+    fn run(&mut self, (entities, names, mut registry): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("name_registry_system");
+
+        for event in names.channel().read(&mut self.reader_id) {
+            match *event {
+                ComponentEvent::Inserted(id) | ComponentEvent::Modified(id) => {
+                    let entity = entities.entity(id);
+                    match names.get(entity) {
+                        Some(named) => registry.insert(entity, named.name.clone()),
+                        // removed later in the same batch of events
+                        None => registry.remove(id),
+                    }
+                }
+                ComponentEvent::Removed(id) => registry.remove(id),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ecs::{
+            prelude::{Builder, WorldExt},
+            shred::RunNow,
+        },
+        WithNamed,
+    };
+
+    fn setup() -> (World, NameRegistrySystem) {
+        let mut world = World::new();
+        let system = NameRegistrySystemDesc::default().build(&mut world);
+        (world, system)
+    }
+
+    fn lookup(world: &World, name: &str) -> Vec<Entity> {
+        world
+            .read_resource::<NameRegistry>()
+            .get_all(name, &world.entities())
+            .collect()
+    }
+
+    #[test]
+    fn tracks_inserted_and_changed_names() {
+        let (mut world, mut system) = setup();
+        let player = world.create_entity().named("player").build();
+        let first = world.create_entity().named("enemy").build();
+        let second = world.create_entity().named("enemy").build();
+        system.run_now(&world);
+
+        {
+            let entities = world.entities();
+            let registry = world.read_resource::<NameRegistry>();
+            assert_eq!(registry.get_single("player", &entities), Ok(player));
+            assert_eq!(
+                registry.get_single("enemy", &entities),
+                Err(NameLookupError::Duplicate("enemy".to_string(), 2))
+            );
+            assert_eq!(
+                registry.get_single("boss", &entities),
+                Err(NameLookupError::NotFound("boss".to_string()))
+            );
+            assert_eq!(registry.name(first), Some("enemy"));
+        }
+        assert_eq!(lookup(&world, "enemy"), vec![first, second]);
+
+        world.write_storage::<Named>().get_mut(second).unwrap().name = "boss".into();
+        world.write_storage::<Named>().remove(player);
+        system.run_now(&world);
+
+        assert_eq!(lookup(&world, "enemy"), vec![first]);
+        assert_eq!(lookup(&world, "boss"), vec![second]);
+        assert!(lookup(&world, "player").is_empty());
+    }
+
+    #[test]
+    fn deleted_entities_are_pruned() {
+        let (mut world, mut system) = setup();
+        let player = world.create_entity().named("player").build();
+        system.run_now(&world);
+
+        world.entities().delete(player).unwrap();
+        system.run_now(&world);
+        assert_eq!(lookup(&world, "player"), vec![player]);
+
+        // skipped as soon as the world is maintained, before the system ran
+        world.maintain();
+        assert!(lookup(&world, "player").is_empty());
+
+        system.run_now(&world);
+        let replacement = world.create_entity().named("player").build();
+        system.run_now(&world);
+        assert_eq!(lookup(&world, "player"), vec![replacement]);
+        assert_eq!(world.read_resource::<NameRegistry>().name(player), None);
+    }
+}
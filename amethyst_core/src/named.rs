@@ -1,6 +1,9 @@
 use std::borrow::Cow;
 
-use crate::ecs::{world::LazyBuilder, Component, DenseVecStorage, EntityBuilder, WriteStorage};
+use crate::ecs::{
+    storage::FlaggedStorage, world::LazyBuilder, Component, DenseVecStorage, EntityBuilder,
+    WriteStorage,
+};
 use serde::{Deserialize, Serialize};
 
 /// A component that gives a name to an [`Entity`].
@@ -22,6 +25,8 @@ use serde::{Deserialize, Serialize};
 /// [str]: https://doc.rust-lang.org/std/primitive.str.html
 /// [`Named::new`]: #method.new
 ///
+/// To find entities by name, see [`NameRegistry`](struct.NameRegistry.html).
+///
 /// # Examples
 ///
 /// Creating a name from string constant:
@@ -113,7 +118,7 @@ impl Named {
 }
 
 impl Component for Named {
-    type Storage = DenseVecStorage<Self>;
+    type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
 }
 
 /// An easy way to name an `Entity` and give it a `Named` `Component`.
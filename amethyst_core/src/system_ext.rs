@@ -3,9 +3,11 @@
 //! This modules contains an extension trait for the System trait which adds useful transformation
 //! functions.
 
+use std::{borrow::Cow, collections::HashSet, fmt, marker::PhantomData};
+
 use crate::{
     ecs::prelude::{Read, System, World},
-    shred::{RunningTime, SystemData},
+    shred::{Resource, ResourceId, RunningTime, SystemData},
 };
 
 #[cfg(feature = "profiler")]
@@ -80,6 +82,174 @@ pub trait SystemExt {
     where
         Self: Sized,
         V: Send + Sync + Default + PartialEq;
+
+    /// Pauses a system while the resource `F` is in the world.
+    ///
+    /// A state can insert the flag in `on_pause` and remove it in `on_resume` to freeze the
+    /// systems of its gameplay while another state is pushed on top of it. The paused system is
+    /// still dispatched, so the systems depending on it keep a valid order.
+    ///
+    /// The notes of [`pausable`] apply.
+    ///
+    /// [`pausable`]: #tymethod.pausable
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use amethyst::{
+    ///     ecs::{System, Write},
+    ///     shred::DispatcherBuilder,
+    ///     prelude::*,
+    /// };
+    ///
+    /// struct GamePaused;
+    ///
+    /// struct AddNumber(u32);
+    ///
+    /// impl<'s> System<'s> for AddNumber {
+    ///     type SystemData = Write<'s, u32>;
+    ///
+    ///     fn run(&mut self, mut number: Self::SystemData) {
+    ///         *number += self.0;
+    ///     }
+    /// }
+    ///
+    /// let mut world = World::new();
+    ///
+    /// let mut dispatcher = DispatcherBuilder::default()
+    ///     .with(AddNumber(1).paused_by::<GamePaused>(), "add_number", &[])
+    ///     .build();
+    ///
+    /// dispatcher.setup(&mut world);
+    ///
+    /// dispatcher.dispatch(&mut world);
+    /// assert_eq!(1, *world.read_resource::<u32>());
+    ///
+    /// world.insert(GamePaused);
+    /// dispatcher.dispatch(&mut world);
+    /// assert_eq!(1, *world.read_resource::<u32>());
+    /// ```
+    fn paused_by<F>(self) -> PausedBy<Self, F>
+    where
+        Self: Sized,
+        F: Resource;
+
+    /// Adds a system to a group of the `SystemGroups` resource, it only runs while the group is
+    /// enabled.
+    ///
+    /// Groups are enabled by default. The system is still dispatched while its group is disabled,
+    /// so the systems depending on it keep a valid order.
+    ///
+    /// The notes of [`pausable`] apply.
+    ///
+    /// [`pausable`]: #tymethod.pausable
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use amethyst::{
+    ///     core::SystemGroups,
+    ///     ecs::{System, Write},
+    ///     shred::DispatcherBuilder,
+    ///     prelude::*,
+    /// };
+    ///
+    /// struct AddNumber(u32);
+    ///
+    /// impl<'s> System<'s> for AddNumber {
+    ///     type SystemData = Write<'s, u32>;
+    ///
+    ///     fn run(&mut self, mut number: Self::SystemData) {
+    ///         *number += self.0;
+    ///     }
+    /// }
+    ///
+    /// let mut world = World::new();
+    ///
+    /// let mut dispatcher = DispatcherBuilder::default()
+    ///     .with(AddNumber(1), "add_number", &[])
+    ///     .with(AddNumber(2).in_group("gameplay"), "add_number_2", &["add_number"])
+    ///     .build();
+    ///
+    /// dispatcher.setup(&mut world);
+    ///
+    /// world.write_resource::<SystemGroups>().disable("gameplay");
+    /// dispatcher.dispatch(&mut world);
+    /// assert_eq!(1, *world.read_resource::<u32>());
+    ///
+    /// world.write_resource::<SystemGroups>().enable("gameplay");
+    /// dispatcher.dispatch(&mut world);
+    /// assert_eq!(1 + 1 + 2, *world.read_resource::<u32>());
+    /// ```
+    fn in_group<N>(self, group: N) -> Grouped<Self>
+    where
+        Self: Sized,
+        N: Into<Cow<'static, str>>;
+
+    /// Runs a system only on the frames `condition` returns true for the resource `R`.
+    ///
+    /// The system is skipped while `R` is not in the world. The data of the system is only
+    /// fetched on the frames it runs, so a write lock is not taken while it is skipped. The
+    /// system is still dispatched, so the systems depending on it keep a valid order.
+    ///
+    /// The notes of [`pausable`] apply.
+    ///
+    /// [`pausable`]: #tymethod.pausable
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use amethyst::{
+    ///     ecs::{System, Write},
+    ///     shred::DispatcherBuilder,
+    ///     prelude::*,
+    /// };
+    ///
+    /// #[derive(Default)]
+    /// struct Score(u32);
+    ///
+    /// struct AddNumber(u32);
+    ///
+    /// impl<'s> System<'s> for AddNumber {
+    ///     type SystemData = Write<'s, u32>;
+    ///
+    ///     fn run(&mut self, mut number: Self::SystemData) {
+    ///         *number += self.0;
+    ///     }
+    /// }
+    ///
+    /// let mut world = World::new();
+    ///
+    /// let mut dispatcher = DispatcherBuilder::default()
+    ///     .with(
+    ///         AddNumber(1).run_when(|score: &Score| score.0 >= 10),
+    ///         "add_number",
+    ///         &[],
+    ///     )
+    ///     .build();
+    ///
+    /// dispatcher.setup(&mut world);
+    ///
+    /// dispatcher.dispatch(&mut world);
+    /// assert_eq!(0, *world.read_resource::<u32>());
+    ///
+    /// world.insert(Score(10));
+    /// dispatcher.dispatch(&mut world);
+    /// assert_eq!(1, *world.read_resource::<u32>());
+    /// ```
+    fn run_when<R, F>(self, condition: F) -> RunWhen<Self, ResourceCondition<R, F>>
+    where
+        Self: Sized,
+        R: Resource,
+        F: FnMut(&R) -> bool + Send + 'static;
+
+    /// Runs a system only on the frames `condition` holds, see [`run_when`].
+    ///
+    /// [`run_when`]: #tymethod.run_when
+    fn run_if<C>(self, condition: C) -> RunWhen<Self, C>
+    where
+        Self: Sized,
+        C: for<'c> RunCondition<'c>;
 }
 
 impl<'s, S> SystemExt for S
@@ -91,41 +261,136 @@ where
         Self: Sized,
         V: Send + Sync + Default + PartialEq,
     {
-        Pausable {
+        self.run_if(ValueIs(value))
+    }
+
+    fn paused_by<F>(self) -> PausedBy<Self, F>
+    where
+        Self: Sized,
+        F: Resource,
+    {
+        self.run_if(ResourceAbsent(PhantomData))
+    }
+
+    fn in_group<N>(self, group: N) -> Grouped<Self>
+    where
+        Self: Sized,
+        N: Into<Cow<'static, str>>,
+    {
+        self.run_if(GroupEnabled(group.into()))
+    }
+
+    fn run_when<R, F>(self, condition: F) -> RunWhen<Self, ResourceCondition<R, F>>
+    where
+        Self: Sized,
+        R: Resource,
+        F: FnMut(&R) -> bool + Send + 'static,
+    {
+        self.run_if(ResourceCondition {
+            condition,
+            marker: PhantomData,
+        })
+    }
+
+    fn run_if<C>(self, condition: C) -> RunWhen<Self, C>
+    where
+        Self: Sized,
+        C: for<'c> RunCondition<'c>,
+    {
+        RunWhen {
             system: self,
-            value,
+            condition,
         }
     }
 }
 
-/// A system that is enabled when `V` has a specific value.
+/// Decides whether a `RunWhen` system runs this frame.
 ///
-/// This is created using the [`SystemExt::pausable`] method.
+/// The condition only fetches its own `SystemData`, usually a single resource, so the data of
+/// the wrapped system is not fetched on the frames it is skipped.
+pub trait RunCondition<'s> {
+    /// The data the condition reads.
+    type SystemData: SystemData<'s>;
+
+    /// Returns true if the system runs this frame.
+    fn should_run(&mut self, data: Self::SystemData) -> bool;
+}
+
+/// `SystemData` declaring the resources of `T` without fetching them, see `RunWhen`.
 ///
-/// [`SystemExt::pausable`]: trait.SystemExt.html#tymethod.pausable
+/// The resources are fetched with [`fetch`](#method.fetch). Since they are declared, the
+/// dispatcher schedules the system as if it fetched them right away.
+#[allow(missing_debug_implementations)]
+pub struct LazyFetch<'s, T> {
+    world: &'s World,
+    marker: PhantomData<T>,
+}
+
+impl<'s, T> LazyFetch<'s, T>
+where
+    T: SystemData<'s>,
+{
+    /// Fetches the data.
+    pub fn fetch(self) -> T {
+        T::fetch(self.world)
+    }
+}
+
+impl<'s, T> SystemData<'s> for LazyFetch<'s, T>
+where
+    T: SystemData<'s>,
+{
+    fn setup(world: &mut World) {
+        T::setup(world);
+    }
+
+    fn fetch(world: &'s World) -> Self {
+        LazyFetch {
+            world,
+            marker: PhantomData,
+        }
+    }
+
+    fn reads() -> Vec<ResourceId> {
+        T::reads()
+    }
+
+    fn writes() -> Vec<ResourceId> {
+        T::writes()
+    }
+}
+
+/// A system that only runs on the frames its `RunCondition` holds.
+///
+/// The condition is checked before the data of the system is fetched, so a skipped system
+/// costs no more than its condition. The system is still dispatched while it is skipped, so
+/// the systems depending on it keep a valid order.
+///
+/// This is created using the [`SystemExt::run_when`] or [`SystemExt::run_if`] methods.
+///
+/// [`SystemExt::run_when`]: trait.SystemExt.html#tymethod.run_when
+/// [`SystemExt::run_if`]: trait.SystemExt.html#tymethod.run_if
 #[derive(Debug)]
-pub struct Pausable<S, V> {
+pub struct RunWhen<S, C> {
     system: S,
-    value: V,
+    condition: C,
 }
 
-impl<'s, S, V: 'static> System<'s> for Pausable<S, V>
+impl<'s, S, C> System<'s> for RunWhen<S, C>
 where
     S::SystemData: SystemData<'s>,
     S: System<'s>,
-    V: Send + Sync + Default + PartialEq,
+    C: RunCondition<'s>,
 {
-    type SystemData = (Read<'s, V>, S::SystemData);
+    type SystemData = (C::SystemData, LazyFetch<'s, S::SystemData>);
 
-    fn run(&mut self, data: Self::SystemData) {
+    fn run(&mut self, (condition, data): Self::SystemData) {
         #[cfg(feature = "profiler")]
-        profile_scope!("pauseable_system");
+        profile_scope!("run_when_system");
 
-        if self.value != *data.0 {
-            return;
+        if self.condition.should_run(condition) {
+            self.system.run(data.fetch());
         }
-
-        self.system.run(data.1);
     }
 
     fn running_time(&self) -> RunningTime {
@@ -138,3 +403,212 @@ where
         self.system.setup(world);
     }
 }
+
+/// Runs the system while the resource `R` exists and the closure returns true for it.
+///
+/// This is created using the [`SystemExt::run_when`] method.
+///
+/// [`SystemExt::run_when`]: trait.SystemExt.html#tymethod.run_when
+pub struct ResourceCondition<R, F> {
+    condition: F,
+    marker: PhantomData<R>,
+}
+
+impl<R, F> fmt::Debug for ResourceCondition<R, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResourceCondition").finish()
+    }
+}
+
+impl<'s, R, F> RunCondition<'s> for ResourceCondition<R, F>
+where
+    R: Resource,
+    F: FnMut(&R) -> bool,
+{
+    type SystemData = Option<Read<'s, R>>;
+
+    fn should_run(&mut self, resource: Self::SystemData) -> bool {
+        resource.map_or(false, |resource| (self.condition)(&resource))
+    }
+}
+
+/// Runs the system while the resource `V` equals a value, see [`SystemExt::pausable`].
+///
+/// [`SystemExt::pausable`]: trait.SystemExt.html#tymethod.pausable
+#[derive(Debug)]
+pub struct ValueIs<V>(V);
+
+impl<'s, V> RunCondition<'s> for ValueIs<V>
+where
+    V: Send + Sync + Default + PartialEq + 'static,
+{
+    type SystemData = Read<'s, V>;
+
+    fn should_run(&mut self, value: Self::SystemData) -> bool {
+        self.0 == *value
+    }
+}
+
+/// Runs the system while the resource `F` is not in the world, see [`SystemExt::paused_by`].
+///
+/// [`SystemExt::paused_by`]: trait.SystemExt.html#tymethod.paused_by
+#[derive(Debug)]
+pub struct ResourceAbsent<F>(PhantomData<F>);
+
+impl<'s, F> RunCondition<'s> for ResourceAbsent<F>
+where
+    F: Resource,
+{
+    type SystemData = Option<Read<'s, F>>;
+
+    fn should_run(&mut self, flag: Self::SystemData) -> bool {
+        flag.is_none()
+    }
+}
+
+/// A system that is enabled when `V` has a specific value.
+///
+/// This is created using the [`SystemExt::pausable`] method.
+///
+/// [`SystemExt::pausable`]: trait.SystemExt.html#tymethod.pausable
+pub type Pausable<S, V> = RunWhen<S, ValueIs<V>>;
+
+/// A system that is paused while the resource `F` exists.
+///
+/// This is created using the [`SystemExt::paused_by`] method.
+///
+/// [`SystemExt::paused_by`]: trait.SystemExt.html#tymethod.paused_by
+pub type PausedBy<S, F> = RunWhen<S, ResourceAbsent<F>>;
+
+/// Resource enabling and disabling groups of systems.
+///
+/// Systems are added to a group with [`SystemExt::in_group`]. Every group is enabled until it is
+/// disabled, typically by a state in `on_pause`, and enabled again in `on_resume`.
+///
+/// [`SystemExt::in_group`]: trait.SystemExt.html#tymethod.in_group
+#[derive(Debug, Default)]
+pub struct SystemGroups {
+    disabled: HashSet<Cow<'static, str>>,
+}
+
+impl SystemGroups {
+    /// Enables the systems of `group`.
+    pub fn enable(&mut self, group: &str) {
+        self.disabled.remove(group);
+    }
+
+    /// Disables the systems of `group`.
+    pub fn disable<N>(&mut self, group: N)
+    where
+        N: Into<Cow<'static, str>>,
+    {
+        self.disabled.insert(group.into());
+    }
+
+    /// Enables or disables the systems of `group`.
+    pub fn set_enabled<N>(&mut self, group: N, enabled: bool)
+    where
+        N: Into<Cow<'static, str>>,
+    {
+        let group = group.into();
+        if enabled {
+            self.enable(&group);
+        } else {
+            self.disable(group);
+        }
+    }
+
+    /// Returns true if the systems of `group` run.
+    pub fn is_enabled(&self, group: &str) -> bool {
+        !self.disabled.contains(group)
+    }
+}
+
+/// Runs the system while its group of `SystemGroups` is enabled, see [`SystemExt::in_group`].
+///
+/// [`SystemExt::in_group`]: trait.SystemExt.html#tymethod.in_group
+#[derive(Debug)]
+pub struct GroupEnabled(Cow<'static, str>);
+
+impl<'s> RunCondition<'s> for GroupEnabled {
+    type SystemData = Read<'s, SystemGroups>;
+
+    fn should_run(&mut self, groups: Self::SystemData) -> bool {
+        groups.is_enabled(&self.0)
+    }
+}
+
+/// A system that only runs while its group of `SystemGroups` is enabled.
+///
+/// This is created using the [`SystemExt::in_group`] method.
+///
+/// [`SystemExt::in_group`]: trait.SystemExt.html#tymethod.in_group
+pub type Grouped<S> = RunWhen<S, GroupEnabled>;
+
+#[cfg(test)]
+mod tests {
+    use super::{SystemExt, SystemGroups};
+    use crate::{
+        ecs::prelude::{RunNow, System, World, WorldExt, Write},
+        shred::{ResourceId, SystemData},
+    };
+
+    #[derive(Default)]
+    struct Enabled(bool);
+
+    /// Panics when fetched, unless the world allows it.
+    struct FetchGuard;
+
+    impl<'s> SystemData<'s> for FetchGuard {
+        fn setup(_: &mut World) {}
+
+        fn fetch(world: &'s World) -> Self {
+            assert!(
+                world.read_resource::<Enabled>().0,
+                "fetched a skipped system"
+            );
+            FetchGuard
+        }
+
+        fn reads() -> Vec<ResourceId> {
+            vec![ResourceId::new::<Enabled>()]
+        }
+
+        fn writes() -> Vec<ResourceId> {
+            vec![]
+        }
+    }
+
+    struct CountRuns;
+
+    impl<'s> System<'s> for CountRuns {
+        type SystemData = (FetchGuard, Write<'s, u32>);
+
+        fn run(&mut self, (_, mut runs): Self::SystemData) {
+            *runs += 1;
+        }
+    }
+
+    #[test]
+    fn skipped_system_data_is_not_fetched() {
+        let mut world = World::new();
+        world.insert(Enabled(false));
+        world.insert(0u32);
+
+        let mut system = CountRuns.run_when(|enabled: &Enabled| enabled.0);
+        system.setup(&mut world);
+        system.run_now(&world);
+        assert_eq!(0, *world.read_resource::<u32>());
+
+        world.insert(Enabled(true));
+        system.run_now(&world);
+        assert_eq!(1, *world.read_resource::<u32>());
+
+        let mut system = CountRuns.in_group("gameplay");
+        system.setup(&mut world);
+        world.insert(Enabled(false));
+        world.write_resource::<SystemGroups>().disable("gameplay");
+        system.run_now(&world);
+        assert_eq!(1, *world.read_resource::<u32>());
+    }
+}
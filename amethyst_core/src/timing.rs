@@ -25,10 +25,18 @@ pub struct Time {
     absolute_time: Duration,
     ///Time multiplier. Affects returned delta_seconds, delta_time and absolute_time.
     time_scale: f32,
+    /// Whether the scaled time is stopped.
+    paused: bool,
     /// Fixed timestep accumulator.
     fixed_time_accumulator: f32,
     /// Fixed update interpolation alpha
     interpolation_alpha: f32,
+    /// Maximum number of fixed updates per frame.
+    max_fixed_steps: u32,
+    /// Number of fixed updates run in the current frame.
+    fixed_steps: u32,
+    /// The total number of fixed updates that have been run in this session.
+    fixed_frame_number: u64,
 }
 
 impl Time {
@@ -98,18 +106,37 @@ impl Time {
         self.time_scale
     }
 
+    /// Returns true if the scaled time is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
     /// Gets the current interpolation alpha factor.
+    ///
+    /// This is the fraction of a fixed time step that elapsed since the last fixed update,
+    /// between 0 and 1. Rendering the state of the last two fixed updates blended by this factor
+    /// hides the mismatch between the fixed update rate and the frame rate.
     pub fn interpolation_alpha(&self) -> f32 {
         self.interpolation_alpha
     }
 
+    /// Gets the maximum number of fixed updates run per frame.
+    pub fn max_fixed_steps(&self) -> u32 {
+        self.max_fixed_steps
+    }
+
+    /// Gets the total number of fixed updates that have been run in this session.
+    pub fn fixed_frame_number(&self) -> u64 {
+        self.fixed_frame_number
+    }
+
     /// Sets both `delta_seconds` and `delta_time` based on the seconds given.
     ///
     /// This should only be called by the engine.  Bad things might happen if you call this in
     /// your game.
     pub fn set_delta_seconds(&mut self, secs: f32) {
-        self.delta_seconds = secs * self.time_scale;
-        self.delta_time = secs_to_duration(secs * self.time_scale);
+        self.delta_seconds = secs * self.effective_time_scale();
+        self.delta_time = secs_to_duration(secs * self.effective_time_scale());
         self.delta_real_seconds = secs;
         self.delta_real_time = secs_to_duration(secs);
 
@@ -122,8 +149,8 @@ impl Time {
     /// This should only be called by the engine.  Bad things might happen if you call this in
     /// your game.
     pub fn set_delta_time(&mut self, time: Duration) {
-        self.delta_seconds = duration_to_secs(time) * self.time_scale;
-        self.delta_time = secs_to_duration(duration_to_secs(time) * self.time_scale);
+        self.delta_seconds = duration_to_secs(time) * self.effective_time_scale();
+        self.delta_time = secs_to_duration(duration_to_secs(time) * self.effective_time_scale());
         self.delta_real_seconds = duration_to_secs(time);
         self.delta_real_time = time;
 
@@ -132,17 +159,43 @@ impl Time {
     }
 
     /// Sets both `fixed_seconds` and `fixed_time` based on the seconds given.
+    ///
+    /// Can be changed at any time, the time accumulated towards the next fixed update is kept.
+    ///
+    /// ## Panics
+    /// This will panic if `secs` is NaN, Infinity, or not greater than 0.
     pub fn set_fixed_seconds(&mut self, secs: f32) {
+        assert!(secs > 0.0 && secs.is_finite());
         self.fixed_seconds = secs;
         self.fixed_time = secs_to_duration(secs);
     }
 
     /// Sets both `fixed_time` and `fixed_seconds` based on the duration given.
+    ///
+    /// Can be changed at any time, the time accumulated towards the next fixed update is kept.
+    ///
+    /// ## Panics
+    /// This will panic if `time` is zero.
     pub fn set_fixed_time(&mut self, time: Duration) {
+        assert!(time > Duration::from_secs(0));
         self.fixed_seconds = duration_to_secs(time);
         self.fixed_time = time;
     }
 
+    /// Sets the maximum number of fixed updates run per frame, 8 by default.
+    ///
+    /// When a frame takes longer than this many fixed time steps, e.g. after a spike or while the
+    /// game was paused by the OS, the fixed updates that did not fit are skipped. Otherwise the
+    /// next frames would take even longer to catch up, never catching up if a fixed update is
+    /// slower than the time step it simulates.
+    ///
+    /// ## Panics
+    /// This will panic if `steps` is 0.
+    pub fn set_max_fixed_steps(&mut self, steps: u32) {
+        assert!(steps > 0);
+        self.max_fixed_steps = steps;
+    }
+
     /// Increments the current frame number by 1.
     ///
     /// This should only be called by the engine.  Bad things might happen if you call this in
@@ -163,26 +216,54 @@ impl Time {
         self.time_scale = multiplier;
     }
 
+    /// Pauses or resumes the scaled time, keeping the time scale.
+    ///
+    /// While paused, `delta_seconds` and `delta_time` are zero, `absolute_time` does not advance
+    /// and no fixed updates are run. The real time values keep advancing, so menus and other
+    /// systems using them keep running.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    fn effective_time_scale(&self) -> f32 {
+        if self.paused {
+            0.0
+        } else {
+            self.time_scale
+        }
+    }
+
     /// Restarts the internal fixed update accumulator to the desired fixed update delta time.
     ///
     /// This should only be called by the engine.  Bad things might happen if you call this in
     /// your game.
     pub fn start_fixed_update(&mut self) {
-        self.fixed_time_accumulator += self.delta_real_seconds;
+        if !self.paused {
+            self.fixed_time_accumulator += self.delta_real_seconds;
+        }
+        self.fixed_steps = 0;
     }
 
     /// Checks to see if we should perform another fixed update iteration, and if so, returns true
     /// and reduces the accumulator.
     ///
+    /// Once `max_fixed_steps` were run in this frame, the whole time steps left in the
+    /// accumulator are dropped.
+    ///
     /// This should only be called by the engine.  Bad things might happen if you call this in
     /// your game.
     pub fn step_fixed_update(&mut self) -> bool {
-        if self.fixed_time_accumulator >= self.fixed_seconds {
-            self.fixed_time_accumulator -= self.fixed_seconds;
-            true
-        } else {
-            false
+        if self.fixed_time_accumulator < self.fixed_seconds {
+            return false;
+        }
+        if self.fixed_steps >= self.max_fixed_steps {
+            self.fixed_time_accumulator %= self.fixed_seconds;
+            return false;
         }
+        self.fixed_time_accumulator -= self.fixed_seconds;
+        self.fixed_steps += 1;
+        self.fixed_frame_number += 1;
+        true
     }
 
     /// Updates the interpolation alpha factor given the current fixed update rate and accumulator.
@@ -190,7 +271,9 @@ impl Time {
     /// This should only be called by the engine.  Bad things might happen if you call this in
     /// your game.
     pub fn finish_fixed_update(&mut self) {
-        self.interpolation_alpha = self.fixed_time_accumulator / self.fixed_seconds;
+        self.interpolation_alpha = (self.fixed_time_accumulator / self.fixed_seconds)
+            .max(0.0)
+            .min(1.0);
     }
 }
 
@@ -206,9 +289,13 @@ impl Default for Time {
             fixed_time_accumulator: 0.0,
             frame_number: 0,
             interpolation_alpha: 0.0,
+            max_fixed_steps: 8,
+            fixed_steps: 0,
+            fixed_frame_number: 0,
             absolute_real_time: Duration::default(),
             absolute_time: Duration::default(),
             time_scale: 1.0,
+            paused: false,
         }
     }
 }
@@ -432,6 +519,80 @@ mod tests {
         }
         assert_eq!(fixed_count, 2);
     }
+
+    #[test]
+    fn pause_stops_scaled_time() {
+        let mut time = super::Time::default();
+        time.set_fixed_seconds(0.5);
+        time.set_time_scale(2.0);
+        time.set_delta_seconds(1.0);
+        assert_eq!(time.absolute_time(), Duration::from_secs(2));
+
+        time.set_paused(true);
+        assert_eq!(fixed_frame(&mut time, 1.0), 0);
+        assert_eq!(time.delta_time(), Duration::from_secs(0));
+        assert!(time.delta_seconds().abs() < std::f32::EPSILON);
+        assert!((time.delta_real_seconds() - 1.0).abs() < std::f32::EPSILON);
+        assert_eq!(time.absolute_time(), Duration::from_secs(2));
+        assert_eq!(time.absolute_real_time(), Duration::from_secs(2));
+
+        time.set_paused(false);
+        assert!((time.time_scale() - 2.0).abs() < std::f32::EPSILON);
+        assert_eq!(fixed_frame(&mut time, 1.0), 2);
+        assert_eq!(time.absolute_time(), Duration::from_secs(4));
+    }
+
+    /// Runs a frame of `delta` seconds, returning the number of fixed updates.
+    fn fixed_frame(time: &mut super::Time, delta: f32) -> u32 {
+        time.set_delta_seconds(delta);
+        time.start_fixed_update();
+        let mut fixed_count = 0;
+        while time.step_fixed_update() {
+            fixed_count += 1;
+        }
+        time.finish_fixed_update();
+        fixed_count
+    }
+
+    // A frame spike runs at most `max_fixed_steps` fixed updates, and the time that did not fit is
+    // not caught up with in the next frames.
+    #[test]
+    fn fixed_update_frame_spike() {
+        let mut time = super::Time::default();
+        time.set_fixed_seconds(0.25);
+        time.set_max_fixed_steps(4);
+
+        assert_eq!(fixed_frame(&mut time, 10.1), 4);
+        assert!((time.interpolation_alpha() - 0.4).abs() < 1e-3);
+        assert_eq!(fixed_frame(&mut time, 0.1), 0);
+        assert_eq!(fixed_frame(&mut time, 0.1), 1);
+        assert_eq!(time.fixed_frame_number(), 5);
+    }
+
+    // Resuming after the game was paused, e.g. while alt-tabbed, with a delta of several minutes.
+    #[test]
+    fn fixed_update_after_pause() {
+        let mut time = super::Time::default();
+        time.set_fixed_seconds(1.0 / 60.0);
+
+        assert_eq!(fixed_frame(&mut time, 300.0), time.max_fixed_steps());
+        let alpha = time.interpolation_alpha();
+        assert!(alpha >= 0.0 && alpha < 1.0);
+        assert!(fixed_frame(&mut time, 1.0 / 60.0) <= 2);
+    }
+
+    // The time step can be changed at runtime, keeping the accumulated time.
+    #[test]
+    fn fixed_update_change_step() {
+        let mut time = super::Time::default();
+        time.set_fixed_seconds(1.0);
+
+        assert_eq!(fixed_frame(&mut time, 0.5), 0);
+        assert!((time.interpolation_alpha() - 0.5).abs() < 1e-5);
+        time.set_fixed_time(Duration::from_millis(100));
+        assert_eq!(fixed_frame(&mut time, 0.05), 5);
+        assert!((time.interpolation_alpha() - 0.5).abs() < 1e-3);
+    }
 }
 
 /// Converts a Duration to the time in seconds.
@@ -7,13 +7,16 @@ use crate::{
     bundle::SystemBundle,
     ecs::prelude::{DispatcherBuilder, World},
     transform::*,
-    SystemDesc,
+    NameRegistrySystemDesc, SystemDesc,
 };
 
 /// Transform bundle
 ///
-/// Will register transform components, and the `TransformSystem`.
-/// `TransformSystem` will be registered with name "transform_system".
+/// Will register transform components, the `TransformSystem`, the
+/// `TransformInterpolationSystem` and the `NameRegistrySystem`.
+/// `TransformSystem` will be registered with name "transform_system",
+/// `TransformInterpolationSystem` with name "transform_interpolation_system", and
+/// `NameRegistrySystem` with name "name_registry_system".
 ///
 /// ## Errors
 ///
@@ -59,6 +62,16 @@ impl<'a, 'b, 'c> SystemBundle<'a, 'b> for TransformBundle<'c> {
             "transform_system",
             &["parent_hierarchy_system"],
         );
+        builder.add(
+            TransformInterpolationSystemDesc::default().build(world),
+            "transform_interpolation_system",
+            &["transform_system"],
+        );
+        builder.add(
+            NameRegistrySystemDesc::default().build(world),
+            "name_registry_system",
+            &[],
+        );
         Ok(())
     }
 }
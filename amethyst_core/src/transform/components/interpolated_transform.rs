@@ -0,0 +1,54 @@
+//! Component blending the transform of an entity between fixed updates.
+
+use crate::{
+    ecs::{
+        prelude::{Component, DenseVecStorage, Join, World, WorldExt},
+        storage::MaskedStorage,
+    },
+    math::Matrix4,
+    transform::Transform,
+};
+
+/// Tags an entity whose `Transform` is changed in fixed updates, so the
+/// `TransformInterpolationSystem` renders it blended between the last two fixed updates.
+///
+/// Only the global matrix of the entity is blended, its local `Transform` keeps the state of the
+/// last fixed update. The global matrices of children are computed from the state of the last
+/// fixed update, they are not blended along.
+#[derive(Clone, Debug, Default)]
+pub struct InterpolatedTransform {
+    pub(crate) previous: Option<Transform>,
+    pub(crate) current: Option<Transform>,
+    /// The global matrix last computed by the `TransformSystem`, before blending.
+    pub(crate) fixed_global: Option<Matrix4<f32>>,
+}
+
+impl InterpolatedTransform {
+    /// Creates the component, interpolating once the next fixed update ran.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the `Transform` of every tagged entity as the state of the fixed update that just
+    /// ran, the state recorded before becoming the previous one.
+    ///
+    /// The application calls this after every fixed update, custom game loops have to do the
+    /// same. Nothing happens if the components are not registered.
+    pub fn record_fixed_update(world: &World) {
+        if !world.has_value::<MaskedStorage<Self>>()
+            || !world.has_value::<MaskedStorage<Transform>>()
+        {
+            return;
+        }
+        let locals = world.read_storage::<Transform>();
+        let mut interpolated = world.write_storage::<Self>();
+        for (local, state) in (&locals, &mut interpolated).join() {
+            state.previous = state.current.take().or_else(|| Some(local.clone()));
+            state.current = Some(local.clone());
+        }
+    }
+}
+
+impl Component for InterpolatedTransform {
+    type Storage = DenseVecStorage<Self>;
+}
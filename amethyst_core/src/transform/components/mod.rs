@@ -1,9 +1,13 @@
 //! Components for the transform processor.
 
 pub use self::{
+    interpolated_transform::InterpolatedTransform,
     parent::{HierarchyEvent, Parent, ParentHierarchy},
     transform::Transform,
 };
 
+pub(crate) use self::transform::decompose;
+
+mod interpolated_transform;
 mod parent;
 mod transform;
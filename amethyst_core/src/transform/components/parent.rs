@@ -32,3 +32,42 @@ impl HParent for Parent {
         self.entity
     }
 }
+
+#[cfg(feature = "saveload")]
+mod saveload {
+    use amethyst_error::{format_err, Error};
+
+    use crate::ecs::{
+        saveload::{ConvertSaveload, Marker},
+        Entity,
+    };
+
+    use super::Parent;
+
+    /// Saves the parent as its marker, so the hierarchy can be restored in another `World`.
+    impl<M: Marker> ConvertSaveload<M> for Parent {
+        type Data = M;
+        type Error = Error;
+
+        fn convert_into<F>(&self, mut ids: F) -> Result<M, Error>
+        where
+            F: FnMut(Entity) -> Option<M>,
+        {
+            ids(self.entity).ok_or_else(|| {
+                format_err!(
+                    "The parent {:?} of a saved entity is not saved itself",
+                    self.entity
+                )
+            })
+        }
+
+        fn convert_from<F>(data: M, mut ids: F) -> Result<Self, Error>
+        where
+            F: FnMut(M) -> Option<Entity>,
+        {
+            ids(data.clone())
+                .map(Parent::new)
+                .ok_or_else(|| format_err!("The parent {:?} of a loaded entity is missing", data))
+        }
+    }
+}
@@ -3,8 +3,8 @@ use crate::{
     alga::general::SubsetOf,
     ecs::prelude::{Component, DenseVecStorage, FlaggedStorage},
     math::{
-        self as na, Isometry3, Matrix4, Quaternion, RealField, Translation3, Unit, UnitQuaternion,
-        Vector3,
+        self as na, Isometry3, Matrix3, Matrix4, Point3, Quaternion, RealField, Rotation3,
+        Translation3, Unit, UnitQuaternion, Vector3,
     },
 };
 use serde::{Deserialize, Serialize};
@@ -14,7 +14,29 @@ use serde::{Deserialize, Serialize};
 /// Used for rendering position and orientation.
 ///
 /// The transforms are preformed in this order: scale, then rotation, then translation.
-#[derive(Getters, Setters, MutGetters, Clone, Debug, PartialEq, Deserialize, Serialize)]
+///
+/// # Serialization
+///
+/// In prefabs, the rotation can be written as a quaternion `(x, y, z, w)`, as Euler angles in
+/// degrees `(euler_degrees: (x, y, z))`, or as an axis and an angle in degrees
+/// `(axis: (x, y, z), angle_degrees: a)`. Euler angles rotate around the x axis first, then
+/// around the y axis, then around the z axis, all fixed, as `set_rotation_euler` does. The scale
+/// can be written as a vector `(x, y, z)` or as a single uniform factor.
+///
+/// A deserialized transform is serialized back in the forms it was written in, so tools saving
+/// prefabs keep them readable. A scale which is no longer uniform is written as a vector.
+///
+/// ```rust
+/// # use amethyst_core::transform::Transform;
+/// let transform: Transform = ron::de::from_str(
+///     "(translation: (0.0, 1.0, 0.0), rotation: (euler_degrees: (0.0, 45.0, 0.0)), scale: 2.0)",
+/// )
+/// .unwrap();
+///
+/// assert_eq!(2.0, transform.scale().x);
+/// assert!((transform.euler_angles().1.to_degrees() - 45.0).abs() < 0.001);
+/// ```
+#[derive(Getters, Setters, MutGetters, Clone, Debug, Deserialize, Serialize)]
 #[serde(from = "TransformValues", into = "TransformValues")]
 pub struct Transform {
     /// Translation + rotation value
@@ -29,6 +51,8 @@ pub struct Transform {
     /// The global transformation matrix.
     #[get = "pub"]
     pub(crate) global_matrix: Matrix4<f32>,
+    /// The forms the rotation and scale are serialized in.
+    value_forms: ValueForms,
 }
 
 impl Transform {
@@ -56,6 +80,7 @@ impl Transform {
             isometry: Isometry3::from_parts(na::convert(position), na::convert(rotation)),
             scale: na::convert(scale),
             global_matrix: na::one(),
+            value_forms: ValueForms::default(),
         }
     }
 
@@ -67,8 +92,9 @@ impl Transform {
     /// Makes the entity point towards `target`.
     ///
     /// `up` says which direction the entity should be 'rolled' to once it is pointing at
-    /// `position`. If `up` is parallel to the direction the entity is looking, the result will be
-    /// garbage.
+    /// `position`. If `up` is parallel to the direction the entity is looking, another up
+    /// direction perpendicular to it is picked. If `target` is the position of the entity, the
+    /// rotation is left unchanged.
     ///
     /// This function only works with respect to the coordinate system of its parent, so when used
     /// with an object that's not a sibling it will not do what you expect.
@@ -103,13 +129,134 @@ impl Transform {
         target: Vector3<N>,
         up: Vector3<N>,
     ) -> &mut Self {
-        self.isometry.rotation = UnitQuaternion::face_towards(
-            &(self.isometry.translation.vector - na::convert::<_, Vector3<f32>>(target)),
-            &na::convert::<_, Vector3<f32>>(up),
-        );
+        let direction = self.isometry.translation.vector - na::convert::<_, Vector3<f32>>(target);
+        let norm = direction.norm();
+        if norm <= std::f32::EPSILON {
+            return self;
+        }
+        let mut up = na::convert::<_, Vector3<f32>>(up);
+        if direction.cross(&up).norm() <= std::f32::EPSILON * norm * up.norm() {
+            // any axis that is not parallel to the direction
+            up = if direction.x.abs() < 0.9 * norm {
+                Vector3::x()
+            } else {
+                Vector3::y()
+            };
+        }
+        self.isometry.rotation = UnitQuaternion::face_towards(&direction, &up);
+        self
+    }
+
+    /// Rotates the entity about the Z axis so its X axis points towards `target`, the usual way
+    /// to aim in a 2d game. The Z coordinate of `target` is ignored.
+    ///
+    /// If `target` is the position of the entity, the rotation is left unchanged.
+    pub fn face_towards_2d(&mut self, target: Vector3<f32>) -> &mut Self {
+        let direction = target.xy() - self.isometry.translation.vector.xy();
+        if direction.norm() <= std::f32::EPSILON {
+            return self;
+        }
+        self.set_rotation_2d(direction.y.atan2(direction.x))
+    }
+
+    /// Moves the entity towards `target` by at most `max_step`, stopping at `target` instead of
+    /// overshooting it.
+    ///
+    /// Like `face_towards`, `target` is in the coordinate system of the parent.
+    pub fn move_towards(&mut self, target: Vector3<f32>, max_step: f32) -> &mut Self {
+        let translation = &mut self.isometry.translation.vector;
+        *translation = step_towards(*translation, target, max_step);
+        self
+    }
+
+    /// Moves the entity towards the world space `target` by at most `max_step` world units,
+    /// stopping at `target` instead of overshooting it.
+    ///
+    /// The position and parent of the entity are taken from its global matrix, so this accounts
+    /// for the rotation and scale of its parents as of the last `TransformSystem` run. Like
+    /// `set_global_translation`, this keeps the global matrix up to date. Nothing happens if the
+    /// global matrix is not invertible, e.g. with a scale of zero.
+    pub fn move_towards_global(&mut self, target: Vector3<f32>, max_step: f32) -> &mut Self {
+        let position = self.global_matrix.column(3).xyz();
+        self.set_global_translation(step_towards(position, target, max_step))
+    }
+
+    /// Moves the entity by `offset` in world space, accounting for the rotation and scale of
+    /// its parents as of the last `TransformSystem` run. Like `set_global_translation`, this keeps
+    /// the global matrix up to date.
+    ///
+    /// Nothing happens if the global matrix is not invertible, e.g. with a scale of zero.
+    pub fn translate_global(&mut self, offset: Vector3<f32>) -> &mut Self {
+        let position = self.global_matrix.column(3).xyz();
+        self.set_global_translation(position + offset)
+    }
+
+    /// Sets the translation so the entity ends up at `position` in world space, accounting for
+    /// the rotation and scale of its parents as of the last `TransformSystem` run.
+    ///
+    /// The global matrix is moved along, so the global helpers can be chained without waiting for
+    /// the next `TransformSystem` run. Nothing happens if the global matrix is not invertible,
+    /// e.g. with a scale of zero.
+    pub fn set_global_translation(&mut self, position: Vector3<f32>) -> &mut Self {
+        // local * global^-1 = local * (parent * local)^-1 = parent^-1
+        if let Some(global_inverse) = self.global_matrix.try_inverse() {
+            let parent_inverse = self.matrix() * global_inverse;
+            self.isometry.translation.vector = parent_inverse
+                .transform_point(&Point3::from(position))
+                .coords;
+            // only the translation changed, which leaves the entity at `position`
+            self.global_matrix
+                .fixed_slice_mut::<na::U3, na::U1>(0, 3)
+                .copy_from(&position);
+        }
         self
     }
 
+    /// Returns the transform at `t` between `self` at 0 and `other` at 1, interpolating the
+    /// translation and scale linearly and the rotation with a normalized linear interpolation.
+    ///
+    /// Cheaper than `slerp`, the rotation speed is not constant over `t` though. The rotation
+    /// takes the shortest path and `t` is not clamped.
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let rotation = self.rotation().nlerp(&self.shortest_rotation_to(other), t);
+        self.interpolated(other, t, rotation)
+    }
+
+    /// Returns the transform at `t` between `self` at 0 and `other` at 1, interpolating the
+    /// translation and scale linearly and the rotation spherically, at a constant angular speed.
+    ///
+    /// The rotation takes the shortest path and `t` is not clamped.
+    pub fn slerp(&self, other: &Self, t: f32) -> Self {
+        let target = self.shortest_rotation_to(other);
+        let rotation = self
+            .rotation()
+            .try_slerp(&target, t, std::f32::EPSILON)
+            .unwrap_or_else(|| self.rotation().nlerp(&target, t));
+        self.interpolated(other, t, rotation)
+    }
+
+    /// Returns the rotation of `other`, negated if needed so interpolating to it from the
+    /// rotation of `self` takes the shortest path. Both quaternions represent the same rotation.
+    fn shortest_rotation_to(&self, other: &Self) -> UnitQuaternion<f32> {
+        if self.rotation().coords.dot(&other.rotation().coords) < 0.0 {
+            UnitQuaternion::new_unchecked(-other.rotation().into_inner())
+        } else {
+            *other.rotation()
+        }
+    }
+
+    fn interpolated(&self, other: &Self, t: f32, rotation: UnitQuaternion<f32>) -> Self {
+        Transform {
+            isometry: Isometry3::from_parts(
+                Translation3::from(self.translation().lerp(other.translation(), t)),
+                rotation,
+            ),
+            scale: self.scale.lerp(&other.scale, t),
+            global_matrix: na::one(),
+            value_forms: self.value_forms,
+        }
+    }
+
     /// Returns the local object matrix for the transform.
     #[inline]
     pub fn matrix(&self) -> Matrix4<f32> {
@@ -527,6 +674,47 @@ impl Transform {
             .append_nonuniform_scaling(&inv_scale)
     }
 
+    /// Returns the position of the entity in world space, from the global matrix as of the last
+    /// `TransformSystem` run.
+    pub fn global_translation(&self) -> Vector3<f32> {
+        self.global_matrix.column(3).xyz()
+    }
+
+    /// Returns the orientation of the entity in world space, from the global matrix as of the
+    /// last `TransformSystem` run.
+    ///
+    /// A parent with a non-uniform scale shears its rotated children, which a rotation and a
+    /// scale can not represent. The returned rotation keeps the X axis of the entity exact and
+    /// the other axes as close as possible.
+    pub fn global_rotation(&self) -> UnitQuaternion<f32> {
+        decompose(&self.global_matrix).0
+    }
+
+    /// Returns the scale of the entity in world space, from the global matrix as of the last
+    /// `TransformSystem` run.
+    ///
+    /// The scale is the length of each axis, so it is only exact without shear, see
+    /// `global_rotation`. A mirrored entity has a negative X scale.
+    pub fn global_scale(&self) -> Vector3<f32> {
+        decompose(&self.global_matrix).1
+    }
+
+    /// Transforms a point from the local space of the entity to world space, using the global
+    /// matrix as of the last `TransformSystem` run.
+    pub fn transform_point(&self, point: &Point3<f32>) -> Point3<f32> {
+        self.global_matrix.transform_point(point)
+    }
+
+    /// Transforms a point from world space to the local space of the entity, using the global
+    /// matrix as of the last `TransformSystem` run.
+    ///
+    /// Returns `None` if the global matrix is not invertible, e.g. with a scale of zero.
+    pub fn inverse_transform_point(&self, point: &Point3<f32>) -> Option<Point3<f32>> {
+        self.global_matrix
+            .try_inverse()
+            .map(|inverse| inverse.transform_point(point))
+    }
+
     /// Calculates the inverse of this transform, which is in effect the 'view matrix' as
     /// commonly seen in computer graphics. This function computes the view matrix for the
     /// global transformation of the entity, and so takes into account `Parent`s.
@@ -560,6 +748,45 @@ impl Transform {
     }
 }
 
+/// Splits the linear part of a transformation matrix into a rotation and a scale, removing the
+/// shear from the rotation.
+pub(crate) fn decompose(matrix: &Matrix4<f32>) -> (UnitQuaternion<f32>, Vector3<f32>) {
+    let columns = [
+        matrix.column(0).xyz(),
+        matrix.column(1).xyz(),
+        matrix.column(2).xyz(),
+    ];
+    let mut scale = Vector3::new(columns[0].norm(), columns[1].norm(), columns[2].norm());
+    if Matrix3::from_columns(&columns).determinant() < 0.0 {
+        scale.x = -scale.x;
+    }
+    let x = match Unit::try_new(columns[0] * scale.x.signum(), std::f32::EPSILON) {
+        Some(x) => x.into_inner(),
+        None => return (UnitQuaternion::identity(), scale),
+    };
+    // Gram-Schmidt, the shear is in the angle between the columns
+    let y = match Unit::try_new(columns[1] - x * x.dot(&columns[1]), std::f32::EPSILON) {
+        Some(y) => y.into_inner(),
+        None => return (UnitQuaternion::identity(), scale),
+    };
+    let rotation = Matrix3::from_columns(&[x, y, x.cross(&y)]);
+    (
+        UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(rotation)),
+        scale,
+    )
+}
+
+/// Moves `from` towards `to` by at most `max_step`, without overshooting.
+fn step_towards(from: Vector3<f32>, to: Vector3<f32>, max_step: f32) -> Vector3<f32> {
+    let offset = to - from;
+    let distance = offset.norm();
+    if distance <= max_step || distance <= std::f32::EPSILON {
+        to
+    } else {
+        from + offset * (max_step.max(0.0) / distance)
+    }
+}
+
 impl Default for Transform {
     /// The default transform does nothing when used to transform an entity.
     fn default() -> Self {
@@ -567,10 +794,21 @@ impl Default for Transform {
             isometry: Isometry3::identity(),
             scale: Vector3::from_element(1.0),
             global_matrix: na::one(),
+            value_forms: ValueForms::default(),
         }
     }
 }
 
+impl PartialEq for Transform {
+    /// Transforms are equal if they transform entities the same way, whichever form they are
+    /// serialized in.
+    fn eq(&self, other: &Self) -> bool {
+        self.isometry == other.isometry
+            && self.scale == other.scale
+            && self.global_matrix == other.global_matrix
+    }
+}
+
 impl Component for Transform {
     type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
 }
@@ -609,12 +847,47 @@ impl From<Vector3<f64>> for Transform {
     }
 }
 
+/// The forms the rotation and scale of a `Transform` were deserialized from.
+#[derive(Clone, Copy, Debug, Default)]
+struct ValueForms {
+    rotation: RotationForm,
+    uniform_scale: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RotationForm {
+    Quaternion,
+    EulerDegrees,
+    AxisAngle,
+}
+
+impl Default for RotationForm {
+    fn default() -> Self {
+        RotationForm::Quaternion
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename = "Transform", default)]
 struct TransformValues {
     translation: [f32; 3],
-    rotation: [f32; 4],
-    scale: [f32; 3],
+    rotation: RotationValues,
+    scale: ScaleValues,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum RotationValues {
+    Quaternion([f32; 4]),
+    EulerDegrees { euler_degrees: [f32; 3] },
+    AxisAngle { axis: [f32; 3], angle_degrees: f32 },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum ScaleValues {
+    Uniform(f32),
+    Vector([f32; 3]),
 }
 
 impl Default for TransformValues {
@@ -622,8 +895,8 @@ impl Default for TransformValues {
     fn default() -> Self {
         TransformValues {
             translation: [0.0; 3],
-            rotation: [0.0, 0.0, 0.0, 1.0],
-            scale: [1.0; 3],
+            rotation: RotationValues::Quaternion([0.0, 0.0, 0.0, 1.0]),
+            scale: ScaleValues::Vector([1.0; 3]),
         }
     }
 }
@@ -636,20 +909,44 @@ impl From<TransformValues> for Transform {
             scale,
         } = transform_values;
 
-        let isometry = Isometry3::from_parts(
-            Translation3::new(translation[0], translation[1], translation[2]),
-            Unit::new_normalize(Quaternion::new(
-                rotation[3],
-                rotation[0],
-                rotation[1],
-                rotation[2],
-            )),
-        );
-        let scale = Vector3::new(scale[0], scale[1], scale[2]);
+        let (rotation, rotation_form) = match rotation {
+            RotationValues::Quaternion([x, y, z, w]) => (
+                Unit::new_normalize(Quaternion::new(w, x, y, z)),
+                RotationForm::Quaternion,
+            ),
+            RotationValues::EulerDegrees {
+                euler_degrees: [x, y, z],
+            } => (
+                UnitQuaternion::from_euler_angles(x.to_radians(), y.to_radians(), z.to_radians()),
+                RotationForm::EulerDegrees,
+            ),
+            RotationValues::AxisAngle {
+                axis,
+                angle_degrees,
+            } => (
+                // A zero axis has no direction to rotate around.
+                Unit::try_new(Vector3::from(axis), std::f32::EPSILON)
+                    .map_or_else(UnitQuaternion::identity, |axis| {
+                        UnitQuaternion::from_axis_angle(&axis, angle_degrees.to_radians())
+                    }),
+                RotationForm::AxisAngle,
+            ),
+        };
+        let (scale, uniform_scale) = match scale {
+            ScaleValues::Uniform(scale) => (Vector3::from_element(scale), true),
+            ScaleValues::Vector(scale) => (Vector3::from(scale), false),
+        };
 
         Transform {
-            isometry,
+            isometry: Isometry3::from_parts(
+                Translation3::new(translation[0], translation[1], translation[2]),
+                rotation,
+            ),
             scale,
+            value_forms: ValueForms {
+                rotation: rotation_form,
+                uniform_scale,
+            },
             ..Default::default()
         }
     }
@@ -657,10 +954,38 @@ impl From<TransformValues> for Transform {
 
 impl Into<TransformValues> for Transform {
     fn into(self) -> TransformValues {
+        let rotation = self.isometry.rotation;
+        let rotation = match self.value_forms.rotation {
+            RotationForm::Quaternion => RotationValues::Quaternion(rotation.as_ref().coords.into()),
+            RotationForm::EulerDegrees => {
+                let (x, y, z) = rotation.euler_angles();
+                RotationValues::EulerDegrees {
+                    euler_degrees: [x.to_degrees(), y.to_degrees(), z.to_degrees()],
+                }
+            }
+            RotationForm::AxisAngle => {
+                let (axis, angle) = rotation
+                    .axis_angle()
+                    .unwrap_or_else(|| (Vector3::y_axis(), 0.0));
+                RotationValues::AxisAngle {
+                    axis: axis.into_inner().into(),
+                    angle_degrees: angle.to_degrees(),
+                }
+            }
+        };
+        let scale = if self.value_forms.uniform_scale
+            && self.scale.x == self.scale.y
+            && self.scale.y == self.scale.z
+        {
+            ScaleValues::Uniform(self.scale.x)
+        } else {
+            ScaleValues::Vector(self.scale.into())
+        };
+
         TransformValues {
             translation: self.isometry.translation.vector.into(),
-            rotation: self.isometry.rotation.as_ref().coords.into(),
-            scale: self.scale.into(),
+            rotation,
+            scale,
         }
     }
 }
@@ -669,7 +994,7 @@ impl Into<TransformValues> for Transform {
 mod tests {
     use crate::{
         approx::*,
-        math::{UnitQuaternion, Vector3},
+        math::{Point3, UnitQuaternion, Vector3},
         Transform,
     };
 
@@ -777,6 +1102,256 @@ mod tests {
         assert_eq!(transform, Transform::default());
     }
 
+    fn round_trip(ron: &str, form: &str) -> (Transform, Transform) {
+        let transform: Transform = ron::de::from_str(ron).unwrap();
+        let s = ron::ser::to_string(&transform).unwrap();
+        assert!(s.contains(form), "{} is not serialized with {}", s, form);
+        (transform, ron::de::from_str(&s).unwrap())
+    }
+
+    #[test]
+    fn rotation_forms_round_trip() {
+        let expected = UnitQuaternion::from_euler_angles(
+            30f32.to_radians(),
+            45f32.to_radians(),
+            60f32.to_radians(),
+        );
+
+        let (euler, euler2) = round_trip(
+            "(rotation: (euler_degrees: (30, 45, 60)))",
+            "euler_degrees:",
+        );
+        assert_relative_eq!(expected, *euler.rotation(), max_relative = 0.000_01);
+        assert_relative_eq!(
+            *euler.rotation(),
+            *euler2.rotation(),
+            max_relative = 0.000_01
+        );
+
+        let (axis, angle) = expected.axis_angle().unwrap();
+        let (axis_angle, axis_angle2) = round_trip(
+            &format!(
+                "(rotation: (axis: ({}, {}, {}), angle_degrees: {}))",
+                axis.x,
+                axis.y,
+                axis.z,
+                angle.to_degrees()
+            ),
+            "angle_degrees:",
+        );
+        assert_relative_eq!(expected, *axis_angle.rotation(), max_relative = 0.000_01);
+        assert_relative_eq!(
+            *axis_angle.rotation(),
+            *axis_angle2.rotation(),
+            max_relative = 0.000_01
+        );
+
+        let q = expected.as_ref().coords;
+        let (quaternion, quaternion2) = round_trip(
+            &format!("(rotation: ({}, {}, {}, {}))", q.x, q.y, q.z, q.w),
+            &format!("rotation:({},", q.x),
+        );
+        assert_relative_eq!(expected, *quaternion.rotation(), max_relative = 0.000_01);
+        assert_eq!(quaternion, quaternion2);
+
+        let (identity, _) = round_trip("(rotation: (axis: (0, 1, 0), angle_degrees: 0))", "axis:");
+        assert_eq!(Transform::default(), identity);
+    }
+
+    #[test]
+    fn scale_forms_round_trip() {
+        let (uniform, uniform2) = round_trip("(scale: 2.5)", "scale:2.5");
+        assert_eq!(Vector3::from_element(2.5), *uniform.scale());
+        assert_eq!(uniform, uniform2);
+
+        let (vector, _) = round_trip("(scale: (2.5, 2.5, 2.5))", "scale:(2.5,2.5,2.5,)");
+        assert_eq!(uniform, vector);
+
+        let mut stretched = uniform;
+        stretched.set_scale(Vector3::new(1.0, 2.0, 3.0));
+        let s = ron::ser::to_string(&stretched).unwrap();
+        assert!(s.contains("scale:(1,2,3,)"), "{}", s);
+    }
+
+    #[test]
+    fn face_towards_degenerate_cases() {
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(1.0, 2.0, 3.0);
+        transform.set_rotation_2d(0.5);
+        let rotation = *transform.rotation();
+        // the target is the position
+        transform.face_towards(Vector3::new(1.0, 2.0, 3.0), Vector3::y());
+        assert_eq!(*transform.rotation(), rotation);
+
+        // up is parallel to the direction
+        transform.face_towards(Vector3::new(1.0, 5.0, 3.0), Vector3::y());
+        let forward = transform.rotation() * -Vector3::z();
+        assert_relative_eq!(forward, Vector3::y(), epsilon = 1e-6);
+        transform.face_towards(Vector3::new(4.0, 2.0, 3.0), Vector3::new(0.0, 0.0, 0.0));
+        assert!(transform.rotation().coords.iter().all(|c| c.is_finite()));
+    }
+
+    #[test]
+    fn face_towards_2d() {
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(1.0, 1.0, 0.0);
+        transform.face_towards_2d(Vector3::new(1.0, 3.0, 5.0));
+        assert_relative_eq!(
+            transform.rotation() * Vector3::x(),
+            Vector3::y(),
+            epsilon = 1e-6
+        );
+        let rotation = *transform.rotation();
+        transform.face_towards_2d(Vector3::new(1.0, 1.0, 0.0));
+        assert_eq!(*transform.rotation(), rotation);
+    }
+
+    #[test]
+    fn move_towards_does_not_overshoot() {
+        let target = Vector3::new(3.0, 4.0, 0.0);
+        let mut transform = Transform::default();
+        transform.move_towards(target, 2.5);
+        assert_relative_eq!(*transform.translation(), Vector3::new(1.5, 2.0, 0.0));
+        transform.move_towards(target, 10.0);
+        assert_eq!(*transform.translation(), target);
+        // already at the target
+        transform.move_towards(target, 1.0);
+        assert_eq!(*transform.translation(), target);
+        transform.move_towards(Vector3::new(0.0, 0.0, 0.0), -1.0);
+        assert_eq!(*transform.translation(), target);
+    }
+
+    #[test]
+    fn global_translation_accounts_for_parent() {
+        let mut parent = Transform::default();
+        parent.set_translation_xyz(10.0, 0.0, 0.0);
+        parent.set_rotation_2d(std::f32::consts::FRAC_PI_2);
+        parent.set_scale(Vector3::new(2.0, 2.0, 2.0));
+        let mut child = Transform::default();
+        child.set_translation_xyz(1.0, 0.0, 0.0);
+        // as computed by the `TransformSystem`, the child is at (10, 2, 0) in world space
+        child.global_matrix = parent.matrix() * child.matrix();
+        let world = |child: &Transform| {
+            (parent.matrix() * child.matrix())
+                .transform_point(&Point3::origin())
+                .coords
+        };
+
+        child.translate_global(Vector3::new(0.0, 2.0, 0.0));
+        assert_relative_eq!(world(&child), Vector3::new(10.0, 4.0, 0.0), epsilon = 1e-5);
+
+        child.move_towards_global(Vector3::new(10.0, 10.0, 0.0), 1.0);
+        assert_relative_eq!(world(&child), Vector3::new(10.0, 5.0, 0.0), epsilon = 1e-5);
+
+        child.set_global_translation(Vector3::new(0.0, 0.0, 0.0));
+        assert_relative_eq!(world(&child), Vector3::new(0.0, 0.0, 0.0), epsilon = 1e-5);
+        assert_relative_eq!(
+            child.global_matrix,
+            parent.matrix() * child.matrix(),
+            epsilon = 1e-5
+        );
+
+        // chained calls build on each other
+        child
+            .translate_global(Vector3::new(1.0, 0.0, 0.0))
+            .translate_global(Vector3::new(1.0, 0.0, 0.0))
+            .move_towards_global(Vector3::new(2.0, 10.0, 0.0), 3.0);
+        assert_relative_eq!(world(&child), Vector3::new(2.0, 3.0, 0.0), epsilon = 1e-5);
+    }
+
+    #[test]
+    fn lerp_and_slerp() {
+        let mut from = Transform::default();
+        from.set_scale(Vector3::new(1.0, 1.0, 1.0));
+        let mut to = Transform::default();
+        to.set_translation_xyz(2.0, 4.0, 6.0);
+        to.set_scale(Vector3::new(3.0, 3.0, 3.0));
+        to.set_rotation_2d(1.0);
+
+        for transform in &[from.lerp(&to, 0.5), from.slerp(&to, 0.5)] {
+            assert_relative_eq!(*transform.translation(), Vector3::new(1.0, 2.0, 3.0));
+            assert_relative_eq!(*transform.scale(), Vector3::new(2.0, 2.0, 2.0));
+            assert_relative_eq!(transform.rotation().angle(), 0.5, epsilon = 1e-5);
+        }
+        assert_relative_eq!(*from.slerp(&to, 0.0).translation(), *from.translation());
+        assert_relative_eq!(
+            from.slerp(&to, 1.0).rotation().angle_to(to.rotation()),
+            0.0,
+            epsilon = 1e-3
+        );
+
+        // the negated quaternion is the same rotation
+        to.set_rotation(UnitQuaternion::new_unchecked(-from.rotation().into_inner()));
+        for transform in &[from.lerp(&to, 0.5), from.slerp(&to, 0.5)] {
+            assert_relative_eq!(transform.rotation().angle(), 0.0, epsilon = 1e-3);
+        }
+    }
+
+    #[test]
+    fn global_accessors() {
+        let mut parent = Transform::default();
+        parent.set_translation_xyz(1.0, 2.0, 3.0);
+        parent.set_rotation_2d(std::f32::consts::FRAC_PI_2);
+        parent.set_scale(Vector3::new(2.0, 3.0, 4.0));
+        let mut child = Transform::default();
+        child.set_translation_xyz(1.0, 1.0, 1.0);
+        child.set_scale(Vector3::new(0.5, 2.0, 1.0));
+        child.global_matrix = parent.matrix() * child.matrix();
+
+        // parent X is world Y and parent Y is world -X
+        assert_relative_eq!(
+            child.global_translation(),
+            Vector3::new(1.0 - 3.0, 2.0 + 2.0, 3.0 + 4.0),
+            epsilon = 1e-5
+        );
+        assert_relative_eq!(
+            child.global_scale(),
+            Vector3::new(1.0, 6.0, 4.0),
+            epsilon = 1e-5
+        );
+        assert_relative_eq!(
+            child.global_rotation().angle_to(parent.rotation()),
+            0.0,
+            epsilon = 1e-3
+        );
+
+        let local = Point3::new(1.0, 0.0, 0.0);
+        let world = child.transform_point(&local);
+        assert_relative_eq!(world.coords, Vector3::new(-2.0, 5.0, 7.0), epsilon = 1e-5);
+        assert_relative_eq!(
+            child.inverse_transform_point(&world).unwrap(),
+            local,
+            epsilon = 1e-5
+        );
+
+        child.set_scale(Vector3::new(0.0, 1.0, 1.0));
+        child.global_matrix = parent.matrix() * child.matrix();
+        assert!(child.inverse_transform_point(&world).is_none());
+        assert_eq!(child.global_rotation(), UnitQuaternion::identity());
+    }
+
+    #[test]
+    fn global_rotation_removes_shear() {
+        let mut parent = Transform::default();
+        parent.set_scale(Vector3::new(4.0, 1.0, 1.0));
+        let mut child = Transform::default();
+        child.set_rotation_2d(std::f32::consts::FRAC_PI_4);
+        child.global_matrix = parent.matrix() * child.matrix();
+
+        let rotation = child.global_rotation();
+        assert_relative_eq!(rotation.norm(), 1.0, epsilon = 1e-6);
+        // the X axis of the child is kept
+        let x = child.global_matrix().column(0).xyz().normalize();
+        assert_relative_eq!(rotation * Vector3::x(), x, epsilon = 1e-5);
+
+        // mirrored
+        child.set_rotation_2d(0.0);
+        child.set_scale(Vector3::new(-1.0, 1.0, 1.0));
+        child.global_matrix = child.matrix();
+        assert_relative_eq!(child.global_scale(), Vector3::new(-1.0, 1.0, 1.0));
+        assert_eq!(child.global_rotation(), UnitQuaternion::identity());
+    }
+
     #[test]
     fn is_finite() {
         let mut transform = Transform::default();
@@ -1,7 +1,8 @@
 //! `amethyst` transform ecs module
 
-pub use self::{bundle::TransformBundle, components::*, systems::*};
+pub use self::{bundle::TransformBundle, components::*, systems::*, world_position::WorldPosition};
 
 pub mod bundle;
 pub mod components;
 pub mod systems;
+pub mod world_position;
@@ -4,14 +4,17 @@ use crate::{
     ecs::{
         hibitset::BitSet,
         prelude::{
-            ComponentEvent, Entities, Join, ReadExpect, ReadStorage, ReaderId, System, SystemData,
-            World, WriteStorage,
+            ComponentEvent, Entities, Entity, Join, Read, ReadExpect, ReadStorage, ReaderId,
+            System, SystemData, World, WriteStorage,
         },
     },
-    SystemDesc,
+    math::Matrix4,
+    timing::Time,
+    ArcThreadPool, SystemDesc,
 };
+use rayon::prelude::*;
 
-use crate::transform::{HierarchyEvent, Parent, ParentHierarchy, Transform};
+use crate::transform::{HierarchyEvent, InterpolatedTransform, Parent, ParentHierarchy, Transform};
 
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
@@ -33,12 +36,21 @@ impl<'a, 'b> SystemDesc<'a, 'b, TransformSystem> for TransformSystemDesc {
     }
 }
 
+/// Levels with fewer entities than this are propagated on the current thread.
+const MIN_PARALLEL_LEVEL: usize = 1024;
+
 /// Handles updating `global_matrix` field from `Transform` components.
+///
+/// Entities with a `Parent` are grouped by their depth in the hierarchy. Every level only
+/// depends on the levels above it, so when the `ArcThreadPool` resource is present, large levels
+/// are computed in parallel. The resulting matrices are identical to computing them one by one.
 #[derive(Debug)]
 pub struct TransformSystem {
     local_modified: BitSet,
     locals_events_id: ReaderId<ComponentEvent>,
     parent_events_id: ReaderId<HierarchyEvent>,
+    levels: Vec<Vec<Entity>>,
+    levels_dirty: bool,
 }
 
 impl TransformSystem {
@@ -51,8 +63,67 @@ impl TransformSystem {
             local_modified: BitSet::default(),
             locals_events_id,
             parent_events_id,
+            levels: Vec::new(),
+            levels_dirty: true,
         }
     }
+
+    /// Groups the entities of the hierarchy by their depth, children of roots being level 0.
+    fn rebuild_levels(&mut self, hierarchy: &ParentHierarchy, parents: &ReadStorage<'_, Parent>) {
+        let mut depths = Vec::<Option<usize>>::new();
+        self.levels.iter_mut().for_each(Vec::clear);
+        // `all` is sorted so that parents come before their children.
+        for entity in hierarchy.all() {
+            let depth = parents
+                .get(*entity)
+                .and_then(|parent| depths.get(parent.entity.id() as usize).cloned().flatten())
+                .map_or(0, |depth| depth + 1);
+            let index = entity.id() as usize;
+            if depths.len() <= index {
+                depths.resize(index + 1, None);
+            }
+            depths[index] = Some(depth);
+            if self.levels.len() <= depth {
+                self.levels.resize_with(depth + 1, Vec::new);
+            }
+            self.levels[depth].push(*entity);
+        }
+        while self.levels.last().map_or(false, Vec::is_empty) {
+            self.levels.pop();
+        }
+        self.levels_dirty = false;
+    }
+}
+
+/// Computes the global matrix of a child entity if it or its parent was modified.
+///
+/// The global matrix of a parent which was not recomputed may have been blended by the
+/// `TransformInterpolationSystem`, children are computed from its matrix before blending.
+fn propagate(
+    entity: Entity,
+    locals: &WriteStorage<'_, Transform>,
+    parents: &ReadStorage<'_, Parent>,
+    interpolated: &ReadStorage<'_, InterpolatedTransform>,
+    modified: &BitSet,
+) -> Option<(Entity, Matrix4<f32>)> {
+    let parent = parents.get(entity)?;
+    let parent_modified = modified.contains(parent.entity.id());
+    if !modified.contains(entity.id()) && !parent_modified {
+        return None;
+    }
+    let local = locals.get(entity)?;
+    let parent_global = locals.get(parent.entity).map(|parent_local| {
+        interpolated
+            .get(parent.entity)
+            .and_then(|state| state.fixed_global.as_ref())
+            .filter(|_| !parent_modified)
+            .unwrap_or(&parent_local.global_matrix)
+    });
+    let global = match parent_global {
+        Some(parent_global) => parent_global * local.matrix(),
+        None => local.matrix(),
+    };
+    Some((entity, global))
 }
 
 impl<'a> System<'a> for TransformSystem {
@@ -61,8 +132,13 @@ impl<'a> System<'a> for TransformSystem {
         ReadExpect<'a, ParentHierarchy>,
         WriteStorage<'a, Transform>,
         ReadStorage<'a, Parent>,
+        ReadStorage<'a, InterpolatedTransform>,
+        Option<Read<'a, ArcThreadPool>>,
     );
-    fn run(&mut self, (entities, hierarchy, mut locals, parents): Self::SystemData) {
+    fn run(
+        &mut self,
+        (entities, hierarchy, mut locals, parents, interpolated, pool): Self::SystemData,
+    ) {
         #[cfg(feature = "profiler")]
         profile_scope!("transform_system");
 
@@ -81,12 +157,14 @@ impl<'a> System<'a> for TransformSystem {
         for event in hierarchy.changed().read(&mut self.parent_events_id) {
             match *event {
                 HierarchyEvent::Removed(entity) => {
+                    self.levels_dirty = true;
                     // Sometimes the user may have already deleted the entity.
                     // This is fine, so we'll ignore any errors this may give
                     // since it can only fail due to the entity already being dead.
                     let _ = entities.delete(entity);
                 }
                 HierarchyEvent::Modified(entity) => {
+                    self.levels_dirty = true;
                     self.local_modified.add(entity.id());
                 }
             }
@@ -111,28 +189,46 @@ impl<'a> System<'a> for TransformSystem {
             self.local_modified.add(id);
         });
 
-        // Compute transforms with parents.
-        for entity in hierarchy.all() {
-            let self_dirty = self.local_modified.contains(entity.id());
-            if let Some(parent) = parents.get(*entity) {
-                let parent_dirty = self.local_modified.contains(parent.entity.id());
-                if parent_dirty || self_dirty {
-                    let combined_transform = {
-                        let local = locals.get(*entity);
-                        if local.is_none() {
-                            continue;
-                        }
-                        let local = local.unwrap();
-                        if let Some(parent_global) = locals.get(parent.entity) {
-                            parent_global.global_matrix * local.matrix()
-                        } else {
-                            local.matrix()
-                        }
-                    };
-                    self.local_modified.add(entity.id());
-                    locals.get_mut(*entity).expect("unreachable: We know this entity has a local because is was just modified.").global_matrix = combined_transform;
+        // Compute transforms with parents, one level at a time.
+        if self.levels_dirty {
+            self.rebuild_levels(&hierarchy, &parents);
+        }
+        let mut globals = Vec::new();
+        let levels = if self.local_modified.is_empty() {
+            &[][..]
+        } else {
+            &self.levels[..]
+        };
+        for level in levels {
+            {
+                let modified = &self.local_modified;
+                let locals = &locals;
+                let parents = &parents;
+                let interpolated = &interpolated;
+                match &pool {
+                    Some(pool) if level.len() >= MIN_PARALLEL_LEVEL => {
+                        globals = pool.install(|| {
+                            level
+                                .par_iter()
+                                .with_min_len(MIN_PARALLEL_LEVEL / 4)
+                                .filter_map(|entity| {
+                                    propagate(*entity, locals, parents, interpolated, modified)
+                                })
+                                .collect()
+                        })
+                    }
+                    _ => globals.extend(level.iter().filter_map(|entity| {
+                        propagate(*entity, locals, parents, interpolated, modified)
+                    })),
                 }
             }
+            for (entity, global) in globals.drain(..) {
+                self.local_modified.add(entity.id());
+                locals
+                    .get_mut(entity)
+                    .expect("unreachable: We know this entity has a local because is was just modified.")
+                    .global_matrix = global;
+            }
         }
 
         // Clear the local event reader.
@@ -140,18 +236,118 @@ impl<'a> System<'a> for TransformSystem {
     }
 }
 
+/// Builds a `TransformInterpolationSystem`.
+#[derive(Default, Debug)]
+pub struct TransformInterpolationSystemDesc;
+
+impl<'a, 'b> SystemDesc<'a, 'b, TransformInterpolationSystem> for TransformInterpolationSystemDesc {
+    fn build(self, world: &mut World) -> TransformInterpolationSystem {
+        <TransformInterpolationSystem as System<'_>>::SystemData::setup(world);
+
+        let locals_events_id = WriteStorage::<Transform>::fetch(&world).register_reader();
+
+        TransformInterpolationSystem::new(locals_events_id)
+    }
+}
+
+/// Blends the global matrix of entities with an `InterpolatedTransform` between their transforms
+/// of the last two fixed updates, by `Time::interpolation_alpha`.
+///
+/// Has to run after the `TransformSystem`. The global matrices computed by the `TransformSystem`
+/// are kept aside, to blend them again the next frame and to compute the global matrices of
+/// children from them.
+///
+/// With the `storage-event-control` feature, the blended matrices are written without flagging
+/// the transforms as modified. Without it, every blended transform is flagged each frame.
+#[derive(Debug)]
+pub struct TransformInterpolationSystem {
+    recomputed: BitSet,
+    locals_events_id: ReaderId<ComponentEvent>,
+}
+
+impl TransformInterpolationSystem {
+    /// Creates a new transform interpolation system.
+    pub fn new(locals_events_id: ReaderId<ComponentEvent>) -> TransformInterpolationSystem {
+        TransformInterpolationSystem {
+            recomputed: BitSet::default(),
+            locals_events_id,
+        }
+    }
+}
+
+impl<'a> System<'a> for TransformInterpolationSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, InterpolatedTransform>,
+    );
+
+    fn run(&mut self, (entities, time, mut locals, mut interpolated): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("transform_interpolation_system");
+
+        // The `TransformSystem` recomputed the global matrix of every flagged transform.
+        self.recomputed.clear();
+        locals
+            .channel()
+            .read(&mut self.locals_events_id)
+            .for_each(|event| match event {
+                ComponentEvent::Inserted(id) | ComponentEvent::Modified(id) => {
+                    self.recomputed.add(*id);
+                }
+                ComponentEvent::Removed(_id) => {}
+            });
+
+        let alpha = time.interpolation_alpha();
+        #[cfg(feature = "storage-event-control")]
+        locals.set_event_emission(false);
+        for (entity, local, state) in (&*entities, &mut locals, &mut interpolated).join() {
+            if state.fixed_global.is_none() || self.recomputed.contains(entity.id()) {
+                state.fixed_global = Some(local.global_matrix);
+            }
+            let (previous, current, fixed_global) =
+                match (&state.previous, &state.current, &state.fixed_global) {
+                    (Some(previous), Some(current), Some(fixed_global)) => {
+                        (previous, current, fixed_global)
+                    }
+                    _ => continue,
+                };
+            // global = parent * local, so parent = global * local^-1
+            if let Some(local_inverse) = local.matrix().try_inverse() {
+                let blended = previous.slerp(current, alpha);
+                local.global_matrix = fixed_global * local_inverse * blended.matrix();
+            }
+        }
+        #[cfg(feature = "storage-event-control")]
+        locals.set_event_emission(true);
+        // The blended transforms have not been recomputed by the `TransformSystem`.
+        #[cfg(not(feature = "storage-event-control"))]
+        locals
+            .channel()
+            .read(&mut self.locals_events_id)
+            .for_each(drop);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
+        approx::assert_relative_eq,
         ecs::{
-            prelude::{Builder, World, WorldExt},
+            prelude::{Builder, Join, World, WorldExt},
             shred::RunNow,
         },
         math::{Matrix4, Quaternion, Unit, Vector3},
-        transform::{Parent, Transform, TransformSystem, TransformSystemDesc},
-        SystemDesc,
+        timing::Time,
+        transform::{
+            InterpolatedTransform, Parent, Transform, TransformInterpolationSystemDesc,
+            TransformSystem, TransformSystemDesc,
+        },
+        ArcThreadPool, SystemDesc,
     };
     use specs_hierarchy::{Hierarchy, HierarchySystem};
+    use std::sync::Arc;
 
     // If this works, then all other tests should work.
     #[test]
@@ -178,6 +374,64 @@ mod tests {
         (world, hs, ts)
     }
 
+    // A deep chain, and a root with wide levels large enough to be split across threads.
+    fn forest(world: &mut World) {
+        let mut parent = None;
+        for i in 0..2000 {
+            let mut local = Transform::default();
+            local.set_translation_xyz(i as f32 * 0.1, 1.0, -0.5);
+            local.set_rotation_y_axis(0.01 * i as f32);
+            let mut builder = world.create_entity().with(local);
+            if let Some(entity) = parent {
+                builder = builder.with(Parent { entity });
+            }
+            parent = Some(builder.build());
+        }
+
+        let mut root = Transform::default();
+        root.set_scale(Vector3::new(2.0, 0.5, 1.5));
+        let root = world.create_entity().with(root).build();
+        for i in 0..5000 {
+            let mut local = Transform::default();
+            local.set_translation_xyz(i as f32, -(i as f32), 0.3);
+            local.set_rotation_z_axis(0.001 * i as f32);
+            let child = world
+                .create_entity()
+                .with(local.clone())
+                .with(Parent { entity: root })
+                .build();
+            world
+                .create_entity()
+                .with(local)
+                .with(Parent { entity: child })
+                .build();
+        }
+    }
+
+    #[test]
+    fn parallel_propagation_matches_serial() {
+        let globals = |pool: Option<ArcThreadPool>| {
+            let (mut world, mut hs, mut system) = transform_world();
+            if let Some(pool) = pool {
+                world.insert(pool);
+            }
+            forest(&mut world);
+            hs.run_now(&world);
+            system.run_now(&world);
+            world
+                .read_storage::<Transform>()
+                .join()
+                .map(|local| *local.global_matrix())
+                .collect::<Vec<_>>()
+        };
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+
+        assert_eq!(globals(None), globals(Some(Arc::new(pool))));
+    }
+
     fn together(global_matrix: Matrix4<f32>, local_matrix: Matrix4<f32>) -> Matrix4<f32> {
         global_matrix * local_matrix
     }
@@ -486,4 +740,142 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn interpolates_between_fixed_updates() {
+        let (mut world, mut hs, mut system) = transform_world();
+        let mut interpolation = TransformInterpolationSystemDesc::default().build(&mut world);
+        let mut time = Time::default();
+        time.set_fixed_seconds(0.25);
+        world.insert(time);
+
+        let mut parent = Transform::default();
+        parent.set_scale(Vector3::new(2.0, 2.0, 2.0));
+        let parent = world.create_entity().with(parent).build();
+        let entity = world
+            .create_entity()
+            .with(Transform::default())
+            .with(InterpolatedTransform::new())
+            .with(Parent { entity: parent })
+            .build();
+        let mut transform_reader = world.write_storage::<Transform>().register_reader();
+
+        // moves the entity by one unit per fixed update, returns its global X coordinate
+        let mut frame = |world: &mut World, delta: f32| {
+            world.write_resource::<Time>().set_delta_seconds(delta);
+            world.write_resource::<Time>().start_fixed_update();
+            while world.write_resource::<Time>().step_fixed_update() {
+                world
+                    .write_storage::<Transform>()
+                    .get_mut(entity)
+                    .unwrap()
+                    .prepend_translation_x(1.0);
+                InterpolatedTransform::record_fixed_update(world);
+            }
+            world.write_resource::<Time>().finish_fixed_update();
+            hs.run_now(world);
+            system.run_now(world);
+            interpolation.run_now(world);
+            world.maintain();
+            world
+                .read_storage::<Transform>()
+                .get(entity)
+                .unwrap()
+                .global_matrix()
+                .column(3)
+                .x
+        };
+
+        assert_relative_eq!(frame(&mut world, 0.25), 2.0);
+        world
+            .read_storage::<Transform>()
+            .channel()
+            .read(&mut transform_reader)
+            .for_each(drop);
+        assert_relative_eq!(frame(&mut world, 0.125), 2.0);
+        // blending without a fixed update does not flag the transform as modified
+        #[cfg(feature = "storage-event-control")]
+        assert_eq!(
+            world
+                .read_storage::<Transform>()
+                .channel()
+                .read(&mut transform_reader)
+                .count(),
+            0
+        );
+        // the new fixed update is rendered blended with the previous one
+        assert_relative_eq!(frame(&mut world, 0.125), 2.0);
+        assert_relative_eq!(frame(&mut world, 0.125), 3.0);
+        assert_relative_eq!(frame(&mut world, 0.125), 4.0);
+        // two fixed updates in a frame blend between the last two of them
+        assert_relative_eq!(frame(&mut world, 0.625), 9.0);
+        // the local transform keeps the state of the last fixed update
+        let translation = *world
+            .read_storage::<Transform>()
+            .get(entity)
+            .unwrap()
+            .translation();
+        assert_relative_eq!(translation.x, 5.0);
+    }
+
+    #[test]
+    fn children_of_interpolated_entities_use_the_fixed_update() {
+        let (mut world, mut hs, mut system) = transform_world();
+        let mut interpolation = TransformInterpolationSystemDesc::default().build(&mut world);
+        let mut time = Time::default();
+        time.set_fixed_seconds(0.25);
+        world.insert(time);
+
+        let parent = world
+            .create_entity()
+            .with(Transform::default())
+            .with(InterpolatedTransform::new())
+            .build();
+        let child = world
+            .create_entity()
+            .with(Transform::default())
+            .with(Parent { entity: parent })
+            .build();
+
+        // moves the parent by one unit per fixed update, returns the global X coordinates of the
+        // parent and the child
+        let mut frame = |world: &mut World, delta: f32| {
+            world.write_resource::<Time>().set_delta_seconds(delta);
+            world.write_resource::<Time>().start_fixed_update();
+            while world.write_resource::<Time>().step_fixed_update() {
+                world
+                    .write_storage::<Transform>()
+                    .get_mut(parent)
+                    .unwrap()
+                    .prepend_translation_x(1.0);
+                InterpolatedTransform::record_fixed_update(world);
+            }
+            world.write_resource::<Time>().finish_fixed_update();
+            hs.run_now(world);
+            system.run_now(world);
+            interpolation.run_now(world);
+            world.maintain();
+            let transforms = world.read_storage::<Transform>();
+            let x = |entity| transforms.get(entity).unwrap().global_matrix().column(3).x;
+            (x(parent), x(child))
+        };
+
+        let (parent_x, child_x) = frame(&mut world, 0.25);
+        assert_relative_eq!(parent_x, 1.0);
+        assert_relative_eq!(child_x, 1.0);
+        // the parent is rendered at the previous fixed update, the child follows the last one
+        let (parent_x, child_x) = frame(&mut world, 0.25);
+        assert_relative_eq!(parent_x, 1.0);
+        assert_relative_eq!(child_x, 2.0);
+
+        // moving the child alone does not compose it with the blended parent
+        world
+            .write_storage::<Transform>()
+            .get_mut(child)
+            .unwrap()
+            .set_translation_x(10.0);
+        let (parent_x, child_x) = frame(&mut world, 0.125);
+        assert_relative_eq!(parent_x, 1.5);
+        assert_relative_eq!(child_x, 12.0);
+    }
 }
@@ -0,0 +1,160 @@
+//! World space queries walking the hierarchy on demand.
+
+use crate::{
+    ecs::{
+        prelude::{Entity, ReadStorage},
+        shred::{ResourceId, SystemData, World},
+    },
+    math::{Matrix4, Point3, UnitQuaternion, Vector3},
+    transform::{components::decompose, Parent, Transform},
+};
+
+/// `SystemData` answering where an entity is in world space.
+///
+/// Unlike the global accessors of `Transform`, which read the global matrix computed by the last
+/// `TransformSystem` run, this multiplies the local transforms of the entity and its parents on
+/// every call. It is exact in the middle of a frame, after transforms were changed but before
+/// they are propagated, at the cost of walking the hierarchy.
+#[derive(SystemData)]
+#[allow(missing_debug_implementations)]
+pub struct WorldPosition<'a> {
+    transforms: ReadStorage<'a, Transform>,
+    parents: ReadStorage<'a, Parent>,
+}
+
+impl<'a> WorldPosition<'a> {
+    /// Returns the matrix from the local space of `entity` to world space, or `None` if it has no
+    /// `Transform`.
+    ///
+    /// Like the `TransformSystem`, parents without a `Transform` end the hierarchy.
+    pub fn global_matrix(&self, entity: Entity) -> Option<Matrix4<f32>> {
+        let mut matrix = self.transforms.get(entity)?.matrix();
+        let mut current = entity;
+        while let Some(parent) = self.parents.get(current) {
+            match self.transforms.get(parent.entity) {
+                Some(transform) => matrix = transform.matrix() * matrix,
+                None => break,
+            }
+            current = parent.entity;
+        }
+        Some(matrix)
+    }
+
+    /// Returns the position of `entity` in world space.
+    pub fn translation(&self, entity: Entity) -> Option<Vector3<f32>> {
+        self.global_matrix(entity)
+            .map(|matrix| matrix.column(3).xyz())
+    }
+
+    /// Returns the orientation of `entity` in world space, see `Transform::global_rotation` for
+    /// the caveats of shear.
+    pub fn rotation(&self, entity: Entity) -> Option<UnitQuaternion<f32>> {
+        self.global_matrix(entity)
+            .map(|matrix| decompose(&matrix).0)
+    }
+
+    /// Returns the scale of `entity` in world space, see `Transform::global_scale` for the
+    /// caveats of shear.
+    pub fn scale(&self, entity: Entity) -> Option<Vector3<f32>> {
+        self.global_matrix(entity)
+            .map(|matrix| decompose(&matrix).1)
+    }
+
+    /// Transforms a point from the local space of `entity` to world space.
+    pub fn transform_point(&self, entity: Entity, point: &Point3<f32>) -> Option<Point3<f32>> {
+        self.global_matrix(entity)
+            .map(|matrix| matrix.transform_point(point))
+    }
+
+    /// Transforms a point from world space to the local space of `entity`.
+    ///
+    /// Returns `None` if `entity` has no `Transform` or its global matrix is not invertible.
+    pub fn inverse_transform_point(
+        &self,
+        entity: Entity,
+        point: &Point3<f32>,
+    ) -> Option<Point3<f32>> {
+        self.global_matrix(entity)?
+            .try_inverse()
+            .map(|inverse| inverse.transform_point(point))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        approx::*,
+        ecs::{
+            prelude::{Builder, WorldExt},
+            shred::RunNow,
+        },
+        transform::TransformSystemDesc,
+        SystemDesc,
+    };
+    use specs_hierarchy::HierarchySystem;
+
+    #[test]
+    fn matches_propagated_transforms() {
+        let mut world = World::new();
+        let mut hierarchy = HierarchySystem::<Parent>::new(&mut world);
+        let mut system = TransformSystemDesc::default().build(&mut world);
+        hierarchy.setup(&mut world);
+        system.setup(&mut world);
+
+        let mut root = Transform::default();
+        root.set_translation_xyz(1.0, 2.0, 3.0);
+        root.set_rotation_euler(0.3, 0.0, 0.5);
+        root.set_scale(Vector3::new(2.0, 1.0, 0.5));
+        let root = world.create_entity().with(root).build();
+        let mut middle = Transform::default();
+        middle.set_translation_xyz(-1.0, 0.5, 2.0);
+        middle.set_rotation_2d(1.2);
+        middle.set_scale(Vector3::new(1.0, 3.0, 1.0));
+        let middle = world
+            .create_entity()
+            .with(middle)
+            .with(Parent::new(root))
+            .build();
+        let mut leaf = Transform::default();
+        leaf.set_translation_xyz(0.0, 1.0, -1.0);
+        let leaf = world
+            .create_entity()
+            .with(leaf)
+            .with(Parent::new(middle))
+            .build();
+        let orphan = world.create_entity().with(Transform::default()).build();
+        let empty = world.create_entity().build();
+
+        // before propagation
+        let point = Point3::new(0.5, -0.5, 2.0);
+        let (matrix, world_point) = {
+            let positions = world.system_data::<WorldPosition<'_>>();
+            let matrix = positions.global_matrix(leaf).unwrap();
+            let world_point = positions.transform_point(leaf, &point).unwrap();
+            assert_relative_eq!(
+                positions
+                    .inverse_transform_point(leaf, &world_point)
+                    .unwrap(),
+                point,
+                epsilon = 1e-4
+            );
+            assert_eq!(positions.global_matrix(orphan), Some(Matrix4::identity()));
+            assert_eq!(positions.global_matrix(empty), None);
+            (matrix, world_point)
+        };
+
+        hierarchy.run_now(&world);
+        system.run_now(&world);
+
+        let transforms = world.read_storage::<Transform>();
+        let leaf = transforms.get(leaf).unwrap();
+        assert_relative_eq!(matrix, *leaf.global_matrix(), epsilon = 1e-5);
+        assert_relative_eq!(leaf.transform_point(&point), world_point, epsilon = 1e-5);
+        assert_relative_eq!(
+            leaf.global_translation(),
+            matrix.column(3).xyz(),
+            epsilon = 1e-5
+        );
+    }
+}
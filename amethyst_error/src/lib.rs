@@ -20,25 +20,81 @@
 
 pub use backtrace::Backtrace;
 use std::{
+    any::Any,
     borrow::Cow,
+    cell::Cell,
     env, error, fmt, result,
     sync::atomic::{self, AtomicUsize},
 };
 
 const RUST_BACKTRACE: &str = "RUST_BACKTRACE";
 
+/// Keys of the metadata Amethyst attaches to its errors, see [`Error::with_meta`](Error::with_meta).
+pub mod keys {
+    /// Name of the asset being loaded, as a `String`.
+    pub const ASSET_NAME: &str = "asset";
+    /// Name of the format importing an asset, as a `&'static str`.
+    pub const FORMAT: &str = "format";
+    /// Path of the file being read, as a `PathBuf`.
+    pub const PATH: &str = "path";
+    /// Entity being worked on, as a `String` formatted from its `Debug` representation.
+    pub const ENTITY: &str = "entity";
+}
+
+/// A value attached to an [`Error`](Error) with [`with_meta`](Error::with_meta).
+///
+/// Implemented for every type which is `Debug`, `Send` and `Sync`.
+pub trait MetaValue: Any + fmt::Debug + Send + Sync {
+    /// Returns the value as `Any`, so it can be downcast.
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T> MetaValue for T
+where
+    T: Any + fmt::Debug + Send + Sync,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
 /// Internal parts of `Error`.
 #[derive(Debug)]
 struct Inner<T: ?Sized> {
     source: Option<Box<Error>>,
     backtrace: Option<Backtrace>,
+    meta: Vec<(&'static str, Box<dyn MetaValue>)>,
     error: T,
 }
 
 /// The error type used by Amethyst.
 ///
-/// Wraps error diagnostics like messages and other errors, and keeps track of causal chains and
-/// backtraces.
+/// Wraps error diagnostics like messages and other errors, and keeps track of causal chains,
+/// metadata and backtraces.
+///
+/// `Display` shows the message of this error only. The alternate flag, `{:#}`, shows the whole
+/// chain of causes with their metadata, each cause indented below the error it caused.
+///
+/// # Examples
+///
+/// ```rust
+/// use amethyst_error::{keys, Error, ResultExt};
+///
+/// fn read_config() -> Result<(), Error> {
+///     Err(Error::from_string("file not found")).with_meta(keys::PATH, || "config.ron")
+/// }
+///
+/// let e = read_config()
+///     .with_message(|| format!("Failed to load the {} config", "display"))
+///     .expect_err("no error");
+///
+/// assert_eq!("Failed to load the display config", e.to_string());
+/// assert_eq!(
+///     "Failed to load the display config\n  caused by: file not found\n    path: \"config.ron\"",
+///     format!("{:#}", e),
+/// );
+/// assert_eq!(Some(&"config.ron"), e.meta::<&str>(keys::PATH));
+/// ```
 pub struct Error {
     inner: Box<Inner<dyn error::Error + Send + Sync>>,
 }
@@ -55,20 +111,55 @@ impl Error {
             inner: Box::new(Inner {
                 source: None,
                 backtrace: new_backtrace(),
+                meta: Vec::new(),
                 error: Box::new(error),
             }),
         }
     }
 
     /// Update the source of an error.
+    ///
+    /// If the source has a backtrace, the backtrace of this error is dropped, so the backtrace of
+    /// the original error site is kept.
     pub fn with_source<S>(mut self, source: S) -> Self
     where
         S: 'static + Into<Error>,
     {
-        self.inner.source = Some(Box::new(source.into()));
+        let source = source.into();
+        if source.backtrace().is_some() {
+            self.inner.backtrace = None;
+        }
+        self.inner.source = Some(Box::new(source));
+        self
+    }
+
+    /// Attaches a value to this error under `key`, e.g. the path of the file that failed to load.
+    ///
+    /// See [`keys`](keys) for the keys used by Amethyst.
+    pub fn with_meta<V>(mut self, key: &'static str, value: V) -> Self
+    where
+        V: MetaValue,
+    {
+        self.inner.meta.push((key, Box::new(value)));
         self
     }
 
+    /// Get the first value of type `V` attached under `key` to this error or one of its causes.
+    pub fn meta<V>(&self, key: &str) -> Option<&V>
+    where
+        V: Any,
+    {
+        self.causes()
+            .flat_map(|e| e.inner.meta.iter())
+            .filter(|(k, _)| *k == key)
+            .find_map(|(_, value)| (**value).as_any().downcast_ref::<V>())
+    }
+
+    /// Iterate over the values attached to this error, not including its causes.
+    pub fn metadata(&self) -> impl Iterator<Item = (&'static str, &dyn MetaValue)> + '_ {
+        self.inner.meta.iter().map(|(key, value)| (*key, &**value))
+    }
+
     /// Construct a new error from a string.
     pub fn from_string<M>(message: M) -> Self
     where
@@ -90,14 +181,21 @@ impl Error {
             inner: Box::new(Inner {
                 source: None,
                 backtrace: new_backtrace(),
+                meta: Vec::new(),
                 error: Box::new(StringError(message.into())),
             }),
         }
     }
 
-    /// Get backtrace.
+    /// Get the backtrace of the original error site, which is the backtrace of the deepest cause
+    /// having one.
+    ///
+    /// Backtraces are only captured if the `RUST_BACKTRACE` environment variable is set. Errors
+    /// created by [`ResultExt`](ResultExt) to wrap an error that has one don't capture another.
     pub fn backtrace(&self) -> Option<&Backtrace> {
-        self.inner.backtrace.as_ref()
+        self.causes()
+            .filter_map(|e| e.inner.backtrace.as_ref())
+            .last()
     }
 
     /// Get the source of the error.
@@ -175,7 +273,28 @@ where
 
 impl fmt::Display for Error {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt::Display::fmt(&self.inner.error, fmt)
+        if !fmt.alternate() {
+            return fmt::Display::fmt(&self.inner.error, fmt);
+        }
+
+        for (depth, cause) in self.causes().enumerate() {
+            let indent = depth * 2;
+            if depth > 0 {
+                write!(fmt, "\n{:indent$}caused by: ", "", indent = indent)?;
+            }
+            write!(fmt, "{}", &cause.inner.error)?;
+            for (key, value) in cause.metadata() {
+                write!(
+                    fmt,
+                    "\n{:indent$}{}: {:?}",
+                    "",
+                    key,
+                    value,
+                    indent = indent + 2
+                )?;
+            }
+        }
+        Ok(())
     }
 }
 
@@ -220,6 +339,38 @@ where
     where
         C: FnOnce(&Error) -> D,
         D: Into<Error>;
+
+    /// Provide a context message for the result in case it is an error.
+    ///
+    /// Like [`with_context`](ResultExt::with_context), with a new error built from the message.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use amethyst_error::{Error, ResultExt};
+    ///
+    /// let path = "sprites.ron";
+    /// let e = Err::<(), _>(Error::from_string("failing"))
+    ///     .with_message(|| format!("Failed to load {}", path))
+    ///     .expect_err("no error");
+    ///
+    /// assert_eq!("Failed to load sprites.ron", e.to_string());
+    /// assert_eq!("failing", e.source().expect("no source").to_string());
+    /// ```
+    fn with_message<C, M>(self, message: C) -> Result<T, Error>
+    where
+        C: FnOnce() -> M,
+        M: Into<Cow<'static, str>>,
+    {
+        self.with_context(|_| Error::from_string(message()))
+    }
+
+    /// Attaches a value to the error in case the result is one, see
+    /// [`Error::with_meta`](Error::with_meta).
+    fn with_meta<C, V>(self, key: &'static str, value: C) -> Result<T, Error>
+    where
+        C: FnOnce() -> V,
+        V: MetaValue;
 }
 
 impl<T, E> ResultExt<T> for result::Result<T, E>
@@ -234,11 +385,24 @@ where
         match self {
             Err(e) => {
                 let e = e.into();
-                Err(chain(&e).into().with_source(e))
+                let context = if e.backtrace().is_some() {
+                    without_backtrace(|| chain(&e).into())
+                } else {
+                    chain(&e).into()
+                };
+                Err(context.with_source(e))
             }
             Ok(value) => Ok(value),
         }
     }
+
+    fn with_meta<C, V>(self, key: &'static str, value: C) -> Result<T, Error>
+    where
+        C: FnOnce() -> V,
+        V: MetaValue,
+    {
+        self.map_err(|e| e.into().with_meta(key, value()))
+    }
 }
 
 /// An iterator over all the causes for this error.
@@ -323,8 +487,31 @@ fn is_backtrace_enabled() -> bool {
 // 2: enabled
 static BACKTRACE_STATUS: AtomicUsize = AtomicUsize::new(0);
 
+thread_local! {
+    /// Set while creating an error wrapping one that already has a backtrace.
+    static SKIP_BACKTRACE: Cell<bool> = Cell::new(false);
+}
+
+/// Runs `f` without capturing backtraces for the errors it creates.
+fn without_backtrace<R>(f: impl FnOnce() -> R) -> R {
+    struct Restore(bool);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SKIP_BACKTRACE.with(|skip| skip.set(self.0));
+        }
+    }
+
+    let _restore = Restore(SKIP_BACKTRACE.with(|skip| skip.replace(true)));
+    f()
+}
+
 /// Constructs a new backtrace, if backtraces are enabled.
 fn new_backtrace() -> Option<Backtrace> {
+    if SKIP_BACKTRACE.with(Cell::get) {
+        return None;
+    }
+
     match BACKTRACE_STATUS.load(atomic::Ordering::Relaxed) {
         0 => {
             let enabled = is_backtrace_enabled();
@@ -415,6 +602,30 @@ mod tests {
         assert_eq!(e.source().map(|e| e.to_string()), Some(String::from("bar")));
     }
 
+    #[test]
+    fn test_meta() {
+        use super::keys;
+        use std::path::PathBuf;
+
+        let e = Err::<(), _>(Error::from_string("bottom"))
+            .with_meta(keys::PATH, || PathBuf::from("a.png"))
+            .with_message(|| "middle")
+            .with_meta(keys::ASSET_NAME, || String::from("a"))
+            .with_meta(keys::FORMAT, || 42u32)
+            .with_context(|_| Error::from_string("top"))
+            .expect_err("no error");
+
+        assert_eq!(Some(&PathBuf::from("a.png")), e.meta::<PathBuf>(keys::PATH));
+        assert_eq!(Some(&String::from("a")), e.meta::<String>(keys::ASSET_NAME));
+        assert_eq!(None, e.meta::<String>(keys::PATH));
+        assert_eq!(0, e.metadata().count());
+        assert_eq!(
+            "top\n  caused by: middle\n    asset: \"a\"\n    format: 42\n    \
+             caused by: bottom\n      path: \"a.png\"",
+            format!("{:#}", e)
+        );
+    }
+
     // Note: all backtrace tests have to be in the same test case since they
     // depend on the state of the global `BACKTRACE_STATUS`.
     #[test]
@@ -445,6 +656,19 @@ mod tests {
             .iter()
             .any(|n| n.ends_with("a_really_unique_name_42")));
 
+        // Wrapping keeps the backtrace of the original error site.
+        let wrapped = Err::<(), _>(a_really_unique_name_42())
+            .with_message(|| "wrapper")
+            .expect_err("no error");
+        assert!(wrapped.inner.backtrace.is_none());
+        assert!(std::ptr::eq(
+            wrapped.backtrace().expect("a backtrace"),
+            wrapped
+                .source()
+                .and_then(Error::backtrace)
+                .expect("a backtrace"),
+        ));
+
         // Test disabled.
         BACKTRACE_STATUS.store(1, atomic::Ordering::Relaxed);
         assert!(Error::from_string("an error").backtrace().is_none());
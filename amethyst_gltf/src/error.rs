@@ -30,7 +30,55 @@ pub enum Error {
     #[error(display = "Not implemented")]
     NotImplemented,
 
+    /// The file requires Draco mesh compression, which amethyst_gltf only supports with the
+    /// `draco` feature
+    #[error(
+        display = "Gltf requires KHR_draco_mesh_compression, which amethyst_gltf does not \
+                   support without the `draco` feature"
+    )]
+    DracoUnsupported,
+
+    /// A Draco compressed primitive could not be decoded
+    #[error(
+        display = "Draco compressed primitive {} of mesh {} could not be decoded: {}",
+        primitive,
+        mesh,
+        reason
+    )]
+    InvalidDraco {
+        /// Index of the mesh in the file
+        mesh: usize,
+        /// Index of the primitive in the mesh
+        primitive: usize,
+        /// What is wrong with the compressed data
+        reason: &'static str,
+    },
+
+    /// Image format other than PNG or JPEG
+    #[error(display = "Unsupported image format: {}", _0)]
+    UnsupportedImage(String),
+
+    /// A sparse accessor overrides an element past its end
+    #[error(display = "Sparse index {} of accessor {} is out of range", _1, _0)]
+    SparseIndexOutOfRange(usize, usize),
+
     /// A loaded glTF buffer is not of the required length.
     #[error(display = "Loaded buffer does not match required length")]
     BufferLength(gltf::json::Path),
+
+    /// A skin has more joints than allowed by `GltfSceneOptions::max_joints`
+    #[error(
+        display = "Skin {} has {} joints, but at most {} are allowed",
+        skin,
+        joints,
+        max_joints
+    )]
+    TooManyJoints {
+        /// Index of the skin in the file
+        skin: usize,
+        /// Number of joints of the skin
+        joints: usize,
+        /// Maximum number of joints
+        max_joints: usize,
+    },
 }
@@ -0,0 +1,302 @@
+//! Reading of sparse accessors, which the `gltf` readers only read from their base buffer view,
+//! and of accessors decoded from Draco compressed primitives, which they can not read at all.
+
+use amethyst_error::Error;
+use gltf::{
+    accessor::{sparse::IndexType, DataType},
+    json, Semantic,
+};
+
+use super::Buffers;
+use crate::error;
+
+/// Vertex attribute types that can be built from the components of an accessor element.
+pub trait FromComponents: Sized {
+    /// Build a value from the components of one element, converted to `f32`.
+    fn from_components(components: &[f32]) -> Self;
+}
+
+impl FromComponents for [f32; 2] {
+    fn from_components(components: &[f32]) -> Self {
+        [component(components, 0, 0.), component(components, 1, 0.)]
+    }
+}
+
+impl FromComponents for [f32; 3] {
+    fn from_components(components: &[f32]) -> Self {
+        [
+            component(components, 0, 0.),
+            component(components, 1, 0.),
+            component(components, 2, 0.),
+        ]
+    }
+}
+
+/// Missing components default to 1, so RGB colors become opaque RGBA colors.
+impl FromComponents for [f32; 4] {
+    fn from_components(components: &[f32]) -> Self {
+        [
+            component(components, 0, 0.),
+            component(components, 1, 0.),
+            component(components, 2, 0.),
+            component(components, 3, 1.),
+        ]
+    }
+}
+
+impl FromComponents for [u16; 4] {
+    fn from_components(components: &[f32]) -> Self {
+        let joint = |index| component(components, index, 0.) as u16;
+        [joint(0), joint(1), joint(2), joint(3)]
+    }
+}
+
+fn component(components: &[f32], index: usize, default: f32) -> f32 {
+    components.get(index).cloned().unwrap_or(default)
+}
+
+/// Read the attribute of a primitive if it is stored in a sparse accessor or was decoded from
+/// Draco compressed data.
+///
+/// Returns `None` for dense attributes, those are read with the `gltf` readers.
+pub fn read_sparse_attribute<T: FromComponents>(
+    primitive: &gltf::Primitive<'_>,
+    semantic: &Semantic,
+    buffers: &Buffers,
+) -> Result<Option<Vec<T>>, Error> {
+    read_if_sparse(primitive.get(semantic), buffers)
+}
+
+/// Read the accessor if it is sparse or was decoded from Draco compressed data.
+pub fn read_if_sparse<T: FromComponents>(
+    accessor: Option<gltf::Accessor<'_>>,
+    buffers: &Buffers,
+) -> Result<Option<Vec<T>>, Error> {
+    let accessor = match accessor {
+        Some(accessor) => accessor,
+        None => return Ok(None),
+    };
+    let dimensions = accessor.dimensions().multiplicity();
+    let elements = |components: &[f32]| {
+        components
+            .chunks(dimensions)
+            .map(T::from_components)
+            .collect()
+    };
+    if let Some(components) = buffers.decoded(&accessor) {
+        Ok(Some(elements(components)))
+    } else if accessor.sparse().is_some() {
+        Ok(Some(elements(&read_components(&accessor, buffers)?)))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Read all components of an accessor as `f32`, resolving sparse storage.
+///
+/// Starts from the base buffer view of the accessor, or zeros if it has none, and then applies
+/// the sparse overrides. Normalized integers are mapped to `0..=1` or `-1..=1`.
+pub fn read_components(
+    accessor: &gltf::Accessor<'_>,
+    buffers: &Buffers,
+) -> Result<Vec<f32>, Error> {
+    let dimensions = accessor.dimensions().multiplicity();
+    let data_type = accessor.data_type();
+    let element_size = dimensions * data_type.size();
+    let count = accessor.count();
+    let path = || json::Path::new().field("accessors").index(accessor.index());
+
+    let mut components = vec![0.; count * dimensions];
+    if let Some(view) = accessor.view() {
+        let data = buffers
+            .view(&view)
+            .ok_or_else(|| error::Error::BufferLength(path()))?;
+        let stride = view.stride().unwrap_or(element_size);
+        for (index, element) in components.chunks_mut(dimensions).enumerate() {
+            let start = accessor.offset() + index * stride;
+            let bytes = data
+                .get(start..start + element_size)
+                .ok_or_else(|| error::Error::BufferLength(path()))?;
+            read_element(bytes, data_type, accessor.normalized(), element);
+        }
+    }
+
+    if let Some(sparse) = accessor.sparse() {
+        let indices = sparse.indices();
+        let index_size = match indices.index_type() {
+            IndexType::U8 => 1,
+            IndexType::U16 => 2,
+            IndexType::U32 => 4,
+        };
+        let index_data = buffers
+            .view(&indices.view())
+            .and_then(|data| data.get(indices.offset() as usize..))
+            .ok_or_else(|| error::Error::BufferLength(path()))?;
+        let values = sparse.values();
+        let value_data = buffers
+            .view(&values.view())
+            .and_then(|data| data.get(values.offset() as usize..))
+            .ok_or_else(|| error::Error::BufferLength(path()))?;
+
+        for i in 0..sparse.count() as usize {
+            let index_bytes = index_data
+                .get(i * index_size..(i + 1) * index_size)
+                .ok_or_else(|| error::Error::BufferLength(path()))?;
+            let index = match indices.index_type() {
+                IndexType::U8 => index_bytes[0] as usize,
+                IndexType::U16 => u16::from_le_bytes([index_bytes[0], index_bytes[1]]) as usize,
+                IndexType::U32 => u32::from_le_bytes([
+                    index_bytes[0],
+                    index_bytes[1],
+                    index_bytes[2],
+                    index_bytes[3],
+                ]) as usize,
+            };
+            if index >= count {
+                return Err(error::Error::SparseIndexOutOfRange(accessor.index(), index).into());
+            }
+            let value_bytes = value_data
+                .get(i * element_size..(i + 1) * element_size)
+                .ok_or_else(|| error::Error::BufferLength(path()))?;
+            read_element(
+                value_bytes,
+                data_type,
+                accessor.normalized(),
+                &mut components[index * dimensions..(index + 1) * dimensions],
+            );
+        }
+    }
+    Ok(components)
+}
+
+fn read_element(bytes: &[u8], data_type: DataType, normalized: bool, element: &mut [f32]) {
+    let size = data_type.size();
+    for (component, bytes) in element.iter_mut().zip(bytes.chunks(size)) {
+        *component = match data_type {
+            DataType::I8 => {
+                let value = f32::from(bytes[0] as i8);
+                if normalized {
+                    (value / 127.).max(-1.)
+                } else {
+                    value
+                }
+            }
+            DataType::U8 => {
+                let value = f32::from(bytes[0]);
+                if normalized {
+                    value / 255.
+                } else {
+                    value
+                }
+            }
+            DataType::I16 => {
+                let value = f32::from(i16::from_le_bytes([bytes[0], bytes[1]]));
+                if normalized {
+                    (value / 32767.).max(-1.)
+                } else {
+                    value
+                }
+            }
+            DataType::U16 => {
+                let value = f32::from(u16::from_le_bytes([bytes[0], bytes[1]]));
+                if normalized {
+                    value / 65535.
+                } else {
+                    value
+                }
+            }
+            DataType::U32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32,
+            DataType::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gltf::Gltf;
+
+    /// Three positions, the sparse accessor replaces the second one with the value stored
+    /// densely in the last accessor.
+    const SPARSE: &str = r#"{
+        "asset": { "version": "2.0" },
+        "buffers": [{ "byteLength": 92 }],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 36, "byteLength": 2 },
+            { "buffer": 0, "byteOffset": 44, "byteLength": 12 },
+            { "buffer": 0, "byteOffset": 56, "byteLength": 36 }
+        ],
+        "accessors": [
+            {
+                "bufferView": 0,
+                "componentType": 5126,
+                "count": 3,
+                "type": "VEC3",
+                "sparse": {
+                    "count": 1,
+                    "indices": { "bufferView": 1, "componentType": 5123 },
+                    "values": { "bufferView": 2 }
+                }
+            },
+            { "bufferView": 3, "componentType": 5126, "count": 3, "type": "VEC3" }
+        ]
+    }"#;
+
+    fn floats(data: &mut Vec<u8>, values: &[f32]) {
+        for value in values {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    fn buffer(sparse_index: u16) -> Vec<u8> {
+        let mut data = Vec::new();
+        floats(&mut data, &[0., 0., 0., 1., 0., 0., 0., 1., 0.]);
+        data.extend_from_slice(&sparse_index.to_le_bytes());
+        data.extend_from_slice(&[0; 6]);
+        floats(&mut data, &[5., 6., 7.]);
+        floats(&mut data, &[0., 0., 0., 5., 6., 7., 0., 1., 0.]);
+        data
+    }
+
+    #[test]
+    fn sparse_matches_dense() {
+        let gltf = Gltf::from_slice(SPARSE.as_bytes()).unwrap();
+        let buffers = Buffers::new(vec![buffer(1)]);
+        let sparse = gltf.accessors().next().unwrap();
+        let dense = gltf.accessors().nth(1).unwrap();
+        assert_eq!(
+            read_components(&sparse, &buffers).unwrap(),
+            read_components(&dense, &buffers).unwrap()
+        );
+        let positions = read_if_sparse::<[f32; 3]>(Some(sparse), &buffers)
+            .unwrap()
+            .unwrap();
+        assert_eq!(positions, vec![[0., 0., 0.], [5., 6., 7.], [0., 1., 0.]]);
+        assert!(read_if_sparse::<[f32; 3]>(Some(dense), &buffers)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn sparse_without_buffer_view_starts_from_zeros() {
+        let json = SPARSE.replacen(r#""bufferView": 0,"#, "", 1);
+        let gltf = Gltf::from_slice(json.as_bytes()).unwrap();
+        let buffers = Buffers::new(vec![buffer(1)]);
+        let sparse = gltf.accessors().next().unwrap();
+        assert!(sparse.view().is_none());
+        assert_eq!(
+            read_components(&sparse, &buffers).unwrap(),
+            vec![0., 0., 0., 5., 6., 7., 0., 0., 0.]
+        );
+    }
+
+    #[test]
+    fn out_of_range_index_names_accessor() {
+        let gltf = Gltf::from_slice(SPARSE.as_bytes()).unwrap();
+        let buffers = Buffers::new(vec![buffer(3)]);
+        let sparse = gltf.accessors().next().unwrap();
+        let err = read_components(&sparse, &buffers).unwrap_err();
+        assert!(err.to_string().contains("accessor 0"), "{}", err);
+    }
+}
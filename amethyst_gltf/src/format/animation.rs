@@ -3,13 +3,14 @@ use std::collections::HashMap;
 use amethyst_error::Error;
 
 use amethyst_animation::{
-    AnimationPrefab, AnimationSetPrefab, InterpolationFunction, InterpolationPrimitive, Sampler,
-    SamplerPrimitive, TransformChannel,
+    AnimationPrefab, AnimationSetPrefab, InterpolationFunction, InterpolationPrimitive,
+    MorphChannel, Sampler, SamplerPrimitive, TransformChannel,
 };
 use amethyst_core::{
     math::{convert, Vector3, Vector4},
     Transform,
 };
+use amethyst_rendy::morph::MorphWeights;
 
 use super::Buffers;
 use crate::error;
@@ -38,17 +39,90 @@ fn load_animation(
     buffers: &Buffers,
 ) -> Result<AnimationPrefab<Transform>, Error> {
     let mut a = AnimationPrefab::default();
-    a.samplers = animation
-        .channels()
-        .map(|ref channel| load_channel(channel, buffers))
-        .collect::<Result<Vec<_>, Error>>()?;
+    for channel in animation.channels() {
+        if let Some(sampler) = load_channel(&channel, buffers)? {
+            a.samplers.push(sampler);
+        }
+    }
     Ok(a)
 }
 
+/// Load the morph target weight animations, as a separate set since they animate `MorphWeights`
+/// instead of `Transform`.
+pub fn load_morph_animations(
+    gltf: &gltf::Gltf,
+    buffers: &Buffers,
+    node_map: &HashMap<usize, usize>,
+) -> Result<AnimationSetPrefab<usize, MorphWeights>, Error> {
+    let mut prefab = AnimationSetPrefab::default();
+    for animation in gltf.animations() {
+        let mut anim = AnimationPrefab::default();
+        for channel in animation.channels() {
+            anim.samplers.extend(load_morph_channel(&channel, buffers)?);
+        }
+        if anim
+            .samplers
+            .iter()
+            .any(|sampler| node_map.contains_key(&sampler.0))
+        {
+            prefab.animations.push((animation.index(), anim));
+        }
+    }
+    Ok(prefab)
+}
+
+fn load_morph_channel(
+    channel: &gltf::animation::Channel<'_>,
+    buffers: &Buffers,
+) -> Result<Vec<(usize, MorphChannel, Sampler<SamplerPrimitive<f32>>)>, Error> {
+    use gltf::animation::{util::ReadOutputs::MorphTargetWeights, Interpolation};
+
+    let reader = channel.reader(|buffer| buffers.buffer(&buffer));
+    let weights = match reader.read_outputs().ok_or(error::Error::MissingOutputs)? {
+        MorphTargetWeights(weights) => weights.into_f32().collect::<Vec<_>>(),
+        _ => return Ok(Vec::new()),
+    };
+    let input = reader
+        .read_inputs()
+        .ok_or(error::Error::MissingInputs)?
+        .collect::<Vec<_>>();
+    let interpolation = channel.sampler().interpolation();
+    // cubic spline keyframes hold an in-tangent, a value and an out-tangent for each target
+    let values_per_key = match interpolation {
+        Interpolation::CubicSpline => 3,
+        _ => 1,
+    };
+    if input.is_empty() {
+        return Ok(Vec::new());
+    }
+    let targets = weights.len() / (input.len() * values_per_key);
+    let node_index = channel.target().node().index();
+
+    Ok((0..targets)
+        .map(|target| {
+            let output = weights
+                .iter()
+                .skip(target)
+                .step_by(targets)
+                .map(|w| SamplerPrimitive::Scalar(*w))
+                .collect();
+            (
+                node_index,
+                MorphChannel::Weight(target),
+                Sampler {
+                    input: input.clone(),
+                    function: map_interpolation_type(interpolation),
+                    output,
+                },
+            )
+        })
+        .collect())
+}
+
 fn load_channel(
     channel: &gltf::animation::Channel<'_>,
     buffers: &Buffers,
-) -> Result<(usize, TransformChannel, Sampler<SamplerPrimitive<f32>>), Error> {
+) -> Result<Option<(usize, TransformChannel, Sampler<SamplerPrimitive<f32>>)>, Error> {
     use gltf::animation::util::ReadOutputs::*;
     let sampler = channel.sampler();
     let target = channel.target();
@@ -61,7 +135,7 @@ fn load_channel(
     let node_index = target.node().index();
 
     match reader.read_outputs().ok_or(error::Error::MissingOutputs)? {
-        Translations(translations) => Ok((
+        Translations(translations) => Ok(Some((
             node_index,
             TransformChannel::Translation,
             Sampler {
@@ -72,7 +146,7 @@ fn load_channel(
                     .map(|t| convert::<_, Vector3<f32>>(t).into())
                     .collect(),
             },
-        )),
+        ))),
         Rotations(rotations) => {
             let ty = map_interpolation_type(sampler.interpolation());
             let ty = if ty == InterpolationFunction::Linear {
@@ -80,7 +154,7 @@ fn load_channel(
             } else {
                 ty
             };
-            Ok((
+            Ok(Some((
                 node_index,
                 TransformChannel::Rotation,
                 Sampler {
@@ -92,9 +166,9 @@ fn load_channel(
                         .map(|q| convert::<_, Vector4<f32>>(q).into())
                         .collect(),
                 },
-            ))
+            )))
         }
-        Scales(scales) => Ok((
+        Scales(scales) => Ok(Some((
             node_index,
             TransformChannel::Scale,
             Sampler {
@@ -105,8 +179,9 @@ fn load_channel(
                     .map(|s| convert::<_, Vector3<f32>>(s).into())
                     .collect(),
             },
-        )),
-        MorphTargetWeights(_) => Err(error::Error::NotImplemented.into()),
+        ))),
+        // loaded by `load_morph_animations`
+        MorphTargetWeights(_) => Ok(None),
     }
 }
 
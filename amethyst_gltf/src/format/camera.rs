@@ -0,0 +1,145 @@
+use amethyst_rendy::camera::{CameraPrefab, Exposure};
+use amethyst_utils::auto_fov::AutoFov;
+use gltf::camera::Projection;
+
+/// Far plane of perspective cameras without one, relative to their near plane.
+///
+/// Keeps the depth range within the precision of the depth buffer.
+const INFINITE_FAR_PLANE_RATIO: f32 = 10_000.;
+
+/// Aspect ratio used until the `AutoFovSystem` adjusts the camera to the window.
+const DEFAULT_ASPECT_RATIO: f32 = 16. / 9.;
+
+/// Convert a glTF camera.
+///
+/// The aspect ratio of perspective cameras is not taken from the file, instead they get an
+/// `AutoFov` that keeps the vertical field of view of the file at a 16:9 aspect ratio. Perspective
+/// cameras with an infinite far plane are clamped to `INFINITE_FAR_PLANE_RATIO` times their near
+/// plane.
+pub fn load_camera(camera: &gltf::Camera<'_>) -> (CameraPrefab, Option<AutoFov>) {
+    match camera.projection() {
+        Projection::Orthographic(proj) => (
+            CameraPrefab::Orthographic {
+                left: -proj.xmag(),
+                right: proj.xmag(),
+                bottom: -proj.ymag(),
+                top: proj.ymag(),
+                znear: proj.znear(),
+                zfar: proj.zfar(),
+                exposure: Exposure::default(),
+            },
+            None,
+        ),
+        Projection::Perspective(proj) => {
+            let fovy = proj.yfov();
+            let mut auto_fov = AutoFov::new();
+            let fovx = 2. * ((fovy / 2.).tan() * DEFAULT_ASPECT_RATIO).atan();
+            auto_fov.set_base_fovx(fovx.max(auto_fov.min_fovx()).min(auto_fov.max_fovx()));
+            (
+                CameraPrefab::Perspective {
+                    aspect: DEFAULT_ASPECT_RATIO,
+                    fovy,
+                    znear: proj.znear(),
+                    zfar: proj
+                        .zfar()
+                        .unwrap_or_else(|| proj.znear() * INFINITE_FAR_PLANE_RATIO),
+                    exposure: Exposure::default(),
+                },
+                Some(auto_fov),
+            )
+        }
+    }
+}
+
+/// Name of the camera, taken from its node or the camera itself
+pub fn camera_name(node: &gltf::Node<'_>, camera: &gltf::Camera<'_>) -> String {
+    node.name()
+        .or_else(|| camera.name())
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("camera{}", camera.index()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use gltf::Gltf;
+
+    const CAMERAS: &[u8] = br#"{
+        "asset": { "version": "2.0" },
+        "cameras": [
+            {
+                "type": "perspective",
+                "perspective": { "yfov": 0.8, "znear": 0.1, "aspectRatio": 1.0 }
+            },
+            {
+                "name": "map",
+                "type": "orthographic",
+                "orthographic": { "xmag": 4.0, "ymag": 3.0, "znear": 0.5, "zfar": 50.0 }
+            }
+        ],
+        "nodes": [
+            { "name": "player", "camera": 0 },
+            { "camera": 1 }
+        ]
+    }"#;
+
+    #[test]
+    fn perspective_camera_follows_window() {
+        let gltf = Gltf::from_slice(CAMERAS).unwrap();
+        let node = gltf.nodes().next().unwrap();
+        let camera = node.camera().unwrap();
+        match load_camera(&camera) {
+            (
+                CameraPrefab::Perspective {
+                    aspect,
+                    fovy,
+                    znear,
+                    zfar,
+                    ..
+                },
+                Some(auto_fov),
+            ) => {
+                assert_relative_eq!(aspect, DEFAULT_ASPECT_RATIO, epsilon = 1e-5);
+                assert_relative_eq!(fovy, 0.8, epsilon = 1e-5);
+                assert_relative_eq!(znear, 0.1, epsilon = 1e-5);
+                assert_relative_eq!(zfar, 1000., epsilon = 1e-2);
+                let restored =
+                    2. * ((auto_fov.base_fovx() / 2.).tan() / DEFAULT_ASPECT_RATIO).atan();
+                assert_relative_eq!(restored, fovy, epsilon = 1e-5);
+            }
+            other => panic!("Expected perspective camera with AutoFov, got {:?}", other),
+        }
+        assert_eq!(camera_name(&node, &camera), "player");
+    }
+
+    #[test]
+    fn orthographic_camera() {
+        let gltf = Gltf::from_slice(CAMERAS).unwrap();
+        let node = gltf.nodes().nth(1).unwrap();
+        let camera = node.camera().unwrap();
+        match load_camera(&camera) {
+            (
+                CameraPrefab::Orthographic {
+                    left,
+                    right,
+                    bottom,
+                    top,
+                    znear,
+                    zfar,
+                    ..
+                },
+                None,
+            ) => {
+                for (value, expected) in [left, right, bottom, top, znear, zfar]
+                    .iter()
+                    .zip(&[-4., 4., -3., 3., 0.5, 50.])
+                {
+                    assert_relative_eq!(*value, *expected, epsilon = 1e-5);
+                }
+            }
+            other => panic!("Expected orthographic camera, got {:?}", other),
+        }
+        assert_eq!(camera_name(&node, &camera), "map");
+    }
+}
@@ -0,0 +1,1129 @@
+//! Decoding of primitives compressed with `KHR_draco_mesh_compression`.
+//!
+//! Supports Draco bitstream version 2.2 with the sequential encoding, for meshes and point
+//! clouds. Attributes can use the generic, integer, quantization and normal decoders, with the
+//! difference prediction scheme. Edgebreaker and k-d tree encoded data is rejected.
+
+use std::collections::{HashMap, HashSet};
+
+use amethyst_error::Error;
+use gltf::{
+    json::{
+        self,
+        validation::{self, Checked, Validate},
+    },
+    Document, Gltf, Semantic,
+};
+use serde::Deserialize;
+
+use super::Buffers;
+use crate::error;
+
+/// The parts of the glTF json `gltf` drops: the Draco extension objects of the primitives.
+#[derive(Debug, Deserialize)]
+struct Root {
+    #[serde(default)]
+    meshes: Vec<Mesh>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Mesh {
+    #[serde(default)]
+    primitives: Vec<Primitive>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Primitive {
+    #[serde(default)]
+    extensions: Option<Extensions>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Extensions {
+    #[serde(rename = "KHR_draco_mesh_compression")]
+    draco: Option<Extension>,
+}
+
+/// The `KHR_draco_mesh_compression` object of a primitive.
+#[derive(Debug, Deserialize)]
+struct Extension {
+    /// The buffer view containing the compressed data
+    #[serde(rename = "bufferView")]
+    buffer_view: usize,
+    /// The unique ids of the Draco attributes, by semantic
+    attributes: HashMap<Checked<Semantic>, u32>,
+}
+
+/// Reads the Draco extension objects of all primitives, with the mesh and primitive indices.
+fn extensions(json: &[u8]) -> Result<Vec<(usize, usize, Extension)>, Error> {
+    let root: Root = json::deserialize::from_slice(json)?;
+    let mut extensions = Vec::new();
+    for (mesh, mesh_json) in root.meshes.into_iter().enumerate() {
+        for (primitive, primitive_json) in mesh_json.primitives.into_iter().enumerate() {
+            if let Some(extension) = primitive_json.extensions.and_then(|e| e.draco) {
+                extensions.push((mesh, primitive, extension));
+            }
+        }
+    }
+    Ok(extensions)
+}
+
+/// Parses and validates the glTF json like `Gltf::from_slice`, except that the accessors of
+/// Draco compressed primitives do not need a buffer view, which they usually lack.
+pub fn parse(json: &[u8]) -> Result<Gltf, Error> {
+    let root = json::Root::from_slice(json)?;
+    let mut compressed = HashSet::new();
+    for (mesh, primitive, extension) in extensions(json)? {
+        let primitive = match root.meshes.get(mesh).map(|mesh| &mesh.primitives) {
+            Some(primitives) if primitive < primitives.len() => &primitives[primitive],
+            _ => continue,
+        };
+        compressed.extend(primitive.indices.as_ref().map(json::Index::value));
+        for semantic in extension.attributes.keys() {
+            compressed.extend(primitive.attributes.get(semantic).map(json::Index::value));
+        }
+    }
+
+    let mut errors = Vec::new();
+    root.validate(&root, json::Path::new, &mut |path, error| {
+        let path = path();
+        let missing_view = error == validation::Error::Missing
+            && compressed.iter().any(|&accessor| {
+                path == json::Path::new()
+                    .field("accessors")
+                    .index(accessor)
+                    .field("bufferView")
+            });
+        if !missing_view {
+            errors.push((path, error));
+        }
+    });
+    if !errors.is_empty() {
+        return Err(gltf::Error::Validation(errors).into());
+    }
+    Ok(Gltf {
+        document: Document::from_json_without_validation(root),
+        blob: None,
+    })
+}
+
+/// Decodes all Draco compressed primitives of the glTF file.
+///
+/// The decoded indices and attributes are stored in `buffers` by the index of the accessor
+/// they replace.
+pub fn decode_primitives(json: &[u8], gltf: &Gltf, buffers: &mut Buffers) -> Result<(), Error> {
+    for (mesh, primitive, extension) in extensions(json)? {
+        let invalid = |reason| error::Error::InvalidDraco {
+            mesh,
+            primitive,
+            reason,
+        };
+        let primitive = gltf
+            .meshes()
+            .nth(mesh)
+            .and_then(|mesh| mesh.primitives().nth(primitive))
+            .ok_or_else(|| invalid("primitive does not exist"))?;
+
+        let view = gltf
+            .views()
+            .nth(extension.buffer_view)
+            .ok_or_else(|| invalid("buffer view does not exist"))?;
+        let data = buffers.view(&view).ok_or_else(|| {
+            let path = json::Path::new().field("bufferViews").index(view.index());
+            error::Error::BufferLength(path)
+        })?;
+        let mut decoded = decode(data).map_err(invalid)?;
+
+        if let Some(accessor) = primitive.indices() {
+            if decoded.indices.len() != accessor.count() {
+                return Err(invalid("indices do not match their accessor").into());
+            }
+            buffers.insert_decoded_indices(accessor.index(), decoded.indices);
+        }
+        for (semantic, unique_id) in extension.attributes {
+            let accessor = match semantic {
+                Checked::Valid(semantic) => primitive.get(&semantic),
+                Checked::Invalid => None,
+            };
+            let accessor = match accessor {
+                Some(accessor) => accessor,
+                None => continue,
+            };
+            let attribute = decoded
+                .attributes
+                .iter_mut()
+                .find(|attribute| attribute.unique_id == unique_id)
+                .ok_or_else(|| invalid("attribute does not exist"))?;
+            let dimensions = accessor.dimensions().multiplicity();
+            if attribute.components != dimensions
+                || attribute.values.len() != accessor.count() * dimensions
+            {
+                return Err(invalid("attribute does not match its accessor").into());
+            }
+            let values = std::mem::take(&mut attribute.values);
+            buffers.insert_decoded(accessor.index(), values);
+        }
+    }
+    Ok(())
+}
+
+/// A decoded Draco mesh or point cloud
+#[derive(Debug)]
+struct Decoded {
+    /// Point indices of the triangles, empty for point clouds
+    indices: Vec<u32>,
+    attributes: Vec<Attribute>,
+}
+
+/// A decoded attribute, with its values converted to `f32`
+#[derive(Debug)]
+struct Attribute {
+    unique_id: u32,
+    components: usize,
+    values: Vec<f32>,
+}
+
+type DecodeResult<T> = Result<T, &'static str>;
+
+const POINT_CLOUD: u8 = 0;
+const TRIANGULAR_MESH: u8 = 1;
+const SEQUENTIAL_ENCODING: u8 = 0;
+const METADATA_FLAG: u16 = 0x8000;
+
+const SEQUENTIAL_COMPRESSED_INDICES: u8 = 0;
+const SEQUENTIAL_UNCOMPRESSED_INDICES: u8 = 1;
+
+const PREDICTION_NONE: i8 = -2;
+const PREDICTION_DIFFERENCE: i8 = 0;
+const TRANSFORM_DELTA: i8 = 0;
+const TRANSFORM_WRAP: i8 = 1;
+const TRANSFORM_NORMAL_OCTAHEDRON_CANONICALIZED: i8 = 3;
+
+const SYMBOL_CODING_TAGGED: u8 = 0;
+const SYMBOL_CODING_RAW: u8 = 1;
+
+fn decode(data: &[u8]) -> DecodeResult<Decoded> {
+    let mut reader = Reader::new(data);
+    if reader.bytes(5)? != b"DRACO" {
+        return Err("not a Draco bitstream");
+    }
+    if (reader.u8()?, reader.u8()?) != (2, 2) {
+        return Err("unsupported bitstream version");
+    }
+    let encoder_type = reader.u8()?;
+    let method = reader.u8()?;
+    let flags = reader.u16()?;
+    if method != SEQUENTIAL_ENCODING {
+        return Err("only the sequential encoding is supported");
+    }
+    if flags & METADATA_FLAG != 0 {
+        skip_metadata(&mut reader)?;
+    }
+
+    let (indices, num_points) = match encoder_type {
+        TRIANGULAR_MESH => decode_connectivity(&mut reader)?,
+        POINT_CLOUD => (Vec::new(), reader.u32()? as usize),
+        _ => return Err("unknown encoder type"),
+    };
+    let attributes = decode_attributes(&mut reader, num_points)?;
+    Ok(Decoded {
+        indices,
+        attributes,
+    })
+}
+
+fn skip_metadata(reader: &mut Reader<'_>) -> DecodeResult<()> {
+    for _ in 0..reader.varint()? {
+        // unique id of the attribute
+        reader.varint()?;
+        skip_metadata_element(reader, 0)?;
+    }
+    skip_metadata_element(reader, 0)
+}
+
+fn skip_metadata_element(reader: &mut Reader<'_>, depth: usize) -> DecodeResult<()> {
+    if depth > 32 {
+        return Err("metadata is nested too deeply");
+    }
+    for _ in 0..reader.varint()? {
+        let name = reader.u8()?;
+        reader.bytes(name as usize)?;
+        let value = reader.varint()?;
+        reader.bytes(value as usize)?;
+    }
+    for _ in 0..reader.varint()? {
+        let name = reader.u8()?;
+        reader.bytes(name as usize)?;
+        skip_metadata_element(reader, depth + 1)?;
+    }
+    Ok(())
+}
+
+/// Decodes the triangles of a sequentially encoded mesh, returns them with the number of points.
+fn decode_connectivity(reader: &mut Reader<'_>) -> DecodeResult<(Vec<u32>, usize)> {
+    let num_faces = reader.varint()?;
+    let num_points = reader.varint()?;
+    if num_faces > u64::from(u32::max_value() / 3) || num_points > u64::from(u32::max_value()) {
+        return Err("too many faces or points");
+    }
+    let num_indices = num_faces as usize * 3;
+    let indices = match reader.u8()? {
+        SEQUENTIAL_COMPRESSED_INDICES => {
+            let mut last = 0i64;
+            decode_symbols(reader, num_indices, 1)?
+                .into_iter()
+                .map(|symbol| {
+                    let diff = i64::from(symbol >> 1);
+                    last += if symbol & 1 == 1 { -diff } else { diff };
+                    last
+                })
+                .collect::<Vec<_>>()
+        }
+        SEQUENTIAL_UNCOMPRESSED_INDICES => {
+            let mut indices = Vec::new();
+            for _ in 0..num_indices {
+                indices.push(match num_points {
+                    0..=0xff => i64::from(reader.u8()?),
+                    0x100..=0xffff => i64::from(reader.u16()?),
+                    0x1_0000..=0x1f_ffff => reader.varint()? as i64,
+                    _ => i64::from(reader.u32()?),
+                });
+            }
+            indices
+        }
+        _ => return Err("unknown connectivity method"),
+    };
+    if indices
+        .iter()
+        .any(|&index| index < 0 || index as u64 >= num_points)
+    {
+        return Err("index out of range");
+    }
+    let indices = indices.into_iter().map(|index| index as u32).collect();
+    Ok((indices, num_points as usize))
+}
+
+/// Sequential attribute decoders
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Decoder {
+    Generic,
+    Integer,
+    Quantization,
+    Normals,
+}
+
+/// Attribute data types
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DataType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    I64,
+    U64,
+    F32,
+    F64,
+    Bool,
+}
+
+impl DataType {
+    fn from_u8(value: u8) -> DecodeResult<Self> {
+        Ok(match value {
+            1 => DataType::I8,
+            2 => DataType::U8,
+            3 => DataType::I16,
+            4 => DataType::U16,
+            5 => DataType::I32,
+            6 => DataType::U32,
+            7 => DataType::I64,
+            8 => DataType::U64,
+            9 => DataType::F32,
+            10 => DataType::F64,
+            11 => DataType::Bool,
+            _ => return Err("unknown attribute data type"),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            DataType::I8 | DataType::U8 | DataType::Bool => 1,
+            DataType::I16 | DataType::U16 => 2,
+            DataType::I32 | DataType::U32 | DataType::F32 => 4,
+            DataType::I64 | DataType::U64 | DataType::F64 => 8,
+        }
+    }
+
+    fn read(self, bytes: &[u8]) -> f64 {
+        let mut le = [0; 8];
+        le[..bytes.len()].copy_from_slice(bytes);
+        match self {
+            DataType::I8 => f64::from(bytes[0] as i8),
+            DataType::U8 | DataType::Bool => f64::from(bytes[0]),
+            DataType::I16 => f64::from(i16::from_le_bytes([le[0], le[1]])),
+            DataType::U16 => f64::from(u16::from_le_bytes([le[0], le[1]])),
+            DataType::I32 => f64::from(i32::from_le_bytes([le[0], le[1], le[2], le[3]])),
+            DataType::U32 => f64::from(u32::from_le_bytes([le[0], le[1], le[2], le[3]])),
+            DataType::I64 => i64::from_le_bytes(le) as f64,
+            DataType::U64 => u64::from_le_bytes(le) as f64,
+            DataType::F32 => f64::from(f32::from_le_bytes([le[0], le[1], le[2], le[3]])),
+            DataType::F64 => f64::from_le_bytes(le),
+        }
+    }
+
+    /// Converts a value, normalized integers are mapped to `0..=1` or `-1..=1`.
+    fn convert(self, value: f64, normalized: bool) -> f32 {
+        let max = match self {
+            DataType::I8 => f64::from(i8::max_value()),
+            DataType::U8 => f64::from(u8::max_value()),
+            DataType::I16 => f64::from(i16::max_value()),
+            DataType::U16 => f64::from(u16::max_value()),
+            DataType::I32 => f64::from(i32::max_value()),
+            DataType::U32 => f64::from(u32::max_value()),
+            _ => return value as f32,
+        };
+        if normalized {
+            (value / max).max(-1.) as f32
+        } else {
+            value as f32
+        }
+    }
+}
+
+/// Description of an attribute, as stored in front of the attribute data
+#[derive(Debug)]
+struct Descriptor {
+    data_type: DataType,
+    components: usize,
+    normalized: bool,
+    unique_id: u32,
+    decoder: Decoder,
+}
+
+fn decode_attributes(reader: &mut Reader<'_>, num_points: usize) -> DecodeResult<Vec<Attribute>> {
+    let mut decoders = Vec::new();
+    for _ in 0..reader.u8()? {
+        let mut descriptors = Vec::new();
+        for _ in 0..reader.varint()? {
+            // the attribute type, the glTF semantic is taken from the extension instead
+            reader.u8()?;
+            let data_type = DataType::from_u8(reader.u8()?)?;
+            let components = reader.u8()? as usize;
+            if components == 0 {
+                return Err("attribute has no components");
+            }
+            descriptors.push(Descriptor {
+                data_type,
+                components,
+                normalized: reader.u8()? > 0,
+                unique_id: reader.varint()? as u32,
+                decoder: Decoder::Generic,
+            });
+        }
+        for descriptor in &mut descriptors {
+            descriptor.decoder = match reader.u8()? {
+                0 => Decoder::Generic,
+                1 => Decoder::Integer,
+                2 => Decoder::Quantization,
+                3 => Decoder::Normals,
+                _ => return Err("unknown attribute decoder"),
+            };
+        }
+        decoders.push(descriptors);
+    }
+
+    // all attributes of a decoder are stored first, followed by the data to transform them
+    let mut attributes = Vec::new();
+    for descriptors in decoders {
+        let mut portable = Vec::new();
+        for descriptor in &descriptors {
+            portable.push(decode_portable(reader, descriptor, num_points)?);
+        }
+        for (descriptor, portable) in descriptors.iter().zip(portable) {
+            attributes.push(transform(reader, descriptor, portable)?);
+        }
+    }
+    Ok(attributes)
+}
+
+/// Attribute values in the form they are stored in
+enum Portable {
+    Values(Vec<f32>),
+    Integers(Vec<i32>),
+}
+
+fn decode_portable(
+    reader: &mut Reader<'_>,
+    descriptor: &Descriptor,
+    num_points: usize,
+) -> DecodeResult<Portable> {
+    let num_values = num_points
+        .checked_mul(descriptor.components)
+        .ok_or("too many attribute values")?;
+    match descriptor.decoder {
+        Decoder::Generic => {
+            let size = descriptor.data_type.size();
+            let bytes = reader.bytes(num_values.checked_mul(size).ok_or("too many values")?)?;
+            Ok(Portable::Values(
+                bytes
+                    .chunks(size)
+                    .map(|value| {
+                        let value = descriptor.data_type.read(value);
+                        descriptor.data_type.convert(value, descriptor.normalized)
+                    })
+                    .collect(),
+            ))
+        }
+        Decoder::Integer | Decoder::Quantization => {
+            decode_integers(reader, num_points, descriptor.components).map(Portable::Integers)
+        }
+        // normals are stored as two octahedral coordinates
+        Decoder::Normals => decode_integers(reader, num_points, 2).map(Portable::Integers),
+    }
+}
+
+fn decode_integers(
+    reader: &mut Reader<'_>,
+    num_points: usize,
+    components: usize,
+) -> DecodeResult<Vec<i32>> {
+    let num_values = num_points * components;
+    let transform = match reader.i8()? {
+        PREDICTION_NONE => None,
+        PREDICTION_DIFFERENCE => Some(reader.i8()?),
+        _ => return Err("unsupported prediction scheme"),
+    };
+
+    let symbols = if reader.u8()? > 0 {
+        decode_symbols(reader, num_values, components)?
+    } else {
+        let size = reader.u8()? as usize;
+        if !(1..=4).contains(&size) {
+            return Err("invalid integer size");
+        }
+        let bytes = reader.bytes(num_values.checked_mul(size).ok_or("too many values")?)?;
+        bytes
+            .chunks(size)
+            .map(|value| {
+                let mut le = [0; 4];
+                le[..size].copy_from_slice(value);
+                u32::from_le_bytes(le)
+            })
+            .collect()
+    };
+
+    // the octahedron transform only produces positive corrections, the others are zigzag encoded
+    let mut values = if transform == Some(TRANSFORM_NORMAL_OCTAHEDRON_CANONICALIZED) {
+        symbols.into_iter().map(|symbol| symbol as i32).collect()
+    } else {
+        symbols
+            .into_iter()
+            .map(|symbol| {
+                let value = (symbol >> 1) as i32;
+                if symbol & 1 == 0 {
+                    value
+                } else {
+                    -value - 1
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+
+    match transform {
+        None => {}
+        Some(TRANSFORM_DELTA) => {
+            for i in components..num_values {
+                values[i] = values[i].wrapping_add(values[i - components]);
+            }
+        }
+        Some(TRANSFORM_WRAP) => {
+            let min = reader.i32()?;
+            let max = reader.i32()?;
+            let dif = i64::from(max) - i64::from(min);
+            if dif < 0 || dif >= i64::from(i32::max_value()) {
+                return Err("invalid wrap transform bounds");
+            }
+            let max_dif = dif + 1;
+            for i in 0..num_values {
+                let predicted = if i < components {
+                    0
+                } else {
+                    i64::from(values[i - components])
+                };
+                let mut value =
+                    predicted.max(i64::from(min)).min(i64::from(max)) + i64::from(values[i]);
+                if value > i64::from(max) {
+                    value -= max_dif;
+                } else if value < i64::from(min) {
+                    value += max_dif;
+                }
+                values[i] = value as i32;
+            }
+        }
+        Some(TRANSFORM_NORMAL_OCTAHEDRON_CANONICALIZED) => {
+            if components != 2 {
+                return Err("octahedron transform of a non normal attribute");
+            }
+            let max_quantized_value = reader.i32()?;
+            // the center value follows, which is derived from the maximum value instead
+            reader.i32()?;
+            if max_quantized_value <= 0 || max_quantized_value % 2 == 0 {
+                return Err("invalid octahedron transform maximum");
+            }
+            let octahedron = Octahedron::new(32 - max_quantized_value.leading_zeros())?;
+            for i in (0..num_values).step_by(2) {
+                let predicted = if i == 0 {
+                    [0, 0]
+                } else {
+                    [i64::from(values[i - 2]), i64::from(values[i - 1])]
+                };
+                let correction = [i64::from(values[i]), i64::from(values[i + 1])];
+                let original = octahedron.original(correction, predicted);
+                values[i] = original[0] as i32;
+                values[i + 1] = original[1] as i32;
+            }
+        }
+        Some(_) => return Err("unsupported prediction transform"),
+    }
+    Ok(values)
+}
+
+/// Reverts the portable form of an attribute, reading the data needed for that.
+fn transform(
+    reader: &mut Reader<'_>,
+    descriptor: &Descriptor,
+    portable: Portable,
+) -> DecodeResult<Attribute> {
+    let integers = match portable {
+        Portable::Values(values) => {
+            return Ok(Attribute {
+                unique_id: descriptor.unique_id,
+                components: descriptor.components,
+                values,
+            })
+        }
+        Portable::Integers(integers) => integers,
+    };
+
+    let values = match descriptor.decoder {
+        Decoder::Quantization => {
+            let mut min = Vec::new();
+            for _ in 0..descriptor.components {
+                min.push(reader.f32()?);
+            }
+            let range = reader.f32()?;
+            let bits = reader.u8()?;
+            if !(1..=30).contains(&bits) {
+                return Err("invalid quantization bits");
+            }
+            let delta = range / ((1u32 << bits) - 1) as f32;
+            integers
+                .iter()
+                .enumerate()
+                .map(|(i, &value)| value as f32 * delta + min[i % descriptor.components])
+                .collect()
+        }
+        Decoder::Normals => {
+            let octahedron = Octahedron::new(u32::from(reader.u8()?))?;
+            let scale = 2. / octahedron.max_value as f32;
+            integers
+                .chunks(2)
+                .flat_map(|st| {
+                    let s = st[0] as f32 * scale - 1.;
+                    let t = st[1] as f32 * scale - 1.;
+                    unit_vector(s, t).to_vec()
+                })
+                .collect()
+        }
+        _ => match descriptor.data_type {
+            DataType::I8
+            | DataType::U8
+            | DataType::I16
+            | DataType::U16
+            | DataType::I32
+            | DataType::Bool => integers
+                .into_iter()
+                .map(|value| {
+                    descriptor
+                        .data_type
+                        .convert(f64::from(value), descriptor.normalized)
+                })
+                .collect(),
+            DataType::U32 => integers
+                .into_iter()
+                .map(|value| {
+                    let value = f64::from(value as u32);
+                    descriptor.data_type.convert(value, descriptor.normalized)
+                })
+                .collect(),
+            _ => return Err("unsupported integer attribute data type"),
+        },
+    };
+    Ok(Attribute {
+        unique_id: descriptor.unique_id,
+        components: if descriptor.decoder == Decoder::Normals {
+            3
+        } else {
+            descriptor.components
+        },
+        values,
+    })
+}
+
+/// Converts octahedral coordinates in `-1..=1` to a unit vector.
+fn unit_vector(s: f32, t: f32) -> [f32; 3] {
+    let x = 1. - s.abs() - t.abs();
+    let offset = (-x).max(0.);
+    let y = if s < 0. { s + offset } else { s - offset };
+    let z = if t < 0. { t + offset } else { t - offset };
+    let norm_squared = x * x + y * y + z * z;
+    if norm_squared < 1e-6 {
+        [0., 0., 0.]
+    } else {
+        let d = norm_squared.sqrt().recip();
+        [x * d, y * d, z * d]
+    }
+}
+
+/// Quantized octahedral coordinates, with `quantization_bits` bits per coordinate
+struct Octahedron {
+    max_quantized_value: i64,
+    max_value: i64,
+    center_value: i64,
+}
+
+impl Octahedron {
+    fn new(quantization_bits: u32) -> DecodeResult<Self> {
+        if !(2..=30).contains(&quantization_bits) {
+            return Err("invalid octahedron quantization bits");
+        }
+        let max_quantized_value = (1 << quantization_bits) - 1;
+        let max_value = max_quantized_value - 1;
+        Ok(Octahedron {
+            max_quantized_value,
+            max_value,
+            center_value: max_value / 2,
+        })
+    }
+
+    /// Reverts the canonicalized octahedron prediction transform.
+    fn original(&self, correction: [i64; 2], predicted: [i64; 2]) -> [i64; 2] {
+        let center = self.center_value;
+        let mut predicted = [predicted[0] - center, predicted[1] - center];
+        let in_diamond = self.in_diamond(predicted);
+        if !in_diamond {
+            predicted = self.invert_diamond(predicted);
+        }
+        let in_bottom_left = in_bottom_left(predicted);
+        let rotations = rotation_count(predicted);
+        if !in_bottom_left {
+            predicted = rotate(predicted, rotations);
+        }
+        let mut original = [
+            self.mod_max(predicted[0] + correction[0]),
+            self.mod_max(predicted[1] + correction[1]),
+        ];
+        if !in_bottom_left {
+            original = rotate(original, (4 - rotations) % 4);
+        }
+        if !in_diamond {
+            original = self.invert_diamond(original);
+        }
+        [original[0] + center, original[1] + center]
+    }
+
+    fn in_diamond(&self, [s, t]: [i64; 2]) -> bool {
+        s.abs() + t.abs() <= self.center_value
+    }
+
+    fn invert_diamond(&self, [s, t]: [i64; 2]) -> [i64; 2] {
+        let (sign_s, sign_t) = if s >= 0 && t >= 0 {
+            (1, 1)
+        } else if s <= 0 && t <= 0 {
+            (-1, -1)
+        } else {
+            (if s > 0 { 1 } else { -1 }, if t > 0 { 1 } else { -1 })
+        };
+        let corner_s = sign_s * self.center_value;
+        let corner_t = sign_t * self.center_value;
+        let (s, t) = (2 * s - corner_s, 2 * t - corner_t);
+        let (s, t) = if sign_s * sign_t >= 0 {
+            (-t, -s)
+        } else {
+            (t, s)
+        };
+        [(s + corner_s) / 2, (t + corner_t) / 2]
+    }
+
+    fn mod_max(&self, x: i64) -> i64 {
+        if x > self.center_value {
+            x - self.max_quantized_value
+        } else if x < -self.center_value {
+            x + self.max_quantized_value
+        } else {
+            x
+        }
+    }
+}
+
+fn in_bottom_left([s, t]: [i64; 2]) -> bool {
+    (s == 0 && t == 0) || (s < 0 && t <= 0)
+}
+
+fn rotation_count([s, t]: [i64; 2]) -> u32 {
+    match (s.signum(), t.signum()) {
+        (0, 0) => 0,
+        (0, 1) => 3,
+        (0, _) => 1,
+        (1, -1) => 1,
+        (1, _) => 2,
+        (_, 1) => 3,
+        (_, _) => 0,
+    }
+}
+
+fn rotate([s, t]: [i64; 2], rotations: u32) -> [i64; 2] {
+    match rotations {
+        1 => [t, -s],
+        2 => [-s, -t],
+        3 => [-t, s],
+        _ => [s, t],
+    }
+}
+
+/// Decodes `num_values` entropy coded symbols, stored in groups of `components`.
+fn decode_symbols(
+    reader: &mut Reader<'_>,
+    num_values: usize,
+    components: usize,
+) -> DecodeResult<Vec<u32>> {
+    if num_values == 0 {
+        return Ok(Vec::new());
+    }
+    let mut values = Vec::new();
+    match reader.u8()? {
+        SYMBOL_CODING_TAGGED => {
+            // the bit length of each group is coded with rANS, the values follow as raw bits
+            let mut tags = RAnsDecoder::new(reader, 5)?;
+            let mut bits = BitReader::new(reader.rest());
+            while values.len() < num_values {
+                let bit_length = tags.symbol();
+                if bit_length > 32 {
+                    return Err("invalid symbol bit length");
+                }
+                for _ in 0..components {
+                    values.push(bits.read(bit_length)?);
+                }
+            }
+            reader.bytes((bits.position + 7) >> 3)?;
+        }
+        SYMBOL_CODING_RAW => {
+            let symbol_bits = u32::from(reader.u8()?);
+            if !(1..=18).contains(&symbol_bits) {
+                return Err("invalid symbol bit length");
+            }
+            let mut symbols = RAnsDecoder::new(reader, symbol_bits)?;
+            for _ in 0..num_values {
+                values.push(symbols.symbol());
+            }
+        }
+        _ => return Err("unknown symbol coding"),
+    }
+    values.truncate(num_values);
+    Ok(values)
+}
+
+/// Decoder of rANS coded symbols
+struct RAnsDecoder<'a> {
+    data: &'a [u8],
+    offset: usize,
+    state: u32,
+    precision: u32,
+    /// Symbol of each value below `precision`
+    lookup: Vec<u32>,
+    /// Probability and cumulative probability of each symbol
+    probabilities: Vec<(u32, u32)>,
+}
+
+impl<'a> RAnsDecoder<'a> {
+    /// Reads the probability table and the coded data of symbols with up to `symbol_bits` bits.
+    fn new(reader: &mut Reader<'a>, symbol_bits: u32) -> DecodeResult<Self> {
+        let precision = match 3 * symbol_bits / 2 {
+            bits if bits < 12 => 1 << 12,
+            bits if bits > 20 => 1 << 20,
+            bits => 1 << bits,
+        };
+        let num_symbols = reader.varint()? as usize;
+        // zero probabilities are run length coded, 64 of them per byte
+        if num_symbols == 0 || num_symbols / 64 > reader.remaining() {
+            return Err("invalid number of symbols");
+        }
+
+        let mut probabilities = Vec::new();
+        let mut lookup = Vec::new();
+        while probabilities.len() < num_symbols {
+            let data = reader.u8()?;
+            if data & 3 == 3 {
+                let zeros = (data >> 2) as usize + 1;
+                if probabilities.len() + zeros > num_symbols {
+                    return Err("invalid symbol probabilities");
+                }
+                for _ in 0..zeros {
+                    probabilities.push((0, lookup.len() as u32));
+                }
+            } else {
+                let mut probability = u32::from(data >> 2);
+                for byte in 0..u32::from(data & 3) {
+                    probability |= u32::from(reader.u8()?) << (8 * (byte + 1) - 2);
+                }
+                if lookup.len() as u32 + probability > precision {
+                    return Err("invalid symbol probabilities");
+                }
+                let symbol = probabilities.len() as u32;
+                probabilities.push((probability, lookup.len() as u32));
+                lookup.resize(lookup.len() + probability as usize, symbol);
+            }
+        }
+        if lookup.len() as u32 != precision {
+            return Err("invalid symbol probabilities");
+        }
+
+        let size = reader.varint()? as usize;
+        let data = reader.bytes(size)?;
+        let (offset, state) = match data.last().map(|byte| byte >> 6) {
+            Some(0) => (size - 1, u32::from(data[size - 1] & 0x3f)),
+            Some(1) if size >= 2 => (
+                size - 2,
+                u32::from(u16::from_le_bytes([data[size - 2], data[size - 1]])) & 0x3fff,
+            ),
+            Some(2) if size >= 3 => (
+                size - 3,
+                u32::from_le_bytes([data[size - 3], data[size - 2], data[size - 1], 0]) & 0x3f_ffff,
+            ),
+            Some(3) if size >= 4 => (
+                size - 4,
+                u32::from_le_bytes([
+                    data[size - 4],
+                    data[size - 3],
+                    data[size - 2],
+                    data[size - 1],
+                ]) & 0x3fff_ffff,
+            ),
+            _ => return Err("invalid rANS data"),
+        };
+        let state = state + precision * 4;
+        if state >= precision * 4 * 256 {
+            return Err("invalid rANS data");
+        }
+        Ok(RAnsDecoder {
+            data,
+            offset,
+            state,
+            precision,
+            lookup,
+            probabilities,
+        })
+    }
+
+    fn symbol(&mut self) -> u32 {
+        while self.state < self.precision * 4 && self.offset > 0 {
+            self.offset -= 1;
+            self.state = self.state * 256 + u32::from(self.data[self.offset]);
+        }
+        let quotient = self.state / self.precision;
+        let remainder = self.state % self.precision;
+        let symbol = self.lookup[remainder as usize];
+        let (probability, cumulative) = self.probabilities[symbol as usize];
+        self.state = quotient * probability + remainder - cumulative;
+        symbol
+    }
+}
+
+/// Reader of bits, starting at the least significant bit of the first byte
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        BitReader { data, position: 0 }
+    }
+
+    fn read(&mut self, bits: u32) -> DecodeResult<u32> {
+        let mut value = 0;
+        for bit in 0..bits {
+            let byte = self
+                .data
+                .get(self.position / 8)
+                .ok_or("unexpected end of data")?;
+            value |= u32::from((byte >> (self.position % 8)) & 1) << bit;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+}
+
+/// Reader of little endian values
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, position: 0 }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.data[self.position..]
+    }
+
+    fn bytes(&mut self, len: usize) -> DecodeResult<&'a [u8]> {
+        if len > self.remaining() {
+            return Err("unexpected end of data");
+        }
+        let bytes = &self.data[self.position..self.position + len];
+        self.position += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> DecodeResult<u8> {
+        self.bytes(1).map(|bytes| bytes[0])
+    }
+
+    fn i8(&mut self) -> DecodeResult<i8> {
+        self.u8().map(|value| value as i8)
+    }
+
+    fn u16(&mut self) -> DecodeResult<u16> {
+        self.bytes(2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> DecodeResult<u32> {
+        self.bytes(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn i32(&mut self) -> DecodeResult<i32> {
+        self.u32().map(|value| value as i32)
+    }
+
+    fn f32(&mut self) -> DecodeResult<f32> {
+        self.u32().map(f32::from_bits)
+    }
+
+    /// Reads an unsigned LEB128 value.
+    fn varint(&mut self) -> DecodeResult<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("invalid varint")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::importer::import, *};
+    use amethyst_assets::Directory;
+    use approx::assert_relative_eq;
+    use std::sync::Arc;
+
+    fn import_quad() -> (Gltf, Buffers) {
+        let source = Arc::new(Directory::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/assets"
+        )));
+        import(source, "draco.gltf").expect("Failed to import test asset")
+    }
+
+    fn decoded(gltf: &Gltf, buffers: &Buffers, semantic: Semantic) -> Vec<f32> {
+        let accessor = gltf
+            .meshes()
+            .next()
+            .and_then(|mesh| mesh.primitives().next())
+            .and_then(|primitive| primitive.get(&semantic))
+            .expect("Test asset misses an attribute");
+        buffers
+            .decoded(&accessor)
+            .expect("Attribute was not decoded")
+            .to_vec()
+    }
+
+    #[test]
+    fn decodes_sequential_mesh() {
+        let (gltf, buffers) = import_quad();
+        let indices = gltf.accessors().next().unwrap();
+        assert_eq!(
+            buffers.decoded_indices(&indices),
+            Some(&[0, 1, 2, 0, 2, 3][..])
+        );
+
+        // positions are quantized to 10 bits
+        let positions = decoded(&gltf, &buffers, Semantic::Positions);
+        let expected = [0., 0., 0., 1., 0., 0., 1., 1., 0., 0., 1., 0.];
+        assert_eq!(positions.len(), expected.len());
+        for (position, expected) in positions.iter().zip(&expected) {
+            assert_relative_eq!(*position, *expected, epsilon = 1e-3);
+        }
+
+        assert_eq!(
+            decoded(&gltf, &buffers, Semantic::Normals),
+            vec![0., 0., 1., 1., 0., 0., 0., -1., 0., 0., 0., 1.]
+        );
+        assert_eq!(
+            decoded(&gltf, &buffers, Semantic::TexCoords(0)),
+            vec![0., 0., 1., 0., 1., 1., 0., 1.]
+        );
+        assert_eq!(
+            decoded(&gltf, &buffers, Semantic::Colors(0)),
+            vec![
+                1.,
+                0.,
+                0.,
+                1.,
+                0.,
+                1.,
+                0.,
+                1.,
+                0.,
+                0.,
+                1.,
+                1.,
+                1.,
+                1.,
+                1.,
+                128. / 255.
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let (_, buffers) = import_quad();
+        let data = &buffers.take()[0];
+        assert!(decode(data).is_ok());
+        for len in 0..data.len() {
+            assert!(decode(&data[..len]).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_edgebreaker() {
+        let header = b"DRACO\x02\x02\x01\x01\x00\x00";
+        assert_eq!(
+            decode(header).unwrap_err(),
+            "only the sequential encoding is supported"
+        );
+    }
+}
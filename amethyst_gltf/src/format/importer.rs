@@ -1,9 +1,11 @@
-use std::{path::Path, sync::Arc};
+use std::{collections::HashMap, path::Path, sync::Arc};
 
 use amethyst_assets::Source as AssetSource;
 use amethyst_error::Error;
 use gltf::{self, json, Gltf};
 
+#[cfg(feature = "draco")]
+use super::draco;
 use crate::error;
 
 #[derive(Debug)]
@@ -13,24 +15,39 @@ pub enum ImageFormat {
 }
 
 impl ImageFormat {
-    fn from_mime_type(mime: &str) -> Self {
+    fn from_mime_type(mime: &str) -> Result<Self, Error> {
         match mime {
-            "image/jpeg" => ImageFormat::Jpeg,
-            "image/png" => ImageFormat::Png,
-            _ => unreachable!(),
+            "image/jpeg" => Ok(ImageFormat::Jpeg),
+            "image/png" => Ok(ImageFormat::Png),
+            _ => Err(error::Error::UnsupportedImage(mime.to_string()).into()),
         }
     }
 }
 
 /// Buffer data returned from `import`.
+///
+/// Also holds the contents of accessors decoded from Draco compressed primitives.
 #[derive(Clone, Debug)]
-pub struct Buffers(Vec<Vec<u8>>);
+pub struct Buffers {
+    data: Vec<Vec<u8>>,
+    decoded: HashMap<usize, Vec<f32>>,
+    decoded_indices: HashMap<usize, Vec<u32>>,
+}
 
 #[allow(unused)]
 impl Buffers {
+    /// Wrap already loaded buffer data.
+    pub fn new(data: Vec<Vec<u8>>) -> Self {
+        Buffers {
+            data,
+            decoded: HashMap::new(),
+            decoded_indices: HashMap::new(),
+        }
+    }
+
     /// Obtain the contents of a loaded buffer.
     pub fn buffer(&self, buffer: &gltf::Buffer<'_>) -> Option<&[u8]> {
-        self.0.get(buffer.index()).map(Vec::as_slice)
+        self.data.get(buffer.index()).map(Vec::as_slice)
     }
 
     /// Obtain the contents of a loaded buffer view.
@@ -42,9 +59,31 @@ impl Buffers {
         })
     }
 
+    /// Obtain the decoded components of an accessor of a Draco compressed primitive.
+    pub fn decoded(&self, accessor: &gltf::Accessor<'_>) -> Option<&[f32]> {
+        self.decoded.get(&accessor.index()).map(Vec::as_slice)
+    }
+
+    /// Obtain the decoded indices of a Draco compressed primitive.
+    pub fn decoded_indices(&self, accessor: &gltf::Accessor<'_>) -> Option<&[u32]> {
+        self.decoded_indices
+            .get(&accessor.index())
+            .map(Vec::as_slice)
+    }
+
+    /// Store the decoded components of an accessor.
+    pub fn insert_decoded(&mut self, accessor: usize, components: Vec<f32>) {
+        self.decoded.insert(accessor, components);
+    }
+
+    /// Store the decoded contents of an indices accessor.
+    pub fn insert_decoded_indices(&mut self, accessor: usize, indices: Vec<u32>) {
+        self.decoded_indices.insert(accessor, indices);
+    }
+
     /// Take the loaded buffer data.
     pub fn take(self) -> Vec<Vec<u8>> {
-        self.0
+        self.data
     }
 }
 
@@ -116,8 +155,14 @@ fn import_standard(
     source: Arc<dyn AssetSource>,
     base_path: &Path,
 ) -> Result<(Gltf, Buffers), Error> {
+    #[cfg(not(feature = "draco"))]
     let gltf = Gltf::from_slice(data)?;
-    let buffers = Buffers(load_external_buffers(source, base_path, &gltf, None)?);
+    #[cfg(feature = "draco")]
+    let gltf = draco::parse(data)?;
+    #[cfg_attr(not(feature = "draco"), allow(unused_mut))]
+    let mut buffers = Buffers::new(load_external_buffers(source, base_path, &gltf, None)?);
+    #[cfg(feature = "draco")]
+    draco::decode_primitives(data, &gltf, &mut buffers)?;
     Ok((gltf, buffers))
 }
 
@@ -127,9 +172,15 @@ fn import_binary(
     base_path: &Path,
 ) -> Result<(Gltf, Buffers), Error> {
     let gltf::binary::Glb { json, bin, .. } = gltf::binary::Glb::from_slice(data)?;
+    #[cfg(not(feature = "draco"))]
     let gltf = Gltf::from_slice(&json)?;
+    #[cfg(feature = "draco")]
+    let gltf = draco::parse(&json)?;
     let bin = bin.map(|x| x.to_vec());
-    let buffers = Buffers(load_external_buffers(source, base_path, &gltf, bin)?);
+    #[cfg_attr(not(feature = "draco"), allow(unused_mut))]
+    let mut buffers = Buffers::new(load_external_buffers(source, base_path, &gltf, bin)?);
+    #[cfg(feature = "draco")]
+    draco::decode_primitives(&json, &gltf, &mut buffers)?;
     Ok((gltf, buffers))
 }
 
@@ -145,14 +196,14 @@ pub fn get_image_data(
             let data = buffers
                 .view(&view)
                 .expect("`view` of image data points to a buffer which does not exist");
-            Ok((data.to_vec(), ImageFormat::from_mime_type(mime_type)))
+            Ok((data.to_vec(), ImageFormat::from_mime_type(mime_type)?))
         }
 
         Source::Uri { uri, mime_type } => {
             if uri.starts_with("data:") {
                 let data = parse_data_uri(uri)?;
                 if let Some(ty) = mime_type {
-                    Ok((data, ImageFormat::from_mime_type(ty)))
+                    Ok((data, ImageFormat::from_mime_type(ty)?))
                 } else {
                     let mimetype = uri
                         .split(',')
@@ -164,7 +215,7 @@ pub fn get_image_data(
                         .split(';')
                         .next()
                         .expect("Unreachable: `split` will always return at least one element");
-                    Ok((data, ImageFormat::from_mime_type(mimetype)))
+                    Ok((data, ImageFormat::from_mime_type(mimetype)?))
                 }
             } else {
                 let path = base_path
@@ -176,7 +227,7 @@ pub fn get_image_data(
                         .expect("Path contains invalid UTF-8 characters"),
                 )?;
                 if let Some(ty) = mime_type {
-                    Ok((data, ImageFormat::from_mime_type(ty)))
+                    Ok((data, ImageFormat::from_mime_type(ty)?))
                 } else {
                     let ext = path
                         .extension()
@@ -185,7 +236,7 @@ pub fn get_image_data(
                     let format = match &ext[..] {
                         "jpg" | "jpeg" => ImageFormat::Jpeg,
                         "png" => ImageFormat::Png,
-                        _ => unreachable!(),
+                        _ => return Err(error::Error::UnsupportedImage(ext).into()),
                     };
                     Ok((data, format))
                 }
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+
+use amethyst_assets::Prefab;
+use amethyst_core::math::{convert, Quaternion, Unit, UnitQuaternion, Vector3, Vector4};
+use amethyst_rendy::{
+    light::{DirectionalLight, Light, PointLight, SpotLight},
+    palette::{LinSrgb, Srgb},
+};
+use gltf::khr_lights_punctual::{self, Kind};
+
+use crate::GltfPrefab;
+
+/// Illuminance, in lux, at which lights without a range stop affecting their surroundings.
+const MIN_ILLUMINANCE: f32 = 0.01;
+
+/// Load the `KHR_lights_punctual` lights of the scene onto the entities of their nodes.
+///
+/// Directional and spot lights have a fixed direction in the engine, it is taken from the
+/// global rotation of the node at import time.
+pub fn load_lights(
+    scene: &gltf::Scene<'_>,
+    node_map: &HashMap<usize, usize>,
+    prefab: &mut Prefab<GltfPrefab>,
+) {
+    for node in scene.nodes() {
+        load_node_lights(&node, UnitQuaternion::identity(), node_map, prefab);
+    }
+}
+
+fn load_node_lights(
+    node: &gltf::Node<'_>,
+    parent_rotation: UnitQuaternion<f32>,
+    node_map: &HashMap<usize, usize>,
+    prefab: &mut Prefab<GltfPrefab>,
+) {
+    let (_, rotation, _) = node.transform().decomposed();
+    let rotation = parent_rotation
+        * Unit::new_normalize(convert::<_, Quaternion<f32>>(Quaternion::from(
+            Vector4::from(rotation),
+        )));
+    if let (Some(light), Some(entity)) = (node.light(), node_map.get(&node.index())) {
+        prefab.data_or_default(*entity).light = Some(load_light(&light, rotation));
+    }
+    for child in node.children() {
+        load_node_lights(&child, rotation, node_map, prefab);
+    }
+}
+
+/// Convert a glTF light, pointing along the negative z axis of a node with the given global
+/// rotation.
+///
+/// Intensities are converted as follows:
+///
+/// * point and spot lights are given in candela by glTF, and converted to lumens by multiplying
+///   with `4π`, the solid angle of a full sphere, as the spot cone is not taken into account by
+///   `KHR_lights_punctual`
+/// * directional lights are given in lux, which is used unchanged
+///
+/// Lights without a range reach as far as their illuminance is at least `MIN_ILLUMINANCE`. The
+/// smoothness of spot lights is the part of the outer cone not covered by the inner cone, so
+/// equal cone angles give a hard edge.
+pub fn load_light(light: &khr_lights_punctual::Light<'_>, rotation: UnitQuaternion<f32>) -> Light {
+    let [r, g, b] = light.color();
+    let color = Srgb::from_linear(LinSrgb::new(r, g, b));
+    let direction = rotation * -Vector3::z();
+    let candela = light.intensity();
+    let lumens = candela * 4. * std::f32::consts::PI;
+    let range = light
+        .range()
+        .unwrap_or_else(|| (candela / MIN_ILLUMINANCE).sqrt());
+    match light.kind() {
+        Kind::Directional => Light::Directional(DirectionalLight {
+            color,
+            intensity: light.intensity(),
+            direction,
+        }),
+        Kind::Point => Light::Point(PointLight {
+            color,
+            intensity: lumens,
+            radius: range,
+            ..PointLight::default()
+        }),
+        Kind::Spot {
+            inner_cone_angle,
+            outer_cone_angle,
+        } => Light::Spot(SpotLight {
+            angle: outer_cone_angle,
+            color,
+            direction,
+            intensity: lumens,
+            range,
+            smoothness: if outer_cone_angle > 0. {
+                (1. - inner_cone_angle / outer_cone_angle).max(0.).min(1.)
+            } else {
+                0.
+            },
+            cookie: None,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use gltf::Gltf;
+
+    const TOLERANCE: f32 = 1e-4;
+
+    fn load_test_lights() -> Prefab<GltfPrefab> {
+        let gltf = Gltf::from_slice(include_bytes!("../../tests/assets/lights.gltf"))
+            .expect("Failed to parse test asset");
+        let scene = gltf.scenes().next().expect("Test asset has no scene");
+        let mut prefab = Prefab::new();
+        let mut node_map = HashMap::new();
+        for node in gltf.nodes() {
+            node_map.insert(node.index(), prefab.add(Some(0), None));
+        }
+        load_lights(&scene, &node_map, &mut prefab);
+        prefab
+    }
+
+    fn light(prefab: &mut Prefab<GltfPrefab>, node: usize) -> Light {
+        prefab
+            .data_or_default(node + 1)
+            .light
+            .clone()
+            .expect("Node has no light")
+    }
+
+    #[test]
+    fn imports_point_light() {
+        let mut prefab = load_test_lights();
+        match light(&mut prefab, 0) {
+            Light::Point(point) => {
+                assert_relative_eq!(
+                    point.intensity,
+                    2. * 4. * std::f32::consts::PI,
+                    epsilon = TOLERANCE
+                );
+                assert_relative_eq!(point.radius, 5., epsilon = TOLERANCE);
+                assert_relative_eq!(point.color.red, 1., epsilon = TOLERANCE);
+                assert_relative_eq!(
+                    point.color.green,
+                    Srgb::from_linear(LinSrgb::new(0.5, 0.5, 0.5)).green,
+                    epsilon = TOLERANCE
+                );
+            }
+            other => panic!("Expected point light, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn imports_directional_light() {
+        let mut prefab = load_test_lights();
+        match light(&mut prefab, 1) {
+            Light::Directional(directional) => {
+                assert_relative_eq!(directional.intensity, 3., epsilon = TOLERANCE);
+                // node is rotated by -90 degrees around the x axis, pointing the light down
+                assert!((directional.direction - Vector3::new(0., -1., 0.)).norm() < TOLERANCE);
+            }
+            other => panic!("Expected directional light, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn imports_spot_light() {
+        let mut prefab = load_test_lights();
+        match light(&mut prefab, 2) {
+            Light::Spot(spot) => {
+                assert_relative_eq!(spot.angle, 0.8, epsilon = TOLERANCE);
+                assert_relative_eq!(spot.smoothness, 0.5, epsilon = TOLERANCE);
+                assert_relative_eq!(
+                    spot.intensity,
+                    4. * std::f32::consts::PI,
+                    epsilon = TOLERANCE
+                );
+                assert_relative_eq!(
+                    spot.range,
+                    (1. / MIN_ILLUMINANCE).sqrt(),
+                    epsilon = TOLERANCE
+                );
+                assert!((spot.direction - Vector3::new(0., 0., -1.)).norm() < TOLERANCE);
+            }
+            other => panic!("Expected spot light, got {:?}", other),
+        }
+    }
+}
@@ -111,6 +111,9 @@ pub fn load_material(
             prefab.alpha_cutoff = 0.0;
         }
     }
+
+    // `KHR_materials_unlit` only uses the base color
+    prefab.unlit = material.unlit();
     Ok(prefab)
 }
 
@@ -124,8 +127,7 @@ fn load_texture_with_factor(
 ) -> Result<(TextureBuilder<'static>, [f32; 4]), Error> {
     match texture {
         Some(info) => Ok((
-            load_texture(&info.texture(), buffers, source, name, srgb)?
-                .with_mip_levels(MipLevels::GenerateAuto),
+            load_texture(&info.texture(), buffers, source, name, srgb)?,
             factor,
         )),
         None => Ok((
@@ -139,6 +141,11 @@ fn load_texture_with_factor(
     }
 }
 
+/// Load the image of a texture with the sampler settings of the texture.
+///
+/// The image is decoded again for every material binding, so an image shared by textures with
+/// different samplers becomes separate `Texture`s, each with its own sampler. Mip levels are only
+/// generated if the minification filter uses them.
 fn load_texture(
     texture: &gltf::Texture<'_>,
     buffers: &Buffers,
@@ -158,7 +165,21 @@ fn load_texture(
         ..Default::default()
     };
 
-    load_from_image(std::io::Cursor::new(&data), metadata).map_err(|e| e.compat().into())
+    let builder = load_from_image(std::io::Cursor::new(&data), metadata).map_err(|e| e.compat())?;
+    Ok(if uses_mipmaps(&texture.sampler()) {
+        builder.with_mip_levels(MipLevels::GenerateAuto)
+    } else {
+        builder
+    })
+}
+
+/// The plain nearest and linear filters of glTF sample the base level only.
+fn uses_mipmaps(sampler: &gltf::texture::Sampler<'_>) -> bool {
+    use gltf::texture::MinFilter;
+    match sampler.min_filter() {
+        Some(MinFilter::Nearest) | Some(MinFilter::Linear) => false,
+        _ => true,
+    }
 }
 
 fn load_sampler_info(sampler: &gltf::texture::Sampler<'_>) -> hal::image::SamplerInfo {
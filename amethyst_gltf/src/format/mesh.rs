@@ -1,16 +1,22 @@
-use super::Buffers;
+use super::{
+    accessor::{read_if_sparse, read_sparse_attribute},
+    Buffers,
+};
 use crate::{error, GltfSceneOptions};
 use amethyst_core::math::{zero, Vector3};
 use amethyst_error::Error;
 use amethyst_rendy::{
+    morph::{MorphMeshData, MorphTarget},
     rendy::mesh::{Color, MeshBuilder, Normal, Position, Tangent, TexCoord},
     skinning::JointCombined,
+    vertex::TexCoord2,
 };
+use gltf::Semantic;
 use log::{trace, warn};
 use mikktspace::{generate_tangents, Geometry};
-use std::{iter::repeat, ops::Range};
+use std::ops::Range;
 
-fn compute_if<T, F: Fn() -> T>(predicate: bool, func: F) -> Option<T> {
+fn compute_if<T, F: FnOnce() -> T>(predicate: bool, func: F) -> Option<T> {
     if predicate {
         Some(func())
     } else {
@@ -18,7 +24,7 @@ fn compute_if<T, F: Fn() -> T>(predicate: bool, func: F) -> Option<T> {
     }
 }
 
-fn try_compute_if<T, F: Fn() -> Option<T>>(predicate: bool, func: F) -> Option<T> {
+fn try_compute_if<T, F: FnOnce() -> Option<T>>(predicate: bool, func: F) -> Option<T> {
     if predicate {
         func()
     } else {
@@ -26,6 +32,7 @@ fn try_compute_if<T, F: Fn() -> Option<T>>(predicate: bool, func: F) -> Option<T
     }
 }
 
+#[derive(Clone)]
 enum Indices {
     None,
     U16(Vec<u16>),
@@ -48,13 +55,37 @@ impl Indices {
             Indices::U32(vec) => vec[face * 3 + vert] as usize,
         }
     }
+
+    fn add_to(self, builder: &mut MeshBuilder<'static>) {
+        match self {
+            Indices::U16(vec) => {
+                builder.set_indices(vec);
+            }
+            Indices::U32(vec) => {
+                builder.set_indices(vec);
+            }
+            Indices::None => {}
+        }
+    }
 }
 
+/// Attribute data, either resolved from a sparse or Draco compressed accessor or read with the
+/// `gltf` readers
+type Attribute<'a, T> = Box<dyn Iterator<Item = T> + 'a>;
+
+/// A loaded mesh primitive: the mesh, its material index, its bounds and its morph targets
+pub type Primitive = (
+    MeshBuilder<'static>,
+    Option<usize>,
+    Range<[f32; 3]>,
+    Option<MorphMeshData>,
+);
+
 pub fn load_mesh(
     mesh: &gltf::Mesh<'_>,
     buffers: &Buffers,
     options: &GltfSceneOptions,
-) -> Result<Vec<(MeshBuilder<'static>, Option<usize>, Range<[f32; 3]>)>, Error> {
+) -> Result<Vec<Primitive>, Error> {
     trace!("Loading mesh");
     let mut primitives = vec![];
 
@@ -65,23 +96,40 @@ pub fn load_mesh(
 
         trace!("Loading indices");
         use gltf::mesh::util::ReadIndices;
-        let indices = match reader.read_indices() {
-            Some(ReadIndices::U8(iter)) => Indices::U16(iter.map(u16::from).collect()),
-            Some(ReadIndices::U16(iter)) => Indices::U16(iter.collect()),
-            Some(ReadIndices::U32(iter)) => Indices::U32(iter.collect()),
-            None => Indices::None,
+        let decoded_indices = primitive
+            .indices()
+            .and_then(|accessor| buffers.decoded_indices(&accessor));
+        let indices = match (decoded_indices, reader.read_indices()) {
+            (Some(indices), _) => Indices::U32(indices.to_vec()),
+            (None, Some(ReadIndices::U8(iter))) => Indices::U16(iter.map(u16::from).collect()),
+            (None, Some(ReadIndices::U16(iter))) => Indices::U16(iter.collect()),
+            (None, Some(ReadIndices::U32(iter))) => Indices::U32(iter.collect()),
+            (None, None) => Indices::None,
         };
 
+        // sparse and Draco compressed accessors are resolved up front, the `gltf` readers only
+        // see their base data
+        let sparse_normals = read_sparse_attribute(&primitive, &Semantic::Normals, buffers)?;
+        let sparse_tangents = read_sparse_attribute(&primitive, &Semantic::Tangents, buffers)?;
+        let sparse_colors = read_sparse_attribute(&primitive, &Semantic::Colors(0), buffers)?;
+        let sparse_joints = read_sparse_attribute(&primitive, &Semantic::Joints(0), buffers)?;
+        let sparse_weights = read_sparse_attribute(&primitive, &Semantic::Weights(0), buffers)?;
+
         trace!("Loading positions");
-        let positions = reader
-            .read_positions()
-            .ok_or(error::Error::MissingPositions)?
-            .map(Position)
-            .collect::<Vec<_>>();
+        let positions = match read_sparse_attribute(&primitive, &Semantic::Positions, buffers)? {
+            Some(positions) => positions.into_iter().map(Position).collect::<Vec<_>>(),
+            None => reader
+                .read_positions()
+                .ok_or(error::Error::MissingPositions)?
+                .map(Position)
+                .collect::<Vec<_>>(),
+        };
 
         let normals = compute_if(options.load_normals || options.load_tangents, || {
             trace!("Loading normals");
-            if let Some(normals) = reader.read_normals() {
+            if let Some(normals) = sparse_normals {
+                normals.into_iter().map(Normal).collect::<Vec<_>>()
+            } else if let Some(normals) = reader.read_normals() {
                 normals.map(Normal).collect::<Vec<_>>()
             } else {
                 trace!("Calculating normals");
@@ -89,31 +137,33 @@ pub fn load_mesh(
             }
         });
 
-        let tex_coords = compute_if(options.load_texcoords || options.load_tangents, || {
+        let tex_coords = if options.load_texcoords || options.load_tangents {
             trace!("Loading texture coordinates");
-            if let Some(tex_coords) = reader.read_tex_coords(0).map(|t| t.into_f32()) {
-                if options.flip_v_coord {
-                    tex_coords
-                        .map(|[u, v]| TexCoord([u, 1. - v]))
-                        .collect::<Vec<_>>()
-                } else {
-                    tex_coords.map(TexCoord).collect::<Vec<_>>()
+            let tex_coords = match load_tex_coords(&primitive, buffers, 0, options.flip_v_coord)? {
+                Some(tex_coords) => tex_coords,
+                None => {
+                    let (u, v) = options.generate_tex_coords;
+                    let v = if options.flip_v_coord { v } else { 1.0 - v };
+                    vec![TexCoord([u, v]); positions.len()]
                 }
-            } else {
-                let (u, v) = options.generate_tex_coords;
-                let v = if options.flip_v_coord { v } else { 1.0 - v };
-                repeat(TexCoord([u, v]))
-                    .take(positions.len())
-                    .collect::<Vec<_>>()
-            }
-        });
+            };
+            Some(tex_coords)
+        } else {
+            None
+        };
+
+        let tex_coords_2 = if options.load_texcoords {
+            load_tex_coords::<TexCoord2>(&primitive, buffers, 1, options.flip_v_coord)?
+        } else {
+            None
+        };
 
         let tangents = compute_if(options.load_tangents, || {
             trace!("Loading tangents");
-            let tangents = reader.read_tangents();
-            match tangents {
-                Some(tangents) => tangents.map(Tangent).collect::<Vec<_>>(),
-                None => {
+            match (sparse_tangents, reader.read_tangents()) {
+                (Some(tangents), _) => tangents.into_iter().map(Tangent).collect::<Vec<_>>(),
+                (None, Some(tangents)) => tangents.map(Tangent).collect::<Vec<_>>(),
+                (None, None) => {
                     trace!("Calculating tangents");
                     calculate_tangents(
                         &positions,
@@ -127,7 +177,9 @@ pub fn load_mesh(
 
         let colors = try_compute_if(options.load_colors, || {
             trace!("Loading colors");
-            if let Some(colors) = reader.read_colors(0) {
+            if let Some(colors) = sparse_colors {
+                Some(colors.into_iter().map(Color).collect::<Vec<_>>())
+            } else if let Some(colors) = reader.read_colors(0) {
                 Some(colors.into_rgba_f32().map(Color).collect::<Vec<_>>())
             } else {
                 None
@@ -136,8 +188,20 @@ pub fn load_mesh(
 
         let joints = try_compute_if(options.load_animations, || {
             trace!("Loading animations");
-            if let (Some(ids), Some(weights)) = (reader.read_joints(0), reader.read_weights(0)) {
-                let zip = ids.into_u16().zip(weights.into_f32());
+            let ids = match sparse_joints {
+                Some(ids) => Some(Box::new(ids.into_iter()) as Attribute<'_, [u16; 4]>),
+                None => reader
+                    .read_joints(0)
+                    .map(|ids| Box::new(ids.into_u16()) as Attribute<'_, [u16; 4]>),
+            };
+            let weights = match sparse_weights {
+                Some(weights) => Some(Box::new(weights.into_iter()) as Attribute<'_, [f32; 4]>),
+                None => reader
+                    .read_weights(0)
+                    .map(|weights| Box::new(weights.into_f32()) as Attribute<'_, [f32; 4]>),
+            };
+            if let (Some(ids), Some(weights)) = (ids, weights) {
+                let zip = ids.zip(weights);
                 let joints = zip
                     .map(|(ids, weights)| JointCombined::new(ids, weights))
                     .collect::<Vec<_>>();
@@ -148,20 +212,56 @@ pub fn load_mesh(
             }
         });
 
-        match indices {
-            Indices::U16(vec) => {
-                builder.set_indices(vec);
-            }
-            Indices::U32(vec) => {
-                builder.set_indices(vec);
+        let targets = if options.load_animations {
+            trace!("Loading morph targets");
+            let mut targets = Vec::new();
+            for (target, (positions, normals, _)) in
+                primitive.morph_targets().zip(reader.read_morph_targets())
+            {
+                targets.push(MorphTarget {
+                    positions: match read_if_sparse(target.positions(), buffers)? {
+                        Some(positions) => positions,
+                        None => positions.map(Iterator::collect).unwrap_or_default(),
+                    },
+                    normals: match read_if_sparse(target.normals(), buffers)? {
+                        Some(normals) => normals,
+                        None => normals.map(Iterator::collect).unwrap_or_default(),
+                    },
+                });
             }
-            Indices::None => {}
+            Some(targets).filter(|targets| !targets.is_empty())
+        } else {
+            None
         };
 
+        // morph targets are blended on the CPU, which needs everything but the positions and
+        // normals in a separate mesh
+        let morph = targets.map(|targets| {
+            trace!("Building morph target rest mesh");
+            let mut rest = MeshBuilder::new();
+            indices.clone().add_to(&mut rest);
+            tangents.clone().map(|v| rest.add_vertices(v));
+            tex_coords.clone().map(|v| rest.add_vertices(v));
+            tex_coords_2.clone().map(|v| rest.add_vertices(v));
+            colors.clone().map(|v| rest.add_vertices(v));
+            joints.clone().map(|v| rest.add_vertices(v));
+            MorphMeshData {
+                rest: rest.into(),
+                positions: positions.iter().map(|p| p.0).collect(),
+                normals: normals
+                    .as_ref()
+                    .map(|normals| normals.iter().map(|n| n.0).collect()),
+                targets,
+            }
+        });
+
+        indices.add_to(&mut builder);
+
         builder.add_vertices(positions);
         normals.map(|v| builder.add_vertices(v));
         tangents.map(|v| builder.add_vertices(v));
         tex_coords.map(|v| builder.add_vertices(v));
+        tex_coords_2.map(|v| builder.add_vertices(v));
         colors.map(|v| builder.add_vertices(v));
         joints.map(|v| builder.add_vertices(v));
 
@@ -170,12 +270,37 @@ pub fn load_mesh(
         let bounds = bounds.min..bounds.max;
         let material = primitive.material().index();
 
-        primitives.push((builder, material, bounds));
+        primitives.push((builder, material, bounds, morph));
     }
     trace!("Loaded mesh");
     Ok(primitives)
 }
 
+/// Reads the texture coordinates of the given set, `TEXCOORD_<set>`, if the primitive has them.
+fn load_tex_coords<T: From<[f32; 2]>>(
+    primitive: &gltf::Primitive<'_>,
+    buffers: &Buffers,
+    set: u32,
+    flip_v_coord: bool,
+) -> Result<Option<Vec<T>>, Error> {
+    let reader = primitive.reader(|buffer| buffers.buffer(&buffer));
+    let tex_coords = match read_sparse_attribute(primitive, &Semantic::TexCoords(set), buffers)? {
+        Some(tex_coords) => Box::new(tex_coords.into_iter()) as Attribute<'_, [f32; 2]>,
+        None => match reader.read_tex_coords(set) {
+            Some(tex_coords) => Box::new(tex_coords.into_f32()) as Attribute<'_, [f32; 2]>,
+            None => return Ok(None),
+        },
+    };
+    let tex_coords = if flip_v_coord {
+        tex_coords
+            .map(|[u, v]| T::from([u, 1. - v]))
+            .collect::<Vec<_>>()
+    } else {
+        tex_coords.map(T::from).collect::<Vec<_>>()
+    };
+    Ok(Some(tex_coords))
+}
+
 fn calculate_normals(positions: &[Position], indices: &Indices) -> Vec<Normal> {
     let mut normals = vec![zero::<Vector3<f32>>(); positions.len()];
     let num_faces = indices.len().unwrap_or_else(|| positions.len()) / 3;
@@ -253,8 +378,13 @@ fn calculate_tangents(
 
 #[cfg(test)]
 mod tests {
-    use super::{calculate_tangents, Indices};
-    use amethyst_rendy::rendy::mesh::{Normal, Position, Tangent, TexCoord};
+    use super::{super::importer::import, calculate_tangents, load_tex_coords, Indices};
+    use amethyst_assets::Directory;
+    use amethyst_rendy::{
+        rendy::mesh::{Normal, Position, Tangent, TexCoord},
+        vertex::TexCoord2,
+    };
+    use std::sync::Arc;
 
     const POSITIONS: &[Position] = &[
         Position([0.0, 0.0, 0.0]),
@@ -317,4 +447,51 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn loads_both_tex_coord_sets() {
+        let source = Arc::new(Directory::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/assets"
+        )));
+        let (gltf, buffers) = import(source, "lightmap.gltf").expect("Failed to import test asset");
+        let primitive = gltf
+            .meshes()
+            .next()
+            .and_then(|mesh| mesh.primitives().next())
+            .expect("Test asset has no primitive");
+
+        let tex_coords = load_tex_coords::<TexCoord>(&primitive, &buffers, 0, false).unwrap();
+        assert_eq!(
+            tex_coords,
+            Some(vec![
+                TexCoord([0.0, 0.0]),
+                TexCoord([1.0, 0.0]),
+                TexCoord([0.0, 1.0]),
+            ])
+        );
+
+        let tex_coords_2 = load_tex_coords::<TexCoord2>(&primitive, &buffers, 1, false).unwrap();
+        assert_eq!(
+            tex_coords_2,
+            Some(vec![
+                TexCoord2([0.5, 0.5]),
+                TexCoord2([0.75, 0.5]),
+                TexCoord2([0.5, 0.75]),
+            ])
+        );
+
+        let flipped = load_tex_coords::<TexCoord2>(&primitive, &buffers, 1, true).unwrap();
+        assert_eq!(
+            flipped,
+            Some(vec![
+                TexCoord2([0.5, 0.5]),
+                TexCoord2([0.75, 0.5]),
+                TexCoord2([0.5, 0.25]),
+            ])
+        );
+
+        let missing = load_tex_coords::<TexCoord2>(&primitive, &buffers, 2, false).unwrap();
+        assert_eq!(missing, None);
+    }
 }
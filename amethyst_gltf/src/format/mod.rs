@@ -13,20 +13,29 @@ use amethyst_core::{
     transform::Transform,
 };
 use amethyst_error::{format_err, Error, ResultExt};
-use amethyst_rendy::camera::CameraPrefab;
+use amethyst_rendy::morph::{MorphMeshPrefab, MorphWeights};
 
-use crate::{error, GltfMaterialSet, GltfNodeExtent, GltfPrefab, GltfSceneOptions, Named};
+use crate::{
+    error, GltfMaterialSet, GltfNodeExtent, GltfNodeNames, GltfPrefab, GltfSceneOptions, Named,
+};
 
 use self::{
-    animation::load_animations,
+    animation::{load_animations, load_morph_animations},
+    camera::{camera_name, load_camera},
     importer::{get_image_data, import, Buffers, ImageFormat},
+    light::load_lights,
     material::load_material,
     mesh::load_mesh,
     skin::load_skin,
 };
 
+mod accessor;
 mod animation;
+mod camera;
+#[cfg(feature = "draco")]
+mod draco;
 mod importer;
+mod light;
 mod material;
 mod mesh;
 mod skin;
@@ -80,6 +89,8 @@ fn load_data(
     source: Arc<dyn Source>,
     name: &str,
 ) -> Result<Prefab<GltfPrefab>, Error> {
+    #[cfg(not(feature = "draco"))]
+    check_extensions(gltf)?;
     let scene_index = get_scene_index(gltf, options)?;
     let mut prefab = Prefab::<GltfPrefab>::new();
     load_scene(
@@ -94,6 +105,20 @@ fn load_data(
     Ok(prefab)
 }
 
+/// Draco mesh compression is only supported with the `draco` feature. Without it, files that only
+/// use it keep the uncompressed attributes as a fallback, which are loaded instead. Files that
+/// require it can not be loaded.
+#[cfg(not(feature = "draco"))]
+fn check_extensions(gltf: &Gltf) -> Result<(), Error> {
+    if gltf
+        .extensions_required()
+        .any(|extension| extension == "KHR_draco_mesh_compression")
+    {
+        return Err(error::Error::DracoUnsupported.into());
+    }
+    Ok(())
+}
+
 fn get_scene_index(gltf: &Gltf, options: &GltfSceneOptions) -> Result<usize, Error> {
     let num_scenes = gltf.scenes().len();
     match (options.scene_index, gltf.default_scene()) {
@@ -124,6 +149,7 @@ fn load_scene(
     let mut skin_map = HashMap::new();
     let mut bounding_box = GltfNodeExtent::default();
     let mut material_set = GltfMaterialSet::default();
+    let mut node_names = GltfNodeNames::default();
     for node in scene.nodes() {
         let index = prefab.add(Some(0), None);
         load_node(
@@ -139,13 +165,32 @@ fn load_scene(
             &mut skin_map,
             &mut bounding_box,
             &mut material_set,
+            &mut node_names,
         )?;
     }
+    node_names.indices = node_map.clone();
+    prefab.data_or_default(0).node_names = Some(node_names);
     if bounding_box.valid() {
         prefab.data_or_default(0).extent = Some(bounding_box);
     }
     prefab.data_or_default(0).materials = Some(material_set);
 
+    // load lights
+    load_lights(&scene, &node_map, prefab);
+
+    // list the cameras, so one of them can be picked as the active camera
+    let cameras = gltf
+        .nodes()
+        .filter_map(|node| {
+            let camera = node.camera()?;
+            let index = node_map.get(&node.index())?;
+            Some((camera_name(&node, &camera), *index))
+        })
+        .collect::<Vec<_>>();
+    if !cameras.is_empty() {
+        prefab.data_or_default(0).cameras = Some(cameras);
+    }
+
     // load skins
     for (node_index, skin_info) in skin_map {
         load_skin(
@@ -158,6 +203,7 @@ fn load_scene(
                 .expect("Unreachable: `node_map` should contain all nodes present in `skin_map`"),
             &node_map,
             skin_info.mesh_indices,
+            options.max_joints,
             prefab,
         )?;
     }
@@ -180,6 +226,21 @@ fn load_scene(
             .animatable
             .get_or_insert_with(Default::default)
             .animation_set = Some(load_animations(gltf, buffers, &node_map)?);
+
+        let morph_animations = load_morph_animations(gltf, buffers, &node_map)?;
+        if !morph_animations.animations.is_empty() {
+            let mut hierarchy_prefab = AnimationHierarchyPrefab::default();
+            hierarchy_prefab.nodes = node_map
+                .iter()
+                .map(|(node, entity)| (*node, *entity))
+                .collect();
+            let morph_animatable = prefab
+                .data_or_default(0)
+                .morph_animatable
+                .get_or_insert_with(Default::default);
+            morph_animatable.hierarchy = Some(hierarchy_prefab);
+            morph_animatable.animation_set = Some(morph_animations);
+        }
     }
 
     Ok(())
@@ -204,12 +265,14 @@ fn load_node(
     skin_map: &mut HashMap<usize, SkinInfo>,
     parent_bounding_box: &mut GltfNodeExtent,
     material_set: &mut GltfMaterialSet,
+    node_names: &mut GltfNodeNames,
 ) -> Result<(), Error> {
     node_map.insert(node.index(), entity_index);
 
     // Load node name.
     if let Some(name) = node.name() {
         prefab.data_or_default(entity_index).name = Some(Named::new(name.to_string()));
+        add_name(&mut node_names.names, name, entity_index);
     }
 
     // Load transformation data, default will be identity
@@ -224,32 +287,10 @@ fn load_node(
 
     // Load camera
     if let Some(camera) = node.camera() {
-        prefab.data_or_default(entity_index).camera = Some(match camera.projection() {
-            gltf::camera::Projection::Orthographic(proj) => CameraPrefab::Orthographic {
-                left: -proj.xmag(),
-                right: proj.xmag(),
-                bottom: -proj.ymag(),
-                top: proj.ymag(),
-                znear: proj.znear(),
-                zfar: proj.zfar(),
-            },
-            gltf::camera::Projection::Perspective(proj) => CameraPrefab::Perspective {
-                aspect: proj.aspect_ratio().ok_or_else(|| {
-                    format_err!(
-                        "Camera {} is a perspective projection, but has no aspect ratio",
-                        camera.index()
-                    )
-                })?,
-                fovy: proj.yfov(),
-                znear: proj.znear(),
-                zfar: proj.zfar().ok_or_else(|| {
-                    format_err!(
-                        "Camera {} is perspective projection, but has no far plane",
-                        camera.index()
-                    )
-                })?,
-            },
-        });
+        let (camera, auto_fov) = load_camera(&camera);
+        let prefab_data = prefab.data_or_default(entity_index);
+        prefab_data.camera = Some(camera);
+        prefab_data.auto_fov = auto_fov;
     }
 
     // check for skinning
@@ -262,14 +303,36 @@ fn load_node(
 
     // load graphics
     if let Some(mesh) = node.mesh() {
+        let mesh_name = mesh.name();
         let mut graphics = load_mesh(&mesh, buffers, options)?;
+
+        // the weights of the morph targets are shared by all primitives of the mesh
+        if let Some(targets) = graphics
+            .iter()
+            .filter_map(|primitive| primitive.3.as_ref())
+            .map(|morph| morph.targets.len())
+            .max()
+        {
+            let weights = mesh
+                .weights()
+                .map(<[f32]>::to_vec)
+                .unwrap_or_else(|| vec![0.; targets]);
+            prefab.data_or_default(entity_index).morph_weights = Some(MorphWeights::new(weights));
+        }
+        let morph_prefab = |morph: Option<_>| {
+            morph.map(|data| MorphMeshPrefab {
+                weights: entity_index,
+                data: Arc::new(data),
+            })
+        };
         match graphics.len().cmp(&1) {
             Ordering::Equal => {
                 // single primitive can be loaded directly onto the node
-                let (mesh, material_index, bounds) = graphics.remove(0);
+                let (mesh, material_index, bounds, morph) = graphics.remove(0);
                 bounding_box.extend_range(&bounds);
                 let prefab_data = prefab.data_or_default(entity_index);
                 prefab_data.mesh = Some(mesh);
+                prefab_data.morph_mesh = morph_prefab(morph);
                 if let Some((material_id, material)) =
                     material_index.and_then(|index| gltf.materials().nth(index).map(|m| (index, m)))
                 {
@@ -278,6 +341,12 @@ fn load_node(
                         .entry(material_id)
                         .or_insert(load_material(&material, buffers, source.clone(), name)?);
                     prefab_data.material_id = Some(material_id);
+                    if let Some(material_name) = material.name() {
+                        add_name(&mut node_names.materials, material_name, entity_index);
+                    }
+                }
+                if let Some(mesh_name) = mesh_name {
+                    add_name(&mut node_names.meshes, mesh_name, entity_index);
                 }
                 // if we have a skin we need to track the mesh entities
                 if let Some(ref mut skin) = skin {
@@ -287,11 +356,12 @@ fn load_node(
             Ordering::Greater => {
                 // if we have multiple primitives,
                 // we need to add each primitive as a child entity to the node
-                for (mesh, material_index, bounds) in graphics {
+                for (mesh, material_index, bounds, morph) in graphics {
                     let mesh_entity = prefab.add(Some(entity_index), None);
                     let prefab_data = prefab.data_or_default(mesh_entity);
                     prefab_data.transform = Some(Transform::default());
                     prefab_data.mesh = Some(mesh);
+                    prefab_data.morph_mesh = morph_prefab(morph);
                     if let Some((material_id, material)) = material_index
                         .and_then(|index| gltf.materials().nth(index).map(|m| (index, m)))
                     {
@@ -300,6 +370,12 @@ fn load_node(
                             .entry(material_id)
                             .or_insert(load_material(&material, buffers, source.clone(), name)?);
                         prefab_data.material_id = Some(material_id);
+                        if let Some(material_name) = material.name() {
+                            add_name(&mut node_names.materials, material_name, mesh_entity);
+                        }
+                    }
+                    if let Some(mesh_name) = mesh_name {
+                        add_name(&mut node_names.meshes, mesh_name, mesh_entity);
                     }
 
                     // if we have a skin we need to track the mesh entities
@@ -332,6 +408,7 @@ fn load_node(
             skin_map,
             &mut bounding_box,
             material_set,
+            node_names,
         )?;
     }
     if bounding_box.valid() {
@@ -346,3 +423,106 @@ fn load_node(
 
     Ok(())
 }
+
+fn add_name(names: &mut HashMap<String, Vec<usize>>, name: &str, entity_index: usize) {
+    names
+        .entry(name.to_string())
+        .or_default()
+        .push(entity_index);
+}
+
+#[cfg(test)]
+mod tests {
+    use amethyst_assets::Directory;
+    use amethyst_core::ecs::prelude::{Builder, World, WorldExt};
+
+    use super::*;
+
+    #[cfg(not(feature = "draco"))]
+    const DRACO_REQUIRED: &[u8] = include_bytes!("../../tests/assets/draco_required.gltf");
+
+    #[cfg(not(feature = "draco"))]
+    #[test]
+    fn rejects_files_requiring_draco() {
+        let gltf = Gltf::from_slice(DRACO_REQUIRED).expect("Failed to parse test asset");
+        let err = check_extensions(&gltf).expect_err("Draco should be unsupported");
+        assert_eq!(err.to_string(), error::Error::DracoUnsupported.to_string());
+    }
+
+    #[cfg(not(feature = "draco"))]
+    #[test]
+    fn accepts_files_only_using_draco() {
+        let json = std::str::from_utf8(DRACO_REQUIRED).expect("Test asset is not UTF-8");
+        let start = json
+            .find("\"extensionsRequired\"")
+            .expect("Test asset requires no extensions");
+        let end = start + json[start..].find("],").expect("Malformed test asset") + 2;
+        let optional = format!("{}{}", &json[..start], &json[end..]);
+        let gltf = Gltf::from_slice(optional.as_bytes()).expect("Failed to parse test asset");
+        assert!(check_extensions(&gltf).is_ok());
+    }
+
+    #[cfg(feature = "draco")]
+    #[test]
+    fn loads_files_requiring_draco() {
+        let source = Arc::new(Directory::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/assets"
+        )));
+        let prefab = load_gltf(source, "draco.gltf", &GltfSceneOptions::default())
+            .expect("Failed to load test asset");
+        assert!(prefab
+            .entities()
+            .filter_map(|entity| entity.data())
+            .any(|data| data.mesh.is_some()));
+    }
+
+    #[test]
+    fn node_map_resolves_spawned_entities() {
+        let source = Arc::new(Directory::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/assets"
+        )));
+        let prefab = load_gltf(source, "node_map.gltf", &GltfSceneOptions::default())
+            .expect("Failed to load test asset");
+
+        let mut world = World::new();
+        let entities = prefab
+            .entities()
+            .map(|_| world.create_entity().build())
+            .collect::<Vec<_>>();
+        let node_map = prefab
+            .entities()
+            .next()
+            .and_then(|entity| entity.data())
+            .and_then(|data| data.node_names.as_ref())
+            .expect("Node names are missing on the main entity")
+            .resolve(&entities);
+
+        assert_eq!(node_map.node("car"), Some(node_map.indices[&0]));
+        assert_eq!(
+            node_map.nodes("wheel"),
+            &[node_map.indices[&1], node_map.indices[&2]]
+        );
+        assert_eq!(node_map.node("lamp"), Some(node_map.indices[&3]));
+        assert_eq!(node_map.node("missing"), None);
+
+        // every node resolves to the entity spawned for its own prefab entity
+        let names = prefab
+            .entities()
+            .map(|entity| {
+                entity
+                    .data()
+                    .and_then(|data| data.name.as_ref())
+                    .map(|name| name.name.to_string())
+            })
+            .collect::<Vec<_>>();
+        for (name, nodes) in &node_map.names {
+            for node in nodes {
+                let index = entities.iter().position(|e| e == node).unwrap();
+                assert_eq!(names[index].as_ref(), Some(name));
+            }
+        }
+        assert_eq!(node_map.indices.len(), 4);
+    }
+}
@@ -5,9 +5,10 @@ use amethyst_assets::Prefab;
 use amethyst_core::math::{convert, Matrix4};
 use amethyst_error::Error;
 use amethyst_rendy::skinning::JointTransformsPrefab;
+use log::debug;
 
 use super::Buffers;
-use crate::GltfPrefab;
+use crate::{error, GltfPrefab};
 
 pub fn load_skin(
     skin: &gltf::Skin<'_>,
@@ -15,8 +16,24 @@ pub fn load_skin(
     skin_entity: usize,
     node_map: &HashMap<usize, usize>,
     meshes: Vec<usize>,
+    max_joints: usize,
     prefab: &mut Prefab<GltfPrefab>,
 ) -> Result<(), Error> {
+    let joint_count = skin.joints().len();
+    debug!(
+        "Loading skin {} with {} joints",
+        skin.name().unwrap_or(&skin.index().to_string()),
+        joint_count
+    );
+    if joint_count > max_joints {
+        return Err(error::Error::TooManyJoints {
+            skin: skin.index(),
+            joints: joint_count,
+            max_joints,
+        }
+        .into());
+    }
+
     let joints = skin
         .joints()
         .map(|j| {
@@ -15,16 +15,23 @@ use amethyst_assets::{
     ProgressCounter,
 };
 use amethyst_core::{
-    ecs::prelude::{Entity, Read, ReadExpect, Write, WriteStorage},
+    ecs::prelude::{Component, Entity, HashMapStorage, Read, ReadExpect, Write, WriteStorage},
     math::{convert, Point3, Vector3},
     transform::Transform,
     Named,
 };
 use amethyst_error::Error;
 use amethyst_rendy::{
-    camera::CameraPrefab, formats::mtl::MaterialPrefab, rendy::mesh::MeshBuilder, types::Mesh,
+    camera::CameraPrefab,
+    formats::mtl::MaterialPrefab,
+    light::Light,
+    morph::{MorphMeshPrefab, MorphWeights},
+    rendy::mesh::MeshBuilder,
+    skinning::MAX_JOINTS,
+    types::Mesh,
     visibility::BoundingSphere,
 };
+use amethyst_utils::auto_fov::AutoFov;
 use derivative::Derivative;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, ops::Range};
@@ -49,8 +56,13 @@ pub struct GltfPrefab {
     /// `Transform` will almost always be placed, the only exception is for the main `Entity` for
     /// certain scenarios (based on the data in the Gltf file)
     pub transform: Option<Transform>,
-    /// `Camera` will always be placed
+    /// `Camera` is placed on all `Entity`s of nodes with a camera
     pub camera: Option<CameraPrefab>,
+    /// `AutoFov` is placed on all `Entity`s with a perspective camera, so the aspect ratio follows
+    /// the window instead of the file
+    pub auto_fov: Option<AutoFov>,
+    /// `Light` is placed on all `Entity`s of nodes with a `KHR_lights_punctual` light
+    pub light: Option<Light>,
     /// `MeshData` is placed on all `Entity`s with graphics primitives
     pub mesh: Option<MeshBuilder<'static>>,
     /// Mesh handle after sub asset loading is done
@@ -62,11 +74,21 @@ pub struct GltfPrefab {
     /// Skin data is placed on `Entity`s involved in the skin, skeleton or graphical primitives
     /// using the skin
     pub skinnable: Option<SkinnablePrefab>,
+    /// Weights of the morph targets, placed on `Entity`s with a mesh with morph targets
+    pub morph_weights: Option<MorphWeights>,
+    /// Morph targets of the graphics primitive, placed on `Entity`s with graphics primitives with
+    /// morph targets
+    pub morph_mesh: Option<MorphMeshPrefab>,
+    /// Loaded morph target weight animations, if applicable, will always only be placed on the
+    /// main `Entity`
+    pub morph_animatable: Option<AnimatablePrefab<usize, MorphWeights>>,
     /// Node extent
     pub extent: Option<GltfNodeExtent>,
     /// Node name
     pub name: Option<Named>,
     pub(crate) materials: Option<GltfMaterialSet>,
+    pub(crate) cameras: Option<Vec<(String, usize)>>,
+    pub(crate) node_names: Option<GltfNodeNames>,
     pub(crate) material_id: Option<usize>,
 }
 
@@ -177,6 +199,102 @@ impl From<Range<[f32; 3]>> for GltfNodeExtent {
     }
 }
 
+/// The cameras imported from a Gltf scene, placed on the main `Entity` of the scene.
+///
+/// Use it to pick one of the cameras as the `ActiveCamera`.
+#[derive(Clone, Debug, Default)]
+pub struct GltfCameras {
+    /// Name and `Entity` of the cameras, in the order of the nodes in the Gltf file.
+    ///
+    /// Cameras are named after their node, or after the camera itself if the node has no name.
+    pub cameras: Vec<(String, Entity)>,
+}
+
+impl GltfCameras {
+    /// Get the first camera with the given name
+    pub fn get(&self, name: &str) -> Option<Entity> {
+        self.cameras
+            .iter()
+            .find(|(camera, _)| camera == name)
+            .map(|(_, entity)| *entity)
+    }
+}
+
+impl Component for GltfCameras {
+    type Storage = HashMapStorage<Self>;
+}
+
+/// The entities created for a Gltf scene, placed on the main `Entity` of the scene.
+///
+/// Names that are used more than once map to all of their entities, in the order the scene
+/// hierarchy is loaded.
+#[derive(Clone, Debug, Default)]
+pub struct GltfNodeMap {
+    /// Node entities by node name
+    pub names: HashMap<String, Vec<Entity>>,
+    /// Node entities by node index in the Gltf file
+    pub indices: HashMap<usize, Entity>,
+    /// Entities with a `Handle<Mesh>` by mesh name, one per primitive of the mesh
+    pub meshes: HashMap<String, Vec<Entity>>,
+    /// Entities with a `Handle<Material>` by material name
+    pub materials: HashMap<String, Vec<Entity>>,
+}
+
+impl GltfNodeMap {
+    /// Get the first node with the given name
+    pub fn node(&self, name: &str) -> Option<Entity> {
+        self.nodes(name).first().cloned()
+    }
+
+    /// Get all nodes with the given name
+    pub fn nodes(&self, name: &str) -> &[Entity] {
+        self.names.get(name).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Get all entities using the material with the given name
+    pub fn material(&self, name: &str) -> &[Entity] {
+        self.materials.get(name).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
+
+impl Component for GltfNodeMap {
+    type Storage = HashMapStorage<Self>;
+}
+
+/// Prefab entity indices for the `GltfNodeMap`, collected during loading
+#[derive(Clone, Debug, Default)]
+pub(crate) struct GltfNodeNames {
+    pub(crate) names: HashMap<String, Vec<usize>>,
+    pub(crate) indices: HashMap<usize, usize>,
+    pub(crate) meshes: HashMap<String, Vec<usize>>,
+    pub(crate) materials: HashMap<String, Vec<usize>>,
+}
+
+impl GltfNodeNames {
+    fn resolve(&self, entities: &[Entity]) -> GltfNodeMap {
+        let resolve_all = |map: &HashMap<String, Vec<usize>>| {
+            map.iter()
+                .map(|(name, indices)| {
+                    (
+                        name.clone(),
+                        indices.iter().map(|index| entities[*index]).collect(),
+                    )
+                })
+                .collect()
+        };
+        GltfNodeMap {
+            names: resolve_all(&self.names),
+            indices: self
+                .indices
+                .iter()
+                .map(|(node, index)| (*node, entities[*index]))
+                .collect(),
+            meshes: resolve_all(&self.meshes),
+            materials: resolve_all(&self.materials),
+        }
+    }
+}
+
 /// Used during gltf loading to contain the materials used from scenes in the file
 #[derive(Debug, Derivative)]
 #[derivative(Default(bound = ""))]
@@ -211,6 +329,9 @@ pub struct GltfSceneOptions {
     /// Load the given scene index, if not supplied will either load the default scene (if set),
     /// or the first scene (only if there is only one scene, otherwise an `Error` will be returned).
     pub scene_index: Option<usize>,
+    #[derivative(Default(value = "MAX_JOINTS"))]
+    /// Maximum number of joints of a skin, loading a skin with more fails with an `Error`
+    pub max_joints: usize,
 }
 
 impl<'a> PrefabData<'a> for GltfPrefab {
@@ -218,9 +339,16 @@ impl<'a> PrefabData<'a> for GltfPrefab {
         <Transform as PrefabData<'a>>::SystemData,
         <Named as PrefabData<'a>>::SystemData,
         <CameraPrefab as PrefabData<'a>>::SystemData,
+        <AutoFov as PrefabData<'a>>::SystemData,
+        WriteStorage<'a, GltfCameras>,
+        WriteStorage<'a, GltfNodeMap>,
+        <Light as PrefabData<'a>>::SystemData,
         <MaterialPrefab as PrefabData<'a>>::SystemData,
         <AnimatablePrefab<usize, Transform> as PrefabData<'a>>::SystemData,
         <SkinnablePrefab as PrefabData<'a>>::SystemData,
+        <MorphWeights as PrefabData<'a>>::SystemData,
+        <MorphMeshPrefab as PrefabData<'a>>::SystemData,
+        <AnimatablePrefab<usize, MorphWeights> as PrefabData<'a>>::SystemData,
         WriteStorage<'a, BoundingSphere>,
         WriteStorage<'a, Handle<Mesh>>,
         Read<'a, AssetStorage<Mesh>>,
@@ -240,9 +368,16 @@ impl<'a> PrefabData<'a> for GltfPrefab {
             transforms,
             names,
             cameras,
+            auto_fovs,
+            camera_lists,
+            node_maps,
+            lights,
             materials,
             animatables,
             skinnables,
+            morph_weights,
+            morph_meshes,
+            morph_animatables,
             bound,
             meshes,
             _,
@@ -258,6 +393,26 @@ impl<'a> PrefabData<'a> for GltfPrefab {
         if let Some(camera) = &self.camera {
             camera.add_to_entity(entity, cameras, entities, children)?;
         }
+        if let Some(auto_fov) = &self.auto_fov {
+            auto_fov.add_to_entity(entity, auto_fovs, entities, children)?;
+        }
+        if let Some(list) = &self.cameras {
+            camera_lists.insert(
+                entity,
+                GltfCameras {
+                    cameras: list
+                        .iter()
+                        .map(|(name, index)| (name.clone(), entities[*index]))
+                        .collect(),
+                },
+            )?;
+        }
+        if let Some(node_names) = &self.node_names {
+            node_maps.insert(entity, node_names.resolve(entities))?;
+        }
+        if let Some(light) = &self.light {
+            light.add_to_entity(entity, lights, entities, children)?;
+        }
         if let Some(name) = &self.name {
             name.add_to_entity(entity, names, entities, children)?;
         }
@@ -270,6 +425,15 @@ impl<'a> PrefabData<'a> for GltfPrefab {
         if let Some(skinnable) = &self.skinnable {
             skinnable.add_to_entity(entity, skinnables, entities, children)?;
         }
+        if let Some(weights) = &self.morph_weights {
+            weights.add_to_entity(entity, morph_weights, entities, children)?;
+        }
+        if let Some(morph_mesh) = &self.morph_mesh {
+            morph_mesh.add_to_entity(entity, morph_meshes, entities, children)?;
+        }
+        if let Some(animatable) = &self.morph_animatable {
+            animatable.add_to_entity(entity, morph_animatables, entities, children)?;
+        }
         if let Some(extent) = &self.extent {
             bound.insert(entity, extent.clone().into())?;
         }
@@ -281,8 +445,26 @@ impl<'a> PrefabData<'a> for GltfPrefab {
         progress: &mut ProgressCounter,
         system_data: &mut Self::SystemData,
     ) -> Result<bool, Error> {
-        let (_, _, _, materials, animatables, _, _, _, meshes_storage, loader, mat_set) =
-            system_data;
+        let (
+            _,
+            _,
+            _,
+            _,
+            _,
+            _,
+            _,
+            materials,
+            animatables,
+            _,
+            _,
+            _,
+            morph_animatables,
+            _,
+            _,
+            meshes_storage,
+            loader,
+            mat_set,
+        ) = system_data;
 
         let mut ret = false;
         if let Some(mut mats) = self.materials.take() {
@@ -305,6 +487,9 @@ impl<'a> PrefabData<'a> for GltfPrefab {
         if let Some(animatable) = &mut self.animatable {
             ret |= animatable.load_sub_assets(progress, animatables)?;
         }
+        if let Some(animatable) = &mut self.morph_animatable {
+            ret |= animatable.load_sub_assets(progress, morph_animatables)?;
+        }
         Ok(ret)
     }
 }
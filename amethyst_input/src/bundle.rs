@@ -61,6 +61,23 @@ impl<T: BindingTypes> InputBundle<T> {
         Ok(self.with_bindings(bindings))
     }
 
+    /// Load bindings from several files, each overriding the ones before it, then from the
+    /// environment variables starting with `AMETHYST_INPUT__`, e.g.
+    /// `AMETHYST_INPUT__ACTIONS__JUMP="[[Key(Space)]]"`.
+    ///
+    /// See `Config::load_layered_with_env` for how the bindings are merged.
+    pub fn with_bindings_from_layered_files<P: AsRef<Path>>(
+        self,
+        files: &[P],
+    ) -> Result<Self, BindingsFileError<T>>
+    where
+        Bindings<T>: Config,
+    {
+        let mut bindings = Bindings::load_layered_with_env(files, "AMETHYST_INPUT")?;
+        bindings.check_invariants()?;
+        Ok(self.with_bindings(bindings))
+    }
+
     /// Load SDL controller mappings from file
     #[cfg(feature = "sdl_controller")]
     pub fn with_sdl_controller_mappings(mut self, mappings: String) -> Self {
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use derivative::Derivative;
 use serde::{Deserialize, Serialize};
 use winit::{MouseButton, VirtualKeyCode};
@@ -116,4 +118,14 @@ where
     ActionReleased(T::Action),
     /// The associated action has its mouse wheel moved.
     ActionWheelMoved(T::Action),
+    /// A file is dragged over the window.
+    ///
+    /// When several files are dragged at once, one event is sent for each of them.
+    FileHovered(PathBuf),
+    /// A file was dropped on the window.
+    ///
+    /// When several files are dropped at once, one event is sent for each of them, in order.
+    FileDropped(PathBuf),
+    /// The files dragged over the window left it without being dropped.
+    FileHoverCancelled,
 }
@@ -232,6 +232,15 @@ where
                     }
                     self.mouse_position = Some(((x as f32) * hidpi, (y as f32) * hidpi));
                 }
+                WindowEvent::HoveredFile(ref path) => {
+                    event_handler.single_write(FileHovered(path.clone()));
+                }
+                WindowEvent::DroppedFile(ref path) => {
+                    event_handler.single_write(FileDropped(path.clone()));
+                }
+                WindowEvent::HoveredFileCancelled => {
+                    event_handler.single_write(FileHoverCancelled);
+                }
                 WindowEvent::Focused(false) => {
                     self.pressed_keys.clear();
                     self.pressed_mouse_buttons.clear();
@@ -1228,6 +1237,35 @@ mod tests {
         assert_ulps_eq!(handler.mouse_wheel_value(true), -1.0);
     }
 
+    #[test]
+    fn dropped_files_are_sent_in_order() {
+        let mut handler = InputHandler::<StringBindings>::new();
+        let mut events = EventChannel::<InputEvent<StringBindings>>::new();
+        let mut reader = events.register_reader();
+        let window_events = vec![
+            WindowEvent::HoveredFile("level.ron".into()),
+            WindowEvent::HoveredFileCancelled,
+            WindowEvent::DroppedFile("level.ron".into()),
+            WindowEvent::DroppedFile("scene.gltf".into()),
+        ];
+        for event in window_events {
+            let event = Event::WindowEvent {
+                window_id: unsafe { WindowId::dummy() },
+                event,
+            };
+            handler.send_event(&event, &mut events, HIDPI);
+        }
+        assert_eq!(
+            events.read(&mut reader).cloned().collect::<Vec<_>>(),
+            vec![
+                InputEvent::FileHovered("level.ron".into()),
+                InputEvent::FileHoverCancelled,
+                InputEvent::FileDropped("level.ron".into()),
+                InputEvent::FileDropped("scene.gltf".into()),
+            ]
+        );
+    }
+
     /// Compares two sets for equality, but not the order
     fn sets_are_equal<T>(a: &[T], b: &[T])
     where
@@ -4,12 +4,15 @@
 
 mod events;
 mod message;
+pub mod replication;
 mod requirements;
+mod stats;
 mod timing;
 mod transport;
 
-pub use events::NetworkSimulationEvent;
+pub use events::{DisconnectReason, NetworkSimulationEvent};
 pub use message::Message;
 pub use requirements::{DeliveryRequirement, UrgencyRequirement};
+pub use stats::{NetworkStats, TrafficStats, STATS_WINDOW};
 pub use timing::{NetworkSimulationTime, NetworkSimulationTimeSystem};
 pub use transport::{laminar, tcp, udp, TransportResource};
@@ -9,8 +9,8 @@ pub enum NetworkSimulationEvent {
     Message(SocketAddr, Bytes),
     // A new host has connected to us
     Connect(SocketAddr),
-    // A host has disconnected from us
-    Disconnect(SocketAddr),
+    // A host has disconnected from us. On a client, this is the server being lost.
+    Disconnect(SocketAddr, DisconnectReason),
     // An error occurred while receiving a message.
     RecvError(io::Error),
     // An error occurred while sending a message.
@@ -18,3 +18,12 @@ pub enum NetworkSimulationEvent {
     // An error occurred while managing connections.
     ConnectionError(io::Error, Option<SocketAddr>),
 }
+
+/// Why a host disconnected.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// The host closed the connection.
+    Graceful,
+    /// Nothing was received from the host for too long.
+    Timeout,
+}
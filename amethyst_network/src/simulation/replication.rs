@@ -0,0 +1,319 @@
+//! Opt-in replication of components from an authoritative server to its clients.
+//!
+//! The server gives every entity to replicate a `NetworkId`, allocated with the
+//! `NetworkIdAllocator`. Components registered with `Replicated<T>` are then sent to all connected
+//! clients, starting with a full snapshot when a client connects and continuing with snapshots of
+//! the changed components at the tick rate of the server. Clients spawn, update and despawn
+//! mirrored entities, rendering the state of the server `interpolation_delay` in the past so
+//! components implementing `Interpolate` move smoothly between snapshots.
+//!
+//! Snapshots are sent as messages starting with `REPLICATION_MESSAGE_PREFIX` with the
+//! `ReliableOrdered` delivery requirement, other messages are ignored by the replication systems.
+
+mod client;
+mod server;
+
+pub use client::{
+    ReplicationClient, ReplicationClientBundle, ReplicationClientSystem,
+    ReplicationClientSystemDesc, ReplicationInterpolationSystem, ReplicationReceiveSystem,
+};
+pub use server::{
+    ReplicationServer, ReplicationServerBundle, ReplicationServerSystem,
+    ReplicationServerSystemDesc, ReplicationSnapshotSystem, ReplicationTickSystem,
+    ReplicationTickSystemDesc,
+};
+
+use amethyst_core::{
+    ecs::{Component, DenseVecStorage},
+    Transform,
+};
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+/// Prefix of the messages carrying snapshots.
+pub const REPLICATION_MESSAGE_PREFIX: &[u8; 4] = b"AREP";
+
+/// Identifies a replicated entity on the server and all clients.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct NetworkId(pub u64);
+
+impl Component for NetworkId {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Resource handing out the `NetworkId`s of the server.
+#[derive(Debug, Default)]
+pub struct NetworkIdAllocator {
+    next: u64,
+}
+
+impl NetworkIdAllocator {
+    /// Returns a `NetworkId` not used before.
+    pub fn allocate(&mut self) -> NetworkId {
+        let id = NetworkId(self.next);
+        self.next += 1;
+        id
+    }
+}
+
+/// Registration of a replicated component type.
+///
+/// The kind identifies the component type in snapshots and has to be the same on the server and
+/// the clients, and unique among the replicated component types.
+#[derive(Debug)]
+pub struct Replicated<T> {
+    kind: u16,
+    marker: PhantomData<T>,
+}
+
+impl<T> Replicated<T> {
+    /// Registers `T` as replicated, identified by `kind`.
+    pub fn new(kind: u16) -> Self {
+        Self {
+            kind,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the kind identifying the component type in snapshots.
+    pub fn kind(&self) -> u16 {
+        self.kind
+    }
+}
+
+/// Components that can be interpolated between two snapshots on clients.
+pub trait Interpolate {
+    /// Returns the value at `t` between `self` at 0 and `other` at 1.
+    fn interpolate(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Interpolate for Transform {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self.slerp(other, t)
+    }
+}
+
+/// Component data sent in a snapshot, `None` if the component was removed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ComponentUpdate {
+    pub(crate) id: NetworkId,
+    pub(crate) kind: u16,
+    pub(crate) data: Option<Vec<u8>>,
+}
+
+/// State of the replicated entities at a time of the server.
+///
+/// Full snapshots contain all replicated entities, entities missing from them are despawned.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Snapshot {
+    pub(crate) time: f64,
+    pub(crate) full: bool,
+    pub(crate) spawned: Vec<NetworkId>,
+    pub(crate) despawned: Vec<NetworkId>,
+    pub(crate) updates: Vec<ComponentUpdate>,
+}
+
+impl Snapshot {
+    pub(crate) fn encode(&self) -> Result<Vec<u8>, bincode::Error> {
+        let mut message = REPLICATION_MESSAGE_PREFIX.to_vec();
+        bincode::serialize_into(&mut message, self)?;
+        Ok(message)
+    }
+
+    /// Returns `None` for messages not carrying a snapshot.
+    pub(crate) fn decode(message: &[u8]) -> Option<Result<Self, bincode::Error>> {
+        if message.starts_with(REPLICATION_MESSAGE_PREFIX) {
+            Some(bincode::deserialize(
+                &message[REPLICATION_MESSAGE_PREFIX.len()..],
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{NetworkSimulationEvent, TransportResource};
+    use amethyst_core::{
+        ecs::{Builder, Dispatcher, DispatcherBuilder, Join, ReadStorage, World, WorldExt},
+        math::{UnitQuaternion, Vector3},
+        shrev::EventChannel,
+        SystemBundle, Time,
+    };
+    use bytes::Bytes;
+    use std::{net::SocketAddr, time::Duration};
+
+    fn server_address() -> SocketAddr {
+        "127.0.0.1:3000".parse().unwrap()
+    }
+
+    fn client_address() -> SocketAddr {
+        "127.0.0.1:3001".parse().unwrap()
+    }
+
+    fn setup<B>(bundle: B) -> (World, Dispatcher<'static, 'static>)
+    where
+        B: for<'a, 'b> SystemBundle<'a, 'b>,
+    {
+        let mut world = World::new();
+        world.insert(Time::default());
+        let mut builder = DispatcherBuilder::new();
+        bundle.build(&mut world, &mut builder).unwrap();
+        let mut dispatcher = builder.build();
+        dispatcher.setup(&mut world);
+        (world, dispatcher)
+    }
+
+    fn step(world: &mut World, dispatcher: &mut Dispatcher<'_, '_>, delta: Duration) {
+        world.write_resource::<Time>().set_delta_time(delta);
+        dispatcher.dispatch(world);
+        world.maintain();
+    }
+
+    /// Moves the messages sent by the server to the event channel of the client.
+    fn transfer(server: &mut World, client: &mut World) {
+        let messages = server
+            .write_resource::<TransportResource>()
+            .drain_messages(|_| true);
+        let mut channel = client.write_resource::<EventChannel<NetworkSimulationEvent>>();
+        for message in messages {
+            assert_eq!(message.destination, client_address());
+            channel.single_write(NetworkSimulationEvent::Message(
+                server_address(),
+                Bytes::copy_from_slice(&message.payload),
+            ));
+        }
+    }
+
+    fn client_translations(world: &World) -> Vec<(NetworkId, f32)> {
+        let (ids, transforms): (ReadStorage<'_, NetworkId>, ReadStorage<'_, Transform>) =
+            world.system_data();
+        let mut translations = (&ids, &transforms)
+            .join()
+            .map(|(id, transform)| (*id, transform.translation().x))
+            .collect::<Vec<_>>();
+        translations.sort_by_key(|(id, _)| *id);
+        translations
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let snapshot = Snapshot {
+            time: 1.5,
+            full: true,
+            spawned: vec![NetworkId(3)],
+            despawned: vec![NetworkId(2)],
+            updates: vec![ComponentUpdate {
+                id: NetworkId(3),
+                kind: 1,
+                data: Some(vec![1, 2, 3]),
+            }],
+        };
+        let message = snapshot.encode().unwrap();
+        assert_eq!(Snapshot::decode(&message).unwrap().unwrap(), snapshot);
+        assert!(Snapshot::decode(b"hello").is_none());
+    }
+
+    #[test]
+    fn interpolates_transform() {
+        let a = Transform::from(Vector3::new(0., 0., 0.));
+        let mut b = Transform::from(Vector3::new(2., 4., 0.));
+        b.set_rotation(UnitQuaternion::from_euler_angles(0., 0., 1.));
+        let half = a.interpolate(&b, 0.5);
+        assert!((half.translation() - Vector3::new(1., 2., 0.)).norm() < 1e-5);
+        assert!((half.rotation().angle() - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn replicates_spawn_update_and_despawn() {
+        let delay = Duration::from_millis(100);
+        let frame = Duration::from_millis(50);
+        let (mut server, mut server_dispatcher) =
+            setup(ReplicationServerBundle::new(20).with(Replicated::<Transform>::new(0)));
+        let (mut client, mut client_dispatcher) =
+            setup(
+                ReplicationClientBundle::new(server_address(), delay)
+                    .with_interpolated(Replicated::<Transform>::new(0)),
+            );
+
+        let id = server.write_resource::<NetworkIdAllocator>().allocate();
+        let entity = server
+            .create_entity()
+            .with(id)
+            .with(Transform::from(Vector3::new(0., 0., 0.)))
+            .build();
+        server
+            .write_resource::<EventChannel<NetworkSimulationEvent>>()
+            .single_write(NetworkSimulationEvent::Connect(client_address()));
+
+        // the entity moves one unit per frame
+        for i in 0..6 {
+            step(&mut server, &mut server_dispatcher, frame);
+            transfer(&mut server, &mut client);
+            step(&mut client, &mut client_dispatcher, frame);
+            server
+                .write_storage::<Transform>()
+                .get_mut(entity)
+                .unwrap()
+                .set_translation_x(i as f32 + 1.);
+        }
+        let translations = client_translations(&client);
+        assert_eq!(translations.len(), 1);
+        assert_eq!(translations[0].0, id);
+        // rendered `delay` in the past, which is two frames
+        assert!((translations[0].1 - 3.).abs() < 1e-4, "{:?}", translations);
+
+        server.delete_entity(entity).unwrap();
+        for _ in 0..4 {
+            step(&mut server, &mut server_dispatcher, frame);
+            transfer(&mut server, &mut client);
+            step(&mut client, &mut client_dispatcher, frame);
+        }
+        assert!(client_translations(&client).is_empty());
+    }
+
+    #[test]
+    fn filter_limits_replication() {
+        let (mut server, mut server_dispatcher) =
+            setup(ReplicationServerBundle::new(20).with(Replicated::<Transform>::new(0)));
+        let (mut client, mut client_dispatcher) = setup(
+            ReplicationClientBundle::new(server_address(), Duration::from_millis(0))
+                .with(Replicated::<Transform>::new(0)),
+        );
+        server
+            .write_resource::<ReplicationServer>()
+            .set_filter(|_, id| id.0 % 2 == 0);
+
+        for x in 0..4 {
+            let id = server.write_resource::<NetworkIdAllocator>().allocate();
+            server
+                .create_entity()
+                .with(id)
+                .with(Transform::from(Vector3::new(x as f32, 0., 0.)))
+                .build();
+        }
+        server
+            .write_resource::<EventChannel<NetworkSimulationEvent>>()
+            .single_write(NetworkSimulationEvent::Connect(client_address()));
+
+        for _ in 0..2 {
+            step(
+                &mut server,
+                &mut server_dispatcher,
+                Duration::from_millis(50),
+            );
+            transfer(&mut server, &mut client);
+            step(
+                &mut client,
+                &mut client_dispatcher,
+                Duration::from_millis(50),
+            );
+        }
+        assert_eq!(
+            client_translations(&client),
+            vec![(NetworkId(0), 0.), (NetworkId(2), 2.)]
+        );
+    }
+}
@@ -0,0 +1,432 @@
+//! Systems mirroring the replicated entities of the server on a client.
+
+use super::{Interpolate, NetworkId, Replicated, Snapshot};
+use crate::simulation::events::NetworkSimulationEvent;
+use amethyst_core::{
+    bundle::SystemBundle,
+    ecs::{
+        Component, DispatcherBuilder, Entities, Entity, Read, ReadExpect, ReaderId, System,
+        SystemData, World, WriteExpect, WriteStorage,
+    },
+    shrev::EventChannel,
+    timing::Time,
+    SystemDesc,
+};
+use amethyst_error::Error;
+use log::error;
+use serde::de::DeserializeOwned;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+    marker::PhantomData,
+    net::SocketAddr,
+    time::Duration,
+};
+
+const CLIENT_SYSTEM_NAME: &str = "replication_client";
+
+/// Resource holding the mirrored entities of a client and the snapshots received from the server.
+#[derive(Debug)]
+pub struct ReplicationClient {
+    server: SocketAddr,
+    interpolation_delay: Duration,
+    entities: HashMap<NetworkId, Entity>,
+    buffer: VecDeque<Snapshot>,
+    time_offset: Option<f64>,
+    render_time: Option<f64>,
+    /// Snapshots received this frame.
+    pub(crate) received: Vec<Snapshot>,
+    /// Snapshots reached by the render time this frame.
+    pub(crate) released: Vec<Snapshot>,
+}
+
+impl ReplicationClient {
+    /// Creates a client mirroring the entities of `server`, `interpolation_delay` in the past.
+    pub fn new(server: SocketAddr, interpolation_delay: Duration) -> Self {
+        Self {
+            server,
+            interpolation_delay,
+            entities: HashMap::new(),
+            buffer: VecDeque::new(),
+            time_offset: None,
+            render_time: None,
+            received: Vec::new(),
+            released: Vec::new(),
+        }
+    }
+
+    /// Returns the address of the server.
+    pub fn server(&self) -> SocketAddr {
+        self.server
+    }
+
+    /// Returns how far in the past the state of the server is rendered.
+    pub fn interpolation_delay(&self) -> Duration {
+        self.interpolation_delay
+    }
+
+    /// Sets how far in the past the state of the server is rendered. Should be a few tick
+    /// intervals of the server, so there is a snapshot to interpolate to even if one is late.
+    pub fn set_interpolation_delay(&mut self, interpolation_delay: Duration) {
+        self.interpolation_delay = interpolation_delay;
+    }
+
+    /// Returns the mirrored entity of a replicated entity.
+    pub fn entity(&self, id: NetworkId) -> Option<Entity> {
+        self.entities.get(&id).cloned()
+    }
+
+    /// Returns the time of the server that is rendered, once a snapshot was received.
+    pub fn render_time(&self) -> Option<f64> {
+        self.render_time
+    }
+
+    fn spawn(
+        &mut self,
+        id: NetworkId,
+        entities: &Entities<'_>,
+        ids: &mut WriteStorage<'_, NetworkId>,
+    ) {
+        if !self.entities.contains_key(&id) {
+            let entity = entities.create();
+            if let Err(e) = ids.insert(entity, id) {
+                error!("Failed to mirror replicated entity: {}", e);
+            }
+            self.entities.insert(id, entity);
+        }
+    }
+
+    fn despawn(&mut self, id: NetworkId, entities: &Entities<'_>) {
+        if let Some(entity) = self.entities.remove(&id) {
+            if let Err(e) = entities.delete(entity) {
+                error!("Failed to delete mirrored entity: {}", e);
+            }
+        }
+    }
+}
+
+/// Builds a `ReplicationClientSystem`.
+#[derive(Debug, Default)]
+pub struct ReplicationClientSystemDesc;
+
+impl<'a, 'b> SystemDesc<'a, 'b, ReplicationClientSystem> for ReplicationClientSystemDesc {
+    fn build(self, world: &mut World) -> ReplicationClientSystem {
+        <ReplicationClientSystem as System<'_>>::SystemData::setup(world);
+        let reader = world
+            .fetch_mut::<EventChannel<NetworkSimulationEvent>>()
+            .register_reader();
+        ReplicationClientSystem { reader }
+    }
+}
+
+/// Receives the snapshots of the server, and spawns and despawns the mirrored entities once the
+/// render time reaches their snapshot.
+///
+/// The server time is estimated from the snapshot received with the lowest latency.
+#[derive(Debug)]
+pub struct ReplicationClientSystem {
+    reader: ReaderId<NetworkSimulationEvent>,
+}
+
+impl<'s> System<'s> for ReplicationClientSystem {
+    type SystemData = (
+        WriteExpect<'s, ReplicationClient>,
+        Read<'s, EventChannel<NetworkSimulationEvent>>,
+        Read<'s, Time>,
+        Entities<'s>,
+        WriteStorage<'s, NetworkId>,
+    );
+
+    fn run(&mut self, (mut client, events, time, entities, mut ids): Self::SystemData) {
+        let client = &mut *client;
+        client.received.clear();
+        client.released.clear();
+
+        let now = time.absolute_real_time_seconds();
+        for event in events.read(&mut self.reader) {
+            let payload = match event {
+                NetworkSimulationEvent::Message(address, payload) if *address == client.server => {
+                    payload
+                }
+                _ => continue,
+            };
+            match Snapshot::decode(payload) {
+                Some(Ok(snapshot)) => {
+                    let offset = now - snapshot.time;
+                    client.time_offset = Some(client.time_offset.map_or(offset, |o| o.min(offset)));
+                    client.received.push(snapshot.clone());
+                    client.buffer.push_back(snapshot);
+                }
+                Some(Err(e)) => error!("Failed to deserialize snapshot: {}", e),
+                None => {}
+            }
+        }
+
+        let offset = match client.time_offset {
+            Some(offset) => offset,
+            None => return,
+        };
+        let render_time = now - offset - client.interpolation_delay.as_secs_f64();
+        client.render_time = Some(render_time);
+
+        while client
+            .buffer
+            .front()
+            .map_or(false, |snapshot| snapshot.time <= render_time)
+        {
+            let snapshot = client.buffer.pop_front().expect("Buffer has a snapshot");
+            if snapshot.full {
+                let present = snapshot
+                    .spawned
+                    .iter()
+                    .chain(snapshot.updates.iter().map(|update| &update.id))
+                    .collect::<HashSet<_>>();
+                let stale = client
+                    .entities
+                    .keys()
+                    .filter(|id| !present.contains(id))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                for id in stale {
+                    client.despawn(id, &entities);
+                }
+            }
+            for id in &snapshot.despawned {
+                client.despawn(*id, &entities);
+            }
+            for id in snapshot
+                .spawned
+                .iter()
+                .chain(snapshot.updates.iter().map(|update| &update.id))
+            {
+                client.spawn(*id, &entities, &mut ids);
+            }
+            client.released.push(snapshot);
+        }
+    }
+}
+
+/// Applies the replicated component `T` from the snapshots reached by the render time.
+#[derive(Debug)]
+pub struct ReplicationReceiveSystem<T> {
+    kind: u16,
+    marker: PhantomData<T>,
+}
+
+impl<T> ReplicationReceiveSystem<T> {
+    /// Creates the system applying `T`.
+    pub fn new(replicated: Replicated<T>) -> Self {
+        Self {
+            kind: replicated.kind,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'s, T> System<'s> for ReplicationReceiveSystem<T>
+where
+    T: Component + DeserializeOwned + Send + Sync,
+{
+    type SystemData = (ReadExpect<'s, ReplicationClient>, WriteStorage<'s, T>);
+
+    fn run(&mut self, (client, mut components): Self::SystemData) {
+        for snapshot in &client.released {
+            for update in snapshot.updates.iter().filter(|u| u.kind == self.kind) {
+                let entity = match client.entity(update.id) {
+                    Some(entity) => entity,
+                    None => continue,
+                };
+                match &update.data {
+                    Some(data) => match bincode::deserialize::<T>(data) {
+                        Ok(component) => {
+                            if let Err(e) = components.insert(entity, component) {
+                                error!("Failed to insert replicated component: {}", e);
+                            }
+                        }
+                        Err(e) => error!("Failed to deserialize replicated component: {}", e),
+                    },
+                    None => {
+                        components.remove(entity);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Interpolates the replicated component `T` between the snapshots around the render time.
+///
+/// Holds the last value once the render time passes the newest snapshot.
+pub struct ReplicationInterpolationSystem<T> {
+    kind: u16,
+    samples: HashMap<NetworkId, VecDeque<(f64, T)>>,
+}
+
+impl<T> fmt::Debug for ReplicationInterpolationSystem<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReplicationInterpolationSystem")
+            .field("kind", &self.kind)
+            .field("entities", &self.samples.len())
+            .finish()
+    }
+}
+
+impl<T> ReplicationInterpolationSystem<T> {
+    /// Creates the system interpolating `T`.
+    pub fn new(replicated: Replicated<T>) -> Self {
+        Self {
+            kind: replicated.kind,
+            samples: HashMap::new(),
+        }
+    }
+}
+
+impl<'s, T> System<'s> for ReplicationInterpolationSystem<T>
+where
+    T: Component + Interpolate + Clone + DeserializeOwned + Send + Sync,
+{
+    type SystemData = (ReadExpect<'s, ReplicationClient>, WriteStorage<'s, T>);
+
+    fn run(&mut self, (client, mut components): Self::SystemData) {
+        let kind = self.kind;
+        for snapshot in &client.received {
+            for update in snapshot.updates.iter().filter(|u| u.kind == kind) {
+                if let Some(data) = &update.data {
+                    match bincode::deserialize::<T>(data) {
+                        Ok(component) => self
+                            .samples
+                            .entry(update.id)
+                            .or_insert_with(VecDeque::new)
+                            .push_back((snapshot.time, component)),
+                        Err(e) => error!("Failed to deserialize replicated component: {}", e),
+                    }
+                }
+            }
+        }
+        // removals take effect at the render time, like despawns
+        for snapshot in &client.released {
+            for update in snapshot.updates.iter().filter(|u| u.kind == kind) {
+                if update.data.is_none() {
+                    self.samples.remove(&update.id);
+                    if let Some(entity) = client.entity(update.id) {
+                        components.remove(entity);
+                    }
+                }
+            }
+        }
+
+        let render_time = match client.render_time() {
+            Some(render_time) => render_time,
+            None => return,
+        };
+        for (id, samples) in &mut self.samples {
+            while samples.len() >= 2 && samples[1].0 <= render_time {
+                samples.pop_front();
+            }
+            let entity = match client.entity(*id) {
+                Some(entity) => entity,
+                None => continue,
+            };
+            let value = match (samples.get(0), samples.get(1)) {
+                (Some((from_time, from)), Some((to_time, to))) if *from_time <= render_time => {
+                    let t = (render_time - from_time) / (to_time - from_time);
+                    from.interpolate(to, t.max(0.).min(1.) as f32)
+                }
+                (Some((time, value)), None) if *time <= render_time => value.clone(),
+                _ => continue,
+            };
+            if let Err(e) = components.insert(entity, value) {
+                error!("Failed to insert replicated component: {}", e);
+            }
+        }
+        self.samples.retain(|id, samples| {
+            client.entity(*id).is_some()
+                || samples
+                    .back()
+                    .map_or(false, |(time, _)| *time > render_time)
+        });
+    }
+}
+
+type Registration = Box<dyn FnOnce(&mut DispatcherBuilder<'_, '_>)>;
+
+/// Adds the systems mirroring the replicated entities of a server.
+pub struct ReplicationClientBundle {
+    server: SocketAddr,
+    interpolation_delay: Duration,
+    registrations: Vec<Registration>,
+}
+
+impl fmt::Debug for ReplicationClientBundle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReplicationClientBundle")
+            .field("server", &self.server)
+            .field("interpolation_delay", &self.interpolation_delay)
+            .finish()
+    }
+}
+
+impl ReplicationClientBundle {
+    /// Creates a bundle mirroring the entities of `server`, `interpolation_delay` in the past.
+    pub fn new(server: SocketAddr, interpolation_delay: Duration) -> Self {
+        Self {
+            server,
+            interpolation_delay,
+            registrations: Vec::new(),
+        }
+    }
+
+    /// Applies the replicated component `T` as snapshots are reached.
+    pub fn with<T>(mut self, replicated: Replicated<T>) -> Self
+    where
+        T: Component + DeserializeOwned + Send + Sync,
+    {
+        let name = format!("replication_client_{}", replicated.kind);
+        self.registrations.push(Box::new(move |builder| {
+            builder.add(
+                ReplicationReceiveSystem::new(replicated),
+                &name,
+                &[CLIENT_SYSTEM_NAME],
+            );
+        }));
+        self
+    }
+
+    /// Interpolates the replicated component `T` between snapshots.
+    pub fn with_interpolated<T>(mut self, replicated: Replicated<T>) -> Self
+    where
+        T: Component + Interpolate + Clone + DeserializeOwned + Send + Sync,
+    {
+        let name = format!("replication_client_{}", replicated.kind);
+        self.registrations.push(Box::new(move |builder| {
+            builder.add(
+                ReplicationInterpolationSystem::new(replicated),
+                &name,
+                &[CLIENT_SYSTEM_NAME],
+            );
+        }));
+        self
+    }
+}
+
+impl<'a, 'b> SystemBundle<'a, 'b> for ReplicationClientBundle {
+    fn build(
+        self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'_, '_>,
+    ) -> Result<(), Error> {
+        world.insert(ReplicationClient::new(
+            self.server,
+            self.interpolation_delay,
+        ));
+        builder.add(
+            ReplicationClientSystemDesc::default().build(world),
+            CLIENT_SYSTEM_NAME,
+            &[],
+        );
+        for register in self.registrations {
+            register(builder);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,447 @@
+//! Systems sending snapshots of the replicated components to the clients.
+
+use super::{ComponentUpdate, NetworkId, NetworkIdAllocator, Replicated, Snapshot};
+use crate::simulation::{
+    events::NetworkSimulationEvent,
+    requirements::{DeliveryRequirement, UrgencyRequirement},
+    transport::TransportResource,
+};
+use amethyst_core::{
+    bundle::SystemBundle,
+    ecs::{
+        storage::{ComponentEvent, Tracked},
+        BitSet, Component, DispatcherBuilder, Entities, Entity, Join, Read, ReadStorage, ReaderId,
+        System, SystemData, World, Write, WriteStorage,
+    },
+    shrev::EventChannel,
+    timing::Time,
+    SystemDesc,
+};
+use amethyst_error::Error;
+use log::error;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    marker::PhantomData,
+    net::SocketAddr,
+    time::Duration,
+};
+
+const TICK_SYSTEM_NAME: &str = "replication_server_tick";
+const SNAPSHOT_SYSTEM_NAME: &str = "replication_server_snapshot";
+
+type Filter = Box<dyn Fn(&SocketAddr, NetworkId) -> bool + Send + Sync>;
+
+/// Resource holding the clients of the server and the snapshot being built.
+///
+/// Clients are added and removed with the `Connect` and `Disconnect` events of the transport, or
+/// manually for transports without connection events.
+pub struct ReplicationServer {
+    tick_interval: Duration,
+    last_tick: Option<Duration>,
+    tick: Option<f64>,
+    clients: HashSet<SocketAddr>,
+    new_clients: Vec<SocketAddr>,
+    filter: Filter,
+    pub(crate) updates: Vec<ComponentUpdate>,
+    pub(crate) full_updates: Vec<ComponentUpdate>,
+}
+
+impl fmt::Debug for ReplicationServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReplicationServer")
+            .field("tick_interval", &self.tick_interval)
+            .field("clients", &self.clients)
+            .field("new_clients", &self.new_clients)
+            .finish()
+    }
+}
+
+impl Default for ReplicationServer {
+    fn default() -> Self {
+        Self::new(20)
+    }
+}
+
+impl ReplicationServer {
+    /// Creates a server sending `tick_rate` snapshots per second.
+    pub fn new(tick_rate: u32) -> Self {
+        Self {
+            tick_interval: Duration::from_secs(1) / tick_rate.max(1),
+            last_tick: None,
+            tick: None,
+            clients: HashSet::new(),
+            new_clients: Vec::new(),
+            filter: Box::new(|_, _| true),
+            updates: Vec::new(),
+            full_updates: Vec::new(),
+        }
+    }
+
+    /// Sets the filter deciding which entities are replicated to which client.
+    ///
+    /// The filter is evaluated for every snapshot, an entity passing it again later is only
+    /// updated with the components changed since then.
+    pub fn set_filter<F>(&mut self, filter: F)
+    where
+        F: Fn(&SocketAddr, NetworkId) -> bool + Send + Sync + 'static,
+    {
+        self.filter = Box::new(filter);
+    }
+
+    /// Adds a client, which receives a full snapshot on the next tick.
+    pub fn add_client(&mut self, address: SocketAddr) {
+        if !self.clients.contains(&address) && !self.new_clients.contains(&address) {
+            self.new_clients.push(address);
+        }
+    }
+
+    /// Removes a client.
+    pub fn remove_client(&mut self, address: &SocketAddr) {
+        self.clients.remove(address);
+        self.new_clients.retain(|client| client != address);
+    }
+
+    /// Returns the clients receiving snapshots.
+    pub fn clients(&self) -> impl Iterator<Item = &SocketAddr> {
+        self.clients.iter().chain(self.new_clients.iter())
+    }
+
+    /// Returns the time of the snapshot sent this frame, if one is sent.
+    pub fn tick(&self) -> Option<f64> {
+        self.tick
+    }
+
+    fn has_new_clients(&self) -> bool {
+        !self.new_clients.is_empty()
+    }
+}
+
+/// Builds a `ReplicationTickSystem`.
+#[derive(Debug, Default)]
+pub struct ReplicationTickSystemDesc;
+
+impl<'a, 'b> SystemDesc<'a, 'b, ReplicationTickSystem> for ReplicationTickSystemDesc {
+    fn build(self, world: &mut World) -> ReplicationTickSystem {
+        <ReplicationTickSystem as System<'_>>::SystemData::setup(world);
+        let reader = world
+            .fetch_mut::<EventChannel<NetworkSimulationEvent>>()
+            .register_reader();
+        ReplicationTickSystem { reader }
+    }
+}
+
+/// Decides whether a snapshot is sent this frame and tracks the connected clients.
+#[derive(Debug)]
+pub struct ReplicationTickSystem {
+    reader: ReaderId<NetworkSimulationEvent>,
+}
+
+impl<'s> System<'s> for ReplicationTickSystem {
+    type SystemData = (
+        Write<'s, ReplicationServer>,
+        Read<'s, EventChannel<NetworkSimulationEvent>>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, (mut server, events, time): Self::SystemData) {
+        for event in events.read(&mut self.reader) {
+            match event {
+                NetworkSimulationEvent::Connect(address) => server.add_client(*address),
+                NetworkSimulationEvent::Disconnect(address, _) => server.remove_client(address),
+                _ => {}
+            }
+        }
+
+        let now = time.absolute_real_time();
+        server.tick = match server.last_tick {
+            Some(last) if now - last < server.tick_interval => None,
+            _ => {
+                server.last_tick = Some(now);
+                Some(time.absolute_real_time_seconds())
+            }
+        };
+    }
+}
+
+/// Builds a `ReplicationServerSystem`.
+#[derive(Debug)]
+pub struct ReplicationServerSystemDesc<T> {
+    kind: u16,
+    marker: PhantomData<T>,
+}
+
+impl<T> ReplicationServerSystemDesc<T> {
+    /// Creates the builder of the system replicating `T`.
+    pub fn new(replicated: Replicated<T>) -> Self {
+        Self {
+            kind: replicated.kind,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, 'b, T> SystemDesc<'a, 'b, ReplicationServerSystem<T>> for ReplicationServerSystemDesc<T>
+where
+    T: Component + Serialize + Send + Sync,
+    T::Storage: Tracked,
+{
+    fn build(self, world: &mut World) -> ReplicationServerSystem<T> {
+        <ReplicationServerSystem<T> as System<'_>>::SystemData::setup(world);
+        let reader = WriteStorage::<T>::fetch(&world).register_reader();
+        ReplicationServerSystem {
+            kind: self.kind,
+            reader,
+            changed: BitSet::new(),
+            removed: BitSet::new(),
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Adds the changes of the replicated component `T` to the snapshot of the server.
+///
+/// Requires `T` to be stored in a `FlaggedStorage`.
+#[derive(Debug)]
+pub struct ReplicationServerSystem<T> {
+    kind: u16,
+    reader: ReaderId<ComponentEvent>,
+    changed: BitSet,
+    removed: BitSet,
+    marker: PhantomData<T>,
+}
+
+impl<'s, T> System<'s> for ReplicationServerSystem<T>
+where
+    T: Component + Serialize + Send + Sync,
+    T::Storage: Tracked,
+{
+    type SystemData = (
+        Write<'s, ReplicationServer>,
+        ReadStorage<'s, NetworkId>,
+        ReadStorage<'s, T>,
+    );
+
+    fn run(&mut self, (mut server, ids, components): Self::SystemData) {
+        for event in components.channel().read(&mut self.reader) {
+            match event {
+                ComponentEvent::Inserted(index) | ComponentEvent::Modified(index) => {
+                    self.changed.add(*index);
+                    self.removed.remove(*index);
+                }
+                ComponentEvent::Removed(index) => {
+                    self.removed.add(*index);
+                    self.changed.remove(*index);
+                }
+            }
+        }
+        if server.tick.is_none() {
+            return;
+        }
+
+        let kind = self.kind;
+        let encode = |id: &NetworkId, component: &T| match bincode::serialize(component) {
+            Ok(data) => Some(ComponentUpdate {
+                id: *id,
+                kind,
+                data: Some(data),
+            }),
+            Err(e) => {
+                error!("Failed to serialize replicated component: {}", e);
+                None
+            }
+        };
+
+        let updates = (&ids, &components, &self.changed)
+            .join()
+            .filter_map(|(id, component, _)| encode(id, component))
+            .chain((&ids, &self.removed).join().map(|(id, _)| ComponentUpdate {
+                id: *id,
+                kind,
+                data: None,
+            }))
+            .collect::<Vec<_>>();
+        server.updates.extend(updates);
+        if server.has_new_clients() {
+            let full_updates = (&ids, &components)
+                .join()
+                .filter_map(|(id, component)| encode(id, component))
+                .collect::<Vec<_>>();
+            server.full_updates.extend(full_updates);
+        }
+        self.changed.clear();
+        self.removed.clear();
+    }
+}
+
+/// Sends the snapshot built by the `ReplicationServerSystem`s to the clients.
+#[derive(Debug, Default)]
+pub struct ReplicationSnapshotSystem {
+    known: HashMap<Entity, NetworkId>,
+}
+
+impl<'s> System<'s> for ReplicationSnapshotSystem {
+    type SystemData = (
+        Write<'s, ReplicationServer>,
+        Write<'s, TransportResource>,
+        Entities<'s>,
+        ReadStorage<'s, NetworkId>,
+    );
+
+    fn run(&mut self, (mut server, mut transport, entities, ids): Self::SystemData) {
+        let time = match server.tick {
+            Some(time) => time,
+            None => return,
+        };
+
+        let current = (&entities, &ids)
+            .join()
+            .map(|(entity, id)| (entity, *id))
+            .collect::<HashMap<_, _>>();
+        let spawned = current
+            .iter()
+            .filter(|(entity, id)| self.known.get(entity) != Some(id))
+            .map(|(_, id)| *id)
+            .collect::<Vec<_>>();
+        let despawned = self
+            .known
+            .iter()
+            .filter(|(entity, id)| current.get(entity) != Some(id))
+            .map(|(_, id)| *id)
+            .collect::<Vec<_>>();
+        let all = current.values().cloned().collect::<Vec<_>>();
+        self.known = current;
+
+        let server = &mut *server;
+        let updates = std::mem::take(&mut server.updates);
+        let full_updates = std::mem::take(&mut server.full_updates);
+        let new_clients = std::mem::take(&mut server.new_clients);
+        let filter = &server.filter;
+
+        let mut send = |client: &SocketAddr, full: bool| {
+            let (spawned, updates) = if full {
+                (&all, &full_updates)
+            } else {
+                (&spawned, &updates)
+            };
+            let snapshot = Snapshot {
+                time,
+                full,
+                spawned: spawned
+                    .iter()
+                    .filter(|id| filter(client, **id))
+                    .cloned()
+                    .collect(),
+                despawned: if full { Vec::new() } else { despawned.clone() },
+                updates: updates
+                    .iter()
+                    .filter(|update| filter(client, update.id))
+                    .cloned()
+                    .collect(),
+            };
+            if !full
+                && snapshot.spawned.is_empty()
+                && snapshot.despawned.is_empty()
+                && snapshot.updates.is_empty()
+            {
+                return;
+            }
+            match snapshot.encode() {
+                Ok(message) => transport.send_with_requirements(
+                    *client,
+                    &message,
+                    DeliveryRequirement::ReliableOrdered(None),
+                    UrgencyRequirement::OnTick,
+                ),
+                Err(e) => error!("Failed to serialize snapshot: {}", e),
+            }
+        };
+        for client in &server.clients {
+            send(client, false);
+        }
+        for client in &new_clients {
+            send(client, true);
+        }
+        server.clients.extend(new_clients);
+    }
+}
+
+type Registration = Box<dyn FnOnce(&mut World, &mut DispatcherBuilder<'_, '_>)>;
+
+/// Adds the systems replicating the registered components to the clients of the server.
+///
+/// Also adds the `NetworkIdAllocator` resource used to identify the replicated entities.
+pub struct ReplicationServerBundle {
+    tick_rate: u32,
+    registrations: Vec<(String, Registration)>,
+}
+
+impl fmt::Debug for ReplicationServerBundle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReplicationServerBundle")
+            .field("tick_rate", &self.tick_rate)
+            .finish()
+    }
+}
+
+impl ReplicationServerBundle {
+    /// Creates a bundle sending `tick_rate` snapshots per second.
+    pub fn new(tick_rate: u32) -> Self {
+        Self {
+            tick_rate,
+            registrations: Vec::new(),
+        }
+    }
+
+    /// Replicates the component `T`, which needs to be stored in a `FlaggedStorage`.
+    pub fn with<T>(mut self, replicated: Replicated<T>) -> Self
+    where
+        T: Component + Serialize + Send + Sync,
+        T::Storage: Tracked,
+    {
+        let name = format!("replication_server_{}", replicated.kind);
+        let system_name = name.clone();
+        self.registrations.push((
+            name,
+            Box::new(move |world, builder| {
+                builder.add(
+                    ReplicationServerSystemDesc::new(replicated).build(world),
+                    &system_name,
+                    &[TICK_SYSTEM_NAME],
+                );
+            }),
+        ));
+        self
+    }
+}
+
+impl<'a, 'b> SystemBundle<'a, 'b> for ReplicationServerBundle {
+    fn build(
+        self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'_, '_>,
+    ) -> Result<(), Error> {
+        world.insert(ReplicationServer::new(self.tick_rate));
+        world
+            .entry::<NetworkIdAllocator>()
+            .or_insert_with(Default::default);
+        builder.add(
+            ReplicationTickSystemDesc::default().build(world),
+            TICK_SYSTEM_NAME,
+            &[],
+        );
+        let mut names = Vec::with_capacity(self.registrations.len());
+        for (name, register) in self.registrations {
+            register(world, builder);
+            names.push(name);
+        }
+        let names = names.iter().map(String::as_str).collect::<Vec<_>>();
+        builder.add(
+            ReplicationSnapshotSystem::default(),
+            SNAPSHOT_SYSTEM_NAME,
+            &names,
+        );
+        Ok(())
+    }
+}
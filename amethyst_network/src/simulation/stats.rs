@@ -0,0 +1,223 @@
+//! Bandwidth and packet statistics of the network transports.
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+/// Length of the window the rates of `NetworkStats` are measured over.
+pub const STATS_WINDOW: Duration = Duration::from_secs(1);
+
+/// Traffic of a connection, or of all connections, averaged over the last `STATS_WINDOW`.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct TrafficStats {
+    /// Bytes sent per second, including packet headers.
+    pub bytes_sent: f32,
+    /// Bytes received per second, including packet headers.
+    pub bytes_received: f32,
+    /// Packets sent per second.
+    pub packets_sent: f32,
+    /// Packets received per second.
+    pub packets_received: f32,
+    /// Packets resent per second because they were not acknowledged in time.
+    pub packets_resent: f32,
+    /// Packets of the remote host estimated lost per second.
+    pub packets_lost: f32,
+    /// Packets sent but not acknowledged yet, at the end of the window.
+    pub send_queue: usize,
+}
+
+impl TrafficStats {
+    /// Returns the fraction of packets of the remote host that were lost, between 0 and 1.
+    pub fn packet_loss(&self) -> f32 {
+        let expected = self.packets_received + self.packets_lost;
+        if expected > 0. {
+            self.packets_lost / expected
+        } else {
+            0.
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+struct Counters {
+    bytes_sent: u64,
+    bytes_received: u64,
+    packets_sent: u64,
+    packets_received: u64,
+    packets_resent: u64,
+    packets_lost: u64,
+    send_queue: usize,
+}
+
+impl Counters {
+    fn add(&mut self, other: &Counters) {
+        self.bytes_sent += other.bytes_sent;
+        self.bytes_received += other.bytes_received;
+        self.packets_sent += other.packets_sent;
+        self.packets_received += other.packets_received;
+        self.packets_resent += other.packets_resent;
+        self.packets_lost += other.packets_lost;
+        self.send_queue += other.send_queue;
+    }
+
+    fn rates(&self, elapsed: Duration) -> TrafficStats {
+        let seconds = elapsed.as_secs_f32();
+        TrafficStats {
+            bytes_sent: self.bytes_sent as f32 / seconds,
+            bytes_received: self.bytes_received as f32 / seconds,
+            packets_sent: self.packets_sent as f32 / seconds,
+            packets_received: self.packets_received as f32 / seconds,
+            packets_resent: self.packets_resent as f32 / seconds,
+            packets_lost: self.packets_lost as f32 / seconds,
+            send_queue: self.send_queue,
+        }
+    }
+}
+
+/// Resource with the traffic of each connection and of all connections together.
+///
+/// The transport systems accumulate counters every frame, which are turned into the rates of
+/// the last `STATS_WINDOW` once it has elapsed. Only the UDP transport records statistics.
+#[derive(Debug, Default)]
+pub struct NetworkStats {
+    window_start: Option<Instant>,
+    counters: HashMap<SocketAddr, Counters>,
+    connections: HashMap<SocketAddr, TrafficStats>,
+    total: TrafficStats,
+}
+
+impl NetworkStats {
+    /// Records a packet of `bytes` sent to `address`.
+    pub fn record_sent(&mut self, address: SocketAddr, bytes: usize) {
+        let counters = self.counters.entry(address).or_default();
+        counters.bytes_sent += bytes as u64;
+        counters.packets_sent += 1;
+    }
+
+    /// Records a packet of `bytes` received from `address`.
+    pub fn record_received(&mut self, address: SocketAddr, bytes: usize) {
+        let counters = self.counters.entry(address).or_default();
+        counters.bytes_received += bytes as u64;
+        counters.packets_received += 1;
+    }
+
+    /// Records packets resent to `address`.
+    pub fn record_resent(&mut self, address: SocketAddr, packets: u64) {
+        self.counters.entry(address).or_default().packets_resent += packets;
+    }
+
+    /// Records packets of `address` detected as lost.
+    pub fn record_lost(&mut self, address: SocketAddr, packets: u64) {
+        self.counters.entry(address).or_default().packets_lost += packets;
+    }
+
+    /// Sets the number of packets sent to `address` waiting for an acknowledgement.
+    pub fn set_send_queue(&mut self, address: SocketAddr, packets: usize) {
+        self.counters.entry(address).or_default().send_queue = packets;
+    }
+
+    /// Stops tracking a closed connection.
+    pub fn remove_connection(&mut self, address: &SocketAddr) {
+        self.counters.remove(address);
+        self.connections.remove(address);
+    }
+
+    /// Turns the counters into rates once `STATS_WINDOW` elapsed since the last refresh.
+    ///
+    /// Returns true if the rates were refreshed.
+    pub fn refresh(&mut self, now: Instant) -> bool {
+        let start = *self.window_start.get_or_insert(now);
+        let elapsed = now.duration_since(start);
+        if elapsed < STATS_WINDOW {
+            return false;
+        }
+        let mut total = Counters::default();
+        self.connections.clear();
+        for (address, counters) in &mut self.counters {
+            total.add(counters);
+            self.connections.insert(*address, counters.rates(elapsed));
+            // the queue depth is a level, not a count, and stays until it is set again
+            *counters = Counters {
+                send_queue: counters.send_queue,
+                ..Counters::default()
+            };
+        }
+        self.total = total.rates(elapsed);
+        self.window_start = Some(now);
+        true
+    }
+
+    /// Clears the counters and rates, starting a new window.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Returns the traffic of the connection to `address` in the last window.
+    pub fn connection(&self, address: &SocketAddr) -> Option<&TrafficStats> {
+        self.connections.get(address)
+    }
+
+    /// Returns the traffic of every connection in the last window.
+    pub fn connections(&self) -> impl Iterator<Item = (&SocketAddr, &TrafficStats)> {
+        self.connections.iter()
+    }
+
+    /// Returns the traffic of all connections together in the last window.
+    pub fn total(&self) -> &TrafficStats {
+        &self.total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address() -> SocketAddr {
+        "127.0.0.1:3000".parse().unwrap()
+    }
+
+    #[test]
+    fn rates_are_refreshed_once_per_window() {
+        let start = Instant::now();
+        let mut stats = NetworkStats::default();
+        assert!(!stats.refresh(start));
+
+        for _ in 0..10 {
+            stats.record_sent(address(), 100);
+        }
+        stats.record_received(address(), 50);
+        stats.record_lost(address(), 1);
+        stats.set_send_queue(address(), 3);
+        assert!(!stats.refresh(start + Duration::from_millis(500)));
+        assert_eq!(stats.connection(&address()), None);
+
+        assert!(stats.refresh(start + Duration::from_secs(2)));
+        let traffic = *stats.connection(&address()).unwrap();
+        assert_eq!(traffic, *stats.total());
+        assert!((traffic.bytes_sent - 500.).abs() < 1e-3);
+        assert!((traffic.packets_sent - 5.).abs() < 1e-3);
+        assert!((traffic.bytes_received - 25.).abs() < 1e-3);
+        assert!((traffic.packet_loss() - 0.5).abs() < 1e-3);
+        assert_eq!(traffic.send_queue, 3);
+
+        // nothing happened in the next window
+        assert!(stats.refresh(start + Duration::from_secs(3)));
+        let traffic = stats.connection(&address()).unwrap();
+        assert!(traffic.bytes_sent.abs() < 1e-3);
+        assert_eq!(traffic.send_queue, 3);
+    }
+
+    #[test]
+    fn reset_clears_everything() {
+        let start = Instant::now();
+        let mut stats = NetworkStats::default();
+        stats.refresh(start);
+        stats.record_sent(address(), 100);
+        stats.refresh(start + Duration::from_secs(1));
+        stats.reset();
+        assert_eq!(stats.connections().count(), 0);
+        assert_eq!(*stats.total(), TrafficStats::default());
+    }
+}
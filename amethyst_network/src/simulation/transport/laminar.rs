@@ -1,7 +1,7 @@
 //! Network systems implementation backed by the Laminar network protocol.
 
 use crate::simulation::{
-    events::NetworkSimulationEvent,
+    events::{DisconnectReason, NetworkSimulationEvent},
     requirements::DeliveryRequirement,
     timing::{NetworkSimulationTime, NetworkSimulationTimeSystem},
     transport::{
@@ -158,7 +158,9 @@ impl<'s> System<'s> for LaminarNetworkRecvSystem {
                         Bytes::copy_from_slice(packet.payload()),
                     ),
                     SocketEvent::Connect(addr) => NetworkSimulationEvent::Connect(addr),
-                    SocketEvent::Timeout(addr) => NetworkSimulationEvent::Disconnect(addr),
+                    SocketEvent::Timeout(addr) => {
+                        NetworkSimulationEvent::Disconnect(addr, DisconnectReason::Timeout)
+                    }
                 };
                 event_channel.single_write(event);
             }
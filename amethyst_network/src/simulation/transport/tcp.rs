@@ -1,7 +1,7 @@
 //! Network systems implementation backed by the TCP network protocol.
 
 use crate::simulation::{
-    events::NetworkSimulationEvent,
+    events::{DisconnectReason, NetworkSimulationEvent},
     message::Message,
     requirements::DeliveryRequirement,
     timing::{NetworkSimulationTime, NetworkSimulationTimeSystem},
@@ -133,7 +133,10 @@ impl<'s> System<'s> for TcpStreamManagementSystem {
         // Remove inactive connections
         net.streams.retain(|addr, (active, _)| {
             if !*active {
-                event_channel.single_write(NetworkSimulationEvent::Disconnect(*addr));
+                event_channel.single_write(NetworkSimulationEvent::Disconnect(
+                    *addr,
+                    DisconnectReason::Graceful,
+                ));
             }
             *active
         });
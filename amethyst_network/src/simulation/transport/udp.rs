@@ -1,8 +1,31 @@
 //! Network systems implementation backed by the UDP network protocol.
+//!
+//! Packets carry a small header used to acknowledge and resend messages, so every
+//! `DeliveryRequirement` is supported. Both hosts need to use this transport.
+//!
+//! Messages larger than `FRAGMENT_SIZE` are split into fragments and reassembled by the receiver,
+//! messages larger than the maximum message size fail to send with a `SendError`.
+//!
+//! Traffic of every connection is recorded in the `NetworkStats` resource.
+//!
+//! Bad network conditions can be simulated with a `NetworkSimulation`, in debug builds or with
+//! the `simulate_network` feature.
+//!
+//! Connections are created when a message is sent to or received from a new address. They
+//! exchange heartbeats while idle and are removed with a `Disconnect` event when the remote host
+//! disconnects or times out.
+
+mod conditions;
+mod reliability;
+
+pub use conditions::{LinkConditions, NetworkSimulation, SIMULATION_AVAILABLE};
+pub use reliability::{
+    ConnectionStats, DeliveryMode, HeartbeatConfig, DEFAULT_MAX_MESSAGE_SIZE, FRAGMENT_SIZE,
+};
 
 use crate::simulation::{
     events::NetworkSimulationEvent,
-    requirements::DeliveryRequirement,
+    stats::NetworkStats,
     timing::{NetworkSimulationTime, NetworkSimulationTimeSystem},
     transport::{
         TransportResource, NETWORK_RECV_SYSTEM_NAME, NETWORK_SEND_SYSTEM_NAME,
@@ -15,13 +38,22 @@ use amethyst_core::{
     shrev::EventChannel,
 };
 use amethyst_error::Error;
-use bytes::Bytes;
-use std::{io, net::UdpSocket};
+use conditions::Conditioner;
+use reliability::Connection;
+use std::{
+    collections::HashMap,
+    io,
+    net::{SocketAddr, UdpSocket},
+    time::Instant,
+};
 
 /// Use this network bundle to add the UDP transport layer to your game.
 pub struct UdpNetworkBundle {
     socket: Option<UdpSocket>,
     recv_buffer_size_bytes: usize,
+    heartbeat: HeartbeatConfig,
+    max_message_size: usize,
+    simulation: NetworkSimulation,
 }
 
 impl UdpNetworkBundle {
@@ -29,8 +61,29 @@ impl UdpNetworkBundle {
         Self {
             socket,
             recv_buffer_size_bytes,
+            heartbeat: HeartbeatConfig::default(),
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            simulation: NetworkSimulation::default(),
         }
     }
+
+    /// Sets the heartbeat interval and timeout of connections.
+    pub fn with_heartbeat(mut self, heartbeat: HeartbeatConfig) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    /// Sets the maximum size of messages sent and received, in bytes.
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    /// Sets the simulated network conditions.
+    pub fn with_network_simulation(mut self, simulation: NetworkSimulation) -> Self {
+        self.simulation = simulation;
+        self
+    }
 }
 
 impl<'a, 'b> SystemBundle<'a, 'b> for UdpNetworkBundle {
@@ -55,7 +108,11 @@ impl<'a, 'b> SystemBundle<'a, 'b> for UdpNetworkBundle {
             &[NETWORK_SIM_TIME_SYSTEM_NAME],
         );
 
-        world.insert(UdpSocketResource::new(self.socket));
+        let mut socket = UdpSocketResource::new(self.socket);
+        socket.set_heartbeat_config(self.heartbeat);
+        socket.set_max_message_size(self.max_message_size);
+        socket.set_network_simulation(self.simulation);
+        world.insert(socket);
         Ok(())
     }
 }
@@ -68,24 +125,64 @@ impl<'s> System<'s> for UdpNetworkSendSystem {
         Write<'s, UdpSocketResource>,
         Read<'s, NetworkSimulationTime>,
         Write<'s, EventChannel<NetworkSimulationEvent>>,
+        Write<'s, NetworkStats>,
     );
 
-    fn run(&mut self, (mut transport, mut socket, sim_time, mut channel): Self::SystemData) {
-        if let Some(socket) = socket.get_mut() {
+    fn run(
+        &mut self,
+        (mut transport, mut socket, sim_time, mut channel, mut stats): Self::SystemData,
+    ) {
+        let UdpSocketResource {
+            socket,
+            connections,
+            heartbeat,
+            max_message_size,
+            conditioner,
+        } = &mut *socket;
+        if let Some(socket) = socket {
+            let now = Instant::now();
             let messages = transport.drain_messages_to_send(|_| sim_time.should_send_message_now());
             for message in messages {
-                match message.delivery {
-                    DeliveryRequirement::Unreliable | DeliveryRequirement::Default => {
-                        if let Err(e) = socket.send_to(&message.payload, message.destination) {
-                            channel.single_write(NetworkSimulationEvent::SendError(e, message));
-                        }
+                let sent = connections
+                    .entry(message.destination)
+                    .or_insert_with(|| Connection::new(*heartbeat, *max_message_size, now))
+                    .send(&message.payload, message.delivery.into(), now)
+                    .and_then(|packets| {
+                        packets.iter().try_for_each(|packet| {
+                            conditioner.send(socket, &mut stats, message.destination, packet, now)
+                        })
+                    });
+                if let Err(e) = sent {
+                    channel.single_write(NetworkSimulationEvent::SendError(e, message));
+                }
+            }
+
+            for (address, connection) in connections.iter_mut() {
+                let resent = connection.stats().packets_resent;
+                for packet in connection.update(now) {
+                    if let Err(e) = conditioner.send(socket, &mut stats, *address, &packet, now) {
+                        channel.single_write(NetworkSimulationEvent::ConnectionError(
+                            e,
+                            Some(*address),
+                        ));
                     }
-                    delivery => panic!(
-                        "{:?} is unsupported. UDP only supports Unreliable by design.",
-                        delivery
-                    ),
                 }
+                stats.record_resent(*address, connection.stats().packets_resent - resent);
+                stats.set_send_queue(*address, connection.pending_packets());
+            }
+
+            connections.retain(|address, connection| match connection.disconnected() {
+                Some(reason) => {
+                    stats.remove_connection(address);
+                    channel.single_write(NetworkSimulationEvent::Disconnect(*address, reason));
+                    false
+                }
+                None => true,
+            });
+            for (address, e) in conditioner.flush(socket, &mut stats, now) {
+                channel.single_write(NetworkSimulationEvent::ConnectionError(e, Some(address)));
             }
+            stats.refresh(now);
         }
     }
 }
@@ -107,47 +204,143 @@ impl<'s> System<'s> for UdpNetworkRecvSystem {
     type SystemData = (
         Write<'s, UdpSocketResource>,
         Write<'s, EventChannel<NetworkSimulationEvent>>,
+        Write<'s, NetworkStats>,
     );
 
-    fn run(&mut self, (mut socket, mut event_channel): Self::SystemData) {
-        if let Some(socket) = socket.get_mut() {
+    fn run(&mut self, (mut socket, mut event_channel, mut stats): Self::SystemData) {
+        let UdpSocketResource {
+            socket,
+            connections,
+            heartbeat,
+            max_message_size,
+            conditioner,
+        } = &mut *socket;
+        if let Some(socket) = socket {
+            let now = Instant::now();
+            let mut receiver = PacketReceiver {
+                connections,
+                heartbeat: *heartbeat,
+                max_message_size: *max_message_size,
+                event_channel: &mut event_channel,
+                stats: &mut stats,
+                now,
+            };
             loop {
                 match socket.recv_from(&mut self.recv_buffer) {
                     Ok((recv_len, address)) => {
-                        let event = NetworkSimulationEvent::Message(
-                            address,
-                            Bytes::copy_from_slice(&self.recv_buffer[..recv_len]),
-                        );
-                        // TODO: Handle other types of events.
-                        event_channel.single_write(event);
+                        let packet = &self.recv_buffer[..recv_len];
+                        if !conditioner.delay_incoming(address, packet, now) {
+                            receiver.receive(address, packet);
+                        }
                     }
                     Err(e) => {
                         if e.kind() != io::ErrorKind::WouldBlock {
-                            event_channel.single_write(NetworkSimulationEvent::RecvError(e));
+                            receiver
+                                .event_channel
+                                .single_write(NetworkSimulationEvent::RecvError(e));
                         }
                         break;
                     }
                 }
             }
+            while let Some((address, packet)) = conditioner.release_incoming(now) {
+                receiver.receive(address, &packet);
+            }
+        }
+    }
+}
+
+/// Hands the packets read from the socket to their connection.
+struct PacketReceiver<'a> {
+    connections: &'a mut HashMap<SocketAddr, Connection>,
+    heartbeat: HeartbeatConfig,
+    max_message_size: usize,
+    event_channel: &'a mut EventChannel<NetworkSimulationEvent>,
+    stats: &'a mut NetworkStats,
+    now: Instant,
+}
+
+impl PacketReceiver<'_> {
+    fn receive(&mut self, address: SocketAddr, packet: &[u8]) {
+        let Self {
+            connections,
+            heartbeat,
+            max_message_size,
+            event_channel,
+            stats,
+            now,
+        } = self;
+        let connection = connections.entry(address).or_insert_with(|| {
+            event_channel.single_write(NetworkSimulationEvent::Connect(address));
+            Connection::new(*heartbeat, *max_message_size, *now)
+        });
+        let lost = connection.stats().packets_lost;
+        let received = connection.receive(packet, *now);
+        stats.record_received(address, packet.len());
+        stats.record_lost(address, connection.stats().packets_lost - lost);
+        if let Some(reason) = connection.disconnected() {
+            connections.remove(&address);
+            stats.remove_connection(&address);
+            event_channel.single_write(NetworkSimulationEvent::Disconnect(address, reason));
+        }
+        match received {
+            Ok(payloads) => {
+                for payload in payloads {
+                    // TODO: Handle other types of events.
+                    event_channel.single_write(NetworkSimulationEvent::Message(address, payload));
+                }
+            }
+            Err(e) => event_channel.single_write(NetworkSimulationEvent::RecvError(e)),
         }
     }
 }
 
-/// Resource to own the UDP socket.
+/// Resource to own the UDP socket and the reliability state of its connections.
 pub struct UdpSocketResource {
     socket: Option<UdpSocket>,
+    connections: HashMap<SocketAddr, Connection>,
+    heartbeat: HeartbeatConfig,
+    max_message_size: usize,
+    conditioner: Conditioner,
 }
 
 impl Default for UdpSocketResource {
     fn default() -> Self {
-        Self { socket: None }
+        Self::new(None)
     }
 }
 
 impl UdpSocketResource {
     /// Create a new instance of the `UdpSocketResource`
     pub fn new(socket: Option<UdpSocket>) -> Self {
-        Self { socket }
+        Self {
+            socket,
+            connections: HashMap::new(),
+            heartbeat: HeartbeatConfig::default(),
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            conditioner: Conditioner::default(),
+        }
+    }
+
+    /// Sets the heartbeat configuration of new connections.
+    pub fn set_heartbeat_config(&mut self, heartbeat: HeartbeatConfig) {
+        self.heartbeat = heartbeat;
+    }
+
+    /// Sets the maximum message size of new connections, in bytes.
+    pub fn set_max_message_size(&mut self, max_message_size: usize) {
+        self.max_message_size = max_message_size;
+    }
+
+    /// Returns the simulated network conditions.
+    pub fn network_simulation(&self) -> &NetworkSimulation {
+        self.conditioner.simulation()
+    }
+
+    /// Sets the simulated network conditions, taking effect for the next packets. Packets already
+    /// delayed keep their delay, unless the simulation is disabled which releases them at once.
+    pub fn set_network_simulation(&mut self, simulation: NetworkSimulation) {
+        self.conditioner.set_simulation(simulation);
     }
 
     /// Returns an immutable reference to the socket if there is one configured.
@@ -165,8 +358,27 @@ impl UdpSocketResource {
         self.socket = Some(socket);
     }
 
-    /// Drops the socket from the `UdpSocketResource`.
+    /// Drops the socket from the `UdpSocketResource`, along with the state of its connections.
     pub fn drop_socket(&mut self) {
         self.socket = None;
+        self.connections.clear();
+        self.conditioner.clear();
+    }
+
+    /// Returns the statistics of the connection to the given address, if a message was sent to
+    /// or received from it.
+    pub fn connection_stats(&self, address: &SocketAddr) -> Option<&ConnectionStats> {
+        self.connections.get(address).map(Connection::stats)
+    }
+
+    /// Closes the connection to the given address, telling the remote host with a disconnect
+    /// packet. No `Disconnect` event is emitted on this side.
+    pub fn disconnect(&mut self, address: &SocketAddr) -> io::Result<()> {
+        if let Some(mut connection) = self.connections.remove(address) {
+            if let Some(socket) = &self.socket {
+                socket.send_to(&connection.disconnect(Instant::now()), *address)?;
+            }
+        }
+        Ok(())
     }
 }
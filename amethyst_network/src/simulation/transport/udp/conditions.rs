@@ -0,0 +1,357 @@
+//! Artificial latency, jitter, loss, duplication and reordering of UDP packets, used to reproduce
+//! bad network conditions locally.
+//!
+//! Conditions are applied to the packets sent to and received from the socket, before the
+//! reliability layer sees them. They are only available in debug builds, or in release builds with
+//! the `simulate_network` feature, so a release build can not ship with them enabled by accident.
+
+use crate::simulation::stats::NetworkStats;
+use log::warn;
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+    io,
+    net::{SocketAddr, UdpSocket},
+    time::{Duration, Instant},
+};
+
+/// Whether network conditions can be simulated in this build.
+pub const SIMULATION_AVAILABLE: bool = cfg!(any(debug_assertions, feature = "simulate_network"));
+
+/// Conditions applied to the packets going in one direction.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LinkConditions {
+    /// Delay added to every packet.
+    pub latency: Duration,
+    /// Maximum random delay added on top of the latency.
+    pub jitter: Duration,
+    /// Fraction of packets dropped, between 0 and 1.
+    pub loss: f32,
+    /// Fraction of packets delivered twice, between 0 and 1.
+    pub duplication: f32,
+    /// Fraction of packets held back by `reorder_delay`, so later packets overtake them.
+    pub reordering: f32,
+    /// Delay added to reordered packets.
+    pub reorder_delay: Duration,
+}
+
+impl Default for LinkConditions {
+    fn default() -> Self {
+        Self {
+            latency: Duration::from_millis(0),
+            jitter: Duration::from_millis(0),
+            loss: 0.,
+            duplication: 0.,
+            reordering: 0.,
+            reorder_delay: Duration::from_millis(50),
+        }
+    }
+}
+
+/// Network conditions simulated by the UDP transport, disabled by default.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct NetworkSimulation {
+    /// Whether the conditions are applied. Ignored with a warning if `SIMULATION_AVAILABLE` is
+    /// false.
+    pub enabled: bool,
+    /// Conditions of the packets sent.
+    pub outgoing: LinkConditions,
+    /// Conditions of the packets received.
+    pub incoming: LinkConditions,
+}
+
+impl NetworkSimulation {
+    /// Creates an enabled simulation applying the same conditions in both directions.
+    pub fn symmetric(conditions: LinkConditions) -> Self {
+        Self {
+            enabled: true,
+            outgoing: conditions,
+            incoming: conditions,
+        }
+    }
+
+    /// Returns true if the conditions are applied.
+    pub fn is_active(&self) -> bool {
+        SIMULATION_AVAILABLE && self.enabled
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Delayed {
+    release: Instant,
+    order: u64,
+    address: SocketAddr,
+    packet: Vec<u8>,
+}
+
+impl Ord for Delayed {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.release, self.order).cmp(&(other.release, other.order))
+    }
+}
+
+impl PartialOrd for Delayed {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Packets of one direction waiting for their simulated delay.
+#[derive(Debug)]
+pub(crate) struct Link {
+    queue: BinaryHeap<Reverse<Delayed>>,
+    next_order: u64,
+    rng: u32,
+}
+
+impl Link {
+    pub(crate) fn new(seed: u32) -> Self {
+        Self {
+            queue: BinaryHeap::new(),
+            next_order: 0,
+            // xorshift gets stuck at zero
+            rng: seed.max(1),
+        }
+    }
+
+    /// Applies the conditions to a packet, queueing it for release after its delay.
+    pub(crate) fn push(
+        &mut self,
+        conditions: &LinkConditions,
+        address: SocketAddr,
+        packet: &[u8],
+        now: Instant,
+    ) {
+        if self.random() < conditions.loss {
+            return;
+        }
+        let copies = if self.random() < conditions.duplication {
+            2
+        } else {
+            1
+        };
+        for _ in 0..copies {
+            let mut delay = conditions.latency + conditions.jitter.mul_f32(self.random());
+            if self.random() < conditions.reordering {
+                delay += conditions.reorder_delay;
+            }
+            self.queue.push(Reverse(Delayed {
+                release: now + delay,
+                order: self.next_order,
+                address,
+                packet: packet.to_vec(),
+            }));
+            self.next_order += 1;
+        }
+    }
+
+    /// Returns the next packet whose delay elapsed, or any queued packet if `all` is set.
+    pub(crate) fn pop(&mut self, now: Instant, all: bool) -> Option<(SocketAddr, Vec<u8>)> {
+        match self.queue.peek() {
+            Some(Reverse(delayed)) if all || delayed.release <= now => {
+                let Reverse(delayed) = self.queue.pop()?;
+                Some((delayed.address, delayed.packet))
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.queue.clear();
+    }
+
+    /// Returns a pseudo random number in `[0, 1)`.
+    fn random(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng >> 8) as f32 / (1 << 24) as f32
+    }
+}
+
+/// Applies the simulated conditions between the UDP socket and the connections.
+#[derive(Debug)]
+pub(crate) struct Conditioner {
+    simulation: NetworkSimulation,
+    outgoing: Link,
+    incoming: Link,
+}
+
+impl Default for Conditioner {
+    fn default() -> Self {
+        Self {
+            simulation: NetworkSimulation::default(),
+            outgoing: Link::new(0x2545_f491),
+            incoming: Link::new(0x9e37_79b9),
+        }
+    }
+}
+
+impl Conditioner {
+    pub(crate) fn simulation(&self) -> &NetworkSimulation {
+        &self.simulation
+    }
+
+    pub(crate) fn set_simulation(&mut self, simulation: NetworkSimulation) {
+        if simulation.enabled && !SIMULATION_AVAILABLE {
+            warn!("Network simulation is not available in release builds without the `simulate_network` feature.");
+        }
+        self.simulation = simulation;
+    }
+
+    /// Sends a packet, or queues it if the conditions are applied.
+    pub(crate) fn send(
+        &mut self,
+        socket: &UdpSocket,
+        stats: &mut NetworkStats,
+        address: SocketAddr,
+        packet: &[u8],
+        now: Instant,
+    ) -> io::Result<()> {
+        if self.simulation.is_active() {
+            self.outgoing
+                .push(&self.simulation.outgoing, address, packet, now);
+        } else {
+            let sent = socket.send_to(packet, address)?;
+            stats.record_sent(address, sent);
+        }
+        Ok(())
+    }
+
+    /// Sends the queued packets whose delay elapsed, all of them if the simulation was disabled.
+    pub(crate) fn flush(
+        &mut self,
+        socket: &UdpSocket,
+        stats: &mut NetworkStats,
+        now: Instant,
+    ) -> Vec<(SocketAddr, io::Error)> {
+        let all = !self.simulation.is_active();
+        let mut errors = Vec::new();
+        while let Some((address, packet)) = self.outgoing.pop(now, all) {
+            match socket.send_to(&packet, address) {
+                Ok(sent) => stats.record_sent(address, sent),
+                Err(e) => errors.push((address, e)),
+            }
+        }
+        errors
+    }
+
+    /// Queues a received packet, returns false if the conditions are not applied and the packet
+    /// has to be processed right away.
+    pub(crate) fn delay_incoming(
+        &mut self,
+        address: SocketAddr,
+        packet: &[u8],
+        now: Instant,
+    ) -> bool {
+        if self.simulation.is_active() {
+            self.incoming
+                .push(&self.simulation.incoming, address, packet, now);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns the next received packet whose delay elapsed.
+    pub(crate) fn release_incoming(&mut self, now: Instant) -> Option<(SocketAddr, Vec<u8>)> {
+        let all = !self.simulation.is_active();
+        self.incoming.pop(now, all)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.outgoing.clear();
+        self.incoming.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address() -> SocketAddr {
+        "127.0.0.1:3000".parse().unwrap()
+    }
+
+    fn released(link: &mut Link, now: Instant) -> Vec<u8> {
+        let mut packets = Vec::new();
+        while let Some((_, packet)) = link.pop(now, false) {
+            packets.push(packet[0]);
+        }
+        packets
+    }
+
+    #[test]
+    fn latency_delays_packets() {
+        let now = Instant::now();
+        let mut link = Link::new(1);
+        let conditions = LinkConditions {
+            latency: Duration::from_millis(100),
+            ..LinkConditions::default()
+        };
+        link.push(&conditions, address(), &[0], now);
+        link.push(&conditions, address(), &[1], now);
+        assert!(released(&mut link, now + Duration::from_millis(99)).is_empty());
+        assert_eq!(
+            released(&mut link, now + Duration::from_millis(100)),
+            vec![0, 1]
+        );
+    }
+
+    #[test]
+    fn loss_drops_about_the_given_fraction() {
+        let now = Instant::now();
+        let mut link = Link::new(1);
+        let conditions = LinkConditions {
+            loss: 0.2,
+            ..LinkConditions::default()
+        };
+        for _ in 0..1000 {
+            link.push(&conditions, address(), &[0], now);
+        }
+        let delivered = released(&mut link, now).len();
+        assert!(delivered > 700 && delivered < 900, "{}", delivered);
+    }
+
+    #[test]
+    fn duplication_and_reordering() {
+        let now = Instant::now();
+        let mut link = Link::new(1);
+        let conditions = LinkConditions {
+            duplication: 1.,
+            ..LinkConditions::default()
+        };
+        link.push(&conditions, address(), &[0], now);
+        assert_eq!(released(&mut link, now), vec![0, 0]);
+
+        let conditions = LinkConditions {
+            reordering: 1.,
+            ..LinkConditions::default()
+        };
+        link.push(&conditions, address(), &[1], now);
+        link.push(&LinkConditions::default(), address(), &[2], now);
+        assert_eq!(
+            released(&mut link, now + conditions.reorder_delay),
+            vec![2, 1]
+        );
+    }
+
+    #[test]
+    fn disabling_releases_queued_packets() {
+        let now = Instant::now();
+        let mut conditioner = Conditioner::default();
+        conditioner.set_simulation(NetworkSimulation::symmetric(LinkConditions {
+            latency: Duration::from_secs(1),
+            ..LinkConditions::default()
+        }));
+        assert!(conditioner.delay_incoming(address(), &[0], now));
+        assert!(conditioner.release_incoming(now).is_none());
+
+        conditioner.set_simulation(NetworkSimulation::default());
+        assert_eq!(
+            conditioner.release_incoming(now),
+            Some((address(), vec![0]))
+        );
+        assert!(!conditioner.delay_incoming(address(), &[1], now));
+    }
+}
@@ -0,0 +1,1168 @@
+//! Acknowledgement and retransmission layer used by the UDP transport to honor the
+//! `DeliveryRequirement` of each message.
+//!
+//! Every packet starts with a header containing the delivery mode, the sequence number of the
+//! packet, the newest sequence number received from the remote host together with a bitfield
+//! acknowledging the 32 packets before it, and the id of the message within its delivery mode.
+//! Reliable packets that are not acknowledged in time are resent with a new sequence number and
+//! an exponentially growing timeout.
+//!
+//! Idle connections send heartbeats, answered by the remote host to measure the round-trip time.
+//! A connection that has not received anything for several heartbeat intervals times out.
+//!
+//! Messages larger than `FRAGMENT_SIZE` are split into fragments sharing the id of the message.
+//! Each fragment is sent, acknowledged and resent like a message of the same delivery mode, so
+//! reliable messages are reassembled once all fragments were resent as needed.
+
+use crate::simulation::{events::DisconnectReason, requirements::DeliveryRequirement};
+use bytes::Bytes;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io,
+    time::{Duration, Instant},
+};
+
+/// Size of the packet header in bytes, fragments have `FRAGMENT_HEADER_SIZE` more bytes.
+pub const HEADER_SIZE: usize = 11;
+
+/// Size of the index and count of a fragment, following the packet header.
+pub const FRAGMENT_HEADER_SIZE: usize = 4;
+
+/// Maximum payload size of a packet, larger messages are fragmented. Keeps packets below the
+/// usual MTU of the internet.
+pub const FRAGMENT_SIZE: usize = 1200;
+
+/// Default maximum size of a message, larger messages fail to send.
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+
+/// Maximum number of reliable packets waiting for an acknowledgement per connection.
+pub const MAX_PENDING_PACKETS: usize = 256;
+
+/// Retransmission timeout used until a round-trip time has been measured.
+const INITIAL_TIMEOUT: Duration = Duration::from_millis(200);
+/// Lower bound of the retransmission timeout, so jitter on fast links does not cause resends.
+const MIN_TIMEOUT: Duration = Duration::from_millis(20);
+/// Upper bound of the exponential backoff.
+const MAX_TIMEOUT: Duration = Duration::from_secs(2);
+/// Number of received reliable message ids remembered to discard duplicates.
+const RECEIVED_HISTORY: usize = 1024;
+/// Time after the last received fragment of an incomplete message after which it is discarded.
+/// Longer than `MAX_TIMEOUT`, so missing fragments of reliable messages are resent before.
+const REASSEMBLY_TIMEOUT: Duration = Duration::from_secs(6);
+/// Header mode of packets only carrying acknowledgements.
+const ACK_ONLY: u8 = 5;
+/// Header mode of packets closing the connection.
+const DISCONNECT: u8 = 6;
+/// Header mode of heartbeats, the message id identifies the heartbeat.
+const PING: u8 = 7;
+/// Header mode of heartbeat answers, echoing the message id of the heartbeat.
+const PONG: u8 = 8;
+/// Flag of the mode byte set when the acknowledgement fields are valid, which they are not
+/// until a packet has been received from the remote host.
+const HAS_ACK: u8 = 0x80;
+/// Flag of the mode byte set on fragments.
+const IS_FRAGMENT: u8 = 0x40;
+
+/// Delivery mode written into the packet header, so both hosts handle a packet the same way.
+///
+/// UDP has a single stream per mode, stream ids of the `DeliveryRequirement` are ignored.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DeliveryMode {
+    /// Packets may be lost, duplicated or reordered.
+    Unreliable = 0,
+    /// Packets may be lost, packets older than the newest received one are dropped.
+    UnreliableSequenced = 1,
+    /// Packets are resent until acknowledged and delivered once, in any order.
+    Reliable = 2,
+    /// Packets are resent until acknowledged, only packets newer than the newest delivered one
+    /// are delivered.
+    ReliableSequenced = 3,
+    /// Packets are resent until acknowledged and delivered in the order they were sent.
+    ReliableOrdered = 4,
+}
+
+impl DeliveryMode {
+    fn from_u8(mode: u8) -> Option<Self> {
+        match mode {
+            0 => Some(DeliveryMode::Unreliable),
+            1 => Some(DeliveryMode::UnreliableSequenced),
+            2 => Some(DeliveryMode::Reliable),
+            3 => Some(DeliveryMode::ReliableSequenced),
+            4 => Some(DeliveryMode::ReliableOrdered),
+            _ => None,
+        }
+    }
+
+    fn is_reliable(self) -> bool {
+        match self {
+            DeliveryMode::Unreliable | DeliveryMode::UnreliableSequenced => false,
+            _ => true,
+        }
+    }
+}
+
+impl From<DeliveryRequirement> for DeliveryMode {
+    fn from(delivery: DeliveryRequirement) -> Self {
+        match delivery {
+            DeliveryRequirement::Unreliable | DeliveryRequirement::Default => {
+                DeliveryMode::Unreliable
+            }
+            DeliveryRequirement::UnreliableSequenced(_) => DeliveryMode::UnreliableSequenced,
+            DeliveryRequirement::Reliable => DeliveryMode::Reliable,
+            DeliveryRequirement::ReliableSequenced(_) => DeliveryMode::ReliableSequenced,
+            DeliveryRequirement::ReliableOrdered(_) => DeliveryMode::ReliableOrdered,
+        }
+    }
+}
+
+/// Configuration of the heartbeats keeping idle connections alive.
+#[derive(Copy, Clone, Debug)]
+pub struct HeartbeatConfig {
+    /// Time without sending anything after which a heartbeat is sent.
+    pub interval: Duration,
+    /// Number of heartbeat intervals without receiving anything after which the connection times
+    /// out.
+    pub max_missed: u32,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            max_missed: 5,
+        }
+    }
+}
+
+/// Statistics of a connection, useful for debugging network conditions.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionStats {
+    /// Smoothed round-trip time, measured from heartbeats and acknowledged packets that were not
+    /// resent.
+    pub rtt: Option<Duration>,
+    /// Number of packets sent, including resends and acknowledgement packets.
+    pub packets_sent: u64,
+    /// Number of valid packets received.
+    pub packets_received: u64,
+    /// Number of reliable packets that were resent.
+    pub packets_resent: u64,
+    /// Estimated number of packets of the remote host that were lost, counted when their
+    /// sequence number leaves the acknowledgement window without having been received.
+    pub packets_lost: u64,
+}
+
+#[derive(Debug)]
+struct Header {
+    mode: u8,
+    sequence: u16,
+    ack: Option<u16>,
+    ack_bits: u32,
+    message_id: u16,
+    /// Index and count of a fragment.
+    fragment: Option<(u16, u16)>,
+}
+
+impl Header {
+    fn size(&self) -> usize {
+        if self.fragment.is_some() {
+            HEADER_SIZE + FRAGMENT_HEADER_SIZE
+        } else {
+            HEADER_SIZE
+        }
+    }
+
+    fn write(&self, packet: &mut Vec<u8>) {
+        let mut mode = self.mode;
+        if self.ack.is_some() {
+            mode |= HAS_ACK;
+        }
+        if self.fragment.is_some() {
+            mode |= IS_FRAGMENT;
+        }
+        packet.push(mode);
+        packet.extend_from_slice(&self.sequence.to_be_bytes());
+        packet.extend_from_slice(&self.ack.unwrap_or(0).to_be_bytes());
+        packet.extend_from_slice(&self.ack_bits.to_be_bytes());
+        packet.extend_from_slice(&self.message_id.to_be_bytes());
+        if let Some((index, count)) = self.fragment {
+            packet.extend_from_slice(&index.to_be_bytes());
+            packet.extend_from_slice(&count.to_be_bytes());
+        }
+    }
+
+    fn read(packet: &[u8]) -> Option<Self> {
+        if packet.len() < HEADER_SIZE {
+            return None;
+        }
+        let ack = u16::from_be_bytes([packet[3], packet[4]]);
+        let fragment = if packet[0] & IS_FRAGMENT != 0 {
+            if packet.len() < HEADER_SIZE + FRAGMENT_HEADER_SIZE {
+                return None;
+            }
+            Some((
+                u16::from_be_bytes([packet[11], packet[12]]),
+                u16::from_be_bytes([packet[13], packet[14]]),
+            ))
+        } else {
+            None
+        };
+        Some(Self {
+            mode: packet[0] & !(HAS_ACK | IS_FRAGMENT),
+            sequence: u16::from_be_bytes([packet[1], packet[2]]),
+            ack: if packet[0] & HAS_ACK != 0 {
+                Some(ack)
+            } else {
+                None
+            },
+            ack_bits: u32::from_be_bytes([packet[5], packet[6], packet[7], packet[8]]),
+            message_id: u16::from_be_bytes([packet[9], packet[10]]),
+            fragment,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct PendingPacket {
+    mode: DeliveryMode,
+    message_id: u16,
+    fragment: Option<(u16, u16)>,
+    payload: Bytes,
+    sent_at: Instant,
+    timeout: Duration,
+    resent: bool,
+}
+
+#[derive(Debug)]
+struct Reassembly {
+    fragments: Vec<Option<Bytes>>,
+    missing: usize,
+    last_received: Instant,
+}
+
+/// State of the connection to a single remote host.
+#[derive(Debug)]
+pub struct Connection {
+    heartbeat: HeartbeatConfig,
+    max_message_size: usize,
+    last_sent: Instant,
+    last_received: Instant,
+    next_ping: u16,
+    ping: Option<(u16, Instant)>,
+    pong_pending: Option<u16>,
+    disconnected: Option<DisconnectReason>,
+    local_sequence: u16,
+    remote_sequence: Option<u16>,
+    received_bits: u32,
+    /// Number of bits of `received_bits` for sequence numbers since the first received packet.
+    received_window: u32,
+    ack_pending: bool,
+    unacked: Vec<u16>,
+    next_message_ids: [u16; 5],
+    newest_sequenced: [Option<u16>; 5],
+    next_ordered: u16,
+    ordered_buffer: HashMap<u16, Bytes>,
+    received_reliable: HashSet<u16>,
+    received_reliable_order: VecDeque<u16>,
+    pending: HashMap<u16, PendingPacket>,
+    reassembly: HashMap<(u8, u16), Reassembly>,
+    stats: ConnectionStats,
+}
+
+impl Connection {
+    /// Creates the state of a new connection, sending and receiving messages of at most
+    /// `max_message_size` bytes.
+    pub fn new(heartbeat: HeartbeatConfig, max_message_size: usize, now: Instant) -> Self {
+        Self {
+            heartbeat,
+            max_message_size,
+            last_sent: now,
+            last_received: now,
+            next_ping: 0,
+            ping: None,
+            pong_pending: None,
+            disconnected: None,
+            local_sequence: 0,
+            remote_sequence: None,
+            received_bits: 0,
+            received_window: 0,
+            ack_pending: false,
+            unacked: Vec::new(),
+            next_message_ids: [0; 5],
+            newest_sequenced: [None; 5],
+            next_ordered: 0,
+            ordered_buffer: HashMap::new(),
+            received_reliable: HashSet::new(),
+            received_reliable_order: VecDeque::new(),
+            pending: HashMap::new(),
+            reassembly: HashMap::new(),
+            stats: ConnectionStats::default(),
+        }
+    }
+
+    /// Returns why the connection was closed, if it was.
+    pub fn disconnected(&self) -> Option<DisconnectReason> {
+        self.disconnected
+    }
+
+    /// Returns the statistics of this connection.
+    pub fn stats(&self) -> &ConnectionStats {
+        &self.stats
+    }
+
+    /// Returns the number of reliable packets waiting for an acknowledgement.
+    pub fn pending_packets(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Builds the packets for a message with the given delivery mode, one per fragment.
+    ///
+    /// Fails if the message is larger than the maximum message size, or if it is reliable and its
+    /// fragments do not fit into the `MAX_PENDING_PACKETS` packets waiting for an acknowledgement.
+    pub fn send(
+        &mut self,
+        payload: &[u8],
+        mode: DeliveryMode,
+        now: Instant,
+    ) -> io::Result<Vec<Vec<u8>>> {
+        if payload.len() > self.max_message_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Message of {} bytes exceeds the maximum message size of {} bytes",
+                    payload.len(),
+                    self.max_message_size
+                ),
+            ));
+        }
+        let count = fragment_count(payload.len());
+        if count > usize::from(u16::max_value()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Message has too many fragments",
+            ));
+        }
+        if mode.is_reliable() && self.pending.len() + count > MAX_PENDING_PACKETS {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Resend buffer of the connection is full",
+            ));
+        }
+        let message_id = self.next_message_ids[mode as usize];
+        self.next_message_ids[mode as usize] = message_id.wrapping_add(1);
+        let timeout = self.retransmit_timeout();
+        if count == 1 {
+            let payload = Bytes::copy_from_slice(payload);
+            return Ok(vec![self.write_packet(
+                mode, message_id, None, payload, now, timeout, false,
+            )]);
+        }
+        Ok(payload
+            .chunks(FRAGMENT_SIZE)
+            .enumerate()
+            .map(|(index, chunk)| {
+                self.write_packet(
+                    mode,
+                    message_id,
+                    Some((index as u16, count as u16)),
+                    Bytes::copy_from_slice(chunk),
+                    now,
+                    timeout,
+                    false,
+                )
+            })
+            .collect())
+    }
+
+    /// Handles a received packet and returns the payloads ready to be delivered.
+    pub fn receive(&mut self, packet: &[u8], now: Instant) -> io::Result<Vec<Bytes>> {
+        let header = Header::read(packet).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Packet is shorter than its header",
+            )
+        })?;
+        if header.mode > PONG || (header.fragment.is_some() && header.mode >= ACK_ONLY) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Unknown delivery mode",
+            ));
+        }
+        self.stats.packets_received += 1;
+        self.last_received = now;
+        if let Some(ack) = header.ack {
+            self.process_acks(ack, header.ack_bits, now);
+        }
+
+        match header.mode {
+            DISCONNECT => self.disconnected = Some(DisconnectReason::Graceful),
+            PING => self.pong_pending = Some(header.message_id),
+            PONG => match self.ping {
+                Some((id, sent_at)) if id == header.message_id => {
+                    self.ping = None;
+                    self.record_rtt(now.duration_since(sent_at));
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        let mode = match DeliveryMode::from_u8(header.mode) {
+            Some(mode) => mode,
+            None => return Ok(Vec::new()),
+        };
+        let duplicate = !self.mark_received(header.sequence);
+        if mode.is_reliable() {
+            self.ack_pending = true;
+            self.unacked.push(header.sequence);
+        }
+        if duplicate {
+            return Ok(Vec::new());
+        }
+
+        let payload = Bytes::copy_from_slice(&packet[header.size()..]);
+        let payload = match header.fragment {
+            // fragments resent after their message was reassembled
+            Some(_) if self.is_delivered(mode, header.message_id) => return Ok(Vec::new()),
+            Some((index, count)) => {
+                match self.reassemble(mode, header.message_id, index, count, payload, now)? {
+                    Some(payload) => payload,
+                    None => return Ok(Vec::new()),
+                }
+            }
+            None => payload,
+        };
+        Ok(self.deliver(mode, header.message_id, payload))
+    }
+
+    /// Returns the payloads to deliver after receiving a complete message.
+    fn deliver(&mut self, mode: DeliveryMode, id: u16, payload: Bytes) -> Vec<Bytes> {
+        match mode {
+            DeliveryMode::Unreliable => vec![payload],
+            DeliveryMode::UnreliableSequenced | DeliveryMode::ReliableSequenced => {
+                let newest = &mut self.newest_sequenced[mode as usize];
+                match *newest {
+                    Some(newest) if !sequence_greater_than(id, newest) => Vec::new(),
+                    _ => {
+                        *newest = Some(id);
+                        vec![payload]
+                    }
+                }
+            }
+            DeliveryMode::Reliable => {
+                if self.received_reliable.insert(id) {
+                    self.received_reliable_order.push_back(id);
+                    if self.received_reliable_order.len() > RECEIVED_HISTORY {
+                        if let Some(oldest) = self.received_reliable_order.pop_front() {
+                            self.received_reliable.remove(&oldest);
+                        }
+                    }
+                    vec![payload]
+                } else {
+                    Vec::new()
+                }
+            }
+            DeliveryMode::ReliableOrdered => {
+                if id == self.next_ordered || sequence_greater_than(id, self.next_ordered) {
+                    self.ordered_buffer.insert(id, payload);
+                }
+                let mut delivered = Vec::new();
+                while let Some(payload) = self.ordered_buffer.remove(&self.next_ordered) {
+                    delivered.push(payload);
+                    self.next_ordered = self.next_ordered.wrapping_add(1);
+                }
+                delivered
+            }
+        }
+    }
+
+    /// Returns true if a reliable message with the given id was already delivered or skipped.
+    fn is_delivered(&self, mode: DeliveryMode, id: u16) -> bool {
+        match mode {
+            DeliveryMode::Unreliable | DeliveryMode::UnreliableSequenced => false,
+            DeliveryMode::ReliableSequenced => match self.newest_sequenced[mode as usize] {
+                Some(newest) => !sequence_greater_than(id, newest),
+                None => false,
+            },
+            DeliveryMode::Reliable => self.received_reliable.contains(&id),
+            DeliveryMode::ReliableOrdered => {
+                (id != self.next_ordered && !sequence_greater_than(id, self.next_ordered))
+                    || self.ordered_buffer.contains_key(&id)
+            }
+        }
+    }
+
+    /// Stores a fragment, returns the message once all its fragments were received.
+    fn reassemble(
+        &mut self,
+        mode: DeliveryMode,
+        id: u16,
+        index: u16,
+        count: u16,
+        payload: Bytes,
+        now: Instant,
+    ) -> io::Result<Option<Bytes>> {
+        let count = usize::from(count);
+        if count > fragment_count(self.max_message_size) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Message of {} fragments exceeds the maximum message size of {} bytes",
+                    count, self.max_message_size
+                ),
+            ));
+        }
+        if usize::from(index) >= count {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Fragment index is out of range",
+            ));
+        }
+
+        let key = (mode as u8, id);
+        let reassembly = self.reassembly.entry(key).or_insert_with(|| Reassembly {
+            fragments: vec![None; count],
+            missing: count,
+            last_received: now,
+        });
+        if reassembly.fragments.len() != count {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Fragment count differs from the other fragments of the message",
+            ));
+        }
+        reassembly.last_received = now;
+        let fragment = &mut reassembly.fragments[usize::from(index)];
+        if fragment.is_none() {
+            *fragment = Some(payload);
+            reassembly.missing -= 1;
+        }
+        if reassembly.missing > 0 {
+            return Ok(None);
+        }
+
+        let reassembly = self
+            .reassembly
+            .remove(&key)
+            .expect("Reassembly was just updated");
+        let mut message = Vec::with_capacity(count * FRAGMENT_SIZE);
+        for fragment in reassembly.fragments.into_iter().flatten() {
+            message.extend_from_slice(&fragment);
+        }
+        Ok(Some(Bytes::from(message)))
+    }
+
+    /// Returns the packets to send without a new message: reliable packets whose timeout expired,
+    /// heartbeats and their answers, and a packet acknowledging received reliable packets if no
+    /// other packet carried the acknowledgement.
+    ///
+    /// Marks the connection as timed out if nothing was received for `max_missed` heartbeat
+    /// intervals.
+    pub fn update(&mut self, now: Instant) -> Vec<Vec<u8>> {
+        self.reassembly.retain(|_, reassembly| {
+            now.duration_since(reassembly.last_received) < REASSEMBLY_TIMEOUT
+        });
+        if self.disconnected.is_some() {
+            return Vec::new();
+        }
+        if now.duration_since(self.last_received)
+            >= self.heartbeat.interval * self.heartbeat.max_missed
+        {
+            self.disconnected = Some(DisconnectReason::Timeout);
+            return Vec::new();
+        }
+
+        let expired = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| now.duration_since(pending.sent_at) >= pending.timeout)
+            .map(|(sequence, _)| *sequence)
+            .collect::<Vec<_>>();
+        let mut packets = Vec::with_capacity(expired.len());
+        for sequence in expired {
+            if let Some(pending) = self.pending.remove(&sequence) {
+                self.stats.packets_resent += 1;
+                packets.push(self.write_packet(
+                    pending.mode,
+                    pending.message_id,
+                    pending.fragment,
+                    pending.payload,
+                    now,
+                    (pending.timeout * 2).min(MAX_TIMEOUT),
+                    true,
+                ));
+            }
+        }
+        if let Some(id) = self.pong_pending.take() {
+            packets.push(self.write_control(PONG, id, now));
+        }
+        if now.duration_since(self.last_sent) >= self.heartbeat.interval {
+            let id = self.next_ping;
+            self.next_ping = id.wrapping_add(1);
+            self.ping = Some((id, now));
+            packets.push(self.write_control(PING, id, now));
+        }
+        if self.ack_pending {
+            packets.push(self.write_control(ACK_ONLY, 0, now));
+        }
+        self.write_old_acks(now, &mut packets);
+        packets
+    }
+
+    /// Acknowledges received reliable packets too old for the bitfield of the regular header,
+    /// which happens when many packets, like the fragments of a large message, arrive at once.
+    fn write_old_acks(&mut self, now: Instant, packets: &mut Vec<Vec<u8>>) {
+        let remote = match self.remote_sequence {
+            Some(remote) => remote,
+            None => return,
+        };
+        let mut old = self
+            .unacked
+            .drain(..)
+            .filter(|sequence| remote.wrapping_sub(*sequence) > 32)
+            .collect::<Vec<_>>();
+        // newest first
+        old.sort_by_key(|sequence| remote.wrapping_sub(*sequence));
+        old.dedup();
+        let mut old = old.into_iter().peekable();
+        while let Some(ack) = old.next() {
+            let mut ack_bits = 0;
+            while let Some(distance) = old.peek().map(|sequence| ack.wrapping_sub(*sequence)) {
+                if distance > 32 {
+                    break;
+                }
+                ack_bits |= 1 << (distance - 1);
+                old.next();
+            }
+            let sequence = self.next_sequence(now);
+            let mut packet = Vec::with_capacity(HEADER_SIZE);
+            Header {
+                mode: ACK_ONLY,
+                sequence,
+                ack: Some(ack),
+                ack_bits,
+                message_id: 0,
+                fragment: None,
+            }
+            .write(&mut packet);
+            packets.push(packet);
+        }
+    }
+
+    /// Builds the packet telling the remote host that the connection is closed.
+    ///
+    /// The packet is not resent, if it is lost the remote host times out instead.
+    pub fn disconnect(&mut self, now: Instant) -> Vec<u8> {
+        self.write_control(DISCONNECT, 0, now)
+    }
+
+    fn write_packet(
+        &mut self,
+        mode: DeliveryMode,
+        message_id: u16,
+        fragment: Option<(u16, u16)>,
+        payload: Bytes,
+        now: Instant,
+        timeout: Duration,
+        resent: bool,
+    ) -> Vec<u8> {
+        let sequence = self.next_sequence(now);
+        let mut header = self.header(mode as u8, sequence, message_id);
+        header.fragment = fragment;
+        let mut packet = Vec::with_capacity(header.size() + payload.len());
+        header.write(&mut packet);
+        packet.extend_from_slice(&payload);
+        if mode.is_reliable() {
+            self.pending.insert(
+                sequence,
+                PendingPacket {
+                    mode,
+                    message_id,
+                    fragment,
+                    payload,
+                    sent_at: now,
+                    timeout,
+                    resent,
+                },
+            );
+        }
+        packet
+    }
+
+    fn write_control(&mut self, mode: u8, message_id: u16, now: Instant) -> Vec<u8> {
+        let sequence = self.next_sequence(now);
+        let mut packet = Vec::with_capacity(HEADER_SIZE);
+        self.header(mode, sequence, message_id).write(&mut packet);
+        packet
+    }
+
+    fn next_sequence(&mut self, now: Instant) -> u16 {
+        self.last_sent = now;
+        let sequence = self.local_sequence;
+        self.local_sequence = sequence.wrapping_add(1);
+        self.stats.packets_sent += 1;
+        sequence
+    }
+
+    /// Every packet carries the acknowledgements, so sending one clears the pending ack.
+    fn header(&mut self, mode: u8, sequence: u16, message_id: u16) -> Header {
+        self.ack_pending = false;
+        Header {
+            mode,
+            sequence,
+            ack: self.remote_sequence,
+            ack_bits: self.received_bits,
+            message_id,
+            fragment: None,
+        }
+    }
+
+    fn retransmit_timeout(&self) -> Duration {
+        self.stats.rtt.map_or(INITIAL_TIMEOUT, |rtt| {
+            (rtt * 2).max(MIN_TIMEOUT).min(MAX_TIMEOUT)
+        })
+    }
+
+    /// Records the sequence number of a received packet, returns false if it was already received.
+    fn mark_received(&mut self, sequence: u16) -> bool {
+        let remote = match self.remote_sequence {
+            Some(remote) => remote,
+            None => {
+                self.remote_sequence = Some(sequence);
+                return true;
+            }
+        };
+        if sequence_greater_than(sequence, remote) {
+            let shift = u32::from(sequence.wrapping_sub(remote));
+            // bits shifted out of the window, then skipped sequence numbers past the window
+            let lost = (0..self.received_window)
+                .filter(|bit| bit + shift >= 32 && self.received_bits & (1 << bit) == 0)
+                .count() as u32
+                + shift.saturating_sub(33);
+            self.stats.packets_lost += u64::from(lost);
+            self.received_window = (self.received_window + shift).min(32);
+            self.received_bits = (self.received_bits.checked_shl(shift).unwrap_or(0))
+                | 1u32.checked_shl(shift - 1).unwrap_or(0);
+            self.remote_sequence = Some(sequence);
+            true
+        } else if sequence == remote {
+            false
+        } else {
+            let bit = u32::from(remote.wrapping_sub(sequence)) - 1;
+            match 1u32.checked_shl(bit) {
+                Some(mask) if self.received_bits & mask == 0 => {
+                    self.received_bits |= mask;
+                    true
+                }
+                Some(_) => false,
+                // Too old to tell, it is delivered again and filtered by its message id.
+                None => true,
+            }
+        }
+    }
+
+    fn process_acks(&mut self, ack: u16, ack_bits: u32, now: Instant) {
+        let acked = self
+            .pending
+            .keys()
+            .filter(|sequence| {
+                let distance = u32::from(ack.wrapping_sub(**sequence));
+                distance == 0 || (distance <= 32 && ack_bits & (1 << (distance - 1)) != 0)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        for sequence in acked {
+            if let Some(pending) = self.pending.remove(&sequence) {
+                // Resent packets are ambiguous, the acknowledgement may be for any of the sends.
+                if !pending.resent {
+                    self.record_rtt(now.duration_since(pending.sent_at));
+                }
+            }
+        }
+    }
+
+    fn record_rtt(&mut self, sample: Duration) {
+        self.stats.rtt = Some(match self.stats.rtt {
+            Some(rtt) => (rtt * 7 + sample) / 8,
+            None => sample,
+        });
+    }
+}
+
+fn fragment_count(size: usize) -> usize {
+    ((size + FRAGMENT_SIZE - 1) / FRAGMENT_SIZE).max(1)
+}
+
+/// Compares sequence numbers, taking wrap around into account.
+fn sequence_greater_than(a: u16, b: u16) -> bool {
+    a != b && a.wrapping_sub(b) < 0x8000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Delivers packets from one connection to the other, collecting the delivered payloads.
+    fn deliver(
+        receiver: &mut Connection,
+        packets: impl IntoIterator<Item = Vec<u8>>,
+        now: Instant,
+        delivered: &mut Vec<Bytes>,
+    ) {
+        for packet in packets {
+            delivered.extend(receiver.receive(&packet, now).unwrap());
+        }
+    }
+
+    fn connection(now: Instant) -> Connection {
+        Connection::new(HeartbeatConfig::default(), DEFAULT_MAX_MESSAGE_SIZE, now)
+    }
+
+    fn payloads(count: u8) -> Vec<Bytes> {
+        (0..count).map(|i| Bytes::copy_from_slice(&[i])).collect()
+    }
+
+    #[test]
+    fn reliable_ordered_survives_drops_and_reordering() {
+        let mut now = Instant::now();
+        let mut sender = connection(now);
+        let mut receiver = connection(now);
+
+        let mut packets = (0..20u8)
+            .map(|i| {
+                sender
+                    .send(&[i], DeliveryMode::ReliableOrdered, now)
+                    .unwrap()
+                    .remove(0)
+            })
+            .enumerate()
+            // drop every third packet
+            .filter(|(i, _)| i % 3 != 0)
+            .map(|(_, packet)| packet)
+            .collect::<Vec<_>>();
+        // swap neighbouring packets
+        for pair in packets.chunks_mut(2) {
+            pair.reverse();
+        }
+
+        let mut delivered = Vec::new();
+        deliver(&mut receiver, packets, now, &mut delivered);
+        assert!(delivered.is_empty(), "First message was dropped");
+
+        for _ in 0..10 {
+            now += MAX_TIMEOUT;
+            let acks = receiver.update(now);
+            deliver(&mut sender, acks, now, &mut Vec::new());
+            let resent = sender.update(now);
+            deliver(&mut receiver, resent, now, &mut delivered);
+        }
+        let acks = receiver.update(now);
+        deliver(&mut sender, acks, now, &mut Vec::new());
+
+        assert_eq!(delivered, payloads(20));
+        assert_eq!(sender.pending_packets(), 0);
+        assert_eq!(sender.stats().packets_resent, 7);
+    }
+
+    #[test]
+    fn reliable_delivers_duplicates_once() {
+        let now = Instant::now();
+        let mut sender = connection(now);
+        let mut receiver = connection(now);
+
+        let first = sender
+            .send(&[0], DeliveryMode::Reliable, now)
+            .unwrap()
+            .remove(0);
+        let second = sender
+            .send(&[1], DeliveryMode::Reliable, now)
+            .unwrap()
+            .remove(0);
+        // the acknowledgement of the first packet is lost, so it is resent
+        let resent = sender.update(now + INITIAL_TIMEOUT);
+
+        let mut delivered = Vec::new();
+        deliver(
+            &mut receiver,
+            vec![second, first.clone(), first],
+            now,
+            &mut delivered,
+        );
+        deliver(&mut receiver, resent, now, &mut delivered);
+        assert_eq!(
+            delivered,
+            vec![Bytes::from_static(&[1]), Bytes::from_static(&[0])]
+        );
+    }
+
+    #[test]
+    fn sequenced_drops_stale_packets() {
+        let now = Instant::now();
+        let mut sender = connection(now);
+        let mut receiver = connection(now);
+
+        let packets = (0..3u8)
+            .map(|i| {
+                sender
+                    .send(&[i], DeliveryMode::UnreliableSequenced, now)
+                    .unwrap()
+                    .remove(0)
+            })
+            .collect::<Vec<_>>();
+        let mut delivered = Vec::new();
+        deliver(
+            &mut receiver,
+            vec![packets[1].clone(), packets[0].clone(), packets[2].clone()],
+            now,
+            &mut delivered,
+        );
+        assert_eq!(
+            delivered,
+            vec![Bytes::from_static(&[1]), Bytes::from_static(&[2])]
+        );
+        assert_eq!(sender.pending_packets(), 0);
+    }
+
+    #[test]
+    fn resend_timeout_backs_off() {
+        let now = Instant::now();
+        let mut sender = connection(now);
+        sender.send(&[0], DeliveryMode::Reliable, now).unwrap();
+
+        assert!(sender.update(now + INITIAL_TIMEOUT / 2).is_empty());
+        let resend_at = now + INITIAL_TIMEOUT;
+        assert_eq!(sender.update(resend_at).len(), 1);
+        assert!(sender.update(resend_at + INITIAL_TIMEOUT).is_empty());
+        assert_eq!(sender.update(resend_at + INITIAL_TIMEOUT * 2).len(), 1);
+        assert_eq!(sender.stats().packets_resent, 2);
+    }
+
+    #[test]
+    fn acknowledgement_measures_rtt() {
+        let now = Instant::now();
+        let mut sender = connection(now);
+        let mut receiver = connection(now);
+        let rtt = Duration::from_millis(30);
+
+        let packet = sender
+            .send(&[0], DeliveryMode::Reliable, now)
+            .unwrap()
+            .remove(0);
+        deliver(&mut receiver, vec![packet], now, &mut Vec::new());
+        let acks = receiver.update(now);
+        assert_eq!(acks.len(), 1);
+        deliver(&mut sender, acks, now + rtt, &mut Vec::new());
+
+        assert_eq!(sender.stats().rtt, Some(rtt));
+        assert_eq!(sender.pending_packets(), 0);
+        assert!(receiver.update(now).is_empty());
+    }
+
+    #[test]
+    fn estimates_packet_loss() {
+        let now = Instant::now();
+        let mut sender = connection(now);
+        let mut receiver = connection(now);
+
+        let mut packets = (0..200u8)
+            .map(|i| {
+                sender
+                    .send(&[i], DeliveryMode::Unreliable, now)
+                    .unwrap()
+                    .remove(0)
+            })
+            .collect::<Vec<_>>();
+        let mut late = packets.split_off(100);
+        // reordered packets are not lost, packets 5, 15, ..., 95 are
+        for pair in packets.chunks_mut(2) {
+            pair.swap(0, 1);
+        }
+        let received = packets
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| i % 10 != 4)
+            .map(|(_, packet)| packet)
+            .collect();
+        deliver(&mut receiver, received, now, &mut Vec::new());
+        // only the packets that left the window of the 32 packets before 99
+        assert_eq!(receiver.stats().packets_lost, 7);
+
+        deliver(
+            &mut receiver,
+            vec![late.pop().unwrap()],
+            now,
+            &mut Vec::new(),
+        );
+        assert_eq!(receiver.stats().packets_lost, 10 + 99 - 32);
+    }
+
+    #[test]
+    fn resend_buffer_is_bounded() {
+        let now = Instant::now();
+        let mut sender = connection(now);
+        for _ in 0..MAX_PENDING_PACKETS {
+            sender.send(&[0], DeliveryMode::Reliable, now).unwrap();
+        }
+        assert!(sender.send(&[0], DeliveryMode::Reliable, now).is_err());
+        assert!(sender.send(&[0], DeliveryMode::Unreliable, now).is_ok());
+    }
+
+    #[test]
+    fn idle_connection_measures_rtt_with_heartbeats() {
+        let now = Instant::now();
+        let config = HeartbeatConfig::default();
+        let mut client = Connection::new(config, DEFAULT_MAX_MESSAGE_SIZE, now);
+        let mut server = Connection::new(config, DEFAULT_MAX_MESSAGE_SIZE, now);
+        let rtt = Duration::from_millis(40);
+
+        assert!(client.update(now + config.interval / 2).is_empty());
+        let ping_at = now + config.interval;
+        let ping = client.update(ping_at);
+        assert_eq!(ping.len(), 1);
+        deliver(&mut server, ping, ping_at, &mut Vec::new());
+        // the answer, followed by the heartbeat of the idle server
+        let answer = server.update(ping_at);
+        assert_eq!(answer.len(), 2);
+        deliver(&mut client, answer, ping_at + rtt, &mut Vec::new());
+
+        assert_eq!(client.stats().rtt, Some(rtt));
+        assert_eq!(client.disconnected(), None);
+    }
+
+    #[test]
+    fn silent_connection_times_out() {
+        let now = Instant::now();
+        let config = HeartbeatConfig::default();
+        let mut connection = Connection::new(config, DEFAULT_MAX_MESSAGE_SIZE, now);
+
+        assert!(!connection
+            .update(now + config.interval * (config.max_missed - 1))
+            .is_empty());
+        assert_eq!(connection.disconnected(), None);
+        assert!(connection
+            .update(now + config.interval * config.max_missed)
+            .is_empty());
+        assert_eq!(connection.disconnected(), Some(DisconnectReason::Timeout));
+    }
+
+    #[test]
+    fn disconnect_is_graceful() {
+        let now = Instant::now();
+        let mut client = connection(now);
+        let mut server = connection(now);
+
+        let packet = client.disconnect(now);
+        assert!(server.receive(&packet, now).unwrap().is_empty());
+        assert_eq!(server.disconnected(), Some(DisconnectReason::Graceful));
+    }
+
+    /// Drops about one in eight packets and reverses the order of every three packets.
+    fn lossy(packets: Vec<Vec<u8>>, seed: &mut u32) -> Vec<Vec<u8>> {
+        let mut packets = packets
+            .into_iter()
+            .filter(|_| {
+                *seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (*seed >> 16) % 8 != 0
+            })
+            .collect::<Vec<_>>();
+        for chunk in packets.chunks_mut(3) {
+            chunk.reverse();
+        }
+        packets
+    }
+
+    #[test]
+    fn large_message_survives_lossy_link() {
+        let mut now = Instant::now();
+        let mut sender = connection(now);
+        let mut receiver = connection(now);
+        let mut seed = 1;
+        let message = (0..200 * 1024)
+            .map(|i| (i * 7 % 251) as u8)
+            .collect::<Vec<_>>();
+
+        let packets = sender
+            .send(&message, DeliveryMode::ReliableOrdered, now)
+            .unwrap();
+        assert_eq!(packets.len(), fragment_count(message.len()));
+        assert!(packets
+            .iter()
+            .all(|packet| packet.len() <= HEADER_SIZE + FRAGMENT_HEADER_SIZE + FRAGMENT_SIZE));
+        let mut delivered = Vec::new();
+        deliver(
+            &mut receiver,
+            lossy(packets, &mut seed),
+            now,
+            &mut delivered,
+        );
+
+        for _ in 0..100 {
+            if !delivered.is_empty() && sender.pending_packets() == 0 {
+                break;
+            }
+            now += Duration::from_millis(500);
+            let acks = lossy(receiver.update(now), &mut seed);
+            deliver(&mut sender, acks, now, &mut Vec::new());
+            let resent = lossy(sender.update(now), &mut seed);
+            deliver(&mut receiver, resent, now, &mut delivered);
+        }
+
+        assert_eq!(delivered.len(), 1);
+        assert!(delivered[0] == message[..], "Reassembled message differs");
+        assert_eq!(sender.pending_packets(), 0);
+        assert!(sender.stats().packets_resent > 0);
+        assert!(receiver.reassembly.is_empty());
+    }
+
+    #[test]
+    fn incomplete_unreliable_message_expires() {
+        let now = Instant::now();
+        let mut sender = connection(now);
+        let mut receiver = connection(now);
+
+        let mut packets = sender
+            .send(&[1; FRAGMENT_SIZE * 3], DeliveryMode::Unreliable, now)
+            .unwrap();
+        assert_eq!(packets.len(), 3);
+        packets.remove(1);
+        let mut delivered = Vec::new();
+        deliver(&mut receiver, packets, now, &mut delivered);
+        assert!(delivered.is_empty());
+        assert_eq!(receiver.reassembly.len(), 1);
+
+        receiver.update(now + REASSEMBLY_TIMEOUT);
+        assert!(receiver.reassembly.is_empty());
+    }
+
+    #[test]
+    fn oversized_messages_are_rejected() {
+        let now = Instant::now();
+        let mut sender = connection(now);
+        let mut receiver = Connection::new(HeartbeatConfig::default(), FRAGMENT_SIZE, now);
+
+        let error = receiver
+            .send(&[0; FRAGMENT_SIZE + 1], DeliveryMode::Reliable, now)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(receiver.pending_packets(), 0);
+
+        let packets = sender
+            .send(&[0; FRAGMENT_SIZE * 2], DeliveryMode::Reliable, now)
+            .unwrap();
+        let error = receiver.receive(&packets[0], now).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn invalid_packets_are_rejected() {
+        let now = Instant::now();
+        let mut receiver = connection(now);
+        assert!(receiver.receive(&[0; HEADER_SIZE - 1], now).is_err());
+        assert!(receiver.receive(&[0x7F; HEADER_SIZE], now).is_err());
+    }
+
+    #[test]
+    fn sequence_numbers_wrap() {
+        assert!(sequence_greater_than(0, u16::max_value()));
+        assert!(sequence_greater_than(1, 0));
+        assert!(!sequence_greater_than(0, 1));
+    }
+}
@@ -0,0 +1,77 @@
+#version 450
+
+// Grades the source image over the target.
+// Keep in sync with amethyst_rendy/src/pod.rs ColorGradeArgs
+
+layout(std140, set = 0, binding = 0) uniform ColorGradeArgs {
+    vec4 vignette_color;
+    float saturation;
+    float contrast;
+    float lift;
+    float gamma;
+    float gain;
+    float lut_strength;
+    float vignette_radius;
+    float vignette_smoothness;
+    bool identity;
+};
+
+layout(set = 1, binding = 0) uniform sampler2D source;
+// A strip of blue slices, each slice holding red along x and green along y.
+layout(set = 2, binding = 0) uniform sampler2D lut;
+
+layout(location = 0) in vec2 tex_coord;
+
+layout(location = 0) out vec4 out_color;
+
+vec3 linear_to_srgb(vec3 color) {
+    vec3 low = color * 12.92;
+    vec3 high = 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055;
+    return mix(high, low, lessThanEqual(color, vec3(0.0031308)));
+}
+
+vec3 srgb_to_linear(vec3 color) {
+    vec3 low = color / 12.92;
+    vec3 high = pow((color + 0.055) / 1.055, vec3(2.4));
+    return mix(high, low, lessThanEqual(color, vec3(0.04045)));
+}
+
+// LUTs are authored for display colors, so they are looked up in sRGB space.
+vec3 apply_lut(vec3 color) {
+    float size = float(textureSize(lut, 0).y);
+    vec3 cell = linear_to_srgb(clamp(color, 0.0, 1.0)) * (size - 1.0);
+    float blue = floor(cell.b);
+    float next_blue = min(blue + 1.0, size - 1.0);
+    vec2 uv = vec2((cell.r + 0.5) / (size * size), (cell.g + 0.5) / size);
+    vec3 low = texture(lut, uv + vec2(blue / size, 0.0)).rgb;
+    vec3 high = texture(lut, uv + vec2(next_blue / size, 0.0)).rgb;
+    return srgb_to_linear(mix(low, high, cell.b - blue));
+}
+
+void main() {
+    vec4 source_color = texelFetch(source, ivec2(gl_FragCoord.xy), 0);
+    if (identity) {
+        out_color = source_color;
+        return;
+    }
+
+    vec3 color = source_color.rgb;
+    color = gain * (color + lift * (1.0 - color));
+    color = pow(max(color, 0.0), vec3(1.0 / gamma));
+    color = (color - 0.5) * contrast + 0.5;
+    float luminance = dot(color, vec3(0.2126, 0.7152, 0.0722));
+    color = mix(vec3(luminance), color, saturation);
+
+    if (lut_strength > 0.0) {
+        color = mix(color, apply_lut(color), lut_strength);
+    }
+
+    if (vignette_color.a > 0.0) {
+        // 1 in the corners of the image
+        float distance = length(tex_coord - 0.5) * 1.41421356;
+        float vignette = smoothstep(vignette_radius, vignette_radius + vignette_smoothness, distance);
+        color = mix(color, vignette_color.rgb, vignette * vignette_color.a);
+    }
+
+    out_color = vec4(color, source_color.a);
+}
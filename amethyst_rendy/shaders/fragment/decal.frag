@@ -0,0 +1,66 @@
+#version 450
+
+#include "header/environment.frag"
+
+layout(set = 1, binding = 0) uniform sampler2D receiver_normal_depth;
+layout(set = 1, binding = 1) uniform usampler2D receiver_layers;
+layout(set = 2, binding = 0) uniform sampler2D albedo;
+layout(set = 3, binding = 0) uniform sampler2D normal_map;
+
+layout(location = 0) flat in mat4 clip_to_world;
+layout(location = 4) flat in mat4 world_to_decal;
+layout(location = 8) flat in mat3 decal_axes;
+layout(location = 11) flat in float opacity;
+layout(location = 12) flat in uvec2 receivers;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    ivec2 pixel = ivec2(gl_FragCoord.xy);
+    // Render layers and flags of the surface, zero where nothing receives decals.
+    uvec2 receiver = texelFetch(receiver_layers, pixel, 0).xy;
+    if ((receiver.x & receivers.x) == 0u || (receiver.y & ~receivers.y) != 0u) {
+        discard;
+    }
+
+    vec4 normal_depth = texelFetch(receiver_normal_depth, pixel, 0);
+    vec2 ndc = gl_FragCoord.xy / vec2(textureSize(receiver_normal_depth, 0)) * 2.0 - 1.0;
+    vec4 world = clip_to_world * vec4(ndc, normal_depth.w, 1.0);
+    vec3 position = world.xyz / world.w;
+    vec3 local = (world_to_decal * vec4(position, 1.0)).xyz;
+    if (any(greaterThan(abs(local), vec3(0.5)))) {
+        discard;
+    }
+
+    // Projected along -z, surfaces turned away from the projection fade out.
+    vec3 surface_normal = normalize(normal_depth.xyz);
+    float facing = smoothstep(0.0, 0.3, dot(surface_normal, decal_axes[2]));
+    vec2 uv = vec2(local.x + 0.5, 0.5 - local.y);
+    vec4 color = texture(albedo, uv);
+    float alpha = color.a * opacity * facing;
+    if (alpha <= 0.0) {
+        discard;
+    }
+
+    // The tangent space of the decal follows its x axis along the surface.
+    vec3 tangent = decal_axes[0] - surface_normal * dot(surface_normal, decal_axes[0]);
+    tangent = normalize(tangent + vec3(0.00001));
+    vec3 bitangent = cross(surface_normal, tangent);
+    vec3 tangent_normal = texture(normal_map, uv).rgb * 2.0 - 1.0;
+    vec3 normal = normalize(mat3(tangent, bitangent, surface_normal) * tangent_normal);
+
+    vec3 lighting = ambient_light(normal);
+    for (int i = 0; i < point_light_count; i++) {
+        vec3 dist = plight[i].position - position;
+        float diff = max(dot(normalize(dist), normal), 0.0);
+        float attenuation = point_attenuation(plight[i], position, plight[i].intensity / dot(dist, dist))
+            * point_shadow(plight[i], position);
+        lighting += diff * plight[i].color * attenuation;
+    }
+    for (int i = 0; i < directional_light_count; i++) {
+        float diff = max(dot(-dlight[i].direction, normal), 0.0);
+        lighting += diff * dlight[i].color * dlight[i].intensity;
+    }
+
+    out_color = vec4(color.rgb * lighting, alpha);
+}
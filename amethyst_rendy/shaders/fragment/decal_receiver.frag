@@ -0,0 +1,13 @@
+#version 450
+
+layout(location = 0) in vec3 in_normal;
+layout(location = 1) flat in uvec2 in_receiver;
+
+layout(location = 0) out vec4 out_normal_depth;
+layout(location = 1) out uvec2 out_receiver;
+
+void main() {
+    // The depth reconstructs the position of the surface in `decal.frag`
+    out_normal_depth = vec4(normalize(in_normal), gl_FragCoord.z);
+    out_receiver = in_receiver;
+}
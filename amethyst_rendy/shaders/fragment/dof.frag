@@ -0,0 +1,14 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform sampler2D sharp;
+layout(set = 0, binding = 1) uniform sampler2D blurred;
+
+layout(location = 0) in vec2 tex_coord;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    vec4 color = texture(sharp, tex_coord);
+    vec4 blur = texture(blurred, tex_coord);
+    out_color = vec4(mix(color.rgb, blur.rgb, blur.a), color.a);
+}
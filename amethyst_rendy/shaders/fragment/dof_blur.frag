@@ -0,0 +1,78 @@
+#version 450
+
+layout(std140, set = 0, binding = 0) uniform DofArgs {
+    mat4 inverse_proj;
+    float focus_distance;
+    float aperture;
+    float max_radius;
+    uint blades;
+    float rotation;
+};
+
+layout(set = 1, binding = 0) uniform sampler2D source;
+layout(set = 1, binding = 1) uniform sampler2D source_depth;
+
+layout(location = 0) in vec2 tex_coord;
+
+layout(location = 0) out vec4 out_color;
+
+const float PI = 3.14159265359;
+// Rings of samples around the center, each ring having RING_SAMPLES more than the previous one.
+const int RINGS = 3;
+const int RING_SAMPLES = 8;
+
+// Signed radius of the circle of confusion in pixels of the source, negative in front of the
+// focus.
+float coc(vec2 uv) {
+    float depth = texture(source_depth, uv).r;
+    vec4 view = inverse_proj * vec4(uv * 2.0 - 1.0, depth, 1.0);
+    float distance = max(-view.z / view.w, 0.0001);
+    return clamp(aperture * (distance - focus_distance) / distance, -1.0, 1.0) * max_radius;
+}
+
+// Distance from the center to the edge of the bokeh in the direction of `angle`, relative to
+// its outer radius.
+float bokeh(float angle) {
+    if (blades < 3u) {
+        return 1.0;
+    }
+    float sector = 2.0 * PI / float(blades);
+    float offset = mod(angle - rotation, sector) - sector * 0.5;
+    return cos(sector * 0.5) / cos(offset);
+}
+
+void main() {
+    vec2 pixel = 1.0 / vec2(textureSize(source, 0));
+    float center_coc = coc(tex_coord);
+
+    vec3 color = texture(source, tex_coord).rgb;
+    float weight = 1.0;
+    float near = 0.0;
+    float count = 0.0;
+
+    // Scatter as gather: each sample spreads over its own circle of confusion, and is gathered
+    // if that circle covers the center.
+    for (int ring = 1; ring <= RINGS; ring++) {
+        int samples = ring * RING_SAMPLES;
+        for (int i = 0; i < samples; i++) {
+            float angle = 2.0 * PI * (float(i) + 0.5 * float(ring)) / float(samples);
+            float radius = float(ring) / float(RINGS) * bokeh(angle) * max_radius;
+            vec2 uv = tex_coord + vec2(cos(angle), sin(angle)) * radius * pixel;
+            float sample_coc = coc(uv);
+            // Surfaces in front bleed over the center, surfaces behind it don't blur over it
+            // more than it is blurred itself.
+            float reach = sample_coc < 0.0 ? -sample_coc : min(sample_coc, abs(center_coc));
+            float sample_weight = clamp(reach - radius + 1.0, 0.0, 1.0);
+            color += texture(source, uv).rgb * sample_weight;
+            weight += sample_weight;
+            near += sample_coc < 0.0 ? sample_weight : 0.0;
+            count += 1.0;
+        }
+    }
+
+    // The blurred image replaces the sharp one where the center is blurred, or covered by
+    // blurred surfaces in front of it.
+    float blend = smoothstep(1.0, 3.0, abs(center_coc));
+    float coverage = clamp(near / count * 4.0, 0.0, 1.0);
+    out_color = vec4(color / weight, max(blend, coverage));
+}
@@ -0,0 +1,22 @@
+#ifndef CLIP_FRAG
+#define CLIP_FRAG
+
+// Fallback for the `ClipPlanes` on devices without clip distances, enabled by specialization.
+// Keep in sync with amethyst_rendy/src/pod.rs ViewArgs.
+layout(constant_id = 0) const bool CLIP_DISCARD = false;
+
+layout(std140, set = 0, binding = 0) uniform ViewArgs {
+    mat4 proj;
+    mat4 view;
+    mat4 proj_view;
+    mat4 clip_planes;
+} clip_view;
+
+/// Discards the fragment at world space `position` if it is behind an enabled clip plane.
+void clip_discard(vec3 position) {
+    if (CLIP_DISCARD && any(lessThan(vec4(position, 1.0) * clip_view.clip_planes, vec4(0.0)))) {
+        discard;
+    }
+}
+
+#endif
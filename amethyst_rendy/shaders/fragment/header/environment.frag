@@ -6,8 +6,22 @@ struct PointLight {
     vec3 position;
     vec3 color;
     float intensity;
+    int shadow;
+    float shadow_range;
+    float shadow_bias;
+    vec4 shadow_tile;
+    int attenuation;
+    float radius;
+    float smoothness;
+    vec3 attenuation_coefficients;
 };
 
+// Attenuation models of point lights, see `Attenuation::shader_model`.
+const int ATTENUATION_LEGACY = 0;
+const int ATTENUATION_INVERSE_SQUARE = 1;
+const int ATTENUATION_EXPONENT = 2;
+const int ATTENUATION_COEFFICIENTS = 3;
+
 struct DirectionalLight {
     vec3 color;
     float intensity;
@@ -22,6 +36,8 @@ struct SpotLight {
     float intensity;
     float range;
     float smoothness;
+    mat4 cookie_projection;
+    int cookie;
 };
 
 layout(std140, set = 0, binding = 1) uniform Environment {
@@ -30,6 +46,9 @@ layout(std140, set = 0, binding = 1) uniform Environment {
     int point_light_count;
     int directional_light_count;
     int spot_light_count;
+    float ibl_intensity;
+    vec3 ambient_ground_color;
+    vec3 ambient_up;
 };
 
 layout(std140, set = 0, binding = 2) uniform PointLights {
@@ -42,4 +61,109 @@ layout(std140, set = 0, binding = 3) uniform DirectionalLights {
 
 layout(std140, set = 0, binding = 4) uniform SpotLights {
     SpotLight slight[128];
-};
\ No newline at end of file
+};
+
+// Ambient light reaching a surface facing `normal`, blended from the ground to the sky color.
+// Both colors are the same without a hemisphere ambient.
+vec3 ambient_light(vec3 normal) {
+    return mix(ambient_ground_color, ambient_color, dot(normal, ambient_up) * 0.5 + 0.5);
+}
+
+// Intensity of a point light reaching a world position. Lights with the legacy model return
+// `legacy`, the falloff the calling shader used before attenuation models.
+float point_attenuation(PointLight light, vec3 position, float legacy) {
+    if (light.attenuation == ATTENUATION_LEGACY) {
+        return legacy;
+    }
+    vec3 to_light = light.position - position;
+    float distance2 = dot(to_light, to_light);
+    float distance = sqrt(distance2);
+    float radius = max(light.radius, 0.00001);
+    // Fades the light out at the radius, equation 26 of Moving Frostbite to PBR.
+    float window = clamp(1.0 - pow(distance / radius, light.smoothness), 0.0, 1.0);
+    window *= window;
+
+    vec3 c = light.attenuation_coefficients;
+    float falloff;
+    if (light.attenuation == ATTENUATION_INVERSE_SQUARE) {
+        falloff = 1.0 / max(distance2, 0.0001);
+    } else if (light.attenuation == ATTENUATION_EXPONENT) {
+        falloff = pow(clamp(1.0 - distance2 / (radius * radius), 0.0, 1.0), c.x);
+    } else {
+        falloff = 1.0 / max(c.x + c.y * distance + c.z * distance2, 0.0001);
+    }
+    return light.intensity * falloff * window;
+}
+
+// Spot light cookies, unused slots hold a placeholder texture.
+layout(set = 0, binding = 5) uniform sampler2D spot_cookie_0;
+layout(set = 0, binding = 6) uniform sampler2D spot_cookie_1;
+layout(set = 0, binding = 7) uniform sampler2D spot_cookie_2;
+layout(set = 0, binding = 8) uniform sampler2D spot_cookie_3;
+
+// Color of the cookie of a spot light at a world position.
+vec3 spot_cookie(int cookie, mat4 cookie_projection, vec3 position) {
+    vec4 clip = cookie_projection * vec4(position, 1.0);
+    vec2 uv = clip.xy / max(clip.w, 0.00001) * 0.5 + 0.5;
+    // Clamp to a black border, so nothing is projected outside of the cone or behind the light.
+    float inside = step(0.0, clip.w) * step(0.0, uv.x) * step(uv.x, 1.0) * step(0.0, uv.y) * step(uv.y, 1.0);
+    uv = clamp(uv, 0.0, 1.0);
+    vec3 color;
+    switch (cookie) {
+        case 0: color = texture(spot_cookie_0, uv).rgb; break;
+        case 1: color = texture(spot_cookie_1, uv).rgb; break;
+        case 2: color = texture(spot_cookie_2, uv).rgb; break;
+        default: color = texture(spot_cookie_3, uv).rgb; break;
+    }
+    return color * inside;
+}
+
+// Distances to the closest surfaces around shadow casting point lights, each light has a row of
+// six cube faces. Holds a placeholder texture without point shadows.
+layout(set = 0, binding = 9) uniform sampler2D point_shadow_atlas;
+
+// Cube faces of the atlas, as in amethyst_rendy/src/pass/point_shadow.rs: direction, right, up.
+const vec3 POINT_SHADOW_FACES[18] = vec3[](
+    vec3(1.0, 0.0, 0.0), vec3(0.0, 0.0, -1.0), vec3(0.0, 1.0, 0.0),
+    vec3(-1.0, 0.0, 0.0), vec3(0.0, 0.0, 1.0), vec3(0.0, 1.0, 0.0),
+    vec3(0.0, 1.0, 0.0), vec3(1.0, 0.0, 0.0), vec3(0.0, 0.0, -1.0),
+    vec3(0.0, -1.0, 0.0), vec3(1.0, 0.0, 0.0), vec3(0.0, 0.0, 1.0),
+    vec3(0.0, 0.0, 1.0), vec3(1.0, 0.0, 0.0), vec3(0.0, 1.0, 0.0),
+    vec3(0.0, 0.0, -1.0), vec3(-1.0, 0.0, 0.0), vec3(0.0, 1.0, 0.0)
+);
+
+// Fraction of a point light reaching a world position, filtered over 3x3 texels.
+float point_shadow(PointLight light, vec3 position) {
+    if (light.shadow < 0) {
+        return 1.0;
+    }
+    vec3 to_position = position - light.position;
+    vec3 axis = abs(to_position);
+    int face;
+    if (axis.x >= axis.y && axis.x >= axis.z) {
+        face = to_position.x > 0.0 ? 0 : 1;
+    } else if (axis.y >= axis.z) {
+        face = to_position.y > 0.0 ? 2 : 3;
+    } else {
+        face = to_position.z > 0.0 ? 4 : 5;
+    }
+    vec3 direction = POINT_SHADOW_FACES[face * 3];
+    vec3 right = POINT_SHADOW_FACES[face * 3 + 1];
+    vec3 up = POINT_SHADOW_FACES[face * 3 + 2];
+    float depth = dot(to_position, direction);
+    vec2 face_uv = vec2(dot(to_position, right), dot(to_position, up)) / depth * 0.5 + 0.5;
+
+    float texel = light.shadow_tile.w;
+    vec2 origin = vec2(float(face) / 6.0, light.shadow_tile.x);
+    float distance = length(to_position) - light.shadow_bias;
+    float lit = 0.0;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            // Samples stay inside the face, they would read another face or light otherwise.
+            vec2 uv = clamp(face_uv + vec2(x, y) * texel, 0.5 * texel, 1.0 - 0.5 * texel);
+            float occluder = texture(point_shadow_atlas, origin + uv * light.shadow_tile.yz).r;
+            lit += step(distance, occluder * light.shadow_range);
+        }
+    }
+    return lit / 9.0;
+}
@@ -0,0 +1,27 @@
+#ifndef FLAT_SHADING_FRAG
+#define FLAT_SHADING_FRAG
+
+#include "math.frag"
+
+layout(std140, set = 1, binding = 0) uniform Material {
+    UvOffset uv_offset;
+    float alpha_cutoff;
+    bool unlit;
+};
+
+layout(set = 1, binding = 1) uniform sampler2D albedo;
+
+layout(location = 0) in VertexData {
+    vec3 position;
+    vec2 tex_coord;
+    vec4 color;
+} vertex;
+
+/// Shades the fragment, discarding it below the alpha cutoff.
+vec4 shade() {
+    vec4 albedo = texture(albedo, tex_coords(vertex.tex_coord, uv_offset));
+    if(albedo.w < alpha_cutoff) discard;
+    return albedo * vertex.color;
+}
+
+#endif
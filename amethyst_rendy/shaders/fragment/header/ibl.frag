@@ -0,0 +1,66 @@
+#ifndef IBL_FRAG
+#define IBL_FRAG
+
+#include "math.frag"
+
+// Image-based lighting maps, baked by `DrawIblBake`.
+// Keep in sync with amethyst_rendy/src/pass/ibl.rs
+
+// Number of roughness levels stacked vertically in the specular map, from 0 to 1.
+const float IBL_SPECULAR_LEVELS = 5.0;
+// Height of a single level of the specular map in pixels.
+const float IBL_LEVEL_HEIGHT = 256.0 / IBL_SPECULAR_LEVELS;
+
+// Maps a direction to equirectangular texture coordinates, +Y at the top.
+vec2 direction_to_equirect(vec3 direction) {
+    vec3 d = normalize(direction);
+    return vec2(atan(d.z, d.x) / (2.0 * PI) + 0.5, acos(clamp(d.y, -1.0, 1.0)) / PI);
+}
+
+vec3 equirect_to_direction(vec2 uv) {
+    float phi = (uv.x - 0.5) * 2.0 * PI;
+    float theta = uv.y * PI;
+    return vec3(sin(theta) * cos(phi), cos(theta), sin(theta) * sin(phi));
+}
+
+vec2 hammersley(uint i, uint count) {
+    uint bits = i;
+    bits = (bits << 16u) | (bits >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return vec2(float(i) / float(count), float(bits) * 2.3283064365386963e-10);
+}
+
+// Rotates a direction around +Z in tangent space to world space around `normal`.
+vec3 tangent_to_world(vec3 direction, vec3 normal) {
+    vec3 up = abs(normal.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0);
+    vec3 tangent = normalize(cross(up, normal));
+    vec3 bitangent = cross(normal, tangent);
+    return normalize(tangent * direction.x + bitangent * direction.y + normal * direction.z);
+}
+
+vec3 importance_sample_ggx(vec2 xi, vec3 normal, float roughness) {
+    float a = roughness * roughness;
+    float phi = 2.0 * PI * xi.x;
+    float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    return tangent_to_world(vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta), normal);
+}
+
+// Geometry term of the split sum approximation, using k = a / 2 for image-based lighting.
+float ibl_geometry(float NdotV, float NdotL, float roughness) {
+    float k = roughness * roughness / 2.0;
+    return (NdotV / (NdotV * (1.0 - k) + k)) * (NdotL / (NdotL * (1.0 - k) + k));
+}
+
+// Coordinates of a direction in a level of the specular map, kept half a pixel away from the
+// neighbouring levels.
+vec2 specular_level_coords(vec3 direction, float level) {
+    vec2 uv = direction_to_equirect(direction);
+    float v = clamp(uv.y, 0.5 / IBL_LEVEL_HEIGHT, 1.0 - 0.5 / IBL_LEVEL_HEIGHT);
+    return vec2(uv.x, (level + v) / IBL_SPECULAR_LEVELS);
+}
+
+#endif
@@ -0,0 +1,81 @@
+#ifndef IBL_BAKE_FRAG
+#define IBL_BAKE_FRAG
+
+// Bakes the image-based lighting maps into three color targets at once.
+// Including shaders define `vec3 environment(vec3 direction)` first.
+
+#include "ibl.frag"
+
+layout(location = 0) in vec2 in_uv;
+
+layout(location = 0) out vec4 out_irradiance;
+layout(location = 1) out vec4 out_specular;
+layout(location = 2) out vec2 out_brdf;
+
+const uint SAMPLE_COUNT = 64u;
+
+// Cosine weighted average of the radiance over the hemisphere around `normal`.
+vec3 irradiance(vec3 normal) {
+    vec3 sum = vec3(0.0);
+    for (uint i = 0u; i < SAMPLE_COUNT; i++) {
+        vec2 xi = hammersley(i, SAMPLE_COUNT);
+        float phi = 2.0 * PI * xi.x;
+        float cos_theta = sqrt(1.0 - xi.y);
+        float sin_theta = sqrt(xi.y);
+        vec3 direction = vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+        sum += environment(tangent_to_world(direction, normal));
+    }
+    return sum / float(SAMPLE_COUNT);
+}
+
+// Radiance reflected towards `reflected` by a GGX lobe, assuming the view along the normal.
+vec3 prefiltered(vec3 reflected, float roughness) {
+    if (roughness == 0.0) {
+        return environment(reflected);
+    }
+    vec3 sum = vec3(0.0);
+    float weight = 0.0;
+    for (uint i = 0u; i < SAMPLE_COUNT; i++) {
+        vec3 halfway = importance_sample_ggx(hammersley(i, SAMPLE_COUNT), reflected, roughness);
+        vec3 light = normalize(2.0 * dot(reflected, halfway) * halfway - reflected);
+        float NdotL = dot(reflected, light);
+        if (NdotL > 0.0) {
+            sum += environment(light) * NdotL;
+            weight += NdotL;
+        }
+    }
+    return sum / max(weight, 0.0001);
+}
+
+// Scale and bias applied to the fresnel base by the split sum approximation.
+vec2 integrate_brdf(float NdotV, float roughness) {
+    vec3 view = vec3(sqrt(1.0 - NdotV * NdotV), 0.0, NdotV);
+    vec3 normal = vec3(0.0, 0.0, 1.0);
+    vec2 sum = vec2(0.0);
+    for (uint i = 0u; i < SAMPLE_COUNT; i++) {
+        vec3 halfway = importance_sample_ggx(hammersley(i, SAMPLE_COUNT), normal, roughness);
+        vec3 light = normalize(2.0 * dot(view, halfway) * halfway - view);
+        float NdotL = max(light.z, 0.0);
+        float NdotH = max(halfway.z, 0.0);
+        float VdotH = max(dot(view, halfway), 0.0);
+        if (NdotL > 0.0) {
+            float visibility = ibl_geometry(NdotV, NdotL, roughness) * VdotH / (NdotH * NdotV);
+            float fresnel = pow(1.0 - VdotH, 5.0);
+            sum += vec2((1.0 - fresnel) * visibility, fresnel * visibility);
+        }
+    }
+    return sum / float(SAMPLE_COUNT);
+}
+
+void main() {
+    out_irradiance = vec4(irradiance(equirect_to_direction(in_uv)), 1.0);
+
+    float level = min(floor(in_uv.y * IBL_SPECULAR_LEVELS), IBL_SPECULAR_LEVELS - 1.0);
+    vec2 level_coord = vec2(in_uv.x, in_uv.y * IBL_SPECULAR_LEVELS - level);
+    float roughness = level / (IBL_SPECULAR_LEVELS - 1.0);
+    out_specular = vec4(prefiltered(equirect_to_direction(level_coord), roughness), 1.0);
+
+    out_brdf = integrate_brdf(max(in_uv.x, 0.001), in_uv.y);
+}
+
+#endif
@@ -0,0 +1,9 @@
+#ifndef LIGHTMAP_FRAG
+#define LIGHTMAP_FRAG
+
+// Values of `Material::lightmap_mode`, keep in sync with amethyst_rendy/src/lightmap.rs
+const int LIGHTMAP_DISABLED = 0;
+const int LIGHTMAP_MULTIPLY = 1;
+const int LIGHTMAP_ADD = 2;
+
+#endif
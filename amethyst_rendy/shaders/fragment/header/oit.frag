@@ -0,0 +1,17 @@
+#ifndef OIT_FRAG
+#define OIT_FRAG
+
+// Weighted blended order-independent transparency, see McGuire and Bavoil, "Weighted Blended
+// Order-Independent Transparency", JCGT 2013.
+// Keep in sync with amethyst_rendy/src/pass/oit.rs
+
+/// Writes a premultiplied color to the accumulation and revealage targets, weighted by its
+/// coverage and depth so that nearer and more opaque surfaces dominate the composite.
+void write_oit(vec4 color, out vec4 accum, out float revealage) {
+    float depth = 1.0 - gl_FragCoord.z * 0.9;
+    float weight = clamp(pow(min(1.0, color.a * 10.0) + 0.01, 3.0) * 1e8 * depth * depth * depth, 1e-2, 3e3);
+    accum = color * weight;
+    revealage = color.a;
+}
+
+#endif
@@ -0,0 +1,39 @@
+#ifndef OUTLINE_FRAG
+#define OUTLINE_FRAG
+
+// Must match `MAX_OUTLINE_THICKNESS`
+#define MAX_THICKNESS 8
+
+layout(set = 0, binding = 0) uniform sampler2D outline_color;
+layout(set = 0, binding = 1) uniform sampler2D outline_data;
+
+layout(location = 0) in vec2 tex_coord;
+
+layout(location = 0) out vec4 out_color;
+
+// Finds the closest outlined pixel whose outline reaches this one, outside of the outlined
+// meshes. Returns false if there is none.
+bool find_outline(out vec4 color, out vec4 data) {
+    ivec2 pixel = ivec2(gl_FragCoord.xy);
+    if (texelFetch(outline_data, pixel, 0).w > 0.0) {
+        return false;
+    }
+
+    ivec2 max_pixel = textureSize(outline_data, 0) - 1;
+    float closest = float(MAX_THICKNESS) + 1.0;
+    for (int y = -MAX_THICKNESS; y <= MAX_THICKNESS; y++) {
+        for (int x = -MAX_THICKNESS; x <= MAX_THICKNESS; x++) {
+            ivec2 neighbor = clamp(pixel + ivec2(x, y), ivec2(0), max_pixel);
+            vec4 neighbor_data = texelFetch(outline_data, neighbor, 0);
+            float distance = length(vec2(x, y));
+            if (neighbor_data.w > 0.0 && distance <= neighbor_data.y && distance < closest) {
+                closest = distance;
+                color = texelFetch(outline_color, neighbor, 0);
+                data = neighbor_data;
+            }
+        }
+    }
+    return closest <= float(MAX_THICKNESS);
+}
+
+#endif
@@ -0,0 +1,83 @@
+#ifndef SHADED_SHADING_FRAG
+#define SHADED_SHADING_FRAG
+
+#include "math.frag"
+
+#include "environment.frag"
+
+layout(set = 1, binding = 0) uniform Material {
+    UvOffset uv_offset;
+    float alpha_cutoff;
+    bool unlit;
+    int lightmap_mode;
+    bool skip_dynamic_lights;
+};
+
+layout(set = 1, binding = 1) uniform sampler2D albedo;
+layout(set = 1, binding = 2) uniform sampler2D emission;
+
+#ifdef LIGHTMAP
+#include "lightmap.frag"
+
+layout(set = 1, binding = 3) uniform sampler2D lightmap;
+#endif
+
+layout(location = 0) in VertexData {
+    vec3 position;
+    vec3 normal;
+    vec2 tex_coord;
+    vec4 color;
+#ifdef LIGHTMAP
+    vec2 lightmap_tex_coord;
+#endif
+} vertex;
+
+/// Shades the fragment, discarding it below the alpha cutoff.
+vec4 shade() {
+    vec2 final_tex_coords   = tex_coords(vertex.tex_coord, uv_offset);
+    vec4 albedo_alpha       = texture(albedo, final_tex_coords);
+    float alpha             = albedo_alpha.a;
+    if(alpha < alpha_cutoff) discard;
+
+    if(unlit) {
+        return albedo_alpha * vertex.color;
+    }
+
+    vec3 albedo = albedo_alpha.rgb;
+    vec3 emission = texture(emission, final_tex_coords).rgb;
+
+    vec3 lighting = vec3(0.0);
+    vec3 normal = normalize(vertex.normal);
+    uint point_lights = skip_dynamic_lights ? 0u : uint(point_light_count);
+    uint directional_lights = skip_dynamic_lights ? 0u : uint(directional_light_count);
+    for (uint i = 0u; i < point_lights; i++) {
+        // Calculate diffuse light
+        vec3 light_dir = normalize(plight[i].position - vertex.position);
+        float diff = max(dot(light_dir, normal), 0.0);
+        vec3 diffuse = diff * normalize(plight[i].color);
+        // Calculate attenuation
+        vec3 dist = plight[i].position - vertex.position;
+        float dist2 = dot(dist, dist);
+        float attenuation = point_attenuation(plight[i], vertex.position, plight[i].intensity / dist2)
+            * point_shadow(plight[i], vertex.position);
+        lighting += diffuse * attenuation;
+    }
+    for (uint i = 0u; i < directional_lights; i++) {
+        vec3 dir = dlight[i].direction;
+        float diff = max(dot(-dir, normal), 0.0);
+        vec3 diffuse = diff * dlight[i].color;
+        lighting += diffuse * dlight[i].intensity;
+    }
+    lighting += ambient_light(normal);
+#ifdef LIGHTMAP
+    vec3 baked = texture(lightmap, vertex.lightmap_tex_coord).rgb;
+    if (lightmap_mode == LIGHTMAP_MULTIPLY) {
+        lighting *= baked;
+    } else if (lightmap_mode == LIGHTMAP_ADD) {
+        lighting += baked;
+    }
+#endif
+    return vec4(lighting * albedo + emission, alpha) * vertex.color;
+}
+
+#endif
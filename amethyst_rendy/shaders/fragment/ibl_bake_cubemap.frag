@@ -0,0 +1,9 @@
+#version 450
+
+layout(set = 1, binding = 0) uniform samplerCube cubemap;
+
+vec3 environment(vec3 direction) {
+    return textureLod(cubemap, direction, 0.0).rgb;
+}
+
+#include "header/ibl_bake.frag"
@@ -0,0 +1,12 @@
+#version 450
+
+layout(std140, set = 0, binding = 0) uniform Gradient {
+    vec3 nadir_color;
+    vec3 zenith_color;
+};
+
+vec3 environment(vec3 direction) {
+    return mix(nadir_color, zenith_color, smoothstep(-1., 1., normalize(direction).y));
+}
+
+#include "header/ibl_bake.frag"
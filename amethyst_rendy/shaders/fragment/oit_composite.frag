@@ -0,0 +1,21 @@
+#version 450
+
+// Resolves the weighted blended transparency targets over the opaque image.
+// Keep in sync with amethyst_rendy/src/pass/oit.rs
+
+layout(set = 0, binding = 0) uniform sampler2D accum;
+layout(set = 0, binding = 1) uniform sampler2D revealage;
+
+layout(location = 0) in vec2 tex_coord;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    float revealage = texture(revealage, tex_coord).r;
+    if (revealage >= 1.0) discard;
+
+    vec4 accum = texture(accum, tex_coord);
+    vec3 average = accum.rgb / max(accum.a, 1e-5);
+    // Premultiplied, blended with the opaque image by the coverage `1 - revealage`.
+    out_color = vec4(average * (1.0 - revealage), 1.0 - revealage);
+}
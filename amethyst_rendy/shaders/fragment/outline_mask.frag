@@ -0,0 +1,13 @@
+#version 450
+
+layout(location = 0) in vec4 in_color;
+layout(location = 1) flat in vec2 in_outline;
+
+layout(location = 0) out vec4 out_color;
+layout(location = 1) out vec4 out_data;
+
+void main() {
+    out_color = in_color;
+    // The depth tests the outline against the scene in `outline.frag`
+    out_data = vec4(gl_FragCoord.z, in_outline, 1.0);
+}
@@ -0,0 +1,13 @@
+#version 450
+
+#include "header/outline.frag"
+
+void main() {
+    vec4 color;
+    vec4 data;
+    if (!find_outline(color, data) || data.z <= 0.0) {
+        discard;
+    }
+    gl_FragDepth = data.x;
+    out_color = vec4(color.rgb, color.a * data.z);
+}
@@ -0,0 +1,12 @@
+#version 450
+
+#define IBL
+#include "header/pbr_shading.frag"
+#include "header/clip.frag"
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    clip_discard(vertex.position);
+    out_color = shade();
+}
@@ -0,0 +1,11 @@
+#version 450
+
+#include "header/pbr_shading.frag"
+#include "header/oit.frag"
+
+layout(location = 0) out vec4 out_accum;
+layout(location = 1) out float out_revealage;
+
+void main() {
+    write_oit(shade(), out_accum, out_revealage);
+}
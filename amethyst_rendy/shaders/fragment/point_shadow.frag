@@ -0,0 +1,10 @@
+#version 450
+
+layout(location = 0) in vec3 view_position;
+layout(location = 1) flat in float far;
+
+layout(location = 0) out float distance;
+
+void main() {
+    distance = length(view_position) / far;
+}
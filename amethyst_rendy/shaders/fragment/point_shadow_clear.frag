@@ -0,0 +1,10 @@
+#version 450
+
+// Resets a face of a point light shadow cube map, nothing occludes the light.
+
+layout(location = 0) out float distance;
+
+void main() {
+    distance = 1e30;
+    gl_FragDepth = 1.0;
+}
@@ -0,0 +1,17 @@
+#version 450
+
+// Fades the target to a color.
+// Keep in sync with amethyst_rendy/src/pass/screen_fade.rs
+
+layout(std140, set = 0, binding = 0) uniform ScreenFadeArgs {
+    vec4 color;
+};
+
+layout(location = 0) in vec2 tex_coord;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    if (color.a <= 0.0) discard;
+    out_color = color;
+}
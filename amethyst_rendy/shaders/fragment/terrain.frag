@@ -0,0 +1,47 @@
+#version 450
+
+#include "header/environment.frag"
+
+// Unused layers hold a placeholder texture.
+layout(set = 1, binding = 1) uniform sampler2D splat;
+layout(set = 1, binding = 2) uniform sampler2D layer_0;
+layout(set = 1, binding = 3) uniform sampler2D layer_1;
+layout(set = 1, binding = 4) uniform sampler2D layer_2;
+layout(set = 1, binding = 5) uniform sampler2D layer_3;
+
+layout(location = 0) in vec3 in_position;
+layout(location = 1) in vec3 in_normal;
+layout(location = 2) in vec2 in_uv;
+layout(location = 3) in vec2 in_local;
+layout(location = 4) flat in vec4 tiling;
+layout(location = 5) flat in uint layer_count;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    vec4 weights = layer_count > 1u ? texture(splat, in_uv) : vec4(1.0, 0.0, 0.0, 0.0);
+    weights *= vec4(greaterThan(uvec4(layer_count), uvec4(0u, 1u, 2u, 3u)));
+    float total = dot(weights, vec4(1.0));
+    weights = total > 0.0 ? weights / total : vec4(1.0, 0.0, 0.0, 0.0);
+
+    vec3 albedo = texture(layer_0, in_local / tiling.x).rgb * weights.x
+        + texture(layer_1, in_local / tiling.y).rgb * weights.y
+        + texture(layer_2, in_local / tiling.z).rgb * weights.z
+        + texture(layer_3, in_local / tiling.w).rgb * weights.w;
+
+    vec3 normal = normalize(in_normal);
+    vec3 lighting = ambient_light(normal);
+    for (int i = 0; i < point_light_count; i++) {
+        vec3 dist = plight[i].position - in_position;
+        float diff = max(dot(normalize(dist), normal), 0.0);
+        float attenuation = point_attenuation(plight[i], in_position, plight[i].intensity / dot(dist, dist))
+            * point_shadow(plight[i], in_position);
+        lighting += diff * plight[i].color * attenuation;
+    }
+    for (int i = 0; i < directional_light_count; i++) {
+        float diff = max(dot(-dlight[i].direction, normal), 0.0);
+        lighting += diff * dlight[i].color * dlight[i].intensity;
+    }
+
+    out_color = vec4(albedo * lighting, 1.0);
+}
@@ -0,0 +1,58 @@
+#version 450
+
+#include "header/environment.frag"
+
+layout(set = 1, binding = 0) uniform sampler2D reflection;
+layout(set = 1, binding = 1) uniform sampler2D refraction;
+layout(set = 2, binding = 0) uniform sampler2D normal_map;
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec2 wave_coord;
+layout(location = 2) in vec4 clip;
+layout(location = 3) flat in mat3 axes;
+layout(location = 6) flat in vec4 color;
+layout(location = 7) flat in vec4 waves;
+layout(location = 8) flat in vec4 surface;
+
+layout(location = 0) out vec4 out_color;
+
+// Base reflectance of water seen head on.
+const float WATER_F0 = 0.02;
+
+void main() {
+    // Two samples of the normal map scrolling in different directions break up the repetition.
+    vec2 first = texture(normal_map, wave_coord).rg * 2.0 - 1.0;
+    vec2 second = texture(normal_map, wave_coord.yx * 0.7 - waves.yx * 1.6).rg * 2.0 - 1.0;
+    vec2 slope = (first + second) * 0.5 * waves.w;
+    vec3 normal = normalize(axes * vec3(slope.x, 1.0, slope.y));
+
+    vec3 to_camera = camera_position - position;
+    float surface_distance = length(to_camera);
+    vec3 view = to_camera / surface_distance;
+
+    // Both scene images are drawn from the point of view of the camera.
+    vec2 screen = clip.xy / clip.w * 0.5 + 0.5;
+    vec2 distorted = clamp(screen + slope * 0.05, 0.0, 1.0);
+    vec3 reflected = texture(reflection, distorted).rgb;
+
+    vec3 below = color.rgb;
+    float alpha = color.a;
+    if (surface.w > 0.5) {
+        vec4 refracted = texture(refraction, distorted);
+        // Distance travelled through the water, fading the scene into the color of the water.
+        float depth = max(refracted.a - surface_distance, 0.0);
+        below = mix(color.rgb, refracted.rgb, exp(-depth * surface.z));
+        alpha = 1.0;
+    }
+
+    float fresnel = WATER_F0 + (1.0 - WATER_F0) * pow(1.0 - max(dot(normal, view), 0.0), 5.0);
+    vec3 result = mix(below, reflected, fresnel);
+
+    for (int i = 0; i < directional_light_count; i++) {
+        vec3 mirrored = reflect(dlight[i].direction, normal);
+        float specular = pow(max(dot(mirrored, view), 0.0), 200.0);
+        result += specular * dlight[i].color * dlight[i].intensity;
+    }
+
+    out_color = vec4(result, mix(alpha, 1.0, fresnel));
+}
@@ -0,0 +1,59 @@
+#version 450
+
+#include "header/math.frag"
+#include "header/environment.frag"
+
+layout(std140, set = 1, binding = 0) uniform Material {
+    UvOffset uv_offset;
+    float alpha_cutoff;
+    bool unlit;
+};
+
+layout(set = 1, binding = 1) uniform sampler2D albedo;
+
+layout(std140, set = 2, binding = 0) uniform WaterView {
+    mat4 proj_view;
+    vec4 clip_plane;
+};
+
+layout(location = 0) in VertexData {
+    vec3 position;
+    vec3 normal;
+    vec2 tex_coord;
+    vec4 color;
+} vertex;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    // The reflection is clipped by its projection, the refraction keeps what is below the water.
+    if (dot(clip_plane, vec4(vertex.position, 1.0)) < 0.0) {
+        discard;
+    }
+
+    vec4 color = texture(albedo, tex_coords(vertex.tex_coord, uv_offset));
+    if (color.a < alpha_cutoff) {
+        discard;
+    }
+    color *= vertex.color;
+
+    // Simple diffuse lighting, the water distorts the details away.
+    vec3 lighting = vec3(1.0);
+    if (!unlit) {
+        vec3 normal = normalize(vertex.normal);
+        lighting = ambient_light(normal);
+        for (int i = 0; i < point_light_count; i++) {
+            vec3 dist = plight[i].position - vertex.position;
+            float diff = max(dot(normalize(dist), normal), 0.0);
+            float attenuation = point_attenuation(plight[i], vertex.position, plight[i].intensity / dot(dist, dist));
+            lighting += diff * plight[i].color * attenuation;
+        }
+        for (int i = 0; i < directional_light_count; i++) {
+            float diff = max(dot(-dlight[i].direction, normal), 0.0);
+            lighting += diff * dlight[i].color * dlight[i].intensity;
+        }
+    }
+
+    // The composite reads the distance to the camera to tint the water with depth.
+    out_color = vec4(color.rgb * lighting, distance(camera_position, vertex.position));
+}
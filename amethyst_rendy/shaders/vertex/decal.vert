@@ -0,0 +1,42 @@
+#version 450
+
+// Draws the box of a decal as a unit cube, without vertex buffers.
+
+layout(std140, set = 0, binding = 0) uniform ViewArgs {
+    uniform mat4 proj;
+    uniform mat4 view;
+    uniform mat4 proj_view;
+};
+
+layout(location = 0) in mat4 model; // instance rate
+layout(location = 4) in float opacity; // instance rate
+layout(location = 5) in uvec2 receivers; // instance rate
+
+layout(location = 0) flat out mat4 clip_to_world;
+layout(location = 4) flat out mat4 world_to_decal;
+layout(location = 8) flat out mat3 decal_axes;
+layout(location = 11) flat out float out_opacity;
+layout(location = 12) flat out uvec2 out_receivers;
+
+// Corner `i` of the cube is at x = i & 1, y = (i >> 1) & 1, z = (i >> 2) & 1.
+// Faces are counter-clockwise seen from outside of the cube.
+const int CUBE_INDICES[36] = int[](
+    5, 1, 3, 5, 3, 7,
+    0, 4, 6, 0, 6, 2,
+    6, 7, 3, 6, 3, 2,
+    0, 1, 5, 0, 5, 4,
+    4, 5, 7, 4, 7, 6,
+    1, 0, 2, 1, 2, 3
+);
+
+void main() {
+    int corner = CUBE_INDICES[gl_VertexIndex];
+    vec3 position = vec3(corner & 1, (corner >> 1) & 1, (corner >> 2) & 1) - 0.5;
+
+    clip_to_world = inverse(proj_view);
+    world_to_decal = inverse(model);
+    decal_axes = mat3(normalize(model[0].xyz), normalize(model[1].xyz), normalize(model[2].xyz));
+    out_opacity = opacity;
+    out_receivers = receivers;
+    gl_Position = proj_view * model * vec4(position, 1.0);
+}
@@ -0,0 +1,21 @@
+#version 450
+
+layout(std140, set = 0, binding = 0) uniform ViewArgs {
+    uniform mat4 proj;
+    uniform mat4 view;
+    uniform mat4 proj_view;
+};
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+layout(location = 2) in mat4 model; // instance rate
+layout(location = 6) in uvec2 receiver; // instance rate
+
+layout(location = 0) out vec3 out_normal;
+layout(location = 1) flat out uvec2 out_receiver;
+
+void main() {
+    out_normal = mat3(model) * normal;
+    out_receiver = receiver;
+    gl_Position = proj_view * model * vec4(position, 1.0);
+}
@@ -0,0 +1,29 @@
+#version 450
+
+#define SKINNING_SET 1
+#include "header/dual_quaternion_skinning.vert"
+
+layout(std140, set = 0, binding = 0) uniform ViewArgs {
+    uniform mat4 proj;
+    uniform mat4 view;
+    uniform mat4 proj_view;
+};
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+layout(location = 2) in uvec4 joint_ids;
+layout(location = 3) in vec4 joint_weights;
+layout(location = 4) in mat4 model; // instance rate
+layout(location = 8) in uvec2 receiver; // instance rate
+layout(location = 9) in uint joints_offset; // instance rate
+
+layout(location = 0) out vec3 out_normal;
+layout(location = 1) flat out uvec2 out_receiver;
+
+void main() {
+    SkinTransform skin = blend_joints(joints_offset, joint_ids, joint_weights);
+
+    out_normal = mat3(model) * skin_normal(skin, normal);
+    out_receiver = receiver;
+    gl_Position = proj_view * model * vec4(skin_position(skin, position), 1.0);
+}
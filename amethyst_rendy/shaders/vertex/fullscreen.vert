@@ -0,0 +1,10 @@
+#version 450
+
+// Draws a triangle covering the whole framebuffer, without vertex buffers.
+
+layout(location = 0) out vec2 tex_coord;
+
+void main() {
+    tex_coord = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    gl_Position = vec4(tex_coord * 2.0 - 1.0, 0.0, 1.0);
+}
@@ -0,0 +1,55 @@
+#ifndef DUAL_QUATERNION_SKINNING_VERT
+#define DUAL_QUATERNION_SKINNING_VERT
+
+#ifndef SKINNING_SET
+#define SKINNING_SET 2
+#endif
+
+// Each joint holds the real and dual parts of its rigid transform in the first two columns and
+// the scale applied before it in the third.
+layout(std430, set = SKINNING_SET, binding = 0) readonly buffer JointTransforms {
+    mat4 joints[];
+};
+
+struct SkinTransform {
+    vec4 real;
+    vec4 dual;
+    vec3 scale;
+};
+
+SkinTransform blend_joints(uint offset, uvec4 ids, vec4 weights) {
+    vec4 first = joints[int(offset + ids.x)][0];
+    SkinTransform skin = SkinTransform(vec4(0.0), vec4(0.0), vec3(0.0));
+    for (int i = 0; i < 4; i++) {
+        mat4 joint = joints[int(offset + ids[i])];
+        // Blend the rotations along the shortest path
+        float weight = dot(first, joint[0]) < 0.0 ? -weights[i] : weights[i];
+        skin.real += weight * joint[0];
+        skin.dual += weight * joint[1];
+        skin.scale += weights[i] * joint[2].xyz;
+    }
+    float len = length(skin.real);
+    skin.real /= len;
+    skin.dual /= len;
+    return skin;
+}
+
+vec3 rotate(vec4 q, vec3 v) {
+    return v + 2.0 * cross(q.xyz, cross(q.xyz, v) + q.w * v);
+}
+
+vec3 skin_position(SkinTransform skin, vec3 position) {
+    vec3 translation = 2.0 * (skin.real.w * skin.dual.xyz - skin.dual.w * skin.real.xyz +
+        cross(skin.real.xyz, skin.dual.xyz));
+    return rotate(skin.real, position * skin.scale) + translation;
+}
+
+vec3 skin_direction(SkinTransform skin, vec3 direction) {
+    return rotate(skin.real, direction * skin.scale);
+}
+
+vec3 skin_normal(SkinTransform skin, vec3 normal) {
+    return rotate(skin.real, normal / skin.scale);
+}
+
+#endif
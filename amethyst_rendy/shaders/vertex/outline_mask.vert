@@ -0,0 +1,21 @@
+#version 450
+
+layout(std140, set = 0, binding = 0) uniform ViewArgs {
+    uniform mat4 proj;
+    uniform mat4 view;
+    uniform mat4 proj_view;
+};
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in mat4 model; // instance rate
+layout(location = 5) in vec4 outline_color; // instance rate
+layout(location = 6) in vec2 outline; // instance rate
+
+layout(location = 0) out vec4 out_color;
+layout(location = 1) flat out vec2 out_outline;
+
+void main() {
+    out_color = outline_color;
+    out_outline = outline;
+    gl_Position = proj_view * model * vec4(position, 1.0);
+}
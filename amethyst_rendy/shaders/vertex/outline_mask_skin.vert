@@ -0,0 +1,34 @@
+#version 450
+
+layout(std140, set = 0, binding = 0) uniform ViewArgs {
+    uniform mat4 proj;
+    uniform mat4 view;
+    uniform mat4 proj_view;
+};
+
+layout(std430, set = 1, binding = 0) readonly buffer JointTransforms {
+    mat4 joints[];
+};
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in uvec4 joint_ids;
+layout(location = 2) in vec4 joint_weights;
+layout(location = 3) in mat4 model; // instance rate
+layout(location = 7) in vec4 outline_color; // instance rate
+layout(location = 8) in vec2 outline; // instance rate
+layout(location = 9) in uint joints_offset; // instance rate
+
+layout(location = 0) out vec4 out_color;
+layout(location = 1) flat out vec2 out_outline;
+
+void main() {
+    mat4 joint_transform =
+        joint_weights.x * joints[int(joints_offset + joint_ids.x)] +
+        joint_weights.y * joints[int(joints_offset + joint_ids.y)] +
+        joint_weights.z * joints[int(joints_offset + joint_ids.z)] +
+        joint_weights.w * joints[int(joints_offset + joint_ids.w)];
+
+    out_color = outline_color;
+    out_outline = outline;
+    gl_Position = proj_view * model * joint_transform * vec4(position, 1.0);
+}
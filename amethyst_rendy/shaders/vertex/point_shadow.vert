@@ -0,0 +1,19 @@
+#version 450
+
+// Draws a mesh into a face of a point light shadow cube map.
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in mat4 model_view; // instance rate
+layout(location = 5) in vec2 depth_range; // instance rate
+
+layout(location = 0) out vec3 view_position;
+layout(location = 1) flat out float far;
+
+void main() {
+    vec4 view = model_view * vec4(position, 1.0);
+    view_position = view.xyz;
+    far = depth_range.y;
+    // Square face with a field of view of 90 degrees, looking down -z.
+    float near = depth_range.x;
+    gl_Position = vec4(view.xy, (far * view.z + near * far) / (near - far), -view.z);
+}
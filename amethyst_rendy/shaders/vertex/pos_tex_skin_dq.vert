@@ -0,0 +1,33 @@
+#version 450
+
+#include "header/dual_quaternion_skinning.vert"
+
+layout(std140, set = 0, binding = 0) uniform ViewArgs {
+    uniform mat4 proj;
+    uniform mat4 view;
+    uniform mat4 proj_view;
+};
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec2 tex_coord;
+layout(location = 2) in uvec4 joint_ids;
+layout(location = 3) in vec4 joint_weights;
+layout(location = 4) in mat4 model; // instance rate
+layout(location = 8) in vec4 tint; // instance rate
+layout(location = 9) in uint joints_offset; // instance rate
+
+layout(location = 0) out VertexData {
+    vec3 position;
+    vec2 tex_coord;
+    vec4 color;
+} vertex;
+
+void main() {
+    SkinTransform skin = blend_joints(joints_offset, joint_ids, joint_weights);
+
+    vec4 vertex_position = model * vec4(skin_position(skin, position), 1.0);
+    vertex.position = vertex_position.xyz;
+    vertex.tex_coord = tex_coord;
+    vertex.color = tint;
+    gl_Position = proj_view * vertex_position;
+}
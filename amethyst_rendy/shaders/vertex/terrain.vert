@@ -0,0 +1,61 @@
+#version 450
+
+layout(std140, set = 0, binding = 0) uniform ViewArgs {
+    uniform mat4 proj;
+    uniform mat4 view;
+    uniform mat4 proj_view;
+};
+
+layout(set = 1, binding = 0) uniform sampler2D heightmap;
+
+// Grid coordinates of the vertex in the chunk along x and z, y is 1 on skirts.
+layout(location = 0) in vec3 position;
+layout(location = 1) in mat4 model; // instance rate
+layout(location = 5) in vec3 chunk; // instance rate
+layout(location = 6) in vec4 terrain; // instance rate
+layout(location = 7) in vec4 tiling; // instance rate
+layout(location = 8) in uint layer_count; // instance rate
+
+layout(location = 0) out vec3 out_position;
+layout(location = 1) out vec3 out_normal;
+layout(location = 2) out vec2 out_uv;
+layout(location = 3) out vec2 out_local;
+layout(location = 4) flat out vec4 out_tiling;
+layout(location = 5) flat out uint out_layer_count;
+
+// Interpolates the four closest samples, like `Heightmap::sample`.
+float height(vec2 uv) {
+    ivec2 size = textureSize(heightmap, 0);
+    vec2 texel = clamp(uv, 0.0, 1.0) * vec2(size - 1);
+    ivec2 base = min(ivec2(floor(texel)), size - 2);
+    vec2 f = texel - vec2(base);
+    float h00 = texelFetch(heightmap, base, 0).r;
+    float h10 = texelFetch(heightmap, base + ivec2(1, 0), 0).r;
+    float h01 = texelFetch(heightmap, base + ivec2(0, 1), 0).r;
+    float h11 = texelFetch(heightmap, base + ivec2(1, 1), 0).r;
+    return mix(mix(h00, h10, f.x), mix(h01, h11, f.x), f.y);
+}
+
+void main() {
+    vec2 size = terrain.xy;
+    float max_height = terrain.z;
+    vec2 uv = chunk.xy + position.xz * chunk.z;
+    vec2 texel = 1.0 / vec2(textureSize(heightmap, 0) - 1);
+
+    float h = height(uv) * max_height - position.y * terrain.w;
+    vec3 local = vec3((uv.x - 0.5) * size.x, h, (uv.y - 0.5) * size.y);
+
+    vec2 du = vec2(texel.x, 0.0);
+    vec2 dv = vec2(0.0, texel.y);
+    float dx = (height(uv + du) - height(uv - du)) * max_height / (2.0 * texel.x * size.x);
+    float dz = (height(uv + dv) - height(uv - dv)) * max_height / (2.0 * texel.y * size.y);
+
+    vec4 world = model * vec4(local, 1.0);
+    out_position = world.xyz;
+    out_normal = mat3(model) * vec3(-dx, 1.0, -dz);
+    out_uv = uv;
+    out_local = local.xz;
+    out_tiling = tiling;
+    out_layer_count = layer_count;
+    gl_Position = proj_view * world;
+}
@@ -0,0 +1,39 @@
+#version 450
+
+layout(std140, set = 0, binding = 0) uniform ViewArgs {
+    uniform mat4 proj;
+    uniform mat4 view;
+    uniform mat4 proj_view;
+};
+
+layout(location = 0) in mat4 model; // instance rate
+layout(location = 4) in vec4 color; // instance rate
+layout(location = 5) in vec4 waves; // instance rate
+layout(location = 6) in vec4 surface; // instance rate
+
+layout(location = 0) out vec3 out_position;
+layout(location = 1) out vec2 out_wave_coord;
+layout(location = 2) out vec4 out_clip;
+layout(location = 3) flat out mat3 out_axes;
+layout(location = 6) flat out vec4 out_color;
+layout(location = 7) flat out vec4 out_waves;
+layout(location = 8) flat out vec4 out_surface;
+
+// Two triangles covering the plane, counter-clockwise seen from above.
+const vec2 QUAD[6] = vec2[](
+    vec2(-1.0, -1.0), vec2(-1.0, 1.0), vec2(1.0, -1.0),
+    vec2(1.0, -1.0), vec2(-1.0, 1.0), vec2(1.0, 1.0)
+);
+
+void main() {
+    vec2 local = QUAD[gl_VertexIndex] * surface.xy;
+    vec4 position = model * vec4(local.x, 0.0, local.y, 1.0);
+    out_position = position.xyz;
+    out_wave_coord = local / waves.z + waves.xy;
+    out_axes = mat3(normalize(model[0].xyz), normalize(model[1].xyz), normalize(model[2].xyz));
+    out_color = color;
+    out_waves = waves;
+    out_surface = surface;
+    out_clip = proj_view * position;
+    gl_Position = out_clip;
+}
@@ -0,0 +1,474 @@
+//! Runtime packing of many small images into shared atlas textures, so that they are drawn in
+//! few batches.
+use crate::{
+    dynamic_texture::{DynamicTexture, DynamicTextureError, TextureRegion},
+    sprite::{Sprite, SpriteRender, SpriteSheet},
+    types::{Texture, TextureData},
+};
+use amethyst_assets::{AssetStorage, Handle, Loader};
+use amethyst_core::ecs::prelude::{Component, DenseVecStorage};
+use rendy::{
+    hal::{
+        format::Format,
+        image::{Filter, Kind, SamplerInfo, ViewKind, WrapMode},
+    },
+    texture::TextureBuilder,
+};
+
+/// Width and height of the pages of a `TextureAtlas` by default.
+pub const DEFAULT_ATLAS_SIZE: u32 = 2048;
+
+/// Bytes of a texel of an `AtlasImage`.
+const TEXEL_SIZE: usize = 4;
+
+/// An image to pack in a `TextureAtlas`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AtlasImage {
+    /// Width of the image in pixels.
+    pub width: u32,
+    /// Height of the image in pixels.
+    pub height: u32,
+    /// Tightly packed rows of RGBA8 sRGB pixels, from the top left corner.
+    pub pixels: Vec<u8>,
+}
+
+impl AtlasImage {
+    /// Creates an image from RGBA8 sRGB pixels, e.g. decoded with the `image` crate.
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        AtlasImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Checks the image isn't empty and holds enough pixels, returning the bytes of its pixels.
+    fn validate(&self) -> Result<usize, DynamicTextureError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(DynamicTextureError::RegionOutOfBounds {
+                region: TextureRegion::new(0, 0, self.width, self.height),
+                size: (self.width, self.height),
+            });
+        }
+        let expected = self.width as usize * self.height as usize * TEXEL_SIZE;
+        if self.pixels.len() < expected {
+            return Err(DynamicTextureError::DataTooShort {
+                expected,
+                actual: self.pixels.len(),
+            });
+        }
+        Ok(expected)
+    }
+}
+
+/// Span of the skyline, the top of the area used by the packed rectangles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Segment {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+/// Packs rectangles in an area with the skyline bottom left heuristic.
+///
+/// The area is filled from the top, placing each rectangle where its bottom edge ends up the
+/// highest. Rectangles are packed as they come and never moved, so more can be packed later in
+/// the space left.
+#[derive(Clone, Debug)]
+pub struct SkylinePacker {
+    width: u32,
+    height: u32,
+    skyline: Vec<Segment>,
+}
+
+impl SkylinePacker {
+    /// Creates a packer for an empty area.
+    pub fn new(width: u32, height: u32) -> Self {
+        SkylinePacker {
+            width,
+            height,
+            skyline: vec![Segment { x: 0, y: 0, width }],
+        }
+    }
+
+    /// Size of the area.
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Packs a rectangle, returning the position of its top left corner, or `None` if it doesn't
+    /// fit in the space left.
+    pub fn pack(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let mut best: Option<(usize, u32)> = None;
+        for index in 0..self.skyline.len() {
+            if let Some(y) = self.fit(index, width, height) {
+                let better = best.map_or(true, |(best_index, best_y)| {
+                    (y, self.skyline[index].x) < (best_y, self.skyline[best_index].x)
+                });
+                if better {
+                    best = Some((index, y));
+                }
+            }
+        }
+
+        let (index, y) = best?;
+        let x = self.skyline[index].x;
+        self.raise(
+            index,
+            Segment {
+                x,
+                y: y + height,
+                width,
+            },
+        );
+        Some((x, y))
+    }
+
+    /// Returns the top of a rectangle placed at the start of the segment `index`, if it fits.
+    fn fit(&self, index: usize, width: u32, height: u32) -> Option<u32> {
+        let x = self.skyline[index].x;
+        if x + width > self.width {
+            return None;
+        }
+        let mut y = 0;
+        let mut remaining = width;
+        for segment in &self.skyline[index..] {
+            y = y.max(segment.y);
+            if y + height > self.height {
+                return None;
+            }
+            if segment.width >= remaining {
+                return Some(y);
+            }
+            remaining -= segment.width;
+        }
+        None
+    }
+
+    /// Inserts `segment` at `index`, covering the segments under it.
+    fn raise(&mut self, index: usize, segment: Segment) {
+        let end = segment.x + segment.width;
+        self.skyline.insert(index, segment);
+
+        let next = index + 1;
+        while next < self.skyline.len() {
+            let covered = &mut self.skyline[next];
+            let covered_end = covered.x + covered.width;
+            if covered_end <= end {
+                self.skyline.remove(next);
+            } else {
+                covered.width = covered_end.saturating_sub(end.max(covered.x));
+                covered.x = covered.x.max(end);
+                break;
+            }
+        }
+
+        // Merge the neighbouring segments of the same height
+        self.skyline.dedup_by(|segment, previous| {
+            if segment.y == previous.y {
+                previous.width += segment.width;
+                true
+            } else {
+                false
+            }
+        });
+    }
+}
+
+/// A page of a `TextureAtlas`.
+#[derive(Debug)]
+struct AtlasPage {
+    packer: SkylinePacker,
+    texture: DynamicTexture,
+    sprite_sheet: Handle<SpriteSheet>,
+}
+
+/// Packs images loaded at runtime, like icons, into pages of shared textures.
+///
+/// Each page is a `DynamicTexture` with a `SpriteSheet` holding the sprites packed in it, so the
+/// returned `SpriteRender`s are drawn by the sprite passes and `UiImage::Sprite` like any other.
+/// Sprites are never moved, and images inserted later are packed in the space left, opening new
+/// pages when full. Images larger than a page are loaded as standalone textures.
+///
+/// Textures which are already loaded can't be packed, as their pixels are only on the GPU.
+///
+/// The atlas is a component, whose pages are uploaded by the `DynamicTextureSystem`.
+#[derive(Debug)]
+pub struct TextureAtlas {
+    size: u32,
+    padding: u32,
+    sampler_info: SamplerInfo,
+    pages: Vec<AtlasPage>,
+}
+
+impl Default for TextureAtlas {
+    fn default() -> Self {
+        TextureAtlas {
+            size: DEFAULT_ATLAS_SIZE,
+            padding: 1,
+            sampler_info: SamplerInfo::new(Filter::Linear, WrapMode::Clamp),
+            pages: Vec::new(),
+        }
+    }
+}
+
+impl TextureAtlas {
+    /// Creates an empty atlas with pages of `DEFAULT_ATLAS_SIZE` pixels, one pixel of padding
+    /// and linear filtering.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the width and height of the pages, for atlases without pages yet.
+    pub fn with_size(mut self, size: u32) -> Self {
+        debug_assert!(self.pages.is_empty());
+        self.size = size;
+        self
+    }
+
+    /// Sets the transparent pixels left between images, so that filtering doesn't bleed the
+    /// neighbouring images, for atlases without pages yet.
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        debug_assert!(self.pages.is_empty());
+        self.padding = padding;
+        self
+    }
+
+    /// Sets the sampler of the pages, for atlases without pages yet.
+    pub fn with_sampler_info(mut self, sampler_info: SamplerInfo) -> Self {
+        debug_assert!(self.pages.is_empty());
+        self.sampler_info = sampler_info;
+        self
+    }
+
+    /// Width and height of the pages.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Sprite sheets of the pages, holding the images in the order they were packed.
+    pub fn sprite_sheets(&self) -> impl Iterator<Item = &Handle<SpriteSheet>> {
+        self.pages.iter().map(|page| &page.sprite_sheet)
+    }
+
+    /// Packs `image`, returning the sprite to draw it with.
+    pub fn insert(
+        &mut self,
+        image: &AtlasImage,
+        loader: &Loader,
+        textures: &AssetStorage<Texture>,
+        sprite_sheets: &mut AssetStorage<SpriteSheet>,
+    ) -> Result<SpriteRender, DynamicTextureError> {
+        let bytes = image.validate()?;
+        if image.width > self.size || image.height > self.size {
+            log::warn!(
+                "Image of {}x{} pixels is larger than the {}x{} texture atlas, loading it as a standalone texture",
+                image.width,
+                image.height,
+                self.size,
+                self.size
+            );
+            return Ok(self.standalone(image, bytes, loader, textures, sprite_sheets));
+        }
+
+        // The padding of the images on the right and bottom edges may lie out of the page
+        let (width, height) = (image.width + self.padding, image.height + self.padding);
+        let found = self
+            .pages
+            .iter_mut()
+            .enumerate()
+            .find_map(|(index, page)| page.packer.pack(width, height).map(|pos| (index, pos)));
+        let (index, (x, y)) = match found {
+            Some(found) => found,
+            None => {
+                let mut page = self.new_page(loader, textures, sprite_sheets)?;
+                let pos = page
+                    .packer
+                    .pack(width, height)
+                    .expect("Image doesn't fit in an empty page");
+                self.pages.push(page);
+                (self.pages.len() - 1, pos)
+            }
+        };
+
+        let page = &mut self.pages[index];
+        page.texture.update(
+            TextureRegion::new(x, y, image.width, image.height),
+            &image.pixels[..bytes],
+        )?;
+        let sheet = sprite_sheets
+            .get_mut(&page.sprite_sheet)
+            .expect("Sprite sheet of an atlas page was unloaded");
+        sheet.sprites.push(Sprite::from_pixel_values(
+            self.size,
+            self.size,
+            image.width,
+            image.height,
+            x,
+            y,
+            [0.0; 2],
+            false,
+            false,
+        ));
+        Ok(SpriteRender {
+            sprite_sheet: page.sprite_sheet.clone(),
+            sprite_number: sheet.sprites.len() - 1,
+        })
+    }
+
+    /// Packs `images` from the tallest down, which wastes less space than packing them in any
+    /// order. Returns the sprites in the order of `images`.
+    pub fn insert_all(
+        &mut self,
+        images: &[AtlasImage],
+        loader: &Loader,
+        textures: &AssetStorage<Texture>,
+        sprite_sheets: &mut AssetStorage<SpriteSheet>,
+    ) -> Result<Vec<SpriteRender>, DynamicTextureError> {
+        let mut order: Vec<usize> = (0..images.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse((images[i].height, images[i].width)));
+
+        let mut sprites = vec![None; images.len()];
+        for i in order {
+            sprites[i] = Some(self.insert(&images[i], loader, textures, sprite_sheets)?);
+        }
+        Ok(sprites.into_iter().map(Option::unwrap).collect())
+    }
+
+    pub(crate) fn textures_mut(&mut self) -> impl Iterator<Item = &mut DynamicTexture> {
+        self.pages.iter_mut().map(|page| &mut page.texture)
+    }
+
+    fn new_page(
+        &self,
+        loader: &Loader,
+        textures: &AssetStorage<Texture>,
+        sprite_sheets: &mut AssetStorage<SpriteSheet>,
+    ) -> Result<AtlasPage, DynamicTextureError> {
+        let texture = DynamicTexture::new(
+            self.size,
+            self.size,
+            Format::Rgba8Srgb,
+            self.sampler_info.clone(),
+            loader,
+            textures,
+        )?;
+        let sprite_sheet = sprite_sheets.insert(SpriteSheet {
+            texture: texture.texture().clone(),
+            sprites: Vec::new(),
+        });
+        Ok(AtlasPage {
+            packer: SkylinePacker::new(self.size + self.padding, self.size + self.padding),
+            texture,
+            sprite_sheet,
+        })
+    }
+
+    fn standalone(
+        &self,
+        image: &AtlasImage,
+        bytes: usize,
+        loader: &Loader,
+        textures: &AssetStorage<Texture>,
+        sprite_sheets: &mut AssetStorage<SpriteSheet>,
+    ) -> SpriteRender {
+        let builder = TextureBuilder::new()
+            .with_kind(Kind::D2(image.width, image.height, 1, 1))
+            .with_view_kind(ViewKind::D2)
+            .with_data_width(image.width)
+            .with_data_height(image.height)
+            .with_sampler_info(self.sampler_info.clone())
+            .with_raw_data(image.pixels[..bytes].to_vec(), Format::Rgba8Srgb);
+        let texture = loader.load_from_data(TextureData(builder), (), textures);
+        let sprite = Sprite::from_pixel_values(
+            image.width,
+            image.height,
+            image.width,
+            image.height,
+            0,
+            0,
+            [0.0; 2],
+            false,
+            false,
+        );
+        SpriteRender {
+            sprite_sheet: sprite_sheets.insert(SpriteSheet {
+                texture,
+                sprites: vec![sprite],
+            }),
+            sprite_number: 0,
+        }
+    }
+}
+
+impl Component for TextureAtlas {
+    type Storage = DenseVecStorage<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlap(a: (u32, u32, u32, u32), b: (u32, u32, u32, u32)) -> bool {
+        a.0 < b.0 + b.2 && b.0 < a.0 + a.2 && a.1 < b.1 + b.3 && b.1 < a.1 + a.3
+    }
+
+    #[test]
+    fn packs_rectangles_without_overlap() {
+        let mut packer = SkylinePacker::new(256, 256);
+        let sizes = [
+            (100, 40),
+            (60, 80),
+            (30, 30),
+            (120, 20),
+            (50, 50),
+            (90, 70),
+            (16, 16),
+        ];
+        let mut packed = Vec::new();
+        for &(width, height) in &sizes {
+            let (x, y) = packer.pack(width, height).unwrap();
+            assert!(x + width <= 256 && y + height <= 256);
+            let rect = (x, y, width, height);
+            assert!(packed.iter().all(|&other| !overlap(rect, other)));
+            packed.push(rect);
+        }
+    }
+
+    #[test]
+    fn fills_the_area_then_fails() {
+        let mut packer = SkylinePacker::new(2048, 2048);
+        let mut positions: Vec<_> = (0..4).map(|_| packer.pack(1024, 1024).unwrap()).collect();
+        positions.sort();
+        assert_eq!(positions, vec![(0, 0), (0, 1024), (1024, 0), (1024, 1024)]);
+        assert_eq!(packer.pack(1, 1), None);
+    }
+
+    #[test]
+    fn packs_later_rectangles_in_the_space_left() {
+        let mut packer = SkylinePacker::new(64, 64);
+        assert_eq!(packer.pack(48, 32), Some((0, 0)));
+        // The space right of the first rectangle is filled before the bottom
+        assert_eq!(packer.pack(16, 32), Some((48, 0)));
+        assert_eq!(packer.pack(64, 16), Some((0, 32)));
+        assert_eq!(packer.pack(32, 16), Some((0, 48)));
+        assert_eq!(packer.pack(32, 16), Some((32, 48)));
+        assert_eq!(packer.pack(1, 1), None);
+    }
+
+    #[test]
+    fn rejects_images_without_enough_pixels() {
+        assert_eq!(AtlasImage::new(2, 2, vec![0; 16]).validate(), Ok(16));
+        assert_eq!(
+            AtlasImage::new(2, 2, vec![0; 12]).validate(),
+            Err(DynamicTextureError::DataTooShort {
+                expected: 16,
+                actual: 12
+            })
+        );
+        assert!(AtlasImage::new(0, 2, Vec::new()).validate().is_err());
+    }
+}
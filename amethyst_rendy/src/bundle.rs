@@ -1,17 +1,23 @@
 //! A home of [RenderingBundle] with it's rendering plugins system and all types directly related to it.
 
 use crate::{
+    gpu_memory::{image_bytes, GpuAllocation, GpuMemoryCategory, GpuMemoryStats, GpuMemorySystem},
+    morph::MorphSystem,
     mtl::Material,
     rendy::{
         factory::Factory,
         graph::{
+            present::PresentNode,
             render::{RenderGroupBuilder, RenderPassNodeBuilder, SubpassBuilder},
             GraphBuilder, ImageId, NodeId,
         },
         hal,
         wsi::Surface,
     },
-    system::{GraphCreator, MeshProcessorSystem, RenderingSystem, TextureProcessorSystem},
+    system::{
+        DynamicMeshSystem, DynamicTextureSystem, GraphCreator, HeadlessAssetProcessorSystem,
+        MeshProcessorSystem, RenderingSystem, TextureProcessorSystem,
+    },
     types::Backend,
     SpriteSheet,
 };
@@ -35,6 +41,7 @@ use std::collections::HashMap;
 #[derive(Debug)]
 pub struct RenderingBundle<B: Backend> {
     plugins: Vec<Box<dyn RenderPlugin<B>>>,
+    gpu_memory_budget: Option<u64>,
 }
 
 impl<B: Backend> RenderingBundle<B> {
@@ -43,9 +50,19 @@ impl<B: Backend> RenderingBundle<B> {
     pub fn new() -> Self {
         Self {
             plugins: Vec::new(),
+            gpu_memory_budget: None,
         }
     }
 
+    /// Set a soft budget for the GPU memory used by the renderer, in bytes.
+    ///
+    /// Exceeding it logs a warning naming the largest allocations and sends a
+    /// [`GpuMemoryEvent`](crate::gpu_memory::GpuMemoryEvent). See [`GpuMemoryStats`].
+    pub fn with_gpu_memory_budget(mut self, bytes: u64) -> Self {
+        self.gpu_memory_budget = Some(bytes);
+        self
+    }
+
     /// Register a [`RenderPlugin`].
     ///
     /// If you want the non-consuming version of this method, see [`add_plugin`].
@@ -72,18 +89,43 @@ impl<'a, 'b, B: Backend> SystemBundle<'a, 'b> for RenderingBundle<B> {
         world: &mut World,
         builder: &mut DispatcherBuilder<'a, 'b>,
     ) -> Result<(), Error> {
-        builder.add(MeshProcessorSystem::<B>::default(), "mesh_processor", &[]);
+        builder.add(MorphSystem, "morph_system", &[]);
+        builder.add(
+            MeshProcessorSystem::<B>::default(),
+            "mesh_processor",
+            &["morph_system"],
+        );
+        builder.add(
+            DynamicMeshSystem::<B>::default(),
+            "dynamic_mesh_system",
+            &["mesh_processor"],
+        );
         builder.add(
             TextureProcessorSystem::<B>::default(),
             "texture_processor",
             &[],
         );
+        builder.add(
+            DynamicTextureSystem::<B>::default(),
+            "dynamic_texture_system",
+            &["texture_processor"],
+        );
         builder.add(Processor::<Material>::new(), "material_processor", &[]);
         builder.add(
             Processor::<SpriteSheet>::new(),
             "sprite_sheet_processor",
             &[],
         );
+        builder.add(Processor::<Heightmap>::new(), "heightmap_processor", &[]);
+
+        let mut memory = GpuMemoryStats::default();
+        memory.set_budget(self.gpu_memory_budget);
+        world.insert(memory);
+        builder.add(
+            GpuMemorySystem::<B>::default(),
+            "gpu_memory_system",
+            &["dynamic_mesh_system", "dynamic_texture_system"],
+        );
 
         // make sure that all renderer-specific systems run after game code
         builder.add_barrier();
@@ -97,6 +139,35 @@ impl<'a, 'b, B: Backend> SystemBundle<'a, 'b> for RenderingBundle<B> {
     }
 }
 
+/// A replacement for [RenderingBundle] in applications running without a window or a GPU.
+///
+/// Processes the renderer's asset types so that loading them still completes, see
+/// [HeadlessAssetProcessorSystem], but never creates a render graph.
+#[derive(Debug, Default)]
+pub struct HeadlessRenderingBundle;
+
+impl<'a, 'b> SystemBundle<'a, 'b> for HeadlessRenderingBundle {
+    fn build(
+        self,
+        _world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        builder.add(
+            HeadlessAssetProcessorSystem,
+            "headless_asset_processor",
+            &[],
+        );
+        builder.add(Processor::<Material>::new(), "material_processor", &[]);
+        builder.add(
+            Processor::<SpriteSheet>::new(),
+            "sprite_sheet_processor",
+            &[],
+        );
+        builder.add(Processor::<Heightmap>::new(), "heightmap_processor", &[]);
+        Ok(())
+    }
+}
+
 struct PluggableRenderGraphCreator<B: Backend> {
     plugins: Vec<Box<dyn RenderPlugin<B>>>,
 }
@@ -119,7 +190,11 @@ impl<B: Backend> GraphCreator<B> for PluggableRenderGraphCreator<B> {
         for plugin in self.plugins.iter_mut() {
             plugin.on_plan(&mut plan, factory, world).unwrap();
         }
-        plan.build(factory).unwrap()
+        let (graph, render_targets) = plan.build(factory).unwrap();
+        if let Some(mut memory) = world.try_fetch_mut::<GpuMemoryStats>() {
+            memory.set_render_targets(render_targets);
+        }
+        graph
     }
 }
 
@@ -206,7 +281,20 @@ impl<B: Backend> RenderPlan<B> {
         target_plan.add_extension(Box::new(closure));
     }
 
-    fn build(self, factory: &Factory<B>) -> Result<GraphBuilder<B, World>, Error> {
+    /// Retrieve the metadata, e.g. size, of a render target whose outputs were already defined
+    /// by a previous plugin. Useful to create images matching the size of another target.
+    pub fn target_metadata(&self, target: Target, factory: &Factory<B>) -> Option<TargetMetadata> {
+        self.targets
+            .get(&target)
+            // safety: surfaces of the plan are created by the same factory
+            .and_then(|t| unsafe { t.metadata(factory.physical()) })
+    }
+
+    /// Builds the graph, also returning the allocations of the images it creates.
+    fn build(
+        self,
+        factory: &Factory<B>,
+    ) -> Result<(GraphBuilder<B, World>, Vec<GpuAllocation>), Error> {
         let mut ctx = PlanContext {
             target_metadata: self
                 .targets
@@ -216,6 +304,8 @@ impl<B: Backend> RenderPlan<B> {
             targets: self.targets,
             passes: Default::default(),
             outputs: Default::default(),
+            presents: Vec::new(),
+            images: Vec::new(),
             graph_builder: GraphBuilder::new(),
         };
 
@@ -223,7 +313,19 @@ impl<B: Backend> RenderPlan<B> {
             ctx.evaluate_target(target)?;
         }
 
-        Ok(ctx.graph_builder)
+        for present in ctx.presents.drain(..) {
+            let modes = present.modes;
+            ctx.graph_builder.add_node(
+                PresentNode::builder(factory, present.surface, present.image)
+                    // the highest priority is preferred
+                    .with_present_modes_priority(move |mode| {
+                        modes.iter().rev().position(|m| *m == mode)
+                    })
+                    .with_dependency(present.pass),
+            );
+        }
+
+        Ok((ctx.graph_builder, ctx.images))
     }
 }
 
@@ -255,12 +357,40 @@ pub struct TargetMetadata {
     layers: u16,
 }
 
+impl TargetMetadata {
+    /// Width of the target in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height of the target in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Number of layers of the target.
+    pub fn layers(&self) -> u16 {
+        self.layers
+    }
+}
+
+/// A surface rendered to through an image, presented once the plan is evaluated.
+#[derive(Debug)]
+struct PendingPresent<B: Backend> {
+    surface: Surface<B>,
+    image: ImageId,
+    pass: NodeId,
+    modes: Vec<hal::window::PresentMode>,
+}
+
 #[derive(Debug)]
 struct PlanContext<B: Backend> {
     targets: HashMap<Target, TargetPlan<B>>,
     target_metadata: HashMap<Target, TargetMetadata>,
     passes: HashMap<Target, EvaluationState>,
     outputs: HashMap<TargetImage, ImageId>,
+    presents: Vec<PendingPresent<B>>,
+    images: Vec<GpuAllocation>,
     graph_builder: GraphBuilder<B, World>,
 }
 
@@ -360,7 +490,12 @@ impl<B: Backend> PlanContext<B> {
         &mut self.graph_builder
     }
 
-    pub fn create_image(&mut self, options: ImageOptions) -> ImageId {
+    pub fn create_image(&mut self, name: String, options: ImageOptions) -> ImageId {
+        self.images.push(GpuAllocation {
+            category: GpuMemoryCategory::RenderTargets,
+            name,
+            bytes: image_bytes(options.kind, options.levels, options.format),
+        });
         self.graph_builder
             .create_image(options.kind, options.levels, options.format, options.clear)
     }
@@ -511,6 +646,9 @@ pub enum OutputColor<B: Backend> {
     Image(ImageOptions),
     /// Render directly to a window surface.
     Surface(Surface<B>, Option<hal::command::ClearValue>),
+    /// Render to an image with specified options, presented to a window surface by a separate
+    /// node using the first present mode of the list the surface supports.
+    PresentedSurface(Surface<B>, ImageOptions, Vec<hal::window::PresentMode>),
 }
 
 /// Definition for set of outputs for a given render target.
@@ -567,7 +705,8 @@ impl<B: Backend> TargetPlan<B> {
                             }
                             framebuffer_layers = min(framebuffer_layers, 1);
                         }
-                        OutputColor::Image(options) => {
+                        OutputColor::Image(options)
+                        | OutputColor::PresentedSurface(_, options, _) => {
                             let extent = options.kind.extent();
                             framebuffer_width = min(framebuffer_width, extent.width);
                             framebuffer_height = min(framebuffer_height, extent.height);
@@ -644,6 +783,7 @@ impl<B: Backend> TargetPlan<B> {
             }
         }
 
+        let mut presented = Vec::new();
         for (i, color) in outputs.colors.drain(..).enumerate() {
             match color {
                 OutputColor::Surface(surface, clear) => {
@@ -651,15 +791,21 @@ impl<B: Backend> TargetPlan<B> {
                     pass.add_surface(surface, clear);
                 }
                 OutputColor::Image(opts) => {
-                    let node = ctx.create_image(opts);
+                    let node = ctx.create_image(format!("{:?} color {}", self.key, i), opts);
+                    ctx.register_output(TargetImage::Color(self.key, i), node)?;
+                    subpass.add_color(node);
+                }
+                OutputColor::PresentedSurface(surface, opts, modes) => {
+                    let node = ctx.create_image(format!("{:?} color {}", self.key, i), opts);
                     ctx.register_output(TargetImage::Color(self.key, i), node)?;
                     subpass.add_color(node);
+                    presented.push((surface, node, modes));
                 }
             }
         }
 
         if let Some(opts) = outputs.depth {
-            let node = ctx.create_image(opts);
+            let node = ctx.create_image(format!("{:?} depth", self.key), opts);
             ctx.register_output(TargetImage::Depth(self.key), node)?;
             subpass.set_depth_stencil(node);
         }
@@ -670,6 +816,16 @@ impl<B: Backend> TargetPlan<B> {
 
         pass.add_subpass(subpass);
         ctx.submit_pass(self.key, pass)?;
+
+        let pass = ctx.get_pass_node_raw(self.key).expect("Just submitted");
+        for (surface, image, modes) in presented {
+            ctx.presents.push(PendingPresent {
+                surface,
+                image,
+                pass,
+                modes,
+            });
+        }
         Ok(())
     }
 }
@@ -862,7 +1018,7 @@ mod tests {
         )
         .unwrap();
 
-        let planned_graph = plan.build(&factory).unwrap();
+        let (planned_graph, _) = plan.build(&factory).unwrap();
 
         let mut manual_graph = GraphBuilder::<DefaultBackend, World>::new();
         let color = manual_graph.create_image(kind, 1, Format::Rgb8Unorm, None);
@@ -937,7 +1093,7 @@ mod tests {
             Ok(())
         });
 
-        let planned_graph = plan.build(&factory).unwrap();
+        let (planned_graph, _) = plan.build(&factory).unwrap();
 
         let mut manual_graph = GraphBuilder::<DefaultBackend, World>::new();
         let depth = manual_graph.create_image(
@@ -593,7 +593,59 @@ impl From<CustomMatrix> for Projection {
 
 impl From<Projection> for Camera {
     fn from(proj: Projection) -> Self {
-        Camera { inner: proj }
+        Camera {
+            inner: proj,
+            exposure: Exposure::default(),
+        }
+    }
+}
+
+/// Exposure of a camera, scaling the light reaching it before it is displayed.
+///
+/// Only used with `LightUnits::Physical`, the scene isn't exposed with `LightUnits::Legacy`.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum Exposure {
+    /// Exposure value at ISO 100, as measured by a light meter. Brighter scenes need higher
+    /// values, e.g. 15 for a sunny day and 7 for an office.
+    Ev100(f32),
+    /// Settings of a physical camera.
+    Manual {
+        /// Relative aperture, in f-stops.
+        aperture: f32,
+        /// Shutter speed, in seconds.
+        shutter_speed: f32,
+        /// Sensitivity of the sensor, in ISO.
+        iso: f32,
+    },
+}
+
+impl Default for Exposure {
+    fn default() -> Self {
+        Exposure::Ev100(15.0)
+    }
+}
+
+impl Exposure {
+    /// Returns the equivalent exposure value at ISO 100.
+    pub fn ev100(&self) -> f32 {
+        match *self {
+            Exposure::Ev100(ev100) => ev100,
+            Exposure::Manual {
+                aperture,
+                shutter_speed,
+                iso,
+            } => (aperture * aperture / shutter_speed * 100.0 / iso).log2(),
+        }
+    }
+
+    /// Returns the factor scaling luminances in cd/m² to displayed values, 1 being the luminance
+    /// saturating the sensor.
+    ///
+    /// Follows the saturation based sensitivity of [Moving Frostbite to PBR][fb], section 5.1.
+    ///
+    /// [fb]: http://www.frostbite.com/wp-content/uploads/2014/11/course_notes_moving_frostbite_to_pbr.pdf
+    pub fn scale(&self) -> f32 {
+        1.0 / (1.2 * 2.0_f32.powf(self.ev100()))
     }
 }
 
@@ -619,6 +671,9 @@ impl From<Projection> for Camera {
 pub struct Camera {
     /// Graphical projection of the camera.
     inner: Projection,
+    /// Exposure of the camera.
+    #[serde(default)]
+    exposure: Exposure,
 }
 
 impl Camera {
@@ -691,6 +746,22 @@ impl Camera {
     pub fn set_projection(&mut self, new: Projection) {
         self.inner = new;
     }
+
+    /// Returns the [Exposure] of this camera.
+    pub fn exposure(&self) -> &Exposure {
+        &self.exposure
+    }
+
+    /// Sets the [Exposure] of this camera.
+    pub fn set_exposure(&mut self, exposure: Exposure) {
+        self.exposure = exposure;
+    }
+
+    /// Returns this camera with the given [Exposure].
+    pub fn with_exposure(mut self, exposure: Exposure) -> Self {
+        self.exposure = exposure;
+        self
+    }
 }
 
 impl Component for Camera {
@@ -722,6 +793,9 @@ pub enum CameraPrefab {
         znear: f32,
         /// The distance between the viewer (the origin) and the furthest face of the cuboid parallel to the xy-plane. If used for a 3D rendering application, this is the furthest clipping plane.
         zfar: f32,
+        /// Exposure of the camera
+        #[serde(default)]
+        exposure: Exposure,
     },
     /// Perspective prefab
     Perspective {
@@ -733,6 +807,9 @@ pub enum CameraPrefab {
         znear: f32,
         /// Far clip plane distance
         zfar: f32,
+        /// Exposure of the camera
+        #[serde(default)]
+        exposure: Exposure,
     },
 }
 
@@ -747,27 +824,29 @@ impl<'a> PrefabData<'a> for CameraPrefab {
         _: &[Entity],
         _: &[Entity],
     ) -> Result<(), Error> {
-        storage.insert(
-            entity,
-            Camera {
-                inner: match *self {
-                    CameraPrefab::Orthographic {
-                        left,
-                        right,
-                        bottom,
-                        top,
-                        znear,
-                        zfar,
-                    } => Projection::orthographic(left, right, bottom, top, znear, zfar),
-                    CameraPrefab::Perspective {
-                        aspect,
-                        fovy,
-                        znear,
-                        zfar,
-                    } => Projection::perspective(aspect, fovy, znear, zfar),
-                },
-            },
-        )?;
+        let camera = match *self {
+            CameraPrefab::Orthographic {
+                left,
+                right,
+                bottom,
+                top,
+                znear,
+                zfar,
+                exposure,
+            } => Camera::from(Projection::orthographic(
+                left, right, bottom, top, znear, zfar,
+            ))
+            .with_exposure(exposure),
+            CameraPrefab::Perspective {
+                aspect,
+                fovy,
+                znear,
+                zfar,
+                exposure,
+            } => Camera::from(Projection::perspective(aspect, fovy, znear, zfar))
+                .with_exposure(exposure),
+        };
+        storage.insert(entity, camera)?;
         Ok(())
     }
 }
@@ -0,0 +1,73 @@
+//! Decals projected onto the opaque meshes of the scene, drawn by `DrawDecalReceiversDesc` and
+//! `DrawDecalDesc`.
+use crate::{layers::RenderLayers, types::Texture};
+use amethyst_assets::Handle;
+use amethyst_core::{
+    ecs::prelude::{Component, DenseVecStorage},
+    math::Vector3,
+};
+
+/// Projects a texture onto the meshes inside a box around the `Transform` of the entity.
+///
+/// The box is centered on the entity and spans `extents` on each side of it, in its local space.
+/// The texture is projected along the local -z axis, with its top along the local y axis, and
+/// fades out on surfaces facing away from that axis.
+///
+/// Only opaque meshes receive decals, skinned meshes only when `on_skinned` is set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Decal {
+    /// Half size of the box along each local axis.
+    pub extents: Vector3<f32>,
+    /// Color and opacity of the decal.
+    pub albedo: Handle<Texture>,
+    /// Tangent space normal map of the decal, perturbing the normal of the receiving surface.
+    pub normal: Option<Handle<Texture>>,
+    /// Opacity multiplied with the alpha of `albedo`.
+    pub opacity: f32,
+    /// Only meshes sharing a layer with it receive the decal.
+    pub layers: RenderLayers,
+    /// Whether skinned meshes receive the decal.
+    pub on_skinned: bool,
+}
+
+impl Decal {
+    /// Creates a decal of `albedo` in a box spanning `extents` on each side of the entity.
+    pub fn new(albedo: Handle<Texture>, extents: Vector3<f32>) -> Self {
+        Decal {
+            extents,
+            albedo,
+            normal: None,
+            opacity: 1.0,
+            layers: RenderLayers::ALL,
+            on_skinned: false,
+        }
+    }
+
+    /// Sets the normal map of the decal.
+    pub fn with_normal(mut self, normal: Handle<Texture>) -> Self {
+        self.normal = Some(normal);
+        self
+    }
+
+    /// Sets the opacity of the decal.
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity;
+        self
+    }
+
+    /// Sets the layers of the meshes receiving the decal.
+    pub fn with_layers(mut self, layers: RenderLayers) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    /// Sets whether skinned meshes receive the decal.
+    pub fn with_on_skinned(mut self, on_skinned: bool) -> Self {
+        self.on_skinned = on_skinned;
+        self
+    }
+}
+
+impl Component for Decal {
+    type Storage = DenseVecStorage<Self>;
+}
@@ -0,0 +1,384 @@
+//! Meshes whose vertices and indices are updated from CPU data, such as deformable terrain.
+use crate::types::{Mesh, MeshData};
+use amethyst_assets::{AssetStorage, Handle, Loader};
+use amethyst_core::ecs::prelude::{Component, DenseVecStorage};
+use rendy::{
+    hal::Primitive,
+    mesh::{AsVertex, MeshBuilder},
+};
+use std::{any::Any, error, fmt};
+
+/// What happens when an update grows a `DynamicMesh` beyond its capacity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GrowthPolicy {
+    /// The update fails with `DynamicMeshError::CapacityExceeded`.
+    Error,
+    /// The capacity is doubled until the update fits.
+    Reallocate,
+}
+
+impl Default for GrowthPolicy {
+    fn default() -> Self {
+        GrowthPolicy::Error
+    }
+}
+
+/// Error of an invalid `DynamicMesh` update.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DynamicMeshError {
+    /// The mesh has no vertices of the updated type.
+    MissingAttribute(&'static str),
+    /// The update starts after the end of the vertices or indices, leaving a gap.
+    OutOfBounds {
+        /// First updated vertex or index.
+        offset: usize,
+        /// Current number of vertices or indices.
+        len: usize,
+    },
+    /// The update grows the mesh beyond its capacity with `GrowthPolicy::Error`.
+    CapacityExceeded {
+        /// Number of vertices or indices after the update.
+        required: usize,
+        /// Capacity of the mesh.
+        capacity: usize,
+    },
+}
+
+impl error::Error for DynamicMeshError {}
+
+impl fmt::Display for DynamicMeshError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            DynamicMeshError::MissingAttribute(name) => {
+                write!(fmt, "Dynamic mesh has no vertices of type {}", name)
+            }
+            DynamicMeshError::OutOfBounds { offset, len } => write!(
+                fmt,
+                "Update at offset {} leaves a gap after the {} existing elements",
+                offset, len
+            ),
+            DynamicMeshError::CapacityExceeded { required, capacity } => write!(
+                fmt,
+                "Update requires {} elements but the capacity is {}",
+                required, capacity
+            ),
+        }
+    }
+}
+
+/// Vertices of a single type, type-erased so meshes can hold any set of vertex types.
+trait VertexStream: Any + Send + Sync + fmt::Debug {
+    fn len(&self) -> usize;
+    fn truncate(&mut self, len: usize);
+    fn add_to(&self, builder: &mut MeshBuilder<'static>);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<V: AsVertex> VertexStream for Vec<V> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn truncate(&mut self, len: usize) {
+        Vec::truncate(self, len)
+    }
+
+    fn add_to(&self, builder: &mut MeshBuilder<'static>) {
+        builder.add_vertices(self.clone());
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// CPU copy of the vertices and indices of a `DynamicMesh`.
+///
+/// All vertex types share the vertex capacity, the mesh draws as many vertices as its shortest
+/// vertex type has.
+#[derive(Debug)]
+pub struct DynamicMeshData {
+    streams: Vec<Box<dyn VertexStream>>,
+    indices: Option<Vec<u32>>,
+    prim: Primitive,
+    vertex_capacity: usize,
+    index_capacity: usize,
+    policy: GrowthPolicy,
+}
+
+impl DynamicMeshData {
+    /// Creates an empty mesh of triangles, with room for `vertex_capacity` vertices and
+    /// `index_capacity` indices.
+    pub fn new(vertex_capacity: usize, index_capacity: usize) -> Self {
+        DynamicMeshData {
+            streams: Vec::new(),
+            indices: None,
+            prim: Primitive::TriangleList,
+            vertex_capacity,
+            index_capacity,
+            policy: GrowthPolicy::default(),
+        }
+    }
+
+    /// Sets what happens when the capacity is exceeded.
+    pub fn with_policy(mut self, policy: GrowthPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Sets the primitive type of the mesh.
+    pub fn with_prim_type(mut self, prim: Primitive) -> Self {
+        self.prim = prim;
+        self
+    }
+
+    /// Adds vertices of a new type, replacing the vertices of the same type.
+    pub fn with_vertices<V: AsVertex>(
+        mut self,
+        vertices: Vec<V>,
+    ) -> Result<Self, DynamicMeshError> {
+        let capacity = self.vertex_capacity;
+        self.vertex_capacity = self.grown(vertices.len(), capacity)?;
+        self.streams.retain(|s| !s.as_any().is::<Vec<V>>());
+        self.streams.push(Box::new(vertices));
+        Ok(self)
+    }
+
+    /// Adds indices to the mesh.
+    pub fn with_indices(mut self, indices: Vec<u32>) -> Result<Self, DynamicMeshError> {
+        let capacity = self.index_capacity;
+        self.index_capacity = self.grown(indices.len(), capacity)?;
+        self.indices = Some(indices);
+        Ok(self)
+    }
+
+    /// Number of vertices drawn, the length of the shortest vertex type.
+    pub fn vertex_count(&self) -> usize {
+        self.streams.iter().map(|s| s.len()).min().unwrap_or(0)
+    }
+
+    /// Number of indices, 0 without indices.
+    pub fn index_count(&self) -> usize {
+        self.indices.as_ref().map_or(0, Vec::len)
+    }
+
+    /// Overwrites the vertices of type `V` from `offset`, appending those past the end.
+    pub fn update_vertices<V: AsVertex>(
+        &mut self,
+        offset: usize,
+        vertices: &[V],
+    ) -> Result<(), DynamicMeshError> {
+        let capacity = self.vertex_capacity;
+        let policy = self.policy;
+        let stream = self
+            .streams
+            .iter_mut()
+            .find_map(|s| s.as_any_mut().downcast_mut::<Vec<V>>())
+            .ok_or_else(|| DynamicMeshError::MissingAttribute(std::any::type_name::<V>()))?;
+        let required = update_len(stream.len(), offset, vertices.len())?;
+        let new_capacity = grow(required, capacity, policy)?;
+        overwrite(stream, offset, vertices);
+        self.vertex_capacity = new_capacity;
+        Ok(())
+    }
+
+    /// Overwrites the indices from `offset`, appending those past the end.
+    pub fn update_indices(
+        &mut self,
+        offset: usize,
+        indices: &[u32],
+    ) -> Result<(), DynamicMeshError> {
+        let existing = self.indices.as_ref().map_or(0, Vec::len);
+        let required = update_len(existing, offset, indices.len())?;
+        self.index_capacity = self.grown(required, self.index_capacity)?;
+        overwrite(self.indices.get_or_insert_with(Vec::new), offset, indices);
+        Ok(())
+    }
+
+    /// Removes the vertices after the first `count` ones, of every type.
+    pub fn truncate_vertices(&mut self, count: usize) {
+        for stream in &mut self.streams {
+            stream.truncate(count);
+        }
+    }
+
+    /// Removes the indices after the first `count` ones.
+    pub fn truncate_indices(&mut self, count: usize) {
+        if let Some(indices) = &mut self.indices {
+            indices.truncate(count);
+        }
+    }
+
+    /// Builder of the mesh with the current vertices and indices.
+    pub fn builder(&self) -> MeshBuilder<'static> {
+        let mut builder = MeshBuilder::new();
+        for stream in &self.streams {
+            stream.add_to(&mut builder);
+        }
+        if let Some(indices) = &self.indices {
+            builder.set_indices(indices.clone());
+        }
+        builder.set_prim_type(self.prim);
+        builder
+    }
+
+    fn grown(&self, required: usize, capacity: usize) -> Result<usize, DynamicMeshError> {
+        grow(required, capacity, self.policy)
+    }
+}
+
+/// Length of the elements after writing `count` of them at `offset`.
+fn update_len(len: usize, offset: usize, count: usize) -> Result<usize, DynamicMeshError> {
+    if offset > len {
+        return Err(DynamicMeshError::OutOfBounds { offset, len });
+    }
+    Ok(len.max(offset + count))
+}
+
+/// Capacity holding `required` elements according to `policy`.
+fn grow(required: usize, capacity: usize, policy: GrowthPolicy) -> Result<usize, DynamicMeshError> {
+    if required <= capacity {
+        return Ok(capacity);
+    }
+    match policy {
+        GrowthPolicy::Error => Err(DynamicMeshError::CapacityExceeded { required, capacity }),
+        GrowthPolicy::Reallocate => Ok(required.next_power_of_two().max(capacity * 2)),
+    }
+}
+
+fn overwrite<T: Copy>(values: &mut Vec<T>, offset: usize, new: &[T]) {
+    let overlap = (values.len() - offset).min(new.len());
+    values[offset..offset + overlap].copy_from_slice(&new[..overlap]);
+    values.extend_from_slice(&new[overlap..]);
+}
+
+/// A mesh whose vertices and indices are updated from CPU data, keeping the same
+/// `Handle<Mesh>`.
+///
+/// Updates are applied to the CPU copy right away, and the `DynamicMeshSystem` uploads the
+/// mesh once per frame when it changed. The mesh it replaces is kept alive until the frame
+/// using it is submitted.
+#[derive(Debug)]
+pub struct DynamicMesh {
+    mesh: Handle<Mesh>,
+    data: DynamicMeshData,
+    dirty: bool,
+}
+
+impl DynamicMesh {
+    /// Loads the mesh of `data`.
+    pub fn new(data: DynamicMeshData, loader: &Loader, storage: &AssetStorage<Mesh>) -> Self {
+        let mesh = loader.load_from_data(MeshData(data.builder()), (), storage);
+        DynamicMesh {
+            mesh,
+            data,
+            dirty: false,
+        }
+    }
+
+    /// Handle of the mesh, which is the same after updates.
+    pub fn mesh(&self) -> &Handle<Mesh> {
+        &self.mesh
+    }
+
+    /// The vertices and indices of the mesh.
+    pub fn data(&self) -> &DynamicMeshData {
+        &self.data
+    }
+
+    /// Mutable access to the vertices and indices of the mesh, which is uploaded again.
+    pub fn data_mut(&mut self) -> &mut DynamicMeshData {
+        self.dirty = true;
+        &mut self.data
+    }
+
+    /// Overwrites the vertices of type `V` from `offset`, see
+    /// `DynamicMeshData::update_vertices`.
+    pub fn update_vertices<V: AsVertex>(
+        &mut self,
+        offset: usize,
+        vertices: &[V],
+    ) -> Result<(), DynamicMeshError> {
+        self.data.update_vertices(offset, vertices)?;
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// Overwrites the indices from `offset`, see `DynamicMeshData::update_indices`.
+    pub fn update_indices(
+        &mut self,
+        offset: usize,
+        indices: &[u32],
+    ) -> Result<(), DynamicMeshError> {
+        self.data.update_indices(offset, indices)?;
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// Returns true if the mesh changed since it was last uploaded.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub(crate) fn mark_uploaded(&mut self) {
+        self.dirty = false;
+    }
+}
+
+impl Component for DynamicMesh {
+    type Storage = DenseVecStorage<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rendy::mesh::{Normal, Position, TexCoord};
+
+    fn quad() -> DynamicMeshData {
+        DynamicMeshData::new(4, 6)
+            .with_vertices(vec![Position([0.0; 3]); 4])
+            .unwrap()
+            .with_vertices(vec![TexCoord([0.0; 2]); 4])
+            .unwrap()
+            .with_indices(vec![0, 1, 2, 2, 1, 3])
+            .unwrap()
+    }
+
+    #[test]
+    fn updates_vertices_in_place() {
+        let mut data = quad();
+        data.update_vertices(2, &[Position([1.0; 3])]).unwrap();
+        assert_eq!(4, data.vertex_count());
+        assert_eq!(
+            Err(DynamicMeshError::OutOfBounds { offset: 5, len: 4 }),
+            data.update_vertices(5, &[Position([1.0; 3])])
+        );
+        assert!(data.update_vertices(0, &[Normal([0.0; 3])]).is_err());
+    }
+
+    #[test]
+    fn growth_follows_policy() {
+        let mut data = quad();
+        data.truncate_vertices(2);
+        assert_eq!(2, data.vertex_count());
+        data.update_vertices(2, &[Position([1.0; 3]); 2]).unwrap();
+        // Texture coordinates were not regrown, so only 2 vertices are drawn
+        assert_eq!(2, data.vertex_count());
+        assert_eq!(
+            Err(DynamicMeshError::CapacityExceeded {
+                required: 7,
+                capacity: 6
+            }),
+            data.update_indices(6, &[0])
+        );
+
+        let mut data = quad().with_policy(GrowthPolicy::Reallocate);
+        data.update_indices(6, &[0, 1, 2]).unwrap();
+        assert_eq!(9, data.index_count());
+    }
+}
@@ -0,0 +1,355 @@
+//! Textures updated from CPU data every frame, such as minimaps, video feeds or painted surfaces.
+use crate::types::{Texture, TextureData};
+use amethyst_assets::{AssetStorage, Handle, Loader};
+use amethyst_core::ecs::prelude::{Component, DenseVecStorage};
+use rendy::{
+    hal::{
+        format::{Aspects, Format},
+        image::{Kind, SamplerInfo, ViewKind},
+    },
+    texture::TextureBuilder,
+};
+use std::{error, fmt};
+
+/// Rectangle of texels of a `DynamicTexture`, from its top left corner.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextureRegion {
+    /// Horizontal offset of the region.
+    pub x: u32,
+    /// Vertical offset of the region.
+    pub y: u32,
+    /// Width of the region.
+    pub width: u32,
+    /// Height of the region.
+    pub height: u32,
+}
+
+impl TextureRegion {
+    /// Creates a new region.
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        TextureRegion {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// Error of an invalid `DynamicTexture` creation or update.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DynamicTextureError {
+    /// The format is compressed or not a color format.
+    UnsupportedFormat(Format),
+    /// The region is empty or exceeds the texture.
+    RegionOutOfBounds {
+        /// Updated region.
+        region: TextureRegion,
+        /// Size of the texture.
+        size: (u32, u32),
+    },
+    /// The stride is not a multiple of the texel size, or shorter than a row of the region.
+    InvalidStride {
+        /// Bytes between the starts of two rows of the data.
+        stride: usize,
+        /// Bytes of a row of the region.
+        row_size: usize,
+        /// Bytes of a texel.
+        texel_size: usize,
+    },
+    /// The data is too short for the region.
+    DataTooShort {
+        /// Bytes required by the region.
+        expected: usize,
+        /// Bytes of the data.
+        actual: usize,
+    },
+}
+
+impl error::Error for DynamicTextureError {}
+
+impl fmt::Display for DynamicTextureError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            DynamicTextureError::UnsupportedFormat(format) => write!(
+                fmt,
+                "Format {:?} is not supported by dynamic textures, only uncompressed color formats are",
+                format
+            ),
+            DynamicTextureError::RegionOutOfBounds { region, size } => write!(
+                fmt,
+                "Region {:?} is empty or exceeds the texture size {:?}",
+                region, size
+            ),
+            DynamicTextureError::InvalidStride {
+                stride,
+                row_size,
+                texel_size,
+            } => write!(
+                fmt,
+                "Stride of {} bytes must be a multiple of {} bytes and hold a row of {} bytes",
+                stride, texel_size, row_size
+            ),
+            DynamicTextureError::DataTooShort { expected, actual } => write!(
+                fmt,
+                "Region requires {} bytes of data, got {}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+/// Update of a region waiting for the `DynamicTextureSystem`.
+#[derive(Debug)]
+pub(crate) struct TextureUpdate {
+    pub(crate) region: TextureRegion,
+    /// Texels between the starts of two rows of `data`.
+    pub(crate) data_width: u32,
+    pub(crate) data: Vec<u8>,
+}
+
+/// A texture whose content is updated from CPU data after it is loaded.
+///
+/// The texture is used like any other through `DynamicTexture::texture`, e.g. in a `Material`.
+/// Updates are queued by `update` and uploaded by the `DynamicTextureSystem` before the frame
+/// is rendered. Only the updated regions are uploaded, and updates queued before the texture
+/// is loaded are kept until it is.
+#[derive(Debug)]
+pub struct DynamicTexture {
+    texture: Handle<Texture>,
+    width: u32,
+    height: u32,
+    format: Format,
+    texel_size: usize,
+    pending: Vec<TextureUpdate>,
+}
+
+impl DynamicTexture {
+    /// Creates a 2D texture of the given size and format, initially zeroed.
+    ///
+    /// Only uncompressed color formats are supported.
+    pub fn new(
+        width: u32,
+        height: u32,
+        format: Format,
+        sampler_info: SamplerInfo,
+        loader: &Loader,
+        storage: &AssetStorage<Texture>,
+    ) -> Result<Self, DynamicTextureError> {
+        let texel_size = texel_size(format)?;
+        if width == 0 || height == 0 {
+            return Err(DynamicTextureError::RegionOutOfBounds {
+                region: TextureRegion::new(0, 0, width, height),
+                size: (width, height),
+            });
+        }
+
+        let builder = TextureBuilder::new()
+            .with_kind(Kind::D2(width, height, 1, 1))
+            .with_view_kind(ViewKind::D2)
+            .with_data_width(width)
+            .with_data_height(height)
+            .with_sampler_info(sampler_info)
+            .with_raw_data(
+                vec![0; width as usize * height as usize * texel_size],
+                format,
+            );
+        let texture = loader.load_from_data(TextureData(builder), (), storage);
+
+        Ok(DynamicTexture {
+            texture,
+            width,
+            height,
+            format,
+            texel_size,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Handle of the texture.
+    pub fn texture(&self) -> &Handle<Texture> {
+        &self.texture
+    }
+
+    /// Size of the texture in texels.
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Format of the texture.
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Queues an update of `region` with tightly packed rows of texels.
+    pub fn update(
+        &mut self,
+        region: TextureRegion,
+        data: &[u8],
+    ) -> Result<(), DynamicTextureError> {
+        let stride = region.width as usize * self.texel_size;
+        self.update_strided(region, data, stride)
+    }
+
+    /// Queues an update of `region` with rows of texels starting every `stride` bytes, e.g. to
+    /// update part of a larger image.
+    pub fn update_strided(
+        &mut self,
+        region: TextureRegion,
+        data: &[u8],
+        stride: usize,
+    ) -> Result<(), DynamicTextureError> {
+        let len = validate_update(
+            (self.width, self.height),
+            self.texel_size,
+            region,
+            stride,
+            data.len(),
+        )?;
+        self.push(TextureUpdate {
+            region,
+            data_width: (stride / self.texel_size) as u32,
+            data: data[..len].to_vec(),
+        });
+        Ok(())
+    }
+
+    /// Queues an update of `region`, written by `write` into a zeroed staging buffer with tightly
+    /// packed rows of texels.
+    pub fn update_with(
+        &mut self,
+        region: TextureRegion,
+        write: impl FnOnce(&mut [u8]),
+    ) -> Result<(), DynamicTextureError> {
+        let stride = region.width as usize * self.texel_size;
+        let len = validate_update(
+            (self.width, self.height),
+            self.texel_size,
+            region,
+            stride,
+            std::usize::MAX,
+        )?;
+        let mut data = vec![0; len];
+        write(&mut data);
+        self.push(TextureUpdate {
+            region,
+            data_width: region.width,
+            data,
+        });
+        Ok(())
+    }
+
+    /// Returns true if updates are waiting to be uploaded.
+    pub fn has_pending_updates(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    pub(crate) fn drain_updates(&mut self) -> std::vec::Drain<'_, TextureUpdate> {
+        self.pending.drain(..)
+    }
+
+    fn push(&mut self, update: TextureUpdate) {
+        // An update of the whole texture overwrites the pending ones
+        if update.region == TextureRegion::new(0, 0, self.width, self.height) {
+            self.pending.clear();
+        }
+        self.pending.push(update);
+    }
+}
+
+impl Component for DynamicTexture {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Bytes of a texel of `format`.
+fn texel_size(format: Format) -> Result<usize, DynamicTextureError> {
+    let desc = format.surface_desc();
+    if desc.is_compressed() || !desc.aspects.contains(Aspects::COLOR) {
+        return Err(DynamicTextureError::UnsupportedFormat(format));
+    }
+    Ok(desc.bits as usize / 8)
+}
+
+/// Checks an update of `region` from data of `data_len` bytes with rows every `stride` bytes,
+/// returning the bytes of data used.
+fn validate_update(
+    size: (u32, u32),
+    texel_size: usize,
+    region: TextureRegion,
+    stride: usize,
+    data_len: usize,
+) -> Result<usize, DynamicTextureError> {
+    let fits = |offset: u32, extent: u32, max: u32| {
+        extent > 0 && offset.checked_add(extent).map_or(false, |end| end <= max)
+    };
+    if !fits(region.x, region.width, size.0) || !fits(region.y, region.height, size.1) {
+        return Err(DynamicTextureError::RegionOutOfBounds { region, size });
+    }
+
+    let row_size = region.width as usize * texel_size;
+    if stride < row_size || stride % texel_size != 0 {
+        return Err(DynamicTextureError::InvalidStride {
+            stride,
+            row_size,
+            texel_size,
+        });
+    }
+
+    let expected = stride * (region.height as usize - 1) + row_size;
+    if data_len < expected {
+        return Err(DynamicTextureError::DataTooShort {
+            expected,
+            actual: data_len,
+        });
+    }
+    Ok(expected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_regions_outside_of_texture() {
+        let region = TextureRegion::new(60, 0, 8, 8);
+        assert_eq!(
+            validate_update((64, 64), 4, region, 32, 256),
+            Err(DynamicTextureError::RegionOutOfBounds {
+                region,
+                size: (64, 64)
+            })
+        );
+        let empty = TextureRegion::new(0, 0, 0, 8);
+        assert!(validate_update((64, 64), 4, empty, 0, 0).is_err());
+    }
+
+    #[test]
+    fn validates_stride_and_data_size() {
+        let region = TextureRegion::new(8, 8, 4, 2);
+        assert_eq!(validate_update((64, 64), 4, region, 16, 32), Ok(32));
+        // The last row doesn't need the padding of the stride
+        assert_eq!(validate_update((64, 64), 4, region, 256, 272), Ok(272));
+        assert_eq!(
+            validate_update((64, 64), 4, region, 18, 64),
+            Err(DynamicTextureError::InvalidStride {
+                stride: 18,
+                row_size: 16,
+                texel_size: 4
+            })
+        );
+        assert_eq!(
+            validate_update((64, 64), 4, region, 16, 31),
+            Err(DynamicTextureError::DataTooShort {
+                expected: 32,
+                actual: 31
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_compressed_and_depth_formats() {
+        assert_eq!(texel_size(Format::Rgba8Unorm), Ok(4));
+        assert!(texel_size(Format::Bc1RgbUnorm).is_err());
+        assert!(texel_size(Format::D32Sfloat).is_err());
+    }
+}
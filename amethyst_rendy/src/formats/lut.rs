@@ -0,0 +1,162 @@
+//! Formats of the 3D lookup tables used by `ColorGradeSettings`.
+//!
+//! A LUT of size `N` is stored as a 2D texture `N * N` wide and `N` high, a strip of `N` square
+//! slices of increasing blue, each with red increasing along x and green along y. This is the
+//! layout of the common 32³ neutral LUT PNGs, which can be edited in any image editor.
+use super::texture::ImageFormat;
+use crate::types::TextureData;
+use amethyst_assets::Format;
+use amethyst_error::Error;
+use rendy::{
+    hal::{
+        format::Format as HalFormat,
+        image::{Filter, Kind, SamplerInfo, ViewKind, WrapMode},
+    },
+    texture::TextureBuilder,
+};
+use serde::{Deserialize, Serialize};
+
+/// Returns the `ImageFormat` loading LUT strip images, which are sampled with linear filtering
+/// and without sRGB conversion.
+pub fn lut_image_format() -> ImageFormat {
+    use rendy::texture::image::Repr;
+
+    let mut format = ImageFormat::default();
+    format.0.repr = Repr::Unorm;
+    format.0.sampler_info = SamplerInfo::new(Filter::Linear, WrapMode::Clamp);
+    format.0.premultiply_alpha = false;
+    format
+}
+
+/// Format of Adobe/Resolve `.cube` 3D LUT files, loaded as a LUT strip texture.
+///
+/// Only 3D tables with the default `0 0 0` to `1 1 1` domain are supported.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct CubeLutFormat;
+
+amethyst_assets::register_format!("CUBE_LUT", CubeLutFormat as TextureData);
+impl Format<TextureData> for CubeLutFormat {
+    fn name(&self) -> &'static str {
+        "CUBE_LUT"
+    }
+
+    fn import_simple(&self, bytes: Vec<u8>) -> Result<TextureData, Error> {
+        let text = std::str::from_utf8(&bytes)
+            .map_err(|e| Error::from_string(format!("Cube LUT is not valid UTF-8: {}", e)))?;
+        let (size, strip) = parse_cube(text)?;
+        let data = strip
+            .iter()
+            .flat_map(|texel| texel.iter().flat_map(|c| c.to_ne_bytes().to_vec()))
+            .collect::<Vec<u8>>();
+
+        Ok(TextureBuilder::new()
+            .with_kind(Kind::D2(size * size, size, 1, 1))
+            .with_view_kind(ViewKind::D2)
+            .with_data_width(size * size)
+            .with_data_height(size)
+            .with_sampler_info(SamplerInfo::new(Filter::Linear, WrapMode::Clamp))
+            .with_raw_data(data, HalFormat::Rgba32Sfloat)
+            .into())
+    }
+}
+
+/// Parses a `.cube` file into its size and the texels of its strip, row by row.
+fn parse_cube(text: &str) -> Result<(u32, Vec<[f32; 4]>), Error> {
+    let mut size = None;
+    let mut table = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        let keyword = words.next().unwrap_or_default();
+        let error = |message: &str| {
+            Error::from_string(format!("Cube LUT line {}: {}", number + 1, message))
+        };
+        match keyword {
+            "TITLE" => {}
+            "LUT_1D_SIZE" => return Err(error("1D LUTs are not supported")),
+            "LUT_3D_SIZE" => {
+                let value = words
+                    .next()
+                    .and_then(|word| word.parse::<u32>().ok())
+                    .filter(|size| *size >= 2)
+                    .ok_or_else(|| error("invalid LUT_3D_SIZE"))?;
+                size = Some(value);
+            }
+            "DOMAIN_MIN" | "DOMAIN_MAX" => {
+                let default = if keyword == "DOMAIN_MIN" { 0.0 } else { 1.0 };
+                if parse_color(words) != Some([default; 3]) {
+                    return Err(error("only the default domain is supported"));
+                }
+            }
+            _ if keyword.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.') => {
+                let [r, g, b] = parse_color(line.split_whitespace())
+                    .ok_or_else(|| error("expected three numbers"))?;
+                table.push([r, g, b, 1.0]);
+            }
+            // Unknown keywords are skipped, as allowed by the specification.
+            _ => {}
+        }
+    }
+
+    let size = size.ok_or_else(|| Error::from_string("Cube LUT has no LUT_3D_SIZE"))?;
+    let len = (size * size * size) as usize;
+    if table.len() != len {
+        return Err(Error::from_string(format!(
+            "Cube LUT of size {} has {} entries instead of {}",
+            size,
+            table.len(),
+            len
+        )));
+    }
+
+    // The table iterates red fastest, then green, then blue.
+    let size = size as usize;
+    let mut strip = vec![[0.0; 4]; len];
+    for (index, texel) in table.into_iter().enumerate() {
+        let (r, g, b) = (index % size, index / size % size, index / (size * size));
+        strip[g * size * size + b * size + r] = texel;
+    }
+    Ok((size as u32, strip))
+}
+
+fn parse_color<'a>(mut words: impl Iterator<Item = &'a str>) -> Option<[f32; 3]> {
+    let mut color = [0.0; 3];
+    for value in color.iter_mut() {
+        *value = words.next()?.parse().ok()?;
+    }
+    Some(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cube_table_is_laid_out_as_strip() {
+        let text = "TITLE \"test\"\n\
+                    # comment\n\
+                    LUT_3D_SIZE 2\n\
+                    DOMAIN_MIN 0 0 0\n\
+                    0 0 0\n1 0 0\n0 1 0\n1 1 0\n\
+                    0 0 1\n1 0 1\n0 1 1\n1 1 1\n";
+        let (size, strip) = parse_cube(text).unwrap();
+        assert_eq!(size, 2);
+        // First row holds green 0, with the blue 0 slice followed by the blue 1 slice.
+        assert_eq!(strip[0], [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(strip[1], [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(strip[2], [0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(strip[3], [1.0, 0.0, 1.0, 1.0]);
+        assert_eq!(strip[4], [0.0, 1.0, 0.0, 1.0]);
+        assert_eq!(strip[7], [1.0, 1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn cube_table_of_wrong_length_fails() {
+        assert!(parse_cube("LUT_3D_SIZE 2\n0 0 0\n").is_err());
+        assert!(parse_cube("LUT_1D_SIZE 2\n0 0 0\n1 1 1\n").is_err());
+        assert!(parse_cube("0 0 0\n").is_err());
+    }
+}
@@ -1,4 +1,5 @@
 //! Module for mesh support.
+use super::{obj::load_obj_mesh, ply::load_ply_mesh};
 use crate::{
     shape::{FromShape, ShapePrefab},
     types::{Mesh, MeshData},
@@ -11,7 +12,10 @@ use amethyst_error::Error;
 use rendy::mesh::MeshBuilder;
 use serde::{Deserialize, Serialize};
 
-/// 'Obj' mesh format `Format` implementation.
+/// 'Obj' mesh format `Format` implementation, loading all the faces of the file as a single
+/// mesh with their vertex colors.
+///
+/// `obj::ObjSceneFormat` loads the faces of each material with their `Material` instead.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct ObjFormat;
 
@@ -24,16 +28,25 @@ impl Format<MeshData> for ObjFormat {
     }
 
     fn import_simple(&self, bytes: Vec<u8>) -> Result<MeshData, Error> {
-        rendy::mesh::obj::load_from_obj(&bytes)
-            .map(|mut builders| {
-                let mut iter = builders.drain(..);
-                let builder = iter.next().unwrap();
-                if iter.next().is_some() {
-                    log::warn!("OBJ file contains more than one object, only loading the first");
-                }
-                builder.0.into()
-            })
-            .map_err(|e| e.compat().into())
+        load_obj_mesh(&bytes).map(Into::into)
+    }
+}
+
+/// 'Ply' mesh format `Format` implementation, for ASCII and binary PLY files like scans.
+///
+/// Positions, normals, colors and texture coordinates of the vertices are read into the same
+/// vertex streams as `ObjFormat`, computing the normals when absent. Faces are triangulated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct PlyFormat;
+
+amethyst_assets::register_format!("PLY", PlyFormat as MeshData);
+impl Format<MeshData> for PlyFormat {
+    fn name(&self) -> &'static str {
+        "PLY"
+    }
+
+    fn import_simple(&self, bytes: Vec<u8>) -> Result<MeshData, Error> {
+        load_ply_mesh(&bytes).map(Into::into)
     }
 }
 
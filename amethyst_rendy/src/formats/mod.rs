@@ -1,6 +1,9 @@
 //! Pre-defined graphical formats and data provided by amethyst_rendy
+pub mod lut;
 pub mod mesh;
 pub mod mtl;
+pub mod obj;
+mod ply;
 pub mod texture;
 
 use self::{mesh::MeshPrefab, mtl::MaterialPrefab};
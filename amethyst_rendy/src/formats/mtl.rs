@@ -2,6 +2,7 @@
 
 use crate::{
     formats::texture::TexturePrefab,
+    lightmap::LightmapMode,
     mtl::{Material, MaterialDefaults, TextureOffset},
     transparent::Transparent,
     types::Texture,
@@ -33,6 +34,14 @@ pub struct MaterialPrefab {
     pub transparent: bool,
     /// Alpha cutoff: the value below which we do not draw the pixel
     pub alpha_cutoff: f32,
+    /// Draw the albedo multiplied with the vertex color, without any lighting
+    pub unlit: bool,
+    /// Lightmap, sampled with the second set of texture coordinates.
+    pub lightmap: Option<TexturePrefab>,
+    /// How the lightmap is combined with the lighting
+    pub lightmap_mode: LightmapMode,
+    /// Skip the dynamic lights, for surfaces fully lit by their lightmap
+    pub skip_dynamic_lights: bool,
     /// Clone handle only
     #[serde(skip)]
     handle: Option<Handle<Material>>,
@@ -63,6 +72,10 @@ impl Default for MaterialPrefab {
             uv_offset: TextureOffset::default(),
             transparent: false,
             alpha_cutoff: std::f32::MIN_POSITIVE,
+            unlit: false,
+            lightmap: None,
+            lightmap_mode: LightmapMode::default(),
+            skip_dynamic_lights: false,
             handle: None,
         }
     }
@@ -141,6 +154,11 @@ impl<'a> PrefabData<'a> for MaterialPrefab {
                 ret = true;
             }
         }
+        if let Some(ref mut texture) = self.lightmap {
+            if texture.load_sub_assets(progress, tp_data)? {
+                ret = true;
+            }
+        }
 
         if self.handle.is_none() {
             let mtl = Material {
@@ -158,6 +176,10 @@ impl<'a> PrefabData<'a> for MaterialPrefab {
                 cavity: load_handle(&self.cavity, &mat_default.0.cavity),
                 uv_offset: self.uv_offset.clone(),
                 alpha_cutoff: self.alpha_cutoff,
+                unlit: self.unlit,
+                lightmap: load_handle(&self.lightmap, &mat_default.0.lightmap),
+                lightmap_mode: self.lightmap_mode,
+                skip_dynamic_lights: self.skip_dynamic_lights,
             };
 
             self.handle
@@ -0,0 +1,685 @@
+//! Wavefront OBJ scenes, with the materials of their MTL files and their vertex colors.
+//!
+//! The vertex colors of the common `v x y z r g b` extension are kept in a `Color` vertex
+//! stream, which is only added when the file has any.
+use super::{mtl::MaterialPrefab, texture::TexturePrefab};
+use crate::types::{Mesh, MeshData};
+use amethyst_assets::{
+    AssetStorage, Format, FormatValue, Handle, Loader, Prefab, PrefabData, ProgressCounter, Source,
+};
+use amethyst_core::{
+    ecs::{Entity, Read, ReadExpect, WriteStorage},
+    math::{Vector2, Vector3},
+    Transform,
+};
+use amethyst_error::Error;
+use palette::{LinSrgba, Srgba};
+use rendy::{
+    hal::image::{Filter, SamplerInfo, WrapMode},
+    mesh::{Color, MeshBuilder, Normal, Position, Tangent, TexCoord},
+    texture::{
+        image::{load_from_image, ImageTextureConfig, Repr},
+        palette::{load_from_linear_rgba, load_from_srgba},
+        MipLevels, TextureBuilder,
+    },
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+
+/// Wavefront OBJ scene format, loading the faces of each material as a child of the main entity,
+/// with a `Mesh`, a `Transform` and the `Material` defined in the MTL files of `mtllib`.
+///
+/// `map_Kd` is loaded as albedo, falling back to the `Kd` color, `map_bump`, `bump` or `norm` as
+/// normal map, and `map_Ks` as cavity map. `Ns` sets the roughness and `d` or `Tr` below 1 makes
+/// the material transparent. Material libraries and textures which fail to load are skipped with
+/// a warning.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct ObjSceneFormat;
+
+impl Format<Prefab<ObjPrefab>> for ObjSceneFormat {
+    fn name(&self) -> &'static str {
+        "OBJScene"
+    }
+
+    fn import(
+        &self,
+        name: String,
+        source: Arc<dyn Source>,
+        _create_reload: Option<Box<dyn Format<Prefab<ObjPrefab>>>>,
+    ) -> Result<FormatValue<Prefab<ObjPrefab>>, Error> {
+        let obj = parse_obj(text(&source.load(&name)?, "OBJ")?)?;
+
+        // Materials with the path of their library, which their textures are relative to
+        let mut materials = HashMap::new();
+        for library in &obj.material_libs {
+            let path = relative_path(&name, library);
+            match source
+                .load(&path)
+                .and_then(|bytes| parse_mtl(text(&bytes, "MTL")?))
+            {
+                Ok(library) => materials.extend(
+                    library
+                        .into_iter()
+                        .map(|(material_name, material)| (material_name, (path.clone(), material))),
+                ),
+                Err(err) => log::warn!(
+                    "Failed to load material library '{}' of '{}': {}",
+                    path,
+                    name,
+                    err
+                ),
+            }
+        }
+
+        let mut prefab = Prefab::new();
+        for group in &obj.groups {
+            let material = match group.material.as_ref() {
+                Some(material_name) => match materials.get(material_name) {
+                    Some((path, material)) => load_material(material, &*source, path),
+                    None => {
+                        log::warn!(
+                            "Material '{}' used by '{}' is not defined",
+                            material_name,
+                            name
+                        );
+                        MaterialPrefab::default()
+                    }
+                },
+                None => MaterialPrefab::default(),
+            };
+            prefab.add(
+                Some(0),
+                Some(ObjPrefab {
+                    transform: Some(Transform::default()),
+                    mesh: Some(obj.mesh(&group.triangles)),
+                    mesh_handle: None,
+                    material: Some(material),
+                }),
+            );
+        }
+        Ok(FormatValue::data(prefab))
+    }
+}
+
+/// `PrefabData` of the entities loaded by `ObjSceneFormat`.
+#[derive(Debug, Default)]
+pub struct ObjPrefab {
+    /// `Transform` placed on the entities of the materials.
+    pub transform: Option<Transform>,
+    /// Faces of a material.
+    pub mesh: Option<MeshBuilder<'static>>,
+    /// Mesh handle after sub asset loading is done.
+    pub mesh_handle: Option<Handle<Mesh>>,
+    /// `Material` of the faces.
+    pub material: Option<MaterialPrefab>,
+}
+
+impl<'a> PrefabData<'a> for ObjPrefab {
+    type SystemData = (
+        <Transform as PrefabData<'a>>::SystemData,
+        WriteStorage<'a, Handle<Mesh>>,
+        <MaterialPrefab as PrefabData<'a>>::SystemData,
+        ReadExpect<'a, Loader>,
+        Read<'a, AssetStorage<Mesh>>,
+    );
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        (transforms, meshes, materials, _, _): &mut Self::SystemData,
+        entities: &[Entity],
+        children: &[Entity],
+    ) -> Result<(), Error> {
+        if let Some(transform) = &self.transform {
+            transform.add_to_entity(entity, transforms, entities, children)?;
+        }
+        if let Some(mesh) = &self.mesh_handle {
+            meshes.insert(entity, mesh.clone())?;
+        }
+        if let Some(material) = &self.material {
+            material.add_to_entity(entity, materials, entities, children)?;
+        }
+        Ok(())
+    }
+
+    fn load_sub_assets(
+        &mut self,
+        progress: &mut ProgressCounter,
+        (_, _, materials, loader, mesh_storage): &mut Self::SystemData,
+    ) -> Result<bool, Error> {
+        let mut ret = false;
+        if let Some(material) = &mut self.material {
+            ret |= material.load_sub_assets(progress, materials)?;
+        }
+        if let Some(mesh) = self.mesh.take() {
+            self.mesh_handle =
+                Some(loader.load_from_data(MeshData::from(mesh), &mut *progress, mesh_storage));
+            ret = true;
+        }
+        Ok(ret)
+    }
+}
+
+/// Loads all the faces of an OBJ file as a single mesh.
+pub(crate) fn load_obj_mesh(bytes: &[u8]) -> Result<MeshBuilder<'static>, Error> {
+    let obj = parse_obj(text(bytes, "OBJ")?)?;
+    let triangles = obj
+        .groups
+        .iter()
+        .flat_map(|group| group.triangles.iter().cloned())
+        .collect::<Vec<_>>();
+    Ok(obj.mesh(&triangles))
+}
+
+fn text<'a>(bytes: &'a [u8], kind: &str) -> Result<&'a str, Error> {
+    std::str::from_utf8(bytes)
+        .map_err(|e| Error::from_string(format!("{} is not valid UTF-8: {}", kind, e)))
+}
+
+/// Path of `file` relative to the directory of the asset at `base`, with `/` separators.
+fn relative_path(base: &str, file: &str) -> String {
+    let file = file.replace('\\', "/");
+    match base.rfind('/') {
+        Some(end) => format!("{}/{}", &base[..end], file),
+        None => file,
+    }
+}
+
+/// Indices of the position, texture coordinates and normal of a face corner.
+pub(super) type Corner = (usize, Option<usize>, Option<usize>);
+
+/// Triangles of the faces using the same material.
+#[derive(Debug, Default)]
+pub(super) struct ObjGroup {
+    pub(super) material: Option<String>,
+    pub(super) triangles: Vec<[Corner; 3]>,
+}
+
+/// Vertex attributes and faces of an OBJ file, also filled by the other mesh formats to build
+/// their meshes the same way.
+#[derive(Debug, Default)]
+pub(super) struct ObjData {
+    pub(super) positions: Vec<[f32; 3]>,
+    /// Color of each position, white for the positions without one.
+    pub(super) colors: Vec<[f32; 3]>,
+    pub(super) has_colors: bool,
+    pub(super) tex_coords: Vec<[f32; 2]>,
+    pub(super) normals: Vec<[f32; 3]>,
+    pub(super) material_libs: Vec<String>,
+    pub(super) groups: Vec<ObjGroup>,
+}
+
+/// Parses the vertices and faces of an OBJ file, grouping the faces by material.
+fn parse_obj(text: &str) -> Result<ObjData, Error> {
+    let mut data = ObjData::default();
+    data.groups.push(ObjGroup::default());
+    let mut current = 0;
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        let keyword = words.next().unwrap_or_default();
+        let error =
+            |message: &str| Error::from_string(format!("OBJ line {}: {}", number + 1, message));
+        match keyword {
+            "v" => {
+                let values = parse_floats(words).ok_or_else(|| error("invalid vertex"))?;
+                let color = match values.len() {
+                    3 | 4 => [1.0; 3],
+                    6 => {
+                        data.has_colors = true;
+                        [values[3], values[4], values[5]]
+                    }
+                    _ => return Err(error("expected a position, optionally followed by a color")),
+                };
+                data.positions.push([values[0], values[1], values[2]]);
+                data.colors.push(color);
+            }
+            "vt" => {
+                let values = parse_floats(words)
+                    .filter(|values| !values.is_empty() && values.len() <= 3)
+                    .ok_or_else(|| error("invalid texture coordinates"))?;
+                // OBJ coordinates start at the bottom of the image, textures at the top.
+                let v = values.get(1).cloned().unwrap_or(0.0);
+                data.tex_coords.push([values[0], 1.0 - v]);
+            }
+            "vn" => {
+                let values = parse_floats(words)
+                    .filter(|values| values.len() == 3)
+                    .ok_or_else(|| error("invalid normal"))?;
+                data.normals.push([values[0], values[1], values[2]]);
+            }
+            "f" => {
+                let counts = (
+                    data.positions.len(),
+                    data.tex_coords.len(),
+                    data.normals.len(),
+                );
+                let corners = words
+                    .map(|word| parse_corner(word, counts))
+                    .collect::<Option<Vec<_>>>()
+                    .filter(|corners| corners.len() >= 3)
+                    .ok_or_else(|| error("invalid face"))?;
+                let triangles = &mut data.groups[current].triangles;
+                for i in 1..corners.len() - 1 {
+                    triangles.push([corners[0], corners[i], corners[i + 1]]);
+                }
+            }
+            "usemtl" => {
+                let material = Some(line[keyword.len()..].trim().to_string());
+                current = match data.groups.iter().position(|g| g.material == material) {
+                    Some(index) => index,
+                    None => {
+                        data.groups.push(ObjGroup {
+                            material,
+                            triangles: Vec::new(),
+                        });
+                        data.groups.len() - 1
+                    }
+                };
+            }
+            "mtllib" => data.material_libs.extend(words.map(String::from)),
+            // Objects, groups, smoothing groups, lines and the rest are skipped.
+            _ => {}
+        }
+    }
+
+    data.groups.retain(|group| !group.triangles.is_empty());
+    Ok(data)
+}
+
+fn parse_floats<'a>(words: impl Iterator<Item = &'a str>) -> Option<Vec<f32>> {
+    words.map(|word| word.parse().ok()).collect()
+}
+
+/// Parses a `v/vt/vn` face corner, given the number of positions, texture coordinates and
+/// normals defined so far.
+fn parse_corner(
+    word: &str,
+    (positions, tex_coords, normals): (usize, usize, usize),
+) -> Option<Corner> {
+    let mut indices = word.split('/');
+    let position = resolve_index(indices.next()?, positions)?;
+    let mut optional = |len| match indices.next() {
+        Some(index) if !index.is_empty() => resolve_index(index, len).map(Some),
+        _ => Some(None),
+    };
+    let tex_coord = optional(tex_coords)?;
+    let normal = optional(normals)?;
+    Some((position, tex_coord, normal))
+}
+
+/// Converts a 1-based index, or a negative index counting back from the last element, to a
+/// 0-based index.
+fn resolve_index(word: &str, len: usize) -> Option<usize> {
+    let index: isize = word.parse().ok()?;
+    let resolved = if index > 0 {
+        index as usize - 1
+    } else if index < 0 {
+        len.checked_sub((-index) as usize)?
+    } else {
+        return None;
+    };
+    if resolved < len {
+        Some(resolved)
+    } else {
+        None
+    }
+}
+
+impl ObjData {
+    /// Builds the mesh of `triangles`. Missing normals are averaged from the faces around the
+    /// vertex, and tangents are derived from the texture coordinates.
+    pub(super) fn mesh(&self, triangles: &[[Corner; 3]]) -> MeshBuilder<'static> {
+        let mut vertices = HashMap::new();
+        let mut corners = Vec::new();
+        let mut indices = Vec::with_capacity(triangles.len() * 3);
+        for &corner in triangles.iter().flatten() {
+            let next = corners.len() as u32;
+            let index = *vertices.entry(corner).or_insert_with(|| {
+                corners.push(corner);
+                next
+            });
+            indices.push(index);
+        }
+
+        let positions: Vec<_> = corners
+            .iter()
+            .map(|c| Vector3::from(self.positions[c.0]))
+            .collect();
+        let tex_coords: Vec<_> = corners
+            .iter()
+            .map(|c| Vector2::from(c.1.map_or([0.0; 2], |i| self.tex_coords[i])))
+            .collect();
+        let mut normals: Vec<_> = corners
+            .iter()
+            .map(|c| {
+                c.2.map_or_else(Vector3::zeros, |i| Vector3::from(self.normals[i]))
+            })
+            .collect();
+        let mut tangents = vec![Vector3::zeros(); corners.len()];
+        let mut bitangents = vec![Vector3::zeros(); corners.len()];
+
+        for triangle in indices.chunks(3) {
+            let (a, b, c) = (
+                triangle[0] as usize,
+                triangle[1] as usize,
+                triangle[2] as usize,
+            );
+            let edge1 = positions[b] - positions[a];
+            let edge2 = positions[c] - positions[a];
+            // Weighted by the area of the face
+            let face_normal = edge1.cross(&edge2);
+            let uv1 = tex_coords[b] - tex_coords[a];
+            let uv2 = tex_coords[c] - tex_coords[a];
+            let det = uv1.x * uv2.y - uv2.x * uv1.y;
+            for &i in &[a, b, c] {
+                if corners[i].2.is_none() {
+                    normals[i] += face_normal;
+                }
+                if det.abs() > std::f32::EPSILON {
+                    tangents[i] += (edge1 * uv2.y - edge2 * uv1.y) / det;
+                    bitangents[i] += (edge2 * uv1.x - edge1 * uv2.x) / det;
+                }
+            }
+        }
+
+        let normals: Vec<_> = normals
+            .into_iter()
+            .map(|n| {
+                n.try_normalize(std::f32::EPSILON)
+                    .unwrap_or_else(Vector3::z)
+            })
+            .collect();
+        let tangents = normals
+            .iter()
+            .zip(tangents.iter().zip(bitangents.iter()))
+            .map(|(n, (t, b))| {
+                // Orthogonalize the tangent, falling back to any for flat texture coordinates
+                let tangent = (t - n * n.dot(t))
+                    .try_normalize(std::f32::EPSILON)
+                    .unwrap_or_else(|| {
+                        let tangent1 = n.cross(&Vector3::x());
+                        let tangent2 = n.cross(&Vector3::y());
+                        if tangent1.norm_squared() > tangent2.norm_squared() {
+                            tangent1.normalize()
+                        } else {
+                            tangent2.normalize()
+                        }
+                    });
+                let handedness = if n.cross(&tangent).dot(b) < 0.0 {
+                    -1.0
+                } else {
+                    1.0
+                };
+                Tangent([tangent.x, tangent.y, tangent.z, handedness])
+            })
+            .collect::<Vec<_>>();
+
+        let builder = MeshBuilder::new()
+            .with_indices(indices)
+            .with_vertices(
+                positions
+                    .iter()
+                    .map(|p| Position([p.x, p.y, p.z]))
+                    .collect::<Vec<_>>(),
+            )
+            .with_vertices(
+                normals
+                    .iter()
+                    .map(|n| Normal([n.x, n.y, n.z]))
+                    .collect::<Vec<_>>(),
+            )
+            .with_vertices(tangents)
+            .with_vertices(
+                tex_coords
+                    .iter()
+                    .map(|uv| TexCoord([uv.x, uv.y]))
+                    .collect::<Vec<_>>(),
+            );
+        if self.has_colors {
+            builder.with_vertices(
+                corners
+                    .iter()
+                    .map(|c| {
+                        let [r, g, b] = self.colors[c.0];
+                        Color([r, g, b, 1.0])
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        } else {
+            builder
+        }
+    }
+}
+
+/// Material of an MTL file.
+#[derive(Debug, Clone, PartialEq)]
+struct MtlMaterial {
+    diffuse: [f32; 3],
+    dissolve: f32,
+    shininess: Option<f32>,
+    diffuse_map: Option<String>,
+    specular_map: Option<String>,
+    bump_map: Option<String>,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        MtlMaterial {
+            diffuse: [1.0; 3],
+            dissolve: 1.0,
+            shininess: None,
+            diffuse_map: None,
+            specular_map: None,
+            bump_map: None,
+        }
+    }
+}
+
+/// Parses the materials of an MTL file by name.
+fn parse_mtl(text: &str) -> Result<HashMap<String, MtlMaterial>, Error> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlMaterial)> = None;
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        let keyword = words.next().unwrap_or_default();
+        let rest = line[keyword.len()..].trim();
+        let error =
+            |message: &str| Error::from_string(format!("MTL line {}: {}", number + 1, message));
+        if keyword == "newmtl" {
+            materials.extend(current.take());
+            current = Some((rest.to_string(), MtlMaterial::default()));
+            continue;
+        }
+        let material = match current.as_mut() {
+            Some((_, material)) => material,
+            None => continue,
+        };
+        // Texture statements may start with options, and end with the file name.
+        let map = || words.clone().last().map(String::from);
+        match keyword {
+            "Kd" => {
+                material.diffuse = parse_floats(words.clone())
+                    .filter(|values| values.len() == 3)
+                    .map(|values| [values[0], values[1], values[2]])
+                    .ok_or_else(|| error("invalid Kd"))?;
+            }
+            "d" | "Tr" | "Ns" => {
+                let value = words
+                    .clone()
+                    .last()
+                    .and_then(|word| word.parse::<f32>().ok())
+                    .ok_or_else(|| error("invalid value"))?;
+                match keyword {
+                    "d" => material.dissolve = value,
+                    "Tr" => material.dissolve = 1.0 - value,
+                    _ => material.shininess = Some(value),
+                }
+            }
+            "map_Kd" => material.diffuse_map = map(),
+            "map_Ks" => material.specular_map = map(),
+            "map_bump" | "map_Bump" | "bump" | "norm" => material.bump_map = map(),
+            _ => {}
+        }
+    }
+
+    materials.extend(current);
+    Ok(materials)
+}
+
+/// Creates the `MaterialPrefab` of `material` from the MTL file at `path`.
+fn load_material(material: &MtlMaterial, source: &dyn Source, path: &str) -> MaterialPrefab {
+    let mut prefab = MaterialPrefab::default();
+    let [r, g, b] = material.diffuse;
+    let albedo = load_map(source, path, &material.diffuse_map, true)
+        .unwrap_or_else(|| load_from_srgba(Srgba::new(r, g, b, material.dissolve)));
+    prefab.albedo = Some(TexturePrefab::Data(albedo.into()));
+    prefab.normal = load_map(source, path, &material.bump_map, false)
+        .map(|texture| TexturePrefab::Data(texture.into()));
+    prefab.cavity = load_map(source, path, &material.specular_map, false)
+        .map(|texture| TexturePrefab::Data(texture.into()));
+
+    // Blinn-Phong exponent to roughness, with B channel metallic and G channel roughness
+    if let Some(shininess) = material.shininess {
+        let roughness = (2.0 / (shininess.max(0.0) + 2.0)).sqrt();
+        prefab.metallic_roughness = Some(TexturePrefab::Data(
+            load_from_linear_rgba(LinSrgba::new(1.0, roughness, 0.0, 1.0)).into(),
+        ));
+    }
+    prefab.transparent = material.dissolve < 1.0;
+    prefab
+}
+
+/// Loads the texture `map` of the MTL file at `path`, warning if it fails.
+fn load_map(
+    source: &dyn Source,
+    path: &str,
+    map: &Option<String>,
+    srgb: bool,
+) -> Option<TextureBuilder<'static>> {
+    let path = relative_path(path, map.as_ref()?);
+    let config = ImageTextureConfig {
+        repr: if srgb { Repr::Srgb } else { Repr::Unorm },
+        sampler_info: SamplerInfo::new(Filter::Linear, WrapMode::Tile),
+        ..Default::default()
+    };
+    let result = source.load(&path).and_then(|bytes| {
+        load_from_image(std::io::Cursor::new(&bytes), config).map_err(|e| e.compat().into())
+    });
+    match result {
+        Ok(builder) => Some(builder.with_mip_levels(MipLevels::GenerateAuto)),
+        Err(err) => {
+            log::warn!("Failed to load texture '{}' of material: {}", path, err);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MemorySource(HashMap<&'static str, &'static str>);
+
+    impl Source for MemorySource {
+        fn modified(&self, _path: &str) -> Result<u64, Error> {
+            Ok(0)
+        }
+
+        fn load(&self, path: &str) -> Result<Vec<u8>, Error> {
+            self.0
+                .get(path)
+                .map(|text| text.as_bytes().to_vec())
+                .ok_or_else(|| Error::from_string(format!("No file at {}", path)))
+        }
+    }
+
+    const OBJ: &str = "mtllib boxes.mtl\n\
+                       v 0 0 0 1 0 0\nv 1 0 0 0 1 0\nv 1 1 0 0 0 1\nv 0 1 0\n\
+                       vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+                       usemtl red\n\
+                       f 1/1 2/2 3/3 4/4\n\
+                       usemtl glass\n\
+                       f -4/1 -2/3 -1/4\n\
+                       usemtl red\n\
+                       f 1 3 4\n";
+
+    const MTL: &str = "newmtl red\n\
+                       Kd 1 0 0\n\
+                       Ns 100\n\
+                       map_Kd -s 1 1 1 missing.png\n\
+                       newmtl glass\n\
+                       Kd 0.5 0.5 1\n\
+                       d 0.25\n";
+
+    #[test]
+    fn faces_are_grouped_by_material() {
+        let obj = parse_obj(OBJ).unwrap();
+        assert!(obj.has_colors);
+        assert_eq!(obj.material_libs, vec!["boxes.mtl".to_string()]);
+        assert_eq!(obj.groups.len(), 2);
+        assert_eq!(obj.groups[0].material.as_deref(), Some("red"));
+        // The quad is split in two triangles, and the group reused by the second `usemtl red`
+        assert_eq!(obj.groups[0].triangles.len(), 3);
+        assert_eq!(obj.groups[1].material.as_deref(), Some("glass"));
+        assert_eq!(
+            obj.groups[1].triangles[0],
+            [(0, Some(0), None), (2, Some(2), None), (3, Some(3), None)]
+        );
+        assert_eq!(obj.colors[1], [0.0, 1.0, 0.0]);
+        assert_eq!(obj.colors[3], [1.0; 3]);
+        assert_eq!(obj.tex_coords[3], [0.0, 0.0]);
+    }
+
+    #[test]
+    fn invalid_indices_fail() {
+        assert!(parse_obj("v 0 0 0\nv 1 0 0\nf 1 2 3\n").is_err());
+        assert!(parse_obj("v 0 0 0\nv 1 0 0\nv 1 1 0\nf 0 1 2\n").is_err());
+        assert!(parse_obj("v 0 0\n").is_err());
+    }
+
+    #[test]
+    fn materials_are_parsed() {
+        let materials = parse_mtl(MTL).unwrap();
+        let red = &materials["red"];
+        assert_eq!(red.diffuse, [1.0, 0.0, 0.0]);
+        assert_eq!(red.shininess, Some(100.0));
+        assert_eq!(red.diffuse_map.as_deref(), Some("missing.png"));
+        assert_eq!(materials["glass"].dissolve, 0.25);
+    }
+
+    #[test]
+    fn scene_has_an_entity_per_material() {
+        let mut files = HashMap::new();
+        files.insert("models/boxes.obj", OBJ);
+        files.insert("models/boxes.mtl", MTL);
+        let source: Arc<dyn Source> = Arc::new(MemorySource(files));
+
+        let prefab = ObjSceneFormat
+            .import("models/boxes.obj".to_string(), source, None)
+            .unwrap()
+            .data;
+        let children = prefab
+            .entities()
+            .filter_map(|entity| entity.data())
+            .collect::<Vec<_>>();
+        assert_eq!(prefab.len(), 3);
+        assert_eq!(children.len(), 2);
+        // The missing diffuse map of red falls back to its color
+        let red = children[0].material.as_ref().unwrap();
+        assert!(!red.transparent);
+        assert!(red.albedo.is_some() && red.metallic_roughness.is_some());
+        assert!(children[1].material.as_ref().unwrap().transparent);
+        assert!(children.iter().all(|child| child.mesh.is_some()));
+    }
+}
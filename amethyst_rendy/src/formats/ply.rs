@@ -0,0 +1,469 @@
+//! Parser of PLY meshes, in ASCII or binary encoding.
+//!
+//! The `vertex` element provides the positions, and optionally normals, colors and texture
+//! coordinates. The `vertex_indices` lists of the `face` element are triangulated as fans. Other
+//! elements and properties are skipped.
+use super::obj::{Corner, ObjData};
+use amethyst_error::Error;
+use rendy::mesh::MeshBuilder;
+
+/// Loads the faces of a PLY file as a mesh, built like the meshes of OBJ files.
+pub(crate) fn load_ply_mesh(bytes: &[u8]) -> Result<MeshBuilder<'static>, Error> {
+    let (data, triangles) = parse_ply(bytes)?;
+    Ok(data.mesh(&triangles))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+/// Type of a scalar property, or of the count and items of a list property.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    /// Value of the color channel `value`, mapping the range of integer types to `0.0..=1.0`.
+    fn normalize(self, value: f64) -> f32 {
+        let max = match self {
+            Scalar::U8 => 255.0,
+            Scalar::U16 => 65535.0,
+            Scalar::I8 => 127.0,
+            Scalar::I16 => 32767.0,
+            Scalar::I32 => 2_147_483_647.0,
+            Scalar::U32 => 4_294_967_295.0,
+            Scalar::F32 | Scalar::F64 => 1.0,
+        };
+        (value / max) as f32
+    }
+
+    fn decode(self, bytes: &[u8], big_endian: bool) -> f64 {
+        macro_rules! decode {
+            ($ty:ty, $size:expr) => {{
+                let mut array = [0; $size];
+                array.copy_from_slice(bytes);
+                if big_endian {
+                    <$ty>::from_be_bytes(array)
+                } else {
+                    <$ty>::from_le_bytes(array)
+                }
+            }};
+        }
+        match self {
+            Scalar::I8 => f64::from(bytes[0] as i8),
+            Scalar::U8 => f64::from(bytes[0]),
+            Scalar::I16 => f64::from(decode!(i16, 2)),
+            Scalar::U16 => f64::from(decode!(u16, 2)),
+            Scalar::I32 => f64::from(decode!(i32, 4)),
+            Scalar::U32 => f64::from(decode!(u32, 4)),
+            Scalar::F32 => f64::from(decode!(f32, 4)),
+            Scalar::F64 => decode!(f64, 8),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PropertyType {
+    Scalar(Scalar),
+    /// Types of the count and of the items.
+    List(Scalar, Scalar),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Property {
+    name: String,
+    ty: PropertyType,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn has(&self, names: &[&str]) -> bool {
+        names
+            .iter()
+            .all(|name| self.properties.iter().any(|p| p.name == *name))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Header {
+    encoding: Encoding,
+    elements: Vec<Element>,
+}
+
+/// Parses the header, returning it with the offset of the body.
+fn parse_header(bytes: &[u8]) -> Result<(Header, usize), Error> {
+    const END: &[u8] = b"end_header";
+    let end = bytes
+        .windows(END.len())
+        .position(|window| window == END)
+        .ok_or_else(|| Error::from_string("PLY header has no end_header"))?;
+    let body = bytes[end..]
+        .iter()
+        .position(|&byte| byte == b'\n')
+        .map_or(bytes.len(), |newline| end + newline + 1);
+    let text = std::str::from_utf8(&bytes[..end])
+        .map_err(|e| Error::from_string(format!("PLY header is not valid UTF-8: {}", e)))?;
+
+    let mut lines = text.lines().enumerate();
+    if lines.next().map(|(_, line)| line.trim()) != Some("ply") {
+        return Err(Error::from_string("PLY header doesn't start with 'ply'"));
+    }
+    let mut encoding = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for (number, line) in lines {
+        let error = |message: String| {
+            Error::from_string(format!("PLY header line {}: {}", number + 1, message))
+        };
+        let words: Vec<_> = line.split_whitespace().collect();
+        match words.first().cloned() {
+            None | Some("comment") | Some("obj_info") => {}
+            Some("format") if words.len() == 3 => {
+                encoding = Some(match words[1] {
+                    "ascii" => Encoding::Ascii,
+                    "binary_little_endian" => Encoding::BinaryLittleEndian,
+                    "binary_big_endian" => Encoding::BinaryBigEndian,
+                    format => return Err(error(format!("unknown format '{}'", format))),
+                });
+            }
+            Some("element") if words.len() == 3 => {
+                let count = words[2]
+                    .parse()
+                    .map_err(|_| error(format!("invalid count of element '{}'", words[1])))?;
+                elements.push(Element {
+                    name: words[1].to_string(),
+                    count,
+                    properties: Vec::new(),
+                });
+            }
+            Some("property") if words.len() >= 3 => {
+                let name = words[words.len() - 1];
+                let scalar = |word: &str| {
+                    Scalar::parse(word).ok_or_else(|| {
+                        error(format!("unknown type '{}' of property '{}'", word, name))
+                    })
+                };
+                let ty = match words.len() {
+                    3 => PropertyType::Scalar(scalar(words[1])?),
+                    5 if words[1] == "list" => {
+                        PropertyType::List(scalar(words[2])?, scalar(words[3])?)
+                    }
+                    _ => return Err(error(format!("invalid property '{}'", name))),
+                };
+                let element = elements.last_mut().ok_or_else(|| {
+                    error(format!(
+                        "property '{}' is declared before any element",
+                        name
+                    ))
+                })?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    ty,
+                });
+            }
+            _ => return Err(error(format!("invalid line '{}'", line.trim()))),
+        }
+    }
+
+    let encoding = encoding.ok_or_else(|| Error::from_string("PLY header has no format"))?;
+    Ok((Header { encoding, elements }, body))
+}
+
+/// Values of the body of a PLY file.
+enum Values<'a> {
+    Ascii(std::str::SplitWhitespace<'a>),
+    Binary { bytes: &'a [u8], big_endian: bool },
+}
+
+impl<'a> Values<'a> {
+    fn next(&mut self, ty: Scalar) -> Option<f64> {
+        match self {
+            Values::Ascii(words) => words.next()?.parse().ok(),
+            Values::Binary { bytes, big_endian } => {
+                let data: &'a [u8] = *bytes;
+                if data.len() < ty.size() {
+                    return None;
+                }
+                let (value, rest) = data.split_at(ty.size());
+                *bytes = rest;
+                Some(ty.decode(value, *big_endian))
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            Values::Ascii(words) => words.clone().next().is_none(),
+            Values::Binary { bytes, .. } => bytes.is_empty(),
+        }
+    }
+
+    /// Reads the values of the `property` of entry `index` of `element` into `out`, a single
+    /// value for scalar properties.
+    fn read(
+        &mut self,
+        element: &Element,
+        index: usize,
+        property: &Property,
+        out: &mut Vec<f64>,
+    ) -> Result<(), Error> {
+        let missing = || {
+            Error::from_string(format!(
+                "PLY element '{}' {} of {} has no valid value for property '{}'",
+                element.name, index, element.count, property.name
+            ))
+        };
+        out.clear();
+        match property.ty {
+            PropertyType::Scalar(ty) => out.push(self.next(ty).ok_or_else(missing)?),
+            PropertyType::List(count_ty, item_ty) => {
+                let count = self.next(count_ty).ok_or_else(missing)?;
+                for _ in 0..count.max(0.0) as usize {
+                    out.push(self.next(item_ty).ok_or_else(missing)?);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses the vertices of a PLY file, and the triangles of its faces.
+fn parse_ply(bytes: &[u8]) -> Result<(ObjData, Vec<[Corner; 3]>), Error> {
+    let (header, body) = parse_header(bytes)?;
+    let body = &bytes[body..];
+    let mut values = match header.encoding {
+        Encoding::Ascii => Values::Ascii(
+            std::str::from_utf8(body)
+                .map_err(|e| Error::from_string(format!("PLY body is not valid UTF-8: {}", e)))?
+                .split_whitespace(),
+        ),
+        Encoding::BinaryLittleEndian => Values::Binary {
+            bytes: body,
+            big_endian: false,
+        },
+        Encoding::BinaryBigEndian => Values::Binary {
+            bytes: body,
+            big_endian: true,
+        },
+    };
+
+    let vertex = header
+        .elements
+        .iter()
+        .find(|element| element.name == "vertex")
+        .ok_or_else(|| Error::from_string("PLY has no element 'vertex'"))?;
+    for name in &["x", "y", "z"] {
+        if !vertex.has(&[*name]) {
+            return Err(Error::from_string(format!(
+                "PLY element 'vertex' has no property '{}'",
+                name
+            )));
+        }
+    }
+    let has_normals = vertex.has(&["nx", "ny", "nz"]);
+    let has_tex_coords = vertex.has(&["u", "v"])
+        || vertex.has(&["s", "t"])
+        || vertex.has(&["texture_u", "texture_v"]);
+
+    let mut data = ObjData::default();
+    data.has_colors = vertex.has(&["red", "green", "blue"]);
+    let mut triangles = Vec::new();
+    let mut scratch = Vec::new();
+    for element in &header.elements {
+        for index in 0..element.count {
+            let mut position = [0.0; 3];
+            let mut normal = [0.0; 3];
+            let mut color = [1.0; 3];
+            let mut tex_coord = [0.0; 2];
+            for property in &element.properties {
+                values.read(element, index, property, &mut scratch)?;
+                match (element.name.as_str(), property.name.as_str(), property.ty) {
+                    ("vertex", name, PropertyType::Scalar(ty)) => {
+                        let value = scratch[0];
+                        match name {
+                            "x" => position[0] = value as f32,
+                            "y" => position[1] = value as f32,
+                            "z" => position[2] = value as f32,
+                            "nx" => normal[0] = value as f32,
+                            "ny" => normal[1] = value as f32,
+                            "nz" => normal[2] = value as f32,
+                            "red" => color[0] = ty.normalize(value),
+                            "green" => color[1] = ty.normalize(value),
+                            "blue" => color[2] = ty.normalize(value),
+                            "u" | "s" | "texture_u" => tex_coord[0] = value as f32,
+                            "v" | "t" | "texture_v" => tex_coord[1] = value as f32,
+                            _ => {}
+                        }
+                    }
+                    ("face", "vertex_indices", _) | ("face", "vertex_index", _) => {
+                        if scratch.len() < 3 {
+                            return Err(Error::from_string(format!(
+                                "PLY element 'face' {} has {} vertices instead of at least 3",
+                                index,
+                                scratch.len()
+                            )));
+                        }
+                        let mut corners = Vec::with_capacity(scratch.len());
+                        for &vertex_index in &scratch {
+                            if vertex_index < 0.0 || vertex_index as usize >= vertex.count {
+                                return Err(Error::from_string(format!(
+                                    "PLY element 'face' {} references vertex {} of {}",
+                                    index, vertex_index, vertex.count
+                                )));
+                            }
+                            let i = vertex_index as usize;
+                            corners.push((
+                                i,
+                                if has_tex_coords { Some(i) } else { None },
+                                if has_normals { Some(i) } else { None },
+                            ));
+                        }
+                        for i in 1..corners.len() - 1 {
+                            triangles.push([corners[0], corners[i], corners[i + 1]]);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            if element.name == "vertex" {
+                data.positions.push(position);
+                data.colors.push(color);
+                if has_normals {
+                    data.normals.push(normal);
+                }
+                if has_tex_coords {
+                    // PLY coordinates start at the bottom of the image, textures at the top.
+                    data.tex_coords.push([tex_coord[0], 1.0 - tex_coord[1]]);
+                }
+            }
+        }
+    }
+
+    if !values.is_empty() {
+        let last = header.elements.last().map_or("", |element| &element.name);
+        return Err(Error::from_string(format!(
+            "PLY has more data than declared, after the last element '{}'",
+            last
+        )));
+    }
+    Ok((data, triangles))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "element vertex 4\n\
+                          property float x\nproperty float y\nproperty float z\n\
+                          property uchar red\nproperty uchar green\nproperty uchar blue\n\
+                          element face 1\n\
+                          property list uchar int vertex_indices\n\
+                          end_header\n";
+
+    #[test]
+    fn ascii_quad_is_triangulated() {
+        let ply = format!(
+            "ply\nformat ascii 1.0\ncomment quad\n{}\
+             0 0 0 255 0 0\n1 0 0 0 255 0\n1 1 0 0 0 255\n0 1 0 255 255 255\n\
+             4 0 1 2 3\n",
+            HEADER
+        );
+        let (data, triangles) = parse_ply(ply.as_bytes()).unwrap();
+        assert_eq!(data.positions[2], [1.0, 1.0, 0.0]);
+        assert!(data.has_colors);
+        assert_eq!(data.colors[1], [0.0, 1.0, 0.0]);
+        assert!(data.normals.is_empty() && data.tex_coords.is_empty());
+        assert_eq!(
+            triangles,
+            vec![
+                [(0, None, None), (1, None, None), (2, None, None)],
+                [(0, None, None), (2, None, None), (3, None, None)]
+            ]
+        );
+    }
+
+    #[test]
+    fn binary_little_endian_matches_ascii() {
+        let mut ply = format!("ply\nformat binary_little_endian 1.0\n{}", HEADER).into_bytes();
+        let vertices = [
+            [0.0f32, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0],
+        ];
+        for position in &vertices {
+            for coordinate in position {
+                ply.extend_from_slice(&coordinate.to_le_bytes());
+            }
+            ply.extend_from_slice(&[255, 128, 0]);
+        }
+        ply.push(4);
+        for index in 0..4i32 {
+            ply.extend_from_slice(&index.to_le_bytes());
+        }
+
+        let (data, triangles) = parse_ply(&ply).unwrap();
+        assert_eq!(data.positions[3], [0.0, 1.0, 0.0]);
+        assert_eq!(data.colors[0][0], 1.0);
+        assert_eq!(triangles.len(), 2);
+    }
+
+    #[test]
+    fn errors_name_element_and_property() {
+        let error = |ply: &str| parse_ply(ply.as_bytes()).unwrap_err().to_string();
+
+        let short = format!("ply\nformat ascii 1.0\n{}0 0 0 255 0 0\n", HEADER);
+        let message = error(&short);
+        assert!(message.contains("'vertex' 1 of 4") && message.contains("'x'"));
+
+        let unknown = "ply\nformat ascii 1.0\nelement vertex 1\nproperty half x\nend_header\n";
+        assert!(error(unknown).contains("'half' of property 'x'"));
+
+        let extra = "ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\n\
+                     property float y\nproperty float z\nend_header\n0 0 0 0\n";
+        assert!(error(extra).contains("element 'vertex'"));
+
+        assert!(error("ply\nformat ascii 1.0\nelement vertex 1\nend_header\n").contains("'x'"));
+        assert!(parse_ply(b"format ascii 1.0\nend_header\n").is_err());
+    }
+}
@@ -2,6 +2,7 @@
 use crate::types::{Texture, TextureData};
 use amethyst_assets::{
     AssetStorage, Format, Handle, Loader, PrefabData, ProgressCounter, SerializableFormat,
+    Signature, SignatureRegistry,
 };
 use amethyst_core::ecs::{Entity, Read, ReadExpect};
 use amethyst_error::Error;
@@ -100,6 +101,39 @@ impl Format<TextureData> for ImageFormat {
     }
 }
 
+/// Returns a `SignatureRegistry` recognizing the image files `ImageFormat` can load,
+/// to be used with `Loader::load_auto`. All images are imported with `format`.
+pub fn texture_signatures(format: ImageFormat) -> SignatureRegistry<TextureData> {
+    SignatureRegistry::new()
+        .with(
+            "PNG",
+            format.clone(),
+            vec![Signature::new(b"\x89PNG\r\n\x1a\n")],
+        )
+        .with(
+            "JPEG",
+            format.clone(),
+            vec![Signature::new(b"\xff\xd8\xff")],
+        )
+        .with(
+            "GIF",
+            format.clone(),
+            vec![Signature::new(b"GIF87a"), Signature::new(b"GIF89a")],
+        )
+        .with("BMP", format.clone(), vec![Signature::new(b"BM")])
+        .with(
+            "TIFF",
+            format.clone(),
+            vec![Signature::new(b"II*\0"), Signature::new(b"MM\0*")],
+        )
+        .with(
+            "WEBP",
+            format.clone(),
+            vec![Signature::new(b"RIFF").and_at(8, b"WEBP")],
+        )
+        .with("ICO", format, vec![Signature::new(b"\0\0\x01\0")])
+}
+
 /// `PrefabData` for loading `Texture`s.
 ///
 /// Will not add any `Component`s to the `Entity`, will only return a `Handle`
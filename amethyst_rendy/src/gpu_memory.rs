@@ -0,0 +1,289 @@
+//! Accounting of the GPU memory used by the renderer, with an optional soft budget.
+//!
+//! Textures and meshes are recorded with the name of their asset when they are processed, and
+//! render targets when the render graph is built. Everything else allocated from device local
+//! memory, like uniform, dynamic and staging buffers, is accounted as `Buffers`. Figures are
+//! accurate up to the granularity of the allocator's blocks.
+use crate::types::{Backend, Mesh, Texture};
+use amethyst_assets::AssetStorage;
+use amethyst_core::{
+    ecs::{Read, ReadExpect, System, Write},
+    shrev::EventChannel,
+};
+use rendy::{
+    factory::Factory,
+    hal::{format::Format, image::Kind, memory::Properties},
+};
+use std::{collections::HashMap, marker::PhantomData};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Number of allocations named when the budget is exceeded.
+const REPORTED_ALLOCATIONS: usize = 5;
+
+/// Kind of resource GPU memory is used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GpuMemoryCategory {
+    /// Images of `Texture` assets.
+    Textures,
+    /// Vertex and index buffers of `Mesh` assets.
+    Meshes,
+    /// Uniform, dynamic and staging buffers, and any memory not attributed to another category.
+    Buffers,
+    /// Images of the render graph.
+    RenderTargets,
+}
+
+/// A single resource accounted in `GpuMemoryStats`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuAllocation {
+    /// Kind of the resource.
+    pub category: GpuMemoryCategory,
+    /// Name of the asset or render target the memory is used for.
+    pub name: String,
+    /// Size of the resource in bytes.
+    pub bytes: u64,
+}
+
+/// Events sent through an `EventChannel<GpuMemoryEvent>` by `GpuMemorySystem`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GpuMemoryEvent {
+    /// The allocated memory grew over the budget set in `GpuMemoryStats`. Sent once each time
+    /// the budget is crossed.
+    BudgetExceeded {
+        /// Bytes of device local memory allocated.
+        allocated: u64,
+        /// The budget in bytes.
+        budget: u64,
+        /// The largest allocations, from the largest down.
+        largest: Vec<GpuAllocation>,
+    },
+}
+
+/// Resource holding the GPU memory used by the renderer, updated each frame by
+/// `GpuMemorySystem`.
+#[derive(Debug, Default)]
+pub struct GpuMemoryStats {
+    textures: HashMap<u32, GpuAllocation>,
+    meshes: HashMap<u32, GpuAllocation>,
+    render_targets: Vec<GpuAllocation>,
+    buffers: u64,
+    allocated: u64,
+    budget: Option<u64>,
+    over_budget: bool,
+}
+
+impl GpuMemoryStats {
+    /// Bytes used by resources of `category`.
+    pub fn bytes(&self, category: GpuMemoryCategory) -> u64 {
+        match category {
+            GpuMemoryCategory::Textures => self.textures.values().map(|a| a.bytes).sum(),
+            GpuMemoryCategory::Meshes => self.meshes.values().map(|a| a.bytes).sum(),
+            GpuMemoryCategory::Buffers => self.buffers,
+            GpuMemoryCategory::RenderTargets => self.render_targets.iter().map(|a| a.bytes).sum(),
+        }
+    }
+
+    /// Total bytes of device local memory allocated from the driver.
+    pub fn allocated(&self) -> u64 {
+        self.allocated
+    }
+
+    /// The soft budget in bytes, if any.
+    pub fn budget(&self) -> Option<u64> {
+        self.budget
+    }
+
+    /// Sets a soft budget in bytes. Exceeding it logs a warning and sends a
+    /// `GpuMemoryEvent::BudgetExceeded`, but allocations are not prevented.
+    pub fn set_budget(&mut self, budget: Option<u64>) {
+        self.budget = budget;
+        self.over_budget = false;
+    }
+
+    /// Whether the allocated memory is currently over the budget.
+    pub fn over_budget(&self) -> bool {
+        self.over_budget
+    }
+
+    /// Returns the `count` largest textures, meshes and render targets, from the largest down.
+    pub fn largest(&self, count: usize) -> Vec<GpuAllocation> {
+        let mut allocations: Vec<_> = self
+            .textures
+            .values()
+            .chain(self.meshes.values())
+            .chain(self.render_targets.iter())
+            .collect();
+        allocations.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+        allocations.into_iter().take(count).cloned().collect()
+    }
+
+    pub(crate) fn track_texture(&mut self, id: u32, name: &str, bytes: u64) {
+        self.textures
+            .insert(id, allocation(GpuMemoryCategory::Textures, name, bytes));
+    }
+
+    pub(crate) fn track_mesh(&mut self, id: u32, name: &str, bytes: u64) {
+        self.meshes
+            .insert(id, allocation(GpuMemoryCategory::Meshes, name, bytes));
+    }
+
+    pub(crate) fn set_render_targets(&mut self, render_targets: Vec<GpuAllocation>) {
+        self.render_targets = render_targets;
+    }
+
+    /// Updates the totals from the memory allocated from the driver, returning the event to send
+    /// if the budget was just exceeded.
+    fn update(&mut self, allocated: u64) -> Option<GpuMemoryEvent> {
+        self.allocated = allocated;
+        let tracked = self.bytes(GpuMemoryCategory::Textures)
+            + self.bytes(GpuMemoryCategory::Meshes)
+            + self.bytes(GpuMemoryCategory::RenderTargets);
+        self.buffers = allocated.saturating_sub(tracked);
+
+        let over_budget = self.budget.map_or(false, |budget| allocated > budget);
+        let crossed = over_budget && !self.over_budget;
+        self.over_budget = over_budget;
+        if !crossed {
+            return None;
+        }
+        Some(GpuMemoryEvent::BudgetExceeded {
+            allocated,
+            budget: self.budget.unwrap_or(0),
+            largest: self.largest(REPORTED_ALLOCATIONS),
+        })
+    }
+}
+
+fn allocation(category: GpuMemoryCategory, name: &str, bytes: u64) -> GpuAllocation {
+    GpuAllocation {
+        category,
+        name: name.to_string(),
+        bytes,
+    }
+}
+
+/// Bytes of an image of `kind` with `levels` mip levels in `format`.
+pub fn image_bytes(kind: Kind, levels: u8, format: Format) -> u64 {
+    let desc = format.surface_desc();
+    let (block_width, block_height) = (u32::from(desc.dim.0), u32::from(desc.dim.1));
+    let extent = kind.extent();
+    let layers = u64::from(kind.num_layers());
+    (0..levels.max(1))
+        .map(|level| {
+            let level = extent.at_level(level);
+            let blocks_x = (level.width + block_width - 1) / block_width;
+            let blocks_y = (level.height + block_height - 1) / block_height;
+            u64::from(blocks_x) * u64::from(blocks_y) * u64::from(level.depth)
+        })
+        .sum::<u64>()
+        * layers
+        * u64::from(desc.bits / 8)
+}
+
+/// Bytes of device local memory of the factory, either allocated from the driver, or effectively
+/// handed out to resources.
+pub(crate) fn device_local_bytes<B: Backend>(factory: &Factory<B>, effective: bool) -> u64 {
+    factory
+        .memory_utilization()
+        .types
+        .iter()
+        .filter(|t| t.properties.contains(Properties::DEVICE_LOCAL))
+        .map(|t| {
+            if effective {
+                t.utilization.effective
+            } else {
+                t.utilization.used
+            }
+        })
+        .sum()
+}
+
+/// Updates `GpuMemoryStats` each frame, and reports when its budget is exceeded.
+#[derive(Debug, derivative::Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct GpuMemorySystem<B: Backend>(PhantomData<B>);
+impl<'a, B: Backend> System<'a> for GpuMemorySystem<B> {
+    type SystemData = (
+        ReadExpect<'a, Factory<B>>,
+        Read<'a, AssetStorage<Texture>>,
+        Read<'a, AssetStorage<Mesh>>,
+        Write<'a, GpuMemoryStats>,
+        Write<'a, EventChannel<GpuMemoryEvent>>,
+    );
+
+    fn run(&mut self, (factory, textures, meshes, mut stats, mut events): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("gpu_memory_system");
+
+        stats.textures.retain(|id, _| textures.contains_id(*id));
+        stats.meshes.retain(|id, _| meshes.contains_id(*id));
+
+        if let Some(event) = stats.update(device_local_bytes(&*factory, false)) {
+            if let GpuMemoryEvent::BudgetExceeded {
+                allocated,
+                budget,
+                ref largest,
+            } = event
+            {
+                let largest = largest
+                    .iter()
+                    .map(|a| format!("{:?} {:?} ({:.1} MiB)", a.category, a.name, mib(a.bytes)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                log::warn!(
+                    "GPU memory budget exceeded: {:.1} MiB allocated of {:.1} MiB. Largest allocations: {}",
+                    mib(allocated),
+                    mib(budget),
+                    largest
+                );
+            }
+            events.single_write(event);
+        }
+    }
+}
+
+fn mib(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_bytes_of_mip_chain() {
+        let kind = Kind::D2(4, 4, 1, 1);
+        assert_eq!(image_bytes(kind, 1, Format::Rgba8Unorm), 64);
+        assert_eq!(image_bytes(kind, 3, Format::Rgba8Unorm), 64 + 16 + 4);
+        // 4x4 blocks of 8 bytes
+        assert_eq!(
+            image_bytes(Kind::D2(6, 4, 2, 1), 1, Format::Bc1RgbUnorm),
+            32
+        );
+    }
+
+    #[test]
+    fn budget_event_sent_once_when_crossed() {
+        let mut stats = GpuMemoryStats::default();
+        stats.set_budget(Some(1000));
+        stats.track_texture(0, "small.png", 100);
+        stats.track_texture(1, "large.png", 600);
+        stats.track_mesh(0, "mesh.obj", 200);
+
+        assert_eq!(stats.update(900), None);
+        assert_eq!(stats.bytes(GpuMemoryCategory::Buffers), 0);
+
+        match stats.update(1200) {
+            Some(GpuMemoryEvent::BudgetExceeded { largest, .. }) => {
+                assert_eq!(largest[0].name, "large.png");
+                assert_eq!(largest[1].name, "mesh.obj");
+            }
+            None => panic!("expected the budget to be exceeded"),
+        }
+        assert_eq!(stats.bytes(GpuMemoryCategory::Buffers), 300);
+        assert_eq!(stats.update(1300), None);
+        assert!(stats.over_budget());
+    }
+}
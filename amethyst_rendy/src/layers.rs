@@ -0,0 +1,84 @@
+//! Render layers, filtering which entities a camera draws and which lights affect them.
+use amethyst_assets::PrefabData;
+use amethyst_core::ecs::prelude::{Component, DenseVecStorage, Entity, WriteStorage};
+use amethyst_error::Error;
+
+/// Bitmask of the layers an entity belongs to.
+///
+/// * On a renderable, it is drawn by the cameras sharing at least one layer with it.
+/// * On a camera, it draws the renderables sharing at least one layer with it.
+/// * On a light, it only affects the renderables sharing at least one layer with it.
+///
+/// Entities without the component are on all layers.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize, PrefabData,
+)]
+#[prefab(Component)]
+pub struct RenderLayers(pub u32);
+
+impl RenderLayers {
+    /// All layers, the default.
+    pub const ALL: RenderLayers = RenderLayers(std::u32::MAX);
+    /// No layer, never drawn and never lit.
+    pub const NONE: RenderLayers = RenderLayers(0);
+
+    /// Only the given layer, between 0 and 31.
+    pub fn layer(layer: u8) -> Self {
+        RenderLayers::NONE.with(layer)
+    }
+
+    /// Adds the given layer, between 0 and 31.
+    pub fn with(self, layer: u8) -> Self {
+        assert!(layer < 32, "Render layer {} out of range", layer);
+        RenderLayers(self.0 | 1 << layer)
+    }
+
+    /// Removes the given layer, between 0 and 31.
+    pub fn without(self, layer: u8) -> Self {
+        assert!(layer < 32, "Render layer {} out of range", layer);
+        RenderLayers(self.0 & !(1 << layer))
+    }
+
+    /// Returns true if both masks share at least one layer.
+    pub fn intersects(self, other: RenderLayers) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    /// Returns the layers of an entity, all layers without the component.
+    pub fn of(layers: Option<&RenderLayers>) -> Self {
+        layers.copied().unwrap_or_default()
+    }
+}
+
+impl Default for RenderLayers {
+    fn default() -> Self {
+        RenderLayers::ALL
+    }
+}
+
+impl Component for RenderLayers {
+    type Storage = DenseVecStorage<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RenderLayers;
+
+    #[test]
+    fn default_intersects_every_layer() {
+        let default = RenderLayers::default();
+        for layer in 0..32 {
+            assert!(default.intersects(RenderLayers::layer(layer)));
+        }
+        assert!(!default.intersects(RenderLayers::NONE));
+    }
+
+    #[test]
+    fn disjoint_layers_do_not_intersect() {
+        let world = RenderLayers::layer(0).with(2);
+        let weapon = RenderLayers::layer(1);
+        assert!(!world.intersects(weapon));
+        assert!(world.intersects(RenderLayers::layer(2)));
+        assert!(!world.without(2).intersects(RenderLayers::layer(2)));
+    }
+}
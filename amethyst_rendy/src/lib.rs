@@ -16,12 +16,27 @@
 //! * [`DrawShadedDesc`](crate::pass::shaded::DrawShadedDesc)
 //! * [`DrawSkyboxDesc`](crate::pass::skybox::DrawSkyboxDesc)
 //! * [`DrawDebugLinesDesc`](crate::pass::debug_lines::DrawDebugLinesDesc)
+//! * [`DrawOutlineMaskDesc`](crate::pass::outline::DrawOutlineMaskDesc)
+//! * [`DrawOutlineDesc`](crate::pass::outline::DrawOutlineDesc)
+//! * [`FullscreenPassDesc`](crate::pass::fullscreen::FullscreenPassDesc)
+//! * [`DrawColorGradeDesc`](crate::pass::color_grade::DrawColorGradeDesc)
+//! * [`DrawDofBlurDesc`](crate::pass::dof::DrawDofBlurDesc)
+//! * [`DrawDepthOfFieldDesc`](crate::pass::dof::DrawDepthOfFieldDesc)
+//! * [`DrawPointShadowDesc`](crate::pass::point_shadow::DrawPointShadowDesc)
+//! * [`DrawDecalReceiversDesc`](crate::pass::decal::DrawDecalReceiversDesc)
+//! * [`DrawDecalDesc`](crate::pass::decal::DrawDecalDesc)
+//! * [`DrawTerrainDesc`](crate::pass::terrain::DrawTerrainDesc)
+//! * [`DrawWaterSceneDesc`](crate::pass::water::DrawWaterSceneDesc)
+//! * [`DrawWaterDesc`](crate::pass::water::DrawWaterDesc)
 //!
 //! ## Systems
 //!
 //! * [`RenderingSystem`](crate::system::RenderingSystem)
+//! * [`MorphSystem`](crate::morph::MorphSystem)
 //! * [`VisibilitySortingSystem`](crate::visibility::VisibilitySortingSystem)
 //! * [`SpriteVisibilitySortingSystem`](crate::sprite_visibility::SpriteVisibilitySortingSystem)
+//! * [`PointShadowSystem`](crate::point_shadow::PointShadowSystem)
+//! * [`TerrainSystem`](crate::terrain::TerrainSystem)
 //!
 //! ## Components
 //!
@@ -33,6 +48,12 @@
 //! * [`Light`](light::Light)
 //! * [`Tint`](resources::Tint)
 //! * [`JointTransforms`](skinning::JointTransforms)
+//! * [`MorphWeights`](morph::MorphWeights)
+//! * [`MorphMesh`](morph::MorphMesh)
+//! * [`Outlined`](outline::Outlined)
+//! * [`Decal`](decal::Decal)
+//! * [`Terrain`](terrain::Terrain)
+//! * [`WaterPlane`](water::WaterPlane)
 //! * [`SpriteRender`](sprite::SpriteRender)
 
 #![warn(
@@ -58,16 +79,26 @@ pub use rendy;
 
 pub mod pass;
 
+pub mod atlas;
 pub mod batch;
 pub mod bundle;
 pub mod camera;
 pub mod debug_drawing;
+pub mod decal;
+pub mod dynamic_mesh;
+pub mod dynamic_texture;
 pub mod error;
 pub mod formats;
+pub mod gpu_memory;
+pub mod layers;
 pub mod light;
+pub mod lightmap;
+pub mod morph;
 pub mod mtl;
+pub mod outline;
 pub mod pipeline;
 pub mod plugins;
+pub mod point_shadow;
 pub mod resources;
 pub mod serde_shim;
 pub mod shape;
@@ -76,9 +107,13 @@ pub mod sprite;
 pub mod sprite_visibility;
 pub mod submodules;
 pub mod system;
+pub mod terrain;
 pub mod transparent;
+pub mod transparent_order;
 pub mod types;
+pub mod vertex;
 pub mod visibility;
+pub mod water;
 
 pub mod pod;
 pub mod util;
@@ -88,19 +123,29 @@ mod render_test_bundle;
 
 #[doc(inline)]
 pub use crate::{
-    bundle::{RenderPlugin, RenderingBundle},
+    atlas::{AtlasImage, TextureAtlas},
+    bundle::{HeadlessRenderingBundle, RenderPlugin, RenderingBundle},
     camera::{ActiveCamera, Camera},
+    decal::Decal,
+    dynamic_mesh::{DynamicMesh, DynamicMeshData, DynamicMeshError, GrowthPolicy},
+    dynamic_texture::{DynamicTexture, DynamicTextureError, TextureRegion},
     formats::{
         mesh::MeshPrefab,
         texture::{ImageFormat, TexturePrefab},
     },
+    gpu_memory::{GpuMemoryCategory, GpuMemoryEvent, GpuMemoryStats},
     mtl::{Material, MaterialDefaults},
+    outline::Outlined,
     plugins::*,
     sprite::{Sprite, SpriteRender, SpriteSheet, SpriteSheetFormat},
-    system::{GraphCreator, MeshProcessorSystem, RenderingSystem, TextureProcessorSystem},
+    system::{
+        DynamicMeshSystem, DynamicTextureSystem, GraphCreator, HeadlessAssetProcessorSystem,
+        MeshProcessorSystem, RenderingSystem, TextureProcessorSystem, UnloadedAssets,
+    },
     transparent::Transparent,
     types::{Backend, Mesh, Texture},
     util::{simple_shader_set, ChangeDetection},
+    water::WaterPlane,
 };
 
 #[cfg(feature = "test-support")]
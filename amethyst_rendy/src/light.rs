@@ -2,11 +2,11 @@
 //!
 //! TODO: Remove redundant padding once `#[repr(align(...))]` stabilizes.
 
-use crate::resources::AmbientColor;
-use amethyst_assets::{PrefabData, ProgressCounter};
+use crate::{camera::Exposure, resources::AmbientColor, types::Texture};
+use amethyst_assets::{Handle, PrefabData, ProgressCounter};
 use amethyst_core::{
     ecs::prelude::{Component, DenseVecStorage, Entity, WriteStorage},
-    math::Vector3,
+    math::{Matrix4, Perspective3, Point3, Vector3},
 };
 use amethyst_error::Error;
 
@@ -35,7 +35,7 @@ pub struct DirectionalLight {
     /// Color of the light in SRGB format.
     #[serde(with = "crate::serde_shim::srgb")]
     pub color: palette::Srgb,
-    /// Brightness of the light source, different unit from Spot and PointLight.
+    /// Brightness of the light source, in lux with `LightUnits::Physical`.
     pub intensity: f32,
     /// Direction that the light is pointing.
     pub direction: Vector3<f32>,
@@ -61,7 +61,8 @@ impl From<DirectionalLight> for Light {
 ///
 /// Lighting calculations are based off of the Frostbite engine's lighting,
 /// which is explained in detail here in [this presentation][fb]. Below is
-/// equation 26, which we used for the lighting evaluation.
+/// equation 26, evaluated by `Attenuation::InverseSquare`. Its window term fades
+/// the other models out to the radius as well, except `Attenuation::Legacy`.
 ///
 /// <p align="center">
 ///     <img src="https://latex.codecogs.com/gif.latex?\dpi{100}&space;E_
@@ -84,13 +85,21 @@ pub struct PointLight {
     /// Color of the light in SRGB format.
     #[serde(with = "crate::serde_shim::srgb")]
     pub color: palette::Srgb,
-    /// Brightness of the light source, in lumens.
+    /// Brightness of the light source, in lumens with `LightUnits::Physical`.
     pub intensity: f32,
     /// Maximum radius of the point light's affected area.
     pub radius: f32,
     /// Smoothness of the light-to-dark transition from the center to the
     /// radius.
     pub smoothness: f32,
+    /// How the brightness falls off with the distance to the light.
+    pub attenuation: Attenuation,
+    /// Whether the light casts shadows, see `PointShadowSettings`. Meshes farther than `radius`
+    /// from the light don't cast shadows.
+    pub casts_shadows: bool,
+    /// Width and height in pixels of each face of the shadow cube map, limited by
+    /// `PointShadowSettings::resolution`.
+    pub shadow_resolution: u32,
 }
 
 impl Default for PointLight {
@@ -100,6 +109,53 @@ impl Default for PointLight {
             intensity: 10.0,
             radius: 10.0,
             smoothness: 4.0,
+            attenuation: Attenuation::Legacy,
+            casts_shadows: false,
+            shadow_resolution: 512,
+        }
+    }
+}
+
+/// How the brightness of a `PointLight` falls off with the distance *d* to the light.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum Attenuation {
+    /// The falloff of point lights before attenuation models could be chosen, for scenes made
+    /// with it to look the same: inverse square without a cutoff, and no falloff at all in the PBR
+    /// pass. Ignores `radius` and `smoothness`.
+    Legacy,
+    /// Physical falloff, `intensity / d²`.
+    InverseSquare,
+    /// Artistic falloff, `intensity * (1 - d² / radius²)^exponent`.
+    Exponent(f32),
+    /// Falloff with explicit terms, `intensity / (constant + linear * d + quadratic * d²)`.
+    Coefficients {
+        /// Constant term.
+        constant: f32,
+        /// Term proportional to the distance.
+        linear: f32,
+        /// Term proportional to the squared distance.
+        quadratic: f32,
+    },
+}
+
+impl Default for Attenuation {
+    fn default() -> Self {
+        Attenuation::Legacy
+    }
+}
+
+impl Attenuation {
+    /// Returns the model index evaluated by the shaders, and its parameters.
+    pub(crate) fn shader_model(&self) -> (i32, [f32; 3]) {
+        match *self {
+            Attenuation::Legacy => (0, [0.0; 3]),
+            Attenuation::InverseSquare => (1, [0.0; 3]),
+            Attenuation::Exponent(exponent) => (2, [exponent, 0.0, 0.0]),
+            Attenuation::Coefficients {
+                constant,
+                linear,
+                quadratic,
+            } => (3, [constant, linear, quadratic]),
         }
     }
 }
@@ -111,6 +167,9 @@ impl From<PointLight> for Light {
 }
 
 /// A spot light source.
+///
+/// A `cookie` texture can be projected from the light along its cone, modulating its color, as
+/// for flashlights or stained-glass windows. Up to 4 distinct cookies are drawn at once.
 #[repr(C)]
 #[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
 #[serde(default)]
@@ -122,13 +181,16 @@ pub struct SpotLight {
     pub color: palette::Srgb,
     /// Direction that the light is pointing.
     pub direction: Vector3<f32>,
-    /// Brightness of the light source, in lumens.
+    /// Brightness of the light source, in candela with `LightUnits::Physical`.
     pub intensity: f32,
     /// Range/length of the light source.
     pub range: f32,
     /// Smoothness of the light-to-dark transition from the center to the
     /// radius.
     pub smoothness: f32,
+    /// Texture projected from the light, nothing is projected outside of the cone.
+    #[serde(skip)]
+    pub cookie: Option<Handle<Texture>>,
 }
 
 impl Default for SpotLight {
@@ -140,10 +202,34 @@ impl Default for SpotLight {
             intensity: 10.0,
             range: 10.0,
             smoothness: 4.0,
+            cookie: None,
         }
     }
 }
 
+impl SpotLight {
+    /// Projection of the cookie of a light at `position`, mapping its cone to the `-1..1` square
+    /// of clip space.
+    pub fn cookie_projection(&self, position: &Point3<f32>) -> Matrix4<f32> {
+        let direction = if self.direction.norm_squared() > 0.0 {
+            self.direction.normalize()
+        } else {
+            -Vector3::y()
+        };
+        let up = if direction.cross(&Vector3::y()).norm_squared() > 1e-6 {
+            Vector3::y()
+        } else {
+            Vector3::z()
+        };
+        let view = Matrix4::look_at_rh(position, &(position + direction), &up);
+        let fovy = (2.0 * self.angle)
+            .max(1e-3)
+            .min(std::f32::consts::PI - 1e-3);
+        let range = self.range.max(1e-3);
+        Perspective3::new(1.0, fovy, range * 1e-3, range).to_homogeneous() * view
+    }
+}
+
 impl From<SpotLight> for Light {
     fn from(sp: SpotLight) -> Self {
         Light::Spot(sp)
@@ -187,6 +273,53 @@ impl Component for Light {
     type Storage = DenseVecStorage<Self>;
 }
 
+/// Resource selecting the units of the `intensity` of lights.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum LightUnits {
+    /// Intensities are unitless factors of the light colors, and the `Exposure` of cameras is
+    /// ignored. Scenes made before physical units look the same.
+    Legacy,
+    /// Point lights are in lumens, spot lights in candela, directional and sun lights in lux.
+    /// `AmbientColor` is the luminance of a white surface in cd/m², scaled like the
+    /// `EnvironmentMap` by the `Exposure` of the active camera along with all lights.
+    ///
+    /// Emission of materials isn't exposed.
+    Physical,
+}
+
+impl Default for LightUnits {
+    fn default() -> Self {
+        LightUnits::Legacy
+    }
+}
+
+impl LightUnits {
+    /// Returns the intensity of `light` as evaluated by the shaders: candela for point and spot
+    /// lights, lux for directional and sun lights.
+    pub fn shader_intensity(self, light: &Light) -> f32 {
+        let (intensity, scale) = match light {
+            Light::Area => (0.0, 1.0),
+            Light::Directional(light) => (light.intensity, 1.0),
+            // A point light radiates its power over the 4π steradians of the sphere.
+            Light::Point(light) => (light.intensity, 1.0 / (4.0 * std::f32::consts::PI)),
+            Light::Spot(light) => (light.intensity, 1.0),
+            Light::Sun(light) => (light.intensity, 1.0),
+        };
+        match self {
+            LightUnits::Legacy => intensity,
+            LightUnits::Physical => intensity * scale,
+        }
+    }
+
+    /// Returns the factor applied to all lighting seen through a camera with `exposure`.
+    pub fn exposure_scale(self, exposure: &Exposure) -> f32 {
+        match self {
+            LightUnits::Legacy => 1.0,
+            LightUnits::Physical => exposure.scale(),
+        }
+    }
+}
+
 /// Prefab for lighting
 #[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize, PrefabData)]
 #[serde(default)]
@@ -194,3 +327,91 @@ pub struct LightPrefab {
     light: Option<Light>,
     ambient_color: Option<AmbientColor>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::math::Vector4;
+
+    fn project(light: &SpotLight, position: &Point3<f32>, point: Point3<f32>) -> Vector4<f32> {
+        let clip = light.cookie_projection(position) * point.to_homogeneous();
+        clip / clip.w
+    }
+
+    #[test]
+    fn cookie_projection_centers_direction() {
+        let light = SpotLight {
+            direction: Vector3::new(1.0, -1.0, 0.0),
+            ..Default::default()
+        };
+        let position = Point3::new(1.0, 2.0, 3.0);
+        let center = project(&light, &position, Point3::new(3.0, 0.0, 3.0));
+        assert!(center.x.abs() < 1e-4 && center.y.abs() < 1e-4);
+    }
+
+    #[test]
+    fn cookie_projection_covers_cone() {
+        let light = SpotLight {
+            angle: std::f32::consts::FRAC_PI_4,
+            direction: -Vector3::y(),
+            ..Default::default()
+        };
+        let position = Point3::origin();
+        let edge = project(&light, &position, Point3::new(0.0, -1.0, 0.999));
+        assert!(edge.y.abs() < 1.0 && edge.y.abs() > 0.99);
+        let outside = project(&light, &position, Point3::new(0.0, -1.0, 1.1));
+        assert!(outside.y.abs() > 1.0);
+    }
+
+    #[test]
+    fn point_lights_without_attenuation_keep_legacy_falloff() {
+        let light: Light = ron::de::from_str("Point((intensity: 5.0, radius: 2.0))").unwrap();
+        match light {
+            Light::Point(light) => assert_eq!(light.attenuation, Attenuation::Legacy),
+            _ => panic!("expected a point light"),
+        }
+
+        let light: Light = ron::de::from_str(
+            "Point((attenuation: Coefficients(constant: 1.0, linear: 0.5, quadratic: 0.25)))",
+        )
+        .unwrap();
+        match light {
+            Light::Point(light) => {
+                assert_eq!(light.attenuation.shader_model(), (3, [1.0, 0.5, 0.25]))
+            }
+            _ => panic!("expected a point light"),
+        }
+    }
+
+    #[test]
+    fn physical_point_light_on_white_diffuse_surface() {
+        let light = Light::Point(PointLight {
+            intensity: 1000.0,
+            ..Default::default()
+        });
+        let exposure = LightUnits::Physical.exposure_scale(&Exposure::Ev100(12.0));
+        // Lambertian white surface facing the light 1 meter away: L = E / π with E = I / d².
+        let illuminance = LightUnits::Physical.shader_intensity(&light) / (1.0 * 1.0);
+        let pixel = illuminance / std::f32::consts::PI * exposure;
+        assert!((pixel - 0.005_153).abs() < 1e-6, "{}", pixel);
+
+        assert_eq!(LightUnits::Legacy.shader_intensity(&light), 1000.0);
+        assert_eq!(
+            LightUnits::Legacy.exposure_scale(&Exposure::Ev100(12.0)),
+            1.0
+        );
+    }
+
+    #[test]
+    fn manual_exposure_matches_ev100() {
+        let manual = |iso| Exposure::Manual {
+            aperture: 16.0,
+            shutter_speed: 1.0 / 125.0,
+            iso,
+        };
+        // Sunny 16 rule: f/16 at 1/125s and ISO 100 for a sunny day.
+        assert!((manual(100.0).ev100() - 14.966).abs() < 1e-3);
+        // Doubling the sensitivity lowers the exposure value by a stop.
+        assert!((manual(200.0).ev100() - (manual(100.0).ev100() - 1.0)).abs() < 1e-4);
+    }
+}
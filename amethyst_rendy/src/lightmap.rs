@@ -0,0 +1,33 @@
+//! Baked lighting of static geometry, sampled from a `Material::lightmap` with the second set of
+//! texture coordinates, `TexCoord2`.
+use serde::{Deserialize, Serialize};
+
+/// How the lightmap of a `Material` is combined with the rest of the lighting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum LightmapMode {
+    /// The lightmap is ignored.
+    Disabled,
+    /// The ambient and dynamic lighting is multiplied by the lightmap, which then holds the baked
+    /// shadows and occlusion.
+    Multiply,
+    /// The lightmap is added to the ambient and dynamic lighting, which then holds the baked
+    /// irradiance.
+    Add,
+}
+
+impl Default for LightmapMode {
+    fn default() -> Self {
+        LightmapMode::Disabled
+    }
+}
+
+impl LightmapMode {
+    /// Value of the mode in the material uniform, see `pod::Material`.
+    pub(crate) fn shader_mode(self) -> i32 {
+        match self {
+            LightmapMode::Disabled => 0,
+            LightmapMode::Multiply => 1,
+            LightmapMode::Add => 2,
+        }
+    }
+}
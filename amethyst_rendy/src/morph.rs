@@ -0,0 +1,264 @@
+//! Morph target (blend shape) support for meshes.
+//!
+//! Morph targets are blended on the CPU: when the `MorphWeights` driving a `MorphMesh` change,
+//! the `MorphSystem` computes the weighted sum of the active targets and uploads it as a new
+//! `Mesh`. Meshes without morph targets have no `MorphMesh` and are never touched.
+use std::sync::Arc;
+
+use amethyst_assets::{AssetStorage, Handle, Loader, PrefabData};
+use amethyst_core::ecs::prelude::{
+    Component, DenseVecStorage, Entities, Entity, Join, Read, ReadExpect, ReadStorage, System,
+    WriteStorage,
+};
+use amethyst_error::Error;
+use rendy::mesh::{MeshBuilder, Normal, Position};
+use serde::{Deserialize, Serialize};
+
+use crate::types::{Mesh, MeshData};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Maximum number of morph targets blended into a mesh at once, the targets with the largest
+/// weights are used.
+pub const MAX_ACTIVE_MORPH_TARGETS: usize = 8;
+
+/// Vertex offsets of a single morph target.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MorphTarget {
+    /// Offset of the position of each vertex
+    pub positions: Vec<[f32; 3]>,
+    /// Offset of the normal of each vertex, empty if the target doesn't change the normals
+    pub normals: Vec<[f32; 3]>,
+}
+
+/// Vertex data of a mesh with morph targets, kept on the CPU for blending.
+#[derive(Clone, Debug)]
+pub struct MorphMeshData {
+    /// Indices and all vertex attributes of the mesh except for the positions and normals
+    pub rest: MeshData,
+    /// Positions of the vertices without any morph target applied
+    pub positions: Vec<[f32; 3]>,
+    /// Normals of the vertices without any morph target applied, if the mesh has normals
+    pub normals: Option<Vec<[f32; 3]>>,
+    /// Morph targets of the mesh
+    pub targets: Vec<MorphTarget>,
+}
+
+impl MorphMeshData {
+    /// Build the mesh with the morph targets applied with the given weights.
+    ///
+    /// Only the `MAX_ACTIVE_MORPH_TARGETS` targets with the largest weights are applied, missing
+    /// weights are 0.
+    pub fn blend(&self, weights: &[f32]) -> MeshBuilder<'static> {
+        let active = active_targets(weights, MAX_ACTIVE_MORPH_TARGETS);
+        let mut positions = self.positions.clone();
+        let mut normals = self.normals.clone();
+        for &(index, weight) in &active {
+            let target = match self.targets.get(index) {
+                Some(target) => target,
+                None => continue,
+            };
+            add_weighted(&mut positions, &target.positions, weight);
+            if let Some(ref mut normals) = normals {
+                add_weighted(normals, &target.normals, weight);
+            }
+        }
+
+        let mut builder = self.rest.0.clone();
+        builder.add_vertices(positions.into_iter().map(Position).collect::<Vec<_>>());
+        if let Some(normals) = normals {
+            builder.add_vertices(
+                normals
+                    .into_iter()
+                    .map(|n| Normal(normalize(n)))
+                    .collect::<Vec<_>>(),
+            );
+        }
+        builder
+    }
+}
+
+fn add_weighted(values: &mut [[f32; 3]], offsets: &[[f32; 3]], weight: f32) {
+    for (value, offset) in values.iter_mut().zip(offsets) {
+        for (v, o) in value.iter_mut().zip(offset) {
+            *v += o * weight;
+        }
+    }
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let length = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if length > 0. {
+        [v[0] / length, v[1] / length, v[2] / length]
+    } else {
+        v
+    }
+}
+
+/// Indices and weights of the non-zero weights with the largest magnitude, at most `max` of them.
+fn active_targets(weights: &[f32], max: usize) -> Vec<(usize, f32)> {
+    let mut active = weights
+        .iter()
+        .cloned()
+        .enumerate()
+        .filter(|&(_, w)| w != 0.)
+        .collect::<Vec<_>>();
+    active.sort_by(|a, b| {
+        b.1.abs()
+            .partial_cmp(&a.1.abs())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    active.truncate(max);
+    active
+}
+
+/// Weights of morph targets, animatable with the `MorphChannel` of `amethyst_animation`.
+///
+/// A single `MorphWeights` can drive several `MorphMesh`es, e.g. all primitives of a mesh.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MorphWeights {
+    /// Weight of each morph target
+    pub weights: Vec<f32>,
+}
+
+impl MorphWeights {
+    /// Create weights for the given targets.
+    pub fn new(weights: Vec<f32>) -> Self {
+        MorphWeights { weights }
+    }
+}
+
+impl Component for MorphWeights {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl<'a> PrefabData<'a> for MorphWeights {
+    type SystemData = WriteStorage<'a, MorphWeights>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        storage: &mut Self::SystemData,
+        _: &[Entity],
+        _: &[Entity],
+    ) -> Result<(), Error> {
+        storage.insert(entity, self.clone())?;
+        Ok(())
+    }
+}
+
+/// Mesh with morph targets, rebuilt by the `MorphSystem` whenever the `MorphWeights` on the
+/// `weights` entity change.
+#[derive(Clone, Debug)]
+pub struct MorphMesh {
+    /// Entity with the `MorphWeights` of the mesh
+    pub weights: Entity,
+    /// Vertex data of the mesh
+    pub data: Arc<MorphMeshData>,
+    applied: Option<Vec<f32>>,
+}
+
+impl MorphMesh {
+    /// Create a morph mesh driven by the `MorphWeights` on the `weights` entity.
+    pub fn new(weights: Entity, data: Arc<MorphMeshData>) -> Self {
+        MorphMesh {
+            weights,
+            data,
+            applied: None,
+        }
+    }
+}
+
+impl Component for MorphMesh {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Prefab for `MorphMesh`
+#[derive(Clone, Debug)]
+pub struct MorphMeshPrefab {
+    /// Index of the `Entity` with the `MorphWeights`
+    pub weights: usize,
+    /// Vertex data of the mesh
+    pub data: Arc<MorphMeshData>,
+}
+
+impl<'a> PrefabData<'a> for MorphMeshPrefab {
+    type SystemData = WriteStorage<'a, MorphMesh>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        storage: &mut Self::SystemData,
+        entities: &[Entity],
+        _: &[Entity],
+    ) -> Result<(), Error> {
+        storage.insert(
+            entity,
+            MorphMesh::new(entities[self.weights], self.data.clone()),
+        )?;
+        Ok(())
+    }
+}
+
+/// Uploads the blended mesh of each `MorphMesh` whose weights changed, replacing the
+/// `Handle<Mesh>` of the entity.
+#[derive(Debug, Default)]
+pub struct MorphSystem;
+
+impl<'a> System<'a> for MorphSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Loader>,
+        Read<'a, AssetStorage<Mesh>>,
+        ReadStorage<'a, MorphWeights>,
+        WriteStorage<'a, MorphMesh>,
+        WriteStorage<'a, Handle<Mesh>>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, loader, mesh_storage, weights, mut morphs, mut meshes): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("morph_system");
+
+        for (entity, morph) in (&entities, &mut morphs).join() {
+            let weights = match weights.get(morph.weights) {
+                Some(weights) => &weights.weights,
+                None => continue,
+            };
+            if morph.applied.as_ref() == Some(weights) {
+                continue;
+            }
+            let mesh = loader.load_from_data(morph.data.blend(weights).into(), (), &mesh_storage);
+            if let Err(err) = meshes.insert(entity, mesh) {
+                log::error!("Failed to replace the mesh of a MorphMesh: {}", err);
+            }
+            morph.applied = Some(weights.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{active_targets, add_weighted};
+
+    #[test]
+    fn largest_weights_are_active() {
+        assert_eq!(
+            vec![(2, -0.9), (0, 0.5)],
+            active_targets(&[0.5, 0., -0.9, 0.1], 2)
+        );
+        assert!(active_targets(&[0., 0.], 8).is_empty());
+    }
+
+    #[test]
+    fn offsets_are_weighted() {
+        let mut positions = vec![[1., 0., 0.], [0., 1., 0.]];
+        add_weighted(&mut positions, &[[2., 0., 0.], [0., 0., 4.]], 0.5);
+        assert_eq!(vec![[2., 0., 0.], [0., 1., 2.]], positions);
+    }
+}
@@ -1,6 +1,6 @@
 //! Physically-based material.
 
-use crate::types::Texture;
+use crate::{lightmap::LightmapMode, types::Texture};
 use amethyst_assets::{Asset, Handle};
 use amethyst_core::ecs::prelude::DenseVecStorage;
 
@@ -41,6 +41,15 @@ pub struct Material {
     pub cavity: Handle<Texture>,
     /// Texture offset
     pub uv_offset: TextureOffset,
+    /// Draw the albedo multiplied with the vertex color, without any lighting
+    pub unlit: bool,
+    /// Lightmap, sampled with the `TexCoord2` of the mesh.
+    pub lightmap: Handle<Texture>,
+    /// How the lightmap is combined with the lighting, disabled by default. Only opaque meshes
+    /// without skinning are lightmapped.
+    pub lightmap_mode: LightmapMode,
+    /// Skip the point, directional and spot lights, for surfaces fully lit by their lightmap
+    pub skip_dynamic_lights: bool,
 }
 
 impl Asset for Material {
@@ -83,6 +92,17 @@ pub type FullTextureSet = (
     TexCavity,
 );
 
+/// Type alias for a tuple collection of a complete PBR texture set followed by the lightmap.
+pub type LightmapTextureSet = (
+    TexAlbedo,
+    TexEmission,
+    TexNormal,
+    TexMetallicRoughness,
+    TexAmbientOcclusion,
+    TexCavity,
+    TexLightmap,
+);
+
 macro_rules! impl_texture {
     ($name:ident, $prop:ident) => {
         #[doc = "Macro Generated Texture Type"]
@@ -104,6 +124,7 @@ impl_texture!(TexNormal, normal);
 impl_texture!(TexMetallicRoughness, metallic_roughness);
 impl_texture!(TexAmbientOcclusion, ambient_occlusion);
 impl_texture!(TexCavity, cavity);
+impl_texture!(TexLightmap, lightmap);
 
 macro_rules! recursive_iter {
     (@value $first:expr, $($rest:expr),*) => { $first.chain(recursive_iter!(@value $($rest),*)) };
@@ -136,3 +157,4 @@ impl_texture_set_tuple!(A, B, C);
 impl_texture_set_tuple!(A, B, C, D);
 impl_texture_set_tuple!(A, B, C, D, E);
 impl_texture_set_tuple!(A, B, C, D, E, F);
+impl_texture_set_tuple!(A, B, C, D, E, F, G);
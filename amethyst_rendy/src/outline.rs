@@ -0,0 +1,52 @@
+//! Colored outlines around selected meshes, drawn by `DrawOutlineMaskDesc` and `DrawOutlineDesc`.
+use amethyst_assets::PrefabData;
+use amethyst_core::ecs::prelude::{Component, DenseVecStorage, Entity, WriteStorage};
+use amethyst_error::Error;
+
+/// Thickness of the widest outline in pixels, thicker outlines are clamped.
+pub const MAX_OUTLINE_THICKNESS: f32 = 8.0;
+
+/// Draws an outline around the mesh of the entity.
+///
+/// The outline is drawn around the silhouette of the mesh, over everything in front of it.
+/// Parts of the outline around occluded parts of the mesh are drawn with `occluded_opacity`, so
+/// they are hidden at 0.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize, PrefabData)]
+#[prefab(Component)]
+#[serde(default)]
+pub struct Outlined {
+    /// Color of the outline.
+    #[serde(with = "crate::serde_shim::srgba")]
+    pub color: palette::Srgba,
+    /// Thickness of the outline in pixels, up to `MAX_OUTLINE_THICKNESS`.
+    pub thickness: f32,
+    /// Opacity of the outline around occluded parts of the mesh, relative to `color`.
+    pub occluded_opacity: f32,
+}
+
+impl Outlined {
+    /// Creates an outline of the given color and thickness in pixels, hidden where occluded.
+    pub fn new(color: palette::Srgba, thickness: f32) -> Self {
+        Outlined {
+            color,
+            thickness,
+            occluded_opacity: 0.0,
+        }
+    }
+
+    /// Sets the opacity of the outline around occluded parts of the mesh.
+    pub fn with_occluded_opacity(mut self, occluded_opacity: f32) -> Self {
+        self.occluded_opacity = occluded_opacity;
+        self
+    }
+}
+
+impl Default for Outlined {
+    fn default() -> Self {
+        Outlined::new(palette::Srgba::new(1.0, 0.6, 0.0, 1.0), 2.0)
+    }
+}
+
+impl Component for Outlined {
+    type Storage = DenseVecStorage<Self>;
+}
@@ -1,14 +1,20 @@
 use crate::{
     batch::{GroupIterator, OrderedTwoLevelBatch, TwoLevelBatch},
+    layers::RenderLayers,
+    lightmap::LightmapMode,
     mtl::{FullTextureSet, Material, StaticTextureSet},
     pipeline::{PipelineDescBuilder, PipelinesBuilder},
     pod::{SkinnedVertexArgs, VertexArgs},
-    resources::Tint,
-    skinning::JointTransforms,
-    submodules::{DynamicVertexBuffer, EnvironmentSub, MaterialId, MaterialSub, SkinningSub},
+    resources::{ClipMode, Tint},
+    skinning::{JointTransforms, SkinningMode},
+    submodules::{
+        DynamicVertexBuffer, EnvironmentMapSub, EnvironmentSub, MaterialId, MaterialSub,
+        SkinningSub, ENVIRONMENT_MAP_IMAGES,
+    },
     transparent::Transparent,
     types::{Backend, Mesh},
     util,
+    vertex::TexCoord2,
     visibility::Visibility,
 };
 use amethyst_assets::{AssetStorage, Handle};
@@ -23,14 +29,14 @@ use rendy::{
     factory::Factory,
     graph::{
         render::{PrepareResult, RenderGroup, RenderGroupDesc},
-        GraphContext, NodeBuffer, NodeImage,
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
     },
-    hal::{self, device::Device, pso},
+    hal::{self, adapter::PhysicalDevice, device::Device, pso, Features},
     mesh::{AsVertex, VertexFormat},
     shader::{Shader, SpirvShader},
 };
 use smallvec::SmallVec;
-use std::marker::PhantomData;
+use std::{borrow::Cow, marker::PhantomData};
 
 macro_rules! profile_scope_impl {
     ($string:expr) => {
@@ -58,14 +64,54 @@ pub trait Base3DPassDef: 'static + std::fmt::Debug + Send + Sync {
     /// Returns the vertex `SpirvShader` which will be used for this pass on skinned meshes
     fn vertex_skinned_shader() -> &'static SpirvShader;
 
+    /// Returns the vertex `SpirvShader` which will be used for this pass on skinned meshes with
+    /// `SkinningMode::DualQuaternion`. Passes without one always use linear blend skinning.
+    fn vertex_dual_quaternion_skinned_shader() -> Option<&'static SpirvShader> {
+        None
+    }
+
+    /// Returns the vertex `SpirvShader` writing the distances to the `ClipPlanes`, used instead
+    /// of `vertex_shader` by passes clipped on devices with clip distances. Without one, the
+    /// fragment shader discards the clipped fragments.
+    fn vertex_clip_shader() -> Option<&'static SpirvShader> {
+        None
+    }
+
     /// Returns the fragment `SpirvShader` which will be used for this pass
     fn fragment_shader() -> &'static SpirvShader;
 
+    /// Returns the fragment `SpirvShader` writing transparent surfaces to the weighted blended
+    /// transparency targets, see `DrawOitAccumDesc`. Passes without one always use sorted
+    /// blending.
+    fn oit_fragment_shader() -> Option<&'static SpirvShader> {
+        None
+    }
+
+    /// Returns the fragment `SpirvShader` adding image-based ambient lighting from the maps baked
+    /// by `DrawIblBakeDesc`, bound at set 3. Passes without one keep the ambient color.
+    fn ibl_fragment_shader() -> Option<&'static SpirvShader> {
+        None
+    }
+
+    /// Returns the vertex and fragment `SpirvShader`s drawing opaque meshes whose material has a
+    /// lightmap, which is the last texture of the `TextureSet`. Passes without them ignore
+    /// lightmaps.
+    fn lightmap_shaders() -> Option<(&'static SpirvShader, &'static SpirvShader)> {
+        None
+    }
+
     /// Returns the `VertexFormat` of this pass
     fn base_format() -> Vec<VertexFormat>;
 
     /// Returns the `VertexFormat` of this pass for skinned meshes
     fn skinned_format() -> Vec<VertexFormat>;
+
+    /// Returns the `VertexFormat` of this pass for meshes with a lightmap
+    fn lightmap_format() -> Vec<VertexFormat> {
+        let mut format = Self::base_format();
+        format.push(TexCoord2::vertex());
+        format
+    }
 }
 
 /// Draw opaque 3d meshes with specified shaders and texture set
@@ -73,6 +119,10 @@ pub trait Base3DPassDef: 'static + std::fmt::Debug + Send + Sync {
 #[derivative(Debug(bound = ""), Default(bound = ""))]
 pub struct DrawBase3DDesc<B: Backend, T: Base3DPassDef> {
     skinning: bool,
+    skinning_mode: SkinningMode,
+    environment_map: bool,
+    point_shadows: bool,
+    clip_mode: ClipMode,
     marker: PhantomData<(B, T)>,
 }
 
@@ -86,7 +136,7 @@ impl<B: Backend, T: Base3DPassDef> DrawBase3DDesc<B, T> {
     pub fn skinned() -> Self {
         Self {
             skinning: true,
-            marker: PhantomData,
+            ..Default::default()
         }
     }
 
@@ -95,12 +145,51 @@ impl<B: Backend, T: Base3DPassDef> DrawBase3DDesc<B, T> {
         self.skinning = skinned;
         self
     }
+
+    /// Create pass blending the joints of skinned meshes with the given mode, if supported by
+    /// the pass.
+    pub fn with_skinning_mode(mut self, mode: SkinningMode) -> Self {
+        self.skinning_mode = supported_skinning_mode::<T>(mode);
+        self
+    }
+
+    /// Create pass with image-based lighting enabled if true is passed and supported by the
+    /// pass. The group must then be built with the images baked by `DrawIblBakeDesc`.
+    pub fn with_environment_map(mut self, environment_map: bool) -> Self {
+        self.environment_map = environment_map && T::ibl_fragment_shader().is_some();
+        self
+    }
+
+    /// Create pass shading point lights with shadows if true is passed. The group must then be
+    /// built with the atlas drawn by `DrawPointShadowDesc`, after the environment map images.
+    pub fn with_point_shadows(mut self, point_shadows: bool) -> Self {
+        self.point_shadows = point_shadows;
+        self
+    }
+
+    /// Create pass clipped by the `ClipPlanes` resource in the given mode.
+    pub fn with_clip_planes(mut self, mode: ClipMode) -> Self {
+        self.clip_mode = mode;
+        self
+    }
 }
 
 impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DDesc<B, T> {
+    fn images(&self) -> Vec<ImageAccess> {
+        let mut images = if self.environment_map {
+            EnvironmentMapSub::<B>::image_accesses()
+        } else {
+            Vec::new()
+        };
+        if self.point_shadows {
+            images.push(EnvironmentSub::<B>::point_shadow_access());
+        }
+        images
+    }
+
     fn build(
         self,
-        _ctx: &GraphContext<B>,
+        ctx: &GraphContext<B>,
         factory: &mut Factory<B>,
         _queue: QueueId,
         _aux: &World,
@@ -108,22 +197,41 @@ impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DDesc<
         framebuffer_height: u32,
         subpass: hal::pass::Subpass<'_, B>,
         _buffers: Vec<NodeBuffer>,
-        _images: Vec<NodeImage>,
+        images: Vec<NodeImage>,
     ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
         profile_scope_impl!("build");
 
-        let env = EnvironmentSub::new(
+        // The fragment shaders read the clip planes of the view.
+        let mut env = EnvironmentSub::new(
             factory,
             [
-                hal::pso::ShaderStageFlags::VERTEX,
+                hal::pso::ShaderStageFlags::VERTEX | hal::pso::ShaderStageFlags::FRAGMENT,
                 hal::pso::ShaderStageFlags::FRAGMENT,
             ],
         )?;
+        env.set_clip_mode(self.clip_mode);
         let materials = MaterialSub::new(factory)?;
-        let skinning = SkinningSub::new(factory)?;
+        let skinning = SkinningSub::new(factory, self.skinning_mode)?;
+        let environment_map = if self.environment_map {
+            Some(EnvironmentMapSub::new(ctx, factory, &images)?)
+        } else {
+            None
+        };
+        if self.point_shadows {
+            let atlas = images
+                .get(
+                    environment_map
+                        .as_ref()
+                        .map_or(0, |_| ENVIRONMENT_MAP_IMAGES),
+                )
+                .ok_or_else(|| failure::format_err!("Point shadow atlas image is missing."))?;
+            env.set_point_shadows(ctx, factory, atlas)?;
+        }
 
         let mut vertex_format_base = T::base_format();
         let mut vertex_format_skinned = T::skinned_format();
+        let mut vertex_format_lightmap = T::lightmap_format();
+        let lightmap = T::lightmap_shaders().is_some();
 
         let (mut pipelines, pipeline_layout) = build_pipelines::<B, T>(
             factory,
@@ -132,8 +240,13 @@ impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DDesc<
             framebuffer_height,
             &vertex_format_base,
             &vertex_format_skinned,
+            &vertex_format_lightmap,
             self.skinning,
+            self.skinning_mode,
+            lightmap,
             false,
+            self.clip_mode,
+            environment_map.as_ref(),
             vec![
                 env.raw_layout(),
                 materials.raw_layout(),
@@ -143,20 +256,28 @@ impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DDesc<
 
         vertex_format_base.sort();
         vertex_format_skinned.sort();
+        vertex_format_lightmap.sort();
+
+        let pipeline_lightmap = if lightmap { pipelines.pop() } else { None };
 
         Ok(Box::new(DrawBase3D::<B, T> {
             pipeline_basic: pipelines.remove(0),
             pipeline_skinned: pipelines.pop(),
+            pipeline_lightmap,
             pipeline_layout,
             static_batches: Default::default(),
             skinned_batches: Default::default(),
+            lightmap_batches: Default::default(),
             vertex_format_base,
             vertex_format_skinned,
+            vertex_format_lightmap,
             env,
+            environment_map,
             materials,
             skinning,
             models: DynamicVertexBuffer::new(),
             skinned_models: DynamicVertexBuffer::new(),
+            lightmap_models: DynamicVertexBuffer::new(),
             marker: PhantomData,
         }))
     }
@@ -169,16 +290,21 @@ impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DDesc<
 pub struct DrawBase3D<B: Backend, T: Base3DPassDef> {
     pipeline_basic: B::GraphicsPipeline,
     pipeline_skinned: Option<B::GraphicsPipeline>,
+    pipeline_lightmap: Option<B::GraphicsPipeline>,
     pipeline_layout: B::PipelineLayout,
-    static_batches: TwoLevelBatch<MaterialId, u32, SmallVec<[VertexArgs; 4]>>,
-    skinned_batches: TwoLevelBatch<MaterialId, u32, SmallVec<[SkinnedVertexArgs; 4]>>,
+    static_batches: TwoLevelBatch<(usize, MaterialId), u32, SmallVec<[VertexArgs; 4]>>,
+    skinned_batches: TwoLevelBatch<(usize, MaterialId), u32, SmallVec<[SkinnedVertexArgs; 4]>>,
+    lightmap_batches: TwoLevelBatch<(usize, MaterialId), u32, SmallVec<[VertexArgs; 4]>>,
     vertex_format_base: Vec<VertexFormat>,
     vertex_format_skinned: Vec<VertexFormat>,
+    vertex_format_lightmap: Vec<VertexFormat>,
     env: EnvironmentSub<B>,
+    environment_map: Option<EnvironmentMapSub<B>>,
     materials: MaterialSub<B, T::TextureSet>,
     skinning: SkinningSub<B>,
     models: DynamicVertexBuffer<B, VertexArgs>,
     skinned_models: DynamicVertexBuffer<B, SkinnedVertexArgs>,
+    lightmap_models: DynamicVertexBuffer<B, VertexArgs>,
     marker: PhantomData<T>,
 }
 
@@ -195,6 +321,7 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
 
         let (
             mesh_storage,
+            material_storage,
             visibility,
             transparent,
             hiddens,
@@ -204,8 +331,10 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
             transforms,
             joints,
             tints,
+            layers,
         ) = <(
             Read<'_, AssetStorage<Mesh>>,
+            Read<'_, AssetStorage<Material>>,
             ReadExpect<'_, Visibility>,
             ReadStorage<'_, Transparent>,
             ReadStorage<'_, Hidden>,
@@ -215,33 +344,68 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
             ReadStorage<'_, Transform>,
             ReadStorage<'_, JointTransforms>,
             ReadStorage<'_, Tint>,
+            ReadStorage<'_, RenderLayers>,
         )>::fetch(resources);
 
-        // Prepare environment
-        self.env.process(factory, index, resources);
         self.materials.maintain();
 
         self.static_batches.clear_inner();
         self.skinned_batches.clear_inner();
+        self.lightmap_batches.clear_inner();
 
         let materials_ref = &mut self.materials;
         let skinning_ref = &mut self.skinning;
+        let env_ref = &mut self.env;
         let statics_ref = &mut self.static_batches;
         let skinned_ref = &mut self.skinned_batches;
-
-        let static_input = || ((&materials, &meshes, &transforms, tints.maybe()), !&joints);
-        let skinned_input = || (&materials, &meshes, &transforms, tints.maybe(), &joints);
+        let lightmap_ref = &mut self.lightmap_batches;
+        let lightmap = self.pipeline_lightmap.is_some();
+
+        let static_input = || {
+            (
+                (
+                    &materials,
+                    &meshes,
+                    &transforms,
+                    tints.maybe(),
+                    layers.maybe(),
+                ),
+                !&joints,
+            )
+        };
+        let skinned_input = || {
+            (
+                &materials,
+                &meshes,
+                &transforms,
+                tints.maybe(),
+                layers.maybe(),
+                &joints,
+            )
+        };
         {
             profile_scope_impl!("prepare");
             (static_input(), &visibility.visible_unordered)
                 .join()
-                .map(|(((mat, mesh, tform, tint), _), _)| {
-                    ((mat, mesh.id()), VertexArgs::from_object_data(tform, tint))
+                .map(|(((mat, mesh, tform, tint, layers), _), _)| {
+                    (
+                        (mat, mesh.id(), RenderLayers::of(layers)),
+                        VertexArgs::from_object_data(tform, tint),
+                    )
                 })
-                .for_each_group(|(mat, mesh_id), data| {
+                .for_each_group(|(mat, mesh_id, layers), data| {
                     if mesh_storage.contains_id(mesh_id) {
+                        let lightmapped = lightmap
+                            && material_storage
+                                .get(mat)
+                                .map_or(false, |m| m.lightmap_mode != LightmapMode::Disabled);
                         if let Some((mat, _)) = materials_ref.insert(factory, resources, mat) {
-                            statics_ref.insert(mat, mesh_id, data.drain(..));
+                            let key = (env_ref.layer_slot(layers), mat);
+                            if lightmapped {
+                                lightmap_ref.insert(key, mesh_id, data.drain(..));
+                            } else {
+                                statics_ref.insert(key, mesh_id, data.drain(..));
+                            }
                         }
                     }
                 });
@@ -251,9 +415,9 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
 
             (skinned_input(), &visibility.visible_unordered)
                 .join()
-                .map(|((mat, mesh, tform, tint, joints), _)| {
+                .map(|((mat, mesh, tform, tint, layers, joints), _)| {
                     (
-                        (mat, mesh.id()),
+                        (mat, mesh.id(), RenderLayers::of(layers)),
                         SkinnedVertexArgs::from_object_data(
                             tform,
                             tint,
@@ -261,20 +425,25 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
                         ),
                     )
                 })
-                .for_each_group(|(mat, mesh_id), data| {
+                .for_each_group(|(mat, mesh_id, layers), data| {
                     if mesh_storage.contains_id(mesh_id) {
                         if let Some((mat, _)) = materials_ref.insert(factory, resources, mat) {
-                            skinned_ref.insert(mat, mesh_id, data.drain(..));
+                            let key = (env_ref.layer_slot(layers), mat);
+                            skinned_ref.insert(key, mesh_id, data.drain(..));
                         }
                     }
                 });
         };
 
+        // Prepare the environments of the layers drawn this frame
+        self.env.process(factory, index, resources);
+
         {
             profile_scope_impl!("write");
 
             self.static_batches.prune();
             self.skinned_batches.prune();
+            self.lightmap_batches.prune();
 
             self.models.write(
                 factory,
@@ -289,6 +458,13 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
                 self.skinned_batches.count() as u64,
                 self.skinned_batches.data(),
             );
+
+            self.lightmap_models.write(
+                factory,
+                index,
+                self.lightmap_batches.count() as u64,
+                self.lightmap_batches.data(),
+            );
             self.skinning.commit(factory, index);
         }
         PrepareResult::DrawRecord
@@ -306,14 +482,20 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
         let mesh_storage = <Read<'_, AssetStorage<Mesh>>>::fetch(resources);
         let models_loc = self.vertex_format_base.len() as u32;
         let skin_models_loc = self.vertex_format_skinned.len() as u32;
+        let lightmap_models_loc = self.vertex_format_lightmap.len() as u32;
 
         encoder.bind_graphics_pipeline(&self.pipeline_basic);
         self.env.bind(index, &self.pipeline_layout, 0, &mut encoder);
+        if let Some(environment_map) = self.environment_map.as_ref() {
+            environment_map.bind(&self.pipeline_layout, 3, &mut encoder);
+        }
 
         if self.models.bind(index, models_loc, 0, &mut encoder) {
             let mut instances_drawn = 0;
-            for (&mat_id, batches) in self.static_batches.iter() {
+            for (&(slot, mat_id), batches) in self.static_batches.iter() {
                 if self.materials.loaded(mat_id) {
+                    self.env
+                        .bind_layers(index, slot, &self.pipeline_layout, 0, &mut encoder);
                     self.materials
                         .bind(&self.pipeline_layout, 1, mat_id, &mut encoder);
                     for (mesh_id, batch_data) in batches {
@@ -335,6 +517,46 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
             }
         }
 
+        if let Some(pipeline_lightmap) = self.pipeline_lightmap.as_ref() {
+            encoder.bind_graphics_pipeline(pipeline_lightmap);
+
+            if self
+                .lightmap_models
+                .bind(index, lightmap_models_loc, 0, &mut encoder)
+            {
+                let mut instances_drawn = 0;
+                for (&(slot, mat_id), batches) in self.lightmap_batches.iter() {
+                    if self.materials.loaded(mat_id) {
+                        self.env
+                            .bind_layers(index, slot, &self.pipeline_layout, 0, &mut encoder);
+                        self.materials
+                            .bind(&self.pipeline_layout, 1, mat_id, &mut encoder);
+                        for (mesh_id, batch_data) in batches {
+                            debug_assert!(mesh_storage.contains_id(*mesh_id));
+                            if let Some(mesh) = B::unwrap_mesh(unsafe {
+                                mesh_storage.get_by_id_unchecked(*mesh_id)
+                            }) {
+                                if let Err(error) = mesh.bind_and_draw(
+                                    0,
+                                    &self.vertex_format_lightmap,
+                                    instances_drawn..instances_drawn + batch_data.len() as u32,
+                                    &mut encoder,
+                                ) {
+                                    log::warn!(
+                                        "Trying to draw a lightmapped mesh that lacks {:?} vertex attributes. Pass {} requires attributes {:?}.",
+                                        error.not_found.attributes,
+                                        T::NAME,
+                                        T::lightmap_format(),
+                                    );
+                                }
+                            }
+                            instances_drawn += batch_data.len() as u32;
+                        }
+                    }
+                }
+            }
+        }
+
         if let Some(pipeline_skinned) = self.pipeline_skinned.as_ref() {
             encoder.bind_graphics_pipeline(pipeline_skinned);
 
@@ -346,8 +568,10 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
                     .bind(index, &self.pipeline_layout, 2, &mut encoder);
 
                 let mut instances_drawn = 0;
-                for (&mat_id, batches) in self.skinned_batches.iter() {
+                for (&(slot, mat_id), batches) in self.skinned_batches.iter() {
                     if self.materials.loaded(mat_id) {
+                        self.env
+                            .bind_layers(index, slot, &self.pipeline_layout, 0, &mut encoder);
                         self.materials
                             .bind(&self.pipeline_layout, 1, mat_id, &mut encoder);
                         for (mesh_id, batch_data) in batches {
@@ -380,6 +604,9 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
             if let Some(pipeline) = self.pipeline_skinned.take() {
                 factory.device().destroy_graphics_pipeline(pipeline);
             }
+            if let Some(pipeline) = self.pipeline_lightmap.take() {
+                factory.device().destroy_graphics_pipeline(pipeline);
+            }
             factory
                 .device()
                 .destroy_pipeline_layout(self.pipeline_layout);
@@ -392,23 +619,24 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
 #[derivative(Debug(bound = ""), Default(bound = ""))]
 pub struct DrawBase3DTransparentDesc<B: Backend, T: Base3DPassDef> {
     skinning: bool,
+    skinning_mode: SkinningMode,
+    environment_map: bool,
+    point_shadows: bool,
+    clip_mode: ClipMode,
     marker: PhantomData<(B, T)>,
 }
 
 impl<B: Backend, T: Base3DPassDef> DrawBase3DTransparentDesc<B, T> {
     /// Create pass in default configuration
     pub fn new() -> Self {
-        Self {
-            skinning: false,
-            marker: PhantomData,
-        }
+        Default::default()
     }
 
     /// Create pass in with vertex skinning enabled
     pub fn skinned() -> Self {
         Self {
             skinning: true,
-            marker: PhantomData,
+            ..Default::default()
         }
     }
 
@@ -417,12 +645,51 @@ impl<B: Backend, T: Base3DPassDef> DrawBase3DTransparentDesc<B, T> {
         self.skinning = skinned;
         self
     }
+
+    /// Create pass blending the joints of skinned meshes with the given mode, if supported by
+    /// the pass.
+    pub fn with_skinning_mode(mut self, mode: SkinningMode) -> Self {
+        self.skinning_mode = supported_skinning_mode::<T>(mode);
+        self
+    }
+
+    /// Create pass with image-based lighting enabled if true is passed and supported by the
+    /// pass. The group must then be built with the images baked by `DrawIblBakeDesc`.
+    pub fn with_environment_map(mut self, environment_map: bool) -> Self {
+        self.environment_map = environment_map && T::ibl_fragment_shader().is_some();
+        self
+    }
+
+    /// Create pass shading point lights with shadows if true is passed. The group must then be
+    /// built with the atlas drawn by `DrawPointShadowDesc`, after the environment map images.
+    pub fn with_point_shadows(mut self, point_shadows: bool) -> Self {
+        self.point_shadows = point_shadows;
+        self
+    }
+
+    /// Create pass clipped by the `ClipPlanes` resource in the given mode.
+    pub fn with_clip_planes(mut self, mode: ClipMode) -> Self {
+        self.clip_mode = mode;
+        self
+    }
 }
 
 impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DTransparentDesc<B, T> {
+    fn images(&self) -> Vec<ImageAccess> {
+        let mut images = if self.environment_map {
+            EnvironmentMapSub::<B>::image_accesses()
+        } else {
+            Vec::new()
+        };
+        if self.point_shadows {
+            images.push(EnvironmentSub::<B>::point_shadow_access());
+        }
+        images
+    }
+
     fn build(
         self,
-        _ctx: &GraphContext<B>,
+        ctx: &GraphContext<B>,
         factory: &mut Factory<B>,
         _queue: QueueId,
         _aux: &World,
@@ -430,18 +697,35 @@ impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DTrans
         framebuffer_height: u32,
         subpass: hal::pass::Subpass<'_, B>,
         _buffers: Vec<NodeBuffer>,
-        _images: Vec<NodeImage>,
+        images: Vec<NodeImage>,
     ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
-        let env = EnvironmentSub::new(
+        // The fragment shaders read the clip planes of the view.
+        let mut env = EnvironmentSub::new(
             factory,
             [
-                hal::pso::ShaderStageFlags::VERTEX,
+                hal::pso::ShaderStageFlags::VERTEX | hal::pso::ShaderStageFlags::FRAGMENT,
                 hal::pso::ShaderStageFlags::FRAGMENT,
             ],
         )?;
+        env.set_clip_mode(self.clip_mode);
 
         let materials = MaterialSub::new(factory)?;
-        let skinning = SkinningSub::new(factory)?;
+        let skinning = SkinningSub::new(factory, self.skinning_mode)?;
+        let environment_map = if self.environment_map {
+            Some(EnvironmentMapSub::new(ctx, factory, &images)?)
+        } else {
+            None
+        };
+        if self.point_shadows {
+            let atlas = images
+                .get(
+                    environment_map
+                        .as_ref()
+                        .map_or(0, |_| ENVIRONMENT_MAP_IMAGES),
+                )
+                .ok_or_else(|| failure::format_err!("Point shadow atlas image is missing."))?;
+            env.set_point_shadows(ctx, factory, atlas)?;
+        }
 
         let mut vertex_format_base = T::base_format();
         let mut vertex_format_skinned = T::skinned_format();
@@ -453,8 +737,13 @@ impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DTrans
             framebuffer_height,
             &vertex_format_base,
             &vertex_format_skinned,
+            &[],
             self.skinning,
+            self.skinning_mode,
+            false,
             true,
+            self.clip_mode,
+            environment_map.as_ref(),
             vec![
                 env.raw_layout(),
                 materials.raw_layout(),
@@ -474,6 +763,7 @@ impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DTrans
             vertex_format_base,
             vertex_format_skinned,
             env,
+            environment_map,
             materials,
             skinning,
             models: DynamicVertexBuffer::new(),
@@ -491,11 +781,12 @@ pub struct DrawBase3DTransparent<B: Backend, T: Base3DPassDef> {
     pipeline_basic: B::GraphicsPipeline,
     pipeline_skinned: Option<B::GraphicsPipeline>,
     pipeline_layout: B::PipelineLayout,
-    static_batches: OrderedTwoLevelBatch<MaterialId, u32, VertexArgs>,
-    skinned_batches: OrderedTwoLevelBatch<MaterialId, u32, SkinnedVertexArgs>,
+    static_batches: OrderedTwoLevelBatch<(usize, MaterialId), u32, VertexArgs>,
+    skinned_batches: OrderedTwoLevelBatch<(usize, MaterialId), u32, SkinnedVertexArgs>,
     vertex_format_base: Vec<VertexFormat>,
     vertex_format_skinned: Vec<VertexFormat>,
     env: EnvironmentSub<B>,
+    environment_map: Option<EnvironmentMapSub<B>>,
     materials: MaterialSub<B, FullTextureSet>,
     skinning: SkinningSub<B>,
     models: DynamicVertexBuffer<B, VertexArgs>,
@@ -515,7 +806,7 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3DTranspare
     ) -> PrepareResult {
         profile_scope_impl!("prepare transparent");
 
-        let (mesh_storage, visibility, meshes, materials, transforms, joints, tints) =
+        let (mesh_storage, visibility, meshes, materials, transforms, joints, tints, layers) =
             <(
                 Read<'_, AssetStorage<Mesh>>,
                 ReadExpect<'_, Visibility>,
@@ -524,10 +815,9 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3DTranspare
                 ReadStorage<'_, Transform>,
                 ReadStorage<'_, JointTransforms>,
                 ReadStorage<'_, Tint>,
+                ReadStorage<'_, RenderLayers>,
             )>::fetch(resources);
 
-        // Prepare environment
-        self.env.process(factory, index, resources);
         self.materials.maintain();
 
         self.static_batches.swap_clear();
@@ -535,38 +825,61 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3DTranspare
 
         let materials_ref = &mut self.materials;
         let skinning_ref = &mut self.skinning;
+        let env_ref = &mut self.env;
         let statics_ref = &mut self.static_batches;
         let skinned_ref = &mut self.skinned_batches;
         let mut changed = false;
 
-        let mut joined = ((&materials, &meshes, &transforms, tints.maybe()), !&joints).join();
+        let mut joined = (
+            (
+                &materials,
+                &meshes,
+                &transforms,
+                tints.maybe(),
+                layers.maybe(),
+            ),
+            !&joints,
+        )
+            .join();
         visibility
             .visible_ordered
             .iter()
             .filter_map(|e| joined.get_unchecked(e.id()))
-            .map(|((mat, mesh, tform, tint), _)| {
-                ((mat, mesh.id()), VertexArgs::from_object_data(tform, tint))
+            .map(|((mat, mesh, tform, tint, layers), _)| {
+                (
+                    (mat, mesh.id(), RenderLayers::of(layers)),
+                    VertexArgs::from_object_data(tform, tint),
+                )
             })
-            .for_each_group(|(mat, mesh_id), data| {
+            .for_each_group(|(mat, mesh_id, layers), data| {
                 if mesh_storage.contains_id(mesh_id) {
                     if let Some((mat, this_changed)) = materials_ref.insert(factory, resources, mat)
                     {
                         changed = changed || this_changed;
-                        statics_ref.insert(mat, mesh_id, data.drain(..));
+                        let key = (env_ref.layer_slot(layers), mat);
+                        statics_ref.insert(key, mesh_id, data.drain(..));
                     }
                 }
             });
 
         if self.pipeline_skinned.is_some() {
-            let mut joined = (&materials, &meshes, &transforms, tints.maybe(), &joints).join();
+            let mut joined = (
+                &materials,
+                &meshes,
+                &transforms,
+                tints.maybe(),
+                layers.maybe(),
+                &joints,
+            )
+                .join();
 
             visibility
                 .visible_ordered
                 .iter()
                 .filter_map(|e| joined.get_unchecked(e.id()))
-                .map(|(mat, mesh, tform, tint, joints)| {
+                .map(|(mat, mesh, tform, tint, layers, joints)| {
                     (
-                        (mat, mesh.id()),
+                        (mat, mesh.id(), RenderLayers::of(layers)),
                         SkinnedVertexArgs::from_object_data(
                             tform,
                             tint,
@@ -574,18 +887,22 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3DTranspare
                         ),
                     )
                 })
-                .for_each_group(|(mat, mesh_id), data| {
+                .for_each_group(|(mat, mesh_id, layers), data| {
                     if mesh_storage.contains_id(mesh_id) {
                         if let Some((mat, this_changed)) =
                             materials_ref.insert(factory, resources, mat)
                         {
                             changed = changed || this_changed;
-                            skinned_ref.insert(mat, mesh_id, data.drain(..));
+                            let key = (env_ref.layer_slot(layers), mat);
+                            skinned_ref.insert(key, mesh_id, data.drain(..));
                         }
                     }
                 });
         }
 
+        // Prepare the environments of the layers drawn this frame
+        self.env.process(factory, index, resources);
+
         self.models.write(
             factory,
             index,
@@ -626,10 +943,14 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3DTranspare
 
         encoder.bind_graphics_pipeline(&self.pipeline_basic);
         self.env.bind(index, layout, 0, encoder);
+        if let Some(environment_map) = self.environment_map.as_ref() {
+            environment_map.bind(layout, 3, encoder);
+        }
 
         if self.models.bind(index, models_loc, 0, encoder) {
-            for (&mat, batches) in self.static_batches.iter() {
+            for (&(slot, mat), batches) in self.static_batches.iter() {
                 if self.materials.loaded(mat) {
+                    self.env.bind_layers(index, slot, layout, 0, encoder);
                     self.materials.bind(layout, 1, mat, encoder);
                     for (mesh, range) in batches {
                         debug_assert!(mesh_storage.contains_id(*mesh));
@@ -660,8 +981,9 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3DTranspare
 
             if self.skinned_models.bind(index, skin_models_loc, 0, encoder) {
                 self.skinning.bind(index, layout, 2, encoder);
-                for (&mat, batches) in self.skinned_batches.iter() {
+                for (&(slot, mat), batches) in self.skinned_batches.iter() {
                     if self.materials.loaded(mat) {
+                        self.env.bind_layers(index, slot, layout, 0, encoder);
                         self.materials.bind(layout, 1, mat, encoder);
                         for (mesh, range) in batches {
                             debug_assert!(mesh_storage.contains_id(*mesh));
@@ -704,17 +1026,70 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3DTranspare
     }
 }
 
-fn build_pipelines<B: Backend, T: Base3DPassDef>(
+/// Returns `mode` if the pass has a skinned vertex shader for it, linear blend skinning otherwise.
+pub(super) fn supported_skinning_mode<T: Base3DPassDef>(mode: SkinningMode) -> SkinningMode {
+    match mode {
+        SkinningMode::DualQuaternion if T::vertex_dual_quaternion_skinned_shader().is_some() => {
+            mode
+        }
+        _ => SkinningMode::Linear,
+    }
+}
+
+/// Returns the vertex shader of the pass for skinned meshes blended with `mode`.
+pub(super) fn skinned_vertex_shader<T: Base3DPassDef>(mode: SkinningMode) -> &'static SpirvShader {
+    match mode {
+        SkinningMode::Linear => T::vertex_skinned_shader(),
+        SkinningMode::DualQuaternion => {
+            T::vertex_dual_quaternion_skinned_shader().unwrap_or_else(T::vertex_skinned_shader)
+        }
+    }
+}
+
+/// Returns the shader set of `vertex` and `fragment`, the fragment shader discarding what is
+/// clipped by the `ClipPlanes` if `clip_discard` is true.
+fn clipped_shader_set<'a, B: Backend>(
+    vertex: &'a B::ShaderModule,
+    fragment: &'a B::ShaderModule,
+    clip_discard: bool,
+) -> pso::GraphicsShaderSet<'a, B> {
+    let mut set = util::simple_shader_set(vertex, Some(fragment));
+    if clip_discard {
+        if let Some(fragment) = set.fragment.as_mut() {
+            // `CLIP_DISCARD` of header/clip.frag
+            fragment.specialization = pso::Specialization {
+                constants: Cow::Owned(vec![pso::SpecializationConstant { id: 0, range: 0..4 }]),
+                data: Cow::Owned(1u32.to_ne_bytes().to_vec()),
+            };
+        }
+    }
+    set
+}
+
+pub(super) fn build_pipelines<B: Backend, T: Base3DPassDef>(
     factory: &Factory<B>,
     subpass: hal::pass::Subpass<'_, B>,
     framebuffer_width: u32,
     framebuffer_height: u32,
     vertex_format_base: &[VertexFormat],
     vertex_format_skinned: &[VertexFormat],
+    vertex_format_lightmap: &[VertexFormat],
     skinning: bool,
+    skinning_mode: SkinningMode,
+    lightmap: bool,
     transparent: bool,
-    layouts: Vec<&B::DescriptorSetLayout>,
+    clip_mode: ClipMode,
+    environment_map: Option<&EnvironmentMapSub<B>>,
+    mut layouts: Vec<&B::DescriptorSetLayout>,
 ) -> Result<(Vec<B::GraphicsPipeline>, B::PipelineLayout), failure::Error> {
+    let fragment_shader = match (environment_map, T::ibl_fragment_shader()) {
+        (Some(environment_map), Some(shader)) => {
+            layouts.push(environment_map.raw_layout());
+            shader
+        }
+        _ => T::fragment_shader(),
+    };
+
     let pipeline_layout = unsafe {
         factory
             .device()
@@ -730,13 +1105,23 @@ fn build_pipelines<B: Backend, T: Base3DPassDef>(
         )))
         .collect::<Vec<_>>();
 
-    let shader_vertex_basic = unsafe { T::vertex_shader().module(factory).unwrap() };
-    let shader_fragment = unsafe { T::fragment_shader().module(factory).unwrap() };
+    let clip = clip_mode != ClipMode::Disabled;
+    let vertex_clip_shader = T::vertex_clip_shader().filter(|_| {
+        clip && factory
+            .physical()
+            .features()
+            .contains(Features::SHADER_CLIP_DISTANCE)
+    });
+    let vertex_shader_basic = vertex_clip_shader.unwrap_or_else(T::vertex_shader);
+
+    let shader_vertex_basic = unsafe { vertex_shader_basic.module(factory).unwrap() };
+    let shader_fragment = unsafe { fragment_shader.module(factory).unwrap() };
     let pipe_desc = PipelineDescBuilder::new()
         .with_vertex_desc(&vertex_desc)
-        .with_shaders(util::simple_shader_set(
+        .with_shaders(clipped_shader_set(
             &shader_vertex_basic,
-            Some(&shader_fragment),
+            &shader_fragment,
+            clip && vertex_clip_shader.is_none(),
         ))
         .with_layout(&pipeline_layout)
         .with_subpass(subpass)
@@ -755,45 +1140,77 @@ fn build_pipelines<B: Backend, T: Base3DPassDef>(
             },
         }]);
 
-    let pipelines = if skinning {
-        let shader_vertex_skinned = unsafe { T::vertex_skinned_shader().module(factory).unwrap() };
-
-        let vertex_desc = vertex_format_skinned
-            .iter()
-            .map(|f| (f.clone(), pso::VertexInputRate::Vertex))
-            .chain(Some((
-                SkinnedVertexArgs::vertex(),
-                pso::VertexInputRate::Instance(1),
-            )))
-            .collect::<Vec<_>>();
-
-        let pipe = PipelinesBuilder::new()
-            .with_pipeline(pipe_desc.clone())
-            .with_child_pipeline(
-                0,
-                pipe_desc
-                    .with_vertex_desc(&vertex_desc)
-                    .with_shaders(util::simple_shader_set(
-                        &shader_vertex_skinned,
-                        Some(&shader_fragment),
-                    )),
+    let shader_vertex_skinned = if skinning {
+        Some(unsafe {
+            skinned_vertex_shader::<T>(skinning_mode)
+                .module(factory)
+                .unwrap()
+        })
+    } else {
+        None
+    };
+    let shaders_lightmap = match T::lightmap_shaders() {
+        Some((vertex, fragment)) if lightmap => Some(unsafe {
+            (
+                vertex.module(factory).unwrap(),
+                fragment.module(factory).unwrap(),
             )
-            .build(factory, None);
+        }),
+        _ => None,
+    };
 
-        unsafe {
-            factory.destroy_shader_module(shader_vertex_skinned);
-        }
+    let vertex_desc_skinned = vertex_format_skinned
+        .iter()
+        .map(|f| (f.clone(), pso::VertexInputRate::Vertex))
+        .chain(Some((
+            SkinnedVertexArgs::vertex(),
+            pso::VertexInputRate::Instance(1),
+        )))
+        .collect::<Vec<_>>();
+    let vertex_desc_lightmap = vertex_format_lightmap
+        .iter()
+        .map(|f| (f.clone(), pso::VertexInputRate::Vertex))
+        .chain(Some((
+            VertexArgs::vertex(),
+            pso::VertexInputRate::Instance(1),
+        )))
+        .collect::<Vec<_>>();
 
-        pipe
-    } else {
-        PipelinesBuilder::new()
-            .with_pipeline(pipe_desc)
-            .build(factory, None)
-    };
+    // Pipelines are returned in order: basic, skinned, lightmap.
+    let mut builder = PipelinesBuilder::new().with_pipeline(pipe_desc.clone());
+    if let Some(shader_vertex_skinned) = shader_vertex_skinned.as_ref() {
+        builder = builder.with_child_pipeline(
+            0,
+            pipe_desc
+                .clone()
+                .with_vertex_desc(&vertex_desc_skinned)
+                .with_shaders(clipped_shader_set(
+                    shader_vertex_skinned,
+                    &shader_fragment,
+                    clip,
+                )),
+        );
+    }
+    if let Some((shader_vertex, shader_fragment)) = shaders_lightmap.as_ref() {
+        builder = builder.with_child_pipeline(
+            0,
+            pipe_desc
+                .with_vertex_desc(&vertex_desc_lightmap)
+                .with_shaders(clipped_shader_set(shader_vertex, shader_fragment, clip)),
+        );
+    }
+    let pipelines = builder.build(factory, None);
 
     unsafe {
         factory.destroy_shader_module(shader_vertex_basic);
         factory.destroy_shader_module(shader_fragment);
+        if let Some(shader_vertex_skinned) = shader_vertex_skinned {
+            factory.destroy_shader_module(shader_vertex_skinned);
+        }
+        if let Some((shader_vertex, shader_fragment)) = shaders_lightmap {
+            factory.destroy_shader_module(shader_vertex);
+            factory.destroy_shader_module(shader_fragment);
+        }
     }
 
     match pipelines {
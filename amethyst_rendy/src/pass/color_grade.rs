@@ -0,0 +1,288 @@
+//! Final color grade of the image, with scalar controls, a 3D LUT and a vignette.
+use crate::{
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    pod::ColorGradeArgs,
+    resources::ColorGradeSettings,
+    submodules::{DynamicUniform, TextureId, TextureSub},
+    types::Backend,
+    util,
+};
+use amethyst_core::ecs::{Read, SystemData, World};
+use glsl_layout::AsStd140;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
+    },
+    hal::{
+        self,
+        device::Device,
+        format::{Aspects, Swizzle},
+        image::{Filter, SamplerInfo, SubresourceRange, ViewKind, WrapMode},
+        pso,
+    },
+    resource::{
+        DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle, ImageView,
+        ImageViewInfo, Sampler,
+    },
+    shader::Shader,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Draw the source image over the target, graded with the `ColorGradeSettings` resource.
+///
+/// Build with `builder().with_image(source)`, the source image having the size of the target.
+/// With the default settings the source is copied bit for bit, so it should have the format of
+/// the target.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DrawColorGradeDesc;
+
+impl DrawColorGradeDesc {
+    /// Create instance of `DrawColorGrade` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawColorGradeDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![ImageAccess {
+            access: hal::image::Access::SHADER_READ,
+            usage: hal::image::Usage::SAMPLED,
+            layout: hal::image::Layout::ShaderReadOnlyOptimal,
+            stages: pso::PipelineStage::FRAGMENT_SHADER,
+        }]
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build_color_grade");
+
+        let args = DynamicUniform::new(factory, pso::ShaderStageFlags::FRAGMENT)?;
+        let textures = TextureSub::new(factory)?;
+        // Defined like the layout of `TextureSub`, so the source set can stand in for the LUT.
+        let layout: RendyHandle<DescriptorSetLayout<B>> = factory
+            .create_descriptor_set_layout(util::set_layout_bindings(Some((
+                1,
+                pso::DescriptorType::CombinedImageSampler,
+                pso::ShaderStageFlags::FRAGMENT,
+            ))))?
+            .into();
+        let sampler = factory.get_sampler(SamplerInfo::new(Filter::Nearest, WrapMode::Clamp))?;
+
+        let image = images
+            .first()
+            .and_then(|node_image| ctx.get_image(node_image.id))
+            .ok_or_else(|| failure::format_err!("Color grade source image is missing."))?;
+        let view = factory.create_image_view(
+            image.clone(),
+            ImageViewInfo {
+                view_kind: ViewKind::D2,
+                format: image.format(),
+                swizzle: Swizzle::NO,
+                range: SubresourceRange {
+                    aspects: Aspects::COLOR,
+                    levels: 0..1,
+                    layers: 0..1,
+                },
+            },
+        )?;
+
+        let set = factory.create_descriptor_set(layout.clone())?;
+        unsafe {
+            factory.write_descriptor_sets(Some(util::desc_write(
+                set.raw(),
+                0,
+                pso::Descriptor::CombinedImageSampler(
+                    view.raw(),
+                    hal::image::Layout::ShaderReadOnlyOptimal,
+                    sampler.raw(),
+                ),
+            )));
+        }
+
+        let (pipeline, pipeline_layout) = build_color_grade_pipeline(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            vec![args.raw_layout(), layout.raw(), textures.raw_layout()],
+        )?;
+
+        Ok(Box::new(DrawColorGrade::<B> {
+            pipeline,
+            pipeline_layout,
+            args,
+            textures,
+            lut: None,
+            set,
+            change: Default::default(),
+            _view: view,
+            _sampler: sampler,
+            _layout: layout,
+        }))
+    }
+}
+
+/// Draws the graded source image over the target with a fullscreen triangle.
+#[derive(Debug)]
+pub struct DrawColorGrade<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    args: DynamicUniform<B, ColorGradeArgs>,
+    textures: TextureSub<B>,
+    lut: Option<TextureId>,
+    set: Escape<DescriptorSet<B>>,
+    change: util::ChangeDetection,
+    _view: Escape<ImageView<B>>,
+    _sampler: RendyHandle<Sampler<B>>,
+    _layout: RendyHandle<DescriptorSetLayout<B>>,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawColorGrade<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare color grade");
+
+        let settings = <Option<Read<'_, ColorGradeSettings>>>::fetch(world)
+            .map(|settings| settings.clone())
+            .unwrap_or_default();
+
+        self.textures.maintain(factory, world);
+        let old_lut = self.lut;
+        self.lut = settings.lut.as_ref().and_then(|lut| {
+            self.textures
+                .insert(
+                    factory,
+                    world,
+                    lut,
+                    hal::image::Layout::ShaderReadOnlyOptimal,
+                )
+                .map(|(id, _)| id)
+        });
+
+        let args = ColorGradeArgs::from_settings(&settings, self.lut.is_some()).std140();
+        let changed = self.args.write(factory, index, args) || old_lut != self.lut;
+        self.change.prepare_result(index, changed)
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw color grade");
+
+        let layout = &self.pipeline_layout;
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.args.bind(index, layout, 0, &mut encoder);
+        unsafe {
+            encoder.bind_graphics_descriptor_sets(
+                layout,
+                1,
+                Some(self.set.raw()),
+                std::iter::empty(),
+            );
+        }
+        match self.lut {
+            Some(lut) if self.textures.loaded(lut) => {
+                self.textures.bind(layout, 2, lut, &mut encoder);
+            }
+            // The shader doesn't sample the LUT without one, but the set must be bound.
+            _ => unsafe {
+                encoder.bind_graphics_descriptor_sets(
+                    layout,
+                    2,
+                    Some(self.set.raw()),
+                    std::iter::empty(),
+                );
+            },
+        }
+        unsafe {
+            encoder.draw(0..3, 0..1);
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn build_color_grade_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let shader_vertex = unsafe { super::FULLSCREEN_VERTEX.module(factory).unwrap() };
+    let shader_fragment = unsafe { super::COLOR_GRADE_FRAGMENT.module(factory).unwrap() };
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            PipelineDescBuilder::new()
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_fragment),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend: None,
+                }]),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+    }
+}
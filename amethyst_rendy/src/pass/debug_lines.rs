@@ -61,7 +61,7 @@ impl<B: Backend> RenderGroupDesc<B, World> for DrawDebugLinesDesc {
         let args = DynamicUniform::new(factory, pso::ShaderStageFlags::VERTEX)?;
         let vertex = DynamicVertexBuffer::new();
 
-        let (pipeline, pipeline_layout) = build_lines_pipeline(
+        let (pipeline, native_pipeline, pipeline_layout) = build_lines_pipelines(
             factory,
             subpass,
             framebuffer_width,
@@ -71,6 +71,7 @@ impl<B: Backend> RenderGroupDesc<B, World> for DrawDebugLinesDesc {
 
         Ok(Box::new(DrawDebugLines::<B> {
             pipeline,
+            native_pipeline,
             pipeline_layout,
             env,
             args,
@@ -78,15 +79,20 @@ impl<B: Backend> RenderGroupDesc<B, World> for DrawDebugLinesDesc {
             framebuffer_width: framebuffer_width as f32,
             framebuffer_height: framebuffer_height as f32,
             lines: Vec::new(),
+            native: true,
             change: Default::default(),
         }))
     }
 }
 
 /// Draws debug lines
+///
+/// Lines 1 pixel wide are drawn as native lines, wider ones as screen space quads, which unlike
+/// wide native lines are supported by every backend.
 #[derive(Debug)]
 pub struct DrawDebugLines<B: Backend> {
     pipeline: B::GraphicsPipeline,
+    native_pipeline: B::GraphicsPipeline,
     pipeline_layout: B::PipelineLayout,
     env: DynamicUniform<B, ViewArgs>,
     args: DynamicUniform<B, DebugLinesArgs>,
@@ -94,6 +100,7 @@ pub struct DrawDebugLines<B: Backend> {
     framebuffer_width: f32,
     framebuffer_height: f32,
     lines: Vec<DebugLine>,
+    native: bool,
     change: util::ChangeDetection,
 }
 
@@ -129,6 +136,9 @@ impl<B: Backend> RenderGroup<B, World> for DrawDebugLines<B> {
         let line_width = line_params
             .map(|p| p.line_width)
             .unwrap_or(DebugLinesParams::default().line_width);
+        self.native = line_width <= 1.0;
+        // Native lines are not extruded.
+        let line_width = if self.native { 0.0 } else { line_width };
 
         self.env.write(factory, index, cam.projview);
         self.args.write(
@@ -170,18 +180,28 @@ impl<B: Backend> RenderGroup<B, World> for DrawDebugLines<B> {
         }
 
         let layout = &self.pipeline_layout;
-        encoder.bind_graphics_pipeline(&self.pipeline);
+        if self.native {
+            encoder.bind_graphics_pipeline(&self.native_pipeline);
+        } else {
+            encoder.bind_graphics_pipeline(&self.pipeline);
+        }
         self.env.bind(index, layout, 0, &mut encoder);
         self.args.bind(index, layout, 1, &mut encoder);
         self.vertex.bind(index, 0, 0, &mut encoder);
+        // The shader places vertices 0 and 1 at the start of a line and 2 and 3 at its end, so
+        // a native line is drawn from vertices 1 and 2.
+        let vertices = if self.native { 1..3 } else { 0..4 };
         unsafe {
-            encoder.draw(0..4, 0..self.lines.len() as u32);
+            encoder.draw(vertices, 0..self.lines.len() as u32);
         }
     }
 
     fn dispose(self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
         unsafe {
             factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_graphics_pipeline(self.native_pipeline);
             factory
                 .device()
                 .destroy_pipeline_layout(self.pipeline_layout);
@@ -189,13 +209,13 @@ impl<B: Backend> RenderGroup<B, World> for DrawDebugLines<B> {
     }
 }
 
-fn build_lines_pipeline<B: Backend>(
+fn build_lines_pipelines<B: Backend>(
     factory: &Factory<B>,
     subpass: hal::pass::Subpass<'_, B>,
     framebuffer_width: u32,
     framebuffer_height: u32,
     layouts: Vec<&B::DescriptorSetLayout>,
-) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+) -> Result<(B::GraphicsPipeline, B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
     let pipeline_layout = unsafe {
         factory
             .device()
@@ -205,27 +225,29 @@ fn build_lines_pipeline<B: Backend>(
     let shader_vertex = unsafe { super::DEBUG_LINES_VERTEX.module(factory).unwrap() };
     let shader_fragment = unsafe { super::DEBUG_LINES_FRAGMENT.module(factory).unwrap() };
 
+    let quads = PipelineDescBuilder::new()
+        .with_vertex_desc(&[(DebugLine::vertex(), pso::VertexInputRate::Instance(1))])
+        .with_primitive(hal::Primitive::TriangleStrip)
+        .with_shaders(util::simple_shader_set(
+            &shader_vertex,
+            Some(&shader_fragment),
+        ))
+        .with_layout(&pipeline_layout)
+        .with_subpass(subpass)
+        .with_framebuffer_size(framebuffer_width, framebuffer_height)
+        .with_blend_targets(vec![pso::ColorBlendDesc {
+            mask: pso::ColorMask::ALL,
+            blend: Some(pso::BlendState::ALPHA),
+        }])
+        .with_depth_test(pso::DepthTest {
+            fun: pso::Comparison::LessEqual,
+            write: true,
+        });
+    let native = quads.clone().with_primitive(hal::Primitive::LineList);
+
     let pipes = PipelinesBuilder::new()
-        .with_pipeline(
-            PipelineDescBuilder::new()
-                .with_vertex_desc(&[(DebugLine::vertex(), pso::VertexInputRate::Instance(1))])
-                .with_input_assembler(pso::InputAssemblerDesc::new(hal::Primitive::TriangleStrip))
-                .with_shaders(util::simple_shader_set(
-                    &shader_vertex,
-                    Some(&shader_fragment),
-                ))
-                .with_layout(&pipeline_layout)
-                .with_subpass(subpass)
-                .with_framebuffer_size(framebuffer_width, framebuffer_height)
-                .with_blend_targets(vec![pso::ColorBlendDesc {
-                    mask: pso::ColorMask::ALL,
-                    blend: Some(pso::BlendState::ALPHA),
-                }])
-                .with_depth_test(pso::DepthTest {
-                    fun: pso::Comparison::LessEqual,
-                    write: true,
-                }),
-        )
+        .with_pipeline(quads)
+        .with_pipeline(native)
         .build(factory, None);
 
     unsafe {
@@ -240,6 +262,9 @@ fn build_lines_pipeline<B: Backend>(
             }
             Err(e)
         }
-        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+        Ok(mut pipes) => {
+            let native = pipes.remove(1);
+            Ok((pipes.remove(0), native, pipeline_layout))
+        }
     }
 }
@@ -0,0 +1,760 @@
+//! Decals projected onto the opaque meshes of the scene.
+//!
+//! `DrawDecalReceivers` draws the opaque meshes into a receiver target with two colors, the world
+//! normal and depth of their surface and its render layers and flags. `DrawDecal` then draws the
+//! box of each `Decal` over the main target, reconstructing the position of the surface under
+//! each pixel from that depth and discarding pixels outside of the box. Decals sharing textures
+//! and render layers are drawn with a single instanced draw call.
+use crate::{
+    batch::{GroupIterator, OneLevelBatch},
+    decal::Decal,
+    layers::RenderLayers,
+    mtl::MaterialDefaults,
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    pod::{DecalArgs, DecalReceiverArgs, SkinnedDecalReceiverArgs},
+    skinning::{JointCombined, JointTransforms, SkinningMode},
+    submodules::{
+        DynamicVertexBuffer, EnvironmentSub, FlatEnvironmentSub, SkinningSub, TextureId, TextureSub,
+    },
+    types::{Backend, Mesh, Texture},
+    util,
+    visibility::Visibility,
+};
+use amethyst_assets::{AssetStorage, Handle};
+use amethyst_core::{
+    ecs::{Join, Read, ReadExpect, ReadStorage, SystemData, World},
+    transform::Transform,
+    Hidden, HiddenPropagate,
+};
+use derivative::Derivative;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
+    },
+    hal::{
+        self,
+        device::Device,
+        format::{Aspects, Format, Swizzle},
+        image::{Filter, SamplerInfo, SubresourceRange, ViewKind, WrapMode},
+        pso,
+    },
+    mesh::{AsVertex, Normal, Position, VertexFormat},
+    resource::{
+        DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle, ImageView,
+        ImageViewInfo, Sampler,
+    },
+    shader::{Shader, SpirvShader},
+};
+use std::marker::PhantomData;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Format of the receiver image holding the world normal and depth of the receiving surfaces.
+pub const DECAL_NORMAL_DEPTH_FORMAT: Format = Format::Rgba32Sfloat;
+/// Format of the receiver image holding the render layers and flags of the receiving surfaces.
+pub const DECAL_RECEIVER_FORMAT: Format = Format::Rg32Uint;
+
+/// Vertices of the unit cube drawn for each decal, generated by the vertex shader.
+const DECAL_CUBE_VERTICES: u32 = 36;
+
+/// Draw the opaque meshes into the two colors of the decal receiver target.
+///
+/// The target must have a `DECAL_NORMAL_DEPTH_FORMAT` color cleared to `[0, 0, 0, 1]`, a
+/// `DECAL_RECEIVER_FORMAT` color cleared to 0 and a depth output cleared to 1.
+#[derive(Clone, Derivative)]
+#[derivative(Debug(bound = ""), Default(bound = ""))]
+pub struct DrawDecalReceiversDesc<B: Backend> {
+    skinning: bool,
+    skinning_mode: SkinningMode,
+    marker: PhantomData<B>,
+}
+
+impl<B: Backend> DrawDecalReceiversDesc<B> {
+    /// Create pass in default configuration
+    pub fn new() -> Self {
+        Self {
+            skinning: false,
+            skinning_mode: SkinningMode::Linear,
+            marker: PhantomData,
+        }
+    }
+
+    /// Create pass in with vertex skinning enabled if true is passed
+    pub fn with_skinning(mut self, skinned: bool) -> Self {
+        self.skinning = skinned;
+        self
+    }
+
+    /// Create pass blending the joints of skinned meshes with the given mode.
+    pub fn with_skinning_mode(mut self, mode: SkinningMode) -> Self {
+        self.skinning_mode = mode;
+        self
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawDecalReceiversDesc<B> {
+    fn colors(&self) -> usize {
+        2
+    }
+
+    fn build(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build_decal_receivers");
+
+        let env = FlatEnvironmentSub::new(factory)?;
+        let skinning = SkinningSub::new(factory, self.skinning_mode)?;
+
+        let mut vertex_format_base = vec![Position::vertex(), Normal::vertex()];
+        let mut vertex_format_skinned = vec![
+            Position::vertex(),
+            Normal::vertex(),
+            JointCombined::vertex(),
+        ];
+
+        let (mut pipelines, pipeline_layout) = build_receiver_pipelines(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            &vertex_format_base,
+            &vertex_format_skinned,
+            self.skinning,
+            self.skinning_mode,
+            vec![env.raw_layout(), skinning.raw_layout()],
+        )?;
+
+        vertex_format_base.sort();
+        vertex_format_skinned.sort();
+
+        let pipeline_skinned = if self.skinning { pipelines.pop() } else { None };
+        let pipeline_basic = pipelines.pop().expect("Unreachable: pipeline was built");
+
+        Ok(Box::new(DrawDecalReceivers::<B> {
+            pipeline_basic,
+            pipeline_skinned,
+            pipeline_layout,
+            static_batches: Default::default(),
+            skinned_batches: Default::default(),
+            vertex_format_base,
+            vertex_format_skinned,
+            env,
+            skinning,
+            models: DynamicVertexBuffer::new(),
+            skinned_models: DynamicVertexBuffer::new(),
+        }))
+    }
+}
+
+/// Draws the opaque meshes into the decal receiver target, see the
+/// [module documentation](index.html).
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct DrawDecalReceivers<B: Backend> {
+    pipeline_basic: B::GraphicsPipeline,
+    pipeline_skinned: Option<B::GraphicsPipeline>,
+    pipeline_layout: B::PipelineLayout,
+    static_batches: OneLevelBatch<u32, DecalReceiverArgs>,
+    skinned_batches: OneLevelBatch<u32, SkinnedDecalReceiverArgs>,
+    vertex_format_base: Vec<VertexFormat>,
+    vertex_format_skinned: Vec<VertexFormat>,
+    env: FlatEnvironmentSub<B>,
+    skinning: SkinningSub<B>,
+    models: DynamicVertexBuffer<B, DecalReceiverArgs>,
+    skinned_models: DynamicVertexBuffer<B, SkinnedDecalReceiverArgs>,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawDecalReceivers<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        resources: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare decal receivers");
+
+        let (mesh_storage, visibility, meshes, transforms, joints, layers) =
+            <(
+                Read<'_, AssetStorage<Mesh>>,
+                ReadExpect<'_, Visibility>,
+                ReadStorage<'_, Handle<Mesh>>,
+                ReadStorage<'_, Transform>,
+                ReadStorage<'_, JointTransforms>,
+                ReadStorage<'_, RenderLayers>,
+            )>::fetch(resources);
+
+        self.env.process(factory, index, resources);
+
+        self.static_batches.clear_inner();
+        self.skinned_batches.clear_inner();
+
+        let statics_ref = &mut self.static_batches;
+        let skinned_ref = &mut self.skinned_batches;
+        let skinning_ref = &mut self.skinning;
+
+        (
+            &meshes,
+            &transforms,
+            layers.maybe(),
+            !&joints,
+            &visibility.visible_unordered,
+        )
+            .join()
+            .map(|(mesh, tform, layers, _, _)| {
+                (
+                    mesh.id(),
+                    DecalReceiverArgs::from_object_data(tform, RenderLayers::of(layers), 0),
+                )
+            })
+            .for_each_group(|mesh_id, data| {
+                if mesh_storage.contains_id(mesh_id) {
+                    statics_ref.insert(mesh_id, data.drain(..));
+                }
+            });
+
+        if self.pipeline_skinned.is_some() {
+            (
+                &meshes,
+                &transforms,
+                layers.maybe(),
+                &joints,
+                &visibility.visible_unordered,
+            )
+                .join()
+                .map(|(mesh, tform, layers, joints, _)| {
+                    (
+                        mesh.id(),
+                        SkinnedDecalReceiverArgs::from_object_data(
+                            tform,
+                            RenderLayers::of(layers),
+                            skinning_ref.insert(joints),
+                        ),
+                    )
+                })
+                .for_each_group(|mesh_id, data| {
+                    if mesh_storage.contains_id(mesh_id) {
+                        skinned_ref.insert(mesh_id, data.drain(..));
+                    }
+                });
+        }
+
+        {
+            #[cfg(feature = "profiler")]
+            profile_scope!("write");
+
+            self.static_batches.prune();
+            self.skinned_batches.prune();
+
+            self.models.write(
+                factory,
+                index,
+                self.static_batches.count() as u64,
+                self.static_batches.data(),
+            );
+            self.skinned_models.write(
+                factory,
+                index,
+                self.skinned_batches.count() as u64,
+                self.skinned_batches.data(),
+            );
+            self.skinning.commit(factory, index);
+        }
+
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        resources: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw decal receivers");
+
+        let mesh_storage = <Read<'_, AssetStorage<Mesh>>>::fetch(resources);
+        let layout = &self.pipeline_layout;
+        let encoder = &mut encoder;
+
+        let models_loc = self.vertex_format_base.len() as u32;
+        let skin_models_loc = self.vertex_format_skinned.len() as u32;
+
+        encoder.bind_graphics_pipeline(&self.pipeline_basic);
+        self.env.bind(index, layout, 0, encoder);
+        if self.models.bind(index, models_loc, 0, encoder) {
+            draw_receivers(
+                &self.static_batches,
+                &self.vertex_format_base,
+                &mesh_storage,
+                encoder,
+            );
+        }
+
+        if let Some(pipeline_skinned) = self.pipeline_skinned.as_ref() {
+            encoder.bind_graphics_pipeline(pipeline_skinned);
+            if self.skinned_models.bind(index, skin_models_loc, 0, encoder) {
+                self.skinning.bind(index, layout, 1, encoder);
+                draw_receivers(
+                    &self.skinned_batches,
+                    &self.vertex_format_skinned,
+                    &mesh_storage,
+                    encoder,
+                );
+            }
+        }
+    }
+
+    fn dispose(mut self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
+        unsafe {
+            let device = factory.device();
+            device.destroy_graphics_pipeline(self.pipeline_basic);
+            if let Some(pipeline) = self.pipeline_skinned.take() {
+                device.destroy_graphics_pipeline(pipeline);
+            }
+            device.destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn draw_receivers<B: Backend, D>(
+    batches: &OneLevelBatch<u32, D>,
+    vertex_format: &[VertexFormat],
+    mesh_storage: &AssetStorage<Mesh>,
+    encoder: &mut RenderPassEncoder<'_, B>,
+) {
+    for (mesh_id, range) in batches.iter() {
+        debug_assert!(mesh_storage.contains_id(*mesh_id));
+        if let Some(mesh) = B::unwrap_mesh(unsafe { mesh_storage.get_by_id_unchecked(*mesh_id) }) {
+            // Vertex attributes missing from a mesh are reported by the opaque pass.
+            let _ = mesh.bind_and_draw(0, vertex_format, range, encoder);
+        }
+    }
+}
+
+/// Returns the receiver vertex shader for skinned meshes blended with `mode`.
+fn skinned_receiver_shader(mode: SkinningMode) -> &'static SpirvShader {
+    match mode {
+        SkinningMode::Linear => &super::DECAL_RECEIVER_SKIN_VERTEX,
+        SkinningMode::DualQuaternion => &super::DECAL_RECEIVER_SKIN_DQ_VERTEX,
+    }
+}
+
+/// Builds the receiver pipeline, followed by its skinned variant if enabled.
+fn build_receiver_pipelines<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    vertex_format_base: &[VertexFormat],
+    vertex_format_skinned: &[VertexFormat],
+    skinning: bool,
+    skinning_mode: SkinningMode,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(Vec<B::GraphicsPipeline>, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let vertex_desc = |formats: &[VertexFormat], args: VertexFormat| {
+        formats
+            .iter()
+            .map(|f| (f.clone(), pso::VertexInputRate::Vertex))
+            .chain(Some((args, pso::VertexInputRate::Instance(1))))
+            .collect::<Vec<_>>()
+    };
+    let base_desc = vertex_desc(vertex_format_base, DecalReceiverArgs::vertex());
+    let skinned_desc = vertex_desc(vertex_format_skinned, SkinnedDecalReceiverArgs::vertex());
+
+    let shader_vertex_basic = unsafe { super::DECAL_RECEIVER_VERTEX.module(factory).unwrap() };
+    let shader_vertex_skinned = unsafe {
+        skinned_receiver_shader(skinning_mode)
+            .module(factory)
+            .unwrap()
+    };
+    let shader_fragment = unsafe { super::DECAL_RECEIVER_FRAGMENT.module(factory).unwrap() };
+
+    let receivers = PipelineDescBuilder::new()
+        .with_layout(&pipeline_layout)
+        .with_subpass(subpass)
+        .with_framebuffer_size(framebuffer_width, framebuffer_height)
+        .with_face_culling(pso::Face::BACK)
+        .with_depth_test(pso::DepthTest {
+            fun: pso::Comparison::Less,
+            write: true,
+        })
+        .with_blend_targets(vec![
+            pso::ColorBlendDesc {
+                mask: pso::ColorMask::ALL,
+                blend: None,
+            };
+            2
+        ]);
+
+    let mut builder = PipelinesBuilder::new().with_pipeline(
+        receivers
+            .clone()
+            .with_vertex_desc(&base_desc)
+            .with_shaders(util::simple_shader_set(
+                &shader_vertex_basic,
+                Some(&shader_fragment),
+            )),
+    );
+    if skinning {
+        builder = builder.with_child_pipeline(
+            0,
+            receivers
+                .with_vertex_desc(&skinned_desc)
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex_skinned,
+                    Some(&shader_fragment),
+                )),
+        );
+    }
+    let pipelines = builder.build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex_basic);
+        factory.destroy_shader_module(shader_vertex_skinned);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipelines {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(pipelines) => Ok((pipelines, pipeline_layout)),
+    }
+}
+
+/// Draw the decals of the scene over the target.
+///
+/// Build with `builder().with_image(normal_depth).with_image(receiver)`, passing the two color
+/// images of the target drawn by `DrawDecalReceiversDesc`, which must have the size of this
+/// target.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DrawDecalDesc;
+
+impl DrawDecalDesc {
+    /// Create instance of `DrawDecal` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawDecalDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![
+            ImageAccess {
+                access: hal::image::Access::SHADER_READ,
+                usage: hal::image::Usage::SAMPLED,
+                layout: hal::image::Layout::ShaderReadOnlyOptimal,
+                stages: pso::PipelineStage::FRAGMENT_SHADER,
+            };
+            2
+        ]
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build_decal");
+
+        let env = EnvironmentSub::new(
+            factory,
+            [
+                pso::ShaderStageFlags::VERTEX,
+                pso::ShaderStageFlags::FRAGMENT,
+            ],
+        )?;
+        let textures = TextureSub::new(factory)?;
+
+        let layout: RendyHandle<DescriptorSetLayout<B>> = factory
+            .create_descriptor_set_layout(util::set_layout_bindings(Some((
+                2,
+                pso::DescriptorType::CombinedImageSampler,
+                pso::ShaderStageFlags::FRAGMENT,
+            ))))?
+            .into();
+        // Receivers are read with `texelFetch`, the sampler is never used to filter them.
+        let sampler = factory.get_sampler(SamplerInfo::new(Filter::Nearest, WrapMode::Clamp))?;
+
+        let mut views = Vec::with_capacity(2);
+        for node_image in &images {
+            let image = ctx
+                .get_image(node_image.id)
+                .ok_or_else(|| failure::format_err!("Decal receiver image is missing."))?;
+            views.push(factory.create_image_view(
+                image.clone(),
+                ImageViewInfo {
+                    view_kind: ViewKind::D2,
+                    format: image.format(),
+                    swizzle: Swizzle::NO,
+                    range: SubresourceRange {
+                        aspects: Aspects::COLOR,
+                        levels: 0..1,
+                        layers: 0..1,
+                    },
+                },
+            )?);
+        }
+
+        let set = factory.create_descriptor_set(layout.clone())?;
+        unsafe {
+            factory.write_descriptor_sets(views.iter().enumerate().map(|(binding, view)| {
+                util::desc_write(
+                    set.raw(),
+                    binding as u32,
+                    pso::Descriptor::CombinedImageSampler(
+                        view.raw(),
+                        hal::image::Layout::ShaderReadOnlyOptimal,
+                        sampler.raw(),
+                    ),
+                )
+            }));
+        }
+
+        let (pipeline, pipeline_layout) = build_decal_pipeline(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            vec![
+                env.raw_layout(),
+                layout.raw(),
+                textures.raw_layout(),
+                textures.raw_layout(),
+            ],
+        )?;
+
+        Ok(Box::new(DrawDecal::<B> {
+            pipeline,
+            pipeline_layout,
+            env,
+            textures,
+            batches: Default::default(),
+            models: DynamicVertexBuffer::new(),
+            set,
+            _views: views,
+            _sampler: sampler,
+            _layout: layout,
+        }))
+    }
+}
+
+/// Draws the boxes of decals over the target, see the [module documentation](index.html).
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct DrawDecal<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    env: EnvironmentSub<B>,
+    textures: TextureSub<B>,
+    batches: OneLevelBatch<(usize, TextureId, TextureId), DecalArgs>,
+    models: DynamicVertexBuffer<B, DecalArgs>,
+    set: Escape<DescriptorSet<B>>,
+    _views: Vec<Escape<ImageView<B>>>,
+    _sampler: RendyHandle<Sampler<B>>,
+    _layout: RendyHandle<DescriptorSetLayout<B>>,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawDecal<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare decal");
+
+        let (defaults, decals, transforms, hiddens, hidden_props) = <(
+            ReadExpect<'_, MaterialDefaults>,
+            ReadStorage<'_, Decal>,
+            ReadStorage<'_, Transform>,
+            ReadStorage<'_, Hidden>,
+            ReadStorage<'_, HiddenPropagate>,
+        )>::fetch(world);
+
+        self.batches.clear_inner();
+
+        let batches_ref = &mut self.batches;
+        let textures_ref = &mut self.textures;
+        let env_ref = &mut self.env;
+        let mut insert = |texture: &Handle<Texture>| {
+            textures_ref
+                .insert(
+                    factory,
+                    world,
+                    texture,
+                    hal::image::Layout::ShaderReadOnlyOptimal,
+                )
+                .map(|(id, _)| id)
+        };
+
+        // Decals sharing their textures and layers are drawn together, whatever their mesh.
+        (&decals, &transforms, !&hiddens, !&hidden_props)
+            .join()
+            .filter_map(|(decal, tform, _, _)| {
+                let albedo = insert(&decal.albedo)?;
+                let normal = insert(decal.normal.as_ref().unwrap_or(&defaults.0.normal))?;
+                let slot = env_ref.layer_slot(decal.layers);
+                Some((
+                    (slot, albedo, normal),
+                    DecalArgs::from_object_data(tform, decal),
+                ))
+            })
+            .for_each_group(|key, data| {
+                batches_ref.insert(key, data.drain(..));
+            });
+
+        self.textures.maintain(factory, world);
+        self.env.process(factory, index, world);
+
+        {
+            #[cfg(feature = "profiler")]
+            profile_scope!("write");
+
+            self.batches.prune();
+            self.models.write(
+                factory,
+                index,
+                self.batches.count() as u64,
+                self.batches.data(),
+            );
+        }
+
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw decal");
+
+        let layout = &self.pipeline_layout;
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        if !self.models.bind(index, 0, 0, &mut encoder) {
+            return;
+        }
+        unsafe {
+            encoder.bind_graphics_descriptor_sets(
+                layout,
+                1,
+                Some(self.set.raw()),
+                std::iter::empty(),
+            );
+        }
+        for (&(slot, albedo, normal), range) in self.batches.iter() {
+            if self.textures.loaded(albedo) && self.textures.loaded(normal) {
+                self.env.bind_layers(index, slot, layout, 0, &mut encoder);
+                self.textures.bind(layout, 2, albedo, &mut encoder);
+                self.textures.bind(layout, 3, normal, &mut encoder);
+                unsafe {
+                    encoder.draw(0..DECAL_CUBE_VERTICES, range);
+                }
+            }
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn build_decal_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let shader_vertex = unsafe { super::DECAL_VERTEX.module(factory).unwrap() };
+    let shader_fragment = unsafe { super::DECAL_FRAGMENT.module(factory).unwrap() };
+
+    // Back faces of the box are drawn without depth test, so the decal is still drawn with the
+    // camera inside of it. The fragment shader rejects pixels outside of the box.
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            PipelineDescBuilder::new()
+                .with_vertex_desc(&[(DecalArgs::vertex(), pso::VertexInputRate::Instance(1))])
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_fragment),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_face_culling(pso::Face::FRONT)
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend: Some(pso::BlendState::ALPHA),
+                }]),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+    }
+}
@@ -0,0 +1,440 @@
+//! Depth of field, blurring the image away from the focus of the camera.
+//!
+//! `DrawDofBlur` computes the circle of confusion of each pixel from the depth of the source and
+//! gathers the surrounding pixels that blur over it into a half resolution image.
+//! `DrawDepthOfField` then blends that image over the sharp source.
+use crate::{
+    camera::Camera,
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    pod::DofArgs,
+    resources::DofSettings,
+    submodules::{gather::CameraGatherer, DynamicUniform},
+    types::Backend,
+    util,
+};
+use amethyst_core::{
+    ecs::{Read, ReadStorage, SystemData, World},
+    math::{convert, Matrix4},
+};
+use glsl_layout::AsStd140;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
+    },
+    hal::{
+        self,
+        device::Device,
+        format::{Aspects, Swizzle},
+        image::{Filter, SamplerInfo, SubresourceRange, ViewKind, WrapMode},
+        pso,
+    },
+    resource::{
+        DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle, ImageView,
+        ImageViewInfo, Sampler,
+    },
+    shader::{Shader, SpirvShader},
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Access of the images read by the depth of field groups.
+const SAMPLED_IMAGE: ImageAccess = ImageAccess {
+    access: hal::image::Access::SHADER_READ,
+    usage: hal::image::Usage::SAMPLED,
+    layout: hal::image::Layout::ShaderReadOnlyOptimal,
+    stages: pso::PipelineStage::FRAGMENT_SHADER,
+};
+
+/// Draw the blurred source image and how much it replaces the sharp one into its alpha.
+///
+/// Build with `builder().with_image(color).with_image(depth)`, passing the color and depth
+/// images of the source. The target should have half the size of the source and a format with
+/// an alpha channel. The focus is read from the `DofSettings` of the active camera, or else the
+/// `DofSettings` resource.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DrawDofBlurDesc;
+
+impl DrawDofBlurDesc {
+    /// Create instance of `DrawDofBlur` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawDofBlurDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![SAMPLED_IMAGE; 2]
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build_dof_blur");
+
+        let args = DynamicUniform::new(factory, pso::ShaderStageFlags::FRAGMENT)?;
+        // Depth formats can't always be filtered, the depth is read without.
+        let samplers = vec![
+            factory.get_sampler(SamplerInfo::new(Filter::Linear, WrapMode::Clamp))?,
+            factory.get_sampler(SamplerInfo::new(Filter::Nearest, WrapMode::Clamp))?,
+        ];
+        let (set, views, layout) = image_set(
+            ctx,
+            factory,
+            &images,
+            &[Aspects::COLOR, Aspects::DEPTH],
+            &samplers,
+        )?;
+
+        let (pipeline, pipeline_layout) = build_dof_pipeline(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            &super::DOF_BLUR_FRAGMENT,
+            vec![args.raw_layout(), layout.raw()],
+        )?;
+
+        Ok(Box::new(DrawDofBlur::<B> {
+            pipeline,
+            pipeline_layout,
+            args,
+            set,
+            change: Default::default(),
+            _views: views,
+            _samplers: samplers,
+            _layout: layout,
+        }))
+    }
+}
+
+/// Draws the half resolution blur of the depth of field with a fullscreen triangle.
+#[derive(Debug)]
+pub struct DrawDofBlur<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    args: DynamicUniform<B, DofArgs>,
+    set: Escape<DescriptorSet<B>>,
+    change: util::ChangeDetection,
+    _views: Vec<Escape<ImageView<B>>>,
+    _samplers: Vec<RendyHandle<Sampler<B>>>,
+    _layout: RendyHandle<DescriptorSetLayout<B>>,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawDofBlur<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare dof blur");
+
+        let (cameras, camera_settings, settings) = <(
+            ReadStorage<'_, Camera>,
+            ReadStorage<'_, DofSettings>,
+            Option<Read<'_, DofSettings>>,
+        )>::fetch(world);
+
+        let camera = CameraGatherer::gather_camera_entity(world);
+        let settings = camera
+            .and_then(|entity| camera_settings.get(entity).copied())
+            .or_else(|| settings.map(|settings| *settings))
+            .unwrap_or_default();
+        let proj = camera
+            .and_then(|entity| cameras.get(entity))
+            .map_or_else(Matrix4::identity, |camera| {
+                convert::<_, Matrix4<f32>>(*camera.as_matrix())
+            });
+
+        let args = DofArgs::from_settings(&settings, &proj).std140();
+        let changed = self.args.write(factory, index, args);
+        self.change.prepare_result(index, changed)
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw dof blur");
+
+        let layout = &self.pipeline_layout;
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.args.bind(index, layout, 0, &mut encoder);
+        unsafe {
+            encoder.bind_graphics_descriptor_sets(
+                layout,
+                1,
+                Some(self.set.raw()),
+                std::iter::empty(),
+            );
+            encoder.draw(0..3, 0..1);
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+/// Draw the sharp source image over the target, replaced by its blur away from the focus.
+///
+/// Build with `builder().with_image(sharp).with_image(blurred)`, passing the color image of the
+/// source and the image drawn by `DrawDofBlurDesc`. The source should have the size and format
+/// of the target.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DrawDepthOfFieldDesc;
+
+impl DrawDepthOfFieldDesc {
+    /// Create instance of `DrawDepthOfField` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawDepthOfFieldDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![SAMPLED_IMAGE; 2]
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build_depth_of_field");
+
+        let samplers = vec![
+            factory.get_sampler(SamplerInfo::new(Filter::Nearest, WrapMode::Clamp))?,
+            factory.get_sampler(SamplerInfo::new(Filter::Linear, WrapMode::Clamp))?,
+        ];
+        let (set, views, layout) = image_set(
+            ctx,
+            factory,
+            &images,
+            &[Aspects::COLOR, Aspects::COLOR],
+            &samplers,
+        )?;
+
+        let (pipeline, pipeline_layout) = build_dof_pipeline(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            &super::DOF_FRAGMENT,
+            vec![layout.raw()],
+        )?;
+
+        Ok(Box::new(DrawDepthOfField::<B> {
+            pipeline,
+            pipeline_layout,
+            set,
+            _views: views,
+            _samplers: samplers,
+            _layout: layout,
+        }))
+    }
+}
+
+/// Draws the sharp and blurred images blended by depth with a fullscreen triangle.
+#[derive(Debug)]
+pub struct DrawDepthOfField<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    set: Escape<DescriptorSet<B>>,
+    _views: Vec<Escape<ImageView<B>>>,
+    _samplers: Vec<RendyHandle<Sampler<B>>>,
+    _layout: RendyHandle<DescriptorSetLayout<B>>,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawDepthOfField<B> {
+    fn prepare(
+        &mut self,
+        _factory: &Factory<B>,
+        _queue: QueueId,
+        _index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) -> PrepareResult {
+        PrepareResult::DrawReuse
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        _index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw depth of field");
+
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        unsafe {
+            encoder.bind_graphics_descriptor_sets(
+                &self.pipeline_layout,
+                0,
+                Some(self.set.raw()),
+                std::iter::empty(),
+            );
+            encoder.draw(0..3, 0..1);
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+/// Creates a descriptor set sampling each of the `images` with the matching aspect and sampler.
+fn image_set<B: Backend>(
+    ctx: &GraphContext<B>,
+    factory: &Factory<B>,
+    images: &[NodeImage],
+    aspects: &[Aspects],
+    samplers: &[RendyHandle<Sampler<B>>],
+) -> Result<
+    (
+        Escape<DescriptorSet<B>>,
+        Vec<Escape<ImageView<B>>>,
+        RendyHandle<DescriptorSetLayout<B>>,
+    ),
+    failure::Error,
+> {
+    let layout: RendyHandle<DescriptorSetLayout<B>> = factory
+        .create_descriptor_set_layout(util::set_layout_bindings(Some((
+            aspects.len() as u32,
+            pso::DescriptorType::CombinedImageSampler,
+            pso::ShaderStageFlags::FRAGMENT,
+        ))))?
+        .into();
+
+    let mut views = Vec::with_capacity(aspects.len());
+    for (node_image, &aspects) in images.iter().zip(aspects) {
+        let image = ctx
+            .get_image(node_image.id)
+            .ok_or_else(|| failure::format_err!("Depth of field image is missing."))?;
+        views.push(factory.create_image_view(
+            image.clone(),
+            ImageViewInfo {
+                view_kind: ViewKind::D2,
+                format: image.format(),
+                swizzle: Swizzle::NO,
+                range: SubresourceRange {
+                    aspects,
+                    levels: 0..1,
+                    layers: 0..1,
+                },
+            },
+        )?);
+    }
+    if views.len() < aspects.len() {
+        return Err(failure::format_err!("Depth of field image is missing."));
+    }
+
+    let set = factory.create_descriptor_set(layout.clone())?;
+    unsafe {
+        factory.write_descriptor_sets(views.iter().zip(samplers).enumerate().map(
+            |(binding, (view, sampler))| {
+                util::desc_write(
+                    set.raw(),
+                    binding as u32,
+                    pso::Descriptor::CombinedImageSampler(
+                        view.raw(),
+                        hal::image::Layout::ShaderReadOnlyOptimal,
+                        sampler.raw(),
+                    ),
+                )
+            },
+        ));
+    }
+    Ok((set, views, layout))
+}
+
+fn build_dof_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    fragment: &SpirvShader,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let shader_vertex = unsafe { super::FULLSCREEN_VERTEX.module(factory).unwrap() };
+    let shader_fragment = unsafe { fragment.module(factory).unwrap() };
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            PipelineDescBuilder::new()
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_fragment),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend: None,
+                }]),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+    }
+}
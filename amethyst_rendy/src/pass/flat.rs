@@ -17,9 +17,18 @@ impl Base3DPassDef for FlatPassDef {
     fn vertex_skinned_shader() -> &'static SpirvShader {
         &super::POS_TEX_SKIN_VERTEX
     }
+    fn vertex_dual_quaternion_skinned_shader() -> Option<&'static SpirvShader> {
+        Some(&super::POS_TEX_SKIN_DQ_VERTEX)
+    }
+    fn vertex_clip_shader() -> Option<&'static SpirvShader> {
+        Some(&super::POS_TEX_CLIP_VERTEX)
+    }
     fn fragment_shader() -> &'static SpirvShader {
         &super::FLAT_FRAGMENT
     }
+    fn oit_fragment_shader() -> Option<&'static SpirvShader> {
+        Some(&super::FLAT_OIT_FRAGMENT)
+    }
     fn base_format() -> Vec<VertexFormat> {
         vec![Position::vertex(), TexCoord::vertex()]
     }
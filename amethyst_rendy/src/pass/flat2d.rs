@@ -392,7 +392,7 @@ impl<B: Backend> RenderGroup<B, World> for DrawFlat2DTransparent<B> {
     }
 }
 
-fn build_sprite_pipeline<B: Backend>(
+pub(super) fn build_sprite_pipeline<B: Backend>(
     factory: &Factory<B>,
     subpass: hal::pass::Subpass<'_, B>,
     framebuffer_width: u32,
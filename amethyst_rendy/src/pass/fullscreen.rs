@@ -0,0 +1,387 @@
+//! Fullscreen passes drawing a single fragment shader over the whole target.
+//!
+//! `FullscreenPassDesc` takes care of the pipeline, the descriptor sets and the per-image uniform
+//! of a post effect, which only has to provide its fragment shader, its inputs and a function
+//! computing its uniform from the `World` every frame.
+//!
+//! The fragment shader receives the uniform in set 0 and the inputs in set 1, in the order they
+//! were added:
+//! ```glsl,ignore
+//! layout(std140, set = 0, binding = 0) uniform Args { ... };
+//! layout(set = 1, binding = 0) uniform sampler2D first_input;
+//!
+//! layout(location = 0) in vec2 tex_coord;
+//! layout(location = 0) out vec4 out_color;
+//! ```
+use crate::{
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    submodules::DynamicUniform,
+    types::{Backend, Texture},
+    util,
+};
+use amethyst_assets::{AssetStorage, Handle};
+use amethyst_core::ecs::{Read, SystemData, World};
+use derivative::Derivative;
+use glsl_layout::AsStd140;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
+    },
+    hal::{
+        self,
+        device::Device,
+        format::{Aspects, Swizzle},
+        image::{Filter, SamplerInfo, SubresourceRange, ViewKind, WrapMode},
+        pso,
+    },
+    resource::{
+        DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle, ImageView,
+        ImageViewInfo, Sampler,
+    },
+    shader::{Shader, SpirvShader},
+};
+use std::marker::PhantomData;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// An input sampled by the fragment shader of a `FullscreenPassDesc`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FullscreenInput {
+    /// An image of the render graph, passed to the builder of the render group with
+    /// `with_image`, in the same order as the other images. Sampled with nearest filtering.
+    Image,
+    /// A texture asset. Nothing is drawn until it is loaded.
+    Texture(Handle<Texture>),
+}
+
+/// Draw a fragment shader over the whole target, see the [module documentation](index.html).
+///
+/// The render group is built again with new image views when the target is resized.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""), Debug(bound = ""))]
+pub struct FullscreenPassDesc<B: Backend, U> {
+    shader: SpirvShader,
+    inputs: Vec<FullscreenInput>,
+    #[derivative(Debug = "ignore")]
+    uniform: fn(&World) -> U,
+    blend: Option<pso::BlendState>,
+    marker: PhantomData<B>,
+}
+
+impl<B: Backend, U> FullscreenPassDesc<B, U> {
+    /// Create a pass drawing `shader`, with a uniform computed by `uniform` every frame.
+    pub fn new(shader: SpirvShader, uniform: fn(&World) -> U) -> Self {
+        Self {
+            shader,
+            inputs: Vec::new(),
+            uniform,
+            blend: None,
+            marker: PhantomData,
+        }
+    }
+
+    /// Add an input sampled by the shader, bound after the inputs added before.
+    pub fn with_input(mut self, input: FullscreenInput) -> Self {
+        self.inputs.push(input);
+        self
+    }
+
+    /// Blend the output of the shader with the target, which is replaced by default.
+    pub fn with_blend(mut self, blend: pso::BlendState) -> Self {
+        self.blend = Some(blend);
+        self
+    }
+}
+
+impl<B, U> RenderGroupDesc<B, World> for FullscreenPassDesc<B, U>
+where
+    B: Backend,
+    U: AsStd140 + std::fmt::Debug + 'static,
+    U::Std140: Sized,
+{
+    fn images(&self) -> Vec<ImageAccess> {
+        let count = self
+            .inputs
+            .iter()
+            .filter(|input| **input == FullscreenInput::Image)
+            .count();
+        vec![
+            ImageAccess {
+                access: hal::image::Access::SHADER_READ,
+                usage: hal::image::Usage::SAMPLED,
+                layout: hal::image::Layout::ShaderReadOnlyOptimal,
+                stages: pso::PipelineStage::FRAGMENT_SHADER,
+            };
+            count
+        ]
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build_fullscreen");
+
+        let uniform = DynamicUniform::new(factory, pso::ShaderStageFlags::FRAGMENT)?;
+        let layout: RendyHandle<DescriptorSetLayout<B>> = factory
+            .create_descriptor_set_layout(util::set_layout_bindings(Some((
+                self.inputs.len() as u32,
+                pso::DescriptorType::CombinedImageSampler,
+                pso::ShaderStageFlags::FRAGMENT,
+            ))))?
+            .into();
+        let set = factory.create_descriptor_set(layout.clone())?;
+        let sampler = factory.get_sampler(SamplerInfo::new(Filter::Nearest, WrapMode::Clamp))?;
+
+        let mut views = Vec::with_capacity(images.len());
+        let mut textures = Vec::new();
+        let mut images = images.iter();
+        for (binding, input) in self.inputs.into_iter().enumerate() {
+            match input {
+                FullscreenInput::Image => {
+                    let node_image = images.next().ok_or_else(|| {
+                        failure::format_err!("Fullscreen pass input image was not passed.")
+                    })?;
+                    let image = ctx.get_image(node_image.id).ok_or_else(|| {
+                        failure::format_err!("Fullscreen pass input image is missing.")
+                    })?;
+                    let view = factory.create_image_view(
+                        image.clone(),
+                        ImageViewInfo {
+                            view_kind: ViewKind::D2,
+                            format: image.format(),
+                            swizzle: Swizzle::NO,
+                            range: SubresourceRange {
+                                aspects: Aspects::COLOR,
+                                levels: 0..1,
+                                layers: 0..1,
+                            },
+                        },
+                    )?;
+                    unsafe {
+                        factory.write_descriptor_sets(Some(util::desc_write(
+                            set.raw(),
+                            binding as u32,
+                            pso::Descriptor::CombinedImageSampler(
+                                view.raw(),
+                                hal::image::Layout::ShaderReadOnlyOptimal,
+                                sampler.raw(),
+                            ),
+                        )));
+                    }
+                    views.push(view);
+                }
+                FullscreenInput::Texture(handle) => textures.push((binding as u32, handle)),
+            }
+        }
+
+        let (pipeline, pipeline_layout) = build_fullscreen_pipeline(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            &self.shader,
+            self.blend,
+            vec![uniform.raw_layout(), layout.raw()],
+        )?;
+
+        Ok(Box::new(FullscreenPass::<B, U> {
+            pipeline,
+            pipeline_layout,
+            uniform,
+            uniform_fn: self.uniform,
+            set,
+            pending_textures: textures,
+            _views: views,
+            _sampler: sampler,
+            _layout: layout,
+        }))
+    }
+}
+
+/// Draws a fragment shader over the whole target with a fullscreen triangle.
+#[derive(Derivative)]
+#[derivative(Debug(bound = "U: std::fmt::Debug"))]
+pub struct FullscreenPass<B: Backend, U: AsStd140>
+where
+    U::Std140: Sized,
+{
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    uniform: DynamicUniform<B, U>,
+    #[derivative(Debug = "ignore")]
+    uniform_fn: fn(&World) -> U,
+    set: Escape<DescriptorSet<B>>,
+    pending_textures: Vec<(u32, Handle<Texture>)>,
+    _views: Vec<Escape<ImageView<B>>>,
+    _sampler: RendyHandle<Sampler<B>>,
+    _layout: RendyHandle<DescriptorSetLayout<B>>,
+}
+
+impl<B, U> RenderGroup<B, World> for FullscreenPass<B, U>
+where
+    B: Backend,
+    U: AsStd140 + std::fmt::Debug + 'static,
+    U::Std140: Sized,
+{
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare fullscreen");
+
+        let mut changed = false;
+        if !self.pending_textures.is_empty() {
+            let texture_storage = <Read<'_, AssetStorage<Texture>>>::fetch(world);
+            let set = &self.set;
+            // The set is only written before the pass draws for the first time, so it is never
+            // in use by a frame in flight.
+            self.pending_textures.retain(|(binding, handle)| {
+                let desc = texture_storage.get(handle).and_then(|texture| {
+                    util::texture_desc(texture, hal::image::Layout::ShaderReadOnlyOptimal)
+                });
+                match desc {
+                    Some(desc) => {
+                        unsafe {
+                            factory.write_descriptor_sets(Some(util::desc_write(
+                                set.raw(),
+                                *binding,
+                                desc,
+                            )));
+                        }
+                        changed = true;
+                        false
+                    }
+                    None => true,
+                }
+            });
+        }
+
+        let uniform = (self.uniform_fn)(world).std140();
+        changed |= self.uniform.write(factory, index, uniform);
+
+        if changed {
+            PrepareResult::DrawRecord
+        } else {
+            PrepareResult::DrawReuse
+        }
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw fullscreen");
+
+        if !self.pending_textures.is_empty() {
+            return;
+        }
+
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.uniform
+            .bind(index, &self.pipeline_layout, 0, &mut encoder);
+        unsafe {
+            encoder.bind_graphics_descriptor_sets(
+                &self.pipeline_layout,
+                1,
+                Some(self.set.raw()),
+                std::iter::empty(),
+            );
+            encoder.draw(0..3, 0..1);
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn build_fullscreen_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    shader: &SpirvShader,
+    blend: Option<pso::BlendState>,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let shader_vertex = unsafe { super::FULLSCREEN_VERTEX.module(factory).unwrap() };
+    let shader_fragment = match unsafe { shader.module(factory) } {
+        Ok(module) => module,
+        Err(e) => {
+            unsafe {
+                factory.destroy_shader_module(shader_vertex);
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            return Err(failure::format_err!(
+                "Fullscreen pass shader failed to load: {:?}",
+                e
+            ));
+        }
+    };
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            PipelineDescBuilder::new()
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_fragment),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend,
+                }]),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+    }
+}
@@ -0,0 +1,289 @@
+//! Baking of the image-based lighting maps of an `EnvironmentMap`.
+//!
+//! The maps are rendered on the GPU into three color images of a dedicated target, only when
+//! the source of the `EnvironmentMap` changes or the render graph is rebuilt:
+//!
+//! * an equirectangular irradiance map, for the diffuse ambient lighting,
+//! * an equirectangular specular map, prefiltered for several roughness levels stacked
+//!   vertically,
+//! * a lookup table of the environment BRDF, indexed by the cosine of the view angle and the
+//!   roughness.
+//!
+//! The images of the target have no clear value, so they keep their contents between frames.
+use crate::{
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    pod::IntoPod,
+    resources::{AmbientColor, EnvironmentMap, EnvironmentSource},
+    submodules::{DynamicUniform, TextureId, TextureSub},
+    types::Backend,
+    util,
+};
+use amethyst_core::ecs::{Read, SystemData, World};
+use glsl_layout::{vec3, AsStd140};
+use palette::Srgb;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, NodeBuffer, NodeImage,
+    },
+    hal::{self, device::Device, format::Format, pso},
+    shader::Shader,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Width and height of the baked images.
+pub const IBL_MAP_SIZE: u32 = 256;
+/// Format of the irradiance map.
+pub const IBL_IRRADIANCE_FORMAT: Format = Format::Rgba16Sfloat;
+/// Format of the prefiltered specular map.
+pub const IBL_SPECULAR_FORMAT: Format = Format::Rgba16Sfloat;
+/// Format of the BRDF lookup table.
+pub const IBL_BRDF_FORMAT: Format = Format::Rg16Sfloat;
+
+#[derive(Clone, Debug, PartialEq, AsStd140)]
+pub(crate) struct GradientUniform {
+    nadir_color: vec3,
+    zenith_color: vec3,
+}
+
+impl GradientUniform {
+    fn new(nadir: Srgb, zenith: Srgb) -> <Self as AsStd140>::Std140 {
+        GradientUniform {
+            nadir_color: nadir.into_pod(),
+            zenith_color: zenith.into_pod(),
+        }
+        .std140()
+    }
+}
+
+/// Bake the image-based lighting maps of the `EnvironmentMap` resource.
+///
+/// The target must have `IBL_IRRADIANCE_FORMAT`, `IBL_SPECULAR_FORMAT` and `IBL_BRDF_FORMAT`
+/// colors of `IBL_MAP_SIZE` pixels, in this order, and no depth.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DrawIblBakeDesc;
+
+impl DrawIblBakeDesc {
+    /// Create instance of `DrawIblBake` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawIblBakeDesc {
+    fn colors(&self) -> usize {
+        3
+    }
+
+    fn depth(&self) -> bool {
+        false
+    }
+
+    fn build(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build_ibl_bake");
+
+        let gradient = DynamicUniform::new(factory, pso::ShaderStageFlags::FRAGMENT)?;
+        let textures = TextureSub::new(factory)?;
+
+        let pipeline_layout = unsafe {
+            factory.device().create_pipeline_layout(
+                vec![gradient.raw_layout(), textures.raw_layout()],
+                None as Option<(_, _)>,
+            )
+        }?;
+
+        let shader_vertex = unsafe { super::FULLSCREEN_VERTEX.module(factory).unwrap() };
+        let shader_gradient = unsafe { super::IBL_BAKE_GRADIENT_FRAGMENT.module(factory).unwrap() };
+        let shader_cubemap = unsafe { super::IBL_BAKE_CUBEMAP_FRAGMENT.module(factory).unwrap() };
+
+        let pipe_desc = PipelineDescBuilder::new()
+            .with_layout(&pipeline_layout)
+            .with_subpass(subpass)
+            .with_framebuffer_size(framebuffer_width, framebuffer_height)
+            .with_blend_targets(vec![
+                pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend: None,
+                };
+                3
+            ]);
+
+        let pipes = PipelinesBuilder::new()
+            .with_pipeline(pipe_desc.clone().with_shaders(util::simple_shader_set(
+                &shader_vertex,
+                Some(&shader_gradient),
+            )))
+            .with_child_pipeline(
+                0,
+                pipe_desc.with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_cubemap),
+                )),
+            )
+            .build(factory, None);
+
+        unsafe {
+            factory.destroy_shader_module(shader_vertex);
+            factory.destroy_shader_module(shader_gradient);
+            factory.destroy_shader_module(shader_cubemap);
+        }
+
+        let mut pipes = match pipes {
+            Err(e) => {
+                unsafe {
+                    factory.device().destroy_pipeline_layout(pipeline_layout);
+                }
+                return Err(e);
+            }
+            Ok(pipes) => pipes,
+        };
+
+        Ok(Box::new(DrawIblBake::<B> {
+            pipeline_cubemap: pipes.pop().expect("Unreachable: pipeline was built"),
+            pipeline_gradient: pipes.pop().expect("Unreachable: pipeline was built"),
+            pipeline_layout,
+            gradient,
+            textures,
+            baked: None,
+            pending: None,
+        }))
+    }
+}
+
+/// What `DrawIblBake` draws in the current frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Bake {
+    Gradient,
+    Cubemap(TextureId),
+}
+
+/// Bakes the image-based lighting maps, see the [module documentation](index.html).
+#[derive(Debug)]
+pub struct DrawIblBake<B: Backend> {
+    pipeline_gradient: B::GraphicsPipeline,
+    pipeline_cubemap: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    gradient: DynamicUniform<B, GradientUniform>,
+    textures: TextureSub<B>,
+    /// Source of the maps currently in the images, `Some(None)` once baked from the ambient
+    /// color.
+    baked: Option<Option<EnvironmentSource>>,
+    pending: Option<Bake>,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawIblBake<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        resources: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare ibl bake");
+
+        let (environment, ambient) = <(
+            Option<Read<'_, EnvironmentMap>>,
+            Option<Read<'_, AmbientColor>>,
+        )>::fetch(resources);
+
+        self.textures.maintain(factory, resources);
+        self.pending = None;
+
+        let source = environment.and_then(|e| e.source.clone());
+        if self.baked.as_ref() == Some(&source) {
+            return PrepareResult::DrawRecord;
+        }
+
+        let bake = match &source {
+            Some(EnvironmentSource::Gradient { nadir, zenith }) => {
+                self.gradient
+                    .write(factory, index, GradientUniform::new(*nadir, *zenith));
+                Some(Bake::Gradient)
+            }
+            Some(EnvironmentSource::Cubemap(handle)) => self
+                .textures
+                .insert(
+                    factory,
+                    resources,
+                    handle,
+                    hal::image::Layout::ShaderReadOnlyOptimal,
+                )
+                .map(|(id, _)| Bake::Cubemap(id)),
+            None => None,
+        };
+
+        if bake.is_some() {
+            self.baked = Some(source);
+            self.pending = bake;
+        } else if self.baked.is_none() {
+            // Until a cubemap is loaded, the maps light the scene with the ambient color.
+            let color = ambient.map_or(Srgb::new(0.0, 0.0, 0.0), |a| a.0.color);
+            self.gradient
+                .write(factory, index, GradientUniform::new(color, color));
+            self.baked = Some(None);
+            self.pending = Some(Bake::Gradient);
+        }
+
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _resources: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw ibl bake");
+
+        match self.pending {
+            Some(Bake::Gradient) => {
+                encoder.bind_graphics_pipeline(&self.pipeline_gradient);
+                self.gradient
+                    .bind(index, &self.pipeline_layout, 0, &mut encoder);
+            }
+            Some(Bake::Cubemap(id)) if self.textures.loaded(id) => {
+                encoder.bind_graphics_pipeline(&self.pipeline_cubemap);
+                self.textures
+                    .bind(&self.pipeline_layout, 1, id, &mut encoder);
+            }
+            _ => return,
+        }
+        unsafe {
+            encoder.draw(0..3, 0..1);
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
+        unsafe {
+            factory
+                .device()
+                .destroy_graphics_pipeline(self.pipeline_gradient);
+            factory
+                .device()
+                .destroy_graphics_pipeline(self.pipeline_cubemap);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
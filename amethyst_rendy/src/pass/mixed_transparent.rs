@@ -0,0 +1,593 @@
+use crate::{
+    batch::{GroupIterator, OrderedOneLevelBatch, OrderedTwoLevelBatch},
+    layers::RenderLayers,
+    mtl::{FullTextureSet, Material},
+    pod::{SkinnedVertexArgs, SpriteArgs, VertexArgs},
+    resources::{ClipMode, Tint},
+    skinning::{JointTransforms, SkinningMode},
+    sprite::{SpriteRender, SpriteSheet},
+    submodules::{
+        DynamicVertexBuffer, EnvironmentSub, FlatEnvironmentSub, MaterialId, MaterialSub,
+        SkinningSub, TextureId, TextureSub,
+    },
+    transparent_order::{TransparentKind, TransparentOrder},
+    types::{Backend, Mesh, Texture},
+    util,
+};
+use amethyst_assets::{AssetStorage, Handle};
+use amethyst_core::{
+    ecs::{Join, Read, ReadExpect, ReadStorage, SystemData, World},
+    transform::Transform,
+};
+use derivative::Derivative;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, NodeBuffer, NodeImage,
+    },
+    hal::{self, device::Device},
+    mesh::VertexFormat,
+};
+use std::{marker::PhantomData, ops::Range};
+
+use super::{
+    base_3d::{build_pipelines, supported_skinning_mode},
+    flat2d::build_sprite_pipeline,
+    Base3DPassDef,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Draw transparent meshes and sprites interleaved in the order of `TransparentOrder`.
+#[derive(Clone, Derivative)]
+#[derivative(Debug(bound = ""), Default(bound = ""))]
+pub struct DrawMixedTransparentDesc<B: Backend, T: Base3DPassDef> {
+    skinning: bool,
+    skinning_mode: SkinningMode,
+    marker: PhantomData<(B, T)>,
+}
+
+impl<B: Backend, T: Base3DPassDef> DrawMixedTransparentDesc<B, T> {
+    /// Create pass in default configuration
+    pub fn new() -> Self {
+        Self {
+            skinning: false,
+            skinning_mode: SkinningMode::Linear,
+            marker: PhantomData,
+        }
+    }
+
+    /// Create pass in with vertex skinning enabled
+    pub fn skinned() -> Self {
+        Self {
+            skinning: true,
+            skinning_mode: SkinningMode::Linear,
+            marker: PhantomData,
+        }
+    }
+
+    /// Create pass in with vertex skinning enabled if true is passed
+    pub fn with_skinning(mut self, skinned: bool) -> Self {
+        self.skinning = skinned;
+        self
+    }
+
+    /// Create pass blending the joints of skinned meshes with the given mode, if supported by
+    /// the pass.
+    pub fn with_skinning_mode(mut self, mode: SkinningMode) -> Self {
+        self.skinning_mode = supported_skinning_mode::<T>(mode);
+        self
+    }
+}
+
+impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawMixedTransparentDesc<B, T> {
+    fn build(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build_mixed_trans");
+
+        // The fragment shaders of `build_pipelines` read the clip planes of the view.
+        let env = EnvironmentSub::new(
+            factory,
+            [
+                hal::pso::ShaderStageFlags::VERTEX | hal::pso::ShaderStageFlags::FRAGMENT,
+                hal::pso::ShaderStageFlags::FRAGMENT,
+            ],
+        )?;
+        let materials = MaterialSub::new(factory)?;
+        let skinning = SkinningSub::new(factory, self.skinning_mode)?;
+        let sprite_env = FlatEnvironmentSub::new(factory)?;
+        let textures = TextureSub::new(factory)?;
+
+        let mut vertex_format_base = T::base_format();
+        let mut vertex_format_skinned = T::skinned_format();
+
+        let (mut pipelines, pipeline_layout) = build_pipelines::<B, T>(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            &vertex_format_base,
+            &vertex_format_skinned,
+            &[],
+            self.skinning,
+            self.skinning_mode,
+            false,
+            true,
+            ClipMode::Disabled,
+            None,
+            vec![
+                env.raw_layout(),
+                materials.raw_layout(),
+                skinning.raw_layout(),
+            ],
+        )?;
+
+        let (sprite_pipeline, sprite_pipeline_layout) = match build_sprite_pipeline(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            true,
+            vec![sprite_env.raw_layout(), textures.raw_layout()],
+        ) {
+            Ok(sprite_pipeline) => sprite_pipeline,
+            Err(e) => {
+                unsafe {
+                    for pipeline in pipelines {
+                        factory.device().destroy_graphics_pipeline(pipeline);
+                    }
+                    factory.device().destroy_pipeline_layout(pipeline_layout);
+                }
+                return Err(e);
+            }
+        };
+
+        vertex_format_base.sort();
+        vertex_format_skinned.sort();
+
+        Ok(Box::new(DrawMixedTransparent::<B, T> {
+            pipeline_basic: pipelines.remove(0),
+            pipeline_skinned: pipelines.pop(),
+            pipeline_layout,
+            sprite_pipeline,
+            sprite_pipeline_layout,
+            runs: Vec::new(),
+            old_runs: Vec::new(),
+            mesh_runs: Vec::new(),
+            sprite_runs: Vec::new(),
+            vertex_format_base,
+            vertex_format_skinned,
+            env,
+            materials,
+            skinning,
+            sprite_env,
+            textures,
+            models: DynamicVertexBuffer::new(),
+            skinned_models: DynamicVertexBuffer::new(),
+            sprites: DynamicVertexBuffer::new(),
+            change: Default::default(),
+            marker: PhantomData,
+        }))
+    }
+}
+
+/// Batches of one run of consecutive transparent meshes.
+#[derive(Debug, Default)]
+struct MeshRun {
+    statics: OrderedTwoLevelBatch<(usize, MaterialId), u32, VertexArgs>,
+    skinned: OrderedTwoLevelBatch<(usize, MaterialId), u32, SkinnedVertexArgs>,
+}
+
+/// Draws transparent meshes and sprites back to front, interleaving them by depth.
+///
+/// Consecutive renderables of the same kind are batched together, so pipelines are only
+/// switched where `TransparentOrder` alternates between meshes and sprites.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct DrawMixedTransparent<B: Backend, T: Base3DPassDef> {
+    pipeline_basic: B::GraphicsPipeline,
+    pipeline_skinned: Option<B::GraphicsPipeline>,
+    pipeline_layout: B::PipelineLayout,
+    sprite_pipeline: B::GraphicsPipeline,
+    sprite_pipeline_layout: B::PipelineLayout,
+    runs: Vec<TransparentKind>,
+    old_runs: Vec<TransparentKind>,
+    mesh_runs: Vec<MeshRun>,
+    sprite_runs: Vec<OrderedOneLevelBatch<TextureId, SpriteArgs>>,
+    vertex_format_base: Vec<VertexFormat>,
+    vertex_format_skinned: Vec<VertexFormat>,
+    env: EnvironmentSub<B>,
+    materials: MaterialSub<B, FullTextureSet>,
+    skinning: SkinningSub<B>,
+    sprite_env: FlatEnvironmentSub<B>,
+    textures: TextureSub<B>,
+    models: DynamicVertexBuffer<B, VertexArgs>,
+    skinned_models: DynamicVertexBuffer<B, SkinnedVertexArgs>,
+    sprites: DynamicVertexBuffer<B, SpriteArgs>,
+    change: util::ChangeDetection,
+    marker: PhantomData<T>,
+}
+
+impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawMixedTransparent<B, T> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare mixed transparent");
+
+        let (
+            mesh_storage,
+            sprite_sheet_storage,
+            tex_storage,
+            order,
+            meshes,
+            materials,
+            sprite_renders,
+            transforms,
+            joints,
+            tints,
+            layers,
+        ) = <(
+            Read<'_, AssetStorage<Mesh>>,
+            Read<'_, AssetStorage<SpriteSheet>>,
+            Read<'_, AssetStorage<Texture>>,
+            ReadExpect<'_, TransparentOrder>,
+            ReadStorage<'_, Handle<Mesh>>,
+            ReadStorage<'_, Handle<Material>>,
+            ReadStorage<'_, SpriteRender>,
+            ReadStorage<'_, Transform>,
+            ReadStorage<'_, JointTransforms>,
+            ReadStorage<'_, Tint>,
+            ReadStorage<'_, RenderLayers>,
+        )>::fetch(world);
+
+        self.sprite_env.process(factory, index, world);
+        self.materials.maintain();
+
+        std::mem::swap(&mut self.old_runs, &mut self.runs);
+        self.runs.clear();
+
+        let skinning_enabled = self.pipeline_skinned.is_some();
+        let materials_ref = &mut self.materials;
+        let skinning_ref = &mut self.skinning;
+        let env_ref = &mut self.env;
+        let textures_ref = &mut self.textures;
+        let mut changed = false;
+        let mut mesh_count = 0;
+        let mut sprite_count = 0;
+
+        for (kind, range) in order.runs() {
+            let entities = order.renderables[range].iter().map(|r| r.entity);
+            match kind {
+                TransparentKind::Mesh => {
+                    if self.mesh_runs.len() == mesh_count {
+                        self.mesh_runs.push(MeshRun::default());
+                    }
+                    let run = &mut self.mesh_runs[mesh_count];
+                    mesh_count += 1;
+                    run.statics.swap_clear();
+                    run.skinned.swap_clear();
+
+                    let statics_ref = &mut run.statics;
+                    let mut joined = (
+                        (
+                            &materials,
+                            &meshes,
+                            &transforms,
+                            tints.maybe(),
+                            layers.maybe(),
+                        ),
+                        !&joints,
+                    )
+                        .join();
+                    entities
+                        .clone()
+                        .filter_map(|e| joined.get_unchecked(e.id()))
+                        .map(|((mat, mesh, tform, tint, layers), _)| {
+                            (
+                                (mat, mesh.id(), RenderLayers::of(layers)),
+                                VertexArgs::from_object_data(tform, tint),
+                            )
+                        })
+                        .for_each_group(|(mat, mesh_id, layers), data| {
+                            if mesh_storage.contains_id(mesh_id) {
+                                if let Some((mat, this_changed)) =
+                                    materials_ref.insert(factory, world, mat)
+                                {
+                                    changed = changed || this_changed;
+                                    let key = (env_ref.layer_slot(layers), mat);
+                                    statics_ref.insert(key, mesh_id, data.drain(..));
+                                }
+                            }
+                        });
+
+                    if skinning_enabled {
+                        let skinned_ref = &mut run.skinned;
+                        let mut joined = (
+                            &materials,
+                            &meshes,
+                            &transforms,
+                            tints.maybe(),
+                            layers.maybe(),
+                            &joints,
+                        )
+                            .join();
+                        entities
+                            .filter_map(|e| joined.get_unchecked(e.id()))
+                            .map(|(mat, mesh, tform, tint, layers, joints)| {
+                                (
+                                    (mat, mesh.id(), RenderLayers::of(layers)),
+                                    SkinnedVertexArgs::from_object_data(
+                                        tform,
+                                        tint,
+                                        skinning_ref.insert(joints),
+                                    ),
+                                )
+                            })
+                            .for_each_group(|(mat, mesh_id, layers), data| {
+                                if mesh_storage.contains_id(mesh_id) {
+                                    if let Some((mat, this_changed)) =
+                                        materials_ref.insert(factory, world, mat)
+                                    {
+                                        changed = changed || this_changed;
+                                        let key = (env_ref.layer_slot(layers), mat);
+                                        skinned_ref.insert(key, mesh_id, data.drain(..));
+                                    }
+                                }
+                            });
+                    }
+
+                    changed = changed || run.statics.changed() || run.skinned.changed();
+                }
+                TransparentKind::Sprite => {
+                    if self.sprite_runs.len() == sprite_count {
+                        self.sprite_runs.push(Default::default());
+                    }
+                    let sprites_ref = &mut self.sprite_runs[sprite_count];
+                    sprite_count += 1;
+                    sprites_ref.swap_clear();
+
+                    let mut joined = (&sprite_renders, &transforms, tints.maybe()).join();
+                    entities
+                        .filter_map(|e| joined.get_unchecked(e.id()))
+                        .filter_map(|(sprite_render, global, tint)| {
+                            let (batch_data, texture) = SpriteArgs::from_data(
+                                &tex_storage,
+                                &sprite_sheet_storage,
+                                &sprite_render,
+                                &global,
+                                tint,
+                            )?;
+                            let (tex_id, this_changed) = textures_ref.insert(
+                                factory,
+                                world,
+                                texture,
+                                hal::image::Layout::ShaderReadOnlyOptimal,
+                            )?;
+                            changed = changed || this_changed;
+                            Some((tex_id, batch_data))
+                        })
+                        .for_each_group(|tex_id, batch_data| {
+                            sprites_ref.insert(tex_id, batch_data.drain(..));
+                        });
+
+                    changed = changed || sprites_ref.changed();
+                }
+            }
+            self.runs.push(kind);
+        }
+
+        self.mesh_runs.truncate(mesh_count);
+        self.sprite_runs.truncate(sprite_count);
+        // Prepare the environments of the layers drawn this frame
+        self.env.process(factory, index, world);
+        self.textures.maintain(factory, world);
+        changed = changed || self.runs != self.old_runs;
+
+        {
+            #[cfg(feature = "profiler")]
+            profile_scope!("write");
+
+            self.models.write(
+                factory,
+                index,
+                self.mesh_runs
+                    .iter()
+                    .map(|r| r.statics.count())
+                    .sum::<usize>() as u64,
+                self.mesh_runs.iter().map(|r| r.statics.data()),
+            );
+            self.skinned_models.write(
+                factory,
+                index,
+                self.mesh_runs
+                    .iter()
+                    .map(|r| r.skinned.count())
+                    .sum::<usize>() as u64,
+                self.mesh_runs.iter().map(|r| r.skinned.data()),
+            );
+            self.sprites.write(
+                factory,
+                index,
+                self.sprite_runs.iter().map(|r| r.count()).sum::<usize>() as u64,
+                self.sprite_runs.iter().map(|r| r.data()),
+            );
+            self.skinning.commit(factory, index);
+        }
+
+        self.change.prepare_result(index, changed)
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw mixed transparent");
+
+        let mesh_storage = <Read<'_, AssetStorage<Mesh>>>::fetch(world);
+        let layout = &self.pipeline_layout;
+        let sprite_layout = &self.sprite_pipeline_layout;
+        let encoder = &mut encoder;
+
+        let models_loc = self.vertex_format_base.len() as u32;
+        let skin_models_loc = self.vertex_format_skinned.len() as u32;
+
+        let mut mesh_runs = self.mesh_runs.iter();
+        let mut sprite_runs = self.sprite_runs.iter();
+        let mut static_offset = 0;
+        let mut skinned_offset = 0;
+        let mut sprite_offset = 0;
+
+        for kind in &self.runs {
+            match kind {
+                TransparentKind::Mesh => {
+                    let run = mesh_runs
+                        .next()
+                        .expect("Unreachable: every mesh run has batches");
+
+                    if run.statics.count() > 0 {
+                        encoder.bind_graphics_pipeline(&self.pipeline_basic);
+                        if self.models.bind(index, models_loc, 0, encoder) {
+                            draw_meshes::<B, T, _>(
+                                &run.statics,
+                                static_offset,
+                                &self.env,
+                                index,
+                                &self.materials,
+                                layout,
+                                &self.vertex_format_base,
+                                &mesh_storage,
+                                encoder,
+                            );
+                        }
+                    }
+
+                    if let (Some(pipeline_skinned), true) =
+                        (self.pipeline_skinned.as_ref(), run.skinned.count() > 0)
+                    {
+                        encoder.bind_graphics_pipeline(pipeline_skinned);
+                        if self.skinned_models.bind(index, skin_models_loc, 0, encoder) {
+                            self.skinning.bind(index, layout, 2, encoder);
+                            draw_meshes::<B, T, _>(
+                                &run.skinned,
+                                skinned_offset,
+                                &self.env,
+                                index,
+                                &self.materials,
+                                layout,
+                                &self.vertex_format_skinned,
+                                &mesh_storage,
+                                encoder,
+                            );
+                        }
+                    }
+
+                    static_offset += run.statics.count() as u32;
+                    skinned_offset += run.skinned.count() as u32;
+                }
+                TransparentKind::Sprite => {
+                    let run = sprite_runs
+                        .next()
+                        .expect("Unreachable: every sprite run has a batch");
+
+                    encoder.bind_graphics_pipeline(&self.sprite_pipeline);
+                    self.sprite_env.bind(index, sprite_layout, 0, encoder);
+                    if self.sprites.bind(index, 0, 0, encoder) {
+                        for (&tex, range) in run.iter() {
+                            if self.textures.loaded(tex) {
+                                self.textures.bind(sprite_layout, 1, tex, encoder);
+                                unsafe {
+                                    encoder.draw(
+                                        0..4,
+                                        range.start + sprite_offset..range.end + sprite_offset,
+                                    );
+                                }
+                            }
+                        }
+                    }
+
+                    sprite_offset += run.count() as u32;
+                }
+            }
+        }
+    }
+
+    fn dispose(mut self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
+        unsafe {
+            factory
+                .device()
+                .destroy_graphics_pipeline(self.pipeline_basic);
+            if let Some(pipeline) = self.pipeline_skinned.take() {
+                factory.device().destroy_graphics_pipeline(pipeline);
+            }
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+            factory
+                .device()
+                .destroy_graphics_pipeline(self.sprite_pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.sprite_pipeline_layout);
+        }
+    }
+}
+
+/// Draws the batches of one mesh run, whose instances start at `offset` in the bound buffer.
+fn draw_meshes<B: Backend, T: Base3DPassDef, D>(
+    batches: &OrderedTwoLevelBatch<(usize, MaterialId), u32, D>,
+    offset: u32,
+    env: &EnvironmentSub<B>,
+    index: usize,
+    materials: &MaterialSub<B, FullTextureSet>,
+    layout: &B::PipelineLayout,
+    vertex_format: &[VertexFormat],
+    mesh_storage: &AssetStorage<Mesh>,
+    encoder: &mut RenderPassEncoder<'_, B>,
+) {
+    for (&(slot, mat), batches) in batches.iter() {
+        if materials.loaded(mat) {
+            env.bind_layers(index, slot, layout, 0, encoder);
+            materials.bind(layout, 1, mat, encoder);
+            for (mesh, range) in batches {
+                debug_assert!(mesh_storage.contains_id(*mesh));
+                if let Some(mesh) =
+                    B::unwrap_mesh(unsafe { mesh_storage.get_by_id_unchecked(*mesh) })
+                {
+                    let range: Range<u32> = range.start + offset..range.end + offset;
+                    if let Err(error) = mesh.bind_and_draw(0, vertex_format, range, encoder) {
+                        log::warn!(
+                            "Trying to draw a mesh that lacks {:?} vertex attributes. Pass {} requires attributes {:?}.",
+                            error.not_found.attributes,
+                            T::NAME,
+                            vertex_format,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,14 +1,30 @@
 //! Passes and shaders implemented by amethyst
 
 mod base_3d;
+mod color_grade;
 mod debug_lines;
+mod decal;
+mod dof;
 mod flat;
 mod flat2d;
+mod fullscreen;
+mod ibl;
+mod mixed_transparent;
+mod oit;
+mod outline;
 mod pbr;
+mod point_shadow;
+mod screen_fade;
 mod shaded;
 mod skybox;
+mod terrain;
+mod water;
 
-pub use self::{base_3d::*, debug_lines::*, flat::*, flat2d::*, pbr::*, shaded::*, skybox::*};
+pub use self::{
+    base_3d::*, color_grade::*, debug_lines::*, decal::*, dof::*, flat::*, flat2d::*,
+    fullscreen::*, ibl::*, mixed_transparent::*, oit::*, outline::*, pbr::*, point_shadow::*,
+    screen_fade::*, shaded::*, skybox::*, terrain::*, water::*,
+};
 
 use rendy::{hal::pso::ShaderStageFlags, shader::SpirvShader};
 
@@ -25,6 +41,12 @@ lazy_static::lazy_static! {
         "main",
     ).unwrap();
 
+    static ref POS_TEX_SKIN_DQ_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/pos_tex_skin_dq.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
     static ref POS_NORM_TEX_VERTEX: SpirvShader = SpirvShader::from_bytes(
         include_bytes!("../../compiled/vertex/pos_norm_tex.vert.spv"),
         ShaderStageFlags::VERTEX,
@@ -37,6 +59,12 @@ lazy_static::lazy_static! {
         "main",
     ).unwrap();
 
+    static ref POS_NORM_TEX_SKIN_DQ_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/pos_norm_tex_skin_dq.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
     static ref POS_NORM_TANG_TEX_VERTEX: SpirvShader = SpirvShader::from_bytes(
         include_bytes!("../../compiled/vertex/pos_norm_tang_tex.vert.spv"),
         ShaderStageFlags::VERTEX,
@@ -49,6 +77,30 @@ lazy_static::lazy_static! {
         "main",
     ).unwrap();
 
+    static ref POS_NORM_TANG_TEX_SKIN_DQ_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/pos_norm_tang_tex_skin_dq.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref POS_TEX_CLIP_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/pos_tex_clip.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref POS_NORM_TEX_CLIP_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/pos_norm_tex_clip.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref POS_NORM_TANG_TEX_CLIP_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/pos_norm_tang_tex_clip.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
     static ref FLAT_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
         include_bytes!("../../compiled/fragment/flat.frag.spv"),
         ShaderStageFlags::FRAGMENT,
@@ -102,4 +154,226 @@ lazy_static::lazy_static! {
         ShaderStageFlags::FRAGMENT,
         "main",
     ).unwrap();
+
+    static ref FLAT_OIT_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/flat_oit.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref SHADED_OIT_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/shaded_oit.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref PBR_OIT_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/pbr_oit.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref FULLSCREEN_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/fullscreen.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref OUTLINE_MASK_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/outline_mask.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref OUTLINE_MASK_SKIN_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/outline_mask_skin.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref OUTLINE_MASK_SKIN_DQ_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/outline_mask_skin_dq.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref OUTLINE_MASK_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/outline_mask.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref OUTLINE_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/outline.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref OUTLINE_OCCLUDED_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/outline_occluded.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref SCREEN_FADE_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/screen_fade.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref COLOR_GRADE_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/color_grade.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref DECAL_RECEIVER_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/decal_receiver.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref DECAL_RECEIVER_SKIN_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/decal_receiver_skin.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref DECAL_RECEIVER_SKIN_DQ_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/decal_receiver_skin_dq.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref DECAL_RECEIVER_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/decal_receiver.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref DECAL_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/decal.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref DECAL_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/decal.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref TERRAIN_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/terrain.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref TERRAIN_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/terrain.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref WATER_SCENE_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/water_scene.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref WATER_SCENE_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/water_scene.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref WATER_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/water.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref WATER_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/water.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref DOF_BLUR_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/dof_blur.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref DOF_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/dof.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref POINT_SHADOW_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/point_shadow.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref POINT_SHADOW_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/point_shadow.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref POINT_SHADOW_CLEAR_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/point_shadow_clear.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref OIT_COMPOSITE_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/oit_composite.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref PBR_IBL_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/pbr_ibl.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref IBL_BAKE_GRADIENT_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/ibl_bake_gradient.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref IBL_BAKE_CUBEMAP_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/ibl_bake_cubemap.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref POS_NORM_TEX_LIGHTMAP_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/pos_norm_tex_lightmap.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref POS_NORM_TANG_TEX_LIGHTMAP_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/vertex/pos_norm_tang_tex_lightmap.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref SHADED_LIGHTMAP_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/shaded_lightmap.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    static ref PBR_LIGHTMAP_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../../compiled/fragment/pbr_lightmap.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
 }
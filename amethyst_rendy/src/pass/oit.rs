@@ -0,0 +1,885 @@
+//! Weighted blended order-independent transparency.
+//!
+//! Transparent meshes are drawn in any order into an accumulation target with two colors, a
+//! weighted sum of their premultiplied colors and the product of their transparencies called
+//! revealage. `DrawOitComposite` then resolves both images over the opaque image of the main
+//! target. Opaque meshes are drawn to the depth buffer of the accumulation target first, so
+//! that transparent surfaces behind them are hidden.
+use crate::{
+    batch::{GroupIterator, OneLevelBatch, TwoLevelBatch},
+    layers::RenderLayers,
+    mtl::{FullTextureSet, Material},
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    pod::{SkinnedVertexArgs, VertexArgs},
+    resources::Tint,
+    skinning::{JointTransforms, SkinningMode},
+    submodules::{DynamicVertexBuffer, EnvironmentSub, MaterialId, MaterialSub, SkinningSub},
+    types::{Backend, Mesh},
+    util,
+    visibility::Visibility,
+};
+use amethyst_assets::{AssetStorage, Handle};
+use amethyst_core::{
+    ecs::{Join, Read, ReadExpect, ReadStorage, SystemData, World},
+    transform::Transform,
+};
+use derivative::Derivative;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
+    },
+    hal::{
+        self,
+        adapter::PhysicalDevice,
+        device::Device,
+        format::{Aspects, Format, ImageFeature, Swizzle},
+        image::{Filter, SamplerInfo, SubresourceRange, ViewKind, WrapMode},
+        pso,
+    },
+    mesh::{AsVertex, VertexFormat},
+    resource::{
+        DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle, ImageView,
+        ImageViewInfo, Sampler,
+    },
+    shader::{Shader, SpirvShader},
+};
+use smallvec::SmallVec;
+use std::marker::PhantomData;
+
+use super::{
+    base_3d::{skinned_vertex_shader, supported_skinning_mode},
+    Base3DPassDef,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Format of the accumulation image, the weighted sum of premultiplied colors.
+pub const OIT_ACCUM_FORMAT: Format = Format::Rgba16Sfloat;
+/// Format of the revealage image, the product of the transparencies of all surfaces.
+pub const OIT_REVEALAGE_FORMAT: Format = Format::R16Sfloat;
+
+/// Returns true if the device can blend into and sample from the formats of the accumulation
+/// target. Renderers fall back to sorted blending otherwise.
+pub fn oit_supported<B: Backend>(factory: &Factory<B>) -> bool {
+    let required = ImageFeature::COLOR_ATTACHMENT
+        | ImageFeature::COLOR_ATTACHMENT_BLEND
+        | ImageFeature::SAMPLED;
+    [OIT_ACCUM_FORMAT, OIT_REVEALAGE_FORMAT]
+        .iter()
+        .all(|format| {
+            factory
+                .physical()
+                .format_properties(Some(*format))
+                .optimal_tiling
+                .contains(required)
+        })
+}
+
+/// Draw transparent meshes into the two colors of the weighted blended transparency target.
+///
+/// The target must have `OIT_ACCUM_FORMAT` and `OIT_REVEALAGE_FORMAT` colors, cleared to 0 and 1
+/// respectively, and a depth output.
+#[derive(Clone, Derivative)]
+#[derivative(Debug(bound = ""), Default(bound = ""))]
+pub struct DrawOitAccumDesc<B: Backend, T: Base3DPassDef> {
+    skinning: bool,
+    skinning_mode: SkinningMode,
+    marker: PhantomData<(B, T)>,
+}
+
+impl<B: Backend, T: Base3DPassDef> DrawOitAccumDesc<B, T> {
+    /// Create pass in default configuration
+    pub fn new() -> Self {
+        Self {
+            skinning: false,
+            skinning_mode: SkinningMode::Linear,
+            marker: PhantomData,
+        }
+    }
+
+    /// Create pass in with vertex skinning enabled if true is passed
+    pub fn with_skinning(mut self, skinned: bool) -> Self {
+        self.skinning = skinned;
+        self
+    }
+
+    /// Create pass blending the joints of skinned meshes with the given mode, if supported by
+    /// the pass.
+    pub fn with_skinning_mode(mut self, mode: SkinningMode) -> Self {
+        self.skinning_mode = supported_skinning_mode::<T>(mode);
+        self
+    }
+}
+
+impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawOitAccumDesc<B, T> {
+    fn colors(&self) -> usize {
+        2
+    }
+
+    fn build(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build_oit_accum");
+
+        let env = EnvironmentSub::new(
+            factory,
+            [
+                hal::pso::ShaderStageFlags::VERTEX,
+                hal::pso::ShaderStageFlags::FRAGMENT,
+            ],
+        )?;
+        let materials = MaterialSub::new(factory)?;
+        let skinning = SkinningSub::new(factory, self.skinning_mode)?;
+
+        let mut vertex_format_base = T::base_format();
+        let mut vertex_format_skinned = T::skinned_format();
+
+        let (mut pipelines, pipeline_layout) = build_accum_pipelines::<B, T>(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            &vertex_format_base,
+            &vertex_format_skinned,
+            self.skinning,
+            self.skinning_mode,
+            vec![
+                env.raw_layout(),
+                materials.raw_layout(),
+                skinning.raw_layout(),
+            ],
+        )?;
+
+        vertex_format_base.sort();
+        vertex_format_skinned.sort();
+
+        let (depth_skinned, accum_skinned) = if self.skinning {
+            let accum = pipelines.pop();
+            (pipelines.pop(), accum)
+        } else {
+            (None, None)
+        };
+        let accum_basic = pipelines.pop().expect("Unreachable: pipeline was built");
+        let depth_basic = pipelines.pop().expect("Unreachable: pipeline was built");
+
+        Ok(Box::new(DrawOitAccum::<B, T> {
+            depth_basic,
+            depth_skinned,
+            accum_basic,
+            accum_skinned,
+            pipeline_layout,
+            opaque_batches: Default::default(),
+            opaque_skinned_batches: Default::default(),
+            static_batches: Default::default(),
+            skinned_batches: Default::default(),
+            vertex_format_base,
+            vertex_format_skinned,
+            env,
+            materials,
+            skinning,
+            opaque_models: DynamicVertexBuffer::new(),
+            opaque_skinned_models: DynamicVertexBuffer::new(),
+            models: DynamicVertexBuffer::new(),
+            skinned_models: DynamicVertexBuffer::new(),
+            marker: PhantomData,
+        }))
+    }
+}
+
+/// Draws opaque meshes to the depth buffer and transparent meshes to the accumulation and
+/// revealage images, see the [module documentation](index.html).
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct DrawOitAccum<B: Backend, T: Base3DPassDef> {
+    depth_basic: B::GraphicsPipeline,
+    depth_skinned: Option<B::GraphicsPipeline>,
+    accum_basic: B::GraphicsPipeline,
+    accum_skinned: Option<B::GraphicsPipeline>,
+    pipeline_layout: B::PipelineLayout,
+    opaque_batches: OneLevelBatch<u32, VertexArgs>,
+    opaque_skinned_batches: OneLevelBatch<u32, SkinnedVertexArgs>,
+    static_batches: TwoLevelBatch<(usize, MaterialId), u32, SmallVec<[VertexArgs; 4]>>,
+    skinned_batches: TwoLevelBatch<(usize, MaterialId), u32, SmallVec<[SkinnedVertexArgs; 4]>>,
+    vertex_format_base: Vec<VertexFormat>,
+    vertex_format_skinned: Vec<VertexFormat>,
+    env: EnvironmentSub<B>,
+    materials: MaterialSub<B, FullTextureSet>,
+    skinning: SkinningSub<B>,
+    opaque_models: DynamicVertexBuffer<B, VertexArgs>,
+    opaque_skinned_models: DynamicVertexBuffer<B, SkinnedVertexArgs>,
+    models: DynamicVertexBuffer<B, VertexArgs>,
+    skinned_models: DynamicVertexBuffer<B, SkinnedVertexArgs>,
+    marker: PhantomData<T>,
+}
+
+impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawOitAccum<B, T> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        resources: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare oit accum");
+
+        let (mesh_storage, visibility, meshes, materials, transforms, joints, tints, layers) =
+            <(
+                Read<'_, AssetStorage<Mesh>>,
+                ReadExpect<'_, Visibility>,
+                ReadStorage<'_, Handle<Mesh>>,
+                ReadStorage<'_, Handle<Material>>,
+                ReadStorage<'_, Transform>,
+                ReadStorage<'_, JointTransforms>,
+                ReadStorage<'_, Tint>,
+                ReadStorage<'_, RenderLayers>,
+            )>::fetch(resources);
+
+        self.materials.maintain();
+
+        self.opaque_batches.clear_inner();
+        self.opaque_skinned_batches.clear_inner();
+        self.static_batches.clear_inner();
+        self.skinned_batches.clear_inner();
+
+        let skinning_enabled = self.depth_skinned.is_some();
+        let materials_ref = &mut self.materials;
+        let skinning_ref = &mut self.skinning;
+        let env_ref = &mut self.env;
+        let opaque_ref = &mut self.opaque_batches;
+        let opaque_skinned_ref = &mut self.opaque_skinned_batches;
+        let statics_ref = &mut self.static_batches;
+        let skinned_ref = &mut self.skinned_batches;
+
+        // Only the depth of opaque meshes is drawn, so they are batched by mesh alone.
+        (
+            (&meshes, &transforms, tints.maybe(), !&joints),
+            &visibility.visible_unordered,
+        )
+            .join()
+            .map(|((mesh, tform, tint, _), _)| {
+                (mesh.id(), VertexArgs::from_object_data(tform, tint))
+            })
+            .for_each_group(|mesh_id, data| {
+                if mesh_storage.contains_id(mesh_id) {
+                    opaque_ref.insert(mesh_id, data.drain(..));
+                }
+            });
+
+        let mut joined = (
+            (
+                &materials,
+                &meshes,
+                &transforms,
+                tints.maybe(),
+                layers.maybe(),
+            ),
+            !&joints,
+        )
+            .join();
+        visibility
+            .visible_ordered
+            .iter()
+            .filter_map(|e| joined.get_unchecked(e.id()))
+            .map(|((mat, mesh, tform, tint, layers), _)| {
+                (
+                    (mat, mesh.id(), RenderLayers::of(layers)),
+                    VertexArgs::from_object_data(tform, tint),
+                )
+            })
+            .for_each_group(|(mat, mesh_id, layers), data| {
+                if mesh_storage.contains_id(mesh_id) {
+                    if let Some((mat, _)) = materials_ref.insert(factory, resources, mat) {
+                        let key = (env_ref.layer_slot(layers), mat);
+                        statics_ref.insert(key, mesh_id, data.drain(..));
+                    }
+                }
+            });
+
+        if skinning_enabled {
+            (
+                (&meshes, &transforms, tints.maybe(), &joints),
+                &visibility.visible_unordered,
+            )
+                .join()
+                .map(|((mesh, tform, tint, joints), _)| {
+                    (
+                        mesh.id(),
+                        SkinnedVertexArgs::from_object_data(
+                            tform,
+                            tint,
+                            skinning_ref.insert(joints),
+                        ),
+                    )
+                })
+                .for_each_group(|mesh_id, data| {
+                    if mesh_storage.contains_id(mesh_id) {
+                        opaque_skinned_ref.insert(mesh_id, data.drain(..));
+                    }
+                });
+
+            let mut joined = (
+                &materials,
+                &meshes,
+                &transforms,
+                tints.maybe(),
+                layers.maybe(),
+                &joints,
+            )
+                .join();
+            visibility
+                .visible_ordered
+                .iter()
+                .filter_map(|e| joined.get_unchecked(e.id()))
+                .map(|(mat, mesh, tform, tint, layers, joints)| {
+                    (
+                        (mat, mesh.id(), RenderLayers::of(layers)),
+                        SkinnedVertexArgs::from_object_data(
+                            tform,
+                            tint,
+                            skinning_ref.insert(joints),
+                        ),
+                    )
+                })
+                .for_each_group(|(mat, mesh_id, layers), data| {
+                    if mesh_storage.contains_id(mesh_id) {
+                        if let Some((mat, _)) = materials_ref.insert(factory, resources, mat) {
+                            let key = (env_ref.layer_slot(layers), mat);
+                            skinned_ref.insert(key, mesh_id, data.drain(..));
+                        }
+                    }
+                });
+        }
+
+        // Prepare the environments of the layers drawn this frame
+        self.env.process(factory, index, resources);
+
+        {
+            #[cfg(feature = "profiler")]
+            profile_scope!("write");
+
+            self.opaque_batches.prune();
+            self.opaque_skinned_batches.prune();
+            self.static_batches.prune();
+            self.skinned_batches.prune();
+
+            self.opaque_models.write(
+                factory,
+                index,
+                self.opaque_batches.count() as u64,
+                self.opaque_batches.data(),
+            );
+            self.opaque_skinned_models.write(
+                factory,
+                index,
+                self.opaque_skinned_batches.count() as u64,
+                self.opaque_skinned_batches.data(),
+            );
+            self.models.write(
+                factory,
+                index,
+                self.static_batches.count() as u64,
+                self.static_batches.data(),
+            );
+            self.skinned_models.write(
+                factory,
+                index,
+                self.skinned_batches.count() as u64,
+                self.skinned_batches.data(),
+            );
+            self.skinning.commit(factory, index);
+        }
+
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        resources: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw oit accum");
+
+        let mesh_storage = <Read<'_, AssetStorage<Mesh>>>::fetch(resources);
+        let layout = &self.pipeline_layout;
+        let encoder = &mut encoder;
+
+        let models_loc = self.vertex_format_base.len() as u32;
+        let skin_models_loc = self.vertex_format_skinned.len() as u32;
+
+        encoder.bind_graphics_pipeline(&self.depth_basic);
+        self.env.bind(index, layout, 0, encoder);
+        if self.opaque_models.bind(index, models_loc, 0, encoder) {
+            draw_depth(
+                &self.opaque_batches,
+                &self.vertex_format_base,
+                &mesh_storage,
+                encoder,
+            );
+        }
+
+        if let Some(depth_skinned) = self.depth_skinned.as_ref() {
+            encoder.bind_graphics_pipeline(depth_skinned);
+            if self
+                .opaque_skinned_models
+                .bind(index, skin_models_loc, 0, encoder)
+            {
+                self.skinning.bind(index, layout, 2, encoder);
+                draw_depth(
+                    &self.opaque_skinned_batches,
+                    &self.vertex_format_skinned,
+                    &mesh_storage,
+                    encoder,
+                );
+            }
+        }
+
+        encoder.bind_graphics_pipeline(&self.accum_basic);
+        if self.models.bind(index, models_loc, 0, encoder) {
+            draw_transparent(
+                &self.static_batches,
+                &self.env,
+                index,
+                &self.materials,
+                layout,
+                &self.vertex_format_base,
+                &mesh_storage,
+                encoder,
+            );
+        }
+
+        if let Some(accum_skinned) = self.accum_skinned.as_ref() {
+            encoder.bind_graphics_pipeline(accum_skinned);
+            if self.skinned_models.bind(index, skin_models_loc, 0, encoder) {
+                self.skinning.bind(index, layout, 2, encoder);
+                draw_transparent(
+                    &self.skinned_batches,
+                    &self.env,
+                    index,
+                    &self.materials,
+                    layout,
+                    &self.vertex_format_skinned,
+                    &mesh_storage,
+                    encoder,
+                );
+            }
+        }
+    }
+
+    fn dispose(mut self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
+        unsafe {
+            let device = factory.device();
+            device.destroy_graphics_pipeline(self.depth_basic);
+            device.destroy_graphics_pipeline(self.accum_basic);
+            if let Some(pipeline) = self.depth_skinned.take() {
+                device.destroy_graphics_pipeline(pipeline);
+            }
+            if let Some(pipeline) = self.accum_skinned.take() {
+                device.destroy_graphics_pipeline(pipeline);
+            }
+            device.destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn draw_depth<B: Backend, D>(
+    batches: &OneLevelBatch<u32, D>,
+    vertex_format: &[VertexFormat],
+    mesh_storage: &AssetStorage<Mesh>,
+    encoder: &mut RenderPassEncoder<'_, B>,
+) {
+    for (mesh_id, range) in batches.iter() {
+        debug_assert!(mesh_storage.contains_id(*mesh_id));
+        if let Some(mesh) = B::unwrap_mesh(unsafe { mesh_storage.get_by_id_unchecked(*mesh_id) }) {
+            // Vertex attributes missing from a mesh are reported by the opaque pass.
+            let _ = mesh.bind_and_draw(0, vertex_format, range, encoder);
+        }
+    }
+}
+
+fn draw_transparent<B: Backend, D>(
+    batches: &TwoLevelBatch<(usize, MaterialId), u32, SmallVec<[D; 4]>>,
+    env: &EnvironmentSub<B>,
+    index: usize,
+    materials: &MaterialSub<B, FullTextureSet>,
+    layout: &B::PipelineLayout,
+    vertex_format: &[VertexFormat],
+    mesh_storage: &AssetStorage<Mesh>,
+    encoder: &mut RenderPassEncoder<'_, B>,
+) {
+    let mut instances_drawn = 0;
+    for (&(slot, mat_id), batches) in batches.iter() {
+        if materials.loaded(mat_id) {
+            env.bind_layers(index, slot, layout, 0, encoder);
+            materials.bind(layout, 1, mat_id, encoder);
+            for (mesh_id, batch_data) in batches {
+                debug_assert!(mesh_storage.contains_id(*mesh_id));
+                if let Some(mesh) =
+                    B::unwrap_mesh(unsafe { mesh_storage.get_by_id_unchecked(*mesh_id) })
+                {
+                    let range = instances_drawn..instances_drawn + batch_data.len() as u32;
+                    let _ = mesh.bind_and_draw(0, vertex_format, range, encoder);
+                }
+                instances_drawn += batch_data.len() as u32;
+            }
+        } else {
+            instances_drawn += batches
+                .map(|(_, batch_data)| batch_data.len() as u32)
+                .sum::<u32>();
+        }
+    }
+}
+
+/// Builds the depth and accumulation pipelines, followed by their skinned variants if enabled.
+fn build_accum_pipelines<B: Backend, T: Base3DPassDef>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    vertex_format_base: &[VertexFormat],
+    vertex_format_skinned: &[VertexFormat],
+    skinning: bool,
+    skinning_mode: SkinningMode,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(Vec<B::GraphicsPipeline>, B::PipelineLayout), failure::Error> {
+    let oit_fragment = T::oit_fragment_shader().ok_or_else(|| {
+        failure::format_err!(
+            "Pass {} has no weighted blended transparency shader.",
+            T::NAME
+        )
+    })?;
+
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let vertex_desc = |formats: &[VertexFormat], args: VertexFormat| {
+        formats
+            .iter()
+            .map(|f| (f.clone(), pso::VertexInputRate::Vertex))
+            .chain(Some((args, pso::VertexInputRate::Instance(1))))
+            .collect::<Vec<_>>()
+    };
+    let base_desc = vertex_desc(vertex_format_base, VertexArgs::vertex());
+    let skinned_desc = vertex_desc(vertex_format_skinned, SkinnedVertexArgs::vertex());
+
+    let shader_vertex_basic = unsafe { T::vertex_shader().module(factory).unwrap() };
+    let shader_vertex_skinned = unsafe {
+        skinned_vertex_shader::<T>(skinning_mode)
+            .module(factory)
+            .unwrap()
+    };
+    let shader_fragment = unsafe { oit_fragment.module(factory).unwrap() };
+
+    let common = PipelineDescBuilder::new()
+        .with_layout(&pipeline_layout)
+        .with_subpass(subpass)
+        .with_framebuffer_size(framebuffer_width, framebuffer_height)
+        .with_face_culling(pso::Face::BACK);
+
+    let depth = common
+        .clone()
+        .with_depth_test(pso::DepthTest {
+            fun: pso::Comparison::Less,
+            write: true,
+        })
+        .with_blend_targets(vec![
+            pso::ColorBlendDesc {
+                mask: pso::ColorMask::empty(),
+                blend: None,
+            };
+            2
+        ]);
+    let accum = common
+        .with_depth_test(pso::DepthTest {
+            fun: pso::Comparison::Less,
+            write: false,
+        })
+        .with_blend_targets(vec![
+            pso::ColorBlendDesc {
+                mask: pso::ColorMask::ALL,
+                blend: Some(pso::BlendState {
+                    color: pso::BlendOp::Add {
+                        src: pso::Factor::One,
+                        dst: pso::Factor::One,
+                    },
+                    alpha: pso::BlendOp::Add {
+                        src: pso::Factor::One,
+                        dst: pso::Factor::One,
+                    },
+                }),
+            },
+            pso::ColorBlendDesc {
+                mask: pso::ColorMask::RED,
+                blend: Some(pso::BlendState {
+                    color: pso::BlendOp::Add {
+                        src: pso::Factor::Zero,
+                        dst: pso::Factor::OneMinusSrcColor,
+                    },
+                    alpha: pso::BlendOp::Add {
+                        src: pso::Factor::Zero,
+                        dst: pso::Factor::OneMinusSrcAlpha,
+                    },
+                }),
+            },
+        ]);
+
+    let mut builder = PipelinesBuilder::new()
+        .with_pipeline(
+            depth
+                .clone()
+                .with_vertex_desc(&base_desc)
+                .with_shaders(util::simple_shader_set(&shader_vertex_basic, None)),
+        )
+        .with_pipeline(accum.clone().with_vertex_desc(&base_desc).with_shaders(
+            util::simple_shader_set(&shader_vertex_basic, Some(&shader_fragment)),
+        ));
+    if skinning {
+        builder = builder
+            .with_child_pipeline(
+                0,
+                depth
+                    .with_vertex_desc(&skinned_desc)
+                    .with_shaders(util::simple_shader_set(&shader_vertex_skinned, None)),
+            )
+            .with_child_pipeline(
+                1,
+                accum
+                    .with_vertex_desc(&skinned_desc)
+                    .with_shaders(util::simple_shader_set(
+                        &shader_vertex_skinned,
+                        Some(&shader_fragment),
+                    )),
+            );
+    }
+    let pipelines = builder.build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex_basic);
+        factory.destroy_shader_module(shader_vertex_skinned);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipelines {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(pipelines) => Ok((pipelines, pipeline_layout)),
+    }
+}
+
+/// Resolve the weighted blended transparency images over the target.
+///
+/// Build with `builder().with_image(accum).with_image(revealage)`, passing the two color images
+/// of the target drawn by `DrawOitAccumDesc`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DrawOitCompositeDesc;
+
+impl DrawOitCompositeDesc {
+    /// Create instance of `DrawOitComposite` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawOitCompositeDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![
+            ImageAccess {
+                access: hal::image::Access::SHADER_READ,
+                usage: hal::image::Usage::SAMPLED,
+                layout: hal::image::Layout::ShaderReadOnlyOptimal,
+                stages: pso::PipelineStage::FRAGMENT_SHADER,
+            };
+            2
+        ]
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build_oit_composite");
+
+        let layout: RendyHandle<DescriptorSetLayout<B>> = factory
+            .create_descriptor_set_layout(util::set_layout_bindings(Some((
+                2,
+                pso::DescriptorType::CombinedImageSampler,
+                pso::ShaderStageFlags::FRAGMENT,
+            ))))?
+            .into();
+        let sampler = factory.get_sampler(SamplerInfo::new(Filter::Nearest, WrapMode::Clamp))?;
+
+        let mut views = Vec::with_capacity(2);
+        for node_image in &images {
+            let image = ctx.get_image(node_image.id).ok_or_else(|| {
+                failure::format_err!("Weighted blended transparency image is missing.")
+            })?;
+            views.push(factory.create_image_view(
+                image.clone(),
+                ImageViewInfo {
+                    view_kind: ViewKind::D2,
+                    format: image.format(),
+                    swizzle: Swizzle::NO,
+                    range: SubresourceRange {
+                        aspects: Aspects::COLOR,
+                        levels: 0..1,
+                        layers: 0..1,
+                    },
+                },
+            )?);
+        }
+
+        let set = factory.create_descriptor_set(layout.clone())?;
+        unsafe {
+            factory.write_descriptor_sets(views.iter().enumerate().map(|(binding, view)| {
+                util::desc_write(
+                    set.raw(),
+                    binding as u32,
+                    pso::Descriptor::CombinedImageSampler(
+                        view.raw(),
+                        hal::image::Layout::ShaderReadOnlyOptimal,
+                        sampler.raw(),
+                    ),
+                )
+            }));
+        }
+
+        let pipeline_layout = unsafe {
+            factory
+                .device()
+                .create_pipeline_layout(Some(layout.raw()), None as Option<(_, _)>)
+        }?;
+
+        let shader_vertex = unsafe { super::FULLSCREEN_VERTEX.module(factory).unwrap() };
+        let shader_fragment = unsafe { super::OIT_COMPOSITE_FRAGMENT.module(factory).unwrap() };
+
+        let pipes = PipelinesBuilder::new()
+            .with_pipeline(
+                PipelineDescBuilder::new()
+                    .with_shaders(util::simple_shader_set(
+                        &shader_vertex,
+                        Some(&shader_fragment),
+                    ))
+                    .with_layout(&pipeline_layout)
+                    .with_subpass(subpass)
+                    .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                    .with_blend_targets(vec![pso::ColorBlendDesc {
+                        mask: pso::ColorMask::ALL,
+                        blend: Some(pso::BlendState::PREMULTIPLIED_ALPHA),
+                    }]),
+            )
+            .build(factory, None);
+
+        unsafe {
+            factory.destroy_shader_module(shader_vertex);
+            factory.destroy_shader_module(shader_fragment);
+        }
+
+        let pipeline = match pipes {
+            Err(e) => {
+                unsafe {
+                    factory.device().destroy_pipeline_layout(pipeline_layout);
+                }
+                return Err(e);
+            }
+            Ok(mut pipes) => pipes.remove(0),
+        };
+
+        Ok(Box::new(DrawOitComposite::<B> {
+            pipeline,
+            pipeline_layout,
+            set,
+            _views: views,
+            _sampler: sampler,
+            _layout: layout,
+        }))
+    }
+}
+
+/// Resolves the weighted blended transparency images over the target with a fullscreen triangle.
+#[derive(Debug)]
+pub struct DrawOitComposite<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    set: Escape<DescriptorSet<B>>,
+    _views: Vec<Escape<ImageView<B>>>,
+    _sampler: RendyHandle<Sampler<B>>,
+    _layout: RendyHandle<DescriptorSetLayout<B>>,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawOitComposite<B> {
+    fn prepare(
+        &mut self,
+        _factory: &Factory<B>,
+        _queue: QueueId,
+        _index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) -> PrepareResult {
+        PrepareResult::DrawReuse
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        _index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw oit composite");
+
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        unsafe {
+            encoder.bind_graphics_descriptor_sets(
+                &self.pipeline_layout,
+                0,
+                Some(self.set.raw()),
+                std::iter::empty(),
+            );
+            encoder.draw(0..3, 0..1);
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
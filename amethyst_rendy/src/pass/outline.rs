@@ -0,0 +1,697 @@
+//! Colored outlines around the meshes of entities with an `Outlined` component.
+//!
+//! `DrawOutlineMask` draws only the outlined meshes into a mask target with two colors, the color
+//! of their outline and its data: depth, thickness, occluded opacity and coverage.
+//! `DrawOutline` then dilates the mask over the main target, testing the depth of the outlined
+//! pixel against the scene to tell visible from occluded parts of the outline.
+use crate::{
+    batch::{GroupIterator, OneLevelBatch},
+    outline::Outlined,
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    pod::{OutlineArgs, SkinnedOutlineArgs},
+    skinning::{JointCombined, JointTransforms, SkinningMode},
+    submodules::{DynamicVertexBuffer, FlatEnvironmentSub, SkinningSub},
+    types::{Backend, Mesh},
+    util,
+};
+use amethyst_assets::{AssetStorage, Handle};
+use amethyst_core::{
+    ecs::{Join, Read, ReadStorage, SystemData, World},
+    transform::Transform,
+    Hidden, HiddenPropagate,
+};
+use derivative::Derivative;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
+    },
+    hal::{
+        self,
+        device::Device,
+        format::{Aspects, Format, Swizzle},
+        image::{Filter, SamplerInfo, SubresourceRange, ViewKind, WrapMode},
+        pso,
+    },
+    mesh::{AsVertex, Position, VertexFormat},
+    resource::{
+        DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle, ImageView,
+        ImageViewInfo, Sampler,
+    },
+    shader::{Shader, SpirvShader},
+};
+use std::marker::PhantomData;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Format of the outline color image of the mask target.
+pub const OUTLINE_COLOR_FORMAT: Format = Format::Rgba16Sfloat;
+/// Format of the outline data image of the mask target, holding the depth, thickness, occluded
+/// opacity and coverage of outlined meshes.
+pub const OUTLINE_DATA_FORMAT: Format = Format::Rgba32Sfloat;
+
+/// Draw the meshes of outlined entities into the two colors of the outline mask target.
+///
+/// The target must have `OUTLINE_COLOR_FORMAT` and `OUTLINE_DATA_FORMAT` colors cleared to 0,
+/// and a depth output cleared to 1.
+#[derive(Clone, Derivative)]
+#[derivative(Debug(bound = ""), Default(bound = ""))]
+pub struct DrawOutlineMaskDesc<B: Backend> {
+    skinning: bool,
+    skinning_mode: SkinningMode,
+    marker: PhantomData<B>,
+}
+
+impl<B: Backend> DrawOutlineMaskDesc<B> {
+    /// Create pass in default configuration
+    pub fn new() -> Self {
+        Self {
+            skinning: false,
+            skinning_mode: SkinningMode::Linear,
+            marker: PhantomData,
+        }
+    }
+
+    /// Create pass in with vertex skinning enabled if true is passed
+    pub fn with_skinning(mut self, skinned: bool) -> Self {
+        self.skinning = skinned;
+        self
+    }
+
+    /// Create pass blending the joints of skinned meshes with the given mode.
+    pub fn with_skinning_mode(mut self, mode: SkinningMode) -> Self {
+        self.skinning_mode = mode;
+        self
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawOutlineMaskDesc<B> {
+    fn colors(&self) -> usize {
+        2
+    }
+
+    fn build(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build_outline_mask");
+
+        let env = FlatEnvironmentSub::new(factory)?;
+        let skinning = SkinningSub::new(factory, self.skinning_mode)?;
+
+        let mut vertex_format_base = vec![Position::vertex()];
+        let mut vertex_format_skinned = vec![Position::vertex(), JointCombined::vertex()];
+
+        let (mut pipelines, pipeline_layout) = build_mask_pipelines(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            &vertex_format_base,
+            &vertex_format_skinned,
+            self.skinning,
+            self.skinning_mode,
+            vec![env.raw_layout(), skinning.raw_layout()],
+        )?;
+
+        vertex_format_base.sort();
+        vertex_format_skinned.sort();
+
+        let pipeline_skinned = if self.skinning { pipelines.pop() } else { None };
+        let pipeline_basic = pipelines.pop().expect("Unreachable: pipeline was built");
+
+        Ok(Box::new(DrawOutlineMask::<B> {
+            pipeline_basic,
+            pipeline_skinned,
+            pipeline_layout,
+            static_batches: Default::default(),
+            skinned_batches: Default::default(),
+            vertex_format_base,
+            vertex_format_skinned,
+            env,
+            skinning,
+            models: DynamicVertexBuffer::new(),
+            skinned_models: DynamicVertexBuffer::new(),
+        }))
+    }
+}
+
+/// Draws the meshes of outlined entities into the outline mask target, see the
+/// [module documentation](index.html).
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct DrawOutlineMask<B: Backend> {
+    pipeline_basic: B::GraphicsPipeline,
+    pipeline_skinned: Option<B::GraphicsPipeline>,
+    pipeline_layout: B::PipelineLayout,
+    static_batches: OneLevelBatch<u32, OutlineArgs>,
+    skinned_batches: OneLevelBatch<u32, SkinnedOutlineArgs>,
+    vertex_format_base: Vec<VertexFormat>,
+    vertex_format_skinned: Vec<VertexFormat>,
+    env: FlatEnvironmentSub<B>,
+    skinning: SkinningSub<B>,
+    models: DynamicVertexBuffer<B, OutlineArgs>,
+    skinned_models: DynamicVertexBuffer<B, SkinnedOutlineArgs>,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawOutlineMask<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        resources: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare outline mask");
+
+        let (mesh_storage, outlines, meshes, transforms, joints, hiddens, hidden_props) =
+            <(
+                Read<'_, AssetStorage<Mesh>>,
+                ReadStorage<'_, Outlined>,
+                ReadStorage<'_, Handle<Mesh>>,
+                ReadStorage<'_, Transform>,
+                ReadStorage<'_, JointTransforms>,
+                ReadStorage<'_, Hidden>,
+                ReadStorage<'_, HiddenPropagate>,
+            )>::fetch(resources);
+
+        self.env.process(factory, index, resources);
+
+        self.static_batches.clear_inner();
+        self.skinned_batches.clear_inner();
+
+        let statics_ref = &mut self.static_batches;
+        let skinned_ref = &mut self.skinned_batches;
+        let skinning_ref = &mut self.skinning;
+
+        // Only outlined entities are joined, which are usually few. Their meshes are not culled
+        // against the view, as the outline may reach into it from a mesh right outside.
+        (
+            &outlines,
+            &meshes,
+            &transforms,
+            !&joints,
+            !&hiddens,
+            !&hidden_props,
+        )
+            .join()
+            .map(|(outline, mesh, tform, _, _, _)| {
+                (mesh.id(), OutlineArgs::from_object_data(tform, outline))
+            })
+            .for_each_group(|mesh_id, data| {
+                if mesh_storage.contains_id(mesh_id) {
+                    statics_ref.insert(mesh_id, data.drain(..));
+                }
+            });
+
+        if self.pipeline_skinned.is_some() {
+            (
+                &outlines,
+                &meshes,
+                &transforms,
+                &joints,
+                !&hiddens,
+                !&hidden_props,
+            )
+                .join()
+                .map(|(outline, mesh, tform, joints, _, _)| {
+                    (
+                        mesh.id(),
+                        SkinnedOutlineArgs::from_object_data(
+                            tform,
+                            outline,
+                            skinning_ref.insert(joints),
+                        ),
+                    )
+                })
+                .for_each_group(|mesh_id, data| {
+                    if mesh_storage.contains_id(mesh_id) {
+                        skinned_ref.insert(mesh_id, data.drain(..));
+                    }
+                });
+        }
+
+        {
+            #[cfg(feature = "profiler")]
+            profile_scope!("write");
+
+            self.static_batches.prune();
+            self.skinned_batches.prune();
+
+            self.models.write(
+                factory,
+                index,
+                self.static_batches.count() as u64,
+                self.static_batches.data(),
+            );
+            self.skinned_models.write(
+                factory,
+                index,
+                self.skinned_batches.count() as u64,
+                self.skinned_batches.data(),
+            );
+            self.skinning.commit(factory, index);
+        }
+
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        resources: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw outline mask");
+
+        let mesh_storage = <Read<'_, AssetStorage<Mesh>>>::fetch(resources);
+        let layout = &self.pipeline_layout;
+        let encoder = &mut encoder;
+
+        let models_loc = self.vertex_format_base.len() as u32;
+        let skin_models_loc = self.vertex_format_skinned.len() as u32;
+
+        encoder.bind_graphics_pipeline(&self.pipeline_basic);
+        self.env.bind(index, layout, 0, encoder);
+        if self.models.bind(index, models_loc, 0, encoder) {
+            draw_mask(
+                &self.static_batches,
+                &self.vertex_format_base,
+                &mesh_storage,
+                encoder,
+            );
+        }
+
+        if let Some(pipeline_skinned) = self.pipeline_skinned.as_ref() {
+            encoder.bind_graphics_pipeline(pipeline_skinned);
+            if self.skinned_models.bind(index, skin_models_loc, 0, encoder) {
+                self.skinning.bind(index, layout, 1, encoder);
+                draw_mask(
+                    &self.skinned_batches,
+                    &self.vertex_format_skinned,
+                    &mesh_storage,
+                    encoder,
+                );
+            }
+        }
+    }
+
+    fn dispose(mut self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
+        unsafe {
+            let device = factory.device();
+            device.destroy_graphics_pipeline(self.pipeline_basic);
+            if let Some(pipeline) = self.pipeline_skinned.take() {
+                device.destroy_graphics_pipeline(pipeline);
+            }
+            device.destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn draw_mask<B: Backend, D>(
+    batches: &OneLevelBatch<u32, D>,
+    vertex_format: &[VertexFormat],
+    mesh_storage: &AssetStorage<Mesh>,
+    encoder: &mut RenderPassEncoder<'_, B>,
+) {
+    for (mesh_id, range) in batches.iter() {
+        debug_assert!(mesh_storage.contains_id(*mesh_id));
+        if let Some(mesh) = B::unwrap_mesh(unsafe { mesh_storage.get_by_id_unchecked(*mesh_id) }) {
+            // Vertex attributes missing from a mesh are reported by the opaque pass.
+            let _ = mesh.bind_and_draw(0, vertex_format, range, encoder);
+        }
+    }
+}
+
+/// Returns the mask vertex shader for skinned meshes blended with `mode`.
+fn skinned_mask_shader(mode: SkinningMode) -> &'static SpirvShader {
+    match mode {
+        SkinningMode::Linear => &super::OUTLINE_MASK_SKIN_VERTEX,
+        SkinningMode::DualQuaternion => &super::OUTLINE_MASK_SKIN_DQ_VERTEX,
+    }
+}
+
+/// Builds the mask pipeline, followed by its skinned variant if enabled.
+fn build_mask_pipelines<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    vertex_format_base: &[VertexFormat],
+    vertex_format_skinned: &[VertexFormat],
+    skinning: bool,
+    skinning_mode: SkinningMode,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(Vec<B::GraphicsPipeline>, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let vertex_desc = |formats: &[VertexFormat], args: VertexFormat| {
+        formats
+            .iter()
+            .map(|f| (f.clone(), pso::VertexInputRate::Vertex))
+            .chain(Some((args, pso::VertexInputRate::Instance(1))))
+            .collect::<Vec<_>>()
+    };
+    let base_desc = vertex_desc(vertex_format_base, OutlineArgs::vertex());
+    let skinned_desc = vertex_desc(vertex_format_skinned, SkinnedOutlineArgs::vertex());
+
+    let shader_vertex_basic = unsafe { super::OUTLINE_MASK_VERTEX.module(factory).unwrap() };
+    let shader_vertex_skinned =
+        unsafe { skinned_mask_shader(skinning_mode).module(factory).unwrap() };
+    let shader_fragment = unsafe { super::OUTLINE_MASK_FRAGMENT.module(factory).unwrap() };
+
+    // The closest outlined surface of each pixel is kept, both outlines and meshes are opaque.
+    let mask = PipelineDescBuilder::new()
+        .with_layout(&pipeline_layout)
+        .with_subpass(subpass)
+        .with_framebuffer_size(framebuffer_width, framebuffer_height)
+        .with_face_culling(pso::Face::BACK)
+        .with_depth_test(pso::DepthTest {
+            fun: pso::Comparison::Less,
+            write: true,
+        })
+        .with_blend_targets(vec![
+            pso::ColorBlendDesc {
+                mask: pso::ColorMask::ALL,
+                blend: None,
+            };
+            2
+        ]);
+
+    let mut builder = PipelinesBuilder::new().with_pipeline(
+        mask.clone()
+            .with_vertex_desc(&base_desc)
+            .with_shaders(util::simple_shader_set(
+                &shader_vertex_basic,
+                Some(&shader_fragment),
+            )),
+    );
+    if skinning {
+        builder = builder.with_child_pipeline(
+            0,
+            mask.with_vertex_desc(&skinned_desc)
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex_skinned,
+                    Some(&shader_fragment),
+                )),
+        );
+    }
+    let pipelines = builder.build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex_basic);
+        factory.destroy_shader_module(shader_vertex_skinned);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipelines {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(pipelines) => Ok((pipelines, pipeline_layout)),
+    }
+}
+
+/// Draw the outlines of the outline mask over the target.
+///
+/// Build with `builder().with_image(color).with_image(data)`, passing the two color images of the
+/// target drawn by `DrawOutlineMaskDesc`. The target must have a depth output holding the scene.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DrawOutlineDesc {
+    occluded: bool,
+}
+
+impl Default for DrawOutlineDesc {
+    fn default() -> Self {
+        Self { occluded: true }
+    }
+}
+
+impl DrawOutlineDesc {
+    /// Create instance of `DrawOutline` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Draw the outline around occluded parts of meshes with the `occluded_opacity` of their
+    /// `Outlined` component if true is passed, enabled by default. Otherwise only visible parts
+    /// are outlined.
+    pub fn with_occluded(mut self, occluded: bool) -> Self {
+        self.occluded = occluded;
+        self
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawOutlineDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![
+            ImageAccess {
+                access: hal::image::Access::SHADER_READ,
+                usage: hal::image::Usage::SAMPLED,
+                layout: hal::image::Layout::ShaderReadOnlyOptimal,
+                stages: pso::PipelineStage::FRAGMENT_SHADER,
+            };
+            2
+        ]
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build_outline");
+
+        let layout: RendyHandle<DescriptorSetLayout<B>> = factory
+            .create_descriptor_set_layout(util::set_layout_bindings(Some((
+                2,
+                pso::DescriptorType::CombinedImageSampler,
+                pso::ShaderStageFlags::FRAGMENT,
+            ))))?
+            .into();
+        let sampler = factory.get_sampler(SamplerInfo::new(Filter::Nearest, WrapMode::Clamp))?;
+
+        let mut views = Vec::with_capacity(2);
+        for node_image in &images {
+            let image = ctx
+                .get_image(node_image.id)
+                .ok_or_else(|| failure::format_err!("Outline mask image is missing."))?;
+            views.push(factory.create_image_view(
+                image.clone(),
+                ImageViewInfo {
+                    view_kind: ViewKind::D2,
+                    format: image.format(),
+                    swizzle: Swizzle::NO,
+                    range: SubresourceRange {
+                        aspects: Aspects::COLOR,
+                        levels: 0..1,
+                        layers: 0..1,
+                    },
+                },
+            )?);
+        }
+
+        let set = factory.create_descriptor_set(layout.clone())?;
+        unsafe {
+            factory.write_descriptor_sets(views.iter().enumerate().map(|(binding, view)| {
+                util::desc_write(
+                    set.raw(),
+                    binding as u32,
+                    pso::Descriptor::CombinedImageSampler(
+                        view.raw(),
+                        hal::image::Layout::ShaderReadOnlyOptimal,
+                        sampler.raw(),
+                    ),
+                )
+            }));
+        }
+
+        let pipeline_layout = unsafe {
+            factory
+                .device()
+                .create_pipeline_layout(Some(layout.raw()), None as Option<(_, _)>)
+        }?;
+
+        let shader_vertex = unsafe { super::FULLSCREEN_VERTEX.module(factory).unwrap() };
+        let shader_visible = unsafe { super::OUTLINE_FRAGMENT.module(factory).unwrap() };
+        let shader_occluded = unsafe { super::OUTLINE_OCCLUDED_FRAGMENT.module(factory).unwrap() };
+
+        // Both pipelines write the depth of the outlined surface, and only differ in the side of
+        // the scene depth they pass on.
+        let common = PipelineDescBuilder::new()
+            .with_layout(&pipeline_layout)
+            .with_subpass(subpass)
+            .with_framebuffer_size(framebuffer_width, framebuffer_height)
+            .with_blend_targets(vec![pso::ColorBlendDesc {
+                mask: pso::ColorMask::ALL,
+                blend: Some(pso::BlendState::ALPHA),
+            }]);
+
+        let mut builder = PipelinesBuilder::new().with_pipeline(
+            common
+                .clone()
+                .with_depth_test(pso::DepthTest {
+                    fun: pso::Comparison::LessEqual,
+                    write: false,
+                })
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_visible),
+                )),
+        );
+        if self.occluded {
+            builder = builder.with_child_pipeline(
+                0,
+                common
+                    .with_depth_test(pso::DepthTest {
+                        fun: pso::Comparison::Greater,
+                        write: false,
+                    })
+                    .with_shaders(util::simple_shader_set(
+                        &shader_vertex,
+                        Some(&shader_occluded),
+                    )),
+            );
+        }
+        let pipes = builder.build(factory, None);
+
+        unsafe {
+            factory.destroy_shader_module(shader_vertex);
+            factory.destroy_shader_module(shader_visible);
+            factory.destroy_shader_module(shader_occluded);
+        }
+
+        let mut pipes = match pipes {
+            Err(e) => {
+                unsafe {
+                    factory.device().destroy_pipeline_layout(pipeline_layout);
+                }
+                return Err(e);
+            }
+            Ok(pipes) => pipes,
+        };
+        let pipeline_occluded = if self.occluded { pipes.pop() } else { None };
+        let pipeline_visible = pipes.pop().expect("Unreachable: pipeline was built");
+
+        Ok(Box::new(DrawOutline::<B> {
+            pipeline_visible,
+            pipeline_occluded,
+            pipeline_layout,
+            set,
+            outlined: false,
+            _views: views,
+            _sampler: sampler,
+            _layout: layout,
+        }))
+    }
+}
+
+/// Draws the outlines of the outline mask over the target with a fullscreen triangle.
+///
+/// Nothing is drawn while no entity has an `Outlined` component.
+#[derive(Debug)]
+pub struct DrawOutline<B: Backend> {
+    pipeline_visible: B::GraphicsPipeline,
+    pipeline_occluded: Option<B::GraphicsPipeline>,
+    pipeline_layout: B::PipelineLayout,
+    set: Escape<DescriptorSet<B>>,
+    outlined: bool,
+    _views: Vec<Escape<ImageView<B>>>,
+    _sampler: RendyHandle<Sampler<B>>,
+    _layout: RendyHandle<DescriptorSetLayout<B>>,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawOutline<B> {
+    fn prepare(
+        &mut self,
+        _factory: &Factory<B>,
+        _queue: QueueId,
+        _index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        let outlined = (&<ReadStorage<'_, Outlined>>::fetch(world))
+            .join()
+            .next()
+            .is_some();
+        if outlined == self.outlined {
+            PrepareResult::DrawReuse
+        } else {
+            self.outlined = outlined;
+            PrepareResult::DrawRecord
+        }
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        _index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw outline");
+
+        if !self.outlined {
+            return;
+        }
+
+        for pipeline in Some(&self.pipeline_visible)
+            .into_iter()
+            .chain(self.pipeline_occluded.as_ref())
+        {
+            encoder.bind_graphics_pipeline(pipeline);
+            unsafe {
+                encoder.bind_graphics_descriptor_sets(
+                    &self.pipeline_layout,
+                    0,
+                    Some(self.set.raw()),
+                    std::iter::empty(),
+                );
+                encoder.draw(0..3, 0..1);
+            }
+        }
+    }
+
+    fn dispose(mut self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            let device = factory.device();
+            device.destroy_graphics_pipeline(self.pipeline_visible);
+            if let Some(pipeline) = self.pipeline_occluded.take() {
+                device.destroy_graphics_pipeline(pipeline);
+            }
+            device.destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use super::base_3d::*;
-use crate::{mtl::FullTextureSet, skinning::JointCombined};
+use crate::{mtl::LightmapTextureSet, skinning::JointCombined};
 use rendy::{
     mesh::{AsVertex, Normal, Position, Tangent, TexCoord, VertexFormat},
     shader::SpirvShader,
@@ -10,16 +10,34 @@ use rendy::{
 pub struct PbrPassDef;
 impl Base3DPassDef for PbrPassDef {
     const NAME: &'static str = "Pbr";
-    type TextureSet = FullTextureSet;
+    type TextureSet = LightmapTextureSet;
     fn vertex_shader() -> &'static SpirvShader {
         &super::POS_NORM_TANG_TEX_VERTEX
     }
     fn vertex_skinned_shader() -> &'static SpirvShader {
         &super::POS_NORM_TANG_TEX_SKIN_VERTEX
     }
+    fn vertex_dual_quaternion_skinned_shader() -> Option<&'static SpirvShader> {
+        Some(&super::POS_NORM_TANG_TEX_SKIN_DQ_VERTEX)
+    }
+    fn vertex_clip_shader() -> Option<&'static SpirvShader> {
+        Some(&super::POS_NORM_TANG_TEX_CLIP_VERTEX)
+    }
     fn fragment_shader() -> &'static SpirvShader {
         &super::PBR_FRAGMENT
     }
+    fn oit_fragment_shader() -> Option<&'static SpirvShader> {
+        Some(&super::PBR_OIT_FRAGMENT)
+    }
+    fn ibl_fragment_shader() -> Option<&'static SpirvShader> {
+        Some(&super::PBR_IBL_FRAGMENT)
+    }
+    fn lightmap_shaders() -> Option<(&'static SpirvShader, &'static SpirvShader)> {
+        Some((
+            &super::POS_NORM_TANG_TEX_LIGHTMAP_VERTEX,
+            &super::PBR_LIGHTMAP_FRAGMENT,
+        ))
+    }
     fn base_format() -> Vec<VertexFormat> {
         vec![
             Position::vertex(),
@@ -0,0 +1,473 @@
+//! Shadow cube maps of point lights, see the [`point_shadow` module](../point_shadow/index.html).
+//!
+//! The atlas has a row per shadow casting light, holding the six faces of its cube map side by
+//! side. Faces store the distance to the closest surface divided by the `radius` of the light,
+//! rendered with a perspective projection of 90 degrees. Their orientation is given by
+//! `FACES`, which the lighting shaders mirror.
+use crate::{
+    light::Light,
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    pod::PointShadowArgs,
+    point_shadow::{face_resolution, PointShadowSlots, POINT_SHADOW_NEAR},
+    skinning::JointTransforms,
+    submodules::DynamicVertexBuffer,
+    types::{Backend, Mesh},
+    util,
+    visibility::BoundingSphere,
+};
+use amethyst_assets::{AssetStorage, Handle};
+use amethyst_core::{
+    ecs::{Entity, Join, Read, ReadStorage, SystemData, World},
+    math::{convert, Matrix4, Point3, Vector3},
+    transform::Transform,
+    Hidden, HiddenPropagate,
+};
+use derivative::Derivative;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, NodeBuffer, NodeImage,
+    },
+    hal::{self, device::Device, format::Format, pso},
+    mesh::{AsVertex, Position, VertexFormat},
+    shader::Shader,
+};
+use std::ops::Range;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Format of the point shadow atlas.
+pub const POINT_SHADOW_FORMAT: Format = Format::R32Sfloat;
+/// Format of the depth of the point shadow atlas.
+pub const POINT_SHADOW_DEPTH_FORMAT: Format = Format::D32Sfloat;
+
+/// Direction, right and up axes of the cube map faces, in the order of the atlas columns.
+const FACES: [[[f32; 3]; 3]; 6] = [
+    [[1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]],
+    [[-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]],
+    [[0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]],
+    [[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+    [[0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+    [[0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+];
+
+/// Draw the shadow cube maps of the point lights in `PointShadowSlots` into the point shadow
+/// atlas.
+///
+/// The target must have a `POINT_SHADOW_FORMAT` color six faces wide and a row high per slot,
+/// and a `POINT_SHADOW_DEPTH_FORMAT` depth of the same size. Both must have no clear value, so
+/// the cube maps are kept between frames. Skinned meshes don't cast shadows.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DrawPointShadowDesc;
+
+impl DrawPointShadowDesc {
+    /// Create instance of `DrawPointShadow` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawPointShadowDesc {
+    fn build(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build_point_shadow");
+
+        let tile_size = framebuffer_width / FACES.len() as u32;
+        if tile_size == 0 || framebuffer_height < tile_size {
+            return Err(failure::format_err!(
+                "Point shadow atlas of {}x{} pixels is too small.",
+                framebuffer_width,
+                framebuffer_height
+            ));
+        }
+
+        let vertex_format = vec![Position::vertex()];
+        let (mut pipelines, pipeline_layout) =
+            build_point_shadow_pipelines(factory, subpass, &vertex_format)?;
+
+        Ok(Box::new(DrawPointShadow::<B> {
+            pipeline_clear: pipelines.pop().expect("Unreachable: pipeline was built"),
+            pipeline_casters: pipelines.pop().expect("Unreachable: pipeline was built"),
+            pipeline_layout,
+            vertex_format,
+            tile_size,
+            drawn: vec![None; (framebuffer_height / tile_size) as usize],
+            faces: Vec::new(),
+            instances: Vec::new(),
+            models: DynamicVertexBuffer::new(),
+        }))
+    }
+}
+
+/// What a row of the atlas was drawn from.
+#[derive(Clone, Debug, PartialEq)]
+struct DrawnShadow {
+    entity: Entity,
+    position: Vector3<f32>,
+    radius: f32,
+    resolution: u32,
+}
+
+/// Meshes drawn into a face in the current frame.
+#[derive(Debug)]
+struct FaceDraw {
+    rect: pso::Rect,
+    meshes: Vec<(u32, Range<u32>)>,
+}
+
+/// Draws the shadow cube maps of moved or newly assigned point lights.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct DrawPointShadow<B: Backend> {
+    pipeline_casters: B::GraphicsPipeline,
+    pipeline_clear: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    vertex_format: Vec<VertexFormat>,
+    tile_size: u32,
+    drawn: Vec<Option<DrawnShadow>>,
+    faces: Vec<FaceDraw>,
+    instances: Vec<PointShadowArgs>,
+    models: DynamicVertexBuffer<B, PointShadowArgs>,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawPointShadow<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        resources: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare point shadow");
+
+        let (slots, lights, transforms) = <(
+            Option<Read<'_, PointShadowSlots>>,
+            ReadStorage<'_, Light>,
+            ReadStorage<'_, Transform>,
+        )>::fetch(resources);
+
+        self.faces.clear();
+        self.instances.clear();
+
+        let tile_size = self.tile_size;
+        let mut dirty = Vec::new();
+        for (slot, drawn) in self.drawn.iter_mut().enumerate() {
+            let current = slots
+                .as_ref()
+                .and_then(|slots| slots.iter().nth(slot))
+                .and_then(|(_, entity)| entity)
+                .and_then(
+                    |entity| match (lights.get(entity), transforms.get(entity)) {
+                        (Some(Light::Point(light)), Some(transform)) if light.casts_shadows => {
+                            Some((entity, light, transform))
+                        }
+                        _ => None,
+                    },
+                )
+                .map(|(entity, light, transform)| DrawnShadow {
+                    entity,
+                    position: convert::<_, Vector3<f32>>(transform.global_matrix().column(3).xyz()),
+                    radius: light.radius,
+                    resolution: face_resolution(light, tile_size),
+                });
+            // Unused rows aren't sampled, they are drawn once a light is assigned to them.
+            if *drawn != current {
+                if let Some(shadow) = current.as_ref() {
+                    dirty.push((slot, shadow.clone()));
+                }
+                *drawn = current;
+            }
+        }
+
+        if !dirty.is_empty() {
+            self.draw_shadows(resources, &dirty);
+        }
+
+        self.models.write(
+            factory,
+            index,
+            self.instances.len() as u64,
+            Some(&self.instances),
+        );
+
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        resources: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw point shadow");
+
+        if self.faces.is_empty() {
+            return;
+        }
+
+        let mesh_storage = <Read<'_, AssetStorage<Mesh>>>::fetch(resources);
+        let models_loc = self.vertex_format.len() as u32;
+
+        for face in &self.faces {
+            unsafe {
+                encoder.set_viewports(
+                    0,
+                    &[pso::Viewport {
+                        rect: face.rect,
+                        depth: 0.0..1.0,
+                    }],
+                );
+                encoder.set_scissors(0, &[face.rect]);
+            }
+
+            encoder.bind_graphics_pipeline(&self.pipeline_clear);
+            unsafe {
+                encoder.draw(0..3, 0..1);
+            }
+
+            if face.meshes.is_empty() {
+                continue;
+            }
+            encoder.bind_graphics_pipeline(&self.pipeline_casters);
+            self.models.bind(index, models_loc, 0, &mut encoder);
+            for (mesh_id, range) in &face.meshes {
+                if let Some(mesh) =
+                    B::unwrap_mesh(unsafe { mesh_storage.get_by_id_unchecked(*mesh_id) })
+                {
+                    // Vertex attributes missing from a mesh are reported by the opaque pass.
+                    let _ = mesh.bind_and_draw(0, &self.vertex_format, range.clone(), &mut encoder);
+                }
+            }
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
+        unsafe {
+            let device = factory.device();
+            device.destroy_graphics_pipeline(self.pipeline_casters);
+            device.destroy_graphics_pipeline(self.pipeline_clear);
+            device.destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+impl<B: Backend> DrawPointShadow<B> {
+    /// Collects the faces and mesh instances of the rows of the atlas to draw.
+    fn draw_shadows(&mut self, resources: &World, dirty: &[(usize, DrawnShadow)]) {
+        let (mesh_storage, meshes, transforms, spheres, joints, hiddens, hidden_props) =
+            <(
+                Read<'_, AssetStorage<Mesh>>,
+                ReadStorage<'_, Handle<Mesh>>,
+                ReadStorage<'_, Transform>,
+                ReadStorage<'_, BoundingSphere>,
+                ReadStorage<'_, JointTransforms>,
+                ReadStorage<'_, Hidden>,
+                ReadStorage<'_, HiddenPropagate>,
+            )>::fetch(resources);
+
+        let origin = Point3::origin();
+        let mut casters = (
+            &meshes,
+            &transforms,
+            spheres.maybe(),
+            !&joints,
+            !&hiddens,
+            !&hidden_props,
+        )
+            .join()
+            .filter(|(mesh, _, _, _, _, _)| mesh_storage.contains_id(mesh.id()))
+            .map(|(mesh, transform, sphere, _, _, _)| {
+                let model = convert::<_, Matrix4<f32>>(*transform.global_matrix());
+                let center = model.transform_point(sphere.map_or(&origin, |s| &s.center));
+                let radius = sphere.map_or(1.0, |s| s.radius)
+                    * model[(0, 0)].max(model[(1, 1)]).max(model[(2, 2)]);
+                (mesh.id(), model, center.coords, radius)
+            })
+            .collect::<Vec<_>>();
+        casters.sort_by_key(|(mesh_id, _, _, _)| *mesh_id);
+
+        for (slot, shadow) in dirty {
+            let near_casters = casters
+                .iter()
+                .filter(|(_, _, center, radius)| {
+                    (center - shadow.position).norm() - radius <= shadow.radius
+                })
+                .collect::<Vec<_>>();
+            for (face, axes) in FACES.iter().enumerate() {
+                let view = face_view(axes, &shadow.position);
+                let mut draw = FaceDraw {
+                    rect: pso::Rect {
+                        x: (face as u32 * self.tile_size) as i16,
+                        y: (*slot as u32 * self.tile_size) as i16,
+                        w: shadow.resolution as i16,
+                        h: shadow.resolution as i16,
+                    },
+                    meshes: Vec::new(),
+                };
+                for (mesh_id, model, _, _) in &near_casters {
+                    let instance = self.instances.len() as u32;
+                    let model_view: [[f32; 4]; 4] = (view * model).into();
+                    self.instances.push(PointShadowArgs {
+                        model_view: model_view.into(),
+                        depth_range: [POINT_SHADOW_NEAR, shadow.radius].into(),
+                    });
+                    match draw.meshes.last_mut() {
+                        Some((last, range)) if *last == *mesh_id => range.end = instance + 1,
+                        _ => draw.meshes.push((*mesh_id, instance..instance + 1)),
+                    }
+                }
+                self.faces.push(draw);
+            }
+        }
+    }
+}
+
+/// View matrix of a cube map face at `position`, looking along the first of its `axes`.
+fn face_view(axes: &[[f32; 3]; 3], position: &Vector3<f32>) -> Matrix4<f32> {
+    let [direction, right, up] = axes;
+    let direction = Vector3::from(*direction);
+    let right = Vector3::from(*right);
+    let up = Vector3::from(*up);
+    Matrix4::new(
+        right.x,
+        right.y,
+        right.z,
+        -right.dot(position),
+        up.x,
+        up.y,
+        up.z,
+        -up.dot(position),
+        -direction.x,
+        -direction.y,
+        -direction.z,
+        direction.dot(position),
+        0.0,
+        0.0,
+        0.0,
+        1.0,
+    )
+}
+
+/// Builds the caster pipeline, followed by the pipeline resetting a face. Viewports are set
+/// when drawing each face.
+fn build_point_shadow_pipelines<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    vertex_format: &[VertexFormat],
+) -> Result<(Vec<B::GraphicsPipeline>, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory.device().create_pipeline_layout(
+            None as Option<&B::DescriptorSetLayout>,
+            None as Option<(_, _)>,
+        )
+    }?;
+
+    let vertex_desc = vertex_format
+        .iter()
+        .map(|f| (f.clone(), pso::VertexInputRate::Vertex))
+        .chain(Some((
+            PointShadowArgs::vertex(),
+            pso::VertexInputRate::Instance(1),
+        )))
+        .collect::<Vec<_>>();
+
+    let shader_vertex = unsafe { super::POINT_SHADOW_VERTEX.module(factory).unwrap() };
+    let shader_fragment = unsafe { super::POINT_SHADOW_FRAGMENT.module(factory).unwrap() };
+    let shader_fullscreen = unsafe { super::FULLSCREEN_VERTEX.module(factory).unwrap() };
+    let shader_clear = unsafe { super::POINT_SHADOW_CLEAR_FRAGMENT.module(factory).unwrap() };
+
+    let blend = vec![pso::ColorBlendDesc {
+        mask: pso::ColorMask::ALL,
+        blend: None,
+    }];
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            // Both sides of the meshes occlude the light, single sided meshes included.
+            PipelineDescBuilder::new()
+                .with_vertex_desc(&vertex_desc)
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_fragment),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_face_culling(pso::Face::NONE)
+                .with_depth_test(pso::DepthTest {
+                    fun: pso::Comparison::Less,
+                    write: true,
+                })
+                .with_blend_targets(blend.clone()),
+        )
+        .with_child_pipeline(
+            0,
+            PipelineDescBuilder::new()
+                .with_shaders(util::simple_shader_set(
+                    &shader_fullscreen,
+                    Some(&shader_clear),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_depth_test(pso::DepthTest {
+                    fun: pso::Comparison::Always,
+                    write: true,
+                })
+                .with_blend_targets(blend),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+        factory.destroy_shader_module(shader_fullscreen);
+        factory.destroy_shader_module(shader_clear);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(pipes) => Ok((pipes, pipeline_layout)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faces_look_along_their_direction() {
+        let position = Vector3::new(1.0, 2.0, 3.0);
+        for axes in &FACES {
+            let view = face_view(axes, &position);
+            let ahead = Point3::from(position + Vector3::from(axes[0]));
+            // Views look down -z, with right along x and up along y.
+            let ahead = view.transform_point(&ahead);
+            assert!((ahead.coords - Vector3::new(0.0, 0.0, -1.0)).norm() < 1e-6);
+            let right = Point3::from(position + Vector3::from(axes[1]));
+            let right = view.transform_point(&right);
+            assert!((right.coords - Vector3::new(1.0, 0.0, 0.0)).norm() < 1e-6);
+        }
+    }
+}
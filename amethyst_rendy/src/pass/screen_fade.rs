@@ -0,0 +1,40 @@
+//! Fading the whole target to a color, built on `FullscreenPassDesc`.
+use super::fullscreen::FullscreenPassDesc;
+use crate::{pod::IntoPod, resources::ScreenFade};
+use amethyst_core::ecs::{Read, SystemData, World};
+use glsl_layout::{vec4, AsStd140};
+use rendy::hal::pso;
+
+/// Uniform of the screen fade shader.
+/// ```glsl,ignore
+/// uniform ScreenFadeArgs {
+///    vec4 color;
+/// };
+/// ```
+#[derive(Clone, Copy, Debug, AsStd140)]
+pub struct ScreenFadeArgs {
+    /// Fade color, with its alpha scaled by the fade amount
+    pub color: vec4,
+}
+
+fn screen_fade_args(world: &World) -> ScreenFadeArgs {
+    let fade = <Option<Read<'_, ScreenFade>>>::fetch(world)
+        .map(|fade| *fade)
+        .unwrap_or_default();
+    let mut color = fade.color;
+    color.alpha *= fade.amount.max(0.0).min(1.0);
+    ScreenFadeArgs {
+        color: color.into_pod(),
+    }
+}
+
+/// Describes a pass fading the target to the color of the `ScreenFade` resource.
+pub type DrawScreenFadeDesc<B> = FullscreenPassDesc<B, ScreenFadeArgs>;
+
+impl<B: crate::types::Backend> DrawScreenFadeDesc<B> {
+    /// Create a pass fading the target to the color of the `ScreenFade` resource.
+    pub fn screen_fade() -> Self {
+        FullscreenPassDesc::new(super::SCREEN_FADE_FRAGMENT.clone(), screen_fade_args)
+            .with_blend(pso::BlendState::ALPHA)
+    }
+}
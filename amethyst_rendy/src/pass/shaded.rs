@@ -1,6 +1,6 @@
 use super::base_3d::*;
 use crate::{
-    mtl::{TexAlbedo, TexEmission},
+    mtl::{TexAlbedo, TexEmission, TexLightmap},
     skinning::JointCombined,
 };
 use rendy::{
@@ -13,16 +13,31 @@ use rendy::{
 pub struct ShadedPassDef;
 impl Base3DPassDef for ShadedPassDef {
     const NAME: &'static str = "Shaded";
-    type TextureSet = (TexAlbedo, TexEmission);
+    type TextureSet = (TexAlbedo, TexEmission, TexLightmap);
     fn vertex_shader() -> &'static SpirvShader {
         &super::POS_NORM_TEX_VERTEX
     }
     fn vertex_skinned_shader() -> &'static SpirvShader {
         &super::POS_NORM_TEX_SKIN_VERTEX
     }
+    fn vertex_dual_quaternion_skinned_shader() -> Option<&'static SpirvShader> {
+        Some(&super::POS_NORM_TEX_SKIN_DQ_VERTEX)
+    }
+    fn vertex_clip_shader() -> Option<&'static SpirvShader> {
+        Some(&super::POS_NORM_TEX_CLIP_VERTEX)
+    }
     fn fragment_shader() -> &'static SpirvShader {
         &super::SHADED_FRAGMENT
     }
+    fn oit_fragment_shader() -> Option<&'static SpirvShader> {
+        Some(&super::SHADED_OIT_FRAGMENT)
+    }
+    fn lightmap_shaders() -> Option<(&'static SpirvShader, &'static SpirvShader)> {
+        Some((
+            &super::POS_NORM_TEX_LIGHTMAP_VERTEX,
+            &super::SHADED_LIGHTMAP_FRAGMENT,
+        ))
+    }
     fn base_format() -> Vec<VertexFormat> {
         vec![Position::vertex(), Normal::vertex(), TexCoord::vertex()]
     }
@@ -78,6 +78,14 @@ impl DrawSkyboxDesc {
             },
         }
     }
+
+    /// Returns the nadir and zenith colors drawn when no [SkyboxSettings] resource exists.
+    pub fn colors(&self) -> (Srgb, Srgb) {
+        (
+            self.default_settings.nadir_color,
+            self.default_settings.zenith_color,
+        )
+    }
 }
 
 impl<B: Backend> RenderGroupDesc<B, World> for DrawSkyboxDesc {
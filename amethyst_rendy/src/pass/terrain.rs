@@ -0,0 +1,474 @@
+//! Terrains drawn as a quadtree of instanced chunks, see the [terrain module](crate::terrain).
+use crate::{
+    camera::Camera,
+    dynamic_texture::DynamicTexture,
+    layers::RenderLayers,
+    mtl::MaterialDefaults,
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    pod::TerrainArgs,
+    submodules::{gather::CameraGatherer, DynamicVertexBuffer, EnvironmentSub},
+    terrain::{Terrain, TerrainChunk, MAX_TERRAIN_LAYERS, TERRAIN_CHUNK_CELLS},
+    types::{Backend, Texture},
+    util,
+    visibility::Frustum,
+};
+use amethyst_assets::{AssetStorage, Handle};
+use amethyst_core::{
+    ecs::{Entities, Join, Read, ReadExpect, ReadStorage, SystemData, World},
+    math::{convert, Matrix4, Point3},
+    transform::Transform,
+    Hidden, HiddenPropagate,
+};
+use derivative::Derivative;
+use fnv::FnvHashMap;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, NodeBuffer, NodeImage,
+    },
+    hal::{self, device::Device, pso},
+    mesh::{AsVertex, Mesh, MeshBuilder, Position},
+    resource::{DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle},
+    shader::Shader,
+};
+use std::{collections::hash_map::Entry, ops::Range};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Textures of the terrain set: heightmap, splat map and layers.
+const TERRAIN_TEXTURES: usize = 2 + MAX_TERRAIN_LAYERS;
+
+/// Draw the `Terrain` components of the scene.
+///
+/// Terrains are drawn once the `TerrainSystem` has added their heightmap texture, and all their
+/// textures are loaded.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DrawTerrainDesc;
+
+impl DrawTerrainDesc {
+    /// Create instance of `DrawTerrain` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawTerrainDesc {
+    fn build(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build_terrain");
+
+        let env = EnvironmentSub::new(
+            factory,
+            [
+                pso::ShaderStageFlags::VERTEX,
+                pso::ShaderStageFlags::FRAGMENT,
+            ],
+        )?;
+        let layout: RendyHandle<DescriptorSetLayout<B>> = factory
+            .create_descriptor_set_layout(util::set_layout_bindings(Some((
+                TERRAIN_TEXTURES as u32,
+                pso::DescriptorType::CombinedImageSampler,
+                pso::ShaderStageFlags::VERTEX | pso::ShaderStageFlags::FRAGMENT,
+            ))))?
+            .into();
+
+        let (vertices, indices) = chunk_grid(TERRAIN_CHUNK_CELLS);
+        let mesh = MeshBuilder::new()
+            .with_vertices(vertices)
+            .with_indices(indices)
+            .build(queue, factory)?;
+
+        let (pipeline, pipeline_layout) = build_terrain_pipeline(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            vec![env.raw_layout(), layout.raw()],
+        )?;
+
+        Ok(Box::new(DrawTerrain::<B> {
+            pipeline,
+            pipeline_layout,
+            env,
+            layout,
+            mesh,
+            sets: Vec::new(),
+            chunks: Vec::new(),
+            args: Vec::new(),
+            draws: Vec::new(),
+            models: DynamicVertexBuffer::new(),
+        }))
+    }
+}
+
+/// Descriptor set of the textures of a terrain, with the ids and versions written to it.
+#[derive(Debug)]
+struct TerrainSet<B: Backend> {
+    set: Escape<DescriptorSet<B>>,
+    textures: Vec<(u32, u32)>,
+    used: bool,
+}
+
+/// Draws the chunks of terrains, see the [terrain module](crate::terrain).
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct DrawTerrain<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    env: EnvironmentSub<B>,
+    layout: RendyHandle<DescriptorSetLayout<B>>,
+    mesh: Mesh<B>,
+    /// Texture sets of each frame in flight, by terrain entity.
+    sets: Vec<FnvHashMap<u32, TerrainSet<B>>>,
+    chunks: Vec<TerrainChunk>,
+    args: Vec<TerrainArgs>,
+    /// Terrain entity, environment slot and chunks of each draw.
+    draws: Vec<(u32, usize, Range<u32>)>,
+    models: DynamicVertexBuffer<B, TerrainArgs>,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawTerrain<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare terrain");
+
+        let (
+            entities,
+            tex_storage,
+            defaults,
+            terrains,
+            heightmaps,
+            transforms,
+            cameras,
+            layers,
+            hiddens,
+            hidden_props,
+        ) = <(
+            Entities<'_>,
+            Read<'_, AssetStorage<Texture>>,
+            ReadExpect<'_, MaterialDefaults>,
+            ReadStorage<'_, Terrain>,
+            ReadStorage<'_, DynamicTexture>,
+            ReadStorage<'_, Transform>,
+            ReadStorage<'_, Camera>,
+            ReadStorage<'_, RenderLayers>,
+            ReadStorage<'_, Hidden>,
+            ReadStorage<'_, HiddenPropagate>,
+        )>::fetch(world);
+
+        self.args.clear();
+        self.draws.clear();
+
+        let camera = CameraGatherer::gather_camera_entity(world).and_then(|entity| {
+            let transform = transforms.get(entity)?;
+            let camera = cameras.get(entity)?;
+            Some((
+                convert::<_, Matrix4<f32>>(*camera.as_matrix())
+                    * convert::<_, Matrix4<f32>>(transform.global_view_matrix()),
+                convert::<_, Matrix4<f32>>(*transform.global_matrix())
+                    .transform_point(&Point3::origin()),
+            ))
+        });
+
+        while self.sets.len() <= index {
+            self.sets.push(FnvHashMap::default());
+        }
+        let sets = &mut self.sets[index];
+        for set in sets.values_mut() {
+            set.used = false;
+        }
+
+        if let Some((proj_view, camera_position)) = camera {
+            for (entity, terrain, heightmap, transform, layers, _, _) in (
+                &entities,
+                &terrains,
+                &heightmaps,
+                &transforms,
+                layers.maybe(),
+                !&hiddens,
+                !&hidden_props,
+            )
+                .join()
+            {
+                let default = &defaults.0.albedo;
+                let handles = Some(heightmap.texture())
+                    .into_iter()
+                    .chain(Some(terrain.splat.as_ref().unwrap_or(default)))
+                    .chain(
+                        (0..MAX_TERRAIN_LAYERS)
+                            .map(|i| terrain.layers.get(i).map_or(default, |l| &l.albedo)),
+                    )
+                    .collect::<Vec<&Handle<Texture>>>();
+                let textures = match handles
+                    .iter()
+                    .map(|handle| {
+                        tex_storage
+                            .get_with_version(handle)
+                            .map(|(texture, version)| ((handle.id(), *version), texture))
+                    })
+                    .collect::<Option<Vec<_>>>()
+                {
+                    Some(textures) => textures,
+                    None => continue,
+                };
+
+                let set = match sets.entry(entity.id()) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        match factory.create_descriptor_set(self.layout.clone()) {
+                            Ok(set) => entry.insert(TerrainSet {
+                                set,
+                                textures: Vec::new(),
+                                used: false,
+                            }),
+                            Err(err) => {
+                                log::error!("Failed to create terrain descriptor set: {}", err);
+                                continue;
+                            }
+                        }
+                    }
+                };
+                let keys = textures.iter().map(|(key, _)| *key).collect::<Vec<_>>();
+                if set.textures != keys {
+                    let raw = set.set.raw();
+                    let writes = textures
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(binding, (_, texture))| {
+                            let desc = util::texture_desc::<B>(
+                                texture,
+                                hal::image::Layout::ShaderReadOnlyOptimal,
+                            )?;
+                            Some(util::desc_write(raw, binding as u32, desc))
+                        })
+                        .collect::<Vec<_>>();
+                    if writes.len() < TERRAIN_TEXTURES {
+                        continue;
+                    }
+                    unsafe {
+                        factory.write_descriptor_sets(writes);
+                    }
+                    set.textures = keys;
+                }
+                set.used = true;
+
+                let model = convert::<_, Matrix4<f32>>(*transform.global_matrix());
+                let inverse = match model.try_inverse() {
+                    Some(inverse) => inverse,
+                    None => continue,
+                };
+                // Chunks are selected and culled in the local space of the terrain.
+                let frustum = Frustum::new(proj_view * model);
+                self.chunks.clear();
+                terrain.select_chunks(
+                    &inverse.transform_point(&camera_position),
+                    |min, max| {
+                        let center = Point3::from((min.coords + max.coords) * 0.5);
+                        frustum.check_sphere(&center, (max - min).norm() * 0.5)
+                    },
+                    &mut self.chunks,
+                );
+
+                let start = self.args.len() as u32;
+                self.args.extend(
+                    self.chunks
+                        .iter()
+                        .map(|chunk| TerrainArgs::from_object_data(transform, terrain, chunk)),
+                );
+                let slot = self.env.layer_slot(RenderLayers::of(layers));
+                self.draws
+                    .push((entity.id(), slot, start..self.args.len() as u32));
+            }
+        }
+        // Sets of removed terrains, the frame using them last time is done.
+        sets.retain(|_, set| set.used);
+
+        self.env.process(factory, index, world);
+        self.models
+            .write(factory, index, self.args.len() as u64, Some(&self.args));
+
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw terrain");
+
+        if self.draws.is_empty() {
+            return;
+        }
+
+        let layout = &self.pipeline_layout;
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        if !self.models.bind(index, 1, 0, &mut encoder) {
+            return;
+        }
+        for (entity, slot, range) in &self.draws {
+            self.env.bind_layers(index, *slot, layout, 0, &mut encoder);
+            unsafe {
+                encoder.bind_graphics_descriptor_sets(
+                    layout,
+                    1,
+                    Some(self.sets[index][entity].set.raw()),
+                    std::iter::empty(),
+                );
+            }
+            // The grid only has positions.
+            let _ = self
+                .mesh
+                .bind_and_draw(0, &[Position::vertex()], range.clone(), &mut encoder);
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+/// Grid of `cells` by `cells` quads between 0 and 1 along x and z, with a skirt along its
+/// border. Skirt vertices have a y of 1, and are moved down by the vertex shader.
+fn chunk_grid(cells: u32) -> (Vec<Position>, Vec<u32>) {
+    let row = cells + 1;
+    let step = 1.0 / cells as f32;
+    let mut vertices = (0..row)
+        .flat_map(|z| (0..row).map(move |x| Position([x as f32 * step, 0.0, z as f32 * step])))
+        .collect::<Vec<_>>();
+    let index = |x: u32, z: u32| z * row + x;
+
+    let mut indices = Vec::with_capacity((cells * cells * 6 + cells * 4 * 12) as usize);
+    for z in 0..cells {
+        for x in 0..cells {
+            let (a, b) = (index(x, z), index(x + 1, z));
+            let (c, d) = (index(x, z + 1), index(x + 1, z + 1));
+            // Counter-clockwise seen from above.
+            indices.extend_from_slice(&[a, c, b, b, c, d]);
+        }
+    }
+
+    // Border of the grid, in order around it.
+    let border = (0..cells)
+        .map(|x| index(x, 0))
+        .chain((0..cells).map(|z| index(cells, z)))
+        .chain((0..cells).map(|x| index(cells - x, cells)))
+        .chain((0..cells).map(|z| index(0, cells - z)))
+        .collect::<Vec<_>>();
+    let first_skirt = vertices.len() as u32;
+    for &vertex in &border {
+        let Position([x, _, z]) = vertices[vertex as usize];
+        vertices.push(Position([x, 1.0, z]));
+    }
+    for i in 0..border.len() {
+        let next = (i + 1) % border.len();
+        let (a, b) = (border[i], border[next]);
+        let (c, d) = (first_skirt + i as u32, first_skirt + next as u32);
+        // Skirts are seen from both sides, whichever chunk they hide a crack of.
+        indices.extend_from_slice(&[a, c, b, b, c, d, a, b, c, b, d, c]);
+    }
+
+    (vertices, indices)
+}
+
+fn build_terrain_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let shader_vertex = unsafe { super::TERRAIN_VERTEX.module(factory).unwrap() };
+    let shader_fragment = unsafe { super::TERRAIN_FRAGMENT.module(factory).unwrap() };
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            PipelineDescBuilder::new()
+                .with_vertex_desc(&[
+                    (Position::vertex(), pso::VertexInputRate::Vertex),
+                    (TerrainArgs::vertex(), pso::VertexInputRate::Instance(1)),
+                ])
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_fragment),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_face_culling(pso::Face::BACK)
+                .with_depth_test(pso::DepthTest {
+                    fun: pso::Comparison::Less,
+                    write: true,
+                })
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend: None,
+                }]),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_grid_has_skirts() {
+        let (vertices, indices) = chunk_grid(2);
+        // 3x3 grid vertices and a skirt vertex under each of the 8 border vertices.
+        assert_eq!(vertices.len(), 9 + 8);
+        assert_eq!(indices.len(), 2 * 2 * 6 + 8 * 12);
+        assert!(vertices[9..].iter().all(|v| v.0[1] == 1.0));
+        assert!(indices.iter().all(|&i| (i as usize) < vertices.len()));
+    }
+}
@@ -0,0 +1,793 @@
+//! Reflective water planes, see the [water module](crate::water).
+//!
+//! `DrawWaterScene` draws the opaque static meshes of the scene into the reflection or
+//! refraction target, and `DrawWater` blends those images over each `WaterPlane` of the main
+//! target.
+use crate::{
+    batch::{GroupIterator, OneLevelBatch, TwoLevelBatch},
+    camera::Camera,
+    layers::RenderLayers,
+    mtl::{Material, MaterialDefaults, TexAlbedo},
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    pod::{VertexArgs, WaterArgs},
+    resources::Tint,
+    skinning::JointTransforms,
+    submodules::{
+        gather::CameraGatherer, DynamicUniform, DynamicVertexBuffer, EnvironmentSub, MaterialId,
+        MaterialSub, TextureId, TextureSub,
+    },
+    transparent::Transparent,
+    types::{Backend, Mesh},
+    util,
+    visibility::{BoundingSphere, Frustum},
+    water::{self, WaterPlane},
+};
+use amethyst_assets::{AssetStorage, Handle};
+use amethyst_core::{
+    ecs::{Join, Read, ReadExpect, ReadStorage, SystemData, World},
+    math::{convert, Matrix4, Point3, Vector4},
+    transform::Transform,
+    Hidden, HiddenPropagate, Time,
+};
+use derivative::Derivative;
+use glsl_layout::{mat4, vec4, AsStd140};
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
+    },
+    hal::{
+        self,
+        device::Device,
+        format::{Aspects, Swizzle},
+        image::{Filter, SamplerInfo, SubresourceRange, ViewKind, WrapMode},
+        pso,
+    },
+    mesh::{AsVertex, Normal, Position, TexCoord, VertexFormat},
+    resource::{
+        DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle, ImageView,
+        ImageViewInfo, Sampler,
+    },
+    shader::Shader,
+};
+use smallvec::SmallVec;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Vertices of the quad drawn for each water plane, generated by the vertex shader.
+const WATER_QUAD_VERTICES: u32 = 6;
+
+/// Which side of the water `DrawWaterSceneDesc` draws.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WaterScene {
+    /// The scene above the water, mirrored about its plane.
+    Reflection,
+    /// The scene below the water, with the distance to the camera in the alpha channel.
+    Refraction,
+}
+
+/// View of the scene drawn by `DrawWaterScene`.
+#[derive(Clone, Copy, Debug, AsStd140)]
+struct WaterViewArgs {
+    proj_view: mat4,
+    /// World plane of the kept side, all points are kept by the reflection.
+    clip_plane: vec4,
+}
+
+/// Draw the opaque static meshes of the scene on one side of the water.
+///
+/// Skinned meshes are not drawn. The target should have a color cleared to the color of the sky
+/// for the reflection, or to a large alpha for the refraction, and a depth output cleared to 1.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DrawWaterSceneDesc {
+    scene: WaterScene,
+}
+
+impl DrawWaterSceneDesc {
+    /// Create instance of `DrawWaterScene` render group drawing `scene`
+    pub fn new(scene: WaterScene) -> Self {
+        Self { scene }
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawWaterSceneDesc {
+    fn build(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build_water_scene");
+
+        let env = EnvironmentSub::new(
+            factory,
+            [
+                pso::ShaderStageFlags::VERTEX,
+                pso::ShaderStageFlags::FRAGMENT,
+            ],
+        )?;
+        let materials = MaterialSub::new(factory)?;
+        let view = DynamicUniform::new(
+            factory,
+            pso::ShaderStageFlags::VERTEX | pso::ShaderStageFlags::FRAGMENT,
+        )?;
+
+        let mut vertex_format = vec![Position::vertex(), Normal::vertex(), TexCoord::vertex()];
+        let (pipeline, pipeline_layout) = build_water_scene_pipeline(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            &vertex_format,
+            self.scene,
+            vec![env.raw_layout(), materials.raw_layout(), view.raw_layout()],
+        )?;
+        vertex_format.sort();
+
+        Ok(Box::new(DrawWaterScene::<B> {
+            pipeline,
+            pipeline_layout,
+            scene: self.scene,
+            env,
+            materials,
+            view,
+            vertex_format,
+            batches: Default::default(),
+            models: DynamicVertexBuffer::new(),
+            active: false,
+        }))
+    }
+}
+
+/// Draws the scene on one side of the water, see the [module documentation](index.html).
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct DrawWaterScene<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    scene: WaterScene,
+    env: EnvironmentSub<B>,
+    materials: MaterialSub<B, TexAlbedo>,
+    view: DynamicUniform<B, WaterViewArgs>,
+    vertex_format: Vec<VertexFormat>,
+    batches: TwoLevelBatch<(usize, MaterialId), u32, SmallVec<[VertexArgs; 4]>>,
+    models: DynamicVertexBuffer<B, VertexArgs>,
+    /// Whether anything is drawn this frame.
+    active: bool,
+}
+
+impl<B: Backend> DrawWaterScene<B> {
+    /// Returns the view of the scene and the plane kept by the fragment shader, if any water is
+    /// visible from this side.
+    fn view(&self, world: &World) -> Option<(Matrix4<f32>, Matrix4<f32>, Vector4<f32>)> {
+        let (cameras, waters, transforms, hiddens, hidden_props) = <(
+            ReadStorage<'_, Camera>,
+            ReadStorage<'_, WaterPlane>,
+            ReadStorage<'_, Transform>,
+            ReadStorage<'_, Hidden>,
+            ReadStorage<'_, HiddenPropagate>,
+        )>::fetch(world);
+
+        let entity = CameraGatherer::gather_camera_entity(world)?;
+        let camera = cameras.get(entity)?;
+        let camera_transform = transforms.get(entity)?;
+        let proj = convert::<_, Matrix4<f32>>(*camera.as_matrix());
+        let view = convert::<_, Matrix4<f32>>(camera_transform.global_view_matrix());
+        let camera_position = convert::<_, Matrix4<f32>>(*camera_transform.global_matrix())
+            .transform_point(&Point3::origin());
+
+        let plane = water::reflected_plane(
+            (&waters, &transforms, !&hiddens, !&hidden_props)
+                .join()
+                .map(|(water, transform, _, _)| (water, transform)),
+            &camera_position,
+        )?;
+
+        match self.scene {
+            WaterScene::Reflection => {
+                // Nothing is reflected seen from below.
+                if plane.xyz().dot(&camera_position.coords) + plane.w <= 0.0 {
+                    return None;
+                }
+                let mirrored = view * water::reflection_matrix(&plane);
+                let clip_plane = mirrored.try_inverse()?.transpose() * plane;
+                let oblique = water::oblique_projection(&proj, &clip_plane);
+                Some((
+                    proj * mirrored,
+                    oblique * mirrored,
+                    Vector4::new(0.0, 0.0, 0.0, 1.0),
+                ))
+            }
+            WaterScene::Refraction => Some((proj * view, proj * view, -plane)),
+        }
+    }
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawWaterScene<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare water scene");
+
+        self.materials.maintain();
+        self.batches.clear_inner();
+
+        let (cull, proj_view, clip_plane) = match self.view(world) {
+            Some(view) => view,
+            None => {
+                self.active = false;
+                return PrepareResult::DrawRecord;
+            }
+        };
+        self.active = true;
+
+        let (
+            mesh_storage,
+            transparent,
+            hiddens,
+            hidden_props,
+            meshes,
+            materials,
+            transforms,
+            joints,
+            tints,
+            layers,
+            spheres,
+        ) = <(
+            Read<'_, AssetStorage<Mesh>>,
+            ReadStorage<'_, Transparent>,
+            ReadStorage<'_, Hidden>,
+            ReadStorage<'_, HiddenPropagate>,
+            ReadStorage<'_, Handle<Mesh>>,
+            ReadStorage<'_, Handle<Material>>,
+            ReadStorage<'_, Transform>,
+            ReadStorage<'_, JointTransforms>,
+            ReadStorage<'_, Tint>,
+            ReadStorage<'_, RenderLayers>,
+            ReadStorage<'_, BoundingSphere>,
+        )>::fetch(world);
+
+        let materials_ref = &mut self.materials;
+        let env_ref = &mut self.env;
+        let batches_ref = &mut self.batches;
+
+        // The visibility of the main camera doesn't apply to the mirrored view, meshes are culled
+        // against its own frustum.
+        let frustum = Frustum::new(cull);
+        let origin = Point3::origin();
+        (
+            (
+                &materials,
+                &meshes,
+                &transforms,
+                tints.maybe(),
+                layers.maybe(),
+                spheres.maybe(),
+            ),
+            !&joints,
+            !&transparent,
+            !&hiddens,
+            !&hidden_props,
+        )
+            .join()
+            .filter(|((_, _, tform, _, _, sphere), _, _, _, _)| {
+                let matrix = tform.global_matrix();
+                let center = matrix.transform_point(sphere.map_or(&origin, |s| &s.center));
+                let radius = sphere.map_or(1.0, |s| s.radius)
+                    * matrix[(0, 0)].max(matrix[(1, 1)]).max(matrix[(2, 2)]);
+                frustum.check_sphere(&center, radius)
+            })
+            .map(|((mat, mesh, tform, tint, layers, _), _, _, _, _)| {
+                (
+                    (mat, mesh.id(), RenderLayers::of(layers)),
+                    VertexArgs::from_object_data(tform, tint),
+                )
+            })
+            .for_each_group(|(mat, mesh_id, layers), data| {
+                if mesh_storage.contains_id(mesh_id) {
+                    if let Some((mat, _)) = materials_ref.insert(factory, world, mat) {
+                        let key = (env_ref.layer_slot(layers), mat);
+                        batches_ref.insert(key, mesh_id, data.drain(..));
+                    }
+                }
+            });
+
+        self.env.process(factory, index, world);
+
+        {
+            #[cfg(feature = "profiler")]
+            profile_scope!("write");
+
+            let proj_view: [[f32; 4]; 4] = proj_view.into();
+            let clip_plane: [f32; 4] = clip_plane.into();
+            self.view.write(
+                factory,
+                index,
+                WaterViewArgs {
+                    proj_view: proj_view.into(),
+                    clip_plane: clip_plane.into(),
+                }
+                .std140(),
+            );
+
+            self.batches.prune();
+            self.models.write(
+                factory,
+                index,
+                self.batches.count() as u64,
+                self.batches.data(),
+            );
+        }
+
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw water scene");
+
+        if !self.active {
+            return;
+        }
+
+        let mesh_storage = <Read<'_, AssetStorage<Mesh>>>::fetch(world);
+        let layout = &self.pipeline_layout;
+        let models_loc = self.vertex_format.len() as u32;
+
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.env.bind(index, layout, 0, &mut encoder);
+        self.view.bind(index, layout, 2, &mut encoder);
+        if !self.models.bind(index, models_loc, 0, &mut encoder) {
+            return;
+        }
+
+        let mut instances_drawn = 0;
+        for (&(slot, mat_id), batches) in self.batches.iter() {
+            if self.materials.loaded(mat_id) {
+                self.env.bind_layers(index, slot, layout, 0, &mut encoder);
+                self.materials.bind(layout, 1, mat_id, &mut encoder);
+                for (mesh_id, batch_data) in batches {
+                    debug_assert!(mesh_storage.contains_id(*mesh_id));
+                    if let Some(mesh) =
+                        B::unwrap_mesh(unsafe { mesh_storage.get_by_id_unchecked(*mesh_id) })
+                    {
+                        // Vertex attributes missing from a mesh are reported by the opaque pass.
+                        let _ = mesh.bind_and_draw(
+                            0,
+                            &self.vertex_format,
+                            instances_drawn..instances_drawn + batch_data.len() as u32,
+                            &mut encoder,
+                        );
+                    }
+                    instances_drawn += batch_data.len() as u32;
+                }
+            }
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn build_water_scene_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    vertex_format: &[VertexFormat],
+    scene: WaterScene,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let vertex_desc = vertex_format
+        .iter()
+        .map(|f| (f.clone(), pso::VertexInputRate::Vertex))
+        .chain(Some((
+            VertexArgs::vertex(),
+            pso::VertexInputRate::Instance(1),
+        )))
+        .collect::<Vec<_>>();
+
+    let shader_vertex = unsafe { super::WATER_SCENE_VERTEX.module(factory).unwrap() };
+    let shader_fragment = unsafe { super::WATER_SCENE_FRAGMENT.module(factory).unwrap() };
+
+    // Mirroring the scene flips the winding of its triangles.
+    let cull_face = match scene {
+        WaterScene::Reflection => pso::Face::FRONT,
+        WaterScene::Refraction => pso::Face::BACK,
+    };
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            PipelineDescBuilder::new()
+                .with_vertex_desc(&vertex_desc)
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_fragment),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_face_culling(cull_face)
+                .with_depth_test(pso::DepthTest {
+                    fun: pso::Comparison::Less,
+                    write: true,
+                })
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend: None,
+                }]),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+    }
+}
+
+/// Draw the `WaterPlane` components of the scene over the target.
+///
+/// Build with `builder().with_image(reflection)`, followed by `with_image(refraction)` if
+/// refraction is enabled, passing the color images of the targets drawn by `DrawWaterSceneDesc`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DrawWaterDesc {
+    refraction: bool,
+}
+
+impl DrawWaterDesc {
+    /// Create instance of `DrawWater` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Read the refraction image, passed after the reflection.
+    pub fn with_refraction(mut self, refraction: bool) -> Self {
+        self.refraction = refraction;
+        self
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawWaterDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![
+            ImageAccess {
+                access: hal::image::Access::SHADER_READ,
+                usage: hal::image::Usage::SAMPLED,
+                layout: hal::image::Layout::ShaderReadOnlyOptimal,
+                stages: pso::PipelineStage::FRAGMENT_SHADER,
+            };
+            if self.refraction { 2 } else { 1 }
+        ]
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build_water");
+
+        let env = EnvironmentSub::new(
+            factory,
+            [
+                pso::ShaderStageFlags::VERTEX,
+                pso::ShaderStageFlags::FRAGMENT,
+            ],
+        )?;
+        let textures = TextureSub::new(factory)?;
+
+        let layout: RendyHandle<DescriptorSetLayout<B>> = factory
+            .create_descriptor_set_layout(util::set_layout_bindings(Some((
+                2,
+                pso::DescriptorType::CombinedImageSampler,
+                pso::ShaderStageFlags::FRAGMENT,
+            ))))?
+            .into();
+        let sampler = factory.get_sampler(SamplerInfo::new(Filter::Linear, WrapMode::Clamp))?;
+
+        let mut views = Vec::with_capacity(2);
+        for node_image in &images {
+            let image = ctx
+                .get_image(node_image.id)
+                .ok_or_else(|| failure::format_err!("Water scene image is missing."))?;
+            views.push(factory.create_image_view(
+                image.clone(),
+                ImageViewInfo {
+                    view_kind: ViewKind::D2,
+                    format: image.format(),
+                    swizzle: Swizzle::NO,
+                    range: SubresourceRange {
+                        aspects: Aspects::COLOR,
+                        levels: 0..1,
+                        layers: 0..1,
+                    },
+                },
+            )?);
+        }
+        if views.is_empty() {
+            return Err(failure::format_err!("Water reflection image is missing."));
+        }
+
+        // Without refraction the reflection is bound in its place, and never read.
+        let set = factory.create_descriptor_set(layout.clone())?;
+        unsafe {
+            factory.write_descriptor_sets((0..2).map(|binding| {
+                let view = views.get(binding).unwrap_or(&views[0]);
+                util::desc_write(
+                    set.raw(),
+                    binding as u32,
+                    pso::Descriptor::CombinedImageSampler(
+                        view.raw(),
+                        hal::image::Layout::ShaderReadOnlyOptimal,
+                        sampler.raw(),
+                    ),
+                )
+            }));
+        }
+
+        let (pipeline, pipeline_layout) = build_water_pipeline(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            vec![env.raw_layout(), layout.raw(), textures.raw_layout()],
+        )?;
+
+        Ok(Box::new(DrawWater::<B> {
+            pipeline,
+            pipeline_layout,
+            refraction: self.refraction,
+            env,
+            textures,
+            batches: Default::default(),
+            models: DynamicVertexBuffer::new(),
+            set,
+            _views: views,
+            _sampler: sampler,
+            _layout: layout,
+        }))
+    }
+}
+
+/// Draws the water planes over the target, see the [module documentation](index.html).
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct DrawWater<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    refraction: bool,
+    env: EnvironmentSub<B>,
+    textures: TextureSub<B>,
+    batches: OneLevelBatch<(usize, TextureId), WaterArgs>,
+    models: DynamicVertexBuffer<B, WaterArgs>,
+    set: Escape<DescriptorSet<B>>,
+    _views: Vec<Escape<ImageView<B>>>,
+    _sampler: RendyHandle<Sampler<B>>,
+    _layout: RendyHandle<DescriptorSetLayout<B>>,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawWater<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare water");
+
+        let (time, defaults, waters, transforms, layers, hiddens, hidden_props) =
+            <(
+                Read<'_, Time>,
+                ReadExpect<'_, MaterialDefaults>,
+                ReadStorage<'_, WaterPlane>,
+                ReadStorage<'_, Transform>,
+                ReadStorage<'_, RenderLayers>,
+                ReadStorage<'_, Hidden>,
+                ReadStorage<'_, HiddenPropagate>,
+            )>::fetch(world);
+
+        self.batches.clear_inner();
+
+        let batches_ref = &mut self.batches;
+        let textures_ref = &mut self.textures;
+        let env_ref = &mut self.env;
+        let seconds = time.absolute_time_seconds();
+        let refraction = self.refraction;
+
+        (
+            &waters,
+            &transforms,
+            layers.maybe(),
+            !&hiddens,
+            !&hidden_props,
+        )
+            .join()
+            .filter_map(|(water, tform, layers, _, _)| {
+                let (normal, _) = textures_ref.insert(
+                    factory,
+                    world,
+                    water.normal_map.as_ref().unwrap_or(&defaults.0.normal),
+                    hal::image::Layout::ShaderReadOnlyOptimal,
+                )?;
+                let slot = env_ref.layer_slot(RenderLayers::of(layers));
+                Some((
+                    (slot, normal),
+                    WaterArgs::from_object_data(tform, water, seconds, refraction),
+                ))
+            })
+            .for_each_group(|key, data| {
+                batches_ref.insert(key, data.drain(..));
+            });
+
+        self.textures.maintain(factory, world);
+        self.env.process(factory, index, world);
+
+        {
+            #[cfg(feature = "profiler")]
+            profile_scope!("write");
+
+            self.batches.prune();
+            self.models.write(
+                factory,
+                index,
+                self.batches.count() as u64,
+                self.batches.data(),
+            );
+        }
+
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw water");
+
+        let layout = &self.pipeline_layout;
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        if !self.models.bind(index, 0, 0, &mut encoder) {
+            return;
+        }
+        unsafe {
+            encoder.bind_graphics_descriptor_sets(
+                layout,
+                1,
+                Some(self.set.raw()),
+                std::iter::empty(),
+            );
+        }
+        for (&(slot, normal), range) in self.batches.iter() {
+            if self.textures.loaded(normal) {
+                self.env.bind_layers(index, slot, layout, 0, &mut encoder);
+                self.textures.bind(layout, 2, normal, &mut encoder);
+                unsafe {
+                    encoder.draw(0..WATER_QUAD_VERTICES, range);
+                }
+            }
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn build_water_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let shader_vertex = unsafe { super::WATER_VERTEX.module(factory).unwrap() };
+    let shader_fragment = unsafe { super::WATER_FRAGMENT.module(factory).unwrap() };
+
+    // Planes are seen from both sides and don't hide each other.
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            PipelineDescBuilder::new()
+                .with_vertex_desc(&[(WaterArgs::vertex(), pso::VertexInputRate::Instance(1))])
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_fragment),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_depth_test(pso::DepthTest {
+                    fun: pso::Comparison::Less,
+                    write: false,
+                })
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend: Some(pso::BlendState::ALPHA),
+                }]),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+    }
+}
@@ -4,15 +4,16 @@ use derivative::Derivative;
 use rendy::{
     factory::Factory,
     hal::{
+        adapter::PhysicalDevice,
         device::Device,
         pass::Subpass,
         pso::{
             AttributeDesc, BakedStates, BasePipeline, BlendDesc, ColorBlendDesc, DepthStencilDesc,
             DepthTest, Face, GraphicsPipelineDesc, GraphicsShaderSet, InputAssemblerDesc,
-            Multisampling, PipelineCreationFlags, Rasterizer, Rect, VertexBufferDesc,
-            VertexInputRate, Viewport,
+            Multisampling, PipelineCreationFlags, PolygonMode, Rasterizer, Rect, State,
+            VertexBufferDesc, VertexInputRate, Viewport,
         },
-        Primitive,
+        Features, IndexType, Primitive,
     },
     mesh::VertexFormat,
 };
@@ -39,6 +40,7 @@ pub struct PipelineDescBuilder<'a, B: Backend> {
     vertex_buffers: Vec<VertexBufferDesc>,
     attributes: Vec<AttributeDesc>,
     input_assembler: InputAssemblerDesc,
+    line_width: f32,
     blender: BlendDesc,
     depth_stencil: DepthStencilDesc,
     multisampling: Option<Multisampling>,
@@ -105,6 +107,44 @@ impl<'a, B: Backend> PipelineDescBuilder<'a, B> {
         self.input_assembler = input_assembler;
     }
 
+    /// Build with the provided `Primitive` topology, keeping the rest of the input assembler.
+    pub fn with_primitive(mut self, primitive: Primitive) -> Self {
+        self.set_primitive(primitive);
+        self
+    }
+    /// Set to use the provided `Primitive` topology, keeping the rest of the input assembler.
+    pub fn set_primitive(&mut self, primitive: Primitive) {
+        self.input_assembler.primitive = primitive;
+    }
+
+    /// Build with primitive restart at the maximal value of `restart_index`, or without it if
+    /// `None`. Only valid with strip topologies.
+    pub fn with_primitive_restart(mut self, restart_index: Option<IndexType>) -> Self {
+        self.set_primitive_restart(restart_index);
+        self
+    }
+    /// Set to use primitive restart at the maximal value of `restart_index`, or no primitive
+    /// restart if `None`. Only valid with strip topologies.
+    pub fn set_primitive_restart(&mut self, restart_index: Option<IndexType>) {
+        self.input_assembler.restart_index = restart_index;
+    }
+
+    /// Build with the provided width of lines in pixels, 1.0 by default.
+    ///
+    /// Applies to line topologies and polygons rasterized as lines. Devices without wide lines
+    /// draw them 1.0 pixel wide instead when built through `PipelinesBuilder`.
+    pub fn with_line_width(mut self, line_width: f32) -> Self {
+        self.set_line_width(line_width);
+        self
+    }
+    /// Set to use the provided width of lines in pixels, 1.0 by default.
+    ///
+    /// Applies to line topologies and polygons rasterized as lines. Devices without wide lines
+    /// draw them 1.0 pixel wide instead when built through `PipelinesBuilder`.
+    pub fn set_line_width(&mut self, line_width: f32) {
+        self.line_width = line_width;
+    }
+
     /// Build with the provided `BlendDesc`
     pub fn with_blender(mut self, blender: BlendDesc) -> Self {
         self.set_blender(blender);
@@ -254,11 +294,57 @@ impl<'a, B: Backend> PipelineDescBuilder<'a, B> {
     pub fn set_blend_targets(&mut self, targets: Vec<ColorBlendDesc>) {
         self.blender.targets = targets;
     }
+    /// Draw lines 1.0 pixel wide, for devices without wide lines.
+    fn fallback_line_width(&mut self) {
+        if (self.line_width - 1.0).abs() > std::f32::EPSILON {
+            log::warn!(
+                "Lines {} pixels wide are not supported by the device, drawing them 1 pixel wide.",
+                self.line_width
+            );
+            self.line_width = 1.0;
+        }
+    }
+
+    /// Names the shader stages of the pipeline, for error messages.
+    fn shader_stages(&self) -> String {
+        let shaders = match self.shaders {
+            Some(ref shaders) => shaders,
+            None => return String::from("no"),
+        };
+        let mut stages = vec!["vertex"];
+        if shaders.hull.is_some() {
+            stages.push("hull");
+        }
+        if shaders.domain.is_some() {
+            stages.push("domain");
+        }
+        if shaders.geometry.is_some() {
+            stages.push("geometry");
+        }
+        if shaders.fragment.is_some() {
+            stages.push("fragment");
+        }
+        stages.join(", ")
+    }
+
     /// Finalize and construct the `GraphicsPipelineDesc`
     pub fn build(self) -> GraphicsPipelineDesc<'a, B> {
+        let mut rasterizer = self.rasterizer;
+        let lines = match self.input_assembler.primitive {
+            Primitive::LineList | Primitive::LineStrip => true,
+            _ => match rasterizer.polygon_mode {
+                PolygonMode::Line(_) => true,
+                _ => false,
+            },
+        };
+        // The line width is part of the line polygon mode, which doesn't affect line primitives.
+        if lines && (self.line_width - 1.0).abs() > std::f32::EPSILON {
+            rasterizer.polygon_mode = PolygonMode::Line(State::Static(self.line_width));
+        }
+
         GraphicsPipelineDesc {
             shaders: self.shaders.expect("Pipeline is missing shaders"),
-            rasterizer: self.rasterizer,
+            rasterizer,
             vertex_buffers: self.vertex_buffers,
             attributes: self.attributes,
             input_assembler: self.input_assembler,
@@ -286,6 +372,7 @@ impl<'a, B: Backend> Default for PipelineDescBuilder<'a, B> {
             vertex_buffers: Vec::new(),
             attributes: Vec::new(),
             input_assembler: InputAssemblerDesc::new(Primitive::TriangleList),
+            line_width: 1.0,
             blender: BlendDesc::default(),
             depth_stencil: DepthStencilDesc::default(),
             multisampling: None,
@@ -341,6 +428,11 @@ impl<'a, B: Backend> PipelinesBuilder<'a, B> {
     }
 
     /// Finalize and construct the `GraphicsPipeline`
+    ///
+    /// Lines wider than 1.0 pixel are drawn 1.0 pixel wide if the device doesn't support wide
+    /// lines, with a warning.
+    ///
+    /// The error names the index and the shader stages of the first pipeline which failed.
     pub fn build(
         self,
         factory: &Factory<B>,
@@ -349,19 +441,40 @@ impl<'a, B: Backend> PipelinesBuilder<'a, B> {
         #[cfg(feature = "profiler")]
         profile_scope!("create_pipelines");
 
-        let mut pipelines = unsafe {
-            factory
-                .device()
-                .create_graphics_pipelines(self.builders.into_iter().map(|b| b.build()), cache)
-        };
+        let wide_lines = factory
+            .physical()
+            .features()
+            .contains(Features::LINE_WIDTH | Features::NON_FILL_POLYGON_MODE);
+        let stages = self
+            .builders
+            .iter()
+            .map(PipelineDescBuilder::shader_stages)
+            .collect::<Vec<_>>();
+        let descs = self.builders.into_iter().map(|mut b| {
+            if !wide_lines {
+                b.fallback_line_width();
+            }
+            b.build()
+        });
+
+        let mut pipelines = unsafe { factory.device().create_graphics_pipelines(descs, cache) };
 
-        if let Some(err) = pipelines.iter().find_map(|p| p.as_ref().err().cloned()) {
+        if let Some((index, err)) = pipelines
+            .iter()
+            .enumerate()
+            .find_map(|(index, p)| p.as_ref().err().map(|err| (index, err.clone())))
+        {
             for p in pipelines.drain(..).filter_map(Result::ok) {
                 unsafe {
                     factory.destroy_graphics_pipeline(p);
                 }
             }
-            failure::bail!(err);
+            failure::bail!(
+                "Failed to create graphics pipeline {} with {} shaders: {}",
+                index,
+                stages[index],
+                err
+            );
         }
 
         Ok(pipelines.into_iter().map(|p| p.unwrap()).collect())
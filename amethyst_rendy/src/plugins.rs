@@ -1,16 +1,31 @@
 //! Set of predefined implementations of `RenderPlugin` for use with `RenderingBundle`.
 
 use crate::{
-    bundle::{RenderOrder, RenderPlan, RenderPlugin, Target},
+    bundle::{
+        ImageOptions, OutputColor, RenderOrder, RenderPlan, RenderPlugin, Target, TargetImage,
+        TargetPlanOutputs,
+    },
+    decal::Decal,
+    outline::Outlined,
     pass::*,
+    point_shadow::{PointShadowSettings, PointShadowSystem},
+    resources::{ClipMode, ColorGradeSettings, DofSettings, HemisphereAmbient, ScreenFade},
+    skinning::SkinningMode,
     sprite_visibility::SpriteVisibilitySortingSystem,
+    submodules::ENVIRONMENT_MAP_IMAGES,
+    terrain::{Terrain, TerrainSystem},
+    transparent_order::TransparentOrderSystem,
     visibility::VisibilitySortingSystem,
-    Backend, Factory,
+    water::WaterPlane,
+    Backend, Factory, Format, Kind,
 };
 use amethyst_core::ecs::{DispatcherBuilder, World};
 use amethyst_error::Error;
 use palette::Srgb;
-use rendy::graph::render::RenderGroupDesc;
+use rendy::{
+    graph::render::RenderGroupDesc,
+    hal::command::{ClearColor, ClearDepthStencil, ClearValue},
+};
 
 #[cfg(feature = "window")]
 pub use window::RenderToWindow;
@@ -18,27 +33,28 @@ pub use window::RenderToWindow;
 #[cfg(feature = "window")]
 mod window {
     use super::*;
-    use crate::{
-        bundle::{ImageOptions, OutputColor},
-        Format, Kind,
-    };
+    use crate::resources::RenderSettings;
     use amethyst_config::{Config, ConfigError};
     use amethyst_core::{
         ecs::{ReadExpect, SystemData},
         SystemBundle,
     };
     use amethyst_window::{DisplayConfig, ScreenDimensions, Window, WindowBundle};
-    use rendy::hal::command::{ClearColor, ClearDepthStencil, ClearValue};
+    use rendy::hal::window::PresentMode;
     use std::path::Path;
 
     /// A [RenderPlugin] for opening a window and displaying a render target to it.
     ///
     /// When you provide [`DisplayConfig`], it opens a window for you using [`WindowBundle`].
+    ///
+    /// Changing the [`RenderSettings`] resource rebuilds the render graph, applying the new
+    /// settings to the window surface.
     #[derive(Default, Debug)]
     pub struct RenderToWindow {
         target: Target,
         config: Option<DisplayConfig>,
         dimensions: Option<ScreenDimensions>,
+        settings: Option<RenderSettings>,
         dirty: bool,
         clear: Option<ClearColor>,
     }
@@ -79,6 +95,9 @@ mod window {
             if let Some(config) = self.config.take() {
                 WindowBundle::from_config(config).build(world, builder)?;
             }
+            world
+                .entry::<RenderSettings>()
+                .or_insert_with(RenderSettings::default);
 
             Ok(())
         }
@@ -91,6 +110,11 @@ mod window {
                 self.dimensions = new_dimensions.map(|d| (*d).clone());
                 return false;
             }
+            let new_settings = world.try_fetch::<RenderSettings>();
+            if self.settings.as_ref() != new_settings.as_deref() {
+                self.dirty = true;
+                self.settings = new_settings.map(|s| (*s).clone());
+            }
             self.dirty
         }
 
@@ -114,14 +138,32 @@ mod window {
                 clear: Some(ClearValue::DepthStencil(ClearDepthStencil(1.0, 0))),
             };
 
+            let clear = self.clear.map(ClearValue::Color);
+            let color = if self.settings.as_ref().map_or(true, |s| s.vsync) {
+                OutputColor::Surface(surface, clear)
+            } else {
+                let format = factory.get_surface_format(&surface);
+                OutputColor::PresentedSurface(
+                    surface,
+                    ImageOptions {
+                        kind: window_kind,
+                        levels: 1,
+                        format,
+                        clear,
+                    },
+                    vec![
+                        PresentMode::Immediate,
+                        PresentMode::Mailbox,
+                        PresentMode::Fifo,
+                    ],
+                )
+            };
+
             plan.add_root(Target::Main);
             plan.define_pass(
                 self.target,
                 crate::bundle::TargetPlanOutputs {
-                    colors: vec![OutputColor::Surface(
-                        surface,
-                        self.clear.map(ClearValue::Color),
-                    )],
+                    colors: vec![color],
                     depth: Some(depth_options),
                 },
             )?;
@@ -145,9 +187,43 @@ pub type RenderPbr3D = RenderBase3D<crate::pass::PbrPassDef>;
 pub struct RenderBase3D<D: Base3DPassDef> {
     target: Target,
     skinning: bool,
+    skinning_mode: SkinningMode,
+    transparency: TransparencyMode,
+    environment_map: bool,
+    point_shadows: bool,
+    clip_mode: ClipMode,
     marker: std::marker::PhantomData<D>,
 }
 
+/// How `RenderBase3D` blends transparent meshes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransparencyMode {
+    /// Draw transparent meshes back to front with alpha blending.
+    Sorted,
+    /// Accumulate transparent meshes in any order into two extra images, which are then resolved
+    /// over the target. Avoids sorting artifacts of intersecting meshes at the cost of a less
+    /// exact result and an additional pass.
+    ///
+    /// Falls back to `Sorted` when the device can't blend into the required formats or the pass
+    /// has no weighted blended shader.
+    WeightedBlended,
+}
+
+impl Default for TransparencyMode {
+    fn default() -> Self {
+        TransparencyMode::Sorted
+    }
+}
+
+/// Render target holding the images of `TransparencyMode::WeightedBlended`.
+const OIT_TARGET: Target = Target::Custom("oit");
+
+/// Render target holding the maps baked from the `EnvironmentMap` resource.
+const IBL_TARGET: Target = Target::Custom("ibl");
+
+/// Render target holding the shadow cube maps of point lights.
+const POINT_SHADOW_TARGET: Target = Target::Custom("point_shadows");
+
 impl<D: Base3DPassDef> RenderBase3D<D> {
     /// Set target to which 3d meshes will be rendered.
     pub fn with_target(mut self, target: Target) -> Self {
@@ -162,6 +238,184 @@ impl<D: Base3DPassDef> RenderBase3D<D> {
         self.skinning = true;
         self
     }
+
+    /// Select how the joints of skinned meshes are blended, linear blend skinning by default.
+    pub fn with_skinning_mode(mut self, mode: SkinningMode) -> Self {
+        self.skinning_mode = mode;
+        self
+    }
+
+    /// Select how transparent meshes are blended.
+    ///
+    /// `TransparencyMode::WeightedBlended` sizes its images after the target, so the plugin
+    /// defining the target must be added before this one.
+    pub fn with_transparency_mode(mut self, mode: TransparencyMode) -> Self {
+        self.transparency = mode;
+        self
+    }
+
+    /// Enable image-based ambient lighting from the `EnvironmentMap` resource, for passes
+    /// supporting it. Without an environment set, the `AmbientColor` is used as before.
+    ///
+    /// NOTE: Transparent meshes drawn with `TransparencyMode::WeightedBlended` keep the ambient
+    /// color.
+    pub fn with_environment_map(mut self) -> Self {
+        self.environment_map = true;
+        self
+    }
+
+    /// Enable shadows of point lights with `casts_shadows`, budgeted by the
+    /// `PointShadowSettings` resource. Its `max_lights` and `resolution` size the shadow atlas
+    /// when the render graph is built.
+    ///
+    /// NOTE: Transparent meshes drawn with `TransparencyMode::WeightedBlended` are lit without
+    /// shadows.
+    pub fn with_point_shadows(mut self) -> Self {
+        self.point_shadows = true;
+        self
+    }
+
+    /// Clip meshes by the planes of the `ClipPlanes` resource, keeping either the inside or the
+    /// outside of all enabled planes.
+    ///
+    /// NOTE: Transparent meshes drawn with `TransparencyMode::WeightedBlended` are not clipped.
+    pub fn with_clip_planes(mut self, mode: ClipMode) -> Self {
+        self.clip_mode = mode;
+        self
+    }
+
+    /// Define the target holding the shadow cube maps of point lights.
+    fn plan_point_shadows<B: Backend>(
+        &self,
+        plan: &mut RenderPlan<B>,
+        world: &World,
+    ) -> Result<(), Error> {
+        let settings = world
+            .try_fetch::<PointShadowSettings>()
+            .map(|settings| settings.clone())
+            .unwrap_or_default();
+        let size = settings.resolution.max(1);
+        let kind = Kind::D2(size * 6, size * settings.max_lights.max(1), 1, 1);
+        // Without clear values the cube maps are kept between frames, and only redrawn when
+        // their light changes.
+        plan.define_pass(
+            POINT_SHADOW_TARGET,
+            TargetPlanOutputs {
+                colors: vec![OutputColor::Image(ImageOptions {
+                    kind,
+                    levels: 1,
+                    format: POINT_SHADOW_FORMAT,
+                    clear: None,
+                })],
+                depth: Some(ImageOptions {
+                    kind,
+                    levels: 1,
+                    format: POINT_SHADOW_DEPTH_FORMAT,
+                    clear: None,
+                }),
+            },
+        )?;
+        plan.extend_target(POINT_SHADOW_TARGET, |ctx| {
+            ctx.add(RenderOrder::Opaque, DrawPointShadowDesc::new().builder())?;
+            Ok(())
+        });
+        Ok(())
+    }
+
+    /// Define the target baking the image-based lighting maps.
+    fn plan_ibl<B: Backend>(&self, plan: &mut RenderPlan<B>) -> Result<(), Error> {
+        let kind = Kind::D2(IBL_MAP_SIZE, IBL_MAP_SIZE, 1, 1);
+        // Without clear values the maps are kept between frames, and only redrawn when the
+        // environment changes.
+        let color = |format| {
+            OutputColor::Image(ImageOptions {
+                kind,
+                levels: 1,
+                format,
+                clear: None,
+            })
+        };
+        plan.define_pass(
+            IBL_TARGET,
+            TargetPlanOutputs {
+                colors: vec![
+                    color(IBL_IRRADIANCE_FORMAT),
+                    color(IBL_SPECULAR_FORMAT),
+                    color(IBL_BRDF_FORMAT),
+                ],
+                depth: None,
+            },
+        )?;
+        plan.extend_target(IBL_TARGET, |ctx| {
+            ctx.add(RenderOrder::Opaque, DrawIblBakeDesc::new().builder())?;
+            Ok(())
+        });
+        Ok(())
+    }
+
+    /// Define the accumulation target of weighted blended transparency, sized after the target.
+    /// Returns false if that mode isn't available and sorted blending has to be used instead.
+    fn plan_oit<B: Backend>(
+        &self,
+        plan: &mut RenderPlan<B>,
+        factory: &Factory<B>,
+    ) -> Result<bool, Error> {
+        if D::oit_fragment_shader().is_none() || !oit_supported(factory) {
+            log::warn!(
+                "Weighted blended transparency is not supported, using sorted blending instead."
+            );
+            return Ok(false);
+        }
+        let metadata = match plan.target_metadata(self.target, factory) {
+            Some(metadata) => metadata,
+            None => {
+                log::warn!(
+                    "Outputs of {:?} must be defined before weighted blended transparency, using sorted blending instead.",
+                    self.target
+                );
+                return Ok(false);
+            }
+        };
+
+        let kind = Kind::D2(metadata.width(), metadata.height(), metadata.layers(), 1);
+        let color = |format, clear| {
+            OutputColor::Image(ImageOptions {
+                kind,
+                levels: 1,
+                format,
+                clear: Some(ClearValue::Color(ClearColor::Sfloat(clear))),
+            })
+        };
+        plan.define_pass(
+            OIT_TARGET,
+            TargetPlanOutputs {
+                colors: vec![
+                    color(OIT_ACCUM_FORMAT, [0.0; 4]),
+                    color(OIT_REVEALAGE_FORMAT, [1.0; 4]),
+                ],
+                depth: Some(ImageOptions {
+                    kind,
+                    levels: 1,
+                    format: Format::D32Sfloat,
+                    clear: Some(ClearValue::DepthStencil(ClearDepthStencil(1.0, 0))),
+                }),
+            },
+        )?;
+
+        let skinning = self.skinning;
+        let skinning_mode = self.skinning_mode;
+        plan.extend_target(OIT_TARGET, move |ctx| {
+            ctx.add(
+                RenderOrder::Transparent,
+                DrawOitAccumDesc::<B, D>::new()
+                    .with_skinning(skinning)
+                    .with_skinning_mode(skinning_mode)
+                    .builder(),
+            )?;
+            Ok(())
+        });
+        Ok(true)
+    }
 }
 
 impl<B: Backend, D: Base3DPassDef> RenderPlugin<B> for RenderBase3D<D> {
@@ -171,29 +425,84 @@ impl<B: Backend, D: Base3DPassDef> RenderPlugin<B> for RenderBase3D<D> {
         builder: &mut DispatcherBuilder<'a, 'b>,
     ) -> Result<(), Error> {
         builder.add(VisibilitySortingSystem::new(), "visibility_system", &[]);
+        if self.point_shadows {
+            world
+                .entry::<PointShadowSettings>()
+                .or_insert_with(PointShadowSettings::default);
+            builder.add(PointShadowSystem::new(), "point_shadow_system", &[]);
+        }
         Ok(())
     }
 
     fn on_plan(
         &mut self,
         plan: &mut RenderPlan<B>,
-        _factory: &mut Factory<B>,
-        _world: &World,
+        factory: &mut Factory<B>,
+        world: &World,
     ) -> Result<(), Error> {
         let skinning = self.skinning;
+        let skinning_mode = self.skinning_mode;
+        let oit = self.transparency == TransparencyMode::WeightedBlended
+            && self.plan_oit(plan, factory)?;
+        let environment_map = self.environment_map && D::ibl_fragment_shader().is_some();
+        if environment_map {
+            self.plan_ibl(plan)?;
+        }
+        let point_shadows = self.point_shadows;
+        if point_shadows {
+            self.plan_point_shadows(plan, world)?;
+        }
+        let clip_mode = self.clip_mode;
         plan.extend_target(self.target, move |ctx| {
+            // The environment map images come first, then the point shadow atlas.
+            let mut images = if environment_map {
+                (0..ENVIRONMENT_MAP_IMAGES)
+                    .map(|i| ctx.get_image(TargetImage::Color(IBL_TARGET, i)))
+                    .collect::<Result<Vec<_>, _>>()?
+            } else {
+                Vec::new()
+            };
+            if point_shadows {
+                images.push(ctx.get_image(TargetImage::Color(POINT_SHADOW_TARGET, 0))?);
+            }
             ctx.add(
                 RenderOrder::Opaque,
-                DrawBase3DDesc::<B, D>::new()
-                    .with_skinning(skinning)
-                    .builder(),
-            )?;
-            ctx.add(
-                RenderOrder::Transparent,
-                DrawBase3DTransparentDesc::<B, D>::new()
-                    .with_skinning(skinning)
-                    .builder(),
+                images.iter().fold(
+                    DrawBase3DDesc::<B, D>::new()
+                        .with_skinning(skinning)
+                        .with_skinning_mode(skinning_mode)
+                        .with_environment_map(environment_map)
+                        .with_point_shadows(point_shadows)
+                        .with_clip_planes(clip_mode)
+                        .builder(),
+                    |builder, image| builder.with_image(*image),
+                ),
             )?;
+            if oit {
+                let accum = ctx.get_image(TargetImage::Color(OIT_TARGET, 0))?;
+                let revealage = ctx.get_image(TargetImage::Color(OIT_TARGET, 1))?;
+                ctx.add(
+                    RenderOrder::Transparent,
+                    DrawOitCompositeDesc::new()
+                        .builder()
+                        .with_image(accum)
+                        .with_image(revealage),
+                )?;
+            } else {
+                ctx.add(
+                    RenderOrder::Transparent,
+                    images.iter().fold(
+                        DrawBase3DTransparentDesc::<B, D>::new()
+                            .with_skinning(skinning)
+                            .with_skinning_mode(skinning_mode)
+                            .with_environment_map(environment_map)
+                            .with_point_shadows(point_shadows)
+                            .with_clip_planes(clip_mode)
+                            .builder(),
+                        |builder, image| builder.with_image(*image),
+                    ),
+                )?;
+            }
             Ok(())
         });
         Ok(())
@@ -247,6 +556,93 @@ impl<B: Backend> RenderPlugin<B> for RenderFlat2D {
     }
 }
 
+/// A `RenderPlugin` for forward rendering of 3d objects and 2d sprites, drawing transparent
+/// meshes and sprites interleaved back to front.
+///
+/// Use it instead of `RenderBase3D` together with `RenderFlat2D` when transparent meshes and
+/// sprites overlap in depth, e.g. particles drawn through glass. Generic over 3d pass rendering
+/// method.
+#[derive(derivative::Derivative)]
+#[derivative(Default(bound = ""), Debug(bound = ""))]
+pub struct RenderMixed<D: Base3DPassDef> {
+    target: Target,
+    skinning: bool,
+    skinning_mode: SkinningMode,
+    marker: std::marker::PhantomData<D>,
+}
+
+impl<D: Base3DPassDef> RenderMixed<D> {
+    /// Set target to which 3d meshes and 2d sprites will be rendered.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Enable rendering for skinned meshes.
+    ///
+    /// NOTE: You must register `VertexSkinningBundle` yourself.
+    pub fn with_skinning(mut self) -> Self {
+        self.skinning = true;
+        self
+    }
+
+    /// Select how the joints of skinned meshes are blended, linear blend skinning by default.
+    pub fn with_skinning_mode(mut self, mode: SkinningMode) -> Self {
+        self.skinning_mode = mode;
+        self
+    }
+}
+
+impl<B: Backend, D: Base3DPassDef> RenderPlugin<B> for RenderMixed<D> {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        builder.add(VisibilitySortingSystem::new(), "visibility_system", &[]);
+        builder.add(
+            SpriteVisibilitySortingSystem::new(),
+            "sprite_visibility_system",
+            &[],
+        );
+        builder.add(
+            TransparentOrderSystem::new(),
+            "transparent_order_system",
+            &["visibility_system", "sprite_visibility_system"],
+        );
+        Ok(())
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        let skinning = self.skinning;
+        let skinning_mode = self.skinning_mode;
+        plan.extend_target(self.target, move |ctx| {
+            ctx.add(
+                RenderOrder::Opaque,
+                DrawBase3DDesc::<B, D>::new()
+                    .with_skinning(skinning)
+                    .with_skinning_mode(skinning_mode)
+                    .builder(),
+            )?;
+            ctx.add(RenderOrder::Opaque, DrawFlat2DDesc::new().builder())?;
+            ctx.add(
+                RenderOrder::Transparent,
+                DrawMixedTransparentDesc::<B, D>::new()
+                    .with_skinning(skinning)
+                    .with_skinning_mode(skinning_mode)
+                    .builder(),
+            )?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
 /// A [RenderPlugin] for drawing debug lines.
 /// Use with [debug_drawing::DebugLines] resource or [debug_drawing::DebugLinesComponent].
 #[derive(Default, Debug)]
@@ -280,6 +676,664 @@ impl<B: Backend> RenderPlugin<B> for RenderDebugLines {
     }
 }
 
+/// Render target holding the mask of outlined meshes.
+const OUTLINE_TARGET: Target = Target::Custom("outline");
+
+/// A [RenderPlugin] for drawing colored outlines around entities with an `Outlined` component.
+///
+/// The outlined meshes are drawn into an extra target sized after the target, so the plugin
+/// defining the target must be added before this one.
+#[derive(Debug)]
+pub struct RenderOutline {
+    target: Target,
+    skinning: bool,
+    skinning_mode: SkinningMode,
+    occluded: bool,
+}
+
+impl Default for RenderOutline {
+    fn default() -> Self {
+        Self {
+            target: Default::default(),
+            skinning: false,
+            skinning_mode: SkinningMode::Linear,
+            occluded: true,
+        }
+    }
+}
+
+impl RenderOutline {
+    /// Set target over which outlines will be rendered.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Enable outlines around skinned meshes.
+    ///
+    /// NOTE: You must register `VertexSkinningBundle` yourself.
+    pub fn with_skinning(mut self) -> Self {
+        self.skinning = true;
+        self
+    }
+
+    /// Select how the joints of skinned meshes are blended, which should match the 3D pass.
+    pub fn with_skinning_mode(mut self, mode: SkinningMode) -> Self {
+        self.skinning_mode = mode;
+        self
+    }
+
+    /// Select whether outlines around occluded parts of meshes are drawn, with the
+    /// `occluded_opacity` of their `Outlined` component. Enabled by default.
+    pub fn with_occluded(mut self, occluded: bool) -> Self {
+        self.occluded = occluded;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderOutline {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        _builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        world.register::<Outlined>();
+        Ok(())
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        let metadata = match plan.target_metadata(self.target, factory) {
+            Some(metadata) => metadata,
+            None => {
+                log::warn!(
+                    "Outputs of {:?} must be defined before outlines, outlines are disabled.",
+                    self.target
+                );
+                return Ok(());
+            }
+        };
+
+        let kind = Kind::D2(metadata.width(), metadata.height(), metadata.layers(), 1);
+        let color = |format| {
+            OutputColor::Image(ImageOptions {
+                kind,
+                levels: 1,
+                format,
+                clear: Some(ClearValue::Color(ClearColor::Sfloat([0.0; 4]))),
+            })
+        };
+        plan.define_pass(
+            OUTLINE_TARGET,
+            TargetPlanOutputs {
+                colors: vec![color(OUTLINE_COLOR_FORMAT), color(OUTLINE_DATA_FORMAT)],
+                depth: Some(ImageOptions {
+                    kind,
+                    levels: 1,
+                    format: Format::D32Sfloat,
+                    clear: Some(ClearValue::DepthStencil(ClearDepthStencil(1.0, 0))),
+                }),
+            },
+        )?;
+
+        let skinning = self.skinning;
+        let skinning_mode = self.skinning_mode;
+        plan.extend_target(OUTLINE_TARGET, move |ctx| {
+            ctx.add(
+                RenderOrder::Opaque,
+                DrawOutlineMaskDesc::<B>::new()
+                    .with_skinning(skinning)
+                    .with_skinning_mode(skinning_mode)
+                    .builder(),
+            )?;
+            Ok(())
+        });
+
+        let occluded = self.occluded;
+        plan.extend_target(self.target, move |ctx| {
+            let color = ctx.get_image(TargetImage::Color(OUTLINE_TARGET, 0))?;
+            let data = ctx.get_image(TargetImage::Color(OUTLINE_TARGET, 1))?;
+            ctx.add(
+                RenderOrder::AfterTransparent,
+                DrawOutlineDesc::new()
+                    .with_occluded(occluded)
+                    .builder()
+                    .with_image(color)
+                    .with_image(data),
+            )?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+/// Render target holding the surfaces receiving decals.
+const DECAL_RECEIVER_TARGET: Target = Target::Custom("decal_receivers");
+
+/// A [RenderPlugin] for projecting the `Decal` components onto the opaque meshes of the scene.
+///
+/// The receiving meshes are drawn again into an extra target sized after the target, so the
+/// plugin defining the target must be added before this one.
+#[derive(Debug)]
+pub struct RenderDecals {
+    target: Target,
+    skinning: bool,
+    skinning_mode: SkinningMode,
+}
+
+impl Default for RenderDecals {
+    fn default() -> Self {
+        Self {
+            target: Default::default(),
+            skinning: false,
+            skinning_mode: SkinningMode::Linear,
+        }
+    }
+}
+
+impl RenderDecals {
+    /// Set target onto which decals will be rendered.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Enable decals on skinned meshes, for the decals with `on_skinned` set.
+    ///
+    /// NOTE: You must register `VertexSkinningBundle` yourself.
+    pub fn with_skinning(mut self) -> Self {
+        self.skinning = true;
+        self
+    }
+
+    /// Select how the joints of skinned meshes are blended, which should match the 3D pass.
+    pub fn with_skinning_mode(mut self, mode: SkinningMode) -> Self {
+        self.skinning_mode = mode;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderDecals {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        _builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        world.register::<Decal>();
+        Ok(())
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        let metadata = match plan.target_metadata(self.target, factory) {
+            Some(metadata) => metadata,
+            None => {
+                log::warn!(
+                    "Outputs of {:?} must be defined before decals, decals are disabled.",
+                    self.target
+                );
+                return Ok(());
+            }
+        };
+
+        let kind = Kind::D2(metadata.width(), metadata.height(), metadata.layers(), 1);
+        plan.define_pass(
+            DECAL_RECEIVER_TARGET,
+            TargetPlanOutputs {
+                colors: vec![
+                    // Pixels without receivers are at the far plane.
+                    OutputColor::Image(ImageOptions {
+                        kind,
+                        levels: 1,
+                        format: DECAL_NORMAL_DEPTH_FORMAT,
+                        clear: Some(ClearValue::Color(ClearColor::Sfloat([0.0, 0.0, 0.0, 1.0]))),
+                    }),
+                    OutputColor::Image(ImageOptions {
+                        kind,
+                        levels: 1,
+                        format: DECAL_RECEIVER_FORMAT,
+                        clear: Some(ClearValue::Color(ClearColor::Uint([0; 4]))),
+                    }),
+                ],
+                depth: Some(ImageOptions {
+                    kind,
+                    levels: 1,
+                    format: Format::D32Sfloat,
+                    clear: Some(ClearValue::DepthStencil(ClearDepthStencil(1.0, 0))),
+                }),
+            },
+        )?;
+
+        let skinning = self.skinning;
+        let skinning_mode = self.skinning_mode;
+        plan.extend_target(DECAL_RECEIVER_TARGET, move |ctx| {
+            ctx.add(
+                RenderOrder::Opaque,
+                DrawDecalReceiversDesc::<B>::new()
+                    .with_skinning(skinning)
+                    .with_skinning_mode(skinning_mode)
+                    .builder(),
+            )?;
+            Ok(())
+        });
+
+        plan.extend_target(self.target, move |ctx| {
+            let normal_depth = ctx.get_image(TargetImage::Color(DECAL_RECEIVER_TARGET, 0))?;
+            let receiver = ctx.get_image(TargetImage::Color(DECAL_RECEIVER_TARGET, 1))?;
+            ctx.add(
+                RenderOrder::AfterOpaque,
+                DrawDecalDesc::new()
+                    .builder()
+                    .with_image(normal_depth)
+                    .with_image(receiver),
+            )?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+/// A [RenderPlugin] for drawing the `Terrain` components of the scene.
+#[derive(Default, Debug)]
+pub struct RenderTerrain {
+    target: Target,
+}
+
+impl RenderTerrain {
+    /// Set target to which terrains will be rendered.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderTerrain {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        world.register::<Terrain>();
+        builder.add(TerrainSystem::new(), "terrain_system", &[]);
+        Ok(())
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        plan.extend_target(self.target, |ctx| {
+            ctx.add(RenderOrder::Opaque, DrawTerrainDesc::new().builder())?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+/// Render target holding the scene mirrored about the water.
+const WATER_REFLECTION_TARGET: Target = Target::Custom("water_reflection");
+/// Render target holding the scene below the water.
+const WATER_REFRACTION_TARGET: Target = Target::Custom("water_refraction");
+
+/// A [RenderPlugin] for drawing the `WaterPlane` components of the scene, reflecting the opaque
+/// meshes above the water and refracting the ones below it.
+///
+/// Both scenes are drawn again for the water, lower their resolution with `with_reflection_scale`
+/// or skip the refraction with `with_refraction` on weak GPUs.
+#[derive(Debug)]
+pub struct RenderWater {
+    target: Target,
+    reflection_scale: f32,
+    refraction: bool,
+    sky_color: Srgb,
+}
+
+impl Default for RenderWater {
+    fn default() -> Self {
+        Self {
+            target: Default::default(),
+            reflection_scale: 0.5,
+            refraction: true,
+            sky_color: Srgb::new(0.75, 1.0, 1.0),
+        }
+    }
+}
+
+impl RenderWater {
+    /// Set target to which water will be rendered.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Set the resolution of the reflection and refraction images, relative to the target.
+    pub fn with_reflection_scale(mut self, scale: f32) -> Self {
+        self.reflection_scale = scale;
+        self
+    }
+
+    /// Set whether the scene below the water is drawn, or replaced by its color.
+    pub fn with_refraction(mut self, refraction: bool) -> Self {
+        self.refraction = refraction;
+        self
+    }
+
+    /// Set the color reflected where no mesh is above the water.
+    pub fn with_sky_color(mut self, color: Srgb) -> Self {
+        self.sky_color = color;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderWater {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        _builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        world.register::<WaterPlane>();
+        Ok(())
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        let metadata = match plan.target_metadata(self.target, factory) {
+            Some(metadata) => metadata,
+            None => {
+                log::warn!(
+                    "Outputs of {:?} must be defined before water, water is disabled.",
+                    self.target
+                );
+                return Ok(());
+            }
+        };
+
+        let scale = |size: u32| ((size as f32 * self.reflection_scale) as u32).max(1);
+        let kind = Kind::D2(scale(metadata.width()), scale(metadata.height()), 1, 1);
+        let scene_outputs = |clear: ClearColor| TargetPlanOutputs {
+            colors: vec![OutputColor::Image(ImageOptions {
+                kind,
+                levels: 1,
+                format: Format::Rgba16Sfloat,
+                clear: Some(ClearValue::Color(clear)),
+            })],
+            depth: Some(ImageOptions {
+                kind,
+                levels: 1,
+                format: Format::D32Sfloat,
+                clear: Some(ClearValue::DepthStencil(ClearDepthStencil(1.0, 0))),
+            }),
+        };
+
+        let (r, g, b) = self.sky_color.into_components();
+        plan.define_pass(
+            WATER_REFLECTION_TARGET,
+            scene_outputs(ClearColor::Sfloat([r, g, b, 1.0])),
+        )?;
+        plan.extend_target(WATER_REFLECTION_TARGET, |ctx| {
+            ctx.add(
+                RenderOrder::Opaque,
+                DrawWaterSceneDesc::new(WaterScene::Reflection).builder(),
+            )?;
+            Ok(())
+        });
+
+        if self.refraction {
+            // Pixels without meshes below the water are far away, showing its color.
+            plan.define_pass(
+                WATER_REFRACTION_TARGET,
+                scene_outputs(ClearColor::Sfloat([0.0, 0.0, 0.0, 65000.0])),
+            )?;
+            plan.extend_target(WATER_REFRACTION_TARGET, |ctx| {
+                ctx.add(
+                    RenderOrder::Opaque,
+                    DrawWaterSceneDesc::new(WaterScene::Refraction).builder(),
+                )?;
+                Ok(())
+            });
+        }
+
+        let refraction = self.refraction;
+        plan.extend_target(self.target, move |ctx| {
+            let mut builder = DrawWaterDesc::new()
+                .with_refraction(refraction)
+                .builder()
+                .with_image(ctx.get_image(TargetImage::Color(WATER_REFLECTION_TARGET, 0))?);
+            if refraction {
+                builder = builder
+                    .with_image(ctx.get_image(TargetImage::Color(WATER_REFRACTION_TARGET, 0))?);
+            }
+            ctx.add(RenderOrder::Transparent, builder)?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+/// A [RenderPlugin] fading the target to the color of the `ScreenFade` resource, for example
+/// during transitions between states.
+#[derive(Default, Debug)]
+pub struct RenderScreenFade {
+    target: Target,
+}
+
+impl RenderScreenFade {
+    /// Set target which will be faded.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderScreenFade {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        _builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        world
+            .entry::<ScreenFade>()
+            .or_insert_with(ScreenFade::default);
+        Ok(())
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        plan.extend_target(self.target, |ctx| {
+            ctx.add(
+                RenderOrder::DisplayPostEffects,
+                DrawScreenFadeDesc::screen_fade().builder(),
+            )?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+/// A [RenderPlugin] drawing the first color image of a source target graded with the
+/// `ColorGradeSettings` resource over the target.
+///
+/// The source target must be defined by another plugin, with the size and format of the target.
+#[derive(Debug)]
+pub struct RenderColorGrade {
+    target: Target,
+    source: Target,
+}
+
+impl RenderColorGrade {
+    /// Create a color grade of the `source` target.
+    pub fn new(source: Target) -> Self {
+        Self {
+            target: Default::default(),
+            source,
+        }
+    }
+
+    /// Set target to which the graded image will be rendered.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderColorGrade {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        _builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        world
+            .entry::<ColorGradeSettings>()
+            .or_insert_with(ColorGradeSettings::default);
+        Ok(())
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        let source = self.source;
+        plan.extend_target(self.target, move |ctx| {
+            let image = ctx.get_image(TargetImage::Color(source, 0))?;
+            ctx.add(
+                RenderOrder::BeforeOpaque,
+                DrawColorGradeDesc::new().builder().with_image(image),
+            )?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+/// Render target holding the half resolution blur of the depth of field.
+const DOF_BLUR_TARGET: Target = Target::Custom("dof_blur");
+
+/// A [RenderPlugin] drawing the `source` target over the target with depth of field, following
+/// the `DofSettings` resource or the `DofSettings` component of the active camera.
+///
+/// The `source` target must have a color output and a depth output.
+#[derive(Debug)]
+pub struct RenderDepthOfField {
+    target: Target,
+    source: Target,
+}
+
+impl RenderDepthOfField {
+    /// Create a depth of field of the `source` target.
+    pub fn new(source: Target) -> Self {
+        Self {
+            target: Default::default(),
+            source,
+        }
+    }
+
+    /// Set target to which the image will be rendered.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderDepthOfField {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        _builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        world.register::<DofSettings>();
+        world
+            .entry::<DofSettings>()
+            .or_insert_with(DofSettings::default);
+        Ok(())
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        let metadata = match plan.target_metadata(self.source, factory) {
+            Some(metadata) => metadata,
+            None => {
+                log::warn!(
+                    "Outputs of {:?} must be defined before depth of field, depth of field is \
+                     disabled.",
+                    self.source
+                );
+                return Ok(());
+            }
+        };
+
+        let kind = Kind::D2(
+            (metadata.width() / 2).max(1),
+            (metadata.height() / 2).max(1),
+            1,
+            1,
+        );
+        plan.define_pass(
+            DOF_BLUR_TARGET,
+            TargetPlanOutputs {
+                colors: vec![OutputColor::Image(ImageOptions {
+                    kind,
+                    levels: 1,
+                    format: Format::Rgba16Sfloat,
+                    clear: None,
+                })],
+                depth: None,
+            },
+        )?;
+
+        let source = self.source;
+        plan.extend_target(DOF_BLUR_TARGET, move |ctx| {
+            let color = ctx.get_image(TargetImage::Color(source, 0))?;
+            let depth = ctx.get_image(TargetImage::Depth(source))?;
+            ctx.add(
+                RenderOrder::BeforeOpaque,
+                DrawDofBlurDesc::new()
+                    .builder()
+                    .with_image(color)
+                    .with_image(depth),
+            )?;
+            Ok(())
+        });
+
+        plan.extend_target(self.target, move |ctx| {
+            let sharp = ctx.get_image(TargetImage::Color(source, 0))?;
+            let blurred = ctx.get_image(TargetImage::Color(DOF_BLUR_TARGET, 0))?;
+            ctx.add(
+                RenderOrder::BeforeOpaque,
+                DrawDepthOfFieldDesc::new()
+                    .builder()
+                    .with_image(sharp)
+                    .with_image(blurred),
+            )?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
 /// RenderPlugin for rendering skyboxes.
 #[derive(Default, Debug)]
 pub struct RenderSkybox {
@@ -301,6 +1355,16 @@ impl RenderSkybox {
         self.target = target;
         self
     }
+
+    /// Returns the ambient light cast by the skybox, to be inserted as a resource so that the
+    /// ambient lighting matches the sky.
+    pub fn hemisphere_ambient(&self) -> HemisphereAmbient {
+        let (nadir, zenith) = match self.colors {
+            Some(colors) => colors,
+            None => DrawSkyboxDesc::new().colors(),
+        };
+        HemisphereAmbient::from_gradient(nadir, zenith)
+    }
 }
 
 impl<B: Backend> RenderPlugin<B> for RenderSkybox {
@@ -1,9 +1,14 @@
 //! GPU POD data types.
 use crate::{
+    decal::Decal,
+    layers::RenderLayers,
     mtl,
-    resources::Tint as TintComponent,
+    outline::Outlined,
+    resources::{BokehShape, ColorGradeSettings, DofSettings, Tint as TintComponent},
     sprite::{SpriteRender, SpriteSheet},
+    terrain::{Terrain, TerrainChunk},
     types::Texture,
+    water::WaterPlane,
 };
 use amethyst_assets::{AssetStorage, Handle};
 use amethyst_core::{
@@ -48,6 +53,7 @@ impl TextureOffset {
 ///    uniform mat4 proj;
 ///    uniform mat4 view;
 ///    uniform mat4 proj_view;
+///    uniform mat4 clip_planes;
 /// };
 /// ```
 #[derive(Clone, Copy, Debug, AsStd140)]
@@ -59,6 +65,9 @@ pub struct ViewArgs {
     pub view: mat4,
     /// Premultiplied Proj-View matrix
     pub proj_view: mat4,
+    /// World space `ClipPlanes` as columns, so that `vec4(position, 1.0) * clip_planes` are the
+    /// distances to them. Planes which don't clip are `(0, 0, 0, 1)`.
+    pub clip_planes: mat4,
 }
 
 /// Tint
@@ -166,12 +175,501 @@ impl SkinnedVertexArgs {
     }
 }
 
+/// Instance-rate vertex arguments of outlined meshes.
+/// ```glsl,ignore
+///  mat4 model;
+///  vec4 outline_color;
+///  vec2 outline; // thickness, occluded opacity
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[repr(C, align(4))]
+pub struct OutlineArgs {
+    /// Instance-rate model matrix
+    pub model: mat4,
+    /// Instance-rate outline color
+    pub outline_color: vec4,
+    /// Instance-rate outline thickness and occluded opacity
+    pub outline: vec2,
+}
+
+impl AsVertex for OutlineArgs {
+    fn vertex() -> VertexFormat {
+        VertexFormat::new((
+            Model::vertex(),
+            (Format::Rgba32Sfloat, "outline_color"),
+            (Format::Rg32Sfloat, "outline"),
+        ))
+    }
+}
+
+impl OutlineArgs {
+    /// Populates `OutlineArgs` from the supplied `Transform` and `Outlined` components.
+    #[inline]
+    pub fn from_object_data(transform: &Transform, outlined: &Outlined) -> Self {
+        let model: [[f32; 4]; 4] = convert::<_, Matrix4<f32>>(*transform.global_matrix()).into();
+        OutlineArgs {
+            model: model.into(),
+            outline_color: outlined.color.into_pod(),
+            outline: [outlined.thickness, outlined.occluded_opacity].into(),
+        }
+    }
+}
+
+/// Instance-rate vertex arguments of meshes drawn into a face of a point shadow cube map.
+/// ```glsl,ignore
+///  mat4 model_view;
+///  vec2 depth_range; // near, far
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[repr(C, align(4))]
+pub struct PointShadowArgs {
+    /// Instance-rate model matrix followed by the view matrix of the face
+    pub model_view: mat4,
+    /// Instance-rate distances of the near and far planes of the face
+    pub depth_range: vec2,
+}
+
+impl AsVertex for PointShadowArgs {
+    fn vertex() -> VertexFormat {
+        VertexFormat::new((
+            (Format::Rgba32Sfloat, "model_view"),
+            (Format::Rgba32Sfloat, "model_view"),
+            (Format::Rgba32Sfloat, "model_view"),
+            (Format::Rgba32Sfloat, "model_view"),
+            (Format::Rg32Sfloat, "depth_range"),
+        ))
+    }
+}
+
+/// Skinned instance-rate vertex arguments of outlined meshes.
+/// ```glsl,ignore
+///  mat4 model;
+///  vec4 outline_color;
+///  vec2 outline; // thickness, occluded opacity
+///  uint joints_offset;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[repr(C, packed)]
+pub struct SkinnedOutlineArgs {
+    /// Instance-rate model matrix
+    pub model: mat4,
+    /// Instance-rate outline color
+    pub outline_color: vec4,
+    /// Instance-rate outline thickness and occluded opacity
+    pub outline: vec2,
+    /// Instance-rate joint offset as `u32`
+    pub joints_offset: u32,
+}
+
+impl AsVertex for SkinnedOutlineArgs {
+    fn vertex() -> VertexFormat {
+        VertexFormat::new((OutlineArgs::vertex(), JointsOffset::vertex()))
+    }
+}
+
+impl SkinnedOutlineArgs {
+    /// Populates `SkinnedOutlineArgs` from the supplied `Transform` and `Outlined` components.
+    #[inline]
+    pub fn from_object_data(
+        transform: &Transform,
+        outlined: &Outlined,
+        joints_offset: u32,
+    ) -> Self {
+        let args = OutlineArgs::from_object_data(transform, outlined);
+        SkinnedOutlineArgs {
+            model: args.model,
+            outline_color: args.outline_color,
+            outline: args.outline,
+            joints_offset,
+        }
+    }
+}
+
+/// Flag of the receivers written by skinned meshes, see `DecalReceiverArgs`.
+pub const DECAL_RECEIVER_SKINNED: u32 = 1;
+
+/// Instance-rate vertex arguments of meshes receiving decals.
+/// ```glsl,ignore
+///  mat4 model;
+///  uvec2 receiver; // render layers, flags
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[repr(C, align(4))]
+pub struct DecalReceiverArgs {
+    /// Instance-rate model matrix
+    pub model: mat4,
+    /// Instance-rate render layers and `DECAL_RECEIVER_SKINNED` flag of the mesh
+    pub receiver: uvec2,
+}
+
+impl AsVertex for DecalReceiverArgs {
+    fn vertex() -> VertexFormat {
+        VertexFormat::new((Model::vertex(), (Format::Rg32Uint, "receiver")))
+    }
+}
+
+impl DecalReceiverArgs {
+    /// Populates `DecalReceiverArgs` from the supplied `Transform` and `RenderLayers`.
+    #[inline]
+    pub fn from_object_data(transform: &Transform, layers: RenderLayers, flags: u32) -> Self {
+        let model: [[f32; 4]; 4] = convert::<_, Matrix4<f32>>(*transform.global_matrix()).into();
+        DecalReceiverArgs {
+            model: model.into(),
+            receiver: [layers.0, flags].into(),
+        }
+    }
+}
+
+/// Skinned instance-rate vertex arguments of meshes receiving decals.
+/// ```glsl,ignore
+///  mat4 model;
+///  uvec2 receiver; // render layers, flags
+///  uint joints_offset;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[repr(C, packed)]
+pub struct SkinnedDecalReceiverArgs {
+    /// Instance-rate model matrix
+    pub model: mat4,
+    /// Instance-rate render layers and `DECAL_RECEIVER_SKINNED` flag of the mesh
+    pub receiver: uvec2,
+    /// Instance-rate joint offset as `u32`
+    pub joints_offset: u32,
+}
+
+impl AsVertex for SkinnedDecalReceiverArgs {
+    fn vertex() -> VertexFormat {
+        VertexFormat::new((DecalReceiverArgs::vertex(), JointsOffset::vertex()))
+    }
+}
+
+impl SkinnedDecalReceiverArgs {
+    /// Populates `SkinnedDecalReceiverArgs` from the supplied `Transform` and `RenderLayers`.
+    #[inline]
+    pub fn from_object_data(
+        transform: &Transform,
+        layers: RenderLayers,
+        joints_offset: u32,
+    ) -> Self {
+        let args = DecalReceiverArgs::from_object_data(transform, layers, DECAL_RECEIVER_SKINNED);
+        SkinnedDecalReceiverArgs {
+            model: args.model,
+            receiver: args.receiver,
+            joints_offset,
+        }
+    }
+}
+
+/// Instance-rate vertex arguments of decal boxes.
+/// ```glsl,ignore
+///  mat4 model; // scaled to the box
+///  float opacity;
+///  uvec2 receivers; // render layers, allowed flags
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[repr(C, align(4))]
+pub struct DecalArgs {
+    /// Instance-rate matrix of the unit cube to the decal box
+    pub model: mat4,
+    /// Instance-rate opacity
+    pub opacity: float,
+    /// Instance-rate render layers and receiver flags accepted by the decal
+    pub receivers: uvec2,
+}
+
+impl AsVertex for DecalArgs {
+    fn vertex() -> VertexFormat {
+        VertexFormat::new((
+            Model::vertex(),
+            (Format::R32Sfloat, "opacity"),
+            (Format::Rg32Uint, "receivers"),
+        ))
+    }
+}
+
+impl DecalArgs {
+    /// Populates `DecalArgs` from the supplied `Transform` and `Decal` components.
+    #[inline]
+    pub fn from_object_data(transform: &Transform, decal: &Decal) -> Self {
+        let model: [[f32; 4]; 4] = (convert::<_, Matrix4<f32>>(*transform.global_matrix())
+            * Matrix4::new_nonuniform_scaling(&(decal.extents * 2.0)))
+        .into();
+        DecalArgs {
+            model: model.into(),
+            opacity: decal.opacity,
+            receivers: [
+                decal.layers.0,
+                if decal.on_skinned {
+                    DECAL_RECEIVER_SKINNED
+                } else {
+                    0
+                },
+            ]
+            .into(),
+        }
+    }
+}
+
+/// Instance-rate vertex arguments of terrain chunks.
+/// ```glsl,ignore
+///  mat4 model;
+///  vec3 chunk; // heightmap offset, size
+///  vec4 terrain; // size, max height, skirt depth
+///  vec4 tiling;
+///  uint layer_count;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[repr(C, align(4))]
+pub struct TerrainArgs {
+    /// Instance-rate model matrix of the terrain
+    pub model: mat4,
+    /// Instance-rate heightmap coordinates of the corner of the chunk, and its size
+    pub chunk: vec3,
+    /// Instance-rate size of the terrain along x and z, maximum height and skirt depth
+    pub terrain: vec4,
+    /// Instance-rate size of a repetition of the texture of each layer
+    pub tiling: vec4,
+    /// Instance-rate number of blended layers
+    pub layer_count: u32,
+}
+
+impl AsVertex for TerrainArgs {
+    fn vertex() -> VertexFormat {
+        VertexFormat::new((
+            Model::vertex(),
+            (Format::Rgb32Sfloat, "chunk"),
+            (Format::Rgba32Sfloat, "terrain"),
+            (Format::Rgba32Sfloat, "tiling"),
+            (Format::R32Uint, "layer_count"),
+        ))
+    }
+}
+
+impl TerrainArgs {
+    /// Populates `TerrainArgs` of a chunk from the supplied `Transform` and `Terrain`.
+    #[inline]
+    pub fn from_object_data(
+        transform: &Transform,
+        terrain: &Terrain,
+        chunk: &TerrainChunk,
+    ) -> Self {
+        let model: [[f32; 4]; 4] = convert::<_, Matrix4<f32>>(*transform.global_matrix()).into();
+        let mut tiling = [1.0; 4];
+        for (tiling, layer) in tiling.iter_mut().zip(&terrain.layers) {
+            *tiling = layer.tiling;
+        }
+        let layer_count = if terrain.splat.is_some() {
+            terrain.layers.len()
+        } else {
+            terrain.layers.len().min(1)
+        };
+        TerrainArgs {
+            model: model.into(),
+            chunk: [chunk.offset.x, chunk.offset.y, chunk.size].into(),
+            terrain: [
+                terrain.size.x,
+                terrain.size.y,
+                terrain.max_height,
+                terrain.skirt_depth,
+            ]
+            .into(),
+            tiling: tiling.into(),
+            layer_count: layer_count as u32,
+        }
+    }
+}
+
+/// Instance-rate vertex arguments of water planes.
+/// ```glsl,ignore
+///  mat4 model;
+///  vec4 color;
+///  vec4 waves; // normal map offset, scale, strength
+///  vec4 surface; // extents, depth falloff, refraction
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[repr(C, align(4))]
+pub struct WaterArgs {
+    /// Instance-rate model matrix of the plane
+    pub model: mat4,
+    /// Instance-rate color of deep water
+    pub color: vec4,
+    /// Instance-rate scrolling offset of the normal map, its scale and the wave strength
+    pub waves: vec4,
+    /// Instance-rate half size of the plane, depth falloff and 1 if the refraction is drawn
+    pub surface: vec4,
+}
+
+impl AsVertex for WaterArgs {
+    fn vertex() -> VertexFormat {
+        VertexFormat::new((
+            Model::vertex(),
+            (Format::Rgba32Sfloat, "color"),
+            (Format::Rgba32Sfloat, "waves"),
+            (Format::Rgba32Sfloat, "surface"),
+        ))
+    }
+}
+
+impl WaterArgs {
+    /// Populates `WaterArgs` from the supplied `Transform` and `WaterPlane`, scrolling its waves
+    /// to `time` in seconds.
+    #[inline]
+    pub fn from_object_data(
+        transform: &Transform,
+        water: &WaterPlane,
+        time: f64,
+        refraction: bool,
+    ) -> Self {
+        let model: [[f32; 4]; 4] = convert::<_, Matrix4<f32>>(*transform.global_matrix()).into();
+        // Wrapped to keep the precision of the offset over long sessions.
+        let offset = |speed: f32| (f64::from(speed) * time).fract() as f32;
+        WaterArgs {
+            model: model.into(),
+            color: water.color.into_pod(),
+            waves: [
+                offset(water.wave_speed.x),
+                offset(water.wave_speed.y),
+                water.wave_scale,
+                water.wave_strength,
+            ]
+            .into(),
+            surface: [
+                water.extents.x,
+                water.extents.y,
+                water.depth_falloff,
+                if refraction { 1.0 } else { 0.0 },
+            ]
+            .into(),
+        }
+    }
+}
+
+/// Color grade uniform
+/// ```glsl,ignore
+/// uniform ColorGradeArgs {
+///    vec4 vignette_color;
+///    float saturation;
+///    float contrast;
+///    float lift;
+///    float gamma;
+///    float gain;
+///    float lut_strength;
+///    float vignette_radius;
+///    float vignette_smoothness;
+///    bool identity;
+/// };
+/// ```
+#[derive(Clone, Copy, Debug, AsStd140)]
+pub struct ColorGradeArgs {
+    /// Color of the vignette, its alpha the vignette strength
+    pub vignette_color: vec4,
+    /// Saturation scale
+    pub saturation: float,
+    /// Contrast scale around middle gray
+    pub contrast: float,
+    /// Lift of the darks
+    pub lift: float,
+    /// Gamma of the midtones
+    pub gamma: float,
+    /// Gain of the highlights
+    pub gain: float,
+    /// Blend factor of the LUT, 0 when no LUT is bound
+    pub lut_strength: float,
+    /// Distance from the center where the vignette starts
+    pub vignette_radius: float,
+    /// Distance over which the vignette fades in
+    pub vignette_smoothness: float,
+    /// Copy the image unchanged
+    pub identity: boolean,
+}
+
+impl ColorGradeArgs {
+    /// Populates `ColorGradeArgs` from the `ColorGradeSettings` resource, `lut_bound` telling if
+    /// its LUT is loaded and bound.
+    pub fn from_settings(settings: &ColorGradeSettings, lut_bound: bool) -> Self {
+        let vignette = settings.vignette.unwrap_or_default();
+        let vignette_strength = settings.vignette.map_or(0.0, |v| v.strength);
+        let (r, g, b) = vignette.color.into_components();
+        ColorGradeArgs {
+            vignette_color: [r, g, b, vignette_strength].into(),
+            saturation: settings.saturation,
+            contrast: settings.contrast,
+            lift: settings.lift,
+            gamma: settings.gamma,
+            gain: settings.gain,
+            lut_strength: if lut_bound && settings.lut.is_some() {
+                settings.lut_strength
+            } else {
+                0.0
+            },
+            vignette_radius: vignette.radius,
+            vignette_smoothness: vignette.smoothness,
+            identity: settings.is_identity(lut_bound).into(),
+        }
+    }
+}
+
+/// Depth of field uniform
+/// ```glsl,ignore
+/// uniform DofArgs {
+///    mat4 inverse_proj;
+///    float focus_distance;
+///    float aperture;
+///    float max_radius;
+///    uint blades;
+///    float rotation;
+/// };
+/// ```
+#[derive(Clone, Copy, Debug, AsStd140)]
+pub struct DofArgs {
+    /// Inverse projection of the camera, reconstructing view positions from depth
+    pub inverse_proj: mat4,
+    /// Distance of the focus from the camera
+    pub focus_distance: float,
+    /// Blur behind the focus relative to the largest blur
+    pub aperture: float,
+    /// Largest blur radius in pixels
+    pub max_radius: float,
+    /// Blades of the polygonal bokeh, 0 for a disk
+    pub blades: uint,
+    /// Rotation of the polygonal bokeh
+    pub rotation: float,
+}
+
+impl DofArgs {
+    /// Populates `DofArgs` from `DofSettings` and the projection of the camera.
+    pub fn from_settings(settings: &DofSettings, proj: &Matrix4<f32>) -> Self {
+        let inverse_proj: [[f32; 4]; 4] =
+            proj.try_inverse().unwrap_or_else(Matrix4::identity).into();
+        let (blades, rotation) = match settings.bokeh {
+            BokehShape::Disk => (0, 0.0),
+            BokehShape::Polygon { blades, rotation } => (blades.max(3), rotation),
+        };
+        DofArgs {
+            inverse_proj: inverse_proj.into(),
+            focus_distance: settings.focus_distance,
+            aperture: settings.aperture,
+            max_radius: settings.max_radius,
+            blades,
+            rotation,
+        }
+    }
+}
+
 /// point light struct
 /// ```glsl,ignore
 /// struct PointLight {
 ///    vec3 position;
 ///    vec3 color;
 ///    float intensity;
+///    int shadow;
+///    float shadow_range;
+///    float shadow_bias;
+///    vec4 shadow_tile;
+///    int attenuation;
+///    float radius;
+///    float smoothness;
+///    vec3 attenuation_coefficients;
 /// };
 /// ```
 #[derive(Clone, Copy, Debug, AsStd140)]
@@ -182,6 +680,23 @@ pub struct PointLight {
     pub color: vec3,
     /// Light intensity (0 - infinity)
     pub intensity: float,
+    /// Row of the light in the point shadow atlas, or -1 without shadows
+    pub shadow: int,
+    /// Distance stored as 1 in the shadow cube map
+    pub shadow_range: float,
+    /// Shadow comparison bias, in world units
+    pub shadow_bias: float,
+    /// Vertical offset of the row, width and height of the drawn part of a face in the atlas,
+    /// and the size of a texel relative to it
+    pub shadow_tile: vec4,
+    /// Attenuation model, see `light::Attenuation::shader_model`
+    pub attenuation: int,
+    /// Distance at which the light is faded out
+    pub radius: float,
+    /// Exponent of the fading to the radius
+    pub smoothness: float,
+    /// Parameters of the attenuation model
+    pub attenuation_coefficients: vec3,
 }
 
 /// directional light struct
@@ -212,6 +727,8 @@ pub struct DirectionalLight {
 ///    float intensity;
 ///    float range;
 ///    float smoothness;
+///    mat4 cookie_projection;
+///    int cookie;
 /// };
 /// ```
 #[derive(Clone, Copy, Debug, AsStd140)]
@@ -230,6 +747,10 @@ pub struct SpotLight {
     pub range: float,
     /// Spotlight smoothness
     pub smoothness: float,
+    /// Projection of the cookie texture from the light
+    pub cookie_projection: mat4,
+    /// Slot of the cookie texture in the environment, -1 without cookie
+    pub cookie: int,
 }
 
 /// Environment Uniform
@@ -240,6 +761,9 @@ pub struct SpotLight {
 ///    int point_light_count;
 ///    int directional_light_count;
 ///    int spot_light_count;
+///    float ibl_intensity;
+///    vec3 ambient_ground_color;
+///    vec3 ambient_up;
 /// };
 /// ```
 #[derive(Clone, Copy, Debug, AsStd140)]
@@ -254,6 +778,12 @@ pub struct Environment {
     pub directional_light_count: int,
     /// Number of spot lights
     pub spot_light_count: int,
+    /// Scale of the image-based ambient lighting, 0 to use the ambient color instead
+    pub ibl_intensity: float,
+    /// Ambient color of surfaces facing down, `ambient_color` being the one of surfaces facing up
+    pub ambient_ground_color: vec3,
+    /// Up direction of the hemisphere ambient
+    pub ambient_up: vec3,
 }
 
 /// Material Uniform
@@ -261,6 +791,9 @@ pub struct Environment {
 /// uniform Material {
 ///    UvOffset uv_offset;
 ///    float alpha_cutoff;
+///    bool unlit;
+///    int lightmap_mode;
+///    bool skip_dynamic_lights;
 /// };
 /// ```
 #[derive(Clone, Copy, Debug, AsStd140)]
@@ -270,6 +803,12 @@ pub struct Material {
     pub uv_offset: TextureOffset,
     /// Material alpha cutoff
     pub alpha_cutoff: float,
+    /// Skip lighting
+    pub unlit: boolean,
+    /// How the lightmap is combined: 0 disabled, 1 multiply, 2 add
+    pub lightmap_mode: int,
+    /// Skip the dynamic lights
+    pub skip_dynamic_lights: boolean,
 }
 
 impl Material {
@@ -278,6 +817,9 @@ impl Material {
         Material {
             uv_offset: TextureOffset::from_offset(&mat.uv_offset),
             alpha_cutoff: mat.alpha_cutoff,
+            unlit: mat.unlit.into(),
+            lightmap_mode: mat.lightmap_mode.shader_mode(),
+            skip_dynamic_lights: mat.skip_dynamic_lights.into(),
         }
     }
 }
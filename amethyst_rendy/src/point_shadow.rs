@@ -0,0 +1,190 @@
+//! Shadows of point lights.
+//!
+//! Point lights with `casts_shadows` render the distance to the closest surface around them into
+//! the six faces of a cube map, stored side by side in a row of a shadow atlas. The atlas holds
+//! `PointShadowSettings::max_lights` rows, the `PointShadowSystem` assigns them each frame to the
+//! shadow casting lights closest to the camera. Lights beyond that budget are unshadowed.
+//!
+//! The cube map of a light is kept between frames, and only rendered again when the light moves,
+//! changes, or gets a row of the atlas. Meshes moving near a static light don't update its
+//! shadows.
+use crate::{
+    camera::{ActiveCamera, Camera},
+    light::{Light, PointLight},
+};
+use amethyst_core::{
+    ecs::prelude::{Entities, Entity, Join, Read, ReadStorage, System, Write},
+    math::{convert, Vector3},
+    Transform,
+};
+use std::cmp::Ordering;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Distance from a light to the near plane of its shadow cube map.
+pub const POINT_SHADOW_NEAR: f32 = 0.05;
+
+/// Resource configuring the shadows of point lights.
+///
+/// `max_lights` and `resolution` size the shadow atlas, changes take effect once the render graph
+/// is rebuilt. The atlas is `6 * resolution` pixels wide, which must not exceed the largest image
+/// size supported by the device.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PointShadowSettings {
+    /// Maximum number of point lights casting shadows in a frame.
+    pub max_lights: u32,
+    /// Largest width and height in pixels of a cube map face.
+    pub resolution: u32,
+    /// Distance subtracted from the distance of a surface to the light before comparing it to the
+    /// shadow map, to avoid self-shadowing.
+    pub bias: f32,
+}
+
+impl Default for PointShadowSettings {
+    fn default() -> Self {
+        Self {
+            max_lights: 4,
+            resolution: 512,
+            bias: 0.05,
+        }
+    }
+}
+
+/// Resource holding the shadow casting point light drawn into each row of the shadow atlas.
+#[derive(Debug, Clone, Default)]
+pub struct PointShadowSlots {
+    slots: Vec<Option<Entity>>,
+}
+
+impl PointShadowSlots {
+    /// Returns the row of the shadow atlas of a light, if it casts shadows this frame.
+    pub fn slot(&self, entity: Entity) -> Option<usize> {
+        self.slots.iter().position(|slot| *slot == Some(entity))
+    }
+
+    /// Iterates over the rows of the shadow atlas and the light drawn into each.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, Option<Entity>)> + '_ {
+        self.slots.iter().cloned().enumerate()
+    }
+}
+
+/// Width and height in pixels of the cube map faces of a light, in an atlas of `tile_size` faces.
+pub fn face_resolution(light: &PointLight, tile_size: u32) -> u32 {
+    light.shadow_resolution.min(tile_size).max(1)
+}
+
+/// Chooses the point lights casting shadows this frame, by distance to the active camera.
+#[derive(Debug, Default)]
+pub struct PointShadowSystem;
+
+impl PointShadowSystem {
+    /// Create new point shadow system
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<'a> System<'a> for PointShadowSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Light>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Camera>,
+        Read<'a, ActiveCamera>,
+        Read<'a, PointShadowSettings>,
+        Write<'a, PointShadowSlots>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, lights, transforms, cameras, active_camera, settings, mut slots): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("point_shadow_system");
+
+        let position = |transform: &Transform| {
+            convert::<_, Vector3<f32>>(transform.global_matrix().column(3).xyz())
+        };
+
+        let camera = active_camera
+            .entity
+            .filter(|entity| cameras.contains(*entity))
+            .and_then(|entity| transforms.get(entity))
+            .or_else(|| {
+                (&cameras, &transforms)
+                    .join()
+                    .next()
+                    .map(|(_, transform)| transform)
+            })
+            .map_or_else(Vector3::zeros, position);
+
+        let candidates = (&entities, &lights, &transforms)
+            .join()
+            .filter_map(|(entity, light, transform)| match light {
+                Light::Point(light) if light.casts_shadows => {
+                    Some((entity, (position(transform) - camera).norm_squared()))
+                }
+                _ => None,
+            })
+            .collect();
+
+        slots.slots = assign_slots(&slots.slots, candidates, settings.max_lights as usize);
+    }
+}
+
+/// Assigns the `max_lights` candidates closest to the camera to slots. Candidates already in a
+/// slot keep it, so their shadows don't have to be drawn again.
+fn assign_slots<T: Copy + PartialEq>(
+    previous: &[Option<T>],
+    mut candidates: Vec<(T, f32)>,
+    max_lights: usize,
+) -> Vec<Option<T>> {
+    candidates.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+    candidates.truncate(max_lights);
+
+    let mut slots = previous
+        .iter()
+        .take(max_lights)
+        .map(|slot| slot.filter(|light| candidates.iter().any(|(c, _)| c == light)))
+        .collect::<Vec<_>>();
+    slots.resize(max_lights, None);
+
+    let mut free = 0;
+    for (light, _) in candidates {
+        if slots.contains(&Some(light)) {
+            continue;
+        }
+        while slots[free].is_some() {
+            free += 1;
+        }
+        slots[free] = Some(light);
+    }
+    slots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closest_lights_get_slots() {
+        let slots = assign_slots(&[], vec![(1, 9.0), (2, 1.0), (3, 4.0)], 2);
+        assert_eq!(slots, vec![Some(2), Some(3)]);
+
+        let slots = assign_slots(&[], vec![(1, 9.0)], 2);
+        assert_eq!(slots, vec![Some(1), None]);
+    }
+
+    #[test]
+    fn selected_lights_keep_their_slot() {
+        let previous = [Some(1), Some(2), Some(3)];
+        // Light 2 is now too far, light 4 takes its slot.
+        let slots = assign_slots(&previous, vec![(1, 5.0), (2, 9.0), (3, 4.0), (4, 1.0)], 3);
+        assert_eq!(slots, vec![Some(1), Some(4), Some(3)]);
+
+        // A smaller budget drops the slots past it.
+        let slots = assign_slots(&previous, vec![(1, 5.0), (3, 4.0)], 1);
+        assert_eq!(slots, vec![Some(3)]);
+    }
+}
@@ -1,9 +1,14 @@
 //! `amethyst` rendering ecs resources
 //!
 
-use amethyst_assets::PrefabData;
-use amethyst_core::ecs::{Component, DenseVecStorage, Entity, Write};
+use crate::types::Texture;
+use amethyst_assets::{Handle, PrefabData};
+use amethyst_core::{
+    ecs::{Component, DenseVecStorage, Entity, Write},
+    math::{Point3, Vector3, Vector4},
+};
 use amethyst_error::Error;
+use palette::Srgb;
 
 /// The ambient color of a scene
 #[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
@@ -31,6 +36,342 @@ impl<'a> PrefabData<'a> for AmbientColor {
     }
 }
 
+/// Ambient light of a scene blended from a sky and a ground color by the direction surfaces face,
+/// replacing the `AmbientColor` when inserted as a resource.
+///
+/// Surfaces facing `up` get the sky color, surfaces facing down the ground color, and vertical
+/// surfaces an even mix of both.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HemisphereAmbient {
+    /// Ambient color of surfaces facing up.
+    #[serde(with = "crate::serde_shim::srgb")]
+    pub sky: Srgb,
+    /// Ambient color of surfaces facing down.
+    #[serde(with = "crate::serde_shim::srgb")]
+    pub ground: Srgb,
+    /// World space direction towards the sky.
+    pub up: Vector3<f32>,
+}
+
+/// Share of the zenith color in the light a skybox gradient casts on a surface facing up.
+///
+/// The cosine weighted average of `smoothstep(-1, 1, y)` over the upper hemisphere.
+const SKY_ZENITH_WEIGHT: f32 = 0.9;
+
+impl HemisphereAmbient {
+    /// Create a hemisphere ambient with the positive y axis up.
+    pub fn new(sky: Srgb, ground: Srgb) -> Self {
+        HemisphereAmbient {
+            sky,
+            ground,
+            up: Vector3::y(),
+        }
+    }
+
+    /// Create the hemisphere ambient cast by a skybox gradient from `nadir` to `zenith`, like the
+    /// one drawn by `RenderSkybox`. See `RenderSkybox::hemisphere_ambient`.
+    pub fn from_gradient(nadir: Srgb, zenith: Srgb) -> Self {
+        let mix = |weight: f32| {
+            Srgb::new(
+                nadir.red + (zenith.red - nadir.red) * weight,
+                nadir.green + (zenith.green - nadir.green) * weight,
+                nadir.blue + (zenith.blue - nadir.blue) * weight,
+            )
+        };
+        Self::new(mix(SKY_ZENITH_WEIGHT), mix(1.0 - SKY_ZENITH_WEIGHT))
+    }
+
+    /// Set the direction towards the sky.
+    pub fn with_up(mut self, up: Vector3<f32>) -> Self {
+        self.up = up;
+        self
+    }
+}
+
+/// Surroundings of a scene lighting its physically based materials, replacing their `AmbientColor`.
+///
+/// Only used by passes with image-based lighting enabled, see
+/// `RenderBase3D::with_environment_map`. Its irradiance and specular maps are baked on the GPU
+/// whenever the source changes, so it can be swapped at runtime.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvironmentMap {
+    /// Source of the environment lighting. Scenes without one keep the constant `AmbientColor`.
+    pub source: Option<EnvironmentSource>,
+    /// Scale applied to the diffuse and specular ambient lighting.
+    pub intensity: f32,
+}
+
+/// Where an `EnvironmentMap` samples the surroundings of a scene.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EnvironmentSource {
+    /// A cubemap texture, built with `ViewKind::Cube` and six layers.
+    Cubemap(Handle<Texture>),
+    /// A gradient from the nadir to the zenith, like the one drawn by `RenderSkybox`.
+    Gradient {
+        /// Color straight below
+        nadir: Srgb,
+        /// Color straight above
+        zenith: Srgb,
+    },
+}
+
+impl Default for EnvironmentMap {
+    fn default() -> Self {
+        EnvironmentMap {
+            source: None,
+            intensity: 1.0,
+        }
+    }
+}
+
+impl EnvironmentMap {
+    /// Create an environment map lit by a cubemap texture.
+    pub fn cubemap(texture: Handle<Texture>) -> Self {
+        EnvironmentMap {
+            source: Some(EnvironmentSource::Cubemap(texture)),
+            ..Default::default()
+        }
+    }
+
+    /// Create an environment map lit by a skybox gradient.
+    pub fn gradient(nadir: Srgb, zenith: Srgb) -> Self {
+        EnvironmentMap {
+            source: Some(EnvironmentSource::Gradient { nadir, zenith }),
+            ..Default::default()
+        }
+    }
+
+    /// Set the scale of the ambient lighting.
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Intensity passed to the shaders, 0 without a source to select the constant ambient color.
+    pub(crate) fn shader_intensity(&self) -> f32 {
+        if self.source.is_some() {
+            self.intensity
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Final color grade applied by `DrawColorGradeDesc` to the whole image.
+///
+/// The scalar controls are applied in linear space in the order lift, gamma, gain, contrast and
+/// saturation, followed by the LUT and the vignette. With the defaults the image is copied
+/// unchanged. Changing any field, including the LUT, takes effect on the next frame.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorGradeSettings {
+    /// Saturation, 0 for grayscale and 1 for unchanged.
+    pub saturation: f32,
+    /// Contrast around middle gray, 1 for unchanged.
+    pub contrast: f32,
+    /// Raises the darks towards white, 0 for unchanged.
+    pub lift: f32,
+    /// Exponent applied to the midtones as `1 / gamma`, 1 for unchanged.
+    pub gamma: f32,
+    /// Scales the highlights, 1 for unchanged.
+    pub gain: f32,
+    /// 3D lookup table stored as a strip of blue slices, see `formats::lut`.
+    pub lut: Option<Handle<Texture>>,
+    /// How much of the `lut` is applied, from 0 for none to 1 for all.
+    pub lut_strength: f32,
+    /// Darkening of the corners of the image.
+    pub vignette: Option<Vignette>,
+}
+
+impl Default for ColorGradeSettings {
+    fn default() -> Self {
+        ColorGradeSettings {
+            saturation: 1.0,
+            contrast: 1.0,
+            lift: 0.0,
+            gamma: 1.0,
+            gain: 1.0,
+            lut: None,
+            lut_strength: 1.0,
+            vignette: None,
+        }
+    }
+}
+
+impl ColorGradeSettings {
+    /// Set the lookup table, applied in full.
+    pub fn with_lut(mut self, lut: Handle<Texture>) -> Self {
+        self.lut = Some(lut);
+        self.lut_strength = 1.0;
+        self
+    }
+
+    /// Set the vignette.
+    pub fn with_vignette(mut self, vignette: Vignette) -> Self {
+        self.vignette = Some(vignette);
+        self
+    }
+
+    /// Returns true if the grade leaves the image unchanged, without a LUT or with `lut_bound`
+    /// false. The image is then copied bit for bit.
+    pub fn is_identity(&self, lut_bound: bool) -> bool {
+        self.saturation == 1.0
+            && self.contrast == 1.0
+            && self.lift == 0.0
+            && self.gamma == 1.0
+            && self.gain == 1.0
+            && (!lut_bound || self.lut.is_none() || self.lut_strength == 0.0)
+            && self.vignette.map_or(true, |v| v.strength == 0.0)
+    }
+}
+
+/// Darkening of the corners of the image towards a color, part of `ColorGradeSettings`.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Vignette {
+    /// Opacity of the vignette color in the corners, 0 to disable the vignette.
+    pub strength: f32,
+    /// Distance from the center where the vignette starts, 1 reaching the corners.
+    pub radius: f32,
+    /// Distance over which the vignette fades in past `radius`.
+    pub smoothness: f32,
+    /// Color of the vignette.
+    #[serde(with = "crate::serde_shim::srgb")]
+    pub color: Srgb,
+}
+
+impl Default for Vignette {
+    fn default() -> Self {
+        Vignette {
+            strength: 0.5,
+            radius: 0.5,
+            smoothness: 0.5,
+            color: Srgb::new(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+/// Depth of field applied by `DrawDepthOfFieldDesc`, blurring the image away from the focus.
+///
+/// Used as a resource, or as a component of the active camera overriding the resource so the
+/// focus can be animated with the camera. Changing any field takes effect on the next frame.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct DofSettings {
+    /// Distance from the camera of the surfaces in focus, in world units.
+    pub focus_distance: f32,
+    /// Blur of the surfaces far behind the focus relative to `max_radius`, from 0 for none to 1.
+    /// Surfaces in front of the focus blur faster, reaching `max_radius` at half the focus
+    /// distance with an aperture of 1.
+    pub aperture: f32,
+    /// Largest radius of the blur, in pixels of the target.
+    pub max_radius: f32,
+    /// Shape of the out of focus highlights.
+    pub bokeh: BokehShape,
+}
+
+impl Default for DofSettings {
+    fn default() -> Self {
+        DofSettings {
+            focus_distance: 10.0,
+            aperture: 0.5,
+            max_radius: 12.0,
+            bokeh: BokehShape::Disk,
+        }
+    }
+}
+
+impl Component for DofSettings {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Shape of the out of focus highlights of `DofSettings`.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum BokehShape {
+    /// Round highlights.
+    Disk,
+    /// Highlights shaped like the polygon of an aperture with `blades` blades, at least 3,
+    /// turned by `rotation` radians.
+    Polygon {
+        /// Number of sides of the polygon.
+        blades: u32,
+        /// Rotation of the polygon.
+        rotation: f32,
+    },
+}
+
+/// Maximal number of `ClipPlanes`.
+pub const MAX_CLIP_PLANES: usize = 4;
+
+/// World space planes clipping the passes built with `ClipMode::Inside` or `ClipMode::Outside`,
+/// e.g. `DrawPbrDesc::with_clip_planes`.
+///
+/// Planes are given as `(n, d)` with `n·x + d = 0` on the plane, and passes with
+/// `ClipMode::Inside` keep the points where `n·x + d >= 0` for every enabled plane.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ClipPlanes {
+    /// The plane equations.
+    pub planes: [Vector4<f32>; MAX_CLIP_PLANES],
+    /// Which of the `planes` clip.
+    pub enabled: [bool; MAX_CLIP_PLANES],
+}
+
+impl ClipPlanes {
+    /// Returns the plane through `point` whose positive side is towards `normal`.
+    pub fn plane(point: &Point3<f32>, normal: &Vector3<f32>) -> Vector4<f32> {
+        let normal = normal.normalize();
+        Vector4::new(normal.x, normal.y, normal.z, -normal.dot(&point.coords))
+    }
+
+    /// Sets and enables the plane at `index`.
+    pub fn with_plane(mut self, index: usize, plane: Vector4<f32>) -> Self {
+        self.set_plane(index, Some(plane));
+        self
+    }
+
+    /// Sets and enables the plane at `index`, or disables it if `None`.
+    pub fn set_plane(&mut self, index: usize, plane: Option<Vector4<f32>>) {
+        self.enabled[index] = plane.is_some();
+        if let Some(plane) = plane {
+            self.planes[index] = plane;
+        }
+    }
+
+    /// Returns the planes clipping with `mode` as the columns of a matrix, disabled ones
+    /// replaced by planes which never clip.
+    pub(crate) fn columns(&self, mode: ClipMode) -> [[f32; 4]; MAX_CLIP_PLANES] {
+        let mut columns = [[0.0, 0.0, 0.0, 1.0]; MAX_CLIP_PLANES];
+        for (column, (plane, enabled)) in columns
+            .iter_mut()
+            .zip(self.planes.iter().zip(&self.enabled))
+        {
+            match mode {
+                ClipMode::Inside if *enabled => *column = (*plane).into(),
+                ClipMode::Outside if *enabled => *column = (-plane).into(),
+                _ => {}
+            }
+        }
+        columns
+    }
+}
+
+/// How a pass is clipped by the `ClipPlanes`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ClipMode {
+    /// The pass ignores the `ClipPlanes`.
+    Disabled,
+    /// The pass keeps the positive side of every enabled plane.
+    Inside,
+    /// The pass keeps the negative side of every enabled plane, which with a single plane is
+    /// exactly what `Inside` clips away.
+    Outside,
+}
+
+impl Default for ClipMode {
+    fn default() -> Self {
+        ClipMode::Disabled
+    }
+}
+
 /// A single object tinting applied in multiplicative mode (modulation)
 #[derive(Clone, Copy, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Tint(#[serde(with = "crate::serde_shim::srgba")] pub palette::Srgba);
@@ -45,3 +386,101 @@ impl Into<[f32; 4]> for Tint {
         [r, g, b, a]
     }
 }
+
+/// Fades the whole target to a color, drawn by the `RenderScreenFade` plugin.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ScreenFade {
+    /// Color the target fades to.
+    #[serde(with = "crate::serde_shim::srgba")]
+    pub color: palette::Srgba,
+    /// How far the target is faded, from 0 for not at all to 1 for fully covered by the color.
+    pub amount: f32,
+}
+
+impl Default for ScreenFade {
+    fn default() -> Self {
+        ScreenFade {
+            color: palette::Srgba::new(0.0, 0.0, 0.0, 1.0),
+            amount: 0.0,
+        }
+    }
+}
+
+/// Settings of the renderer which can be changed at runtime.
+///
+/// The `RenderToWindow` plugin rebuilds the render graph when they change, recreating the
+/// swapchain without recreating the window or the device.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RenderSettings {
+    /// Whether presenting waits for the vertical blank of the display, preventing tearing.
+    pub vsync: bool,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings { vsync: true }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hemisphere_from_gradient_leans_to_facing_pole() {
+        let ambient =
+            HemisphereAmbient::from_gradient(Srgb::new(0.0, 0.0, 0.0), Srgb::new(1.0, 0.5, 0.0));
+        assert!((ambient.sky.red - 0.9).abs() < 1e-6);
+        assert!((ambient.sky.green - 0.45).abs() < 1e-6);
+        assert!((ambient.ground.red - 0.1).abs() < 1e-6);
+        assert_eq!(ambient.up, Vector3::y());
+    }
+
+    #[test]
+    fn environment_map_without_source_keeps_ambient_color() {
+        let map = EnvironmentMap::default().with_intensity(2.0);
+        assert!(map.shader_intensity() == 0.0);
+
+        let map = EnvironmentMap::gradient(Srgb::new(0.0, 0.0, 0.0), Srgb::new(1.0, 1.0, 1.0))
+            .with_intensity(2.0);
+        assert!(map.shader_intensity() == 2.0);
+    }
+
+    #[test]
+    fn default_color_grade_is_identity() {
+        let settings = ColorGradeSettings::default();
+        assert!(settings.is_identity(true));
+
+        let settings = ColorGradeSettings {
+            saturation: 0.5,
+            ..Default::default()
+        };
+        assert!(!settings.is_identity(true));
+
+        let settings = ColorGradeSettings::default().with_vignette(Vignette {
+            strength: 0.0,
+            ..Default::default()
+        });
+        assert!(settings.is_identity(true));
+        assert!(!ColorGradeSettings::default()
+            .with_vignette(Vignette::default())
+            .is_identity(true));
+    }
+
+    #[test]
+    fn clip_modes_keep_complementary_sides() {
+        let plane = ClipPlanes::plane(&Point3::new(0.0, 2.0, 0.0), &Vector3::new(0.0, 3.0, 0.0));
+        let planes = ClipPlanes::default().with_plane(1, plane);
+        let distance = |mode, y: f32| {
+            let column = planes.columns(mode)[1];
+            column[1] * y + column[3]
+        };
+
+        assert!(distance(ClipMode::Inside, 3.0) > 0.0);
+        assert!(distance(ClipMode::Inside, 1.0) < 0.0);
+        assert!(distance(ClipMode::Outside, 3.0) < 0.0);
+        assert!(distance(ClipMode::Outside, 1.0) > 0.0);
+        assert_eq!(planes.columns(ClipMode::Disabled)[1], [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(planes.columns(ClipMode::Inside)[0], [0.0, 0.0, 0.0, 1.0]);
+    }
+}
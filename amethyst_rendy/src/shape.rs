@@ -1,5 +1,8 @@
 //! Basic shape prefabs.
-use crate::types::Mesh;
+use crate::{
+    types::Mesh,
+    vertex::{PosNormTangTex2, PosNormTex2, TexCoord2},
+};
 use amethyst_assets::{AssetStorage, Handle, Loader, PrefabData, Progress, ProgressCounter};
 use amethyst_core::{
     ecs::{
@@ -314,6 +317,12 @@ impl FromInternalVertex for TexCoord {
     }
 }
 
+impl FromInternalVertex for TexCoord2 {
+    fn from_internal(v: &InternalVertexData) -> Self {
+        TexCoord2([v.2[0], v.2[1]])
+    }
+}
+
 impl FromInternalVertex for Normal {
     fn from_internal(v: &InternalVertexData) -> Self {
         Normal([v.1[0], v.1[1], v.1[2]])
@@ -342,6 +351,8 @@ impl_interleaved! {
     PosTex { position, tex_coord },
     PosNormTex { position, normal, tex_coord },
     PosNormTangTex { position, normal, tangent, tex_coord },
+    PosNormTex2 { position, normal, tex_coord, tex_coord_2 },
+    PosNormTangTex2 { position, normal, tangent, tex_coord, tex_coord_2 },
 }
 
 macro_rules! impl_nested_from {
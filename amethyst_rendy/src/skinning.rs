@@ -2,7 +2,7 @@
 use amethyst_assets::PrefabData;
 use amethyst_core::{
     ecs::prelude::{Component, DenseVecStorage, Entity, FlaggedStorage, WriteStorage},
-    math::Matrix4,
+    math::{Matrix3, Matrix4, Quaternion, Rotation3, UnitQuaternion, Vector3, U1, U3},
 };
 use amethyst_error::Error;
 use rendy::{
@@ -11,6 +11,26 @@ use rendy::{
 };
 use std::result::Result as StdResult;
 
+/// Number of joints a skin can have, limited by the range of `JointIds`.
+pub const MAX_JOINTS: usize = std::u16::MAX as usize + 1;
+
+/// How the joints influencing a vertex are blended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum SkinningMode {
+    /// Blends the joint matrices. Fast and supports any joint transform, but twisting joints
+    /// lose volume.
+    Linear,
+    /// Blends the joint transforms as dual quaternions, which preserves volume around twisting
+    /// joints. Non-uniform scales and shears of joints are approximated.
+    DualQuaternion,
+}
+
+impl Default for SkinningMode {
+    fn default() -> Self {
+        SkinningMode::Linear
+    }
+}
+
 /// Type for joint weights attribute of vertex
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
@@ -120,3 +140,63 @@ impl<'a> PrefabData<'a> for JointTransformsPrefab {
         Ok(())
     }
 }
+
+/// Converts a joint matrix to the layout read by dual quaternion skinning shaders: the real
+/// and dual parts of its rigid transform in the first two columns, and the scale applied before
+/// it in the third.
+pub(crate) fn dual_quaternion_joint(matrix: &Matrix4<f32>) -> [[f32; 4]; 4] {
+    let mut linear: Matrix3<f32> = matrix.fixed_slice::<U3, U3>(0, 0).into_owned();
+    let mut scale = Vector3::new(
+        linear.column(0).norm(),
+        linear.column(1).norm(),
+        linear.column(2).norm(),
+    );
+    if linear.determinant() < 0.0 {
+        scale.x = -scale.x;
+    }
+    for (i, s) in scale.iter().enumerate() {
+        if s.abs() > std::f32::EPSILON {
+            linear.column_mut(i).unscale_mut(*s);
+        }
+    }
+
+    let real = UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(linear))
+        .into_inner();
+    let translation = Quaternion::from_imag(matrix.fixed_slice::<U3, U1>(0, 3).into_owned());
+    let dual = translation * real * 0.5;
+
+    [
+        real.coords.into(),
+        dual.coords.into(),
+        [scale.x, scale.y, scale.z, 0.0],
+        [0.0; 4],
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::math::{Point3, Translation3, Vector4};
+
+    #[test]
+    fn dual_quaternion_joint_keeps_rigid_transform() {
+        let rotation = UnitQuaternion::from_euler_angles(0.3, -1.2, 2.0);
+        let translation = Translation3::new(1.0, -2.0, 3.0);
+        let matrix = translation.to_homogeneous()
+            * rotation.to_homogeneous()
+            * Matrix4::new_nonuniform_scaling(&Vector3::new(2.0, 1.0, 0.5));
+        let joint = dual_quaternion_joint(&matrix);
+
+        let real = Quaternion::from(Vector4::from(joint[0]));
+        let dual = Quaternion::from(Vector4::from(joint[1]));
+        let scale = Vector3::new(joint[2][0], joint[2][1], joint[2][2]);
+        assert!((scale - Vector3::new(2.0, 1.0, 0.5)).norm() < 1e-5);
+
+        // Apply the transform like the shaders do
+        let point = Point3::new(0.5, 1.0, -1.5);
+        let scaled = point.coords.component_mul(&scale);
+        let rotated = UnitQuaternion::from_quaternion(real) * scaled;
+        let offset = (dual * real.conjugate() * 2.0).imag();
+        assert!((rotated + offset - (matrix * point.to_homogeneous()).xyz()).norm() < 1e-4);
+    }
+}
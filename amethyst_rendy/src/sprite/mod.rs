@@ -23,6 +23,16 @@ pub struct SpriteSheet {
     pub sprites: Vec<Sprite>,
 }
 
+impl SpriteSheet {
+    /// Creates a `SpriteSheet` for `texture` with the sprites laid out by `grid`.
+    pub fn from_grid(texture: Handle<Texture>, grid: &SpriteGrid) -> Self {
+        SpriteSheet {
+            texture,
+            sprites: grid.build_sprites(),
+        }
+    }
+}
+
 impl Asset for SpriteSheet {
     const NAME: &'static str = "renderer::SpriteSheet";
     type Data = Self;
@@ -255,6 +265,22 @@ pub struct SpriteList {
 /// | 4 | 5 | 6 | 7 |
 /// |---|---|---|---|
 /// ```
+///
+/// A grid of 16x16 sprites separated by 1 pixel, with its last row only partly filled and the
+/// sprites anchored at their bottom center:
+///
+/// ```text,ignore
+/// #![enable(implicit_some)]
+/// Grid((
+///     texture_width: 67,
+///     texture_height: 33,
+///     columns: 4,
+///     sprite_count: 6,
+///     padding: (1, 1),
+///     pivot: (0.5, 0.0),
+///     half_texel_inset: true,
+/// ))
+/// ```
 #[derive(Clone, Debug, Deserialize, Serialize, Default)]
 pub struct SpriteGrid {
     /// Width of the texture in pixels.
@@ -276,6 +302,17 @@ pub struct SpriteGrid {
     /// Specifies the position of the grid on a texture. If this is not given it will be set to (0, 0).
     /// Positions originate in the top-left corner (bitmap image convention).
     pub position: Option<(u32, u32)>,
+    /// Specifies the gap between adjacent cells in pixels. If this is not given it will be set to
+    /// (0, 0). Tuple order is `(horizontal, vertical)`.
+    pub padding: Option<(u32, u32)>,
+    /// Specifies the point of each sprite placed at the position of the entity, as a fraction of
+    /// the sprite size with (0.0, 0.0) at its bottom left corner. If this is not given the sprites
+    /// are centered.
+    pub pivot: Option<(f32, f32)>,
+    /// Shrinks the texture coordinates of each sprite by half a texel on every side, so that
+    /// linear filtering does not sample the neighbouring cells.
+    #[serde(default)]
+    pub half_texel_inset: bool,
 }
 
 /// Defined the sprites that are part of a `SpriteSheetPrefab`.
@@ -339,7 +376,11 @@ impl SpriteGrid {
                         (c / self.columns) + 1
                     }
                 })
-                .or_else(|| self.cell_size.map(|(_, y)| (self.sheet_height() / y)))
+                .or_else(|| {
+                    let padding = self.padding().1;
+                    self.cell_size
+                        .map(|(_, y)| (self.sheet_height() + padding) / (y + padding))
+                })
                 .unwrap_or(1)
         })
     }
@@ -351,10 +392,13 @@ impl SpriteGrid {
 
     fn cell_size(&self) -> (u32, u32) {
         self.cell_size.unwrap_or_else(|| {
-            (
-                (self.sheet_width() / self.columns),
-                (self.sheet_height() / self.rows()),
-            )
+            let (padding_x, padding_y) = self.padding();
+            let rows = self.rows();
+            let width = self
+                .sheet_width()
+                .saturating_sub((self.columns - 1) * padding_x);
+            let height = self.sheet_height().saturating_sub((rows - 1) * padding_y);
+            (width / self.columns, height / rows)
         })
     }
 
@@ -362,47 +406,80 @@ impl SpriteGrid {
         self.position.unwrap_or((0, 0))
     }
 
+    fn padding(&self) -> (u32, u32) {
+        self.padding.unwrap_or((0, 0))
+    }
+
+    /// The offsets placing the pivot of a sprite of the given size at the entity position.
+    fn offsets(&self, (width, height): (u32, u32)) -> [f32; 2] {
+        let (pivot_x, pivot_y) = self.pivot.unwrap_or((0.5, 0.5));
+        [
+            (pivot_x - 0.5) * width as f32,
+            (pivot_y - 0.5) * height as f32,
+        ]
+    }
+
     /// Creates a `Vec<Sprite>` from `SpriteGrid`.
     pub fn build_sprites(&self) -> Vec<Sprite> {
         let rows = self.rows();
         let sprite_count = self.sprite_count();
         let cell_size = self.cell_size();
         let position = self.position();
-        if (self.columns * cell_size.0) > self.sheet_width() {
+        let padding = self.padding();
+        if (self.columns * (cell_size.0 + padding.0)).saturating_sub(padding.0) > self.sheet_width()
+        {
             log::warn!(
-                "Grid spritesheet contains more columns than can fit in the given width: {} * {} > {} - {}",
+                "Grid spritesheet contains more columns than can fit in the given width: {} * ({} + {}) - {} > {} - {}",
                 self.columns,
                 cell_size.0,
+                padding.0,
+                padding.0,
                 self.texture_width,
                 position.0
             );
         }
-        if (rows * cell_size.1) > self.sheet_height() {
+        if (rows * (cell_size.1 + padding.1)).saturating_sub(padding.1) > self.sheet_height() {
             log::warn!(
-                "Grid spritesheet contains more rows than can fit in the given height: {} * {} > {} - {}",
+                "Grid spritesheet contains more rows than can fit in the given height: {} * ({} + {}) - {} > {} - {}",
                 rows,
                 cell_size.1,
+                padding.1,
+                padding.1,
                 self.texture_height,
                 position.1
             );
         }
+        let offsets = self.offsets(cell_size);
+        let (inset_x, inset_y) = if self.half_texel_inset {
+            (
+                0.5 / self.texture_width as f32,
+                0.5 / self.texture_height as f32,
+            )
+        } else {
+            (0.0, 0.0)
+        };
         (0..sprite_count)
             .map(|cell| {
                 let row = cell / self.columns;
                 let column = cell - (row * self.columns);
-                let x = column * cell_size.0 + position.0;
-                let y = row * cell_size.1 + position.1;
-                Sprite::from_pixel_values(
+                let x = column * (cell_size.0 + padding.0) + position.0;
+                let y = row * (cell_size.1 + padding.1) + position.1;
+                let mut sprite = Sprite::from_pixel_values(
                     self.texture_width,
                     self.texture_height,
                     cell_size.0,
                     cell_size.1,
                     x,
                     y,
-                    [0.0; 2],
+                    offsets,
                     false,
                     false,
-                )
+                );
+                sprite.tex_coords.left += inset_x;
+                sprite.tex_coords.right -= inset_x;
+                sprite.tex_coords.top += inset_y;
+                sprite.tex_coords.bottom -= inset_y;
+                sprite
             })
             .collect()
     }
@@ -492,6 +492,55 @@ mod tests {
         assert_ulps_eq!(0.666_666_7, sprites[4].tex_coords.bottom);
     }
 
+    #[test]
+    fn grid_padding_pivot_inset() {
+        let sprites = SpriteGrid {
+            texture_width: 67,
+            texture_height: 33,
+            columns: 4,
+            sprite_count: Some(6),
+            padding: Some((1, 1)),
+            pivot: Some((0.5, 0.0)),
+            half_texel_inset: true,
+            ..Default::default()
+        }
+        .build_sprites();
+
+        assert_eq!(6, sprites.len());
+        for sprite in &sprites {
+            assert_ulps_eq!(16.0, sprite.width);
+            assert_ulps_eq!(16.0, sprite.height);
+            assert_ulps_eq!(0.0, sprite.offsets[0]);
+            assert_ulps_eq!(-8.0, sprite.offsets[1]);
+        }
+
+        assert_ulps_eq!(0.5 / 67.0, sprites[0].tex_coords.left);
+        assert_ulps_eq!(15.5 / 67.0, sprites[0].tex_coords.right);
+        assert_ulps_eq!(0.5 / 33.0, sprites[0].tex_coords.top);
+        assert_ulps_eq!(15.5 / 33.0, sprites[0].tex_coords.bottom);
+
+        assert_ulps_eq!(17.5 / 67.0, sprites[5].tex_coords.left);
+        assert_ulps_eq!(32.5 / 67.0, sprites[5].tex_coords.right);
+        assert_ulps_eq!(17.5 / 33.0, sprites[5].tex_coords.top);
+        assert_ulps_eq!(32.5 / 33.0, sprites[5].tex_coords.bottom);
+    }
+
+    #[test]
+    fn repeat_rows_cell_size_padding_set() {
+        assert_eq!(
+            3,
+            SpriteGrid {
+                texture_width: 200,
+                texture_height: 62,
+                columns: 5,
+                cell_size: Some((20, 20)),
+                padding: Some((1, 1)),
+                ..Default::default()
+            }
+            .rows()
+        );
+    }
+
     #[test]
     fn repeat_cell_size_set() {
         assert_eq!(
@@ -1,17 +1,22 @@
-//! Transparency, visibility sorting and camera centroid culling for 2D Sprites.
+//! Transparency, visibility sorting and camera frustum culling for 2D Sprites.
 use crate::{
     camera::{ActiveCamera, Camera},
+    layers::RenderLayers,
+    sprite::{Sprite, SpriteRender, SpriteSheet},
     transparent::Transparent,
+    visibility::{CameraVisibility, Frustum},
 };
+use amethyst_assets::AssetStorage;
 use amethyst_core::{
     ecs::{
         hibitset::BitSet,
         prelude::{Entities, Entity, Join, Read, ReadStorage, System, Write},
     },
-    math::{Point3, Vector3},
+    math::{convert, Matrix4, Point3, Vector4},
     Hidden, HiddenPropagate, Transform,
 };
 use derivative::Derivative;
+use fnv::FnvHashMap;
 use std::cmp::Ordering;
 
 #[cfg(feature = "profiler")]
@@ -19,17 +24,28 @@ use thread_profiler::profile_scope;
 
 /// Resource for controlling what entities should be rendered, and whether to draw them ordered or
 /// not, which is useful for transparent surfaces.
+///
+/// The top level sets and counters are the ones of the active camera.
 #[derive(Default, Debug)]
 pub struct SpriteVisibility {
     /// Visible entities that can be drawn in any order
     pub visible_unordered: BitSet,
     /// Visible entities that need to be drawn in the given order
     pub visible_ordered: Vec<Entity>,
+    /// Visible entities of every camera, culled by its frustum and `RenderLayers`.
+    pub cameras: FnvHashMap<Entity, CameraVisibility>,
+    /// Number of visible entities.
+    pub drawn: usize,
+    /// Number of sprites culled for being out of view.
+    pub culled: usize,
 }
 
 /// Determines what entities to be drawn. Will also sort transparent entities back to front based on
 /// position on the Z axis.
 ///
+/// Sprites are culled by the frustum of each camera with `sprite_bounds`. Entities without a
+/// loaded sprite are only culled when behind the camera.
+///
 /// The sprite render pass should draw all sprites without semi-transparent pixels, then draw the
 /// sprites with semi-transparent pixels from far to near.
 ///
@@ -48,7 +64,20 @@ struct Internals {
     transparent: bool,
     centroid: Point3<f32>,
     camera_distance: f32,
-    from_camera: Vector3<f32>,
+}
+
+/// Returns the center and radius of a sphere bounding the quad drawn for `sprite` with
+/// `transform`. The bounds hold the quad for any rotation and scale.
+pub fn sprite_bounds(sprite: &Sprite, transform: &Transform) -> (Point3<f32>, f32) {
+    let matrix = convert::<_, Matrix4<f32>>(*transform.global_matrix());
+    // The quad is centered on the offset position, and spans its x and y axes in the xy plane.
+    let center = matrix * Vector4::new(-sprite.offsets[0], -sprite.offsets[1], 0.0, 1.0);
+    let dir_x = matrix.column(0).xy() * sprite.width;
+    let dir_y = matrix.column(1).xy() * sprite.height;
+    (
+        Point3::from(center.xyz()),
+        0.5 * (dir_x.norm() + dir_y.norm()),
+    )
 }
 
 impl SpriteVisibilitySortingSystem {
@@ -56,60 +85,58 @@ impl SpriteVisibilitySortingSystem {
     pub fn new() -> Self {
         Default::default()
     }
-}
-
-impl<'a> System<'a> for SpriteVisibilitySortingSystem {
-    type SystemData = (
-        Entities<'a>,
-        Write<'a, SpriteVisibility>,
-        ReadStorage<'a, Hidden>,
-        ReadStorage<'a, HiddenPropagate>,
-        Read<'a, ActiveCamera>,
-        ReadStorage<'a, Camera>,
-        ReadStorage<'a, Transparent>,
-        ReadStorage<'a, Transform>,
-    );
 
-    fn run(
+    /// Collects the entities visible to a camera into `out`, sorting transparent ones back to
+    /// front. Returns the number of culled sprites.
+    fn cull<'a>(
         &mut self,
-        (entities, mut visibility, hidden, hidden_prop, active, camera, transparent, transform): Self::SystemData,
-    ) {
-        #[cfg(feature = "profiler")]
-        profile_scope!("sprite_visibility_sorting_system");
-
+        renderables: impl Iterator<Item = (Entity, &'a Transform, Option<&'a Sprite>)>,
+        (camera, camera_transform, camera_layers): (&Camera, &Transform, RenderLayers),
+        transparent: &ReadStorage<'_, Transparent>,
+        layers: &ReadStorage<'_, RenderLayers>,
+        out: &mut CameraVisibility,
+    ) -> usize {
         let origin = Point3::origin();
+        let camera_backward = camera_transform.global_matrix().column(2).xyz();
+        let camera_centroid = camera_transform.global_matrix().transform_point(&origin);
+        let frustum = Frustum::new(
+            convert::<_, Matrix4<f32>>(*camera.as_matrix())
+                * camera_transform.global_matrix().try_inverse().unwrap(),
+        );
 
-        // The camera position is used to determine culling, but the sprites are ordered based on
-        // the Z coordinate
-        let camera: Option<&Transform> = active
-            .entity
-            .and_then(|a| transform.get(a))
-            .or_else(|| (&camera, &transform).join().map(|ct| ct.1).next());
-        let camera_backward = camera
-            .map(|c| c.global_matrix().column(2).xyz())
-            .unwrap_or_else(Vector3::z);
-        let camera_centroid = camera
-            .map(|t| t.global_matrix().transform_point(&origin))
-            .unwrap_or_else(|| origin);
-
+        let mut culled = 0;
         self.centroids.clear();
         self.centroids.extend(
-            (&*entities, &transform, !&hidden, !&hidden_prop)
-                .join()
-                .map(|(e, t, _, _)| (e, t.global_matrix().transform_point(&origin)))
-                // filter entities behind the camera
-                .filter(|(_, c)| (c - camera_centroid).dot(&camera_backward) < 0.0)
+            renderables
+                .filter(|(entity, _, _)| {
+                    camera_layers.intersects(RenderLayers::of(layers.get(*entity)))
+                })
+                .filter_map(|(entity, transform, sprite)| {
+                    let centroid = transform.global_matrix().transform_point(&origin);
+                    let visible = match sprite {
+                        Some(sprite) => {
+                            let (center, radius) = sprite_bounds(sprite, transform);
+                            frustum.check_sphere(&center, radius)
+                        }
+                        None => (centroid - camera_centroid).dot(&camera_backward) < 0.0,
+                    };
+                    if visible {
+                        Some((entity, centroid))
+                    } else {
+                        culled += sprite.is_some() as usize;
+                        None
+                    }
+                })
                 .map(|(entity, centroid)| Internals {
                     entity,
                     transparent: transparent.contains(entity),
                     centroid,
                     camera_distance: (centroid.z - camera_centroid.z).abs(),
-                    from_camera: centroid - camera_centroid,
                 }),
         );
 
-        visibility.visible_unordered.clear();
-        visibility.visible_unordered.extend(
+        out.visible_unordered.clear();
+        out.visible_unordered.extend(
             self.centroids
                 .iter()
                 .filter(|c| !c.transparent)
@@ -128,9 +155,183 @@ impl<'a> System<'a> for SpriteVisibilitySortingSystem {
                 .unwrap_or(Ordering::Equal)
         });
 
-        visibility.visible_ordered.clear();
-        visibility
-            .visible_ordered
+        out.visible_ordered.clear();
+        out.visible_ordered
             .extend(self.transparent.iter().map(|c| c.entity));
+        culled
+    }
+}
+
+impl<'a> System<'a> for SpriteVisibilitySortingSystem {
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, SpriteVisibility>,
+        ReadStorage<'a, Hidden>,
+        ReadStorage<'a, HiddenPropagate>,
+        Read<'a, ActiveCamera>,
+        ReadStorage<'a, Camera>,
+        ReadStorage<'a, Transparent>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, RenderLayers>,
+        ReadStorage<'a, SpriteRender>,
+        Read<'a, AssetStorage<SpriteSheet>>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut visibility,
+            hidden,
+            hidden_prop,
+            active,
+            camera,
+            transparent,
+            transform,
+            layers,
+            sprite_renders,
+            sprite_sheets,
+        ): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("sprite_visibility_sorting_system");
+
+        let defcam = Camera::standard_2d(1.0, 1.0);
+        let identity = Transform::default();
+        let visibility = &mut *visibility;
+
+        let renderables = || {
+            (
+                &*entities,
+                &transform,
+                sprite_renders.maybe(),
+                !&hidden,
+                !&hidden_prop,
+            )
+                .join()
+                .map(|(entity, transform, sprite_render, _, _)| {
+                    let sprite = sprite_render.and_then(|sprite_render| {
+                        sprite_sheets
+                            .get(&sprite_render.sprite_sheet)
+                            .and_then(|sheet| sheet.sprites.get(sprite_render.sprite_number))
+                    });
+                    (entity, transform, sprite)
+                })
+        };
+
+        // Forget the cameras which were removed
+        visibility
+            .cameras
+            .retain(|entity, _| camera.contains(*entity) && transform.contains(*entity));
+
+        let active_entity = active
+            .entity
+            .filter(|e| camera.contains(*e) && transform.contains(*e))
+            .or_else(|| (&*entities, &camera, &transform).join().map(|j| j.0).next());
+
+        let mut active_culled = None;
+        for (entity, camera, camera_transform, camera_layers) in
+            (&*entities, &camera, &transform, layers.maybe()).join()
+        {
+            let out = visibility.cameras.entry(entity).or_default();
+            let culled = self.cull(
+                renderables(),
+                (camera, camera_transform, RenderLayers::of(camera_layers)),
+                &transparent,
+                &layers,
+                out,
+            );
+            if Some(entity) == active_entity {
+                active_culled = Some(culled);
+            }
+        }
+
+        let (active_visibility, culled) = match (
+            active_entity.and_then(|e| visibility.cameras.get(&e)),
+            active_culled,
+        ) {
+            (Some(camera_visibility), Some(culled)) => (camera_visibility.clone(), culled),
+            _ => {
+                let mut default_visibility = CameraVisibility::default();
+                let culled = self.cull(
+                    renderables(),
+                    (&defcam, &identity, RenderLayers::ALL),
+                    &transparent,
+                    &layers,
+                    &mut default_visibility,
+                );
+                (default_visibility, culled)
+            }
+        };
+        visibility.drawn = (&active_visibility.visible_unordered).join().count()
+            + active_visibility.visible_ordered.len();
+        visibility.culled = culled;
+        visibility.visible_unordered = active_visibility.visible_unordered;
+        visibility.visible_ordered = active_visibility.visible_ordered;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sprite::TextureCoordinates;
+    use amethyst_core::math::{UnitQuaternion, Vector3};
+
+    fn sprite(width: f32, height: f32) -> Sprite {
+        Sprite {
+            width,
+            height,
+            offsets: [0.0, 0.0],
+            tex_coords: TextureCoordinates {
+                left: 0.0,
+                right: 1.0,
+                bottom: 0.0,
+                top: 1.0,
+            },
+        }
+    }
+
+    #[test]
+    fn bounds_hold_rotated_sprite() {
+        let sprite = sprite(40.0, 10.0);
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(100.0, 50.0, 0.0);
+        transform.set_scale(Vector3::new(2.0, 2.0, 1.0));
+        transform.set_rotation(UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 0.7));
+        transform.copy_local_to_global();
+
+        let (center, radius) = sprite_bounds(&sprite, &transform);
+        assert!((center - Point3::new(100.0, 50.0, 0.0)).norm() < 1e-4);
+        let matrix = convert::<_, Matrix4<f32>>(*transform.global_matrix());
+        for (x, y) in &[(-0.5, -0.5), (0.5, -0.5), (-0.5, 0.5), (0.5, 0.5)] {
+            let corner = matrix.transform_point(&Point3::new(x * 40.0, y * 10.0, 0.0));
+            assert!((corner - center).norm() <= radius + 1e-4);
+        }
+    }
+
+    #[test]
+    fn sprites_out_of_orthographic_view_are_culled() {
+        let camera = Camera::standard_2d(200.0, 100.0);
+        let mut camera_transform = Transform::default();
+        camera_transform.set_translation_z(10.0);
+        camera_transform.copy_local_to_global();
+        let frustum = Frustum::new(
+            convert::<_, Matrix4<f32>>(*camera.as_matrix())
+                * camera_transform.global_matrix().try_inverse().unwrap(),
+        );
+
+        let sprite = sprite(20.0, 20.0);
+        let at = |x: f32, y: f32| {
+            let mut transform = Transform::default();
+            transform.set_translation_xyz(x, y, 0.0);
+            transform.copy_local_to_global();
+            let (center, radius) = sprite_bounds(&sprite, &transform);
+            frustum.check_sphere(&center, radius)
+        };
+        assert!(at(0.0, 0.0));
+        // Partly on screen past the right edge at x = 100.
+        assert!(at(105.0, 0.0));
+        assert!(!at(130.0, 0.0));
+        assert!(!at(0.0, -80.0));
     }
 }
@@ -1,22 +1,45 @@
 //! Environment submodule for shared environmental descriptor set data.
 //! Fetches and sets projection and lighting descriptor set information.
+//!
+//! Lights are filtered by `RenderLayers`: every distinct set of layers drawn by a render group
+//! gets its own environment, holding only the lights sharing a layer with it.
+//!
+//! The cookies of spot lights are bound after the lights, unused slots hold the default albedo.
+//! The point shadow atlas follows them, or the default albedo when the submodule isn't given one
+//! with `set_point_shadows`.
 use crate::{
-    light::Light,
+    layers::RenderLayers,
+    light::{Light, LightUnits},
+    mtl::MaterialDefaults,
     pod::{self, IntoPod},
+    point_shadow::{face_resolution, PointShadowSettings, PointShadowSlots},
     rendy::{
         command::RenderPassEncoder,
         factory::Factory,
-        hal::{self, adapter::PhysicalDevice, device::Device, pso::Descriptor},
+        graph::{GraphContext, ImageAccess, NodeImage},
+        hal::{
+            self,
+            adapter::PhysicalDevice,
+            device::Device,
+            format::{Aspects, Swizzle},
+            image::{Filter, SamplerInfo, SubresourceRange, ViewKind, WrapMode},
+            pso::Descriptor,
+        },
         memory::Write as _,
-        resource::{Buffer, DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle},
+        resource::{
+            Buffer, DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle, ImageView,
+            ImageViewInfo, Sampler,
+        },
     },
+    resources::ClipMode,
     submodules::gather::{AmbientGatherer, CameraGatherer},
-    types::Backend,
+    types::{Backend, Texture},
     util::{self, TapCountIter},
 };
+use amethyst_assets::{AssetStorage, Handle};
 use amethyst_core::{
-    ecs::{Join, ReadStorage, SystemData, World},
-    math::{convert, Vector3},
+    ecs::{Entities, Join, Read, ReadExpect, ReadStorage, SystemData, World},
+    math::{convert, Matrix4, Point3, Vector3},
     transform::Transform,
 };
 use glsl_layout::*;
@@ -27,6 +50,11 @@ use thread_profiler::profile_scope;
 const MAX_POINT_LIGHTS: usize = 128;
 const MAX_DIR_LIGHTS: usize = 16;
 const MAX_SPOT_LIGHTS: usize = 128;
+const MAX_SPOT_COOKIES: usize = 4;
+const COOKIE_BINDING: u32 = 5;
+const POINT_SHADOW_BINDING: u32 = COOKIE_BINDING + MAX_SPOT_COOKIES as u32;
+
+type Std140<T> = <T as AsStd140>::Std140;
 
 /// Submodule for loading and binding descriptor sets for a 3D, lit environment.
 /// This also abstracts away the need for handling multiple images in flight, as it provides
@@ -34,16 +62,46 @@ const MAX_SPOT_LIGHTS: usize = 128;
 #[derive(Debug)]
 pub struct EnvironmentSub<B: Backend> {
     layout: RendyHandle<DescriptorSetLayout<B>>,
-    per_image: Vec<PerImageEnvironmentSub<B>>,
+    layers: Vec<RenderLayers>,
+    per_image: Vec<Vec<PerImageEnvironmentSub<B>>>,
+    point_shadows: Option<PointShadowAtlas<B>>,
+    clip_mode: ClipMode,
+}
+
+/// The point shadow atlas bound by an `EnvironmentSub`.
+#[derive(Debug)]
+struct PointShadowAtlas<B: Backend> {
+    view: Escape<ImageView<B>>,
+    sampler: RendyHandle<Sampler<B>>,
+    layout: PointShadowLayout,
+}
+
+/// Size of the faces and number of rows of the point shadow atlas.
+#[derive(Debug, Clone, Copy)]
+struct PointShadowLayout {
+    tile_size: u32,
+    slots: u32,
 }
 
 /// Submodule for loading and binding descriptor sets for a 3D, lit environment.
 /// This is the actual implementation for a given environment, but multiple instances may exist
-/// for each image in flight.
+/// for each image in flight and set of layers.
 #[derive(Debug)]
 struct PerImageEnvironmentSub<B: Backend> {
     buffer: Option<Escape<Buffer<B>>>,
     set: Escape<DescriptorSet<B>>,
+    cookies: [Option<(u32, u32)>; MAX_SPOT_COOKIES],
+    point_shadows: bool,
+}
+
+/// Camera, ambient and light data of the world, gathered once per frame for all layers.
+struct GatheredEnvironment {
+    camera: CameraGatherer,
+    env: pod::Environment,
+    point_lights: Vec<(RenderLayers, Std140<pod::PointLight>)>,
+    dir_lights: Vec<(RenderLayers, Std140<pod::DirectionalLight>)>,
+    spot_lights: Vec<(RenderLayers, Std140<pod::SpotLight>)>,
+    cookies: Vec<Handle<Texture>>,
 }
 
 impl<B: Backend> EnvironmentSub<B> {
@@ -54,32 +112,159 @@ impl<B: Backend> EnvironmentSub<B> {
         flags: [hal::pso::ShaderStageFlags; 2],
     ) -> Result<Self, failure::Error> {
         Ok(Self {
-            layout: set_layout! {factory, [1] UniformBuffer flags[0], [4] UniformBuffer flags[1]},
+            layout: set_layout! {
+                factory,
+                [1] UniformBuffer flags[0],
+                [4] UniformBuffer flags[1],
+                [MAX_SPOT_COOKIES] CombinedImageSampler flags[1],
+                [1] CombinedImageSampler flags[1]
+            },
+            layers: vec![RenderLayers::ALL],
             per_image: Vec::new(),
+            point_shadows: None,
+            clip_mode: ClipMode::Disabled,
         })
     }
 
+    /// Access of the point shadow atlas, to be returned by `RenderGroupDesc::images` of render
+    /// groups calling `set_point_shadows`.
+    pub fn point_shadow_access() -> ImageAccess {
+        ImageAccess {
+            access: hal::image::Access::SHADER_READ,
+            usage: hal::image::Usage::SAMPLED,
+            layout: hal::image::Layout::ShaderReadOnlyOptimal,
+            stages: hal::pso::PipelineStage::FRAGMENT_SHADER,
+        }
+    }
+
+    /// Clip the views with the `ClipPlanes` in `mode`. The set must then be visible to the
+    /// shaders reading them, usually both the vertex and fragment stages.
+    pub fn set_clip_mode(&mut self, mode: ClipMode) {
+        self.clip_mode = mode;
+    }
+
+    /// Shade point lights with the shadows of the atlas drawn by `DrawPointShadow`. Without it,
+    /// all lights are unshadowed.
+    pub fn set_point_shadows(
+        &mut self,
+        ctx: &GraphContext<B>,
+        factory: &Factory<B>,
+        atlas: &NodeImage,
+    ) -> Result<(), failure::Error> {
+        let image = ctx
+            .get_image(atlas.id)
+            .ok_or_else(|| failure::format_err!("Point shadow atlas image is missing."))?;
+        let extent = image.kind().extent();
+        let tile_size = extent.width / 6;
+        if tile_size == 0 || extent.height < tile_size {
+            return Err(failure::format_err!(
+                "Point shadow atlas of {}x{} pixels is too small.",
+                extent.width,
+                extent.height
+            ));
+        }
+        let view = factory.create_image_view(
+            image.clone(),
+            ImageViewInfo {
+                view_kind: ViewKind::D2,
+                format: image.format(),
+                swizzle: Swizzle::NO,
+                range: SubresourceRange {
+                    aspects: Aspects::COLOR,
+                    levels: 0..1,
+                    layers: 0..1,
+                },
+            },
+        )?;
+        // Distances are compared in the shader, they must not be filtered.
+        let sampler = factory.get_sampler(SamplerInfo::new(Filter::Nearest, WrapMode::Clamp))?;
+        self.point_shadows = Some(PointShadowAtlas {
+            view,
+            sampler,
+            layout: PointShadowLayout {
+                tile_size,
+                slots: extent.height / tile_size,
+            },
+        });
+        for env in self.per_image.iter_mut().flatten() {
+            env.point_shadows = false;
+        }
+        Ok(())
+    }
+
     /// Returns the raw `DescriptorSetLayout` for this environment
     pub fn raw_layout(&self) -> &B::DescriptorSetLayout {
         self.layout.raw()
     }
 
+    /// Returns the index of the environment lit by the lights sharing a layer with `layers`, to
+    /// be passed to `bind_layers`. Indices are stable for the lifetime of the submodule, the
+    /// environment is written by the next `process`.
+    pub fn layer_slot(&mut self, layers: RenderLayers) -> usize {
+        match self.layers.iter().position(|l| *l == layers) {
+            Some(slot) => slot,
+            None => {
+                self.layers.push(layers);
+                self.layers.len() - 1
+            }
+        }
+    }
+
     /// Performs any re-allocation and GPU memory writing required for this environment set.
     pub fn process(&mut self, factory: &Factory<B>, index: usize, world: &World) -> bool {
         #[cfg(feature = "profiler")]
         profile_scope!("process");
 
+        let (tex_storage, defaults) = <(
+            Read<'_, AssetStorage<Texture>>,
+            ReadExpect<'_, MaterialDefaults>,
+        )>::fetch(world);
+        let gathered = GatheredEnvironment::gather(
+            world,
+            &tex_storage,
+            self.point_shadows.as_ref().map(|atlas| atlas.layout),
+            self.clip_mode,
+        );
+        let cookies = (0..MAX_SPOT_COOKIES)
+            .map(|slot| {
+                let handle = gathered.cookies.get(slot).unwrap_or(&defaults.0.albedo);
+                tex_storage
+                    .get_with_version(handle)
+                    .map(|(texture, version)| ((handle.id(), *version), texture))
+            })
+            .collect::<Vec<_>>();
+        let atlas = self.point_shadows.as_ref();
+        let point_shadows = || match atlas {
+            Some(atlas) => Some(Descriptor::CombinedImageSampler(
+                atlas.view.raw(),
+                hal::image::Layout::ShaderReadOnlyOptimal,
+                atlas.sampler.raw(),
+            )),
+            None => tex_storage.get(&defaults.0.albedo).and_then(|texture| {
+                util::texture_desc::<B>(texture, hal::image::Layout::ShaderReadOnlyOptimal)
+            }),
+        };
+        let layout = &self.layout;
         let this_image = {
             while self.per_image.len() <= index {
-                self.per_image
-                    .push(PerImageEnvironmentSub::new(factory, &self.layout));
+                self.per_image.push(Vec::new());
             }
             &mut self.per_image[index]
         };
-        this_image.process(factory, world)
+        while this_image.len() < self.layers.len() {
+            this_image.push(PerImageEnvironmentSub::new(factory, layout));
+        }
+
+        let mut new_buffer = false;
+        for (env, layers) in this_image.iter_mut().zip(&self.layers) {
+            new_buffer |= env.process(factory, &gathered, *layers);
+            env.write_cookies(factory, &cookies);
+            env.write_point_shadows(factory, &point_shadows);
+        }
+        new_buffer
     }
 
-    /// Binds this environment set for all images.
+    /// Binds the environment lit by all lights.
     #[inline]
     pub fn bind(
         &self,
@@ -88,15 +273,257 @@ impl<B: Backend> EnvironmentSub<B> {
         set_id: u32,
         encoder: &mut RenderPassEncoder<'_, B>,
     ) {
-        self.per_image[index].bind(pipeline_layout, set_id, encoder);
+        self.bind_layers(index, 0, pipeline_layout, set_id, encoder);
+    }
+
+    /// Binds the environment of a `layer_slot`.
+    #[inline]
+    pub fn bind_layers(
+        &self,
+        index: usize,
+        slot: usize,
+        pipeline_layout: &B::PipelineLayout,
+        set_id: u32,
+        encoder: &mut RenderPassEncoder<'_, B>,
+    ) {
+        self.per_image[index][slot].bind(pipeline_layout, set_id, encoder);
     }
 }
 
+impl GatheredEnvironment {
+    fn gather(
+        world: &World,
+        tex_storage: &AssetStorage<Texture>,
+        point_shadows: Option<PointShadowLayout>,
+        clip_mode: ClipMode,
+    ) -> Self {
+        let camera = CameraGatherer::gather_clipped(world, clip_mode);
+        // Lighting is exposed before upload, so every shader reading the environment is alike.
+        let units = <Option<Read<'_, LightUnits>>>::fetch(world)
+            .map_or_else(LightUnits::default, |units| *units);
+        let exposure = units.exposure_scale(&camera.exposure);
+        let (ambient_color, ambient_ground_color, ambient_up) =
+            AmbientGatherer::gather_hemisphere(world, exposure);
+        let env = pod::Environment {
+            ambient_color,
+            camera_position: camera.camera_position,
+            point_light_count: 0,
+            directional_light_count: 0,
+            spot_light_count: 0,
+            ibl_intensity: AmbientGatherer::gather_ibl_intensity(world) * exposure,
+            ambient_ground_color,
+            ambient_up,
+        };
+        let intensity = |light: &Light| units.shader_intensity(light) * exposure;
+
+        let (entities, lights, transforms, layers, shadow_slots, shadow_settings) =
+            <(
+                Entities<'_>,
+                ReadStorage<'_, Light>,
+                ReadStorage<'_, Transform>,
+                ReadStorage<'_, RenderLayers>,
+                Option<Read<'_, PointShadowSlots>>,
+                Option<Read<'_, PointShadowSettings>>,
+            )>::fetch(world);
+        let shadow_bias = shadow_settings.map_or(0.0, |settings| settings.bias);
+
+        let point_lights = (&entities, &lights, &transforms, layers.maybe())
+            .join()
+            .filter_map(|(entity, light, transform, layers)| match light {
+                Light::Point(point) => {
+                    // Lights past the rows of the atlas are left unshadowed.
+                    let shadow = point_shadows.and_then(|atlas| {
+                        shadow_slots
+                            .as_ref()
+                            .and_then(|slots| slots.slot(entity))
+                            .filter(|slot| point.casts_shadows && *slot < atlas.slots as usize)
+                            .map(|slot| (slot, atlas))
+                    });
+                    let shadow_tile = match shadow {
+                        Some((slot, atlas)) => {
+                            let resolution = face_resolution(point, atlas.tile_size) as f32;
+                            let size = resolution / atlas.tile_size as f32;
+                            [
+                                slot as f32 / atlas.slots as f32,
+                                size / 6.0,
+                                size / atlas.slots as f32,
+                                1.0 / resolution,
+                            ]
+                        }
+                        None => [0.0; 4],
+                    };
+                    let (model, coefficients) = point.attenuation.shader_model();
+                    Some((
+                        RenderLayers::of(layers),
+                        pod::PointLight {
+                            position: convert::<_, Vector3<f32>>(
+                                transform.global_matrix().column(3).xyz(),
+                            )
+                            .into_pod(),
+                            color: point.color.into_pod(),
+                            intensity: intensity(light),
+                            shadow: shadow.map_or(-1, |(slot, _)| slot as i32),
+                            shadow_range: point.radius,
+                            shadow_bias,
+                            shadow_tile: shadow_tile.into(),
+                            attenuation: model,
+                            radius: point.radius,
+                            smoothness: point.smoothness,
+                            attenuation_coefficients: coefficients.into(),
+                        }
+                        .std140(),
+                    ))
+                }
+                _ => None,
+            })
+            .collect();
+
+        let dir_lights = (&lights, layers.maybe())
+            .join()
+            .filter_map(|(light, layers)| match light {
+                Light::Directional(ref directional) => Some((
+                    RenderLayers::of(layers),
+                    pod::DirectionalLight {
+                        color: directional.color.into_pod(),
+                        intensity: intensity(light),
+                        direction: directional.direction.into_pod(),
+                    }
+                    .std140(),
+                )),
+                _ => None,
+            })
+            .collect();
+
+        let mut cookies = Vec::new();
+        let spot_lights = (&lights, &transforms, layers.maybe())
+            .join()
+            .filter_map(|(light, transform, layers)| {
+                let exposed = intensity(light);
+                if let Light::Spot(ref light) = *light {
+                    let position =
+                        convert::<_, Vector3<f32>>(transform.global_matrix().column(3).xyz());
+                    let cookie = light
+                        .cookie
+                        .as_ref()
+                        .filter(|cookie| tex_storage.get(cookie).is_some())
+                        .and_then(|cookie| cookie_slot(&mut cookies, cookie));
+                    let cookie_projection: [[f32; 4]; 4] = match cookie {
+                        Some(_) => light.cookie_projection(&Point3::from(position)).into(),
+                        None => Matrix4::identity().into(),
+                    };
+                    Some((
+                        RenderLayers::of(layers),
+                        pod::SpotLight {
+                            position: position.into_pod(),
+                            color: light.color.into_pod(),
+                            direction: light.direction.into_pod(),
+                            angle: light.angle.cos(),
+                            intensity: exposed,
+                            range: light.range,
+                            smoothness: light.smoothness,
+                            cookie_projection: cookie_projection.into(),
+                            cookie: cookie.map_or(-1, |slot| slot as i32),
+                        }
+                        .std140(),
+                    ))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        Self {
+            camera,
+            env,
+            point_lights,
+            dir_lights,
+            spot_lights,
+            cookies,
+        }
+    }
+}
+
+/// Slot of a cookie texture, shared by the lights projecting the same texture. Returns `None`
+/// when all slots are taken by other textures.
+fn cookie_slot(cookies: &mut Vec<Handle<Texture>>, cookie: &Handle<Texture>) -> Option<usize> {
+    match cookies.iter().position(|c| c == cookie) {
+        Some(slot) => Some(slot),
+        None if cookies.len() < MAX_SPOT_COOKIES => {
+            cookies.push(cookie.clone());
+            Some(cookies.len() - 1)
+        }
+        None => {
+            log::warn!(
+                "More than {} spot light cookies, the rest are not projected.",
+                MAX_SPOT_COOKIES
+            );
+            None
+        }
+    }
+}
+
+/// Lights of `lights` sharing a layer with `layers`.
+fn lights_in<T: Copy>(
+    lights: &[(RenderLayers, T)],
+    layers: RenderLayers,
+) -> impl Iterator<Item = T> + '_ {
+    lights
+        .iter()
+        .filter(move |(light_layers, _)| light_layers.intersects(layers))
+        .map(|(_, light)| *light)
+}
+
 impl<B: Backend> PerImageEnvironmentSub<B> {
     fn new(factory: &Factory<B>, layout: &RendyHandle<DescriptorSetLayout<B>>) -> Self {
         Self {
             buffer: None,
             set: factory.create_descriptor_set(layout.clone()).unwrap(),
+            cookies: [None; MAX_SPOT_COOKIES],
+            point_shadows: false,
+        }
+    }
+
+    /// Writes the point shadow atlas, or its placeholder, once.
+    fn write_point_shadows<'a>(
+        &mut self,
+        factory: &Factory<B>,
+        desc: impl Fn() -> Option<Descriptor<'a, B>>,
+    ) {
+        if self.point_shadows {
+            return;
+        }
+        if let Some(desc) = desc() {
+            unsafe {
+                factory.write_descriptor_sets(Some(util::desc_write(
+                    self.set.raw(),
+                    POINT_SHADOW_BINDING,
+                    desc,
+                )));
+            }
+            self.point_shadows = true;
+        }
+    }
+
+    /// Writes the cookie textures which changed since the last frame.
+    fn write_cookies(&mut self, factory: &Factory<B>, cookies: &[Option<((u32, u32), &Texture)>]) {
+        for (slot, cookie) in cookies.iter().enumerate() {
+            if let Some((key, texture)) = cookie {
+                if self.cookies[slot] == Some(*key) {
+                    continue;
+                }
+                let layout = hal::image::Layout::ShaderReadOnlyOptimal;
+                if let Some(desc) = util::texture_desc::<B>(texture, layout) {
+                    let binding = COOKIE_BINDING + slot as u32;
+                    unsafe {
+                        factory.write_descriptor_sets(Some(util::desc_write(
+                            self.set.raw(),
+                            binding,
+                            desc,
+                        )));
+                    }
+                    self.cookies[slot] = Some(*key);
+                }
+            }
         }
     }
 
@@ -117,7 +544,12 @@ impl<B: Backend> PerImageEnvironmentSub<B> {
         }
     }
 
-    fn process(&mut self, factory: &Factory<B>, world: &World) -> bool {
+    fn process(
+        &mut self,
+        factory: &Factory<B>,
+        gathered: &GatheredEnvironment,
+        layers: RenderLayers,
+    ) -> bool {
         let align = factory
             .physical()
             .limits()
@@ -168,84 +600,15 @@ impl<B: Backend> PerImageEnvironmentSub<B> {
                 }
             }
 
-            let CameraGatherer {
-                camera_position,
-                projview,
-            } = CameraGatherer::gather(world);
-
             let mut mapped = buffer.map(factory, whole_range.clone()).unwrap();
             let mut writer = unsafe { mapped.write::<u8>(factory, whole_range).unwrap() };
             let dst_slice = unsafe { writer.slice() };
 
-            let mut env = pod::Environment {
-                ambient_color: AmbientGatherer::gather(world),
-                camera_position,
-                point_light_count: 0,
-                directional_light_count: 0,
-                spot_light_count: 0,
-            }
-            .std140();
+            let mut env = gathered.env.std140();
 
-            let (lights, transforms) =
-                <(ReadStorage<'_, Light>, ReadStorage<'_, Transform>)>::fetch(world);
-
-            let point_lights = (&lights, &transforms)
-                .join()
-                .filter_map(|(light, transform)| match light {
-                    Light::Point(light) => Some(
-                        pod::PointLight {
-                            position: convert::<_, Vector3<f32>>(
-                                transform.global_matrix().column(3).xyz(),
-                            )
-                            .into_pod(),
-                            color: light.color.into_pod(),
-                            intensity: light.intensity,
-                        }
-                        .std140(),
-                    ),
-                    _ => None,
-                })
-                .take(MAX_POINT_LIGHTS);
-
-            let dir_lights = lights
-                .join()
-                .filter_map(|light| match light {
-                    Light::Directional(ref light) => Some(
-                        pod::DirectionalLight {
-                            color: light.color.into_pod(),
-                            intensity: light.intensity,
-                            direction: light.direction.into_pod(),
-                        }
-                        .std140(),
-                    ),
-                    _ => None,
-                })
-                .take(MAX_DIR_LIGHTS);
-
-            let spot_lights = (&lights, &transforms)
-                .join()
-                .filter_map(|(light, transform)| {
-                    if let Light::Spot(ref light) = *light {
-                        Some(
-                            pod::SpotLight {
-                                position: convert::<_, Vector3<f32>>(
-                                    transform.global_matrix().column(3).xyz(),
-                                )
-                                .into_pod(),
-                                color: light.color.into_pod(),
-                                direction: light.direction.into_pod(),
-                                angle: light.angle.cos(),
-                                intensity: light.intensity,
-                                range: light.range,
-                                smoothness: light.smoothness,
-                            }
-                            .std140(),
-                        )
-                    } else {
-                        None
-                    }
-                })
-                .take(MAX_SPOT_LIGHTS);
+            let point_lights = lights_in(&gathered.point_lights, layers).take(MAX_POINT_LIGHTS);
+            let dir_lights = lights_in(&gathered.dir_lights, layers).take(MAX_DIR_LIGHTS);
+            let spot_lights = lights_in(&gathered.spot_lights, layers).take(MAX_SPOT_LIGHTS);
 
             use util::{usize_range, write_into_slice};
             write_into_slice(
@@ -260,7 +623,10 @@ impl<B: Backend> PerImageEnvironmentSub<B> {
                 &mut dst_slice[usize_range(slight_range)],
                 spot_lights.tap_count(&mut env.spot_light_count),
             );
-            write_into_slice(&mut dst_slice[usize_range(projview_range)], Some(projview));
+            write_into_slice(
+                &mut dst_slice[usize_range(projview_range)],
+                Some(gathered.camera.projview),
+            );
             write_into_slice(&mut dst_slice[usize_range(env_range)], Some(env));
         }
 
@@ -0,0 +1,149 @@
+//! Environment map submodule for binding the image-based lighting maps baked by `DrawIblBake`.
+use crate::{
+    rendy::{
+        command::RenderPassEncoder,
+        factory::Factory,
+        graph::{GraphContext, ImageAccess, NodeImage},
+        hal::{
+            self,
+            format::{Aspects, Swizzle},
+            image::{Filter, SamplerInfo, SubresourceRange, ViewKind, WrapMode},
+            pso::{Descriptor, DescriptorType, ShaderStageFlags},
+        },
+        resource::{
+            DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle, ImageView,
+            ImageViewInfo, Sampler,
+        },
+    },
+    types::Backend,
+    util,
+};
+
+/// Number of images read by `EnvironmentMapSub`: the irradiance map, the specular map and the
+/// BRDF lookup table, in this order.
+pub const ENVIRONMENT_MAP_IMAGES: usize = 3;
+
+/// Submodule binding the images baked by `DrawIblBake` as a descriptor set of samplers.
+///
+/// Render groups using it must request `EnvironmentMapSub::image_accesses` and be built with the
+/// baked images, see `RenderBase3D::with_environment_map`.
+#[derive(Debug)]
+pub struct EnvironmentMapSub<B: Backend> {
+    layout: RendyHandle<DescriptorSetLayout<B>>,
+    set: Escape<DescriptorSet<B>>,
+    _views: Vec<Escape<ImageView<B>>>,
+    _samplers: Vec<RendyHandle<Sampler<B>>>,
+}
+
+impl<B: Backend> EnvironmentMapSub<B> {
+    /// Accesses of the baked images, to be returned by `RenderGroupDesc::images`.
+    pub fn image_accesses() -> Vec<ImageAccess> {
+        vec![
+            ImageAccess {
+                access: hal::image::Access::SHADER_READ,
+                usage: hal::image::Usage::SAMPLED,
+                layout: hal::image::Layout::ShaderReadOnlyOptimal,
+                stages: hal::pso::PipelineStage::FRAGMENT_SHADER,
+            };
+            ENVIRONMENT_MAP_IMAGES
+        ]
+    }
+
+    /// Create the descriptor set of the baked images passed to the render group.
+    pub fn new(
+        ctx: &GraphContext<B>,
+        factory: &Factory<B>,
+        images: &[NodeImage],
+    ) -> Result<Self, failure::Error> {
+        let layout: RendyHandle<DescriptorSetLayout<B>> = factory
+            .create_descriptor_set_layout(util::set_layout_bindings(Some((
+                ENVIRONMENT_MAP_IMAGES as u32,
+                DescriptorType::CombinedImageSampler,
+                ShaderStageFlags::FRAGMENT,
+            ))))?
+            .into();
+
+        // Equirectangular maps wrap around horizontally, the lookup table doesn't.
+        let mut equirect = SamplerInfo::new(Filter::Linear, WrapMode::Clamp);
+        equirect.wrap_mode.0 = WrapMode::Tile;
+        let samplers = vec![
+            factory.get_sampler(equirect.clone())?,
+            factory.get_sampler(equirect)?,
+            factory.get_sampler(SamplerInfo::new(Filter::Linear, WrapMode::Clamp))?,
+        ];
+
+        let mut views = Vec::with_capacity(ENVIRONMENT_MAP_IMAGES);
+        for node_image in images.iter().take(ENVIRONMENT_MAP_IMAGES) {
+            let image = ctx
+                .get_image(node_image.id)
+                .ok_or_else(|| failure::format_err!("Environment map image is missing."))?;
+            views.push(factory.create_image_view(
+                image.clone(),
+                ImageViewInfo {
+                    view_kind: ViewKind::D2,
+                    format: image.format(),
+                    swizzle: Swizzle::NO,
+                    range: SubresourceRange {
+                        aspects: Aspects::COLOR,
+                        levels: 0..1,
+                        layers: 0..1,
+                    },
+                },
+            )?);
+        }
+        if views.len() != ENVIRONMENT_MAP_IMAGES {
+            return Err(failure::format_err!(
+                "Expected {} environment map images, got {}.",
+                ENVIRONMENT_MAP_IMAGES,
+                views.len()
+            ));
+        }
+
+        let set = factory.create_descriptor_set(layout.clone())?;
+        unsafe {
+            factory.write_descriptor_sets(views.iter().zip(&samplers).enumerate().map(
+                |(binding, (view, sampler))| {
+                    util::desc_write(
+                        set.raw(),
+                        binding as u32,
+                        Descriptor::CombinedImageSampler(
+                            view.raw(),
+                            hal::image::Layout::ShaderReadOnlyOptimal,
+                            sampler.raw(),
+                        ),
+                    )
+                },
+            ));
+        }
+
+        Ok(Self {
+            layout,
+            set,
+            _views: views,
+            _samplers: samplers,
+        })
+    }
+
+    /// Returns the raw `DescriptorSetLayout` of the baked images
+    pub fn raw_layout(&self) -> &B::DescriptorSetLayout {
+        self.layout.raw()
+    }
+
+    /// Binds the baked images.
+    #[inline]
+    pub fn bind(
+        &self,
+        pipeline_layout: &B::PipelineLayout,
+        set_id: u32,
+        encoder: &mut RenderPassEncoder<'_, B>,
+    ) {
+        unsafe {
+            encoder.bind_graphics_descriptor_sets(
+                pipeline_layout,
+                set_id,
+                Some(self.set.raw()),
+                std::iter::empty(),
+            );
+        }
+    }
+}
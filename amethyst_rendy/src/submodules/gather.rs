@@ -1,8 +1,8 @@
 //! Helper gatherer structures for collecting information about the world.
 use crate::{
-    camera::{ActiveCamera, Camera},
+    camera::{ActiveCamera, Camera, Exposure},
     pod::{self, IntoPod},
-    resources::AmbientColor,
+    resources::{AmbientColor, ClipMode, ClipPlanes, EnvironmentMap, HemisphereAmbient},
 };
 use amethyst_core::{
     ecs::{Entities, Entity, Join, Read, ReadStorage, SystemData, World},
@@ -23,6 +23,8 @@ pub struct CameraGatherer {
     pub camera_position: vec3,
     /// Fetched camera projection matrix.
     pub projview: Std140<pod::ViewArgs>,
+    /// Fetched camera exposure.
+    pub exposure: Exposure,
 }
 
 impl CameraGatherer {
@@ -66,13 +68,19 @@ impl CameraGatherer {
     ///
     /// The matrix returned is the camera's `Projection` matrix and the camera `Transform::global_view_matrix`
     pub fn gather(world: &World) -> Self {
+        Self::gather_clipped(world, ClipMode::Disabled)
+    }
+
+    /// Like `gather`, also including the `ClipPlanes` clipping with `mode`.
+    pub fn gather_clipped(world: &World, mode: ClipMode) -> Self {
         #[cfg(feature = "profiler")]
         profile_scope!("gather_cameras");
 
-        let (active_camera, cameras, transforms) = <(
+        let (active_camera, cameras, transforms, clip_planes) = <(
             Read<'_, ActiveCamera>,
             ReadStorage<'_, Camera>,
             ReadStorage<'_, Transform>,
+            Option<Read<'_, ClipPlanes>>,
         )>::fetch(world);
 
         let defcam = Camera::standard_2d(1.0, 1.0);
@@ -102,17 +110,20 @@ impl CameraGatherer {
         let proj_view: [[f32; 4]; 4] = ((*proj) * view).into();
         let proj: [[f32; 4]; 4] = (*proj).into();
         let view: [[f32; 4]; 4] = convert::<_, Matrix4<f32>>(transform.global_view_matrix()).into();
+        let clip_planes = clip_planes.map_or_else(ClipPlanes::default, |planes| *planes);
 
         let projview = pod::ViewArgs {
             proj: proj.into(),
             view: view.into(),
             proj_view: proj_view.into(),
+            clip_planes: clip_planes.columns(mode).into(),
         }
         .std140();
 
         Self {
             camera_position,
             projview,
+            exposure: *camera.exposure(),
         }
     }
 }
@@ -123,10 +134,49 @@ pub struct AmbientGatherer;
 impl AmbientGatherer {
     /// If an `AmbientColor` exists in the world, return it - otherwise return pure white.
     pub fn gather(world: &World) -> vec3 {
+        Self::gather_exposed(world, 1.0)
+    }
+
+    /// Like `gather`, scaled by the exposure of the camera.
+    pub fn gather_exposed(world: &World, exposure: f32) -> vec3 {
         let ambient_color = <Option<Read<'_, AmbientColor>>>::fetch(world);
         ambient_color.map_or([0.0, 0.0, 0.0].into(), |c| {
             let (r, g, b, _) = c.0.into_components();
-            [r, g, b].into()
+            [r * exposure, g * exposure, b * exposure].into()
         })
     }
+
+    /// Return the sky color, ground color and up direction of the `HemisphereAmbient` if it exists
+    /// in the world, scaled by the exposure of the camera. Otherwise both colors are the exposed
+    /// `AmbientColor`, lighting all directions alike.
+    pub fn gather_hemisphere(world: &World, exposure: f32) -> (vec3, vec3, vec3) {
+        let hemisphere = <Option<Read<'_, HemisphereAmbient>>>::fetch(world);
+        match hemisphere {
+            Some(hemisphere) => {
+                let exposed = |color: palette::Srgb| {
+                    let (r, g, b) = color.into_components();
+                    [r * exposure, g * exposure, b * exposure].into()
+                };
+                let up = hemisphere
+                    .up
+                    .try_normalize(std::f32::EPSILON)
+                    .unwrap_or_else(Vector3::y);
+                (
+                    exposed(hemisphere.sky),
+                    exposed(hemisphere.ground),
+                    up.into_pod(),
+                )
+            }
+            None => {
+                let ambient = Self::gather_exposed(world, exposure);
+                (ambient, ambient, Vector3::y().into_pod())
+            }
+        }
+    }
+
+    /// If an `EnvironmentMap` with a source exists in the world, return its intensity -
+    /// otherwise return 0, selecting the ambient color.
+    pub fn gather_ibl_intensity(world: &World) -> float {
+        <Option<Read<'_, EnvironmentMap>>>::fetch(world).map_or(0.0, |map| map.shader_intensity())
+    }
 }
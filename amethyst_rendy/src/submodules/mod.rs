@@ -1,5 +1,6 @@
 //! Various helpers and implementations for sub functions of render passes.
 mod environment;
+mod environment_map;
 mod flat_environment;
 mod material;
 mod skinning;
@@ -10,6 +11,7 @@ mod vertex;
 pub mod gather;
 
 pub use environment::*;
+pub use environment_map::*;
 pub use flat_environment::*;
 pub use material::*;
 pub use skinning::*;
@@ -7,7 +7,7 @@ use crate::{
         memory::Write as _,
         resource::{Buffer, DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle},
     },
-    skinning::JointTransforms,
+    skinning::{dual_quaternion_joint, JointTransforms, SkinningMode},
     types::Backend,
     util,
 };
@@ -20,6 +20,7 @@ use thread_profiler::profile_scope;
 #[derive(Debug)]
 pub struct SkinningSub<B: Backend> {
     layout: RendyHandle<DescriptorSetLayout<B>>,
+    mode: SkinningMode,
     skin_offset_map: FnvHashMap<u32, u32>,
     staging: Vec<[[f32; 4]; 4]>,
     per_image: Vec<PerImageSkinningSub<B>>,
@@ -32,10 +33,12 @@ struct PerImageSkinningSub<B: Backend> {
 }
 
 impl<B: Backend> SkinningSub<B> {
-    /// Create a new `SkinningSub`, allocating using the provided `Factory`
-    pub fn new(factory: &Factory<B>) -> Result<Self, failure::Error> {
+    /// Create a new `SkinningSub` writing joints for the given `SkinningMode`, allocating using
+    /// the provided `Factory`
+    pub fn new(factory: &Factory<B>, mode: SkinningMode) -> Result<Self, failure::Error> {
         Ok(Self {
             layout: set_layout! {factory, [1] StorageBuffer hal::pso::ShaderStageFlags::VERTEX},
+            mode,
             skin_offset_map: Default::default(),
             staging: Vec::new(),
             per_image: Vec::new(),
@@ -67,17 +70,23 @@ impl<B: Backend> SkinningSub<B> {
         profile_scope!("insert");
 
         let staging = &mut self.staging;
+        let mode = self.mode;
         *self
             .skin_offset_map
             .entry(joints.skin.id())
             .or_insert_with(|| {
                 let len = staging.len();
-                staging.extend(
-                    joints
-                        .matrices
-                        .iter()
-                        .map(|m| -> [[f32; 4]; 4] { (*m).into() }),
-                );
+                match mode {
+                    SkinningMode::Linear => staging.extend(
+                        joints
+                            .matrices
+                            .iter()
+                            .map(|m| -> [[f32; 4]; 4] { (*m).into() }),
+                    ),
+                    SkinningMode::DualQuaternion => {
+                        staging.extend(joints.matrices.iter().map(dual_quaternion_joint))
+                    }
+                }
                 len as u32
             })
     }
@@ -1,7 +1,12 @@
 //! Renderer system
 use crate::{
+    atlas::TextureAtlas,
     camera::{ActiveCamera, Camera},
     debug_drawing::DebugLinesComponent,
+    dynamic_mesh::DynamicMesh,
+    dynamic_texture::DynamicTexture,
+    gpu_memory::{device_local_bytes, image_bytes, GpuMemoryStats},
+    layers::RenderLayers,
     light::Light,
     mtl::{Material, MaterialDefaults},
     resources::Tint,
@@ -14,7 +19,10 @@ use crate::{
 use amethyst_assets::{AssetStorage, Handle, HotReloadStrategy, ProcessingState, ThreadPool};
 use amethyst_core::{
     components::Transform,
-    ecs::{Read, ReadExpect, ReadStorage, RunNow, System, SystemData, World, Write, WriteExpect},
+    ecs::{
+        Join, Read, ReadExpect, ReadStorage, RunNow, System, SystemData, World, Write, WriteExpect,
+        WriteStorage,
+    },
     timing::Time,
     Hidden, HiddenPropagate,
 };
@@ -79,6 +87,7 @@ type SetupData<'a> = (
     ReadStorage<'a, HiddenPropagate>,
     ReadStorage<'a, DebugLinesComponent>,
     ReadStorage<'a, Transparent>,
+    ReadStorage<'a, RenderLayers>,
     ReadStorage<'a, Transform>,
     ReadStorage<'a, SpriteRender>,
     Option<Read<'a, Visibility>>,
@@ -126,7 +135,13 @@ where
         self.graph
             .as_mut()
             .unwrap()
-            .run(&mut factory, self.families.as_mut().unwrap(), world)
+            .run(&mut factory, self.families.as_mut().unwrap(), world);
+
+        // The graph is done recording and submitting this frame, so it no longer refers to the
+        // unloaded assets. The frames still executing on the GPU are not waited for.
+        if let Some(mut unloaded) = world.try_fetch_mut::<UnloadedAssets>() {
+            unloaded.release();
+        }
     }
 }
 
@@ -182,6 +197,31 @@ where
     }
 }
 
+/// Meshes and textures which have been unloaded from their `AssetStorage`s.
+///
+/// They are kept here until the `RenderingSystem` has submitted the current frame, so that they
+/// are not dropped while the frame is being recorded. They are dropped right after submission,
+/// without waiting for the fences of the frames in flight.
+#[derive(Debug, Default)]
+pub struct UnloadedAssets {
+    meshes: Vec<Mesh>,
+    textures: Vec<Texture>,
+}
+
+impl UnloadedAssets {
+    fn release(&mut self) {
+        if !self.meshes.is_empty() || !self.textures.is_empty() {
+            log::debug!(
+                "Releasing {} meshes and {} textures",
+                self.meshes.len(),
+                self.textures.len()
+            );
+        }
+        self.meshes.clear();
+        self.textures.clear();
+    }
+}
+
 /// Asset processing system for `Mesh` asset type.
 #[derive(Debug, derivative::Derivative)]
 #[derivative(Default(bound = ""))]
@@ -194,25 +234,35 @@ impl<'a, B: Backend> System<'a> for MeshProcessorSystem<B> {
         ReadExpect<'a, Arc<ThreadPool>>,
         Option<Read<'a, HotReloadStrategy>>,
         ReadExpect<'a, Factory<B>>,
+        Write<'a, UnloadedAssets>,
+        Write<'a, GpuMemoryStats>,
     );
 
     fn run(
         &mut self,
-        (mut mesh_storage, queue_id, time, pool, strategy, factory): Self::SystemData,
+        (mut mesh_storage, queue_id, time, pool, strategy, factory, mut unloaded, mut memory): Self::SystemData,
     ) {
         #[cfg(feature = "profiler")]
         profile_scope!("mesh_processor");
 
-        mesh_storage.process(
-            |b| {
+        mesh_storage.process_custom_drop_named(
+            |b, handle, name| {
                 #[cfg(feature = "profiler")]
                 profile_scope!("process_mesh");
 
+                // The buffer sizes of a mesh are not exposed, measure them from the allocator.
+                let before = device_local_bytes(&*factory, true);
                 b.0.build(*queue_id, &factory)
+                    .map(|mesh| {
+                        let bytes = device_local_bytes(&*factory, true).saturating_sub(before);
+                        memory.track_mesh(handle.id(), name, bytes);
+                        mesh
+                    })
                     .map(B::wrap_mesh)
                     .map(ProcessingState::Loaded)
                     .map_err(|e| e.compat().into())
             },
+            |mesh| unloaded.meshes.push(mesh),
             time.frame_number(),
             &**pool,
             strategy.as_deref(),
@@ -220,6 +270,28 @@ impl<'a, B: Backend> System<'a> for MeshProcessorSystem<B> {
     }
 }
 
+/// Asset processing system for headless applications, which have no GPU to upload to.
+///
+/// Mesh and texture files are still read and parsed by their formats, so load errors and
+/// progress are reported as usual, but the resulting data is dropped and handles to these
+/// assets never resolve.
+#[derive(Debug, Default)]
+pub struct HeadlessAssetProcessorSystem;
+impl<'a> System<'a> for HeadlessAssetProcessorSystem {
+    type SystemData = (
+        Write<'a, AssetStorage<Mesh>>,
+        Write<'a, AssetStorage<Texture>>,
+    );
+
+    fn run(&mut self, (mut mesh_storage, mut texture_storage): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("headless_asset_processor");
+
+        mesh_storage.discard_processed(|_| Ok(()));
+        texture_storage.discard_processed(|_| Ok(()));
+    }
+}
+
 /// Asset processing system for `Texture` asset type.
 #[derive(Debug, derivative::Derivative)]
 #[derivative(Default(bound = ""))]
@@ -232,17 +304,28 @@ impl<'a, B: Backend> System<'a> for TextureProcessorSystem<B> {
         ReadExpect<'a, Arc<ThreadPool>>,
         Option<Read<'a, HotReloadStrategy>>,
         WriteExpect<'a, Factory<B>>,
+        Write<'a, UnloadedAssets>,
+        Write<'a, GpuMemoryStats>,
     );
 
     fn run(
         &mut self,
-        (mut texture_storage, queue_id, time, pool, strategy, mut factory): Self::SystemData,
+        (
+            mut texture_storage,
+            queue_id,
+            time,
+            pool,
+            strategy,
+            mut factory,
+            mut unloaded,
+            mut memory,
+        ): Self::SystemData,
     ) {
         #[cfg(feature = "profiler")]
         profile_scope!("texture_processor");
 
-        texture_storage.process(
-            |b| {
+        texture_storage.process_custom_drop_named(
+            |b, handle, name| {
                 #[cfg(feature = "profiler")]
                 profile_scope!("process_texture");
 
@@ -256,10 +339,17 @@ impl<'a, B: Backend> System<'a> for TextureProcessorSystem<B> {
                     },
                     &mut factory,
                 )
+                .map(|texture| {
+                    let image = texture.image();
+                    let bytes = image_bytes(image.kind(), image.levels(), image.format());
+                    memory.track_texture(handle.id(), name, bytes);
+                    texture
+                })
                 .map(B::wrap_texture)
                 .map(ProcessingState::Loaded)
                 .map_err(|e| e.compat().into())
             },
+            |texture| unloaded.textures.push(texture),
             time.frame_number(),
             &**pool,
             strategy.as_deref(),
@@ -267,6 +357,129 @@ impl<'a, B: Backend> System<'a> for TextureProcessorSystem<B> {
     }
 }
 
+/// Uploads the `DynamicMesh`es which changed, replacing the mesh under their handle.
+#[derive(Debug, derivative::Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct DynamicMeshSystem<B: Backend>(PhantomData<B>);
+impl<'a, B: Backend> System<'a> for DynamicMeshSystem<B> {
+    type SystemData = (
+        WriteStorage<'a, DynamicMesh>,
+        Write<'a, AssetStorage<Mesh>>,
+        ReadExpect<'a, QueueId>,
+        ReadExpect<'a, Factory<B>>,
+        Write<'a, UnloadedAssets>,
+    );
+
+    fn run(
+        &mut self,
+        (mut dynamic_meshes, mut mesh_storage, queue_id, factory, mut unloaded): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("dynamic_mesh_system");
+
+        for dynamic in (&mut dynamic_meshes).join() {
+            // Wait for the initial mesh, which is built from the same data
+            if !dynamic.is_dirty() || mesh_storage.get(dynamic.mesh()).is_none() {
+                continue;
+            }
+            match dynamic.data().builder().build(*queue_id, &factory) {
+                Ok(mesh) => {
+                    let old = mesh_storage.replace(dynamic.mesh(), B::wrap_mesh(mesh));
+                    // Frames being recorded may still use the old buffers
+                    unloaded.meshes.push(old);
+                }
+                Err(err) => log::error!("Failed to upload dynamic mesh: {}", err),
+            }
+            dynamic.mark_uploaded();
+        }
+    }
+}
+
+/// Uploads the pending updates of `DynamicTexture`s and of the pages of `TextureAtlas`es once
+/// their texture is loaded.
+///
+/// The uploads are submitted before the next frame, after the frames in flight are done
+/// sampling the texture.
+#[derive(Debug, derivative::Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct DynamicTextureSystem<B: Backend>(PhantomData<B>);
+impl<'a, B: Backend> System<'a> for DynamicTextureSystem<B> {
+    type SystemData = (
+        WriteStorage<'a, DynamicTexture>,
+        WriteStorage<'a, TextureAtlas>,
+        Read<'a, AssetStorage<Texture>>,
+        ReadExpect<'a, QueueId>,
+        ReadExpect<'a, Factory<B>>,
+    );
+
+    fn run(
+        &mut self,
+        (mut dynamic_textures, mut atlases, texture_storage, queue_id, factory): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("dynamic_texture_system");
+
+        use rendy::hal::{
+            format::Aspects,
+            image::{Access, Extent, Layout, Offset, SubresourceLayers},
+            pso::PipelineStage,
+        };
+
+        let state = ImageState {
+            queue: *queue_id,
+            stage: PipelineStage::VERTEX_SHADER | PipelineStage::FRAGMENT_SHADER,
+            access: Access::SHADER_READ,
+            layout: Layout::ShaderReadOnlyOptimal,
+        };
+
+        let atlas_pages = (&mut atlases).join().flat_map(|atlas| atlas.textures_mut());
+        for dynamic in (&mut dynamic_textures).join().chain(atlas_pages) {
+            if !dynamic.has_pending_updates() {
+                continue;
+            }
+            let texture = match texture_storage
+                .get(dynamic.texture())
+                .and_then(B::unwrap_texture)
+            {
+                Some(texture) => texture,
+                None => continue,
+            };
+
+            for update in dynamic.drain_updates() {
+                let region = update.region;
+                let result = unsafe {
+                    factory.upload_image(
+                        texture.image().clone(),
+                        update.data_width,
+                        region.height,
+                        SubresourceLayers {
+                            aspects: Aspects::COLOR,
+                            level: 0,
+                            layers: 0..1,
+                        },
+                        Offset {
+                            x: region.x as i32,
+                            y: region.y as i32,
+                            z: 0,
+                        },
+                        Extent {
+                            width: region.width,
+                            height: region.height,
+                            depth: 1,
+                        },
+                        &update.data,
+                        state,
+                        state,
+                    )
+                };
+                if let Err(err) = result {
+                    log::error!("Failed to update dynamic texture: {:?}", err);
+                }
+            }
+        }
+    }
+}
+
 fn create_default_mat<B: Backend>(world: &mut World) -> Material {
     use crate::mtl::TextureOffset;
 
@@ -280,6 +493,7 @@ fn create_default_mat<B: Backend>(world: &mut World) -> Material {
     let metallic_roughness = load_from_linear_rgba(LinSrgba::new(0.0, 0.5, 0.0, 0.0));
     let ambient_occlusion = load_from_linear_rgba(LinSrgba::new(1.0, 1.0, 1.0, 1.0));
     let cavity = load_from_linear_rgba(LinSrgba::new(1.0, 1.0, 1.0, 1.0));
+    let lightmap = load_from_linear_rgba(LinSrgba::new(1.0, 1.0, 1.0, 1.0));
 
     let tex_storage = world.fetch();
 
@@ -289,6 +503,7 @@ fn create_default_mat<B: Backend>(world: &mut World) -> Material {
     let metallic_roughness = loader.load_from_data(metallic_roughness.into(), (), &tex_storage);
     let ambient_occlusion = loader.load_from_data(ambient_occlusion.into(), (), &tex_storage);
     let cavity = loader.load_from_data(cavity.into(), (), &tex_storage);
+    let lightmap = loader.load_from_data(lightmap.into(), (), &tex_storage);
 
     Material {
         alpha_cutoff: 0.01,
@@ -299,5 +514,9 @@ fn create_default_mat<B: Backend>(world: &mut World) -> Material {
         ambient_occlusion,
         cavity,
         uv_offset: TextureOffset::default(),
+        unlit: false,
+        lightmap,
+        lightmap_mode: Default::default(),
+        skip_dynamic_lights: false,
     }
 }
@@ -0,0 +1,542 @@
+//! Large terrains drawn from a heightmap.
+//!
+//! A `Terrain` is drawn by `DrawTerrainDesc` as a quadtree of chunks, all sharing the same grid
+//! of `TERRAIN_CHUNK_CELLS` cells, displaced by the heightmap in the vertex shader. Chunks close
+//! to the camera are split into four smaller chunks, down to chunks spanning about one texel of
+//! the heightmap per cell, and chunks outside of the view are skipped. Neighbouring chunks of
+//! different levels don't share all their vertices, the cracks between them are hidden by skirts
+//! hanging from the border of every chunk.
+//!
+//! The heights are uploaded to a `DynamicTexture` added to the terrain entity by the
+//! `TerrainSystem`, changes made with `Terrain::set_heights` are streamed to it.
+use crate::{
+    dynamic_texture::{DynamicTexture, DynamicTextureError, TextureRegion},
+    types::Texture,
+};
+use amethyst_assets::{Asset, AssetStorage, Format, Handle, Loader};
+use amethyst_core::{
+    ecs::prelude::{
+        Component, DenseVecStorage, Entities, Join, Read, ReadExpect, System, VecStorage,
+        WriteStorage,
+    },
+    math::{Point3, Vector2, Vector3},
+};
+use amethyst_error::Error;
+use rendy::hal::{
+    format::Format as HalFormat,
+    image::{Filter, SamplerInfo, WrapMode},
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Cells along each side of the grid of a terrain chunk.
+pub const TERRAIN_CHUNK_CELLS: u32 = 32;
+/// Maximum number of texture layers blended by the splat map of a terrain.
+pub const MAX_TERRAIN_LAYERS: usize = 4;
+
+/// Grid of heights between 0 and 1, sampled along x by columns and along z by rows.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Heightmap {
+    width: u32,
+    depth: u32,
+    heights: Vec<f32>,
+}
+
+impl Heightmap {
+    /// Creates a heightmap of `width` columns and `depth` rows from its heights, row by row.
+    ///
+    /// Both sizes must be at least 2, and `heights` must hold `width * depth` values.
+    pub fn new(width: u32, depth: u32, heights: Vec<f32>) -> Result<Self, Error> {
+        if width < 2 || depth < 2 {
+            return Err(Error::from_string(format!(
+                "Heightmap of {}x{} samples is too small, at least 2x2 are required",
+                width, depth
+            )));
+        }
+        if heights.len() != width as usize * depth as usize {
+            return Err(Error::from_string(format!(
+                "Heightmap of {}x{} samples requires {} heights, got {}",
+                width,
+                depth,
+                width as usize * depth as usize,
+                heights.len()
+            )));
+        }
+        Ok(Heightmap {
+            width,
+            depth,
+            heights,
+        })
+    }
+
+    /// Creates a heightmap of `width` columns and `depth` rows from the height of each sample.
+    pub fn from_fn(
+        width: u32,
+        depth: u32,
+        mut height: impl FnMut(u32, u32) -> f32,
+    ) -> Result<Self, Error> {
+        let heights = (0..depth)
+            .flat_map(|z| (0..width).map(move |x| (x, z)))
+            .map(|(x, z)| height(x, z))
+            .collect();
+        Self::new(width, depth, heights)
+    }
+
+    /// Number of samples along x.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Number of samples along z.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Heights of the samples, row by row.
+    pub fn heights(&self) -> &[f32] {
+        &self.heights
+    }
+
+    /// Height of the sample at column `x` and row `z`.
+    pub fn get(&self, x: u32, z: u32) -> f32 {
+        self.heights[(z * self.width + x) as usize]
+    }
+
+    /// Height at the coordinates `u` and `v` between 0 and 1, interpolated between the four
+    /// closest samples like the vertex shader does.
+    pub fn sample(&self, u: f32, v: f32) -> f32 {
+        let x = u.max(0.0).min(1.0) * (self.width - 1) as f32;
+        let z = v.max(0.0).min(1.0) * (self.depth - 1) as f32;
+        let (x0, z0) = (
+            (x.floor() as u32).min(self.width - 2),
+            (z.floor() as u32).min(self.depth - 2),
+        );
+        let (fx, fz) = (x - x0 as f32, z - z0 as f32);
+        let top = lerp(self.get(x0, z0), self.get(x0 + 1, z0), fx);
+        let bottom = lerp(self.get(x0, z0 + 1), self.get(x0 + 1, z0 + 1), fx);
+        lerp(top, bottom, fz)
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+impl Asset for Heightmap {
+    const NAME: &'static str = "renderer::Heightmap";
+    type Data = Self;
+    type HandleStorage = VecStorage<Handle<Self>>;
+}
+
+/// Format of raw heightmaps, exported by most terrain editors: unsigned 16 bits little endian
+/// samples, row by row, without header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RawHeightmapFormat {
+    /// Number of samples along x.
+    pub width: u32,
+    /// Number of samples along z.
+    pub depth: u32,
+}
+
+impl Format<Heightmap> for RawHeightmapFormat {
+    fn name(&self) -> &'static str {
+        "RAW_HEIGHTMAP"
+    }
+
+    fn import_simple(&self, bytes: Vec<u8>) -> Result<Heightmap, Error> {
+        let heights = bytes
+            .chunks_exact(2)
+            .map(|sample| f32::from(u16::from_le_bytes([sample[0], sample[1]])) / 65535.0)
+            .collect();
+        Heightmap::new(self.width, self.depth, heights)
+    }
+}
+
+/// Texture blended over a terrain by a channel of its splat map.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TerrainLayer {
+    /// Color of the layer.
+    pub albedo: Handle<Texture>,
+    /// Size in world units of a repetition of the texture over the terrain.
+    pub tiling: f32,
+}
+
+/// Chunk of the quadtree of a terrain, selected by `Terrain::select_chunks`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TerrainChunk {
+    /// Heightmap coordinates of the corner of the chunk, between 0 and 1.
+    pub offset: Vector2<f32>,
+    /// Size of the chunk in heightmap coordinates.
+    pub size: f32,
+    /// Level of the chunk in the quadtree, 0 being the whole terrain.
+    pub level: u32,
+}
+
+/// A terrain displaced by a heightmap, centered on the `Transform` of its entity.
+///
+/// The terrain spans `size` along the local x and z axes and rises from 0 to `max_height` along
+/// y. Up to `MAX_TERRAIN_LAYERS` textures are blended by the red, green, blue and alpha channels
+/// of the `splat` texture, stretched over the terrain. Without a splat map only the first layer
+/// is drawn.
+#[derive(Clone, Debug)]
+pub struct Terrain {
+    heightmap: Heightmap,
+    /// Extent of the terrain along the local x and z axes.
+    pub size: Vector2<f32>,
+    /// Height of the terrain where the heightmap is 1.
+    pub max_height: f32,
+    /// Weights of the `layers`, one per channel.
+    pub splat: Option<Handle<Texture>>,
+    /// Textures blended over the terrain.
+    pub layers: Vec<TerrainLayer>,
+    /// Chunks closer to the camera than their size times this factor are split, higher values
+    /// draw more detail further away.
+    pub lod_factor: f32,
+    /// Depth of the skirts hiding cracks between chunks of different levels.
+    pub skirt_depth: f32,
+    pending: Vec<TextureRegion>,
+}
+
+impl Terrain {
+    /// Creates a terrain spanning `size` along x and z, displaced up to `max_height`.
+    pub fn new(heightmap: Heightmap, size: Vector2<f32>, max_height: f32) -> Self {
+        let pending = vec![TextureRegion::new(0, 0, heightmap.width, heightmap.depth)];
+        Terrain {
+            heightmap,
+            size,
+            max_height,
+            splat: None,
+            layers: Vec::new(),
+            lod_factor: 2.0,
+            skirt_depth: max_height * 0.05,
+            pending,
+        }
+    }
+
+    /// Sets the splat map blending the layers.
+    pub fn with_splat(mut self, splat: Handle<Texture>) -> Self {
+        self.splat = Some(splat);
+        self
+    }
+
+    /// Adds a layer, blended by the next channel of the splat map. Layers past
+    /// `MAX_TERRAIN_LAYERS` are ignored.
+    pub fn with_layer(mut self, albedo: Handle<Texture>, tiling: f32) -> Self {
+        if self.layers.len() < MAX_TERRAIN_LAYERS {
+            self.layers.push(TerrainLayer { albedo, tiling });
+        } else {
+            log::warn!(
+                "Terrains blend at most {} layers, ignoring the extra layer",
+                MAX_TERRAIN_LAYERS
+            );
+        }
+        self
+    }
+
+    /// Sets the distance factor at which chunks are split.
+    pub fn with_lod_factor(mut self, lod_factor: f32) -> Self {
+        self.lod_factor = lod_factor;
+        self
+    }
+
+    /// Heightmap of the terrain.
+    pub fn heightmap(&self) -> &Heightmap {
+        &self.heightmap
+    }
+
+    /// Replaces the heights of `region` of the heightmap with `heights`, row by row, and queues
+    /// their upload.
+    pub fn set_heights(
+        &mut self,
+        region: TextureRegion,
+        heights: &[f32],
+    ) -> Result<(), DynamicTextureError> {
+        let (width, depth) = (self.heightmap.width, self.heightmap.depth);
+        if region.width == 0
+            || region.height == 0
+            || region.x + region.width > width
+            || region.y + region.height > depth
+        {
+            return Err(DynamicTextureError::RegionOutOfBounds {
+                region,
+                size: (width, depth),
+            });
+        }
+        let expected = region.width as usize * region.height as usize;
+        if heights.len() < expected {
+            return Err(DynamicTextureError::DataTooShort {
+                expected: expected * 4,
+                actual: heights.len() * 4,
+            });
+        }
+
+        for (row, source) in heights[..expected]
+            .chunks(region.width as usize)
+            .enumerate()
+        {
+            let start = ((region.y + row as u32) * width + region.x) as usize;
+            self.heightmap.heights[start..start + source.len()].copy_from_slice(source);
+        }
+        self.pending.push(region);
+        Ok(())
+    }
+
+    /// Height of the terrain at `x` and `z` in its local space, or `None` outside of it.
+    pub fn height_at(&self, x: f32, z: f32) -> Option<f32> {
+        let (u, v) = self.local_to_uv(x, z)?;
+        Some(self.heightmap.sample(u, v) * self.max_height)
+    }
+
+    /// Normal of the terrain at `x` and `z` in its local space, or `None` outside of it.
+    pub fn normal_at(&self, x: f32, z: f32) -> Option<Vector3<f32>> {
+        let (u, v) = self.local_to_uv(x, z)?;
+        let du = 1.0 / (self.heightmap.width - 1) as f32;
+        let dv = 1.0 / (self.heightmap.depth - 1) as f32;
+        let height = |u: f32, v: f32| self.heightmap.sample(u, v) * self.max_height;
+        let dx = (height(u + du, v) - height(u - du, v)) / (2.0 * du * self.size.x);
+        let dz = (height(u, v + dv) - height(u, v - dv)) / (2.0 * dv * self.size.y);
+        Some(Vector3::new(-dx, 1.0, -dz).normalize())
+    }
+
+    fn local_to_uv(&self, x: f32, z: f32) -> Option<(f32, f32)> {
+        let u = x / self.size.x + 0.5;
+        let v = z / self.size.y + 0.5;
+        if (0.0..=1.0).contains(&u) && (0.0..=1.0).contains(&v) {
+            Some((u, v))
+        } else {
+            None
+        }
+    }
+
+    /// Number of levels of the quadtree, the chunks of the last level spanning about one texel
+    /// of the heightmap per cell.
+    pub fn lod_levels(&self) -> u32 {
+        let texels = (self.heightmap.width.max(self.heightmap.depth) - 1) as f32;
+        (texels / TERRAIN_CHUNK_CELLS as f32).log2().ceil().max(0.0) as u32 + 1
+    }
+
+    /// Selects the chunks to draw for a camera at `camera` in the local space of the terrain,
+    /// skipping the chunks whose local bounding box, given as its minimum and maximum corners,
+    /// isn't `visible`.
+    pub fn select_chunks(
+        &self,
+        camera: &Point3<f32>,
+        mut visible: impl FnMut(&Point3<f32>, &Point3<f32>) -> bool,
+        out: &mut Vec<TerrainChunk>,
+    ) {
+        self.select_node(
+            camera,
+            &mut visible,
+            TerrainChunk {
+                offset: Vector2::zeros(),
+                size: 1.0,
+                level: 0,
+            },
+            self.lod_levels(),
+            out,
+        );
+    }
+
+    fn select_node(
+        &self,
+        camera: &Point3<f32>,
+        visible: &mut impl FnMut(&Point3<f32>, &Point3<f32>) -> bool,
+        chunk: TerrainChunk,
+        levels: u32,
+        out: &mut Vec<TerrainChunk>,
+    ) {
+        let min = Point3::new(
+            (chunk.offset.x - 0.5) * self.size.x,
+            -self.skirt_depth,
+            (chunk.offset.y - 0.5) * self.size.y,
+        );
+        let max = Point3::new(
+            min.x + chunk.size * self.size.x,
+            self.max_height,
+            min.z + chunk.size * self.size.y,
+        );
+        if !visible(&min, &max) {
+            return;
+        }
+
+        let closest = Point3::new(
+            camera.x.max(min.x).min(max.x),
+            camera.y.max(min.y).min(max.y),
+            camera.z.max(min.z).min(max.z),
+        );
+        let extent = chunk.size * self.size.x.max(self.size.y);
+        if chunk.level + 1 < levels && (camera - closest).norm() < extent * self.lod_factor {
+            let size = chunk.size * 0.5;
+            for &(x, z) in &[(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)] {
+                let child = TerrainChunk {
+                    offset: chunk.offset + Vector2::new(x, z) * size,
+                    size,
+                    level: chunk.level + 1,
+                };
+                self.select_node(camera, visible, child, levels, out);
+            }
+        } else {
+            out.push(chunk);
+        }
+    }
+
+    /// Takes the regions of the heightmap changed since the last call.
+    fn take_pending(&mut self) -> Vec<TextureRegion> {
+        std::mem::replace(&mut self.pending, Vec::new())
+    }
+}
+
+impl Component for Terrain {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Adds a `DynamicTexture` holding the heights to the entities of new terrains, and streams the
+/// heights changed by `Terrain::set_heights` to it.
+///
+/// The `DynamicTextureSystem` uploads the heights before the next frame.
+#[derive(Debug, Default)]
+pub struct TerrainSystem;
+
+impl TerrainSystem {
+    /// Create new terrain system
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<'a> System<'a> for TerrainSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Terrain>,
+        WriteStorage<'a, DynamicTexture>,
+        ReadExpect<'a, Loader>,
+        Read<'a, AssetStorage<Texture>>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, mut terrains, mut dynamic_textures, loader, texture_storage): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("terrain_system");
+
+        for (entity, terrain) in (&entities, &mut terrains).join() {
+            let heightmap = &terrain.heightmap;
+            let size = (heightmap.width, heightmap.depth);
+            let stale = dynamic_textures
+                .get(entity)
+                .map_or(true, |texture| texture.size() != size);
+
+            let regions = if stale {
+                let texture = match DynamicTexture::new(
+                    size.0,
+                    size.1,
+                    HalFormat::R32Sfloat,
+                    SamplerInfo::new(Filter::Nearest, WrapMode::Clamp),
+                    &loader,
+                    &texture_storage,
+                ) {
+                    Ok(texture) => texture,
+                    Err(err) => {
+                        log::error!("Failed to create terrain heightmap texture: {}", err);
+                        continue;
+                    }
+                };
+                if let Err(err) = dynamic_textures.insert(entity, texture) {
+                    log::error!("Failed to add terrain heightmap texture: {}", err);
+                    continue;
+                }
+                terrain.pending.clear();
+                vec![TextureRegion::new(0, 0, size.0, size.1)]
+            } else {
+                terrain.take_pending()
+            };
+
+            let texture = dynamic_textures
+                .get_mut(entity)
+                .expect("Unreachable: heightmap texture was added");
+            for region in regions {
+                let start = (region.y * size.0 + region.x) as usize;
+                let end = start + ((region.height - 1) * size.0 + region.width) as usize;
+                let data = heightmap.heights[start..end]
+                    .iter()
+                    .flat_map(|height| height.to_ne_bytes().to_vec())
+                    .collect::<Vec<u8>>();
+                if let Err(err) = texture.update_strided(region, &data, size.0 as usize * 4) {
+                    log::error!("Failed to update terrain heightmap texture: {}", err);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn slope() -> Terrain {
+        // Rises from 0 at x = -5 to 1 at x = 5.
+        let heightmap = Heightmap::from_fn(65, 65, |x, _| x as f32 / 64.0).unwrap();
+        Terrain::new(heightmap, Vector2::new(10.0, 10.0), 1.0)
+    }
+
+    #[test]
+    fn height_queries_interpolate_the_heightmap() {
+        let terrain = slope();
+        assert_relative_eq!(terrain.height_at(-5.0, 0.0).unwrap(), 0.0);
+        assert_relative_eq!(terrain.height_at(0.0, 3.0).unwrap(), 0.5);
+        assert_relative_eq!(terrain.height_at(2.5, -1.0).unwrap(), 0.75, epsilon = 1e-5);
+        assert_eq!(terrain.height_at(5.5, 0.0), None);
+
+        let normal = terrain.normal_at(0.0, 0.0).unwrap();
+        assert_relative_eq!(
+            normal,
+            Vector3::new(-0.1, 1.0, 0.0).normalize(),
+            epsilon = 1e-5
+        );
+    }
+
+    #[test]
+    fn set_heights_updates_a_region() {
+        let mut terrain = slope();
+        terrain.take_pending();
+        terrain
+            .set_heights(TextureRegion::new(1, 2, 2, 2), &[9.0, 8.0, 7.0, 6.0])
+            .unwrap();
+        assert_eq!(terrain.heightmap().get(2, 2), 8.0);
+        assert_eq!(terrain.heightmap().get(1, 3), 7.0);
+        assert_eq!(terrain.take_pending(), vec![TextureRegion::new(1, 2, 2, 2)]);
+
+        assert!(terrain
+            .set_heights(TextureRegion::new(64, 0, 2, 1), &[0.0, 0.0])
+            .is_err());
+    }
+
+    #[test]
+    fn chunks_are_finer_near_the_camera() {
+        let terrain = slope();
+        // 64 texels, chunks of 32 cells: two levels.
+        assert_eq!(terrain.lod_levels(), 2);
+
+        let mut chunks = Vec::new();
+        terrain.select_chunks(&Point3::new(0.0, 100.0, 0.0), |_, _| true, &mut chunks);
+        assert_eq!(chunks.len(), 1);
+
+        chunks.clear();
+        terrain.select_chunks(&Point3::new(-4.0, 1.0, -4.0), |_, _| true, &mut chunks);
+        assert_eq!(chunks.len(), 4);
+        assert!(chunks.iter().all(|chunk| chunk.level == 1));
+
+        // Culled chunks are skipped.
+        chunks.clear();
+        terrain.select_chunks(
+            &Point3::new(-4.0, 1.0, -4.0),
+            |min, _| min.x < 0.0 && min.z < 0.0,
+            &mut chunks,
+        );
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].offset, Vector2::new(0.0, 0.0));
+    }
+}
@@ -0,0 +1,249 @@
+//! Back to front ordering of transparent meshes and sprites in a single list.
+//!
+//! `VisibilitySortingSystem` and `SpriteVisibilitySortingSystem` each order their own transparent
+//! entities, so a pass drawing all transparent meshes and then all transparent sprites blends
+//! them incorrectly when they overlap in depth. `TransparentOrderSystem` merges both lists by view
+//! depth, to be drawn by `DrawMixedTransparent`.
+use crate::{
+    camera::{ActiveCamera, Camera},
+    sprite_visibility::SpriteVisibility,
+    visibility::Visibility,
+};
+use amethyst_core::{
+    ecs::prelude::{Entity, Join, Read, ReadStorage, System, Write},
+    math::{Point3, Vector3},
+    Transform,
+};
+use std::{cmp::Ordering, ops::Range};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Which pass draws a transparent renderable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransparentKind {
+    /// A mesh from `Visibility::visible_ordered`.
+    Mesh,
+    /// A sprite from `SpriteVisibility::visible_ordered`.
+    Sprite,
+}
+
+/// A transparent entity, the pass drawing it and its distance in front of the camera.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransparentRenderable {
+    /// The entity being drawn.
+    pub entity: Entity,
+    /// The pass drawing the entity.
+    pub kind: TransparentKind,
+    /// Distance of the entity along the view direction of the camera.
+    pub depth: f32,
+}
+
+/// Resource holding all visible transparent meshes and sprites, ordered back to front.
+#[derive(Default, Debug)]
+pub struct TransparentOrder {
+    /// Transparent renderables, the farthest first.
+    pub renderables: Vec<TransparentRenderable>,
+}
+
+impl TransparentOrder {
+    /// Returns the ranges of consecutive renderables drawn by the same pass, in drawing order.
+    ///
+    /// A renderer only needs to switch pipelines between two runs.
+    pub fn runs(&self) -> impl Iterator<Item = (TransparentKind, Range<usize>)> + '_ {
+        let mut start = 0;
+        std::iter::from_fn(move || {
+            let kind = self.renderables.get(start)?.kind;
+            let len = self.renderables[start..]
+                .iter()
+                .take_while(|r| r.kind == kind)
+                .count();
+            let range = start..start + len;
+            start = range.end;
+            Some((kind, range))
+        })
+    }
+}
+
+/// Merges the transparent entities of `Visibility` and `SpriteVisibility` into `TransparentOrder`,
+/// sorted back to front by their depth along the view direction of the active camera.
+///
+/// Must run after both visibility sorting systems. Entities at the same depth keep meshes
+/// before sprites.
+#[derive(Default, Debug)]
+pub struct TransparentOrderSystem;
+
+impl TransparentOrderSystem {
+    /// Create new transparent ordering system
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<'a> System<'a> for TransparentOrderSystem {
+    type SystemData = (
+        Option<Read<'a, Visibility>>,
+        Option<Read<'a, SpriteVisibility>>,
+        Write<'a, TransparentOrder>,
+        Read<'a, ActiveCamera>,
+        ReadStorage<'a, Camera>,
+        ReadStorage<'a, Transform>,
+    );
+
+    fn run(
+        &mut self,
+        (visibility, sprite_visibility, mut order, active, camera, transform): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("transparent_order_system");
+
+        let origin = Point3::origin();
+        let camera: Option<&Transform> = active
+            .entity
+            .and_then(|a| transform.get(a))
+            .or_else(|| (&camera, &transform).join().map(|ct| ct.1).next());
+        let camera_forward = camera
+            .map(|c| -c.global_matrix().column(2).xyz())
+            .unwrap_or_else(|| -Vector3::z());
+        let camera_centroid = camera
+            .map(|t| t.global_matrix().transform_point(&origin))
+            .unwrap_or_else(|| origin);
+
+        let meshes = visibility
+            .iter()
+            .flat_map(|v| v.visible_ordered.iter())
+            .map(|e| (*e, TransparentKind::Mesh));
+        let sprites = sprite_visibility
+            .iter()
+            .flat_map(|v| v.visible_ordered.iter())
+            .map(|e| (*e, TransparentKind::Sprite));
+
+        order.renderables.clear();
+        order
+            .renderables
+            .extend(meshes.chain(sprites).filter_map(|(entity, kind)| {
+                let centroid = transform
+                    .get(entity)?
+                    .global_matrix()
+                    .transform_point(&origin);
+                Some(TransparentRenderable {
+                    entity,
+                    kind,
+                    depth: (centroid - camera_centroid).dot(&camera_forward),
+                })
+            }));
+
+        // Stable, so that meshes stay before sprites at the same depth.
+        order
+            .renderables
+            .sort_by(|a, b| b.depth.partial_cmp(&a.depth).unwrap_or(Ordering::Equal));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::ecs::{Builder, RunNow, World, WorldExt};
+
+    fn transform_at(z: f32) -> Transform {
+        let mut transform = Transform::default();
+        transform.set_translation_z(z);
+        transform.copy_local_to_global();
+        transform
+    }
+
+    #[test]
+    fn alternating_stack_is_interleaved() {
+        let mut world = World::new();
+        world.register::<Camera>();
+        world.register::<Transform>();
+        world.insert(ActiveCamera::default());
+        world.insert(TransparentOrder::default());
+
+        world
+            .create_entity()
+            .with(Camera::standard_3d(100.0, 100.0))
+            .with(transform_at(10.0))
+            .build();
+
+        // Layers from near to far, alternating between sprites and meshes.
+        let layers = (0..6)
+            .map(|i| {
+                world
+                    .create_entity()
+                    .with(transform_at(5.0 - i as f32))
+                    .build()
+            })
+            .collect::<Vec<_>>();
+
+        let mut visibility = Visibility::default();
+        let mut sprite_visibility = SpriteVisibility::default();
+        for (i, entity) in layers.iter().enumerate().rev() {
+            if i % 2 == 0 {
+                sprite_visibility.visible_ordered.push(*entity);
+            } else {
+                visibility.visible_ordered.push(*entity);
+            }
+        }
+        world.insert(visibility);
+        world.insert(sprite_visibility);
+
+        TransparentOrderSystem::new().run_now(&world);
+
+        let order = world.read_resource::<TransparentOrder>();
+        assert_eq!(
+            layers.iter().rev().cloned().collect::<Vec<_>>(),
+            order
+                .renderables
+                .iter()
+                .map(|r| r.entity)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![
+                (TransparentKind::Mesh, 0..1),
+                (TransparentKind::Sprite, 1..2),
+                (TransparentKind::Mesh, 2..3),
+                (TransparentKind::Sprite, 3..4),
+                (TransparentKind::Mesh, 4..5),
+                (TransparentKind::Sprite, 5..6),
+            ],
+            order.runs().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn runs_group_consecutive_kinds() {
+        let mut world = World::new();
+        let entities = (0..4)
+            .map(|_| world.create_entity().build())
+            .collect::<Vec<_>>();
+        let kinds = [
+            TransparentKind::Sprite,
+            TransparentKind::Sprite,
+            TransparentKind::Mesh,
+            TransparentKind::Sprite,
+        ];
+        let order = TransparentOrder {
+            renderables: entities
+                .iter()
+                .zip(kinds.iter())
+                .map(|(entity, kind)| TransparentRenderable {
+                    entity: *entity,
+                    kind: *kind,
+                    depth: 0.0,
+                })
+                .collect(),
+        };
+
+        assert_eq!(
+            vec![
+                (TransparentKind::Sprite, 0..2),
+                (TransparentKind::Mesh, 2..3),
+                (TransparentKind::Sprite, 3..4),
+            ],
+            order.runs().collect::<Vec<_>>()
+        );
+        assert_eq!(0, TransparentOrder::default().runs().count());
+    }
+}
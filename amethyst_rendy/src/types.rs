@@ -1,6 +1,14 @@
 //! 'Global' rendering type declarations
-use amethyst_assets::{Asset, Handle};
+use amethyst_assets::{Asset, AssetStorage, Handle, Loader, Progress};
 use amethyst_core::ecs::DenseVecStorage;
+use rendy::{
+    hal::{
+        format::Format,
+        image::{Anisotropic, Filter, Kind, PackedColor, SamplerInfo, ViewKind, WrapMode},
+    },
+    mesh::{AsVertex, MeshBuilder},
+    texture::TextureBuilder,
+};
 use serde::{Deserialize, Serialize};
 
 /// Extension of the rendy Backend trait.
@@ -118,6 +126,53 @@ impl_backends!(
     Empty, "empty", rendy::empty::Backend;
 );
 
+impl Mesh {
+    /// Creates a mesh from vertices and optional indices, returning its handle right away.
+    ///
+    /// The mesh is not reloadable. See `MeshData::from_vertices`.
+    pub fn from_vertices<V, P>(
+        vertices: Vec<V>,
+        indices: Option<Vec<u32>>,
+        loader: &Loader,
+        progress: P,
+        storage: &AssetStorage<Mesh>,
+    ) -> Handle<Mesh>
+    where
+        V: AsVertex,
+        P: Progress,
+    {
+        loader.load_from_data(
+            MeshData::from_vertices(vertices, indices),
+            progress,
+            storage,
+        )
+    }
+}
+
+impl Texture {
+    /// Creates a 2D texture from RGBA8 pixels in sRGB color space, returning its handle right
+    /// away.
+    ///
+    /// The texture is not reloadable. See `TextureData::from_rgba_bytes`.
+    pub fn from_rgba_bytes<P>(
+        width: u32,
+        height: u32,
+        pixels: Vec<u8>,
+        loader: &Loader,
+        progress: P,
+        storage: &AssetStorage<Texture>,
+    ) -> Handle<Texture>
+    where
+        P: Progress,
+    {
+        loader.load_from_data(
+            TextureData::from_rgba_bytes(width, height, pixels),
+            progress,
+            storage,
+        )
+    }
+}
+
 impl Asset for Mesh {
     const NAME: &'static str = "Mesh";
     type Data = MeshData;
@@ -140,6 +195,58 @@ pub struct MeshData(
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TextureData(pub rendy::texture::TextureBuilder<'static>);
 
+impl MeshData {
+    /// Creates mesh data from a single vertex buffer of `V` and optional indices.
+    pub fn from_vertices<V: AsVertex>(vertices: Vec<V>, indices: Option<Vec<u32>>) -> Self {
+        let builder = MeshBuilder::new().with_vertices(vertices);
+        match indices {
+            Some(indices) => builder.with_indices(indices),
+            None => builder,
+        }
+        .into()
+    }
+}
+
+impl TextureData {
+    /// Creates 2D texture data from RGBA8 pixels in sRGB color space, stored row by row from
+    /// the top left corner. The texture is sampled like the images of the default `ImageFormat`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pixels` does not hold exactly `width * height * 4` bytes.
+    pub fn from_rgba_bytes(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        assert_eq!(
+            pixels.len(),
+            width as usize * height as usize * 4,
+            "Expected {}x{} RGBA pixels",
+            width,
+            height,
+        );
+        TextureBuilder::new()
+            .with_kind(Kind::D2(width, height, 1, 1))
+            .with_view_kind(ViewKind::D2)
+            .with_data_width(width)
+            .with_data_height(height)
+            .with_sampler_info(SamplerInfo {
+                min_filter: Filter::Nearest,
+                mag_filter: Filter::Nearest,
+                mip_filter: Filter::Nearest,
+                wrap_mode: (WrapMode::Tile, WrapMode::Tile, WrapMode::Tile),
+                lod_bias: 0.0.into(),
+                lod_range: std::ops::Range {
+                    start: 0.0.into(),
+                    end: 1000.0.into(),
+                },
+                comparison: None,
+                border: PackedColor(0),
+                normalized: true,
+                anisotropic: Anisotropic::Off,
+            })
+            .with_raw_data(pixels, Format::Rgba8Srgb)
+            .into()
+    }
+}
+
 impl From<rendy::mesh::MeshBuilder<'static>> for MeshData {
     fn from(builder: rendy::mesh::MeshBuilder<'static>) -> Self {
         Self(builder)
@@ -0,0 +1,101 @@
+//! Vertex attributes in addition to the ones of `rendy::mesh`.
+//!
+//! Passes only require the attributes they declare in their vertex formats, meshes can hold
+//! more of them.
+use rendy::{
+    hal::format::Format,
+    mesh::{AsAttribute, AsVertex, Normal, Position, Tangent, TexCoord, VertexFormat},
+};
+
+/// Second set of texture coordinates of a vertex, used by lightmaps and detail textures.
+///
+/// Imported from `TEXCOORD_1` by glTF.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct TexCoord2(pub [f32; 2]);
+
+impl From<[f32; 2]> for TexCoord2 {
+    fn from(from: [f32; 2]) -> Self {
+        Self(from)
+    }
+}
+
+impl AsAttribute for TexCoord2 {
+    const NAME: &'static str = "tex_coord_2";
+    const FORMAT: Format = Format::Rg32Sfloat;
+}
+
+/// Vertex format with position, normal and both sets of texture coordinates.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct PosNormTex2 {
+    /// Position of the vertex in 3D space.
+    pub position: Position,
+    /// Normal vector of the vertex.
+    pub normal: Normal,
+    /// First set of texture coordinates.
+    pub tex_coord: TexCoord,
+    /// Second set of texture coordinates.
+    pub tex_coord_2: TexCoord2,
+}
+
+impl AsVertex for PosNormTex2 {
+    fn vertex() -> VertexFormat {
+        VertexFormat::new((
+            Position::vertex(),
+            Normal::vertex(),
+            TexCoord::vertex(),
+            TexCoord2::vertex(),
+        ))
+    }
+}
+
+/// Vertex format with position, normal, tangent and both sets of texture coordinates.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct PosNormTangTex2 {
+    /// Position of the vertex in 3D space.
+    pub position: Position,
+    /// Normal vector of the vertex.
+    pub normal: Normal,
+    /// Tangent vector of the vertex.
+    pub tangent: Tangent,
+    /// First set of texture coordinates.
+    pub tex_coord: TexCoord,
+    /// Second set of texture coordinates.
+    pub tex_coord_2: TexCoord2,
+}
+
+impl AsVertex for PosNormTangTex2 {
+    fn vertex() -> VertexFormat {
+        VertexFormat::new((
+            Position::vertex(),
+            Normal::vertex(),
+            Tangent::vertex(),
+            TexCoord::vertex(),
+            TexCoord2::vertex(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interleaved_formats_match_layout() {
+        let format = PosNormTangTex2::vertex();
+        assert_eq!(std::mem::size_of::<PosNormTangTex2>() as u32, format.stride);
+        let offsets = format
+            .attributes
+            .iter()
+            .map(|a| (a.name.clone(), a.element.offset))
+            .collect::<Vec<_>>();
+        assert_eq!(offsets[3], (TexCoord::NAME.into(), 40));
+        assert_eq!(offsets[4], (TexCoord2::NAME.into(), 48));
+        assert_eq!(
+            std::mem::size_of::<PosNormTex2>() as u32,
+            PosNormTex2::vertex().stride
+        );
+    }
+}
@@ -1,6 +1,7 @@
 //! Transparency, visibility sorting and camera centroid culling for 3D Meshes.
 use crate::{
     camera::{ActiveCamera, Camera},
+    layers::RenderLayers,
     transparent::Transparent,
 };
 use amethyst_core::{
@@ -14,6 +15,7 @@ use amethyst_core::{
     Hidden, HiddenPropagate, Transform,
 };
 
+use fnv::FnvHashMap;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 
@@ -22,12 +24,25 @@ use thread_profiler::profile_scope;
 
 /// Resource for controlling what entities should be rendered, and whether to draw them ordered or
 /// not, which is useful for transparent surfaces.
+///
+/// The top level sets are the ones of the active camera.
 #[derive(Default, Debug)]
 pub struct Visibility {
     /// Visible entities that can be drawn in any order
     pub visible_unordered: BitSet,
     /// Visible entities that need to be drawn in the given order
     pub visible_ordered: Vec<Entity>,
+    /// Visible entities of every camera, culled by its frustum and `RenderLayers`.
+    pub cameras: FnvHashMap<Entity, CameraVisibility>,
+}
+
+/// Entities visible to a single camera.
+#[derive(Default, Debug, Clone)]
+pub struct CameraVisibility {
+    /// Visible entities that can be drawn in any order
+    pub visible_unordered: BitSet,
+    /// Visible entities that need to be drawn in the given order
+    pub visible_ordered: Vec<Entity>,
 }
 
 /// Determine what entities are visible to the camera, and which are not. Will also sort transparent
@@ -91,49 +106,18 @@ impl VisibilitySortingSystem {
     pub fn new() -> Self {
         Self::default()
     }
-}
-
-impl<'a> System<'a> for VisibilitySortingSystem {
-    type SystemData = (
-        Entities<'a>,
-        Write<'a, Visibility>,
-        ReadStorage<'a, Hidden>,
-        ReadStorage<'a, HiddenPropagate>,
-        Read<'a, ActiveCamera>,
-        ReadStorage<'a, Camera>,
-        ReadStorage<'a, Transparent>,
-        ReadStorage<'a, Transform>,
-        ReadStorage<'a, BoundingSphere>,
-    );
 
-    fn run(
+    /// Collects the entities visible to a camera into `out`, sorting transparent ones back to
+    /// front.
+    fn cull<'a>(
         &mut self,
-        (
-            entities,
-            mut visibility,
-            hidden,
-            hidden_prop,
-            active,
-            camera,
-            transparent,
-            transform,
-            bound,
-        ): Self::SystemData,
+        renderables: impl Iterator<Item = (Entity, &'a Transform, Option<&'a BoundingSphere>)>,
+        (camera, camera_transform, camera_layers): (&Camera, &Transform, RenderLayers),
+        transparent: &ReadStorage<'_, Transparent>,
+        layers: &ReadStorage<'_, RenderLayers>,
+        out: &mut CameraVisibility,
     ) {
-        #[cfg(feature = "profiler")]
-        profile_scope!("visibility_sorting_system");
-
         let origin = Point3::origin();
-        let defcam = Camera::standard_2d(1.0, 1.0);
-        let identity = Transform::default();
-
-        let mut camera_join = (&camera, &transform).join();
-        let (camera, camera_transform) = active
-            .entity
-            .and_then(|a| camera_join.get(a, &entities))
-            .or_else(|| camera_join.next())
-            .unwrap_or((&defcam, &identity));
-
         let camera_centroid = camera_transform.global_matrix().transform_point(&origin);
         let frustum = Frustum::new(
             convert::<_, Matrix4<f32>>(*camera.as_matrix())
@@ -142,15 +126,11 @@ impl<'a> System<'a> for VisibilitySortingSystem {
 
         self.centroids.clear();
         self.centroids.extend(
-            (
-                &*entities,
-                &transform,
-                bound.maybe(),
-                !&hidden,
-                !&hidden_prop,
-            )
-                .join()
-                .map(|(entity, transform, sphere, _, _)| {
+            renderables
+                .filter(|(entity, _, _)| {
+                    camera_layers.intersects(RenderLayers::of(layers.get(*entity)))
+                })
+                .map(|(entity, transform, sphere)| {
                     let pos = sphere.map_or(&origin, |s| &s.center);
                     let matrix = transform.global_matrix();
                     (
@@ -178,21 +158,110 @@ impl<'a> System<'a> for VisibilitySortingSystem {
                 .unwrap_or(Ordering::Equal)
         });
 
-        visibility.visible_unordered.clear();
-        visibility.visible_unordered.extend(
+        out.visible_unordered.clear();
+        out.visible_unordered.extend(
             self.centroids
                 .iter()
                 .filter(|c| !c.transparent)
                 .map(|c| c.entity.id()),
         );
 
-        visibility.visible_ordered.clear();
-        visibility
-            .visible_ordered
+        out.visible_ordered.clear();
+        out.visible_ordered
             .extend(self.transparent.iter().map(|c| c.entity));
     }
 }
 
+impl<'a> System<'a> for VisibilitySortingSystem {
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, Visibility>,
+        ReadStorage<'a, Hidden>,
+        ReadStorage<'a, HiddenPropagate>,
+        Read<'a, ActiveCamera>,
+        ReadStorage<'a, Camera>,
+        ReadStorage<'a, Transparent>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, BoundingSphere>,
+        ReadStorage<'a, RenderLayers>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut visibility,
+            hidden,
+            hidden_prop,
+            active,
+            camera,
+            transparent,
+            transform,
+            bound,
+            layers,
+        ): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("visibility_sorting_system");
+
+        let defcam = Camera::standard_2d(1.0, 1.0);
+        let identity = Transform::default();
+        let visibility = &mut *visibility;
+
+        let renderables = || {
+            (
+                &*entities,
+                &transform,
+                bound.maybe(),
+                !&hidden,
+                !&hidden_prop,
+            )
+                .join()
+                .map(|(entity, transform, sphere, _, _)| (entity, transform, sphere))
+        };
+
+        // Forget the cameras which were removed
+        visibility
+            .cameras
+            .retain(|entity, _| camera.contains(*entity) && transform.contains(*entity));
+
+        for (entity, camera, camera_transform, camera_layers) in
+            (&*entities, &camera, &transform, layers.maybe()).join()
+        {
+            let out = visibility.cameras.entry(entity).or_default();
+            self.cull(
+                renderables(),
+                (camera, camera_transform, RenderLayers::of(camera_layers)),
+                &transparent,
+                &layers,
+                out,
+            );
+        }
+
+        let active_entity = active
+            .entity
+            .filter(|e| visibility.cameras.contains_key(e))
+            .or_else(|| (&*entities, &camera, &transform).join().map(|j| j.0).next());
+
+        let active_visibility = match active_entity.and_then(|e| visibility.cameras.get(&e)) {
+            Some(camera_visibility) => camera_visibility.clone(),
+            None => {
+                let mut default_visibility = CameraVisibility::default();
+                self.cull(
+                    renderables(),
+                    (&defcam, &identity, RenderLayers::ALL),
+                    &transparent,
+                    &layers,
+                    &mut default_visibility,
+                );
+                default_visibility
+            }
+        };
+        visibility.visible_unordered = active_visibility.visible_unordered;
+        visibility.visible_ordered = active_visibility.visible_ordered;
+    }
+}
+
 /// Simple view Frustum implementation
 #[derive(Debug)]
 pub struct Frustum {
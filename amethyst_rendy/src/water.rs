@@ -0,0 +1,182 @@
+//! Reflective water planes, drawn by `RenderWater`.
+//!
+//! The scene above the water is drawn mirrored about its plane into a reflection target, and
+//! optionally the scene below it into a refraction target. `DrawWaterDesc` then blends both over
+//! each `WaterPlane` with a fresnel term, distorted by a scrolling normal map.
+use crate::types::Texture;
+use amethyst_assets::Handle;
+use amethyst_core::{
+    ecs::prelude::{Component, DenseVecStorage},
+    math::{convert, Matrix4, Point3, Vector2, Vector3, Vector4},
+    Transform,
+};
+use palette::Srgba;
+
+/// A rectangle of water on the local xz plane of the entity, facing its local y axis.
+///
+/// All water planes share a single reflection, mirrored about the plane closest to the camera,
+/// so the planes of a scene should be at the same height. Nothing is reflected while the camera
+/// is below that plane.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WaterPlane {
+    /// Half size of the rectangle along the local x and z axes.
+    pub extents: Vector2<f32>,
+    /// Color of deep water. Without refraction, its alpha is the opacity of the water.
+    pub color: Srgba,
+    /// How fast the scene below the water fades into `color` with depth, per world unit.
+    pub depth_falloff: f32,
+    /// Tangent space normal map animating the surface, flat if `None`.
+    pub normal_map: Option<Handle<Texture>>,
+    /// Size of a repetition of the normal map, in world units.
+    pub wave_scale: f32,
+    /// Scrolling of the normal map, in repetitions per second.
+    pub wave_speed: Vector2<f32>,
+    /// Strength of the waves, scaling both the normals and the distortion of the scene.
+    pub wave_strength: f32,
+}
+
+impl WaterPlane {
+    /// Creates a water plane spanning `extents` on each side of the entity along its x and z
+    /// axes.
+    pub fn new(extents: Vector2<f32>) -> Self {
+        WaterPlane {
+            extents,
+            color: Srgba::new(0.05, 0.2, 0.25, 0.8),
+            depth_falloff: 0.3,
+            normal_map: None,
+            wave_scale: 4.0,
+            wave_speed: Vector2::new(0.02, 0.01),
+            wave_strength: 0.3,
+        }
+    }
+
+    /// Sets the color of deep water.
+    pub fn with_color(mut self, color: Srgba) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Sets how fast the scene below the water fades into its color with depth.
+    pub fn with_depth_falloff(mut self, depth_falloff: f32) -> Self {
+        self.depth_falloff = depth_falloff;
+        self
+    }
+
+    /// Sets the normal map animating the surface.
+    pub fn with_normal_map(mut self, normal_map: Handle<Texture>) -> Self {
+        self.normal_map = Some(normal_map);
+        self
+    }
+
+    /// Sets the size, scrolling speed and strength of the waves.
+    pub fn with_waves(mut self, scale: f32, speed: Vector2<f32>, strength: f32) -> Self {
+        self.wave_scale = scale;
+        self.wave_speed = speed;
+        self.wave_strength = strength;
+        self
+    }
+
+    /// Returns the world space plane of the water as `(n, d)`, with `n·x + d = 0` on the plane
+    /// and positive above it.
+    pub fn world_plane(transform: &Transform) -> Vector4<f32> {
+        let global = convert::<_, Matrix4<f32>>(*transform.global_matrix());
+        let normal = global
+            .transform_vector(&Vector3::y())
+            .try_normalize(std::f32::EPSILON)
+            .unwrap_or_else(Vector3::y);
+        let origin = global.transform_point(&Point3::origin());
+        Vector4::new(normal.x, normal.y, normal.z, -normal.dot(&origin.coords))
+    }
+}
+
+impl Component for WaterPlane {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Returns the world plane of the water closest to `camera_position`, which is reflected.
+pub fn reflected_plane<'a>(
+    planes: impl Iterator<Item = (&'a WaterPlane, &'a Transform)>,
+    camera_position: &Point3<f32>,
+) -> Option<Vector4<f32>> {
+    planes
+        .map(|(_, transform)| {
+            let center = convert::<_, Matrix4<f32>>(*transform.global_matrix())
+                .transform_point(&Point3::origin());
+            let distance = (center - camera_position).norm_squared();
+            (distance, WaterPlane::world_plane(transform))
+        })
+        .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(_, plane)| plane)
+}
+
+/// Returns the matrix mirroring points about a world `plane` given as `(n, d)`.
+pub fn reflection_matrix(plane: &Vector4<f32>) -> Matrix4<f32> {
+    let n = plane.xyz();
+    let d = plane.w;
+    let mut matrix = Matrix4::identity();
+    for row in 0..3 {
+        for column in 0..3 {
+            matrix[(row, column)] -= 2.0 * n[row] * n[column];
+        }
+        matrix[(row, 3)] = -2.0 * d * n[row];
+    }
+    matrix
+}
+
+/// Returns `proj` with its near plane replaced by the view space `clip_plane`, given as `(n, d)`
+/// and keeping the points in front of it.
+///
+/// The far plane is moved to keep the frustum as tight as possible around the original one, which
+/// costs depth precision the more the clip plane is tilted away from the view direction.
+pub fn oblique_projection(proj: &Matrix4<f32>, clip_plane: &Vector4<f32>) -> Matrix4<f32> {
+    let inverse = match proj.try_inverse() {
+        Some(inverse) => inverse,
+        None => return *proj,
+    };
+    // Corner of the frustum opposite to the clip plane, which the far plane must go through.
+    let clip_space = inverse.transpose() * clip_plane;
+    let corner = inverse * Vector4::new(clip_space.x.signum(), clip_space.y.signum(), 1.0, 1.0);
+    let scale = proj.row(3).transpose().dot(&corner) / clip_plane.dot(&corner);
+    let mut oblique = *proj;
+    oblique.set_row(2, &(clip_plane * scale).transpose());
+    oblique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::math::Perspective3;
+
+    fn depth(proj: &Matrix4<f32>, point: Vector4<f32>) -> f32 {
+        let clip = proj * point;
+        clip.z / clip.w
+    }
+
+    #[test]
+    fn oblique_projection_clips_at_plane() {
+        let mut proj = Perspective3::new(1.0, 1.0, 0.1, 100.0).to_homogeneous();
+        // Vulkan depth range and y axis, as in `Camera`.
+        proj[(1, 1)] *= -1.0;
+        proj[(2, 2)] = 100.0 / (0.1 - 100.0);
+        proj[(2, 3)] = -(100.0 * 0.1) / (100.0 - 0.1);
+
+        // Keep what is above y = -1 in view space.
+        let oblique = oblique_projection(&proj, &Vector4::new(0.0, 1.0, 0.0, 1.0));
+        assert!(depth(&oblique, Vector4::new(0.0, -1.0, -5.0, 1.0)).abs() < 1e-4);
+        let above = depth(&oblique, Vector4::new(0.0, 0.0, -5.0, 1.0));
+        assert!(above > 0.0 && above < 1.0);
+        assert!(depth(&oblique, Vector4::new(0.0, -2.0, -5.0, 1.0)) < 0.0);
+    }
+
+    #[test]
+    fn reflection_mirrors_about_plane() {
+        let mut transform = Transform::default();
+        transform.set_translation_y(2.0);
+        transform.copy_local_to_global();
+        let plane = WaterPlane::world_plane(&transform);
+        assert_eq!(plane, Vector4::new(0.0, 1.0, 0.0, -2.0));
+
+        let mirrored = reflection_matrix(&plane).transform_point(&Point3::new(1.0, 5.0, 3.0));
+        assert!((mirrored - Point3::new(1.0, -1.0, 3.0)).norm() < 1e-5);
+    }
+}
@@ -1,7 +1,7 @@
 use std::time::Duration;
 
 use amethyst::{
-    assets::ProgressCounter,
+    assets::{Completion, ProgressCounter},
     core::Stopwatch,
     ecs::{World, WorldExt},
     State, StateData, Trans,
@@ -30,6 +30,9 @@ pub struct WaitForLoad {
 
 impl WaitForLoad {
     /// Returns a `WaitForLoad` that assumes a `ProgressCounter` resource exists in the `World`.
+    ///
+    /// If an asset fails to load, the state keeps waiting. Use `new_failing_on_error` to stop
+    /// with the errors of the failed assets instead.
     pub fn new() -> Self {
         WaitForLoad {
             fn_complete: |world| world.read_resource::<ProgressCounter>().is_complete(),
@@ -37,6 +40,31 @@ impl WaitForLoad {
         }
     }
 
+    /// Returns a `WaitForLoad` that assumes a `ProgressCounter` resource exists in the `World`,
+    /// and fails as soon as an asset fails to load.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the errors of the failed assets if any asset fails to load.
+    pub fn new_failing_on_error() -> Self {
+        WaitForLoad {
+            fn_complete: |world| {
+                let progress_counter = world.read_resource::<ProgressCounter>();
+                if progress_counter.complete() == Completion::Failed {
+                    let errors = progress_counter
+                        .errors()
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    panic!("Failed to load assets:\n{}", errors);
+                }
+                progress_counter.is_complete()
+            },
+            stopwatch: Stopwatch::new(),
+        }
+    }
+
     /// Returns a `WaitForLoad` with a custom completion check.
     ///
     /// # Parameters
@@ -189,6 +217,39 @@ mod tests {
             .run()
     }
 
+    #[test]
+    #[should_panic(expected = "Failed to load assets")]
+    fn failing_on_error_panics_with_asset_errors() {
+        AmethystApplication::blank()
+            .with_system(Processor::<TestAsset>::new(), "test_asset_processor", &[])
+            .with_effect(|world| {
+                let mut in_memory_source = InMemorySource::new();
+                in_memory_source.insert(String::from("file.ron"), b"(val: oops)".to_vec());
+
+                let mut loader = world.write_resource::<Loader>();
+                loader.add_source(IN_MEMORY_SOURCE_ID, in_memory_source);
+            })
+            .with_effect(|world| {
+                let mut progress_counter = ProgressCounter::new();
+                let test_asset_handle = {
+                    let loader = world.read_resource::<Loader>();
+                    loader.load_from(
+                        "file.ron",
+                        RonFormat,
+                        IN_MEMORY_SOURCE_ID,
+                        &mut progress_counter,
+                        &world.read_resource::<AssetStorage<TestAsset>>(),
+                    )
+                };
+
+                world.insert(test_asset_handle);
+                world.insert(progress_counter);
+            })
+            .with_state(WaitForLoad::new_failing_on_error)
+            .run()
+            .unwrap();
+    }
+
     #[derive(Debug, Deserialize, PartialEq, Serialize)]
     pub struct TestAsset {
         val: u32,
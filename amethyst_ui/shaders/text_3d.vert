@@ -0,0 +1,46 @@
+#version 450
+
+layout(std140, set = 0, binding = 0) uniform ViewArgs {
+    uniform mat4 proj;
+    uniform mat4 view;
+    uniform mat4 proj_view;
+};
+
+layout(location = 0) in mat4 model;
+layout(location = 4) in vec2 coords;
+layout(location = 5) in vec2 dimensions;
+layout(location = 6) in vec4 tex_coord_bounds;
+layout(location = 7) in vec4 color;
+layout(location = 8) in vec4 color_bias;
+layout(location = 9) in uint billboard;
+
+layout(location = 0) out vec2 out_tex_coords;
+layout(location = 1) out vec4 out_color;
+layout(location = 2) out vec4 out_color_bias;
+
+const vec2 positions[4] = vec2[](
+    vec2(0.5, -0.5), // Right bottom
+    vec2(-0.5, -0.5), // Left bottom
+    vec2(0.5, 0.5), // Right top
+    vec2(-0.5, 0.5) // Left top
+);
+
+void main() {
+    vec2 pos = positions[gl_VertexIndex];
+
+    // Texture coordinates go down, the glyph layout goes up
+    vec2 coords_base = vec2(pos.x + 0.5, 0.5 - pos.y);
+    out_tex_coords = mix(tex_coord_bounds.xy, tex_coord_bounds.zw, coords_base);
+    out_color = color;
+    out_color_bias = color_bias;
+
+    vec2 local = coords + dimensions * pos;
+    if (billboard != 0) {
+        // Keep the origin and scale of the entity, facing the camera
+        vec4 origin = view * model * vec4(0.0, 0.0, 0.0, 1.0);
+        vec2 scale = vec2(length(model[0].xyz), length(model[1].xyz));
+        gl_Position = proj * (origin + vec4(local * scale, 0.0, 0.0));
+    } else {
+        gl_Position = proj_view * model * vec4(local, 0.0, 1.0);
+    }
+}
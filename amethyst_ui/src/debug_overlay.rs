@@ -0,0 +1,406 @@
+//! A small corner overlay showing the frame rate, frame times and entity count.
+
+use std::{collections::VecDeque, fmt::Write as _, time::Duration};
+
+use amethyst_assets::{AssetStorage, Loader};
+use amethyst_core::{
+    bundle::SystemBundle,
+    ecs::prelude::{
+        DispatcherBuilder, Entities, Entity, Join, Read, ReadExpect, System, World, WriteStorage,
+    },
+    timing::duration_to_secs,
+    Hidden, Time,
+};
+use amethyst_error::Error;
+use amethyst_input::{BindingTypes, InputHandler};
+use amethyst_utils::frame_stats::FrameTimeStats;
+
+use crate::{get_default_font, Anchor, FontAsset, LineMode, UiText, UiTransform};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+const OVERLAY_WIDTH: f32 = 300.0;
+const OVERLAY_Z: f32 = 1000.0;
+const OVERLAY_COLOR: [f32; 4] = [1.0, 1.0, 0.0, 1.0];
+
+/// A row of statistics shown by the debug overlay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugOverlayRow {
+    /// Frames per second, averaged over the sample window.
+    Fps,
+    /// Average and 99th percentile frame time over the sample window.
+    FrameTime,
+    /// Number of living entities.
+    EntityCount,
+    /// Histogram of the frame times, and their median and 95th percentile, from the
+    /// `FrameTimeStats` resource added by the `FrameTimeStatsBundle`. Not shown by default.
+    FrameTimeHistogram,
+}
+
+/// Adds a `DebugOverlaySystem` showing frame statistics in a corner of the screen.
+///
+/// Requires the `UiBundle`. When a toggle action is set, the `InputBundle` with the same
+/// binding types is needed as well.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use amethyst_core::{
+/// #     ecs::prelude::{DispatcherBuilder, World, WorldExt},
+/// #     SystemBundle,
+/// # };
+/// use amethyst_input::StringBindings;
+/// use amethyst_ui::{Anchor, DebugOverlayBundle};
+///
+/// # let mut world = World::new();
+/// # let mut builder = DispatcherBuilder::new();
+/// DebugOverlayBundle::<StringBindings>::new()
+///     .with_anchor(Anchor::BottomRight)
+///     .with_toggle_action("toggle_debug_overlay".to_string())
+///     .build(&mut world, &mut builder)
+///     .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct DebugOverlayBundle<T: BindingTypes> {
+    anchor: Anchor,
+    update_interval: Duration,
+    sample_count: usize,
+    font_size: f32,
+    rows: Vec<DebugOverlayRow>,
+    toggle_action: Option<T::Action>,
+    visible: bool,
+}
+
+impl<T: BindingTypes> Default for DebugOverlayBundle<T> {
+    fn default() -> Self {
+        DebugOverlayBundle {
+            anchor: Anchor::TopLeft,
+            update_interval: Duration::from_millis(250),
+            sample_count: 120,
+            font_size: 16.0,
+            rows: vec![
+                DebugOverlayRow::Fps,
+                DebugOverlayRow::FrameTime,
+                DebugOverlayRow::EntityCount,
+            ],
+            toggle_action: None,
+            visible: true,
+        }
+    }
+}
+
+impl<T: BindingTypes> DebugOverlayBundle<T> {
+    /// Creates an overlay in the top left corner showing all rows, refreshed four times per second.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the corner, or edge, of the screen the overlay is placed in.
+    pub fn with_anchor(mut self, anchor: Anchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Sets how often the text is refreshed. Updating it every frame would rebuild its glyphs
+    /// every frame and make the numbers impossible to read.
+    pub fn with_update_interval(mut self, interval: Duration) -> Self {
+        self.update_interval = interval;
+        self
+    }
+
+    /// Sets over how many frames the frame rate and frame times are computed.
+    pub fn with_sample_count(mut self, sample_count: usize) -> Self {
+        self.sample_count = sample_count.max(1);
+        self
+    }
+
+    /// Sets the height of a line of text in pixels.
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    /// Sets which rows are shown, in order.
+    pub fn with_rows(mut self, rows: Vec<DebugOverlayRow>) -> Self {
+        self.rows = rows;
+        self
+    }
+
+    /// Sets the input action toggling the visibility of the overlay.
+    pub fn with_toggle_action(mut self, action: T::Action) -> Self {
+        self.toggle_action = Some(action);
+        self
+    }
+
+    /// Sets whether the overlay is visible before it is first toggled, true by default.
+    pub fn with_visible(mut self, visible: bool) -> Self {
+        self.visible = visible;
+        self
+    }
+}
+
+impl<'a, 'b, T: BindingTypes> SystemBundle<'a, 'b> for DebugOverlayBundle<T> {
+    fn build(
+        self,
+        _world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        builder.add(
+            DebugOverlaySystem::<T> {
+                frame_times: FrameTimes::new(self.sample_count),
+                since_refresh: self.update_interval,
+                entity: None,
+                toggle_was_down: false,
+                bundle: self,
+            },
+            "debug_overlay_system",
+            &[],
+        );
+        Ok(())
+    }
+}
+
+/// Frame times in seconds over a sliding window of frames.
+#[derive(Debug)]
+struct FrameTimes {
+    samples: VecDeque<f32>,
+    capacity: usize,
+}
+
+impl FrameTimes {
+    fn new(capacity: usize) -> Self {
+        FrameTimes {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    fn push(&mut self, seconds: f32) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(seconds);
+    }
+
+    fn average(&self) -> f32 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        self.samples.iter().sum::<f32>() / self.samples.len() as f32
+    }
+
+    fn percentile(&self, percentile: f32) -> f32 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        let mut sorted = self.samples.iter().cloned().collect::<Vec<_>>();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let rank = (percentile / 100.0 * sorted.len() as f32).ceil() as usize;
+        sorted[rank.max(1).min(sorted.len()) - 1]
+    }
+}
+
+/// Creates and updates the text of the debug overlay, see `DebugOverlayBundle`.
+#[derive(Debug)]
+pub struct DebugOverlaySystem<T: BindingTypes> {
+    bundle: DebugOverlayBundle<T>,
+    frame_times: FrameTimes,
+    since_refresh: Duration,
+    entity: Option<Entity>,
+    toggle_was_down: bool,
+}
+
+impl<T: BindingTypes> DebugOverlaySystem<T> {
+    fn create_entity(
+        &self,
+        entities: &Entities<'_>,
+        loader: &Loader,
+        font_storage: &AssetStorage<FontAsset>,
+        transforms: &mut WriteStorage<'_, UiTransform>,
+        texts: &mut WriteStorage<'_, UiText>,
+        hiddens: &mut WriteStorage<'_, Hidden>,
+    ) -> Entity {
+        let font_size = self.bundle.font_size;
+        let height = font_size * 1.25 * self.bundle.rows.len().max(1) as f32;
+        let entity = entities.create();
+        let mut transform = UiTransform::new(
+            "debug_overlay".to_string(),
+            self.bundle.anchor,
+            self.bundle.anchor,
+            0.0,
+            0.0,
+            OVERLAY_Z,
+            OVERLAY_WIDTH,
+            height,
+        );
+        transform.opaque = false;
+        let mut text = UiText::new(
+            get_default_font(loader, font_storage),
+            String::new(),
+            OVERLAY_COLOR,
+            font_size,
+        );
+        text.line_mode = LineMode::Wrap;
+        text.align = self.bundle.anchor;
+        transforms
+            .insert(entity, transform)
+            .expect("Unreachable: entity was just created");
+        texts
+            .insert(entity, text)
+            .expect("Unreachable: entity was just created");
+        if !self.bundle.visible {
+            hiddens
+                .insert(entity, Hidden)
+                .expect("Unreachable: entity was just created");
+        }
+        entity
+    }
+
+    fn format(&self, entity_count: usize, stats: Option<&FrameTimeStats>) -> String {
+        let average = self.frame_times.average();
+        let mut text = String::new();
+        for row in &self.bundle.rows {
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            // Writing to a `String` can not fail.
+            let _ = match row {
+                DebugOverlayRow::Fps => {
+                    let fps = if average > 0.0 { 1.0 / average } else { 0.0 };
+                    write!(text, "FPS: {:.1}", fps)
+                }
+                DebugOverlayRow::FrameTime => write!(
+                    text,
+                    "Frame: {:.2} ms avg, {:.2} ms p99",
+                    average * 1000.0,
+                    self.frame_times.percentile(99.0) * 1000.0,
+                ),
+                DebugOverlayRow::EntityCount => write!(text, "Entities: {}", entity_count),
+                DebugOverlayRow::FrameTimeHistogram => match stats {
+                    Some(stats) => write!(
+                        text,
+                        "Hist: [{}] p50 {:.2} ms, p95 {:.2} ms",
+                        stats.histogram_bars(),
+                        duration_to_secs(stats.percentile(50.0)) * 1000.0,
+                        duration_to_secs(stats.percentile(95.0)) * 1000.0,
+                    ),
+                    None => write!(text, "Hist: no FrameTimeStats"),
+                },
+            };
+        }
+        text
+    }
+}
+
+impl<'a, T: BindingTypes> System<'a> for DebugOverlaySystem<T> {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        Option<Read<'a, InputHandler<T>>>,
+        ReadExpect<'a, Loader>,
+        Read<'a, AssetStorage<FontAsset>>,
+        WriteStorage<'a, UiTransform>,
+        WriteStorage<'a, UiText>,
+        WriteStorage<'a, Hidden>,
+        Option<Read<'a, FrameTimeStats>>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            time,
+            input,
+            loader,
+            font_storage,
+            mut transforms,
+            mut texts,
+            mut hiddens,
+            stats,
+        ): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("debug_overlay_system");
+
+        self.frame_times
+            .push(duration_to_secs(time.delta_real_time()));
+        self.since_refresh += time.delta_real_time();
+
+        let entity = match self.entity.filter(|e| entities.is_alive(*e)) {
+            Some(entity) => entity,
+            None => {
+                let entity = self.create_entity(
+                    &entities,
+                    &loader,
+                    &font_storage,
+                    &mut transforms,
+                    &mut texts,
+                    &mut hiddens,
+                );
+                self.entity = Some(entity);
+                self.since_refresh = self.bundle.update_interval;
+                entity
+            }
+        };
+
+        if let (Some(action), Some(input)) = (&self.bundle.toggle_action, &input) {
+            let down = input.action_is_down(action).unwrap_or(false);
+            if down && !self.toggle_was_down {
+                if hiddens.contains(entity) {
+                    hiddens.remove(entity);
+                    self.since_refresh = self.bundle.update_interval;
+                } else {
+                    hiddens
+                        .insert(entity, Hidden)
+                        .expect("Unreachable: entity is alive");
+                }
+            }
+            self.toggle_was_down = down;
+        }
+
+        if self.since_refresh < self.bundle.update_interval || hiddens.contains(entity) {
+            return;
+        }
+        self.since_refresh = Duration::from_secs(0);
+
+        let entity_count = (&*entities).join().count();
+        let content = self.format(entity_count, stats.as_deref());
+        if let Some(text) = texts.get_mut(entity) {
+            if text.text != content {
+                text.text = content;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FrameTimes;
+
+    #[test]
+    fn frame_times_keep_a_sliding_window() {
+        let mut times = FrameTimes::new(4);
+        for i in 1..=6 {
+            times.push(i as f32);
+        }
+        assert_eq!(
+            vec![3, 4, 5, 6],
+            times.samples.iter().map(|s| *s as u32).collect::<Vec<_>>()
+        );
+        assert!((times.average() - 4.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn percentile_picks_the_slow_frames() {
+        let mut times = FrameTimes::new(100);
+        for _ in 0..98 {
+            times.push(0.016);
+        }
+        times.push(0.1);
+        times.push(0.2);
+        assert!((times.percentile(99.0) - 0.1).abs() < 1e-6);
+        assert!((times.percentile(100.0) - 0.2).abs() < 1e-6);
+        assert!((FrameTimes::new(1).percentile(99.0)).abs() < 1e-6);
+    }
+}
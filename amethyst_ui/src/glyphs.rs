@@ -1,8 +1,10 @@
 //! Module containing the system managing glyphbrush state for visible UI Text components.
 
 use crate::{
-    pass::UiArgs, text::CachedGlyph, FontAsset, LineMode, Selected, TextEditing, UiText,
-    UiTransform,
+    pass::UiArgs,
+    text::CachedGlyph,
+    text_3d::{Text3d, TEXT_3D_GLYPH_SIZE},
+    FontAsset, FontHandle, LineMode, Selected, TextEditing, UiText, UiTransform,
 };
 use amethyst_assets::{AssetStorage, Handle};
 use amethyst_core::{
@@ -30,6 +32,9 @@ use glyph_brush::{
 use std::{collections::HashMap, marker::PhantomData};
 use unicode_segmentation::UnicodeSegmentation;
 
+/// Set in the entity ids smuggled in the glyphs of `Text3d`, to tell them from `UiText` glyphs.
+const TEXT_3D_ID_FLAG: u32 = 1 << 31;
+
 #[derive(Debug)]
 pub struct UiGlyphsResource {
     glyph_tex: Option<Handle<Texture>>,
@@ -119,6 +124,7 @@ impl<'a, B: Backend> System<'a> for UiGlyphsSystem<B> {
         ReadStorage<'a, UiTransform>,
         WriteStorage<'a, UiText>,
         WriteStorage<'a, UiGlyphs>,
+        WriteStorage<'a, Text3d>,
         ReadStorage<'a, TextEditing>,
         ReadStorage<'a, Hidden>,
         ReadStorage<'a, HiddenPropagate>,
@@ -138,6 +144,7 @@ impl<'a, B: Backend> System<'a> for UiGlyphsSystem<B> {
             transforms,
             mut texts,
             mut glyphs,
+            mut texts_3d,
             text_editings,
             hiddens,
             hidden_propagates,
@@ -185,16 +192,9 @@ impl<'a, B: Backend> System<'a> for UiGlyphsSystem<B> {
             ui_text.cached_glyphs.clear();
 
             let font_asset = font_storage.get(&ui_text.font).map(|font| font.0.clone());
-            let font_lookup = fonts_map_ref
-                .entry(ui_text.font.id())
-                .or_insert(FontState::NotFound);
-            if font_lookup.id().is_none() {
-                if let Some(font) = font_storage.get(&ui_text.font) {
-                    *font_lookup = FontState::Ready(glyph_brush_ref.add_font(font.0.clone()));
-                }
-            }
+            let font_id = font_id(fonts_map_ref, glyph_brush_ref, &font_storage, &ui_text.font);
 
-            if let (Some(font_id), Some(font_asset)) = (font_lookup.id(), font_asset) {
+            if let (Some(font_id), Some(font_asset)) = (font_id, font_asset) {
                 let tint_color = tint.map_or([1., 1., 1., 1.], |t| {
                     let (r, g, b, a) = t.0.into_components();
                     [r, g, b, a]
@@ -364,6 +364,46 @@ impl<'a, B: Backend> System<'a> for UiGlyphsSystem<B> {
             }
         }
 
+        // Queued after all `UiText`s, so their glyphs come last
+        for (entity, text, tint, _, _) in (
+            &entities,
+            &texts_3d,
+            tints.maybe(),
+            !&hiddens,
+            !&hidden_propagates,
+        )
+            .join()
+        {
+            if let Some(font_id) =
+                font_id(fonts_map_ref, glyph_brush_ref, &font_storage, &text.font)
+            {
+                let tint_color = tint.map_or([1., 1., 1., 1.], |t| {
+                    let (r, g, b, a) = t.0.into_components();
+                    [r, g, b, a]
+                });
+                let layout = Layout::Wrap {
+                    line_breaker: CustomLineBreaker::BuiltIn(
+                        BuiltInLineBreaker::UnicodeLineBreaker,
+                    ),
+                    h_align: text.align.horizontal_align(),
+                    v_align: text.align.vertical_align(),
+                };
+                let section = VariedSection {
+                    screen_position: (0., 0.),
+                    bounds: (std::f32::INFINITY, std::f32::INFINITY),
+                    z: f32::from_bits(entity.id() | TEXT_3D_ID_FLAG),
+                    layout: Default::default(), // overriden on queue
+                    text: vec![SectionText {
+                        text: &text.text,
+                        scale: Scale::uniform(TEXT_3D_GLYPH_SIZE),
+                        color: mul_blend(&text.color, &tint_color),
+                        font_id,
+                    }],
+                };
+                glyph_brush_ref.queue_custom_layout(section, &layout);
+            }
+        }
+
         loop {
             let action = glyph_brush_ref.process_queued(
                 |rect, data| unsafe {
@@ -476,6 +516,9 @@ impl<'a, B: Backend> System<'a> for UiGlyphsSystem<B> {
                         glyph_data.vertices.clear();
                         glyph_data.sel_vertices.clear();
                     }
+                    for text in (&mut texts_3d).join() {
+                        text.vertices.clear();
+                    }
 
                     for (entity, ui_text, editing, tint, transform, _, _) in (
                         &entities,
@@ -560,6 +603,19 @@ impl<'a, B: Backend> System<'a> for UiGlyphsSystem<B> {
                             );
                         }
                     }
+
+                    for (entity, text, _, _) in
+                        (&entities, &mut texts_3d, !&hiddens, !&hidden_propagates).join()
+                    {
+                        let e_id = entity.id() | TEXT_3D_ID_FLAG;
+                        let len = vertices[glyph_ctr..]
+                            .iter()
+                            .take_while(|(id, _)| *id == e_id)
+                            .count();
+                        text.vertices
+                            .extend(vertices[glyph_ctr..glyph_ctr + len].iter().map(|v| v.1));
+                        glyph_ctr += len;
+                    }
                     break;
                 }
                 Ok(BrushAction::ReDraw) => {
@@ -604,6 +660,22 @@ impl<'a, B: Backend> System<'a> for UiGlyphsSystem<B> {
     }
 }
 
+/// Returns the id of `font` in the glyph brush, adding it once it is loaded.
+fn font_id(
+    fonts_map: &mut HashMap<u32, FontState>,
+    glyph_brush: &mut GlyphBrush<'static, (u32, UiArgs)>,
+    font_storage: &AssetStorage<FontAsset>,
+    font: &FontHandle,
+) -> Option<FontId> {
+    let font_lookup = fonts_map.entry(font.id()).or_insert(FontState::NotFound);
+    if font_lookup.id().is_none() {
+        if let Some(font) = font_storage.get(font) {
+            *font_lookup = FontState::Ready(glyph_brush.add_font(font.0.clone()));
+        }
+    }
+    font_lookup.id()
+}
+
 fn update_cursor_position(
     glyph_data: &mut UiGlyphs,
     ui_text: &UiText,
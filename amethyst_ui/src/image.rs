@@ -52,3 +52,9 @@ pub enum UiImage {
 impl Component for UiImage {
     type Storage = DenseVecStorage<Self>;
 }
+
+impl From<SpriteRender> for UiImage {
+    fn from(sprite: SpriteRender) -> Self {
+        UiImage::Sprite(sprite)
+    }
+}
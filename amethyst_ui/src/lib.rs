@@ -17,6 +17,7 @@ pub use self::{
         UiButtonActionRetriggerSystemDesc, UiButtonActionType, UiButtonBuilder,
         UiButtonBuilderResources, UiButtonSystem, UiButtonSystemDesc,
     },
+    debug_overlay::{DebugOverlayBundle, DebugOverlayRow, DebugOverlaySystem},
     drag::{DragWidgetSystemDesc, Draggable},
     event::{
         targeted, targeted_below, Interactable, TargetedEvent, UiEvent, UiEventType, UiMouseSystem,
@@ -50,6 +51,7 @@ pub use self::{
         UiSoundSystem, UiSoundSystemDesc,
     },
     text::{LineMode, TextEditing, TextEditingMouseSystem, TextEditingMouseSystemDesc, UiText},
+    text_3d::{DrawText3d, DrawText3dDesc, Text3d},
     text_editing::{TextEditingInputSystem, TextEditingInputSystemDesc},
     transform::{get_parent_pixel_size, UiFinder, UiTransform},
     widgets::{Widget, WidgetId, Widgets},
@@ -60,6 +62,7 @@ pub(crate) use amethyst_core::ecs::prelude::Entity;
 mod blink;
 mod bundle;
 mod button;
+mod debug_overlay;
 mod drag;
 mod event;
 mod event_retrigger;
@@ -76,6 +79,7 @@ mod selection;
 mod selection_order_cache;
 mod sound;
 mod text;
+mod text_3d;
 mod text_editing;
 mod transform;
 mod widgets;
@@ -1,6 +1,6 @@
 use crate::{
     glyphs::{UiGlyphs, UiGlyphsResource},
-    Selected, TextEditing, UiGlyphsSystemDesc, UiImage, UiTransform,
+    DrawText3dDesc, Selected, TextEditing, UiGlyphsSystemDesc, UiImage, UiTransform,
 };
 use amethyst_assets::{AssetStorage, Handle, Loader};
 use amethyst_core::{
@@ -51,6 +51,7 @@ use thread_profiler::profile_scope;
 #[derive(Debug, Default)]
 pub struct RenderUi {
     target: Target,
+    text_3d: bool,
 }
 
 impl RenderUi {
@@ -59,6 +60,13 @@ impl RenderUi {
         self.target = target;
         self
     }
+
+    /// Enable drawing `Text3d` components in the 3D scene of the target, tested against its
+    /// depth.
+    pub fn with_text_3d(mut self) -> Self {
+        self.text_3d = true;
+        self
+    }
 }
 
 impl<B: Backend> RenderPlugin<B> for RenderUi {
@@ -81,7 +89,11 @@ impl<B: Backend> RenderPlugin<B> for RenderUi {
         _factory: &mut Factory<B>,
         _world: &World,
     ) -> Result<(), Error> {
-        plan.extend_target(self.target, |ctx| {
+        let text_3d = self.text_3d;
+        plan.extend_target(self.target, move |ctx| {
+            if text_3d {
+                ctx.add(RenderOrder::Transparent, DrawText3dDesc::new().builder())?;
+            }
             ctx.add(RenderOrder::Overlay, DrawUiDesc::new().builder())?;
             Ok(())
         });
@@ -123,7 +135,7 @@ lazy_static::lazy_static! {
         "main",
     ).unwrap();
 
-    static ref UI_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+    pub(crate) static ref UI_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
         include_bytes!("../compiled/ui.frag.spv"),
         ShaderStageFlags::FRAGMENT,
         "main",
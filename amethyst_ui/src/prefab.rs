@@ -10,6 +10,7 @@ use amethyst_assets::{
     PrefabLoaderSystemDesc, Progress, ProgressCounter,
 };
 use amethyst_audio::Source as Audio;
+use amethyst_config::parse_ron;
 use amethyst_core::{
     ecs::{
         prelude::{Entities, Entity, Read, ReadExpect, World, Write, WriteStorage},
@@ -890,13 +891,8 @@ where
     }
 
     fn import_simple(&self, bytes: Vec<u8>) -> Result<UiPrefab<C::PrefabData, W>, Error> {
-        use ron::de::Deserializer;
-        let mut d = Deserializer::from_bytes(&bytes)
-            .with_context(|_| format_err!("Failed deserializing Ron file"))?;
-        let root: UiWidget<C, W> = UiWidget::deserialize(&mut d)
-            .with_context(|_| format_err!("Failed parsing Ron file"))?;
-        d.end()
-            .with_context(|_| format_err!("Failed parsing Ron file"))?;
+        let root: UiWidget<C, W> =
+            parse_ron(&bytes).with_context(|_| format_err!("Failed parsing Ron file"))?;
 
         let mut prefab = Prefab::new();
         walk_ui_tree(root, 0, &mut prefab, Default::default());
@@ -0,0 +1,336 @@
+//! Module containing text drawn in the 3D scene.
+
+use crate::{
+    glyphs::UiGlyphsResource,
+    pass::{UiArgs, UI_FRAGMENT},
+    Anchor, FontHandle,
+};
+use amethyst_core::{
+    ecs::{Component, DenseVecStorage, Join, ReadExpect, ReadStorage, SystemData, World},
+    math::{convert, Matrix4},
+    Hidden, HiddenPropagate, Transform,
+};
+use amethyst_rendy::{
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    rendy::{
+        command::{QueueId, RenderPassEncoder},
+        factory::Factory,
+        graph::{
+            render::{PrepareResult, RenderGroup, RenderGroupDesc},
+            GraphContext, NodeBuffer, NodeImage,
+        },
+        hal::{
+            self,
+            device::Device,
+            format::Format,
+            pso::{self, ShaderStageFlags},
+        },
+        mesh::{AsVertex, Model, VertexFormat},
+        shader::{Shader, SpirvShader},
+    },
+    simple_shader_set,
+    submodules::{DynamicVertexBuffer, FlatEnvironmentSub, TextureId, TextureSub},
+    types::Backend,
+    ChangeDetection,
+};
+use glsl_layout::{mat4, uint};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Height of a line of text in pixels at which the glyphs of `Text3d` are rasterized.
+pub(crate) const TEXT_3D_GLYPH_SIZE: f32 = 64.0;
+
+/// A component used to display text at the `Transform` of this entity in the 3D scene.
+///
+/// The text is laid out by the `UiGlyphsSystem` in the XY plane of the entity, facing +Z, and
+/// shares the glyph texture of `UiText`. Layouts are cached, so the text is only laid out again
+/// when it changes. It is drawn by `DrawText3d`, enabled with `RenderUi::with_text_3d`.
+#[derive(Clone, Debug)]
+pub struct Text3d {
+    /// The string rendered by this.
+    pub text: String,
+    /// The font used for rendering.
+    pub font: FontHandle,
+    /// The height of a line of text in world units.
+    pub size: f32,
+    /// The color of the rendered text, using a range of 0.0 to 1.0 per channel.
+    pub color: [f32; 4],
+    /// How to align the text on the origin of the entity.
+    pub align: Anchor,
+    /// If true the text faces the camera, keeping the position and scale of the entity.
+    pub billboard: bool,
+    /// Glyph quads in pixels of `TEXT_3D_GLYPH_SIZE`, written by the `UiGlyphsSystem`.
+    pub(crate) vertices: Vec<UiArgs>,
+}
+
+impl Text3d {
+    /// Initializes a new `Text3d`, centered on the origin of the entity.
+    ///
+    /// # Parameters
+    ///
+    /// * `font`: A handle to a `Font` asset
+    /// * `text`: the glyphs to render
+    /// * `color`: RGBA color with a maximum of 1.0 and a minimum of 0.0 for each channel
+    /// * `size`: the height of a line of text in world units
+    pub fn new(font: FontHandle, text: String, color: [f32; 4], size: f32) -> Self {
+        Text3d {
+            text,
+            font,
+            size,
+            color,
+            align: Anchor::Middle,
+            billboard: false,
+            vertices: Vec::new(),
+        }
+    }
+
+    /// Sets how to align the text on the origin of the entity.
+    pub fn with_align(mut self, align: Anchor) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Sets whether the text faces the camera.
+    pub fn with_billboard(mut self, billboard: bool) -> Self {
+        self.billboard = billboard;
+        self
+    }
+}
+
+impl Component for Text3d {
+    type Storage = DenseVecStorage<Self>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[repr(C, align(4))]
+struct Text3dArgs {
+    model: mat4,
+    glyph: UiArgs,
+    billboard: uint,
+}
+
+impl AsVertex for Text3dArgs {
+    fn vertex() -> VertexFormat {
+        VertexFormat::new((
+            Model::vertex(),
+            UiArgs::vertex(),
+            (Format::R32Uint, "billboard"),
+        ))
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref TEXT_3D_VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../compiled/text_3d.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+}
+
+/// Draw `Text3d` components in the 3D scene, tested against the depth of the target.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DrawText3dDesc;
+
+impl DrawText3dDesc {
+    /// Create new `DrawText3d` pass description
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawText3dDesc {
+    fn build(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _resources: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let env = FlatEnvironmentSub::new(factory)?;
+        let textures = TextureSub::new(factory)?;
+
+        let (pipeline, pipeline_layout) = build_text_3d_pipeline(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            vec![env.raw_layout(), textures.raw_layout()],
+        )?;
+
+        Ok(Box::new(DrawText3d::<B> {
+            pipeline,
+            pipeline_layout,
+            env,
+            textures,
+            vertex: DynamicVertexBuffer::new(),
+            args: Vec::new(),
+            glyph_tex: None,
+            change: Default::default(),
+        }))
+    }
+}
+
+/// Draws `Text3d` components in the 3D scene.
+#[derive(Debug)]
+pub struct DrawText3d<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    env: FlatEnvironmentSub<B>,
+    textures: TextureSub<B>,
+    vertex: DynamicVertexBuffer<B, Text3dArgs>,
+    args: Vec<Text3dArgs>,
+    glyph_tex: Option<TextureId>,
+    change: ChangeDetection,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawText3d<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        resources: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        let (texts, transforms, hiddens, hidden_propagates, glyphs_res) =
+            <(
+                ReadStorage<'_, Text3d>,
+                ReadStorage<'_, Transform>,
+                ReadStorage<'_, Hidden>,
+                ReadStorage<'_, HiddenPropagate>,
+                ReadExpect<'_, UiGlyphsResource>,
+            ) as SystemData>::fetch(resources);
+
+        self.env.process(factory, index, resources);
+
+        let last_count = self.args.len();
+        let last_tex = self.glyph_tex;
+        self.args.clear();
+        let glyph_tex = glyphs_res.glyph_tex().and_then(|tex| {
+            self.textures
+                .insert(factory, resources, tex, hal::image::Layout::General)
+        });
+        let mut changed = glyph_tex.map_or(false, |(_, changed)| changed);
+        self.glyph_tex = glyph_tex.map(|(id, _)| id);
+
+        if self.glyph_tex.is_some() {
+            for (text, transform, _, _) in
+                (&texts, &transforms, !&hiddens, !&hidden_propagates).join()
+            {
+                let model: [[f32; 4]; 4] = (convert::<_, Matrix4<f32>>(*transform.global_matrix())
+                    * Matrix4::new_scaling(text.size / TEXT_3D_GLYPH_SIZE))
+                .into();
+                self.args
+                    .extend(text.vertices.iter().map(|glyph| Text3dArgs {
+                        model: model.into(),
+                        glyph: *glyph,
+                        billboard: text.billboard as u32,
+                    }));
+            }
+        }
+
+        self.textures.maintain(factory, resources);
+        changed = self
+            .vertex
+            .write(factory, index, self.args.len() as u64, Some(&self.args[..]))
+            || changed;
+
+        changed = changed || last_count != self.args.len() || last_tex != self.glyph_tex;
+        self.change.prepare_result(index, changed)
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _resources: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        if let (Some(glyph_tex), false) = (self.glyph_tex, self.args.is_empty()) {
+            let layout = &self.pipeline_layout;
+            encoder.bind_graphics_pipeline(&self.pipeline);
+            self.env.bind(index, layout, 0, &mut encoder);
+            self.textures.bind(layout, 1, glyph_tex, &mut encoder);
+            self.vertex.bind(index, 0, 0, &mut encoder);
+            unsafe {
+                encoder.draw(0..4, 0..self.args.len() as u32);
+            }
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn build_text_3d_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let shader_vertex = unsafe { TEXT_3D_VERTEX.module(factory).unwrap() };
+    let shader_fragment = unsafe { UI_FRAGMENT.module(factory).unwrap() };
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            PipelineDescBuilder::new()
+                .with_vertex_desc(&[(Text3dArgs::vertex(), pso::VertexInputRate::Instance(1))])
+                .with_input_assembler(pso::InputAssemblerDesc::new(hal::Primitive::TriangleStrip))
+                .with_shaders(simple_shader_set(&shader_vertex, Some(&shader_fragment)))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_depth_test(pso::DepthTest {
+                    fun: pso::Comparison::Less,
+                    write: false,
+                })
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend: Some(pso::BlendState::ALPHA),
+                }]),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+    }
+}
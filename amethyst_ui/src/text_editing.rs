@@ -1,7 +1,5 @@
 use std::ops::Range;
 
-use clipboard::{ClipboardContext, ClipboardProvider};
-use log::error;
 use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
 use unicode_segmentation::UnicodeSegmentation;
 use winit::{ElementState, Event, KeyboardInput, ModifiersState, VirtualKeyCode, WindowEvent};
@@ -11,6 +9,7 @@ use amethyst_core::{
     shrev::{EventChannel, ReaderId},
 };
 use amethyst_derive::SystemDesc;
+use amethyst_window::Clipboard;
 
 use crate::{LineMode, Selected, TextEditing, UiEvent, UiEventType, UiText};
 
@@ -42,11 +41,12 @@ impl<'a> System<'a> for TextEditingInputSystem {
         ReadStorage<'a, Selected>,
         Read<'a, EventChannel<Event>>,
         Write<'a, EventChannel<UiEvent>>,
+        Read<'a, Clipboard>,
     );
 
     fn run(
         &mut self,
-        (entities, mut texts, mut editables, selecteds, events, mut edit_events): Self::SystemData,
+        (entities, mut texts, mut editables, selecteds, events, mut edit_events, clipboard): Self::SystemData,
     ) {
         for text in (&mut texts).join() {
             if (*text.text).chars().any(is_combining_mark) {
@@ -224,19 +224,11 @@ impl<'a> System<'a> for TextEditingInputSystem {
                         VirtualKeyCode::X => {
                             if ctrl_or_cmd(modifiers) {
                                 let new_clip = extract_highlighted(focused_edit, focused_text);
-                                if !new_clip.is_empty() {
-                                    match ClipboardProvider::new().and_then(
-                                        |mut ctx: ClipboardContext| ctx.set_contents(new_clip),
-                                    ) {
-                                        Ok(_) => edit_events.single_write(UiEvent::new(
-                                            UiEventType::ValueChange,
-                                            entity,
-                                        )),
-                                        Err(e) => error!(
-                                            "Error occured when cutting to clipboard: {:?}",
-                                            e
-                                        ),
-                                    }
+                                if !new_clip.is_empty() && clipboard.set_text(&new_clip) {
+                                    edit_events.single_write(UiEvent::new(
+                                        UiEventType::ValueChange,
+                                        entity,
+                                    ));
                                 }
                             }
                         }
@@ -244,13 +236,7 @@ impl<'a> System<'a> for TextEditingInputSystem {
                             if ctrl_or_cmd(modifiers) {
                                 let new_clip = read_highlighted(focused_edit, focused_text);
                                 if !new_clip.is_empty() {
-                                    if let Err(e) = ClipboardProvider::new().and_then(
-                                        |mut ctx: ClipboardContext| {
-                                            ctx.set_contents(new_clip.to_owned())
-                                        },
-                                    ) {
-                                        error!("Error occured when copying to clipboard: {:?}", e);
-                                    }
+                                    clipboard.set_text(new_clip);
                                 }
                             }
                         }
@@ -258,33 +244,25 @@ impl<'a> System<'a> for TextEditingInputSystem {
                             if ctrl_or_cmd(modifiers) {
                                 delete_highlighted(focused_edit, focused_text);
 
-                                match ClipboardProvider::new()
-                                    .and_then(|mut ctx: ClipboardContext| ctx.get_contents())
-                                {
-                                    Ok(contents) => {
-                                        let index = cursor_byte_index(focused_edit, focused_text);
-                                        let empty_space = focused_edit.max_length
-                                            - focused_text.text.graphemes(true).count();
-                                        let contents = contents
-                                            .graphemes(true)
-                                            .take(empty_space)
-                                            .fold(String::new(), |mut init, new| {
-                                                init.push_str(new);
-                                                init
-                                            });
-                                        focused_text.text.insert_str(index, &contents);
-                                        focused_edit.cursor_position +=
-                                            contents.graphemes(true).count() as isize;
+                                if let Some(contents) = clipboard.get_text() {
+                                    let index = cursor_byte_index(focused_edit, focused_text);
+                                    let empty_space = focused_edit.max_length
+                                        - focused_text.text.graphemes(true).count();
+                                    let contents = contents.graphemes(true).take(empty_space).fold(
+                                        String::new(),
+                                        |mut init, new| {
+                                            init.push_str(new);
+                                            init
+                                        },
+                                    );
+                                    focused_text.text.insert_str(index, &contents);
+                                    focused_edit.cursor_position +=
+                                        contents.graphemes(true).count() as isize;
 
-                                        edit_events.single_write(UiEvent::new(
-                                            UiEventType::ValueChange,
-                                            entity,
-                                        ));
-                                    }
-                                    Err(e) => error!(
-                                        "Error occured when pasting contents of clipboard: {:?}",
-                                        e
-                                    ),
+                                    edit_events.single_write(UiEvent::new(
+                                        UiEventType::ValueChange,
+                                        entity,
+                                    ));
                                 }
                             }
                         }
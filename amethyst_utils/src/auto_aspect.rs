@@ -0,0 +1,150 @@
+//! Keeps the aspect ratio of cameras in sync with the screen.
+
+use amethyst_assets::PrefabData;
+use amethyst_core::{
+    ecs::{
+        BitSet, Component, Entities, Entity, Join, NullStorage, Read, ReadExpect, ReadStorage,
+        System, SystemData, WriteStorage,
+    },
+    shrev::{EventChannel, ReaderId},
+};
+use amethyst_derive::{PrefabData, SystemDesc};
+use amethyst_error::Error;
+use amethyst_rendy::camera::{Camera, Orthographic, Perspective, Projection};
+use amethyst_window::{ScreenDimensions, ScreenDimensionsChanged};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Marks a `Camera` whose aspect ratio follows the screen, see `AutoAspectSystem`.
+///
+/// Perspective cameras keep their vertical field of view. Orthographic cameras keep their bottom
+/// and top, and their left and right are moved around their horizontal center. Cameras with a
+/// custom matrix are left as they are.
+///
+/// `AutoFov` and `CameraOrtho` adjust the projection themselves, so they should not be combined
+/// with this component.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PrefabData, Serialize)]
+#[prefab(Component)]
+pub struct AutoAspect;
+
+impl Component for AutoAspect {
+    type Storage = NullStorage<Self>;
+}
+
+/// System setting the aspect ratio of the cameras with an `AutoAspect` component to the aspect
+/// ratio of the screen.
+///
+/// Cameras are adjusted when they get an `AutoAspect`, and again when a
+/// `ScreenDimensionsChanged` event is received. The system is created with the
+/// `AutoAspectSystemDesc`.
+#[derive(Debug, SystemDesc)]
+#[system_desc(name(AutoAspectSystemDesc))]
+pub struct AutoAspectSystem {
+    #[system_desc(event_channel_reader)]
+    resize_reader: ReaderId<ScreenDimensionsChanged>,
+    #[system_desc(skip)]
+    adjusted: BitSet,
+}
+
+impl AutoAspectSystem {
+    /// Returns a new `AutoAspectSystem` reading the screen resizes with `resize_reader`.
+    pub fn new(resize_reader: ReaderId<ScreenDimensionsChanged>) -> Self {
+        Self {
+            resize_reader,
+            adjusted: BitSet::new(),
+        }
+    }
+}
+
+impl<'a> System<'a> for AutoAspectSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, ScreenDimensions>,
+        Read<'a, EventChannel<ScreenDimensionsChanged>>,
+        ReadStorage<'a, AutoAspect>,
+        WriteStorage<'a, Camera>,
+    );
+
+    fn run(&mut self, (entities, screen, resizes, auto_aspects, mut cameras): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("auto_aspect_system");
+
+        if resizes.read(&mut self.resize_reader).count() > 0 {
+            self.adjusted.clear();
+        }
+        for (entity, camera, _) in (&*entities, &mut cameras, &auto_aspects).join() {
+            if self.adjusted.add(entity.id()) {
+                continue;
+            }
+            if let Some(projection) = with_aspect(camera.projection(), screen.aspect_ratio()) {
+                camera.set_projection(projection);
+            }
+        }
+    }
+}
+
+/// Returns `projection` adjusted to `aspect`, or `None` for a custom matrix.
+fn with_aspect(projection: &Projection, aspect: f32) -> Option<Projection> {
+    if let Some(perspective) = projection.as_perspective() {
+        Some(
+            Perspective::new(
+                aspect,
+                perspective.fovy(),
+                perspective.near(),
+                perspective.far(),
+            )
+            .into(),
+        )
+    } else if let Some(ortho) = projection.as_orthographic() {
+        let center = (ortho.left() + ortho.right()) / 2.0;
+        let half_width = (ortho.top() - ortho.bottom()).abs() * aspect / 2.0;
+        Some(
+            Orthographic::new(
+                center - half_width,
+                center + half_width,
+                ortho.bottom(),
+                ortho.top(),
+                ortho.near(),
+                ortho.far(),
+            )
+            .into(),
+        )
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amethyst_rendy::camera::Projection;
+    use approx::assert_relative_eq;
+
+    use super::with_aspect;
+
+    #[test]
+    fn perspective_keeps_vertical_fov() {
+        let projection = Projection::perspective(1.0, 1.2, 0.1, 100.0);
+        let projection = with_aspect(&projection, 2.0).unwrap();
+        let perspective = projection.as_perspective().unwrap();
+
+        assert_relative_eq!(2.0, perspective.aspect(), epsilon = 1e-4);
+        assert_relative_eq!(1.2, perspective.fovy(), epsilon = 1e-4);
+        assert_relative_eq!(0.1, perspective.near(), epsilon = 1e-4);
+    }
+
+    #[test]
+    fn orthographic_keeps_height_and_center() {
+        let projection = Projection::orthographic(0.0, 4.0, 0.0, 2.0, 0.1, 10.0);
+        let projection = with_aspect(&projection, 0.5).unwrap();
+        let ortho = projection.as_orthographic().unwrap();
+
+        assert_relative_eq!(1.5, ortho.left(), epsilon = 1e-4);
+        assert_relative_eq!(2.5, ortho.right(), epsilon = 1e-4);
+        assert_relative_eq!(0.0, ortho.bottom(), epsilon = 1e-4);
+        assert_relative_eq!(2.0, ortho.top(), epsilon = 1e-4);
+        assert_relative_eq!(0.1, ortho.near(), epsilon = 1e-4);
+    }
+}
@@ -1,14 +1,17 @@
 //! Utility to adjust the aspect ratio of cameras automatically
 
 use amethyst_assets::PrefabData;
-use amethyst_core::ecs::{
-    Component, Entity, HashMapStorage, Join, ReadExpect, ReadStorage, System, SystemData,
-    WriteStorage,
+use amethyst_core::{
+    ecs::{
+        BitSet, Component, Entities, Entity, HashMapStorage, Join, Read, ReadExpect, ReadStorage,
+        System, SystemData, WriteStorage,
+    },
+    shrev::{EventChannel, ReaderId},
 };
 use amethyst_derive::{PrefabData, SystemDesc};
 use amethyst_error::Error;
 use amethyst_rendy::camera::Camera;
-use amethyst_window::ScreenDimensions;
+use amethyst_window::{ScreenDimensions, ScreenDimensionsChanged};
 
 use serde::{Deserialize, Serialize};
 
@@ -229,49 +232,55 @@ impl Default for AutoFov {
 ///
 /// If the camera is being loaded by a prefab, it is best to have the `PrefabLoaderSystem` loading
 /// the camera as a dependency of this system. It enables the system to adjust the camera right
-/// after it is created -- simply put, in the same frame.
+/// after it is created -- simply put, in the same frame. Cameras created after the first frame are
+/// adjusted as soon as they have both components, and every camera is adjusted again when a
+/// `ScreenDimensionsChanged` event is received.
+///
+/// The system is created with the `AutoFovSystemDesc`.
 #[derive(Debug, SystemDesc)]
+#[system_desc(name(AutoFovSystemDesc))]
 pub struct AutoFovSystem {
-    last_dimensions: ScreenDimensions,
+    #[system_desc(event_channel_reader)]
+    resize_reader: ReaderId<ScreenDimensionsChanged>,
+    #[system_desc(skip)]
+    adjusted: BitSet,
 }
 
 impl AutoFovSystem {
-    /// Sets up `SystemData` and returns a new `AutoFovSystem`.
-    pub fn new() -> Self {
+    /// Returns a new `AutoFovSystem` reading the screen resizes with `resize_reader`.
+    pub fn new(resize_reader: ReaderId<ScreenDimensionsChanged>) -> Self {
         Self {
-            last_dimensions: ScreenDimensions::new(0, 0, 0.0),
+            resize_reader,
+            adjusted: BitSet::new(),
         }
     }
 }
 
 impl<'a> System<'a> for AutoFovSystem {
     type SystemData = (
+        Entities<'a>,
         ReadExpect<'a, ScreenDimensions>,
+        Read<'a, EventChannel<ScreenDimensionsChanged>>,
         ReadStorage<'a, AutoFov>,
         WriteStorage<'a, Camera>,
     );
 
-    fn run(&mut self, (screen, auto_fovs, mut cameras): Self::SystemData) {
+    fn run(&mut self, (entities, screen, resizes, auto_fovs, mut cameras): Self::SystemData) {
         #[cfg(feature = "profiler")]
         profile_scope!("auto_fov_system");
 
-        if self.last_dimensions != *screen {
-            for (camera, auto_fov) in (&mut cameras, &auto_fovs).join() {
-                if let Some(perspective) = camera.projection_mut().as_perspective_mut() {
-                    let fovy = perspective.fovy();
-                    let fovx = auto_fov.new_fovx(screen.aspect_ratio(), fovy);
-                    perspective.set_aspect(fovx / fovy);
-                }
-            }
-            self.last_dimensions = screen.clone();
+        if resizes.read(&mut self.resize_reader).count() > 0 {
+            self.adjusted.clear();
         }
-    }
-}
-
-impl Default for AutoFovSystem {
-    fn default() -> Self {
-        Self {
-            last_dimensions: ScreenDimensions::new(0, 0, 0.0),
+        for (entity, camera, auto_fov) in (&*entities, &mut cameras, &auto_fovs).join() {
+            if self.adjusted.add(entity.id()) {
+                continue;
+            }
+            if let Some(perspective) = camera.projection_mut().as_perspective_mut() {
+                let fovy = perspective.fovy();
+                let fovx = auto_fov.new_fovx(screen.aspect_ratio(), fovy);
+                perspective.set_aspect(fovx / fovy);
+            }
         }
     }
 }
@@ -0,0 +1,427 @@
+//! Components spinning entities and moving them around a target, e.g. for showcase scenes.
+
+use amethyst_assets::PrefabData;
+use amethyst_core::{
+    ecs::{
+        Component, DenseVecStorage, Entities, Entity, Join, Read, ReadStorage, System, WriteStorage,
+    },
+    math::{Point3, Unit, UnitQuaternion, Vector3},
+    timing::Time,
+    Parent, Transform,
+};
+use amethyst_derive::PrefabData;
+use amethyst_error::Error;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Space in which the axis of an `AutoRotate` is given.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RotationSpace {
+    /// The axis turns along with the entity.
+    Local,
+    /// The axis is fixed in the world.
+    World,
+}
+
+impl Default for RotationSpace {
+    fn default() -> Self {
+        RotationSpace::Local
+    }
+}
+
+/// Rotates an entity around an axis at a constant speed, see `AutoRotateSystem`.
+///
+/// # Example
+///
+/// In a prefab, spinning around the vertical axis of the world:
+///
+/// ```ron,ignore
+/// auto_rotate: (
+///     axis: [0.0, 1.0, 0.0],
+///     degrees_per_second: 30.0,
+///     space: World,
+/// ),
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, PrefabData)]
+#[serde(default)]
+#[prefab(Component)]
+pub struct AutoRotate {
+    /// Axis of the rotation. It does not need to be normalized, and a zero axis stops it.
+    pub axis: Vector3<f32>,
+    /// Speed of the rotation, counter-clockwise when looking down the axis.
+    pub degrees_per_second: f32,
+    /// Whether `axis` turns along with the entity or is fixed in the world.
+    pub space: RotationSpace,
+}
+
+impl Default for AutoRotate {
+    fn default() -> Self {
+        AutoRotate::new(Vector3::y(), 45.0)
+    }
+}
+
+impl Component for AutoRotate {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl AutoRotate {
+    /// Creates a rotation around `axis` in local space.
+    pub fn new(axis: Vector3<f32>, degrees_per_second: f32) -> Self {
+        AutoRotate {
+            axis,
+            degrees_per_second,
+            space: RotationSpace::Local,
+        }
+    }
+
+    /// Sets the space in which the axis is given.
+    pub fn with_space(mut self, space: RotationSpace) -> Self {
+        self.space = space;
+        self
+    }
+
+    /// Rotates `transform` by the angle covered in `delta_seconds`. `parent_rotation` is the
+    /// global rotation of the parent of the entity, if it has one.
+    fn rotate(
+        &self,
+        transform: &mut Transform,
+        parent_rotation: Option<UnitQuaternion<f32>>,
+        delta_seconds: f32,
+    ) {
+        let axis = match Unit::try_new(self.axis, std::f32::EPSILON) {
+            Some(axis) => axis,
+            None => return,
+        };
+        let angle = self.degrees_per_second.to_radians() * delta_seconds;
+        match (self.space, parent_rotation) {
+            (RotationSpace::Local, _) => {
+                transform.append_rotation(axis, angle);
+            }
+            (RotationSpace::World, None) => {
+                transform.prepend_rotation(axis, angle);
+            }
+            (RotationSpace::World, Some(parent_rotation)) => {
+                let axis = Unit::new_normalize(parent_rotation.inverse() * axis.into_inner());
+                transform.prepend_rotation(axis, angle);
+            }
+        }
+    }
+}
+
+/// What an `Orbit` moves around.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OrbitTarget {
+    /// A fixed point, in the same space as the translation of the orbiting entity.
+    Point(Vector3<f32>),
+    /// The position of another entity, followed as it moves.
+    Entity(Entity),
+}
+
+/// Moves an entity around a target at a constant speed, see `AutoRotateSystem`.
+///
+/// The entity turns around `axis` going through the target, at the distance it starts at. When
+/// the target is an entity, it should not be a descendant of the orbiting entity.
+#[derive(Clone, Debug)]
+pub struct Orbit {
+    /// What the entity moves around.
+    pub target: OrbitTarget,
+    /// Axis of the orbit, in the same space as the translation of the entity.
+    pub axis: Vector3<f32>,
+    /// Speed of the orbit, counter-clockwise when looking down the axis.
+    pub degrees_per_second: f32,
+    /// Whether the distance to the target is kept when the target moves.
+    pub lock_radius: bool,
+    /// Whether the entity is turned to face the target as it moves.
+    pub look_at_target: bool,
+    radius: Option<f32>,
+}
+
+impl Component for Orbit {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl Orbit {
+    /// Creates an orbit around `target` which neither locks its radius nor turns the entity.
+    pub fn new(target: OrbitTarget, axis: Vector3<f32>, degrees_per_second: f32) -> Self {
+        Orbit {
+            target,
+            axis,
+            degrees_per_second,
+            lock_radius: false,
+            look_at_target: false,
+            radius: None,
+        }
+    }
+
+    /// Keeps the distance to the target the entity had when the orbit was first applied.
+    pub fn with_locked_radius(mut self) -> Self {
+        self.lock_radius = true;
+        self
+    }
+
+    /// Turns the entity to face the target as it moves.
+    pub fn looking_at_target(mut self) -> Self {
+        self.look_at_target = true;
+        self
+    }
+
+    /// Moves `transform` around `target` by the angle covered in `delta_seconds`. `target` is in
+    /// the same space as the translation of the entity.
+    fn step(&mut self, transform: &mut Transform, target: Vector3<f32>, delta_seconds: f32) {
+        let mut offset = transform.translation() - target;
+        if let Some(axis) = Unit::try_new(self.axis, std::f32::EPSILON) {
+            let angle = self.degrees_per_second.to_radians() * delta_seconds;
+            offset = UnitQuaternion::from_axis_angle(&axis, angle) * offset;
+        }
+        if self.lock_radius {
+            let radius = *self.radius.get_or_insert_with(|| offset.norm());
+            let norm = offset.norm();
+            if norm > std::f32::EPSILON {
+                offset *= radius / norm;
+            }
+        }
+        transform.set_translation(target + offset);
+        if self.look_at_target {
+            transform.face_towards(target, self.axis);
+        }
+    }
+}
+
+/// What an `OrbitPrefab` moves around.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum OrbitTargetPrefab {
+    /// A fixed point, in the same space as the translation of the orbiting entity.
+    Point(Vector3<f32>),
+    /// Index of the `Prefab` `Entity` to move around.
+    Entity(usize),
+}
+
+/// `PrefabData` for loading an `Orbit`.
+///
+/// # Example
+///
+/// ```ron,ignore
+/// orbit: (
+///     target: Entity(0),
+///     axis: [0.0, 1.0, 0.0],
+///     degrees_per_second: 20.0,
+///     look_at_target: true,
+/// ),
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OrbitPrefab {
+    /// What the entity moves around.
+    pub target: OrbitTargetPrefab,
+    /// Axis of the orbit.
+    pub axis: Vector3<f32>,
+    /// Speed of the orbit in degrees per second.
+    pub degrees_per_second: f32,
+    /// Whether the distance to the target is kept when the target moves.
+    pub lock_radius: bool,
+    /// Whether the entity is turned to face the target as it moves.
+    pub look_at_target: bool,
+}
+
+impl Default for OrbitPrefab {
+    fn default() -> Self {
+        OrbitPrefab {
+            target: OrbitTargetPrefab::Point(Vector3::zeros()),
+            axis: Vector3::y(),
+            degrees_per_second: 45.0,
+            lock_radius: false,
+            look_at_target: false,
+        }
+    }
+}
+
+impl<'a> PrefabData<'a> for OrbitPrefab {
+    type SystemData = WriteStorage<'a, Orbit>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        storage: &mut Self::SystemData,
+        entities: &[Entity],
+        _: &[Entity],
+    ) -> Result<(), Error> {
+        let target = match self.target {
+            OrbitTargetPrefab::Point(point) => OrbitTarget::Point(point),
+            OrbitTargetPrefab::Entity(index) => OrbitTarget::Entity(entities[index]),
+        };
+        storage
+            .insert(
+                entity,
+                Orbit {
+                    target,
+                    axis: self.axis,
+                    degrees_per_second: self.degrees_per_second,
+                    lock_radius: self.lock_radius,
+                    look_at_target: self.look_at_target,
+                    radius: None,
+                },
+            )
+            .map(|_| ())?;
+
+        Ok(())
+    }
+}
+
+/// Applies the `AutoRotate` and `Orbit` components, following the scaled `Time`, so they stop
+/// while the time is paused.
+///
+/// Global positions and rotations are read from the `Transform`s, so the system should run
+/// before the `TransformSystem`, e.g. by adding it before the `TransformBundle`.
+#[derive(Debug, Default)]
+pub struct AutoRotateSystem;
+
+impl<'a> System<'a> for AutoRotateSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        ReadStorage<'a, Parent>,
+        ReadStorage<'a, AutoRotate>,
+        WriteStorage<'a, Orbit>,
+        WriteStorage<'a, Transform>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, time, parents, auto_rotates, mut orbits, mut transforms): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("auto_rotate_system");
+
+        let delta_seconds = time.delta_seconds();
+        if delta_seconds == 0.0 {
+            return;
+        }
+
+        for (entity, auto_rotate, transform) in (&*entities, &auto_rotates, &mut transforms).join()
+        {
+            let parent_rotation = if parents.contains(entity) {
+                Some(transform.global_rotation() * transform.rotation().inverse())
+            } else {
+                None
+            };
+            auto_rotate.rotate(transform, parent_rotation, delta_seconds);
+        }
+
+        for (entity, orbit) in (&*entities, &mut orbits).join() {
+            let target = match orbit.target {
+                OrbitTarget::Point(point) => point,
+                OrbitTarget::Entity(target) => match transforms.get(target) {
+                    Some(target) => target.global_translation(),
+                    None => continue,
+                },
+            };
+            let transform = match transforms.get_mut(entity) {
+                Some(transform) => transform,
+                None => continue,
+            };
+            let target = match orbit.target {
+                OrbitTarget::Entity(_) if parents.contains(entity) => {
+                    // The parent space maps the local matrix to the global one.
+                    match transform.global_matrix().try_inverse() {
+                        Some(global_inverse) => {
+                            (transform.matrix() * global_inverse)
+                                .transform_point(&Point3::from(target))
+                                .coords
+                        }
+                        None => continue,
+                    }
+                }
+                _ => target,
+            };
+            orbit.step(transform, target, delta_seconds);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amethyst_core::{
+        math::{UnitQuaternion, Vector3},
+        Transform,
+    };
+    use approx::assert_relative_eq;
+
+    use super::{AutoRotate, Orbit, OrbitTarget, RotationSpace};
+
+    #[test]
+    fn rotation_axis_follows_space() {
+        let mut transform = Transform::default();
+        transform.set_rotation_x_axis(std::f32::consts::FRAC_PI_2);
+        let mut world = transform.clone();
+
+        AutoRotate::new(Vector3::y(), 90.0).rotate(&mut transform, None, 1.0);
+        AutoRotate::new(Vector3::y(), 90.0)
+            .with_space(RotationSpace::World)
+            .rotate(&mut world, None, 1.0);
+
+        // Local Y points along world Z after the first rotation.
+        assert_relative_eq!(
+            Vector3::y(),
+            transform.rotation() * Vector3::x(),
+            epsilon = 1e-4
+        );
+        assert_relative_eq!(
+            -Vector3::z(),
+            world.rotation() * Vector3::x(),
+            epsilon = 1e-4
+        );
+
+        let parent = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 0.5);
+        let mut child = Transform::default();
+        AutoRotate::new(Vector3::y(), 90.0)
+            .with_space(RotationSpace::World)
+            .rotate(&mut child, Some(parent), 1.0);
+        assert_relative_eq!(
+            -Vector3::z(),
+            parent * child.rotation() * parent.inverse() * Vector3::x(),
+            epsilon = 1e-4
+        );
+    }
+
+    #[test]
+    fn orbit_turns_around_target() {
+        let target = Vector3::new(1.0, 0.0, 0.0);
+        let mut orbit = Orbit::new(OrbitTarget::Point(target), Vector3::y(), 90.0)
+            .with_locked_radius()
+            .looking_at_target();
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(3.0, 0.0, 0.0);
+
+        orbit.step(&mut transform, target, 0.0);
+        assert_relative_eq!(
+            Vector3::new(3.0, 0.0, 0.0),
+            *transform.translation(),
+            epsilon = 1e-4
+        );
+
+        orbit.step(&mut transform, target, 1.0);
+        assert_relative_eq!(
+            Vector3::new(1.0, 0.0, -2.0),
+            *transform.translation(),
+            epsilon = 1e-4
+        );
+        // The entity looks down its -Z axis.
+        assert_relative_eq!(
+            Vector3::z(),
+            transform.rotation() * -Vector3::z(),
+            epsilon = 1e-4
+        );
+
+        // The target moved closer, the radius is kept.
+        orbit.step(&mut transform, Vector3::new(1.0, 0.0, -1.0), 0.0);
+        assert_relative_eq!(
+            Vector3::new(1.0, 0.0, -3.0),
+            *transform.translation(),
+            epsilon = 1e-4
+        );
+    }
+}
@@ -79,6 +79,23 @@ impl FpsCounter {
         }
         1.0e9 * self.buf.queue().len() as f32 / self.sum as f32
     }
+
+    ///Get the frame pacing jitter over the samplesize frames, in seconds.
+    ///
+    ///This is the average difference between the duration of a frame and the average frame
+    ///duration, so it is zero when every frame takes exactly as long.
+    pub fn sampled_jitter(&self) -> f32 {
+        let queue = self.buf.queue();
+        if queue.is_empty() {
+            return 0.0;
+        }
+        let mean = self.sum as f64 / queue.len() as f64;
+        let deviation = queue
+            .iter()
+            .map(|nanos| (*nanos as f64 - mean).abs())
+            .sum::<f64>();
+        (deviation / queue.len() as f64 / 1.0e9) as f32
+    }
 }
 
 /// Add this system to your game to automatically push FPS values
@@ -95,9 +112,10 @@ impl<'a> System<'a> for FpsCounterSystem {
         counter.push(duration_to_nanos(time.delta_real_time()));
         //Enable this to debug performance engine wide.
         log::debug!(
-            "Cur FPS: {}, Sampled: {}",
+            "Cur FPS: {}, Sampled: {}, Jitter: {}s",
             counter.frame_fps(),
-            counter.sampled_fps()
+            counter.sampled_fps(),
+            counter.sampled_jitter()
         );
     }
 }
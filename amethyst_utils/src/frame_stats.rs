@@ -0,0 +1,324 @@
+//! Frame time histogram, percentiles and spike detection.
+
+use std::time::Duration;
+
+use amethyst_core::{
+    ecs::prelude::{DispatcherBuilder, Read, System, World, Write},
+    shrev::EventChannel,
+    timing::{duration_to_nanos, Time},
+    SystemBundle,
+};
+use amethyst_error::Error;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Number of frames recorded before spikes are detected, so the median is meaningful.
+const MIN_SPIKE_SAMPLES: usize = 10;
+
+/// Characters used by `FrameTimeStats::histogram_bars`, from an empty to the fullest bucket.
+const BAR_LEVELS: &[u8] = b" .:-=+*#%@";
+
+/// Event sent by the `FrameTimeStatsSystem` when a frame took much longer than the recent
+/// frames, see `FrameTimeStats::with_spike_factor`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameSpike {
+    /// Number of the frame, as in `Time::frame_number`.
+    pub frame: u64,
+    /// Duration of the frame.
+    pub duration: Duration,
+    /// Median duration of the frames before it.
+    pub median: Duration,
+}
+
+/// Frame times over a sliding window of frames, sorted into a histogram.
+///
+/// Recording a frame does not allocate. Percentiles sort a copy of the window, so they are
+/// meant to be queried now and then, e.g. by a debug overlay.
+///
+/// # Example
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # use amethyst_utils::frame_stats::FrameTimeStats;
+/// let mut stats = FrameTimeStats::new(120)
+///     .with_bucket_edges(&[Duration::from_millis(17), Duration::from_millis(34)]);
+/// for frame in 0..100 {
+///     stats.push(frame, Duration::from_millis(16));
+/// }
+/// let spike = stats.push(100, Duration::from_millis(80));
+///
+/// assert_eq!(Some(Duration::from_millis(80)), spike.map(|s| s.duration));
+/// assert_eq!(&[100, 0, 1], stats.bucket_counts());
+/// assert_eq!(Duration::from_millis(16), stats.percentile(50.0));
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrameTimeStats {
+    /// Frame times in nanoseconds, used as a ring buffer once full.
+    samples: Vec<u64>,
+    next: usize,
+    window: usize,
+    /// Preallocated buffer to compute the median in.
+    scratch: Vec<u64>,
+    bucket_edges: Vec<u64>,
+    bucket_counts: Vec<u32>,
+    spike_factor: f32,
+}
+
+impl Default for FrameTimeStats {
+    fn default() -> Self {
+        FrameTimeStats::new(300)
+    }
+}
+
+impl FrameTimeStats {
+    /// Creates frame statistics over the last `window` frames.
+    ///
+    /// The histogram buckets are split at 60, 30 and 20 frames per second and at 100 ms,
+    /// and spikes are frames taking more than twice as long as the median.
+    pub fn new(window: usize) -> Self {
+        let window = window.max(1);
+        FrameTimeStats {
+            samples: Vec::with_capacity(window),
+            next: 0,
+            window,
+            scratch: Vec::with_capacity(window),
+            bucket_edges: Vec::new(),
+            bucket_counts: Vec::new(),
+            spike_factor: 2.0,
+        }
+        .with_bucket_edges(&[
+            Duration::from_nanos(16_666_667),
+            Duration::from_nanos(33_333_333),
+            Duration::from_nanos(50_000_000),
+            Duration::from_millis(100),
+        ])
+    }
+
+    /// Sets the frame times splitting the histogram buckets, so there is one more bucket than
+    /// there are edges. A frame which takes exactly as long as an edge goes into the bucket
+    /// above it.
+    pub fn with_bucket_edges(mut self, edges: &[Duration]) -> Self {
+        self.bucket_edges = edges.iter().map(|e| duration_to_nanos(*e)).collect();
+        self.bucket_edges.sort();
+        self.bucket_edges.dedup();
+        self.bucket_counts = vec![0; self.bucket_edges.len() + 1];
+        for &sample in &self.samples {
+            self.bucket_counts[bucket(&self.bucket_edges, sample)] += 1;
+        }
+        self
+    }
+
+    /// Sets how many times longer than the median of the window a frame has to take to be
+    /// reported as a spike.
+    pub fn with_spike_factor(mut self, factor: f32) -> Self {
+        self.spike_factor = factor;
+        self
+    }
+
+    /// Records the duration of a frame, returning a `FrameSpike` if it took more than the
+    /// spike factor times the median of the frames before it.
+    pub fn push(&mut self, frame: u64, duration: Duration) -> Option<FrameSpike> {
+        let nanos = duration_to_nanos(duration);
+        let spike = if self.samples.len() >= MIN_SPIKE_SAMPLES.min(self.window) {
+            let median = self.median();
+            if nanos as f64 > median as f64 * f64::from(self.spike_factor) {
+                Some(FrameSpike {
+                    frame,
+                    duration,
+                    median: Duration::from_nanos(median),
+                })
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        if self.samples.len() < self.window {
+            self.samples.push(nanos);
+        } else {
+            let evicted = std::mem::replace(&mut self.samples[self.next], nanos);
+            self.bucket_counts[bucket(&self.bucket_edges, evicted)] -= 1;
+        }
+        self.next = (self.next + 1) % self.window;
+        self.bucket_counts[bucket(&self.bucket_edges, nanos)] += 1;
+
+        spike
+    }
+
+    /// Forgets all recorded frames.
+    pub fn reset(&mut self) {
+        self.samples.clear();
+        self.next = 0;
+        for count in &mut self.bucket_counts {
+            *count = 0;
+        }
+    }
+
+    /// Returns the number of frames in the window.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Returns `true` if no frames have been recorded since the last reset.
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Returns the frame times splitting the histogram buckets, in ascending order.
+    pub fn bucket_edges(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.bucket_edges.iter().map(|e| Duration::from_nanos(*e))
+    }
+
+    /// Returns the number of frames in the window in each histogram bucket, from the fastest
+    /// to the slowest frames.
+    pub fn bucket_counts(&self) -> &[u32] {
+        &self.bucket_counts
+    }
+
+    /// Returns the frame time which `percentile` percent of the frames in the window do not
+    /// exceed, e.g. 99.0 for the 99th percentile. Returns zero if the window is empty.
+    pub fn percentile(&self, percentile: f32) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::from_secs(0);
+        }
+        let mut sorted = self.samples.clone();
+        sorted.sort_unstable();
+        let rank = (percentile / 100.0 * sorted.len() as f32).ceil() as usize;
+        Duration::from_nanos(sorted[rank.max(1).min(sorted.len()) - 1])
+    }
+
+    /// Renders the histogram with one character per bucket, from the fastest to the slowest
+    /// frames, with more ink for fuller buckets, e.g. `"@- . "`. Empty buckets are spaces.
+    pub fn histogram_bars(&self) -> String {
+        let max = self.bucket_counts.iter().cloned().max().unwrap_or(0) as usize;
+        self.bucket_counts
+            .iter()
+            .map(|&count| {
+                let level = if max == 0 {
+                    0
+                } else {
+                    // Rounded up, so a bucket holding any frame is never blank.
+                    (count as usize * (BAR_LEVELS.len() - 1) + max - 1) / max
+                };
+                BAR_LEVELS[level] as char
+            })
+            .collect()
+    }
+
+    fn median(&mut self) -> u64 {
+        self.scratch.clear();
+        self.scratch.extend_from_slice(&self.samples);
+        self.scratch.sort_unstable();
+        self.scratch[self.scratch.len() / 2]
+    }
+}
+
+/// Returns the index of the histogram bucket holding a frame of `nanos` nanoseconds.
+fn bucket(edges: &[u64], nanos: u64) -> usize {
+    edges
+        .iter()
+        .position(|edge| nanos < *edge)
+        .unwrap_or_else(|| edges.len())
+}
+
+/// Records the duration of every frame into the `FrameTimeStats` resource, and sends a
+/// `FrameSpike` event for frames taking much longer than the recent ones.
+#[derive(Debug, Default)]
+pub struct FrameTimeStatsSystem;
+
+impl<'a> System<'a> for FrameTimeStatsSystem {
+    type SystemData = (
+        Read<'a, Time>,
+        Write<'a, FrameTimeStats>,
+        Write<'a, EventChannel<FrameSpike>>,
+    );
+
+    fn run(&mut self, (time, mut stats, mut spikes): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("frame_time_stats_system");
+
+        if let Some(spike) = stats.push(time.frame_number(), time.delta_real_time()) {
+            log::debug!(
+                "Frame {} took {:?}, the median is {:?}",
+                spike.frame,
+                spike.duration,
+                spike.median
+            );
+            spikes.single_write(spike);
+        }
+    }
+}
+
+/// Adds the `FrameTimeStatsSystem` with the given `FrameTimeStats`.
+#[derive(Debug, Default)]
+pub struct FrameTimeStatsBundle {
+    stats: FrameTimeStats,
+}
+
+impl FrameTimeStatsBundle {
+    /// Creates the bundle recording into `stats`.
+    pub fn new(stats: FrameTimeStats) -> Self {
+        FrameTimeStatsBundle { stats }
+    }
+}
+
+impl<'a, 'b> SystemBundle<'a, 'b> for FrameTimeStatsBundle {
+    fn build(
+        self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        world.insert(self.stats);
+        builder.add(FrameTimeStatsSystem, "frame_time_stats_system", &[]);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::FrameTimeStats;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn histogram_follows_the_sliding_window() {
+        let mut stats = FrameTimeStats::new(4).with_bucket_edges(&[ms(10), ms(20)]);
+        for (frame, millis) in [5, 15, 25, 25, 5, 5].iter().enumerate() {
+            stats.push(frame as u64, ms(*millis));
+        }
+        assert_eq!(4, stats.len());
+        assert_eq!(&[2, 0, 2], stats.bucket_counts());
+        assert_eq!(ms(5), stats.percentile(50.0));
+        assert_eq!(ms(25), stats.percentile(95.0));
+        assert_eq!("@ @", stats.histogram_bars());
+
+        stats.reset();
+        assert!(stats.is_empty());
+        assert_eq!(&[0, 0, 0], stats.bucket_counts());
+        assert_eq!("   ", stats.histogram_bars());
+        assert_eq!(Duration::from_secs(0), stats.percentile(99.0));
+    }
+
+    #[test]
+    fn spikes_are_relative_to_the_median() {
+        let mut stats = FrameTimeStats::new(60).with_spike_factor(3.0);
+        assert!(
+            stats.push(0, ms(100)).is_none(),
+            "too few frames to compare"
+        );
+        for frame in 1..30 {
+            assert!(stats.push(frame, ms(16)).is_none());
+        }
+        assert!(stats.push(30, ms(40)).is_none());
+        let spike = stats.push(31, ms(80)).unwrap();
+        assert_eq!(31, spike.frame);
+        assert_eq!(ms(80), spike.duration);
+        assert_eq!(ms(16), spike.median);
+    }
+}
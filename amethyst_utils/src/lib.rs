@@ -12,11 +12,16 @@
 pub use self::app_root_dir::*;
 
 pub mod app_root_dir;
+pub mod auto_aspect;
 pub mod auto_fov;
+pub mod auto_rotate;
 pub mod circular_buffer;
 pub mod fps_counter;
+pub mod frame_stats;
+pub mod lifetime;
 pub mod ortho_camera;
 pub mod removal;
 pub mod scene;
+pub mod spline;
 pub mod tag;
 pub mod time_destroy;
@@ -0,0 +1,297 @@
+//! Deletes entities after a time to live, at a given frame or at the end of the frame.
+//!
+//! Add the `LifetimeBundle` to your game data. It deletes entities carrying an expired
+//! `TimeToLive` or `DeleteAtFrame` component, and entities queued in the `DeferredDelete`
+//! resource, after all other systems of the dispatcher have run.
+
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use amethyst_core::{
+    ecs::{
+        hibitset::BitSet, Component, DenseVecStorage, DispatcherBuilder, Entities, Entity, Join,
+        NullStorage, Read, ReadStorage, System, World, WriteStorage,
+    },
+    timing::Time,
+    transform::Parent,
+    SystemBundle,
+};
+use amethyst_error::Error;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Deletes the entity once it has been alive for this long, counted in game time.
+///
+/// The remaining time is decreased every frame, so it can be read or extended while the
+/// entity is alive.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TimeToLive(pub Duration);
+
+impl Component for TimeToLive {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Deletes the entity at the end of the given frame, see `Time::frame_number`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeleteAtFrame(pub u64);
+
+impl Component for DeleteAtFrame {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Makes the deletion of this entity by `TimeToLive` or `DeleteAtFrame` also delete all of its
+/// descendants, i.e. all entities with a `Parent` chain leading to it.
+///
+/// Descendants are looked up when the entity is deleted, so children added after the
+/// components were attached are deleted as well.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeleteDescendants;
+
+impl Component for DeleteDescendants {
+    type Storage = NullStorage<Self>;
+}
+
+/// Queue of entities to delete once all systems of the current dispatch have run.
+///
+/// Only needs to be read, so any system can push to it without blocking other systems.
+/// Entities that are already dead when the queue is processed are skipped.
+///
+/// # Example
+///
+/// ```rust
+/// # use amethyst_core::ecs::prelude::*;
+/// use amethyst_utils::lifetime::DeferredDelete;
+///
+/// struct DespawnSystem;
+///
+/// impl<'a> System<'a> for DespawnSystem {
+///     type SystemData = (Entities<'a>, Read<'a, DeferredDelete>);
+///
+///     fn run(&mut self, (entities, deferred): Self::SystemData) {
+///         for entity in (&*entities).join() {
+///             deferred.delete(entity);
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct DeferredDelete {
+    queue: Mutex<Vec<(Entity, bool)>>,
+}
+
+impl DeferredDelete {
+    /// Queues the entity for deletion.
+    pub fn delete(&self, entity: Entity) {
+        self.push(entity, false);
+    }
+
+    /// Queues the entity and all of its descendants for deletion.
+    pub fn delete_recursive(&self, entity: Entity) {
+        self.push(entity, true);
+    }
+
+    fn push(&self, entity: Entity, recursive: bool) {
+        self.queue
+            .lock()
+            .expect("DeferredDelete queue was poisoned")
+            .push((entity, recursive));
+    }
+
+    fn drain(&self) -> Vec<(Entity, bool)> {
+        std::mem::replace(
+            &mut *self
+                .queue
+                .lock()
+                .expect("DeferredDelete queue was poisoned"),
+            Vec::new(),
+        )
+    }
+}
+
+/// Deletes expired and queued entities, see the [module documentation](index.html).
+#[derive(Debug, Default)]
+pub struct LifetimeSystem;
+
+impl<'a> System<'a> for LifetimeSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        Read<'a, DeferredDelete>,
+        WriteStorage<'a, TimeToLive>,
+        ReadStorage<'a, DeleteAtFrame>,
+        ReadStorage<'a, DeleteDescendants>,
+        ReadStorage<'a, Parent>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, time, deferred, mut ttls, delete_at, recursive, parents): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("lifetime_system");
+
+        let mut doomed = deferred.drain();
+
+        let delta = time.delta_time();
+        for (entity, ttl) in (&entities, &mut ttls).join() {
+            ttl.0 = ttl.0.checked_sub(delta).unwrap_or_default();
+            if ttl.0 == Duration::from_secs(0) {
+                doomed.push((entity, recursive.contains(entity)));
+            }
+        }
+
+        let frame = time.frame_number();
+        for (entity, at) in (&entities, &delete_at).join() {
+            if frame >= at.0 {
+                doomed.push((entity, recursive.contains(entity)));
+            }
+        }
+
+        if doomed.is_empty() {
+            return;
+        }
+
+        let mut children = HashMap::<Entity, Vec<Entity>>::new();
+        if doomed.iter().any(|(_, recursive)| *recursive) {
+            for (entity, parent) in (&entities, &parents).join() {
+                children.entry(parent.entity).or_default().push(entity);
+            }
+        }
+
+        let mut deleted = BitSet::new();
+        while let Some((entity, recursive)) = doomed.pop() {
+            if deleted.contains(entity.id()) || !entities.is_alive(entity) {
+                continue;
+            }
+            deleted.add(entity.id());
+            // The entity was checked to be alive, so this can not fail.
+            let _ = entities.delete(entity);
+            if recursive {
+                if let Some(children) = children.get(&entity) {
+                    doomed.extend(children.iter().map(|child| (*child, true)));
+                }
+            }
+        }
+    }
+}
+
+/// Adds the `LifetimeSystem`, running after all other systems of the dispatcher.
+#[derive(Debug, Default)]
+pub struct LifetimeBundle;
+
+impl<'a, 'b> SystemBundle<'a, 'b> for LifetimeBundle {
+    fn build(
+        self,
+        _world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        builder.add_thread_local(LifetimeSystem);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amethyst_core::ecs::{Builder, RunNow, WorldExt};
+
+    use super::*;
+
+    fn setup() -> World {
+        let mut world = World::new();
+        world.register::<TimeToLive>();
+        world.register::<DeleteAtFrame>();
+        world.register::<DeleteDescendants>();
+        world.register::<Parent>();
+        world.insert(DeferredDelete::default());
+        world.insert(Time::default());
+        world
+    }
+
+    fn step(world: &mut World, delta: Duration) {
+        {
+            let mut time = world.write_resource::<Time>();
+            time.increment_frame_number();
+            time.set_delta_time(delta);
+        }
+        LifetimeSystem.run_now(world);
+        world.maintain();
+    }
+
+    fn child_of(world: &mut World, parent: Entity) -> Entity {
+        world
+            .create_entity()
+            .with(Parent { entity: parent })
+            .build()
+    }
+
+    #[test]
+    fn time_to_live_expires() {
+        let mut world = setup();
+        let entity = world
+            .create_entity()
+            .with(TimeToLive(Duration::from_millis(25)))
+            .build();
+
+        step(&mut world, Duration::from_millis(10));
+        step(&mut world, Duration::from_millis(10));
+        assert!(world.is_alive(entity));
+        step(&mut world, Duration::from_millis(10));
+        assert!(!world.is_alive(entity));
+    }
+
+    #[test]
+    fn recursive_deletion_includes_late_descendants() {
+        let mut world = setup();
+        let parent = world
+            .create_entity()
+            .with(TimeToLive(Duration::from_millis(10)))
+            .with(DeleteDescendants)
+            .build();
+        let child = child_of(&mut world, parent);
+        let grandchild = child_of(&mut world, child);
+        let plain = world
+            .create_entity()
+            .with(TimeToLive(Duration::from_millis(10)))
+            .build();
+        let orphan = child_of(&mut world, plain);
+
+        step(&mut world, Duration::from_millis(10));
+        assert!(!world.is_alive(parent));
+        assert!(!world.is_alive(child));
+        assert!(!world.is_alive(grandchild));
+        assert!(!world.is_alive(plain));
+        assert!(world.is_alive(orphan));
+    }
+
+    #[test]
+    fn delete_at_frame_is_frame_exact() {
+        let mut world = setup();
+        let entity = world.create_entity().with(DeleteAtFrame(3)).build();
+
+        step(&mut world, Duration::from_millis(10));
+        step(&mut world, Duration::from_millis(10));
+        assert!(world.is_alive(entity));
+        step(&mut world, Duration::from_millis(10));
+        assert!(!world.is_alive(entity));
+    }
+
+    #[test]
+    fn deferred_delete_ignores_dead_entities() {
+        let mut world = setup();
+        let entity = world.create_entity().build();
+        let dead = world.create_entity().build();
+        world.delete_entity(dead).unwrap();
+        let reused = world.create_entity().build();
+
+        {
+            let deferred = world.read_resource::<DeferredDelete>();
+            deferred.delete(dead);
+            deferred.delete(entity);
+            deferred.delete(entity);
+        }
+        step(&mut world, Duration::from_millis(10));
+        assert!(!world.is_alive(entity));
+        assert!(world.is_alive(reused));
+    }
+}
@@ -2,13 +2,17 @@
 
 use amethyst_assets::PrefabData;
 use amethyst_core::{
-    ecs::{Component, DenseVecStorage, Entity, Join, ReadExpect, System, WriteStorage},
+    ecs::{
+        Component, DenseVecStorage, Entity, Join, Read, ReadExpect, System, SystemData,
+        WriteStorage,
+    },
+    shrev::{EventChannel, ReaderId},
     Axis2,
 };
-use amethyst_derive::PrefabData;
+use amethyst_derive::{PrefabData, SystemDesc};
 use amethyst_error::Error;
 use amethyst_rendy::camera::{Camera, Orthographic};
-use amethyst_window::ScreenDimensions;
+use amethyst_window::{ScreenDimensions, ScreenDimensionsChanged};
 use derive_new::new;
 
 use serde::{Deserialize, Serialize};
@@ -231,25 +235,42 @@ impl Default for CameraNormalizeMode {
 
 /// System that automatically changes the camera matrix according to the settings in
 /// the `CameraOrtho` attached to the camera entity.
-#[derive(Default, Debug)]
-pub struct CameraOrthoSystem;
+///
+/// Cameras are adjusted when they get a `CameraOrtho`, and again when a
+/// `ScreenDimensionsChanged` event is received. The system is created with the
+/// `CameraOrthoSystemDesc`.
+#[derive(Debug, SystemDesc)]
+#[system_desc(name(CameraOrthoSystemDesc))]
+pub struct CameraOrthoSystem {
+    #[system_desc(event_channel_reader)]
+    resize_reader: ReaderId<ScreenDimensionsChanged>,
+}
+
+impl CameraOrthoSystem {
+    /// Returns a new `CameraOrthoSystem` reading the screen resizes with `resize_reader`.
+    pub fn new(resize_reader: ReaderId<ScreenDimensionsChanged>) -> Self {
+        Self { resize_reader }
+    }
+}
 
 impl<'a> System<'a> for CameraOrthoSystem {
     type SystemData = (
         ReadExpect<'a, ScreenDimensions>,
+        Read<'a, EventChannel<ScreenDimensionsChanged>>,
         WriteStorage<'a, Camera>,
         WriteStorage<'a, CameraOrtho>,
     );
 
-    #[allow(clippy::float_cmp)] // cmp just used to recognize change
-    fn run(&mut self, (dimensions, mut cameras, mut ortho_cameras): Self::SystemData) {
+    #[allow(clippy::float_cmp)] // cmp just used to recognize cameras not adjusted yet
+    fn run(&mut self, (dimensions, resizes, mut cameras, mut ortho_cameras): Self::SystemData) {
         #[cfg(feature = "profiler")]
         profile_scope!("camera_ortho_system");
 
+        let resized = resizes.read(&mut self.resize_reader).count() > 0;
         let aspect = dimensions.aspect_ratio();
 
         for (camera, mut ortho_camera) in (&mut cameras, &mut ortho_cameras).join() {
-            if aspect != ortho_camera.aspect_ratio_cache {
+            if resized || ortho_camera.aspect_ratio_cache == 0.0 {
                 ortho_camera.aspect_ratio_cache = aspect;
                 let offsets = ortho_camera.camera_offsets(aspect);
 
@@ -0,0 +1,675 @@
+//! Smooth paths and a component moving entities along them.
+//!
+//! A `Spline` is an asset so that many followers can share one path. Add the
+//! `PathFollowerBundle`, then give entities a `Transform` and a `PathFollower` referring to the
+//! spline. Reaching an end of the path or passing one of its named waypoints sends a `PathEvent`.
+
+use amethyst_assets::{Asset, AssetStorage, Handle, ProcessingState, Processor};
+use amethyst_core::{
+    ecs::{
+        Component, DenseVecStorage, DispatcherBuilder, Entities, Entity, Join, Read, ReadStorage,
+        System, VecStorage, World, Write, WriteStorage,
+    },
+    math::{Point3, Vector3},
+    shrev::EventChannel,
+    timing::Time,
+    transform::Transform,
+    SystemBundle,
+};
+use amethyst_error::{format_err, Error};
+use amethyst_rendy::{debug_drawing::DebugLines, palette::Srgba};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Number of samples per segment used to map distances to curve parameters.
+const ARC_LENGTH_SAMPLES: usize = 16;
+
+/// The kind of curve described by the control points of a `Spline`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SplineKind {
+    /// A curve passing through every control point. Needs at least two points.
+    CatmullRom,
+    /// Cubic Bézier segments sharing their end points: a knot, two handles, a knot, two handles
+    /// and so on, ending with a knot. Needs `3 * n + 1` points.
+    CubicBezier,
+}
+
+/// The serialized form of a `Spline`, e.g. loaded with `RonFormat`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SplineData {
+    /// The kind of curve.
+    pub kind: SplineKind,
+    /// The control points.
+    pub points: Vec<Vector3<f32>>,
+    /// Named waypoints as pairs of a name and the index of a knot, the points the curve passes
+    /// through. For Catmull-Rom splines every point is a knot, for Bézier splines every third.
+    #[serde(default)]
+    pub waypoints: Vec<(String, usize)>,
+}
+
+/// A named position along a `Spline`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Waypoint {
+    /// The name sent with `PathEventKind::WaypointPassed`.
+    pub name: String,
+    /// Distance from the start of the spline.
+    pub distance: f32,
+}
+
+/// A smooth path with precomputed arc lengths, so it can be traversed at a constant speed.
+#[derive(Debug, Clone)]
+pub struct Spline {
+    kind: SplineKind,
+    points: Vec<Vector3<f32>>,
+    /// Cumulative distance at every arc length sample, per segment.
+    samples: Vec<[f32; ARC_LENGTH_SAMPLES + 1]>,
+    /// Distance from the start of the spline to the start of every segment and to its end.
+    offsets: Vec<f32>,
+    waypoints: Vec<Waypoint>,
+}
+
+impl Spline {
+    /// Creates a Catmull-Rom spline passing through all `points`.
+    pub fn catmull_rom(points: Vec<Vector3<f32>>) -> Result<Self, Error> {
+        if points.len() < 2 {
+            return Err(format_err!(
+                "A Catmull-Rom spline needs at least 2 points, got {}",
+                points.len()
+            ));
+        }
+        Ok(Self::build(SplineKind::CatmullRom, points))
+    }
+
+    /// Creates a spline from cubic Bézier segments, see `SplineKind::CubicBezier`.
+    pub fn cubic_bezier(points: Vec<Vector3<f32>>) -> Result<Self, Error> {
+        if points.len() < 4 || (points.len() - 1) % 3 != 0 {
+            return Err(format_err!(
+                "A cubic Bézier spline needs 3 * n + 1 points, got {}",
+                points.len()
+            ));
+        }
+        Ok(Self::build(SplineKind::CubicBezier, points))
+    }
+
+    /// Creates a spline from its serialized form.
+    pub fn from_data(data: SplineData) -> Result<Self, Error> {
+        let mut spline = match data.kind {
+            SplineKind::CatmullRom => Self::catmull_rom(data.points)?,
+            SplineKind::CubicBezier => Self::cubic_bezier(data.points)?,
+        };
+        for (name, knot) in data.waypoints {
+            spline = spline.with_waypoint(name, knot)?;
+        }
+        Ok(spline)
+    }
+
+    fn build(kind: SplineKind, points: Vec<Vector3<f32>>) -> Self {
+        let mut spline = Spline {
+            kind,
+            points,
+            samples: Vec::new(),
+            offsets: vec![0.0],
+            waypoints: Vec::new(),
+        };
+        let mut total = 0.0;
+        for segment in 0..spline.segment_count() {
+            let mut lengths = [0.0; ARC_LENGTH_SAMPLES + 1];
+            let mut previous = spline.segment_point(segment, 0.0);
+            for (i, length) in lengths.iter_mut().enumerate().skip(1) {
+                let point = spline.segment_point(segment, i as f32 / ARC_LENGTH_SAMPLES as f32);
+                total += (point - previous).norm();
+                *length = total;
+                previous = point;
+            }
+            lengths[0] = *spline
+                .offsets
+                .last()
+                .expect("Unreachable: offsets start with 0");
+            spline.samples.push(lengths);
+            spline.offsets.push(total);
+        }
+        spline
+    }
+
+    /// Adds a named waypoint at the given knot, see `SplineData::waypoints`.
+    pub fn with_waypoint(mut self, name: impl Into<String>, knot: usize) -> Result<Self, Error> {
+        let distance = *self.offsets.get(knot).ok_or_else(|| {
+            format_err!(
+                "Knot {} does not exist, the spline has {} knots",
+                knot,
+                self.offsets.len()
+            )
+        })?;
+        self.waypoints.push(Waypoint {
+            name: name.into(),
+            distance,
+        });
+        self.waypoints.sort_by(|a, b| {
+            a.distance
+                .partial_cmp(&b.distance)
+                .expect("Finite distances")
+        });
+        Ok(self)
+    }
+
+    /// The kind of curve.
+    pub fn kind(&self) -> SplineKind {
+        self.kind
+    }
+
+    /// The control points.
+    pub fn points(&self) -> &[Vector3<f32>] {
+        &self.points
+    }
+
+    /// The named waypoints, ordered by distance.
+    pub fn waypoints(&self) -> &[Waypoint] {
+        &self.waypoints
+    }
+
+    /// The length of the whole spline.
+    pub fn length(&self) -> f32 {
+        *self
+            .offsets
+            .last()
+            .expect("Unreachable: offsets start with 0")
+    }
+
+    /// The position at `distance` from the start, clamped to the ends of the spline.
+    pub fn position(&self, distance: f32) -> Vector3<f32> {
+        let (segment, t) = self.locate(distance);
+        self.segment_point(segment, t)
+    }
+
+    /// The normalized direction of the spline at `distance` from the start, or zero if the
+    /// spline does not move there.
+    pub fn tangent(&self, distance: f32) -> Vector3<f32> {
+        let (segment, t) = self.locate(distance);
+        self.segment_derivative(segment, t)
+            .try_normalize(std::f32::EPSILON)
+            .unwrap_or_else(Vector3::zeros)
+    }
+
+    /// Submits lines approximating the spline to be rendered, along with a short line at every
+    /// waypoint.
+    pub fn draw_debug(&self, lines: &mut DebugLines, color: Srgba) {
+        for segment in 0..self.segment_count() {
+            let mut previous = self.segment_point(segment, 0.0);
+            for i in 1..=ARC_LENGTH_SAMPLES {
+                let point = self.segment_point(segment, i as f32 / ARC_LENGTH_SAMPLES as f32);
+                lines.draw_line(Point3::from(previous), Point3::from(point), color);
+                previous = point;
+            }
+        }
+        for waypoint in &self.waypoints {
+            lines.draw_direction(
+                Point3::from(self.position(waypoint.distance)),
+                Vector3::y() * 0.25,
+                color,
+            );
+        }
+    }
+
+    fn segment_count(&self) -> usize {
+        match self.kind {
+            SplineKind::CatmullRom => self.points.len() - 1,
+            SplineKind::CubicBezier => (self.points.len() - 1) / 3,
+        }
+    }
+
+    /// Finds the segment and curve parameter at `distance` from the start.
+    fn locate(&self, distance: f32) -> (usize, f32) {
+        let distance = distance.max(0.0).min(self.length());
+        let segment = match self.offsets[1..]
+            .iter()
+            .position(|offset| distance <= *offset)
+        {
+            Some(segment) => segment,
+            None => self.segment_count() - 1,
+        };
+        let lengths = &self.samples[segment];
+        let i = lengths[1..]
+            .iter()
+            .position(|length| distance <= *length)
+            .unwrap_or(ARC_LENGTH_SAMPLES - 1);
+        let (start, end) = (lengths[i], lengths[i + 1]);
+        let fraction = if end > start {
+            (distance - start) / (end - start)
+        } else {
+            0.0
+        };
+        (segment, (i as f32 + fraction) / ARC_LENGTH_SAMPLES as f32)
+    }
+
+    fn control_points(&self, segment: usize) -> [Vector3<f32>; 4] {
+        let p = &self.points;
+        match self.kind {
+            SplineKind::CatmullRom => {
+                let last = p.len() - 1;
+                [
+                    p[segment.saturating_sub(1)],
+                    p[segment],
+                    p[segment + 1],
+                    p[(segment + 2).min(last)],
+                ]
+            }
+            SplineKind::CubicBezier => {
+                let i = segment * 3;
+                [p[i], p[i + 1], p[i + 2], p[i + 3]]
+            }
+        }
+    }
+
+    fn segment_point(&self, segment: usize, t: f32) -> Vector3<f32> {
+        let [p0, p1, p2, p3] = self.control_points(segment);
+        let (t2, t3) = (t * t, t * t * t);
+        match self.kind {
+            SplineKind::CatmullRom => {
+                (p1 * 2.0
+                    + (p2 - p0) * t
+                    + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+                    + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+                    * 0.5
+            }
+            SplineKind::CubicBezier => {
+                let u = 1.0 - t;
+                p0 * (u * u * u) + p1 * (3.0 * u * u * t) + p2 * (3.0 * u * t2) + p3 * t3
+            }
+        }
+    }
+
+    fn segment_derivative(&self, segment: usize, t: f32) -> Vector3<f32> {
+        let [p0, p1, p2, p3] = self.control_points(segment);
+        match self.kind {
+            SplineKind::CatmullRom => {
+                ((p2 - p0)
+                    + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * (2.0 * t)
+                    + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * (3.0 * t * t))
+                    * 0.5
+            }
+            SplineKind::CubicBezier => {
+                let u = 1.0 - t;
+                (p1 - p0) * (3.0 * u * u) + (p2 - p1) * (6.0 * u * t) + (p3 - p2) * (3.0 * t * t)
+            }
+        }
+    }
+}
+
+impl Asset for Spline {
+    const NAME: &'static str = "utils::Spline";
+    type Data = SplineData;
+    type HandleStorage = VecStorage<Handle<Self>>;
+}
+
+impl amethyst_assets::ProcessableAsset for Spline {
+    fn process(data: SplineData) -> Result<ProcessingState<Spline>, Error> {
+        Spline::from_data(data).map(ProcessingState::Loaded)
+    }
+}
+
+/// What a `PathFollower` does when it reaches an end of its spline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PathMode {
+    /// Stop at the end.
+    Once,
+    /// Jump back to the start.
+    Loop,
+    /// Turn around and go back towards the other end.
+    PingPong,
+}
+
+/// Moves the entity along a `Spline` at a constant speed by writing its `Transform`.
+#[derive(Debug, Clone)]
+pub struct PathFollower {
+    /// The path to follow.
+    pub spline: Handle<Spline>,
+    /// Speed in world units per second.
+    pub speed: f32,
+    /// What to do at the end of the path.
+    pub mode: PathMode,
+    /// Whether the entity is rotated to face along the path, see `Transform::face_towards`.
+    pub orient: bool,
+    /// Distance travelled from the start of the spline.
+    pub distance: f32,
+    /// Whether the follower currently moves towards the start, in `PathMode::PingPong`.
+    pub reversed: bool,
+    /// Whether a `PathMode::Once` follower has reached the end.
+    pub finished: bool,
+}
+
+impl PathFollower {
+    /// Creates a follower at the start of `spline` which stops at the end.
+    pub fn new(spline: Handle<Spline>, speed: f32) -> Self {
+        PathFollower {
+            spline,
+            speed,
+            mode: PathMode::Once,
+            orient: false,
+            distance: 0.0,
+            reversed: false,
+            finished: false,
+        }
+    }
+
+    /// Sets what to do at the end of the path.
+    pub fn with_mode(mut self, mode: PathMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets whether the entity is rotated to face along the path.
+    pub fn with_orientation(mut self, orient: bool) -> Self {
+        self.orient = orient;
+        self
+    }
+
+    /// Moves `step` along `spline`, calling `event` for every waypoint passed and end reached.
+    fn advance(&mut self, spline: &Spline, step: f32, mut event: impl FnMut(PathEventKind)) {
+        let length = spline.length();
+        let mut remaining = step.max(0.0);
+        // Waypoints exactly at the position the follower starts from were already passed.
+        let mut inclusive = false;
+        while !self.finished && remaining > 0.0 && length > 0.0 {
+            let (from, target) = if self.reversed {
+                (self.distance, self.distance - remaining)
+            } else {
+                (self.distance, self.distance + remaining)
+            };
+            let end = if self.reversed { 0.0 } else { length };
+            let overshoot = if self.reversed {
+                -target
+            } else {
+                target - length
+            };
+            let to = if overshoot >= 0.0 { end } else { target };
+
+            let reversed = self.reversed;
+            let passed = spline.waypoints().iter().filter(|w| {
+                if reversed {
+                    (w.distance < from || (inclusive && w.distance <= from)) && w.distance >= to
+                } else {
+                    (w.distance > from || (inclusive && w.distance >= from)) && w.distance <= to
+                }
+            });
+            if self.reversed {
+                for waypoint in passed.rev() {
+                    event(PathEventKind::WaypointPassed(waypoint.name.clone()));
+                }
+            } else {
+                for waypoint in passed {
+                    event(PathEventKind::WaypointPassed(waypoint.name.clone()));
+                }
+            }
+
+            if overshoot < 0.0 {
+                self.distance = target;
+                break;
+            }
+            event(PathEventKind::EndReached);
+            remaining = overshoot;
+            match self.mode {
+                PathMode::Once => {
+                    self.distance = end;
+                    self.finished = true;
+                }
+                PathMode::Loop => {
+                    self.distance = 0.0;
+                    inclusive = true;
+                }
+                PathMode::PingPong => {
+                    self.distance = end;
+                    self.reversed = !self.reversed;
+                    inclusive = false;
+                }
+            }
+        }
+    }
+}
+
+impl Component for PathFollower {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// What happened to a `PathFollower`, see `PathEvent`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathEventKind {
+    /// The follower passed the waypoint with this name.
+    WaypointPassed(String),
+    /// The follower reached an end of the spline. Sent every time an end is reached, also when
+    /// looping or turning around.
+    EndReached,
+}
+
+/// Sent through an `EventChannel<PathEvent>` by the `PathFollowerSystem`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathEvent {
+    /// The entity following the path.
+    pub entity: Entity,
+    /// What happened.
+    pub kind: PathEventKind,
+}
+
+/// Moves `PathFollower`s along their splines, see the [module documentation](index.html).
+#[derive(Debug, Default)]
+pub struct PathFollowerSystem;
+
+impl<'a> System<'a> for PathFollowerSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        Read<'a, AssetStorage<Spline>>,
+        WriteStorage<'a, PathFollower>,
+        WriteStorage<'a, Transform>,
+        Write<'a, EventChannel<PathEvent>>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, time, splines, mut followers, mut transforms, mut events): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("path_follower_system");
+
+        for (entity, follower, transform) in (&entities, &mut followers, &mut transforms).join() {
+            let spline = match splines.get(&follower.spline) {
+                Some(spline) => spline,
+                None => continue,
+            };
+            let step = follower.speed * time.delta_seconds();
+            follower.advance(spline, step, |kind| {
+                events.single_write(PathEvent { entity, kind })
+            });
+
+            let position = spline.position(follower.distance);
+            transform.set_translation(position);
+            if follower.orient {
+                let tangent = spline.tangent(follower.distance);
+                let direction = if follower.reversed { -tangent } else { tangent };
+                transform.face_towards(position + direction, Vector3::y());
+            }
+        }
+    }
+}
+
+/// Draws the splines of all `PathFollower`s with the `DebugLines` resource.
+#[derive(Debug)]
+pub struct SplineDebugLinesSystem {
+    /// The color of the lines.
+    pub color: Srgba,
+}
+
+impl<'a> System<'a> for SplineDebugLinesSystem {
+    type SystemData = (
+        Read<'a, AssetStorage<Spline>>,
+        ReadStorage<'a, PathFollower>,
+        Option<Write<'a, DebugLines>>,
+    );
+
+    fn run(&mut self, (splines, followers, lines): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("spline_debug_lines_system");
+
+        let mut lines = match lines {
+            Some(lines) => lines,
+            None => return,
+        };
+        let mut drawn = Vec::new();
+        for follower in followers.join() {
+            if drawn.contains(&follower.spline.id()) {
+                continue;
+            }
+            drawn.push(follower.spline.id());
+            if let Some(spline) = splines.get(&follower.spline) {
+                spline.draw_debug(&mut lines, self.color);
+            }
+        }
+    }
+}
+
+/// Adds the `Spline` processor and the `PathFollowerSystem`, and optionally the
+/// `SplineDebugLinesSystem`.
+#[derive(Debug, Default)]
+pub struct PathFollowerBundle {
+    debug_lines: Option<Srgba>,
+}
+
+impl PathFollowerBundle {
+    /// Creates the bundle without debug lines.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draws the splines being followed in the given color, using the `DebugLines` resource.
+    pub fn with_debug_lines(mut self, color: Srgba) -> Self {
+        self.debug_lines = Some(color);
+        self
+    }
+}
+
+impl<'a, 'b> SystemBundle<'a, 'b> for PathFollowerBundle {
+    fn build(
+        self,
+        _world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        builder.add(Processor::<Spline>::new(), "spline_processor", &[]);
+        builder.add(
+            PathFollowerSystem,
+            "path_follower_system",
+            &["spline_processor"],
+        );
+        if let Some(color) = self.debug_lines {
+            builder.add(
+                SplineDebugLinesSystem { color },
+                "spline_debug_lines_system",
+                &["spline_processor"],
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amethyst_assets::{AssetStorage, Handle};
+    use amethyst_core::math::Vector3;
+
+    use super::*;
+
+    fn straight() -> Spline {
+        Spline::catmull_rom(vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(5.0, 0.0, 0.0),
+            Vector3::new(10.0, 0.0, 0.0),
+        ])
+        .unwrap()
+        .with_waypoint("middle", 1)
+        .unwrap()
+    }
+
+    fn follower(spline: Handle<Spline>, mode: PathMode) -> PathFollower {
+        PathFollower::new(spline, 1.0).with_mode(mode)
+    }
+
+    fn advance(follower: &mut PathFollower, spline: &Spline, step: f32) -> Vec<PathEventKind> {
+        let mut events = Vec::new();
+        follower.advance(spline, step, |e| events.push(e));
+        events
+    }
+
+    #[test]
+    fn arc_length_is_precomputed() {
+        let spline = straight();
+        assert!((spline.length() - 10.0).abs() < 1e-3);
+        assert!((spline.position(2.5) - Vector3::new(2.5, 0.0, 0.0)).norm() < 1e-2);
+        assert!((spline.tangent(7.0) - Vector3::x()).norm() < 1e-3);
+        assert!((spline.waypoints()[0].distance - 5.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn bezier_passes_through_knots() {
+        let spline = Spline::cubic_bezier(vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(1.0, 1.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+        ])
+        .unwrap();
+        assert!(spline.position(0.0).norm() < 1e-6);
+        assert!((spline.position(spline.length()) - Vector3::x()).norm() < 1e-6);
+        assert!(Spline::cubic_bezier(vec![Vector3::zeros(); 5]).is_err());
+        assert!(Spline::catmull_rom(vec![Vector3::zeros()]).is_err());
+    }
+
+    #[test]
+    fn once_stops_at_the_end() {
+        let spline = straight();
+        let handle = AssetStorage::new().insert(straight());
+        let mut follower = follower(handle, PathMode::Once);
+
+        assert_eq!(
+            vec![PathEventKind::WaypointPassed("middle".to_string())],
+            advance(&mut follower, &spline, 6.0)
+        );
+        assert_eq!(
+            vec![PathEventKind::EndReached],
+            advance(&mut follower, &spline, 6.0)
+        );
+        assert!(follower.finished);
+        assert!(advance(&mut follower, &spline, 6.0).is_empty());
+    }
+
+    #[test]
+    fn loop_and_ping_pong_wrap_around() {
+        let spline = straight();
+        let handle = AssetStorage::new().insert(straight());
+
+        let mut looping = follower(handle.clone(), PathMode::Loop);
+        assert_eq!(
+            vec![
+                PathEventKind::WaypointPassed("middle".to_string()),
+                PathEventKind::EndReached,
+                PathEventKind::WaypointPassed("middle".to_string()),
+            ],
+            advance(&mut looping, &spline, 16.0)
+        );
+        assert!((looping.distance - 6.0).abs() < 1e-3);
+
+        let mut ping_pong = follower(handle, PathMode::PingPong);
+        assert_eq!(
+            vec![
+                PathEventKind::WaypointPassed("middle".to_string()),
+                PathEventKind::EndReached,
+            ],
+            advance(&mut ping_pong, &spline, 13.0)
+        );
+        assert!(ping_pong.reversed);
+        assert!((ping_pong.distance - 7.0).abs() < 1e-3);
+        assert_eq!(
+            vec![PathEventKind::WaypointPassed("middle".to_string())],
+            advance(&mut ping_pong, &spline, 3.0)
+        );
+    }
+}
@@ -1,4 +1,5 @@
-use crate::{DisplayConfig, EventsLoopSystem, WindowSystem};
+use crate::{DisplayConfig, EventsLoopSystem, WindowIcon, WindowSystem};
+use amethyst_assets::Processor;
 use amethyst_config::{Config, ConfigError};
 use amethyst_core::{bundle::SystemBundle, ecs::World, shred::DispatcherBuilder};
 use amethyst_error::Error;
@@ -33,6 +34,19 @@ impl WindowBundle {
         )?))
     }
 
+    /// Builds a new window bundle by loading the `DisplayConfig` from several files, each
+    /// overriding the ones before it, then from the environment variables starting with
+    /// `AMETHYST_DISPLAY__`, e.g. `AMETHYST_DISPLAY__FULLSCREEN="Borderless(Primary)"`.
+    ///
+    /// See `Config::load_layered_with_env` for how the configuration is merged.
+    pub fn from_layered_config<P: AsRef<std::path::Path>>(
+        paths: &[P],
+    ) -> Result<Self, ConfigError> {
+        Ok(WindowBundle::from_config(
+            DisplayConfig::load_layered_with_env(paths, "AMETHYST_DISPLAY")?,
+        ))
+    }
+
     /// Builds a new window bundle with a predefined `DisplayConfig`.
     ///
     /// This uses a `DisplayConfig::default()`, but with the following differences:
@@ -56,11 +70,9 @@ impl<'a, 'b> SystemBundle<'a, 'b> for WindowBundle {
         builder: &mut DispatcherBuilder<'a, 'b>,
     ) -> Result<(), Error> {
         let event_loop = EventsLoop::new();
-        builder.add(
-            WindowSystem::from_config(world, &event_loop, self.config),
-            "window",
-            &[],
-        );
+        builder.add(Processor::<WindowIcon>::new(), "window_icon_processor", &[]);
+        // winit requires some window changes to be made on the thread owning the events loop
+        builder.add_thread_local(WindowSystem::from_config(world, &event_loop, self.config));
         builder.add_thread_local(EventsLoopSystem::new(event_loop));
         Ok(())
     }
@@ -0,0 +1,71 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+use clipboard::{ClipboardContext, ClipboardProvider};
+use log::{debug, warn};
+
+/// World resource reading and writing the text of the system clipboard.
+///
+/// A platform clipboard context is opened for every call and the calls are serialized, so the
+/// resource can be used from any system. On platforms without clipboard access, reading returns
+/// `None` and writing does nothing, and the failure is logged once.
+///
+/// # Examples
+///
+/// ```
+/// use amethyst_core::ecs::{Read, System};
+/// use amethyst_window::Clipboard;
+///
+/// struct CopySeedSystem;
+///
+/// impl<'s> System<'s> for CopySeedSystem {
+///     type SystemData = Read<'s, Clipboard>;
+///
+///     fn run(&mut self, clipboard: Self::SystemData) {
+///         clipboard.set_text("seed-1234");
+///     }
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct Clipboard {
+    lock: Mutex<()>,
+    failed: AtomicBool,
+}
+
+impl Clipboard {
+    /// Returns the text of the clipboard, or `None` if the clipboard can not be read.
+    pub fn get_text(&self) -> Option<String> {
+        let _lock = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        match ClipboardProvider::new().and_then(|mut ctx: ClipboardContext| ctx.get_contents()) {
+            Ok(text) => Some(text),
+            Err(e) => {
+                self.report("read", &*e);
+                None
+            }
+        }
+    }
+
+    /// Replaces the text of the clipboard, returning whether it was written.
+    pub fn set_text(&self, text: &str) -> bool {
+        let _lock = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        match ClipboardProvider::new()
+            .and_then(|mut ctx: ClipboardContext| ctx.set_contents(text.to_owned()))
+        {
+            Ok(()) => true,
+            Err(e) => {
+                self.report("write", &*e);
+                false
+            }
+        }
+    }
+
+    fn report(&self, action: &str, error: &dyn std::error::Error) {
+        if self.failed.swap(true, Ordering::Relaxed) {
+            debug!("Failed to {} the clipboard: {}", action, error);
+        } else {
+            warn!("Failed to {} the clipboard: {}", action, error);
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use winit::MouseCursor;
+
+use crate::{config::Fullscreen, icon::WindowIconHandle};
+
+/// A change to the window, applied by the `WindowSystem`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WindowCommand {
+    /// Switches between windowed, borderless and exclusive fullscreen.
+    ///
+    /// Leaving fullscreen restores the size and position the window had before entering it.
+    SetFullscreen(Fullscreen),
+    /// Changes the title of the window.
+    SetTitle(String),
+    /// Changes the window icon, once the icon is loaded.
+    SetIcon(WindowIconHandle),
+    /// Changes the mouse cursor to one of the standard cursors.
+    SetCursorIcon(MouseCursor),
+    /// Changes the mouse cursor to an image, with the hotspot in pixels from its top left corner.
+    ///
+    /// winit does not support custom cursors yet, so this only logs a warning.
+    SetCustomCursor(WindowIconHandle, (u32, u32)),
+}
+
+/// World resource queuing changes to the window.
+///
+/// The commands are applied in order by the `WindowSystem` on its next run, on the thread owning
+/// the events loop. They change the existing window, so the renderer only has to resize its
+/// surface. A command failing, for example because the platform does not support it, logs an
+/// error instead of panicking.
+///
+/// # Examples
+///
+/// ```
+/// use amethyst_window::{Fullscreen, MonitorSelection, WindowCommands};
+///
+/// let mut commands = WindowCommands::default();
+/// commands.set_fullscreen(Fullscreen::Borderless(MonitorSelection::Index(1)));
+/// ```
+#[derive(Debug, Default)]
+pub struct WindowCommands {
+    queue: Vec<WindowCommand>,
+}
+
+impl WindowCommands {
+    /// Queues a command.
+    pub fn push(&mut self, command: WindowCommand) {
+        self.queue.push(command);
+    }
+
+    /// Queues a change of the fullscreen mode.
+    pub fn set_fullscreen(&mut self, fullscreen: Fullscreen) {
+        self.push(WindowCommand::SetFullscreen(fullscreen));
+    }
+
+    /// Queues a change of the window title.
+    pub fn set_title(&mut self, title: impl Into<String>) {
+        self.push(WindowCommand::SetTitle(title.into()));
+    }
+
+    /// Queues a change of the window icon.
+    pub fn set_icon(&mut self, icon: WindowIconHandle) {
+        self.push(WindowCommand::SetIcon(icon));
+    }
+
+    /// Queues a change of the mouse cursor.
+    pub fn set_cursor_icon(&mut self, cursor: MouseCursor) {
+        self.push(WindowCommand::SetCursorIcon(cursor));
+    }
+
+    /// Queues a change of the mouse cursor to an image.
+    pub fn set_custom_cursor(&mut self, image: WindowIconHandle, hotspot: (u32, u32)) {
+        self.push(WindowCommand::SetCustomCursor(image, hotspot));
+    }
+
+    pub(crate) fn drain(&mut self) -> impl Iterator<Item = WindowCommand> + '_ {
+        self.queue.drain(..)
+    }
+}
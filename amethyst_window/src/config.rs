@@ -1,10 +1,52 @@
 use std::path::PathBuf;
 
-use log::error;
+use log::{error, warn};
 use serde::{Deserialize, Serialize};
-use winit::{Icon, WindowAttributes, WindowBuilder};
+use winit::{Icon, MonitorId, WindowAttributes, WindowBuilder};
 
-use crate::monitor::{MonitorIdent, MonitorsAccess};
+use crate::monitor::{MonitorSelection, MonitorsAccess, VideoMode};
+
+/// How the window covers the screen.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub enum Fullscreen {
+    /// A regular window.
+    Windowed,
+    /// A window without decorations covering the whole monitor.
+    Borderless(MonitorSelection),
+    /// Fullscreen handled by the platform, optionally with a display mode of the monitor.
+    ///
+    /// winit can not change the display mode of a monitor, so a mode other than the current one
+    /// of the monitor is ignored with a warning.
+    Exclusive(MonitorSelection, Option<VideoMode>),
+}
+
+impl Default for Fullscreen {
+    fn default() -> Self {
+        Fullscreen::Windowed
+    }
+}
+
+impl Fullscreen {
+    /// Returns the monitor for exclusive fullscreen, or `None` for the other modes.
+    pub(crate) fn exclusive_monitor(&self, monitors: &impl MonitorsAccess) -> Option<MonitorId> {
+        match self {
+            Fullscreen::Exclusive(selection, mode) => {
+                let monitor = selection.monitor_id(monitors);
+                if let Some(mode) = mode {
+                    let dimensions = monitor.get_dimensions().into();
+                    if mode.dimensions != dimensions {
+                        warn!(
+                            "Video mode {:?} is not supported, using the current mode {:?}",
+                            mode.dimensions, dimensions
+                        );
+                    }
+                }
+                Some(monitor)
+            }
+            _ => None,
+        }
+    }
+}
 
 /// Configuration for a window display.
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -12,10 +54,10 @@ pub struct DisplayConfig {
     /// Name of the application window.
     #[serde(default = "default_title")]
     pub title: String,
-    /// Enables fullscreen mode on specific monitor when set.
-    /// Defaults to `None`, which means fullscreen is off.
+    /// Fullscreen mode, and the monitor it is shown on.
+    /// Defaults to `Fullscreen::Windowed`.
     #[serde(default)]
-    pub fullscreen: Option<MonitorIdent>,
+    pub fullscreen: Fullscreen,
     /// Current window dimensions, measured in pixels (px).
     #[serde(default)]
     pub dimensions: Option<(u32, u32)>,
@@ -30,6 +72,10 @@ pub struct DisplayConfig {
     pub visibility: bool,
     /// A path to the icon used for the window.
     /// If `loaded_icon` is present, this will be ignored.
+    ///
+    /// When the `Loader` resource exists, the icon is loaded through the asset system relative to
+    /// the assets directory, and shown once loaded. Otherwise it is read from the file system
+    /// when the window is created.
     #[serde(default)]
     pub icon: Option<PathBuf>,
     /// Whether the window should always be on top of other windows.
@@ -85,7 +131,7 @@ impl Default for DisplayConfig {
     fn default() -> Self {
         DisplayConfig {
             title: default_title(),
-            fullscreen: None,
+            fullscreen: Fullscreen::Windowed,
             dimensions: None,
             min_dimensions: None,
             max_dimensions: None,
@@ -121,20 +167,33 @@ fn default_resizable() -> bool {
 impl DisplayConfig {
     /// Creates a `winit::WindowBuilder` using the values set in the `DisplayConfig`.
     ///
-    /// The `MonitorsAccess` is needed to configure a fullscreen window.
+    /// The `MonitorsAccess` is needed to configure a fullscreen window. A borderless window is
+    /// sized to its monitor, but only the `WindowSystem` moves it onto the monitor.
     pub fn into_window_builder(self, monitors: &impl MonitorsAccess) -> WindowBuilder {
+        let mut dimensions = self.dimensions.map(Into::into);
+        let mut decorations = self.decorations;
+        if let Fullscreen::Borderless(selection) = &self.fullscreen {
+            let monitor = selection.monitor_id(monitors);
+            dimensions = Some(
+                monitor
+                    .get_dimensions()
+                    .to_logical(monitor.get_hidpi_factor()),
+            );
+            decorations = false;
+        }
+
         let attrs = WindowAttributes {
-            dimensions: self.dimensions.map(Into::into),
+            dimensions,
             max_dimensions: self.max_dimensions.map(Into::into),
             min_dimensions: self.min_dimensions.map(Into::into),
             title: self.title,
             maximized: self.maximized,
             visible: self.visibility,
             transparent: self.transparent,
-            decorations: self.decorations,
+            decorations,
             always_on_top: self.always_on_top,
             window_icon: None,
-            fullscreen: self.fullscreen.map(|ident| ident.monitor_id(monitors)),
+            fullscreen: self.fullscreen.exclusive_monitor(monitors),
             resizable: self.resizable,
             multitouch: self.multitouch,
         };
@@ -0,0 +1,37 @@
+use amethyst_assets::{Asset, Format, Handle};
+use amethyst_core::ecs::prelude::VecStorage;
+use amethyst_error::Error;
+use serde::{Deserialize, Serialize};
+use winit::Icon;
+
+/// An image used as the window icon, loaded through the asset system.
+#[derive(Clone, Debug)]
+pub struct WindowIcon(pub Icon);
+
+/// A handle to a window icon.
+pub type WindowIconHandle = Handle<WindowIcon>;
+
+impl Asset for WindowIcon {
+    const NAME: &'static str = "window::WindowIcon";
+    type Data = WindowIcon;
+    type HandleStorage = VecStorage<WindowIconHandle>;
+}
+
+/// Loads window icons from PNG files, decoded to RGBA.
+///
+/// Other image formats known to the `image` crate are detected and decoded as well.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct IconFormat;
+
+amethyst_assets::register_format_type!(WindowIcon);
+
+amethyst_assets::register_format!("ICON", IconFormat as WindowIcon);
+impl Format<WindowIcon> for IconFormat {
+    fn name(&self) -> &'static str {
+        "ICON"
+    }
+
+    fn import_simple(&self, bytes: Vec<u8>) -> Result<WindowIcon, Error> {
+        Ok(WindowIcon(Icon::from_bytes(&bytes)?))
+    }
+}
@@ -11,7 +11,10 @@
 #![allow(clippy::new_without_default)]
 
 mod bundle;
+mod clipboard;
+mod command;
 mod config;
+mod icon;
 mod monitor;
 mod resources;
 mod system;
@@ -20,9 +23,12 @@ mod system;
 pub use crate::bundle::{SCREEN_HEIGHT, SCREEN_WIDTH};
 pub use crate::{
     bundle::WindowBundle,
-    config::DisplayConfig,
-    monitor::{MonitorIdent, MonitorsAccess},
-    resources::ScreenDimensions,
+    clipboard::Clipboard,
+    command::{WindowCommand, WindowCommands},
+    config::{DisplayConfig, Fullscreen},
+    icon::{IconFormat, WindowIcon, WindowIconHandle},
+    monitor::{MonitorIdent, MonitorInfo, MonitorSelection, Monitors, MonitorsAccess, VideoMode},
+    resources::{ScreenDimensions, ScreenDimensionsChanged},
     system::{EventsLoopSystem, WindowSystem},
 };
-pub use winit::{Icon, Window};
+pub use winit::{Icon, MouseCursor, Window};
@@ -1,3 +1,4 @@
+use log::warn;
 use serde::{Deserialize, Serialize};
 use winit::{AvailableMonitorsIter, EventsLoop, MonitorId, Window};
 
@@ -80,3 +81,121 @@ impl MonitorIdent {
             .unwrap_or_else(|| monitors.primary())
     }
 }
+
+/// Selects the monitor a fullscreen window is shown on.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub enum MonitorSelection {
+    /// The primary monitor.
+    Primary,
+    /// The monitor at this index in the list of available monitors, as listed by `Monitors`.
+    Index(usize),
+    /// The first monitor with this name.
+    Name(String),
+    /// The monitor matching this identifier most closely.
+    Ident(MonitorIdent),
+}
+
+impl Default for MonitorSelection {
+    fn default() -> Self {
+        MonitorSelection::Primary
+    }
+}
+
+impl MonitorSelection {
+    /// Select the monitor, falling back to the primary monitor with a warning if it is not found.
+    pub fn monitor_id(&self, monitors: &impl MonitorsAccess) -> MonitorId {
+        let found = match self {
+            MonitorSelection::Primary => return monitors.primary(),
+            MonitorSelection::Index(index) => monitors.iter().nth(*index),
+            MonitorSelection::Name(name) => monitors
+                .iter()
+                .find(|m| m.get_name().as_ref() == Some(name)),
+            MonitorSelection::Ident(ident) => return ident.monitor_id(monitors),
+        };
+        found.unwrap_or_else(|| {
+            warn!(
+                "Monitor {:?} not found, using the primary monitor instead",
+                self
+            );
+            monitors.primary()
+        })
+    }
+}
+
+/// A display mode of a monitor.
+///
+/// winit does not support changing the display mode of a monitor, so the only mode listed for a
+/// monitor is its current one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct VideoMode {
+    /// Resolution of the mode, measured in physical pixels (px).
+    pub dimensions: (u32, u32),
+}
+
+/// Description of a monitor, listed by the `Monitors` resource.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MonitorInfo {
+    /// Human readable name of the monitor, if the platform provides one.
+    pub name: Option<String>,
+    /// Position of the top left corner of the monitor on the desktop, in physical pixels (px).
+    pub position: (i32, i32),
+    /// Current resolution of the monitor, in physical pixels (px).
+    pub dimensions: (u32, u32),
+    /// The ratio between physical pixels and logical pixels on this monitor.
+    pub hidpi_factor: f64,
+    /// The display modes the monitor can be set to.
+    pub video_modes: Vec<VideoMode>,
+    /// Whether this is the primary monitor.
+    pub primary: bool,
+}
+
+impl MonitorInfo {
+    fn new(monitor: &MonitorId, primary: &MonitorId) -> Self {
+        let dimensions = monitor.get_dimensions().into();
+        MonitorInfo {
+            name: monitor.get_name(),
+            position: monitor.get_position().into(),
+            dimensions,
+            hidpi_factor: monitor.get_hidpi_factor(),
+            video_modes: vec![VideoMode { dimensions }],
+            primary: monitor.get_name() == primary.get_name()
+                && monitor.get_position() == primary.get_position(),
+        }
+    }
+}
+
+/// World resource listing the available monitors, so a settings menu can offer them.
+///
+/// The list is refreshed by the `WindowSystem` every time the fullscreen mode changes.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Monitors {
+    monitors: Vec<MonitorInfo>,
+}
+
+impl Monitors {
+    /// Lists the monitors currently available.
+    pub fn from_access(monitors: &impl MonitorsAccess) -> Self {
+        let primary = monitors.primary();
+        Monitors {
+            monitors: monitors
+                .iter()
+                .map(|monitor| MonitorInfo::new(&monitor, &primary))
+                .collect(),
+        }
+    }
+
+    /// Returns the monitors, in the order used by `MonitorSelection::Index`.
+    pub fn iter(&self) -> impl Iterator<Item = &MonitorInfo> {
+        self.monitors.iter()
+    }
+
+    /// Returns the monitor at `index`.
+    pub fn get(&self, index: usize) -> Option<&MonitorInfo> {
+        self.monitors.get(index)
+    }
+
+    /// Returns the primary monitor.
+    pub fn primary(&self) -> Option<&MonitorInfo> {
+        self.monitors.iter().find(|monitor| monitor.primary)
+    }
+}
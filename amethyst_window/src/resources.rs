@@ -72,4 +72,23 @@ impl ScreenDimensions {
     pub fn update_hidpi_factor(&mut self, factor: f64) {
         self.hidpi = factor;
     }
+
+    /// Returns true if the size or the hidpi factor differs from `other`.
+    #[allow(clippy::float_cmp)] // cmp just used to recognize change
+    pub(crate) fn differs_from(&self, other: &ScreenDimensions) -> bool {
+        self.w != other.w || self.h != other.h || self.hidpi != other.hidpi
+    }
+}
+
+/// Event sent by the `WindowSystem` when the size or the hidpi factor of the screen changed.
+///
+/// It is written to the `EventChannel<ScreenDimensionsChanged>` resource, and states receive it
+/// as `StateEvent::ScreenDimensions`. The changes made during one frame, e.g. while the window is
+/// being resized, are sent as a single event.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScreenDimensionsChanged {
+    /// The dimensions before the change.
+    pub old: ScreenDimensions,
+    /// The dimensions after the change.
+    pub new: ScreenDimensions,
 }
@@ -1,15 +1,39 @@
-use crate::{config::DisplayConfig, resources::ScreenDimensions};
+use crate::{
+    command::{WindowCommand, WindowCommands},
+    config::{DisplayConfig, Fullscreen},
+    icon::{IconFormat, WindowIcon, WindowIconHandle},
+    monitor::Monitors,
+    resources::{ScreenDimensions, ScreenDimensionsChanged},
+};
+use amethyst_assets::{AssetStorage, Loader};
 use amethyst_config::{Config, ConfigError};
 use amethyst_core::{
-    ecs::{ReadExpect, RunNow, System, SystemData, World, Write, WriteExpect},
+    ecs::{Read, ReadExpect, RunNow, System, SystemData, World, Write, WriteExpect},
     shrev::EventChannel,
 };
+use log::warn;
 use std::path::Path;
-use winit::{Event, EventsLoop, Window};
+use winit::{
+    dpi::{LogicalPosition, LogicalSize},
+    Event, EventsLoop, MonitorId, Window,
+};
 
 /// System for opening and managing the window.
+///
+/// It applies the `WindowCommands` and keeps the `ScreenDimensions` and `Monitors` resources up
+/// to date. When the screen dimensions change, it sends a `ScreenDimensionsChanged` event. The `WindowBundle` adds it as a thread local system, so the commands are applied on
+/// the thread owning the events loop.
 #[derive(Debug)]
-pub struct WindowSystem;
+pub struct WindowSystem {
+    fullscreen: Fullscreen,
+    decorations: bool,
+    /// Size and position of the window before it entered fullscreen.
+    windowed: Option<(Option<LogicalSize>, Option<LogicalPosition>)>,
+    /// Icon shown as soon as it is loaded.
+    pending_icon: Option<WindowIconHandle>,
+    /// Dimensions as of the last `ScreenDimensionsChanged` event.
+    last_dimensions: ScreenDimensions,
+}
 
 impl WindowSystem {
     /// Builds and spawns a new `Window`, using the provided `DisplayConfig` and `EventsLoop` as
@@ -28,12 +52,44 @@ impl WindowSystem {
 
     /// Builds and spawns a new `Window`, using the provided `DisplayConfig` and `EventsLoop` as
     /// sources. Returns a new `WindowSystem`
-    pub fn from_config(world: &mut World, events_loop: &EventsLoop, config: DisplayConfig) -> Self {
+    ///
+    /// The icon of the config is loaded through the asset system if the `Loader` resource exists.
+    pub fn from_config(
+        world: &mut World,
+        events_loop: &EventsLoop,
+        mut config: DisplayConfig,
+    ) -> Self {
+        let mut pending_icon = None;
+        if config.loaded_icon.is_none() && world.has_value::<Loader>() {
+            if let Some(icon) = config.icon.take() {
+                world
+                    .entry::<AssetStorage<WindowIcon>>()
+                    .or_insert_with(AssetStorage::default);
+                let loader = world.fetch::<Loader>();
+                pending_icon = Some(loader.load(
+                    icon.to_string_lossy(),
+                    IconFormat,
+                    (),
+                    &world.fetch::<AssetStorage<WindowIcon>>(),
+                ));
+            }
+        }
+
+        let fullscreen = config.fullscreen.clone();
+        let decorations = config.decorations;
         let window = config
             .into_window_builder(events_loop)
             .build(events_loop)
             .unwrap();
-        Self::new(world, window)
+        if let Fullscreen::Borderless(selection) = &fullscreen {
+            move_to_monitor(&window, &selection.monitor_id(&window));
+        }
+        Self {
+            fullscreen,
+            decorations,
+            pending_icon,
+            ..Self::new(world, window)
+        }
     }
 
     /// Create a new `WindowSystem` wrapping the provided `Window`
@@ -44,9 +100,80 @@ impl WindowSystem {
             .expect("Window closed during initialization!")
             .to_physical(hidpi)
             .into();
-        world.insert(ScreenDimensions::new(width, height, hidpi));
+        let dimensions = ScreenDimensions::new(width, height, hidpi);
+        world.insert(dimensions.clone());
+        world.insert(Monitors::from_access(&window));
         world.insert(window);
-        Self
+        Self {
+            fullscreen: Fullscreen::Windowed,
+            decorations: true,
+            windowed: None,
+            pending_icon: None,
+            last_dimensions: dimensions,
+        }
+    }
+
+    fn apply(&mut self, window: &Window, command: WindowCommand) {
+        match command {
+            WindowCommand::SetFullscreen(fullscreen) => self.set_fullscreen(window, fullscreen),
+            WindowCommand::SetTitle(title) => window.set_title(&title),
+            WindowCommand::SetIcon(icon) => self.pending_icon = Some(icon),
+            WindowCommand::SetCursorIcon(cursor) => window.set_cursor(cursor),
+            WindowCommand::SetCustomCursor(..) => {
+                warn!("Custom cursors are not supported on this platform, ignoring the cursor");
+            }
+        }
+    }
+
+    fn show_loaded_icon(&mut self, window: &Window, icons: &AssetStorage<WindowIcon>) {
+        let loaded = self
+            .pending_icon
+            .as_ref()
+            .and_then(|handle| icons.get(handle))
+            .map(|icon| icon.0.clone());
+        if let Some(icon) = loaded {
+            window.set_window_icon(Some(icon));
+            self.pending_icon = None;
+        }
+    }
+
+    fn set_fullscreen(&mut self, window: &Window, fullscreen: Fullscreen) {
+        if fullscreen == self.fullscreen {
+            return;
+        }
+        if self.fullscreen == Fullscreen::Windowed {
+            self.windowed = Some((window.get_inner_size(), window.get_position()));
+        }
+
+        match &fullscreen {
+            Fullscreen::Windowed => {
+                window.set_fullscreen(None);
+                window.set_decorations(self.decorations);
+                if let Some((size, position)) = self.windowed.take() {
+                    if let Some(size) = size {
+                        window.set_inner_size(size);
+                    }
+                    if let Some(position) = position {
+                        window.set_position(position);
+                    }
+                }
+            }
+            Fullscreen::Borderless(selection) => {
+                let monitor = selection.monitor_id(window);
+                window.set_fullscreen(None);
+                window.set_decorations(false);
+                move_to_monitor(window, &monitor);
+                window.set_inner_size(
+                    monitor
+                        .get_dimensions()
+                        .to_logical(monitor.get_hidpi_factor()),
+                );
+            }
+            Fullscreen::Exclusive(..) => {
+                window.set_fullscreen(fullscreen.exclusive_monitor(window));
+            }
+        }
+        self.fullscreen = fullscreen;
     }
 
     fn manage_dimensions(&mut self, mut screen_dimensions: &mut ScreenDimensions, window: &Window) {
@@ -78,16 +205,53 @@ impl WindowSystem {
 }
 
 impl<'a> System<'a> for WindowSystem {
-    type SystemData = (WriteExpect<'a, ScreenDimensions>, ReadExpect<'a, Window>);
+    type SystemData = (
+        WriteExpect<'a, ScreenDimensions>,
+        ReadExpect<'a, Window>,
+        Write<'a, WindowCommands>,
+        WriteExpect<'a, Monitors>,
+        Read<'a, AssetStorage<WindowIcon>>,
+        Write<'a, EventChannel<ScreenDimensionsChanged>>,
+    );
 
-    fn run(&mut self, (mut screen_dimensions, window): Self::SystemData) {
+    fn run(
+        &mut self,
+        (mut screen_dimensions, window, mut commands, mut monitors, icons, mut resizes): Self::SystemData,
+    ) {
         #[cfg(feature = "profiler")]
         profile_scope!("window_system");
 
+        let mut fullscreen_changed = false;
+        for command in commands.drain() {
+            if let WindowCommand::SetFullscreen(_) = command {
+                fullscreen_changed = true;
+            }
+            self.apply(&window, command);
+        }
+        if fullscreen_changed {
+            *monitors = Monitors::from_access(&*window);
+        }
+        self.show_loaded_icon(&window, &icons);
+
         self.manage_dimensions(&mut screen_dimensions, &window);
+        if screen_dimensions.differs_from(&self.last_dimensions) {
+            let old = std::mem::replace(&mut self.last_dimensions, screen_dimensions.clone());
+            resizes.single_write(ScreenDimensionsChanged {
+                old,
+                new: screen_dimensions.clone(),
+            });
+        }
     }
 }
 
+fn move_to_monitor(window: &Window, monitor: &MonitorId) {
+    window.set_position(
+        monitor
+            .get_position()
+            .to_logical(monitor.get_hidpi_factor()),
+    );
+}
+
 /// System that polls the window events and pushes them to appropriate event channels.
 ///
 /// This system must be active for any `GameState` to receive
@@ -22,7 +22,7 @@ use amethyst::{
     },
     ui::{RenderUi, UiBundle, UiCreator, UiFinder, UiText},
     utils::{
-        auto_fov::{AutoFov, AutoFovSystem},
+        auto_fov::{AutoFov, AutoFovSystemDesc},
         tag::{Tag, TagFinder},
     },
     window::ScreenDimensions,
@@ -49,7 +49,7 @@ fn main() -> Result<(), Error> {
         )
         // This makes the system adjust the camera right after it has been loaded (in the same
         // frame), preventing any flickering
-        .with(AutoFovSystem::new(), "auto_fov", &["prefab"])
+        .with_system_desc(AutoFovSystemDesc::default(), "auto_fov", &["prefab"])
         .with(ShowFovSystem::new(), "show_fov", &["auto_fov"])
         .with_bundle(TransformBundle::new())?
         .with_bundle(InputBundle::<StringBindings>::new())?
@@ -0,0 +1,56 @@
+//! Loads data on a background thread, then creates entities from it through the
+//! `CallbackQueue`, which runs the callback on the main thread.
+
+use std::{thread, time::Duration};
+
+use amethyst::{
+    core::Named,
+    ecs::{Join, ReadStorage},
+    prelude::*,
+};
+
+/// Inserted by the callback once the entities have been created.
+struct Spawned;
+
+struct Example;
+
+impl EmptyState for Example {
+    fn on_start(&mut self, data: StateData<'_, ()>) {
+        let sender = data.world.read_resource::<CallbackQueue>().send_handle();
+        thread::spawn(move || {
+            // Stands in for reading a large file or waiting for a server.
+            thread::sleep(Duration::from_millis(500));
+            let names = vec!["Amethyst", "Ruby", "Sapphire"];
+
+            sender
+                .send(Box::new(move |world: &mut World| {
+                    for name in names {
+                        world.create_entity().named(name).build();
+                    }
+                    world.insert(Spawned);
+                }))
+                .expect("The application has stopped");
+        });
+    }
+
+    fn update(&mut self, data: StateData<'_, ()>) -> EmptyTrans {
+        if !data.world.has_value::<Spawned>() {
+            return Trans::None;
+        }
+        data.world.exec(|names: ReadStorage<'_, Named>| {
+            for named in names.join() {
+                println!("Created {}", named.name);
+            }
+        });
+        Trans::Quit
+    }
+}
+
+fn main() -> amethyst::Result<()> {
+    amethyst::start_logger(Default::default());
+    let assets_dir = "./";
+    let mut game = Application::new(assets_dir, Example, ())?;
+    game.run();
+
+    Ok(())
+}
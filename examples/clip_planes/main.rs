@@ -0,0 +1,127 @@
+//! Displays a sphere cut in two by a clip plane, each half drawn by a different pass.
+
+use amethyst::{
+    assets::{PrefabLoader, PrefabLoaderSystemDesc, RonFormat},
+    core::{
+        math::{Point3, Vector3},
+        transform::TransformBundle,
+        Time,
+    },
+    ecs::{prelude::WorldExt, DispatcherBuilder, World},
+    error::Error,
+    input::{is_close_requested, is_key_down},
+    prelude::*,
+    renderer::{
+        bundle::{RenderOrder, RenderPlan, RenderPlugin, Target},
+        pass::DrawFlatDesc,
+        plugins::{RenderShaded3D, RenderToWindow},
+        rendy::{
+            graph::render::RenderGroupDesc,
+            mesh::{Normal, Position, TexCoord},
+        },
+        resources::{ClipMode, ClipPlanes},
+        types::{Backend, DefaultBackend},
+        Factory, RenderingBundle,
+    },
+    utils::{application_root_dir, scene::BasicScenePrefab},
+    winit::VirtualKeyCode,
+};
+
+type MyPrefabData = BasicScenePrefab<(Vec<Position>, Vec<Normal>, Vec<TexCoord>)>;
+
+/// Distance from the center of the sphere the clip plane sweeps to on each side.
+const SWEEP: f32 = 1.2;
+
+struct Example;
+
+impl SimpleState for Example {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let handle = data.world.exec(|loader: PrefabLoader<'_, MyPrefabData>| {
+            loader.load("prefab/sphere.ron", RonFormat, ())
+        });
+        data.world.create_entity().with(handle).build();
+        data.world.insert(ClipPlanes::default());
+    }
+
+    fn handle_event(
+        &mut self,
+        _: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
+                return Trans::Quit;
+            }
+        }
+        Trans::None
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        let time = data.world.read_resource::<Time>().absolute_time_seconds() as f32;
+        // A tilted plane sweeping through the sphere, with its positive side towards +x.
+        let point = Point3::new(time.sin() * SWEEP, 0.0, 0.0);
+        let plane = ClipPlanes::plane(&point, &Vector3::new(1.0, 0.5, 0.0));
+        data.world
+            .write_resource::<ClipPlanes>()
+            .set_plane(0, Some(plane));
+        Trans::None
+    }
+}
+
+/// Draws the part of the scene `RenderShaded3D` clips away with flat shading.
+#[derive(Default, Debug)]
+struct RenderFlatOutside;
+
+impl<B: Backend> RenderPlugin<B> for RenderFlatOutside {
+    fn on_build<'a, 'b>(
+        &mut self,
+        _world: &mut World,
+        _builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        plan.extend_target(Target::Main, |ctx| {
+            ctx.add(
+                RenderOrder::Opaque,
+                DrawFlatDesc::new()
+                    .with_clip_planes(ClipMode::Outside)
+                    .builder(),
+            )?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+fn main() -> amethyst::Result<()> {
+    amethyst::start_logger(Default::default());
+
+    let app_root = application_root_dir()?;
+
+    let display_config_path = app_root.join("examples/clip_planes/config/display.ron");
+    let assets_dir = app_root.join("examples/assets/");
+
+    let game_data = GameDataBuilder::default()
+        .with_system_desc(PrefabLoaderSystemDesc::<MyPrefabData>::default(), "", &[])
+        .with_bundle(TransformBundle::new())?
+        .with_bundle(
+            RenderingBundle::<DefaultBackend>::new()
+                .with_plugin(
+                    RenderToWindow::from_config_path(display_config_path)?
+                        .with_clear([0.34, 0.36, 0.52, 1.0]),
+                )
+                // Adds the visibility sorting both passes draw from.
+                .with_plugin(RenderShaded3D::default().with_clip_planes(ClipMode::Inside))
+                .with_plugin(RenderFlatOutside),
+        )?;
+    let mut game = Application::new(assets_dir, Example, game_data)?;
+    game.run();
+    Ok(())
+}
@@ -0,0 +1,140 @@
+//! Loads the glTF files dropped on the window into the scene.
+
+use amethyst::{
+    assets::{AssetStorage, Directory, Loader},
+    core::{Transform, TransformBundle},
+    ecs::{World, WorldExt},
+    input::{
+        is_close_requested, is_key_down, InputBundle, InputEvent, StringBindings, VirtualKeyCode,
+    },
+    prelude::*,
+    renderer::{
+        camera::{Camera, Projection},
+        light::{Light, PointLight},
+        palette::Srgb,
+        plugins::{RenderPbr3D, RenderSkybox, RenderToWindow},
+        types::DefaultBackend,
+        RenderingBundle,
+    },
+    utils::application_root_dir,
+    Error,
+};
+use amethyst_gltf::{GltfSceneAsset, GltfSceneFormat, GltfSceneLoaderSystemDesc};
+use std::path::Path;
+
+struct DropExample;
+
+impl SimpleState for DropExample {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        initialise_camera(data.world);
+        initialise_light(data.world);
+        println!("Drop .gltf or .glb files on the window to load them.");
+    }
+
+    fn handle_event(
+        &mut self,
+        data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        match event {
+            StateEvent::Window(event) => {
+                if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
+                    return Trans::Quit;
+                }
+            }
+            StateEvent::Input(InputEvent::FileHovered(path)) => {
+                println!("Drop to load {}", path.display());
+            }
+            StateEvent::Input(InputEvent::FileHoverCancelled) => {
+                println!("Nothing dropped");
+            }
+            StateEvent::Input(InputEvent::FileDropped(path)) => load_scene(data.world, &path),
+            _ => {}
+        }
+        Trans::None
+    }
+}
+
+/// Loads a dropped glTF file, through a source for its directory so its buffers and images are
+/// found next to it.
+fn load_scene(world: &mut World, path: &Path) {
+    let is_gltf = path
+        .extension()
+        .map_or(false, |extension| extension == "gltf" || extension == "glb");
+    let (directory, file) = match (path.parent(), path.file_name()) {
+        (Some(directory), Some(file)) if is_gltf => (directory, file),
+        _ => {
+            println!("Ignoring {}, it is not a glTF file", path.display());
+            return;
+        }
+    };
+
+    let source = directory.to_string_lossy().into_owned();
+    world
+        .write_resource::<Loader>()
+        .add_source(source.clone(), Directory::new(directory));
+
+    let scene = world.read_resource::<Loader>().load_from(
+        file.to_string_lossy(),
+        GltfSceneFormat::default(),
+        &source,
+        (),
+        &world.read_resource::<AssetStorage<GltfSceneAsset>>(),
+    );
+    world.create_entity().with(scene).build();
+    println!("Loading {}", path.display());
+}
+
+fn initialise_camera(world: &mut World) {
+    let mut transform = Transform::default();
+    transform.set_translation_xyz(0.0, 1.0, 8.0);
+
+    world
+        .create_entity()
+        .with(Camera::from(Projection::perspective(
+            1.33,
+            std::f32::consts::FRAC_PI_3,
+            0.1,
+            1000.0,
+        )))
+        .with(transform)
+        .build();
+}
+
+fn initialise_light(world: &mut World) {
+    let light: Light = PointLight {
+        intensity: 100.0,
+        radius: 1.0,
+        color: Srgb::new(1.0, 1.0, 1.0),
+        ..Default::default()
+    }
+    .into();
+
+    let mut transform = Transform::default();
+    transform.set_translation_xyz(5.0, 10.0, 10.0);
+
+    world.create_entity().with(light).with(transform).build();
+}
+
+fn main() -> Result<(), Error> {
+    amethyst::start_logger(Default::default());
+
+    let app_root = application_root_dir()?;
+    let display_config_path = app_root.join("examples/drop_files/config/display.ron");
+    let assets_dir = app_root.join("examples/assets/");
+
+    let game_data = GameDataBuilder::default()
+        .with_system_desc(GltfSceneLoaderSystemDesc::default(), "gltf_loader", &[])
+        .with_bundle(InputBundle::<StringBindings>::new())?
+        .with_bundle(TransformBundle::new().with_dep(&["gltf_loader"]))?
+        .with_bundle(
+            RenderingBundle::<DefaultBackend>::new()
+                .with_plugin(RenderToWindow::from_config_path(display_config_path)?)
+                .with_plugin(RenderPbr3D::default())
+                .with_plugin(RenderSkybox::default()),
+        )?;
+
+    let mut game = Application::build(assets_dir, DropExample)?.build(game_data)?;
+    game.run();
+    Ok(())
+}
@@ -2,8 +2,8 @@
 
 use amethyst::{
     animation::{
-        get_animation_set, AnimationBundle, AnimationCommand, AnimationControlSet, AnimationSet,
-        EndControl, VertexSkinningBundle,
+        get_animation_set, AnimationBundle, AnimationCommand, AnimationControlSet,
+        AnimationSampling, AnimationSet, EndControl, VertexSkinningBundle,
     },
     assets::{
         AssetPrefab, Completion, Handle, Prefab, PrefabData, PrefabLoader, PrefabLoaderSystemDesc,
@@ -18,13 +18,14 @@ use amethyst::{
     renderer::{
         camera::CameraPrefab,
         light::LightPrefab,
+        morph::MorphWeights,
         plugins::{RenderPbr3D, RenderSkybox, RenderToWindow},
         types::DefaultBackend,
         RenderingBundle,
     },
     utils::{
         application_root_dir,
-        auto_fov::{AutoFov, AutoFovSystem},
+        auto_fov::{AutoFov, AutoFovSystemDesc},
         tag::{Tag, TagFinder},
     },
     Error,
@@ -88,12 +89,24 @@ impl SimpleState for Example {
             if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
                 Trans::Quit
             } else if is_key_down(&event, VirtualKeyCode::Space) {
-                toggle_or_cycle_animation(
+                let mut scene = world.write_resource::<Scene>();
+                let index = scene.animation_index;
+                // morph target weight animations are played alongside the transform animations
+                let count = toggle_animation::<Transform>(
                     self.entity,
-                    &mut world.write_resource(),
+                    index,
                     &world.read_storage(),
                     &mut world.write_storage(),
-                );
+                )
+                .max(toggle_animation::<MorphWeights>(
+                    self.entity,
+                    index,
+                    &world.read_storage(),
+                    &mut world.write_storage(),
+                ));
+                if count > 0 {
+                    scene.animation_index = (index + 1) % count;
+                }
                 Trans::None
             } else {
                 Trans::None
@@ -144,33 +157,35 @@ impl SimpleState for Example {
     }
 }
 
-fn toggle_or_cycle_animation(
+/// Toggles the animation with the given index, returning the number of animations of the entity
+fn toggle_animation<T>(
     entity: Option<Entity>,
-    scene: &mut Scene,
-    sets: &ReadStorage<'_, AnimationSet<usize, Transform>>,
-    controls: &mut WriteStorage<'_, AnimationControlSet<usize, Transform>>,
-) {
+    index: usize,
+    sets: &ReadStorage<'_, AnimationSet<usize, T>>,
+    controls: &mut WriteStorage<'_, AnimationControlSet<usize, T>>,
+) -> usize
+where
+    T: AnimationSampling,
+{
     if let Some((entity, Some(animations))) = entity.map(|entity| (entity, sets.get(entity))) {
-        if animations.animations.len() > scene.animation_index {
-            let animation = animations.animations.get(&scene.animation_index).unwrap();
-            let set = get_animation_set::<usize, Transform>(controls, entity).unwrap();
-            if set.has_animation(scene.animation_index) {
-                set.toggle(scene.animation_index);
+        if let Some(animation) = animations.animations.get(&index) {
+            let set = get_animation_set::<usize, T>(controls, entity).unwrap();
+            if set.has_animation(index) {
+                set.toggle(index);
             } else {
-                println!("Running animation {}", scene.animation_index);
+                println!("Running animation {}", index);
                 set.add_animation(
-                    scene.animation_index,
+                    index,
                     animation,
                     EndControl::Normal,
                     1.0,
                     AnimationCommand::Start,
                 );
             }
-            scene.animation_index += 1;
-            if scene.animation_index >= animations.animations.len() {
-                scene.animation_index = 0;
-            }
         }
+        animations.animations.len()
+    } else {
+        0
     }
 }
 
@@ -183,7 +198,7 @@ fn main() -> Result<(), amethyst::Error> {
     let assets_dir = app_root.join("examples/assets/");
 
     let game_data = GameDataBuilder::default()
-        .with(AutoFovSystem::default(), "auto_fov", &[])
+        .with_system_desc(AutoFovSystemDesc::default(), "auto_fov", &[])
         .with_system_desc(
             PrefabLoaderSystemDesc::<ScenePrefabData>::default(),
             "scene_loader",
@@ -198,6 +213,13 @@ fn main() -> Result<(), amethyst::Error> {
             AnimationBundle::<usize, Transform>::new("animation_control", "sampler_interpolation")
                 .with_dep(&["gltf_loader"]),
         )?
+        .with_bundle(
+            AnimationBundle::<usize, MorphWeights>::new(
+                "morph_animation_control",
+                "morph_sampler_interpolation",
+            )
+            .with_dep(&["gltf_loader"]),
+        )?
         .with_bundle(
             FlyControlBundle::<StringBindings>::new(None, None, None)
                 .with_sensitivity(0.1, 0.1)
@@ -15,6 +15,7 @@ use amethyst::{
             mesh::{Normal, Position, Tangent, TexCoord},
             texture::palette::load_from_linear_rgba,
         },
+        resources::EnvironmentMap,
         shape::Shape,
         types::DefaultBackend,
         Mesh, RenderingBundle, Texture,
@@ -124,6 +125,12 @@ impl SimpleState for Example {
             .with(light2_transform)
             .build();
 
+        // Ambient lighting reflected by the metallic spheres.
+        world.insert(
+            EnvironmentMap::gradient(Srgb::new(0.1, 0.1, 0.12), Srgb::new(0.34, 0.36, 0.52))
+                .with_intensity(0.8),
+        );
+
         println!("Put camera");
 
         let mut transform = Transform::default();
@@ -158,7 +165,7 @@ fn main() -> amethyst::Result<()> {
                     RenderToWindow::from_config_path(display_config_path)?
                         .with_clear([0.34, 0.36, 0.52, 1.0]),
                 )
-                .with_plugin(RenderPbr3D::default()),
+                .with_plugin(RenderPbr3D::default().with_environment_map()),
         )?;
 
     let mut game = Application::new(assets_dir, Example, game_data)?;
@@ -113,7 +113,9 @@ impl<'a> System<'a> for SpamSystem {
             match event {
                 NetworkSimulationEvent::Message(_addr, payload) => info!("Payload: {:?}", payload),
                 NetworkSimulationEvent::Connect(addr) => info!("New client connection: {}", addr),
-                NetworkSimulationEvent::Disconnect(addr) => info!("Server Disconnected: {}", addr),
+                NetworkSimulationEvent::Disconnect(addr, reason) => {
+                    info!("Server Disconnected: {} ({:?})", addr, reason)
+                }
                 NetworkSimulationEvent::RecvError(e) => {
                     error!("Recv Error: {:?}", e);
                 }
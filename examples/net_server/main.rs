@@ -113,8 +113,8 @@ impl<'a> System<'a> for SpamReceiveSystem {
                     net.send(*addr, b"ok");
                 }
                 NetworkSimulationEvent::Connect(addr) => info!("New client connection: {}", addr),
-                NetworkSimulationEvent::Disconnect(addr) => {
-                    info!("Client Disconnected: {}", addr);
+                NetworkSimulationEvent::Disconnect(addr, reason) => {
+                    info!("Client Disconnected: {} ({:?})", addr, reason);
                 }
                 NetworkSimulationEvent::RecvError(e) => {
                     error!("Recv Error: {:?}", e);
@@ -0,0 +1,118 @@
+// CLIENT PRINTING NETWORK STATISTICS
+use std::{net::UdpSocket, time::Duration};
+
+use amethyst::{
+    core::{bundle::SystemBundle, frame_limiter::FrameRateLimitStrategy, Time},
+    ecs::{DispatcherBuilder, Read, System, World, Write},
+    network::simulation::{
+        udp::UdpNetworkBundle, DeliveryRequirement, NetworkSimulationTime, NetworkStats,
+        TransportResource, UrgencyRequirement,
+    },
+    prelude::*,
+    utils::application_root_dir,
+    Result,
+};
+use log::info;
+
+/// Time between two printed tables.
+const PRINT_INTERVAL: f64 = 3.;
+
+fn main() -> Result<()> {
+    amethyst::start_logger(Default::default());
+
+    let assets_dir = application_root_dir()?.join("./");
+
+    let socket = UdpSocket::bind("0.0.0.0:3455")?;
+    socket.set_nonblocking(true)?;
+
+    let game_data = GameDataBuilder::default()
+        .with_bundle(UdpNetworkBundle::new(Some(socket), 2048))?
+        .with_bundle(StatsBundle)?;
+
+    let mut game = Application::build(assets_dir, GameState)?
+        .with_frame_limit(
+            FrameRateLimitStrategy::SleepAndYield(Duration::from_millis(2)),
+            60,
+        )
+        .build(game_data)?;
+    game.run();
+    Ok(())
+}
+
+/// Default empty state
+pub struct GameState;
+impl SimpleState for GameState {}
+
+#[derive(Debug)]
+struct StatsBundle;
+
+impl<'a, 'b> SystemBundle<'a, 'b> for StatsBundle {
+    fn build(self, _world: &mut World, builder: &mut DispatcherBuilder<'a, 'b>) -> Result<()> {
+        builder.add(SendSystem, "send_system", &[]);
+        builder.add(PrintStatsSystem::default(), "print_stats_system", &[]);
+        Ok(())
+    }
+}
+
+/// Sends a reliable message every simulation frame, roughly the size of a game state update.
+struct SendSystem;
+
+impl<'a> System<'a> for SendSystem {
+    type SystemData = (
+        Read<'a, NetworkSimulationTime>,
+        Write<'a, TransportResource>,
+    );
+
+    fn run(&mut self, (sim_time, mut net): Self::SystemData) {
+        let server_addr = "127.0.0.1:3457".parse().unwrap();
+        for frame in sim_time.sim_frames_to_run() {
+            let payload = format!("CL: sim_frame:{}", frame).repeat(20);
+            net.send_with_requirements(
+                server_addr,
+                payload.as_bytes(),
+                DeliveryRequirement::ReliableOrdered(None),
+                UrgencyRequirement::OnTick,
+            );
+        }
+    }
+}
+
+/// Prints the traffic of every connection every `PRINT_INTERVAL` seconds.
+#[derive(Default)]
+struct PrintStatsSystem {
+    last_print: f64,
+}
+
+impl<'a> System<'a> for PrintStatsSystem {
+    type SystemData = (Read<'a, Time>, Read<'a, NetworkStats>);
+
+    fn run(&mut self, (time, stats): Self::SystemData) {
+        let now = time.absolute_real_time_seconds();
+        if now - self.last_print < PRINT_INTERVAL {
+            return;
+        }
+        self.last_print = now;
+
+        info!(
+            "{:<22} {:>10} {:>10} {:>8} {:>8} {:>7} {:>6} {:>6}",
+            "address", "kbps out", "kbps in", "pkt out", "pkt in", "resent", "loss", "queue"
+        );
+        let rows = stats
+            .connections()
+            .map(|(address, traffic)| (address.to_string(), traffic))
+            .chain(std::iter::once(("total".to_string(), stats.total())));
+        for (name, traffic) in rows {
+            info!(
+                "{:<22} {:>10.1} {:>10.1} {:>8.1} {:>8.1} {:>7.1} {:>5.1}% {:>6}",
+                name,
+                traffic.bytes_sent * 8. / 1000.,
+                traffic.bytes_received * 8. / 1000.,
+                traffic.packets_sent,
+                traffic.packets_received,
+                traffic.packets_resent,
+                traffic.packet_loss() * 100.,
+                traffic.send_queue,
+            );
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! Pauses the game time while the real time keeps running.
+
+use amethyst::{
+    assets::{AssetStorage, Loader},
+    core::{transform::TransformBundle, Hidden, Time},
+    ecs::prelude::{Entity, WorldExt},
+    input::{is_close_requested, is_key_down, InputBundle, StringBindings},
+    prelude::*,
+    renderer::{plugins::RenderToWindow, types::DefaultBackend, RenderingBundle},
+    ui::{get_default_font, Anchor, FontAsset, RenderUi, UiBundle, UiText, UiTransform},
+    utils::application_root_dir,
+    winit::VirtualKeyCode,
+};
+
+const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+#[derive(Default)]
+struct Example {
+    game_time: Option<Entity>,
+    real_time: Option<Entity>,
+    bouncing: Option<Entity>,
+    paused: Option<Entity>,
+}
+
+fn create_label(
+    world: &mut World,
+    id: &str,
+    text: &str,
+    anchor: Anchor,
+    y: f32,
+    font_size: f32,
+) -> Entity {
+    let font = {
+        let loader = world.read_resource::<Loader>();
+        let storage = world.read_resource::<AssetStorage<FontAsset>>();
+        get_default_font(&loader, &storage)
+    };
+    world
+        .create_entity()
+        .with(UiTransform::new(
+            id.to_string(),
+            anchor,
+            anchor,
+            0.0,
+            y,
+            1.0,
+            400.0,
+            40.0,
+        ))
+        .with(UiText::new(font, text.to_string(), WHITE, font_size))
+        .build()
+}
+
+impl SimpleState for Example {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+
+        self.game_time = Some(create_label(
+            world,
+            "game_time",
+            "",
+            Anchor::TopMiddle,
+            -30.0,
+            25.0,
+        ));
+        self.real_time = Some(create_label(
+            world,
+            "real_time",
+            "",
+            Anchor::TopMiddle,
+            -70.0,
+            25.0,
+        ));
+        self.bouncing = Some(create_label(
+            world,
+            "bouncing",
+            "Bouncing",
+            Anchor::Middle,
+            0.0,
+            30.0,
+        ));
+        let paused = create_label(world, "paused", "PAUSED", Anchor::BottomMiddle, 40.0, 40.0);
+        world
+            .write_storage::<Hidden>()
+            .insert(paused, Hidden)
+            .expect("Failed to hide the pause label");
+        self.paused = Some(paused);
+    }
+
+    fn handle_event(
+        &mut self,
+        data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
+                return Trans::Quit;
+            }
+            if is_key_down(&event, VirtualKeyCode::P) {
+                let mut time = data.world.write_resource::<Time>();
+                let paused = !time.is_paused();
+                time.set_paused(paused);
+            }
+        }
+        Trans::None
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        let world = &mut data.world;
+        let (game_time, real_time, paused) = {
+            let time = world.read_resource::<Time>();
+            (
+                time.absolute_time_seconds(),
+                time.absolute_real_time_seconds(),
+                time.is_paused(),
+            )
+        };
+
+        {
+            let mut texts = world.write_storage::<UiText>();
+            if let Some(text) = self.game_time.and_then(|e| texts.get_mut(e)) {
+                text.text = format!("Game time: {:.1}", game_time);
+            }
+            if let Some(text) = self.real_time.and_then(|e| texts.get_mut(e)) {
+                text.text = format!("Real time: {:.1}", real_time);
+            }
+        }
+
+        // The label moves with the scaled time, so it stops while paused.
+        {
+            let mut transforms = world.write_storage::<UiTransform>();
+            if let Some(transform) = self.bouncing.and_then(|e| transforms.get_mut(e)) {
+                transform.local_y = (game_time * 3.0).sin() as f32 * 150.0;
+            }
+        }
+
+        // The pause label blinks with the real time, which keeps running while paused.
+        if let Some(label) = self.paused {
+            let mut hidden = world.write_storage::<Hidden>();
+            let visible = paused && real_time % 1.0 < 0.5;
+            if visible {
+                hidden.remove(label);
+            } else if !hidden.contains(label) {
+                hidden
+                    .insert(label, Hidden)
+                    .expect("Failed to hide the pause label");
+            }
+        }
+
+        Trans::None
+    }
+}
+
+fn main() -> amethyst::Result<()> {
+    amethyst::start_logger(Default::default());
+
+    let app_root = application_root_dir()?;
+
+    let display_config_path = app_root.join("examples/pause/config/display.ron");
+    let assets_dir = app_root.join("examples/assets");
+
+    let game_data = GameDataBuilder::default()
+        .with_bundle(TransformBundle::new())?
+        .with_bundle(InputBundle::<StringBindings>::new())?
+        .with_bundle(UiBundle::<StringBindings>::new())?
+        .with_bundle(
+            RenderingBundle::<DefaultBackend>::new()
+                .with_plugin(
+                    RenderToWindow::from_config_path(display_config_path)?
+                        .with_clear([0.34, 0.36, 0.52, 1.0]),
+                )
+                .with_plugin(RenderUi::default()),
+        )?;
+
+    let mut game = Application::new(assets_dir, Example::default(), game_data)?;
+    game.run();
+    Ok(())
+}
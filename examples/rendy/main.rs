@@ -44,7 +44,7 @@ use amethyst::{
     },
     utils::{
         application_root_dir,
-        auto_fov::{AutoFov, AutoFovSystem},
+        auto_fov::{AutoFov, AutoFovSystemDesc},
         fps_counter::FpsCounterBundle,
         tag::TagFinder,
     },
@@ -609,7 +609,7 @@ fn main() -> amethyst::Result<()> {
 
     let game_data = GameDataBuilder::default()
         .with(OrbitSystem, "orbit", &[])
-        .with(AutoFovSystem::default(), "auto_fov", &[])
+        .with_system_desc(AutoFovSystemDesc::default(), "auto_fov", &[])
         .with_bundle(FpsCounterBundle::default())?
         .with_system_desc(
             PrefabLoaderSystemDesc::<ScenePrefabData>::default(),
@@ -0,0 +1,79 @@
+//! Saves a scene with a transform hierarchy and restores it.
+
+use amethyst::{
+    assets::{load_world, save_world, ProgressCounter, SaveBundle, SaveMarker},
+    core::{
+        math::Vector3,
+        transform::{Parent, Transform, TransformBundle},
+    },
+    ecs::saveload::MarkedBuilder,
+    prelude::*,
+    utils::application_root_dir,
+};
+
+struct Example;
+
+impl SimpleState for Example {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+
+        let sun = world
+            .create_entity()
+            .with(Transform::from(Vector3::new(0.0, 0.0, 0.0)))
+            .marked::<SaveMarker>()
+            .build();
+        let planet = world
+            .create_entity()
+            .with(Transform::from(Vector3::new(10.0, 0.0, 0.0)))
+            .with(Parent { entity: sun })
+            .marked::<SaveMarker>()
+            .build();
+        world
+            .create_entity()
+            .with(Transform::from(Vector3::new(2.0, 0.0, 0.0)))
+            .with(Parent { entity: planet })
+            .marked::<SaveMarker>()
+            .build();
+
+        let save = save_world(world, |_| true).expect("Failed to save the scene");
+        println!("Saved scene:\n{}", String::from_utf8_lossy(&save));
+
+        world.delete_all();
+        world.maintain();
+
+        let entities = load_world(world, &save, &mut ProgressCounter::new())
+            .expect("Failed to load the scene");
+        println!("Restored {} entities:", entities.len());
+        let transforms = world.read_storage::<Transform>();
+        let parents = world.read_storage::<Parent>();
+        for entity in entities {
+            println!(
+                "{:?} at {:?}, child of {:?}",
+                entity,
+                transforms.get(entity).map(|t| t.translation().x),
+                parents.get(entity).map(|p| p.entity),
+            );
+        }
+    }
+
+    fn update(&mut self, _: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        Trans::Quit
+    }
+}
+
+fn main() -> amethyst::Result<()> {
+    amethyst::start_logger(Default::default());
+
+    let app_root = application_root_dir()?;
+    let game_data = GameDataBuilder::default()
+        .with_bundle(TransformBundle::new())?
+        .with_bundle(
+            SaveBundle::new()
+                .with::<Transform>("transform")
+                .with::<Parent>("parent"),
+        )?;
+
+    let mut game = Application::new(app_root, Example, game_data)?;
+    game.run();
+    Ok(())
+}
@@ -86,6 +86,14 @@ impl SimpleState for Game {
                 log::info!("Input Event detected: {:?}.", input);
                 Trans::None
             }
+            StateEvent::ScreenDimensions(resize) => {
+                log::info!(
+                    "Screen resized to {}x{}.",
+                    resize.new.width(),
+                    resize.new.height()
+                );
+                Trans::None
+            }
         }
     }
 
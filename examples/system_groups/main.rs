@@ -0,0 +1,120 @@
+//! An example showing how to pause a group of systems while a state is paused.
+
+use amethyst::{
+    core::{SystemGroups, Time},
+    ecs::{Component, DenseVecStorage, Join, Read, ReadStorage, System, WriteStorage},
+    prelude::*,
+    utils::application_root_dir,
+    Error,
+};
+
+const PHYSICS: &str = "physics";
+
+struct Position(f32);
+
+impl Component for Position {
+    type Storage = DenseVecStorage<Self>;
+}
+
+struct Velocity(f32);
+
+impl Component for Velocity {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Moves the entities, frozen while the game is paused.
+struct MovementSystem;
+
+impl<'s> System<'s> for MovementSystem {
+    type SystemData = (
+        Read<'s, Time>,
+        ReadStorage<'s, Velocity>,
+        WriteStorage<'s, Position>,
+    );
+
+    fn run(&mut self, (time, velocities, mut positions): Self::SystemData) {
+        for (velocity, position) in (&velocities, &mut positions).join() {
+            position.0 += velocity.0 * time.delta_seconds();
+        }
+    }
+}
+
+/// Prints the positions, running every frame after the `MovementSystem`.
+struct ReportSystem;
+
+impl<'s> System<'s> for ReportSystem {
+    type SystemData = (Read<'s, SystemGroups>, ReadStorage<'s, Position>);
+
+    fn run(&mut self, (groups, positions): Self::SystemData) {
+        for position in positions.join() {
+            println!(
+                "position: {:.3} (physics enabled: {})",
+                position.0,
+                groups.is_enabled(PHYSICS)
+            );
+        }
+    }
+}
+
+#[derive(Default)]
+struct GameState {
+    frames: u32,
+}
+
+impl SimpleState for GameState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        data.world
+            .create_entity()
+            .with(Position(0.0))
+            .with(Velocity(1.0))
+            .build();
+    }
+
+    fn on_pause(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        data.world.write_resource::<SystemGroups>().disable(PHYSICS);
+    }
+
+    fn on_resume(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        data.world.write_resource::<SystemGroups>().enable(PHYSICS);
+    }
+
+    fn update(&mut self, _: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        self.frames += 1;
+        match self.frames {
+            5 => Trans::Push(Box::new(PauseState::default())),
+            10 => Trans::Quit,
+            _ => Trans::None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct PauseState {
+    frames: u32,
+}
+
+impl SimpleState for PauseState {
+    fn update(&mut self, _: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        self.frames += 1;
+        if self.frames == 3 {
+            Trans::Pop
+        } else {
+            Trans::None
+        }
+    }
+}
+
+fn main() -> Result<(), Error> {
+    amethyst::start_logger(Default::default());
+    let app_root = application_root_dir()?;
+    let assets_dir = app_root.join("examples/assets");
+
+    let game_data = GameDataBuilder::default()
+        .with(MovementSystem.in_group(PHYSICS), "movement", &[])
+        // Depending on a disabled system is fine, it is skipped but still dispatched.
+        .with(ReportSystem, "report", &["movement"]);
+
+    let mut game = Application::build(assets_dir, GameState::default())?.build(game_data)?;
+    game.run();
+    Ok(())
+}
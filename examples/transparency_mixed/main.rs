@@ -0,0 +1,181 @@
+//! Demonstrates transparent sprites and meshes sorted together by depth.
+
+use amethyst::{
+    assets::{AssetLoaderSystemData, AssetStorage, Handle, Loader},
+    core::{math::Vector3, Transform, TransformBundle},
+    ecs::{Entity, World, WorldExt},
+    input::{is_close_requested, is_key_down},
+    prelude::*,
+    renderer::{
+        mtl::{Material, MaterialDefaults},
+        palette::{LinSrgba, Srgba},
+        pass::FlatPassDef,
+        plugins::{RenderMixed, RenderToWindow},
+        rendy::{
+            mesh::{Normal, Position, Tangent, TexCoord},
+            texture::palette::load_from_linear_rgba,
+        },
+        resources::Tint,
+        shape::Shape,
+        types::DefaultBackend,
+        Camera, ImageFormat, Mesh, RenderingBundle, SpriteRender, SpriteSheet, SpriteSheetFormat,
+        Texture, Transparent,
+    },
+    utils::application_root_dir,
+    winit::VirtualKeyCode,
+};
+
+const LAYERS: usize = 6;
+const LAYER_OFFSET: f32 = 24.0;
+/// Sprites of the circle sprite sheet are 64 pixels wide, `Shape::Plane` is 2 units wide.
+const MESH_SCALE: f32 = 32.0;
+
+#[derive(Debug, Default)]
+struct Example {
+    layers: Vec<Entity>,
+    reverse: bool,
+}
+
+impl SimpleState for Example {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+
+        let mut camera_transform = Transform::default();
+        camera_transform.set_translation_z(300.0);
+        world
+            .create_entity()
+            .with(Camera::standard_3d(500.0, 500.0))
+            .with(camera_transform)
+            .build();
+
+        let sprite_sheet = load_sprite_sheet(world);
+        let (mesh, material) = load_mesh(world);
+
+        for i in 0..LAYERS {
+            let builder = world
+                .create_entity()
+                .with(Transform::default())
+                .with(Transparent);
+            let entity = if i % 2 == 0 {
+                builder
+                    .with(SpriteRender {
+                        sprite_sheet: sprite_sheet.clone(),
+                        sprite_number: 0,
+                    })
+                    .with(Tint(Srgba::new(1.0, 0.4, 0.4, 0.6)))
+                    .build()
+            } else {
+                builder.with(mesh.clone()).with(material.clone()).build()
+            };
+            self.layers.push(entity);
+        }
+        self.place_layers(world);
+    }
+
+    fn handle_event(
+        &mut self,
+        data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
+                return Trans::Quit;
+            }
+            if is_key_down(&event, VirtualKeyCode::R) {
+                self.reverse = !self.reverse;
+                self.place_layers(data.world);
+            }
+        }
+        Trans::None
+    }
+}
+
+impl Example {
+    /// Places the layers diagonally, each one in front of the previous unless reversed.
+    fn place_layers(&self, world: &mut World) {
+        let mut transforms = world.write_storage::<Transform>();
+        let center = (LAYERS - 1) as f32 / 2.0;
+        for (i, entity) in self.layers.iter().enumerate() {
+            let offset = i as f32 - center;
+            let z = if self.reverse { -offset } else { offset };
+            if let Some(transform) = transforms.get_mut(*entity) {
+                transform.set_translation_xyz(offset * LAYER_OFFSET, offset * LAYER_OFFSET, z);
+                if i % 2 == 1 {
+                    transform.set_scale(Vector3::from_element(MESH_SCALE));
+                }
+            }
+        }
+    }
+}
+
+fn load_sprite_sheet(world: &mut World) -> Handle<SpriteSheet> {
+    let texture_handle = {
+        let loader = world.read_resource::<Loader>();
+        let texture_storage = world.read_resource::<AssetStorage<Texture>>();
+        loader.load(
+            "texture/Circle_Spritesheet.png",
+            ImageFormat::default(),
+            (),
+            &texture_storage,
+        )
+    };
+    let loader = world.read_resource::<Loader>();
+    let sprite_sheet_store = world.read_resource::<AssetStorage<SpriteSheet>>();
+    loader.load(
+        "texture/Circle_Spritesheet.ron",
+        SpriteSheetFormat(texture_handle),
+        (),
+        &sprite_sheet_store,
+    )
+}
+
+fn load_mesh(world: &mut World) -> (Handle<Mesh>, Handle<Material>) {
+    let mat_defaults = world.read_resource::<MaterialDefaults>().0.clone();
+    let mesh = world.exec(|loader: AssetLoaderSystemData<'_, Mesh>| {
+        loader.load_from_data(
+            Shape::Plane(None)
+                .generate::<(Vec<Position>, Vec<Normal>, Vec<Tangent>, Vec<TexCoord>)>(None)
+                .into(),
+            (),
+        )
+    });
+    let albedo = world.exec(|loader: AssetLoaderSystemData<'_, Texture>| {
+        loader.load_from_data(
+            load_from_linear_rgba(LinSrgba::new(0.2, 0.4, 1.0, 0.6)).into(),
+            (),
+        )
+    });
+    let material = world.exec(|loader: AssetLoaderSystemData<'_, Material>| {
+        loader.load_from_data(
+            Material {
+                albedo,
+                ..mat_defaults
+            },
+            (),
+        )
+    });
+    (mesh, material)
+}
+
+fn main() -> amethyst::Result<()> {
+    amethyst::start_logger(Default::default());
+
+    let app_root = application_root_dir()?;
+    let display_config_path = app_root.join("examples/transparency_mixed/config/display.ron");
+    let assets_dir = app_root.join("examples/assets/");
+
+    let game_data = GameDataBuilder::default()
+        .with_bundle(TransformBundle::new())?
+        .with_bundle(
+            RenderingBundle::<DefaultBackend>::new()
+                .with_plugin(
+                    RenderToWindow::from_config_path(display_config_path)?
+                        .with_clear([0.34, 0.36, 0.52, 1.0]),
+                )
+                .with_plugin(RenderMixed::<FlatPassDef>::default()),
+        )?;
+
+    let mut game = Application::new(assets_dir, Example::default(), game_data)?;
+    game.run();
+    Ok(())
+}
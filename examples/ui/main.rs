@@ -85,6 +85,14 @@ impl SimpleState for Example {
                 info!("Input Event detected: {:?}.", input);
                 Trans::None
             }
+            StateEvent::ScreenDimensions(resize) => {
+                info!(
+                    "Screen resized to {}x{}.",
+                    resize.new.width(),
+                    resize.new.height()
+                );
+                Trans::None
+            }
         }
     }
 
@@ -0,0 +1,118 @@
+//! Demonstrates a main menu fading in, driven entirely by the animations in its UI prefab.
+
+use amethyst::{
+    animation::{
+        get_animation_set, AnimatedUi, AnimationBundle, AnimationCommand, AnimationControlSet,
+        AnimationSampling, AnimationSet, EndControl,
+    },
+    core::transform::TransformBundle,
+    ecs::{
+        prelude::{Component, Entities, Join, ReadStorage, WriteStorage},
+        BitSet,
+    },
+    input::{InputBundle, StringBindings},
+    prelude::*,
+    renderer::{plugins::RenderToWindow, types::DefaultBackend, RenderingBundle},
+    ui::{RenderUi, UiBundle, UiCreator, UiImage, UiText, UiTransform},
+    utils::application_root_dir,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Eq, PartialOrd, PartialEq, Hash, Debug, Copy, Clone, Deserialize, Serialize)]
+enum MenuAnimation {
+    FadeIn,
+}
+
+type MenuUi = AnimatedUi<MenuAnimation>;
+
+#[derive(Default)]
+struct Menu {
+    // Entities the fade in was started on, per animated component
+    transforms: BitSet,
+    texts: BitSet,
+    images: BitSet,
+}
+
+impl SimpleState for Menu {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        data.world.exec(|mut creator: UiCreator<'_, MenuUi>| {
+            creator.create("ui/animated_menu.ron", ());
+        });
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        // The animation sets are added once the prefab is loaded
+        fade_in::<UiTransform>(data.world, &mut self.transforms);
+        fade_in::<UiText>(data.world, &mut self.texts);
+        fade_in::<UiImage>(data.world, &mut self.images);
+        Trans::None
+    }
+}
+
+fn fade_in<T>(world: &mut World, started: &mut BitSet)
+where
+    T: AnimationSampling + Component + Clone,
+{
+    world.exec(
+        |(entities, sets, mut controls): (
+            Entities<'_>,
+            ReadStorage<'_, AnimationSet<MenuAnimation, T>>,
+            WriteStorage<'_, AnimationControlSet<MenuAnimation, T>>,
+        )| {
+            for (entity, set) in (&entities, &sets).join() {
+                // `add` returns whether the animation was started already
+                if started.add(entity.id()) {
+                    continue;
+                }
+                if let Some(animation) = set.get(&MenuAnimation::FadeIn) {
+                    get_animation_set(&mut controls, entity)
+                        .expect("Unreachable: the entity is alive")
+                        .add_animation(
+                            MenuAnimation::FadeIn,
+                            animation,
+                            EndControl::Stay,
+                            1.0,
+                            AnimationCommand::Start,
+                        );
+                }
+            }
+        },
+    );
+}
+
+fn main() -> amethyst::Result<()> {
+    amethyst::start_logger(Default::default());
+
+    let app_root = application_root_dir()?;
+    let display_config_path = app_root.join("examples/ui_animation/config/display.ron");
+    let assets_dir = app_root.join("examples/assets");
+
+    let game_data = GameDataBuilder::default()
+        .with_bundle(AnimationBundle::<MenuAnimation, UiTransform>::new(
+            "ui_transform_animation_control",
+            "ui_transform_sampler_interpolation",
+        ))?
+        .with_bundle(AnimationBundle::<MenuAnimation, UiText>::new(
+            "ui_text_animation_control",
+            "ui_text_sampler_interpolation",
+        ))?
+        .with_bundle(AnimationBundle::<MenuAnimation, UiImage>::new(
+            "ui_image_animation_control",
+            "ui_image_sampler_interpolation",
+        ))?
+        .with_bundle(TransformBundle::new())?
+        .with_bundle(InputBundle::<StringBindings>::new())?
+        .with_bundle(UiBundle::<StringBindings, MenuUi>::new())?
+        .with_bundle(
+            RenderingBundle::<DefaultBackend>::new()
+                .with_plugin(
+                    RenderToWindow::from_config_path(display_config_path)?
+                        .with_clear([0., 0., 0., 1.0]),
+                )
+                .with_plugin(RenderUi::default()),
+        )?;
+
+    let mut game = Application::new(assets_dir, Menu::default(), game_data)?;
+    game.run();
+    Ok(())
+}
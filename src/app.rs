@@ -20,16 +20,32 @@ use crate::{
         frame_limiter::{FrameLimiter, FrameRateLimitConfig, FrameRateLimitStrategy},
         shrev::{EventChannel, ReaderId},
         timing::{Stopwatch, Time},
+        transform::InterpolatedTransform,
         ArcThreadPool, EventReader, Named,
     },
     ecs::prelude::{Component, Read, World, WorldExt, Write},
     error::Error,
     game_data::{DataDispose, DataInit},
-    state::{State, StateData, StateMachine, TransEvent},
+    lifecycle::{ApplicationLifecycle, ExitReason, ExitRequested, FrameEnd},
+    logger::LogLevels,
+    state::{State, StateData, StateMachine, Trans, TransEvent},
     state_event::{StateEvent, StateEventReader},
     ui::UiEvent,
+    window::ScreenDimensions,
 };
 
+/// How a headless application paces its frames.
+///
+/// In headless mode every frame advances the game by exactly one fixed step, see
+/// [`ApplicationBuilder::headless`](struct.ApplicationBuilder.html#method.headless).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeadlessPacing {
+    /// Sleep between frames so that one fixed step takes one fixed step length of wall time.
+    RealTime,
+    /// Run the next frame immediately, e.g. for tests, simulations or servers catching up.
+    AsFastAsPossible,
+}
+
 /// `CoreApplication` is the application implementation for the game engine. This is fully generic
 /// over the state type and event type.
 ///
@@ -60,6 +76,7 @@ where
     trans_reader_id: ReaderId<TransEvent<T, E>>,
     states: StateMachine<'a, T, E>,
     ignore_window_close: bool,
+    headless: Option<HeadlessPacing>,
     data: T,
 }
 
@@ -220,6 +237,9 @@ where
     /// `Trans::Pop` on the last state in from the stack. See full
     /// documentation on this in [State](trait.State.html) documentation.
     ///
+    /// Exit requests, like `Trans::Quit`, go through the `ApplicationLifecycle` resource,
+    /// which also runs the shutdown hooks once the states have stopped.
+    ///
     /// # Examples
     ///
     /// See the example supplied in the
@@ -241,7 +261,7 @@ where
         self.world.write_resource::<Stopwatch>().start();
         while self.states.is_running() {
             self.advance_frame();
-            {
+            if self.headless != Some(HeadlessPacing::AsFastAsPossible) {
                 #[cfg(feature = "profiler")]
                 profile_scope!("frame_limiter wait");
                 self.world.write_resource::<FrameLimiter>().wait();
@@ -250,7 +270,12 @@ where
                 let elapsed = self.world.read_resource::<Stopwatch>().elapsed();
                 let mut time = self.world.write_resource::<Time>();
                 time.increment_frame_number();
-                time.set_delta_time(elapsed);
+                if self.headless.is_some() {
+                    let step = time.fixed_time();
+                    time.set_delta_time(step);
+                } else {
+                    time.set_delta_time(elapsed);
+                }
             }
             let mut stopwatch = self.world.write_resource::<Stopwatch>();
             stopwatch.stop();
@@ -270,7 +295,7 @@ where
     }
 
     // React to window close events
-    fn should_close(&mut self) -> bool {
+    fn close_requested(&mut self) -> bool {
         if self.ignore_window_close {
             false
         } else {
@@ -309,10 +334,10 @@ where
         for<'b> R: EventReader<'b, Event = E>,
     {
         trace!("Advancing frame (`Application::advance_frame`)");
-        if self.should_close() {
-            let world = &mut self.world;
-            let states = &mut self.states;
-            states.stop(StateData::new(world, &mut self.data));
+        if self.close_requested() {
+            self.world
+                .write_resource::<ApplicationLifecycle>()
+                .request_exit(ExitReason::WindowClosed);
         }
 
         // Read the Trans queue and apply changes.
@@ -324,21 +349,8 @@ where
             let trans = world
                 .read_resource::<EventChannel<TransEvent<T, E>>>()
                 .read(reader)
-                .map(|e| e())
-                .collect::<Vec<_>>();
-            for tr in trans {
-                states.transition(tr, StateData::new(&mut world, &mut self.data));
-            }
-        }
-
-        {
-            #[cfg(feature = "profiler")]
-            profile_scope!("run_callback_queue");
-            let mut world = &mut self.world;
-            let receiver = world.read_resource::<CallbackQueue>().receiver.clone();
-            while let Ok(func) = receiver.try_recv() {
-                func(&mut world);
-            }
+                .fold(Trans::None, |trans, e| trans.then(e()));
+            states.transition(trans, StateData::new(&mut world, &mut self.data));
         }
 
         {
@@ -368,6 +380,7 @@ where
             while self.world.write_resource::<Time>().step_fixed_update() {
                 self.states
                     .fixed_update(StateData::new(&mut self.world, &mut self.data));
+                InterpolatedTransform::record_fixed_update(&self.world);
             }
             {
                 self.world.write_resource::<Time>().finish_fixed_update();
@@ -379,15 +392,49 @@ where
             self.states
                 .update(StateData::new(&mut self.world, &mut self.data));
         }
+        {
+            #[cfg(feature = "profiler")]
+            profile_scope!("run_callback_queue");
+            CallbackQueue::run(&mut self.world);
+        }
+        self.end_frame();
 
         #[cfg(feature = "profiler")]
         profile_scope!("maintain");
         self.world.maintain();
     }
 
+    /// Announces a pending exit request, or stops the states once it went through.
+    fn end_frame(&mut self) {
+        let end = self
+            .world
+            .write_resource::<ApplicationLifecycle>()
+            .end_frame();
+        match end {
+            FrameEnd::Continue => (),
+            FrameEnd::Announce(event) => {
+                info!("Exit requested: {:?}", event.reason);
+                self.world
+                    .write_resource::<EventChannel<ExitRequested>>()
+                    .single_write(event);
+            }
+            FrameEnd::Stop => {
+                self.states
+                    .stop(StateData::new(&mut self.world, &mut self.data));
+            }
+        }
+    }
+
     /// Cleans up after the quit signal is received.
     fn shutdown(&mut self) {
         info!("Engine is shutting down");
+        let hooks = self
+            .world
+            .write_resource::<ApplicationLifecycle>()
+            .take_shutdown_hooks();
+        for hook in hooks {
+            hook(&mut self.world);
+        }
         self.data.dispose(&mut self.world);
     }
 }
@@ -418,6 +465,7 @@ pub struct ApplicationBuilder<S, T, E, R> {
     /// Used by bundles to access the world directly
     pub world: World,
     ignore_window_close: bool,
+    headless: Option<HeadlessPacing>,
     phantom: PhantomData<(T, E, R)>,
 }
 
@@ -551,6 +599,11 @@ where
         world.insert(Stopwatch::default());
         world.insert(Time::default());
         world.insert(CallbackQueue::default());
+        world.insert(ApplicationLifecycle::default());
+        world.insert(EventChannel::<ExitRequested>::with_capacity(1));
+        if let Some(levels) = LogLevels::active() {
+            world.insert(levels);
+        }
 
         world.register::<Named>();
 
@@ -558,6 +611,7 @@ where
             initial_state,
             world,
             ignore_window_close: false,
+            headless: None,
             phantom: PhantomData,
         })
     }
@@ -679,6 +733,26 @@ where
         self
     }
 
+    /// Registers a hook run once the application has stopped, after the `on_stop` of the last
+    /// state and before the `GameData` is disposed of, see `ApplicationLifecycle::on_shutdown`.
+    ///
+    /// # Parameters
+    ///
+    /// - `hook`: The code to run, e.g. flushing saves or closing connections.
+    ///
+    /// # Returns
+    ///
+    /// This function returns the ApplicationBuilder after modifying it.
+    pub fn with_shutdown_hook<F>(mut self, hook: F) -> Self
+    where
+        F: FnOnce(&mut World) + Send + Sync + 'static,
+    {
+        self.world
+            .write_resource::<ApplicationLifecycle>()
+            .on_shutdown(hook);
+        self
+    }
+
     /// Register an asset store with the loader logic of the Application.
     ///
     /// If the asset store exists, that shares a name with the new store the net
@@ -855,6 +929,34 @@ where
         self
     }
 
+    /// Runs the application without a window or a renderer.
+    ///
+    /// A `ScreenDimensions` resource with the given size is inserted so that code which
+    /// reads it keeps working. Don't add the `WindowBundle` or a `RenderingBundle`; use
+    /// `HeadlessRenderingBundle` from the renderer instead, which still parses mesh and
+    /// texture assets but skips uploading them.
+    ///
+    /// There is no event loop, so the only input comes from events written into the
+    /// `EventChannel<Event>` resource, e.g. by a test or a network system.
+    ///
+    /// Every frame advances time by exactly one fixed step length, see
+    /// [`with_fixed_step_length`](#method.with_fixed_step_length), and `pacing` decides
+    /// whether frames are spread out over real time or run back to back.
+    ///
+    /// # Parameters
+    ///
+    /// `width`, `height`: The reported screen dimensions.
+    /// `pacing`: How frames are paced.
+    ///
+    /// # Returns
+    ///
+    /// This function returns the ApplicationBuilder after modifying it.
+    pub fn headless(mut self, width: u32, height: u32, pacing: HeadlessPacing) -> Self {
+        self.world.insert(ScreenDimensions::new(width, height, 1.0));
+        self.headless = Some(pacing);
+        self
+    }
+
     /// Build an `Application` object using the `ApplicationBuilder` as configured.
     ///
     /// # Returns
@@ -890,6 +992,17 @@ where
         #[cfg(feature = "profiler")]
         profile_scope!("new");
 
+        if self.headless == Some(HeadlessPacing::RealTime) {
+            let step = self.world.read_resource::<Time>().fixed_time();
+            let fps = (1.0 / step.as_secs_f64()).round().max(1.0) as u32;
+            let strategy = self
+                .world
+                .read_resource::<FrameLimiter>()
+                .strategy()
+                .clone();
+            self.world.insert(FrameLimiter::new(strategy, fps));
+        }
+
         let mut reader = X::default();
         reader.setup(&mut self.world);
         let data = init.build(&mut self.world);
@@ -907,9 +1020,193 @@ where
             reader,
             events: Vec::new(),
             ignore_window_close: self.ignore_window_close,
+            headless: self.headless,
             data,
             event_reader_id,
             trans_reader_id,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::{Application, HeadlessPacing};
+    use crate::{
+        core::{
+            shrev::{EventChannel, ReaderId},
+            timing::Time,
+        },
+        input::{InputEvent, StringBindings},
+        prelude::*,
+        window::ScreenDimensions,
+        ApplicationLifecycle, ExitReason, ExitRequested,
+    };
+
+    #[derive(Default)]
+    struct Counts {
+        updates: u32,
+        fixed_updates: u32,
+        jumps: u32,
+    }
+
+    struct CountingState(Arc<Mutex<Counts>>);
+
+    impl SimpleState for CountingState {
+        fn handle_event(
+            &mut self,
+            _: StateData<'_, GameData<'_, '_>>,
+            event: StateEvent,
+        ) -> SimpleTrans {
+            if let StateEvent::Input(InputEvent::ActionPressed(action)) = event {
+                if action == "jump" {
+                    self.0.lock().unwrap().jumps += 1;
+                }
+            }
+            Trans::None
+        }
+
+        fn fixed_update(&mut self, _: StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+            self.0.lock().unwrap().fixed_updates += 1;
+            Trans::None
+        }
+
+        fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+            let mut counts = self.0.lock().unwrap();
+            counts.updates += 1;
+            if counts.updates % 100 == 0 {
+                data.world
+                    .write_resource::<EventChannel<InputEvent<StringBindings>>>()
+                    .single_write(InputEvent::ActionPressed("jump".to_string()));
+            }
+            // The application runs one final frame after `Trans::Quit`.
+            if counts.updates == 999 {
+                Trans::Quit
+            } else {
+                Trans::None
+            }
+        }
+    }
+
+    #[test]
+    fn headless_application_runs_fixed_steps() {
+        let counts = Arc::new(Mutex::new(Counts::default()));
+        let mut game = Application::build(".", CountingState(counts.clone()))
+            .unwrap()
+            .headless(320, 240, HeadlessPacing::AsFastAsPossible)
+            .build(GameDataBuilder::default())
+            .unwrap();
+        game.run();
+
+        let counts = counts.lock().unwrap();
+        assert_eq!(1000, counts.updates);
+        // Time starts at zero, so the first frame has no fixed update.
+        assert_eq!(999, counts.fixed_updates);
+        // The event written on the last frame is never read.
+        assert_eq!(9, counts.jumps);
+        assert_eq!(1000, game.world.read_resource::<Time>().frame_number());
+        let dimensions = game.world.read_resource::<ScreenDimensions>();
+        assert_eq!(
+            (320, 240),
+            (dimensions.width() as u32, dimensions.height() as u32)
+        );
+    }
+
+    type Log = Arc<Mutex<Vec<&'static str>>>;
+
+    struct DropLogger(Log);
+
+    impl Drop for DropLogger {
+        fn drop(&mut self) {
+            self.0.lock().unwrap().push("drop resources");
+        }
+    }
+
+    struct ExitingState {
+        log: Log,
+        updates: u32,
+        exit_reader: Option<ReaderId<ExitRequested>>,
+    }
+
+    impl SimpleState for ExitingState {
+        fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+            self.exit_reader = Some(
+                data.world
+                    .write_resource::<EventChannel<ExitRequested>>()
+                    .register_reader(),
+            );
+        }
+
+        fn on_stop(&mut self, _: StateData<'_, GameData<'_, '_>>) {
+            self.log.lock().unwrap().push("on_stop");
+        }
+
+        fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+            self.updates += 1;
+            let mut log = self.log.lock().unwrap();
+            let requested = data
+                .world
+                .read_resource::<EventChannel<ExitRequested>>()
+                .read(self.exit_reader.as_mut().unwrap())
+                .any(|event| event.reason == ExitReason::Quit);
+            if requested {
+                log.push("exit requested");
+                if data
+                    .world
+                    .write_resource::<ApplicationLifecycle>()
+                    .veto_exit()
+                {
+                    log.push("vetoed");
+                } else {
+                    log.push("veto refused");
+                }
+            }
+            if self.updates == 1 || self.updates == 3 {
+                log.push("quit");
+                Trans::Quit
+            } else {
+                Trans::None
+            }
+        }
+    }
+
+    #[test]
+    fn exit_runs_a_final_frame_then_shutdown_hooks() {
+        let log = Log::default();
+        let state = ExitingState {
+            log: log.clone(),
+            updates: 0,
+            exit_reader: None,
+        };
+        let hook_log = log.clone();
+        let mut game = Application::build(".", state)
+            .unwrap()
+            .headless(320, 240, HeadlessPacing::AsFastAsPossible)
+            .with_resource(DropLogger(log.clone()))
+            .with_shutdown_hook(move |world| {
+                assert!(world.has_value::<DropLogger>());
+                hook_log.lock().unwrap().push("shutdown hook");
+            })
+            .build(GameDataBuilder::default())
+            .unwrap();
+        game.run();
+        assert_eq!(4, game.world.read_resource::<Time>().frame_number());
+        drop(game);
+
+        assert_eq!(
+            vec![
+                "quit",
+                "exit requested",
+                "vetoed",
+                "quit",
+                "exit requested",
+                "veto refused",
+                "on_stop",
+                "shutdown hook",
+                "drop resources",
+            ],
+            *log.lock().unwrap()
+        );
+    }
+}
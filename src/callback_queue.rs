@@ -1,5 +1,8 @@
+use std::panic::{self, AssertUnwindSafe};
+
 use crate::core::ecs::World;
 use crossbeam_channel::{Receiver, Sender};
+use log::error;
 
 /// The type of a callback.
 /// This is meant to be created from within asynchonous functions (`Future` for example).
@@ -9,7 +12,14 @@ pub type Callback = Box<dyn FnOnce(&mut World) + Send>;
 /// A simple `Callback` queue.
 /// Using the `Sender` you can get using the `send_handle` method, you
 /// can add functions modifying `World` from an asynchronous context.
-/// Those callbacks will be ran sequentially without preserving ordering.
+///
+/// The `Application` runs the callbacks once per frame, after the state has been updated,
+/// which runs the dispatcher of `GameData`, and before the `World` is maintained. Callbacks
+/// sent through the same `Sender` run in the order they were sent. Callbacks sent by a
+/// callback run in the next frame.
+///
+/// A callback which panics is logged and skipped, see `set_abort_on_panic`.
+///
 /// # Example
 /// ```rust,ignore
 /// // First, get a `Sender` handle.
@@ -18,13 +28,15 @@ pub type Callback = Box<dyn FnOnce(&mut World) + Send>;
 /// let future = ...;
 /// // Finally, use that handle inside of the asynchronous context to run code that can affect `World`.
 /// future.on_complete(move || {
-///     handle.send(|mut world| world.create_entity().build()).expect("Failed to add Callback to CallbackQueue.");
+///     handle.send(Box::new(|world: &mut World| { world.create_entity().build(); }))
+///         .expect("Failed to add Callback to CallbackQueue.");
 /// });
 /// ```
 #[allow(missing_debug_implementations)]
 pub struct CallbackQueue {
     sender: Sender<Callback>,
-    pub(crate) receiver: Receiver<Callback>,
+    receiver: Receiver<Callback>,
+    abort_on_panic: bool,
 }
 
 impl CallbackQueue {
@@ -37,11 +49,88 @@ impl CallbackQueue {
     pub fn send_handle(&self) -> Sender<Callback> {
         self.sender.clone()
     }
+
+    /// Sets whether a panicking callback panics the main loop, instead of being logged and
+    /// skipped. Disabled by default.
+    pub fn set_abort_on_panic(&mut self, abort: bool) {
+        self.abort_on_panic = abort;
+    }
+
+    /// Runs the callbacks which have been sent so far.
+    pub(crate) fn run(world: &mut World) {
+        let (receiver, abort_on_panic) = {
+            let queue = world.read_resource::<CallbackQueue>();
+            (queue.receiver.clone(), queue.abort_on_panic)
+        };
+        // Callbacks sent by these callbacks are left for the next frame.
+        for callback in receiver.try_iter().take(receiver.len()) {
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| callback(world))) {
+                if abort_on_panic {
+                    panic::resume_unwind(payload);
+                }
+                let message = payload
+                    .downcast_ref::<String>()
+                    .cloned()
+                    .or_else(|| payload.downcast_ref::<&str>().map(|m| m.to_string()))
+                    .unwrap_or_default();
+                error!(
+                    "Skipped a callback of the `CallbackQueue` which panicked: {}",
+                    message
+                );
+            }
+        }
+    }
 }
 
 impl Default for CallbackQueue {
     fn default() -> Self {
         let (sender, receiver) = crossbeam_channel::unbounded();
-        Self { sender, receiver }
+        Self {
+            sender,
+            receiver,
+            abort_on_panic: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::{Callback, CallbackQueue};
+    use crate::core::ecs::{World, WorldExt};
+
+    type Log = Arc<Mutex<Vec<u32>>>;
+
+    fn record(log: &Log, value: u32) -> Callback {
+        let log = log.clone();
+        Box::new(move |_: &mut World| log.lock().unwrap().push(value))
+    }
+
+    #[test]
+    fn runs_callbacks_in_order_and_skips_panics() {
+        let mut world = World::new();
+        world.insert(CallbackQueue::new());
+        let sender = world.read_resource::<CallbackQueue>().send_handle();
+        let log = Log::default();
+
+        sender.send(record(&log, 1)).unwrap();
+        sender
+            .send(Box::new(|_: &mut World| panic!("callback failed")))
+            .unwrap();
+        sender.send(record(&log, 2)).unwrap();
+        let (nested, nested_log) = (sender.clone(), log.clone());
+        sender
+            .send(Box::new(move |_: &mut World| {
+                nested.send(record(&nested_log, 4)).unwrap();
+            }))
+            .unwrap();
+        sender.send(record(&log, 3)).unwrap();
+
+        CallbackQueue::run(&mut world);
+        assert_eq!(vec![1, 2, 3], *log.lock().unwrap());
+
+        CallbackQueue::run(&mut world);
+        assert_eq!(vec![1, 2, 3, 4], *log.lock().unwrap());
     }
 }
@@ -1,4 +1,9 @@
-use std::marker::PhantomData;
+use std::{
+    any::type_name,
+    io,
+    marker::PhantomData,
+    panic::{self, AssertUnwindSafe},
+};
 
 use crate::{
     core::{
@@ -7,9 +12,11 @@ use crate::{
             DispatcherOperation,
         },
         ecs::prelude::{Dispatcher, DispatcherBuilder, RunNow, System, World, WorldExt},
-        ArcThreadPool, RunNowDesc, SystemBundle, SystemDesc,
+        shred::Resource,
+        ArcThreadPool, RunNowDesc, SystemBundle, SystemDesc, SystemExt,
     },
     error::Error,
+    system_graph::{DependencyError, Node, SystemGraph},
 };
 
 /// Initialise trait for game data
@@ -71,6 +78,7 @@ impl DataDispose for GameData<'_, '_> {
 pub struct GameDataBuilder<'a, 'b> {
     dispatcher_operations: Vec<Box<dyn DispatcherOperation<'a, 'b>>>,
     disp_builder: DispatcherBuilder<'a, 'b>,
+    graph: SystemGraph,
 }
 
 impl<'a, 'b> Default for GameDataBuilder<'a, 'b> {
@@ -85,6 +93,7 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
         GameDataBuilder {
             dispatcher_operations: Vec::new(),
             disp_builder: DispatcherBuilder::new(),
+            graph: SystemGraph::default(),
         }
     }
 
@@ -125,6 +134,7 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
     /// ~~~
     pub fn with_barrier(mut self) -> Self {
         self.dispatcher_operations.push(Box::new(AddBarrier));
+        self.graph.push(Node::Barrier);
         self
     }
 
@@ -193,6 +203,10 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
             .map(Clone::clone)
             .map(Into::<String>::into)
             .collect::<Vec<String>>();
+        self.graph.push(Node::System {
+            name: name.clone(),
+            dependencies: dependencies.clone(),
+        });
         let dispatcher_operation = Box::new(AddSystem {
             system,
             name,
@@ -202,6 +216,63 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
         self
     }
 
+    /// Adds a given system which only runs on the frames `condition` returns true for the
+    /// resource `R`.
+    ///
+    /// The data of the system is only fetched on the frames it runs, see
+    /// [`SystemExt::run_when`]. It is skipped while `R` is not in the world.
+    ///
+    /// __Note:__ all dependencies must be added before you add the system.
+    ///
+    /// # Parameters
+    ///
+    /// - `system`: The system that is to be added to the game loop.
+    /// - `name`: A unique string to identify the system by, as for `with`.
+    /// - `dependencies`: A list of named system that _must_ have completed running
+    ///                 before this system is permitted to run.
+    /// - `condition`: Decides from the resource `R` whether the system runs this frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as `with`.
+    ///
+    /// [`SystemExt::run_when`]: ../amethyst_core/trait.SystemExt.html#tymethod.run_when
+    ///
+    /// # Examples
+    ///
+    /// ~~~no_run
+    /// use amethyst::prelude::*;
+    /// use amethyst::ecs::prelude::{System, SystemData, World};
+    ///
+    /// #[derive(Default)]
+    /// struct Debugging(bool);
+    ///
+    /// struct NopSystem;
+    /// impl<'a> System<'a> for NopSystem {
+    ///     type SystemData = ();
+    ///     fn run(&mut self, _: Self::SystemData) {}
+    /// }
+    ///
+    /// GameDataBuilder::default()
+    ///     // The "debug_lines" system only runs while debugging is on.
+    ///     .with_conditional(NopSystem, "debug_lines", &[], |debug: &Debugging| debug.0);
+    /// ~~~
+    pub fn with_conditional<S, N, R, F>(
+        self,
+        system: S,
+        name: N,
+        dependencies: &[N],
+        condition: F,
+    ) -> Self
+    where
+        S: for<'c> System<'c> + 'static + Send,
+        N: Into<String> + Clone,
+        R: Resource,
+        F: FnMut(&R) -> bool + Send + 'static,
+    {
+        self.with(system.run_when(condition), name, dependencies)
+    }
+
     /// Adds a system descriptor.
     ///
     /// This differs from the `with` System call by deferring instantiation of the `System` to
@@ -278,6 +349,10 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
             .map(Clone::clone)
             .map(Into::<String>::into)
             .collect::<Vec<String>>();
+        self.graph.push(Node::System {
+            name: name.clone(),
+            dependencies: dependencies.clone(),
+        });
         let dispatcher_operation = Box::new(AddSystemDesc {
             system_desc,
             name,
@@ -335,6 +410,9 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
     {
         self.dispatcher_operations
             .push(Box::new(AddThreadLocal { system }));
+        self.graph.push(Node::ThreadLocal {
+            type_name: type_name::<S>(),
+        });
         self
     }
 
@@ -389,6 +467,9 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
                 system_desc,
                 marker: PhantomData::<S>,
             }));
+        self.graph.push(Node::ThreadLocal {
+            type_name: type_name::<SD>(),
+        });
         self
     }
 
@@ -418,6 +499,9 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
     {
         self.dispatcher_operations
             .push(Box::new(AddBundle { bundle }));
+        self.graph.push(Node::Bundle {
+            type_name: type_name::<B>(),
+        });
         Ok(self)
     }
 
@@ -459,6 +543,36 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
     //     }
     // }
 
+    /// Checks the dependencies of the systems added so far.
+    ///
+    /// Fails if a system depends on a system that is not registered, with suggestions for names
+    /// close to it, on a system added after it, or if systems depend on each other in a loop. The
+    /// systems added by bundles are only known when the dispatcher is built, which reports the
+    /// bundle that failed to add its systems.
+    pub fn validate(&self) -> Result<(), DependencyError> {
+        self.graph.validate()
+    }
+
+    /// Writes the systems and their dependencies in the DOT format of Graphviz.
+    ///
+    /// Bundles are drawn as a single node, since their systems are only known once they are
+    /// built.
+    ///
+    /// # Examples
+    ///
+    /// ~~~no_run
+    /// use amethyst::prelude::*;
+    /// use std::fs::File;
+    ///
+    /// let game_data = GameDataBuilder::default();
+    /// game_data
+    ///     .write_dependency_graph(File::create("systems.dot").unwrap())
+    ///     .unwrap();
+    /// ~~~
+    pub fn write_dependency_graph<W: io::Write>(&self, w: W) -> io::Result<()> {
+        self.graph.write_dot(w)
+    }
+
     /// Instead of using `DataInit` for constructing `GameData`, build a standalone `Dispatcher`,
     /// which will be the same dispatcher that would have been created for the `GameData`.
     pub fn build_dispatcher(self, mut world: &mut World) -> Dispatcher<'a, 'b> {
@@ -466,11 +580,30 @@ impl<'a, 'b> GameDataBuilder<'a, 'b> {
         let pool = (*world.read_resource::<ArcThreadPool>()).clone();
 
         let mut dispatcher_builder = self.disp_builder;
+        let graph = self.graph;
 
-        self.dispatcher_operations
-            .into_iter()
-            .try_for_each(|dispatcher_operation| {
-                dispatcher_operation.exec(world, &mut dispatcher_builder)
+        graph
+            .validate()
+            .map_err(Error::from)
+            .and_then(|_| {
+                self.dispatcher_operations
+                    .into_iter()
+                    .enumerate()
+                    .try_for_each(|(index, dispatcher_operation)| {
+                        // the dispatcher builder panics on unknown dependencies, which can only
+                        // be caught here for the systems added by bundles
+                        panic::catch_unwind(AssertUnwindSafe(|| {
+                            dispatcher_operation.exec(world, &mut dispatcher_builder)
+                        }))
+                        .unwrap_or_else(|payload| {
+                            let message = payload
+                                .downcast_ref::<String>()
+                                .cloned()
+                                .or_else(|| payload.downcast_ref::<&str>().map(|m| m.to_string()))
+                                .unwrap_or_default();
+                            Err(graph.failure(index, message).into())
+                        })
+                    })
             })
             .unwrap_or_else(|e| panic!("Failed to set up dispatcher: {}", e));
 
@@ -93,16 +93,23 @@ pub use crate::core::{ecs, shred, shrev};
 pub use crate::derive::*;
 
 pub use self::{
-    app::{Application, ApplicationBuilder, CoreApplication},
+    app::{Application, ApplicationBuilder, CoreApplication, HeadlessPacing},
     callback_queue::{Callback, CallbackQueue},
     error::Error,
     game_data::{DataDispose, DataInit, GameData, GameDataBuilder},
-    logger::{start_logger, LevelFilter as LogLevelFilter, Logger, LoggerConfig, StdoutLog},
+    lifecycle::{ApplicationLifecycle, ExitHandle, ExitReason, ExitRequested, ShutdownHook},
+    loading_state::{LoadingProgress, LoadingState},
+    logger::{
+        start_logger, LevelFilter as LogLevelFilter, LogFileRotation, LogFormat, LogLevels, Logger,
+        LoggerConfig, StdoutLog,
+    },
     state::{
         EmptyState, EmptyTrans, SimpleState, SimpleTrans, State, StateData, StateMachine, Trans,
-        TransEvent,
+        TransEvent, TransQueue,
     },
     state_event::{StateEvent, StateEventReader},
+    state_scope::{ActiveStateScope, CreateScopedEntity, StateScope},
+    system_graph::DependencyError,
 };
 
 /// Convenience alias for use in main functions that uses Amethyst.
@@ -113,6 +120,10 @@ pub mod prelude;
 mod app;
 mod callback_queue;
 mod game_data;
+mod lifecycle;
+mod loading_state;
 mod logger;
 mod state;
 mod state_event;
+mod state_scope;
+mod system_graph;
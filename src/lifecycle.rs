@@ -0,0 +1,322 @@
+//! Requesting the application to exit, and running code when it shuts down.
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use derivative::Derivative;
+
+use crate::ecs::World;
+
+/// Code run once the application has stopped, see `ApplicationLifecycle::on_shutdown`.
+pub type ShutdownHook = Box<dyn FnOnce(&mut World) + Send + Sync>;
+
+/// What asked the application to exit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    /// The close button of the window was pressed.
+    WindowClosed,
+    /// A state transition returned or queued `Trans::Quit`.
+    Quit,
+    /// An `ExitHandle` was used, e.g. by an OS signal handler.
+    External,
+}
+
+/// Event sent when the application has been asked to exit.
+///
+/// It is written to the `EventChannel<ExitRequested>` resource at the end of the frame in which
+/// exit was requested. The application then runs one final frame, during which states and
+/// systems can read the event and veto or delay the exit through the `ApplicationLifecycle`
+/// resource, and stops at the end of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExitRequested {
+    /// What asked the application to exit.
+    pub reason: ExitReason,
+}
+
+/// Requests the application to exit from any thread, e.g. from an OS signal handler.
+///
+/// The request is picked up at the end of the current frame.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let handle = world.read_resource::<ApplicationLifecycle>().exit_handle();
+/// ctrlc::set_handler(move || handle.request_exit()).expect("Failed to set Ctrl-C handler");
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExitHandle {
+    requests: Arc<AtomicUsize>,
+}
+
+impl ExitHandle {
+    /// Requests the application to exit, as `ExitReason::External`.
+    pub fn request_exit(&self) {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// What the application does at the end of a frame, see `ApplicationLifecycle::end_frame`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FrameEnd {
+    /// Keep running.
+    Continue,
+    /// Send the event and run one final frame.
+    Announce(ExitRequested),
+    /// Stop the state machine and shut down.
+    Stop,
+}
+
+#[derive(Debug)]
+struct ExitRequest {
+    reason: ExitReason,
+    announced: bool,
+    delayed: bool,
+    forced: bool,
+}
+
+/// Resource controlling how the application exits.
+///
+/// Closing the window, `Trans::Quit` and `ExitHandle`s request the application to exit instead
+/// of stopping it right away, see `ExitRequested`. A request can be vetoed or delayed once
+/// during the lifetime of the application, e.g. while a save is in progress. Requesting exit
+/// again once the request has been announced forces the application to stop at the end of the
+/// frame, without a final frame and without a veto. Requests made in the same frame, like the
+/// window close button and the `Trans::Quit` returned for it, count as one.
+///
+/// Once the application has stopped, after the `on_stop` of the last state, the shutdown hooks
+/// run in the order they were registered, then the `GameData` is disposed of. The resources are
+/// dropped with the application.
+///
+/// Popping the last state stops the application right away, without an `ExitRequested` event.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+///     data.data.update(&data.world);
+///     let requested = data
+///         .world
+///         .read_resource::<EventChannel<ExitRequested>>()
+///         .read(&mut self.exit_reader)
+///         .count() > 0;
+///     if requested && self.saving {
+///         data.world.write_resource::<ApplicationLifecycle>().delay_exit();
+///     }
+///     if self.saving && save_finished() {
+///         data.world.write_resource::<ApplicationLifecycle>().resume_exit();
+///     }
+///     Trans::None
+/// }
+/// ```
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct ApplicationLifecycle {
+    request: Option<ExitRequest>,
+    postponed: bool,
+    external_requests: Arc<AtomicUsize>,
+    seen_external_requests: usize,
+    #[derivative(Debug = "ignore")]
+    shutdown_hooks: Vec<ShutdownHook>,
+}
+
+impl Default for ApplicationLifecycle {
+    fn default() -> Self {
+        ApplicationLifecycle {
+            request: None,
+            postponed: false,
+            external_requests: Arc::new(AtomicUsize::new(0)),
+            seen_external_requests: 0,
+            shutdown_hooks: Vec::new(),
+        }
+    }
+}
+
+impl ApplicationLifecycle {
+    /// Creates a lifecycle without pending requests nor shutdown hooks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests the application to exit. If a request has already been announced, the
+    /// application is forced to stop at the end of the frame.
+    pub fn request_exit(&mut self, reason: ExitReason) {
+        match self.request {
+            Some(ref mut request) if request.announced => request.forced = true,
+            Some(_) => (),
+            None => {
+                self.request = Some(ExitRequest {
+                    reason,
+                    announced: false,
+                    delayed: false,
+                    forced: false,
+                })
+            }
+        }
+    }
+
+    /// Returns the reason of the pending exit request, if any.
+    pub fn exit_requested(&self) -> Option<ExitReason> {
+        self.request.as_ref().map(|request| request.reason)
+    }
+
+    /// Cancels the pending exit request, so the application keeps running.
+    ///
+    /// Returns `false` if there is no request to cancel, if it is forced, or if a request was
+    /// already vetoed or delayed.
+    pub fn veto_exit(&mut self) -> bool {
+        if self.can_postpone() {
+            self.request = None;
+            self.postponed = true;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Keeps the application running until `resume_exit` is called, then stops it at the end of
+    /// that frame.
+    ///
+    /// Returns `false` if there is no request to delay, if it is forced, or if a request was
+    /// already vetoed or delayed.
+    pub fn delay_exit(&mut self) -> bool {
+        if self.can_postpone() {
+            self.request.as_mut().unwrap().delayed = true;
+            self.postponed = true;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Lets a delayed exit request go through.
+    pub fn resume_exit(&mut self) {
+        if let Some(ref mut request) = self.request {
+            request.delayed = false;
+        }
+    }
+
+    /// Returns a handle requesting exit from another thread.
+    pub fn exit_handle(&self) -> ExitHandle {
+        ExitHandle {
+            requests: self.external_requests.clone(),
+        }
+    }
+
+    /// Registers a hook run once the application has stopped, after the `on_stop` of the last
+    /// state and before the `GameData` is disposed of.
+    pub fn on_shutdown<F>(&mut self, hook: F)
+    where
+        F: FnOnce(&mut World) + Send + Sync + 'static,
+    {
+        self.shutdown_hooks.push(Box::new(hook));
+    }
+
+    fn can_postpone(&self) -> bool {
+        !self.postponed
+            && self
+                .request
+                .as_ref()
+                .map_or(false, |request| !request.forced)
+    }
+
+    /// Decides what the application does at the end of the current frame.
+    pub(crate) fn end_frame(&mut self) -> FrameEnd {
+        let requests = self.external_requests.load(Ordering::SeqCst);
+        while self.seen_external_requests < requests {
+            self.seen_external_requests += 1;
+            self.request_exit(ExitReason::External);
+        }
+
+        match self.request {
+            None => FrameEnd::Continue,
+            Some(ref request) if request.forced => FrameEnd::Stop,
+            Some(ref mut request) if !request.announced => {
+                request.announced = true;
+                FrameEnd::Announce(ExitRequested {
+                    reason: request.reason,
+                })
+            }
+            Some(ref request) if request.delayed => FrameEnd::Continue,
+            Some(_) => FrameEnd::Stop,
+        }
+    }
+
+    /// Takes the shutdown hooks out, in the order they were registered.
+    pub(crate) fn take_shutdown_hooks(&mut self) -> Vec<ShutdownHook> {
+        std::mem::replace(&mut self.shutdown_hooks, Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ApplicationLifecycle, ExitReason, ExitRequested, FrameEnd};
+
+    #[test]
+    fn exit_is_announced_for_one_frame() {
+        let mut lifecycle = ApplicationLifecycle::new();
+        assert_eq!(FrameEnd::Continue, lifecycle.end_frame());
+
+        lifecycle.request_exit(ExitReason::Quit);
+        assert_eq!(
+            FrameEnd::Announce(ExitRequested {
+                reason: ExitReason::Quit
+            }),
+            lifecycle.end_frame()
+        );
+        assert_eq!(FrameEnd::Stop, lifecycle.end_frame());
+    }
+
+    #[test]
+    fn exit_can_be_postponed_once() {
+        let mut lifecycle = ApplicationLifecycle::new();
+        assert!(!lifecycle.veto_exit());
+
+        lifecycle.request_exit(ExitReason::WindowClosed);
+        lifecycle.end_frame();
+        assert!(lifecycle.veto_exit());
+        assert_eq!(None, lifecycle.exit_requested());
+        assert_eq!(FrameEnd::Continue, lifecycle.end_frame());
+
+        lifecycle.exit_handle().request_exit();
+        match lifecycle.end_frame() {
+            FrameEnd::Announce(event) => assert_eq!(ExitReason::External, event.reason),
+            end => panic!("Expected the request to be announced, got {:?}", end),
+        }
+        assert!(!lifecycle.delay_exit());
+        assert!(!lifecycle.veto_exit());
+        assert_eq!(FrameEnd::Stop, lifecycle.end_frame());
+    }
+
+    #[test]
+    fn delayed_exit_waits_for_resume_or_second_request() {
+        let mut lifecycle = ApplicationLifecycle::new();
+        lifecycle.request_exit(ExitReason::Quit);
+        lifecycle.end_frame();
+        assert!(lifecycle.delay_exit());
+        assert_eq!(FrameEnd::Continue, lifecycle.end_frame());
+        assert_eq!(FrameEnd::Continue, lifecycle.end_frame());
+        lifecycle.resume_exit();
+        assert_eq!(FrameEnd::Stop, lifecycle.end_frame());
+
+        let mut lifecycle = ApplicationLifecycle::new();
+        lifecycle.request_exit(ExitReason::Quit);
+        lifecycle.end_frame();
+        lifecycle.delay_exit();
+        lifecycle.request_exit(ExitReason::WindowClosed);
+        assert_eq!(FrameEnd::Stop, lifecycle.end_frame());
+    }
+
+    #[test]
+    fn second_request_forces_exit() {
+        let mut lifecycle = ApplicationLifecycle::new();
+        lifecycle.request_exit(ExitReason::WindowClosed);
+        lifecycle.request_exit(ExitReason::Quit);
+        assert_eq!(Some(ExitReason::WindowClosed), lifecycle.exit_requested());
+        lifecycle.end_frame();
+        lifecycle.request_exit(ExitReason::WindowClosed);
+        assert!(!lifecycle.veto_exit());
+        assert_eq!(FrameEnd::Stop, lifecycle.end_frame());
+    }
+}
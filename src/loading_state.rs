@@ -0,0 +1,347 @@
+//! A state loading assets before switching to the next one.
+
+use std::time::Duration;
+
+use derivative::Derivative;
+use log::error;
+
+use crate::{
+    assets::{AssetErrorMeta, ProgressCounter},
+    core::Time,
+    ecs::{World, WorldExt},
+    GameData, State, StateData, StateEvent, Trans,
+};
+
+type LoadFn<A> = Box<dyn FnOnce(&mut World, &mut ProgressCounter) -> A>;
+type NextFn<A, T, E> = Box<dyn FnOnce(A) -> Box<dyn State<T, E>>>;
+type ErrorFn<A, T, E> = Box<dyn FnOnce(A, Vec<AssetErrorMeta>) -> Box<dyn State<T, E>>>;
+type ProgressFn = Box<dyn FnMut(&mut World, &LoadingProgress<'_>)>;
+
+/// Progress of a `LoadingState`, passed to its progress callback every frame.
+#[derive(Debug)]
+pub struct LoadingProgress<'a> {
+    /// Number of assets loaded.
+    pub finished: usize,
+    /// Number of assets that failed to load.
+    pub failed: usize,
+    /// Number of assets started by the loading closure.
+    pub total: usize,
+    /// Errors of the assets that failed so far.
+    pub errors: &'a [AssetErrorMeta],
+}
+
+impl<'a> LoadingProgress<'a> {
+    /// Returns the fraction of assets done loading, successfully or not, between 0 and 1.
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            1.
+        } else {
+            (self.finished + self.failed) as f32 / self.total as f32
+        }
+    }
+}
+
+/// State starting asset loads, then switching to the next state once they are done.
+///
+/// `A` is whatever the loading closure returns, usually the handles of the assets, which is passed
+/// on to the next state. Once every asset finished loading, and the minimum duration elapsed, the
+/// state switches to the state made by the `next` factory. If any asset failed, it switches to the
+/// error state instead, or quits if there is none.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use amethyst::{
+///     assets::{AssetStorage, Handle, Loader},
+///     audio::{OggFormat, Source},
+///     prelude::*,
+///     LoadingState,
+/// };
+/// use std::time::Duration;
+///
+/// struct Game {
+///     music: Handle<Source>,
+/// }
+///
+/// impl SimpleState for Game {}
+///
+/// let loading: LoadingState<_> = LoadingState::new(
+///     |world, progress| {
+///         let loader = world.read_resource::<Loader>();
+///         loader.load(
+///             "audio/music.ogg",
+///             OggFormat,
+///             progress,
+///             &world.read_resource::<AssetStorage<Source>>(),
+///         )
+///     },
+///     |music| Box::new(Game { music }),
+/// )
+/// .with_progress(|_, progress| println!("Loading: {:.0}%", progress.fraction() * 100.))
+/// .with_min_duration(Duration::from_secs(2));
+/// ```
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct LoadingState<A, T = GameData<'static, 'static>, E = StateEvent> {
+    #[derivative(Debug = "ignore")]
+    load: Option<LoadFn<A>>,
+    #[derivative(Debug = "ignore")]
+    next: Option<NextFn<A, T, E>>,
+    #[derivative(Debug = "ignore")]
+    on_error: Option<ErrorFn<A, T, E>>,
+    #[derivative(Debug = "ignore")]
+    on_progress: Option<ProgressFn>,
+    min_duration: Duration,
+    #[derivative(Debug = "ignore")]
+    loaded: Option<A>,
+    progress: ProgressCounter,
+    errors: Vec<AssetErrorMeta>,
+    start_time: Duration,
+}
+
+impl<A, T, E> LoadingState<A, T, E> {
+    /// Creates a loading state calling `load` when it starts, and switching to the state made by
+    /// `next` from its result.
+    pub fn new<L, N>(load: L, next: N) -> Self
+    where
+        L: FnOnce(&mut World, &mut ProgressCounter) -> A + 'static,
+        N: FnOnce(A) -> Box<dyn State<T, E>> + 'static,
+    {
+        LoadingState {
+            load: Some(Box::new(load)),
+            next: Some(Box::new(next)),
+            on_error: None,
+            on_progress: None,
+            min_duration: Duration::from_secs(0),
+            loaded: None,
+            progress: ProgressCounter::new(),
+            errors: Vec::new(),
+            start_time: Duration::from_secs(0),
+        }
+    }
+
+    /// Calls `on_progress` every frame while loading, to display the progress.
+    pub fn with_progress<P>(mut self, on_progress: P) -> Self
+    where
+        P: FnMut(&mut World, &LoadingProgress<'_>) + 'static,
+    {
+        self.on_progress = Some(Box::new(on_progress));
+        self
+    }
+
+    /// Switches to the state made by `on_error` if any asset fails to load, instead of quitting.
+    pub fn with_error_state<F>(mut self, on_error: F) -> Self
+    where
+        F: FnOnce(A, Vec<AssetErrorMeta>) -> Box<dyn State<T, E>> + 'static,
+    {
+        self.on_error = Some(Box::new(on_error));
+        self
+    }
+
+    /// Stays in the loading state for at least `min_duration` of real time, so a splash screen
+    /// does not flash when the assets load quickly.
+    pub fn with_min_duration(mut self, min_duration: Duration) -> Self {
+        self.min_duration = min_duration;
+        self
+    }
+
+    fn finish(&mut self) -> Trans<T, E> {
+        let loaded = match self.loaded.take() {
+            Some(loaded) => loaded,
+            None => return Trans::None,
+        };
+        if self.errors.is_empty() {
+            let next = self
+                .next
+                .take()
+                .expect("Unreachable: The state finishes once");
+            return Trans::Switch(next(loaded));
+        }
+        let errors = self.errors.drain(..).collect();
+        match self.on_error.take() {
+            Some(on_error) => Trans::Switch(on_error(loaded, errors)),
+            None => {
+                error!("Failed to load assets, quitting");
+                Trans::Quit
+            }
+        }
+    }
+}
+
+impl<A, T, E: Send + Sync + 'static> State<T, E> for LoadingState<A, T, E> {
+    fn on_start(&mut self, data: StateData<'_, T>) {
+        self.start_time = data.world.read_resource::<Time>().absolute_real_time();
+        if let Some(load) = self.load.take() {
+            self.loaded = Some(load(data.world, &mut self.progress));
+        }
+    }
+
+    fn update(&mut self, data: StateData<'_, T>) -> Trans<T, E> {
+        self.errors.extend(self.progress.errors());
+        if let Some(on_progress) = &mut self.on_progress {
+            let progress = LoadingProgress {
+                finished: self.progress.num_finished(),
+                failed: self.progress.num_failed(),
+                total: self.progress.num_assets(),
+                errors: &self.errors,
+            };
+            on_progress(data.world, &progress);
+        }
+
+        if self.progress.num_loading() > 0 {
+            return Trans::None;
+        }
+        let elapsed = data.world.read_resource::<Time>().absolute_real_time() - self.start_time;
+        if self.progress.num_failed() == 0 && elapsed < self.min_duration {
+            return Trans::None;
+        }
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assets::{Progress, Tracker},
+        error::Error,
+        StateMachine,
+    };
+    use std::sync::{Arc, Mutex};
+
+    type Shared<V> = Arc<Mutex<Vec<V>>>;
+    type CounterTracker = <&'static mut ProgressCounter as Progress>::Tracker;
+
+    /// Records its name when it starts.
+    struct Next(&'static str, Shared<&'static str>);
+
+    impl State<(), ()> for Next {
+        fn on_start(&mut self, _: StateData<'_, ()>) {
+            self.1.lock().unwrap().push(self.0);
+        }
+    }
+
+    fn loading(
+        trackers: &Shared<CounterTracker>,
+        started: &Shared<&'static str>,
+        assets: usize,
+    ) -> LoadingState<usize, (), ()> {
+        let trackers = trackers.clone();
+        let started = started.clone();
+        LoadingState::new(
+            move |_, mut progress: &mut ProgressCounter| {
+                for _ in 0..assets {
+                    progress.add_assets(1);
+                    trackers
+                        .lock()
+                        .unwrap()
+                        .push((&mut *progress).create_tracker());
+                }
+                assets
+            },
+            move |loaded| {
+                assert_eq!(loaded, assets);
+                Box::new(Next("next", started))
+            },
+        )
+    }
+
+    fn finish(trackers: &Shared<CounterTracker>, success: bool) {
+        let tracker = Box::new(trackers.lock().unwrap().remove(0));
+        if success {
+            tracker.success();
+        } else {
+            tracker.fail(0, "Test", "test".to_string(), Error::from_string("failed"));
+        }
+    }
+
+    fn setup() -> World {
+        let mut world = World::new();
+        world.insert(Time::default());
+        world
+    }
+
+    #[test]
+    fn switches_once_loaded() {
+        let mut world = setup();
+        let trackers = Shared::default();
+        let started = Shared::default();
+        let fractions = Shared::default();
+        let recorded = fractions.clone();
+        let state = loading(&trackers, &started, 2).with_progress(move |_, progress| {
+            recorded.lock().unwrap().push(progress.fraction());
+        });
+
+        let mut sm = StateMachine::new(state);
+        sm.start(StateData::new(&mut world, &mut ())).unwrap();
+        sm.update(StateData::new(&mut world, &mut ()));
+        finish(&trackers, true);
+        sm.update(StateData::new(&mut world, &mut ()));
+        assert!(started.lock().unwrap().is_empty());
+        finish(&trackers, true);
+        sm.update(StateData::new(&mut world, &mut ()));
+
+        assert_eq!(*started.lock().unwrap(), vec!["next"]);
+        assert_eq!(*fractions.lock().unwrap(), vec![0., 0.5, 1.]);
+    }
+
+    #[test]
+    fn switches_to_error_state() {
+        let mut world = setup();
+        let trackers = Shared::default();
+        let started = Shared::default();
+        let error_started = started.clone();
+        let state = loading(&trackers, &started, 2).with_error_state(move |_, errors| {
+            assert_eq!(errors.len(), 1);
+            Box::new(Next("error", error_started))
+        });
+
+        let mut sm = StateMachine::new(state);
+        sm.start(StateData::new(&mut world, &mut ())).unwrap();
+        finish(&trackers, false);
+        sm.update(StateData::new(&mut world, &mut ()));
+        assert!(started.lock().unwrap().is_empty());
+        finish(&trackers, true);
+        sm.update(StateData::new(&mut world, &mut ()));
+
+        assert_eq!(*started.lock().unwrap(), vec!["error"]);
+    }
+
+    #[test]
+    fn quits_on_error_without_error_state() {
+        let mut world = setup();
+        let trackers = Shared::default();
+        let started = Shared::default();
+        let mut sm = StateMachine::new(loading(&trackers, &started, 1));
+        sm.start(StateData::new(&mut world, &mut ())).unwrap();
+        finish(&trackers, false);
+        sm.update(StateData::new(&mut world, &mut ()));
+
+        assert!(!sm.is_running());
+        assert!(started.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn waits_for_min_duration() {
+        let mut world = setup();
+        let trackers = Shared::default();
+        let started = Shared::default();
+        let state = loading(&trackers, &started, 1).with_min_duration(Duration::from_secs(2));
+        let mut sm = StateMachine::new(state);
+        sm.start(StateData::new(&mut world, &mut ())).unwrap();
+        finish(&trackers, true);
+
+        sm.update(StateData::new(&mut world, &mut ()));
+        world
+            .write_resource::<Time>()
+            .set_delta_time(Duration::from_secs(1));
+        sm.update(StateData::new(&mut world, &mut ()));
+        assert!(started.lock().unwrap().is_empty());
+
+        world
+            .write_resource::<Time>()
+            .set_delta_time(Duration::from_secs(1));
+        sm.update(StateData::new(&mut world, &mut ()));
+        assert_eq!(*started.lock().unwrap(), vec!["next"]);
+    }
+}
@@ -1,9 +1,25 @@
 pub use log::LevelFilter;
 
+use lazy_static::lazy_static;
 use log::debug;
 use serde::{Deserialize, Serialize};
 
-use std::{borrow::Cow, env, fmt, io, path::PathBuf, str::FromStr};
+use std::{
+    borrow::Cow,
+    env, fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex, RwLock},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+lazy_static! {
+    /// Levels of the Amethyst logger, once it has been started.
+    static ref ACTIVE_LEVELS: Mutex<Option<LogLevels>> = Mutex::new(None);
+}
 
 /// An enum that contains options for logging to the terminal.
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
@@ -16,6 +32,29 @@ pub enum StdoutLog {
     Colored,
 }
 
+/// The layout of the logged lines.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum LogFormat {
+    /// The level and target of the message, e.g. `[INFO][amethyst::app] Initializing`.
+    Compact,
+    /// The UTC time, the thread, the level and the module of the message, e.g.
+    /// `2020-03-14T15:09:26.535Z [main][INFO][amethyst::app] Initializing`.
+    Full,
+}
+
+/// Size based rotation of the log file.
+///
+/// Once the log file would grow past `max_file_size_mb` megabytes, it is renamed with a `.1`
+/// suffix, shifting the older files to `.2`, `.3` and so on, and a new file is started. Only the
+/// `max_files` most recent files are kept, including the one being written.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct LogFileRotation {
+    /// Size in megabytes after which a new file is started.
+    pub max_file_size_mb: u64,
+    /// Number of files kept, including the one being written.
+    pub max_files: usize,
+}
+
 /// Logger configuration object.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(default)]
@@ -26,13 +65,18 @@ pub struct LoggerConfig {
     pub level_filter: LevelFilter,
     /// If set, enables logging to file at the given path.
     pub log_file: Option<PathBuf>,
+    /// If set, rotates the log file once it reaches a given size.
+    pub log_file_rotation: Option<LogFileRotation>,
+    /// The layout of the logged lines. Ignored by `Logger::from_config_formatter`.
+    pub format: LogFormat,
     /// If set, allows the config values to be overriden via the corresponding environmental variables.
     pub allow_env_override: bool,
     /// Sets a different level for gfx_backend if Some
     pub log_gfx_backend_level: Option<LevelFilter>,
     /// Sets a different level for gfx_rendy if Some
     pub log_gfx_rendy_level: Option<LevelFilter>,
-    /// Sets the levels for specific modules.
+    /// Sets the levels for specific modules, e.g. `("amethyst_rendy", Trace)`. A level applies
+    /// to the module and all its submodules, and the most specific module wins.
     pub module_levels: Vec<(String, LevelFilter)>,
 }
 
@@ -42,6 +86,8 @@ impl Default for LoggerConfig {
             stdout: StdoutLog::Colored,
             level_filter: LevelFilter::Info,
             log_file: None,
+            log_file_rotation: None,
+            format: LogFormat::Compact,
             allow_env_override: true,
             log_gfx_backend_level: Some(LevelFilter::Warn),
             log_gfx_rendy_level: Some(LevelFilter::Warn),
@@ -72,19 +118,31 @@ impl Default for LoggerConfig {
 #[allow(missing_debug_implementations)]
 pub struct Logger {
     dispatch: fern::Dispatch,
+    levels: LogLevels,
 }
 
 impl Logger {
-    fn new() -> Self {
-        let dispatch = fern::Dispatch::new().format(|out, message, record| {
-            out.finish(format_args!(
-                "[{level}][{target}] {message}",
-                level = record.level(),
-                target = record.target(),
-                message = message,
-            ))
-        });
-        Self { dispatch }
+    fn new(format: LogFormat) -> Self {
+        match format {
+            LogFormat::Compact => Logger::new_formatter(|out, message, record| {
+                out.finish(format_args!(
+                    "[{level}][{target}] {message}",
+                    level = record.level(),
+                    target = record.target(),
+                    message = message,
+                ))
+            }),
+            LogFormat::Full => Logger::new_formatter(|out, message, record| {
+                out.finish(format_args!(
+                    "{time} [{thread}][{level}][{module}] {message}",
+                    time = format_timestamp(SystemTime::now()),
+                    thread = thread::current().name().unwrap_or("<unnamed>"),
+                    level = record.level(),
+                    module = record.module_path().unwrap_or_else(|| record.target()),
+                    message = message,
+                ))
+            }),
+        }
     }
 
     /// Create a new Logger with a passed in formatter callback
@@ -95,8 +153,14 @@ impl Logger {
             + Send
             + 'static,
     {
-        let dispatch = fern::Dispatch::new().format(formatter);
-        Self { dispatch }
+        let levels = LogLevels::new(LevelFilter::Info);
+        let filter_levels = levels.clone();
+        // The levels are checked by the filter so they can be changed after the logger started.
+        let dispatch = fern::Dispatch::new()
+            .format(formatter)
+            .level(LevelFilter::Trace)
+            .filter(move |metadata| filter_levels.enabled(metadata.target(), metadata.level()));
+        Self { dispatch, levels }
     }
 
     /// Create a new logger from [`LoggerConfig`] and the Logger it will be added to
@@ -105,7 +169,36 @@ impl Logger {
             env_var_override(&mut config);
         }
 
-        logger.dispatch = logger.dispatch.level(config.level_filter);
+        {
+            let mut levels = logger.levels.levels.write().unwrap();
+            levels.default = config.level_filter;
+
+            let log_gfx_backend_level = config.log_gfx_backend_level.unwrap_or(LevelFilter::Warn);
+            for module in &[
+                "gfx_backend_empty",
+                "gfx_backend_vulkan",
+                "gfx_backend_dx12",
+                "gfx_backend_metal",
+            ] {
+                levels.set(module.to_string(), log_gfx_backend_level);
+            }
+
+            let log_gfx_rendy_level = config.log_gfx_rendy_level.unwrap_or(LevelFilter::Warn);
+            for module in &[
+                "rendy_factory::factory",
+                "rendy_memory::allocator::dynamic",
+                "rendy_graph::node::render::pass",
+                "rendy_graph::graph",
+                "rendy_memory::allocator::linear",
+                "rendy_wsi",
+            ] {
+                levels.set(module.to_string(), log_gfx_rendy_level);
+            }
+
+            for (module, level) in config.module_levels.into_iter() {
+                levels.set(module, level);
+            }
+        }
 
         match config.stdout {
             StdoutLog::Plain => logger.dispatch = logger.dispatch.chain(io::stdout()),
@@ -117,48 +210,17 @@ impl Logger {
             StdoutLog::Off => {}
         }
 
-        if let Some(log_gfx_backend_level) = config.log_gfx_backend_level {
-            logger.dispatch = logger
-                .dispatch
-                .level_for("gfx_backend_empty", log_gfx_backend_level)
-                .level_for("gfx_backend_vulkan", log_gfx_backend_level)
-                .level_for("gfx_backend_dx12", log_gfx_backend_level)
-                .level_for("gfx_backend_metal", log_gfx_backend_level)
-        } else {
-            logger.dispatch = logger
-                .dispatch
-                .level_for("gfx_backend_empty", LevelFilter::Warn)
-                .level_for("gfx_backend_vulkan", LevelFilter::Warn)
-                .level_for("gfx_backend_dx12", LevelFilter::Warn)
-                .level_for("gfx_backend_metal", LevelFilter::Warn)
-        }
-
-        if let Some(log_gfx_rendy_level) = config.log_gfx_rendy_level {
-            logger.dispatch = logger
-                .dispatch
-                .level_for("rendy_factory::factory", log_gfx_rendy_level)
-                .level_for("rendy_memory::allocator::dynamic", log_gfx_rendy_level)
-                .level_for("rendy_graph::node::render::pass", log_gfx_rendy_level)
-                .level_for("rendy_graph::graph", log_gfx_rendy_level)
-                .level_for("rendy_memory::allocator::linear", log_gfx_rendy_level)
-                .level_for("rendy_wsi", log_gfx_rendy_level);
-        } else {
-            logger.dispatch = logger
-                .dispatch
-                .level_for("rendy_factory::factory", LevelFilter::Warn)
-                .level_for("rendy_memory::allocator::dynamic", LevelFilter::Warn)
-                .level_for("rendy_graph::node::render::pass", LevelFilter::Warn)
-                .level_for("rendy_graph::graph", LevelFilter::Warn)
-                .level_for("rendy_memory::allocator::linear", LevelFilter::Warn)
-                .level_for("rendy_wsi", LevelFilter::Warn);
-        }
-
-        for (module, level) in config.module_levels.into_iter() {
-            logger.dispatch = logger.dispatch.level_for(Cow::Owned(module), level);
-        }
-
         if let Some(path) = config.log_file {
-            if let Ok(log_file) = fern::log_file(path) {
+            let log_file: io::Result<fern::Output> = match config.log_file_rotation {
+                Some(rotation) => RotatingFile::open(
+                    path,
+                    rotation.max_file_size_mb.saturating_mul(1024 * 1024),
+                    rotation.max_files,
+                )
+                .map(|file| (Box::new(file) as Box<dyn Write + Send>).into()),
+                None => fern::log_file(path).map(Into::into),
+            };
+            if let Ok(log_file) = log_file {
                 logger.dispatch = logger.dispatch.chain(log_file)
             } else {
                 eprintln!("Unable to access the log file, as such it will not be used")
@@ -170,7 +232,8 @@ impl Logger {
 
     /// Create a new Logger from [`LoggerConfig`]
     pub fn from_config(config: LoggerConfig) -> Self {
-        Logger::new_with_config(config, Logger::new())
+        let format = config.format;
+        Logger::new_with_config(config, Logger::new(format))
     }
 
     /// Create a new Logger from [`LoggerConfig`] and a formatter
@@ -185,17 +248,250 @@ impl Logger {
     }
 
     /// Set individual log levels for modules.
-    pub fn level_for<T: Into<Cow<'static, str>>>(mut self, module: T, level: LevelFilter) -> Self {
-        self.dispatch = self.dispatch.level_for(module, level);
+    pub fn level_for<T: Into<Cow<'static, str>>>(self, module: T, level: LevelFilter) -> Self {
+        self.levels
+            .levels
+            .write()
+            .unwrap()
+            .set(module.into().into_owned(), level);
         self
     }
 
     /// Starts [`Logger`] by consuming it.
+    ///
+    /// The levels can then be changed through [`LogLevels`], which the `Application` adds as a
+    /// resource.
     pub fn start(self) {
-        self.dispatch.apply().unwrap_or_else(|_| {
-            debug!("Global logger already set, default Amethyst logger will not be used")
-        });
+        match self.dispatch.apply() {
+            Ok(()) => {
+                self.levels.update_max_level();
+                *ACTIVE_LEVELS.lock().unwrap() = Some(self.levels);
+            }
+            Err(_) => {
+                debug!("Global logger already set, default Amethyst logger will not be used")
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Levels {
+    default: LevelFilter,
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl Levels {
+    fn set(&mut self, module: String, level: LevelFilter) {
+        match self.modules.iter_mut().find(|(m, _)| *m == module) {
+            Some(entry) => entry.1 = level,
+            None => self.modules.push((module, level)),
+        }
+    }
+
+    /// Returns the level of the most specific module containing `target`.
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .filter(|(module, _)| {
+                target.starts_with(module.as_str())
+                    && (target.len() == module.len() || target[module.len()..].starts_with("::"))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, std::cmp::max)
+    }
+}
+
+/// Resource changing the levels of the Amethyst logger while the game runs, e.g. to toggle
+/// verbose rendering logs with a debug key.
+///
+/// Clones share the same levels. The `Application` adds it as a resource when the logger has
+/// been started with [`Logger::start`] or [`start_logger`].
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let levels = world.read_resource::<LogLevels>();
+/// if levels.level_for("amethyst_rendy") == LogLevelFilter::Trace {
+///     levels.reset_level_for("amethyst_rendy");
+/// } else {
+///     levels.set_level_for("amethyst_rendy", LogLevelFilter::Trace);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct LogLevels {
+    levels: Arc<RwLock<Levels>>,
+}
+
+impl LogLevels {
+    fn new(default: LevelFilter) -> Self {
+        LogLevels {
+            levels: Arc::new(RwLock::new(Levels {
+                default,
+                modules: Vec::new(),
+            })),
+        }
+    }
+
+    /// Returns the levels of the Amethyst logger, if it has been started.
+    pub fn active() -> Option<LogLevels> {
+        ACTIVE_LEVELS.lock().unwrap().clone()
     }
+
+    /// Returns the level of the modules without a level of their own.
+    pub fn level(&self) -> LevelFilter {
+        self.levels.read().unwrap().default
+    }
+
+    /// Sets the level of the modules without a level of their own.
+    pub fn set_level(&self, level: LevelFilter) {
+        self.levels.write().unwrap().default = level;
+        self.update_max_level();
+    }
+
+    /// Returns the level applying to `module`, which is the level of the module itself or of
+    /// its closest parent having one.
+    pub fn level_for(&self, module: &str) -> LevelFilter {
+        self.levels.read().unwrap().level_for(module)
+    }
+
+    /// Sets the level of `module` and its submodules.
+    pub fn set_level_for<T: Into<String>>(&self, module: T, level: LevelFilter) {
+        self.levels.write().unwrap().set(module.into(), level);
+        self.update_max_level();
+    }
+
+    /// Removes the level of `module`, so it uses the level of its parent again.
+    pub fn reset_level_for(&self, module: &str) {
+        self.levels
+            .write()
+            .unwrap()
+            .modules
+            .retain(|(m, _)| m != module);
+        self.update_max_level();
+    }
+
+    fn enabled(&self, target: &str, level: log::Level) -> bool {
+        level <= self.levels.read().unwrap().level_for(target)
+    }
+
+    fn update_max_level(&self) {
+        log::set_max_level(self.levels.read().unwrap().max_level());
+    }
+}
+
+/// Log file starting a new file once it reaches its maximum size, see [`LogFileRotation`].
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    max_files: usize,
+    /// Whether the next write starts a new line, as files are only rotated between lines.
+    at_line_start: bool,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, max_size: u64, max_files: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(RotatingFile {
+            path,
+            file,
+            size,
+            max_size,
+            max_files,
+            at_line_start: true,
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        path.into()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.max_files > 1 {
+            let oldest = self.rotated_path(self.max_files - 1);
+            if oldest.exists() {
+                fs::remove_file(oldest)?;
+            }
+            for index in (1..self.max_files - 1).rev() {
+                let rotated = self.rotated_path(index);
+                if rotated.exists() {
+                    fs::rename(rotated, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.at_line_start && self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        if written > 0 {
+            self.at_line_start = buf[written - 1] == b'\n';
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Formats a time as an ISO 8601 UTC timestamp with milliseconds.
+fn format_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (days, seconds_of_day) = (seconds / 86_400, seconds % 86_400);
+
+    // Civil date from the days since 1970-01-01, in eras of 400 years starting on March 1st.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = era * 400 + year_of_era + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60,
+        since_epoch.subsec_millis(),
+    )
 }
 
 /// Starts a basic logger outputting to stdout with color on supported platforms, and/or to file.
@@ -267,4 +563,58 @@ mod tests {
 
         assert_eq!(config.stdout, StdoutLog::Plain);
     }
+
+    #[test]
+    fn most_specific_module_level_applies() {
+        let levels = LogLevels::new(LevelFilter::Info);
+        levels.set_level_for("amethyst_rendy", LevelFilter::Trace);
+        levels.set_level_for("amethyst_rendy::pass", LevelFilter::Error);
+
+        assert_eq!(
+            LevelFilter::Trace,
+            levels.level_for("amethyst_rendy::types")
+        );
+        assert_eq!(
+            LevelFilter::Error,
+            levels.level_for("amethyst_rendy::pass::flat")
+        );
+        assert_eq!(LevelFilter::Info, levels.level_for("amethyst_rendy_extra"));
+        assert!(levels.enabled("amethyst_rendy", log::Level::Trace));
+        assert!(!levels.enabled("amethyst_core", log::Level::Debug));
+
+        levels.reset_level_for("amethyst_rendy");
+        assert_eq!(LevelFilter::Info, levels.level_for("amethyst_rendy::types"));
+    }
+
+    #[test]
+    fn log_file_rotates_between_lines() {
+        let dir = env::temp_dir().join(format!("amethyst_log_rotation_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("game.log");
+        let mut file = RotatingFile::open(path.clone(), 10, 3).unwrap();
+        for line in &["first", "second", "third", "fourth"] {
+            file.write_all(line.as_bytes()).unwrap();
+            file.write_all(b"\n").unwrap();
+        }
+        file.flush().unwrap();
+
+        let read = |suffix: &str| {
+            let mut name = path.clone().into_os_string();
+            name.push(suffix);
+            fs::read_to_string(PathBuf::from(name)).ok()
+        };
+        assert_eq!(Some("fourth\n".to_string()), read(""));
+        assert_eq!(Some("third\n".to_string()), read(".1"));
+        assert_eq!(Some("second\n".to_string()), read(".2"));
+        assert_eq!(None, read(".3"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn timestamps_are_utc() {
+        let time = UNIX_EPOCH + std::time::Duration::from_millis(951_782_400_005);
+        assert_eq!("2000-02-29T00:00:00.005Z", format_timestamp(time));
+        let time = UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        assert_eq!("2023-11-14T22:13:20.000Z", format_timestamp(time));
+    }
 }
@@ -1,7 +1,7 @@
 //! Contains common types that can be glob-imported (`*`) for convenience.
 
 pub use crate::{
-    app::{Application, ApplicationBuilder, CoreApplication},
+    app::{Application, ApplicationBuilder, CoreApplication, HeadlessPacing},
     callback_queue::{Callback, CallbackQueue},
     config::Config,
     core::{SystemDesc, SystemExt, WithNamed},
@@ -9,6 +9,8 @@ pub use crate::{
     game_data::{DataInit, GameData, GameDataBuilder},
     state::{
         EmptyState, EmptyTrans, SimpleState, SimpleTrans, State, StateData, Trans, TransEvent,
+        TransQueue,
     },
     state_event::StateEvent,
+    state_scope::CreateScopedEntity,
 };
@@ -4,7 +4,16 @@ use amethyst_input::is_close_requested;
 
 use derivative::Derivative;
 
-use crate::{ecs::World, GameData, StateEvent};
+use crate::{
+    ecs::{
+        shred::{ResourceId, SystemData},
+        EntityBuilder, World, Write,
+    },
+    lifecycle::{ApplicationLifecycle, ExitReason},
+    shrev::EventChannel,
+    state_scope::{self, CreateScopedEntity, StateScope},
+    GameData, StateEvent,
+};
 
 use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
 
@@ -45,6 +54,11 @@ where
     pub fn new(world: &'a mut World, data: &'a mut T) -> Self {
         StateData { world, data }
     }
+
+    /// Creates an entity deleted when the active state stops, see `CreateScopedEntity`.
+    pub fn create_scoped_entity(&mut self) -> EntityBuilder<'_> {
+        self.world.create_scoped_entity()
+    }
 }
 
 /// Types of state transitions.
@@ -64,11 +78,56 @@ pub enum Trans<T, E> {
     Replace(Box<dyn State<T, E>>),
     /// Remove all states on the stack and insert new stack.
     NewStack(Vec<Box<dyn State<T, E>>>),
-    /// Execute a series of Trans's.
+    /// Execute a series of Trans's, in order and within the same update.
+    ///
+    /// Each transition is complete, including the `on_start` of pushed states, before the next
+    /// one is applied. Once a transition stops the state machine, like `Quit` or popping the last
+    /// state, the rest of the sequence is discarded.
     Sequence(Vec<Trans<T, E>>),
     /// Stop and remove all states and shut down the engine.
+    ///
+    /// In an `Application`, this requests the application to exit, see `ApplicationLifecycle`,
+    /// and the states are stopped at the end of the next frame.
     Quit,
 }
+impl<T, E> Trans<T, E> {
+    /// Returns true if this is `Trans::None`, or a sequence of them.
+    pub fn is_none(&self) -> bool {
+        match self {
+            Trans::None => true,
+            Trans::Sequence(sequence) => sequence.iter().all(Trans::is_none),
+            _ => false,
+        }
+    }
+
+    /// Combines two transitions into one applying `self`, then `next`.
+    ///
+    /// `Trans::None` is dropped and sequences are flattened, so merging the transitions of
+    /// several sources always gives the same flat sequence, or a single transition.
+    pub fn then(self, next: Trans<T, E>) -> Trans<T, E> {
+        let mut sequence = Vec::new();
+        self.flatten_into(&mut sequence);
+        next.flatten_into(&mut sequence);
+        match sequence.len() {
+            0 => Trans::None,
+            1 => sequence.pop().unwrap(),
+            _ => Trans::Sequence(sequence),
+        }
+    }
+
+    fn flatten_into(self, sequence: &mut Vec<Trans<T, E>>) {
+        match self {
+            Trans::None => (),
+            Trans::Sequence(inner) => {
+                for trans in inner {
+                    trans.flatten_into(sequence);
+                }
+            }
+            trans => sequence.push(trans),
+        }
+    }
+}
+
 impl<T, E> Debug for Trans<T, E> {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match self {
@@ -90,9 +149,89 @@ impl<T, E> Debug for Trans<T, E> {
 /// world.write_resource::<EventChannel<TransEvent<MyGameData, StateEvent>>>().single_write(Box::new(|| Trans::Quit));
 /// ```
 ///
-/// Transitions will be executed sequentially by Amethyst's `CoreApplication` update loop.
+/// The events written during a frame are applied at the start of the next one, after the
+/// dispatcher ran, in the order they were written. They are merged with `Trans::then` and follow
+/// the rules of `Trans::Sequence`.
+///
+/// Systems can use the `TransQueue` system data instead of the channel.
 pub type TransEvent<T, E> = Box<dyn Fn() -> Trans<T, E> + Send + Sync + 'static>;
 
+/// `SystemData` queueing state transitions from a system.
+///
+/// The default type parameters match `SimpleState`, so most systems can use `TransQueue<'s>`.
+///
+/// # Example
+///
+/// ```
+/// use amethyst::{ecs::prelude::*, prelude::*, TransQueue};
+///
+/// struct EndLevelSystem;
+///
+/// impl<'s> System<'s> for EndLevelSystem {
+///     type SystemData = TransQueue<'s>;
+///
+///     fn run(&mut self, mut queue: Self::SystemData) {
+///         queue.push(|| Trans::Pop);
+///     }
+/// }
+/// ```
+#[allow(missing_debug_implementations)]
+pub struct TransQueue<'a, T = GameData<'static, 'static>, E = StateEvent>
+where
+    T: 'static,
+    E: Send + Sync + 'static,
+{
+    channel: Write<'a, EventChannel<TransEvent<T, E>>>,
+}
+
+impl<'a, T, E> TransQueue<'a, T, E>
+where
+    T: 'static,
+    E: Send + Sync + 'static,
+{
+    /// Queues the transition returned by `trans`.
+    pub fn push<F>(&mut self, trans: F)
+    where
+        F: Fn() -> Trans<T, E> + Send + Sync + 'static,
+    {
+        self.channel.single_write(Box::new(trans));
+    }
+
+    /// Queues a `Trans::Pop`.
+    pub fn pop(&mut self) {
+        self.push(|| Trans::Pop);
+    }
+
+    /// Queues a `Trans::Quit`.
+    pub fn quit(&mut self) {
+        self.push(|| Trans::Quit);
+    }
+}
+
+impl<'a, T, E> SystemData<'a> for TransQueue<'a, T, E>
+where
+    T: 'static,
+    E: Send + Sync + 'static,
+{
+    fn setup(world: &mut World) {
+        <Write<'a, EventChannel<TransEvent<T, E>>> as SystemData<'a>>::setup(world);
+    }
+
+    fn fetch(world: &'a World) -> Self {
+        TransQueue {
+            channel: <Write<'a, EventChannel<TransEvent<T, E>>> as SystemData<'a>>::fetch(world),
+        }
+    }
+
+    fn reads() -> Vec<ResourceId> {
+        Vec::new()
+    }
+
+    fn writes() -> Vec<ResourceId> {
+        <Write<'a, EventChannel<TransEvent<T, E>>> as SystemData<'a>>::writes()
+    }
+}
+
 /// An empty `Trans`. Made to be used with `EmptyState`.
 pub type EmptyTrans = Trans<(), StateEvent>;
 
@@ -142,6 +281,11 @@ pub trait State<T, E: Send + Sync + 'static> {
     /// even when this is not the active state,
     /// as long as this state is on the [StateMachine](struct.StateMachine.html)'s state-stack.
     fn shadow_update(&mut self, _data: StateData<'_, T>) {}
+    /// Whether the entities created with `create_scoped_entity` while this state was active are
+    /// deleted when it stops. Pausing the state never deletes them.
+    fn delete_scoped_entities(&self) -> bool {
+        true
+    }
 }
 
 /// An empty `State` trait. It contains no `StateData` or custom `StateEvent`.
@@ -192,6 +336,11 @@ pub trait EmptyState {
     /// even when this is not the active state,
     /// as long as this state is on the [StateMachine](struct.StateMachine.html)'s state-stack.
     fn shadow_update(&mut self, _data: StateData<'_, ()>) {}
+    /// Whether the entities created with `create_scoped_entity` while this state was active are
+    /// deleted when it stops. Pausing the state never deletes them.
+    fn delete_scoped_entities(&self) -> bool {
+        true
+    }
 }
 
 impl<T: EmptyState> State<(), StateEvent> for T {
@@ -245,6 +394,11 @@ impl<T: EmptyState> State<(), StateEvent> for T {
     fn shadow_update(&mut self, data: StateData<'_, ()>) {
         self.shadow_update(data);
     }
+    /// Whether the entities created with `create_scoped_entity` while this state was active are
+    /// deleted when it stops. Pausing the state never deletes them.
+    fn delete_scoped_entities(&self) -> bool {
+        self.delete_scoped_entities()
+    }
 }
 
 /// A simple `State` trait. It contains `GameData` as its `StateData` and no custom `StateEvent`.
@@ -299,6 +453,11 @@ pub trait SimpleState {
     /// even when this is not the active state,
     /// as long as this state is on the [StateMachine](struct.StateMachine.html)'s state-stack.
     fn shadow_update(&mut self, _data: StateData<'_, GameData<'_, '_>>) {}
+    /// Whether the entities created with `create_scoped_entity` while this state was active are
+    /// deleted when it stops. Pausing the state never deletes them.
+    fn delete_scoped_entities(&self) -> bool {
+        true
+    }
 }
 
 impl<T: SimpleState> State<GameData<'static, 'static>, StateEvent> for T {
@@ -360,15 +519,24 @@ impl<T: SimpleState> State<GameData<'static, 'static>, StateEvent> for T {
     fn shadow_update(&mut self, data: StateData<'_, GameData<'_, '_>>) {
         self.shadow_update(data);
     }
+    /// Whether the entities created with `create_scoped_entity` while this state was active are
+    /// deleted when it stops. Pausing the state never deletes them.
+    fn delete_scoped_entities(&self) -> bool {
+        self.delete_scoped_entities()
+    }
 }
 
 /// A simple stack-based state machine (pushdown automaton).
+///
+/// Every state started gets a new `StateScope`, see `CreateScopedEntity`.
 #[derive(Derivative)]
 #[derivative(Debug)]
 pub struct StateMachine<'a, T, E> {
     running: bool,
     #[derivative(Debug = "ignore")]
     state_stack: Vec<Box<dyn State<T, E> + 'a>>,
+    scopes: Vec<StateScope>,
+    next_scope: u64,
 }
 
 impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
@@ -377,6 +545,8 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
         StateMachine {
             running: false,
             state_stack: vec![Box::new(initial_state)],
+            scopes: vec![StateScope::new(0)],
+            next_scope: 1,
         }
     }
 
@@ -388,11 +558,14 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
     /// Initializes the state machine.
     pub fn start(&mut self, data: StateData<'_, T>) -> Result<(), StateError> {
         if !self.running {
+            let StateData { world, data } = data;
             let state = self
                 .state_stack
                 .last_mut()
                 .ok_or(StateError::NoStatesPresent)?;
-            state.on_start(data);
+            state_scope::setup(world);
+            state_scope::set_active_scope(world, self.scopes.last().cloned());
+            state.on_start(StateData { world, data });
             self.running = true;
         }
         Ok(())
@@ -478,14 +651,21 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
                 Trans::NewStack(states) => self.new_stack(states, data),
                 Trans::Sequence(sequence) => {
                     for trans in sequence {
+                        if !self.running {
+                            break;
+                        }
+                        let quit = if let Trans::Quit = trans { true } else { false };
                         let temp_data = StateData {
                             world: data.world,
                             data: data.data,
                         };
                         self.transition(trans, temp_data);
+                        if quit {
+                            break;
+                        }
                     }
                 }
-                Trans::Quit => self.stop(data),
+                Trans::Quit => self.quit(data),
             }
         }
     }
@@ -494,15 +674,8 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
     fn switch(&mut self, state: Box<dyn State<T, E>>, data: StateData<'_, T>) {
         if self.running {
             let StateData { world, data } = data;
-            if let Some(mut state) = self.state_stack.pop() {
-                state.on_stop(StateData { world, data });
-            }
-
-            self.state_stack.push(state);
-
-            //State was just pushed, thus pop will always succeed
-            let new_state = self.state_stack.last_mut().unwrap();
-            new_state.on_start(StateData { world, data });
+            self.stop_active(world, data);
+            self.start_new(state, world, data);
         }
     }
 
@@ -514,11 +687,7 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
                 state.on_pause(StateData { world, data });
             }
 
-            self.state_stack.push(state);
-
-            //State was just pushed, thus pop will always succeed
-            let new_state = self.state_stack.last_mut().unwrap();
-            new_state.on_start(StateData { world, data });
+            self.start_new(state, world, data);
         }
     }
 
@@ -527,9 +696,7 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
     fn pop(&mut self, data: StateData<'_, T>) {
         if self.running {
             let StateData { world, data } = data;
-            if let Some(mut state) = self.state_stack.pop() {
-                state.on_stop(StateData { world, data });
-            }
+            self.stop_active(world, data);
 
             if let Some(state) = self.state_stack.last_mut() {
                 state.on_resume(StateData { world, data });
@@ -544,16 +711,10 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
         if self.running {
             //Pemove all current states
             let StateData { world, data } = data;
-            while let Some(mut state) = self.state_stack.pop() {
-                state.on_stop(StateData { world, data });
-            }
+            while self.stop_active(world, data) {}
 
             //Push the new state
-            self.state_stack.push(state);
-
-            //State was just pushed, thus pop will always succeed
-            let new_state = self.state_stack.last_mut().unwrap();
-            new_state.on_start(StateData { world, data });
+            self.start_new(state, world, data);
         }
     }
 
@@ -562,37 +723,78 @@ impl<'a, T, E: Send + Sync + 'static> StateMachine<'a, T, E> {
         if self.running {
             //remove all current states
             let StateData { world, data } = data;
-            while let Some(mut state) = self.state_stack.pop() {
-                state.on_stop(StateData { world, data });
-            }
+            while self.stop_active(world, data) {}
 
             //push the new states
             let state_count = states.len();
             for (count, state) in states.into_iter().enumerate() {
-                self.state_stack.push(state);
-
-                //State was just pushed, thus pop will always succeed
-                let new_state = self.state_stack.last_mut().unwrap();
-                new_state.on_start(StateData { world, data });
+                self.start_new(state, world, data);
                 if count != state_count - 1 {
                     //pause on each state but the last
+                    //State was just pushed, thus last will always succeed
+                    let new_state = self.state_stack.last_mut().unwrap();
                     new_state.on_pause(StateData { world, data });
                 }
             }
         }
     }
 
+    /// Requests the application to exit through the `ApplicationLifecycle` resource, or shuts
+    /// the state machine down right away if there is none.
+    fn quit(&mut self, data: StateData<'_, T>) {
+        let requested = data
+            .world
+            .try_fetch_mut::<ApplicationLifecycle>()
+            .map(|mut lifecycle| lifecycle.request_exit(ExitReason::Quit))
+            .is_some();
+        if !requested {
+            self.stop(data);
+        }
+    }
+
     /// Shuts the state machine down.
     pub(crate) fn stop(&mut self, data: StateData<'_, T>) {
         if self.running {
             let StateData { world, data } = data;
-            while let Some(mut state) = self.state_stack.pop() {
-                state.on_stop(StateData { world, data });
-            }
+            while self.stop_active(world, data) {}
 
             self.running = false;
         }
     }
+
+    /// Pushes a state in a new scope and starts it.
+    fn start_new(&mut self, state: Box<dyn State<T, E>>, world: &mut World, data: &mut T) {
+        let scope = StateScope::new(self.next_scope);
+        self.next_scope += 1;
+        self.state_stack.push(state);
+        self.scopes.push(scope);
+        state_scope::set_active_scope(world, Some(scope));
+
+        //State was just pushed, thus last will always succeed
+        let new_state = self.state_stack.last_mut().unwrap();
+        new_state.on_start(StateData { world, data });
+    }
+
+    /// Stops and removes the active state, deleting the entities of its scope.
+    ///
+    /// Returns false if there was no state to stop.
+    fn stop_active(&mut self, world: &mut World, data: &mut T) -> bool {
+        let mut state = match self.state_stack.pop() {
+            Some(state) => state,
+            None => return false,
+        };
+        state.on_stop(StateData { world, data });
+
+        let scope = self
+            .scopes
+            .pop()
+            .expect("Unreachable: Every state has a scope");
+        if state.delete_scoped_entities() {
+            state_scope::delete_scope(world, scope);
+        }
+        state_scope::set_active_scope(world, self.scopes.last().cloned());
+        true
+    }
 }
 
 #[cfg(test)]
@@ -724,4 +926,284 @@ mod tests {
         sm.update(StateData::new(&mut world, &mut ()));
         assert_eq!(sm.state_stack.len(), 1);
     }
+
+    type Log = std::rc::Rc<std::cell::RefCell<Vec<String>>>;
+
+    struct LoggedState {
+        name: &'static str,
+        log: Log,
+    }
+
+    impl LoggedState {
+        fn new(name: &'static str, log: &Log) -> Self {
+            LoggedState {
+                name,
+                log: log.clone(),
+            }
+        }
+    }
+
+    impl State<(), ()> for LoggedState {
+        fn on_start(&mut self, _: StateData<'_, ()>) {
+            self.log.borrow_mut().push(format!("start {}", self.name));
+        }
+
+        fn on_stop(&mut self, _: StateData<'_, ()>) {
+            self.log.borrow_mut().push(format!("stop {}", self.name));
+        }
+
+        fn on_pause(&mut self, _: StateData<'_, ()>) {
+            self.log.borrow_mut().push(format!("pause {}", self.name));
+        }
+
+        fn on_resume(&mut self, _: StateData<'_, ()>) {
+            self.log.borrow_mut().push(format!("resume {}", self.name));
+        }
+    }
+
+    #[test]
+    fn sequence_order() {
+        use crate::ecs::prelude::WorldExt;
+
+        let mut world = World::new();
+        let log = Log::default();
+
+        let mut sm = StateMachine::new(LoggedState::new("menu", &log));
+        sm.start(StateData::new(&mut world, &mut ())).unwrap();
+        sm.transition(
+            Trans::Push(Box::new(LoggedState::new("pause", &log))),
+            StateData::new(&mut world, &mut ()),
+        );
+        log.borrow_mut().clear();
+
+        sm.transition(
+            Trans::Sequence(vec![
+                Trans::Pop,
+                Trans::Push(Box::new(LoggedState::new("results", &log))),
+                Trans::Switch(Box::new(LoggedState::new("credits", &log))),
+            ]),
+            StateData::new(&mut world, &mut ()),
+        );
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                "stop pause",
+                "resume menu",
+                "pause menu",
+                "start results",
+                "stop results",
+                "start credits",
+            ]
+        );
+        assert_eq!(sm.state_stack.len(), 2);
+    }
+
+    #[test]
+    fn sequence_stops_after_quit() {
+        use crate::ecs::prelude::WorldExt;
+
+        let mut world = World::new();
+        let log = Log::default();
+
+        let mut sm = StateMachine::new(LoggedState::new("menu", &log));
+        sm.start(StateData::new(&mut world, &mut ())).unwrap();
+        sm.transition(
+            Trans::Sequence(vec![
+                Trans::Pop,
+                Trans::Push(Box::new(LoggedState::new("game", &log))),
+            ]),
+            StateData::new(&mut world, &mut ()),
+        );
+        assert!(!sm.is_running());
+        assert_eq!(*log.borrow(), vec!["start menu", "stop menu"]);
+        assert!(sm.state_stack.is_empty());
+
+        let mut sm = StateMachine::new(State0);
+        sm.start(StateData::new(&mut world, &mut ())).unwrap();
+        sm.transition(
+            Trans::Sequence(vec![Trans::Quit, Trans::Push(Box::new(State0))]),
+            StateData::new(&mut world, &mut ()),
+        );
+        assert!(!sm.is_running());
+        assert!(sm.state_stack.is_empty());
+    }
+
+    #[test]
+    fn trans_then() {
+        let trans = Trans::<(), ()>::None.then(Trans::None);
+        assert!(trans.is_none());
+        assert!(Trans::<(), ()>::Sequence(vec![Trans::None]).is_none());
+
+        match Trans::<(), ()>::None.then(Trans::Pop) {
+            Trans::Pop => (),
+            trans => panic!("expected Pop, got {:?}", trans),
+        }
+
+        let merged = Trans::<(), ()>::Sequence(vec![Trans::Pop, Trans::None])
+            .then(Trans::None)
+            .then(Trans::Sequence(vec![
+                Trans::Push(Box::new(State0)),
+                Trans::Sequence(vec![Trans::Quit]),
+            ]));
+        assert_eq!(format!("{:?}", merged), "Sequence [Pop, Push, Quit]");
+    }
+
+    #[test]
+    fn trans_queue() {
+        use crate::{
+            ecs::prelude::{System, WorldExt},
+            shred::RunNow,
+            shrev::EventChannel,
+        };
+
+        struct EndLevelSystem;
+
+        impl<'s> System<'s> for EndLevelSystem {
+            type SystemData = TransQueue<'s, (), ()>;
+
+            fn run(&mut self, mut queue: Self::SystemData) {
+                queue.pop();
+                queue.push(|| Trans::Push(Box::new(State0)));
+                queue.quit();
+            }
+        }
+
+        let mut world = World::new();
+        let mut system = EndLevelSystem;
+        System::setup(&mut system, &mut world);
+        let mut reader = world
+            .write_resource::<EventChannel<TransEvent<(), ()>>>()
+            .register_reader();
+        system.run_now(&world);
+
+        let trans = world
+            .read_resource::<EventChannel<TransEvent<(), ()>>>()
+            .read(&mut reader)
+            .fold(Trans::None, |trans, event| trans.then(event()));
+        assert_eq!(format!("{:?}", trans), "Sequence [Pop, Push, Quit]");
+    }
+
+    struct ScopedState {
+        name: &'static str,
+        keep: bool,
+    }
+
+    impl ScopedState {
+        fn new(name: &'static str) -> Self {
+            ScopedState { name, keep: false }
+        }
+    }
+
+    impl State<(), ()> for ScopedState {
+        fn on_start(&mut self, mut data: StateData<'_, ()>) {
+            use crate::{
+                core::{Parent, WithNamed},
+                ecs::prelude::Builder,
+            };
+
+            let parent = data.create_scoped_entity().named(self.name).build();
+            data.world
+                .create_entity()
+                .with(Parent::new(parent))
+                .named(format!("{}_child", self.name))
+                .build();
+        }
+
+        fn delete_scoped_entities(&self) -> bool {
+            !self.keep
+        }
+    }
+
+    fn names(world: &World) -> Vec<String> {
+        use crate::{
+            core::Named,
+            ecs::prelude::{Join, WorldExt},
+        };
+
+        let mut names = world
+            .read_storage::<Named>()
+            .join()
+            .map(|named| named.name.to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn scoped_entities() {
+        use crate::{
+            core::{Named, Parent, WithNamed},
+            ecs::prelude::{Builder, WorldExt},
+        };
+
+        let mut world = World::new();
+        world.register::<Named>();
+        world.register::<Parent>();
+        world.create_entity().named("global").build();
+
+        let mut sm = StateMachine::new(ScopedState::new("menu"));
+        sm.start(StateData::new(&mut world, &mut ())).unwrap();
+        sm.transition(
+            Trans::Push(Box::new(ScopedState::new("game"))),
+            StateData::new(&mut world, &mut ()),
+        );
+        sm.transition(
+            Trans::Push(Box::new(ScopedState::new("pause"))),
+            StateData::new(&mut world, &mut ()),
+        );
+        assert_eq!(
+            names(&world),
+            vec![
+                "game",
+                "game_child",
+                "global",
+                "menu",
+                "menu_child",
+                "pause",
+                "pause_child"
+            ]
+        );
+
+        // the resumed state keeps its entities
+        sm.transition(Trans::Pop, StateData::new(&mut world, &mut ()));
+        assert_eq!(
+            names(&world),
+            vec!["game", "game_child", "global", "menu", "menu_child"]
+        );
+
+        sm.transition(
+            Trans::Switch(Box::new(ScopedState {
+                name: "kept",
+                keep: true,
+            })),
+            StateData::new(&mut world, &mut ()),
+        );
+        assert_eq!(
+            names(&world),
+            vec!["global", "kept", "kept_child", "menu", "menu_child"]
+        );
+
+        sm.transition(Trans::Pop, StateData::new(&mut world, &mut ()));
+        world.create_scoped_entity().named("menu_late").build();
+        assert_eq!(
+            names(&world),
+            vec![
+                "global",
+                "kept",
+                "kept_child",
+                "menu",
+                "menu_child",
+                "menu_late"
+            ]
+        );
+
+        sm.transition(
+            Trans::Replace(Box::new(ScopedState::new("credits"))),
+            StateData::new(&mut world, &mut ()),
+        );
+        assert_eq!(
+            names(&world),
+            vec!["credits", "credits_child", "global", "kept", "kept_child"]
+        );
+    }
 }
@@ -10,6 +10,7 @@ use crate::{
     derive::EventReader,
     input::{BindingTypes, InputEvent, StringBindings},
     ui::UiEvent,
+    window::ScreenDimensionsChanged,
 };
 
 /// The enum holding the different types of event that can be received in a `State` in the
@@ -27,4 +28,6 @@ where
     Ui(UiEvent),
     /// Events sent by the input system.
     Input(InputEvent<T>),
+    /// Events sent by the window system when the screen is resized.
+    ScreenDimensions(ScreenDimensionsChanged),
 }
@@ -0,0 +1,117 @@
+//! Entities living as long as the state that created them.
+
+use crate::{
+    core::Parent,
+    ecs::prelude::{
+        BitSet, Builder, Component, DenseVecStorage, Entities, Entity, EntityBuilder, Join,
+        ReadStorage, World, WorldExt,
+    },
+};
+
+/// Component tagging an entity with the scope of the state that created it.
+///
+/// The `StateMachine` deletes the entities of a state's scope, along with their children, when
+/// the state is stopped, unless `State::delete_scoped_entities` returns false. Pausing a state
+/// keeps them alive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StateScope(u64);
+
+impl StateScope {
+    pub(crate) fn new(id: u64) -> Self {
+        StateScope(id)
+    }
+
+    /// Returns the id of the scope, unique to every state started by a `StateMachine`.
+    pub fn id(self) -> u64 {
+        self.0
+    }
+}
+
+impl Component for StateScope {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Resource with the scope of the active state, set by the `StateMachine`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ActiveStateScope(Option<StateScope>);
+
+impl ActiveStateScope {
+    /// Returns the scope of the active state, or `None` if no state machine is running.
+    pub fn scope(self) -> Option<StateScope> {
+        self.0
+    }
+}
+
+/// Creates entities in the scope of the active state.
+pub trait CreateScopedEntity {
+    /// Creates an entity deleted when the active state stops.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no state is active.
+    fn create_scoped_entity(&mut self) -> EntityBuilder<'_>;
+}
+
+impl CreateScopedEntity for World {
+    fn create_scoped_entity(&mut self) -> EntityBuilder<'_> {
+        let scope = self
+            .try_fetch::<ActiveStateScope>()
+            .and_then(|active| active.scope())
+            .expect("Tried to create a scoped entity while no state is active");
+        self.create_entity().with(scope)
+    }
+}
+
+/// Registers the scope component, done when the `StateMachine` starts.
+pub(crate) fn setup(world: &mut World) {
+    world.register::<StateScope>();
+}
+
+pub(crate) fn set_active_scope(world: &mut World, scope: Option<StateScope>) {
+    world.insert(ActiveStateScope(scope));
+}
+
+/// Deletes the entities of `scope`, and every entity parented under them.
+pub(crate) fn delete_scope(world: &mut World, scope: StateScope) {
+    let deleted = world.exec(
+        |(entities, scopes, parents): (
+            Entities<'_>,
+            ReadStorage<'_, StateScope>,
+            ReadStorage<'_, Parent>,
+        )| {
+            let mut deleted = BitSet::new();
+            for (entity, _) in (&entities, &scopes)
+                .join()
+                .filter(|(_, entity_scope)| **entity_scope == scope)
+            {
+                deleted.add(entity.id());
+            }
+            if deleted.is_empty() {
+                return Vec::new();
+            }
+
+            // children can be created before their parents, loop until no descendant is left
+            loop {
+                let mut added = false;
+                for (entity, parent) in (&entities, &parents).join() {
+                    if !deleted.contains(entity.id()) && deleted.contains(parent.entity.id()) {
+                        deleted.add(entity.id());
+                        added = true;
+                    }
+                }
+                if !added {
+                    break;
+                }
+            }
+            (&entities, &deleted)
+                .join()
+                .map(|(entity, _)| entity)
+                .collect::<Vec<Entity>>()
+        },
+    );
+    if !deleted.is_empty() {
+        if let Err(e) = world.delete_entities(&deleted) {
+            log::error!("Failed to delete the entities of a state scope: {}", e);
+        }
+    }
+}
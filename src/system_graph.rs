@@ -0,0 +1,411 @@
+//! Validation and export of the systems registered with a `GameDataBuilder`.
+
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::{self, Write},
+};
+
+/// Error found in the dependencies of the systems of a `GameDataBuilder`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DependencyError {
+    /// A system depends on a system that is never added.
+    Missing {
+        /// The system declaring the dependency.
+        system: String,
+        /// Where the system was added, the builder or a bundle.
+        origin: String,
+        /// The missing dependency.
+        dependency: String,
+        /// Registered systems with a name close to the missing one.
+        suggestions: Vec<String>,
+    },
+    /// A system depends on a system that is added after it.
+    AddedLater {
+        /// The system declaring the dependency.
+        system: String,
+        /// The dependency, which has to be added first.
+        dependency: String,
+    },
+    /// Systems depend on each other in a loop.
+    Cycle {
+        /// The systems of the loop, starting and ending with the same system.
+        path: Vec<String>,
+    },
+    /// A bundle failed to add its systems, usually because of a missing dependency.
+    Bundle {
+        /// Type name of the bundle.
+        bundle: String,
+        /// The message of the failure.
+        message: String,
+        /// Registered systems with a name close to the one in the message.
+        suggestions: Vec<String>,
+    },
+}
+
+impl Display for DependencyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            DependencyError::Missing {
+                system,
+                origin,
+                dependency,
+                suggestions,
+            } => {
+                write!(
+                    f,
+                    "System `{}` added by {} depends on `{}`, which is not registered",
+                    system, origin, dependency
+                )?;
+                write_suggestions(f, suggestions)
+            }
+            DependencyError::AddedLater { system, dependency } => write!(
+                f,
+                "System `{}` depends on `{}`, which has to be added before it",
+                system, dependency
+            ),
+            DependencyError::Cycle { path } => {
+                write!(f, "Systems depend on each other: {}", path.join(" -> "))
+            }
+            DependencyError::Bundle {
+                bundle,
+                message,
+                suggestions,
+            } => {
+                write!(
+                    f,
+                    "Bundle `{}` failed to add its systems: {}",
+                    bundle, message
+                )?;
+                write_suggestions(f, suggestions)
+            }
+        }
+    }
+}
+
+impl Error for DependencyError {}
+
+fn write_suggestions(f: &mut Formatter<'_>, suggestions: &[String]) -> FmtResult {
+    if !suggestions.is_empty() {
+        write!(f, ", did you mean `{}`?", suggestions.join("`, `"))?;
+    }
+    Ok(())
+}
+
+/// A dispatcher operation as seen by the graph.
+#[derive(Debug)]
+pub(crate) enum Node {
+    System {
+        name: String,
+        dependencies: Vec<String>,
+    },
+    ThreadLocal {
+        type_name: &'static str,
+    },
+    Barrier,
+    Bundle {
+        type_name: &'static str,
+    },
+}
+
+impl Node {
+    fn is_bundle(&self) -> bool {
+        match self {
+            Node::Bundle { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+/// The systems added to a `GameDataBuilder`, in order.
+///
+/// Systems added by bundles are only known once the bundles are built, so a bundle is a single
+/// node and the dependencies on its systems can not be checked ahead of time.
+#[derive(Debug, Default)]
+pub(crate) struct SystemGraph {
+    nodes: Vec<Node>,
+}
+
+impl SystemGraph {
+    pub(crate) fn push(&mut self, node: Node) {
+        self.nodes.push(node);
+    }
+
+    /// Turns a panic of the dispatcher builder while executing the operation at `index` into an
+    /// error naming what failed.
+    pub(crate) fn failure(&self, index: usize, message: String) -> DependencyError {
+        // the dispatcher builder quotes the name of a missing dependency
+        let quoted = message.split('"').nth(1).map(str::to_string);
+        let suggestions = quoted
+            .as_ref()
+            .map(|name| self.suggestions(name))
+            .unwrap_or_default();
+        match &self.nodes[index] {
+            Node::System { name, .. } => DependencyError::Missing {
+                system: name.clone(),
+                origin: "GameDataBuilder".to_string(),
+                dependency: quoted.unwrap_or(message),
+                suggestions,
+            },
+            Node::Bundle { type_name } | Node::ThreadLocal { type_name } => {
+                DependencyError::Bundle {
+                    bundle: type_name.to_string(),
+                    message,
+                    suggestions,
+                }
+            }
+            Node::Barrier => DependencyError::Bundle {
+                bundle: "barrier".to_string(),
+                message,
+                suggestions,
+            },
+        }
+    }
+
+    fn systems(&self) -> impl Iterator<Item = (usize, &String, &Vec<String>)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, node)| match node {
+                Node::System { name, dependencies } => Some((index, name, dependencies)),
+                _ => None,
+            })
+    }
+
+    fn position(&self, system: &str) -> Option<usize> {
+        self.systems()
+            .find(|(_, name, _)| *name == system)
+            .map(|(index, _, _)| index)
+    }
+
+    /// Returns the known systems with a name close to `name`, closest first.
+    pub(crate) fn suggestions(&self, name: &str) -> Vec<String> {
+        let max_distance = (name.chars().count() / 3).max(1);
+        let mut close = self
+            .systems()
+            .map(|(_, system, _)| (edit_distance(name, system), system))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .collect::<Vec<_>>();
+        close.sort();
+        close
+            .into_iter()
+            .map(|(_, system)| system.clone())
+            .collect()
+    }
+
+    /// Checks the dependencies of the systems added directly to the builder.
+    pub(crate) fn validate(&self) -> Result<(), DependencyError> {
+        if let Some(path) = self.find_cycle() {
+            return Err(DependencyError::Cycle { path });
+        }
+        for (index, name, dependencies) in self.systems() {
+            for dependency in dependencies {
+                match self.position(dependency) {
+                    Some(position) if position < index => (),
+                    Some(_) => {
+                        return Err(DependencyError::AddedLater {
+                            system: name.clone(),
+                            dependency: dependency.clone(),
+                        });
+                    }
+                    // a bundle added earlier may have registered it
+                    None if self.nodes[..index].iter().any(Node::is_bundle) => (),
+                    None => {
+                        return Err(DependencyError::Missing {
+                            system: name.clone(),
+                            origin: "GameDataBuilder".to_string(),
+                            dependency: dependency.clone(),
+                            suggestions: self.suggestions(dependency),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn find_cycle(&self) -> Option<Vec<String>> {
+        fn visit<'s>(
+            graph: &'s SystemGraph,
+            name: &'s str,
+            path: &mut Vec<&'s str>,
+            done: &mut Vec<&'s str>,
+        ) -> Option<Vec<String>> {
+            if let Some(start) = path.iter().position(|visited| *visited == name) {
+                let mut cycle = path[start..]
+                    .iter()
+                    .map(|name| name.to_string())
+                    .collect::<Vec<_>>();
+                cycle.push(name.to_string());
+                return Some(cycle);
+            }
+            if done.contains(&name) {
+                return None;
+            }
+            path.push(name);
+            let dependencies = graph
+                .systems()
+                .find(|(_, system, _)| *system == name)
+                .map(|(_, _, dependencies)| dependencies);
+            for dependency in dependencies.into_iter().flatten() {
+                if let Some(cycle) = visit(graph, dependency, path, done) {
+                    return Some(cycle);
+                }
+            }
+            path.pop();
+            done.push(name);
+            None
+        }
+
+        let mut done = Vec::new();
+        for (_, name, _) in self.systems() {
+            if let Some(cycle) = visit(self, name, &mut Vec::new(), &mut done) {
+                return Some(cycle);
+            }
+        }
+        None
+    }
+
+    /// Writes the graph in the DOT format of Graphviz.
+    pub(crate) fn write_dot<W: Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "digraph systems {{")?;
+        let mut previous_barrier: Option<usize> = None;
+        let mut since_barrier = Vec::new();
+        for (index, node) in self.nodes.iter().enumerate() {
+            let id = match node {
+                Node::System { name, dependencies } => {
+                    writeln!(w, "    {:?};", name)?;
+                    for dependency in dependencies {
+                        writeln!(w, "    {:?} -> {:?};", dependency, name)?;
+                    }
+                    name.clone()
+                }
+                Node::ThreadLocal { type_name } => {
+                    let id = format!("thread local {}: {}", index, type_name);
+                    writeln!(w, "    {:?} [style=dashed];", id)?;
+                    id
+                }
+                Node::Bundle { type_name } => {
+                    let id = format!("bundle {}: {}", index, type_name);
+                    writeln!(w, "    {:?} [shape=box];", id)?;
+                    id
+                }
+                Node::Barrier => {
+                    let id = format!("barrier {}", index);
+                    writeln!(w, "    {:?} [shape=point];", id)?;
+                    for before in since_barrier.drain(..) {
+                        writeln!(w, "    {:?} -> {:?};", before, id)?;
+                    }
+                    previous_barrier = Some(index);
+                    continue;
+                }
+            };
+            if let Some(barrier) = previous_barrier {
+                writeln!(w, "    {:?} -> {:?};", format!("barrier {}", barrier), id)?;
+            }
+            since_barrier.push(id);
+        }
+        writeln!(w, "}}")
+    }
+}
+
+/// Returns the number of single character edits turning `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + if a == *b { 0 } else { 1 };
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn system(name: &str, dependencies: &[&str]) -> Node {
+        Node::System {
+            name: name.to_string(),
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+        }
+    }
+
+    fn graph(nodes: Vec<Node>) -> SystemGraph {
+        let mut graph = SystemGraph::default();
+        for node in nodes {
+            graph.push(node);
+        }
+        graph
+    }
+
+    #[test]
+    fn missing_dependency_suggests_near_misses() {
+        let graph = graph(vec![
+            system("transform_system", &[]),
+            system("physics", &["transfrom_system"]),
+        ]);
+        assert_eq!(
+            graph.validate(),
+            Err(DependencyError::Missing {
+                system: "physics".to_string(),
+                origin: "GameDataBuilder".to_string(),
+                dependency: "transfrom_system".to_string(),
+                suggestions: vec!["transform_system".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn dependencies_of_bundles_are_checked_when_built() {
+        let graph = graph(vec![
+            Node::Bundle {
+                type_name: "Bundle",
+            },
+            system("physics", &["bundle_system"]),
+        ]);
+        assert_eq!(graph.validate(), Ok(()));
+    }
+
+    #[test]
+    fn cycles_and_order() {
+        let cyclic = graph(vec![
+            system("a", &["c"]),
+            system("b", &["a"]),
+            system("c", &["b"]),
+        ]);
+        assert_eq!(
+            cyclic.validate(),
+            Err(DependencyError::Cycle {
+                path: vec!["a", "c", "b", "a"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+            })
+        );
+
+        let late = graph(vec![system("a", &["b"]), system("b", &[])]);
+        assert_eq!(
+            late.validate(),
+            Err(DependencyError::AddedLater {
+                system: "a".to_string(),
+                dependency: "b".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn writes_dot() {
+        let graph = graph(vec![system("a", &[]), Node::Barrier, system("b", &["a"])]);
+        let mut dot = Vec::new();
+        graph.write_dot(&mut dot).unwrap();
+        assert_eq!(
+            String::from_utf8(dot).unwrap(),
+            "digraph systems {\n    \"a\";\n    \"barrier 1\" [shape=point];\n    \"a\" -> \"barrier 1\";\n    \"b\";\n    \"a\" -> \"b\";\n    \"barrier 1\" -> \"b\";\n}\n"
+        );
+    }
+}